@@ -0,0 +1,12 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE izettle_transaction_bundle
+    ALTER COLUMN price TYPE INTEGER;
+
+ALTER TABLE izettle_transaction
+    ALTER COLUMN amount TYPE INTEGER;
+
+ALTER TABLE transaction_bundles
+    ALTER COLUMN price TYPE INTEGER;
+
+ALTER TABLE transactions
+    ALTER COLUMN amount TYPE INTEGER;
@@ -0,0 +1,12 @@
+-- Store money as BIGINT to avoid overflowing at ~21.4 million kr in aggregates
+ALTER TABLE transactions
+    ALTER COLUMN amount TYPE BIGINT;
+
+ALTER TABLE transaction_bundles
+    ALTER COLUMN price TYPE BIGINT;
+
+ALTER TABLE izettle_transaction
+    ALTER COLUMN amount TYPE BIGINT;
+
+ALTER TABLE izettle_transaction_bundle
+    ALTER COLUMN price TYPE BIGINT;
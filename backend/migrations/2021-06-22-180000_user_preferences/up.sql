@@ -0,0 +1,4 @@
+CREATE TABLE user_preferences (
+    user_name VARCHAR(64) PRIMARY KEY REFERENCES users(name) ON DELETE CASCADE,
+    preferences JSONB NOT NULL DEFAULT '{}'
+);
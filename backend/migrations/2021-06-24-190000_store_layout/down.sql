@@ -0,0 +1,2 @@
+DROP TABLE store_layout_buttons;
+DROP TABLE store_layout_tabs;
@@ -0,0 +1,18 @@
+CREATE TABLE store_layout_tabs (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    position INTEGER NOT NULL
+);
+
+CREATE TABLE store_layout_buttons (
+    id SERIAL PRIMARY KEY,
+    tab_id INTEGER NOT NULL REFERENCES store_layout_tabs(id) ON DELETE CASCADE,
+    item_id INTEGER REFERENCES inventory(id) ON DELETE CASCADE,
+    bundle_id INTEGER REFERENCES inventory_bundles(id) ON DELETE CASCADE,
+    x INTEGER NOT NULL CHECK (x >= 0),
+    y INTEGER NOT NULL CHECK (y >= 0),
+    width INTEGER NOT NULL DEFAULT 1 CHECK (width > 0),
+    height INTEGER NOT NULL DEFAULT 1 CHECK (height > 0),
+    color TEXT,
+    CHECK ((item_id IS NULL) <> (bundle_id IS NULL))
+);
@@ -0,0 +1,4 @@
+CREATE TABLE inventory_barcodes (
+    barcode TEXT PRIMARY KEY,
+    item_id INTEGER NOT NULL REFERENCES inventory(id) ON DELETE CASCADE
+);
@@ -0,0 +1 @@
+DROP TABLE parked_carts;
@@ -0,0 +1,8 @@
+CREATE TABLE parked_carts (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    debited_account INTEGER REFERENCES book_accounts(id),
+    -- The transaction bundles of the cart
+    bundles JSONB NOT NULL
+);
@@ -0,0 +1,7 @@
+ALTER TABLE izettle_transaction
+    DROP COLUMN note,
+    DROP COLUMN tags;
+
+ALTER TABLE transactions
+    DROP COLUMN note,
+    DROP COLUMN tags;
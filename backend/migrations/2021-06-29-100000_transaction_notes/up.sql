@@ -0,0 +1,8 @@
+ALTER TABLE transactions
+    ADD COLUMN note TEXT,
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+
+-- Pending card payments keep their note and tags until they become transactions
+ALTER TABLE izettle_transaction
+    ADD COLUMN note TEXT,
+    ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
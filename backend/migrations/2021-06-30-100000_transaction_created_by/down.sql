@@ -0,0 +1,5 @@
+ALTER TABLE izettle_transaction
+    DROP COLUMN created_by;
+
+ALTER TABLE transactions
+    DROP COLUMN created_by;
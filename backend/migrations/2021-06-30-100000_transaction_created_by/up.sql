@@ -0,0 +1,6 @@
+-- The user who made the transaction, unknown for transactions made before this was recorded
+ALTER TABLE transactions
+    ADD COLUMN created_by VARCHAR REFERENCES users(name) ON DELETE SET NULL;
+
+ALTER TABLE izettle_transaction
+    ADD COLUMN created_by VARCHAR REFERENCES users(name) ON DELETE SET NULL;
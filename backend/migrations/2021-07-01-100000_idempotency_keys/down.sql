@@ -0,0 +1,5 @@
+ALTER TABLE izettle_transaction
+    DROP COLUMN idempotency_key;
+
+ALTER TABLE transactions
+    DROP COLUMN idempotency_key;
@@ -0,0 +1,6 @@
+-- Chosen by the client, so that a purchase which is submitted twice is only stored once
+ALTER TABLE transactions
+    ADD COLUMN idempotency_key TEXT UNIQUE;
+
+ALTER TABLE izettle_transaction
+    ADD COLUMN idempotency_key TEXT UNIQUE;
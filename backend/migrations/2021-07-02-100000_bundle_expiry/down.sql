@@ -0,0 +1,2 @@
+ALTER TABLE transaction_bundles
+    DROP COLUMN expires_on;
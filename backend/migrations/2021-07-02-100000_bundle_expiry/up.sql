@@ -0,0 +1,3 @@
+-- The best-before date of restocked items. Every bundle with a positive change is a batch.
+ALTER TABLE transaction_bundles
+    ADD COLUMN expires_on DATE;
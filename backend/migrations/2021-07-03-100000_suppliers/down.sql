@@ -0,0 +1,4 @@
+ALTER TABLE inventory
+    DROP COLUMN supplier_id;
+
+DROP TABLE suppliers;
@@ -0,0 +1,11 @@
+CREATE TABLE suppliers (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    contact TEXT,
+    ordering_url TEXT,
+    -- abbreviated english weekday names, e.g. "Mon"
+    delivery_days TEXT[] NOT NULL DEFAULT '{}'
+);
+
+ALTER TABLE inventory
+    ADD COLUMN supplier_id INTEGER REFERENCES suppliers(id) ON DELETE SET NULL;
@@ -0,0 +1,31 @@
+-- everything except the first organization is lost
+DELETE FROM transactions WHERE organization_id <> 1;
+DELETE FROM izettle_transaction WHERE organization_id <> 1;
+DELETE FROM parked_carts WHERE organization_id <> 1;
+DELETE FROM store_layout_tabs WHERE organization_id <> 1;
+DELETE FROM suppliers WHERE organization_id <> 1;
+DELETE FROM inventory_bundles WHERE organization_id <> 1;
+DELETE FROM inventory WHERE organization_id <> 1;
+DELETE FROM book_accounts WHERE organization_id <> 1;
+DELETE FROM members WHERE organization_id <> 1;
+
+ALTER TABLE suppliers DROP COLUMN organization_id;
+ALTER TABLE store_layout_tabs DROP COLUMN organization_id;
+ALTER TABLE parked_carts DROP COLUMN organization_id;
+ALTER TABLE izettle_transaction DROP COLUMN organization_id;
+ALTER TABLE transactions DROP COLUMN organization_id;
+ALTER TABLE inventory_bundles DROP COLUMN organization_id;
+
+ALTER TABLE inventory_barcodes DROP CONSTRAINT inventory_barcodes_pkey;
+ALTER TABLE inventory_barcodes ADD PRIMARY KEY (barcode);
+
+ALTER TABLE inventory DROP COLUMN organization_id;
+ALTER TABLE inventory ADD UNIQUE (name);
+
+ALTER TABLE book_accounts DROP COLUMN organization_id;
+ALTER TABLE book_accounts ADD UNIQUE (name);
+
+ALTER TABLE members DROP COLUMN organization_id;
+
+DROP TABLE user_organizations;
+DROP TABLE organizations;
@@ -0,0 +1,53 @@
+CREATE TABLE organizations (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+-- everything which already exists belongs to the first organization
+INSERT INTO organizations (id, name) VALUES (1, 'Strecklistan');
+SELECT setval('organizations_id_seq', 1);
+
+CREATE TABLE user_organizations (
+    user_name VARCHAR(64) NOT NULL REFERENCES users(name) ON DELETE CASCADE,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    PRIMARY KEY (user_name, organization_id)
+);
+
+INSERT INTO user_organizations (user_name, organization_id) SELECT name, 1 FROM users;
+
+-- the default is only used to fill in the existing rows, new rows must always say where they
+-- belong
+ALTER TABLE members ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE members ALTER COLUMN organization_id DROP DEFAULT;
+
+ALTER TABLE book_accounts ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE book_accounts ALTER COLUMN organization_id DROP DEFAULT;
+ALTER TABLE book_accounts DROP CONSTRAINT book_accounts_name_key;
+ALTER TABLE book_accounts ADD UNIQUE (organization_id, name);
+
+ALTER TABLE inventory ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE inventory ALTER COLUMN organization_id DROP DEFAULT;
+ALTER TABLE inventory DROP CONSTRAINT inventory_name_key;
+ALTER TABLE inventory ADD UNIQUE (organization_id, name);
+
+-- the same product, and barcode, may be sold by several organizations
+ALTER TABLE inventory_barcodes DROP CONSTRAINT inventory_barcodes_pkey;
+ALTER TABLE inventory_barcodes ADD PRIMARY KEY (barcode, item_id);
+
+ALTER TABLE inventory_bundles ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE inventory_bundles ALTER COLUMN organization_id DROP DEFAULT;
+
+ALTER TABLE transactions ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE transactions ALTER COLUMN organization_id DROP DEFAULT;
+
+ALTER TABLE izettle_transaction ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE izettle_transaction ALTER COLUMN organization_id DROP DEFAULT;
+
+ALTER TABLE parked_carts ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE parked_carts ALTER COLUMN organization_id DROP DEFAULT;
+
+ALTER TABLE store_layout_tabs ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE store_layout_tabs ALTER COLUMN organization_id DROP DEFAULT;
+
+ALTER TABLE suppliers ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE suppliers ALTER COLUMN organization_id DROP DEFAULT;
@@ -0,0 +1,2 @@
+-- sessions issued before this time are rejected, e.g. after a password leak
+ALTER TABLE users ADD COLUMN sessions_invalidated_at TIMESTAMPTZ;
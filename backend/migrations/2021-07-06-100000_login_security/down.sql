@@ -0,0 +1,7 @@
+DROP TABLE audit_log;
+DROP TABLE user_devices;
+
+ALTER TABLE users
+    DROP COLUMN email,
+    DROP COLUMN failed_logins,
+    DROP COLUMN locked_until;
@@ -0,0 +1,25 @@
+-- failed_logins counts the failed attempts since the last successful login or lockout
+ALTER TABLE users
+    ADD COLUMN email VARCHAR(255),
+    ADD COLUMN failed_logins INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN locked_until TIMESTAMPTZ;
+
+-- The devices which users have logged in from, to notice logins from new ones
+CREATE TABLE user_devices (
+    user_name VARCHAR NOT NULL REFERENCES users(name) ON DELETE CASCADE,
+    device_id TEXT NOT NULL,
+    last_seen TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (user_name, device_id)
+);
+
+-- Security relevant events, e.g. logins and lockouts
+CREATE TABLE audit_log (
+    id SERIAL PRIMARY KEY,
+    time TIMESTAMPTZ NOT NULL DEFAULT now(),
+    organization_id INTEGER REFERENCES organizations(id) ON DELETE CASCADE,
+    user_name VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    action TEXT NOT NULL,
+    description TEXT NOT NULL
+);
+
+CREATE INDEX audit_log_time_idx ON audit_log (time);
@@ -0,0 +1,17 @@
+-- Side effects of changes, e.g. mail and webhooks. They are written in the same transaction as the
+-- change itself, and delivered by a worker once it has been committed.
+CREATE TABLE outbox (
+    id SERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_error TEXT,
+    delivered_at TIMESTAMPTZ,
+    -- set when the message is given up on after too many attempts
+    failed_at TIMESTAMPTZ
+);
+
+CREATE INDEX outbox_pending_idx ON outbox (next_attempt_at)
+    WHERE delivered_at IS NULL AND failed_at IS NULL;
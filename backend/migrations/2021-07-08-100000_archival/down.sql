@@ -0,0 +1,5 @@
+ALTER TABLE members
+    DROP COLUMN archived_at;
+
+ALTER TABLE inventory
+    DROP COLUMN archived_at;
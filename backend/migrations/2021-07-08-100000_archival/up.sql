@@ -0,0 +1,7 @@
+-- Archived items and members are hidden from the store, but kept so that old transactions can
+-- still refer to them.
+ALTER TABLE inventory
+    ADD COLUMN archived_at TIMESTAMPTZ;
+
+ALTER TABLE members
+    ADD COLUMN archived_at TIMESTAMPTZ;
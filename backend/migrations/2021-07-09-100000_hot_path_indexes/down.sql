@@ -0,0 +1,7 @@
+DROP INDEX inventory_barcodes_item_id_idx;
+DROP INDEX members_organization_id_idx;
+DROP INDEX izettle_transaction_time_idx;
+DROP INDEX transaction_items_item_id_idx;
+DROP INDEX transaction_items_bundle_id_idx;
+DROP INDEX transaction_bundles_transaction_id_idx;
+DROP INDEX transactions_list_idx;
@@ -0,0 +1,17 @@
+-- The transaction list, newest first. Deleted transactions are never listed.
+CREATE INDEX transactions_list_idx ON transactions (organization_id, time DESC, id DESC)
+    WHERE deleted_at IS NULL;
+
+-- Postgres doesn't index foreign keys by itself, but the transaction list joins on these
+CREATE INDEX transaction_bundles_transaction_id_idx ON transaction_bundles (transaction_id);
+CREATE INDEX transaction_items_bundle_id_idx ON transaction_items (bundle_id);
+CREATE INDEX transaction_items_item_id_idx ON transaction_items (item_id);
+
+-- The bridge polls for the oldest pending payment every half second
+CREATE INDEX izettle_transaction_time_idx ON izettle_transaction (time ASC);
+
+-- Members are listed by organization. Book accounts already have (organization_id, name) unique.
+CREATE INDEX members_organization_id_idx ON members (organization_id);
+
+-- The primary key starts with the barcode, which doesn't help when looking up by item
+CREATE INDEX inventory_barcodes_item_id_idx ON inventory_barcodes (item_id);
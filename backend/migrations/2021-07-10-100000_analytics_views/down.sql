@@ -0,0 +1,2 @@
+DROP MATERIALIZED VIEW item_totals;
+DROP MATERIALIZED VIEW daily_sales;
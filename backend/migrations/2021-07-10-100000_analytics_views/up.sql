@@ -0,0 +1,36 @@
+-- Aggregates for the analytics page, which used to be computed from every transaction.
+--
+-- Days are calendar days in the timezone of the session doing the refresh. The server refreshes
+-- the views in the reporting timezone, see util/analytics.rs.
+
+-- How many of every item each cashier sold, per day
+CREATE MATERIALIZED VIEW daily_sales AS
+SELECT transactions.organization_id,
+       transactions.time::DATE AS day,
+       transactions.created_by,
+       item.item_id,
+       -SUM(bundle.change)::BIGINT AS sold
+FROM transactions
+    INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+    INNER JOIN transaction_items AS item ON item.bundle_id = bundle.id
+WHERE transactions.deleted_at IS NULL
+  AND bundle.change < 0
+GROUP BY transactions.organization_id, day, transactions.created_by, item.item_id;
+
+-- refreshing concurrently requires a unique index, this one is also used for reading date ranges
+CREATE UNIQUE INDEX daily_sales_key_idx ON daily_sales (organization_id, day, item_id, created_by);
+
+-- How many of every item have been sold and restocked, ever
+CREATE MATERIALIZED VIEW item_totals AS
+SELECT transactions.organization_id,
+       item.item_id,
+       COALESCE(-SUM(bundle.change) FILTER (WHERE bundle.change < 0), 0)::BIGINT AS sold,
+       COALESCE(SUM(bundle.change) FILTER (WHERE bundle.change > 0), 0)::BIGINT AS restocked,
+       MAX(transactions.time) FILTER (WHERE bundle.change < 0) AS last_sold
+FROM transactions
+    INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+    INNER JOIN transaction_items AS item ON item.bundle_id = bundle.id
+WHERE transactions.deleted_at IS NULL
+GROUP BY transactions.organization_id, item.item_id;
+
+CREATE UNIQUE INDEX item_totals_item_id_idx ON item_totals (item_id);
@@ -0,0 +1,7 @@
+ALTER TABLE izettle_transaction
+    DROP COLUMN event_code;
+
+DROP INDEX transactions_event_code_idx;
+
+ALTER TABLE transactions
+    DROP COLUMN event_code;
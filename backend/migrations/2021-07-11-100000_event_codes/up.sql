@@ -0,0 +1,10 @@
+-- The event or project a transaction belongs to, e.g. "pubrunda-21", for its profit and loss
+ALTER TABLE transactions
+    ADD COLUMN event_code TEXT;
+
+CREATE INDEX transactions_event_code_idx ON transactions (organization_id, event_code)
+    WHERE event_code IS NOT NULL AND deleted_at IS NULL;
+
+-- Pending card payments keep their event until they become transactions
+ALTER TABLE izettle_transaction
+    ADD COLUMN event_code TEXT;
@@ -0,0 +1,9 @@
+DROP MATERIALIZED VIEW modifier_totals;
+
+ALTER TABLE izettle_transaction_bundle
+    DROP COLUMN modifier_ids;
+
+ALTER TABLE transaction_bundles
+    DROP COLUMN modifier_ids;
+
+DROP TABLE inventory_modifiers;
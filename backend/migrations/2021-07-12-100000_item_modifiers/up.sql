@@ -0,0 +1,37 @@
+-- Variants and add-ons of an item, e.g. the sizes of a coffee or "med mjölk".
+--
+-- Modifiers in the same group are alternatives of which at most one is chosen, e.g. the sizes.
+-- Modifiers without a group can be added independently.
+CREATE TABLE inventory_modifiers (
+    id SERIAL PRIMARY KEY,
+    item_id INTEGER NOT NULL REFERENCES inventory(id),
+    group_name TEXT,
+    name TEXT NOT NULL,
+    price_delta INTEGER NOT NULL DEFAULT 0,
+    archived_at TIMESTAMPTZ
+);
+
+CREATE INDEX inventory_modifiers_item_id_idx ON inventory_modifiers (item_id);
+
+-- The modifiers chosen for the items of a bundle. The items are counted as usual, so a large
+-- coffee is still sold as a coffee.
+ALTER TABLE transaction_bundles
+    ADD COLUMN modifier_ids INTEGER[] NOT NULL DEFAULT '{}';
+
+ALTER TABLE izettle_transaction_bundle
+    ADD COLUMN modifier_ids INTEGER[] NOT NULL DEFAULT '{}';
+
+-- How many times every modifier has been sold, refreshed together with the other analytics
+CREATE MATERIALIZED VIEW modifier_totals AS
+SELECT transactions.organization_id,
+       modifier.item_id,
+       modifier.id AS modifier_id,
+       -SUM(bundle.change)::BIGINT AS sold
+FROM transactions
+    INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+    INNER JOIN inventory_modifiers AS modifier ON modifier.id = ANY(bundle.modifier_ids)
+WHERE transactions.deleted_at IS NULL
+  AND bundle.change < 0
+GROUP BY transactions.organization_id, modifier.item_id, modifier.id;
+
+CREATE UNIQUE INDEX modifier_totals_modifier_id_idx ON modifier_totals (modifier_id);
@@ -0,0 +1,10 @@
+ALTER TABLE izettle_transaction
+    DROP COLUMN age_verified;
+
+DROP INDEX transactions_age_verified_idx;
+
+ALTER TABLE transactions
+    DROP COLUMN age_verified;
+
+ALTER TABLE inventory
+    DROP COLUMN age_restricted;
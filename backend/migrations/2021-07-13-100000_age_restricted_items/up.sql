@@ -0,0 +1,14 @@
+-- Items which may only be sold after checking the ID of the buyer, e.g. strong beer
+ALTER TABLE inventory
+    ADD COLUMN age_restricted BOOLEAN NOT NULL DEFAULT false;
+
+-- Whether the cashier confirmed that the ID of the buyer was checked, for the compliance log
+ALTER TABLE transactions
+    ADD COLUMN age_verified BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX transactions_age_verified_idx ON transactions (organization_id, time)
+    WHERE age_verified;
+
+-- Pending card payments keep the confirmation until they become transactions
+ALTER TABLE izettle_transaction
+    ADD COLUMN age_verified BOOLEAN NOT NULL DEFAULT false;
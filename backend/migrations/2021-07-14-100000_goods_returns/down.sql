@@ -0,0 +1,5 @@
+DROP TABLE goods_return_items;
+
+DROP TABLE goods_returns;
+
+DROP TYPE RETURN_REASON;
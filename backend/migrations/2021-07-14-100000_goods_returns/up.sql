@@ -0,0 +1,22 @@
+-- Returns of goods from a sale. A return is a transaction of its own, which pays back for the
+-- returned items, linked here to the sale they were returned from.
+CREATE TYPE RETURN_REASON AS ENUM ('defective', 'wrong_item', 'expired', 'changed_mind', 'other');
+
+CREATE TABLE goods_returns (
+    transaction_id INTEGER PRIMARY KEY REFERENCES transactions(id),
+    original_transaction_id INTEGER NOT NULL REFERENCES transactions(id),
+    reason RETURN_REASON NOT NULL,
+    -- whether the items were put back in stock, by the bundles of the return transaction
+    restocked BOOLEAN NOT NULL
+);
+
+CREATE INDEX goods_returns_original_transaction_id_idx
+    ON goods_returns (original_transaction_id);
+
+-- The returned items, also those which weren't put back in stock
+CREATE TABLE goods_return_items (
+    transaction_id INTEGER NOT NULL REFERENCES goods_returns(transaction_id),
+    item_id INTEGER NOT NULL REFERENCES inventory(id),
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    PRIMARY KEY (transaction_id, item_id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE inventory
+    DROP COLUMN cost_price;
@@ -0,0 +1,3 @@
+-- What an item costs to buy, used to value the stock when no purchase of it tells otherwise
+ALTER TABLE inventory
+    ADD COLUMN cost_price INTEGER CHECK (cost_price >= 0);
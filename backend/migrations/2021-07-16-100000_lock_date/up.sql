@@ -0,0 +1,3 @@
+-- Transactions before this date are in closed periods and can't be voided or edited
+ALTER TABLE organizations
+    ADD COLUMN lock_date DATE;
@@ -0,0 +1,15 @@
+DROP MATERIALIZED VIEW inventory_stock;
+
+CREATE MATERIALIZED VIEW inventory_stock AS
+SELECT i.id, i.name, i.price, i.image_url, COALESCE(SUM(change), 0)::INTEGER AS stock
+FROM inventory AS i
+    LEFT JOIN transaction_items AS item ON item.item_id = i.id
+    LEFT JOIN transaction_bundles AS bundle ON bundle.id = item.bundle_id
+    LEFT JOIN transactions ON transactions.id = bundle.transaction_id
+WHERE deleted_at IS NULL
+GROUP BY i.id, i.name;
+
+ALTER TABLE transactions
+    DROP COLUMN import_batch_id;
+
+DROP TABLE import_batches;
@@ -0,0 +1,27 @@
+-- Files of historical transactions from before the system was used, which can be rolled back
+CREATE TABLE import_batches (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    created_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    rolled_back_at TIMESTAMP WITH TIME ZONE
+);
+
+ALTER TABLE transactions
+    ADD COLUMN import_batch_id INTEGER REFERENCES import_batches(id);
+
+CREATE INDEX transactions_import_batch_id_idx ON transactions (import_batch_id)
+    WHERE import_batch_id IS NOT NULL;
+
+-- imported sales were made before the stock was counted in the system, so they don't count
+DROP MATERIALIZED VIEW inventory_stock;
+
+CREATE MATERIALIZED VIEW inventory_stock AS
+SELECT i.id, i.name, i.price, i.image_url,
+       COALESCE(SUM(change) FILTER (WHERE transactions.import_batch_id IS NULL), 0)::INTEGER AS stock
+FROM inventory AS i
+    LEFT JOIN transaction_items AS item ON item.item_id = i.id
+    LEFT JOIN transaction_bundles AS bundle ON bundle.id = item.bundle_id
+    LEFT JOIN transactions ON transactions.id = bundle.transaction_id
+WHERE deleted_at IS NULL
+GROUP BY i.id, i.name;
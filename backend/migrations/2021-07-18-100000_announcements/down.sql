@@ -0,0 +1,3 @@
+DROP TABLE announcements;
+
+DROP TYPE ANNOUNCEMENT_SEVERITY;
@@ -0,0 +1,16 @@
+-- Messages from the admins which are shown as a banner on every page while they're active
+CREATE TYPE ANNOUNCEMENT_SEVERITY AS ENUM ('info', 'warning', 'critical');
+
+CREATE TABLE announcements (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    message TEXT NOT NULL,
+    severity ANNOUNCEMENT_SEVERITY NOT NULL,
+    starts_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    -- shown until it's removed if NULL
+    ends_at TIMESTAMP WITH TIME ZONE CHECK (ends_at > starts_at),
+    created_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX announcements_organization_id_idx ON announcements (organization_id);
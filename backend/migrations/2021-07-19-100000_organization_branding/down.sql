@@ -0,0 +1,6 @@
+ALTER TABLE organizations
+    DROP COLUMN org_number,
+    DROP COLUMN footer_text,
+    DROP COLUMN logo,
+    DROP COLUMN logo_content_type,
+    DROP COLUMN logo_updated_at;
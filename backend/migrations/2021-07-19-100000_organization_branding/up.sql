@@ -0,0 +1,7 @@
+-- How the organization presents itself on reports, exports, mail and in the header
+ALTER TABLE organizations
+    ADD COLUMN org_number VARCHAR,
+    ADD COLUMN footer_text TEXT,
+    ADD COLUMN logo BYTEA,
+    ADD COLUMN logo_content_type VARCHAR,
+    ADD COLUMN logo_updated_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,18 @@
+-- Revenue or quantity goals for a period or an event, e.g. a pub night
+CREATE TABLE sales_goals (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    name TEXT NOT NULL,
+    -- in öre, exactly one of the targets is set
+    revenue_target BIGINT CHECK (revenue_target > 0),
+    quantity_target INTEGER CHECK (quantity_target > 0),
+    -- a reporting period such as "2021-W26" or "VT2021", or an event code
+    period TEXT,
+    event_code TEXT,
+    created_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CHECK ((revenue_target IS NULL) <> (quantity_target IS NULL)),
+    CHECK ((period IS NULL) <> (event_code IS NULL))
+);
+
+CREATE INDEX sales_goals_organization_id_idx ON sales_goals (organization_id);
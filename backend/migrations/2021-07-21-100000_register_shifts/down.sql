@@ -0,0 +1 @@
+DROP TABLE register_shifts;
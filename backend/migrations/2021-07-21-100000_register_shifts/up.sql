@@ -0,0 +1,22 @@
+-- The register is opened and closed once per day, so that a forgotten closing can be noticed
+CREATE TABLE register_shifts (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    opened_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    opened_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    closed_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    closed_at TIMESTAMP WITH TIME ZONE,
+    -- closed by the server since it was still open after closing time
+    auto_closed BOOLEAN NOT NULL DEFAULT FALSE,
+    -- when the webhooks were told that it was still open after closing time
+    reminded_at TIMESTAMP WITH TIME ZONE,
+    reconciled_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    reconciled_at TIMESTAMP WITH TIME ZONE,
+    CHECK (closed_at IS NOT NULL OR NOT auto_closed),
+    CHECK (auto_closed OR reconciled_at IS NULL)
+);
+
+-- at most one open register per organization
+CREATE UNIQUE INDEX register_shifts_open_idx ON register_shifts (organization_id)
+    WHERE closed_at IS NULL;
+CREATE INDEX register_shifts_organization_id_idx ON register_shifts (organization_id);
@@ -0,0 +1,16 @@
+-- The numbers handed out with sales when the orders are prepared after payment
+CREATE TABLE queue_tickets (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    transaction_id INTEGER NOT NULL UNIQUE REFERENCES transactions(id) ON DELETE CASCADE,
+    -- the day in the reporting timezone, the numbers start over every day
+    day DATE NOT NULL,
+    number INTEGER NOT NULL CHECK (number > 0),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    ready_at TIMESTAMP WITH TIME ZONE,
+    collected_at TIMESTAMP WITH TIME ZONE,
+    UNIQUE (organization_id, day, number)
+);
+
+CREATE INDEX queue_tickets_waiting_idx ON queue_tickets (organization_id, created_at)
+    WHERE collected_at IS NULL;
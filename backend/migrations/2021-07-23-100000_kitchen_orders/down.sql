@@ -0,0 +1,4 @@
+DROP TABLE prepared_orders;
+
+ALTER TABLE inventory
+    DROP COLUMN prepared;
@@ -0,0 +1,11 @@
+-- Items which are prepared after payment, e.g. food, and shown on the kitchen board
+ALTER TABLE inventory
+    ADD COLUMN prepared BOOLEAN NOT NULL DEFAULT false;
+
+-- The orders which the kitchen has marked as done, and so are removed from the board
+CREATE TABLE prepared_orders (
+    transaction_id INTEGER PRIMARY KEY REFERENCES transactions(id) ON DELETE CASCADE,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    prepared_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    prepared_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
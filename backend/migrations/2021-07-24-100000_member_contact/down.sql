@@ -0,0 +1,9 @@
+ALTER TABLE members
+    DROP COLUMN mail_event_info,
+    DROP COLUMN mail_balance_reminders,
+    DROP COLUMN mail_receipts,
+    DROP COLUMN email_code_attempts,
+    DROP COLUMN email_code_sent_at,
+    DROP COLUMN email_code,
+    DROP COLUMN email_verified_at,
+    DROP COLUMN email;
@@ -0,0 +1,14 @@
+-- Where members are mailed. Nothing but the code is sent to an address until the member has
+-- confirmed it with the code.
+ALTER TABLE members
+    ADD COLUMN email TEXT,
+    ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN email_code TEXT,
+    ADD COLUMN email_code_sent_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN email_code_attempts INTEGER NOT NULL DEFAULT 0;
+
+-- What members have opted in to be mailed about
+ALTER TABLE members
+    ADD COLUMN mail_receipts BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN mail_balance_reminders BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN mail_event_info BOOLEAN NOT NULL DEFAULT false;
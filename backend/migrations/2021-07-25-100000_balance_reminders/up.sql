@@ -0,0 +1,4 @@
+-- When the member was last mailed about a tillgodo balance which hasn't been touched for a while.
+-- They are reminded again only once the balance has been touched and left again.
+ALTER TABLE members
+    ADD COLUMN balance_reminded_at TIMESTAMP WITH TIME ZONE;
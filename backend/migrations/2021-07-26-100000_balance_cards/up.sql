@@ -0,0 +1,4 @@
+-- The code on the printed balance card of a member, which lets anyone who scans it see the
+-- balance. A new code is made if the card is lost, so that the old one stops working.
+ALTER TABLE members
+    ADD COLUMN balance_token TEXT UNIQUE;
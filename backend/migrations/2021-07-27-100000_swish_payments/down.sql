@@ -0,0 +1,3 @@
+DROP TABLE swish_payments;
+
+DROP TYPE SWISH_STATUS;
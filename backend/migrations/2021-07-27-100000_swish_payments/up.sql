@@ -0,0 +1,23 @@
+-- Deposits which members make themselves with Swish, by scanning their balance card. The deposit
+-- is made once Swish reports the payment as paid.
+CREATE TYPE SWISH_STATUS AS ENUM ('created', 'paid', 'declined', 'error', 'cancelled');
+
+CREATE TABLE swish_payments (
+    -- the instruction UUID of the payment request, which Swish calls back about
+    id TEXT PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    member_id INTEGER NOT NULL REFERENCES members(id),
+    credited_account INTEGER NOT NULL REFERENCES book_accounts(id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    status SWISH_STATUS NOT NULL DEFAULT 'created',
+    -- opens the payment in the Swish app, while it's pending
+    token TEXT,
+    error TEXT,
+    transaction_id INTEGER UNIQUE REFERENCES transactions(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMP WITH TIME ZONE,
+    CHECK ((status = 'paid') = (transaction_id IS NOT NULL))
+);
+
+CREATE INDEX swish_payments_pending_idx ON swish_payments (organization_id, created_at)
+    WHERE status = 'created';
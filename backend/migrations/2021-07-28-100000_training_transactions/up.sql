@@ -0,0 +1,16 @@
+-- Sales made in training mode, for teaching new committee members. They are kept apart from the
+-- real transactions, so that they never end up in the books, the stock, or the balances of members.
+CREATE TABLE training_transactions (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    created_by VARCHAR NOT NULL REFERENCES users(name) ON DELETE CASCADE,
+    time TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    description TEXT,
+    debited_account INTEGER NOT NULL REFERENCES book_accounts(id),
+    credited_account INTEGER NOT NULL REFERENCES book_accounts(id),
+    amount BIGINT NOT NULL,
+    -- the bundles of the sale, as sent by the frontend
+    bundles JSONB NOT NULL
+);
+
+CREATE INDEX training_transactions_organization_idx ON training_transactions (organization_id, time);
@@ -0,0 +1,4 @@
+ALTER TABLE register_shifts DROP COLUMN closed_on;
+ALTER TABLE register_shifts DROP COLUMN opened_on;
+ALTER TABLE transactions DROP COLUMN device;
+DROP TABLE devices;
@@ -0,0 +1,18 @@
+-- Devices which an admin has given a name, e.g. the register "Kassa 1", so that what is done on
+-- them can be told apart
+CREATE TABLE devices (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    -- the device cookie of the browser, the same as in user_devices
+    token TEXT NOT NULL,
+    name TEXT NOT NULL,
+    registered_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    registered_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (organization_id, token),
+    UNIQUE (organization_id, name)
+);
+
+-- The name of the device at the time, so that it's kept if the device is renamed or removed
+ALTER TABLE transactions ADD COLUMN device TEXT;
+ALTER TABLE register_shifts ADD COLUMN opened_on TEXT;
+ALTER TABLE register_shifts ADD COLUMN closed_on TEXT;
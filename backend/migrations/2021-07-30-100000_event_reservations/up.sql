@@ -0,0 +1,10 @@
+-- Items set aside for those who signed up to an event, e.g. pre-ordered food. They can't be sold
+-- until the event starts, and are released if the signup is cancelled.
+CREATE TABLE event_reservations (
+    signup_id INTEGER NOT NULL REFERENCES event_signups(id) ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES inventory(id),
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    PRIMARY KEY (signup_id, item_id)
+);
+
+CREATE INDEX event_reservations_item_idx ON event_reservations (item_id);
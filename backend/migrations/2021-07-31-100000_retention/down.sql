@@ -0,0 +1,49 @@
+DROP MATERIALIZED VIEW item_totals;
+
+CREATE MATERIALIZED VIEW item_totals AS
+SELECT transactions.organization_id,
+       item.item_id,
+       COALESCE(-SUM(bundle.change) FILTER (WHERE bundle.change < 0), 0)::BIGINT AS sold,
+       COALESCE(SUM(bundle.change) FILTER (WHERE bundle.change > 0), 0)::BIGINT AS restocked,
+       MAX(transactions.time) FILTER (WHERE bundle.change < 0) AS last_sold
+FROM transactions
+    INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+    INNER JOIN transaction_items AS item ON item.bundle_id = bundle.id
+WHERE transactions.deleted_at IS NULL
+GROUP BY transactions.organization_id, item.item_id;
+
+CREATE UNIQUE INDEX item_totals_item_id_idx ON item_totals (item_id);
+
+DROP MATERIALIZED VIEW daily_sales;
+
+CREATE MATERIALIZED VIEW daily_sales AS
+SELECT transactions.organization_id,
+       transactions.time::DATE AS day,
+       transactions.created_by,
+       item.item_id,
+       -SUM(bundle.change)::BIGINT AS sold
+FROM transactions
+    INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+    INNER JOIN transaction_items AS item ON item.bundle_id = bundle.id
+WHERE transactions.deleted_at IS NULL
+  AND bundle.change < 0
+GROUP BY transactions.organization_id, day, transactions.created_by, item.item_id;
+
+CREATE UNIQUE INDEX daily_sales_key_idx ON daily_sales (organization_id, day, item_id, created_by);
+
+DROP MATERIALIZED VIEW inventory_stock;
+
+CREATE MATERIALIZED VIEW inventory_stock AS
+SELECT i.id, i.name, i.price, i.image_url,
+       COALESCE(SUM(change) FILTER (WHERE transactions.import_batch_id IS NULL), 0)::INTEGER AS stock
+FROM inventory AS i
+    LEFT JOIN transaction_items AS item ON item.item_id = i.id
+    LEFT JOIN transaction_bundles AS bundle ON bundle.id = item.bundle_id
+    LEFT JOIN transactions ON transactions.id = bundle.transaction_id
+WHERE deleted_at IS NULL
+GROUP BY i.id, i.name;
+
+DROP TABLE transaction_line_summaries;
+
+ALTER TABLE organizations
+    DROP COLUMN transactions_exported_at;
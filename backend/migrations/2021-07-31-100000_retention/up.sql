@@ -0,0 +1,100 @@
+-- When the transactions of the organization were last exported in full, or rather when that
+-- export started. Only the lines of transactions before it may be compacted.
+ALTER TABLE organizations
+    ADD COLUMN transactions_exported_at TIMESTAMP WITH TIME ZONE;
+
+-- What the lines of old transactions added up to per item and day, after the lines themselves
+-- have been compacted by the retention job. Days are calendar days in the reporting timezone.
+CREATE TABLE transaction_line_summaries (
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    day DATE NOT NULL,
+    item_id INTEGER NOT NULL REFERENCES inventory(id),
+    sold BIGINT NOT NULL,
+    restocked BIGINT NOT NULL,
+    -- imported transactions don't count towards the stock
+    stock_change BIGINT NOT NULL,
+    PRIMARY KEY (organization_id, day, item_id)
+);
+
+CREATE INDEX transaction_line_summaries_item_id_idx ON transaction_line_summaries (item_id);
+
+-- the stock, sales and totals count the summaries along with the lines which are left
+
+DROP MATERIALIZED VIEW inventory_stock;
+
+CREATE MATERIALIZED VIEW inventory_stock AS
+SELECT i.id, i.name, i.price, i.image_url,
+       (COALESCE(lines.stock, 0) + COALESCE(summaries.stock, 0))::INTEGER AS stock
+FROM inventory AS i
+    LEFT JOIN (
+        SELECT item.item_id, SUM(bundle.change) AS stock
+        FROM transaction_items AS item
+            INNER JOIN transaction_bundles AS bundle ON bundle.id = item.bundle_id
+            INNER JOIN transactions ON transactions.id = bundle.transaction_id
+        WHERE transactions.deleted_at IS NULL
+          AND transactions.import_batch_id IS NULL
+        GROUP BY item.item_id
+    ) AS lines ON lines.item_id = i.id
+    LEFT JOIN (
+        SELECT item_id, SUM(stock_change) AS stock
+        FROM transaction_line_summaries
+        GROUP BY item_id
+    ) AS summaries ON summaries.item_id = i.id;
+
+DROP MATERIALIZED VIEW daily_sales;
+
+CREATE MATERIALIZED VIEW daily_sales AS
+SELECT organization_id, day, created_by, item_id, SUM(sold)::BIGINT AS sold
+FROM (
+    SELECT transactions.organization_id,
+           transactions.time::DATE AS day,
+           transactions.created_by,
+           item.item_id,
+           -SUM(bundle.change) AS sold
+    FROM transactions
+        INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+        INNER JOIN transaction_items AS item ON item.bundle_id = bundle.id
+    WHERE transactions.deleted_at IS NULL
+      AND bundle.change < 0
+    GROUP BY transactions.organization_id, day, transactions.created_by, item.item_id
+    UNION ALL
+    -- the cashiers aren't kept in the summaries
+    SELECT organization_id, day, NULL, item_id, sold
+    FROM transaction_line_summaries
+    WHERE sold > 0
+) AS sales
+GROUP BY organization_id, day, created_by, item_id;
+
+CREATE UNIQUE INDEX daily_sales_key_idx ON daily_sales (organization_id, day, item_id, created_by);
+
+DROP MATERIALIZED VIEW item_totals;
+
+CREATE MATERIALIZED VIEW item_totals AS
+SELECT organization_id,
+       item_id,
+       SUM(sold)::BIGINT AS sold,
+       SUM(restocked)::BIGINT AS restocked,
+       MAX(last_sold) AS last_sold
+FROM (
+    SELECT transactions.organization_id,
+           item.item_id,
+           COALESCE(-SUM(bundle.change) FILTER (WHERE bundle.change < 0), 0) AS sold,
+           COALESCE(SUM(bundle.change) FILTER (WHERE bundle.change > 0), 0) AS restocked,
+           MAX(transactions.time) FILTER (WHERE bundle.change < 0) AS last_sold
+    FROM transactions
+        INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+        INNER JOIN transaction_items AS item ON item.bundle_id = bundle.id
+    WHERE transactions.deleted_at IS NULL
+    GROUP BY transactions.organization_id, item.item_id
+    UNION ALL
+    SELECT organization_id,
+           item_id,
+           SUM(sold),
+           SUM(restocked),
+           MAX(day)::TIMESTAMP WITH TIME ZONE
+    FROM transaction_line_summaries
+    GROUP BY organization_id, item_id
+) AS totals
+GROUP BY organization_id, item_id;
+
+CREATE UNIQUE INDEX item_totals_item_id_idx ON item_totals (item_id);
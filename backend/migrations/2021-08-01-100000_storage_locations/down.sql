@@ -0,0 +1,3 @@
+ALTER TABLE inventory DROP COLUMN low_stock_at;
+DROP TABLE stored_items;
+DROP TABLE storage_locations;
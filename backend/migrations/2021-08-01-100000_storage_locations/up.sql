@@ -0,0 +1,20 @@
+-- Places where items are kept apart from those for sale, e.g. the "förråd". What of the stock of
+-- an item isn't kept in any of them is for sale.
+CREATE TABLE storage_locations (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+    name TEXT NOT NULL,
+    UNIQUE (organization_id, name)
+);
+
+-- How many of an item are kept in a storage location, removed when none are left
+CREATE TABLE stored_items (
+    location_id INTEGER NOT NULL REFERENCES storage_locations(id) ON DELETE CASCADE,
+    item_id INTEGER NOT NULL REFERENCES inventory(id) ON DELETE CASCADE,
+    amount INTEGER NOT NULL CHECK (amount > 0),
+    PRIMARY KEY (location_id, item_id)
+);
+CREATE INDEX stored_items_item_id ON stored_items (item_id);
+
+-- warn when this many or fewer of the item are left for sale
+ALTER TABLE inventory ADD COLUMN low_stock_at INTEGER CHECK (low_stock_at >= 0);
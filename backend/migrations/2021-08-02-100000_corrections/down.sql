@@ -0,0 +1,3 @@
+DROP TABLE corrections;
+
+DROP TYPE CORRECTION_STATUS;
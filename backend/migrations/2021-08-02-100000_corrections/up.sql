@@ -0,0 +1,36 @@
+-- Manual corrections of balances and of the stock. Those which are larger than the limits in the
+-- config wait for the approval of a second user, and are only made once it's given.
+CREATE TYPE CORRECTION_STATUS AS ENUM ('pending', 'applied', 'rejected');
+
+CREATE TABLE corrections (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+
+    -- a balance correction moves the amount between two book accounts
+    debited_account INTEGER REFERENCES book_accounts(id),
+    credited_account INTEGER REFERENCES book_accounts(id),
+    amount BIGINT CHECK (amount > 0),
+
+    -- a stock correction changes the stock of an item
+    item_id INTEGER REFERENCES inventory(id),
+    stock_change INTEGER CHECK (stock_change <> 0),
+
+    reason TEXT NOT NULL,
+    status CORRECTION_STATUS NOT NULL DEFAULT 'pending',
+    requested_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    requested_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    decided_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    decided_at TIMESTAMP WITH TIME ZONE,
+    transaction_id INTEGER UNIQUE REFERENCES transactions(id),
+
+    CHECK (
+        (debited_account IS NOT NULL AND credited_account IS NOT NULL AND amount IS NOT NULL
+            AND item_id IS NULL AND stock_change IS NULL)
+        OR (debited_account IS NULL AND credited_account IS NULL AND amount IS NULL
+            AND item_id IS NOT NULL AND stock_change IS NOT NULL)
+    ),
+    CHECK ((status = 'applied') = (transaction_id IS NOT NULL))
+);
+
+CREATE INDEX corrections_pending_idx ON corrections (organization_id, requested_at)
+    WHERE status = 'pending';
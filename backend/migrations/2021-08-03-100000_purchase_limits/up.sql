@@ -0,0 +1,7 @@
+-- At most `quantity` of an item may be sold to a member in every day, week, month or semester.
+-- Selling more needs the approval of an admin.
+CREATE TABLE purchase_limits (
+    item_id INTEGER PRIMARY KEY REFERENCES inventory(id) ON DELETE CASCADE,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    per TEXT NOT NULL CHECK (per IN ('day', 'week', 'month', 'semester'))
+);
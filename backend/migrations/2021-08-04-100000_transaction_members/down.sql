@@ -0,0 +1,2 @@
+ALTER TABLE izettle_transaction DROP COLUMN member_id;
+ALTER TABLE transactions DROP COLUMN member_id;
@@ -0,0 +1,14 @@
+-- The member who bought the goods of a sale, also when it wasn't paid from their tillgodo, for
+-- purchase limits and statistics
+ALTER TABLE transactions ADD COLUMN member_id INTEGER REFERENCES members(id);
+ALTER TABLE izettle_transaction ADD COLUMN member_id INTEGER REFERENCES members(id);
+CREATE INDEX transactions_member_id ON transactions (member_id);
+
+-- earlier sales from tillgodo accounts, and what was returned from them
+UPDATE transactions SET member_id = book_accounts.creditor
+    FROM book_accounts
+    WHERE transactions.debited_account = book_accounts.id;
+UPDATE transactions SET member_id = originals.member_id
+    FROM goods_returns, transactions AS originals
+    WHERE goods_returns.transaction_id = transactions.id
+    AND originals.id = goods_returns.original_transaction_id;
@@ -0,0 +1,3 @@
+DROP TABLE failed_payments;
+
+DROP TYPE FAILED_PAYMENT_STATUS;
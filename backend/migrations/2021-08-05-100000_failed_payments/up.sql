@@ -0,0 +1,38 @@
+-- Card payments which the bridge reported as failed, or which got no result in time. They used to
+-- just disappear, now they are kept until an admin retries them or writes them off.
+CREATE TYPE FAILED_PAYMENT_STATUS AS ENUM ('unresolved', 'retried', 'written_off', 'paid_late');
+
+CREATE TABLE failed_payments (
+    id SERIAL PRIMARY KEY,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id),
+
+    -- the id the payment had in izettle_transaction
+    reference INTEGER NOT NULL UNIQUE,
+    reason TEXT NOT NULL,
+    timed_out BOOLEAN NOT NULL DEFAULT FALSE,
+    failed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+
+    -- the pending payment as it was, so that it can be retried
+    time TIMESTAMP WITH TIME ZONE NOT NULL,
+    description TEXT,
+    debited_account INTEGER NOT NULL REFERENCES book_accounts(id),
+    credited_account INTEGER NOT NULL REFERENCES book_accounts(id),
+    amount BIGINT NOT NULL,
+    note TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    created_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    event_code TEXT,
+    age_verified BOOLEAN NOT NULL DEFAULT FALSE,
+    member_id INTEGER REFERENCES members(id),
+    bundles JSONB NOT NULL,
+
+    status FAILED_PAYMENT_STATUS NOT NULL DEFAULT 'unresolved',
+    resolved_by VARCHAR REFERENCES users(name) ON DELETE SET NULL,
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    retry_reference INTEGER,
+
+    CHECK (status <> 'retried' OR retry_reference IS NOT NULL)
+);
+
+CREATE INDEX failed_payments_unresolved_idx ON failed_payments (organization_id, failed_at)
+    WHERE status = 'unresolved';
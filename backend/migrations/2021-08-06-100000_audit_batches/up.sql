@@ -0,0 +1,3 @@
+-- Changes made to many transactions at once share a batch id, so that they can be found together
+ALTER TABLE audit_log ADD COLUMN batch_id TEXT;
+CREATE INDEX audit_log_batch_id ON audit_log (batch_id) WHERE batch_id IS NOT NULL;
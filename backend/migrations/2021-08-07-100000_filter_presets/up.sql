@@ -0,0 +1,12 @@
+-- Named filters which a user has saved for the transactions or the analytics page, e.g. "this
+-- month, card payments only"
+CREATE TABLE filter_presets (
+    id SERIAL PRIMARY KEY,
+    user_name VARCHAR(64) NOT NULL REFERENCES users(name) ON DELETE CASCADE,
+    organization_id INTEGER NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    page TEXT NOT NULL CHECK (page IN ('transactions', 'analytics')),
+    name TEXT NOT NULL,
+    filter JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (user_name, organization_id, page, name)
+);
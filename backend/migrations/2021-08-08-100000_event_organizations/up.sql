@@ -0,0 +1,24 @@
+-- the view lists the columns of events, so it has to be made again with the new one
+DROP VIEW events_with_signups;
+
+-- existing events belong to the first organization, like everything else did
+ALTER TABLE events ADD COLUMN organization_id INTEGER NOT NULL DEFAULT 1 REFERENCES organizations(id);
+ALTER TABLE events ALTER COLUMN organization_id DROP DEFAULT;
+
+CREATE VIEW events_with_signups AS
+SELECT
+    events.*,
+    COALESCE(t_signup_count.count, 0) AS signups
+FROM
+    events
+    LEFT JOIN
+        (
+            SELECT
+                count(id),
+                event
+            FROM
+                event_signups
+            GROUP BY
+                event
+        ) t_signup_count
+    ON events.id = t_signup_count.event;
@@ -0,0 +1,7 @@
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE users
+SET is_admin = TRUE
+WHERE name IN (SELECT user_name FROM user_organizations WHERE is_admin);
+
+ALTER TABLE user_organizations DROP COLUMN is_admin;
@@ -0,0 +1,9 @@
+-- admins of one organization must not be admins of every other organization they belong to
+ALTER TABLE user_organizations ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE user_organizations
+SET is_admin = users.is_admin
+FROM users
+WHERE users.name = user_organizations.user_name;
+
+ALTER TABLE users DROP COLUMN is_admin;
@@ -0,0 +1,7 @@
+DROP INDEX izettle_transaction_idempotency_key_key;
+ALTER TABLE izettle_transaction
+    ADD CONSTRAINT izettle_transaction_idempotency_key_key UNIQUE (idempotency_key);
+
+DROP INDEX transactions_idempotency_key_key;
+ALTER TABLE transactions
+    ADD CONSTRAINT transactions_idempotency_key_key UNIQUE (idempotency_key);
@@ -0,0 +1,8 @@
+-- the keys are chosen by the clients, so only those of the same organization may collide
+ALTER TABLE transactions DROP CONSTRAINT transactions_idempotency_key_key;
+CREATE UNIQUE INDEX transactions_idempotency_key_key
+    ON transactions (organization_id, idempotency_key);
+
+ALTER TABLE izettle_transaction DROP CONSTRAINT izettle_transaction_idempotency_key_key;
+CREATE UNIQUE INDEX izettle_transaction_idempotency_key_key
+    ON izettle_transaction (organization_id, idempotency_key);
@@ -0,0 +1,401 @@
+//! Password hashing and login sessions.
+//!
+//! A session is stored in an encrypted ("private") cookie, so the server doesn't need to keep
+//! track of them. Note that this means `ROCKET_SECRET_KEY` must be set in production, otherwise
+//! all sessions are invalidated on restart.
+//!
+//! The session also holds the organization which the user logged in to. Everything which belongs
+//! to an organization must be filtered by [Session::organization].
+//!
+//! Sessions can be revoked by setting `sessions_invalidated_at` of the user, which rejects every
+//! session issued before then.
+
+use crate::config::{parse_secret_key, Config, PasswordConfig, SessionConfig};
+use crate::database::DatabasePool;
+use crate::util::client::Client;
+use crate::util::StatusJson;
+use argon2::password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Version};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use diesel::prelude::*;
+use rand_core::OsRng;
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::State;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512};
+use std::convert::TryFrom;
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::user::UserName;
+
+pub const SESSION_COOKIE: &str = "session";
+
+const SALT_LEN: usize = 64;
+
+/// Request guard for an authenticated user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub user: UserName,
+    pub organization: OrganizationId,
+    pub expires: DateTime<Utc>,
+
+    /// When the user logged in. Sessions from before this was tracked count as very old.
+    #[serde(default = "unix_epoch")]
+    pub issued: DateTime<Utc>,
+
+    /// Whether the user is practicing. Sales made in training mode are kept out of the books, see
+    /// [Session::check_not_training].
+    #[serde(default)]
+    pub training: bool,
+}
+
+fn unix_epoch() -> DateTime<Utc> {
+    Utc.timestamp(0, 0)
+}
+
+impl Session {
+    pub fn new(user: UserName, organization: OrganizationId, config: &Config) -> Self {
+        let now = Utc::now();
+        Session {
+            user,
+            organization,
+            expires: now + Duration::hours(config.sessions.lifetime_hours.into()),
+            issued: now,
+            training: false,
+        }
+    }
+
+    /// Reject requests which change the books in other ways than by sales while in training
+    /// mode, since only sales can be kept in the sandbox
+    pub fn check_not_training(&self) -> Result<(), StatusJson> {
+        if self.training {
+            Err(
+                StatusJson::new(Status::Forbidden, "Not possible in training mode")
+                    .with_code(ErrorCode::TrainingMode),
+            )
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Store the session in the cookie jar of the response.
+    ///
+    /// The cookie is marked as `Secure` if the client connected over https, so that it's never
+    /// sent in plain text.
+    pub fn set_cookie(&self, cookies: &CookieJar<'_>, secure: bool) {
+        let value = serde_json::to_string(self).expect("Failed to serialize session");
+        let cookie = Cookie::build(SESSION_COOKIE, value)
+            .path("/")
+            .http_only(true)
+            .same_site(SameSite::Strict)
+            .secure(secure)
+            .finish();
+        cookies.add_private(cookie);
+    }
+
+    pub fn clear_cookie(cookies: &CookieJar<'_>) {
+        cookies.remove_private(Cookie::named(SESSION_COOKIE));
+    }
+
+    /// Read and check the session of a request
+    async fn from_cookie(req: &Request<'_>) -> Result<Session, StatusJson> {
+        let unauthorized = |msg| StatusJson::new(Status::Unauthorized, msg);
+        let cookies = req.cookies();
+
+        let (cookie, rotated) = match cookies.get_private(SESSION_COOKIE) {
+            Some(cookie) => (cookie, false),
+            None => {
+                let cookie = match req.guard::<&State<PreviousSessionKeys>>().await {
+                    Outcome::Success(keys) => keys.decrypt(cookies),
+                    _ => None,
+                };
+                (cookie.ok_or_else(|| unauthorized("Not logged in"))?, true)
+            }
+        };
+
+        let session: Session =
+            serde_json::from_str(cookie.value()).map_err(|_| unauthorized("Not logged in"))?;
+        if session.expires < Utc::now() {
+            return Err(unauthorized("Session expired"));
+        }
+
+        let db_pool = match req.guard::<&State<DatabasePool>>().await {
+            Outcome::Success(db_pool) => db_pool,
+            _ => return Err(Status::InternalServerError.into()),
+        };
+        let invalidated_at: Option<Option<DateTime<Utc>>> = {
+            use crate::schema::tables::users::dsl::*;
+            users
+                .filter(name.eq(&session.user))
+                .select(sessions_invalidated_at)
+                .first(&db_pool.inner().get()?)
+                .optional()?
+        };
+        match invalidated_at {
+            None => return Err(unauthorized("No such user")),
+            Some(Some(time)) if session.issued < time => {
+                return Err(unauthorized("Session revoked"))
+            }
+            Some(_) => {}
+        }
+
+        // move the session over to the current key
+        if rotated {
+            let secure = req
+                .guard::<Client>()
+                .await
+                .succeeded()
+                .map(|client| client.secure)
+                .unwrap_or(false);
+            session.set_cookie(cookies, secure);
+        }
+
+        Ok(session)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Session {
+    type Error = StatusJson;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        // the session is needed by several guards, so it's only checked once per request
+        match req.local_cache_async(Session::from_cookie(req)).await {
+            Ok(session) => Outcome::Success(session.clone()),
+            Err(e) => Outcome::Failure((e.status, e.clone())),
+        }
+    }
+}
+
+/// The secret keys which sessions may have been encrypted with before the current one, see
+/// [SessionConfig::previous_secret_keys]
+pub struct PreviousSessionKeys(Vec<cookie::Key>);
+
+impl PreviousSessionKeys {
+    pub fn new(config: &SessionConfig) -> Self {
+        PreviousSessionKeys(
+            config
+                .previous_secret_keys
+                .iter()
+                .map(|key| parse_secret_key(key).expect("Invalid secret key"))
+                .collect(),
+        )
+    }
+
+    fn decrypt(&self, cookies: &CookieJar<'_>) -> Option<Cookie<'static>> {
+        let encrypted = cookies.get(SESSION_COOKIE).cloned()?;
+        self.0.iter().find_map(|key| {
+            let mut jar = cookie::CookieJar::new();
+            jar.add_original(encrypted.clone());
+            jar.private(key).get(SESSION_COOKIE)
+        })
+    }
+}
+
+/// Request guard for an authenticated user with the admin role
+#[derive(Debug, Clone)]
+pub struct AdminSession(pub Session);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for AdminSession {
+    type Error = StatusJson;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let session = match req.guard::<Session>().await {
+            Outcome::Success(session) => session,
+            Outcome::Failure(failure) => return Outcome::Failure(failure),
+            Outcome::Forward(()) => return Outcome::Forward(()),
+        };
+
+        let db_pool = match req.guard::<&State<DatabasePool>>().await {
+            Outcome::Success(db_pool) => db_pool,
+            _ => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    Status::InternalServerError.into(),
+                ))
+            }
+        };
+
+        let admin = db_pool
+            .inner()
+            .get()
+            .map_err(StatusJson::from)
+            .and_then(|connection| {
+                is_admin_of(&connection, &session.user, session.organization)
+                    .map_err(StatusJson::from)
+            });
+
+        match admin {
+            Ok(true) => Outcome::Success(AdminSession(session)),
+            Ok(false) => Outcome::Failure((
+                Status::Forbidden,
+                StatusJson::new(Status::Forbidden, "Admin role required"),
+            )),
+            Err(e) => Outcome::Failure((e.status, e)),
+        }
+    }
+}
+
+/// Whether `user` has the admin role in `organization`.
+///
+/// The role is kept per organization, so users who don't belong to it are never admins of it.
+pub fn is_admin_of(
+    connection: &PgConnection,
+    user: &str,
+    organization: OrganizationId,
+) -> QueryResult<bool> {
+    use crate::schema::tables::user_organizations::dsl::*;
+    let admin: Option<bool> = user_organizations
+        .filter(user_name.eq(user))
+        .filter(organization_id.eq(organization))
+        .select(is_admin)
+        .first(connection)
+        .optional()?;
+    Ok(admin.unwrap_or(false))
+}
+
+/// Request guard for the iZettle bridge.
+///
+/// If `payments.bridge_token` is configured, the request must have it as a bearer token in the
+/// `Authorization` header. Otherwise anyone may act as the bridge, as before.
+pub struct BridgeAuth;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for BridgeAuth {
+    type Error = StatusJson;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let config = match req.guard::<&State<Config>>().await {
+            Outcome::Success(config) => config,
+            _ => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    Status::InternalServerError.into(),
+                ))
+            }
+        };
+
+        let token = match &config.payments.bridge_token {
+            Some(token) => token,
+            None => return Outcome::Success(BridgeAuth),
+        };
+
+        let given = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|header| header.strip_prefix("Bearer "));
+
+        // compared by digest, so the time taken doesn't tell how much of the token was right
+        match given {
+            Some(given) if Sha512::digest(given.as_bytes()) == Sha512::digest(token.as_bytes()) => {
+                Outcome::Success(BridgeAuth)
+            }
+            _ => Outcome::Failure((
+                Status::Unauthorized,
+                StatusJson::new(Status::Unauthorized, "Invalid bridge token"),
+            )),
+        }
+    }
+}
+
+/// The result of checking a password, see [check_password]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordCheck {
+    Wrong,
+
+    /// `outdated` is set if the stored hash should be replaced with one from [hash_password]
+    Correct {
+        outdated: bool,
+    },
+}
+
+/// Hash a password with argon2id, for the `salted_pass` column.
+///
+/// The hash is a PHC string which includes the salt and parameters.
+pub fn hash_password(
+    password: &str,
+    config: &PasswordConfig,
+) -> Result<String, password_hash::Error> {
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, config.argon2_params()?);
+    let salt = SaltString::generate(&mut OsRng);
+    Ok(argon2
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+/// Check a password against a `salted_pass` value from the database.
+///
+/// Old hashes are iterated SHA-512 with `iterations` rounds, new ones are argon2id PHC strings
+/// which carry their own parameters.
+pub fn check_password(
+    password: &str,
+    salted_pass: &str,
+    iterations: u32,
+    config: &PasswordConfig,
+) -> PasswordCheck {
+    if !salted_pass.starts_with('$') {
+        return if verify_legacy_password(password, salted_pass, iterations) {
+            PasswordCheck::Correct { outdated: true }
+        } else {
+            PasswordCheck::Wrong
+        };
+    }
+
+    let hash = match PasswordHash::new(salted_pass) {
+        Ok(hash) => hash,
+        Err(_) => return PasswordCheck::Wrong,
+    };
+
+    // verifies with the parameters of the hash, not the configured ones
+    if Argon2::default()
+        .verify_password(password.as_bytes(), &hash)
+        .is_err()
+    {
+        return PasswordCheck::Wrong;
+    }
+
+    let outdated = hash.algorithm != Algorithm::Argon2id.ident()
+        || match (argon2::Params::try_from(&hash), config.argon2_params()) {
+            (Ok(used), Ok(wanted)) => {
+                used.m_cost() != wanted.m_cost()
+                    || used.t_cost() != wanted.t_cost()
+                    || used.p_cost() != wanted.p_cost()
+            }
+            _ => false,
+        };
+
+    PasswordCheck::Correct { outdated }
+}
+
+fn verify_legacy_password(password: &str, salted_pass: &str, iterations: u32) -> bool {
+    let bytes = match hex::decode(salted_pass) {
+        Ok(bytes) if bytes.len() > SALT_LEN => bytes,
+        _ => return false,
+    };
+    let (salt, expected) = bytes.split_at(SALT_LEN);
+    let hash = hash_with_salt(password, salt, iterations);
+
+    // compare in constant time
+    hash.len() == expected.len()
+        && hash
+            .iter()
+            .zip(expected)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn hash_with_salt(password: &str, salt: &[u8], iterations: u32) -> Vec<u8> {
+    let mut hash = Sha512::new()
+        .chain(salt)
+        .chain(password.as_bytes())
+        .finalize();
+
+    for _ in 1..iterations {
+        hash = Sha512::new().chain(salt).chain(&hash).finalize();
+    }
+
+    hash.to_vec()
+}
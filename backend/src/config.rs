@@ -0,0 +1,1315 @@
+//! Typed configuration for the backend.
+//!
+//! Settings are read from a TOML file, see `example.toml`. The path is taken from `CONFIG_FILE`,
+//! falling back to `strecklistan.toml` in the working directory if it exists.
+//! Most settings can then be overridden by environment variables, which is convenient for
+//! containers and keeps old `.env` files working.
+
+use chrono::NaiveTime;
+use serde::Deserialize;
+use std::env;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::report::{FeeRates, PaymentProvider};
+use strecklistan_api::time::{Tz, DEFAULT_REPORTING_TIMEZONE};
+
+const DEFAULT_CONFIG_FILE: &str = "strecklistan.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub database: DatabaseConfig,
+    pub sessions: SessionConfig,
+
+    /// How expensive password hashes are to compute
+    pub passwords: PasswordConfig,
+
+    /// When accounts are locked after failed logins
+    pub lockout: LockoutConfig,
+
+    /// What users who aren't admins need the approval of an admin for
+    pub approvals: ApprovalsConfig,
+
+    pub static_files: StaticFilesConfig,
+
+    /// The timezone used when grouping things by calendar day or week
+    pub reporting_timezone: Tz,
+
+    pub payments: PaymentsConfig,
+
+    /// Deposits which members make themselves with Swish. Disabled if this is not set.
+    pub swish: Option<SwishConfig>,
+
+    pub stock: StockConfig,
+
+    /// Outgoing mail. Mail is disabled if this is not set.
+    pub smtp: Option<SmtpConfig>,
+
+    /// Endpoints which are notified about events in the system.
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// How mail and webhooks are delivered and retried
+    pub outbox: OutboxConfig,
+
+    /// How the aggregates for the analytics page are kept up to date
+    pub analytics: AnalyticsConfig,
+
+    /// When the register should be closed at the end of the day
+    pub closing: ClosingConfig,
+
+    /// Numbers handed out with sales, for orders which are prepared after payment
+    pub queue: QueueConfig,
+
+    /// When members are reminded about tillgodo balances they haven't touched for a while, and
+    /// when such balances may be forfeited
+    pub dormant: DormantConfig,
+
+    /// What old data is compacted or deleted, and when
+    pub retention: RetentionConfig,
+
+    /// How long the items and their prices are cached in memory
+    pub item_cache: ItemCacheConfig,
+
+    /// Where database backups end up. Only used to report their status on the admin page.
+    pub backup: Option<BackupConfig>,
+
+    /// The unauthenticated stats shown on the kiosk page
+    pub public: PublicConfig,
+
+    /// How often every client may call routes which don't require a login
+    pub rate_limits: RateLimitsConfig,
+
+    /// The max size of request bodies
+    pub body_limits: BodyLimitsConfig,
+
+    /// How the server is reached through a reverse proxy, e.g. nginx
+    pub proxy: ProxyConfig,
+
+    /// Which other sites may call the API from a browser
+    pub cors: CorsConfig,
+
+    /// How exports for the annual audit are signed
+    pub exports: ExportsConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// Env: `DATABASE_URL`
+    pub url: String,
+
+    /// Env: `DATABASE_POOL_SIZE`
+    pub pool_size: u32,
+
+    /// Env: `RUN_MIGRATIONS`
+    pub run_migrations: bool,
+
+    /// A database used by the analytics, report and export routes, typically a read-only replica.
+    /// Their heavy queries then can't slow down the register. Connections to it are read-only.
+    /// If not set, those routes use the main database.
+    ///
+    /// Env: `REPORTING_DATABASE_URL`
+    pub reporting_url: Option<String>,
+
+    /// Env: `REPORTING_DATABASE_POOL_SIZE`
+    pub reporting_pool_size: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// How long a login lasts, in hours.
+    ///
+    /// Env: `SESSION_LIFETIME`
+    pub lifetime_hours: u32,
+
+    /// Secret keys which were used before the current `ROCKET_SECRET_KEY`, base64 encoded.
+    ///
+    /// Sessions encrypted with these are still accepted, and are encrypted with the current key
+    /// again. This way the key can be rotated without logging everyone out.
+    ///
+    /// Env: `PREVIOUS_SECRET_KEYS`, comma separated
+    pub previous_secret_keys: Vec<String>,
+}
+
+/// Argon2id parameters for new password hashes.
+///
+/// Every hash stores the parameters it was made with, so changing these doesn't break existing
+/// passwords. Users are rehashed with the new parameters the next time they log in.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PasswordConfig {
+    /// Env: `PASSWORD_HASH_MEMORY_KIB`
+    pub memory_kib: u32,
+
+    /// Env: `PASSWORD_HASH_ITERATIONS`
+    pub iterations: u32,
+
+    /// Env: `PASSWORD_HASH_PARALLELISM`
+    pub parallelism: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LockoutConfig {
+    /// How many failed logins in a row lock an account.
+    ///
+    /// Env: `LOCKOUT_MAX_FAILED_LOGINS`
+    pub max_failed_logins: u32,
+
+    /// How long an account stays locked, in minutes.
+    ///
+    /// Env: `LOCKOUT_MINUTES`
+    pub minutes: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StaticFilesConfig {
+    /// Env: `ENABLE_STATIC_FILE_CACHE`
+    pub enable_cache: bool,
+
+    /// The max-age of the Cache-Control header, in seconds.
+    ///
+    /// Env: `STATIC_FILES_MAX_AGE`
+    pub max_age: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaymentsConfig {
+    /// Whether card payments through the iZettle bridge are accepted.
+    ///
+    /// Env: `IZETTLE_ENABLED`
+    pub izettle_enabled: bool,
+
+    /// Whether the bridge may ask for a tip on card payments for sales. Tips are booked on the
+    /// tips account rather than as sales.
+    ///
+    /// Env: `TIPS_ENABLED`
+    pub tips_enabled: bool,
+
+    /// Pending card payments which the bridge hasn't reported a result for after this many
+    /// seconds are moved to the failed payments, for an admin to retry or write off.
+    ///
+    /// Env: `IZETTLE_TIMEOUT_SECS`
+    pub izettle_timeout_secs: u64,
+
+    /// Cash totals are rounded to the nearest multiple of this many öre, e.g. 100 for whole
+    /// kronor. 1 keeps them exact. Card and tillgodo payments are always exact.
+    ///
+    /// Env: `CASH_ROUNDING`
+    pub cash_rounding: i64,
+
+    /// If set, the bridge must send this as a bearer token with every request.
+    ///
+    /// Env: `IZETTLE_BRIDGE_TOKEN`
+    pub bridge_token: Option<String>,
+
+    /// What the payment providers keep of every payment, listed in the daily and monthly reports.
+    pub fees: FeesConfig,
+}
+
+/// The fee of every payment provider, in hundredths of a percent of each payment, e.g. 185 for
+/// 1.85 %. Providers which aren't set keep nothing.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeesConfig {
+    /// Env: `IZETTLE_FEE_BASIS_POINTS`
+    pub izettle_basis_points: u32,
+
+    /// Env: `SWISH_FEE_BASIS_POINTS`
+    pub swish_basis_points: u32,
+}
+
+impl FeesConfig {
+    pub fn rates(&self) -> FeeRates {
+        vec![
+            (PaymentProvider::IZettle, self.izettle_basis_points),
+            (PaymentProvider::Swish, self.swish_basis_points),
+        ]
+        .into_iter()
+        .filter(|&(_, rate)| rate > 0)
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SwishConfig {
+    /// The Swish number which is paid.
+    ///
+    /// Env: `SWISH_PAYEE`
+    pub payee: String,
+
+    /// The base url of the Swish Commerce API.
+    ///
+    /// Env: `SWISH_API_URL`
+    #[serde(default = "default_swish_api_url")]
+    pub api_url: String,
+
+    /// The url of this API as Swish reaches it, e.g. `https://example.com/api`. Swish reports the
+    /// result of every payment here, so it must be https.
+    ///
+    /// Env: `SWISH_CALLBACK_URL`
+    pub callback_url: String,
+
+    /// The client certificate issued by Swish, as a PKCS#12 file. May only be left out if a proxy
+    /// in front of `api_url` presents it instead.
+    ///
+    /// Env: `SWISH_CERTIFICATE`
+    #[serde(default)]
+    pub certificate: Option<PathBuf>,
+
+    /// Env: `SWISH_CERTIFICATE_PASSWORD`
+    #[serde(default)]
+    pub certificate_password: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApprovalsConfig {
+    /// If set, voiding a transaction which is older than this many minutes needs approval.
+    ///
+    /// Env: `APPROVE_VOID_AFTER_MINUTES`
+    pub void_after_minutes: Option<u32>,
+
+    /// If set, a sale for less than its list price needs approval when the discount is larger
+    /// than this many percent of the list price.
+    ///
+    /// Env: `APPROVE_DISCOUNT_OVER_PERCENT`
+    pub discount_over_percent: Option<u32>,
+
+    /// Whether a sale which takes the tillgodo balance of a member below zero needs approval.
+    ///
+    /// Env: `APPROVE_NEGATIVE_BALANCE`
+    pub negative_balance: bool,
+
+    /// If set, a manual correction which moves more than this many kronor between two book
+    /// accounts waits until an admin other than the one who made it approves it.
+    ///
+    /// Env: `APPROVE_CORRECTION_OVER_KRONOR`
+    pub correction_over_kronor: Option<u32>,
+
+    /// If set, a manual correction which changes the stock of an item by more than this many
+    /// waits until an admin other than the one who made it approves it.
+    ///
+    /// Env: `APPROVE_STOCK_CORRECTION_OVER`
+    pub stock_correction_over: Option<u32>,
+
+    /// For how many seconds an approval may be used, after the admin entered their password.
+    ///
+    /// Env: `APPROVAL_TTL_SECS`
+    pub ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StockConfig {
+    /// What to do with sales of items which aren't in stock.
+    ///
+    /// Env: `NEGATIVE_STOCK` (`reject` or `warn`)
+    pub negative_stock: NegativeStock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NegativeStock {
+    /// Refuse the sale
+    Reject,
+
+    /// Log a warning and make the sale anyway, for when the stock isn't counted carefully
+    Warn,
+}
+
+impl Default for NegativeStock {
+    fn default() -> Self {
+        NegativeStock::Reject
+    }
+}
+
+impl FromStr for NegativeStock {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(NegativeStock::Reject),
+            "warn" => Ok(NegativeStock::Warn),
+            _ => Err("expected \"reject\" or \"warn\"".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PublicConfig {
+    /// The organization whose stats are public.
+    ///
+    /// Env: `PUBLIC_ORGANIZATION`
+    pub organization: OrganizationId,
+
+    /// Items with this tag have their stock shown.
+    ///
+    /// Env: `PUBLIC_STOCK_TAG`
+    pub stock_tag: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitsConfig {
+    /// Env: `RATE_LIMIT_LOGIN_PER_MINUTE`, `RATE_LIMIT_LOGIN_BURST`
+    pub login: RateLimitConfig,
+
+    /// Env: `RATE_LIMIT_PUBLIC_PER_MINUTE`, `RATE_LIMIT_PUBLIC_BURST`
+    pub public: RateLimitConfig,
+
+    /// Signing up to events.
+    ///
+    /// Env: `RATE_LIMIT_SIGNUP_PER_MINUTE`, `RATE_LIMIT_SIGNUP_BURST`
+    pub signup: RateLimitConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// How many requests every client may make per minute, on average
+    pub requests_per_minute: u32,
+
+    /// How many requests every client may make in quick succession
+    pub burst: u32,
+}
+
+/// The max size of request bodies per route family, in KiB
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BodyLimitsConfig {
+    /// Routes which take JSON, which is every route at the moment.
+    ///
+    /// Env: `BODY_LIMIT_JSON_KIB`
+    pub json_kib: u64,
+
+    /// Routes which take files, e.g. images.
+    ///
+    /// Env: `BODY_LIMIT_UPLOAD_KIB`
+    pub upload_kib: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// Addresses of the proxies whose `X-Forwarded-For` and `X-Forwarded-Proto` headers are
+    /// trusted. The headers are ignored on requests from anywhere else, since clients can set them
+    /// to anything.
+    ///
+    /// Env: `TRUSTED_PROXIES`, comma separated
+    pub trusted: Vec<IpAddr>,
+
+    /// The path which the proxy serves the site under, e.g. `/strecklistan`, and strips from
+    /// requests before passing them on. Empty if the site is served at the root. The frontend
+    /// makes its links and API calls relative to it.
+    ///
+    /// Env: `BASE_PATH`
+    pub base_path: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Origins, e.g. `https://signup.example.com`, which may call the API. Only the site itself
+    /// may do so if this is empty.
+    ///
+    /// Note that the session cookie is `SameSite=Strict`, so it's only sent along from origins on
+    /// the same site, e.g. subdomains.
+    ///
+    /// Env: `CORS_ALLOWED_ORIGINS`, comma separated
+    pub allowed_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportsConfig {
+    /// The secret which exports for the annual audit are signed with (HMAC-SHA256), so that the
+    /// auditor can tell that they haven't been edited. Those exports are disabled if this isn't
+    /// set.
+    ///
+    /// Env: `EXPORT_SIGNING_KEY`
+    pub signing_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmtpConfig {
+    /// Env: `SMTP_HOST`
+    pub host: String,
+
+    /// Env: `SMTP_PORT`
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+
+    /// Env: `SMTP_USERNAME`
+    #[serde(default)]
+    pub username: Option<String>,
+
+    /// Env: `SMTP_PASSWORD`
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// The sender address of outgoing mail.
+    ///
+    /// Env: `SMTP_FROM`
+    pub from: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    pub url: String,
+
+    /// Shared secret used to sign the payloads, if any.
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutboxConfig {
+    /// How often the outbox is checked for messages to deliver, in seconds.
+    ///
+    /// Env: `OUTBOX_INTERVAL_SECS`
+    pub interval_secs: u64,
+
+    /// How many times delivering a message is attempted before it is given up on. The wait
+    /// between attempts doubles every time, up to an hour.
+    ///
+    /// Env: `OUTBOX_MAX_ATTEMPTS`
+    pub max_attempts: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyticsConfig {
+    /// How often the analytics aggregates are recomputed, in seconds. The analytics page lags
+    /// behind the transactions by at most this long, unless an admin refreshes it.
+    ///
+    /// Env: `ANALYTICS_REFRESH_INTERVAL_SECS`
+    pub refresh_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClosingConfig {
+    /// The time of day, in the reporting timezone, when the register should be closed, e.g.
+    /// `02:00`. A register which is still open by then is reported to the webhooks and shown in
+    /// a banner. Nothing is checked if this isn't set.
+    ///
+    /// Env: `CLOSING_TIME`
+    pub time: Option<String>,
+
+    /// If set, a register which is still open this many minutes after closing time is closed by
+    /// the server, and flagged so that the cash is counted the next day.
+    ///
+    /// Env: `CLOSING_AUTO_CLOSE_AFTER_MINUTES`
+    pub auto_close_after_minutes: Option<u32>,
+
+    /// How often the open register is checked against the closing time, in seconds.
+    ///
+    /// Env: `CLOSING_CHECK_INTERVAL_SECS`
+    pub check_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QueueConfig {
+    /// Whether sales are given a queue number, shown to the cashier and on the queue page.
+    ///
+    /// Env: `QUEUE_ENABLED`
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DormantConfig {
+    /// If set, members who have opted in are mailed once their tillgodo balance has been left
+    /// untouched for this many days. They are reminded again only once the balance has been
+    /// touched and left again.
+    ///
+    /// Env: `DORMANT_REMIND_AFTER_DAYS`
+    pub remind_after_days: Option<u32>,
+
+    /// After how many days a balance left untouched may be forfeited, according to the policy of
+    /// the organization. Such balances are listed on the admin page, nothing is forfeited
+    /// automatically.
+    ///
+    /// Env: `DORMANT_FORFEIT_AFTER_DAYS`
+    pub forfeit_after_days: u32,
+
+    /// How often the balances are checked for reminders to send, in seconds.
+    ///
+    /// Env: `DORMANT_CHECK_INTERVAL_SECS`
+    pub check_interval_secs: u64,
+}
+
+/// Client error reports aren't kept by the server, they are only written to its log, which is
+/// rotated by whatever collects it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// If set, the items of transactions older than this many years are compacted into daily
+    /// totals per item, once the transactions have been exported in full with
+    /// `/api/export/transactions.csv`. The transactions and their amounts are kept, and so are
+    /// the stock and the sales per item, but not who sold what or with which modifiers.
+    ///
+    /// Env: `RETENTION_COMPACT_AFTER_YEARS`
+    pub compact_after_years: Option<u32>,
+
+    /// If set, audit log entries older than this many days are deleted.
+    ///
+    /// Env: `RETENTION_AUDIT_LOG_DAYS`
+    pub audit_log_days: Option<u32>,
+
+    /// Only log what would be compacted or deleted, and show it on the admin page, without doing
+    /// it. On by default, so that nothing is removed before someone has looked at what would be.
+    ///
+    /// Env: `RETENTION_DRY_RUN`
+    pub dry_run: bool,
+
+    /// How often the retention jobs run, in seconds.
+    ///
+    /// Env: `RETENTION_CHECK_INTERVAL_SECS`
+    pub check_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ItemCacheConfig {
+    /// How long the cached items are used before they are read from the database again, in
+    /// seconds. Changes made through the API are seen right away, this only matters for changes
+    /// made directly in the database. 0 disables the cache.
+    ///
+    /// Env: `ITEM_CACHE_TTL_SECS`
+    pub ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackupConfig {
+    /// Env: `BACKUP_DIR`
+    pub directory: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Env {
+        var: &'static str,
+        value: String,
+        reason: String,
+    },
+    Invalid(String),
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            database: DatabaseConfig::default(),
+            sessions: SessionConfig::default(),
+            passwords: PasswordConfig::default(),
+            lockout: LockoutConfig::default(),
+            approvals: ApprovalsConfig::default(),
+            static_files: StaticFilesConfig::default(),
+            reporting_timezone: DEFAULT_REPORTING_TIMEZONE,
+            payments: PaymentsConfig::default(),
+            swish: None,
+            stock: StockConfig::default(),
+            smtp: None,
+            webhooks: vec![],
+            outbox: OutboxConfig::default(),
+            analytics: AnalyticsConfig::default(),
+            closing: ClosingConfig::default(),
+            queue: QueueConfig::default(),
+            dormant: DormantConfig::default(),
+            retention: RetentionConfig::default(),
+            item_cache: ItemCacheConfig::default(),
+            backup: None,
+            public: PublicConfig::default(),
+            rate_limits: RateLimitsConfig::default(),
+            body_limits: BodyLimitsConfig::default(),
+            proxy: ProxyConfig::default(),
+            cors: CorsConfig::default(),
+            exports: ExportsConfig::default(),
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        DatabaseConfig {
+            url: String::new(),
+            pool_size: 15,
+            run_migrations: false,
+            reporting_url: None,
+            reporting_pool_size: 5,
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            lifetime_hours: 12,
+            previous_secret_keys: vec![],
+        }
+    }
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        PasswordConfig {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        LockoutConfig {
+            max_failed_logins: 5,
+            minutes: 15,
+        }
+    }
+}
+
+impl Default for ApprovalsConfig {
+    fn default() -> Self {
+        ApprovalsConfig {
+            void_after_minutes: None,
+            discount_over_percent: None,
+            negative_balance: false,
+            correction_over_kronor: None,
+            stock_correction_over: None,
+            ttl_secs: 120,
+        }
+    }
+}
+
+impl Default for PaymentsConfig {
+    fn default() -> Self {
+        PaymentsConfig {
+            izettle_enabled: true,
+            tips_enabled: false,
+            izettle_timeout_secs: 5 * 60,
+            cash_rounding: 1,
+            bridge_token: None,
+            fees: FeesConfig::default(),
+        }
+    }
+}
+
+impl Default for OutboxConfig {
+    fn default() -> Self {
+        OutboxConfig {
+            interval_secs: 5,
+            max_attempts: 10,
+        }
+    }
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        AnalyticsConfig {
+            refresh_interval_secs: 15 * 60,
+        }
+    }
+}
+
+impl Default for ClosingConfig {
+    fn default() -> Self {
+        ClosingConfig {
+            time: None,
+            auto_close_after_minutes: None,
+            check_interval_secs: 60,
+        }
+    }
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        QueueConfig { enabled: false }
+    }
+}
+
+impl Default for DormantConfig {
+    fn default() -> Self {
+        DormantConfig {
+            remind_after_days: None,
+            forfeit_after_days: 3 * 365,
+            check_interval_secs: 60 * 60,
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            compact_after_years: None,
+            audit_log_days: None,
+            dry_run: true,
+            check_interval_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+impl Default for ItemCacheConfig {
+    fn default() -> Self {
+        ItemCacheConfig { ttl_secs: 60 }
+    }
+}
+
+impl Default for PublicConfig {
+    fn default() -> Self {
+        PublicConfig {
+            organization: 1,
+            stock_tag: "fika".to_string(),
+        }
+    }
+}
+
+impl Default for RateLimitsConfig {
+    fn default() -> Self {
+        RateLimitsConfig {
+            login: RateLimitConfig {
+                requests_per_minute: 10,
+                burst: 5,
+            },
+            public: RateLimitConfig {
+                requests_per_minute: 30,
+                burst: 10,
+            },
+            signup: RateLimitConfig {
+                requests_per_minute: 10,
+                burst: 5,
+            },
+        }
+    }
+}
+
+impl Default for BodyLimitsConfig {
+    fn default() -> Self {
+        BodyLimitsConfig {
+            json_kib: 64,
+            upload_kib: 4096,
+        }
+    }
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_swish_api_url() -> String {
+    "https://cpc.getswish.net/swish-cpcapi/api".to_string()
+}
+
+impl Config {
+    /// Load the config file, apply environment overrides and validate the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match env::var("CONFIG_FILE") {
+            Ok(path) => Config::from_file(path.into())?,
+            Err(_) => {
+                let path = PathBuf::from(DEFAULT_CONFIG_FILE);
+                if path.exists() {
+                    Config::from_file(path)?
+                } else {
+                    Config::default()
+                }
+            }
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: PathBuf) -> Result<Self, ConfigError> {
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => return Err(ConfigError::Read(path, e)),
+        };
+        toml::from_str(&contents).map_err(|e| ConfigError::Parse(path, e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        override_from_env(&mut self.database.url, "DATABASE_URL")?;
+        override_from_env(&mut self.database.pool_size, "DATABASE_POOL_SIZE")?;
+        override_from_env(&mut self.database.run_migrations, "RUN_MIGRATIONS")?;
+        if let Ok(url) = env::var("REPORTING_DATABASE_URL") {
+            self.database.reporting_url = Some(url);
+        }
+        override_from_env(
+            &mut self.database.reporting_pool_size,
+            "REPORTING_DATABASE_POOL_SIZE",
+        )?;
+        override_from_env(&mut self.sessions.lifetime_hours, "SESSION_LIFETIME")?;
+        override_list_from_env(
+            &mut self.sessions.previous_secret_keys,
+            "PREVIOUS_SECRET_KEYS",
+        )?;
+        override_from_env(&mut self.passwords.memory_kib, "PASSWORD_HASH_MEMORY_KIB")?;
+        override_from_env(&mut self.passwords.iterations, "PASSWORD_HASH_ITERATIONS")?;
+        override_from_env(&mut self.passwords.parallelism, "PASSWORD_HASH_PARALLELISM")?;
+        override_from_env(
+            &mut self.lockout.max_failed_logins,
+            "LOCKOUT_MAX_FAILED_LOGINS",
+        )?;
+        override_from_env(&mut self.lockout.minutes, "LOCKOUT_MINUTES")?;
+        if env::var("APPROVE_VOID_AFTER_MINUTES").is_ok() {
+            let mut minutes = 0;
+            override_from_env(&mut minutes, "APPROVE_VOID_AFTER_MINUTES")?;
+            self.approvals.void_after_minutes = Some(minutes);
+        }
+        if env::var("APPROVE_DISCOUNT_OVER_PERCENT").is_ok() {
+            let mut percent = 0;
+            override_from_env(&mut percent, "APPROVE_DISCOUNT_OVER_PERCENT")?;
+            self.approvals.discount_over_percent = Some(percent);
+        }
+        override_from_env(
+            &mut self.approvals.negative_balance,
+            "APPROVE_NEGATIVE_BALANCE",
+        )?;
+        if env::var("APPROVE_CORRECTION_OVER_KRONOR").is_ok() {
+            let mut kronor = 0;
+            override_from_env(&mut kronor, "APPROVE_CORRECTION_OVER_KRONOR")?;
+            self.approvals.correction_over_kronor = Some(kronor);
+        }
+        if env::var("APPROVE_STOCK_CORRECTION_OVER").is_ok() {
+            let mut count = 0;
+            override_from_env(&mut count, "APPROVE_STOCK_CORRECTION_OVER")?;
+            self.approvals.stock_correction_over = Some(count);
+        }
+        override_from_env(&mut self.approvals.ttl_secs, "APPROVAL_TTL_SECS")?;
+        override_from_env(
+            &mut self.static_files.enable_cache,
+            "ENABLE_STATIC_FILE_CACHE",
+        )?;
+        override_from_env(&mut self.static_files.max_age, "STATIC_FILES_MAX_AGE")?;
+        override_from_env(&mut self.reporting_timezone, "REPORTING_TIMEZONE")?;
+        override_from_env(&mut self.payments.izettle_enabled, "IZETTLE_ENABLED")?;
+        override_from_env(&mut self.payments.tips_enabled, "TIPS_ENABLED")?;
+        override_from_env(
+            &mut self.payments.izettle_timeout_secs,
+            "IZETTLE_TIMEOUT_SECS",
+        )?;
+        override_from_env(&mut self.payments.cash_rounding, "CASH_ROUNDING")?;
+        override_from_env(
+            &mut self.payments.fees.izettle_basis_points,
+            "IZETTLE_FEE_BASIS_POINTS",
+        )?;
+        override_from_env(
+            &mut self.payments.fees.swish_basis_points,
+            "SWISH_FEE_BASIS_POINTS",
+        )?;
+        override_from_env(&mut self.stock.negative_stock, "NEGATIVE_STOCK")?;
+        override_from_env(&mut self.public.organization, "PUBLIC_ORGANIZATION")?;
+        override_from_env(&mut self.public.stock_tag, "PUBLIC_STOCK_TAG")?;
+        override_from_env(
+            &mut self.rate_limits.login.requests_per_minute,
+            "RATE_LIMIT_LOGIN_PER_MINUTE",
+        )?;
+        override_from_env(&mut self.rate_limits.login.burst, "RATE_LIMIT_LOGIN_BURST")?;
+        override_from_env(
+            &mut self.rate_limits.public.requests_per_minute,
+            "RATE_LIMIT_PUBLIC_PER_MINUTE",
+        )?;
+        override_from_env(
+            &mut self.rate_limits.public.burst,
+            "RATE_LIMIT_PUBLIC_BURST",
+        )?;
+        override_from_env(
+            &mut self.rate_limits.signup.requests_per_minute,
+            "RATE_LIMIT_SIGNUP_PER_MINUTE",
+        )?;
+        override_from_env(
+            &mut self.rate_limits.signup.burst,
+            "RATE_LIMIT_SIGNUP_BURST",
+        )?;
+        override_from_env(&mut self.body_limits.json_kib, "BODY_LIMIT_JSON_KIB")?;
+        override_from_env(&mut self.body_limits.upload_kib, "BODY_LIMIT_UPLOAD_KIB")?;
+        override_list_from_env(&mut self.proxy.trusted, "TRUSTED_PROXIES")?;
+        override_from_env(&mut self.proxy.base_path, "BASE_PATH")?;
+        override_list_from_env(&mut self.cors.allowed_origins, "CORS_ALLOWED_ORIGINS")?;
+
+        if let Ok(host) = env::var("SMTP_HOST") {
+            let smtp = self.smtp.get_or_insert_with(|| SmtpConfig {
+                host: String::new(),
+                port: default_smtp_port(),
+                username: None,
+                password: None,
+                from: String::new(),
+            });
+            smtp.host = host;
+        }
+
+        if let Some(smtp) = &mut self.smtp {
+            override_from_env(&mut smtp.port, "SMTP_PORT")?;
+            override_from_env(&mut smtp.from, "SMTP_FROM")?;
+            if let Ok(username) = env::var("SMTP_USERNAME") {
+                smtp.username = Some(username);
+            }
+            if let Ok(password) = env::var("SMTP_PASSWORD") {
+                smtp.password = Some(password);
+            }
+        }
+
+        if let Ok(payee) = env::var("SWISH_PAYEE") {
+            let swish = self.swish.get_or_insert_with(|| SwishConfig {
+                payee: String::new(),
+                api_url: default_swish_api_url(),
+                callback_url: String::new(),
+                certificate: None,
+                certificate_password: String::new(),
+            });
+            swish.payee = payee;
+        }
+
+        if let Some(swish) = &mut self.swish {
+            override_from_env(&mut swish.api_url, "SWISH_API_URL")?;
+            override_from_env(&mut swish.callback_url, "SWISH_CALLBACK_URL")?;
+            override_from_env(
+                &mut swish.certificate_password,
+                "SWISH_CERTIFICATE_PASSWORD",
+            )?;
+            if let Ok(path) = env::var("SWISH_CERTIFICATE") {
+                swish.certificate = Some(path.into());
+            }
+        }
+
+        override_from_env(&mut self.outbox.interval_secs, "OUTBOX_INTERVAL_SECS")?;
+        override_from_env(&mut self.outbox.max_attempts, "OUTBOX_MAX_ATTEMPTS")?;
+        override_from_env(
+            &mut self.analytics.refresh_interval_secs,
+            "ANALYTICS_REFRESH_INTERVAL_SECS",
+        )?;
+        override_from_env(&mut self.item_cache.ttl_secs, "ITEM_CACHE_TTL_SECS")?;
+        if let Ok(time) = env::var("CLOSING_TIME") {
+            self.closing.time = Some(time);
+        }
+        if env::var("CLOSING_AUTO_CLOSE_AFTER_MINUTES").is_ok() {
+            let mut minutes = 0;
+            override_from_env(&mut minutes, "CLOSING_AUTO_CLOSE_AFTER_MINUTES")?;
+            self.closing.auto_close_after_minutes = Some(minutes);
+        }
+        override_from_env(
+            &mut self.closing.check_interval_secs,
+            "CLOSING_CHECK_INTERVAL_SECS",
+        )?;
+        override_from_env(&mut self.queue.enabled, "QUEUE_ENABLED")?;
+        if env::var("DORMANT_REMIND_AFTER_DAYS").is_ok() {
+            let mut days = 0;
+            override_from_env(&mut days, "DORMANT_REMIND_AFTER_DAYS")?;
+            self.dormant.remind_after_days = Some(days);
+        }
+        override_from_env(
+            &mut self.dormant.forfeit_after_days,
+            "DORMANT_FORFEIT_AFTER_DAYS",
+        )?;
+        override_from_env(
+            &mut self.dormant.check_interval_secs,
+            "DORMANT_CHECK_INTERVAL_SECS",
+        )?;
+        if env::var("RETENTION_COMPACT_AFTER_YEARS").is_ok() {
+            let mut years = 0;
+            override_from_env(&mut years, "RETENTION_COMPACT_AFTER_YEARS")?;
+            self.retention.compact_after_years = Some(years);
+        }
+        if env::var("RETENTION_AUDIT_LOG_DAYS").is_ok() {
+            let mut days = 0;
+            override_from_env(&mut days, "RETENTION_AUDIT_LOG_DAYS")?;
+            self.retention.audit_log_days = Some(days);
+        }
+        override_from_env(&mut self.retention.dry_run, "RETENTION_DRY_RUN")?;
+        override_from_env(
+            &mut self.retention.check_interval_secs,
+            "RETENTION_CHECK_INTERVAL_SECS",
+        )?;
+
+        if let Ok(key) = env::var("EXPORT_SIGNING_KEY") {
+            self.exports.signing_key = Some(key);
+        }
+
+        if let Ok(token) = env::var("IZETTLE_BRIDGE_TOKEN") {
+            self.payments.bridge_token = Some(token);
+        }
+
+        if let Ok(directory) = env::var("BACKUP_DIR") {
+            self.backup = Some(BackupConfig {
+                directory: directory.into(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |msg: &str| Err(ConfigError::Invalid(msg.to_string()));
+
+        if self.database.url.is_empty() {
+            return invalid("database.url (or DATABASE_URL) must be set");
+        }
+
+        if self.database.pool_size == 0 {
+            return invalid("database.pool_size must be greater than 0");
+        }
+
+        if self.database.reporting_url.is_some() && self.database.reporting_pool_size == 0 {
+            return invalid("database.reporting_pool_size must be greater than 0");
+        }
+
+        if self.sessions.lifetime_hours == 0 {
+            return invalid("sessions.lifetime_hours must be greater than 0");
+        }
+
+        for key in &self.sessions.previous_secret_keys {
+            if let Err(e) = parse_secret_key(key) {
+                return Err(ConfigError::Invalid(format!(
+                    "sessions.previous_secret_keys: {}",
+                    e
+                )));
+            }
+        }
+
+        if let Err(e) = self.passwords.argon2_params() {
+            return Err(ConfigError::Invalid(format!("passwords: {}", e)));
+        }
+
+        if self.payments.cash_rounding < 1 {
+            return invalid("payments.cash_rounding must be at least 1");
+        }
+        if self.payments.izettle_timeout_secs == 0 {
+            return invalid("payments.izettle_timeout_secs must be greater than 0");
+        }
+        if self.payments.fees.izettle_basis_points > 10000 {
+            return invalid("payments.fees.izettle_basis_points must be at most 10000");
+        }
+        if self.payments.fees.swish_basis_points > 10000 {
+            return invalid("payments.fees.swish_basis_points must be at most 10000");
+        }
+
+        if self.lockout.max_failed_logins == 0 {
+            return invalid("lockout.max_failed_logins must be greater than 0");
+        }
+
+        for (name, limit) in &[
+            ("login", &self.rate_limits.login),
+            ("public", &self.rate_limits.public),
+            ("signup", &self.rate_limits.signup),
+        ] {
+            if limit.requests_per_minute == 0 || limit.burst == 0 {
+                return Err(ConfigError::Invalid(format!(
+                    "rate_limits.{} must allow at least 1 request",
+                    name
+                )));
+            }
+        }
+
+        if self.body_limits.json_kib == 0 || self.body_limits.upload_kib == 0 {
+            return invalid("body_limits must be greater than 0");
+        }
+
+        if let Some(smtp) = &self.smtp {
+            if smtp.host.is_empty() {
+                return invalid("smtp.host must not be empty");
+            }
+            if !smtp.from.contains('@') {
+                return invalid("smtp.from must be an email address");
+            }
+        }
+
+        if let Some(swish) = &self.swish {
+            if swish.payee.is_empty() || !swish.payee.chars().all(|c| c.is_ascii_digit()) {
+                return invalid("swish.payee must be a Swish number, e.g. 1231181189");
+            }
+            if !swish.api_url.starts_with("http://") && !swish.api_url.starts_with("https://") {
+                return invalid("swish.api_url must start with http:// or https://");
+            }
+            if !swish.callback_url.starts_with("https://") || swish.callback_url.ends_with('/') {
+                return invalid("swish.callback_url must be an https url without a trailing slash");
+            }
+        }
+
+        let base_path = &self.proxy.base_path;
+        let valid = base_path.is_empty()
+            || (base_path.starts_with('/')
+                && !base_path.ends_with('/')
+                && base_path
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "/-_.".contains(c)));
+        if !valid {
+            return invalid("proxy.base_path must be empty or a path like /strecklistan");
+        }
+
+        for origin in &self.cors.allowed_origins {
+            let valid = (origin.starts_with("http://") || origin.starts_with("https://"))
+                && !origin.ends_with('/');
+            if !valid {
+                return Err(ConfigError::Invalid(format!(
+                    "cors origin \"{}\" must be a scheme and host, e.g. https://example.com",
+                    origin
+                )));
+            }
+        }
+
+        for webhook in &self.webhooks {
+            if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+                return Err(ConfigError::Invalid(format!(
+                    "webhook url \"{}\" must start with http:// or https://",
+                    webhook.url
+                )));
+            }
+        }
+
+        if self.outbox.interval_secs == 0 || self.outbox.max_attempts == 0 {
+            return invalid("outbox.interval_secs and outbox.max_attempts must be greater than 0");
+        }
+
+        if self.analytics.refresh_interval_secs == 0 {
+            return invalid("analytics.refresh_interval_secs must be greater than 0");
+        }
+
+        if self.closing.time.is_some() && self.closing.closing_time().is_none() {
+            return invalid("closing.time must be formatted as HH:MM");
+        }
+
+        if self.closing.check_interval_secs == 0 {
+            return invalid("closing.check_interval_secs must be greater than 0");
+        }
+
+        if self.dormant.remind_after_days == Some(0) || self.dormant.forfeit_after_days == 0 {
+            return invalid(
+                "dormant.remind_after_days and dormant.forfeit_after_days must be greater than 0",
+            );
+        }
+
+        if self.dormant.check_interval_secs == 0 {
+            return invalid("dormant.check_interval_secs must be greater than 0");
+        }
+
+        if self.retention.compact_after_years == Some(0) || self.retention.audit_log_days == Some(0)
+        {
+            return invalid(
+                "retention.compact_after_years and retention.audit_log_days must be greater than 0",
+            );
+        }
+
+        if self.retention.check_interval_secs == 0 {
+            return invalid("retention.check_interval_secs must be greater than 0");
+        }
+
+        if let Some("") = self.exports.signing_key.as_deref() {
+            return invalid("exports.signing_key must not be empty");
+        }
+
+        if let Some("") = self.payments.bridge_token.as_deref() {
+            return invalid("payments.bridge_token must not be empty");
+        }
+
+        Ok(())
+    }
+}
+
+impl DormantConfig {
+    /// How many days a balance has been untouched before it's listed as dormant. Those which are
+    /// reminded about, or if there are no reminders, those which may be forfeited.
+    pub fn dormant_after_days(&self) -> u32 {
+        self.remind_after_days.unwrap_or(self.forfeit_after_days)
+    }
+}
+
+impl PasswordConfig {
+    pub fn argon2_params(&self) -> Result<argon2::Params, argon2::Error> {
+        argon2::Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+    }
+}
+
+impl ClosingConfig {
+    /// The parsed closing time, if one is set
+    pub fn closing_time(&self) -> Option<NaiveTime> {
+        let time = self.time.as_deref()?;
+        NaiveTime::parse_from_str(time, "%H:%M").ok()
+    }
+}
+
+/// Parse a base64 encoded secret key, the same way as Rocket reads `ROCKET_SECRET_KEY`
+pub fn parse_secret_key(key: &str) -> Result<cookie::Key, String> {
+    let bytes = base64::decode(key).map_err(|e| format!("invalid base64: {}", e))?;
+    match bytes.len() {
+        64 => Ok(cookie::Key::from(&bytes)),
+        32 => Ok(cookie::Key::derive_from(&bytes)),
+        n => Err(format!("expected 32 or 64 bytes, got {}", n)),
+    }
+}
+
+/// Replace `field` with the parsed value of the environment variable `var`, if it is set.
+fn override_from_env<T>(field: &mut T, var: &'static str) -> Result<(), ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Ok(value) = env::var(var) {
+        *field = value.parse().map_err(|e: T::Err| ConfigError::Env {
+            var,
+            reason: e.to_string(),
+            value,
+        })?;
+    }
+    Ok(())
+}
+
+/// Replace `field` with the comma separated values of the environment variable `var`, if it is
+/// set.
+fn override_list_from_env<T>(field: &mut Vec<T>, var: &'static str) -> Result<(), ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Ok(value) = env::var(var) {
+        *field = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| item.parse())
+            .collect::<Result<_, T::Err>>()
+            .map_err(|e| ConfigError::Env {
+                var,
+                reason: e.to_string(),
+                value: value.clone(),
+            })?;
+    }
+    Ok(())
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, e) => {
+                write!(f, "could not read config file {}: {}", path.display(), e)
+            }
+            ConfigError::Parse(path, e) => {
+                write!(f, "could not parse config file {}: {}", path.display(), e)
+            }
+            ConfigError::Env { var, value, reason } => {
+                write!(f, "invalid value \"{}\" for {}: {}", value, var, reason)
+            }
+            ConfigError::Invalid(msg) => write!(f, "invalid configuration: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -1,20 +1,30 @@
 use crate::database::DatabaseConn;
 use crate::models::event::EventWithSignups as EventWS;
-use chrono::Local;
+use chrono::Utc;
 use diesel::prelude::*;
 use diesel::result::QueryResult as Result;
+use strecklistan_api::organization::OrganizationId;
 
-pub fn get_event_ws(connection: DatabaseConn, id: i32, published_only: bool) -> Result<EventWS> {
-    use crate::schema::views::events_with_signups::dsl::{events_with_signups, published};
+pub fn get_event_ws(
+    connection: DatabaseConn,
+    organization: OrganizationId,
+    id: i32,
+    published_only: bool,
+) -> Result<EventWS> {
+    use crate::schema::views::events_with_signups::dsl::{
+        events_with_signups, organization_id, published,
+    };
 
     events_with_signups
         .find(id)
+        .filter(organization_id.eq(organization))
         .filter(published.eq(true).or(!published_only))
         .first(&connection)
 }
 
 pub fn get_event_ws_range(
     connection: DatabaseConn,
+    organization: OrganizationId,
     low: i64,
     high: i64,
     published_only: bool,
@@ -23,11 +33,12 @@ pub fn get_event_ws_range(
 
     assert!(high > low);
 
-    let now = Local::now().naive_local();
+    let now = Utc::now();
 
     let mut previous: Vec<EventWS> = if low < 0 {
         events_with_signups
             .filter(end_time.le(now))
+            .filter(organization_id.eq(organization))
             .filter(published.eq(true).or(!published_only))
             .order_by(start_time.desc())
             .limit(-low)
@@ -39,6 +50,7 @@ pub fn get_event_ws_range(
     let mut upcoming: Vec<EventWS> = if high > 0 {
         events_with_signups
             .filter(end_time.gt(now))
+            .filter(organization_id.eq(organization))
             .filter(published.eq(true).or(!published_only))
             .order_by(start_time.asc())
             .limit(high)
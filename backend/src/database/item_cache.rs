@@ -0,0 +1,228 @@
+//! An in-memory cache of the items, bundles and item modifiers of every organization.
+//!
+//! Every sale, quote and bridge payment looks up the prices of the items being sold. Those
+//! rarely change, so they are kept here instead of being queried every time. The stock changes
+//! with every sale and is not cached.
+//!
+//! Routes which change items invalidate the cache of their organization right away. Since items
+//! may also be edited directly in the database, entries also expire after
+//! [ItemCacheConfig::ttl_secs](crate::config::ItemCacheConfig::ttl_secs).
+
+use crate::config::ItemCacheConfig;
+use crate::models::inventory::{InventoryBundle as InventoryBundleRel, InventoryBundleItem};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::inventory::{
+    InventoryBundle, InventoryBundleId, InventoryItemId, ItemModifier, ItemModifierId,
+};
+use strecklistan_api::organization::OrganizationId;
+
+/// What is cached about an item
+#[derive(Debug, Clone)]
+pub struct CachedItem {
+    pub name: Option<String>,
+    pub price: Option<Currency>,
+    pub archived: bool,
+    pub age_restricted: bool,
+    pub prepared: bool,
+}
+
+/// The cached items, bundles and item modifiers of an organization
+#[derive(Debug)]
+pub struct CachedInventory {
+    pub items: HashMap<InventoryItemId, CachedItem>,
+    pub bundles: HashMap<InventoryBundleId, InventoryBundle>,
+    pub modifiers: HashMap<ItemModifierId, ItemModifier>,
+    loaded_at: Instant,
+}
+
+impl CachedInventory {
+    /// The prices of the items which have one
+    pub fn item_prices(&self) -> HashMap<InventoryItemId, Currency> {
+        self.items
+            .iter()
+            .filter_map(|(&id, item)| Some((id, item.price?)))
+            .collect()
+    }
+
+    /// The price deltas of the item modifiers
+    pub fn modifier_prices(&self) -> HashMap<ItemModifierId, Currency> {
+        self.modifiers
+            .iter()
+            .map(|(&id, modifier)| (id, modifier.price_delta))
+            .collect()
+    }
+}
+
+pub struct ItemCache {
+    ttl: Duration,
+    organizations: RwLock<HashMap<OrganizationId, Arc<CachedInventory>>>,
+}
+
+impl ItemCache {
+    pub fn new(config: &ItemCacheConfig) -> Self {
+        ItemCache {
+            ttl: Duration::from_secs(config.ttl_secs),
+            organizations: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The items and bundles of `organization`, if they are cached and haven't expired
+    pub fn cached(&self, organization: OrganizationId) -> Option<Arc<CachedInventory>> {
+        let organizations = self.organizations.read().unwrap();
+        let cached = organizations.get(&organization)?;
+        (cached.loaded_at.elapsed() < self.ttl).then(|| Arc::clone(cached))
+    }
+
+    /// The items and bundles of `organization`, loaded from the database if they aren't cached
+    pub fn get(
+        &self,
+        connection: &PgConnection,
+        organization: OrganizationId,
+    ) -> QueryResult<Arc<CachedInventory>> {
+        match self.cached(organization) {
+            Some(cached) => Ok(cached),
+            None => self.reload(connection, organization),
+        }
+    }
+
+    /// Load the items and bundles of `organization` from the database, replacing what's cached
+    pub fn reload(
+        &self,
+        connection: &PgConnection,
+        organization: OrganizationId,
+    ) -> QueryResult<Arc<CachedInventory>> {
+        let loaded = Arc::new(CachedInventory {
+            items: load_items(connection, organization)?,
+            bundles: load_bundles(connection, organization)?,
+            modifiers: load_modifiers(connection, organization)?,
+            loaded_at: Instant::now(),
+        });
+
+        self.organizations
+            .write()
+            .unwrap()
+            .insert(organization, Arc::clone(&loaded));
+        Ok(loaded)
+    }
+
+    /// Forget the items and bundles of `organization`, call after changing any of them
+    pub fn invalidate(&self, organization: OrganizationId) {
+        self.organizations.write().unwrap().remove(&organization);
+    }
+}
+
+fn load_items(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<HashMap<InventoryItemId, CachedItem>> {
+    use crate::schema::tables::inventory::dsl::*;
+    Ok(inventory
+        .filter(organization_id.eq(organization))
+        .select((
+            id,
+            name,
+            price,
+            archived_at.is_not_null(),
+            age_restricted,
+            prepared,
+        ))
+        .load::<(
+            InventoryItemId,
+            Option<String>,
+            Option<i32>,
+            bool,
+            bool,
+            bool,
+        )>(connection)?
+        .into_iter()
+        .map(
+            |(item_id, item_name, item_price, archived, restricted, is_prepared)| {
+                let item = CachedItem {
+                    name: item_name,
+                    price: item_price.map(Currency::from),
+                    archived,
+                    age_restricted: restricted,
+                    prepared: is_prepared,
+                };
+                (item_id, item)
+            },
+        )
+        .collect())
+}
+
+fn load_bundles(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<HashMap<InventoryBundleId, InventoryBundle>> {
+    use crate::schema::tables::inventory_bundle_items::dsl::{bundle_id, inventory_bundle_items};
+    use crate::schema::tables::inventory_bundles::dsl::{id, inventory_bundles, organization_id};
+
+    let joined: Vec<(InventoryBundleRel, Option<InventoryBundleItem>)> = inventory_bundles
+        .filter(organization_id.eq(organization))
+        .left_join(inventory_bundle_items.on(bundle_id.eq(id)))
+        .load(connection)?;
+
+    Ok(joined
+        .into_iter()
+        .group_by(|(bundle, _)| bundle.id)
+        .into_iter()
+        .map(|(_, mut elements)| {
+            let (bundle, item) = elements.next().unwrap();
+            InventoryBundle {
+                id: bundle.id,
+                name: bundle.name,
+                price: bundle.price.into(),
+                image_url: bundle.image_url,
+                item_ids: std::iter::once(item)
+                    .chain(elements.map(|(_, item)| item))
+                    .flatten() // Remove None:s
+                    .map(|item| item.item_id)
+                    .collect(),
+            }
+        })
+        .map(|bundle| (bundle.id, bundle))
+        .collect())
+}
+
+fn load_modifiers(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<HashMap<ItemModifierId, ItemModifier>> {
+    use crate::schema::tables::inventory::dsl::{id as inventory_id, inventory, organization_id};
+    use crate::schema::tables::inventory_modifiers::dsl::*;
+    type Row = (
+        ItemModifierId,
+        InventoryItemId,
+        Option<String>,
+        String,
+        i32,
+        Option<DateTime<Utc>>,
+    );
+
+    Ok(inventory_modifiers
+        .inner_join(inventory.on(inventory_id.eq(item_id)))
+        .filter(organization_id.eq(organization))
+        .select((id, item_id, group_name, name, price_delta, archived_at))
+        .load::<Row>(connection)?
+        .into_iter()
+        .map(
+            |(modifier_id, modifier_item, group, modifier_name, delta, archived)| {
+                let modifier = ItemModifier {
+                    id: modifier_id,
+                    item_id: modifier_item,
+                    group,
+                    name: modifier_name,
+                    price_delta: delta.into(),
+                    archived_at: archived,
+                };
+                (modifier_id, modifier)
+            },
+        )
+        .collect())
+}
@@ -1,18 +1,64 @@
 pub mod event;
+pub mod item_cache;
 
+use crate::config::DatabaseConfig;
+use diesel::connection::SimpleConnection;
 use diesel::pg::PgConnection;
-use diesel::r2d2::ConnectionManager;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection};
+use log::info;
 use r2d2::{Pool, PooledConnection};
-use std::env;
 use std::error::Error;
+use std::ops::Deref;
 
 pub type DatabasePool = Pool<ConnectionManager<PgConnection>>;
 pub type DatabaseConn = PooledConnection<ConnectionManager<PgConnection>>;
 
-pub fn create_pool() -> Result<DatabasePool, Box<dyn Error>> {
-    let db_url = env::var("DATABASE_URL")?;
-    let db_manager: ConnectionManager<PgConnection> = ConnectionManager::new(db_url);
-    let db_pool: Pool<ConnectionManager<PgConnection>> =
-        Pool::builder().max_size(15).build(db_manager)?;
+pub fn create_pool(config: &DatabaseConfig) -> Result<DatabasePool, Box<dyn Error>> {
+    let db_manager: ConnectionManager<PgConnection> = ConnectionManager::new(&config.url);
+    let db_pool: Pool<ConnectionManager<PgConnection>> = Pool::builder()
+        .max_size(config.pool_size)
+        .build(db_manager)?;
     Ok(db_pool)
 }
+
+/// The pool used by the analytics, report and export routes, see
+/// [DatabaseConfig::reporting_url]
+#[derive(Clone)]
+pub struct ReportingPool(DatabasePool);
+
+impl ReportingPool {
+    /// Connect to the reporting database, or share `main` if there is none
+    pub fn new(config: &DatabaseConfig, main: &DatabasePool) -> Result<Self, Box<dyn Error>> {
+        let url = match &config.reporting_url {
+            Some(url) => url,
+            None => return Ok(ReportingPool(main.clone())),
+        };
+
+        info!("Using a separate database for reporting");
+        let db_pool = Pool::builder()
+            .max_size(config.reporting_pool_size)
+            .connection_customizer(Box::new(ReadOnly))
+            .build(ConnectionManager::new(url))?;
+        Ok(ReportingPool(db_pool))
+    }
+}
+
+impl Deref for ReportingPool {
+    type Target = DatabasePool;
+
+    fn deref(&self) -> &DatabasePool {
+        &self.0
+    }
+}
+
+/// Makes every transaction of a connection read-only, also if it's to the main database
+#[derive(Debug)]
+struct ReadOnly;
+
+impl CustomizeConnection<PgConnection, diesel::r2d2::Error> for ReadOnly {
+    fn on_acquire(&self, connection: &mut PgConnection) -> Result<(), diesel::r2d2::Error> {
+        connection
+            .batch_execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
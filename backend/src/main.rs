@@ -1,34 +1,48 @@
 #[macro_use]
 extern crate diesel;
 
+pub mod auth;
+pub mod config;
 mod database;
 pub mod models;
 pub mod routes;
 mod schema;
 pub mod util;
 
+#[cfg(test)]
+mod testutil;
+
+use crate::auth::PreviousSessionKeys;
+use crate::config::{Config, StaticFilesConfig};
 use crate::database::create_pool;
-use crate::database::DatabasePool;
+use crate::database::item_cache::ItemCache;
+use crate::database::{DatabasePool, ReportingPool};
+use crate::routes::index::IndexHtml;
+use crate::routes::rest::izettle::izettle_bridge_poll::{BridgeActivity, PaymentQueue};
+use crate::routes::rest::live::LiveEvents;
 use crate::routes::{index, rest};
+use crate::util::analytics::AnalyticsRefresher;
+use crate::util::approval::Approvals;
+use crate::util::body_limits::BodyLimits;
+use crate::util::closing::ClosingWatcher;
+use crate::util::cors::Cors;
+use crate::util::device::DeviceActivity;
+use crate::util::dormant::DormantReminder;
+use crate::util::http_cache::HttpCache;
+use crate::util::outbox::OutboxWorker;
+use crate::util::payment_timeout::PaymentTimeout;
+use crate::util::rate_limit::RateLimiters;
+use crate::util::retention::RetentionJob;
 use crate::util::{catchers, StaticCachedFiles};
 use diesel_migrations::{
     find_migrations_directory, mark_migrations_in_directory, run_pending_migrations, setup_database,
 };
 use dotenv::dotenv;
 use rocket::fs::FileServer;
-use rocket::routes;
-use std::env;
-
-fn handle_migrations(db_pool: &DatabasePool) {
-    let run_migrations = env::var("RUN_MIGRATIONS")
-        .map(|s| {
-            s.parse().unwrap_or_else(|_| {
-                panic!("Could not parse \"{}\" as a bool for RUN_MIGRATIONS", s)
-            })
-        })
-        .unwrap_or(false);
-
-    if run_migrations {
+use rocket::{routes, Build, Rocket};
+
+fn handle_migrations(db_pool: &DatabasePool, config: &Config) {
+    if config.database.run_migrations {
         let connection = db_pool.get().expect("Could not connect to database");
 
         setup_database(&connection).expect("Could not set up database");
@@ -67,61 +81,240 @@ fn handle_migrations(db_pool: &DatabasePool) {
 async fn main() {
     dotenv().ok();
 
-    let db_pool = create_pool().expect("Could not create database pool");
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
 
-    handle_migrations(&db_pool);
+    let db_pool = create_pool(&config.database).expect("Could not create database pool");
 
-    let enable_static_file_cache: bool = env::var("ENABLE_STATIC_FILE_CACHE")
-        .map(|s| {
-            s.parse()
-                .expect("Invalid ENABLE_STATIC_FILE_CACHE. Expected true or false.")
-        })
-        .unwrap_or(false);
+    handle_migrations(&db_pool, &config);
 
-    let max_age = env::var("STATIC_FILES_MAX_AGE")
-        .map(|s| {
-            s.parse()
-                .expect("Invalid STATIC_FILES_MAX_AGE. Expected a number.")
-        })
-        .unwrap_or(0);
+    let static_files = config.static_files.clone();
+    let base_path = config.proxy.base_path.clone();
+    let rocket = mount_static_files(build_rocket(config, db_pool), &static_files, &base_path);
+    rocket
+        .attach(OutboxWorker)
+        .attach(AnalyticsRefresher)
+        .attach(ClosingWatcher)
+        .attach(DormantReminder)
+        .attach(RetentionJob)
+        .attach(PaymentTimeout)
+        .launch()
+        .await
+        .unwrap();
+}
 
-    let mut rocket = rocket::build()
+/// The server with all routes, but without the static files of the frontend
+pub fn build_rocket(config: Config, db_pool: DatabasePool) -> Rocket<Build> {
+    let rate_limiters = RateLimiters::new(&config.rate_limits);
+    let body_limits = BodyLimits::new(&config.body_limits);
+    let cors = Cors::new(&config.cors);
+    let previous_session_keys = PreviousSessionKeys::new(&config.sessions);
+    let reporting_pool = ReportingPool::new(&config.database, &db_pool)
+        .expect("Could not create reporting database pool");
+    let item_cache = ItemCache::new(&config.item_cache);
+
+    rocket::build()
         .manage(db_pool)
+        .manage(reporting_pool)
+        .manage(item_cache)
+        .manage(config)
+        .manage(BridgeActivity::default())
+        .manage(PaymentQueue::default())
+        .manage(LiveEvents::default())
+        .manage(Approvals::default())
+        .manage(DeviceActivity::default())
+        .manage(rate_limiters)
+        .manage(previous_session_keys)
+        .attach(body_limits)
+        .attach(cors)
+        .attach(HttpCache)
         .register("/", catchers())
         .mount(
             "/api/",
             routes![
                 rest::event::get_event,
                 rest::event::get_event_range,
+                rest::event::get_event_profit_and_loss,
+                rest::event::get_event_signups,
+                rest::event::post_event_signup,
+                rest::event::delete_event_signup,
                 rest::inventory::get_inventory,
                 rest::inventory::get_tags,
+                rest::inventory::get_barcodes,
                 rest::inventory::get_inventory_bundles,
+                rest::inventory::get_item_modifiers,
+                rest::inventory::get_popular_items,
+                rest::inventory::get_expiring,
+                rest::inventory::put_item_archived,
+                rest::inventory::put_item_age_restricted,
+                rest::inventory::put_item_prepared,
+                rest::inventory::put_item_cost_price,
+                rest::inventory::put_item_low_stock_at,
+                rest::inventory::get_purchase_limits,
+                rest::inventory::put_item_purchase_limit,
+                rest::inventory::patch_inventory_batch,
+                rest::location::get_locations,
+                rest::location::post_location,
+                rest::location::delete_location,
+                rest::location::post_transfer,
+                rest::location::get_low_stock,
                 rest::transaction::get_transactions,
+                rest::transaction::get_transaction,
                 rest::transaction::post_transaction,
                 rest::transaction::delete_transaction,
+                rest::transaction::patch_transaction,
+                rest::transaction::post_transaction_batch,
+                rest::transaction::post_goods_return,
+                rest::live::live_events,
+                rest::live::poll_live_events,
                 rest::book_account::get_accounts,
                 rest::book_account::get_master_accounts,
+                rest::book_account::get_ledger,
                 rest::book_account::add_account,
+                rest::checkout::get_rules,
+                rest::checkout::post_quote,
+                rest::client_error::post_client_error,
+                rest::kitchen::get_kitchen_orders,
+                rest::kitchen::post_order_done,
+                rest::queue::get_queue,
+                rest::queue::get_transaction_ticket,
+                rest::queue::ready_ticket,
+                rest::queue::collect_ticket,
+                rest::register::get_register,
+                rest::register::open_register,
+                rest::register::close_register,
+                rest::register::reconcile_shift,
+                rest::report::get_daily_report,
+                rest::report::get_monthly_report,
+                rest::report::get_stock_value,
+                rest::dashboard::get_dashboard,
+                rest::analytics::get_daily_sales,
+                rest::analytics::get_shifts,
+                rest::analytics::get_payment_method_sales,
+                rest::analytics::get_category_sales,
+                rest::analytics::get_item_totals,
+                rest::analytics::get_modifier_totals,
+                rest::analytics::get_member_items,
+                rest::analytics::get_item_sparklines,
+                rest::analytics::refresh_analytics,
+                rest::export::export_transactions_csv,
+                rest::export::export_member_balances_csv,
+                rest::export::export_member_balances_pdf,
                 rest::member::get_members,
+                rest::member::search_members,
                 rest::member::add_member_with_book_account,
+                rest::member::put_member_archived,
+                rest::member::get_member_contact,
+                rest::member::put_member_email,
+                rest::member::verify_member_email,
+                rest::member::put_contact_preferences,
+                rest::member::get_balance_card,
+                rest::member::put_balance_card,
+                rest::member::get_balance_card_svg,
+                rest::auth::login,
+                rest::auth::logout,
+                rest::auth::get_me,
+                rest::auth::post_approval,
+                rest::training::get_training_mode,
+                rest::training::put_training_mode,
+                rest::training::get_training_transactions,
+                rest::training::delete_training_transactions,
+                rest::admin::get_admin_status,
+                rest::admin::get_users,
+                rest::admin::put_user_admin,
+                rest::admin::put_lock_date,
+                rest::admin::invalidate_user_sessions,
+                rest::admin::get_duplicates,
+                rest::admin::get_dormant_balances,
+                rest::admin::get_retention,
+                rest::device::get_device,
+                rest::device::get_devices,
+                rest::device::post_device,
+                rest::device::put_device,
+                rest::device::delete_device,
+                rest::correction::get_corrections,
+                rest::correction::get_pending_corrections,
+                rest::correction::post_correction,
+                rest::correction::approve_correction,
+                rest::correction::reject_correction,
+                rest::announcement::get_announcements,
+                rest::announcement::get_all_announcements,
+                rest::announcement::post_announcement,
+                rest::announcement::delete_announcement,
+                rest::goal::get_goals,
+                rest::goal::post_goal,
+                rest::goal::delete_goal,
+                rest::organization::get_branding,
+                rest::organization::get_logo,
+                rest::organization::put_branding,
+                rest::organization::put_logo,
+                rest::organization::delete_logo,
+                rest::import::post_import,
+                rest::import::get_import_batches,
+                rest::import::rollback_import_batch,
+                rest::catalog::export_catalog,
+                rest::catalog::import_catalog,
+                rest::query_plan::get_query_plans,
+                rest::preferences::get_preferences,
+                rest::preferences::put_preferences,
+                rest::filter_preset::get_filter_presets,
+                rest::filter_preset::put_filter_preset,
+                rest::filter_preset::delete_filter_preset,
+                rest::store_layout::get_store_layout,
+                rest::store_layout::put_store_layout,
+                rest::parked_cart::get_parked_carts,
+                rest::parked_cart::park_cart,
+                rest::parked_cart::delete_parked_cart,
+                rest::supplier::get_suppliers,
+                rest::supplier::post_supplier,
+                rest::supplier::put_supplier,
+                rest::supplier::delete_supplier,
+                rest::public::get_public_stats,
+                rest::public::get_public_balance,
+                rest::public::get_public_open,
+                rest::swish::post_swish_deposit,
+                rest::swish::get_swish_payment,
+                rest::swish::swish_callback,
+                rest::swish::get_pending_swish_payments,
                 rest::get_api_version,
+                rest::get_reporting_timezone,
                 rest::izettle::izettle_bridge_poll::poll_for_transaction,
+                rest::izettle::izettle_bridge_poll::bridge_events,
                 rest::izettle::izettle_bridge_result::complete_izettle_transaction,
                 rest::izettle::izettle_transaction::begin_izettle_transaction,
+                rest::izettle::izettle_transaction::begin_izettle_deposit,
+                rest::izettle::izettle_transaction::cancel_izettle_transaction,
                 rest::izettle::izettle_transaction_poll::poll_for_izettle,
+                rest::izettle::izettle_transaction_poll::payment_events,
+                rest::izettle::failed_payment::get_failed_payments,
+                rest::izettle::failed_payment::retry_failed_payment,
+                rest::izettle::failed_payment::write_off_failed_payment,
             ],
         )
-        .mount("/", routes![index::wildcard, index::root]);
+        .mount("/", routes![index::wildcard, index::root])
+}
 
+fn mount_static_files(
+    mut rocket: Rocket<Build>,
+    config: &StaticFilesConfig,
+    base_path: &str,
+) -> Rocket<Build> {
     let static_routes = &[("/pkg", "www/pkg"), ("/static", "www/static")];
 
-    for &(route, path) in static_routes {
-        rocket = if enable_static_file_cache {
-            rocket.mount(route, StaticCachedFiles::from(path).max_age(max_age))
-        } else {
-            rocket.mount(route, FileServer::from(path))
-        };
+    if config.enable_cache {
+        let mut versioned_urls = vec![];
+        for &(route, path) in static_routes {
+            let files = StaticCachedFiles::from(path).max_age(config.max_age);
+            versioned_urls.extend(files.versioned_urls(route));
+            rocket = rocket.mount(route, files);
+        }
+        rocket.manage(IndexHtml::versioned(&versioned_urls).with_base_path(base_path))
+    } else {
+        for &(route, path) in static_routes {
+            rocket = rocket.mount(route, FileServer::from(path));
+        }
+        rocket.manage(IndexHtml::default().with_base_path(base_path))
     }
-
-    rocket.launch().await.unwrap();
 }
@@ -7,6 +7,7 @@ pub struct BookAccount {
     pub name: String,
     pub account_type: BookAccountType,
     pub creditor: Option<i32>,
+    pub organization_id: i32,
 }
 
 impl From<BookAccount> for BookAccountCommon {
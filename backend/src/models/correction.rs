@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use strecklistan_api::correction::{
+    Correction as CorrectionCommon, CorrectionKind, CorrectionStatus,
+};
+
+#[derive(Queryable, Debug, PartialEq)]
+pub struct Correction {
+    pub id: i32,
+    pub organization_id: i32,
+    pub debited_account: Option<i32>,
+    pub credited_account: Option<i32>,
+    pub amount: Option<i64>,
+    pub item_id: Option<i32>,
+    pub stock_change: Option<i32>,
+    pub reason: String,
+    pub status: CorrectionStatus,
+    pub requested_by: Option<String>,
+    pub requested_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub transaction_id: Option<i32>,
+}
+
+impl Correction {
+    pub fn kind(&self) -> CorrectionKind {
+        // the table only allows one kind or the other
+        match (self.item_id, self.stock_change) {
+            (Some(item_id), Some(change)) => CorrectionKind::Stock { item_id, change },
+            _ => CorrectionKind::Balance {
+                debited_account: self.debited_account.unwrap_or_default(),
+                credited_account: self.credited_account.unwrap_or_default(),
+                amount: self.amount.unwrap_or_default().into(),
+            },
+        }
+    }
+}
+
+impl From<Correction> for CorrectionCommon {
+    fn from(val: Correction) -> Self {
+        CorrectionCommon {
+            id: val.id,
+            kind: val.kind(),
+            reason: val.reason,
+            status: val.status,
+            requested_by: val.requested_by,
+            requested_at: val.requested_at,
+            decided_by: val.decided_by,
+            decided_at: val.decided_at,
+            transaction_id: val.transaction_id,
+        }
+    }
+}
@@ -15,10 +15,10 @@ impl EventRange {
     pub fn validate(&self) -> Result<(), StatusJson> {
         match self.low >= self.high {
             false => Ok(()),
-            true => Err(StatusJson {
-                status: Status::BadRequest,
-                description: "EventRange: high must be greater than low".into(),
-            }),
+            true => Err(StatusJson::new(
+                Status::BadRequest,
+                "EventRange: high must be greater than low",
+            )),
         }
     }
 }
@@ -33,6 +33,7 @@ pub struct EventWithSignups {
     pub end_time: DateTime<Utc>,
     pub price: i32,
     pub published: bool,
+    pub organization_id: i32,
     pub signups: i64,
 }
 
@@ -46,6 +47,7 @@ pub struct Event {
     pub end_time: DateTime<Utc>,
     pub price: i32,
     pub published: bool,
+    pub organization_id: i32,
 }
 
 #[derive(Insertable, Serialize, Deserialize, Debug)]
@@ -57,6 +59,7 @@ pub struct NewEvent {
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
     pub price: Option<i32>,
+    pub organization_id: i32,
 }
 
 impl From<Event> for EventWithSignups {
@@ -70,6 +73,7 @@ impl From<Event> for EventWithSignups {
             end_time: event.end_time,
             price: event.price,
             published: event.published,
+            organization_id: event.organization_id,
             signups: 0,
         }
     }
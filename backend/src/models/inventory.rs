@@ -6,6 +6,7 @@ pub struct InventoryBundle {
     pub name: String,
     pub price: i32,
     pub image_url: Option<String>,
+    pub organization_id: i32,
 }
 
 #[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
@@ -1,5 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use strecklistan_api::izettle::{FailedPayment as FailedPaymentCommon, FailedPaymentStatus};
 pub use strecklistan_api::transaction as object;
 
 use crate::schema::tables::{
@@ -7,10 +9,32 @@ use crate::schema::tables::{
     izettle_transaction_item,
 };
 
-#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
+/// A pending payment, as the bridge sees it
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct IZettleTransactionPartial {
     pub id: i32,
-    pub amount: i32,
+    pub amount: i64,
+
+    /// Only included if the bridge asks for the lines
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// What is being paid for, only included if the bridge asks for it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lines: Option<Vec<IZettleTransactionLine>>,
+
+    /// Whether the bridge may ask the customer for a tip
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub tip_allowed: bool,
+}
+
+/// A line of a pending payment, for showing on the reader and the receipt
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct IZettleTransactionLine {
+    /// The description of the bundle, or else the names of its items
+    pub name: Option<String>,
+    pub quantity: i32,
+    pub unit_price: Option<i64>,
 }
 
 #[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
@@ -20,7 +44,15 @@ pub struct IZettleTransaction {
     pub time: DateTime<Utc>,
     pub debited_account: i32,
     pub credited_account: i32,
-    pub amount: i32,
+    pub amount: i64,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub created_by: Option<String>,
+    pub idempotency_key: Option<String>,
+    pub organization_id: i32,
+    pub event_code: Option<String>,
+    pub age_verified: bool,
+    pub member_id: Option<i32>,
 }
 
 #[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
@@ -38,7 +70,15 @@ pub struct NewIZettleTransaction {
     pub time: Option<DateTime<Utc>>,
     pub debited_account: i32,
     pub credited_account: i32,
-    pub amount: i32,
+    pub amount: i64,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub created_by: Option<String>,
+    pub idempotency_key: Option<String>,
+    pub organization_id: i32,
+    pub event_code: Option<String>,
+    pub age_verified: bool,
+    pub member_id: Option<i32>,
 }
 
 #[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
@@ -46,8 +86,19 @@ pub struct NewIZettleTransaction {
 pub struct NewIZettleTransactionBundle {
     pub transaction_id: i32,
     pub description: Option<String>,
-    pub price: Option<i32>,
+    pub price: Option<i64>,
+    pub change: i32,
+    pub modifier_ids: Vec<i32>,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
+pub struct IZettleTransactionBundle {
+    pub id: i32,
+    pub transaction_id: i32,
+    pub description: Option<String>,
+    pub price: Option<i64>,
     pub change: i32,
+    pub modifier_ids: Vec<i32>,
 }
 
 #[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
@@ -70,3 +121,55 @@ pub const TRANSACTION_IN_PROGRESS: &str = "in_progress";
 pub const TRANSACTION_PAID: &str = "paid";
 pub const TRANSACTION_CANCELLED: &str = "cancelled";
 pub const TRANSACTION_FAILED: &str = "failed";
+
+#[derive(Queryable, Debug, PartialEq)]
+pub struct FailedPayment {
+    pub id: i32,
+    pub organization_id: i32,
+    pub reference: i32,
+    pub reason: String,
+    pub timed_out: bool,
+    pub failed_at: DateTime<Utc>,
+    pub time: DateTime<Utc>,
+    pub description: Option<String>,
+    pub debited_account: i32,
+    pub credited_account: i32,
+    pub amount: i64,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub created_by: Option<String>,
+    pub event_code: Option<String>,
+    pub age_verified: bool,
+    pub member_id: Option<i32>,
+    pub bundles: serde_json::Value,
+    pub status: FailedPaymentStatus,
+    pub resolved_by: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub retry_reference: Option<i32>,
+}
+
+impl TryFrom<FailedPayment> for FailedPaymentCommon {
+    type Error = serde_json::Error;
+
+    fn try_from(val: FailedPayment) -> Result<Self, Self::Error> {
+        Ok(FailedPaymentCommon {
+            id: val.id,
+            reference: val.reference,
+            reason: val.reason,
+            timed_out: val.timed_out,
+            time: val.time,
+            failed_at: val.failed_at,
+            description: val.description,
+            debited_account: val.debited_account,
+            credited_account: val.credited_account,
+            amount: val.amount.into(),
+            bundles: serde_json::from_value(val.bundles)?,
+            created_by: val.created_by,
+            member_id: val.member_id,
+            status: val.status,
+            resolved_by: val.resolved_by,
+            resolved_at: val.resolved_at,
+            retry_reference: val.retry_reference,
+        })
+    }
+}
@@ -1,8 +1,11 @@
 pub mod book_account;
+pub mod correction;
 pub mod event;
 pub mod inventory;
 pub mod izettle_transaction;
 pub mod signup;
+pub mod store_layout;
+pub mod swish;
 pub mod transaction;
 
 pub use self::event::{Event, EventRange, EventWithSignups, NewEvent};
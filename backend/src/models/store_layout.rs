@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
+pub struct StoreLayoutTab {
+    pub id: i32,
+    pub name: String,
+    pub position: i32,
+    pub organization_id: i32,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
+pub struct StoreLayoutButton {
+    pub id: i32,
+    pub tab_id: i32,
+    pub item_id: Option<i32>,
+    pub bundle_id: Option<i32>,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub color: Option<String>,
+}
@@ -0,0 +1,34 @@
+use chrono::{DateTime, Utc};
+use strecklistan_api::swish::{SwishPayment as SwishPaymentCommon, SwishStatus};
+
+#[derive(Queryable, Debug, PartialEq)]
+pub struct SwishPayment {
+    pub id: String,
+    pub organization_id: i32,
+    pub member_id: i32,
+    pub credited_account: i32,
+    pub amount: i64,
+    pub status: SwishStatus,
+    pub token: Option<String>,
+    pub error: Option<String>,
+    pub transaction_id: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl From<SwishPayment> for SwishPaymentCommon {
+    fn from(val: SwishPayment) -> Self {
+        SwishPaymentCommon {
+            id: val.id,
+            member_id: val.member_id,
+            credited_account: val.credited_account,
+            amount: val.amount.into(),
+            status: val.status,
+            token: val.token,
+            error: val.error,
+            transaction_id: val.transaction_id,
+            created_at: val.created_at,
+            completed_at: val.completed_at,
+        }
+    }
+}
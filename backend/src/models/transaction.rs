@@ -3,9 +3,12 @@ pub use strecklistan_api::transaction as object;
 
 /// Relational data models - as represented by the database.
 pub mod relational {
-    use crate::schema::tables::{transaction_bundles, transaction_items, transactions};
-    use chrono::{DateTime, Utc};
+    use crate::schema::tables::{
+        goods_return_items, goods_returns, transaction_bundles, transaction_items, transactions,
+    };
+    use chrono::{DateTime, NaiveDate, Utc};
     use serde::{Deserialize, Serialize};
+    use strecklistan_api::transaction::ReturnReason;
 
     #[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
     #[table_name = "transactions"]
@@ -14,7 +17,21 @@ pub mod relational {
         pub time: Option<DateTime<Utc>>,
         pub debited_account: i32,
         pub credited_account: i32,
-        pub amount: i32,
+        pub amount: i64,
+        pub note: Option<String>,
+        pub tags: Vec<String>,
+        pub created_by: Option<String>,
+        pub idempotency_key: Option<String>,
+        pub organization_id: i32,
+        pub event_code: Option<String>,
+        pub age_verified: bool,
+
+        /// The name of the registered device the transaction was made on
+        pub device: Option<String>,
+
+        /// The member who bought the goods, see
+        /// [NewTransaction](strecklistan_api::transaction::NewTransaction::member_id)
+        pub member_id: Option<i32>,
     }
 
     #[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
@@ -24,8 +41,18 @@ pub mod relational {
         pub time: DateTime<Utc>,
         pub debited_account: i32,
         pub credited_account: i32,
-        pub amount: i32,
+        pub amount: i64,
         pub deleted_at: Option<DateTime<Utc>>,
+        pub note: Option<String>,
+        pub tags: Vec<String>,
+        pub created_by: Option<String>,
+        pub idempotency_key: Option<String>,
+        pub organization_id: i32,
+        pub event_code: Option<String>,
+        pub age_verified: bool,
+        pub import_batch_id: Option<i32>,
+        pub device: Option<String>,
+        pub member_id: Option<i32>,
     }
 
     #[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
@@ -33,8 +60,10 @@ pub mod relational {
     pub struct NewTransactionBundle {
         pub transaction_id: i32,
         pub description: Option<String>,
-        pub price: Option<i32>,
+        pub price: Option<i64>,
         pub change: i32,
+        pub expires_on: Option<NaiveDate>,
+        pub modifier_ids: Vec<i32>,
     }
 
     #[derive(Queryable, Serialize, Deserialize, Debug, PartialEq)]
@@ -42,8 +71,10 @@ pub mod relational {
         pub id: i32,
         pub transaction_id: i32,
         pub description: Option<String>,
-        pub price: Option<i32>,
+        pub price: Option<i64>,
         pub change: i32,
+        pub expires_on: Option<NaiveDate>,
+        pub modifier_ids: Vec<i32>,
     }
 
     #[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
@@ -59,4 +90,21 @@ pub mod relational {
         pub bundle_id: i32,
         pub item_id: i32,
     }
+
+    #[derive(Insertable, Queryable, Serialize, Deserialize, Debug, PartialEq)]
+    #[table_name = "goods_returns"]
+    pub struct GoodsReturn {
+        pub transaction_id: i32,
+        pub original_transaction_id: i32,
+        pub reason: ReturnReason,
+        pub restocked: bool,
+    }
+
+    #[derive(Insertable, Queryable, Serialize, Deserialize, Debug, PartialEq)]
+    #[table_name = "goods_return_items"]
+    pub struct GoodsReturnItem {
+        pub transaction_id: i32,
+        pub item_id: i32,
+        pub amount: i32,
+    }
 }
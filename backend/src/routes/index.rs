@@ -1,13 +1,86 @@
-use rocket::{get, response::content::Html};
+use rocket::{get, response::content::Html, State};
 
 const INDEX_HTML: &str = include_str!("../index.html");
 
+/// The page which loads the frontend
+pub struct IndexHtml(String);
+
+impl IndexHtml {
+    /// Link to the versioned urls of the static files, so that browsers may cache them forever.
+    ///
+    /// `urls` are pairs of urls and versioned urls, see
+    /// [StaticCachedFiles::versioned_urls](crate::util::StaticCachedFiles::versioned_urls).
+    pub fn versioned(urls: &[(String, String)]) -> Self {
+        let mut html = INDEX_HTML.to_string();
+        for (url, versioned) in urls {
+            for quote in &['"', '\''] {
+                html = html.replace(
+                    &format!("{}{}{}", quote, url, quote),
+                    &format!("{}{}{}", quote, versioned, quote),
+                );
+            }
+        }
+        IndexHtml(html)
+    }
+
+    /// Serve the page under `base_path`, see
+    /// [ProxyConfig::base_path](crate::config::ProxyConfig::base_path). The static files are
+    /// linked under it, and the frontend finds it in the `base-path` meta tag.
+    pub fn with_base_path(self, base_path: &str) -> Self {
+        if base_path.is_empty() {
+            return self;
+        }
+
+        let mut html = self.0.replace(
+            r#"<meta name="base-path" content="">"#,
+            &format!(r#"<meta name="base-path" content="{}">"#, base_path),
+        );
+        for route in &["/static/", "/pkg/"] {
+            for quote in &['"', '\''] {
+                html = html.replace(
+                    &format!("{}{}", quote, route),
+                    &format!("{}{}{}", quote, base_path, route),
+                );
+            }
+        }
+        IndexHtml(html)
+    }
+}
+
+impl Default for IndexHtml {
+    fn default() -> Self {
+        IndexHtml(INDEX_HTML.to_string())
+    }
+}
+
 #[get("/")]
-pub async fn root() -> Html<&'static str> {
-    Html(INDEX_HTML)
+pub async fn root(index: &State<IndexHtml>) -> Html<String> {
+    Html(index.0.clone())
 }
 
 #[get("/<_page>", rank = 99)]
-pub async fn wildcard(_page: String) -> Html<&'static str> {
-    Html(INDEX_HTML)
+pub async fn wildcard(index: &State<IndexHtml>, _page: String) -> Html<String> {
+    Html(index.0.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::TestDb;
+    use rocket::http::Status;
+
+    #[test]
+    fn test_base_path() {
+        let mut db = TestDb::new();
+        db.config.proxy.base_path = "/strecklistan".to_string();
+        let client = db.client();
+
+        // the proxy strips the base path, but the page has to link to the files through it
+        let response = client.get("/store").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let html = response.into_string().unwrap();
+        assert!(html.contains(r#"<meta name="base-path" content="/strecklistan">"#));
+        assert!(html.contains(r#"href="/strecklistan/static/styles.css""#));
+        assert!(html.contains("'/strecklistan/pkg/package.js'"));
+        assert!(!html.contains(r#""/static/"#));
+    }
 }
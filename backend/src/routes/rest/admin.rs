@@ -0,0 +1,655 @@
+use crate::auth::AdminSession;
+use crate::config::Config;
+use crate::database::{DatabasePool, ReportingPool};
+use crate::routes::rest::izettle::izettle_bridge_poll::BridgeActivity;
+use crate::routes::rest::member::load_member_accounts;
+use crate::routes::rest::transaction::{load_lock_date, load_transactions};
+use crate::util::audit::{self, AuditAction};
+use crate::util::retention;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::dsl::{count_star, min};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, put, State};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use strecklistan_api::admin::{
+    AdminStatus, BackupFile, BackupStatus, BridgeStatus, FeatureFlags, RetentionReport,
+    WebhookStatus,
+};
+use strecklistan_api::member::MemberId;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::report::{DormantBalanceReport, MemberLiability};
+use strecklistan_api::time::local_date;
+use strecklistan_api::transaction::{find_duplicates, DuplicatePair};
+use strecklistan_api::user::User;
+
+/// Transactions with the same contents this close in time are probably double-submits
+const DUPLICATE_WINDOW_SECONDS: i64 = 10;
+
+/// GET `/admin/status`
+///
+/// Feature flags, bridge, webhook and backup status in one go
+#[get("/admin/status")]
+pub fn get_admin_status(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    activity: &State<BridgeActivity>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<AdminStatus>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let backup = match &config.backup {
+        Some(backup) => BackupStatus {
+            configured: true,
+            latest: latest_backup(&backup.directory).map_err(|e| {
+                SJ::new(
+                    Status::InternalServerError,
+                    format!("Could not read backup directory: {}", e),
+                )
+            })?,
+        },
+        None => BackupStatus {
+            configured: false,
+            latest: None,
+        },
+    };
+
+    Ok(accept.ser(AdminStatus {
+        features: FeatureFlags {
+            izettle_enabled: config.payments.izettle_enabled,
+            smtp_enabled: config.smtp.is_some(),
+            webhooks_enabled: !config.webhooks.is_empty(),
+        },
+        bridge: bridge_status(&connection, activity, admin.0.organization)?,
+        webhooks: config
+            .webhooks
+            .iter()
+            .map(|webhook| WebhookStatus {
+                url: webhook.url.clone(),
+                signed: webhook.secret.is_some(),
+            })
+            .collect(),
+        backup,
+        lock_date: load_lock_date(&connection, admin.0.organization)?,
+    }))
+}
+
+/// When the bridge was last heard from, and the payments which are waiting for it
+pub fn bridge_status(
+    connection: &PgConnection,
+    activity: &BridgeActivity,
+    organization: OrganizationId,
+) -> Result<BridgeStatus, SJ> {
+    use crate::schema::tables::izettle_transaction::dsl::*;
+
+    let (pending_payments, oldest_pending): (i64, Option<DateTime<Utc>>) = izettle_transaction
+        .filter(organization_id.eq(organization))
+        .select((count_star(), min(time)))
+        .first(connection)?;
+
+    Ok(BridgeStatus {
+        last_poll: activity.last_poll(),
+        pending_payments,
+        oldest_pending,
+    })
+}
+
+/// PUT `/admin/lock_date`
+///
+/// Close the books before a date, e.g. once a month has been reported, or open them again with
+/// `null`. Transactions before the lock date can't be voided or edited.
+#[put("/admin/lock_date", data = "<date>")]
+pub fn put_lock_date(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    admin: AdminSession,
+    accept: SerAccept,
+    date: Json<Option<NaiveDate>>,
+) -> Result<Ser<Option<NaiveDate>>, SJ> {
+    let date = date.into_inner();
+    let today = local_date(Utc::now(), config.reporting_timezone);
+    if date.map(|date| date > today).unwrap_or(false) {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "The lock date can't be in the future",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    let organization = admin.0.organization;
+
+    connection.transaction::<_, SJ, _>(|| {
+        {
+            use crate::schema::tables::organizations::dsl::{id, lock_date, organizations};
+            diesel::update(organizations.filter(id.eq(organization)))
+                .set(lock_date.eq(date))
+                .execute(&connection)?;
+        }
+
+        let description = match date {
+            Some(date) => format!("Locked the transactions before {}", date),
+            None => "Removed the lock date".to_string(),
+        };
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&admin.0.user),
+            AuditAction::LockDateChanged,
+            &description,
+        )?;
+
+        Ok(())
+    })?;
+
+    Ok(accept.ser(date))
+}
+
+/// The most recently modified file in `directory`
+fn latest_backup(directory: &Path) -> std::io::Result<Option<BackupFile>> {
+    let mut latest: Option<BackupFile> = None;
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let modified: DateTime<Utc> = metadata.modified()?.into();
+        if latest
+            .as_ref()
+            .map(|l| l.modified < modified)
+            .unwrap_or(true)
+        {
+            latest = Some(BackupFile {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                modified,
+                size_bytes: metadata.len(),
+            });
+        }
+    }
+    Ok(latest)
+}
+
+/// GET `/admin/users`
+///
+/// The users of the organization of the admin
+#[get("/admin/users")]
+pub fn get_users(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Vec<User>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::user_organizations::dsl::{
+        is_admin, organization_id, user_name, user_organizations,
+    };
+    use crate::schema::tables::users::dsl::*;
+
+    let all_users = users
+        .inner_join(user_organizations.on(user_name.eq(name)))
+        .filter(organization_id.eq(admin.0.organization))
+        .select((name, display_name, is_admin))
+        .order_by(name.asc())
+        .load(&connection)?;
+
+    Ok(accept.ser(all_users))
+}
+
+/// PUT `/admin/users/<user_name>/admin`
+///
+/// Grant or revoke the admin role of a user in the organization of the admin
+#[put("/admin/users/<user_name>/admin", data = "<admin>")]
+pub fn put_user_admin(
+    db_pool: &State<DatabasePool>,
+    session: AdminSession,
+    accept: SerAccept,
+    user_name: String,
+    admin: Json<bool>,
+) -> Result<Ser<User>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let admin = admin.into_inner();
+    if !admin && user_name == session.0.user {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "You can not revoke your own admin role",
+        ));
+    }
+
+    check_same_organization(&connection, &user_name, session.0.organization)?;
+
+    {
+        use crate::schema::tables::user_organizations::dsl::{
+            is_admin, organization_id, user_name as member_name, user_organizations,
+        };
+        diesel::update(
+            user_organizations
+                .filter(member_name.eq(&user_name))
+                .filter(organization_id.eq(session.0.organization)),
+        )
+        .set(is_admin.eq(admin))
+        .execute(&connection)?;
+    }
+
+    use crate::schema::tables::users::dsl::*;
+
+    let (user, user_display_name): (String, Option<String>) = users
+        .filter(name.eq(&user_name))
+        .select((name, display_name))
+        .first(&connection)?;
+
+    Ok(accept.ser(User {
+        name: user,
+        display_name: user_display_name,
+        is_admin: admin,
+    }))
+}
+
+/// POST `/admin/users/<user_name>/invalidate_sessions`
+///
+/// Log a user out everywhere, e.g. if their password may have leaked
+#[post("/admin/users/<user_name>/invalidate_sessions")]
+pub fn invalidate_user_sessions(
+    db_pool: &State<DatabasePool>,
+    session: AdminSession,
+    user_name: String,
+) -> Result<(), SJ> {
+    let connection = db_pool.inner().get()?;
+
+    check_same_organization(&connection, &user_name, session.0.organization)?;
+
+    use crate::schema::tables::users::dsl::*;
+
+    diesel::update(users.filter(name.eq(&user_name)))
+        .set(sessions_invalidated_at.eq(Utc::now()))
+        .execute(&connection)?;
+
+    Ok(())
+}
+
+/// Admins may only manage the users of their own organization
+fn check_same_organization(
+    connection: &PgConnection,
+    user: &str,
+    organization: OrganizationId,
+) -> Result<(), SJ> {
+    use crate::schema::tables::user_organizations::dsl::*;
+
+    let same_organization: bool = diesel::select(diesel::dsl::exists(
+        user_organizations
+            .filter(user_name.eq(user))
+            .filter(organization_id.eq(organization)),
+    ))
+    .get_result(connection)?;
+
+    if same_organization {
+        Ok(())
+    } else {
+        Err(SJ::new(Status::NotFound, "No such user"))
+    }
+}
+
+/// GET `/admin/duplicates`
+///
+/// Pairs of transactions which were likely submitted twice by mistake
+#[get("/admin/duplicates")]
+pub fn get_duplicates(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Vec<DuplicatePair>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let transactions = load_transactions(&connection, admin.0.organization, None)?;
+    Ok(accept.ser(find_duplicates(
+        &transactions,
+        Duration::seconds(DUPLICATE_WINDOW_SECONDS),
+    )))
+}
+
+/// GET `/admin/dormant_balances`
+///
+/// The tillgodo balances which haven't been touched for a while, with those which may be
+/// forfeited according to `dormant.forfeit_after_days`
+#[get("/admin/dormant_balances")]
+pub fn get_dormant_balances(
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<DormantBalanceReport>, SJ> {
+    let connection = reporting_pool.inner().get()?;
+    let organization = admin.0.organization;
+    let now = Utc::now();
+
+    let accounts = load_member_accounts(&connection, organization)?;
+    let transactions = load_transactions(&connection, organization, None)?;
+    let reminded: HashMap<MemberId, DateTime<Utc>> = {
+        use crate::schema::tables::members::dsl::*;
+        members
+            .filter(organization_id.eq(organization))
+            .filter(balance_reminded_at.is_not_null())
+            .select((id, balance_reminded_at))
+            .load::<(MemberId, Option<DateTime<Utc>>)>(&connection)?
+            .into_iter()
+            .filter_map(|(member, at)| Some((member, at?)))
+            .collect()
+    };
+
+    Ok(accept.ser(DormantBalanceReport::compute(
+        now,
+        config.dormant.dormant_after_days(),
+        config.dormant.forfeit_after_days,
+        MemberLiability::compute(now, &accounts, &transactions),
+        &reminded,
+    )))
+}
+
+/// GET `/admin/retention`
+///
+/// What the retention jobs would compact or delete of the organization right now, see
+/// `retention` in the config
+#[get("/admin/retention")]
+pub fn get_retention(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<RetentionReport>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let report = retention::plan(
+        &connection,
+        &config.retention,
+        admin.0.organization,
+        Utc::now(),
+    )?;
+    Ok(accept.ser(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_lock_date() {
+        use chrono::{Duration, Utc};
+        use diesel::prelude::*;
+        use strecklistan_api::admin::AdminStatus;
+        use strecklistan_api::error::{ApiError, ErrorCode};
+        use strecklistan_api::time::local_date;
+        use strecklistan_api::transaction::TransactionAnnotation;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (_, member_account) = db.member(org, "Testsson");
+        let old = db.transaction(org, cash, member_account, 1000.into());
+        let recent = db.transaction(org, cash, member_account, 500.into());
+        {
+            use crate::schema::tables::transactions::dsl::*;
+            diesel::update(transactions.filter(id.eq(old)))
+                .set(time.eq(Utc::now() - Duration::days(3)))
+                .execute(&db.conn())
+                .unwrap();
+        }
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+
+        let today = local_date(Utc::now(), db.config.reporting_timezone);
+        let set_lock_date = |date| {
+            client
+                .put("/api/admin/lock_date")
+                .header(ContentType::JSON)
+                .body(serde_json::to_string(&date).unwrap())
+                .dispatch()
+                .status()
+        };
+        assert_eq!(
+            set_lock_date(Some(today + Duration::days(1))),
+            Status::BadRequest
+        );
+        assert_eq!(set_lock_date(Some(today - Duration::days(1))), Status::Ok);
+
+        let status: AdminStatus = get_json(&client, "/api/admin/status");
+        assert_eq!(status.lock_date, Some(today - Duration::days(1)));
+
+        let annotation = TransactionAnnotation {
+            note: Some("Korrigerad".to_string()),
+            tags: vec![],
+            event_code: None,
+        };
+        let response = client
+            .patch(format!("/api/transaction/{}", old))
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&annotation).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+
+        let response = client
+            .delete(format!("/api/transaction/{}", old))
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        let error: ApiError<chrono::NaiveDate> = response.into_json().unwrap();
+        assert_eq!(error.code, ErrorCode::PeriodLocked);
+        assert_eq!(error.details, Some(today - Duration::days(1)));
+
+        let response = client
+            .delete(format!("/api/transaction/{}", recent))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok, "after the lock date");
+
+        // the books can be opened again
+        assert_eq!(set_lock_date(None), Status::Ok);
+        let response = client
+            .delete(format!("/api/transaction/{}", old))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_dormant_balances() {
+        use crate::config::SmtpConfig;
+        use crate::util::dormant::remind_dormant;
+        use chrono::{Duration, Utc};
+        use strecklistan_api::report::DormantBalanceReport;
+
+        let mut db = TestDb::new();
+        db.config.smtp = Some(SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port: 9,
+            username: None,
+            password: None,
+            from: "strecklistan@example.com".to_string(),
+        });
+        db.config.dormant.remind_after_days = Some(365);
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (member, member_account) = db.member(org, "Testsson");
+        let (_, other_account) = db.member(org, "Andersson");
+        let deposit = db.transaction(org, cash, member_account, 10000.into());
+        let purchase = db.transaction(org, member_account, cash, 2500.into());
+        db.transaction(org, cash, other_account, 5000.into());
+
+        let now = Utc::now();
+        {
+            use crate::schema::tables::transactions::dsl::*;
+            diesel::update(transactions.filter(id.eq_any(vec![deposit, purchase])))
+                .set(time.eq(now - Duration::days(400)))
+                .execute(&db.conn())
+                .unwrap();
+        }
+
+        let remind = |at| remind_dormant(&db.conn(), &db.config, at).unwrap();
+        let audited = |logged: &str| -> i64 {
+            use crate::schema::tables::audit_log::dsl::*;
+            audit_log
+                .filter(action.eq(logged))
+                .count()
+                .get_result(&db.conn())
+                .unwrap()
+        };
+
+        // not opted in
+        assert_eq!(remind(now), 0);
+
+        {
+            use crate::schema::tables::members::dsl::*;
+            diesel::update(members.filter(id.eq(member)))
+                .set((
+                    email.eq("testsson@example.com"),
+                    email_verified_at.eq(now),
+                    mail_balance_reminders.eq(true),
+                ))
+                .execute(&db.conn())
+                .unwrap();
+        }
+
+        // reminded once, until the balance is touched again
+        assert_eq!(remind(now), 1);
+        assert_eq!(remind(now + Duration::days(30)), 0);
+        assert_eq!(audited("balance_reminder_sent"), 1);
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+        let report: DormantBalanceReport = get_json(&client, "/api/admin/dormant_balances");
+        assert_eq!(report.dormant_days, 365);
+        assert_eq!(report.balances.len(), 1);
+        let balance = &report.balances[0];
+        assert_eq!(balance.member_id, member);
+        assert_eq!(balance.balance, 7500.into());
+        assert!(balance.reminded_at.is_some());
+        assert!(!balance.forfeitable);
+        assert_eq!(report.total_forfeitable(), 0.into());
+    }
+
+    #[test]
+    fn test_retention() {
+        use crate::util::retention;
+        use chrono::{Duration, Utc};
+        use diesel::dsl::count_star;
+        use diesel::prelude::*;
+        use strecklistan_api::admin::RetentionReport;
+        use strecklistan_api::time::local_date;
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        db.config.retention.compact_after_years = Some(2);
+        db.config.retention.audit_log_days = Some(30);
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+
+        let transaction = |change: i32| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change,
+                item_ids: vec![(kaffe, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let restock: i32 = post_json(&client, "/api/transaction", &transaction(5));
+        let sale: i32 = post_json(&client, "/api/transaction", &transaction(-2));
+        let _: i32 = post_json(&client, "/api/transaction", &transaction(-1));
+
+        let now = Utc::now();
+        {
+            use crate::schema::tables::transactions::dsl::*;
+            diesel::update(transactions.filter(id.eq_any(vec![restock, sale])))
+                .set(time.eq(now - Duration::days(3 * 365)))
+                .execute(&db.conn())
+                .unwrap();
+        }
+        {
+            use crate::schema::tables::audit_log::dsl::*;
+            diesel::update(audit_log)
+                .set(time.eq(now - Duration::days(60)))
+                .execute(&db.conn())
+                .unwrap();
+        }
+
+        // nothing is compacted before it has been exported
+        let report: RetentionReport = get_json(&client, "/api/admin/retention");
+        assert!(report.dry_run);
+        assert_eq!(report.transactions_exported_at, None);
+        assert_eq!(report.compactable_lines, 0);
+        assert!(report.prunable_audit_entries > 0);
+
+        let response = client.get("/api/export/transactions.csv").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        response.into_string().unwrap();
+
+        let report: RetentionReport = get_json(&client, "/api/admin/retention");
+        assert!(report.transactions_exported_at.is_some());
+        assert_eq!(report.compactable_transactions, 2);
+        assert_eq!(report.compactable_lines, 2);
+
+        let lines = || -> i64 {
+            use crate::schema::tables::transaction_items::dsl::*;
+            transaction_items
+                .select(count_star())
+                .first(&db.conn())
+                .unwrap()
+        };
+        let audited = |logged: &str| -> i64 {
+            use crate::schema::tables::audit_log::dsl::*;
+            audit_log
+                .filter(action.eq(logged))
+                .count()
+                .get_result(&db.conn())
+                .unwrap()
+        };
+
+        // a dry run changes nothing
+        retention::run(&db.conn(), &db.config, Utc::now()).unwrap();
+        assert_eq!(lines(), 3);
+        assert_eq!(audited("retention_applied"), 0);
+
+        db.config.retention.dry_run = false;
+        retention::run(&db.conn(), &db.config, Utc::now()).unwrap();
+        assert_eq!(lines(), 1);
+        assert_eq!(audited("login"), 0);
+        assert_eq!(audited("retention_applied"), 1);
+
+        // the compacted items still count
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert_eq!(items[&kaffe].stock, 2);
+        let as_of = local_date(now - Duration::days(365), db.config.reporting_timezone);
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, &format!("/api/inventory/items?as_of={}", as_of));
+        assert_eq!(items[&kaffe].stock, 3);
+
+        let report: RetentionReport = get_json(&client, "/api/admin/retention");
+        assert!(report.is_empty());
+    }
+}
@@ -0,0 +1,599 @@
+use crate::auth::{AdminSession, Session};
+use crate::config::Config;
+use crate::database::{DatabasePool, ReportingPool};
+use crate::models::book_account as relational;
+use crate::routes::rest::book_account::reporting_master_accounts;
+use crate::routes::rest::transaction::{load_transactions, member_purchases};
+use crate::util::analytics::refresh;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::{get, post, State};
+use std::collections::HashMap;
+use std::ops::Range;
+use strecklistan_api::analytics::{
+    CashierShift, CategorySales, DailySales, ItemSparklines, ItemTotals, ModifierTotals,
+    PaymentMethodSales, SPARKLINE_DAYS,
+};
+use strecklistan_api::book_account::{BookAccount, BookAccountId};
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::member::MemberId;
+use strecklistan_api::time::{local_date, start_of_day, PeriodParseError, ReportingPeriod, Tz};
+
+/// GET `/analytics/daily_sales?<from>&<to>&<period>`
+///
+/// The number of every item sold per day and cashier, optionally only from `from` up to and
+/// including `to` (formatted as YYYY-MM-DD), or only during a [ReportingPeriod] such as
+/// `2021-W26` or `VT2021`. Lags behind the transactions until the next refresh.
+#[get("/analytics/daily_sales?<from>&<to>&<period>")]
+pub fn get_daily_sales(
+    reporting_pool: &State<ReportingPool>,
+    session: Session,
+    accept: SerAccept,
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+) -> Result<Ser<Vec<DailySales>>, SJ> {
+    let connection = reporting_pool.inner().get()?;
+    let (from, to) = parse_days(from, to, period)?;
+
+    use crate::schema::views::daily_sales::dsl::*;
+    let mut query = daily_sales
+        .filter(organization_id.eq(session.organization))
+        .order_by((day.asc(), item_id.asc()))
+        .select((day, created_by, item_id, sold))
+        .into_boxed();
+
+    if let Some(from) = from {
+        query = query.filter(day.ge(from));
+    }
+    if let Some(to) = to {
+        query = query.filter(day.le(to));
+    }
+
+    Ok(accept.ser(query.load(&connection)?))
+}
+
+/// GET `/analytics/shifts?<from>&<to>&<period>`
+///
+/// The shifts of the cashiers, latest first, with the same filters as `/analytics/daily_sales`.
+/// Without any filter, the shifts of the last 30 days. Admins see the shifts of everyone, other
+/// users only their own.
+#[get("/analytics/shifts?<from>&<to>&<period>")]
+#[allow(clippy::too_many_arguments)]
+pub fn get_shifts(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    admin: Option<AdminSession>,
+    accept: SerAccept,
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+) -> Result<Ser<Vec<CashierShift>>, SJ> {
+    let range = parse_range(from, to, period, config.reporting_timezone)?;
+
+    let sales_account = reporting_master_accounts(db_pool, session.organization)?.sales_account_id;
+    let connection = reporting_pool.inner().get()?;
+    let transactions = load_transactions(&connection, session.organization, Some(range))?;
+    let mut shifts = CashierShift::group(&transactions, sales_account);
+    if admin.is_none() {
+        shifts.retain(|shift| shift.cashier == session.user);
+    }
+
+    Ok(accept.ser(shifts))
+}
+
+/// GET `/analytics/payment_methods?<from>&<to>&<period>`
+///
+/// The sales per day and payment method, with the same filters and default as
+/// `/analytics/shifts`
+#[get("/analytics/payment_methods?<from>&<to>&<period>")]
+#[allow(clippy::too_many_arguments)]
+pub fn get_payment_method_sales(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+) -> Result<Ser<Vec<PaymentMethodSales>>, SJ> {
+    let tz = config.reporting_timezone;
+    let range = parse_range(from, to, period, tz)?;
+
+    let masters = reporting_master_accounts(db_pool, session.organization)?;
+    let connection = reporting_pool.inner().get()?;
+
+    let accounts: HashMap<BookAccountId, BookAccount> = {
+        use crate::schema::tables::book_accounts::dsl::{book_accounts, organization_id};
+        book_accounts
+            .filter(organization_id.eq(session.organization))
+            .load(&connection)?
+            .into_iter()
+            .map(|acc: relational::BookAccount| (acc.id, acc.into()))
+            .collect()
+    };
+
+    let transactions = load_transactions(&connection, session.organization, Some(range))?;
+    Ok(accept.ser(PaymentMethodSales::group(
+        &transactions,
+        &masters,
+        &accounts,
+        tz,
+    )))
+}
+
+/// GET `/analytics/categories?<from>&<to>&<period>`
+///
+/// The revenue per day and item category, with the same filters and default as
+/// `/analytics/shifts`. Items with several tags are counted under the first of them.
+#[get("/analytics/categories?<from>&<to>&<period>")]
+#[allow(clippy::too_many_arguments)]
+pub fn get_category_sales(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+) -> Result<Ser<Vec<CategorySales>>, SJ> {
+    let tz = config.reporting_timezone;
+    let range = parse_range(from, to, period, tz)?;
+
+    let sales_account = reporting_master_accounts(db_pool, session.organization)?.sales_account_id;
+    let connection = reporting_pool.inner().get()?;
+
+    let categories: HashMap<InventoryItemId, String> = {
+        use crate::schema::tables::inventory::dsl::{id, inventory, organization_id};
+        use crate::schema::tables::inventory_tags::dsl::{inventory_tags, item_id, tag};
+        let tags: Vec<(InventoryItemId, String)> = inventory_tags
+            .filter(
+                item_id.eq_any(
+                    inventory
+                        .filter(organization_id.eq(session.organization))
+                        .select(id),
+                ),
+            )
+            .order_by((item_id.asc(), tag.asc()))
+            .select((item_id, tag))
+            .load(&connection)?;
+
+        let mut categories = HashMap::new();
+        for (item, category) in tags {
+            categories.entry(item).or_insert(category);
+        }
+        categories
+    };
+
+    let transactions = load_transactions(&connection, session.organization, Some(range))?;
+    Ok(accept.ser(CategorySales::group(
+        &transactions,
+        sales_account,
+        &categories,
+        tz,
+    )))
+}
+
+/// GET `/analytics/item_totals`
+///
+/// How many of every item have been sold and restocked, most sold first
+#[get("/analytics/item_totals")]
+pub fn get_item_totals(
+    reporting_pool: &State<ReportingPool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<ItemTotals>>, SJ> {
+    let connection = reporting_pool.inner().get()?;
+
+    use crate::schema::views::item_totals::dsl::*;
+    Ok(accept.ser(
+        item_totals
+            .filter(organization_id.eq(session.organization))
+            .order_by((sold.desc(), item_id.asc()))
+            .select((item_id, sold, restocked, last_sold))
+            .load(&connection)?,
+    ))
+}
+
+/// GET `/analytics/modifier_totals`
+///
+/// How many of every item have been sold with each of its modifiers, most sold first
+#[get("/analytics/modifier_totals")]
+pub fn get_modifier_totals(
+    reporting_pool: &State<ReportingPool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<ModifierTotals>>, SJ> {
+    let connection = reporting_pool.inner().get()?;
+
+    use crate::schema::views::modifier_totals::dsl::*;
+    Ok(accept.ser(
+        modifier_totals
+            .filter(organization_id.eq(session.organization))
+            .order_by((sold.desc(), modifier_id.asc()))
+            .select((item_id, modifier_id, sold))
+            .load(&connection)?,
+    ))
+}
+
+/// GET `/analytics/member/<member_id>/items?<from>&<to>&<period>`
+///
+/// How many of every item a member has bought, less what they have returned, with the same
+/// filters and default as `/analytics/shifts`. Counts the sales paid from their tillgodo as well
+/// as those they were tagged in.
+#[get("/analytics/member/<member_id>/items?<from>&<to>&<period>")]
+#[allow(clippy::too_many_arguments)]
+pub fn get_member_items(
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+) -> Result<Ser<HashMap<InventoryItemId, i64>>, SJ> {
+    let connection = reporting_pool.inner().get()?;
+    let range = parse_range(from, to, period, config.reporting_timezone)?;
+    Ok(accept.ser(member_purchases(
+        &connection,
+        session.organization,
+        member_id,
+        range,
+    )?))
+}
+
+/// GET `/analytics/item_sparklines`
+///
+/// How many of every item have been sold per day during the last [SPARKLINE_DAYS] days, summed
+/// over all cashiers. Lags behind the transactions until the next refresh.
+#[get("/analytics/item_sparklines")]
+pub fn get_item_sparklines(
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<ItemSparklines>, SJ> {
+    let connection = reporting_pool.inner().get()?;
+    let today = local_date(Utc::now(), config.reporting_timezone);
+    let first_day = today - Duration::days(SPARKLINE_DAYS as i64 - 1);
+
+    use crate::schema::views::daily_sales::dsl::*;
+    let rows: Vec<(NaiveDate, InventoryItemId, i64)> = daily_sales
+        .filter(organization_id.eq(session.organization))
+        .filter(day.ge(first_day))
+        .filter(day.le(today))
+        .select((day, item_id, sold))
+        .load(&connection)?;
+
+    let mut sparklines: HashMap<InventoryItemId, Vec<i64>> = HashMap::new();
+    for (sold_on, item, count) in rows {
+        let index = (sold_on - first_day).num_days() as usize;
+        sparklines
+            .entry(item)
+            .or_insert_with(|| vec![0; SPARKLINE_DAYS])[index] += count;
+    }
+
+    Ok(accept.ser(ItemSparklines {
+        first_day,
+        sold: sparklines,
+    }))
+}
+
+/// POST `/analytics/refresh`
+///
+/// Recompute the analytics right away, instead of waiting for the scheduled refresh
+#[post("/analytics/refresh")]
+pub fn refresh_analytics(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    _admin: AdminSession,
+) -> Result<Status, SJ> {
+    let connection = db_pool.inner().get()?;
+    refresh(&connection, config.reporting_timezone)?;
+    Ok(Status::NoContent)
+}
+
+/// The days from `from` up to and including `to`, or those of `period`
+fn parse_days(
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), SJ> {
+    let parse_date = |date: Option<String>| {
+        date.map(|date| NaiveDate::parse_from_str(&date, "%Y-%m-%d"))
+            .transpose()
+            .map_err(|_| SJ::new(Status::BadRequest, "Invalid date, expected YYYY-MM-DD"))
+    };
+    match period {
+        Some(_) if from.is_some() || to.is_some() => Err(SJ::new(
+            Status::BadRequest,
+            "Either a period or dates can be given, not both",
+        )),
+        Some(period) => {
+            let period: ReportingPeriod = period
+                .parse()
+                .map_err(|e: PeriodParseError| SJ::new(Status::BadRequest, e.to_string()))?;
+            Ok((Some(period.first_day()), Some(period.last_day())))
+        }
+        None => Ok((parse_date(from)?, parse_date(to)?)),
+    }
+}
+
+/// The end of the day `as_of` (formatted as YYYY-MM-DD) in the timezone `tz`, which a historical
+/// snapshot of e.g. the stock or the balances is computed at
+pub fn parse_as_of(as_of: &str, tz: Tz) -> Result<DateTime<Utc>, SJ> {
+    let date = NaiveDate::parse_from_str(as_of, "%Y-%m-%d")
+        .map_err(|_| SJ::new(Status::BadRequest, "Invalid date, expected YYYY-MM-DD"))?;
+    Ok(start_of_day(date + Duration::days(1), tz))
+}
+
+/// The time range of [parse_days] in the timezone `tz`, the last 30 days if neither end is given
+fn parse_range(
+    from: Option<String>,
+    to: Option<String>,
+    period: Option<String>,
+    tz: Tz,
+) -> Result<Range<DateTime<Utc>>, SJ> {
+    let (from, to) = parse_days(from, to, period)?;
+    let today = local_date(Utc::now(), tz);
+    let from = from.unwrap_or_else(|| to.unwrap_or(today) - Duration::days(29));
+    let to = to.unwrap_or(today);
+    Ok(start_of_day(from, tz)..start_of_day(to + Duration::days(1), tz))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_analytics() {
+        use chrono::Utc;
+        use strecklistan_api::analytics::{DailySales, ItemSparklines, ItemTotals, SPARKLINE_DAYS};
+        use strecklistan_api::time::{PeriodKind, ReportingPeriod, DEFAULT_REPORTING_TIMEZONE};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, true);
+        let item = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        for &change in &[5, -2] {
+            let mut item_ids = HashMap::new();
+            item_ids.insert(item, 1);
+            let transaction = NewTransaction {
+                description: None,
+                bundles: vec![TransactionBundle {
+                    description: None,
+                    price: None,
+                    change,
+                    item_ids,
+                    expires_on: None,
+                    modifier_ids: vec![],
+                }],
+                debited_account: cash,
+                credited_account: sales,
+                amount: 1000.into(),
+                note: None,
+                tags: vec![],
+                event_code: None,
+                age_verified: false,
+                member_id: None,
+                idempotency_key: None,
+            };
+            let _: i32 = post_json(&client, "/api/transaction", &transaction);
+        }
+
+        // the views are only updated when refreshed
+        let daily: Vec<DailySales> = get_json(&client, "/api/analytics/daily_sales");
+        assert!(daily.is_empty());
+
+        let response = client.post("/api/analytics/refresh").dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+
+        let daily: Vec<DailySales> = get_json(&client, "/api/analytics/daily_sales");
+        assert_eq!(daily.len(), 1);
+        assert_eq!(daily[0].sold, 2);
+        assert_eq!(daily[0].created_by.as_deref(), Some("tester"));
+
+        let this_month =
+            ReportingPeriod::at(PeriodKind::Month, Utc::now(), DEFAULT_REPORTING_TIMEZONE);
+        let in_period = |period: ReportingPeriod| -> Vec<DailySales> {
+            get_json(
+                &client,
+                &format!("/api/analytics/daily_sales?period={}", period),
+            )
+        };
+        assert_eq!(in_period(this_month).len(), 1);
+        assert!(in_period(this_month.previous()).is_empty());
+        let uri = format!(
+            "/api/analytics/daily_sales?period={}&from=2021-01-01",
+            this_month
+        );
+        assert_eq!(client.get(uri).dispatch().status(), Status::BadRequest);
+
+        let totals: Vec<ItemTotals> = get_json(&client, "/api/analytics/item_totals");
+        assert_eq!(totals.len(), 1);
+        assert_eq!((totals[0].sold, totals[0].restocked), (2, 5));
+
+        let sparklines: ItemSparklines = get_json(&client, "/api/analytics/item_sparklines");
+        let sold = &sparklines.sold[&item];
+        assert_eq!(sold.len(), SPARKLINE_DAYS);
+        assert_eq!(sold.last(), Some(&2), "today is last");
+        assert_eq!(sold.iter().sum::<i64>(), 2);
+    }
+
+    #[test]
+    fn test_shifts() {
+        use crate::config::NegativeStock;
+        use strecklistan_api::analytics::CashierShift;
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        db.config.stock.negative_stock = NegativeStock::Warn;
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+        db.user("kassör", "hunter3", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let sale = |amount: i32| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change: -1,
+                item_ids: vec![(kaffe, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        let admin = db.client();
+        login(&admin, "admin", "hunter2");
+        let _: i32 = post_json(&admin, "/api/transaction", &sale(500));
+
+        let cashier = db.client();
+        login(&cashier, "kassör", "hunter3");
+        let _: i32 = post_json(&cashier, "/api/transaction", &sale(500));
+        let _: i32 = post_json(&cashier, "/api/transaction", &sale(1000));
+
+        let shifts: Vec<CashierShift> = get_json(&admin, "/api/analytics/shifts");
+        let mut cashiers: Vec<&str> = shifts.iter().map(|s| s.cashier.as_str()).collect();
+        cashiers.sort_unstable();
+        assert_eq!(cashiers, vec!["admin", "kassör"]);
+
+        // the other cashiers' shifts aren't shown to non-admins
+        let shifts: Vec<CashierShift> = get_json(&cashier, "/api/analytics/shifts");
+        assert_eq!(shifts.len(), 1);
+        assert_eq!(shifts[0].cashier, "kassör");
+        assert_eq!(shifts[0].transaction_count, 2);
+        assert_eq!(shifts[0].revenue, 1500.into());
+        assert_eq!(shifts[0].average_basket(), 750.into());
+
+        let status = admin
+            .get("/api/analytics/shifts?period=VT2021&from=2021-01-01")
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::BadRequest);
+    }
+
+    #[test]
+    fn test_revenue_breakdown() {
+        use crate::config::NegativeStock;
+        use diesel::prelude::*;
+        use strecklistan_api::analytics::{CategorySales, PaymentMethod, PaymentMethodSales};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        db.config.stock.negative_stock = NegativeStock::Warn;
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let bulle = db.item(org, "Bulle", Some(1000.into()));
+        let cash = db.account(org, "Kontantkassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let (_, tillgodo) = db.member(org, "Kalle");
+
+        {
+            use crate::schema::tables::inventory_tags::dsl::*;
+            diesel::insert_into(inventory_tags)
+                .values((tag.eq("Dryck"), item_id.eq(kaffe)))
+                .execute(&db.conn())
+                .unwrap();
+        }
+
+        let sale = |debited_account, items: &[(i32, i32)], amount: i32| NewTransaction {
+            description: None,
+            bundles: items
+                .iter()
+                .map(|&(item, price)| TransactionBundle {
+                    description: None,
+                    price: Some(price.into()),
+                    change: -1,
+                    item_ids: vec![(item, 1)].into_iter().collect(),
+                    expires_on: None,
+                    modifier_ids: vec![],
+                })
+                .collect(),
+            debited_account,
+            credited_account: sales,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let _: i32 = post_json(
+            &client,
+            "/api/transaction",
+            &sale(cash, &[(kaffe, 500)], 500),
+        );
+        let _: i32 = post_json(
+            &client,
+            "/api/transaction",
+            &sale(tillgodo, &[(kaffe, 500), (bulle, 1000)], 1500),
+        );
+
+        let by_method: Vec<PaymentMethodSales> =
+            get_json(&client, "/api/analytics/payment_methods");
+        let by_method: Vec<(PaymentMethod, u32, i64)> = by_method
+            .into_iter()
+            .map(|sales| (sales.method, sales.count, sales.revenue.into()))
+            .collect();
+        assert_eq!(
+            by_method,
+            vec![
+                (PaymentMethod::Cash, 1, 500),
+                (PaymentMethod::Tillgodo, 1, 1500)
+            ]
+        );
+
+        let by_category: Vec<CategorySales> = get_json(&client, "/api/analytics/categories");
+        let by_category: Vec<(Option<String>, i64)> = by_category
+            .into_iter()
+            .map(|sales| (sales.category, sales.revenue.into()))
+            .collect();
+        assert_eq!(
+            by_category,
+            vec![(None, 1000), (Some("Dryck".to_string()), 1000)]
+        );
+
+        let status = client
+            .get("/api/analytics/categories?from=yesterday")
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::BadRequest);
+    }
+}
@@ -0,0 +1,231 @@
+use crate::auth::{AdminSession, Session};
+use crate::database::DatabasePool;
+use crate::routes::rest::live::LiveEvents;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::Utc;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use strecklistan_api::announcement::{Announcement, AnnouncementId, NewAnnouncement};
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::validation::Validate;
+
+/// GET `/announcements`
+///
+/// The announcements which haven't ended, including those which haven't started yet so that the
+/// client can show them on time. The most severe first.
+#[get("/announcements")]
+pub fn get_announcements(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<Announcement>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::announcements::dsl::*;
+    let result = announcements
+        .filter(organization_id.eq(session.organization))
+        .filter(ends_at.is_null().or(ends_at.gt(Utc::now())))
+        .order_by((severity.desc(), starts_at.desc()))
+        .select((
+            id, message, severity, starts_at, ends_at, created_by, created_at,
+        ))
+        .load(&connection)?;
+
+    Ok(accept.ser(result))
+}
+
+/// GET `/admin/announcements`
+///
+/// Every announcement of the organization, also those which have ended, the latest first
+#[get("/admin/announcements")]
+pub fn get_all_announcements(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Vec<Announcement>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::announcements::dsl::*;
+    let result = announcements
+        .filter(organization_id.eq(admin.0.organization))
+        .order_by((starts_at.desc(), id.desc()))
+        .select((
+            id, message, severity, starts_at, ends_at, created_by, created_at,
+        ))
+        .load(&connection)?;
+
+    Ok(accept.ser(result))
+}
+
+/// POST `/admin/announcements`
+///
+/// Make an announcement, returns its id
+#[post("/admin/announcements", data = "<announcement>")]
+pub fn post_announcement(
+    db_pool: &State<DatabasePool>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    accept: SerAccept,
+    announcement: Json<NewAnnouncement>,
+) -> Result<Ser<AnnouncementId>, SJ> {
+    let mut announcement = announcement.into_inner();
+    announcement.message = announcement.message.trim().to_string();
+    announcement.validate()?;
+    if announcement
+        .ends_at
+        .map(|end| end <= announcement.starts_at)
+        .unwrap_or(false)
+    {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "An announcement must end after it starts",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    let announcement_id = {
+        use crate::schema::tables::announcements::dsl::*;
+        diesel::insert_into(announcements)
+            .values((
+                organization_id.eq(admin.0.organization),
+                message.eq(&announcement.message),
+                severity.eq(announcement.severity),
+                starts_at.eq(announcement.starts_at),
+                ends_at.eq(announcement.ends_at),
+                created_by.eq(&admin.0.user),
+            ))
+            .returning(id)
+            .get_result(&connection)?
+    };
+
+    live.notify(admin.0.organization, LiveEvent::AnnouncementsChanged);
+
+    Ok(accept.ser(announcement_id))
+}
+
+/// DELETE `/admin/announcements/<announcement_id>`
+///
+/// Remove an announcement, e.g. once the problem it was about has been fixed
+#[delete("/admin/announcements/<announcement_id>")]
+pub fn delete_announcement(
+    db_pool: &State<DatabasePool>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    accept: SerAccept,
+    announcement_id: AnnouncementId,
+) -> Result<Ser<AnnouncementId>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let deleted_id = {
+        use crate::schema::tables::announcements::dsl::*;
+        diesel::delete(
+            announcements
+                .filter(id.eq(announcement_id))
+                .filter(organization_id.eq(admin.0.organization)),
+        )
+        .returning(id)
+        .get_result(&connection)?
+    };
+
+    live.notify(admin.0.organization, LiveEvent::AnnouncementsChanged);
+
+    Ok(accept.ser(deleted_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+
+    #[test]
+    fn test_announcements() {
+        use crate::routes::rest::live::LiveEvents;
+        use chrono::{Duration, Utc};
+        use strecklistan_api::announcement::{
+            Announcement, AnnouncementId, AnnouncementSeverity, NewAnnouncement,
+        };
+        use strecklistan_api::live::LiveEvent;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+        let mut live = client.rocket().state::<LiveEvents>().unwrap().subscribe();
+
+        let now = Utc::now();
+        let announcement = |severity, starts_at, ends_at| NewAnnouncement {
+            message: "Kortläsaren är trasig ikväll, endast kontant/tillgodo".to_string(),
+            severity,
+            starts_at,
+            ends_at,
+        };
+        let broken_reader: AnnouncementId = post_json(
+            &client,
+            "/api/admin/announcements",
+            &announcement(
+                AnnouncementSeverity::Warning,
+                now - Duration::hours(1),
+                Some(now + Duration::hours(5)),
+            ),
+        );
+        let closed: AnnouncementId = post_json(
+            &client,
+            "/api/admin/announcements",
+            &announcement(
+                AnnouncementSeverity::Critical,
+                now + Duration::days(1),
+                None,
+            ),
+        );
+        let ended: AnnouncementId = post_json(
+            &client,
+            "/api/admin/announcements",
+            &announcement(
+                AnnouncementSeverity::Info,
+                now - Duration::days(2),
+                Some(now - Duration::days(1)),
+            ),
+        );
+        let (event_org, event) = live.try_recv().unwrap();
+        assert_eq!(event_org, org);
+        assert_eq!(event, LiveEvent::AnnouncementsChanged);
+
+        let status = post(
+            &client,
+            "/api/admin/announcements",
+            &announcement(AnnouncementSeverity::Info, now, Some(now)),
+        );
+        assert_eq!(status, Status::BadRequest);
+        let status = post(
+            &client,
+            "/api/admin/announcements",
+            &NewAnnouncement {
+                message: "  ".to_string(),
+                ..announcement(AnnouncementSeverity::Info, now, None)
+            },
+        );
+        assert_eq!(status, Status::UnprocessableEntity);
+
+        // the ended one is left out, and the one to come is shown once it starts
+        let current: Vec<Announcement> = get_json(&client, "/api/announcements");
+        let ids: Vec<AnnouncementId> = current.iter().map(|a| a.id).collect();
+        assert_eq!(ids, vec![closed, broken_reader]);
+        assert!(current[1].is_active(now));
+        assert!(!current[0].is_active(now));
+        assert_eq!(current[1].created_by.as_deref(), Some("admin"));
+
+        let all: Vec<Announcement> = get_json(&client, "/api/admin/announcements");
+        assert_eq!(all.len(), 3);
+        assert!(all.iter().any(|a| a.id == ended));
+
+        let uri = format!("/api/admin/announcements/{}", broken_reader);
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::Ok);
+        let current: Vec<Announcement> = get_json(&client, "/api/announcements");
+        assert_eq!(current.len(), 1);
+    }
+}
@@ -0,0 +1,651 @@
+use crate::auth::{check_password, hash_password, is_admin_of, PasswordCheck, Session};
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::routes::rest::organization::load_branding;
+use crate::util::approval::Approvals;
+use crate::util::audit::{self, AuditAction};
+use crate::util::client::Client;
+use crate::util::device::DEVICE_COOKIE;
+use crate::util::mail::Mail;
+use crate::util::outbox;
+use crate::util::rate_limit::{Login, RateLimit};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use log::{info, warn};
+use rocket::http::{Cookie, CookieJar, SameSite, Status};
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use strecklistan_api::approval::{Approval, ApprovalRequest};
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::organization::Organization;
+use strecklistan_api::user::{Credentials, User};
+use uuid::Uuid;
+
+/// POST `/login`
+///
+/// Check the credentials and set a session cookie.
+///
+/// If the user belongs to several organizations and none was chosen, responds with 300 Multiple
+/// Choices and the organizations as details.
+///
+/// Rate limited per client, to slow down password guessing. Accounts are also locked after too
+/// many failed logins in a row, which is recorded in the audit log and mailed to the user.
+#[post("/login", data = "<credentials>")]
+pub fn login(
+    _limit: RateLimit<Login>,
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    cookies: &CookieJar<'_>,
+    client: Client,
+    accept: SerAccept,
+    credentials: Json<Credentials>,
+) -> Result<Ser<User>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let credentials = credentials.into_inner();
+    let client_ip = client
+        .ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "an unknown address".to_string());
+
+    let user: Option<LoginUser> = {
+        use crate::schema::tables::users::dsl::*;
+        users
+            .filter(name.eq(&credentials.name))
+            .select((
+                name,
+                display_name,
+                salted_pass,
+                hash_iterations,
+                email,
+                failed_logins,
+                locked_until,
+            ))
+            .first(&connection)
+            .optional()?
+    };
+
+    // locked accounts don't even get their password checked, so it can't be guessed meanwhile
+    if let Some(until) = user.as_ref().and_then(|user| user.locked_until) {
+        if until > Utc::now() {
+            warn!("Login as locked {} from {}", credentials.name, client_ip);
+            return Err(SJ::new(Status::Locked, "The account is locked")
+                .with_code(ErrorCode::AccountLocked)
+                .with_details(&until));
+        }
+    }
+
+    let check = match &user {
+        Some(user) => check_password(
+            &credentials.password,
+            &user.salted_pass,
+            user.hash_iterations as u32,
+            &config.passwords,
+        ),
+        None => PasswordCheck::Wrong,
+    };
+
+    let user = match (user, check) {
+        (Some(user), PasswordCheck::Correct { outdated }) => {
+            if outdated {
+                rehash_password(&connection, &user.name, &credentials.password, config);
+            }
+            if user.failed_logins > 0 {
+                use crate::schema::tables::users::dsl::*;
+                diesel::update(users.filter(name.eq(&user.name)))
+                    .set(failed_logins.eq(0))
+                    .execute(&connection)?;
+            }
+            user
+        }
+        (user, _) => {
+            warn!("Failed login as {} from {}", credentials.name, client_ip);
+            match user {
+                Some(user) => connection
+                    .transaction(|| record_failed_login(&connection, config, &user, &client_ip))?,
+                None => audit::record(
+                    &connection,
+                    None,
+                    None,
+                    AuditAction::LoginFailed,
+                    &format!("Unknown user {} from {}", credentials.name, client_ip),
+                )?,
+            }
+            return Err(
+                SJ::new(Status::Unauthorized, "Invalid username or password")
+                    .with_code(ErrorCode::InvalidCredentials),
+            );
+        }
+    };
+
+    let organizations: Vec<Organization> = {
+        use crate::schema::tables::organizations::dsl::{id, name as org_name, organizations};
+        use crate::schema::tables::user_organizations::dsl::{
+            organization_id, user_name, user_organizations,
+        };
+        user_organizations
+            .inner_join(organizations.on(id.eq(organization_id)))
+            .filter(user_name.eq(&user.name))
+            .select((id, org_name))
+            .order_by(org_name.asc())
+            .load(&connection)?
+    };
+
+    let organization = match (credentials.organization, organizations.as_slice()) {
+        (Some(chosen), _) => organizations
+            .iter()
+            .find(|org| org.id == chosen)
+            .ok_or_else(|| SJ::new(Status::Forbidden, "Not a member of that organization"))?,
+        (None, [only]) => only,
+        (None, []) => {
+            return Err(SJ::new(
+                Status::Forbidden,
+                "Not a member of any organization",
+            ))
+        }
+        (None, _) => {
+            return Err(SJ::new(Status::MultipleChoices, "Choose an organization")
+                .with_code(ErrorCode::ChooseOrganization)
+                .with_details(&organizations))
+        }
+    };
+
+    info!("{} logged in from {}", user.name, client_ip);
+    audit::record(
+        &connection,
+        Some(organization.id),
+        Some(&user.name),
+        AuditAction::Login,
+        &format!("From {}", client_ip),
+    )?;
+    connection.transaction(|| {
+        check_device(
+            &connection,
+            config,
+            cookies,
+            &user,
+            organization,
+            client,
+            &client_ip,
+        )
+    })?;
+
+    let is_admin = is_admin_of(&connection, &user.name, organization.id)?;
+    Session::new(user.name.clone(), organization.id, config).set_cookie(cookies, client.secure);
+    Ok(accept.ser(User {
+        name: user.name,
+        display_name: user.display_name,
+        is_admin,
+    }))
+}
+
+/// POST `/approval`
+///
+/// Let an admin approve a [GatedAction](strecklistan_api::approval::GatedAction) of the logged
+/// in user by entering their own password. The returned token is sent in the
+/// [APPROVAL_HEADER](strecklistan_api::approval::APPROVAL_HEADER) of the request for the action.
+///
+/// Failed attempts count towards locking the account of the admin, just like failed logins.
+#[post("/approval", data = "<request>")]
+pub fn post_approval(
+    _limit: RateLimit<Login>,
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    approvals: &State<Approvals>,
+    session: Session,
+    client: Client,
+    accept: SerAccept,
+    request: Json<ApprovalRequest>,
+) -> Result<Ser<Approval>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let request = request.into_inner();
+    let client_ip = client
+        .ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "an unknown address".to_string());
+
+    let user: Option<LoginUser> = {
+        use crate::schema::tables::users::dsl::*;
+        users
+            .filter(name.eq(&request.name))
+            .select((
+                name,
+                display_name,
+                salted_pass,
+                hash_iterations,
+                email,
+                failed_logins,
+                locked_until,
+            ))
+            .first(&connection)
+            .optional()?
+    };
+
+    if let Some(until) = user.as_ref().and_then(|user| user.locked_until) {
+        if until > Utc::now() {
+            return Err(SJ::new(Status::Locked, "The account is locked")
+                .with_code(ErrorCode::AccountLocked)
+                .with_details(&until));
+        }
+    }
+
+    let check = match &user {
+        Some(user) => check_password(
+            &request.password,
+            &user.salted_pass,
+            user.hash_iterations as u32,
+            &config.passwords,
+        ),
+        None => PasswordCheck::Wrong,
+    };
+
+    let admin = match (user, check) {
+        (Some(user), PasswordCheck::Correct { .. }) => user,
+        (user, _) => {
+            warn!(
+                "Failed approval by {} for {} from {}",
+                request.name, session.user, client_ip
+            );
+            if let Some(user) = user {
+                connection
+                    .transaction(|| record_failed_login(&connection, config, &user, &client_ip))?;
+            }
+            return Err(
+                SJ::new(Status::Unauthorized, "Invalid username or password")
+                    .with_code(ErrorCode::InvalidCredentials),
+            );
+        }
+    };
+
+    if !is_admin_of(&connection, &admin.name, session.organization)? {
+        return Err(SJ::new(
+            Status::Forbidden,
+            "Only admins of the organization can approve this",
+        ));
+    }
+
+    let ttl = Duration::seconds(config.approvals.ttl_secs as i64);
+    Ok(accept.ser(approvals.grant(session.organization, request.action, admin.name, ttl)))
+}
+
+/// The columns of `users` which are needed to log in
+#[derive(Queryable)]
+struct LoginUser {
+    name: String,
+    display_name: Option<String>,
+    salted_pass: String,
+    hash_iterations: i32,
+    email: Option<String>,
+    failed_logins: i32,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+/// Count a failed login of an existing user, and lock the account if there were too many
+fn record_failed_login(
+    connection: &PgConnection,
+    config: &Config,
+    user: &LoginUser,
+    client_ip: &str,
+) -> Result<(), SJ> {
+    audit::record(
+        connection,
+        None,
+        Some(&user.name),
+        AuditAction::LoginFailed,
+        &format!("From {}", client_ip),
+    )?;
+
+    // counted in the database, so that concurrent failures can't overwrite each other's counts
+    let failures: i32 = {
+        use crate::schema::tables::users::dsl::*;
+        diesel::update(users.filter(name.eq(&user.name)))
+            .set(failed_logins.eq(failed_logins + 1))
+            .returning(failed_logins)
+            .get_result(connection)?
+    };
+    if failures < config.lockout.max_failed_logins as i32 {
+        return Ok(());
+    }
+
+    let until = Utc::now() + Duration::minutes(config.lockout.minutes.into());
+    {
+        use crate::schema::tables::users::dsl::*;
+        diesel::update(users.filter(name.eq(&user.name)))
+            .set((failed_logins.eq(0), locked_until.eq(until)))
+            .execute(connection)?;
+    }
+
+    warn!("Locked {} after {} failed logins", user.name, failures);
+    audit::record(
+        connection,
+        None,
+        Some(&user.name),
+        AuditAction::AccountLocked,
+        &format!(
+            "After {} failed logins, the last from {}",
+            failures, client_ip
+        ),
+    )?;
+
+    if let Some(to) = &user.email {
+        outbox::enqueue_mail(
+            connection,
+            config,
+            Mail {
+                to: to.clone(),
+                subject: "Your account has been locked".to_string(),
+                body: format!(
+                    "Someone failed to log in as {} {} times in a row, the last time from {}.\n\n\
+                     The account is locked until {}. If this wasn't you, consider changing your \
+                     password.",
+                    user.name,
+                    failures,
+                    client_ip,
+                    until.format("%Y-%m-%d %H:%M UTC"),
+                ),
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Remember the device of a login, and notify the user if it's a new one.
+///
+/// Devices are told apart by a long lived cookie. The first device of a user is not notified
+/// about, since that would notify everyone the first time they log in.
+fn check_device(
+    connection: &PgConnection,
+    config: &Config,
+    cookies: &CookieJar<'_>,
+    user: &LoginUser,
+    organization: &Organization,
+    client: Client,
+    client_ip: &str,
+) -> Result<(), SJ> {
+    use crate::schema::tables::user_devices::dsl::*;
+
+    let device = match cookies.get_private(DEVICE_COOKIE) {
+        Some(cookie) => cookie.value().to_string(),
+        None => {
+            let device = Uuid::new_v4().to_string();
+            cookies.add_private(
+                Cookie::build(DEVICE_COOKIE, device.clone())
+                    .path("/")
+                    .http_only(true)
+                    .same_site(SameSite::Strict)
+                    .secure(client.secure)
+                    .permanent()
+                    .finish(),
+            );
+            device
+        }
+    };
+
+    let known: Vec<String> = user_devices
+        .filter(user_name.eq(&user.name))
+        .select(device_id)
+        .load(connection)?;
+
+    if known.contains(&device) {
+        diesel::update(
+            user_devices
+                .filter(user_name.eq(&user.name))
+                .filter(device_id.eq(&device)),
+        )
+        .set(last_seen.eq(Utc::now()))
+        .execute(connection)?;
+        return Ok(());
+    }
+
+    diesel::insert_into(user_devices)
+        .values((user_name.eq(&user.name), device_id.eq(&device)))
+        .execute(connection)?;
+
+    if known.is_empty() {
+        return Ok(());
+    }
+
+    info!("{} logged in from a new device at {}", user.name, client_ip);
+    audit::record(
+        connection,
+        Some(organization.id),
+        Some(&user.name),
+        AuditAction::NewDeviceLogin,
+        &format!("From {}", client_ip),
+    )?;
+
+    if let Some(to) = &user.email {
+        let mut body = format!(
+            "{} logged in to {} from a new device at {}, on {}.\n\n\
+             If this wasn't you, change your password and ask an admin to log you out \
+             everywhere.",
+            user.name,
+            organization.name,
+            client_ip,
+            Utc::now().format("%Y-%m-%d %H:%M UTC"),
+        );
+        if let Some(footer) = load_branding(connection, organization.id)?.footer_text {
+            body.push_str("\n\n-- \n");
+            body.push_str(&footer);
+        }
+
+        outbox::enqueue_mail(
+            connection,
+            config,
+            Mail {
+                to: to.clone(),
+                subject: "New login to your account".to_string(),
+                body,
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Replace the stored hash of a user with one using the configured parameters.
+///
+/// The login still succeeds if this fails, the user is then rehashed on the next login instead.
+fn rehash_password(connection: &PgConnection, user: &str, password: &str, config: &Config) {
+    let hash = match hash_password(password, &config.passwords) {
+        Ok(hash) => hash,
+        Err(e) => {
+            warn!("Failed to rehash the password of {}: {}", user, e);
+            return;
+        }
+    };
+
+    use crate::schema::tables::users::dsl::*;
+    let result = diesel::update(users.filter(name.eq(user)))
+        .set((
+            salted_pass.eq(hash),
+            // only used by the old hashes, argon2 hashes store their own parameters
+            hash_iterations.eq(config.passwords.iterations as i32),
+        ))
+        .execute(connection);
+
+    match result {
+        Ok(_) => info!("Rehashed the password of {}", user),
+        Err(e) => warn!("Failed to rehash the password of {}: {}", user, e),
+    }
+}
+
+/// POST `/logout`
+#[post("/logout")]
+pub fn logout(cookies: &CookieJar<'_>) -> Status {
+    Session::clear_cookie(cookies);
+    Status::NoContent
+}
+
+/// GET `/me`
+///
+/// The currently logged in user
+#[get("/me")]
+pub fn get_me(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<User>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let (user_name, user_display_name): (String, Option<String>) = {
+        use crate::schema::tables::users::dsl::*;
+        users
+            .filter(name.eq(&session.user))
+            .select((name, display_name))
+            .first(&connection)?
+    };
+
+    Ok(accept.ser(User {
+        is_admin: is_admin_of(&connection, &user_name, session.organization)?,
+        name: user_name,
+        display_name: user_display_name,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, TestDb};
+    use rocket::http::{ContentType, Status};
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::user::{Credentials, User};
+
+    #[test]
+    fn test_login() {
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let client = db.client();
+
+        let wrong = Credentials {
+            name: "tester".to_string(),
+            password: "hunter3".to_string(),
+            organization: None,
+        };
+        assert_eq!(post(&client, "/api/login", &wrong), Status::Unauthorized);
+
+        login(&client, "tester", "hunter2");
+        let me: User = get_json(&client, "/api/me");
+        assert_eq!(me.name, "tester");
+    }
+
+    #[test]
+    fn test_admin_per_organization() {
+        let db = TestDb::new();
+        let ours = db.organization("Test");
+        let theirs = db.organization("Other");
+        db.user("boss", "hunter2", ours, true);
+        db.membership("boss", theirs, false);
+        let client = db.client();
+
+        let login_to = |organization| {
+            let credentials = Credentials {
+                name: "boss".to_string(),
+                password: "hunter2".to_string(),
+                organization: Some(organization),
+            };
+            assert_eq!(post(&client, "/api/login", &credentials), Status::Ok);
+        };
+
+        login_to(ours);
+        let me: User = get_json(&client, "/api/me");
+        assert!(me.is_admin);
+        assert_eq!(
+            client.get("/api/admin/users").dispatch().status(),
+            Status::Ok
+        );
+
+        // the admin role of one organization doesn't carry over to the others
+        login_to(theirs);
+        let me: User = get_json(&client, "/api/me");
+        assert!(!me.is_admin);
+        assert_eq!(
+            client.get("/api/admin/users").dispatch().status(),
+            Status::Forbidden
+        );
+    }
+
+    #[test]
+    fn test_approval() {
+        use diesel::prelude::*;
+        use rocket::http::Header;
+        use strecklistan_api::approval::{Approval, ApprovalRequest, GatedAction, APPROVAL_HEADER};
+        use strecklistan_api::error::{ApiError, ErrorCode};
+
+        let mut db = TestDb::new();
+        db.config.approvals.void_after_minutes = Some(0);
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        db.user("boss", "hunter3", org, true);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let (_, member_account) = db.member(org, "Testsson");
+        let first = db.transaction(org, member_account, sales, 1000.into());
+        let second = db.transaction(org, member_account, sales, 500.into());
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let void = |transaction: i32, token: Option<&str>| {
+            let mut request = client.delete(format!("/api/transaction/{}", transaction));
+            if let Some(token) = token {
+                request = request.header(Header::new(APPROVAL_HEADER, token.to_string()));
+            }
+            request.dispatch()
+        };
+        let approve = |name: &str, password: &str| {
+            let request = ApprovalRequest {
+                name: name.to_string(),
+                password: password.to_string(),
+                action: GatedAction::Void,
+            };
+            client
+                .post("/api/approval")
+                .header(ContentType::JSON)
+                .body(serde_json::to_string(&request).unwrap())
+                .dispatch()
+        };
+
+        let response = void(first, None);
+        assert_eq!(response.status(), Status::Forbidden);
+        let error: ApiError<GatedAction> = response.into_json().unwrap();
+        assert_eq!(error.code, ErrorCode::ApprovalRequired);
+        assert_eq!(error.details, Some(GatedAction::Void));
+
+        // admins approve with their own password
+        assert_eq!(approve("boss", "hunter2").status(), Status::Unauthorized);
+
+        let approval: Approval = approve("boss", "hunter3").into_json().unwrap();
+        assert_eq!(approval.approved_by, "boss");
+        assert_eq!(void(first, Some(&approval.token)).status(), Status::Ok);
+
+        assert_eq!(void(second, Some("forged")).status(), Status::Forbidden);
+
+        // approvals last until they expire
+        assert_eq!(void(second, Some(&approval.token)).status(), Status::Ok);
+
+        let entries: Vec<(Option<String>, String)> = {
+            use crate::schema::tables::audit_log::dsl::*;
+            audit_log
+                .filter(organization_id.eq(org))
+                .filter(action.eq("action_approved"))
+                .order_by(id)
+                .select((user_name, description))
+                .load(&db.conn())
+                .unwrap()
+        };
+        assert_eq!(
+            entries,
+            vec![
+                (
+                    Some("tester".to_string()),
+                    format!("Void of transaction {}, approved by boss", first),
+                ),
+                (
+                    Some("tester".to_string()),
+                    format!("Void of transaction {}, approved by boss", second),
+                ),
+            ]
+        );
+    }
+}
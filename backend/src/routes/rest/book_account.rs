@@ -1,32 +1,62 @@
+use crate::auth::Session;
+use crate::config::Config;
 use crate::database::DatabasePool;
 use crate::models::book_account as relational;
 use crate::models::transaction::relational::Transaction;
+use crate::routes::rest::analytics::parse_as_of;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
 use diesel::prelude::*;
+use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{get, post, State};
 use std::collections::HashMap;
 use strecklistan_api::book_account::{
-    BookAccount, BookAccountId, BookAccountType, MasterAccounts, NewBookAccount,
+    BookAccount, BookAccountId, BookAccountType, LedgerEntry, MasterAccounts, NewBookAccount,
 };
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::validation::Validate;
 
-#[get("/book_accounts")]
+/// GET `/book_accounts?<as_of>`
+///
+/// All book accounts with their balances, or with the balances as they were at the end of the day
+/// `as_of` (YYYY-MM-DD) in the reporting timezone. Past balances are computed from the ledger as
+/// it was then, so transactions deleted since are still counted.
+#[get("/book_accounts?<as_of>")]
 pub fn get_accounts(
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
     accept: SerAccept,
+    as_of: Option<String>,
 ) -> Result<Ser<HashMap<BookAccountId, BookAccount>>, SJ> {
     let connection = db_pool.inner().get()?;
+    let cutoff = as_of
+        .map(|as_of| parse_as_of(&as_of, config.reporting_timezone))
+        .transpose()?;
 
     let (transactions, accounts) = connection
         .transaction::<(Vec<Transaction>, Vec<relational::BookAccount>), SJ, _>(|| {
-            use crate::schema::tables::book_accounts::dsl::book_accounts;
-            use crate::schema::tables::transactions::dsl::{deleted_at, transactions};
+            use crate::schema::tables::book_accounts::dsl::{
+                book_accounts, organization_id as account_organization,
+            };
+            use crate::schema::tables::transactions::dsl::{
+                deleted_at, organization_id, time, transactions,
+            };
+            let query = transactions
+                .filter(organization_id.eq(session.organization))
+                .into_boxed();
+            let query = match cutoff {
+                Some(cutoff) => query
+                    .filter(time.lt(cutoff))
+                    .filter(deleted_at.is_null().or(deleted_at.ge(cutoff))),
+                None => query.filter(deleted_at.is_null()),
+            };
             Ok((
-                transactions
-                    .filter(deleted_at.is_null())
+                query.load(&connection)?,
+                book_accounts
+                    .filter(account_organization.eq(session.organization))
                     .load(&connection)?,
-                book_accounts.load(&connection)?,
             ))
         })?;
 
@@ -35,6 +65,16 @@ pub fn get_accounts(
         .map(|acc| (acc.id, acc.into()))
         .collect();
 
+    apply_transactions(&mut accounts, &transactions);
+
+    Ok(accept.ser(accounts))
+}
+
+/// Compute the balances of `accounts` from the given transactions
+pub fn apply_transactions(
+    accounts: &mut HashMap<BookAccountId, BookAccount>,
+    transactions: &[Transaction],
+) {
     for tr in transactions.iter() {
         if let Some(account) = accounts.get_mut(&tr.credited_account) {
             account.credit(tr.amount.into());
@@ -44,16 +84,77 @@ pub fn get_accounts(
             account.debit(tr.amount.into());
         }
     }
+}
 
-    Ok(accept.ser(accounts))
+/// GET `/book_account/<account_id>/ledger`
+///
+/// All transactions involving the account, oldest first, with the running balance
+#[get("/book_account/<account_id>/ledger")]
+pub fn get_ledger(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    account_id: BookAccountId,
+) -> Result<Ser<Vec<LedgerEntry>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let account: Option<relational::BookAccount> = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(id.eq(account_id))
+            .filter(organization_id.eq(session.organization))
+            .first(&connection)
+            .optional()?
+    };
+    let mut account: BookAccount = account
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such book account"))?
+        .into();
+
+    let transactions: Vec<Transaction> = {
+        use crate::schema::tables::transactions::dsl::*;
+        transactions
+            .filter(deleted_at.is_null())
+            .filter(
+                credited_account
+                    .eq(account_id)
+                    .or(debited_account.eq(account_id)),
+            )
+            .order_by((time.asc(), id.asc()))
+            .load(&connection)?
+    };
+
+    let ledger = transactions
+        .into_iter()
+        .map(|tr| {
+            let before = account.balance;
+            if tr.credited_account == account_id {
+                account.credit(tr.amount.into());
+            }
+            if tr.debited_account == account_id {
+                account.debit(tr.amount.into());
+            }
+
+            LedgerEntry {
+                transaction_id: tr.id,
+                time: tr.time,
+                description: tr.description,
+                change: account.balance - before,
+                balance: account.balance,
+            }
+        })
+        .collect();
+
+    Ok(accept.ser(ledger))
 }
 
 #[post("/book_account", data = "<account>")]
 pub fn add_account(
     db_pool: &State<DatabasePool>,
+    session: Session,
     accept: SerAccept,
     account: Json<NewBookAccount>,
 ) -> Result<Ser<i32>, SJ> {
+    account.validate()?;
     let connection = db_pool.inner().get()?;
 
     use crate::schema::tables::book_accounts::dsl::*;
@@ -64,6 +165,7 @@ pub fn add_account(
                 name.eq(&account.name),
                 account_type.eq(&account.account_type),
                 creditor.eq(&account.creditor),
+                organization_id.eq(session.organization),
             ))
             .returning(id)
             .get_result(&connection)?,
@@ -73,65 +175,234 @@ pub fn add_account(
 #[get("/book_accounts/masters")]
 pub fn get_master_accounts(
     db_pool: &State<DatabasePool>,
+    session: Session,
     accept: SerAccept,
 ) -> Result<Ser<MasterAccounts>, SJ> {
     let connection = db_pool.inner().get()?;
+    Ok(accept.ser(master_accounts(&connection, session.organization)?))
+}
+
+/// Check that all of `accounts` exist and belong to `organization`
+pub fn check_accounts(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    accounts: &[BookAccountId],
+) -> Result<(), SJ> {
     use crate::schema::tables::book_accounts::dsl::*;
 
-    // TODO: Get the values for the master accounts from some configuration.
-    let bank_account_name = "Bankkonto";
-    let cash_account_name = "Kontantkassa";
-    let sales_account_name = "Försäljning";
-    let purchases_account_name = "Inköp";
+    let found: Vec<BookAccountId> = book_accounts
+        .filter(id.eq_any(accounts))
+        .filter(organization_id.eq(organization))
+        .select(id)
+        .load(connection)?;
+
+    if accounts.iter().all(|account| found.contains(account)) {
+        Ok(())
+    } else {
+        Err(SJ::new(Status::NotFound, "No such book account"))
+    }
+}
+
+// TODO: Get the values for the master accounts from some configuration.
+const MASTER_ACCOUNTS: [(&str, BookAccountType); 5] = [
+    ("Bankkonto", BookAccountType::Assets),
+    ("Kontantkassa", BookAccountType::Assets),
+    ("Försäljning", BookAccountType::Revenue),
+    ("Inköp", BookAccountType::Expenses),
+    ("Dricks", BookAccountType::Revenue),
+];
 
+/// Get the ids of the master accounts of an organization, creating them if they don't exist
+pub fn master_accounts(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> Result<MasterAccounts, SJ> {
     connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::book_accounts::dsl::*;
+
         // Make sure the accounts exist in the database
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(bank_account_name),
-                account_type.eq(BookAccountType::Assets),
-            ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(cash_account_name),
-                account_type.eq(BookAccountType::Assets),
-            ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(sales_account_name),
-                account_type.eq(BookAccountType::Revenue),
-            ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-        diesel::insert_into(book_accounts)
-            .values((
-                name.eq(purchases_account_name),
-                account_type.eq(BookAccountType::Expenses),
-            ))
-            .on_conflict_do_nothing()
-            .execute(&connection)?;
-
-        Ok(accept.ser(MasterAccounts {
-            bank_account_id: book_accounts
-                .filter(name.eq(bank_account_name))
-                .select(id)
-                .get_result(&connection)?,
-            cash_account_id: book_accounts
-                .filter(name.eq(cash_account_name))
-                .select(id)
-                .get_result(&connection)?,
-            sales_account_id: book_accounts
-                .filter(name.eq(sales_account_name))
-                .select(id)
-                .get_result(&connection)?,
-            purchases_account_id: book_accounts
-                .filter(name.eq(purchases_account_name))
-                .select(id)
-                .get_result(&connection)?,
-        }))
+        for &(account_name, master_type) in MASTER_ACCOUNTS.iter() {
+            diesel::insert_into(book_accounts)
+                .values((
+                    name.eq(account_name),
+                    account_type.eq(master_type),
+                    organization_id.eq(organization),
+                ))
+                .on_conflict_do_nothing()
+                .execute(connection)?;
+        }
+
+        find_master_accounts(connection, organization)?.ok_or_else(|| {
+            SJ::new(
+                Status::InternalServerError,
+                "The master accounts could not be created",
+            )
+        })
     })
 }
+
+/// Get the ids of the master accounts of an organization, for the routes which otherwise read
+/// from the [ReportingPool](crate::database::ReportingPool).
+///
+/// The master accounts are created if missing, which the read-only reporting database can't, so
+/// they're always loaded from the writable database.
+pub fn reporting_master_accounts(
+    db_pool: &DatabasePool,
+    organization: OrganizationId,
+) -> Result<MasterAccounts, SJ> {
+    master_accounts(&db_pool.get()?, organization)
+}
+
+/// Get the ids of the master accounts of an organization without creating them, for the routes
+/// which must not write to the database. None if any of them doesn't exist yet.
+pub fn find_master_accounts(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<Option<MasterAccounts>> {
+    use crate::schema::tables::book_accounts::dsl::*;
+
+    let names: Vec<&str> = MASTER_ACCOUNTS
+        .iter()
+        .map(|&(account_name, _)| account_name)
+        .collect();
+    let found: HashMap<String, BookAccountId> = book_accounts
+        .filter(organization_id.eq(organization))
+        .filter(name.eq_any(names.clone()))
+        .select((name, id))
+        .load::<(String, BookAccountId)>(connection)?
+        .into_iter()
+        .collect();
+
+    let ids: Option<Vec<BookAccountId>> = names
+        .iter()
+        .map(|account_name| found.get(*account_name).copied())
+        .collect();
+    Ok(ids.map(|ids| MasterAccounts {
+        bank_account_id: ids[0],
+        cash_account_id: ids[1],
+        sales_account_id: ids[2],
+        purchases_account_id: ids[3],
+        tips_account_id: ids[4],
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post_json, TestDb};
+    use rocket::http::Status;
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::{
+        BookAccount, BookAccountId, BookAccountType, NewBookAccount,
+    };
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_fixtures() {
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        let other_org = db.organization("Other");
+        db.user("tester", "hunter2", org, false);
+
+        let item = db.item(org, "Kaffe", Some(500.into()));
+        db.item(other_org, "Te", Some(500.into()));
+        let (_, member_account) = db.member(org, "Testsson");
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        db.transaction(org, cash, member_account, 1000.into());
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert_eq!(items.keys().collect::<Vec<_>>(), vec![&item]);
+
+        let accounts: HashMap<BookAccountId, BookAccount> = get_json(&client, "/api/book_accounts");
+        assert_eq!(accounts[&member_account].balance, 1000.into());
+
+        let new_account = NewBookAccount {
+            name: "Bank".to_string(),
+            account_type: BookAccountType::Assets,
+            creditor: None,
+        };
+        let bank: BookAccountId = post_json(&client, "/api/book_account", &new_account);
+        let accounts: HashMap<BookAccountId, BookAccount> = get_json(&client, "/api/book_accounts");
+        assert!(accounts.contains_key(&bank));
+    }
+
+    #[test]
+    fn test_as_of() {
+        use chrono::{Duration, Utc};
+        use diesel::prelude::*;
+        use strecklistan_api::time::local_date;
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let transaction = |change: i32| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change,
+                item_ids: vec![(kaffe, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let restock: i32 = post_json(&client, "/api/transaction", &transaction(5));
+        let accounts_then: HashMap<BookAccountId, BookAccount> =
+            get_json(&client, "/api/book_accounts");
+        let _: i32 = post_json(&client, "/api/transaction", &transaction(-2));
+        {
+            use crate::schema::tables::transactions::dsl::*;
+            diesel::update(transactions.filter(id.eq(restock)))
+                .set(time.eq(Utc::now() - Duration::days(3)))
+                .execute(&db.conn())
+                .unwrap();
+        }
+        // deleted after the snapshot, so it still counts in it
+        let response = client
+            .delete(format!("/api/transaction/{}", restock))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let as_of = local_date(Utc::now() - Duration::days(2), db.config.reporting_timezone);
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, &format!("/api/inventory/items?as_of={}", as_of));
+        assert_eq!(items[&kaffe].stock, 5);
+        let accounts: HashMap<BookAccountId, BookAccount> =
+            get_json(&client, &format!("/api/book_accounts?as_of={}", as_of));
+        assert_eq!(accounts[&cash].balance, accounts_then[&cash].balance);
+        assert_eq!(accounts[&sales].balance, accounts_then[&sales].balance);
+
+        let accounts: HashMap<BookAccountId, BookAccount> = get_json(&client, "/api/book_accounts");
+        assert_eq!(accounts[&cash].balance, accounts_then[&cash].balance);
+
+        // before anything happened
+        let as_of = local_date(Utc::now() - Duration::days(4), db.config.reporting_timezone);
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, &format!("/api/inventory/items?as_of={}", as_of));
+        assert_eq!(items[&kaffe].stock, 0);
+
+        let response = client.get("/api/book_accounts?as_of=yesterday").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+}
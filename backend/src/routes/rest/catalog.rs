@@ -0,0 +1,615 @@
+//! Export and import of the whole item catalog as one JSON document, e.g. to seed the deployment
+//! of a sister chapter or a staging environment with the items of this one.
+//!
+//! Items and bundles are matched by name when a catalog is imported. Those which already exist
+//! are updated, the others are created. The categories of an item are replaced, while barcodes
+//! and modifiers (matched by group and name) are only added or updated. Nothing which isn't in
+//! the catalog is removed, except for the store layout, which is replaced unless the catalog has
+//! none.
+//!
+//! The catalog is sent as JSON, so a large one may need a higher `BODY_LIMIT_JSON_KIB`.
+
+use crate::auth::{AdminSession, Session};
+use crate::database::item_cache::ItemCache;
+use crate::database::DatabasePool;
+use crate::routes::rest::store_layout::{load_store_layout, replace_store_layout};
+use crate::util::audit::{self, AuditAction};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::prelude::*;
+use itertools::Itertools;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use std::collections::{HashMap, HashSet};
+use strecklistan_api::catalog::{
+    Catalog, CatalogBundle, CatalogImportSummary, CatalogItem, CatalogModifier, CATALOG_VERSION,
+};
+use strecklistan_api::inventory::{InventoryBundleId, InventoryItemId};
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::store_layout::{QuickButtonTarget, StoreLayout, StoreTab};
+use strecklistan_api::validation::Validate;
+
+/// GET `/export/catalog.json`
+///
+/// Every item which isn't archived, with its categories, barcodes and modifiers, every bundle of
+/// those items, and the store layout. See the [module docs](self).
+#[get("/export/catalog.json")]
+pub fn export_catalog(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+) -> Result<Json<Catalog>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(Json(load_catalog(&connection, session.organization)?))
+}
+
+/// POST `/admin/import/catalog?<dry_run>`
+///
+/// Add the items, bundles and layout of an exported catalog to the organization, see the
+/// [module docs](self). With `dry_run` the catalog is only checked.
+#[post("/admin/import/catalog?<dry_run>", data = "<catalog>")]
+pub fn import_catalog(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    admin: AdminSession,
+    accept: SerAccept,
+    dry_run: Option<bool>,
+    catalog: Json<Catalog>,
+) -> Result<Ser<CatalogImportSummary>, SJ> {
+    let catalog = catalog.into_inner();
+    catalog.validate()?;
+    catalog
+        .check()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    let session = admin.0;
+    let organization = session.organization;
+    let connection = db_pool.inner().get()?;
+    let summary = connection.transaction::<_, SJ, _>(|| {
+        let items: HashMap<String, InventoryItemId> = {
+            use crate::schema::tables::inventory::dsl::*;
+            inventory
+                .filter(organization_id.eq(organization))
+                .filter(archived_at.is_null())
+                .select((name, id))
+                .load(&connection)?
+                .into_iter()
+                .filter_map(|(item_name, item_id): (Option<String>, i32)| {
+                    Some((item_name?, item_id))
+                })
+                .collect()
+        };
+        let bundles: HashMap<String, InventoryBundleId> = {
+            use crate::schema::tables::inventory_bundles::dsl::*;
+            inventory_bundles
+                .filter(organization_id.eq(organization))
+                .select((name, id))
+                .load(&connection)?
+                .into_iter()
+                .collect()
+        };
+
+        let updated_items = catalog
+            .items
+            .iter()
+            .filter(|item| items.contains_key(item.name.trim()))
+            .count() as u32;
+        let updated_bundles = catalog
+            .bundles
+            .iter()
+            .filter(|bundle| bundles.contains_key(bundle.name.trim()))
+            .count() as u32;
+        let summary = CatalogImportSummary {
+            items_created: catalog.items.len() as u32 - updated_items,
+            items_updated: updated_items,
+            bundles_created: catalog.bundles.len() as u32 - updated_bundles,
+            bundles_updated: updated_bundles,
+            layout_replaced: !catalog.layout.tabs.is_empty(),
+        };
+        if dry_run == Some(true) {
+            return Ok(summary);
+        }
+
+        // the ids of the catalog to those in the database
+        let mut item_ids = HashMap::new();
+        for item in &catalog.items {
+            let item_id = save_item(&connection, organization, items.get(item.name.trim()), item)?;
+            item_ids.insert(item.id, item_id);
+        }
+
+        let mut bundle_ids = HashMap::new();
+        for bundle in &catalog.bundles {
+            let existing = bundles.get(bundle.name.trim());
+            let bundle_id = save_bundle(&connection, organization, existing, bundle, &item_ids)?;
+            bundle_ids.insert(bundle.id, bundle_id);
+        }
+
+        if summary.layout_replaced {
+            let mut layout = catalog.layout.clone();
+            for button in layout.tabs.iter_mut().flat_map(|tab| &mut tab.buttons) {
+                button.target = match button.target {
+                    QuickButtonTarget::Item(id) => QuickButtonTarget::Item(item_ids[&id]),
+                    QuickButtonTarget::Bundle(id) => QuickButtonTarget::Bundle(bundle_ids[&id]),
+                };
+            }
+            replace_store_layout(&connection, organization, &layout)?;
+        }
+
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&session.user),
+            AuditAction::CatalogImported,
+            &format!(
+                "Imported a catalog: {} items created, {} updated, {} bundles created, {} updated",
+                summary.items_created,
+                summary.items_updated,
+                summary.bundles_created,
+                summary.bundles_updated,
+            ),
+        )?;
+
+        Ok(summary)
+    })?;
+
+    if dry_run != Some(true) {
+        item_cache.invalidate(organization);
+    }
+
+    Ok(accept.ser(summary))
+}
+
+fn load_catalog(connection: &PgConnection, organization: OrganizationId) -> QueryResult<Catalog> {
+    type ItemRow = (
+        InventoryItemId,
+        Option<String>,
+        Option<i32>,
+        Option<i32>,
+        Option<String>,
+        bool,
+        bool,
+    );
+    let rows: Vec<ItemRow> = {
+        use crate::schema::tables::inventory::dsl::*;
+        inventory
+            .filter(organization_id.eq(organization))
+            .filter(archived_at.is_null())
+            .order_by(id)
+            .select((
+                id,
+                name,
+                price,
+                cost_price,
+                image_url,
+                age_restricted,
+                prepared,
+            ))
+            .load(connection)?
+    };
+    let ids: Vec<InventoryItemId> = rows.iter().map(|row| row.0).collect();
+
+    let mut categories = {
+        use crate::schema::tables::inventory_tags::dsl::*;
+        inventory_tags
+            .filter(item_id.eq_any(&ids))
+            .order_by((item_id, tag))
+            .select((item_id, tag))
+            .load::<(InventoryItemId, String)>(connection)?
+            .into_iter()
+            .into_group_map()
+    };
+    let mut barcodes = {
+        use crate::schema::tables::inventory_barcodes::dsl::*;
+        inventory_barcodes
+            .filter(item_id.eq_any(&ids))
+            .order_by((item_id, barcode))
+            .select((item_id, barcode))
+            .load::<(InventoryItemId, String)>(connection)?
+            .into_iter()
+            .into_group_map()
+    };
+    let mut modifiers = {
+        use crate::schema::tables::inventory_modifiers::dsl::*;
+        inventory_modifiers
+            .filter(item_id.eq_any(&ids))
+            .filter(archived_at.is_null())
+            .order_by(id)
+            .select((item_id, group_name, name, price_delta))
+            .load::<(InventoryItemId, Option<String>, String, i32)>(connection)?
+            .into_iter()
+            .map(|(item, group, modifier_name, delta)| {
+                let modifier = CatalogModifier {
+                    group,
+                    name: modifier_name,
+                    price_delta: delta.into(),
+                };
+                (item, modifier)
+            })
+            .into_group_map()
+    };
+
+    // items without a name can't be matched when the catalog is imported
+    let items: Vec<CatalogItem> = rows
+        .into_iter()
+        .filter_map(
+            |(id, name, price, cost_price, image_url, age_restricted, prepared)| {
+                Some(CatalogItem {
+                    id,
+                    name: name?,
+                    price: price.map(Into::into),
+                    cost_price: cost_price.map(Into::into),
+                    image_url,
+                    age_restricted,
+                    prepared,
+                    categories: categories.remove(&id).unwrap_or_default(),
+                    barcodes: barcodes.remove(&id).unwrap_or_default(),
+                    modifiers: modifiers.remove(&id).unwrap_or_default(),
+                })
+            },
+        )
+        .collect();
+    let item_ids: HashSet<InventoryItemId> = items.iter().map(|item| item.id).collect();
+
+    let bundle_rows: Vec<(InventoryBundleId, String, i32, Option<String>)> = {
+        use crate::schema::tables::inventory_bundles::dsl::*;
+        inventory_bundles
+            .filter(organization_id.eq(organization))
+            .order_by(id)
+            .select((id, name, price, image_url))
+            .load(connection)?
+    };
+    let mut bundle_items = {
+        use crate::schema::tables::inventory_bundle_items::dsl::*;
+        let bundle_ids: Vec<InventoryBundleId> = bundle_rows.iter().map(|row| row.0).collect();
+        inventory_bundle_items
+            .filter(bundle_id.eq_any(&bundle_ids))
+            .order_by(id)
+            .select((bundle_id, item_id))
+            .load::<(InventoryBundleId, InventoryItemId)>(connection)?
+            .into_iter()
+            .into_group_map()
+    };
+
+    // bundles of archived items aren't sold anymore
+    let bundles: Vec<CatalogBundle> = bundle_rows
+        .into_iter()
+        .map(|(id, name, price, image_url)| CatalogBundle {
+            id,
+            name,
+            price: price.into(),
+            image_url,
+            item_ids: bundle_items.remove(&id).unwrap_or_default(),
+        })
+        .filter(|bundle| bundle.item_ids.iter().all(|id| item_ids.contains(id)))
+        .collect();
+    let bundle_ids: HashSet<InventoryBundleId> = bundles.iter().map(|bundle| bundle.id).collect();
+
+    let layout = load_store_layout(connection, organization)?;
+    let layout = StoreLayout {
+        tabs: layout
+            .tabs
+            .into_iter()
+            .map(|tab| StoreTab {
+                name: tab.name,
+                buttons: tab
+                    .buttons
+                    .into_iter()
+                    .filter(|button| match button.target {
+                        QuickButtonTarget::Item(id) => item_ids.contains(&id),
+                        QuickButtonTarget::Bundle(id) => bundle_ids.contains(&id),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    };
+
+    Ok(Catalog {
+        version: CATALOG_VERSION,
+        items,
+        bundles,
+        layout,
+    })
+}
+
+/// Update the item `existing` to match `item`, or create it if it's `None`. Returns its id.
+fn save_item(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    existing: Option<&InventoryItemId>,
+    item: &CatalogItem,
+) -> QueryResult<InventoryItemId> {
+    let saved_id: InventoryItemId = {
+        use crate::schema::tables::inventory::dsl::*;
+        let values = (
+            name.eq(item.name.trim()),
+            price.eq(item.price.map(|p| i64::from(p) as i32)),
+            cost_price.eq(item.cost_price.map(|p| i64::from(p) as i32)),
+            image_url.eq(&item.image_url),
+            age_restricted.eq(item.age_restricted),
+            prepared.eq(item.prepared),
+        );
+        match existing {
+            Some(&existing) => diesel::update(inventory.filter(id.eq(existing)))
+                .set(values)
+                .returning(id)
+                .get_result(connection)?,
+            None => diesel::insert_into(inventory)
+                .values((values, organization_id.eq(organization)))
+                .returning(id)
+                .get_result(connection)?,
+        }
+    };
+
+    {
+        use crate::schema::tables::inventory_tags::dsl::*;
+        diesel::delete(inventory_tags.filter(item_id.eq(saved_id))).execute(connection)?;
+        let rows: Vec<_> = item
+            .categories
+            .iter()
+            .map(|category| (tag.eq(category.trim()), item_id.eq(saved_id)))
+            .collect();
+        diesel::insert_into(inventory_tags)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+
+    {
+        use crate::schema::tables::inventory_barcodes::dsl::*;
+        let rows: Vec<_> = item
+            .barcodes
+            .iter()
+            .map(|code| (barcode.eq(code.trim()), item_id.eq(saved_id)))
+            .collect();
+        diesel::insert_into(inventory_barcodes)
+            .values(&rows)
+            .on_conflict_do_nothing()
+            .execute(connection)?;
+    }
+
+    use crate::schema::tables::inventory_modifiers::dsl::*;
+    let current: Vec<(i32, Option<String>, String)> = inventory_modifiers
+        .filter(item_id.eq(saved_id))
+        .filter(archived_at.is_null())
+        .select((id, group_name, name))
+        .load(connection)?;
+    for modifier in &item.modifiers {
+        let delta = i64::from(modifier.price_delta) as i32;
+        let same = current
+            .iter()
+            .find(|(_, group, modifier_name)| {
+                group == &modifier.group && modifier_name == &modifier.name
+            })
+            .map(|&(modifier_id, _, _)| modifier_id);
+        match same {
+            Some(modifier_id) => {
+                diesel::update(inventory_modifiers.filter(id.eq(modifier_id)))
+                    .set(price_delta.eq(delta))
+                    .execute(connection)?;
+            }
+            None => {
+                diesel::insert_into(inventory_modifiers)
+                    .values((
+                        item_id.eq(saved_id),
+                        group_name.eq(&modifier.group),
+                        name.eq(&modifier.name),
+                        price_delta.eq(delta),
+                    ))
+                    .execute(connection)?;
+            }
+        }
+    }
+
+    Ok(saved_id)
+}
+
+/// Update the bundle `existing` to match `bundle`, or create it if it's `None`. `item_ids` maps
+/// the items of the catalog to those in the database. Returns the id of the bundle.
+fn save_bundle(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    existing: Option<&InventoryBundleId>,
+    bundle: &CatalogBundle,
+    item_ids: &HashMap<InventoryItemId, InventoryItemId>,
+) -> QueryResult<InventoryBundleId> {
+    let saved_id: InventoryBundleId = {
+        use crate::schema::tables::inventory_bundles::dsl::*;
+        let values = (
+            name.eq(bundle.name.trim()),
+            price.eq(i64::from(bundle.price) as i32),
+            image_url.eq(&bundle.image_url),
+        );
+        match existing {
+            Some(&existing) => diesel::update(inventory_bundles.filter(id.eq(existing)))
+                .set(values)
+                .returning(id)
+                .get_result(connection)?,
+            None => diesel::insert_into(inventory_bundles)
+                .values((values, organization_id.eq(organization)))
+                .returning(id)
+                .get_result(connection)?,
+        }
+    };
+
+    use crate::schema::tables::inventory_bundle_items::dsl::*;
+    diesel::delete(inventory_bundle_items.filter(bundle_id.eq(saved_id))).execute(connection)?;
+    let rows: Vec<_> = bundle
+        .item_ids
+        .iter()
+        .map(|item| (bundle_id.eq(saved_id), item_id.eq(item_ids[item])))
+        .collect();
+    diesel::insert_into(inventory_bundle_items)
+        .values(&rows)
+        .execute(connection)?;
+
+    Ok(saved_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use std::collections::HashMap;
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+
+    #[test]
+    fn test_catalog() {
+        use diesel::prelude::*;
+        use strecklistan_api::catalog::{Catalog, CatalogImportSummary};
+        use strecklistan_api::store_layout::{
+            QuickButton, QuickButtonTarget, StoreLayout, StoreTab,
+        };
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        let sister = db.organization("Syster");
+        db.user("tester", "hunter2", org, false);
+        db.user("boss", "hunter2", org, true);
+        db.user("admin", "hunter2", sister, true);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let bulle = db.item(org, "Bulle", Some(1000.into()));
+        let archived = db.item(org, "Julmust", Some(1500.into()));
+        db.modifier(kaffe, Some("Storlek"), "stor", 200.into());
+        let existing = db.item(sister, "Kaffe", Some(400.into()));
+        {
+            let conn = db.conn();
+            use crate::schema::tables::{inventory, inventory_bundle_items, inventory_bundles};
+            use crate::schema::tables::{inventory_barcodes, inventory_tags};
+            diesel::update(inventory::table.filter(inventory::id.eq(archived)))
+                .set(inventory::archived_at.eq(chrono::Utc::now()))
+                .execute(&conn)
+                .unwrap();
+            diesel::insert_into(inventory_tags::table)
+                .values((
+                    inventory_tags::tag.eq("Dryck"),
+                    inventory_tags::item_id.eq(kaffe),
+                ))
+                .execute(&conn)
+                .unwrap();
+            diesel::insert_into(inventory_barcodes::table)
+                .values((
+                    inventory_barcodes::barcode.eq("7310050001234"),
+                    inventory_barcodes::item_id.eq(kaffe),
+                ))
+                .execute(&conn)
+                .unwrap();
+            let fika: i32 = diesel::insert_into(inventory_bundles::table)
+                .values((
+                    inventory_bundles::name.eq("Fika"),
+                    inventory_bundles::price.eq(1200),
+                    inventory_bundles::organization_id.eq(org),
+                ))
+                .returning(inventory_bundles::id)
+                .get_result(&conn)
+                .unwrap();
+            diesel::insert_into(inventory_bundle_items::table)
+                .values(&vec![
+                    (
+                        inventory_bundle_items::bundle_id.eq(fika),
+                        inventory_bundle_items::item_id.eq(kaffe),
+                    ),
+                    (
+                        inventory_bundle_items::bundle_id.eq(fika),
+                        inventory_bundle_items::item_id.eq(bulle),
+                    ),
+                ])
+                .execute(&conn)
+                .unwrap();
+        }
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let boss = db.client();
+        login(&boss, "boss", "hunter2");
+        let put_layout = |client: &Client, layout: &StoreLayout| {
+            client
+                .put("/api/store/layout")
+                .header(ContentType::JSON)
+                .body(serde_json::to_string(layout).unwrap())
+                .dispatch()
+                .status()
+        };
+        let mut layout = StoreLayout {
+            tabs: vec![StoreTab {
+                name: "Fika".to_string(),
+                buttons: vec![
+                    QuickButton::new(QuickButtonTarget::Item(bulle), 0, 0),
+                    QuickButton::new(QuickButtonTarget::Item(existing), 1, 0),
+                ],
+            }],
+        };
+
+        // only admins arrange the store, and only with the items of their organization
+        assert_eq!(put_layout(&client, &layout), Status::Forbidden);
+        assert_eq!(put_layout(&boss, &layout), Status::NotFound);
+        layout.tabs[0].buttons[1].target = QuickButtonTarget::Item(archived);
+        assert_eq!(put_layout(&boss, &layout), Status::NoContent);
+
+        let catalog: Catalog = get_json(&client, "/api/export/catalog.json");
+        let names: Vec<&str> = catalog.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["Kaffe", "Bulle"], "archived items are left out");
+        assert_eq!(catalog.items[0].categories, ["Dryck"]);
+        assert_eq!(catalog.items[0].barcodes, ["7310050001234"]);
+        assert_eq!(catalog.items[0].modifiers[0].name, "stor");
+        assert_eq!(catalog.bundles[0].item_ids, [kaffe, bulle]);
+        assert_eq!(catalog.layout.tabs[0].buttons.len(), 1);
+
+        // importing requires an admin
+        assert_eq!(
+            post(&client, "/api/admin/import/catalog", &catalog),
+            Status::Forbidden
+        );
+
+        let admin = db.client();
+        login(&admin, "admin", "hunter2");
+        let summary: CatalogImportSummary =
+            post_json(&admin, "/api/admin/import/catalog?dry_run=true", &catalog);
+        assert_eq!(summary.items_created, 1);
+        assert_eq!(summary.items_updated, 1);
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&admin, "/api/inventory/items");
+        assert_eq!(items.len(), 1, "a dry run changes nothing");
+
+        let summary: CatalogImportSummary =
+            post_json(&admin, "/api/admin/import/catalog", &catalog);
+        assert_eq!(
+            summary,
+            CatalogImportSummary {
+                items_created: 1,
+                items_updated: 1,
+                bundles_created: 1,
+                bundles_updated: 0,
+                layout_replaced: true,
+            }
+        );
+
+        let imported: Catalog = get_json(&admin, "/api/export/catalog.json");
+        assert_eq!(imported.items.len(), 2);
+        assert_eq!(imported.items[0].id, existing, "matched by name");
+        assert_eq!(imported.items[0].price, Some(500.into()));
+        assert_eq!(imported.items[0].categories, ["Dryck"]);
+        assert_eq!(imported.items[0].modifiers, catalog.items[0].modifiers);
+        let imported_bulle = imported.items[1].id;
+        assert_eq!(imported.bundles[0].item_ids, [existing, imported_bulle]);
+        assert_eq!(
+            imported.layout.tabs[0].buttons[0].target,
+            QuickButtonTarget::Item(imported_bulle)
+        );
+
+        // importing it again only updates
+        let summary: CatalogImportSummary =
+            post_json(&admin, "/api/admin/import/catalog", &catalog);
+        assert_eq!(summary.items_created, 0);
+        assert_eq!(summary.bundles_updated, 1);
+        let again: Catalog = get_json(&admin, "/api/export/catalog.json");
+        assert_eq!(again, imported);
+
+        let mut broken = catalog;
+        broken.bundles[0].item_ids.push(archived);
+        assert_eq!(
+            post(&admin, "/api/admin/import/catalog", &broken),
+            Status::BadRequest
+        );
+    }
+}
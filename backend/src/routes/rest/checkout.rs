@@ -0,0 +1,120 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::database::item_cache::ItemCache;
+use crate::database::DatabasePool;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use strecklistan_api::checkout::{CheckoutQuote, CheckoutRules, QuoteRequest};
+
+fn checkout_rules(config: &Config) -> CheckoutRules {
+    CheckoutRules {
+        cash_rounding: config.payments.cash_rounding.into(),
+    }
+}
+
+/// GET `/checkout/rules`
+///
+/// How carts are priced, so that the store page can price them the same way as the server
+#[get("/checkout/rules")]
+pub fn get_rules(
+    config: &State<Config>,
+    _session: Session,
+    accept: SerAccept,
+) -> Ser<CheckoutRules> {
+    accept.ser(checkout_rules(config))
+}
+
+/// POST `/checkout/quote`
+///
+/// Price a cart, using the same calculation as the store page. Cash totals are rounded. Only
+/// uses the database if the items aren't cached.
+#[post("/checkout/quote", data = "<request>")]
+pub fn post_quote(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    item_cache: &State<ItemCache>,
+    session: Session,
+    accept: SerAccept,
+    request: Json<QuoteRequest>,
+) -> Result<Ser<CheckoutQuote>, SJ> {
+    let inventory = match item_cache.cached(session.organization) {
+        Some(inventory) => inventory,
+        None => item_cache.reload(&db_pool.inner().get()?, session.organization)?,
+    };
+    let item_prices = inventory.item_prices();
+    let modifier_prices = inventory.modifier_prices();
+    let rules = checkout_rules(config);
+
+    Ok(accept.ser(CheckoutQuote::compute(
+        &request,
+        &item_prices,
+        &modifier_prices,
+        &rules,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{login, post_json, TestDb};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::inventory::InventoryItemId;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_item_cache() {
+        use strecklistan_api::checkout::{CheckoutQuote, QuoteRequest};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let bundle = |item: InventoryItemId| {
+            let mut item_ids = HashMap::new();
+            item_ids.insert(item, 1);
+            TransactionBundle {
+                description: None,
+                price: None,
+                change: 1,
+                item_ids,
+                expires_on: None,
+                modifier_ids: vec![],
+            }
+        };
+
+        // caches the items
+        let request = QuoteRequest {
+            bundles: vec![bundle(kaffe)],
+            tendered: None,
+            cash: false,
+        };
+        let quote: CheckoutQuote = post_json(&client, "/api/checkout/quote", &request);
+        assert_eq!(quote.subtotal, 500.into());
+
+        // an item added behind the back of the cache can still be sold
+        let te = db.item(org, "Te", Some(400.into()));
+        let transaction = NewTransaction {
+            description: None,
+            bundles: vec![bundle(te)],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 400.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let _: i32 = post_json(&client, "/api/transaction", &transaction);
+    }
+}
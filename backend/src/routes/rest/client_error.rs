@@ -0,0 +1,94 @@
+use crate::auth::Session;
+use crate::util::client::Client;
+use crate::util::rate_limit::{Public, RateLimit};
+use log::error;
+use rocket::http::Status;
+use rocket::post;
+use rocket::serde::json::Json;
+use std::fmt::Write;
+use strecklistan_api::client_error::{ClientErrorReport, MAX_BREADCRUMBS};
+
+/// Longer breadcrumbs are cut off, so that a report can't flood the log
+const MAX_BREADCRUMB_LENGTH: usize = 500;
+
+/// POST `/client_error`
+///
+/// Write an error which happened in the frontend to the log, together with the state of the app
+/// and what led up to it. Doesn't require a login, since the frontend may break before it has
+/// one, so it's rate limited per client instead.
+#[post("/client_error", data = "<report>")]
+pub fn post_client_error(
+    _limit: RateLimit<Public>,
+    session: Option<Session>,
+    client: Client,
+    report: Json<ClientErrorReport>,
+) -> Status {
+    let report = report.into_inner();
+    let user = session
+        .as_ref()
+        .map(|session| session.user.as_str())
+        .unwrap_or("nobody");
+    let client_ip = client
+        .ip
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|| "an unknown address".to_string());
+
+    let mut entry = format!(
+        "Client error of {} from {}, version {} at {}: {}",
+        user, client_ip, report.version, report.url, report.message
+    );
+    for (key, value) in &report.state {
+        let _ = write!(entry, "\n  {} = {}", key, value);
+    }
+
+    let skipped = report.breadcrumbs.len().saturating_sub(MAX_BREADCRUMBS);
+    for breadcrumb in report.breadcrumbs.iter().skip(skipped) {
+        let text: String = breadcrumb
+            .text
+            .chars()
+            .take(MAX_BREADCRUMB_LENGTH)
+            .collect();
+        let _ = write!(
+            entry,
+            "\n  {} {:?} {}",
+            breadcrumb.time.format("%H:%M:%S%.3f"),
+            breadcrumb.kind,
+            text
+        );
+    }
+
+    error!("{}", entry);
+    Status::NoContent
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{post, TestDb};
+    use rocket::http::Status;
+
+    #[test]
+    fn test_client_error() {
+        use chrono::Utc;
+        use strecklistan_api::client_error::{Breadcrumb, BreadcrumbKind, ClientErrorReport};
+
+        let db = TestDb::new();
+        let client = db.client();
+        let report = ClientErrorReport {
+            message: "panicked at 'index out of bounds'".to_string(),
+            url: "http://localhost/store".to_string(),
+            version: "0.10.0".to_string(),
+            state: Default::default(),
+            breadcrumbs: vec![Breadcrumb {
+                time: Utc::now(),
+                kind: BreadcrumbKind::Msg,
+                text: "x".repeat(10000),
+            }],
+        };
+
+        // the frontend may break before anyone has logged in
+        assert_eq!(
+            post(&client, "/api/client_error", &report),
+            Status::NoContent
+        );
+    }
+}
@@ -0,0 +1,537 @@
+use crate::auth::{AdminSession, Session};
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::models::correction::Correction as CorrectionRow;
+use crate::models::transaction::{object, relational};
+use crate::routes::rest::book_account::{check_accounts, master_accounts};
+use crate::routes::rest::inventory::check_stock;
+use crate::routes::rest::live::LiveEvents;
+use crate::routes::rest::transaction::insert_bundles;
+use crate::util::audit::{self, AuditAction};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::Utc;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use strecklistan_api::correction::{
+    Correction, CorrectionId, CorrectionKind, CorrectionStatus, NewCorrection,
+};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::transaction::TransactionId;
+use strecklistan_api::validation::Validate;
+
+const CORRECTION_DESCRIPTION: &str = "Rättelse";
+
+/// How many of the decided corrections are listed along with the pending ones
+const DECIDED_CORRECTIONS: i64 = 50;
+
+/// GET `/corrections`
+///
+/// The pending corrections, oldest first, followed by the latest decided ones
+#[get("/corrections")]
+pub fn get_corrections(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<Correction>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let mut corrections = load_pending(&connection, session.organization)?;
+    corrections.extend(load_decided(&connection, session.organization)?);
+    Ok(accept.ser(corrections))
+}
+
+/// GET `/corrections/pending`
+///
+/// The corrections which wait for approval, oldest first
+#[get("/corrections/pending")]
+pub fn get_pending_corrections(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<Correction>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_pending(&connection, session.organization)?))
+}
+
+/// POST `/corrections`
+///
+/// Correct a balance or the stock of an item by hand. Corrections which are larger than the
+/// limits in [ApprovalsConfig](crate::config::ApprovalsConfig) are left pending until an admin
+/// other than the one who requested them approves them, the others are applied at once.
+#[post("/corrections", data = "<correction>")]
+pub fn post_correction(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    session: Session,
+    accept: SerAccept,
+    correction: Json<NewCorrection>,
+) -> Result<Ser<Correction>, SJ> {
+    session.check_not_training()?;
+    let mut correction = correction.into_inner();
+    correction.reason = correction.reason.trim().to_string();
+    correction.validate()?;
+
+    let connection = db_pool.inner().get()?;
+    let organization = session.organization;
+
+    let (correction_id, transaction_id) = connection.transaction::<_, SJ, _>(|| {
+        match correction.kind {
+            CorrectionKind::Balance {
+                debited_account,
+                credited_account,
+                ..
+            } => check_accounts(
+                &connection,
+                organization,
+                &[debited_account, credited_account],
+            )?,
+            CorrectionKind::Stock { item_id, .. } => {
+                use crate::schema::tables::inventory::dsl::*;
+                let found: bool = diesel::select(diesel::dsl::exists(
+                    inventory
+                        .filter(id.eq(item_id))
+                        .filter(organization_id.eq(organization)),
+                ))
+                .get_result(&connection)?;
+                if !found {
+                    return Err(SJ::new(Status::NotFound, "No such item"));
+                }
+            }
+        }
+
+        let (debited, credited, moved, item, counted) = match correction.kind {
+            CorrectionKind::Balance {
+                debited_account,
+                credited_account,
+                amount,
+            } => (
+                Some(debited_account),
+                Some(credited_account),
+                Some(i64::from(amount)),
+                None,
+                None,
+            ),
+            CorrectionKind::Stock { item_id, change } => {
+                (None, None, None, Some(item_id), Some(change))
+            }
+        };
+
+        let correction_id: CorrectionId = {
+            use crate::schema::tables::corrections::dsl::*;
+            diesel::insert_into(corrections)
+                .values((
+                    organization_id.eq(organization),
+                    debited_account.eq(debited),
+                    credited_account.eq(credited),
+                    amount.eq(moved),
+                    item_id.eq(item),
+                    stock_change.eq(counted),
+                    reason.eq(&correction.reason),
+                    requested_by.eq(&session.user),
+                ))
+                .returning(id)
+                .get_result(&connection)?
+        };
+
+        let what = describe(correction_id, &correction.kind, &correction.reason);
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&session.user),
+            AuditAction::CorrectionRequested,
+            &what,
+        )?;
+
+        if needs_approval(config, &correction.kind) {
+            return Ok((correction_id, None));
+        }
+
+        let transaction_id = apply(&connection, config, organization, correction_id)?;
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&session.user),
+            AuditAction::CorrectionApplied,
+            &format!("{}, below the limit for approval", what),
+        )?;
+        Ok((correction_id, Some(transaction_id)))
+    })?;
+
+    live.notify(organization, LiveEvent::CorrectionsChanged);
+    if let Some(id) = transaction_id {
+        live.notify(organization, LiveEvent::TransactionCreated { id });
+    }
+    Ok(accept.ser(load_correction(&connection, organization, correction_id)?))
+}
+
+/// POST `/corrections/<correction_id>/approve`
+///
+/// Apply a pending correction. Responds with 403 Forbidden if the admin requested it themselves.
+#[post("/corrections/<correction_id>/approve")]
+pub fn approve_correction(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    accept: SerAccept,
+    correction_id: CorrectionId,
+) -> Result<Ser<Correction>, SJ> {
+    let session = admin.0;
+    session.check_not_training()?;
+    let connection = db_pool.inner().get()?;
+    let organization = session.organization;
+
+    let transaction_id = connection.transaction::<_, SJ, _>(|| {
+        let correction = decide(&connection, &session, correction_id)?;
+        let transaction_id = apply(&connection, config, organization, correction_id)?;
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&session.user),
+            AuditAction::CorrectionApplied,
+            &format!(
+                "{}, requested by {}, approved by {}",
+                describe(correction.id, &correction.kind, &correction.reason),
+                correction.requested_by.as_deref().unwrap_or("?"),
+                session.user,
+            ),
+        )?;
+        Ok(transaction_id)
+    })?;
+
+    live.notify(organization, LiveEvent::CorrectionsChanged);
+    live.notify(
+        organization,
+        LiveEvent::TransactionCreated { id: transaction_id },
+    );
+    Ok(accept.ser(load_correction(&connection, organization, correction_id)?))
+}
+
+/// POST `/corrections/<correction_id>/reject`
+///
+/// Reject a pending correction, so that it's never applied
+#[post("/corrections/<correction_id>/reject")]
+pub fn reject_correction(
+    db_pool: &State<DatabasePool>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    accept: SerAccept,
+    correction_id: CorrectionId,
+) -> Result<Ser<Correction>, SJ> {
+    let session = admin.0;
+    let connection = db_pool.inner().get()?;
+    let organization = session.organization;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let correction = decide(&connection, &session, correction_id)?;
+        {
+            use crate::schema::tables::corrections::dsl::*;
+            diesel::update(corrections.filter(id.eq(correction_id)))
+                .set(status.eq(CorrectionStatus::Rejected))
+                .execute(&connection)?;
+        }
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&session.user),
+            AuditAction::CorrectionRejected,
+            &format!(
+                "{}, requested by {}, rejected by {}",
+                describe(correction.id, &correction.kind, &correction.reason),
+                correction.requested_by.as_deref().unwrap_or("?"),
+                session.user,
+            ),
+        )?;
+        Ok(())
+    })?;
+
+    live.notify(organization, LiveEvent::CorrectionsChanged);
+    Ok(accept.ser(load_correction(&connection, organization, correction_id)?))
+}
+
+/// Whether `kind` is larger than the limits of the config
+fn needs_approval(config: &Config, kind: &CorrectionKind) -> bool {
+    let approvals = &config.approvals;
+    match *kind {
+        CorrectionKind::Balance { amount, .. } => approvals
+            .correction_over_kronor
+            .map(|kronor| amount > Currency::from(i64::from(kronor) * 100))
+            .unwrap_or(false),
+        CorrectionKind::Stock { change, .. } => approvals
+            .stock_correction_over
+            .map(|count| change.unsigned_abs() > count)
+            .unwrap_or(false),
+    }
+}
+
+/// Lock a pending correction to decide on it, and record who did
+fn decide(
+    connection: &PgConnection,
+    session: &Session,
+    correction_id: CorrectionId,
+) -> Result<Correction, SJ> {
+    let row: Option<CorrectionRow> = {
+        use crate::schema::tables::corrections::dsl::*;
+        corrections
+            .filter(id.eq(correction_id))
+            .filter(organization_id.eq(session.organization))
+            .for_update()
+            .first(connection)
+            .optional()?
+    };
+    let correction: Correction = row
+        .map(Correction::from)
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such correction"))?;
+
+    if correction.status != CorrectionStatus::Pending {
+        return Err(SJ::new(
+            Status::Conflict,
+            "The correction has already been decided on",
+        ));
+    }
+    if correction.requested_by.as_deref() == Some(session.user.as_str()) {
+        return Err(SJ::new(
+            Status::Forbidden,
+            "Someone other than who requested the correction has to approve it",
+        ));
+    }
+
+    use crate::schema::tables::corrections::dsl::*;
+    diesel::update(corrections.filter(id.eq(correction_id)))
+        .set((decided_by.eq(&session.user), decided_at.eq(Utc::now())))
+        .execute(connection)?;
+
+    Ok(correction)
+}
+
+/// Make the transaction of a correction, and mark it as applied
+fn apply(
+    connection: &PgConnection,
+    config: &Config,
+    organization: OrganizationId,
+    correction_id: CorrectionId,
+) -> Result<TransactionId, SJ> {
+    let correction = load_correction(connection, organization, correction_id)?;
+
+    let (debited, credited, amount, bundles) = match correction.kind {
+        CorrectionKind::Balance {
+            debited_account,
+            credited_account,
+            amount,
+        } => (debited_account, credited_account, amount, vec![]),
+        // the stock changes without any money changing hands
+        CorrectionKind::Stock { item_id, change } => {
+            let purchases = master_accounts(connection, organization)?.purchases_account_id;
+            let bundle = object::TransactionBundle {
+                description: None,
+                price: None,
+                change,
+                item_ids: std::iter::once((item_id, 1)).collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            };
+            (purchases, purchases, Currency::default(), vec![bundle])
+        }
+    };
+    check_stock(connection, &bundles, config.stock.negative_stock)?;
+
+    let transaction = relational::NewTransaction {
+        description: Some(CORRECTION_DESCRIPTION.to_string()),
+        time: None,
+        debited_account: debited,
+        credited_account: credited,
+        amount: amount.into(),
+        note: Some(correction.reason),
+        tags: vec![],
+        created_by: correction.requested_by,
+        idempotency_key: None,
+        organization_id: organization,
+        event_code: None,
+        age_verified: false,
+        device: None,
+        member_id: None,
+    };
+    let transaction_id: TransactionId = {
+        use crate::schema::tables::transactions::dsl::*;
+        diesel::insert_into(transactions)
+            .values(&transaction)
+            .returning(id)
+            .get_result(connection)?
+    };
+    insert_bundles(connection, transaction_id, bundles)?;
+
+    use crate::schema::tables::corrections::dsl::{
+        corrections, id, status, transaction_id as applied_as,
+    };
+    diesel::update(corrections.filter(id.eq(correction_id)))
+        .set((
+            status.eq(CorrectionStatus::Applied),
+            applied_as.eq(transaction_id),
+        ))
+        .execute(connection)?;
+
+    Ok(transaction_id)
+}
+
+/// E.g. "Correction #3: 500:- from account 1 to account 2, because the deposit was made twice"
+fn describe(id: CorrectionId, kind: &CorrectionKind, reason: &str) -> String {
+    let what = match *kind {
+        CorrectionKind::Balance {
+            debited_account,
+            credited_account,
+            amount,
+        } => format!(
+            "{}:- from account {} to account {}",
+            amount, debited_account, credited_account
+        ),
+        CorrectionKind::Stock { item_id, change } => {
+            format!("stock of item {} changed by {:+}", item_id, change)
+        }
+    };
+    format!("Correction #{}: {}, because {}", id, what, reason)
+}
+
+fn load_correction(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    correction_id: CorrectionId,
+) -> Result<Correction, SJ> {
+    use crate::schema::tables::corrections::dsl::*;
+    corrections
+        .filter(id.eq(correction_id))
+        .filter(organization_id.eq(organization))
+        .first::<CorrectionRow>(connection)
+        .optional()?
+        .map(Correction::from)
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such correction"))
+}
+
+fn load_pending(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<Vec<Correction>> {
+    use crate::schema::tables::corrections::dsl::*;
+    let rows: Vec<CorrectionRow> = corrections
+        .filter(organization_id.eq(organization))
+        .filter(status.eq(CorrectionStatus::Pending))
+        .order_by((requested_at.asc(), id.asc()))
+        .load(connection)?;
+    Ok(rows.into_iter().map(Correction::from).collect())
+}
+
+fn load_decided(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<Vec<Correction>> {
+    use crate::schema::tables::corrections::dsl::*;
+    let rows: Vec<CorrectionRow> = corrections
+        .filter(organization_id.eq(organization))
+        .filter(status.ne(CorrectionStatus::Pending))
+        .order_by((requested_at.desc(), id.desc()))
+        .limit(DECIDED_CORRECTIONS)
+        .load(connection)?;
+    Ok(rows.into_iter().map(Correction::from).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType};
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+
+    #[test]
+    fn test_corrections() {
+        use strecklistan_api::correction::{
+            Correction, CorrectionKind, CorrectionStatus, NewCorrection,
+        };
+
+        let mut db = TestDb::new();
+        db.config.approvals.correction_over_kronor = Some(1000);
+        db.config.approvals.stock_correction_over = Some(10);
+        let org = db.organization("Test");
+        db.user("alice", "hunter2", org, true);
+        db.user("bob", "hunter3", org, true);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (_, member_account) = db.member(org, "Testsson");
+
+        let alice = db.client();
+        login(&alice, "alice", "hunter2");
+        let bob = db.client();
+        login(&bob, "bob", "hunter3");
+
+        let balance = |kronor: i64, reason: &str| NewCorrection {
+            kind: CorrectionKind::Balance {
+                debited_account: cash,
+                credited_account: member_account,
+                amount: (kronor * 100).into(),
+            },
+            reason: reason.to_string(),
+        };
+        let stock = |change: i32| NewCorrection {
+            kind: CorrectionKind::Stock {
+                item_id: kaffe,
+                change,
+            },
+            reason: "Inventering".to_string(),
+        };
+        let member_balance = || {
+            let accounts: HashMap<BookAccountId, BookAccount> =
+                get_json(&alice, "/api/book_accounts");
+            accounts[&member_account].balance
+        };
+
+        let status = post(&alice, "/api/corrections", &balance(200, " "));
+        assert_eq!(status, Status::UnprocessableEntity, "a reason is required");
+
+        let small: Correction = post_json(&alice, "/api/corrections", &balance(200, "Fel konto"));
+        assert_eq!(small.status, CorrectionStatus::Applied);
+        assert!(small.transaction_id.is_some());
+        assert_eq!(small.decided_by, None);
+        assert_eq!(member_balance(), 20000.into());
+
+        let large: Correction = post_json(&alice, "/api/corrections", &balance(5000, "Dubbel"));
+        assert_eq!(large.status, CorrectionStatus::Pending);
+        assert_eq!(member_balance(), 20000.into());
+
+        let pending: Vec<Correction> = get_json(&bob, "/api/corrections/pending");
+        assert_eq!(pending, vec![large.clone()]);
+
+        let approve = format!("/api/corrections/{}/approve", large.id);
+        assert_eq!(post(&alice, &approve, &()), Status::Forbidden);
+        let approved: Correction = post_json(&bob, &approve, &());
+        assert_eq!(approved.status, CorrectionStatus::Applied);
+        assert_eq!(approved.requested_by.as_deref(), Some("alice"));
+        assert_eq!(approved.decided_by.as_deref(), Some("bob"));
+        assert_eq!(member_balance(), 520000.into());
+        assert_eq!(post(&bob, &approve, &()), Status::Conflict);
+
+        let counted: Correction = post_json(&alice, "/api/corrections", &stock(3));
+        assert_eq!(counted.status, CorrectionStatus::Applied);
+        let recounted: Correction = post_json(&alice, "/api/corrections", &stock(-20));
+        assert_eq!(recounted.status, CorrectionStatus::Pending);
+        let rejected: Correction = post_json(
+            &bob,
+            &format!("/api/corrections/{}/reject", recounted.id),
+            &(),
+        );
+        assert_eq!(rejected.status, CorrectionStatus::Rejected);
+        assert_eq!(rejected.transaction_id, None);
+
+        let inventory: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&alice, "/api/inventory/items");
+        assert_eq!(inventory[&kaffe].stock, 3);
+
+        let pending: Vec<Correction> = get_json(&alice, "/api/corrections/pending");
+        assert!(pending.is_empty());
+        let all: Vec<Correction> = get_json(&alice, "/api/corrections");
+        assert_eq!(all.len(), 4);
+    }
+}
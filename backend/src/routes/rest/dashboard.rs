@@ -0,0 +1,105 @@
+use crate::auth::AdminSession;
+use crate::config::Config;
+use crate::database::{DatabasePool, ReportingPool};
+use crate::routes::rest::admin::bridge_status;
+use crate::routes::rest::book_account::reporting_master_accounts;
+use crate::routes::rest::izettle::izettle_bridge_poll::BridgeActivity;
+use crate::routes::rest::transaction::load_transactions;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::Utc;
+use rocket::{get, State};
+use strecklistan_api::dashboard::Dashboard;
+use strecklistan_api::time::{local_date, PeriodKind, ReportingPeriod};
+
+/// GET `/dashboard`
+///
+/// The sales of today so far, hour by hour, and the state of the iZettle bridge if card payments
+/// are enabled. Refetched by the dashboard page whenever a transaction is made.
+#[get("/dashboard")]
+pub fn get_dashboard(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    activity: &State<BridgeActivity>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Dashboard>, SJ> {
+    let organization = admin.0.organization;
+    let tz = config.reporting_timezone;
+    let today = local_date(Utc::now(), tz);
+
+    let masters = reporting_master_accounts(db_pool, organization)?;
+    let connection = db_pool.inner().get()?;
+    let bridge = if config.payments.izettle_enabled {
+        Some(bridge_status(&connection, activity, organization)?)
+    } else {
+        None
+    };
+    let bridge_online = bridge.is_some() && activity.is_online();
+
+    let day = ReportingPeriod::containing(PeriodKind::Day, today).range(tz);
+    let transactions = load_transactions(&reporting_pool.inner().get()?, organization, Some(day))?;
+
+    Ok(accept.ser(Dashboard {
+        bridge,
+        bridge_online,
+        ..Dashboard::compute(today, tz, &transactions, &masters)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, TestDb};
+    use rocket::http::Status;
+
+    #[test]
+    fn test_dashboard() {
+        use strecklistan_api::book_account::MasterAccounts;
+        use strecklistan_api::dashboard::Dashboard;
+        use strecklistan_api::preferences::{DefaultPage, UserPreferences};
+
+        let mut db = TestDb::new();
+        db.config.payments.izettle_enabled = true;
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+        db.user("tester", "hunter2", org, false);
+        let (_, member_account) = db.member(org, "Kalle");
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+
+        let dashboard: Dashboard = get_json(&client, "/api/dashboard");
+        assert_eq!(dashboard.sales, 0);
+        assert!(dashboard.hours.is_empty());
+
+        let masters: MasterAccounts = get_json(&client, "/api/book_accounts/masters");
+        let (cash, sales) = (masters.cash_account_id, masters.sales_account_id);
+        db.transaction(org, cash, sales, 1000.into());
+        db.transaction(org, cash, sales, 500.into());
+        // deposits aren't sales
+        db.transaction(org, cash, member_account, 10000.into());
+
+        let dashboard: Dashboard = get_json(&client, "/api/dashboard");
+        assert_eq!(dashboard.revenue, 1500.into());
+        assert_eq!(dashboard.sales, 2);
+        let per_hour: u32 = dashboard.hours.iter().map(|hour| hour.count).sum();
+        assert_eq!(per_hour, 2);
+        assert_eq!(
+            dashboard.bridge.map(|bridge| bridge.pending_payments),
+            Some(0)
+        );
+        assert!(!dashboard.bridge_online, "the bridge hasn't polled yet");
+
+        // admins land on the dashboard until they pick another page
+        let preferences: UserPreferences = get_json(&client, "/api/preferences");
+        assert_eq!(preferences.default_page, DefaultPage::Dashboard);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let response = client.get("/api/dashboard").dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let preferences: UserPreferences = get_json(&client, "/api/preferences");
+        assert_eq!(preferences.default_page, DefaultPage::Store);
+    }
+}
@@ -0,0 +1,348 @@
+use crate::auth::AdminSession;
+use crate::database::DatabasePool;
+use crate::util::audit::{self, AuditAction};
+use crate::util::device::{DeviceActivity, ThisDevice};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use strecklistan_api::device::{Device, DeviceId, DeviceName};
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::validation::Validate;
+
+type DeviceRow = (DeviceId, String, Option<String>, DateTime<Utc>);
+
+/// GET `/device`
+///
+/// The registered device the request comes from, if it's registered
+#[get("/device")]
+pub fn get_device(
+    db_pool: &State<DatabasePool>,
+    activity: &State<DeviceActivity>,
+    device: ThisDevice,
+    accept: SerAccept,
+) -> Result<Ser<Option<Device>>, SJ> {
+    let (device_id, _) = match device.registered {
+        Some(registered) => registered,
+        None => return Ok(accept.ser(None)),
+    };
+
+    let connection = db_pool.inner().get()?;
+    let row: DeviceRow = {
+        use crate::schema::tables::devices::dsl::*;
+        devices
+            .filter(id.eq(device_id))
+            .select((id, name, registered_by, registered_at))
+            .first(&connection)?
+    };
+
+    Ok(accept.ser(Some(to_device(row, activity))))
+}
+
+/// GET `/admin/devices`
+///
+/// The registered devices of the organization, sorted by name, and whether they are online
+#[get("/admin/devices")]
+pub fn get_devices(
+    db_pool: &State<DatabasePool>,
+    activity: &State<DeviceActivity>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Vec<Device>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::devices::dsl::*;
+    let rows: Vec<DeviceRow> = devices
+        .filter(organization_id.eq(admin.0.organization))
+        .select((id, name, registered_by, registered_at))
+        .order_by(name.asc())
+        .load(&connection)?;
+
+    let result = rows
+        .into_iter()
+        .map(|row| to_device(row, activity))
+        .collect();
+
+    Ok(accept.ser(result))
+}
+
+/// POST `/admin/devices`
+///
+/// Register the device the request comes from under a name, e.g. "Kassa 1"
+#[post("/admin/devices", data = "<device_name>")]
+pub fn post_device(
+    db_pool: &State<DatabasePool>,
+    activity: &State<DeviceActivity>,
+    admin: AdminSession,
+    device: ThisDevice,
+    accept: SerAccept,
+    device_name: Json<DeviceName>,
+) -> Result<Ser<Device>, SJ> {
+    let device_name = validate(device_name.into_inner())?;
+    let device_token = device.token.ok_or_else(|| {
+        SJ::new(
+            Status::BadRequest,
+            "The device has no device cookie, log in on it again",
+        )
+    })?;
+    if let Some((_, registered_name)) = device.registered {
+        return Err(SJ::new(
+            Status::Conflict,
+            format!("The device is already registered as {}", registered_name),
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    let organization = admin.0.organization;
+
+    let row = connection.transaction::<_, SJ, _>(|| {
+        check_name_free(&connection, organization, &device_name.name, None)?;
+
+        let row: DeviceRow = {
+            use crate::schema::tables::devices::dsl::*;
+            diesel::insert_into(devices)
+                .values((
+                    organization_id.eq(organization),
+                    token.eq(&device_token),
+                    name.eq(&device_name.name),
+                    registered_by.eq(&admin.0.user),
+                ))
+                .returning((id, name, registered_by, registered_at))
+                .get_result(&connection)?
+        };
+
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&admin.0.user),
+            AuditAction::DeviceRegistered,
+            &format!("Registered the device {}", device_name.name),
+        )?;
+
+        Ok(row)
+    })?;
+
+    activity.record(row.0);
+    Ok(accept.ser(to_device(row, activity)))
+}
+
+/// PUT `/admin/devices/<device_id>`
+///
+/// Rename a device. What was already done on it keeps the old name.
+#[put("/admin/devices/<device_id>", data = "<device_name>")]
+pub fn put_device(
+    db_pool: &State<DatabasePool>,
+    activity: &State<DeviceActivity>,
+    admin: AdminSession,
+    accept: SerAccept,
+    device_id: DeviceId,
+    device_name: Json<DeviceName>,
+) -> Result<Ser<Device>, SJ> {
+    let device_name = validate(device_name.into_inner())?;
+    let connection = db_pool.inner().get()?;
+    let organization = admin.0.organization;
+
+    let row = connection.transaction::<_, SJ, _>(|| {
+        check_name_free(
+            &connection,
+            organization,
+            &device_name.name,
+            Some(device_id),
+        )?;
+
+        use crate::schema::tables::devices::dsl::*;
+        let row: DeviceRow = diesel::update(
+            devices
+                .filter(id.eq(device_id))
+                .filter(organization_id.eq(organization)),
+        )
+        .set(name.eq(&device_name.name))
+        .returning((id, name, registered_by, registered_at))
+        .get_result(&connection)?;
+
+        Ok(row)
+    })?;
+
+    Ok(accept.ser(to_device(row, activity)))
+}
+
+/// DELETE `/admin/devices/<device_id>`
+///
+/// Forget a registered device. What was done on it keeps its name.
+#[delete("/admin/devices/<device_id>")]
+pub fn delete_device(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+    device_id: DeviceId,
+) -> Result<Ser<DeviceId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let organization = admin.0.organization;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let (deleted_id, deleted_name): (DeviceId, String) = {
+            use crate::schema::tables::devices::dsl::*;
+            diesel::delete(
+                devices
+                    .filter(id.eq(device_id))
+                    .filter(organization_id.eq(organization)),
+            )
+            .returning((id, name))
+            .get_result(&connection)?
+        };
+
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&admin.0.user),
+            AuditAction::DeviceRemoved,
+            &format!("Removed the device {}", deleted_name),
+        )?;
+
+        Ok(accept.ser(deleted_id))
+    })
+}
+
+fn validate(mut device_name: DeviceName) -> Result<DeviceName, SJ> {
+    device_name.name = device_name.name.trim().to_string();
+    device_name.validate()?;
+    Ok(device_name)
+}
+
+/// Fail if another device than `except` of the organization is called `device_name`
+fn check_name_free(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    device_name: &str,
+    except: Option<DeviceId>,
+) -> Result<(), SJ> {
+    use crate::schema::tables::devices::dsl::*;
+    let taken: Option<DeviceId> = devices
+        .filter(organization_id.eq(organization))
+        .filter(name.eq(device_name))
+        .select(id)
+        .first(connection)
+        .optional()?;
+
+    match taken {
+        Some(taken) if Some(taken) != except => Err(SJ::new(
+            Status::Conflict,
+            format!("There already is a device called {}", device_name),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn to_device(
+    (device_id, device_name, registered_by, registered_at): DeviceRow,
+    activity: &DeviceActivity,
+) -> Device {
+    Device {
+        id: device_id,
+        name: device_name,
+        registered_by,
+        registered_at,
+        last_seen: activity.last_seen(device_id),
+        online: activity.is_online(device_id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_devices() {
+        use strecklistan_api::device::{Device, DeviceName};
+        use strecklistan_api::register::RegisterStatus;
+        use strecklistan_api::transaction::TransactionDetail;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        db.user("boss", "hunter3", org, true);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let register = db.client();
+        login(&register, "boss", "hunter3");
+        let cashier = db.client();
+        login(&cashier, "tester", "hunter2");
+
+        let name = |name: &str| DeviceName {
+            name: name.to_string(),
+        };
+        let this_device = |client: &Client| -> Option<Device> { get_json(client, "/api/device") };
+        assert_eq!(this_device(&register), None);
+        assert_eq!(
+            post(&cashier, "/api/admin/devices", &name("Kassa 1")),
+            Status::Forbidden
+        );
+
+        let device: Device = post_json(&register, "/api/admin/devices", &name(" Kassa 1 "));
+        assert_eq!(device.name, "Kassa 1");
+        assert_eq!(device.registered_by.as_deref(), Some("boss"));
+        assert!(device.online);
+        assert_eq!(
+            this_device(&register).map(|device| device.id),
+            Some(device.id)
+        );
+        assert_eq!(
+            post(&register, "/api/admin/devices", &name("Kassa 2")),
+            Status::Conflict
+        );
+
+        // what's done on the device is attributed to it
+        let sale = NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let id: i32 = post_json(&register, "/api/transaction", &sale);
+        let elsewhere: i32 = post_json(&cashier, "/api/transaction", &sale);
+        let detail = |id: i32| -> TransactionDetail {
+            get_json(&register, &format!("/api/transaction/{}", id))
+        };
+        assert_eq!(detail(id).device.as_deref(), Some("Kassa 1"));
+        assert_eq!(detail(elsewhere).device, None);
+
+        let status: RegisterStatus = post_json(&register, "/api/register/open", &());
+        assert_eq!(
+            status.open_shift.unwrap().opened_on.as_deref(),
+            Some("Kassa 1")
+        );
+
+        let devices: Vec<Device> = get_json(&register, "/api/admin/devices");
+        assert_eq!(devices.len(), 1);
+        assert!(devices[0].online && devices[0].last_seen.is_some());
+
+        // a renamed device keeps what was done on it under the old name
+        let uri = format!("/api/admin/devices/{}", device.id);
+        let response = register
+            .put(&uri)
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&name("Bar")).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(this_device(&register).unwrap().name, "Bar");
+        assert_eq!(detail(id).device.as_deref(), Some("Kassa 1"));
+
+        assert_eq!(register.delete(&uri).dispatch().status(), Status::Ok);
+        assert_eq!(this_device(&register), None);
+    }
+}
@@ -1,25 +1,492 @@
+use crate::auth::Session;
+use crate::config::Config;
 use crate::database::event::{get_event_ws, get_event_ws_range};
-use crate::database::DatabasePool;
+use crate::database::{DatabasePool, ReportingPool};
+use crate::models::book_account as relational;
 use crate::models::event::EventWithSignups as EventWS;
+use crate::models::{NewSignup, Signup};
+use crate::routes::rest::inventory::check_available;
+use crate::routes::rest::transaction::load_event_transactions;
+use crate::util::rate_limit::{Public, RateLimit, Signup};
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
-use rocket::{get, State};
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use std::collections::{BTreeMap, HashMap};
+use strecklistan_api::book_account::{BookAccount, BookAccountId};
+use strecklistan_api::event::{EventId, EventProfitAndLoss, EventSignup, NewEventSignup, SignupId};
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::validation::Validate;
 
+/// GET `/event/<id>`
+///
+/// A published event of the public organization. Doesn't require a login, so it's rate limited
+/// per client instead.
 #[get("/event/<id>")]
 pub fn get_event(
+    _limit: RateLimit<Public>,
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
     accept: SerAccept,
     id: i32,
 ) -> Result<Ser<EventWS>, SJ> {
-    Ok(accept.ser(get_event_ws(db_pool.inner().get()?, id, true)?))
+    let connection = db_pool.inner().get()?;
+    let organization = config.public.organization;
+    Ok(accept.ser(get_event_ws(connection, organization, id, true)?))
 }
 
+/// GET `/events?<low>&<high>`
+///
+/// The published events of the public organization, like [get_event]
 #[get("/events?<low>&<high>")]
 pub fn get_event_range(
+    _limit: RateLimit<Public>,
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
     accept: SerAccept,
     low: i64,
     high: i64,
 ) -> Result<Ser<Vec<EventWS>>, SJ> {
-    Ok(accept.ser(get_event_ws_range(db_pool.inner().get()?, low, high, true)?))
+    let connection = db_pool.inner().get()?;
+    let organization = config.public.organization;
+    Ok(accept.ser(get_event_ws_range(
+        connection,
+        organization,
+        low,
+        high,
+        true,
+    )?))
+}
+
+/// GET `/events/profit_and_loss`
+///
+/// The revenue and costs of every event code which transactions have been tagged with, the most
+/// recent event first
+#[get("/events/profit_and_loss")]
+pub fn get_event_profit_and_loss(
+    reporting_pool: &State<ReportingPool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<EventProfitAndLoss>>, SJ> {
+    let connection = reporting_pool.inner().get()?;
+    let transactions = load_event_transactions(&connection, session.organization)?;
+
+    let accounts: HashMap<BookAccountId, BookAccount> = {
+        use crate::schema::tables::book_accounts::dsl::{book_accounts, organization_id};
+        book_accounts
+            .filter(organization_id.eq(session.organization))
+            .load(&connection)?
+            .into_iter()
+            .map(|acc: relational::BookAccount| (acc.id, acc.into()))
+            .collect()
+    };
+
+    Ok(accept.ser(EventProfitAndLoss::compute(&transactions, &accounts)))
+}
+
+/// GET `/event/<id>/signups`
+///
+/// Who signed up to an event, in the order they signed up, and what they pre-ordered
+#[get("/event/<id>/signups")]
+pub fn get_event_signups(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    id: EventId,
+) -> Result<Ser<Vec<EventSignup>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    if !event_exists(&connection, session.organization, id)? {
+        return Err(SJ::new(Status::NotFound, "No such event"));
+    }
+
+    let signups: Vec<Signup> = {
+        use crate::schema::tables::event_signups::dsl::{event, event_signups, id as signup_id};
+        event_signups
+            .filter(event.eq(id))
+            .order_by(signup_id.asc())
+            .load(&connection)?
+    };
+
+    let mut reservations: HashMap<SignupId, BTreeMap<InventoryItemId, u32>> = HashMap::new();
+    {
+        use crate::schema::tables::event_reservations::dsl::*;
+        let signup_ids: Vec<SignupId> = signups.iter().map(|signup| signup.id).collect();
+        let rows: Vec<(SignupId, InventoryItemId, i32)> = event_reservations
+            .filter(signup_id.eq_any(&signup_ids))
+            .select((signup_id, item_id, amount))
+            .load(&connection)?;
+        for (signup, item, count) in rows {
+            reservations
+                .entry(signup)
+                .or_default()
+                .insert(item, count as u32);
+        }
+    }
+
+    let result = signups
+        .into_iter()
+        .map(|signup| EventSignup {
+            reservations: reservations.remove(&signup.id).unwrap_or_default(),
+            id: signup.id,
+            event: signup.event,
+            name: signup.name,
+            email: signup.email,
+        })
+        .collect();
+
+    Ok(accept.ser(result))
+}
+
+/// POST `/event/<id>/signups`
+///
+/// Sign someone up to an event, and set aside what they pre-ordered until the event starts.
+/// Returns the id of the signup.
+///
+/// Responds with 409 Conflict, and the items which there aren't enough of, like a sale would, if
+/// there aren't enough of the pre-ordered items to be set aside.
+#[post("/event/<id>/signups", data = "<signup>")]
+pub fn post_event_signup(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    id: EventId,
+    signup: Json<NewEventSignup>,
+    _limit: RateLimit<Signup>,
+) -> Result<Ser<SignupId>, SJ> {
+    session.check_not_training()?;
+    signup.validate()?;
+    let NewEventSignup {
+        name,
+        email,
+        reservations,
+    } = signup.into_inner();
+    let reservations: BTreeMap<InventoryItemId, u32> = reservations
+        .into_iter()
+        .filter(|&(_, count)| count > 0)
+        .collect();
+
+    let connection = db_pool.inner().get()?;
+    connection.transaction::<_, SJ, _>(|| {
+        let start: Option<DateTime<Utc>> = {
+            use crate::schema::tables::events::dsl::{
+                events, organization_id, published, start_time,
+            };
+            events
+                .find(id)
+                .filter(organization_id.eq(session.organization))
+                .filter(published.eq(true))
+                .select(start_time)
+                .first(&connection)
+                .optional()?
+        };
+        match start {
+            None => return Err(SJ::new(Status::NotFound, "No such event")),
+            Some(start) if start <= Utc::now() => {
+                return Err(SJ::new(Status::BadRequest, "The event has already started"))
+            }
+            Some(_) => {}
+        }
+
+        let item_ids: Vec<InventoryItemId> = reservations.keys().copied().collect();
+        let found: i64 = {
+            use crate::schema::tables::inventory::dsl::{
+                id as item_id, inventory, organization_id,
+            };
+            inventory
+                .filter(item_id.eq_any(&item_ids))
+                .filter(organization_id.eq(session.organization))
+                .count()
+                .get_result(&connection)?
+        };
+        if found != item_ids.len() as i64 {
+            return Err(SJ::new(Status::NotFound, "No such inventory item"));
+        }
+
+        let requested = reservations
+            .iter()
+            .map(|(&item, &count)| (item, count as i32))
+            .collect();
+        check_available(&connection, requested, config.stock.negative_stock)?;
+
+        let new_signup_id: SignupId = {
+            use crate::schema::tables::event_signups::dsl::{event_signups, id as signup_id};
+            diesel::insert_into(event_signups)
+                .values(&NewSignup {
+                    event: id,
+                    name: name.trim().to_string(),
+                    email: email.trim().to_string(),
+                })
+                .returning(signup_id)
+                .get_result(&connection)?
+        };
+
+        {
+            use crate::schema::tables::event_reservations::dsl::*;
+            let rows: Vec<_> = reservations
+                .iter()
+                .map(|(&item, &count)| {
+                    (
+                        signup_id.eq(new_signup_id),
+                        item_id.eq(item),
+                        amount.eq(count as i32),
+                    )
+                })
+                .collect();
+            if !rows.is_empty() {
+                diesel::insert_into(event_reservations)
+                    .values(&rows)
+                    .execute(&connection)?;
+            }
+        }
+
+        Ok(accept.ser(new_signup_id))
+    })
+}
+
+/// DELETE `/event/<id>/signups/<signup_id>`
+///
+/// Cancel a signup, which releases what was set aside for it
+#[delete("/event/<id>/signups/<signup_id>")]
+pub fn delete_event_signup(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    id: EventId,
+    signup_id: SignupId,
+) -> Result<Ser<SignupId>, SJ> {
+    session.check_not_training()?;
+    let connection = db_pool.inner().get()?;
+    if !event_exists(&connection, session.organization, id)? {
+        return Err(SJ::new(Status::NotFound, "No such event"));
+    }
+
+    use crate::schema::tables::event_signups::dsl::{event, event_signups, id as signup};
+    let deleted_id = diesel::delete(
+        event_signups
+            .filter(signup.eq(signup_id))
+            .filter(event.eq(id)),
+    )
+    .returning(signup)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(deleted_id))
+}
+
+/// Whether `organization` has an event `id`, published or not
+fn event_exists(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    id: EventId,
+) -> QueryResult<bool> {
+    use crate::schema::tables::events::dsl::{events, organization_id};
+    diesel::select(diesel::dsl::exists(
+        events.find(id).filter(organization_id.eq(organization)),
+    ))
+    .get_result(connection)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_event_profit_and_loss() {
+        use strecklistan_api::event::EventProfitAndLoss;
+        use strecklistan_api::transaction::TransactionAnnotation;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let purchases = db.account(org, "Inköp", BookAccountType::Expenses);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let transaction =
+            |debited_account, credited_account, amount: i32, event: Option<&str>| NewTransaction {
+                description: None,
+                bundles: vec![],
+                debited_account,
+                credited_account,
+                amount: amount.into(),
+                note: None,
+                tags: vec![],
+                event_code: event.map(|event| event.to_string()),
+                age_verified: false,
+                member_id: None,
+                idempotency_key: None,
+            };
+        let restock = transaction(purchases, cash, 3000, Some(" pubrunda "));
+        let _: i32 = post_json(&client, "/api/transaction", &restock);
+        let sale = transaction(cash, sales, 2500, Some("pubrunda"));
+        let _: i32 = post_json(&client, "/api/transaction", &sale);
+        let untagged: i32 = post_json(
+            &client,
+            "/api/transaction",
+            &transaction(cash, sales, 1000, None),
+        );
+
+        // tagged afterwards
+        let annotation = TransactionAnnotation {
+            note: None,
+            tags: vec![],
+            event_code: Some("pubrunda".to_string()),
+        };
+        let response = client
+            .patch(format!("/api/transaction/{}", untagged))
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&annotation).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let events: Vec<EventProfitAndLoss> = get_json(&client, "/api/events/profit_and_loss");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_code, "pubrunda");
+        assert_eq!(events[0].transaction_count, 3);
+        assert_eq!(events[0].revenue, 3500.into());
+        assert_eq!(events[0].costs, 3000.into());
+        assert_eq!(events[0].result(), 500.into());
+    }
+
+    #[test]
+    fn test_event_reservations() {
+        use chrono::{Duration, Utc};
+        use strecklistan_api::event::{EventSignup, NewEventSignup, SignupId};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let tarta = db.item(org, "Smörgåstårta", Some(6000.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let sittning = db.event(org, "Sittning", Utc::now() + Duration::days(7));
+        let past = db.event(org, "Gasque", Utc::now() - Duration::days(7));
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let signup = |count: u32| NewEventSignup {
+            name: "Testsson".to_string(),
+            email: "test@example.com".to_string(),
+            reservations: vec![(tarta, count)].into_iter().collect(),
+        };
+        let transaction = |change: i32| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(6000.into()),
+                change,
+                item_ids: vec![(tarta, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 0.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let signups_uri = format!("/api/event/{}/signups", sittning);
+
+        // nothing to set aside yet
+        assert_eq!(post(&client, &signups_uri, &signup(3)), Status::Conflict);
+        let _: i32 = post_json(&client, "/api/transaction", &transaction(5));
+
+        let id: SignupId = post_json(&client, &signups_uri, &signup(3));
+        let past_uri = format!("/api/event/{}/signups", past);
+        assert_eq!(post(&client, &past_uri, &signup(1)), Status::BadRequest);
+
+        let signups: Vec<EventSignup> = get_json(&client, &signups_uri);
+        assert_eq!(signups.len(), 1);
+        assert_eq!(signups[0].id, id);
+        assert_eq!(signups[0].reservations[&tarta], 3);
+
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert_eq!(items[&tarta].reserved, 3);
+
+        // what's set aside can't be sold
+        assert_eq!(
+            post(&client, "/api/transaction", &transaction(-3)),
+            Status::Conflict
+        );
+        let _: i32 = post_json(&client, "/api/transaction", &transaction(-2));
+
+        // cancelling the signup releases it
+        let uri = format!("/api/event/{}/signups/{}", sittning, id);
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::Ok);
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert_eq!(items[&tarta].reserved, 0);
+        let _: i32 = post_json(&client, "/api/transaction", &transaction(-3));
+    }
+
+    #[test]
+    fn test_events_per_organization() {
+        use chrono::{Duration, Utc};
+        use strecklistan_api::event::{EventSignup, NewEventSignup, SignupId};
+
+        let mut db = TestDb::new();
+        let org = db.organization("Test");
+        let sister = db.organization("Syster");
+        db.config.public.organization = org;
+        db.user("tester", "hunter2", org, false);
+        db.user("syster", "hunter2", sister, false);
+        let ours = db.event(org, "Sittning", Utc::now() + Duration::days(7));
+        let theirs = db.event(sister, "Gasque", Utc::now() + Duration::days(7));
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let other = db.client();
+        login(&other, "syster", "hunter2");
+
+        let signup = NewEventSignup {
+            name: "Testsson".to_string(),
+            email: "test@example.com".to_string(),
+            reservations: Default::default(),
+        };
+        let theirs_uri = format!("/api/event/{}/signups", theirs);
+        let id: SignupId = post_json(&other, &theirs_uri, &signup);
+
+        // only the events of the public organization are listed, without logging in
+        let public = db.client();
+        let events: Vec<serde_json::Value> = get_json(&public, "/api/events?low=-10&high=10");
+        let titles: Vec<&str> = events.iter().filter_map(|e| e["title"].as_str()).collect();
+        assert_eq!(titles, ["Sittning"]);
+        let _: serde_json::Value = get_json(&public, &format!("/api/event/{}", ours));
+        let uri = format!("/api/event/{}", theirs);
+        assert_eq!(public.get(&uri).dispatch().status(), Status::NotFound);
+
+        // the events of other organizations can't be signed up to
+        assert_eq!(
+            client.get(&theirs_uri).dispatch().status(),
+            Status::NotFound
+        );
+        assert_eq!(post(&client, &theirs_uri, &signup), Status::NotFound);
+
+        // nor can their signups be cancelled
+        let uri = format!("/api/event/{}/signups/{}", theirs, id);
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::NotFound);
+        let signups: Vec<EventSignup> = get_json(&other, &theirs_uri);
+        assert_eq!(signups.len(), 1);
+
+        let _: SignupId = post_json(&client, &format!("/api/event/{}/signups", ours), &signup);
+    }
 }
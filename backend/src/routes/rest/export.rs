@@ -0,0 +1,524 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::database::{DatabasePool, ReportingPool};
+use crate::routes::rest::member::load_member_accounts;
+use crate::routes::rest::organization::load_branding;
+use crate::routes::rest::transaction::{load_transaction_page, load_transactions_at};
+use crate::util::status_json::StatusJson as SJ;
+use crate::util::{pdf, signing};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use diesel::prelude::*;
+use log::error;
+use rocket::http::{ContentType, Header, Status};
+use rocket::response::stream::ByteStream;
+use rocket::tokio::sync::mpsc;
+use rocket::{get, Responder, State};
+use serde::Serialize;
+use std::mem;
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::member::MemberId;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::report::MemberLiability;
+use strecklistan_api::time::{PeriodKind, ReportingPeriod, Tz};
+use strecklistan_api::transaction::{Transaction, TransactionId};
+
+/// How many transactions are read from the database at a time
+const PAGE_SIZE: i64 = 500;
+
+/// How many pages may be waiting to be sent to a slow client
+const BUFFERED_PAGES: usize = 4;
+
+/// GET `/export/transactions.csv`
+///
+/// Every transaction of the organization as CSV, one row per item, with dates and times in the
+/// reporting timezone. The columns are the same as those of the CSV export of the transactions
+/// page.
+///
+/// The rows are streamed as they are read from the database, so the whole history is never in
+/// memory at once.
+///
+/// Once a whole export has been sent, the transactions from before it started may be compacted by
+/// the retention jobs, see `retention` in the config.
+#[get("/export/transactions.csv")]
+pub fn export_transactions_csv(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+) -> Result<(ContentType, ByteStream![Vec<u8>]), SJ> {
+    // connect before the response starts, so that failing to do so is reported as an error
+    let connection = reporting_pool.inner().get()?;
+    let db_pool = db_pool.inner().clone();
+    let organization = session.organization;
+    let tz = config.reporting_timezone;
+    let started = Utc::now();
+
+    let (sender, mut receiver) = mpsc::channel(BUFFERED_PAGES);
+    rocket::tokio::task::spawn_blocking(move || {
+        let result = write_transactions(&connection, organization, tz, |chunk| {
+            sender.blocking_send(chunk).is_ok()
+        })
+        .and_then(|completed| {
+            if completed {
+                mark_exported(&db_pool, organization, started)?;
+            }
+            Ok(())
+        });
+        // the response has already started, so all that can be done is to end it early
+        if let Err(e) = result {
+            error!("Exporting transactions failed: {}", e.description);
+        }
+    });
+
+    Ok((
+        ContentType::CSV,
+        ByteStream! {
+            while let Some(chunk) = receiver.recv().await {
+                yield chunk;
+            }
+        },
+    ))
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    transaction_id: TransactionId,
+    description: Option<&'a String>,
+    date: NaiveDate,
+    time: NaiveTime,
+    debited_account: BookAccountId,
+    credited_account: BookAccountId,
+    amount: Currency,
+
+    bundle_index: Option<usize>,
+    bundle_description: Option<&'a String>,
+    bundle_price: Option<Currency>,
+    bundle_change: Option<i32>,
+
+    item_id: Option<InventoryItemId>,
+    item_amount: Option<u32>,
+}
+
+/// Write the transactions as CSV, one page at a time, to `send`.
+///
+/// Stops early if `send` returns false, e.g. since the client went away. Returns whether all of
+/// them were written.
+fn write_transactions(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    tz: Tz,
+    mut send: impl FnMut(Vec<u8>) -> bool,
+) -> Result<bool, SJ> {
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(vec![]);
+    let mut after = None;
+
+    loop {
+        let page = load_transaction_page(connection, organization, after, PAGE_SIZE)?;
+        let last = match page.last() {
+            Some(last) => (last.time, last.id),
+            None => return Ok(true),
+        };
+
+        for transaction in &page {
+            write_transaction(&mut writer, transaction, tz)?;
+        }
+
+        writer.flush().map_err(csv::Error::from)?;
+        if !send(mem::take(writer.get_mut())) {
+            return Ok(false);
+        }
+        after = Some(last);
+    }
+}
+
+/// Remember that the transactions of `organization` from before `started` have been exported
+fn mark_exported(
+    db_pool: &DatabasePool,
+    organization: OrganizationId,
+    started: DateTime<Utc>,
+) -> Result<(), SJ> {
+    // the reporting pool may be read-only
+    let connection = db_pool.get()?;
+    use crate::schema::tables::organizations::dsl::*;
+    diesel::update(organizations.find(organization))
+        .set(transactions_exported_at.eq(started))
+        .execute(&connection)?;
+    Ok(())
+}
+
+fn write_transaction(
+    writer: &mut csv::Writer<Vec<u8>>,
+    transaction: &Transaction,
+    tz: Tz,
+) -> Result<(), csv::Error> {
+    let local_time = transaction.time.with_timezone(&tz);
+    let tr_record = Record {
+        transaction_id: transaction.id,
+        description: transaction.description.as_ref(),
+        date: local_time.naive_local().date(),
+        time: local_time.time(),
+        debited_account: transaction.debited_account,
+        credited_account: transaction.credited_account,
+        amount: transaction.amount,
+
+        bundle_index: None,
+        bundle_description: None,
+        bundle_price: None,
+        bundle_change: None,
+
+        item_id: None,
+        item_amount: None,
+    };
+
+    if transaction.bundles.is_empty() {
+        return writer.serialize(tr_record);
+    }
+
+    for (bundle_index, bundle) in transaction.bundles.iter().enumerate() {
+        let bundle_record = Record {
+            bundle_index: Some(bundle_index),
+            bundle_description: bundle.description.as_ref(),
+            bundle_price: bundle.price,
+            bundle_change: Some(bundle.change),
+
+            ..tr_record
+        };
+
+        if bundle.item_ids.is_empty() {
+            writer.serialize(bundle_record)?;
+            continue;
+        }
+
+        let mut item_ids: Vec<_> = bundle.item_ids.iter().collect();
+        item_ids.sort();
+        for (&item_id, &item_amount) in item_ids {
+            writer.serialize(Record {
+                item_id: Some(item_id),
+                item_amount: Some(item_amount),
+
+                ..bundle_record
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// An export together with its signature, which is sent in the `X-Strecklistan-Signature` header
+#[derive(Responder)]
+pub struct SignedExport {
+    body: (ContentType, Vec<u8>),
+    signature: Header<'static>,
+}
+
+impl SignedExport {
+    fn new(content_type: ContentType, body: Vec<u8>, key: &str) -> Self {
+        let signature = Header::new("X-Strecklistan-Signature", signing::sign(key, &body));
+        SignedExport {
+            body: (content_type, body),
+            signature,
+        }
+    }
+}
+
+/// The tillgodo balances of all members at the end of a day
+struct BalanceStatement {
+    date: NaiveDate,
+    cutoff: DateTime<Utc>,
+    generated_at: DateTime<Utc>,
+    tz: Tz,
+    liabilities: Vec<MemberLiability>,
+}
+
+/// GET `/export/member_balances.csv?<date>`
+///
+/// The tillgodo balance of every member at the end of `date` (YYYY-MM-DD) in the reporting
+/// timezone, for the annual audit. The balances are computed from the ledger as it was at the
+/// cutoff, so transactions deleted after it are still counted.
+///
+/// The cutoff and the time of the export are listed in comments before the rows, and the last
+/// line is a signature of everything before it, made with `exports.signing_key`.
+#[get("/export/member_balances.csv?<date>")]
+pub fn export_member_balances_csv(
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    date: String,
+) -> Result<SignedExport, SJ> {
+    let key = signing_key(config)?;
+    let statement = load_balance_statement(reporting_pool, config, session.organization, &date)?;
+    let tz = statement.tz;
+
+    #[derive(Serialize)]
+    struct Record<'a> {
+        member_id: MemberId,
+        account_id: BookAccountId,
+        name: &'a str,
+        balance: Currency,
+        last_activity: Option<String>,
+    }
+
+    let mut csv = format!(
+        "# cutoff: {}\n# generated_at: {}\n",
+        statement.cutoff.with_timezone(&tz).to_rfc3339(),
+        statement.generated_at.with_timezone(&tz).to_rfc3339(),
+    )
+    .into_bytes();
+
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::NonNumeric)
+        .from_writer(vec![]);
+    for liability in &statement.liabilities {
+        writer.serialize(Record {
+            member_id: liability.member_id,
+            account_id: liability.account_id,
+            name: &liability.name,
+            balance: liability.balance,
+            last_activity: liability
+                .last_activity
+                .map(|time| time.with_timezone(&tz).to_rfc3339()),
+        })?;
+    }
+    writer.flush().map_err(csv::Error::from)?;
+    csv.extend(mem::take(writer.get_mut()));
+
+    let total: Currency = statement.liabilities.iter().map(|l| l.balance).sum();
+    csv.extend(format!("# total: {}\n", i64::from(total)).as_bytes());
+
+    let signature = signing::sign(key, &csv);
+    csv.extend(format!("# signature: {}\n", signature).as_bytes());
+
+    Ok(SignedExport::new(ContentType::CSV, csv, key))
+}
+
+/// GET `/export/member_balances.pdf?<date>`
+///
+/// The same statement as `/export/member_balances.csv`, laid out for printing. The last line is a
+/// signature of the lines before it, joined with newlines.
+#[get("/export/member_balances.pdf?<date>")]
+pub fn export_member_balances_pdf(
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    date: String,
+) -> Result<SignedExport, SJ> {
+    let key = signing_key(config)?;
+    let statement = load_balance_statement(reporting_pool, config, session.organization, &date)?;
+    let branding = load_branding(&reporting_pool.get()?, session.organization)?;
+    let tz = statement.tz;
+    let fmt_time =
+        |time: DateTime<Utc>| time.with_timezone(&tz).format("%Y-%m-%d %H:%M").to_string();
+
+    let title = format!("Tillgodo {}", statement.date);
+    let mut lines = vec![
+        match &branding.org_number {
+            Some(number) => format!("{} ({})", branding.name, number),
+            None => branding.name.clone(),
+        },
+        title.clone(),
+        format!(
+            "Cutoff:    {}",
+            statement.cutoff.with_timezone(&tz).to_rfc3339()
+        ),
+        format!(
+            "Generated: {}",
+            statement.generated_at.with_timezone(&tz).to_rfc3339()
+        ),
+        String::new(),
+        format!(
+            "{:>6} {:>7}  {:<40} {:>12}  {}",
+            "Member", "Account", "Name", "Balance", "Last activity"
+        ),
+    ];
+    lines.extend(statement.liabilities.iter().map(|liability| {
+        format!(
+            "{:>6} {:>7}  {:<40} {:>12}  {}",
+            liability.member_id,
+            liability.account_id,
+            liability.name.chars().take(40).collect::<String>(),
+            liability.balance.to_string(),
+            liability.last_activity.map(fmt_time).unwrap_or_default(),
+        )
+    }));
+
+    let total: Currency = statement.liabilities.iter().map(|l| l.balance).sum();
+    lines.push(format!(
+        "{:>6} {:>7}  {:<40} {:>12}",
+        "",
+        "",
+        "Total",
+        total.to_string()
+    ));
+    lines.push(String::new());
+    if let Some(footer) = &branding.footer_text {
+        lines.extend(footer.lines().map(str::to_string));
+        lines.push(String::new());
+    }
+
+    let signature = signing::sign(key, lines.join("\n").as_bytes());
+    lines.push(format!("Signature: {}", signature));
+
+    let document = pdf::text_document(&title, statement.generated_at, &lines);
+    Ok(SignedExport::new(ContentType::PDF, document, key))
+}
+
+fn signing_key(config: &Config) -> Result<&str, SJ> {
+    config
+        .exports
+        .signing_key
+        .as_deref()
+        .ok_or_else(|| SJ::new(Status::ServiceUnavailable, "Signed exports are disabled"))
+}
+
+/// The balances of every member account, including those of archived members, at the end of
+/// `date`
+fn load_balance_statement(
+    reporting_pool: &ReportingPool,
+    config: &Config,
+    organization: OrganizationId,
+    date: &str,
+) -> Result<BalanceStatement, SJ> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| SJ::new(Status::BadRequest, "Invalid date, expected YYYY-MM-DD"))?;
+    let tz = config.reporting_timezone;
+    let cutoff = ReportingPeriod::containing(PeriodKind::Day, date)
+        .range(tz)
+        .end;
+    let generated_at = Utc::now();
+    if cutoff > generated_at {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "The balances can only be exported for days which have ended",
+        ));
+    }
+
+    let connection = reporting_pool.get()?;
+
+    let accounts = load_member_accounts(&connection, organization)?;
+
+    let transactions = load_transactions_at(&connection, organization, cutoff)?;
+
+    Ok(BalanceStatement {
+        date,
+        cutoff,
+        generated_at,
+        tz,
+        liabilities: MemberLiability::compute(cutoff, &accounts, &transactions),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{login, TestDb};
+    use rocket::http::{ContentType, Status};
+    use strecklistan_api::book_account::BookAccountType;
+
+    #[test]
+    fn test_export() {
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (_, member_account) = db.member(org, "Testsson");
+        for _ in 0..3 {
+            db.transaction(org, cash, member_account, 1000.into());
+        }
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let response = client.get("/api/export/transactions.csv").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::CSV));
+
+        let csv = response.into_string().unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4, "a header and one line per transaction");
+        assert!(lines[0].starts_with("\"transaction_id\""));
+    }
+
+    #[test]
+    fn test_export_member_balances() {
+        use crate::util::signing;
+        use chrono::{Duration, Utc};
+        use diesel::prelude::*;
+        use strecklistan_api::time::local_date;
+
+        let mut db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (_, member_account) = db.member(org, "Testsson");
+        let deposit = db.transaction(org, cash, member_account, 1000.into());
+        let deleted = db.transaction(org, cash, member_account, 500.into());
+        db.transaction(org, cash, member_account, 2000.into());
+
+        // two deposits were made a few days ago, one of which has been deleted since
+        {
+            use crate::schema::tables::transactions::dsl::*;
+            let connection = db.conn();
+            diesel::update(transactions.filter(id.eq_any(vec![deposit, deleted])))
+                .set(time.eq(Utc::now() - Duration::days(3)))
+                .execute(&connection)
+                .unwrap();
+            diesel::update(transactions.filter(id.eq(deleted)))
+                .set(deleted_at.eq(Some(Utc::now())))
+                .execute(&connection)
+                .unwrap();
+        }
+
+        let yesterday = local_date(Utc::now() - Duration::days(1), db.config.reporting_timezone);
+        let csv_url = format!("/api/export/member_balances.csv?date={}", yesterday);
+        let pdf_url = format!("/api/export/member_balances.pdf?date={}", yesterday);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let response = client.get(&csv_url).dispatch();
+        assert_eq!(
+            response.status(),
+            Status::ServiceUnavailable,
+            "no signing key"
+        );
+        drop(client);
+
+        db.config.exports.signing_key = Some("hemligt".to_string());
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let today = local_date(Utc::now(), db.config.reporting_timezone);
+        let today_url = format!("/api/export/member_balances.csv?date={}", today);
+        let response = client.get(&today_url).dispatch();
+        assert_eq!(
+            response.status(),
+            Status::BadRequest,
+            "the day hasn't ended"
+        );
+
+        let response = client.get(&csv_url).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::CSV));
+        let header = response.headers().get_one("X-Strecklistan-Signature");
+        let header = header.unwrap().to_string();
+        let csv = response.into_bytes().unwrap();
+        assert_eq!(header, signing::sign("hemligt", &csv));
+
+        let csv = String::from_utf8(csv).unwrap();
+        let (body, signature) = csv.trim_end().rsplit_once('\n').unwrap();
+        let expected = signing::sign("hemligt", format!("{}\n", body).as_bytes());
+        assert_eq!(signature, format!("# signature: {}", expected));
+        assert!(
+            body.lines().any(|line| line.contains(",1500,")),
+            "the deleted deposit counts, the one after the cutoff doesn't"
+        );
+        assert!(body.contains("# total: 1500"));
+
+        let response = client.get(&pdf_url).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::PDF));
+        assert!(response.into_bytes().unwrap().starts_with(b"%PDF"));
+    }
+}
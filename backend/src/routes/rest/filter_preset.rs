@@ -0,0 +1,206 @@
+use crate::auth::Session;
+use crate::database::DatabasePool;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, put, State};
+use strecklistan_api::filter_preset::{FilterPreset, FilterPresetId, NewFilterPreset};
+use strecklistan_api::validation::Validate;
+
+/// GET `/filter_presets`
+///
+/// The presets which the logged in user has saved, of every page, by name
+#[get("/filter_presets")]
+pub fn get_filter_presets(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<FilterPreset>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::filter_presets::dsl::*;
+
+    let rows: Vec<(i32, String, String, serde_json::Value)> = filter_presets
+        .filter(user_name.eq(&session.user))
+        .filter(organization_id.eq(session.organization))
+        .select((id, name, page, filter))
+        .order_by(name.asc())
+        .load(&connection)?;
+
+    let presets = rows
+        .into_iter()
+        .map(|(preset_id, preset_name, preset_page, preset_filter)| {
+            let invalid = |e: String| {
+                SJ::new(
+                    Status::InternalServerError,
+                    format!("Stored filter preset is invalid: {}", e),
+                )
+            };
+            Ok(FilterPreset {
+                id: preset_id,
+                name: preset_name,
+                page: preset_page.parse().map_err(|_| invalid(preset_page))?,
+                filter: serde_json::from_value(preset_filter)
+                    .map_err(|e| invalid(e.to_string()))?,
+            })
+        })
+        .collect::<Result<_, SJ>>()?;
+
+    Ok(accept.ser(presets))
+}
+
+/// PUT `/filter_preset`
+///
+/// Save a preset, replacing the one of the same page with the same name. Returns the saved
+/// preset.
+#[put("/filter_preset", data = "<preset>")]
+pub fn put_filter_preset(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    preset: Json<NewFilterPreset>,
+) -> Result<Ser<FilterPreset>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::filter_presets::dsl::*;
+
+    let preset = preset.into_inner();
+    preset.validate()?;
+
+    let preset_name = preset.name.trim();
+    let preset_filter = serde_json::to_value(&preset.filter)
+        .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+    let preset_id = diesel::insert_into(filter_presets)
+        .values((
+            user_name.eq(&session.user),
+            organization_id.eq(session.organization),
+            page.eq(preset.page.to_string()),
+            name.eq(preset_name),
+            filter.eq(&preset_filter),
+        ))
+        .on_conflict((user_name, organization_id, page, name))
+        .do_update()
+        .set(filter.eq(&preset_filter))
+        .returning(id)
+        .get_result(&connection)?;
+
+    Ok(accept.ser(FilterPreset {
+        id: preset_id,
+        name: preset_name.to_string(),
+        page: preset.page,
+        filter: preset.filter,
+    }))
+}
+
+/// DELETE `/filter_preset/<preset_id>`
+///
+/// Only the user's own presets can be deleted
+#[delete("/filter_preset/<preset_id>")]
+pub fn delete_filter_preset(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    preset_id: FilterPresetId,
+) -> Result<Ser<FilterPresetId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::filter_presets::dsl::*;
+
+    let deleted_id = diesel::delete(
+        filter_presets
+            .filter(id.eq(preset_id))
+            .filter(user_name.eq(&session.user))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .returning(id)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(deleted_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, TestDb};
+    use rocket::http::{ContentType, Status};
+    use strecklistan_api::book_account::BookAccountType;
+
+    #[test]
+    fn test_filter_presets() {
+        use strecklistan_api::filter_preset::{
+            FilterPreset, NewFilterPreset, PresetDates, PresetFilter, PresetPage,
+        };
+        use strecklistan_api::time::PeriodKind;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("treasurer", "hunter2", org, false);
+        db.user("other", "hunter2", org, false);
+        let card = db.account(org, "iZettle", BookAccountType::Assets);
+
+        let client = db.client();
+        login(&client, "treasurer", "hunter2");
+
+        let save = |name: &str, page, filter: &PresetFilter| {
+            client
+                .put("/api/filter_preset")
+                .header(ContentType::JSON)
+                .body(
+                    serde_json::to_string(&NewFilterPreset {
+                        name: name.to_string(),
+                        page,
+                        filter: filter.clone(),
+                    })
+                    .unwrap(),
+                )
+                .dispatch()
+        };
+
+        let mut card_payments = PresetFilter {
+            dates: PresetDates::Current(PeriodKind::Month),
+            paid_with: Some(card),
+            ..Default::default()
+        };
+        let saved = save(" Kort ", PresetPage::Transactions, &card_payments);
+        assert_eq!(saved.status(), Status::Ok);
+        let saved: FilterPreset = saved.into_json().unwrap();
+        assert_eq!(saved.name, "Kort");
+
+        // saving with the same name replaces the preset, but only on the same page
+        card_payments.search = "sittning".to_string();
+        let replaced: FilterPreset = save("Kort", PresetPage::Transactions, &card_payments)
+            .into_json()
+            .unwrap();
+        assert_eq!(replaced.id, saved.id);
+        let analytics = PresetFilter::default();
+        assert_eq!(
+            save("Kort", PresetPage::Analytics, &analytics).status(),
+            Status::Ok
+        );
+        assert_eq!(
+            save(" ", PresetPage::Analytics, &analytics).status(),
+            Status::UnprocessableEntity
+        );
+
+        let presets: Vec<FilterPreset> = get_json(&client, "/api/filter_presets");
+        assert_eq!(presets.len(), 2);
+        let transactions_preset = presets
+            .iter()
+            .find(|preset| preset.page == PresetPage::Transactions)
+            .unwrap();
+        assert_eq!(transactions_preset.filter, card_payments);
+
+        // the presets of other users are neither shown nor deletable
+        let client = db.client();
+        login(&client, "other", "hunter2");
+        let presets: Vec<FilterPreset> = get_json(&client, "/api/filter_presets");
+        assert!(presets.is_empty());
+        let uri = format!("/api/filter_preset/{}", saved.id);
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::NotFound);
+
+        let client = db.client();
+        login(&client, "treasurer", "hunter2");
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::Ok);
+        let presets: Vec<FilterPreset> = get_json(&client, "/api/filter_presets");
+        assert_eq!(presets.len(), 1);
+    }
+}
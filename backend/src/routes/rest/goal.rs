@@ -0,0 +1,338 @@
+use crate::auth::{AdminSession, Session};
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::routes::rest::book_account::master_accounts;
+use crate::routes::rest::transaction::{load_event_transactions, load_transactions};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use std::convert::TryFrom;
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::goal::{
+    GoalScope, GoalTarget, NewSalesGoal, SalesGoal, SalesGoalId, SalesGoalProgress,
+};
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::time::{local_date, Tz};
+use strecklistan_api::user::UserName;
+use strecklistan_api::validation::Validate;
+
+type GoalRow = (
+    SalesGoalId,
+    String,
+    Option<i64>,
+    Option<i32>,
+    Option<String>,
+    Option<String>,
+    Option<UserName>,
+    DateTime<Utc>,
+);
+
+/// The goals of an organization which haven't ended, in the order they were set.
+///
+/// Goals of an event never end, they are removed by the admins instead.
+pub fn load_goals(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    tz: Tz,
+) -> Result<Vec<SalesGoal>, SJ> {
+    use crate::schema::tables::sales_goals::dsl::*;
+    let rows: Vec<GoalRow> = sales_goals
+        .filter(organization_id.eq(organization))
+        .order_by(id.asc())
+        .select((
+            id,
+            name,
+            revenue_target,
+            quantity_target,
+            period,
+            event_code,
+            created_by,
+            created_at,
+        ))
+        .load(connection)?;
+
+    let today = local_date(Utc::now(), tz);
+    let mut goals = vec![];
+    for row in rows {
+        let goal = goal_from_row(row)?;
+        match &goal.scope {
+            GoalScope::Period(goal_period) if goal_period.last_day() < today => {}
+            _ => goals.push(goal),
+        }
+    }
+    Ok(goals)
+}
+
+/// Sum up the sales to `sales_account` which count towards each of `goals`
+pub fn load_progress(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    tz: Tz,
+    sales_account: BookAccountId,
+    goals: Vec<SalesGoal>,
+) -> Result<Vec<SalesGoalProgress>, SJ> {
+    let mut progress = vec![];
+    for goal in goals {
+        let transactions = match &goal.scope {
+            GoalScope::Period(period) => {
+                load_transactions(connection, organization, Some(period.range(tz)))?
+            }
+            GoalScope::Event(_) => load_event_transactions(connection, organization)?,
+        };
+        progress.push(SalesGoalProgress::compute(
+            goal,
+            &transactions,
+            sales_account,
+            tz,
+        ));
+    }
+    Ok(progress)
+}
+
+/// GET `/goals`
+///
+/// How far the goals which haven't ended have come
+#[get("/goals")]
+pub fn get_goals(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<SalesGoalProgress>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let tz = config.reporting_timezone;
+    let goals = load_goals(&connection, session.organization, tz)?;
+    let sales_account = master_accounts(&connection, session.organization)?.sales_account_id;
+    Ok(accept.ser(load_progress(
+        &connection,
+        session.organization,
+        tz,
+        sales_account,
+        goals,
+    )?))
+}
+
+/// POST `/admin/goals`
+///
+/// Set a goal, returns its id
+#[post("/admin/goals", data = "<goal>")]
+pub fn post_goal(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+    goal: Json<NewSalesGoal>,
+) -> Result<Ser<SalesGoalId>, SJ> {
+    let goal = validate(goal.into_inner())?;
+    let (revenue, quantity) = match goal.target {
+        GoalTarget::Revenue(amount) => (Some(i64::from(amount)), None),
+        GoalTarget::Quantity(count) => {
+            let count = i32::try_from(count)
+                .map_err(|_| SJ::new(Status::BadRequest, "The target is too large"))?;
+            (None, Some(count))
+        }
+    };
+    let (goal_period, goal_event) = match &goal.scope {
+        GoalScope::Period(goal_period) => (Some(goal_period.to_string()), None),
+        GoalScope::Event(code) => (None, Some(code.clone())),
+    };
+
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::sales_goals::dsl::*;
+    let goal_id = diesel::insert_into(sales_goals)
+        .values((
+            organization_id.eq(admin.0.organization),
+            name.eq(&goal.name),
+            revenue_target.eq(revenue),
+            quantity_target.eq(quantity),
+            period.eq(goal_period),
+            event_code.eq(goal_event),
+            created_by.eq(&admin.0.user),
+        ))
+        .returning(id)
+        .get_result(&connection)?;
+
+    Ok(accept.ser(goal_id))
+}
+
+/// DELETE `/admin/goals/<goal_id>`
+#[delete("/admin/goals/<goal_id>")]
+pub fn delete_goal(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+    goal_id: SalesGoalId,
+) -> Result<Ser<SalesGoalId>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::sales_goals::dsl::*;
+    let deleted_id = diesel::delete(
+        sales_goals
+            .filter(id.eq(goal_id))
+            .filter(organization_id.eq(admin.0.organization)),
+    )
+    .returning(id)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(deleted_id))
+}
+
+fn goal_from_row(row: GoalRow) -> Result<SalesGoal, SJ> {
+    let (id, name, revenue, quantity, period, event_code, created_by, created_at) = row;
+    let invalid = || {
+        SJ::new(
+            Status::InternalServerError,
+            "Invalid sales goal in the database",
+        )
+    };
+
+    let target = match (revenue, quantity) {
+        (Some(amount), None) => GoalTarget::Revenue(amount.into()),
+        (None, Some(count)) => GoalTarget::Quantity(count as u32),
+        _ => return Err(invalid()),
+    };
+    let scope = match (period, event_code) {
+        (Some(period), None) => GoalScope::Period(period.parse().map_err(|_| invalid())?),
+        (None, Some(code)) => GoalScope::Event(code),
+        _ => return Err(invalid()),
+    };
+
+    Ok(SalesGoal {
+        id,
+        name,
+        target,
+        scope,
+        created_by,
+        created_at,
+    })
+}
+
+fn validate(mut goal: NewSalesGoal) -> Result<NewSalesGoal, SJ> {
+    goal.name = goal.name.trim().to_string();
+    if let GoalScope::Event(code) = &mut goal.scope {
+        *code = code.trim().to_string();
+    }
+
+    goal.validate()?;
+    Ok(goal)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_goals() {
+        use crate::config::NegativeStock;
+        use chrono::Utc;
+        use strecklistan_api::goal::{
+            GoalScope, GoalTarget, NewSalesGoal, SalesGoalId, SalesGoalProgress,
+        };
+        use strecklistan_api::public::PublicStats;
+        use strecklistan_api::time::{PeriodKind, ReportingPeriod};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        db.config.stock.negative_stock = NegativeStock::Warn;
+        let org = db.organization("Test");
+        db.config.public.organization = org;
+        db.user("admin", "hunter2", org, true);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+
+        let today = ReportingPeriod::at(PeriodKind::Day, Utc::now(), db.config.reporting_timezone);
+        let revenue_goal: SalesGoalId = post_json(
+            &client,
+            "/api/admin/goals",
+            &NewSalesGoal {
+                name: " Tusenlappen ".to_string(),
+                target: GoalTarget::Revenue(100000.into()),
+                scope: GoalScope::Period(today),
+            },
+        );
+        let quantity_goal: SalesGoalId = post_json(
+            &client,
+            "/api/admin/goals",
+            &NewSalesGoal {
+                name: "Pubrunda".to_string(),
+                target: GoalTarget::Quantity(4),
+                scope: GoalScope::Event(" pubrunda ".to_string()),
+            },
+        );
+        // ended yesterday
+        let _: SalesGoalId = post_json(
+            &client,
+            "/api/admin/goals",
+            &NewSalesGoal {
+                name: "Igår".to_string(),
+                target: GoalTarget::Quantity(1),
+                scope: GoalScope::Period(today.previous()),
+            },
+        );
+        let status = post(
+            &client,
+            "/api/admin/goals",
+            &NewSalesGoal {
+                name: "Ingenting".to_string(),
+                target: GoalTarget::Quantity(0),
+                scope: GoalScope::Period(today),
+            },
+        );
+        assert_eq!(status, Status::UnprocessableEntity);
+
+        let sale = NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change: -5,
+                item_ids: vec![(kaffe, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 2500.into(),
+            note: None,
+            tags: vec![],
+            event_code: Some("pubrunda".to_string()),
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let _: i32 = post_json(&client, "/api/transaction", &sale);
+
+        let goals: Vec<SalesGoalProgress> = get_json(&client, "/api/goals");
+        let ids: Vec<SalesGoalId> = goals.iter().map(|progress| progress.goal.id).collect();
+        assert_eq!(ids, vec![revenue_goal, quantity_goal]);
+        assert_eq!(goals[0].goal.name, "Tusenlappen");
+        assert_eq!(goals[0].achieved, 2500);
+        assert!(!goals[0].is_reached());
+        assert_eq!(
+            goals[1].goal.scope,
+            GoalScope::Event("pubrunda".to_string())
+        );
+        assert_eq!(goals[1].achieved, 5);
+        assert!(goals[1].is_reached());
+
+        // the revenue isn't public
+        let stats: PublicStats = get_json(&client, "/api/public/stats");
+        assert_eq!(stats.goals.len(), 1);
+        assert_eq!(stats.goals[0].goal.id, quantity_goal);
+
+        let uri = format!("/api/admin/goals/{}", quantity_goal);
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::Ok);
+        let goals: Vec<SalesGoalProgress> = get_json(&client, "/api/goals");
+        assert_eq!(goals.len(), 1);
+    }
+}
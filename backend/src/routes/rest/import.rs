@@ -0,0 +1,634 @@
+//! Import of historical transactions, e.g. from the spreadsheets which were used before this
+//! system, so that the analytics cover those years too.
+//!
+//! The file is CSV with a header row. The columns may come in any order, and other columns are
+//! ignored.
+//!
+//! | Column        | Required | Contents                                                  |
+//! |---------------|----------|-----------------------------------------------------------|
+//! | `date`        | yes      | YYYY-MM-DD, in the reporting timezone                     |
+//! | `time`        | no       | HH:MM, noon if empty                                      |
+//! | `kind`        | yes      | `sale` or `deposit`                                       |
+//! | `amount`      | yes      | In kronor, e.g. `25` or `25.50`. A decimal comma is fine. |
+//! | `account`     | yes      | The name of a book account, see below                     |
+//! | `item`        | no       | The name of the item which was sold                       |
+//! | `quantity`    | no       | How many of `item` were sold, 1 if empty                  |
+//! | `description` | no       |                                                           |
+//!
+//! A sale is paid from `account`, e.g. `Kontantkassa` or the tillgodo account of a member, to the
+//! sales account. A deposit is made to `account`, which must be the tillgodo account of a member,
+//! from the cash account.
+//!
+//! ```csv
+//! date,time,kind,amount,account,item,quantity,description
+//! 2019-03-01,18:30,sale,25,Kontantkassa,Kaffe,1,
+//! 2019-03-01,,deposit,200,Stina Svensson,,,
+//! 2019-03-02,12:15,sale,"12,50",Stina Svensson,Kaka,1,Fika
+//! ```
+//!
+//! The imported transactions are kept apart in an import batch, which can be rolled back if the
+//! file turns out to be wrong. Imported sales don't count towards the stock, since they were made
+//! before it was counted in the system.
+
+use crate::auth::AdminSession;
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::models::transaction::{object, relational};
+use crate::routes::rest::book_account::master_accounts;
+use crate::routes::rest::live::LiveEvents;
+use crate::routes::rest::transaction::{insert_bundles, load_lock_date};
+use crate::util::analytics;
+use crate::util::audit::{self, AuditAction};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use log::error;
+use rocket::http::Status;
+use rocket::{get, post, State};
+use serde::Deserialize;
+use std::collections::HashMap;
+use strecklistan_api::book_account::{BookAccountId, MasterAccounts};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::import::{ImportBatch, ImportBatchId, ImportError, ImportSummary};
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::member::MemberId;
+use strecklistan_api::organization::is_locked;
+use strecklistan_api::time::{local_date, Tz};
+use strecklistan_api::validation::{MAX_AMOUNT, MAX_TEXT_LENGTH};
+
+/// A line of the file, as it was written
+#[derive(Deserialize)]
+struct Row {
+    date: String,
+    #[serde(default)]
+    time: String,
+    kind: String,
+    amount: String,
+    account: String,
+    #[serde(default)]
+    item: String,
+    #[serde(default)]
+    quantity: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// A line of the file which has been checked, ready to be inserted
+struct ImportedTransaction {
+    sale: bool,
+    time: DateTime<Utc>,
+    debited_account: BookAccountId,
+    credited_account: BookAccountId,
+    amount: Currency,
+    description: Option<String>,
+    item: Option<(InventoryItemId, u32)>,
+
+    /// The member whose tillgodo paid for a sale
+    member_id: Option<MemberId>,
+}
+
+/// What the lines of the file are checked against
+struct Context {
+    tz: Tz,
+    now: DateTime<Utc>,
+    lock_date: Option<NaiveDate>,
+    masters: MasterAccounts,
+
+    /// The id of every book account by name, and the member it belongs to, if any
+    accounts: HashMap<String, (BookAccountId, Option<MemberId>)>,
+    items: HashMap<String, InventoryItemId>,
+}
+
+/// POST `/admin/import/transactions?<dry_run>`
+///
+/// Import historical transactions from a CSV file, see the [module docs](self) for the format.
+///
+/// The whole file is checked first, and nothing is imported if any line has errors, which are
+/// listed in the summary. With `dry_run` the file is only checked.
+#[post("/admin/import/transactions?<dry_run>", data = "<csv>")]
+pub fn post_import(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    accept: SerAccept,
+    dry_run: Option<bool>,
+    csv: Vec<u8>,
+) -> Result<Ser<ImportSummary>, SJ> {
+    let organization = admin.0.organization;
+    let connection = db_pool.inner().get()?;
+
+    // before the accounts are loaded, since this creates them if they are missing
+    let masters = master_accounts(&connection, organization)?;
+
+    let accounts = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(organization_id.eq(organization))
+            .select((name, id, creditor))
+            .load(&connection)?
+            .into_iter()
+            .map(
+                |(account_name, account_id, member): (String, i32, Option<i32>)| {
+                    (account_name, (account_id, member))
+                },
+            )
+            .collect()
+    };
+
+    let items = {
+        use crate::schema::tables::inventory::dsl::*;
+        inventory
+            .filter(organization_id.eq(organization))
+            .select((name, id))
+            .load(&connection)?
+            .into_iter()
+            .filter_map(|(item_name, item_id): (Option<String>, i32)| Some((item_name?, item_id)))
+            .collect()
+    };
+
+    let context = Context {
+        tz: config.reporting_timezone,
+        now: Utc::now(),
+        lock_date: load_lock_date(&connection, organization)?,
+        masters,
+        accounts,
+        items,
+    };
+    let (imported, errors) = parse_file(&csv, &context);
+
+    let mut summary = ImportSummary {
+        batch_id: None,
+        sales: 0,
+        sales_total: Currency::default(),
+        deposits: 0,
+        deposits_total: Currency::default(),
+        first_date: None,
+        last_date: None,
+        errors,
+    };
+    for transaction in &imported {
+        if transaction.sale {
+            summary.sales += 1;
+            summary.sales_total += transaction.amount;
+        } else {
+            summary.deposits += 1;
+            summary.deposits_total += transaction.amount;
+        }
+
+        let date = local_date(transaction.time, context.tz);
+        summary.first_date = Some(summary.first_date.map_or(date, |first| first.min(date)));
+        summary.last_date = Some(summary.last_date.map_or(date, |last| last.max(date)));
+    }
+
+    if dry_run.unwrap_or(false) || !summary.errors.is_empty() || imported.is_empty() {
+        return Ok(accept.ser(summary));
+    }
+
+    let batch_id = connection.transaction::<_, SJ, _>(|| {
+        let batch_id: ImportBatchId = {
+            use crate::schema::tables::import_batches::dsl::*;
+            diesel::insert_into(import_batches)
+                .values((
+                    organization_id.eq(organization),
+                    created_by.eq(&admin.0.user),
+                ))
+                .returning(id)
+                .get_result(&connection)?
+        };
+
+        // no webhooks are notified, the transactions are old news
+        for transaction in imported {
+            let new_transaction = relational::NewTransaction {
+                description: transaction.description,
+                time: Some(transaction.time),
+                debited_account: transaction.debited_account,
+                credited_account: transaction.credited_account,
+                amount: transaction.amount.into(),
+                note: None,
+                tags: vec![],
+                created_by: None,
+                idempotency_key: None,
+                organization_id: organization,
+                event_code: None,
+                age_verified: false,
+                device: None,
+                member_id: transaction.member_id,
+            };
+
+            let transaction_id = {
+                use crate::schema::tables::transactions::dsl::*;
+                diesel::insert_into(transactions)
+                    .values((&new_transaction, import_batch_id.eq(batch_id)))
+                    .returning(id)
+                    .get_result(&connection)?
+            };
+
+            if let Some((item_id, count)) = transaction.item {
+                // the price of a bundle is per item, which is left out if it isn't exact
+                let amount: i64 = transaction.amount.into();
+                let price = Some(amount)
+                    .filter(|amount| amount % i64::from(count) == 0)
+                    .map(|amount| Currency::from(amount / i64::from(count)));
+
+                let bundle = object::TransactionBundle {
+                    description: None,
+                    price,
+                    change: -(count as i32),
+                    item_ids: vec![(item_id, 1)].into_iter().collect(),
+                    expires_on: None,
+                    modifier_ids: vec![],
+                };
+                insert_bundles(&connection, transaction_id, vec![bundle])?;
+            }
+        }
+
+        Ok(batch_id)
+    })?;
+    summary.batch_id = Some(batch_id);
+    live.notify(organization, LiveEvent::TransactionsChanged);
+
+    // so that the imported sales show up on the analytics page right away
+    if let Err(e) = analytics::refresh(&connection, config.reporting_timezone) {
+        error!("Refreshing the analytics after an import failed: {}", e);
+    }
+
+    Ok(accept.ser(summary))
+}
+
+/// GET `/admin/import/batches`
+///
+/// Every import of the organization, the latest first
+#[get("/admin/import/batches")]
+pub fn get_import_batches(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Vec<ImportBatch>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let counts: HashMap<ImportBatchId, i64> = {
+        use crate::schema::tables::transactions::dsl::*;
+        transactions
+            .filter(organization_id.eq(admin.0.organization))
+            .filter(import_batch_id.is_not_null())
+            .group_by(import_batch_id)
+            .select((import_batch_id, count_star()))
+            .load(&connection)?
+            .into_iter()
+            .filter_map(|(batch, count): (Option<i32>, i64)| Some((batch?, count)))
+            .collect()
+    };
+
+    use crate::schema::tables::import_batches::dsl::*;
+    let batches = import_batches
+        .filter(organization_id.eq(admin.0.organization))
+        .order_by(created_at.desc())
+        .select((id, created_at, created_by, rolled_back_at))
+        .load::<(
+            ImportBatchId,
+            DateTime<Utc>,
+            Option<String>,
+            Option<DateTime<Utc>>,
+        )>(&connection)?
+        .into_iter()
+        .map(
+            |(batch_id, batch_created_at, batch_created_by, batch_rolled_back_at)| ImportBatch {
+                id: batch_id,
+                created_at: batch_created_at,
+                created_by: batch_created_by,
+                transaction_count: counts.get(&batch_id).copied().unwrap_or(0) as u32,
+                rolled_back_at: batch_rolled_back_at,
+            },
+        )
+        .collect();
+
+    Ok(accept.ser(batches))
+}
+
+/// POST `/admin/import/batches/<batch_id>/rollback`
+///
+/// Remove the transactions of an import again. They are deleted like any other transaction, so
+/// this is refused if any of them are before the lock date.
+#[post("/admin/import/batches/<batch_id>/rollback")]
+pub fn rollback_import_batch(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    batch_id: ImportBatchId,
+) -> Result<Status, SJ> {
+    let organization = admin.0.organization;
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let rolled_back: Option<DateTime<Utc>> = {
+            use crate::schema::tables::import_batches::dsl::*;
+            import_batches
+                .filter(id.eq(batch_id))
+                .filter(organization_id.eq(organization))
+                .select(rolled_back_at)
+                .for_update()
+                .first(&connection)?
+        };
+        if rolled_back.is_some() {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "The import has already been rolled back",
+            ));
+        }
+
+        use crate::schema::tables::transactions::dsl::{
+            deleted_at, import_batch_id, time, transactions,
+        };
+        let batch = || {
+            transactions
+                .filter(import_batch_id.eq(batch_id))
+                .filter(deleted_at.is_null())
+        };
+
+        let first: Option<DateTime<Utc>> =
+            batch().select(diesel::dsl::min(time)).first(&connection)?;
+        let lock_date = load_lock_date(&connection, organization)?;
+        if let Some(first) = first {
+            if is_locked(first, lock_date, config.reporting_timezone) {
+                return Err(SJ::new(
+                    Status::Conflict,
+                    "The import has transactions before the lock date and can't be rolled back",
+                )
+                .with_code(ErrorCode::PeriodLocked)
+                .with_details(&lock_date));
+            }
+        }
+
+        let now = Utc::now();
+        diesel::update(batch())
+            .set(deleted_at.eq(Some(now)))
+            .execute(&connection)?;
+
+        use crate::schema::tables::import_batches::dsl::{id, import_batches, rolled_back_at};
+        diesel::update(import_batches.filter(id.eq(batch_id)))
+            .set(rolled_back_at.eq(Some(now)))
+            .execute(&connection)?;
+
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&admin.0.user),
+            AuditAction::ImportRolledBack,
+            &format!("Rolled back import {}", batch_id),
+        )?;
+
+        Ok(())
+    })?;
+    live.notify(organization, LiveEvent::TransactionsChanged);
+
+    if let Err(e) = analytics::refresh(&connection, config.reporting_timezone) {
+        error!("Refreshing the analytics after a rollback failed: {}", e);
+    }
+
+    Ok(Status::NoContent)
+}
+
+/// Read and check every line of the file
+fn parse_file(csv: &[u8], context: &Context) -> (Vec<ImportedTransaction>, Vec<ImportError>) {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv);
+
+    let mut transactions = vec![];
+    let mut errors = vec![];
+
+    let headers = match reader.headers() {
+        Ok(headers) => headers.clone(),
+        Err(e) => {
+            errors.push(ImportError {
+                line: 1,
+                message: e.to_string(),
+            });
+            return (transactions, errors);
+        }
+    };
+
+    for record in reader.records() {
+        let result = record.and_then(|record| {
+            let line = record.position().map_or(0, |position| position.line());
+            Ok((line, record.deserialize::<Row>(Some(&headers))?))
+        });
+
+        match result {
+            Ok((line, row)) => match parse_row(row, context) {
+                Ok(transaction) => transactions.push(transaction),
+                Err(message) => errors.push(ImportError { line, message }),
+            },
+            Err(e) => errors.push(ImportError {
+                line: e.position().map_or(0, |position| position.line()),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    (transactions, errors)
+}
+
+fn parse_row(row: Row, context: &Context) -> Result<ImportedTransaction, String> {
+    let date = NaiveDate::parse_from_str(&row.date, "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date \"{}\", expected YYYY-MM-DD", row.date))?;
+    let time_of_day = match row.time.as_str() {
+        "" => NaiveTime::from_hms(12, 0, 0),
+        time => NaiveTime::parse_from_str(time, "%H:%M")
+            .map_err(|_| format!("Invalid time \"{}\", expected HH:MM", time))?,
+    };
+    let time = context
+        .tz
+        .from_local_datetime(&date.and_time(time_of_day))
+        .earliest()
+        .ok_or_else(|| {
+            format!(
+                "{} {} doesn't exist in the reporting timezone",
+                date, time_of_day
+            )
+        })?
+        .with_timezone(&Utc);
+
+    if time > context.now {
+        return Err(format!("{} is in the future", date));
+    }
+    if is_locked(time, context.lock_date, context.tz) {
+        return Err(format!(
+            "{} is before the lock date {}",
+            date,
+            context.lock_date.unwrap_or(date)
+        ));
+    }
+
+    let amount: Currency = row
+        .amount
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| format!("Invalid amount \"{}\"", row.amount))?;
+    if amount <= Currency::default() || amount > MAX_AMOUNT {
+        return Err(format!(
+            "The amount must be more than 0 and at most {}",
+            MAX_AMOUNT
+        ));
+    }
+
+    let (account, member) = *context
+        .accounts
+        .get(&row.account)
+        .ok_or_else(|| format!("Unknown account \"{}\"", row.account))?;
+
+    let description = Some(row.description).filter(|d| !d.is_empty());
+    if description.as_ref().map_or(0, |d| d.chars().count()) > MAX_TEXT_LENGTH {
+        return Err(format!(
+            "The description is longer than {} characters",
+            MAX_TEXT_LENGTH
+        ));
+    }
+
+    match row.kind.to_lowercase().as_str() {
+        "sale" => {
+            let item = match row.item.as_str() {
+                "" => None,
+                item_name => {
+                    let item_id = *context
+                        .items
+                        .get(item_name)
+                        .ok_or_else(|| format!("Unknown item \"{}\"", item_name))?;
+                    let count = match row.quantity.as_str() {
+                        "" => 1,
+                        quantity => quantity
+                            .parse()
+                            .ok()
+                            .filter(|&count| count > 0)
+                            .ok_or_else(|| format!("Invalid quantity \"{}\"", quantity))?,
+                    };
+                    Some((item_id, count))
+                }
+            };
+
+            Ok(ImportedTransaction {
+                sale: true,
+                time,
+                debited_account: account,
+                credited_account: context.masters.sales_account_id,
+                amount,
+                description,
+                item,
+                member_id: member,
+            })
+        }
+        "deposit" => {
+            if member.is_none() {
+                return Err(format!(
+                    "Deposits can only be made to member accounts, \"{}\" isn't one",
+                    row.account
+                ));
+            }
+
+            Ok(ImportedTransaction {
+                sale: false,
+                time,
+                debited_account: context.masters.cash_account_id,
+                credited_account: account,
+                amount,
+                description,
+                item: None,
+                member_id: None,
+            })
+        }
+        kind => Err(format!(
+            "Unknown kind \"{}\", expected sale or deposit",
+            kind
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::{BookAccount, BookAccountId};
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+
+    #[test]
+    fn test_import() {
+        use chrono::NaiveDate;
+        use strecklistan_api::import::{ImportBatch, ImportSummary};
+        use strecklistan_api::transaction::Transaction;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+        let coffee = db.item(org, "Kaffe", Some(1000.into()));
+        let (_, member_account) = db.member(org, "Testsson");
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+
+        let import = |uri: &str, csv: &str| -> ImportSummary {
+            let response = client
+                .post(uri)
+                .header(ContentType::CSV)
+                .body(csv)
+                .dispatch();
+            assert_eq!(response.status(), Status::Ok, "POST {}", uri);
+            response.into_json().expect("Invalid response body")
+        };
+
+        let csv = "date,time,kind,amount,account,item,quantity,description\n\
+                   2019-03-01,18:30,sale,30,Testsson,Kaffe,3,\n\
+                   2019-03-01,,deposit,\"200,50\",Testsson,,,Insättning\n\
+                   2019-03-02,12:15,sale,25,Nobody,,,\n";
+        let summary = import("/api/admin/import/transactions", csv);
+        assert_eq!(summary.batch_id, None, "nothing is imported with errors");
+        assert_eq!(summary.errors.len(), 1);
+        assert_eq!(summary.errors[0].line, 4);
+
+        let csv = csv.replace("Nobody", "Kontantkassa");
+        let summary = import("/api/admin/import/transactions?dry_run=true", &csv);
+        assert_eq!(summary.batch_id, None);
+        assert!(summary.errors.is_empty());
+        assert_eq!(summary.sales, 2);
+        assert_eq!(summary.sales_total, 5500.into());
+        assert_eq!(summary.deposits_total, 20050.into());
+        assert_eq!(summary.first_date, Some(NaiveDate::from_ymd(2019, 3, 1)));
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert!(transactions.is_empty());
+
+        let summary = import("/api/admin/import/transactions", &csv);
+        let batch_id = summary.batch_id.expect("imported");
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 3);
+        let sale = transactions.iter().find(|t| !t.bundles.is_empty()).unwrap();
+        assert_eq!(sale.bundles[0].change, -3);
+        assert_eq!(sale.bundles[0].price, Some(1000.into()));
+
+        let accounts: HashMap<BookAccountId, BookAccount> = get_json(&client, "/api/book_accounts");
+        assert_eq!(accounts[&member_account].balance, (20050 - 3000).into());
+
+        // the stock was counted after the import
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert_eq!(items[&coffee].stock, 0);
+
+        let batches: Vec<ImportBatch> = get_json(&client, "/api/admin/import/batches");
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].transaction_count, 3);
+        assert_eq!(batches[0].created_by.as_deref(), Some("admin"));
+
+        let uri = format!("/api/admin/import/batches/{}/rollback", batch_id);
+        assert_eq!(client.post(&uri).dispatch().status(), Status::NoContent);
+        assert_eq!(client.post(&uri).dispatch().status(), Status::BadRequest);
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert!(transactions.is_empty());
+        let batches: Vec<ImportBatch> = get_json(&client, "/api/admin/import/batches");
+        assert!(batches[0].rolled_back_at.is_some());
+    }
+}
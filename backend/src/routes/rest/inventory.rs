@@ -1,78 +1,1311 @@
+use crate::auth::{AdminSession, Session};
+use crate::config::{Config, NegativeStock};
+use crate::database::item_cache::{CachedInventory, ItemCache};
 use crate::database::DatabasePool;
-use crate::models::inventory::{InventoryBundle as InventoryBundleRel, InventoryBundleItem};
+use crate::routes::rest::analytics::parse_as_of;
+use crate::util::audit::{self, AuditAction};
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use diesel::dsl::{sql, sum};
 use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer};
 use itertools::Itertools;
-use rocket::{get, State};
-use std::collections::HashMap;
+use log::warn;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, patch, put, State};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::error::ErrorCode;
 use strecklistan_api::inventory::InventoryBundle as InventoryBundleObj;
 use strecklistan_api::inventory::{
-    InventoryBundleId, InventoryItemId, InventoryItemStock, InventoryItemTag,
+    compatible_modifiers, remaining_batches, ExpiringBatch, InventoryBatchEdit, InventoryBundleId,
+    InventoryItemId, InventoryItemStock, InventoryItemTag, ItemModifier, ItemModifierId,
+    PurchaseLimit, StockShortage,
 };
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::time::{local_date, PeriodKind, Tz};
+use strecklistan_api::transaction::TransactionBundle;
 
-#[get("/inventory/items")]
+/// GET `/inventory/items?<as_of>`
+///
+/// All items with their stock, or with the stock as it was at the end of the day `as_of`
+/// (YYYY-MM-DD) in the reporting timezone. A past stock is computed from the transactions as
+/// they were then, so those deleted since are still counted, and nothing counts as reserved.
+#[get("/inventory/items?<as_of>")]
 pub fn get_inventory(
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
     accept: SerAccept,
+    as_of: Option<String>,
 ) -> Result<Ser<HashMap<InventoryItemId, InventoryItemStock>>, SJ> {
     let connection = db_pool.inner().get()?;
+    let mut items = load_items(&connection, session.organization)?;
 
-    use crate::schema::views::inventory_stock::dsl::inventory_stock;
-    Ok(accept.ser(
+    if let Some(as_of) = as_of {
+        let cutoff = parse_as_of(&as_of, config.reporting_timezone)?;
+        let stock_then = load_stock_at(
+            &connection,
+            session.organization,
+            cutoff,
+            config.reporting_timezone,
+        )?;
+        for item in &mut items {
+            item.stock = stock_then.get(&item.id).copied().unwrap_or(0);
+            item.reserved = 0;
+        }
+    }
+
+    Ok(accept.ser(items.into_iter().map(|item| (item.id, item)).collect()))
+}
+
+/// All items of `organization`, including the archived ones
+pub fn load_items(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<Vec<InventoryItemStock>> {
+    let mut items: Vec<InventoryItemStock> = {
+        use crate::schema::tables::inventory::dsl::{
+            age_restricted, archived_at, id as item_id, inventory, low_stock_at, organization_id,
+            prepared,
+        };
+        use crate::schema::views::inventory_stock::dsl::{
+            id, image_url, inventory_stock, name, price, stock,
+        };
         inventory_stock
-            .load(&connection)?
+            .inner_join(inventory.on(item_id.eq(id)))
+            .filter(organization_id.eq(organization))
+            .select((
+                id,
+                name,
+                price,
+                image_url,
+                stock,
+                archived_at,
+                age_restricted,
+                prepared,
+                low_stock_at,
+                // filled in below
+                sql::<Integer>("0"),
+            ))
+            .load(connection)?
+    };
+
+    let item_ids: Vec<InventoryItemId> = items.iter().map(|item| item.id).collect();
+    let reserved = load_reserved(connection, &item_ids)?;
+    for item in &mut items {
+        item.reserved = reserved.get(&item.id).copied().unwrap_or(0);
+    }
+    Ok(items)
+}
+
+/// How many of each of `item_ids` are reserved for events which haven't started yet, see
+/// [EventSignup](strecklistan_api::event::EventSignup)
+pub fn load_reserved(
+    connection: &PgConnection,
+    item_ids: &[InventoryItemId],
+) -> QueryResult<HashMap<InventoryItemId, i32>> {
+    use crate::schema::tables::event_reservations::dsl::{
+        amount, event_reservations, item_id, signup_id,
+    };
+    use crate::schema::tables::event_signups::dsl::{event, event_signups, id as signup};
+    use crate::schema::tables::events::dsl::{events, id as event_id, start_time};
+    Ok(event_reservations
+        .inner_join(event_signups.on(signup.eq(signup_id)))
+        .inner_join(events.on(event_id.eq(event)))
+        .filter(start_time.gt(Utc::now()))
+        .filter(item_id.eq_any(item_ids))
+        .group_by(item_id)
+        .select((item_id, sum(amount)))
+        .load(connection)?
+        .into_iter()
+        .map(|(item, reserved): (InventoryItemId, Option<i64>)| {
+            (item, reserved.unwrap_or(0) as i32)
+        })
+        .collect())
+}
+
+/// The stock of the items of `organization` as it was at `cutoff`, counting the transactions
+/// deleted after it like the `inventory_stock` view counts those which aren't deleted.
+///
+/// The items of compacted transactions are counted by the day, in `tz`, see
+/// [retention](crate::util::retention).
+pub fn load_stock_at(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    cutoff: DateTime<Utc>,
+    tz: Tz,
+) -> QueryResult<HashMap<InventoryItemId, i32>> {
+    let mut stock: HashMap<InventoryItemId, i32> = {
+        use crate::schema::tables::transaction_line_summaries::dsl::*;
+        transaction_line_summaries
+            .filter(organization_id.eq(organization))
+            .filter(day.lt(local_date(cutoff, tz)))
+            .group_by(item_id)
+            .select((item_id, sql::<BigInt>("CAST(SUM(stock_change) AS BIGINT)")))
+            .load(connection)?
             .into_iter()
-            .map(|item: InventoryItemStock| (item.id, item))
-            .collect(),
-    ))
+            .map(|(item, count): (InventoryItemId, i64)| (item, count as i32))
+            .collect()
+    };
+
+    use crate::schema::tables::transaction_bundles::dsl::{
+        change, id as bundle_id, transaction_bundles, transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, item_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::{
+        deleted_at, id, import_batch_id, organization_id, time, transactions,
+    };
+    let lines: Vec<(InventoryItemId, Option<i64>)> = transaction_items
+        .inner_join(transaction_bundles.on(bundle_id.eq(item_bundle_id)))
+        .inner_join(transactions.on(id.eq(transaction_id)))
+        .filter(organization_id.eq(organization))
+        .filter(time.lt(cutoff))
+        .filter(deleted_at.is_null().or(deleted_at.ge(cutoff)))
+        .filter(import_batch_id.is_null())
+        .group_by(item_id)
+        .select((item_id, sum(change)))
+        .load(connection)?;
+    for (item, count) in lines {
+        *stock.entry(item).or_default() += count.unwrap_or(0) as i32;
+    }
+    Ok(stock)
+}
+
+/// PUT `/inventory/item/<item_id>/archived`
+///
+/// Archive or restore an item. Archived items are hidden from the store, but kept in the history.
+#[put("/inventory/item/<item_id>/archived", data = "<archived>")]
+pub fn put_item_archived(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    session: Session,
+    accept: SerAccept,
+    item_id: InventoryItemId,
+    archived: Json<bool>,
+) -> Result<Ser<InventoryItemId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let archived = archived.into_inner().then(Utc::now);
+
+    use crate::schema::tables::inventory::dsl::*;
+    let updated_id = diesel::update(
+        inventory
+            .filter(id.eq(item_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .set(archived_at.eq(archived))
+    .returning(id)
+    .get_result(&connection)?;
+    item_cache.invalidate(session.organization);
+
+    Ok(accept.ser(updated_id))
+}
+
+/// PUT `/inventory/item/<item_id>/age_restricted`
+///
+/// Mark an item as age-restricted, or not. The ID of the buyer must be checked before such items
+/// are sold.
+#[put("/inventory/item/<item_id>/age_restricted", data = "<restricted>")]
+pub fn put_item_age_restricted(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    session: Session,
+    accept: SerAccept,
+    item_id: InventoryItemId,
+    restricted: Json<bool>,
+) -> Result<Ser<InventoryItemId>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::inventory::dsl::*;
+    let updated_id = diesel::update(
+        inventory
+            .filter(id.eq(item_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .set(age_restricted.eq(restricted.into_inner()))
+    .returning(id)
+    .get_result(&connection)?;
+    item_cache.invalidate(session.organization);
+
+    Ok(accept.ser(updated_id))
+}
+
+/// PUT `/inventory/item/<item_id>/prepared`
+///
+/// Mark an item as prepared after payment, or not. Such items are shown on the kitchen board.
+#[put("/inventory/item/<item_id>/prepared", data = "<is_prepared>")]
+pub fn put_item_prepared(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    session: Session,
+    accept: SerAccept,
+    item_id: InventoryItemId,
+    is_prepared: Json<bool>,
+) -> Result<Ser<InventoryItemId>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::inventory::dsl::*;
+    let updated_id = diesel::update(
+        inventory
+            .filter(id.eq(item_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .set(prepared.eq(is_prepared.into_inner()))
+    .returning(id)
+    .get_result(&connection)?;
+    item_cache.invalidate(session.organization);
+
+    Ok(accept.ser(updated_id))
+}
+
+/// PUT `/inventory/item/<item_id>/cost_price`
+///
+/// Set what an item costs to buy, or clear it with `null`. Used to value the stock.
+#[put("/inventory/item/<item_id>/cost_price", data = "<cost>")]
+pub fn put_item_cost_price(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    item_id: InventoryItemId,
+    cost: Json<Option<Currency>>,
+) -> Result<Ser<InventoryItemId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let cost = match cost.into_inner().map(i32::try_from) {
+        None => None,
+        Some(Ok(cost)) if cost >= 0 => Some(cost),
+        _ => return Err(SJ::new(Status::BadRequest, "Invalid cost price")),
+    };
+
+    use crate::schema::tables::inventory::dsl::*;
+    let updated_id = diesel::update(
+        inventory
+            .filter(id.eq(item_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .set(cost_price.eq(cost))
+    .returning(id)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(updated_id))
+}
+
+/// PUT `/inventory/item/<item_id>/low_stock_at`
+///
+/// Warn when this many or fewer of an item are left for sale, or never with `null`, see
+/// [low_stock_alerts](strecklistan_api::location::low_stock_alerts)
+#[put("/inventory/item/<item_id>/low_stock_at", data = "<threshold>")]
+pub fn put_item_low_stock_at(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    item_id: InventoryItemId,
+    threshold: Json<Option<i32>>,
+) -> Result<Ser<InventoryItemId>, SJ> {
+    let threshold = threshold.into_inner();
+    if threshold.map_or(false, |threshold| threshold < 0) {
+        return Err(SJ::new(Status::BadRequest, "Invalid low stock threshold"));
+    }
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::inventory::dsl::*;
+    let updated_id = diesel::update(
+        inventory
+            .filter(id.eq(item_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .set(low_stock_at.eq(threshold))
+    .returning(id)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(updated_id))
+}
+
+/// GET `/inventory/purchase_limits`
+///
+/// How many of the limited items every member may buy per period
+#[get("/inventory/purchase_limits")]
+pub fn get_purchase_limits(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<HashMap<InventoryItemId, PurchaseLimit>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let limits = load_purchase_limits(&connection, session.organization, None)?;
+    Ok(accept.ser(limits))
+}
+
+/// PUT `/inventory/item/<item_id>/purchase_limit`
+///
+/// Limit how many of an item every member may buy per period, or remove the limit with `null`
+#[put("/inventory/item/<item_id>/purchase_limit", data = "<limit>")]
+pub fn put_item_purchase_limit(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+    item_id: InventoryItemId,
+    limit: Json<Option<PurchaseLimit>>,
+) -> Result<Ser<InventoryItemId>, SJ> {
+    let session = admin.0;
+    let limit = limit.into_inner();
+    let limited_quantity = match limit {
+        Some(limit) => match i32::try_from(limit.quantity) {
+            Ok(limited) if limited > 0 => Some(limited),
+            _ => return Err(SJ::new(Status::BadRequest, "Invalid purchase limit")),
+        },
+        None => None,
+    };
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let item_name: Option<String> = {
+            use crate::schema::tables::inventory::dsl::*;
+            inventory
+                .filter(id.eq(item_id))
+                .filter(organization_id.eq(session.organization))
+                .select(name)
+                .first(&connection)
+                .optional()?
+                .ok_or_else(|| SJ::new(Status::NotFound, "No such item"))?
+        };
+
+        {
+            use crate::schema::tables::purchase_limits::dsl::{
+                item_id as limited_item, per, purchase_limits, quantity,
+            };
+            match (limit, limited_quantity) {
+                (Some(limit), Some(limited)) => {
+                    diesel::insert_into(purchase_limits)
+                        .values((
+                            limited_item.eq(item_id),
+                            quantity.eq(limited),
+                            per.eq(limit.per.to_string()),
+                        ))
+                        .on_conflict(limited_item)
+                        .do_update()
+                        .set((quantity.eq(limited), per.eq(limit.per.to_string())))
+                        .execute(&connection)?;
+                }
+                _ => {
+                    diesel::delete(purchase_limits.filter(limited_item.eq(item_id)))
+                        .execute(&connection)?;
+                }
+            }
+        }
+
+        let change = match limit {
+            Some(limit) => format!("purchase limit {} per {}", limit.quantity, limit.per),
+            None => "no purchase limit".to_string(),
+        };
+        audit::record(
+            &connection,
+            Some(session.organization),
+            Some(&session.user),
+            AuditAction::ItemEdited,
+            &format!(
+                "Edited {} (#{}): {}",
+                item_name.as_deref().unwrap_or("?"),
+                item_id,
+                change
+            ),
+        )?;
+        Ok(())
+    })?;
+
+    Ok(accept.ser(item_id))
+}
+
+/// The purchase limits of the items of `organization`, or only of `item_ids`
+pub fn load_purchase_limits(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    item_ids: Option<&[InventoryItemId]>,
+) -> Result<HashMap<InventoryItemId, PurchaseLimit>, SJ> {
+    use crate::schema::tables::inventory::dsl::{id, inventory, organization_id};
+    use crate::schema::tables::purchase_limits::dsl::{item_id, per, purchase_limits, quantity};
+
+    let mut query = purchase_limits
+        .inner_join(inventory.on(id.eq(item_id)))
+        .filter(organization_id.eq(organization))
+        .select((item_id, quantity, per))
+        .into_boxed();
+    if let Some(item_ids) = item_ids {
+        query = query.filter(item_id.eq_any(item_ids));
+    }
+    let rows: Vec<(InventoryItemId, i32, String)> = query.load(connection)?;
+
+    rows.into_iter()
+        .map(|(limited_item, limited, period)| {
+            let limit = PurchaseLimit {
+                quantity: limited as u32,
+                per: period.parse::<PeriodKind>().map_err(|_| {
+                    SJ::new(Status::InternalServerError, "Invalid purchase limit period")
+                })?,
+            };
+            Ok((limited_item, limit))
+        })
+        .collect()
+}
+
+/// PATCH `/inventory/batch`
+///
+/// Change the category, price or archival of several items at once. Either every item is changed
+/// or, if any of them can't be, none of them are. Each changed item gets its own entry in the
+/// audit log.
+#[patch("/inventory/batch", data = "<edit>")]
+pub fn patch_inventory_batch(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    session: Session,
+    accept: SerAccept,
+    edit: Json<InventoryBatchEdit>,
+) -> Result<Ser<Vec<InventoryItemId>>, SJ> {
+    let edit = edit.into_inner();
+    if edit.is_empty() {
+        return Err(SJ::new(Status::BadRequest, "Nothing to change"));
+    }
+    let category = edit.category.as_deref().map(str::trim);
+
+    let connection = db_pool.inner().get()?;
+    let edited = connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::{inventory, inventory_tags};
+
+        let items: Vec<(InventoryItemId, Option<String>, Option<i32>, bool)> = inventory::table
+            .filter(inventory::id.eq_any(&edit.item_ids))
+            .filter(inventory::organization_id.eq(session.organization))
+            .select((
+                inventory::id,
+                inventory::name,
+                inventory::price,
+                inventory::archived_at.is_not_null(),
+            ))
+            .for_update()
+            .load(&connection)?;
+        let requested: HashSet<InventoryItemId> = edit.item_ids.iter().copied().collect();
+        if items.len() != requested.len() {
+            return Err(SJ::new(Status::NotFound, "No such item"));
+        }
+
+        let now = Utc::now();
+        let mut edited = vec![];
+        for (item_id, item_name, item_price, archived) in items {
+            let item_name = item_name.unwrap_or_default();
+            let mut changes = vec![];
+
+            if let Some(category) = category {
+                diesel::delete(inventory_tags::table.filter(inventory_tags::item_id.eq(item_id)))
+                    .execute(&connection)?;
+                if category.is_empty() {
+                    changes.push("removed the category".to_string());
+                } else {
+                    diesel::insert_into(inventory_tags::table)
+                        .values((
+                            inventory_tags::tag.eq(category),
+                            inventory_tags::item_id.eq(item_id),
+                        ))
+                        .execute(&connection)?;
+                    changes.push(format!("category {}", category));
+                }
+            }
+
+            if let (Some(adjustment), Some(old_price)) = (edit.price, item_price) {
+                let new_price = adjustment
+                    .apply(old_price.into())
+                    .and_then(|new_price| i32::try_from(new_price).ok())
+                    .ok_or_else(|| {
+                        SJ::new(
+                            Status::BadRequest,
+                            format!("The new price of {} would be invalid", item_name),
+                        )
+                    })?;
+                diesel::update(inventory::table.filter(inventory::id.eq(item_id)))
+                    .set(inventory::price.eq(new_price))
+                    .execute(&connection)?;
+                changes.push(format!(
+                    "price {}:- → {}:-",
+                    Currency::from(old_price),
+                    Currency::from(new_price)
+                ));
+            }
+
+            if edit.archive && !archived {
+                diesel::update(inventory::table.filter(inventory::id.eq(item_id)))
+                    .set(inventory::archived_at.eq(now))
+                    .execute(&connection)?;
+                changes.push("archived".to_string());
+            }
+
+            if !changes.is_empty() {
+                audit::record(
+                    &connection,
+                    Some(session.organization),
+                    Some(&session.user),
+                    AuditAction::ItemEdited,
+                    &format!(
+                        "Edited {} (#{}): {}",
+                        item_name,
+                        item_id,
+                        changes.join(", ")
+                    ),
+                )?;
+                edited.push(item_id);
+            }
+        }
+        Ok(edited)
+    })?;
+    item_cache.invalidate(session.organization);
+
+    Ok(accept.ser(edited))
 }
 
 #[get("/inventory/tags")]
 pub fn get_tags(
     db_pool: &State<DatabasePool>,
+    session: Session,
     accept: SerAccept,
 ) -> Result<Ser<Vec<InventoryItemTag>>, SJ> {
     let connection = db_pool.inner().get()?;
 
-    use crate::schema::tables::inventory_tags::dsl::inventory_tags;
-    Ok(accept.ser(inventory_tags.load(&connection)?))
+    use crate::schema::tables::inventory::dsl::{id, inventory, organization_id};
+    use crate::schema::tables::inventory_tags::dsl::{inventory_tags, item_id};
+    Ok(accept.ser(
+        inventory_tags
+            .filter(
+                item_id.eq_any(
+                    inventory
+                        .filter(organization_id.eq(session.organization))
+                        .select(id),
+                ),
+            )
+            .load(&connection)?,
+    ))
+}
+
+/// GET `/inventory/barcodes`
+///
+/// A map from barcodes (e.g. EAN-13) to the items they belong to
+#[get("/inventory/barcodes")]
+pub fn get_barcodes(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<HashMap<String, InventoryItemId>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::inventory::dsl::{id, inventory, organization_id};
+    use crate::schema::tables::inventory_barcodes::dsl::{inventory_barcodes, item_id};
+    Ok(accept.ser(
+        inventory_barcodes
+            .filter(
+                item_id.eq_any(
+                    inventory
+                        .filter(organization_id.eq(session.organization))
+                        .select(id),
+                ),
+            )
+            .load::<(String, InventoryItemId)>(&connection)?
+            .into_iter()
+            .collect(),
+    ))
 }
 
 #[get("/inventory/bundles")]
 pub fn get_inventory_bundles(
     db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    session: Session,
     accept: SerAccept,
 ) -> Result<Ser<HashMap<InventoryBundleId, InventoryBundleObj>>, SJ> {
     let connection = db_pool.inner().get()?;
+    let inventory = item_cache.get(&connection, session.organization)?;
+    Ok(accept.ser(inventory.bundles.clone()))
+}
+
+/// GET `/inventory/modifiers`
+///
+/// The variants and add-ons of the items, e.g. the sizes of a coffee, including the archived ones
+#[get("/inventory/modifiers")]
+pub fn get_item_modifiers(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<HashMap<ItemModifierId, ItemModifier>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let inventory = item_cache.get(&connection, session.organization)?;
+    Ok(accept.ser(inventory.modifiers.clone()))
+}
+
+/// GET `/inventory/popular?<days>&<limit>`
+///
+/// The most sold items during the last `days` days (default 30), most sold first. Archived items
+/// are left out.
+#[get("/inventory/popular?<days>&<limit>")]
+pub fn get_popular_items(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    days: Option<u32>,
+    limit: Option<usize>,
+) -> Result<Ser<Vec<InventoryItemId>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let since = Utc::now() - Duration::days(days.unwrap_or(30).into());
+
+    let sold: Vec<(InventoryItemId, i32)> = {
+        use crate::schema::tables::transaction_bundles::dsl::{
+            change, id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
+        };
+        use crate::schema::tables::transaction_items::dsl::{
+            bundle_id as item_bundle_id, item_id, transaction_items,
+        };
+        use crate::schema::tables::transactions::dsl::{
+            deleted_at, id, organization_id, time, transactions,
+        };
+
+        transactions
+            .filter(deleted_at.is_null())
+            .filter(organization_id.eq(session.organization))
+            .filter(time.gt(since))
+            .inner_join(transaction_bundles.on(id.eq(bundle_transaction_id)))
+            .inner_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
+            // a negative change means that the items were sold
+            .filter(change.lt(0))
+            .select((item_id, change))
+            .load(&connection)?
+    };
 
-    use crate::schema::tables::inventory_bundle_items::dsl::{bundle_id, inventory_bundle_items};
-    use crate::schema::tables::inventory_bundles::dsl::{id, inventory_bundles};
+    let archived: HashSet<InventoryItemId> = {
+        use crate::schema::tables::inventory::dsl::*;
+        inventory
+            .filter(organization_id.eq(session.organization))
+            .filter(archived_at.is_not_null())
+            .select(id)
+            .load::<InventoryItemId>(&connection)?
+            .into_iter()
+            .collect()
+    };
 
-    let joined: Vec<(InventoryBundleRel, Option<InventoryBundleItem>)> = inventory_bundles
-        .left_join(inventory_bundle_items.on(bundle_id.eq(id)))
-        .load(&connection)?;
+    let mut counts: HashMap<InventoryItemId, i64> = HashMap::new();
+    for (item, item_change) in sold {
+        if !archived.contains(&item) {
+            *counts.entry(item).or_default() -= i64::from(item_change);
+        }
+    }
 
-    let bundles = joined
+    let popular = counts
         .into_iter()
-        .group_by(|(bundle, _)| bundle.id)
+        .sorted_by(|(id_a, count_a), (id_b, count_b)| count_b.cmp(count_a).then(id_a.cmp(id_b)))
+        .map(|(item, _)| item)
+        .take(limit.unwrap_or(8))
+        .collect();
+
+    Ok(accept.ser(popular))
+}
+
+/// GET `/inventory/expiring?<days>`
+///
+/// The items in stock which expire within `days` days (default 7), or already have expired.
+/// Sorted by expiry date, soonest first.
+#[get("/inventory/expiring?<days>")]
+pub fn get_expiring(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    days: Option<u32>,
+) -> Result<Ser<Vec<ExpiringBatch>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let today = local_date(Utc::now(), config.reporting_timezone);
+    let last_day = today + Duration::days(days.unwrap_or(7).into());
+
+    // every restock of every item, oldest first
+    let restocks: Vec<(InventoryItemId, i32, i32, Option<NaiveDate>)> = {
+        use crate::schema::tables::transaction_bundles::dsl::{
+            change, expires_on, id as bundle_id, transaction_bundles, transaction_id,
+        };
+        use crate::schema::tables::transaction_items::dsl::{
+            bundle_id as item_bundle_id, item_id, transaction_items,
+        };
+        use crate::schema::tables::transactions::dsl::{
+            deleted_at, id, organization_id, time, transactions,
+        };
+        transaction_items
+            .inner_join(transaction_bundles.on(bundle_id.eq(item_bundle_id)))
+            .inner_join(transactions.on(id.eq(transaction_id)))
+            .filter(deleted_at.is_null())
+            .filter(organization_id.eq(session.organization))
+            .filter(change.gt(0))
+            .order_by((time, bundle_id))
+            .select((item_id, bundle_id, change, expires_on))
+            .load(&connection)?
+    };
+
+    // there is one item row for every item in a bundle, so the rows of a bundle are summed
+    let mut batches: HashMap<InventoryItemId, Vec<(i32, Option<NaiveDate>, i32)>> = HashMap::new();
+    for (item, bundle, count, expiry) in restocks {
+        let item_batches = batches.entry(item).or_default();
+        match item_batches.last_mut() {
+            Some((last_bundle, _, size)) if *last_bundle == bundle => *size += count,
+            _ => item_batches.push((bundle, expiry, count)),
+        }
+    }
+
+    let items = load_items(&connection, session.organization)?;
+
+    let expiring = items
         .into_iter()
-        .map(|(_, mut elements)| {
-            let (bundle, item) = elements.next().unwrap();
-            InventoryBundleObj {
-                id: bundle.id,
-                name: bundle.name,
-                price: bundle.price.into(),
-                image_url: bundle.image_url,
-                item_ids: std::iter::once(item)
-                    .chain(elements.map(|(_, item)| item))
-                    .flatten() // Remove None:s
-                    .map(|item| item.item_id)
-                    .collect(),
-            }
+        .filter(|item| item.stock > 0 && item.archived_at.is_none())
+        .flat_map(|item| {
+            let item_batches: Vec<_> = batches
+                .remove(&item.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(_, expiry, size)| (expiry, size))
+                .collect();
+            remaining_batches(item.stock, &item_batches)
+                .into_iter()
+                .filter_map(|(expiry, count)| Some((expiry?, count)))
+                .filter(|&(expiry, _)| expiry <= last_day)
+                .map(move |(expires_on, count)| ExpiringBatch {
+                    item_id: item.id,
+                    name: item.name.clone(),
+                    expires_on,
+                    count,
+                })
+                .collect::<Vec<_>>()
+        })
+        .sorted_by(|a, b| a.expires_on.cmp(&b.expires_on).then(a.name.cmp(&b.name)))
+        .collect();
+
+    Ok(accept.ser(expiring))
+}
+
+/// Check that all items and modifiers in `bundles` exist and belong to `organization`, and that
+/// the modifiers of every bundle belong to its items and may be chosen together
+pub fn check_items(
+    connection: &PgConnection,
+    item_cache: &ItemCache,
+    organization: OrganizationId,
+    bundles: &[TransactionBundle],
+) -> Result<(), SJ> {
+    let all_found = |inventory: &CachedInventory| {
+        bundles.iter().all(|bundle| {
+            bundle
+                .item_ids
+                .keys()
+                .all(|item_id| inventory.items.contains_key(item_id))
+                && bundle
+                    .modifier_ids
+                    .iter()
+                    .all(|modifier_id| inventory.modifiers.contains_key(modifier_id))
+        })
+    };
+
+    // the item may have been added since the items were cached
+    let mut inventory = item_cache.get(connection, organization)?;
+    if !all_found(&inventory) {
+        inventory = item_cache.reload(connection, organization)?;
+        if !all_found(&inventory) {
+            return Err(SJ::new(Status::NotFound, "No such inventory item"));
+        }
+    }
+
+    for bundle in bundles {
+        let modifiers: Vec<&ItemModifier> = bundle
+            .modifier_ids
+            .iter()
+            .filter_map(|modifier_id| inventory.modifiers.get(modifier_id))
+            .collect();
+
+        if !modifiers
+            .iter()
+            .all(|modifier| bundle.item_ids.contains_key(&modifier.item_id))
+        {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "A modifier doesn't belong to any item of its bundle",
+            ));
+        }
+        if !compatible_modifiers(&modifiers) {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "Only one modifier of each group may be chosen",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Check that the cashier has confirmed checking the ID of the buyer, if `bundles` sell any
+/// age-restricted items. Call after [check_items], which makes sure the items are cached.
+pub fn check_age_verified(
+    connection: &PgConnection,
+    item_cache: &ItemCache,
+    organization: OrganizationId,
+    bundles: &[TransactionBundle],
+    age_verified: bool,
+) -> Result<(), SJ> {
+    if age_verified {
+        return Ok(());
+    }
+
+    let inventory = item_cache.get(connection, organization)?;
+    let restricted = bundles
+        .iter()
+        .filter(|bundle| bundle.change < 0)
+        .flat_map(|bundle| bundle.item_ids.keys())
+        .any(|item_id| {
+            inventory
+                .items
+                .get(item_id)
+                .map(|item| item.age_restricted)
+                .unwrap_or(false)
+        });
+
+    if restricted {
+        Err(SJ::new(
+            Status::BadRequest,
+            "The ID of the buyer must be checked before selling age-restricted items",
+        )
+        .with_code(ErrorCode::AgeNotVerified))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that selling `bundles` won't take any item below zero in stock, or into what's reserved
+/// for upcoming events.
+///
+/// The inventory rows of the sold items are locked, so this must be called within the same
+/// database transaction as the one inserting the sale. Otherwise two registers could both sell
+/// the last item.
+pub fn check_stock(
+    connection: &PgConnection,
+    bundles: &[TransactionBundle],
+    policy: NegativeStock,
+) -> Result<(), SJ> {
+    let mut requested: BTreeMap<InventoryItemId, i32> = BTreeMap::new();
+    for bundle in bundles {
+        for (&item_id, &per_bundle) in &bundle.item_ids {
+            *requested.entry(item_id).or_default() -= bundle.change * per_bundle as i32;
+        }
+    }
+    check_available(connection, requested, policy)
+}
+
+/// Check that `requested` of each item can be taken, e.g. sold or reserved, see [check_stock]
+pub fn check_available(
+    connection: &PgConnection,
+    mut requested: BTreeMap<InventoryItemId, i32>,
+    policy: NegativeStock,
+) -> Result<(), SJ> {
+    requested.retain(|_, &mut count| count > 0);
+
+    if requested.is_empty() {
+        return Ok(());
+    }
+    let item_ids: Vec<InventoryItemId> = requested.keys().copied().collect();
+
+    let names: HashMap<InventoryItemId, Option<String>> = {
+        use crate::schema::tables::inventory::dsl::*;
+        inventory
+            .filter(id.eq_any(&item_ids))
+            .select((id, name))
+            .for_update()
+            .load(connection)?
+            .into_iter()
+            .collect()
+    };
+
+    // the inventory_stock view is only refreshed after a transaction is committed, so the stock
+    // has to be computed from the transactions, and the items compacted by the retention jobs
+    let mut stock: HashMap<InventoryItemId, i64> = {
+        use crate::schema::tables::transaction_bundles::dsl::{
+            change, id as bundle_id, transaction_bundles, transaction_id,
+        };
+        use crate::schema::tables::transaction_items::dsl::{
+            bundle_id as item_bundle_id, item_id, transaction_items,
+        };
+        use crate::schema::tables::transactions::dsl::{
+            deleted_at, id, import_batch_id, transactions,
+        };
+        transaction_items
+            .inner_join(transaction_bundles.on(bundle_id.eq(item_bundle_id)))
+            .inner_join(transactions.on(id.eq(transaction_id)))
+            .filter(deleted_at.is_null())
+            // imported sales were made before the stock was counted in the system
+            .filter(import_batch_id.is_null())
+            .filter(item_id.eq_any(&item_ids))
+            .group_by(item_id)
+            .select((item_id, sum(change)))
+            .load(connection)?
+            .into_iter()
+            .map(|(item, count): (i32, Option<i64>)| (item, count.unwrap_or(0)))
+            .collect()
+    };
+    {
+        use crate::schema::tables::transaction_line_summaries::dsl::*;
+        let compacted: Vec<(InventoryItemId, i64)> = transaction_line_summaries
+            .filter(item_id.eq_any(&item_ids))
+            .group_by(item_id)
+            .select((item_id, sql::<BigInt>("CAST(SUM(stock_change) AS BIGINT)")))
+            .load(connection)?;
+        for (item, count) in compacted {
+            *stock.entry(item).or_default() += count;
+        }
+    }
+
+    let reserved = load_reserved(connection, &item_ids)?;
+
+    let shortages: Vec<StockShortage> = requested
+        .into_iter()
+        .map(|(item_id, count)| StockShortage {
+            item_id,
+            name: names.get(&item_id).cloned().flatten().unwrap_or_default(),
+            in_stock: stock.get(&item_id).copied().unwrap_or(0) as i32
+                - reserved.get(&item_id).copied().unwrap_or(0),
+            requested: count,
         })
-        .map(|bundle| (bundle.id, bundle))
+        .filter(|shortage| shortage.in_stock < shortage.requested)
         .collect();
 
-    Ok(accept.ser(bundles))
+    if shortages.is_empty() {
+        return Ok(());
+    }
+
+    match policy {
+        NegativeStock::Reject => Err(SJ::new(Status::Conflict, "Not enough items in stock")
+            .with_code(ErrorCode::StockEmpty)
+            .with_details(&shortages)),
+        NegativeStock::Warn => {
+            for shortage in &shortages {
+                warn!(
+                    "Taking {} of item {} ({}), but only {} can be sold",
+                    shortage.requested, shortage.item_id, shortage.name, shortage.in_stock,
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_item_modifiers() {
+        use strecklistan_api::analytics::ModifierTotals;
+        use strecklistan_api::checkout::{CheckoutQuote, QuoteRequest};
+        use strecklistan_api::inventory::{ItemModifier, ItemModifierId};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, true);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let large = db.modifier(kaffe, Some("Storlek"), "stor", 100.into());
+        let small = db.modifier(kaffe, Some("Storlek"), "liten", (-50).into());
+        let milk = db.modifier(kaffe, None, "mjölk", 0.into());
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let modifiers: HashMap<ItemModifierId, ItemModifier> =
+            get_json(&client, "/api/inventory/modifiers");
+        assert_eq!(modifiers.len(), 3);
+        assert_eq!(modifiers[&small].price_delta, (-50).into());
+
+        let sale = |modifier_ids: Vec<ItemModifierId>| {
+            let mut item_ids = HashMap::new();
+            item_ids.insert(kaffe, 1);
+            NewTransaction {
+                description: None,
+                bundles: vec![TransactionBundle {
+                    description: None,
+                    price: None,
+                    change: -2,
+                    item_ids,
+                    expires_on: None,
+                    modifier_ids,
+                }],
+                debited_account: cash,
+                credited_account: sales,
+                amount: 1200.into(),
+                note: None,
+                tags: vec![],
+                event_code: None,
+                age_verified: false,
+                member_id: None,
+                idempotency_key: None,
+            }
+        };
+
+        let quote: CheckoutQuote = post_json(
+            &client,
+            "/api/checkout/quote",
+            &QuoteRequest {
+                bundles: sale(vec![large, milk]).bundles,
+                tendered: None,
+                cash: false,
+            },
+        );
+        assert_eq!(quote.lines[0].list_price, 600.into());
+        assert_eq!(quote.total, 1200.into());
+
+        // only one size at a time
+        let status = post(&client, "/api/transaction", &sale(vec![large, small]));
+        assert_eq!(status, Status::BadRequest);
+
+        let _: i32 = post_json(&client, "/api/transaction", &sale(vec![large, milk]));
+        let response = client.post("/api/analytics/refresh").dispatch();
+        assert_eq!(response.status(), Status::NoContent);
+
+        let totals: Vec<ModifierTotals> = get_json(&client, "/api/analytics/modifier_totals");
+        let mut sold: Vec<_> = totals.iter().map(|t| (t.modifier_id, t.sold)).collect();
+        sold.sort();
+        assert_eq!(sold, vec![(large, 2), (milk, 2)]);
+    }
+
+    #[test]
+    fn test_age_restricted() {
+        use strecklistan_api::transaction::{TransactionBundle, TransactionDetail};
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let beer = db.item(org, "Öl", Some(2500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let response = client
+            .put(format!("/api/inventory/item/{}/age_restricted", beer))
+            .header(ContentType::JSON)
+            .body("true")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert!(items[&beer].age_restricted);
+
+        let mut item_ids = HashMap::new();
+        item_ids.insert(beer, 1);
+        let mut sale = NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: None,
+                change: -1,
+                item_ids,
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 2500.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        let status = post(&client, "/api/transaction", &sale);
+        assert_eq!(status, Status::BadRequest);
+
+        sale.age_verified = true;
+        let id: i32 = post_json(&client, "/api/transaction", &sale);
+        let detail: TransactionDetail = get_json(&client, &format!("/api/transaction/{}", id));
+        assert!(detail.transaction.age_verified);
+    }
+
+    #[test]
+    fn test_inventory_batch() {
+        use strecklistan_api::inventory::{InventoryBatchEdit, InventoryItemTag, PriceAdjustment};
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        let other_org = db.organization("Other");
+        db.user("tester", "hunter2", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(1000.into()));
+        let te = db.item(org, "Te", Some(800.into()));
+        let kaka = db.item(org, "Kaka", None);
+        let other = db.item(other_org, "Kaffe", Some(1000.into()));
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let patch = |edit: &InventoryBatchEdit| {
+            client
+                .patch("/api/inventory/batch")
+                .header(ContentType::JSON)
+                .body(serde_json::to_string(edit).unwrap())
+                .dispatch()
+        };
+        let prices = || -> HashMap<InventoryItemId, Option<i32>> {
+            let items: HashMap<InventoryItemId, InventoryItemStock> =
+                get_json(&client, "/api/inventory/items");
+            items
+                .into_iter()
+                .map(|(id, item)| (id, item.price))
+                .collect()
+        };
+
+        let mut edit = InventoryBatchEdit {
+            item_ids: vec![kaffe, te, kaka],
+            category: Some("varmt".to_string()),
+            price: Some(PriceAdjustment::Percent(10)),
+            archive: false,
+        };
+        let response = patch(&edit);
+        assert_eq!(response.status(), Status::Ok);
+        let edited: Vec<InventoryItemId> = response.into_json().unwrap();
+        assert_eq!(edited.len(), 3);
+
+        let prices_after = prices();
+        assert_eq!(prices_after[&kaffe], Some(1100));
+        assert_eq!(prices_after[&te], Some(880));
+        assert_eq!(prices_after[&kaka], None);
+        let tags: Vec<InventoryItemTag> = get_json(&client, "/api/inventory/tags");
+        assert_eq!(tags.len(), 3);
+        assert!(tags.iter().all(|t| t.tag == "varmt"));
+
+        let audited: i64 = {
+            use crate::schema::tables::audit_log::dsl::*;
+            audit_log
+                .filter(action.eq("item_edited"))
+                .count()
+                .get_result(&db.conn())
+                .unwrap()
+        };
+        assert_eq!(audited, 3);
+
+        // the price of te can't go below zero, so nothing is changed
+        edit.category = None;
+        edit.price = Some(PriceAdjustment::Amount((-900).into()));
+        edit.archive = true;
+        assert_eq!(patch(&edit).status(), Status::BadRequest);
+        assert_eq!(prices(), prices_after);
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert!(items.values().all(|item| item.archived_at.is_none()));
+
+        // the items of other organizations can't be edited
+        edit.item_ids = vec![kaffe, other];
+        edit.price = None;
+        assert_eq!(patch(&edit).status(), Status::NotFound);
+
+        edit.item_ids = vec![];
+        assert_eq!(patch(&edit).status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_purchase_limits() {
+        use strecklistan_api::approval::GatedAction;
+        use strecklistan_api::error::{ApiError, ErrorCode};
+        use strecklistan_api::inventory::PurchaseLimit;
+        use strecklistan_api::time::PeriodKind;
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        db.user("boss", "hunter3", org, true);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let (_, member_account) = db.member(org, "Testsson");
+
+        let sale = |debited_account, change: i32| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change,
+                item_ids: vec![(kaffe, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account,
+            credited_account: sales,
+            amount: (500 * i64::from(change.abs())).into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let limit = PurchaseLimit {
+            quantity: 2,
+            per: PeriodKind::Day,
+        };
+        let uri = format!("/api/inventory/item/{}/purchase_limit", kaffe);
+
+        let boss = db.client();
+        login(&boss, "boss", "hunter3");
+        let _: i32 = post_json(&boss, "/api/transaction", &sale(cash, 10));
+        let response = boss
+            .put(&uri)
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&Some(limit)).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let response = client
+            .put(&uri)
+            .header(ContentType::JSON)
+            .body("null")
+            .dispatch();
+        assert_eq!(
+            response.status(),
+            Status::Forbidden,
+            "only admins set limits"
+        );
+
+        let limits: HashMap<InventoryItemId, PurchaseLimit> =
+            get_json(&client, "/api/inventory/purchase_limits");
+        assert_eq!(limits[&kaffe], limit);
+
+        let _: i32 = post_json(&client, "/api/transaction", &sale(member_account, -2));
+        let response = client
+            .post("/api/transaction")
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&sale(member_account, -1)).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let error: ApiError<GatedAction> = response.into_json().unwrap();
+        assert_eq!(error.code, ErrorCode::ApprovalRequired);
+        assert_eq!(error.details, Some(GatedAction::PurchaseLimit));
+
+        // only sales to members are limited, and admins may go over the limit
+        let _: i32 = post_json(&client, "/api/transaction", &sale(cash, -3));
+        let _: i32 = post_json(&boss, "/api/transaction", &sale(member_account, -1));
+
+        let response = boss
+            .put(&uri)
+            .header(ContentType::JSON)
+            .body("null")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let _: i32 = post_json(&client, "/api/transaction", &sale(member_account, -1));
+    }
 }
@@ -0,0 +1,549 @@
+use crate::auth::{AdminSession, Session};
+use crate::config::Config;
+use crate::database::item_cache::ItemCache;
+use crate::database::DatabasePool;
+use crate::models::izettle_transaction::{
+    FailedPayment as FailedPaymentRow, IZettleTransaction, TRANSACTION_FAILED,
+    TRANSACTION_IN_PROGRESS,
+};
+use crate::models::transaction::object;
+use crate::routes::rest::izettle::izettle_bridge_poll::{BridgeActivity, PaymentQueue};
+use crate::routes::rest::izettle::izettle_bridge_result::{
+    load_pending_rows, update_izettle_post_transaction, PendingRow,
+};
+use crate::routes::rest::izettle::izettle_transaction::{
+    check_izettle_available, insert_pending_bundles, insert_pending_transaction,
+};
+use crate::routes::rest::live::LiveEvents;
+use crate::util::audit::{self, AuditAction};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use itertools::Itertools;
+use rocket::http::Status;
+use rocket::{get, post, State};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::izettle::{FailedPayment, FailedPaymentId, FailedPaymentStatus};
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::organization::OrganizationId;
+
+/// How many of the resolved failed payments are listed along with the unresolved ones
+const RESOLVED_FAILED_PAYMENTS: i64 = 50;
+
+/// GET `/izettle/failed_payments`
+///
+/// The unresolved failed payments, oldest first, followed by the latest resolved ones
+#[get("/izettle/failed_payments")]
+pub fn get_failed_payments(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<FailedPayment>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::failed_payments::dsl::*;
+
+    let mut rows: Vec<FailedPaymentRow> = failed_payments
+        .filter(organization_id.eq(session.organization))
+        .filter(status.eq(FailedPaymentStatus::Unresolved))
+        .order_by((failed_at.asc(), id.asc()))
+        .load(&connection)?;
+    rows.extend(
+        failed_payments
+            .filter(organization_id.eq(session.organization))
+            .filter(status.ne(FailedPaymentStatus::Unresolved))
+            .order_by((failed_at.desc(), id.desc()))
+            .limit(RESOLVED_FAILED_PAYMENTS)
+            .load::<FailedPaymentRow>(&connection)?,
+    );
+
+    let payments = rows.into_iter().map(to_common).collect::<Result<_, SJ>>()?;
+    Ok(accept.ser(payments))
+}
+
+/// POST `/izettle/failed_payments/<failed_payment_id>/retry`
+///
+/// Send an unresolved failed payment to the bridge again, as a new payment which is followed like
+/// any other. The stock is checked again, but not the purchase limits, since an admin decided to
+/// retry it.
+#[post("/izettle/failed_payments/<failed_payment_id>/retry")]
+#[allow(clippy::too_many_arguments)]
+pub async fn retry_failed_payment(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    item_cache: &State<ItemCache>,
+    activity: &State<BridgeActivity>,
+    queue: &State<PaymentQueue>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    accept: SerAccept,
+    failed_payment_id: FailedPaymentId,
+) -> Result<Ser<FailedPayment>, SJ> {
+    let session = admin.0;
+    session.check_not_training()?;
+    check_izettle_available(config, activity)?;
+    let connection = db_pool.inner().get()?;
+    let organization = session.organization;
+
+    let (payment, new_reference) = connection.transaction::<_, SJ, _>(|| {
+        let payment = resolve(&connection, organization, failed_payment_id)?;
+
+        let transaction = object::NewTransaction {
+            description: payment.description.clone(),
+            bundles: bundles_of_row(&payment)?,
+            debited_account: payment.debited_account,
+            credited_account: payment.credited_account,
+            amount: payment.amount.into(),
+            note: payment.note.clone(),
+            tags: payment.tags.clone(),
+            event_code: payment.event_code.clone(),
+            age_verified: payment.age_verified,
+            member_id: payment.member_id,
+            idempotency_key: None,
+        };
+        let new_reference = insert_pending_transaction(
+            &connection,
+            item_cache,
+            transaction,
+            organization,
+            payment
+                .created_by
+                .clone()
+                .unwrap_or_else(|| session.user.clone()),
+            config.stock.negative_stock,
+        )?;
+
+        {
+            use crate::schema::tables::failed_payments::dsl::*;
+            diesel::update(failed_payments.filter(id.eq(failed_payment_id)))
+                .set((
+                    status.eq(FailedPaymentStatus::Retried),
+                    resolved_by.eq(&session.user),
+                    resolved_at.eq(Utc::now()),
+                    retry_reference.eq(new_reference),
+                ))
+                .execute(&connection)?;
+        }
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&session.user),
+            AuditAction::FailedPaymentRetried,
+            &format!(
+                "{}, retried as payment {}",
+                describe(&payment),
+                new_reference
+            ),
+        )?;
+
+        Ok((
+            load(&connection, organization, failed_payment_id)?,
+            new_reference,
+        ))
+    })?;
+
+    queue.notify(new_reference);
+    live.notify(organization, LiveEvent::FailedPaymentsChanged);
+    Ok(accept.ser(payment))
+}
+
+/// POST `/izettle/failed_payments/<failed_payment_id>/write_off`
+///
+/// Give up on an unresolved failed payment. Nothing is booked, it's only marked as looked at.
+#[post("/izettle/failed_payments/<failed_payment_id>/write_off")]
+pub fn write_off_failed_payment(
+    db_pool: &State<DatabasePool>,
+    live: &State<LiveEvents>,
+    admin: AdminSession,
+    accept: SerAccept,
+    failed_payment_id: FailedPaymentId,
+) -> Result<Ser<FailedPayment>, SJ> {
+    let session = admin.0;
+    let connection = db_pool.inner().get()?;
+    let organization = session.organization;
+
+    let payment = connection.transaction::<_, SJ, _>(|| {
+        let payment = resolve(&connection, organization, failed_payment_id)?;
+        {
+            use crate::schema::tables::failed_payments::dsl::*;
+            diesel::update(failed_payments.filter(id.eq(failed_payment_id)))
+                .set((
+                    status.eq(FailedPaymentStatus::WrittenOff),
+                    resolved_by.eq(&session.user),
+                    resolved_at.eq(Utc::now()),
+                ))
+                .execute(&connection)?;
+        }
+        audit::record(
+            &connection,
+            Some(organization),
+            Some(&session.user),
+            AuditAction::FailedPaymentWrittenOff,
+            &describe(&payment),
+        )?;
+        load(&connection, organization, failed_payment_id)
+    })?;
+
+    live.notify(organization, LiveEvent::FailedPaymentsChanged);
+    Ok(accept.ser(payment))
+}
+
+/// Keep a pending payment which failed, along with why. The caller takes it off the queue of the
+/// bridge.
+pub fn record_failed_payment(
+    connection: &PgConnection,
+    rows: &[PendingRow],
+    failure: &str,
+    has_timed_out: bool,
+) -> Result<FailedPaymentId, SJ> {
+    let pending: &IZettleTransaction = match rows.first() {
+        Some((pending, _, _)) => pending,
+        None => return Err(SJ::new(Status::NotFound, "No such pending payment")),
+    };
+    let pending_bundles = serde_json::to_value(bundles_of(rows))
+        .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+    use crate::schema::tables::failed_payments::dsl::*;
+    Ok(diesel::insert_into(failed_payments)
+        .values((
+            organization_id.eq(pending.organization_id),
+            reference.eq(pending.id),
+            reason.eq(failure),
+            timed_out.eq(has_timed_out),
+            time.eq(pending.time),
+            description.eq(&pending.description),
+            debited_account.eq(pending.debited_account),
+            credited_account.eq(pending.credited_account),
+            amount.eq(pending.amount),
+            note.eq(&pending.note),
+            tags.eq(&pending.tags),
+            created_by.eq(&pending.created_by),
+            event_code.eq(&pending.event_code),
+            age_verified.eq(pending.age_verified),
+            member_id.eq(pending.member_id),
+            bundles.eq(pending_bundles),
+        ))
+        .returning(id)
+        .get_result(connection)?)
+}
+
+/// Move the payments which have been pending for longer than `payments.izettle_timeout_secs` at
+/// `now` to the failed payments. Returns their references, and the organizations they belong to.
+pub fn fail_timed_out(
+    connection: &PgConnection,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> Result<Vec<(OrganizationId, i32)>, SJ> {
+    let timeout_secs = config.payments.izettle_timeout_secs;
+    let started_before = now - Duration::seconds(timeout_secs as i64);
+
+    connection.transaction::<_, SJ, _>(|| {
+        // locked like the outbox, so that several servers sharing a database don't both move them
+        let references: Vec<i32> = {
+            use crate::schema::tables::izettle_transaction::dsl::*;
+            izettle_transaction
+                .filter(time.lt(started_before))
+                .order_by((time.asc(), id.asc()))
+                .select(id)
+                .for_update()
+                .skip_locked()
+                .load(connection)?
+        };
+
+        let mut failed = vec![];
+        for pending_id in references {
+            let rows = load_pending_rows(connection, pending_id)?;
+            let failure = format!("No result from the bridge in {} seconds", timeout_secs);
+            record_failed_payment(connection, &rows, &failure, true)?;
+
+            {
+                use crate::schema::tables::izettle_transaction::dsl::*;
+                diesel::delete(izettle_transaction.filter(id.eq(pending_id)))
+                    .execute(connection)?;
+            }
+            update_izettle_post_transaction(
+                pending_id,
+                TRANSACTION_FAILED.to_string(),
+                None,
+                Some(failure),
+                connection,
+            )?;
+            failed.push((rows[0].0.organization_id, pending_id));
+        }
+
+        Ok(failed)
+    })
+}
+
+/// Put the payment `pending_id` back on the queue of the bridge if it timed out, so that the
+/// result which the bridge reports late is booked like any other. Returns the organization of the
+/// payment, if it was put back.
+pub fn restore_timed_out(
+    connection: &PgConnection,
+    pending_id: i32,
+) -> Result<Option<OrganizationId>, SJ> {
+    let row: Option<FailedPaymentRow> = {
+        use crate::schema::tables::failed_payments::dsl::*;
+        failed_payments
+            .filter(reference.eq(pending_id))
+            .filter(timed_out.eq(true))
+            .filter(status.ne(FailedPaymentStatus::PaidLate))
+            .for_update()
+            .first(connection)
+            .optional()?
+    };
+    let payment = match row {
+        Some(row) => row,
+        None => return Ok(None),
+    };
+    let organization = payment.organization_id;
+
+    {
+        use crate::schema::tables::izettle_transaction::dsl::*;
+        diesel::insert_into(izettle_transaction)
+            .values((
+                id.eq(pending_id),
+                description.eq(&payment.description),
+                time.eq(payment.time),
+                debited_account.eq(payment.debited_account),
+                credited_account.eq(payment.credited_account),
+                amount.eq(payment.amount),
+                note.eq(&payment.note),
+                tags.eq(&payment.tags),
+                created_by.eq(&payment.created_by),
+                organization_id.eq(organization),
+                event_code.eq(&payment.event_code),
+                age_verified.eq(payment.age_verified),
+                member_id.eq(payment.member_id),
+            ))
+            .execute(connection)?;
+    }
+    insert_pending_bundles(connection, pending_id, bundles_of_row(&payment)?)?;
+    update_izettle_post_transaction(
+        pending_id,
+        TRANSACTION_IN_PROGRESS.to_string(),
+        None,
+        None,
+        connection,
+    )?;
+
+    {
+        use crate::schema::tables::failed_payments::dsl::*;
+        diesel::update(failed_payments.filter(id.eq(payment.id)))
+            .set((
+                status.eq(FailedPaymentStatus::PaidLate),
+                resolved_at.eq(Utc::now()),
+            ))
+            .execute(connection)?;
+    }
+    audit::record(
+        connection,
+        Some(organization),
+        None,
+        AuditAction::FailedPaymentPaidLate,
+        &describe(&payment),
+    )?;
+
+    Ok(Some(organization))
+}
+
+/// The bundles of a pending payment, from its joined rows
+fn bundles_of(rows: &[PendingRow]) -> Vec<object::TransactionBundle> {
+    rows.iter()
+        .filter_map(|(_, bundle, item)| bundle.as_ref().map(|bundle| (bundle, item)))
+        .group_by(|(bundle, _)| bundle.id)
+        .into_iter()
+        .map(|(_, rows)| {
+            let rows: Vec<_> = rows.collect();
+            let bundle = rows[0].0;
+            let mut item_ids = HashMap::new();
+            for item in rows.iter().filter_map(|(_, item)| item.as_ref()) {
+                *item_ids.entry(item.item_id).or_insert(0) += 1;
+            }
+            object::TransactionBundle {
+                description: bundle.description.clone(),
+                price: bundle.price.map(|price| price.into()),
+                change: bundle.change,
+                item_ids,
+                expires_on: None,
+                modifier_ids: bundle.modifier_ids.clone(),
+            }
+        })
+        .collect()
+}
+
+/// The bundles stored with a failed payment
+fn bundles_of_row(row: &FailedPaymentRow) -> Result<Vec<object::TransactionBundle>, SJ> {
+    serde_json::from_value(row.bundles.clone()).map_err(|e| {
+        SJ::new(
+            Status::InternalServerError,
+            format!("Stored failed payment is invalid: {}", e),
+        )
+    })
+}
+
+fn to_common(row: FailedPaymentRow) -> Result<FailedPayment, SJ> {
+    FailedPayment::try_from(row).map_err(|e| {
+        SJ::new(
+            Status::InternalServerError,
+            format!("Stored failed payment is invalid: {}", e),
+        )
+    })
+}
+
+fn load(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    failed_payment_id: FailedPaymentId,
+) -> Result<FailedPayment, SJ> {
+    use crate::schema::tables::failed_payments::dsl::*;
+    let row: FailedPaymentRow = failed_payments
+        .filter(id.eq(failed_payment_id))
+        .filter(organization_id.eq(organization))
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such failed payment"))?;
+    to_common(row)
+}
+
+/// Lock an unresolved failed payment to resolve it
+fn resolve(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    failed_payment_id: FailedPaymentId,
+) -> Result<FailedPaymentRow, SJ> {
+    use crate::schema::tables::failed_payments::dsl::*;
+    let row: FailedPaymentRow = failed_payments
+        .filter(id.eq(failed_payment_id))
+        .filter(organization_id.eq(organization))
+        .for_update()
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such failed payment"))?;
+
+    if row.status != FailedPaymentStatus::Unresolved {
+        return Err(SJ::new(
+            Status::Conflict,
+            "The failed payment has already been resolved",
+        ));
+    }
+    Ok(row)
+}
+
+/// E.g. "Failed payment 42: 10:- by cashier, because Declined"
+fn describe(row: &FailedPaymentRow) -> String {
+    format!(
+        "Failed payment {}: {}:- by {}, because {}",
+        row.reference,
+        Currency::from(row.amount),
+        row.created_by.as_deref().unwrap_or("?"),
+        row.reason,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+
+    #[test]
+    fn test_failed_payments() {
+        use crate::routes::rest::izettle::failed_payment::fail_timed_out;
+        use chrono::{Duration, Utc};
+        use serde_json::json;
+        use strecklistan_api::izettle::{FailedPayment, FailedPaymentStatus, IZettlePayment};
+        use strecklistan_api::transaction::Transaction;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        db.user("boss", "hunter3", org, true);
+        let (_, member_account) = db.member(org, "Testsson");
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let boss = db.client();
+        login(&boss, "boss", "hunter3");
+
+        let deposit = json!({
+            "credited_account": member_account,
+            "amount": 20000,
+        });
+        let report = |reference: i32, response: serde_json::Value| {
+            let uri = format!("/api/izettle/bridge/payment_response/{}", reference);
+            post(&client, &uri, &response)
+        };
+        let failed_payments =
+            || -> Vec<FailedPayment> { get_json(&client, "/api/izettle/failed_payments") };
+
+        let declined: i32 = post_json(&client, "/api/izettle/client/deposit", &deposit);
+        let failure = json!({ "type": "TransactionFailed", "reason": "Declined" });
+        assert_eq!(report(declined, failure), Status::Ok);
+
+        let failed = failed_payments();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].reference, declined);
+        assert_eq!(failed[0].reason, "Declined");
+        assert_eq!(failed[0].status, FailedPaymentStatus::Unresolved);
+        assert_eq!(failed[0].created_by.as_deref(), Some("tester"));
+
+        let retry = format!("/api/izettle/failed_payments/{}/retry", failed[0].id);
+        assert_eq!(post(&client, &retry, &()), Status::Forbidden);
+        let retried: FailedPayment = post_json(&boss, &retry, &());
+        assert_eq!(retried.status, FailedPaymentStatus::Retried);
+        assert_eq!(retried.resolved_by.as_deref(), Some("boss"));
+        assert_eq!(post(&boss, &retry, &()), Status::Conflict);
+
+        // the bridge gets the payment again, under a new reference
+        let retry_reference = retried.retry_reference.unwrap();
+        let poll: serde_json::Value = get_json(&client, "/api/izettle/bridge/poll");
+        assert_eq!(poll["id"], json!(retry_reference));
+
+        let late: i32 = post_json(&client, "/api/izettle/client/deposit", &deposit);
+        let timeout = Duration::seconds(db.config.payments.izettle_timeout_secs as i64);
+        let timed_out = fail_timed_out(&db.conn(), &db.config, Utc::now()).unwrap();
+        assert!(timed_out.is_empty());
+        let timed_out = fail_timed_out(&db.conn(), &db.config, Utc::now() + timeout).unwrap();
+        assert_eq!(timed_out, vec![(org, retry_reference), (org, late)]);
+
+        let payment: IZettlePayment =
+            get_json(&client, &format!("/api/izettle/client/poll/{}", late));
+        assert!(matches!(payment, IZettlePayment::Failed { .. }));
+        let poll: serde_json::Value = get_json(&client, "/api/izettle/bridge/poll");
+        assert_eq!(poll["type"], json!("NoPendingTransaction"));
+
+        // the customer was charged after all
+        let paid = json!({ "type": "TransactionPaid", "amount": 20000 });
+        assert_eq!(report(late, paid), Status::Ok);
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].credited_account, member_account);
+
+        let failed = failed_payments();
+        let status_of = |reference: i32| {
+            failed
+                .iter()
+                .find(|payment| payment.reference == reference)
+                .map(|payment| (payment.status, payment.timed_out))
+        };
+        assert_eq!(status_of(late), Some((FailedPaymentStatus::PaidLate, true)));
+        assert_eq!(
+            status_of(retry_reference),
+            Some((FailedPaymentStatus::Unresolved, true))
+        );
+
+        let unresolved = failed
+            .iter()
+            .find(|payment| payment.reference == retry_reference)
+            .unwrap();
+        let write_off = format!("/api/izettle/failed_payments/{}/write_off", unresolved.id);
+        let written_off: FailedPayment = post_json(&boss, &write_off, &());
+        assert_eq!(written_off.status, FailedPaymentStatus::WrittenOff);
+        assert_eq!(post(&boss, &write_off, &()), Status::Conflict);
+
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 1, "nothing is booked for a write-off");
+    }
+}
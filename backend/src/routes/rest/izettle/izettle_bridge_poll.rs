@@ -1,13 +1,25 @@
+use crate::auth::BridgeAuth;
+use crate::config::Config;
+use crate::database::item_cache::ItemCache;
 use crate::database::DatabasePool;
 use crate::diesel::RunQueryDsl;
-use crate::models::izettle_transaction::IZettleTransactionPartial;
+use crate::models::izettle_transaction::{IZettleTransactionLine, IZettleTransactionPartial};
 use crate::schema::tables::izettle_transaction::dsl::izettle_transaction;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::StatusJson;
-use diesel::result::Error;
-use diesel::{ExpressionMethods, QueryDsl, QueryResult};
-use rocket::{get, State};
+use chrono::{DateTime, Duration, Utc};
+use diesel::{
+    ExpressionMethods, NullableExpressionMethods, OptionalExtension, PgConnection, QueryDsl,
+    QueryResult,
+};
+use itertools::Itertools;
+use log::error;
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::sync::broadcast::{self, error::RecvError};
+use rocket::{get, Shutdown, State};
 use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use strecklistan_api::organization::OrganizationId;
 
 #[derive(Serialize)]
 #[serde(tag = "type")]
@@ -16,28 +28,396 @@ pub enum BridgePollResult {
     NoPendingTransaction,
 }
 
-#[get("/izettle/bridge/poll")]
+/// The bridge polls continuously, so it is considered offline if it has been silent this long
+const BRIDGE_OFFLINE_AFTER_SECS: i64 = 60;
+
+/// How often the event streams update the bridge activity, which doesn't touch the database
+pub const ACTIVITY_INTERVAL_SECS: u64 = 5;
+
+/// How many changes an event stream may fall behind before it has to start over
+const QUEUE_CHANGES_CAPACITY: usize = 64;
+
+/// Keeps track of when the bridge was last heard from, for the admin page
+#[derive(Default, Clone)]
+pub struct BridgeActivity {
+    last_poll: Arc<Mutex<Option<DateTime<Utc>>>>,
+}
+
+impl BridgeActivity {
+    pub fn record_poll(&self) {
+        *self.last_poll.lock().unwrap() = Some(Utc::now());
+    }
+
+    pub fn last_poll(&self) -> Option<DateTime<Utc>> {
+        *self.last_poll.lock().unwrap()
+    }
+
+    /// Whether the bridge has stopped polling.
+    ///
+    /// A bridge which hasn't polled since the server started isn't considered offline, since it
+    /// may just not have had the chance yet.
+    pub fn is_offline(&self) -> bool {
+        match self.last_poll() {
+            Some(time) => Utc::now() - time > Duration::seconds(BRIDGE_OFFLINE_AFTER_SECS),
+            None => false,
+        }
+    }
+
+    /// Whether the bridge has been heard from recently
+    pub fn is_online(&self) -> bool {
+        match self.last_poll() {
+            Some(time) => Utc::now() - time <= Duration::seconds(BRIDGE_OFFLINE_AFTER_SECS),
+            None => false,
+        }
+    }
+}
+
+/// Tells the event streams when a pending payment is added or resolved, so that they only query
+/// the table when something has happened.
+///
+/// The table is still the source of truth, a change only means that it's worth looking at again.
+/// Changes only reach the event streams of the same server process.
+///
+/// Unlike everything else, the queue isn't split by organization. There is a single card reader
+/// and bridge token for the whole server, see [poll_for_transaction].
+#[derive(Clone)]
+pub struct PaymentQueue {
+    changes: broadcast::Sender<i32>,
+}
+
+impl Default for PaymentQueue {
+    fn default() -> Self {
+        let (changes, _) = broadcast::channel(QUEUE_CHANGES_CAPACITY);
+        PaymentQueue { changes }
+    }
+}
+
+impl PaymentQueue {
+    /// Announce that the pending payment `id` was added or resolved, once it's committed
+    pub fn notify(&self, id: i32) {
+        // fails if nobody is listening, which is fine
+        let _ = self.changes.send(id);
+    }
+
+    /// The ids of the payments which change from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<i32> {
+        self.changes.subscribe()
+    }
+}
+
+/// GET `/izettle/bridge/poll?<lines>`
+///
+/// The oldest pending payment, if any. With `lines=true`, the description and the lines of the
+/// payment are included, so the bridge can show what's being paid for. Bridges which don't ask
+/// get the bare amount, as before.
+///
+/// If tips are enabled, sales are marked as allowing a tip. Deposits never do.
+///
+/// The bridge serves every organization on the server, so their payments are queued together and
+/// taken oldest first. Each payment keeps the organization it was made in, and its result is only
+/// booked there.
+#[get("/izettle/bridge/poll?<lines>")]
 pub async fn poll_for_transaction(
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    item_cache: &State<ItemCache>,
+    activity: &State<BridgeActivity>,
+    _bridge: BridgeAuth,
     accept: SerAccept,
+    lines: Option<bool>,
 ) -> Result<Ser<BridgePollResult>, StatusJson> {
+    activity.record_poll();
     let connection = db_pool.inner().get()?;
 
-    let transaction_res: QueryResult<IZettleTransactionPartial> = {
-        use crate::schema::tables::izettle_transaction::dsl::{amount, id, time};
+    let transaction: Option<(i32, i64, Option<String>, OrganizationId)> = {
+        use crate::schema::tables::izettle_transaction::dsl::{
+            amount, description, id, organization_id, time,
+        };
 
         izettle_transaction
             .order_by(time.asc())
-            .select((id, amount))
+            .select((id, amount, description, organization_id))
             .first(&connection)
+            .optional()?
     };
 
-    if let Err(Error::NotFound) = transaction_res {
-        return Ok(accept.ser(BridgePollResult::NoPendingTransaction));
-    }
-
     // Potential optimization: This function could sleep for up
     // to a few seconds if there is no pending transaction.
     // This way the latency between the server and the bridge would be lower.
-    Ok(accept.ser(BridgePollResult::PendingPayment(transaction_res?)))
+    let (id, amount, description, organization) = match transaction {
+        Some(transaction) => transaction,
+        None => return Ok(accept.ser(BridgePollResult::NoPendingTransaction)),
+    };
+
+    let mut transaction = IZettleTransactionPartial {
+        id,
+        amount,
+        description: None,
+        lines: None,
+        tip_allowed: config.payments.tips_enabled && is_sale(&connection, id)?,
+    };
+    if lines == Some(true) {
+        transaction.description = description;
+        transaction.lines = Some(load_lines(&connection, item_cache, organization, id)?);
+    }
+
+    Ok(accept.ser(BridgePollResult::PendingPayment(transaction)))
+}
+
+/// GET `/izettle/bridge/events`
+///
+/// Server-sent events for bridges which would rather not poll. A `pending` event with the id of
+/// the oldest pending payment is sent whenever it changes, after which the bridge fetches the
+/// payment from the poll route as usual. The stream is kept open by heartbeats, and an open
+/// stream counts as the bridge being online.
+///
+/// The pending payments are only looked up when the [PaymentQueue] says that they have changed.
+#[get("/izettle/bridge/events")]
+pub fn bridge_events(
+    db_pool: &State<DatabasePool>,
+    activity: &State<BridgeActivity>,
+    queue: &State<PaymentQueue>,
+    _bridge: BridgeAuth,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let db_pool = db_pool.inner().clone();
+    let activity = activity.inner().clone();
+    // subscribe before the first lookup, so that no change is missed in between
+    let mut changes = queue.subscribe();
+    let period = std::time::Duration::from_secs(ACTIVITY_INTERVAL_SECS);
+
+    EventStream! {
+        let mut interval = rocket::tokio::time::interval(period);
+        let mut last_pending = None;
+        let mut changed = true;
+
+        loop {
+            if changed {
+                let db_pool = db_pool.clone();
+                let pending =
+                    rocket::tokio::task::spawn_blocking(move || oldest_pending(&db_pool)).await;
+                let pending = match pending {
+                    Ok(Ok(pending)) => pending,
+                    Ok(Err(e)) => {
+                        // the bridge reconnects, and gets the error then
+                        error!("Checking for pending payments failed: {}", e.description);
+                        break;
+                    }
+                    Err(_) => break,
+                };
+
+                if pending != last_pending {
+                    last_pending = pending;
+                    if let Some(id) = pending {
+                        yield Event::data(id.to_string()).event("pending");
+                    }
+                }
+            }
+
+            changed = rocket::tokio::select! {
+                _ = interval.tick() => {
+                    activity.record_poll();
+                    false
+                },
+                change = changes.recv() => match change {
+                    Ok(_) | Err(RecvError::Lagged(_)) => true,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = &mut shutdown => break,
+            };
+        }
+    }
+}
+
+/// The id of the oldest pending payment, if any
+pub fn oldest_pending(db_pool: &DatabasePool) -> Result<Option<i32>, StatusJson> {
+    use crate::schema::tables::izettle_transaction::dsl::{id, time};
+    let connection = db_pool.get()?;
+    Ok(izettle_transaction
+        .order_by(time.asc())
+        .select(id)
+        .first(&connection)
+        .optional()?)
+}
+
+/// Whether a pending payment is for a sale, rather than for a deposit which has no bundles
+fn is_sale(connection: &PgConnection, transaction: i32) -> QueryResult<bool> {
+    use crate::schema::tables::izettle_transaction_bundle::dsl::*;
+    diesel::select(diesel::dsl::exists(
+        izettle_transaction_bundle.filter(transaction_id.eq(transaction)),
+    ))
+    .get_result(connection)
+}
+
+/// The bundles of a pending payment, named by their items if they have no description
+fn load_lines(
+    connection: &PgConnection,
+    item_cache: &ItemCache,
+    organization: OrganizationId,
+    transaction: i32,
+) -> Result<Vec<IZettleTransactionLine>, StatusJson> {
+    use crate::schema::tables::izettle_transaction_bundle::dsl as bundle;
+    use crate::schema::tables::izettle_transaction_item::dsl as item;
+
+    let joined: Vec<(i32, Option<String>, Option<i64>, i32, Option<i32>)> =
+        bundle::izettle_transaction_bundle
+            .filter(bundle::transaction_id.eq(transaction))
+            .left_join(item::izettle_transaction_item.on(item::bundle_id.eq(bundle::id)))
+            .order_by((bundle::id.asc(), item::item_id.asc()))
+            .select((
+                bundle::id,
+                bundle::description,
+                bundle::price,
+                bundle::change,
+                item::item_id.nullable(),
+            ))
+            .load(connection)?;
+
+    let inventory = item_cache.get(connection, organization)?;
+
+    Ok(joined
+        .into_iter()
+        .group_by(|&(bundle_id, ..)| bundle_id)
+        .into_iter()
+        .map(|(_, rows)| {
+            let rows: Vec<_> = rows.collect();
+            let (_, description, price, change, _) = rows[0].clone();
+            let item_names = rows
+                .iter()
+                .filter_map(|&(.., item_id)| item_id)
+                .unique()
+                .filter_map(|item_id| inventory.items.get(&item_id)?.name.clone())
+                .join(", ");
+
+            IZettleTransactionLine {
+                name: description.or_else(|| Some(item_names).filter(|names| !names.is_empty())),
+                // sales take items out of stock, but the customer is buying a positive amount
+                quantity: -change,
+                unit_price: price,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_bridge_poll_lines() {
+        use crate::config::NegativeStock;
+        use serde_json::{json, Value};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        // nothing has been restocked
+        db.config.stock.negative_stock = NegativeStock::Warn;
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let bank = db.account(org, "Bank", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let mut item_ids = HashMap::new();
+        item_ids.insert(kaffe, 1);
+        let transaction = NewTransaction {
+            description: Some("Fika".to_string()),
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change: -2,
+                item_ids,
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: bank,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let _: i32 = post_json(&client, "/api/izettle/client/transaction", &transaction);
+
+        let bare: Value = get_json(&client, "/api/izettle/bridge/poll");
+        assert_eq!(bare["amount"], json!(1000));
+        assert!(bare.get("lines").is_none());
+
+        let poll: Value = get_json(&client, "/api/izettle/bridge/poll?lines=true");
+        assert_eq!(poll["description"], json!("Fika"));
+        assert_eq!(
+            poll["lines"],
+            json!([{ "name": "Kaffe", "quantity": 2, "unit_price": 500 }])
+        );
+    }
+
+    #[test]
+    fn test_bridge_shared_by_organizations() {
+        use serde_json::json;
+        use strecklistan_api::transaction::Transaction;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        let sister = db.organization("Syster");
+        db.user("tester", "hunter2", org, false);
+        db.user("syster", "hunter2", sister, false);
+        let (_, ours) = db.member(org, "Testsson");
+        let (_, theirs) = db.member(sister, "Systersson");
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let other = db.client();
+        login(&other, "syster", "hunter2");
+
+        let deposit = |account| json!({ "credited_account": account, "amount": 20000 });
+        let first: i32 = post_json(&client, "/api/izettle/client/deposit", &deposit(ours));
+        let second: i32 = post_json(&other, "/api/izettle/client/deposit", &deposit(theirs));
+
+        // one bridge takes the payments of every organization, oldest first
+        let paid = json!({ "type": "TransactionPaid", "amount": 20000 });
+        for reference in [first, second] {
+            let poll: serde_json::Value = get_json(&client, "/api/izettle/bridge/poll");
+            assert_eq!(poll["id"], json!(reference));
+            let uri = format!("/api/izettle/bridge/payment_response/{}", reference);
+            assert_eq!(post(&client, &uri, &paid), Status::Ok);
+        }
+
+        // but each payment is booked in the organization it was made in
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].credited_account, ours);
+        let transactions: Vec<Transaction> = get_json(&other, "/api/transactions");
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].credited_account, theirs);
+    }
+
+    #[test]
+    fn test_bridge_token() {
+        use rocket::http::Header;
+
+        let mut db = TestDb::new();
+        db.config.payments.bridge_token = Some("hemligt".to_string());
+        let client = db.client();
+
+        let poll = |authorization: Option<&'static str>| {
+            let mut request = client.get("/api/izettle/bridge/poll");
+            if let Some(authorization) = authorization {
+                request = request.header(Header::new("Authorization", authorization));
+            }
+            request.dispatch().status()
+        };
+        assert_eq!(poll(None), Status::Unauthorized);
+        assert_eq!(poll(Some("Bearer fel")), Status::Unauthorized);
+        assert_eq!(poll(Some("Bearer hemligt")), Status::Ok);
+    }
 }
@@ -1,31 +1,89 @@
+use crate::auth::BridgeAuth;
+use crate::config::Config;
 use crate::database::DatabasePool;
 use crate::diesel::RunQueryDsl;
 use crate::models::izettle_transaction::{
-    IZettleTransaction, TRANSACTION_CANCELLED, TRANSACTION_FAILED, TRANSACTION_PAID,
+    IZettlePostTransaction, IZettleTransaction, IZettleTransactionBundle, TRANSACTION_CANCELLED,
+    TRANSACTION_FAILED, TRANSACTION_PAID,
 };
 use crate::models::transaction::relational;
 use crate::models::transaction::relational::{
     NewTransaction, NewTransactionBundle, NewTransactionItem,
 };
+use crate::routes::rest::book_account::master_accounts;
+use crate::routes::rest::izettle::failed_payment::{record_failed_payment, restore_timed_out};
+use crate::routes::rest::izettle::izettle_bridge_poll::PaymentQueue;
+use crate::routes::rest::live::LiveEvents;
+use crate::routes::rest::member::mail_receipts;
+use crate::routes::rest::queue::issue_ticket;
+use crate::util::outbox;
 use crate::util::status_json::StatusJson as SJ;
-use diesel::r2d2::{ConnectionManager, PooledConnection};
-use diesel::{Connection, ExpressionMethods, JoinOnDsl, PgConnection, QueryDsl};
+use diesel::{
+    Connection, ExpressionMethods, JoinOnDsl, OptionalExtension, PgConnection, QueryDsl,
+    QueryResult,
+};
 use itertools::Itertools;
 use log::info;
 use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{post, State};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::iter;
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::transaction::TransactionId;
+
+const TIP_DESCRIPTION: &str = "Dricks";
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum PaymentResponse {
-    TransactionPaid,
-    TransactionFailed { reason: String },
+    TransactionPaid {
+        /// Paid on top of the amount, if the customer chose to tip
+        #[serde(default)]
+        tip: Option<i64>,
+
+        /// What the card was charged, not counting the tip. Checked against the payment.
+        ///
+        /// Bridges from before the check didn't send it, so it still parses as optional, but
+        /// such reports are rejected with [ErrorCode::AmountMissing] to tell the bridge to be
+        /// updated rather than failing to parse.
+        #[serde(default)]
+        amount: Option<i64>,
+    },
+    TransactionFailed {
+        reason: String,
+    },
     TransactionCancelled,
 }
 
+impl PaymentResponse {
+    /// The status the payment gets from this response, see `izettle_post_transaction`
+    fn status(&self) -> &'static str {
+        match self {
+            PaymentResponse::TransactionPaid { .. } => TRANSACTION_PAID,
+            PaymentResponse::TransactionFailed { .. } => TRANSACTION_FAILED,
+            PaymentResponse::TransactionCancelled => TRANSACTION_CANCELLED,
+        }
+    }
+}
+
+/// POST `/izettle/bridge/payment_response/<reference>`
+///
+/// The result of the pending payment `reference`.
+///
+/// Reporting the same result again is fine and changes nothing, since the bridge can't know
+/// whether its first report arrived. Reporting another result for a payment which is already
+/// done fails with [ErrorCode::PaymentFinalized], and payments which never existed with
+/// [ErrorCode::UnknownPayment].
+///
+/// Payments reported as paid must say what was charged, and fail with
+/// [ErrorCode::AmountMismatch] if it isn't the amount of the payment. Older bridges which leave
+/// the amount out get [ErrorCode::AmountMissing] and have to be updated before the server is,
+/// since their payments can't be completed in the meantime. Tips are rejected unless
+/// `payments.tips_enabled`, since the bridge was told not to ask for them.
 #[post(
     "/izettle/bridge/payment_response/<reference>",
     data = "<payment_response>"
@@ -34,57 +92,95 @@ pub async fn complete_izettle_transaction(
     reference: i32,
     payment_response: Json<PaymentResponse>,
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    queue: &State<PaymentQueue>,
+    live: &State<LiveEvents>,
+    _bridge: BridgeAuth,
 ) -> Result<SJ, SJ> {
+    if let PaymentResponse::TransactionPaid { tip, amount } = &*payment_response {
+        if tip.is_some_and(|tip| tip < 0) {
+            return Err(SJ::new(Status::BadRequest, "The tip can't be negative"));
+        }
+        if tip.is_some_and(|tip| tip > 0) && !config.payments.tips_enabled {
+            return Err(SJ::new(Status::BadRequest, "Tips are disabled"));
+        }
+        if amount.is_none() {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "The bridge has to say what it charged, it may need to be updated",
+            )
+            .with_code(ErrorCode::AmountMissing));
+        }
+    }
+
     let connection = db_pool.inner().get()?;
 
-    connection.transaction::<_, SJ, _>(|| {
-        let joined: Vec<(
-            IZettleTransaction,
-            Option<relational::TransactionBundle>,
-            Option<relational::TransactionItem>,
-        )> = {
-            use crate::schema::tables::izettle_transaction::dsl::{
-                id as transaction_id, izettle_transaction,
-            };
-            use crate::schema::tables::izettle_transaction_bundle::dsl::{
-                id as bundle_id, izettle_transaction_bundle, transaction_id as bundle_trans_id,
-            };
-            use crate::schema::tables::izettle_transaction_item::dsl::{
-                bundle_id as item_bundle_id, izettle_transaction_item,
-            };
-            izettle_transaction
-                .left_join(izettle_transaction_bundle.on(bundle_trans_id.eq(transaction_id)))
-                .left_join(izettle_transaction_item.on(item_bundle_id.eq(bundle_id)))
-                .filter(transaction_id.eq(reference))
-                .load(&connection)?
-        };
+    // the transactions made by the payment and their queue numbers, to tell the clients about once
+    // they are committed
+    let mut created: Vec<(OrganizationId, TransactionId)> = vec![];
+    let mut issued: Vec<(OrganizationId, TransactionId, i32)> = vec![];
+    // the organization whose failed payments changed, if any
+    let mut failed_changed: Option<OrganizationId> = None;
+
+    let result = connection.transaction::<_, SJ, _>(|| {
+        let mut joined = load_pending_rows(&connection, reference)?;
+
+        // the payment timed out, but the customer was charged after all
+        if joined.is_empty() {
+            if let PaymentResponse::TransactionPaid { .. } = &*payment_response {
+                if let Some(organization) = restore_timed_out(&connection, reference)? {
+                    failed_changed = Some(organization);
+                    joined = load_pending_rows(&connection, reference)?;
+                }
+            }
+        }
 
         let grouped = joined
             .into_iter()
             .group_by(|(transaction, _, _)| transaction.id);
 
-        let (izettle_transaction_id, mut transaction_rows) = match grouped.into_iter().next() {
-            Some(group) => group,
-            None => {
+        let (izettle_transaction_id, transaction_rows) = match grouped.into_iter().next() {
+            Some((id, rows)) => (id, rows.collect::<Vec<_>>()),
+            None => return already_reported(&connection, reference, &payment_response),
+        };
+
+        if let PaymentResponse::TransactionPaid {
+            amount: Some(amount),
+            ..
+        } = &*payment_response
+        {
+            let expected = transaction_rows[0].0.amount;
+            if *amount != expected {
                 return Err(SJ::new(
-                    Status::BadRequest,
-                    format!("No pending transaction with reference {}", reference),
-                ));
+                    Status::Conflict,
+                    format!(
+                        "The bridge charged {} but the payment is for {}",
+                        amount, expected
+                    ),
+                )
+                .with_code(ErrorCode::AmountMismatch)
+                .with_details(&expected));
             }
-        };
+        }
 
         {
             // Delete the transaction from izettle_transaction
             use crate::schema::tables::izettle_transaction::dsl::{
                 id as iz_id, izettle_transaction,
             };
-            diesel::delete(izettle_transaction)
+            let deleted = diesel::delete(izettle_transaction)
                 .filter(iz_id.eq(izettle_transaction_id))
                 .execute(&connection)?;
+
+            // the same result was reported twice at once, and the other report got here first
+            if deleted == 0 {
+                return already_reported(&connection, reference, &payment_response);
+            }
         }
 
+        let mut transaction_rows = transaction_rows.into_iter();
         match &*payment_response {
-            PaymentResponse::TransactionPaid => {
+            PaymentResponse::TransactionPaid { tip, .. } => {
                 // Get all the joined rows for the selected izettle transaction
                 let (izettle_transaction, bundle0, item0) = transaction_rows.next().unwrap();
 
@@ -96,6 +192,16 @@ pub async fn complete_izettle_transaction(
                         debited_account: izettle_transaction.debited_account,
                         credited_account: izettle_transaction.credited_account,
                         amount: izettle_transaction.amount,
+                        note: izettle_transaction.note.clone(),
+                        tags: izettle_transaction.tags.clone(),
+                        created_by: izettle_transaction.created_by.clone(),
+                        // the key only protects the payment while it's pending
+                        idempotency_key: None,
+                        organization_id: izettle_transaction.organization_id,
+                        event_code: izettle_transaction.event_code.clone(),
+                        age_verified: izettle_transaction.age_verified,
+                        device: None,
+                        member_id: izettle_transaction.member_id,
                     };
 
                     use crate::schema::tables::transactions::dsl::*;
@@ -104,6 +210,48 @@ pub async fn complete_izettle_transaction(
                         .returning(id)
                         .get_result(&connection)?
                 };
+                created.push((izettle_transaction.organization_id, new_transaction_id));
+
+                outbox::notify_webhooks(
+                    &connection,
+                    config,
+                    izettle_transaction.organization_id,
+                    "transaction.created",
+                    json!({
+                        "transaction_id": new_transaction_id,
+                        "amount": izettle_transaction.amount,
+                        "debited_account": izettle_transaction.debited_account,
+                        "credited_account": izettle_transaction.credited_account,
+                        "created_by": izettle_transaction.created_by,
+                    }),
+                )?;
+
+                mail_receipts(
+                    &connection,
+                    config,
+                    new_transaction_id,
+                    (
+                        izettle_transaction.debited_account,
+                        izettle_transaction.credited_account,
+                    ),
+                    izettle_transaction.amount.into(),
+                    izettle_transaction.description.as_deref(),
+                )?;
+
+                let queue_number = issue_ticket(
+                    &connection,
+                    config,
+                    izettle_transaction.organization_id,
+                    new_transaction_id,
+                    izettle_transaction.credited_account,
+                )?;
+                if let Some(number) = queue_number {
+                    issued.push((
+                        izettle_transaction.organization_id,
+                        new_transaction_id,
+                        number,
+                    ));
+                }
 
                 // Iterate over all the joined rows for each *bundle* in the transaction
                 let bundles = iter::once((bundle0, item0))
@@ -120,6 +268,8 @@ pub async fn complete_izettle_transaction(
                             description: bundle.description.clone(),
                             price: bundle.price,
                             change: bundle.change,
+                            expires_on: None,
+                            modifier_ids: bundle.modifier_ids.clone(),
                         };
 
                         use crate::schema::tables::transaction_bundles::dsl::*;
@@ -147,6 +297,37 @@ pub async fn complete_izettle_transaction(
                     }
                 }
 
+                // The tip is booked as a transaction of its own, so it isn't counted as sales
+                if let Some(tip) = tip.filter(|&tip| tip > 0) {
+                    let masters =
+                        master_accounts(&connection, izettle_transaction.organization_id)?;
+                    let tip_transaction = NewTransaction {
+                        description: Some(TIP_DESCRIPTION.to_string()),
+                        time: Some(izettle_transaction.time),
+                        debited_account: izettle_transaction.debited_account,
+                        credited_account: masters.tips_account_id,
+                        amount: tip,
+                        note: None,
+                        tags: vec![],
+                        created_by: izettle_transaction.created_by.clone(),
+                        idempotency_key: None,
+                        organization_id: izettle_transaction.organization_id,
+                        event_code: izettle_transaction.event_code.clone(),
+                        age_verified: false,
+                        device: None,
+                        member_id: None,
+                    };
+
+                    let tip_transaction_id = {
+                        use crate::schema::tables::transactions::dsl::*;
+                        diesel::insert_into(transactions)
+                            .values(tip_transaction)
+                            .returning(id)
+                            .get_result(&connection)?
+                    };
+                    created.push((izettle_transaction.organization_id, tip_transaction_id));
+                }
+
                 // Mark the transaction in izettle_transaction as paid
                 update_izettle_post_transaction(
                     izettle_transaction_id,
@@ -161,6 +342,10 @@ pub async fn complete_izettle_transaction(
             PaymentResponse::TransactionFailed { reason } => {
                 info!("IZettle failed due to: {}", reason);
 
+                let rows: Vec<PendingRow> = transaction_rows.collect();
+                record_failed_payment(&connection, &rows, reason, false)?;
+                failed_changed = Some(rows[0].0.organization_id);
+
                 // Mark the transaction as failed
                 update_izettle_post_transaction(
                     izettle_transaction_id,
@@ -185,15 +370,102 @@ pub async fn complete_izettle_transaction(
                 Ok(SJ::new(Status::Ok, "Transaction cancelled"))
             }
         }
-    })
+    })?;
+
+    queue.notify(reference);
+    if let Some(organization) = failed_changed {
+        live.notify(organization, LiveEvent::FailedPaymentsChanged);
+    }
+    for (organization, id) in created {
+        live.notify(organization, LiveEvent::TransactionCreated { id });
+    }
+    for (organization, transaction_id, number) in issued {
+        live.notify(
+            organization,
+            LiveEvent::QueueTicketIssued {
+                transaction_id,
+                number,
+            },
+        );
+    }
+    Ok(result)
+}
+
+/// A pending payment joined with one of its bundles and one of the items of it, as they are
+/// loaded by [load_pending_rows]
+pub type PendingRow = (
+    IZettleTransaction,
+    Option<IZettleTransactionBundle>,
+    Option<relational::TransactionItem>,
+);
+
+/// The pending payment `reference`, with its bundles and items. Empty if it isn't pending.
+pub fn load_pending_rows(
+    connection: &PgConnection,
+    reference: i32,
+) -> QueryResult<Vec<PendingRow>> {
+    use crate::schema::tables::izettle_transaction::dsl::{
+        id as transaction_id, izettle_transaction,
+    };
+    use crate::schema::tables::izettle_transaction_bundle::dsl::{
+        id as bundle_id, izettle_transaction_bundle, transaction_id as bundle_trans_id,
+    };
+    use crate::schema::tables::izettle_transaction_item::dsl::{
+        bundle_id as item_bundle_id, izettle_transaction_item,
+    };
+    izettle_transaction
+        .left_join(izettle_transaction_bundle.on(bundle_trans_id.eq(transaction_id)))
+        .left_join(izettle_transaction_item.on(item_bundle_id.eq(bundle_id)))
+        .filter(transaction_id.eq(reference))
+        .load(connection)
+}
+
+/// Answer a result for a payment which isn't pending, which is fine if it's a repeat of the
+/// result it already has
+fn already_reported(
+    connection: &PgConnection,
+    reference: i32,
+    payment_response: &PaymentResponse,
+) -> Result<SJ, SJ> {
+    use crate::schema::tables::izettle_post_transaction::dsl::{
+        izettle_post_transaction, izettle_transaction_id,
+    };
+    let post_transaction: Option<IZettlePostTransaction> = izettle_post_transaction
+        .filter(izettle_transaction_id.eq(reference))
+        .first(connection)
+        .optional()?;
+
+    match post_transaction {
+        None => Err(SJ::new(
+            Status::NotFound,
+            format!("No payment with reference {}", reference),
+        )
+        .with_code(ErrorCode::UnknownPayment)),
+        Some(post_transaction) if post_transaction.status == payment_response.status() => {
+            info!(
+                "The bridge reported the result of payment {} again",
+                reference
+            );
+            Ok(SJ::new(Status::Ok, "The result was already reported"))
+        }
+        Some(post_transaction) => Err(SJ::new(
+            Status::Conflict,
+            format!(
+                "Payment {} is already done, with the status {}",
+                reference, post_transaction.status
+            ),
+        )
+        .with_code(ErrorCode::PaymentFinalized)
+        .with_details(&post_transaction.status)),
+    }
 }
 
-fn update_izettle_post_transaction(
+pub fn update_izettle_post_transaction(
     izettle_transaction_id: i32,
     status: String,
     transaction_id: Option<i32>,
     error: Option<String>,
-    connection: &PooledConnection<ConnectionManager<PgConnection>>,
+    connection: &PgConnection,
 ) -> Result<(), diesel::result::Error> {
     use crate::schema::tables::izettle_post_transaction::dsl::{
         error as err, izettle_post_transaction, izettle_transaction_id as iz_tran_id,
@@ -207,3 +479,157 @@ fn update_izettle_post_transaction(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_tips() {
+        use crate::config::NegativeStock;
+        use serde_json::{json, Value};
+        use strecklistan_api::book_account::MasterAccounts;
+        use strecklistan_api::report::{DailyReport, MonthlyReport};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        db.config.stock.negative_stock = NegativeStock::Warn;
+        db.config.payments.tips_enabled = true;
+        db.config.payments.fees.izettle_basis_points = 185;
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let masters: MasterAccounts = get_json(&client, "/api/book_accounts/masters");
+        let mut item_ids = HashMap::new();
+        item_ids.insert(kaffe, 1);
+        let transaction = NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change: -1,
+                item_ids,
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: masters.bank_account_id,
+            credited_account: masters.sales_account_id,
+            amount: 500.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let reference: i32 = post_json(&client, "/api/izettle/client/transaction", &transaction);
+
+        let poll: Value = get_json(&client, "/api/izettle/bridge/poll");
+        assert_eq!(poll["tip_allowed"], json!(true));
+
+        let paid = json!({ "type": "TransactionPaid", "tip": 100, "amount": 500 });
+        let uri = format!("/api/izettle/bridge/payment_response/{}", reference);
+        assert_eq!(post(&client, &uri, &paid), Status::Ok);
+
+        let today = chrono::Utc::now().with_timezone(&db.config.reporting_timezone);
+        let uri = format!("/api/report/daily/{}", today.format("%Y-%m-%d"));
+        let report: DailyReport = get_json(&client, &uri);
+        assert_eq!(report.total_sales(), 500.into());
+        assert_eq!(report.total_tips(), 100.into());
+
+        // the fee is charged on the whole card payment, tip included
+        assert_eq!(report.total_fees(), 11.into());
+        assert_eq!(report.fees[0].count, 1);
+
+        let uri = format!("/api/report/monthly/{}", today.format("%Y-%m"));
+        let report: MonthlyReport = get_json(&client, &uri);
+        assert_eq!(report.total_fees(), 11.into());
+        assert_eq!(report.days.len(), 1);
+
+        // the bridge isn't asked for tips when they are disabled, so it mustn't report any
+        db.config.payments.tips_enabled = false;
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let reference: i32 = post_json(&client, "/api/izettle/client/transaction", &transaction);
+        let poll: Value = get_json(&client, "/api/izettle/bridge/poll");
+        assert_eq!(poll["tip_allowed"], json!(false));
+
+        let uri = format!("/api/izettle/bridge/payment_response/{}", reference);
+        assert_eq!(post(&client, &uri, &paid), Status::BadRequest);
+        let paid = json!({ "type": "TransactionPaid", "amount": 500 });
+        assert_eq!(post(&client, &uri, &paid), Status::Ok);
+    }
+
+    #[test]
+    fn test_bridge_result() {
+        use serde_json::json;
+        use strecklistan_api::book_account::MasterAccounts;
+        use strecklistan_api::error::{ApiError, ErrorCode};
+        use strecklistan_api::transaction::Transaction;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let masters: MasterAccounts = get_json(&client, "/api/book_accounts/masters");
+        let (_, member_account) = db.member(org, "Testsson");
+        let deposit = json!({
+            "credited_account": member_account,
+            "amount": 20000,
+        });
+        let reference: i32 = post_json(&client, "/api/izettle/client/deposit", &deposit);
+        let uri = format!("/api/izettle/bridge/payment_response/{}", reference);
+
+        let report = |uri: &str, response: serde_json::Value| {
+            let response = client
+                .post(uri)
+                .header(ContentType::JSON)
+                .body(response.to_string())
+                .dispatch();
+            let status = response.status();
+            let error: Option<ApiError<serde_json::Value>> = response.into_json();
+            (status, error.map(|error| error.code))
+        };
+
+        let paid = |amount: i64| json!({ "type": "TransactionPaid", "amount": amount });
+
+        // bridges from before the amount was checked are told to update
+        assert_eq!(
+            report(&uri, json!({ "type": "TransactionPaid" })),
+            (Status::BadRequest, Some(ErrorCode::AmountMissing))
+        );
+        assert_eq!(
+            report(&uri, paid(10000)),
+            (Status::Conflict, Some(ErrorCode::AmountMismatch))
+        );
+        assert_eq!(report(&uri, paid(20000)).0, Status::Ok);
+
+        // the bridge may not have heard the answer, and reports again
+        assert_eq!(report(&uri, paid(20000)).0, Status::Ok);
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].debited_account, masters.bank_account_id);
+
+        assert_eq!(
+            report(&uri, json!({ "type": "TransactionCancelled" })),
+            (Status::Conflict, Some(ErrorCode::PaymentFinalized))
+        );
+        assert_eq!(
+            report(
+                "/api/izettle/bridge/payment_response/-1",
+                json!({ "type": "TransactionCancelled" })
+            ),
+            (Status::NotFound, Some(ErrorCode::UnknownPayment))
+        );
+    }
+}
@@ -1,78 +1,309 @@
+use crate::auth::Session;
+use crate::config::{Config, NegativeStock};
+use crate::database::item_cache::ItemCache;
 use crate::database::DatabasePool;
 use crate::models::izettle_transaction::{
     NewIZettlePostTransaction, NewIZettleTransaction, NewIZettleTransactionBundle,
-    NewIZettleTransactionItem, TRANSACTION_IN_PROGRESS,
+    NewIZettleTransactionItem, TRANSACTION_CANCELLED, TRANSACTION_IN_PROGRESS,
 };
 use crate::models::transaction::object;
+use crate::routes::rest::book_account::{check_accounts, master_accounts};
+use crate::routes::rest::inventory::{check_age_verified, check_items, check_stock};
+use crate::routes::rest::izettle::izettle_bridge_poll::{BridgeActivity, PaymentQueue};
+use crate::routes::rest::izettle::izettle_transaction_poll::load_payment;
+use crate::routes::rest::transaction::{check_purchase_limits, resolve_member};
+use crate::util::approval::Gate;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
-use diesel::{Connection, RunQueryDsl};
+use diesel::{
+    Connection, ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, QueryResult,
+    RunQueryDsl,
+};
+use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::{post, State};
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::izettle::{IZettleDeposit, IZettlePayment};
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::user::UserName;
+use strecklistan_api::validation::Validate;
+
+const DEPOSIT_DESCRIPTION: &str = "Insättning";
 
+/// POST `/izettle/client/transaction`
+///
+/// Begin a card payment for a sale. The sale may be tagged with a member like those posted to
+/// `/transaction`, and then it counts towards their purchase limits.
 #[post("/izettle/client/transaction", data = "<transaction>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn begin_izettle_transaction(
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    item_cache: &State<ItemCache>,
+    activity: &State<BridgeActivity>,
+    queue: &State<PaymentQueue>,
+    session: Session,
+    gate: Gate<'_>,
     accept: SerAccept,
     transaction: Json<object::NewTransaction>,
 ) -> Result<Ser<i32>, SJ> {
+    session.check_not_training()?;
+    check_izettle_available(config, activity)?;
+    transaction.validate()?;
+    let connection = db_pool.inner().get()?;
+
+    let mut transaction = transaction.into_inner();
+    transaction.member_id = resolve_member(
+        &connection,
+        session.organization,
+        transaction.debited_account,
+        transaction.member_id,
+    )?;
+    check_purchase_limits(
+        &connection,
+        config,
+        &gate,
+        &session,
+        transaction.member_id,
+        &transaction.bundles,
+    )?;
+
+    let reference = insert_pending_transaction(
+        &connection,
+        item_cache,
+        transaction,
+        session.organization,
+        session.user,
+        config.stock.negative_stock,
+    )?;
+    queue.notify(reference);
+    Ok(accept.ser(reference))
+}
+
+/// POST `/izettle/client/deposit`
+///
+/// Begin a card payment for a tillgodo deposit.
+///
+/// The member's balance is only credited once the bridge reports the payment as completed, since
+/// the transaction isn't created until then.
+#[post("/izettle/client/deposit", data = "<deposit>")]
+pub async fn begin_izettle_deposit(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    item_cache: &State<ItemCache>,
+    activity: &State<BridgeActivity>,
+    queue: &State<PaymentQueue>,
+    session: Session,
+    accept: SerAccept,
+    deposit: Json<IZettleDeposit>,
+) -> Result<Ser<i32>, SJ> {
+    session.check_not_training()?;
+    check_izettle_available(config, activity)?;
+    deposit.validate()?;
     let connection = db_pool.inner().get()?;
 
+    let IZettleDeposit {
+        credited_account,
+        amount,
+    } = deposit.into_inner();
+
+    let creditor: Option<Option<i32>> = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(id.eq(credited_account))
+            .filter(organization_id.eq(session.organization))
+            .select(creditor)
+            .first(&connection)
+            .optional()?
+    };
+
+    match creditor {
+        None => Err(SJ::new(Status::NotFound, "No such book account")),
+        Some(None) => Err(SJ::new(
+            Status::BadRequest,
+            "Deposits can only be made to tillgodo accounts",
+        )
+        .with_code(ErrorCode::NotAMemberAccount)),
+        Some(Some(_)) => {
+            let transaction = object::NewTransaction {
+                description: Some(DEPOSIT_DESCRIPTION.to_string()),
+                bundles: vec![],
+                debited_account: master_accounts(&connection, session.organization)?
+                    .bank_account_id,
+                credited_account,
+                amount: amount.into(),
+                note: None,
+                tags: vec![],
+                event_code: None,
+                age_verified: false,
+                member_id: None,
+                idempotency_key: None,
+            };
+            let reference = insert_pending_transaction(
+                &connection,
+                item_cache,
+                transaction,
+                session.organization,
+                session.user,
+                config.stock.negative_stock,
+            )?;
+            queue.notify(reference);
+            Ok(accept.ser(reference))
+        }
+    }
+}
+
+/// POST `/izettle/client/cancel/<izettle_transaction_id>`
+///
+/// Give up on a pending card payment, e.g. to take cash instead. The payment is taken off the
+/// queue of the bridge and marked as cancelled, and the new state of it is returned. If the bridge
+/// got there first, the payment may already be paid, and then that's what is returned.
+///
+/// A payment which is already on the reader must be cancelled on the reader as well, or the
+/// bridge may still charge the card.
+#[post("/izettle/client/cancel/<izettle_transaction_id>")]
+pub async fn cancel_izettle_transaction(
+    db_pool: &State<DatabasePool>,
+    queue: &State<PaymentQueue>,
+    session: Session,
+    accept: SerAccept,
+    izettle_transaction_id: i32,
+) -> Result<Ser<IZettlePayment>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let payment = connection.transaction::<_, SJ, _>(|| {
+        let deleted = {
+            use crate::schema::tables::izettle_transaction::dsl::*;
+            diesel::delete(
+                izettle_transaction
+                    .filter(id.eq(izettle_transaction_id))
+                    .filter(organization_id.eq(session.organization)),
+            )
+            .execute(&connection)?
+        };
+
+        if deleted > 0 {
+            use crate::schema::tables::izettle_post_transaction::dsl::{
+                izettle_post_transaction, izettle_transaction_id as iz_id, status,
+            };
+            diesel::update(izettle_post_transaction)
+                .filter(iz_id.eq(izettle_transaction_id))
+                .set(status.eq(TRANSACTION_CANCELLED))
+                .execute(&connection)?;
+        }
+
+        load_payment(&connection, izettle_transaction_id)
+    })?;
+
+    queue.notify(izettle_transaction_id);
+    Ok(accept.ser(payment))
+}
+
+/// Payments which the bridge won't pick up are rejected rather than left pending forever
+pub fn check_izettle_available(config: &Config, activity: &BridgeActivity) -> Result<(), SJ> {
+    if !config.payments.izettle_enabled {
+        Err(
+            SJ::new(Status::ServiceUnavailable, "iZettle payments are disabled")
+                .with_code(ErrorCode::IZettleDisabled),
+        )
+    } else if activity.is_offline() {
+        Err(
+            SJ::new(Status::ServiceUnavailable, "The iZettle bridge is offline")
+                .with_code(ErrorCode::BridgeOffline),
+        )
+    } else {
+        Ok(())
+    }
+}
+
+/// Store a transaction which is waiting for the iZettle bridge, returns the reference for polling.
+///
+/// The stock is checked before the payment is started, since a paid sale can't be rejected.
+pub fn insert_pending_transaction(
+    connection: &PgConnection,
+    item_cache: &ItemCache,
+    transaction: object::NewTransaction,
+    organization: OrganizationId,
+    created_by: UserName,
+    negative_stock: NegativeStock,
+) -> Result<i32, SJ> {
     let object::NewTransaction {
         description,
         bundles,
         debited_account,
         credited_account,
         amount,
-    } = transaction.into_inner();
+        note,
+        tags,
+        event_code,
+        age_verified,
+        member_id,
+        idempotency_key,
+    } = transaction;
 
+    let annotation = object::TransactionAnnotation {
+        note,
+        tags,
+        event_code,
+    }
+    .normalized();
     let transaction = NewIZettleTransaction {
         description,
         time: None,
         debited_account,
         credited_account,
         amount: amount.into(),
+        note: annotation.note,
+        tags: annotation.tags,
+        created_by: Some(created_by),
+        idempotency_key,
+        organization_id: organization,
+        event_code: annotation.event_code,
+        age_verified,
+        member_id,
     };
 
     connection.transaction::<_, SJ, _>(|| {
-        let transactions_id = {
+        // the payment has already been started, keep polling the same one
+        if let Some(existing) = find_started(connection, organization, &transaction)? {
+            return Ok(existing);
+        }
+
+        check_accounts(
+            connection,
+            organization,
+            &[transaction.debited_account, transaction.credited_account],
+        )?;
+        check_items(connection, item_cache, organization, &bundles)?;
+        check_age_verified(
+            connection,
+            item_cache,
+            organization,
+            &bundles,
+            transaction.age_verified,
+        )?;
+        check_stock(connection, &bundles, negative_stock)?;
+
+        let transactions_id: Option<i32> = {
             use crate::schema::tables::izettle_transaction::dsl::*;
             diesel::insert_into(izettle_transaction)
-                .values(transaction)
+                .values(&transaction)
+                .on_conflict_do_nothing()
                 .returning(id)
-                .get_result(&connection)?
+                .get_result(connection)
+                .optional()?
         };
 
-        for bundle in bundles.into_iter() {
-            let new_bundle = NewIZettleTransactionBundle {
-                transaction_id: transactions_id,
-                description: bundle.description,
-                price: bundle.price.map(|p| p.into()),
-                change: bundle.change,
-            };
-
-            let bundle_id = {
-                use crate::schema::tables::izettle_transaction_bundle::dsl::*;
-                diesel::insert_into(izettle_transaction_bundle)
-                    .values(&new_bundle)
-                    .returning(id)
-                    .get_result(&connection)?
-            };
-
-            let item_ids: Vec<_> = bundle
-                .item_ids
-                .into_iter()
-                .flat_map(|(item_id, count)| std::iter::repeat(item_id).take(count as usize))
-                .map(|item_id| NewIZettleTransactionItem { bundle_id, item_id })
-                .collect();
-
-            {
-                use crate::schema::tables::izettle_transaction_item::dsl::*;
-                diesel::insert_into(izettle_transaction_item)
-                    .values(&item_ids)
-                    .execute(&connection)?;
+        // the same key was started concurrently, and that request got to insert it first
+        let transactions_id = match transactions_id {
+            Some(transactions_id) => transactions_id,
+            None => {
+                return find_started(connection, organization, &transaction)?
+                    .ok_or_else(|| SJ::new(Status::Conflict, "The payment already exists"))
             }
-        }
+        };
+
+        insert_pending_bundles(connection, transactions_id, bundles)?;
 
         {
             let post_tran: NewIZettlePostTransaction = NewIZettlePostTransaction {
@@ -85,9 +316,119 @@ pub async fn begin_izettle_transaction(
             use crate::schema::tables::izettle_post_transaction::dsl::*;
             diesel::insert_into(izettle_post_transaction)
                 .values(post_tran)
-                .execute(&connection)?;
+                .execute(connection)?;
         }
 
-        Ok(accept.ser(transactions_id))
+        Ok(transactions_id)
     })
 }
+
+/// The id of the payment which was already started with the idempotency key of `transaction`
+fn find_started(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    transaction: &NewIZettleTransaction,
+) -> QueryResult<Option<i32>> {
+    use crate::schema::tables::izettle_transaction::dsl::*;
+    match &transaction.idempotency_key {
+        Some(key) => izettle_transaction
+            .filter(organization_id.eq(organization))
+            .filter(idempotency_key.eq(key))
+            .select(id)
+            .first(connection)
+            .optional(),
+        None => Ok(None),
+    }
+}
+
+/// Store the bundles of a transaction which is waiting for the iZettle bridge
+pub fn insert_pending_bundles(
+    connection: &PgConnection,
+    pending_id: i32,
+    bundles: Vec<object::TransactionBundle>,
+) -> QueryResult<()> {
+    for bundle in bundles.into_iter() {
+        let new_bundle = NewIZettleTransactionBundle {
+            transaction_id: pending_id,
+            description: bundle.description,
+            price: bundle.price.map(|p| p.into()),
+            change: bundle.change,
+            modifier_ids: bundle.modifier_ids,
+        };
+
+        let bundle_id = {
+            use crate::schema::tables::izettle_transaction_bundle::dsl::*;
+            diesel::insert_into(izettle_transaction_bundle)
+                .values(&new_bundle)
+                .returning(id)
+                .get_result(connection)?
+        };
+
+        let item_ids: Vec<_> = bundle
+            .item_ids
+            .into_iter()
+            .flat_map(|(item_id, count)| std::iter::repeat(item_id).take(count as usize))
+            .map(|item_id| NewIZettleTransactionItem { bundle_id, item_id })
+            .collect();
+
+        {
+            use crate::schema::tables::izettle_transaction_item::dsl::*;
+            diesel::insert_into(izettle_transaction_item)
+                .values(&item_ids)
+                .execute(connection)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+
+    #[test]
+    fn test_cancel_izettle() {
+        use serde_json::json;
+        use strecklistan_api::izettle::IZettlePayment;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let (_, member_account) = db.member(org, "Testsson");
+        let deposit = json!({
+            "credited_account": member_account,
+            "amount": 20000,
+        });
+        let cancelled: i32 = post_json(&client, "/api/izettle/client/deposit", &deposit);
+        let paid: i32 = post_json(&client, "/api/izettle/client/deposit", &deposit);
+
+        let cancel = |reference: i32| -> IZettlePayment {
+            post_json(
+                &client,
+                &format!("/api/izettle/client/cancel/{}", reference),
+                &json!(null),
+            )
+        };
+        assert_eq!(cancel(cancelled), IZettlePayment::Cancelled);
+        assert_eq!(cancel(cancelled), IZettlePayment::Cancelled);
+
+        // the bridge no longer sees the cancelled payment
+        let poll: serde_json::Value = get_json(&client, "/api/izettle/bridge/poll");
+        assert_eq!(poll["id"], json!(paid));
+
+        let response = client
+            .post(format!("/api/izettle/bridge/payment_response/{}", paid))
+            .header(ContentType::JSON)
+            .body(json!({ "type": "TransactionPaid", "amount": 20000 }).to_string())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        // too late, the payment went through
+        assert!(matches!(cancel(paid), IZettlePayment::Paid { .. }));
+    }
+}
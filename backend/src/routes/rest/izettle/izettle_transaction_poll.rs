@@ -1,17 +1,23 @@
+use crate::auth::Session;
 use crate::database::DatabasePool;
 use crate::diesel::RunQueryDsl;
 use crate::models::izettle_transaction::{
     IZettlePostTransaction, TRANSACTION_CANCELLED, TRANSACTION_FAILED, TRANSACTION_IN_PROGRESS,
     TRANSACTION_PAID,
 };
+use crate::routes::rest::izettle::izettle_bridge_poll::{
+    oldest_pending, BridgeActivity, PaymentQueue, ACTIVITY_INTERVAL_SECS,
+};
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::StatusJson;
-use diesel::{ExpressionMethods, QueryDsl};
+use diesel::{ExpressionMethods, PgConnection, QueryDsl};
 use log::error;
 use rocket::http::Status;
-use rocket::{get, State};
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{get, Shutdown, State};
 use serde::Serialize;
-use strecklistan_api::izettle::IZettlePayment;
+use strecklistan_api::izettle::{IZettlePayment, IZettlePaymentStatus};
 
 #[derive(Clone, Serialize)]
 pub struct IZettleResult {
@@ -25,7 +31,98 @@ pub async fn poll_for_izettle(
     izettle_transaction_id: i32,
 ) -> Result<Ser<IZettlePayment>, StatusJson> {
     let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_payment(&connection, izettle_transaction_id)?))
+}
+
+/// GET `/izettle/client/events/<izettle_transaction_id>`
+///
+/// Server-sent events for the store page while it waits for a card payment. A `status` event with
+/// an [IZettlePaymentStatus] is sent whenever the payment, or the state of the bridge, changes.
+/// The stream ends once the payment is no longer pending.
+///
+/// The payment is only looked up when the [PaymentQueue] says that the pending payments have
+/// changed, since that may also move it to the reader.
+#[get("/izettle/client/events/<izettle_transaction_id>")]
+pub fn payment_events(
+    db_pool: &State<DatabasePool>,
+    activity: &State<BridgeActivity>,
+    queue: &State<PaymentQueue>,
+    _session: Session,
+    mut shutdown: Shutdown,
+    izettle_transaction_id: i32,
+) -> EventStream![] {
+    let db_pool = db_pool.inner().clone();
+    let activity = activity.inner().clone();
+    // subscribe before the first lookup, so that no change is missed in between
+    let mut changes = queue.subscribe();
+    let period = std::time::Duration::from_secs(ACTIVITY_INTERVAL_SECS);
+
+    EventStream! {
+        let mut interval = rocket::tokio::time::interval(period);
+        let mut last_status = None;
+        let mut payment = (IZettlePayment::Pending, false);
+        let mut changed = true;
+
+        loop {
+            if changed {
+                let db_pool = db_pool.clone();
+                let loaded = rocket::tokio::task::spawn_blocking(move || {
+                    payment_state(&db_pool, izettle_transaction_id)
+                })
+                .await;
+                payment = match loaded {
+                    Ok(Ok(loaded)) => loaded,
+                    Ok(Err(e)) => {
+                        // the store page falls back to polling
+                        error!("Checking the status of a payment failed: {}", e.description);
+                        break;
+                    }
+                    Err(_) => break,
+                };
+            }
+
+            let status = IZettlePaymentStatus {
+                payment: payment.0.clone(),
+                bridge_online: activity.is_online(),
+                at_reader: payment.1,
+            };
+            if last_status.as_ref() != Some(&status) {
+                let done = status.payment != IZettlePayment::Pending;
+                yield Event::json(&status).event("status");
+                if done {
+                    break;
+                }
+                last_status = Some(status);
+            }
 
+            changed = rocket::tokio::select! {
+                _ = interval.tick() => false,
+                change = changes.recv() => match change {
+                    Ok(_) | Err(RecvError::Lagged(_)) => true,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = &mut shutdown => break,
+            };
+        }
+    }
+}
+
+/// The state of a payment, and whether it's the one on the reader
+fn payment_state(
+    db_pool: &DatabasePool,
+    izettle_transaction_id: i32,
+) -> Result<(IZettlePayment, bool), StatusJson> {
+    let payment = load_payment(&db_pool.get()?, izettle_transaction_id)?;
+    let at_reader = payment == IZettlePayment::Pending
+        && oldest_pending(db_pool)? == Some(izettle_transaction_id);
+    Ok((payment, at_reader))
+}
+
+/// The state of a card payment, as the store page sees it
+pub fn load_payment(
+    connection: &PgConnection,
+    izettle_transaction_id: i32,
+) -> Result<IZettlePayment, StatusJson> {
     let post_izettle_transaction: Result<IZettlePostTransaction, diesel::result::Error> = {
         use crate::schema::tables::izettle_post_transaction::dsl::{
             izettle_post_transaction, izettle_transaction_id as iz_id,
@@ -33,13 +130,13 @@ pub async fn poll_for_izettle(
 
         izettle_post_transaction
             .filter(iz_id.eq(izettle_transaction_id))
-            .first(&connection)
+            .first(connection)
     };
 
     match post_izettle_transaction {
-        Err(diesel::result::Error::NotFound) => Ok(accept.ser(IZettlePayment::NoTransaction)),
+        Err(diesel::result::Error::NotFound) => Ok(IZettlePayment::NoTransaction),
         Ok(IZettlePostTransaction { status, .. }) if status == TRANSACTION_IN_PROGRESS => {
-            Ok(accept.ser(IZettlePayment::Pending))
+            Ok(IZettlePayment::Pending)
         }
         Ok(IZettlePostTransaction {
             status,
@@ -53,23 +150,23 @@ pub async fn poll_for_izettle(
                 );
                 StatusJson::new(Status::InternalServerError, "Internal Server Error")
             })?;
-            Ok(accept.ser(IZettlePayment::Paid { transaction_id }))
+            Ok(IZettlePayment::Paid { transaction_id })
         }
         Ok(IZettlePostTransaction { status, .. }) if status == TRANSACTION_CANCELLED => {
-            Ok(accept.ser(IZettlePayment::Cancelled))
+            Ok(IZettlePayment::Cancelled)
         }
         Ok(IZettlePostTransaction { status, error, .. }) if status == TRANSACTION_FAILED => {
-            Ok(accept.ser(IZettlePayment::Failed {
+            Ok(IZettlePayment::Failed {
                 reason: error.unwrap_or_else(|| "Unknown error".to_string()),
-            }))
+            })
         }
         Err(err) => Err(err.into()),
-        Ok(transaction) => Err(StatusJson {
-            status: Status::new(500),
-            description: format!(
+        Ok(transaction) => Err(StatusJson::new(
+            Status::new(500),
+            format!(
                 "Invalid status {}, perhaps add it to the match.",
                 transaction.status
             ),
-        }),
+        )),
     }
 }
@@ -1,3 +1,4 @@
+pub mod failed_payment;
 pub mod izettle_bridge_poll;
 pub mod izettle_bridge_result;
 pub mod izettle_transaction;
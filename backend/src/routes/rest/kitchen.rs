@@ -0,0 +1,263 @@
+use crate::auth::Session;
+use crate::database::item_cache::ItemCache;
+use crate::database::DatabasePool;
+use crate::routes::rest::book_account::master_accounts;
+use crate::routes::rest::live::LiveEvents;
+use crate::routes::rest::queue::QUEUE_HOURS;
+use crate::routes::rest::transaction::load_transactions;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::{get, post, State};
+use std::collections::{HashMap, HashSet};
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::kitchen::KitchenOrder;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::transaction::TransactionId;
+
+/// GET `/kitchen`
+///
+/// The paid orders with items to prepare which haven't been marked as done, oldest first
+#[get("/kitchen")]
+pub fn get_kitchen_orders(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<KitchenOrder>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_orders(&connection, item_cache, session.organization)?))
+}
+
+/// POST `/kitchen/<sale_id>/done`
+///
+/// The order of a sale has been prepared, so it's removed from the board and its number is
+/// called on the queue page
+#[post("/kitchen/<sale_id>/done")]
+pub fn post_order_done(
+    db_pool: &State<DatabasePool>,
+    item_cache: &State<ItemCache>,
+    live: &State<LiveEvents>,
+    session: Session,
+    accept: SerAccept,
+    sale_id: TransactionId,
+) -> Result<Ser<Vec<KitchenOrder>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let ticket_ready = connection.transaction::<_, SJ, _>(|| {
+        {
+            use crate::schema::tables::transactions::dsl::*;
+            transactions
+                .filter(id.eq(sale_id))
+                .filter(organization_id.eq(session.organization))
+                .filter(deleted_at.is_null())
+                .select(id)
+                .first::<TransactionId>(&connection)?;
+        }
+
+        {
+            use crate::schema::tables::prepared_orders::dsl::*;
+            let inserted = diesel::insert_into(prepared_orders)
+                .values((
+                    transaction_id.eq(sale_id),
+                    organization_id.eq(session.organization),
+                    prepared_by.eq(&session.user),
+                ))
+                .on_conflict_do_nothing()
+                .execute(&connection)?;
+            if inserted == 0 {
+                return Err(SJ::new(Status::Conflict, "The order is already done"));
+            }
+        }
+
+        use crate::schema::tables::queue_tickets::dsl::*;
+        let updated = diesel::update(
+            queue_tickets
+                .filter(transaction_id.eq(sale_id))
+                .filter(ready_at.is_null())
+                .filter(collected_at.is_null()),
+        )
+        .set(ready_at.eq(Utc::now()))
+        .execute(&connection)?;
+        Ok(updated > 0)
+    })?;
+
+    live.notify(session.organization, LiveEvent::KitchenChanged);
+    if ticket_ready {
+        live.notify(session.organization, LiveEvent::QueueChanged);
+    }
+    Ok(accept.ser(load_orders(&connection, item_cache, session.organization)?))
+}
+
+/// The recent sales of an organization with items to prepare, which haven't been marked as done
+/// in the kitchen or as ready in the queue
+fn load_orders(
+    connection: &PgConnection,
+    item_cache: &ItemCache,
+    organization: OrganizationId,
+) -> Result<Vec<KitchenOrder>, SJ> {
+    let inventory = item_cache.get(connection, organization)?;
+    let prepared: HashMap<InventoryItemId, String> = inventory
+        .items
+        .iter()
+        .filter(|(_, item)| item.prepared)
+        .map(|(&id, item)| (id, item.name.clone().unwrap_or_default()))
+        .collect();
+    if prepared.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let now = Utc::now();
+    let since = now - Duration::hours(QUEUE_HOURS);
+    let sales_account = master_accounts(connection, organization)?.sales_account_id;
+    let mut transactions = load_transactions(connection, organization, Some(since..now))?;
+    // oldest first, which is the order they are prepared in
+    transactions.reverse();
+
+    let mut done: HashSet<TransactionId> = {
+        use crate::schema::tables::prepared_orders::dsl::*;
+        prepared_orders
+            .filter(organization_id.eq(organization))
+            .filter(prepared_at.ge(since))
+            .select(transaction_id)
+            .load::<TransactionId>(connection)?
+            .into_iter()
+            .collect()
+    };
+
+    let tickets: Vec<(TransactionId, i32, bool)> = {
+        use crate::schema::tables::queue_tickets::dsl::*;
+        queue_tickets
+            .filter(organization_id.eq(organization))
+            .filter(created_at.ge(since))
+            .select((
+                transaction_id,
+                number,
+                ready_at.is_not_null().or(collected_at.is_not_null()),
+            ))
+            .load(connection)?
+    };
+    let mut queue_numbers = HashMap::new();
+    for (sale_id, number, ready) in tickets {
+        queue_numbers.insert(sale_id, number);
+        if ready {
+            done.insert(sale_id);
+        }
+    }
+
+    Ok(transactions
+        .iter()
+        .filter(|tr| tr.credited_account == sales_account && !done.contains(&tr.id))
+        .filter_map(|tr| {
+            let lines = KitchenOrder::lines(tr, &prepared, &inventory.modifiers);
+            (!lines.is_empty()).then(|| KitchenOrder {
+                transaction_id: tr.id,
+                time: tr.time,
+                queue_number: queue_numbers.get(&tr.id).copied(),
+                lines,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::inventory::InventoryItemId;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_kitchen() {
+        use crate::config::NegativeStock;
+        use strecklistan_api::kitchen::{KitchenLine, KitchenOrder};
+        use strecklistan_api::queue::QueueTicket;
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        db.config.stock.negative_stock = NegativeStock::Warn;
+        db.config.queue.enabled = true;
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let burgare = db.item(org, "Burgare", Some(4000.into()));
+        let cola = db.item(org, "Cola", Some(1500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let sale = |item_ids: Vec<(InventoryItemId, u32)>| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(5500.into()),
+                change: -1,
+                item_ids: item_ids.into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 5500.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let response = client
+            .put(format!("/api/inventory/item/{}/prepared", burgare))
+            .header(ContentType::JSON)
+            .body("true")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let meal: i32 = post_json(
+            &client,
+            "/api/transaction",
+            &sale(vec![(burgare, 1), (cola, 1)]),
+        );
+        // nothing to prepare
+        let _: i32 = post_json(&client, "/api/transaction", &sale(vec![(cola, 2)]));
+
+        let orders: Vec<KitchenOrder> = get_json(&client, "/api/kitchen");
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].transaction_id, meal);
+        assert_eq!(orders[0].queue_number, Some(1));
+        assert_eq!(
+            orders[0].lines,
+            vec![KitchenLine {
+                name: "Burgare".to_string(),
+                quantity: 1,
+            }]
+        );
+
+        // done in the kitchen, so ready in the queue
+        let uri = format!("/api/kitchen/{}/done", meal);
+        let orders: Vec<KitchenOrder> = post_json(&client, &uri, &());
+        assert!(orders.is_empty());
+        assert_eq!(post(&client, &uri, &()), Status::Conflict);
+        let queue: Vec<QueueTicket> = get_json(&client, "/api/queue");
+        assert!(queue
+            .iter()
+            .find(|t| t.transaction_id == meal)
+            .unwrap()
+            .is_ready());
+
+        // an order which is ready in the queue is done in the kitchen as well
+        let second: i32 = post_json(&client, "/api/transaction", &sale(vec![(burgare, 2)]));
+        let ticket: Option<QueueTicket> =
+            get_json(&client, &format!("/api/queue/transaction/{}", second));
+        let ready = format!("/api/queue/{}/ready", ticket.unwrap().id);
+        let _: Vec<QueueTicket> = post_json(&client, &ready, &());
+        let orders: Vec<KitchenOrder> = get_json(&client, "/api/kitchen");
+        assert!(orders.is_empty());
+    }
+}
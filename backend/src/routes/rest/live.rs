@@ -0,0 +1,247 @@
+use crate::auth::Session;
+use crate::util::device::{DeviceActivity, ThisDevice};
+use chrono::Utc;
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::Json;
+use rocket::tokio::sync::broadcast::{self, error::RecvError};
+use rocket::tokio::time::{sleep, Duration};
+use rocket::{get, Shutdown, State};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use strecklistan_api::live::{LiveEvent, LivePoll};
+use strecklistan_api::organization::OrganizationId;
+
+/// How many events a client may fall behind before it's told to start over
+const LIVE_EVENTS_CAPACITY: usize = 256;
+
+/// How long a long poll waits for an event, below the timeouts of common proxies
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(25);
+
+/// Passes changes on to the clients which are listening, so that they don't have to poll.
+///
+/// Only the clients connected to the same server process are told. Events are sent once the
+/// change is committed, and the clients fetch what changed themselves.
+///
+/// The latest events are also kept for the clients which long poll, see [poll_live_events].
+#[derive(Clone)]
+pub struct LiveEvents {
+    events: broadcast::Sender<(OrganizationId, LiveEvent)>,
+    history: Arc<Mutex<History>>,
+}
+
+/// The latest events, numbered in the order they were sent
+struct History {
+    /// The number of the next event. Starts at the time the server started in ms, so that the
+    /// cursors of clients from before a restart are older than every event.
+    next: u64,
+
+    /// Oldest first
+    events: VecDeque<(u64, OrganizationId, LiveEvent)>,
+}
+
+impl Default for LiveEvents {
+    fn default() -> Self {
+        let (events, _) = broadcast::channel(LIVE_EVENTS_CAPACITY);
+        let history = History {
+            next: Utc::now().timestamp_millis() as u64,
+            events: VecDeque::with_capacity(LIVE_EVENTS_CAPACITY),
+        };
+        LiveEvents {
+            events,
+            history: Arc::new(Mutex::new(history)),
+        }
+    }
+}
+
+impl LiveEvents {
+    /// Tell the clients of `organization` about a committed change
+    pub fn notify(&self, organization: OrganizationId, event: LiveEvent) {
+        let mut history = self.history.lock().unwrap();
+        if history.events.len() >= LIVE_EVENTS_CAPACITY {
+            history.events.pop_front();
+        }
+        let number = history.next;
+        history.next += 1;
+        history
+            .events
+            .push_back((number, organization, event.clone()));
+
+        // fails if nobody is listening, which is fine
+        let _ = self.events.send((organization, event));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<(OrganizationId, LiveEvent)> {
+        self.events.subscribe()
+    }
+
+    /// The cursor of a client which has seen every event so far
+    pub fn cursor(&self) -> u64 {
+        self.history.lock().unwrap().next
+    }
+
+    /// The events of `organization` from `cursor` on, and the cursor after them. If some of them
+    /// have been forgotten, or the cursor is from before a restart, a
+    /// [LiveEvent::TransactionsChanged] stands in for them.
+    pub fn since(&self, organization: OrganizationId, cursor: u64) -> LivePoll {
+        let history = self.history.lock().unwrap();
+        let oldest = history
+            .events
+            .front()
+            .map(|&(number, _, _)| number)
+            .unwrap_or(history.next);
+
+        let events = if cursor < oldest || cursor > history.next {
+            vec![LiveEvent::TransactionsChanged]
+        } else {
+            history
+                .events
+                .iter()
+                .filter(|&&(number, org, _)| number >= cursor && org == organization)
+                .map(|(_, _, event)| event.clone())
+                .collect()
+        };
+        LivePoll {
+            cursor: history.next,
+            events,
+        }
+    }
+}
+
+/// GET `/live`
+///
+/// A stream of the changes made in the organization of the session, as server-sent `change`
+/// events. If the client falls too far behind, it gets a [LiveEvent::TransactionsChanged] in place
+/// of what it missed.
+///
+/// A registered device counts as online while its stream is open.
+#[get("/live")]
+pub fn live_events(
+    live: &State<LiveEvents>,
+    activity: &State<DeviceActivity>,
+    session: Session,
+    device: ThisDevice,
+    mut shutdown: Shutdown,
+) -> EventStream![] {
+    let mut events = live.subscribe();
+    let organization = session.organization;
+    let listening = device
+        .registered
+        .map(|(device_id, _)| activity.listen(device_id));
+
+    EventStream! {
+        let _listening = listening;
+        loop {
+            let event = rocket::tokio::select! {
+                event = events.recv() => match event {
+                    Ok((org, event)) if org == organization => event,
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(_)) => LiveEvent::TransactionsChanged,
+                    Err(RecvError::Closed) => break,
+                },
+                _ = &mut shutdown => break,
+            };
+            yield Event::json(&event).event("change");
+        }
+    }
+}
+
+/// GET `/live/poll?<after>`
+///
+/// The changes of [live_events] for clients whose network doesn't let the stream through, e.g. a
+/// proxy which buffers responses. Answers with the events from the cursor `after` on as soon as
+/// there are any, or with none after [LONG_POLL_TIMEOUT]. Without `after`, answers right away
+/// with the current cursor.
+#[get("/live/poll?<after>")]
+pub async fn poll_live_events(
+    live: &State<LiveEvents>,
+    session: Session,
+    // marks a registered device as online
+    _device: ThisDevice,
+    after: Option<u64>,
+    mut shutdown: Shutdown,
+) -> Json<LivePoll> {
+    let organization = session.organization;
+    let after = match after {
+        Some(after) => after,
+        None => {
+            return Json(LivePoll {
+                cursor: live.cursor(),
+                events: vec![],
+            })
+        }
+    };
+
+    // subscribed before looking, so that events sent in between aren't missed
+    let mut changes = live.subscribe();
+    let timeout = sleep(LONG_POLL_TIMEOUT);
+    rocket::tokio::pin!(timeout);
+    loop {
+        let poll = live.since(organization, after);
+        if !poll.events.is_empty() {
+            return Json(poll);
+        }
+
+        rocket::tokio::select! {
+            change = changes.recv() => match change {
+                // lagging is fine, since the history is looked at again
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Json(poll),
+            },
+            _ = &mut timeout => return Json(poll),
+            _ = &mut shutdown => return Json(poll),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post_json, TestDb};
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_live_poll() {
+        use crate::routes::rest::live::LiveEvents;
+        use strecklistan_api::live::{LiveEvent, LivePoll};
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let other = db.organization("Other");
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let start: LivePoll = get_json(&client, "/api/live/poll");
+        assert!(start.events.is_empty());
+
+        let sale = NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let id: i32 = post_json(&client, "/api/transaction", &sale);
+        let live = client.rocket().state::<LiveEvents>().unwrap();
+        live.notify(other, LiveEvent::AnnouncementsChanged);
+
+        // events which were already sent are answered right away, without those of other
+        // organizations
+        let uri = format!("/api/live/poll?after={}", start.cursor);
+        let poll: LivePoll = get_json(&client, &uri);
+        assert_eq!(poll.events, vec![LiveEvent::TransactionCreated { id }]);
+        assert!(poll.cursor > start.cursor);
+
+        // e.g. a client from before a restart of the server
+        let poll: LivePoll = get_json(&client, "/api/live/poll?after=0");
+        assert_eq!(poll.events, vec![LiveEvent::TransactionsChanged]);
+    }
+}
@@ -0,0 +1,440 @@
+use crate::auth::Session;
+use crate::database::DatabasePool;
+use crate::routes::rest::inventory::load_items;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use std::collections::BTreeMap;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::location::{
+    low_stock_alerts, LowStockAlert, NewStorageLocation, StockTransfer, StorageLocation,
+    StorageLocationId,
+};
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::validation::Validate;
+
+/// GET `/inventory/locations`
+///
+/// The storage locations, sorted by name, with how many of each item are kept in them
+#[get("/inventory/locations")]
+pub fn get_locations(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<StorageLocation>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_locations(&connection, session.organization)?))
+}
+
+/// POST `/inventory/locations`
+///
+/// Add a storage location, e.g. "Förråd", returns its id
+#[post("/inventory/locations", data = "<location>")]
+pub fn post_location(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    location: Json<NewStorageLocation>,
+) -> Result<Ser<StorageLocationId>, SJ> {
+    let mut location = location.into_inner();
+    location.name = location.name.trim().to_string();
+    location.validate()?;
+
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::storage_locations::dsl::*;
+    let taken: Option<StorageLocationId> = storage_locations
+        .filter(organization_id.eq(session.organization))
+        .filter(name.eq(&location.name))
+        .select(id)
+        .first(&connection)
+        .optional()?;
+    if taken.is_some() {
+        return Err(SJ::new(
+            Status::Conflict,
+            format!(
+                "There already is a storage location called {}",
+                location.name
+            ),
+        ));
+    }
+
+    let location_id = diesel::insert_into(storage_locations)
+        .values((
+            organization_id.eq(session.organization),
+            name.eq(&location.name),
+        ))
+        .returning(id)
+        .get_result(&connection)?;
+
+    Ok(accept.ser(location_id))
+}
+
+/// DELETE `/inventory/locations/<location_id>`
+///
+/// Remove a storage location. The items kept in it have to be moved out first, so that they
+/// aren't counted as for sale by mistake.
+#[delete("/inventory/locations/<location_id>")]
+pub fn delete_location(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    location_id: StorageLocationId,
+) -> Result<Ser<StorageLocationId>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        find_location(&connection, session.organization, location_id)?;
+
+        let stored: i64 = {
+            use crate::schema::tables::stored_items::dsl::{
+                location_id as stored_in, stored_items,
+            };
+            stored_items
+                .filter(stored_in.eq(location_id))
+                .count()
+                .get_result(&connection)?
+        };
+        if stored > 0 {
+            return Err(SJ::new(
+                Status::Conflict,
+                "Move the items out of the storage location before removing it",
+            ));
+        }
+
+        use crate::schema::tables::storage_locations::dsl::*;
+        let deleted_id = diesel::delete(storage_locations.filter(id.eq(location_id)))
+            .returning(id)
+            .get_result(&connection)?;
+
+        Ok(accept.ser(deleted_id))
+    })
+}
+
+/// POST `/inventory/transfers`
+///
+/// Move some of an item between two storage locations, or between one and the items for sale.
+/// Fails if there aren't that many where they're moved from.
+#[post("/inventory/transfers", data = "<transfer>")]
+pub fn post_transfer(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    transfer: Json<StockTransfer>,
+) -> Result<Ser<InventoryItemId>, SJ> {
+    let transfer = transfer.into_inner();
+    if transfer.amount <= 0 {
+        return Err(SJ::new(Status::BadRequest, "Invalid amount"));
+    }
+    if transfer.from == transfer.to {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "The items have to be moved somewhere else",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    let organization = session.organization;
+
+    connection.transaction::<_, SJ, _>(|| {
+        // locked like when selling, so that what's for sale isn't both sold and moved
+        let item_name: Option<String> = {
+            use crate::schema::tables::inventory::dsl::*;
+            inventory
+                .filter(id.eq(transfer.item_id))
+                .filter(organization_id.eq(organization))
+                .select(name)
+                .for_update()
+                .first(&connection)?
+        };
+        let item_name = item_name.unwrap_or_default();
+
+        match transfer.from {
+            Some(from) => {
+                let location_name = find_location(&connection, organization, from)?;
+                let stored = take_stored(&connection, from, transfer.item_id, transfer.amount)?;
+                if stored < transfer.amount {
+                    return Err(SJ::new(
+                        Status::Conflict,
+                        format!(
+                            "Only {} of {} are kept in {}",
+                            stored, item_name, location_name
+                        ),
+                    ));
+                }
+            }
+            None => {
+                let for_sale = load_for_sale(&connection, transfer.item_id)?;
+                if for_sale < transfer.amount {
+                    return Err(SJ::new(
+                        Status::Conflict,
+                        format!("Only {} of {} are for sale", for_sale.max(0), item_name),
+                    ));
+                }
+            }
+        }
+
+        if let Some(to) = transfer.to {
+            find_location(&connection, organization, to)?;
+
+            use crate::schema::tables::stored_items::dsl::*;
+            diesel::insert_into(stored_items)
+                .values((
+                    location_id.eq(to),
+                    item_id.eq(transfer.item_id),
+                    amount.eq(transfer.amount),
+                ))
+                .on_conflict((location_id, item_id))
+                .do_update()
+                .set(amount.eq(amount + transfer.amount))
+                .execute(&connection)?;
+        }
+
+        Ok(accept.ser(transfer.item_id))
+    })
+}
+
+/// GET `/inventory/low_stock`
+///
+/// The items of which few are left for sale, with where more of them are kept, see
+/// [low_stock_alerts]
+#[get("/inventory/low_stock")]
+pub fn get_low_stock(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<LowStockAlert>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let items = load_items(&connection, session.organization)?;
+    let locations = load_locations(&connection, session.organization)?;
+    Ok(accept.ser(low_stock_alerts(&items, &locations)))
+}
+
+/// The storage locations of `organization`, sorted by name
+pub fn load_locations(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<Vec<StorageLocation>> {
+    let mut items: BTreeMap<StorageLocationId, BTreeMap<InventoryItemId, i32>> = BTreeMap::new();
+    {
+        use crate::schema::tables::storage_locations::dsl::{
+            id, organization_id, storage_locations,
+        };
+        use crate::schema::tables::stored_items::dsl::*;
+        let rows: Vec<(StorageLocationId, InventoryItemId, i32)> = stored_items
+            .inner_join(storage_locations.on(id.eq(location_id)))
+            .filter(organization_id.eq(organization))
+            .select((location_id, item_id, amount))
+            .load(connection)?;
+        for (location, item, count) in rows {
+            items.entry(location).or_default().insert(item, count);
+        }
+    }
+
+    use crate::schema::tables::storage_locations::dsl::*;
+    let rows: Vec<(StorageLocationId, String)> = storage_locations
+        .filter(organization_id.eq(organization))
+        .select((id, name))
+        .order_by(name.asc())
+        .load(connection)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(location_id, location_name)| StorageLocation {
+            id: location_id,
+            name: location_name,
+            items: items.remove(&location_id).unwrap_or_default(),
+        })
+        .collect())
+}
+
+/// The name of the storage location, or 404 if `organization` has no such location
+fn find_location(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    location: StorageLocationId,
+) -> Result<String, SJ> {
+    use crate::schema::tables::storage_locations::dsl::*;
+    storage_locations
+        .filter(id.eq(location))
+        .filter(organization_id.eq(organization))
+        .select(name)
+        .first::<String>(connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such storage location"))
+}
+
+/// How many of the stock of `item` aren't kept in any storage location
+fn load_for_sale(connection: &PgConnection, item: InventoryItemId) -> QueryResult<i32> {
+    let stock: i32 = {
+        use crate::schema::views::inventory_stock::dsl::*;
+        inventory_stock
+            .filter(id.eq(item))
+            .select(stock)
+            .first(connection)?
+    };
+
+    use crate::schema::tables::stored_items::dsl::*;
+    let stored: Vec<i32> = stored_items
+        .filter(item_id.eq(item))
+        .select(amount)
+        .load(connection)?;
+
+    Ok(stock - stored.into_iter().sum::<i32>())
+}
+
+/// Take `count` of `item` out of `location`, removing the row if none are left. Returns how many
+/// were kept there, and takes none if that's fewer than `count`.
+fn take_stored(
+    connection: &PgConnection,
+    location: StorageLocationId,
+    item: InventoryItemId,
+    count: i32,
+) -> QueryResult<i32> {
+    use crate::schema::tables::stored_items::dsl::*;
+    let row = stored_items
+        .filter(location_id.eq(location))
+        .filter(item_id.eq(item));
+
+    let stored: i32 = row
+        .select(amount)
+        .for_update()
+        .first(connection)
+        .optional()?
+        .unwrap_or(0);
+
+    if stored == count {
+        diesel::delete(row).execute(connection)?;
+    } else if stored > count {
+        diesel::update(row)
+            .set(amount.eq(amount - count))
+            .execute(connection)?;
+    }
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::inventory::InventoryItemId;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_storage_locations() {
+        use strecklistan_api::location::{
+            LowStockAlert, NewStorageLocation, StockTransfer, StorageLocation, StorageLocationId,
+        };
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let restock = NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change: 30,
+                item_ids: vec![(kaffe, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let _: i32 = post_json(&client, "/api/transaction", &restock);
+
+        let new_location = NewStorageLocation {
+            name: " Förråd ".to_string(),
+        };
+        let forrad: StorageLocationId =
+            post_json(&client, "/api/inventory/locations", &new_location);
+        let status = post(&client, "/api/inventory/locations", &new_location);
+        assert_eq!(status, Status::Conflict);
+
+        let transfer = |from, to, amount| StockTransfer {
+            item_id: kaffe,
+            from,
+            to,
+            amount,
+        };
+        let _: InventoryItemId = post_json(
+            &client,
+            "/api/inventory/transfers",
+            &transfer(None, Some(forrad), 24),
+        );
+        let status = post(
+            &client,
+            "/api/inventory/transfers",
+            &transfer(None, Some(forrad), 7),
+        );
+        assert_eq!(status, Status::Conflict, "only 6 are for sale");
+        let status = post(
+            &client,
+            "/api/inventory/transfers",
+            &transfer(Some(forrad), None, 25),
+        );
+        assert_eq!(status, Status::Conflict, "only 24 are kept in the förråd");
+
+        let locations: Vec<StorageLocation> = get_json(&client, "/api/inventory/locations");
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].name, "Förråd");
+        assert_eq!(locations[0].items[&kaffe], 24);
+
+        let response = client
+            .put(format!("/api/inventory/item/{}/low_stock_at", kaffe))
+            .header(ContentType::JSON)
+            .body("10")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let alerts: Vec<LowStockAlert> = get_json(&client, "/api/inventory/low_stock");
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].for_sale, 6);
+        assert_eq!(alerts[0].stored[&forrad], 24);
+
+        let _: InventoryItemId = post_json(
+            &client,
+            "/api/inventory/transfers",
+            &transfer(Some(forrad), None, 12),
+        );
+        let alerts: Vec<LowStockAlert> = get_json(&client, "/api/inventory/low_stock");
+        assert!(alerts.is_empty());
+
+        let response = client
+            .delete(format!("/api/inventory/locations/{}", forrad))
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+
+        let _: InventoryItemId = post_json(
+            &client,
+            "/api/inventory/transfers",
+            &transfer(Some(forrad), None, 12),
+        );
+        let locations: Vec<StorageLocation> = get_json(&client, "/api/inventory/locations");
+        assert!(locations[0].items.is_empty());
+        let response = client
+            .delete(format!("/api/inventory/locations/{}", forrad))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+}
@@ -1,16 +1,46 @@
+use crate::auth::Session;
+use crate::config::Config;
 use crate::database::DatabasePool;
+use crate::models::book_account as relational;
+use crate::models::transaction::relational::Transaction;
+use crate::routes::rest::book_account::apply_transactions;
+use crate::routes::rest::organization::load_branding;
+use crate::util::mail::Mail;
+use crate::util::outbox;
+use crate::util::qr;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Duration, Utc};
 use diesel::prelude::*;
+use rand_core::{OsRng, RngCore};
+use rocket::http::{ContentType, Status};
 use rocket::serde::json::Json;
-use rocket::{get, post, State};
+use rocket::{get, post, put, State};
 use std::collections::HashMap;
-use strecklistan_api::book_account::{BookAccountId, BookAccountType};
-use strecklistan_api::member::{Member, MemberId, NewMember};
+use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::member::{
+    ContactPreferences, MailTopic, Member, MemberContact, MemberId, MemberSearchResult, NewMember,
+};
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::transaction::TransactionId;
+use strecklistan_api::validation::{Validate, Validator, MAX_NAME_LENGTH};
+
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// How long the code which confirms an email address is valid
+const EMAIL_CODE_HOURS: i64 = 48;
+
+/// How many wrong codes may be entered before a new one must be sent
+const MAX_EMAIL_CODE_ATTEMPTS: i32 = 5;
+
+/// The size of the random code on balance cards, enough that it can't be guessed
+const BALANCE_TOKEN_BYTES: usize = 16;
 
 #[get("/members")]
 pub fn get_members(
     db_pool: &State<DatabasePool>,
+    session: Session,
     accept: SerAccept,
 ) -> Result<Ser<HashMap<MemberId, Member>>, SJ> {
     let connection = db_pool.inner().get()?;
@@ -18,6 +48,8 @@ pub fn get_members(
 
     Ok(accept.ser(
         members
+            .filter(organization_id.eq(session.organization))
+            .select((id, first_name, last_name, nickname, archived_at))
             .load(&connection)?
             .into_iter()
             .map(|member: Member| (member.id, member))
@@ -25,15 +57,541 @@ pub fn get_members(
     ))
 }
 
+/// GET `/members/search?<q>&<limit>`
+///
+/// Members whose name or nickname contains `q`, together with their tillgodo accounts.
+/// Members where the name or nickname starts with `q` are listed first. Archived members are
+/// left out.
+#[get("/members/search?<q>&<limit>")]
+pub fn search_members(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    q: String,
+    limit: Option<usize>,
+) -> Result<Ser<Vec<MemberSearchResult>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let query = q.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(accept.ser(vec![]));
+    }
+
+    let rows: Vec<(relational::BookAccount, Member)> = {
+        use crate::schema::tables::book_accounts::dsl::{
+            book_accounts, organization_id as account_organization,
+        };
+        use crate::schema::tables::members::dsl::{
+            archived_at, first_name, id as member_id, last_name, members, nickname,
+        };
+        book_accounts
+            .inner_join(members)
+            .filter(account_organization.eq(session.organization))
+            .filter(archived_at.is_null())
+            .select((
+                crate::schema::tables::book_accounts::all_columns,
+                (member_id, first_name, last_name, nickname, archived_at),
+            ))
+            .load(&connection)?
+    };
+
+    let mut matches: Vec<(bool, BookAccount, Member)> = rows
+        .into_iter()
+        .filter_map(|(account, member)| {
+            let full_name = format!("{} {}", member.first_name, member.last_name).to_lowercase();
+            let nickname = member.nickname.as_deref().unwrap_or("").to_lowercase();
+            if !full_name.contains(&query) && !nickname.contains(&query) {
+                return None;
+            }
+
+            let prefix_match = full_name.starts_with(&query)
+                || nickname.starts_with(&query)
+                || member.last_name.to_lowercase().starts_with(&query);
+            Some((prefix_match, account.into(), member))
+        })
+        .collect();
+
+    matches.sort_by(|(prefix_a, _, member_a), (prefix_b, _, member_b)| {
+        prefix_b
+            .cmp(prefix_a)
+            .then_with(|| member_a.first_name.cmp(&member_b.first_name))
+            .then_with(|| member_a.last_name.cmp(&member_b.last_name))
+    });
+    matches.truncate(limit.unwrap_or(DEFAULT_SEARCH_LIMIT));
+
+    let mut accounts: HashMap<BookAccountId, BookAccount> = matches
+        .iter()
+        .map(|(_, account, _)| (account.id, account.clone()))
+        .collect();
+    let account_ids: Vec<BookAccountId> = accounts.keys().copied().collect();
+
+    let transactions: Vec<Transaction> = {
+        use crate::schema::tables::transactions::dsl::*;
+        transactions
+            .filter(deleted_at.is_null())
+            .filter(
+                credited_account
+                    .eq_any(&account_ids)
+                    .or(debited_account.eq_any(&account_ids)),
+            )
+            .load(&connection)?
+    };
+    apply_transactions(&mut accounts, &transactions);
+
+    let results = matches
+        .into_iter()
+        .filter_map(|(_, account, member)| {
+            accounts
+                .remove(&account.id)
+                .map(|account| MemberSearchResult { member, account })
+        })
+        .collect();
+
+    Ok(accept.ser(results))
+}
+
+/// PUT `/member/<member_id>/archived`
+///
+/// Archive or restore a member. Archived members can't be searched for, but are kept in the
+/// history.
+#[put("/member/<member_id>/archived", data = "<archived>")]
+pub fn put_member_archived(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+    archived: Json<bool>,
+) -> Result<Ser<MemberId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let archived = archived.into_inner().then(Utc::now);
+
+    use crate::schema::tables::members::dsl::*;
+    let updated_id = diesel::update(
+        members
+            .filter(id.eq(member_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .set(archived_at.eq(archived))
+    .returning(id)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(updated_id))
+}
+
+/// GET `/member/<member_id>/contact`
+///
+/// The email address of a member, and what they may be mailed about
+#[get("/member/<member_id>/contact")]
+pub fn get_member_contact(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+) -> Result<Ser<MemberContact>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_contact(&connection, session.organization, member_id)?))
+}
+
+/// PUT `/member/<member_id>/email`
+///
+/// Set the email address of a member, or remove it with `null`. A code is mailed to the new
+/// address, which the member confirms it with. Nothing else is sent to the address until then.
+///
+/// Setting an unconfirmed address again sends a new code.
+#[put("/member/<member_id>/email", data = "<address>")]
+pub fn put_member_email(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+    address: Json<Option<String>>,
+) -> Result<Ser<MemberContact>, SJ> {
+    let new_email = address
+        .into_inner()
+        .map(|email| email.trim().to_string())
+        .filter(|email| !email.is_empty());
+    Validator::default().email("email", &new_email).finish()?;
+
+    let connection = db_pool.inner().get()?;
+    let contact = connection.transaction::<_, SJ, _>(|| {
+        let current = load_contact(&connection, session.organization, member_id)?;
+        if current.email == new_email
+            && (new_email.is_none() || current.email_verified_at.is_some())
+        {
+            return Ok(current);
+        }
+
+        let code = new_email
+            .as_ref()
+            .map(|_| format!("{:06}", OsRng.next_u32() % 1_000_000));
+
+        {
+            use crate::schema::tables::members::dsl::*;
+            diesel::update(members.filter(id.eq(member_id)))
+                .set((
+                    email.eq(&new_email),
+                    email_verified_at.eq(None::<DateTime<Utc>>),
+                    email_code.eq(&code),
+                    email_code_sent_at.eq(code.as_ref().map(|_| Utc::now())),
+                    email_code_attempts.eq(0),
+                ))
+                .execute(&connection)?;
+        }
+
+        if let (Some(to), Some(code)) = (&new_email, &code) {
+            outbox::enqueue_mail(
+                &connection,
+                config,
+                Mail {
+                    to: to.clone(),
+                    subject: "Confirm your email address".to_string(),
+                    body: format!(
+                        "Your code is {}.\n\n\
+                         Give it to the cashier to confirm that this is your address. It's valid \
+                         for {} hours. Until then, nothing else is sent to this address.\n\n\
+                         If you didn't ask for this, you can ignore this mail.",
+                        code, EMAIL_CODE_HOURS,
+                    ),
+                },
+            )?;
+        }
+
+        load_contact(&connection, session.organization, member_id)
+    })?;
+
+    Ok(accept.ser(contact))
+}
+
+/// POST `/member/<member_id>/verify_email`
+///
+/// Confirm the email address of a member with the code which was mailed to it.
+///
+/// After a few wrong codes, or once the code has expired, a new code must be sent.
+#[post("/member/<member_id>/verify_email", data = "<code>")]
+pub fn verify_member_email(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+    code: Json<String>,
+) -> Result<Ser<MemberContact>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let code = code.into_inner();
+
+    let verified = connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::members::dsl::*;
+
+        let (sent_code, sent_at, attempts): (Option<String>, Option<DateTime<Utc>>, i32) = members
+            .filter(id.eq(member_id))
+            .filter(organization_id.eq(session.organization))
+            .select((email_code, email_code_sent_at, email_code_attempts))
+            .for_update()
+            .first(&connection)?;
+
+        let (sent_code, sent_at) = match (sent_code, sent_at) {
+            (Some(sent_code), Some(sent_at)) => (sent_code, sent_at),
+            _ => return Err(SJ::new(Status::Conflict, "No code has been sent")),
+        };
+        if attempts >= MAX_EMAIL_CODE_ATTEMPTS
+            || sent_at + Duration::hours(EMAIL_CODE_HOURS) < Utc::now()
+        {
+            return Err(SJ::new(
+                Status::Gone,
+                "The code is no longer valid, set the address again to send a new one",
+            ));
+        }
+
+        // the attempt is counted even though the request fails, so it must be committed
+        if code.trim() != sent_code {
+            diesel::update(members.filter(id.eq(member_id)))
+                .set(email_code_attempts.eq(attempts + 1))
+                .execute(&connection)?;
+            return Ok(false);
+        }
+
+        diesel::update(members.filter(id.eq(member_id)))
+            .set((
+                email_verified_at.eq(Utc::now()),
+                email_code.eq(None::<String>),
+                email_code_sent_at.eq(None::<DateTime<Utc>>),
+                email_code_attempts.eq(0),
+            ))
+            .execute(&connection)?;
+        Ok(true)
+    })?;
+
+    if !verified {
+        return Err(SJ::new(Status::BadRequest, "Wrong code"));
+    }
+    Ok(accept.ser(load_contact(&connection, session.organization, member_id)?))
+}
+
+/// GET `/member/<member_id>/balance_card`
+///
+/// The code on the balance card of a member, `null` if they have none
+#[get("/member/<member_id>/balance_card")]
+pub fn get_balance_card(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+) -> Result<Ser<Option<String>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_balance_token(
+        &connection,
+        session.organization,
+        member_id,
+    )?))
+}
+
+/// PUT `/member/<member_id>/balance_card`
+///
+/// Make a new balance card for a member. The code of the old card, if any, stops working.
+#[put("/member/<member_id>/balance_card")]
+pub fn put_balance_card(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+) -> Result<Ser<String>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let mut bytes = [0u8; BALANCE_TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    use crate::schema::tables::members::dsl::*;
+    diesel::update(
+        members
+            .filter(id.eq(member_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .set(balance_token.eq(&token))
+    .returning(id)
+    .get_result::<MemberId>(&connection)?;
+
+    Ok(accept.ser(token))
+}
+
+/// GET `/member/<member_id>/balance_card.svg?<page>`
+///
+/// The balance card of a member to print, with a QR code which opens `page` with the code of the
+/// card, e.g. `https://example.com/balance`
+#[get("/member/<member_id>/balance_card.svg?<page>")]
+pub fn get_balance_card_svg(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    member_id: MemberId,
+    page: String,
+) -> Result<(ContentType, String), SJ> {
+    let valid_page = (page.starts_with("http://") || page.starts_with("https://"))
+        && !page.contains(|c| c == '?' || c == '#');
+    if !valid_page {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "The page must be an http(s) url without a query",
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+    let token = load_balance_token(&connection, session.organization, member_id)?
+        .ok_or_else(|| SJ::new(Status::NotFound, "The member has no balance card"))?;
+    let (first, last): (String, String) = {
+        use crate::schema::tables::members::dsl::*;
+        members
+            .filter(id.eq(member_id))
+            .select((first_name, last_name))
+            .first(&connection)?
+    };
+    let branding = load_branding(&connection, session.organization)?;
+
+    let url = format!("{}?card={}", page, token);
+    let name = format!("{} {}", first, last);
+    let svg = qr::card(
+        &url,
+        &[
+            &name,
+            &branding.name,
+            "Scanna för att se ditt saldo",
+            "Scan to see your balance",
+        ],
+    )
+    .map_err(|e| {
+        SJ::new(
+            Status::InternalServerError,
+            format!("Could not make a QR code: {}", e),
+        )
+    })?;
+
+    Ok((ContentType::SVG, svg))
+}
+
+/// The code on the balance card of a member of `organization`
+fn load_balance_token(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    member_id: MemberId,
+) -> Result<Option<String>, SJ> {
+    use crate::schema::tables::members::dsl::*;
+    Ok(members
+        .filter(id.eq(member_id))
+        .filter(organization_id.eq(organization))
+        .select(balance_token)
+        .first(connection)?)
+}
+
+/// PUT `/member/<member_id>/contact_preferences`
+///
+/// Set what a member has opted in to be mailed about
+#[put("/member/<member_id>/contact_preferences", data = "<preferences>")]
+pub fn put_contact_preferences(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    member_id: MemberId,
+    preferences: Json<ContactPreferences>,
+) -> Result<Ser<MemberContact>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let preferences = preferences.into_inner();
+
+    {
+        use crate::schema::tables::members::dsl::*;
+        diesel::update(
+            members
+                .filter(id.eq(member_id))
+                .filter(organization_id.eq(session.organization)),
+        )
+        .set((
+            mail_receipts.eq(preferences.receipts),
+            mail_balance_reminders.eq(preferences.balance_reminders),
+            mail_event_info.eq(preferences.event_info),
+        ))
+        .returning(id)
+        .get_result::<MemberId>(&connection)?;
+    }
+
+    Ok(accept.ser(load_contact(&connection, session.organization, member_id)?))
+}
+
+fn load_contact(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    member_id: MemberId,
+) -> Result<MemberContact, SJ> {
+    use crate::schema::tables::members::dsl::*;
+    let (address, verified_at, receipts, balance_reminders, event_info) = members
+        .filter(id.eq(member_id))
+        .filter(organization_id.eq(organization))
+        .select((
+            email,
+            email_verified_at,
+            mail_receipts,
+            mail_balance_reminders,
+            mail_event_info,
+        ))
+        .first(connection)?;
+
+    Ok(MemberContact {
+        member_id,
+        email: address,
+        email_verified_at: verified_at,
+        preferences: ContactPreferences {
+            receipts,
+            balance_reminders,
+            event_info,
+        },
+    })
+}
+
+/// The tillgodo accounts of every member of an organization, including archived members
+pub fn load_member_accounts(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<Vec<(Member, BookAccount)>> {
+    use crate::schema::tables::book_accounts::dsl::{
+        book_accounts, organization_id as account_organization,
+    };
+    use crate::schema::tables::members::dsl::{
+        archived_at, first_name, id as member_id, last_name, members, nickname,
+    };
+    Ok(book_accounts
+        .inner_join(members)
+        .filter(account_organization.eq(organization))
+        .select((
+            crate::schema::tables::book_accounts::all_columns,
+            (member_id, first_name, last_name, nickname, archived_at),
+        ))
+        .load(connection)?
+        .into_iter()
+        .map(|(account, member): (relational::BookAccount, Member)| (member, account.into()))
+        .collect())
+}
+
+/// Mail a receipt of a transaction to the members whose tillgodo accounts it's on, if they have
+/// opted in to receipts. Must be called in the database transaction which inserts it.
+pub fn mail_receipts(
+    connection: &PgConnection,
+    config: &Config,
+    transaction_id: TransactionId,
+    accounts: (BookAccountId, BookAccountId),
+    amount: Currency,
+    description: Option<&str>,
+) -> Result<(), SJ> {
+    let (debited, credited) = accounts;
+    let member_accounts: Vec<(BookAccountId, Option<MemberId>)> = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(id.eq_any(vec![debited, credited]))
+            .filter(creditor.is_not_null())
+            .select((id, creditor))
+            .load(connection)?
+    };
+
+    for (account, member) in member_accounts {
+        let member = match member {
+            Some(member) => member,
+            None => continue,
+        };
+        // tillgodo accounts are liabilities, so they are debited when the member pays from them
+        let (subject, change) = if account == debited {
+            ("Receipt of your purchase", -amount)
+        } else {
+            ("Receipt of your deposit", amount)
+        };
+        let body = format!(
+            "{}\n\nChange of your balance: {}:-\nTransaction: #{}",
+            description.unwrap_or(subject),
+            change,
+            transaction_id,
+        );
+        outbox::enqueue_member_mail(
+            connection,
+            config,
+            member,
+            MailTopic::Receipts,
+            subject.to_string(),
+            body,
+        )?;
+    }
+
+    Ok(())
+}
+
 #[post("/add_member_with_book_account", data = "<data>")]
 pub fn add_member_with_book_account(
     db_pool: &State<DatabasePool>,
+    session: Session,
     accept: SerAccept,
     data: Json<(NewMember, String)>,
 ) -> Result<Ser<(MemberId, BookAccountId)>, SJ> {
     let connection = db_pool.inner().get()?;
 
     let (new_member, account_name) = data.into_inner();
+    Validator::default()
+        .include(new_member.validate())
+        .required("account_name", &account_name, MAX_NAME_LENGTH)
+        .finish()?;
 
     connection.transaction::<_, SJ, _>(|| {
         let member_id = {
@@ -44,6 +602,7 @@ pub fn add_member_with_book_account(
                     first_name.eq(&new_member.first_name),
                     last_name.eq(&new_member.last_name),
                     nickname.eq(&new_member.nickname),
+                    organization_id.eq(session.organization),
                 ))
                 .returning(id)
                 .get_result(&connection)?
@@ -57,6 +616,7 @@ pub fn add_member_with_book_account(
                     name.eq(&account_name),
                     account_type.eq(&BookAccountType::Liabilities),
                     creditor.eq(&Some(member_id)),
+                    organization_id.eq(session.organization),
                 ))
                 .returning(id)
                 .get_result(&connection)?
@@ -65,3 +625,170 @@ pub fn add_member_with_book_account(
         Ok(accept.ser((member_id, acc_id)))
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_archive() {
+        use strecklistan_api::member::{Member, MemberId, MemberSearchResult};
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let item = db.item(org, "Kaffe", Some(500.into()));
+        let (member, _) = db.member(org, "Testsson");
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let archive = |uri: String, archived: bool| {
+            let response = client
+                .put(uri)
+                .header(ContentType::JSON)
+                .body(serde_json::to_string(&archived).unwrap())
+                .dispatch();
+            assert_eq!(response.status(), Status::Ok);
+        };
+        archive(format!("/api/inventory/item/{}/archived", item), true);
+        archive(format!("/api/member/{}/archived", member), true);
+
+        // archived items and members are still there for the history
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert!(!items[&item].is_for_sale());
+        let members: HashMap<MemberId, Member> = get_json(&client, "/api/members");
+        assert!(members[&member].archived_at.is_some());
+
+        let found: Vec<MemberSearchResult> = get_json(&client, "/api/members/search?q=test");
+        assert!(found.is_empty());
+
+        archive(format!("/api/member/{}/archived", member), false);
+        let found: Vec<MemberSearchResult> = get_json(&client, "/api/members/search?q=test");
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn test_member_contact() {
+        use crate::config::SmtpConfig;
+        use strecklistan_api::member::{ContactPreferences, MemberContact};
+
+        let mut db = TestDb::new();
+        db.config.smtp = Some(SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port: 9,
+            username: None,
+            password: None,
+            from: "strecklistan@example.com".to_string(),
+        });
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (member, member_account) = db.member(org, "Testsson");
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let mails = || -> i64 {
+            use crate::schema::tables::outbox::dsl::*;
+            outbox
+                .filter(kind.eq("mail"))
+                .count()
+                .get_result(&db.conn())
+                .unwrap()
+        };
+        let sent_code = || -> Option<String> {
+            use crate::schema::tables::members::dsl::*;
+            members
+                .filter(id.eq(member))
+                .select(email_code)
+                .first(&db.conn())
+                .unwrap()
+        };
+        let put = |uri: String, body: String| {
+            client
+                .put(uri)
+                .header(ContentType::JSON)
+                .body(body)
+                .dispatch()
+        };
+        let deposit = NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account: cash,
+            credited_account: member_account,
+            amount: 10000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        let contact: MemberContact = get_json(&client, &format!("/api/member/{}/contact", member));
+        assert_eq!(contact.email, None);
+        assert_eq!(contact.preferences, ContactPreferences::default());
+
+        let email_uri = format!("/api/member/{}/email", member);
+        let response = put(email_uri.clone(), r#""testsson""#.to_string());
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+
+        let response = put(email_uri.clone(), r#"" testsson@example.com ""#.to_string());
+        assert_eq!(response.status(), Status::Ok);
+        let contact: MemberContact = response.into_json().unwrap();
+        assert_eq!(contact.email.as_deref(), Some("testsson@example.com"));
+        assert_eq!(contact.email_verified_at, None);
+        assert_eq!(mails(), 1);
+
+        // opted in, but the address isn't confirmed
+        let preferences = ContactPreferences {
+            receipts: true,
+            ..ContactPreferences::default()
+        };
+        let response = put(
+            format!("/api/member/{}/contact_preferences", member),
+            serde_json::to_string(&preferences).unwrap(),
+        );
+        assert_eq!(response.status(), Status::Ok);
+        let _: i32 = post_json(&client, "/api/transaction", &deposit);
+        assert_eq!(mails(), 1);
+
+        let verify_uri = format!("/api/member/{}/verify_email", member);
+        assert_eq!(post(&client, &verify_uri, &"000000x"), Status::BadRequest);
+        let code = sent_code().unwrap();
+        let contact: MemberContact = post_json(&client, &verify_uri, &code);
+        assert!(contact.email_verified_at.is_some());
+        assert_eq!(sent_code(), None);
+
+        let _: i32 = post_json(&client, "/api/transaction", &deposit);
+        assert_eq!(mails(), 2);
+
+        // not opted in
+        let response = put(
+            format!("/api/member/{}/contact_preferences", member),
+            serde_json::to_string(&ContactPreferences::default()).unwrap(),
+        );
+        assert_eq!(response.status(), Status::Ok);
+        let _: i32 = post_json(&client, "/api/transaction", &deposit);
+        assert_eq!(mails(), 2);
+
+        // too many wrong codes
+        let _: MemberContact = put(email_uri, r#""kalle@example.com""#.to_string())
+            .into_json()
+            .unwrap();
+        for _ in 0..5 {
+            assert_eq!(post(&client, &verify_uri, &"x"), Status::BadRequest);
+        }
+        assert_eq!(
+            post(&client, &verify_uri, &sent_code().unwrap()),
+            Status::Gone
+        );
+    }
+}
@@ -1,13 +1,53 @@
+pub mod admin;
+pub mod analytics;
+pub mod announcement;
+pub mod auth;
 pub mod book_account;
+pub mod catalog;
+pub mod checkout;
+pub mod client_error;
+pub mod correction;
+pub mod dashboard;
+pub mod device;
 pub mod event;
+pub mod export;
+pub mod filter_preset;
+pub mod goal;
+pub mod import;
 pub mod inventory;
 pub mod izettle;
+pub mod kitchen;
+pub mod live;
+pub mod location;
 pub mod member;
+pub mod organization;
+pub mod parked_cart;
+pub mod preferences;
+pub mod public;
+pub mod query_plan;
+pub mod queue;
+pub mod register;
+pub mod report;
+pub mod store_layout;
+pub mod supplier;
+pub mod swish;
+pub mod training;
 pub mod transaction;
 
-use rocket::get;
+use crate::config::Config;
+use crate::util::ser::{Ser, SerAccept};
+use rocket::{get, State};
+use strecklistan_api::time::Tz;
 
 #[get("/version")]
 pub fn get_api_version() -> &'static str {
     env!("CARGO_PKG_VERSION")
 }
+
+/// GET `/reporting_timezone`
+///
+/// The timezone used for grouping by day/week, as an IANA name (e.g. "Europe/Stockholm")
+#[get("/reporting_timezone")]
+pub fn get_reporting_timezone(config: &State<Config>, accept: SerAccept) -> Ser<Tz> {
+    accept.ser(config.reporting_timezone)
+}
@@ -0,0 +1,245 @@
+use crate::auth::{AdminSession, Session};
+use crate::database::DatabasePool;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::Json;
+use rocket::{delete, get, put, State};
+use strecklistan_api::organization::{Branding, BrandingSettings, OrganizationId};
+use strecklistan_api::validation::Validate;
+
+/// The image formats a logo may be uploaded as. SVG isn't allowed since it can carry scripts.
+const LOGO_TYPES: &[ContentType] = &[
+    ContentType::PNG,
+    ContentType::JPEG,
+    ContentType::GIF,
+    ContentType::WEBP,
+];
+
+/// The branding of an organization
+pub fn load_branding(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> Result<Branding, SJ> {
+    use crate::schema::tables::organizations::dsl::*;
+    let (org_name, number, footer, logo_time) = organizations
+        .filter(id.eq(organization))
+        .select((name, org_number, footer_text, logo_updated_at))
+        .first::<(
+            String,
+            Option<String>,
+            Option<String>,
+            Option<DateTime<Utc>>,
+        )>(connection)?;
+
+    Ok(Branding {
+        name: org_name,
+        org_number: number,
+        footer_text: footer,
+        logo_updated_at: logo_time,
+    })
+}
+
+/// GET `/organization/branding`
+///
+/// How the organization of the session presents itself
+#[get("/organization/branding")]
+pub fn get_branding(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Branding>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_branding(&connection, session.organization)?))
+}
+
+/// GET `/organization/logo`
+///
+/// The logo of the organization of the session, as it was uploaded
+#[get("/organization/logo")]
+pub fn get_logo(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+) -> Result<(ContentType, Vec<u8>), SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::organizations::dsl::*;
+    let (image, image_type): (Option<Vec<u8>>, Option<String>) = organizations
+        .filter(id.eq(session.organization))
+        .select((logo, logo_content_type))
+        .first(&connection)?;
+
+    match (image, image_type) {
+        (Some(image), Some(image_type)) => Ok((
+            ContentType::parse_flexible(&image_type).unwrap_or(ContentType::Binary),
+            image,
+        )),
+        _ => Err(SJ::new(Status::NotFound, "The organization has no logo")),
+    }
+}
+
+/// PUT `/admin/organization`
+///
+/// Change the name, organisationsnummer and footer of the organization
+#[put("/admin/organization", data = "<settings>")]
+pub fn put_branding(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+    settings: Json<BrandingSettings>,
+) -> Result<Ser<Branding>, SJ> {
+    let settings = validate(settings.into_inner())?;
+    let connection = db_pool.inner().get()?;
+
+    {
+        use crate::schema::tables::organizations::dsl::*;
+        diesel::update(organizations.filter(id.eq(admin.0.organization)))
+            .set((
+                name.eq(&settings.name),
+                org_number.eq(&settings.org_number),
+                footer_text.eq(&settings.footer_text),
+            ))
+            .execute(&connection)?;
+    }
+
+    Ok(accept.ser(load_branding(&connection, admin.0.organization)?))
+}
+
+/// PUT `/admin/organization/logo`
+///
+/// Replace the logo of the organization with the image in the body
+#[put("/admin/organization/logo", data = "<image>")]
+pub fn put_logo(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+    content_type: &ContentType,
+    image: Vec<u8>,
+) -> Result<Ser<Branding>, SJ> {
+    if !LOGO_TYPES.contains(content_type) {
+        return Err(SJ::new(
+            Status::UnsupportedMediaType,
+            "The logo must be a PNG, JPEG, GIF or WebP image",
+        ));
+    }
+    if image.is_empty() {
+        return Err(SJ::new(Status::BadRequest, "The image is empty"));
+    }
+
+    let connection = db_pool.inner().get()?;
+    {
+        use crate::schema::tables::organizations::dsl::*;
+        diesel::update(organizations.filter(id.eq(admin.0.organization)))
+            .set((
+                logo.eq(Some(image)),
+                logo_content_type.eq(Some(content_type.to_string())),
+                logo_updated_at.eq(Some(Utc::now())),
+            ))
+            .execute(&connection)?;
+    }
+
+    Ok(accept.ser(load_branding(&connection, admin.0.organization)?))
+}
+
+/// DELETE `/admin/organization/logo`
+#[delete("/admin/organization/logo")]
+pub fn delete_logo(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Branding>, SJ> {
+    let connection = db_pool.inner().get()?;
+    {
+        use crate::schema::tables::organizations::dsl::*;
+        diesel::update(organizations.filter(id.eq(admin.0.organization)))
+            .set((
+                logo.eq(None::<Vec<u8>>),
+                logo_content_type.eq(None::<String>),
+                logo_updated_at.eq(None::<DateTime<Utc>>),
+            ))
+            .execute(&connection)?;
+    }
+
+    Ok(accept.ser(load_branding(&connection, admin.0.organization)?))
+}
+
+fn validate(mut settings: BrandingSettings) -> Result<BrandingSettings, SJ> {
+    settings.name = settings.name.trim().to_string();
+
+    let non_empty = |field: Option<String>| {
+        field
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    settings.org_number = non_empty(settings.org_number);
+    settings.footer_text = non_empty(settings.footer_text);
+
+    settings.validate()?;
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, TestDb};
+    use rocket::http::{ContentType, Status};
+
+    #[test]
+    fn test_branding() {
+        use strecklistan_api::organization::{Branding, BrandingSettings};
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("admin", "hunter2", org, true);
+
+        let client = db.client();
+        login(&client, "admin", "hunter2");
+
+        let branding: Branding = get_json(&client, "/api/organization/branding");
+        assert_eq!(branding.name, "Test");
+        assert_eq!(branding.logo_url(), None);
+
+        let settings = BrandingSettings {
+            name: " Teknologsektionen ".to_string(),
+            org_number: Some("802412-3456".to_string()),
+            footer_text: Some(" ".to_string()),
+        };
+        let response = client
+            .put("/api/admin/organization")
+            .header(ContentType::JSON)
+            .body(serde_json::to_string(&settings).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let branding: Branding = get_json(&client, "/api/organization/branding");
+        assert_eq!(branding.name, "Teknologsektionen");
+        assert_eq!(branding.org_number.as_deref(), Some("802412-3456"));
+        assert_eq!(branding.footer_text, None);
+
+        let image = vec![0x89, b'P', b'N', b'G', 1, 2, 3];
+        let response = client
+            .put("/api/admin/organization/logo")
+            .header(ContentType::Plain)
+            .body(image.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::UnsupportedMediaType);
+        let response = client
+            .put("/api/admin/organization/logo")
+            .header(ContentType::PNG)
+            .body(image.clone())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let branding: Branding = get_json(&client, "/api/organization/branding");
+        assert!(branding.logo_url().is_some());
+        let response = client.get("/api/organization/logo").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::PNG));
+        assert_eq!(response.into_bytes(), Some(image));
+
+        let response = client.delete("/api/admin/organization/logo").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let response = client.get("/api/organization/logo").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::auth::Session;
+use crate::database::DatabasePool;
+use crate::routes::rest::book_account::check_accounts;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, State};
+use strecklistan_api::parked_cart::{NewParkedCart, ParkedCart, ParkedCartId};
+use strecklistan_api::validation::Validate;
+
+/// GET `/parked_carts`
+///
+/// Returns all parked carts, oldest first
+#[get("/parked_carts")]
+pub fn get_parked_carts(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<ParkedCart>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::parked_carts::dsl::*;
+
+    let rows: Vec<(i32, String, DateTime<Utc>, Option<i32>, serde_json::Value)> = parked_carts
+        .filter(organization_id.eq(session.organization))
+        .select((id, name, created_at, debited_account, bundles))
+        .order_by(created_at.asc())
+        .load(&connection)?;
+
+    let carts = rows
+        .into_iter()
+        .map(|(cart_id, cart_name, created, account, cart_bundles)| {
+            Ok(ParkedCart {
+                id: cart_id,
+                name: cart_name,
+                created_at: created,
+                debited_account: account,
+                bundles: serde_json::from_value(cart_bundles).map_err(|e| {
+                    SJ::new(
+                        Status::InternalServerError,
+                        format!("Stored cart is invalid: {}", e),
+                    )
+                })?,
+            })
+        })
+        .collect::<Result<_, SJ>>()?;
+
+    Ok(accept.ser(carts))
+}
+
+/// POST `/parked_cart`
+///
+/// Park a cart, returns the id of the new parked cart
+#[post("/parked_cart", data = "<cart>")]
+pub fn park_cart(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    cart: Json<NewParkedCart>,
+) -> Result<Ser<ParkedCartId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::parked_carts::dsl::*;
+
+    let cart = cart.into_inner();
+    cart.validate()?;
+    if let Some(account) = cart.debited_account {
+        check_accounts(&connection, session.organization, &[account])?;
+    }
+
+    let cart_bundles = serde_json::to_value(&cart.bundles)
+        .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+    let cart_id = diesel::insert_into(parked_carts)
+        .values((
+            name.eq(cart.name.trim()),
+            debited_account.eq(cart.debited_account),
+            bundles.eq(cart_bundles),
+            organization_id.eq(session.organization),
+        ))
+        .returning(id)
+        .get_result(&connection)?;
+
+    Ok(accept.ser(cart_id))
+}
+
+/// DELETE `/parked_cart/<cart_id>`
+///
+/// Remove a parked cart, either when it has been resumed or when it is discarded
+#[delete("/parked_cart/<cart_id>")]
+pub fn delete_parked_cart(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    cart_id: ParkedCartId,
+) -> Result<Ser<ParkedCartId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::parked_carts::dsl::*;
+
+    let deleted_id = diesel::delete(
+        parked_carts
+            .filter(id.eq(cart_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .returning(id)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(deleted_id))
+}
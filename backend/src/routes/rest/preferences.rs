@@ -0,0 +1,76 @@
+use crate::auth::{is_admin_of, Session};
+use crate::database::DatabasePool;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, put, State};
+use strecklistan_api::preferences::{DefaultPage, UserPreferences};
+
+/// GET `/preferences`
+///
+/// The preferences of the logged in user, or the defaults if none are saved. Admins land on the
+/// dashboard by default.
+#[get("/preferences")]
+pub fn get_preferences(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<UserPreferences>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::user_preferences::dsl::*;
+
+    let stored: Option<serde_json::Value> = user_preferences
+        .filter(user_name.eq(&session.user))
+        .select(preferences)
+        .first(&connection)
+        .optional()?;
+
+    let prefs = match stored {
+        Some(value) => serde_json::from_value(value).map_err(|e| {
+            SJ::new(
+                Status::InternalServerError,
+                format!("Stored preferences are invalid: {}", e),
+            )
+        })?,
+        None => {
+            let admin = is_admin_of(&connection, &session.user, session.organization)?;
+            UserPreferences {
+                default_page: if admin {
+                    DefaultPage::Dashboard
+                } else {
+                    DefaultPage::Store
+                },
+                ..UserPreferences::default()
+            }
+        }
+    };
+
+    Ok(accept.ser(prefs))
+}
+
+/// PUT `/preferences`
+#[put("/preferences", data = "<prefs>")]
+pub fn put_preferences(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    prefs: Json<UserPreferences>,
+) -> Result<Ser<UserPreferences>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::user_preferences::dsl::*;
+
+    let prefs = prefs.into_inner();
+    let value = serde_json::to_value(&prefs)
+        .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+    diesel::insert_into(user_preferences)
+        .values((user_name.eq(&session.user), preferences.eq(&value)))
+        .on_conflict(user_name)
+        .do_update()
+        .set(preferences.eq(&value))
+        .execute(&connection)?;
+
+    Ok(accept.ser(prefs))
+}
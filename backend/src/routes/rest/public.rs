@@ -0,0 +1,320 @@
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::routes::rest::book_account::find_master_accounts;
+use crate::routes::rest::goal::{load_goals, load_progress};
+use crate::routes::rest::transaction::load_transactions;
+use crate::util::rate_limit::{Public, RateLimit};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::{get, State};
+use strecklistan_api::book_account::{tillgodo_balances, BookAccountId};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::goal::GoalTarget;
+use strecklistan_api::member::MemberId;
+use strecklistan_api::public::{
+    PublicBalance, PublicDeposit, PublicOpenStatus, PublicStats, PublicStock,
+};
+use strecklistan_api::time::{PeriodKind, ReportingPeriod};
+
+/// How many of the latest deposits are shown with a balance
+const PUBLIC_DEPOSITS: usize = 5;
+
+/// GET `/public/stats`
+///
+/// Aggregates of the public organization which may be shown to anyone. Doesn't require a login,
+/// so it's rate limited per client instead.
+#[get("/public/stats")]
+pub fn get_public_stats(
+    _limit: RateLimit<Public>,
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    accept: SerAccept,
+) -> Result<Ser<PublicStats>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let organization = config.public.organization;
+    let tz = config.reporting_timezone;
+
+    let day = ReportingPeriod::at(PeriodKind::Day, Utc::now(), tz).range(tz);
+
+    // anyone may call this, so it only reads. Without the accounts nothing has been sold yet.
+    let sales_account = match find_master_accounts(&connection, organization)? {
+        Some(masters) => masters.sales_account_id,
+        None => {
+            return Ok(accept.ser(PublicStats {
+                items_sold_today: 0,
+                stock: vec![],
+                goals: vec![],
+            }))
+        }
+    };
+
+    let items_sold_today = load_transactions(&connection, organization, Some(day))?
+        .iter()
+        .filter(|tr| tr.credited_account == sales_account)
+        .flat_map(|tr| &tr.bundles)
+        .map(|bundle| {
+            let per_bundle: u32 = bundle.item_ids.values().sum();
+            -bundle.change * per_bundle as i32
+        })
+        .sum();
+
+    let stock: Vec<PublicStock> = {
+        use crate::schema::tables::inventory::dsl::{id as item_id, inventory, organization_id};
+        use crate::schema::tables::inventory_tags::dsl::{
+            inventory_tags, item_id as tag_item_id, tag,
+        };
+        use crate::schema::views::inventory_stock::dsl::*;
+        inventory_stock
+            .filter(
+                id.eq_any(
+                    inventory
+                        .filter(organization_id.eq(organization))
+                        .select(item_id),
+                ),
+            )
+            .filter(
+                id.eq_any(
+                    inventory_tags
+                        .filter(tag.eq(&config.public.stock_tag))
+                        .select(tag_item_id),
+                ),
+            )
+            .order_by(name.asc())
+            .select((name, image_url, stock))
+            .load::<(String, Option<String>, i32)>(&connection)?
+            .into_iter()
+            .map(|(item_name, item_image, item_stock)| PublicStock {
+                name: item_name,
+                image_url: item_image,
+                stock: item_stock,
+            })
+            .collect()
+    };
+
+    // revenue is kept within the organization
+    let goals = load_goals(&connection, organization, tz)?
+        .into_iter()
+        .filter(|goal| matches!(goal.target, GoalTarget::Quantity(_)))
+        .collect();
+    let goals = load_progress(&connection, organization, tz, sales_account, goals)?;
+
+    Ok(accept.ser(PublicStats {
+        items_sold_today,
+        stock,
+        goals,
+    }))
+}
+
+/// GET `/public/balance/<token>`
+///
+/// The tillgodo balance of the member whose balance card has the code `token`. Doesn't require a
+/// login, so it's rate limited per client instead, which also keeps the codes from being guessed.
+#[get("/public/balance/<token>")]
+pub fn get_public_balance(
+    _limit: RateLimit<Public>,
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    accept: SerAccept,
+    token: String,
+) -> Result<Ser<PublicBalance>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let (member, member_name): (MemberId, String) = {
+        use crate::schema::tables::members::dsl::*;
+        members
+            .filter(balance_token.eq(&token))
+            .filter(archived_at.is_null())
+            .select((id, first_name))
+            .first(&connection)
+            .optional()?
+            .ok_or_else(|| SJ::new(Status::NotFound, "No such balance card"))?
+    };
+
+    let accounts: Vec<BookAccountId> = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(creditor.eq(member))
+            .select(id)
+            .load(&connection)?
+    };
+
+    // newest first
+    let rows: Vec<(BookAccountId, BookAccountId, i64, DateTime<Utc>)> = {
+        use crate::schema::tables::transactions::dsl::*;
+        transactions
+            .filter(deleted_at.is_null())
+            .filter(
+                debited_account
+                    .eq_any(&accounts)
+                    .or(credited_account.eq_any(&accounts)),
+            )
+            .order_by((time.desc(), id.desc()))
+            .select((debited_account, credited_account, amount, time))
+            .load(&connection)?
+    };
+
+    // transfers between the accounts of the member aren't deposits
+    let deposits = rows
+        .iter()
+        .filter(|(debited, credited, _, _)| {
+            !accounts.contains(debited) && accounts.contains(credited)
+        })
+        .take(PUBLIC_DEPOSITS)
+        .map(|&(_, _, change, at)| PublicDeposit {
+            time: at,
+            amount: Currency::from(change),
+        })
+        .collect();
+
+    let balances = tillgodo_balances(
+        &accounts,
+        rows.into_iter()
+            .map(|(debited, credited, change, at)| (debited, credited, Currency::from(change), at)),
+    );
+    let balance: Currency = balances.values().map(|account| account.balance).sum();
+
+    Ok(accept.ser(PublicBalance {
+        first_name: member_name,
+        balance,
+        deposits,
+        swish: config.swish.is_some(),
+    }))
+}
+
+/// GET `/public/open`
+///
+/// Whether the register of the public organization is open, for e.g. Home Assistant to poll. The
+/// webhooks are also told with `store.opened` and `store.closed`.
+#[get("/public/open")]
+pub fn get_public_open(
+    _limit: RateLimit<Public>,
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    accept: SerAccept,
+) -> Result<Ser<PublicOpenStatus>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    // at most one shift is open, and it's the latest one
+    let latest: Option<(DateTime<Utc>, Option<DateTime<Utc>>)> = {
+        use crate::schema::tables::register_shifts::dsl::*;
+        register_shifts
+            .filter(organization_id.eq(config.public.organization))
+            .order_by(opened_at.desc())
+            .select((opened_at, closed_at))
+            .first(&connection)
+            .optional()?
+    };
+
+    let status = match latest {
+        Some((opened, None)) => PublicOpenStatus {
+            open: true,
+            since: Some(opened),
+        },
+        Some((_, closed)) => PublicOpenStatus {
+            open: false,
+            since: closed,
+        },
+        None => PublicOpenStatus {
+            open: false,
+            since: None,
+        },
+    };
+    Ok(accept.ser(status))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, TestDb};
+    use rocket::http::{ContentType, Status};
+    use strecklistan_api::book_account::BookAccountType;
+
+    #[test]
+    fn test_balance_card() {
+        use strecklistan_api::public::PublicBalance;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (member, member_account) = db.member(org, "Testsson");
+        db.transaction(org, cash, member_account, 10000.into());
+        db.transaction(org, member_account, cash, 2500.into());
+        db.transaction(org, cash, member_account, 500.into());
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let card_uri = format!("/api/member/{}/balance_card", member);
+        let token: Option<String> = get_json(&client, &card_uri);
+        assert_eq!(token, None);
+
+        let response = client.put(&card_uri).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let token: String = response.into_json().unwrap();
+
+        let svg_uri = format!(
+            "/api/member/{}/balance_card.svg?page=https://example.com/balance",
+            member
+        );
+        let response = client.get(&svg_uri).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(ContentType::SVG));
+        assert!(response.into_string().unwrap().starts_with("<svg"));
+        let bad_uri = format!(
+            "/api/member/{}/balance_card.svg?page=javascript:alert(1)",
+            member
+        );
+        assert_eq!(client.get(&bad_uri).dispatch().status(), Status::BadRequest);
+
+        // anyone with the card can see the balance
+        let public = db.client();
+        let balance: PublicBalance = get_json(&public, &format!("/api/public/balance/{}", token));
+        assert_eq!(balance.first_name, "Testsson");
+        assert_eq!(balance.balance, 8000.into());
+        assert_eq!(
+            balance
+                .deposits
+                .iter()
+                .map(|deposit| deposit.amount)
+                .collect::<Vec<_>>(),
+            vec![500.into(), 10000.into()]
+        );
+
+        // a lost card stops working once it's replaced
+        let response = client.put(&card_uri).dispatch();
+        let new_token: String = response.into_json().unwrap();
+        assert_ne!(new_token, token);
+        let response = public
+            .get(format!("/api/public/balance/{}", token))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_stats_without_accounts() {
+        use diesel::dsl::count_star;
+        use diesel::prelude::*;
+        use strecklistan_api::public::PublicStats;
+
+        let mut db = TestDb::new();
+        let org = db.organization("Test");
+        db.config.public.organization = org;
+
+        // the stats are public, so they must not create the missing master accounts
+        let stats: PublicStats = get_json(&db.client(), "/api/public/stats");
+        assert_eq!(stats.items_sold_today, 0);
+        assert!(stats.goals.is_empty());
+
+        let accounts: i64 = {
+            use crate::schema::tables::book_accounts::dsl::*;
+            book_accounts
+                .filter(organization_id.eq(org))
+                .select(count_star())
+                .first(&db.conn())
+                .unwrap()
+        };
+        assert_eq!(accounts, 0);
+    }
+}
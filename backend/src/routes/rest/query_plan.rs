@@ -0,0 +1,103 @@
+use crate::auth::AdminSession;
+use crate::database::DatabasePool;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::deserialize::{self, QueryableByName};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::row::NamedRow;
+use diesel::sql_types::Text;
+use rocket::{get, State};
+use serde::Serialize;
+use strecklistan_api::organization::OrganizationId;
+
+/// The queries which run the most, or over the most rows.
+///
+/// They mirror the diesel queries of the routes named, with `$1` standing in for the
+/// organization. Keep them in sync when changing those routes.
+const HOT_QUERIES: &[(&str, &str)] = &[
+    (
+        "GET /transactions",
+        "SELECT * FROM transactions
+            LEFT JOIN transaction_bundles ON transactions.id = transaction_bundles.transaction_id
+            LEFT JOIN transaction_items ON transaction_bundles.id = transaction_items.bundle_id
+         WHERE transactions.deleted_at IS NULL AND transactions.organization_id = $1
+         ORDER BY transactions.time DESC, transactions.id DESC",
+    ),
+    (
+        "GET /izettle/bridge/poll",
+        "SELECT id, amount, description, organization_id FROM izettle_transaction
+         ORDER BY time ASC LIMIT 1",
+    ),
+    (
+        "GET /members/search",
+        "SELECT * FROM book_accounts
+            INNER JOIN members ON members.id = book_accounts.creditor
+         WHERE book_accounts.organization_id = $1 AND members.archived_at IS NULL",
+    ),
+    (
+        "GET /inventory/items",
+        "SELECT inventory_stock.*, inventory.archived_at FROM inventory_stock
+            INNER JOIN inventory ON inventory.id = inventory_stock.id
+         WHERE inventory.organization_id = $1",
+    ),
+    (
+        "GET /inventory/barcodes",
+        "SELECT * FROM inventory_barcodes
+         WHERE item_id IN (SELECT id FROM inventory WHERE organization_id = $1)",
+    ),
+];
+
+#[derive(Serialize)]
+pub struct QueryPlan {
+    pub route: &'static str,
+    pub query: &'static str,
+    pub plan: Vec<String>,
+}
+
+/// One line of the output of EXPLAIN
+struct PlanLine(String);
+
+impl QueryableByName<Pg> for PlanLine {
+    fn build<R: NamedRow<Pg>>(row: &R) -> deserialize::Result<Self> {
+        row.get::<Text, String>("QUERY PLAN").map(PlanLine)
+    }
+}
+
+/// GET `/admin/query_plans`
+///
+/// How postgres runs the hot queries for the organization of the admin, with actual timings.
+/// For finding missing indexes.
+#[get("/admin/query_plans")]
+pub fn get_query_plans(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    accept: SerAccept,
+) -> Result<Ser<Vec<QueryPlan>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let plans = HOT_QUERIES
+        .iter()
+        .map(|&(route, query)| {
+            Ok(QueryPlan {
+                route,
+                query,
+                plan: explain(&connection, query, admin.0.organization)?,
+            })
+        })
+        .collect::<QueryResult<_>>()?;
+
+    Ok(accept.ser(plans))
+}
+
+fn explain(
+    connection: &PgConnection,
+    query: &str,
+    organization: OrganizationId,
+) -> QueryResult<Vec<String>> {
+    // the organization is an integer, so it can be put in the query as is
+    let query = query.replace("$1", &organization.to_string());
+    let lines: Vec<PlanLine> =
+        diesel::sql_query(format!("EXPLAIN (ANALYZE, BUFFERS) {}", query)).load(connection)?;
+    Ok(lines.into_iter().map(|PlanLine(line)| line).collect())
+}
@@ -0,0 +1,279 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::routes::rest::book_account::master_accounts;
+use crate::routes::rest::live::LiveEvents;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{Duration, NaiveDate, Utc};
+use diesel::dsl::max;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::{get, post, State};
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::queue::{QueueTicket, QueueTicketId};
+use strecklistan_api::time::local_date;
+use strecklistan_api::transaction::TransactionId;
+
+/// How long an order which was never marked as collected stays in the queue, and on the kitchen
+/// board
+pub const QUEUE_HOURS: i64 = 12;
+
+/// Give a new sale the next number in the queue, if queue tickets are enabled.
+///
+/// Sales are the transactions which credit the sales account, so deposits and refunds aren't
+/// given a number. Must be called in the database transaction which inserts the sale.
+pub fn issue_ticket(
+    connection: &PgConnection,
+    config: &Config,
+    organization: OrganizationId,
+    sale_id: TransactionId,
+    credited_account: BookAccountId,
+) -> Result<Option<i32>, SJ> {
+    if !config.queue.enabled
+        || credited_account != master_accounts(connection, organization)?.sales_account_id
+    {
+        return Ok(None);
+    }
+
+    // two sales at once would otherwise be given the same number
+    {
+        use crate::schema::tables::organizations::dsl::*;
+        organizations
+            .filter(id.eq(organization))
+            .select(id)
+            .for_update()
+            .first::<OrganizationId>(connection)?;
+    }
+
+    use crate::schema::tables::queue_tickets::dsl::*;
+    let today: NaiveDate = local_date(Utc::now(), config.reporting_timezone);
+    let last: Option<i32> = queue_tickets
+        .filter(organization_id.eq(organization))
+        .filter(day.eq(today))
+        .select(max(number))
+        .first(connection)?;
+
+    let next = last.unwrap_or(0) + 1;
+    diesel::insert_into(queue_tickets)
+        .values((
+            organization_id.eq(organization),
+            transaction_id.eq(sale_id),
+            day.eq(today),
+            number.eq(next),
+        ))
+        .execute(connection)?;
+
+    Ok(Some(next))
+}
+
+/// GET `/queue`
+///
+/// The orders which haven't been collected, oldest first
+#[get("/queue")]
+pub fn get_queue(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<QueueTicket>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_queue(&connection, session.organization)?))
+}
+
+/// GET `/queue/transaction/<sale_id>`
+///
+/// The queue ticket of a sale, `null` if it wasn't given one
+#[get("/queue/transaction/<sale_id>")]
+pub fn get_transaction_ticket(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    sale_id: TransactionId,
+) -> Result<Ser<Option<QueueTicket>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::queue_tickets::dsl::*;
+    let ticket = queue_tickets
+        .filter(organization_id.eq(session.organization))
+        .filter(transaction_id.eq(sale_id))
+        .select((id, number, transaction_id, created_at, ready_at))
+        .first(&connection)
+        .optional()?;
+
+    Ok(accept.ser(ticket))
+}
+
+/// POST `/queue/<ticket_id>/ready`
+///
+/// The order has been prepared, so its number is called on the queue page
+#[post("/queue/<ticket_id>/ready")]
+pub fn ready_ticket(
+    db_pool: &State<DatabasePool>,
+    live: &State<LiveEvents>,
+    session: Session,
+    accept: SerAccept,
+    ticket_id: QueueTicketId,
+) -> Result<Ser<Vec<QueueTicket>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    {
+        use crate::schema::tables::queue_tickets::dsl::*;
+        diesel::update(
+            queue_tickets
+                .filter(id.eq(ticket_id))
+                .filter(organization_id.eq(session.organization))
+                .filter(ready_at.is_null())
+                .filter(collected_at.is_null()),
+        )
+        .set(ready_at.eq(Utc::now()))
+        .returning(id)
+        .get_result::<QueueTicketId>(&connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such order being prepared"))?;
+    }
+
+    live.notify(session.organization, LiveEvent::QueueChanged);
+    Ok(accept.ser(load_queue(&connection, session.organization)?))
+}
+
+/// POST `/queue/<ticket_id>/collected`
+///
+/// The customer has picked up the order, so it's removed from the queue
+#[post("/queue/<ticket_id>/collected")]
+pub fn collect_ticket(
+    db_pool: &State<DatabasePool>,
+    live: &State<LiveEvents>,
+    session: Session,
+    accept: SerAccept,
+    ticket_id: QueueTicketId,
+) -> Result<Ser<Vec<QueueTicket>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    {
+        use crate::schema::tables::queue_tickets::dsl::*;
+        diesel::update(
+            queue_tickets
+                .filter(id.eq(ticket_id))
+                .filter(organization_id.eq(session.organization))
+                .filter(collected_at.is_null()),
+        )
+        .set(collected_at.eq(Utc::now()))
+        .returning(id)
+        .get_result::<QueueTicketId>(&connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such order in the queue"))?;
+    }
+
+    live.notify(session.organization, LiveEvent::QueueChanged);
+    Ok(accept.ser(load_queue(&connection, session.organization)?))
+}
+
+/// The orders of an organization which haven't been collected, without those of deleted sales
+fn load_queue(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> Result<Vec<QueueTicket>, SJ> {
+    use crate::schema::tables::queue_tickets::dsl::*;
+    use crate::schema::tables::transactions;
+
+    let since = Utc::now() - Duration::hours(QUEUE_HOURS);
+    let tickets = queue_tickets
+        .inner_join(transactions::table)
+        .filter(organization_id.eq(organization))
+        .filter(collected_at.is_null())
+        .filter(created_at.ge(since))
+        .filter(transactions::deleted_at.is_null())
+        .order_by(created_at.asc())
+        .select((id, number, transaction_id, created_at, ready_at))
+        .load(connection)?;
+
+    Ok(tickets)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_queue() {
+        use crate::config::NegativeStock;
+        use strecklistan_api::queue::QueueTicket;
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let mut db = TestDb::new();
+        db.config.stock.negative_stock = NegativeStock::Warn;
+        db.config.queue.enabled = true;
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let burgare = db.item(org, "Burgare", Some(4000.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let transaction = |debited_account, credited_account| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(4000.into()),
+                change: -1,
+                item_ids: vec![(burgare, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account,
+            credited_account,
+            amount: 4000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let first: i32 = post_json(&client, "/api/transaction", &transaction(cash, sales));
+        let second: i32 = post_json(&client, "/api/transaction", &transaction(cash, sales));
+        // only sales are given a number
+        let deposit: i32 = post_json(&client, "/api/transaction", &transaction(sales, cash));
+
+        let ticket = |id| -> Option<QueueTicket> {
+            get_json(&client, &format!("/api/queue/transaction/{}", id))
+        };
+        let first = ticket(first).unwrap();
+        assert_eq!(first.number, 1);
+        assert_eq!(ticket(second).unwrap().number, 2);
+        assert_eq!(ticket(deposit), None);
+
+        let queue: Vec<QueueTicket> = get_json(&client, "/api/queue");
+        assert_eq!(
+            queue.iter().map(|t| t.number).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert!(!queue[0].is_ready());
+
+        let ready = format!("/api/queue/{}/ready", first.id);
+        let queue: Vec<QueueTicket> = post_json(&client, &ready, &());
+        assert!(queue[0].is_ready());
+        assert_eq!(post(&client, &ready, &()), Status::NotFound);
+
+        let collected = format!("/api/queue/{}/collected", first.id);
+        let queue: Vec<QueueTicket> = post_json(&client, &collected, &());
+        assert_eq!(queue.iter().map(|t| t.number).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(post(&client, &collected, &()), Status::NotFound);
+
+        // the number isn't reused, and the ticket leaves the queue with its sale
+        let third: i32 = post_json(&client, "/api/transaction", &transaction(cash, sales));
+        assert_eq!(ticket(third).unwrap().number, 3);
+        let uri = format!("/api/transaction/{}", third);
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::Ok);
+        let queue: Vec<QueueTicket> = get_json(&client, "/api/queue");
+        assert_eq!(queue.len(), 1);
+    }
+}
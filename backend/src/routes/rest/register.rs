@@ -0,0 +1,353 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::routes::rest::live::LiveEvents;
+use crate::util::device::ThisDevice;
+use crate::util::outbox;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::Utc;
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::serde::json::json;
+use rocket::{get, post, State};
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::register::{
+    closing_deadline, RegisterShift, RegisterShiftId, RegisterStatus,
+};
+
+/// Whether the register of an organization is open, and which shifts need to be reconciled
+pub fn load_status(
+    connection: &PgConnection,
+    config: &Config,
+    organization: OrganizationId,
+) -> Result<RegisterStatus, SJ> {
+    use crate::schema::tables::register_shifts::dsl::*;
+    let columns = (
+        id,
+        opened_by,
+        opened_at,
+        closed_by,
+        closed_at,
+        auto_closed,
+        reconciled_by,
+        reconciled_at,
+        opened_on,
+        closed_on,
+    );
+
+    let open_shift: Option<RegisterShift> = register_shifts
+        .filter(organization_id.eq(organization))
+        .filter(closed_at.is_null())
+        .select(columns)
+        .first(connection)
+        .optional()?;
+
+    let unreconciled: Vec<RegisterShift> = register_shifts
+        .filter(organization_id.eq(organization))
+        .filter(auto_closed.eq(true))
+        .filter(reconciled_at.is_null())
+        .order_by(opened_at.asc())
+        .select(columns)
+        .load(connection)?;
+
+    let closes_at = match (&open_shift, config.closing.closing_time()) {
+        (Some(shift), Some(time)) => Some(closing_deadline(
+            shift.opened_at,
+            time,
+            config.reporting_timezone,
+        )),
+        _ => None,
+    };
+
+    Ok(RegisterStatus {
+        open_shift,
+        closes_at,
+        unreconciled,
+    })
+}
+
+/// Tell the webhooks that the store opened or closed, which it does along with the register.
+///
+/// Unlike the `register.*` events, these carry nothing but whether the store is open, so that
+/// e.g. a sign by the door can be lit without knowing about shifts.
+pub fn notify_store(
+    connection: &PgConnection,
+    config: &Config,
+    organization: OrganizationId,
+    open: bool,
+) -> QueryResult<()> {
+    let event = if open { "store.opened" } else { "store.closed" };
+    outbox::notify_webhooks(
+        connection,
+        config,
+        organization,
+        event,
+        json!({ "open": open }),
+    )
+}
+
+/// GET `/register`
+///
+/// Whether the register is open, when it should be closed, and which shifts were closed
+/// automatically without the cash being counted
+#[get("/register")]
+pub fn get_register(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<RegisterStatus>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_status(&connection, config, session.organization)?))
+}
+
+/// POST `/register/open`
+///
+/// Open the register for the day
+#[post("/register/open")]
+pub fn open_register(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    session: Session,
+    device: ThisDevice,
+    accept: SerAccept,
+) -> Result<Ser<RegisterStatus>, SJ> {
+    session.check_not_training()?;
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::register_shifts::dsl::*;
+        let already_open: Option<RegisterShiftId> = register_shifts
+            .filter(organization_id.eq(session.organization))
+            .filter(closed_at.is_null())
+            .select(id)
+            .for_update()
+            .first(&connection)
+            .optional()?;
+        if already_open.is_some() {
+            return Err(SJ::new(Status::Conflict, "The register is already open"));
+        }
+
+        let shift_id: RegisterShiftId = diesel::insert_into(register_shifts)
+            .values((
+                organization_id.eq(session.organization),
+                opened_by.eq(&session.user),
+                opened_on.eq(device.name()),
+            ))
+            .returning(id)
+            .get_result(&connection)?;
+
+        outbox::notify_webhooks(
+            &connection,
+            config,
+            session.organization,
+            "register.opened",
+            json!({
+                "shift_id": shift_id,
+                "opened_by": session.user,
+            }),
+        )?;
+        notify_store(&connection, config, session.organization, true)?;
+        Ok(())
+    })?;
+
+    live.notify(session.organization, LiveEvent::RegisterChanged);
+    Ok(accept.ser(load_status(&connection, config, session.organization)?))
+}
+
+/// POST `/register/close`
+///
+/// Close the register at the end of the day, once the cash has been counted
+#[post("/register/close")]
+pub fn close_register(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    session: Session,
+    device: ThisDevice,
+    accept: SerAccept,
+) -> Result<Ser<RegisterStatus>, SJ> {
+    session.check_not_training()?;
+    let connection = db_pool.inner().get()?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::register_shifts::dsl::*;
+        let shift_id: RegisterShiftId = diesel::update(
+            register_shifts
+                .filter(organization_id.eq(session.organization))
+                .filter(closed_at.is_null()),
+        )
+        .set((
+            closed_by.eq(&session.user),
+            closed_at.eq(Utc::now()),
+            closed_on.eq(device.name()),
+        ))
+        .returning(id)
+        .get_result(&connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "The register isn't open"))?;
+
+        outbox::notify_webhooks(
+            &connection,
+            config,
+            session.organization,
+            "register.closed",
+            json!({
+                "shift_id": shift_id,
+                "closed_by": session.user,
+            }),
+        )?;
+        notify_store(&connection, config, session.organization, false)?;
+        Ok(())
+    })?;
+
+    live.notify(session.organization, LiveEvent::RegisterChanged);
+    Ok(accept.ser(load_status(&connection, config, session.organization)?))
+}
+
+/// POST `/register/shifts/<shift_id>/reconcile`
+///
+/// Mark a shift which was closed automatically as reconciled, once the cash has been counted
+#[post("/register/shifts/<shift_id>/reconcile")]
+pub fn reconcile_shift(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    session: Session,
+    accept: SerAccept,
+    shift_id: RegisterShiftId,
+) -> Result<Ser<RegisterStatus>, SJ> {
+    session.check_not_training()?;
+    let connection = db_pool.inner().get()?;
+
+    {
+        use crate::schema::tables::register_shifts::dsl::*;
+        diesel::update(
+            register_shifts
+                .filter(id.eq(shift_id))
+                .filter(organization_id.eq(session.organization))
+                .filter(auto_closed.eq(true))
+                .filter(reconciled_at.is_null()),
+        )
+        .set((
+            reconciled_by.eq(&session.user),
+            reconciled_at.eq(Utc::now()),
+        ))
+        .returning(id)
+        .get_result::<RegisterShiftId>(&connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such shift to reconcile"))?;
+    }
+
+    live.notify(session.organization, LiveEvent::RegisterChanged);
+    Ok(accept.ser(load_status(&connection, config, session.organization)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+
+    #[test]
+    fn test_register_closing() {
+        use crate::config::WebhookConfig;
+        use crate::schema::tables::outbox::dsl::*;
+        use crate::util::closing::check_closing;
+        use chrono::Duration;
+        use diesel::prelude::*;
+        use strecklistan_api::public::PublicOpenStatus;
+        use strecklistan_api::register::RegisterStatus;
+
+        let mut db = TestDb::new();
+        db.config.webhooks = vec![WebhookConfig {
+            url: "http://127.0.0.1:9/hook".to_string(),
+            secret: None,
+        }];
+        db.config.closing.time = Some("02:00".to_string());
+        db.config.closing.auto_close_after_minutes = Some(60);
+        let org = db.organization("Test");
+        db.config.public.organization = org;
+        db.user("tester", "hunter2", org, false);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let check = |now| check_closing(&db.conn(), &db.config, now).unwrap();
+        let webhook_calls = || -> i64 { outbox.count().get_result(&db.conn()).unwrap() };
+        let store_events = || -> Vec<String> {
+            outbox
+                .order_by(id)
+                .select(payload)
+                .load::<serde_json::Value>(&db.conn())
+                .unwrap()
+                .into_iter()
+                .map(|call| call["event"]["event"].as_str().unwrap().to_string())
+                .filter(|event| event.starts_with("store."))
+                .collect()
+        };
+
+        let open: PublicOpenStatus = get_json(&client, "/api/public/open");
+        assert_eq!(
+            open,
+            PublicOpenStatus {
+                open: false,
+                since: None
+            }
+        );
+
+        let status: RegisterStatus = post_json(&client, "/api/register/open", &());
+        let shift = status.open_shift.clone().unwrap();
+        let closes_at = status.closes_at.unwrap();
+        assert!(!status.is_overdue(shift.opened_at));
+        assert_eq!(post(&client, "/api/register/open", &()), Status::Conflict);
+        // register.opened and store.opened
+        assert_eq!(webhook_calls(), 2);
+        let open: PublicOpenStatus = get_json(&client, "/api/public/open");
+        assert!(open.open);
+        assert_eq!(open.since, Some(shift.opened_at));
+
+        assert_eq!(check(closes_at - Duration::minutes(1)), vec![]);
+
+        // reminded once at closing time
+        assert_eq!(check(closes_at), vec![org]);
+        assert_eq!(check(closes_at + Duration::minutes(30)), vec![]);
+        assert_eq!(webhook_calls(), 3);
+        let status: RegisterStatus = get_json(&client, "/api/register");
+        assert!(status.is_overdue(closes_at));
+
+        // and closed by the server an hour later
+        assert_eq!(check(closes_at + Duration::hours(1)), vec![org]);
+        // register.auto_closed and store.closed
+        assert_eq!(webhook_calls(), 5);
+        let open: PublicOpenStatus = get_json(&client, "/api/public/open");
+        assert!(!open.open);
+        let status: RegisterStatus = get_json(&client, "/api/register");
+        assert_eq!(status.open_shift, None);
+        assert_eq!(status.unreconciled.len(), 1);
+        assert!(status.unreconciled[0].needs_reconciliation());
+        assert_eq!(post(&client, "/api/register/close", &()), Status::NotFound);
+
+        let uri = format!("/api/register/shifts/{}/reconcile", shift.id);
+        let status: RegisterStatus = post_json(&client, &uri, &());
+        assert!(status.unreconciled.is_empty());
+        assert_eq!(post(&client, &uri, &()), Status::NotFound);
+
+        // a register closed by hand needs no reconciliation
+        let _: RegisterStatus = post_json(&client, "/api/register/open", &());
+        let status: RegisterStatus = post_json(&client, "/api/register/close", &());
+        assert_eq!(status.open_shift, None);
+        assert!(status.unreconciled.is_empty());
+        assert_eq!(
+            store_events(),
+            vec![
+                "store.opened",
+                "store.closed",
+                "store.opened",
+                "store.closed"
+            ]
+        );
+    }
+}
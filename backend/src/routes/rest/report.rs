@@ -0,0 +1,370 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::database::{DatabasePool, ReportingPool};
+use crate::models::book_account as relational;
+use crate::routes::rest::book_account::reporting_master_accounts;
+use crate::routes::rest::transaction::load_transactions;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::{get, State};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use strecklistan_api::book_account::{BookAccount, BookAccountId, MasterAccounts};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::report::{
+    DailyReport, ItemStockValue, MonthlyReport, PaymentFees, PaymentProvider, StockValuation,
+    StockValueReport,
+};
+use strecklistan_api::time::{PeriodKind, ReportingPeriod};
+
+/// GET `/report/daily/<date>`
+///
+/// The end-of-day summary (Z-report) of `date` (YYYY-MM-DD) in the reporting timezone
+#[get("/report/daily/<date>")]
+pub fn get_daily_report(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    date: String,
+) -> Result<Ser<DailyReport>, SJ> {
+    let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+        .map_err(|_| SJ::new(Status::BadRequest, "Invalid date, expected YYYY-MM-DD"))?;
+    let tz = config.reporting_timezone;
+
+    let masters = reporting_master_accounts(db_pool, session.organization)?;
+    let connection = reporting_pool.inner().get()?;
+
+    let day = ReportingPeriod::containing(PeriodKind::Day, date).range(tz);
+    let transactions = load_transactions(&connection, session.organization, Some(day.clone()))?;
+
+    let accounts: HashMap<BookAccountId, BookAccount> = {
+        use crate::schema::tables::book_accounts::dsl::{book_accounts, organization_id};
+        book_accounts
+            .filter(organization_id.eq(session.organization))
+            .load(&connection)?
+            .into_iter()
+            .map(|acc: relational::BookAccount| (acc.id, acc.into()))
+            .collect()
+    };
+
+    let item_names: HashMap<InventoryItemId, String> = {
+        use crate::schema::tables::inventory::dsl::*;
+        inventory
+            .filter(organization_id.eq(session.organization))
+            .select((id, name))
+            .load(&connection)?
+            .into_iter()
+            .filter_map(|(item_id, item_name): (i32, Option<String>)| Some((item_id, item_name?)))
+            .collect()
+    };
+
+    let fees = payment_fees(&connection, config, session.organization, &masters, day)?;
+
+    Ok(accept.ser(DailyReport::compute(
+        date,
+        tz,
+        &transactions,
+        &accounts,
+        &masters,
+        &item_names,
+        &fees,
+    )))
+}
+
+/// GET `/report/monthly/<month>`
+///
+/// The fees which the payment providers are expected to have kept during `month` (YYYY-MM) in the
+/// reporting timezone, day by day, for matching them against the bank statement
+#[get("/report/monthly/<month>")]
+pub fn get_monthly_report(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    month: String,
+) -> Result<Ser<MonthlyReport>, SJ> {
+    let month = NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+        .map_err(|_| SJ::new(Status::BadRequest, "Invalid month, expected YYYY-MM"))?;
+    let tz = config.reporting_timezone;
+
+    let masters = reporting_master_accounts(db_pool, session.organization)?;
+    let connection = reporting_pool.inner().get()?;
+
+    let range = ReportingPeriod::containing(PeriodKind::Month, month).range(tz);
+    let transactions = load_transactions(&connection, session.organization, Some(range.clone()))?;
+    let fees = payment_fees(&connection, config, session.organization, &masters, range)?;
+
+    Ok(accept.ser(MonthlyReport::compute(month, tz, &transactions, &fees)))
+}
+
+/// Which of the transactions made during `range` were paid through a payment provider, and what
+/// the providers keep of them
+fn payment_fees(
+    connection: &PgConnection,
+    config: &Config,
+    organization: OrganizationId,
+    masters: &MasterAccounts,
+    range: Range<DateTime<Utc>>,
+) -> Result<PaymentFees, SJ> {
+    use crate::schema::tables::transactions::dsl::{
+        credited_account, debited_account, id, organization_id, time, transactions,
+    };
+
+    // tips are only taken on card payments, and booked at the same time as the payment
+    let tips: Vec<i32> = transactions
+        .filter(organization_id.eq(organization))
+        .filter(time.ge(range.start))
+        .filter(time.lt(range.end))
+        .filter(debited_account.eq(masters.bank_account_id))
+        .filter(credited_account.eq(masters.tips_account_id))
+        .select(id)
+        .load(connection)?;
+
+    let izettle: Vec<i32> = {
+        use crate::schema::tables::izettle_post_transaction::dsl::izettle_post_transaction;
+        izettle_post_transaction
+            .inner_join(transactions)
+            .filter(organization_id.eq(organization))
+            .filter(time.ge(range.start))
+            .filter(time.lt(range.end))
+            .select(id)
+            .load(connection)?
+    };
+
+    let swish: Vec<i32> = {
+        use crate::schema::tables::swish_payments::dsl::swish_payments;
+        swish_payments
+            .inner_join(transactions)
+            .filter(organization_id.eq(organization))
+            .filter(time.ge(range.start))
+            .filter(time.lt(range.end))
+            .select(id)
+            .load(connection)?
+    };
+
+    let providers = izettle
+        .into_iter()
+        .chain(tips)
+        .map(|tr_id| (tr_id, PaymentProvider::IZettle))
+        .chain(
+            swish
+                .into_iter()
+                .map(|tr_id| (tr_id, PaymentProvider::Swish)),
+        )
+        .collect();
+
+    Ok(PaymentFees {
+        providers,
+        rates: config.payments.fees.rates(),
+    })
+}
+
+/// GET `/report/stock_value?<fifo>`
+///
+/// The value of everything in stock right now. Items are valued at their cost price, or if `fifo`
+/// is set, at what the latest purchases of them cost. Purchases are the restocks debited to the
+/// purchases account.
+#[get("/report/stock_value?<fifo>")]
+pub fn get_stock_value(
+    db_pool: &State<DatabasePool>,
+    reporting_pool: &State<ReportingPool>,
+    session: Session,
+    accept: SerAccept,
+    fifo: Option<bool>,
+) -> Result<Ser<StockValueReport>, SJ> {
+    let valuation = match fifo {
+        Some(true) => StockValuation::Fifo,
+        _ => StockValuation::CostPrice,
+    };
+
+    let masters = reporting_master_accounts(db_pool, session.organization)?;
+    let connection = reporting_pool.inner().get()?;
+
+    let items: Vec<(InventoryItemId, String, i32, Option<i32>)> = {
+        use crate::schema::tables::inventory::dsl::{
+            cost_price, id as item_id, inventory, organization_id,
+        };
+        use crate::schema::views::inventory_stock::dsl::{id, inventory_stock, name, stock};
+        inventory_stock
+            .inner_join(inventory.on(item_id.eq(id)))
+            .filter(organization_id.eq(session.organization))
+            .filter(stock.gt(0))
+            .order_by((name, id))
+            .select((id, name, stock, cost_price))
+            .load(&connection)?
+    };
+
+    // every purchase of every item, oldest first
+    let purchases: Vec<(InventoryItemId, i32, i32, Option<i64>)> = {
+        use crate::schema::tables::transaction_bundles::dsl::{
+            change, id as bundle_id, price, transaction_bundles, transaction_id,
+        };
+        use crate::schema::tables::transaction_items::dsl::{
+            bundle_id as item_bundle_id, item_id, transaction_items,
+        };
+        use crate::schema::tables::transactions::dsl::{
+            debited_account, deleted_at, id, organization_id, time, transactions,
+        };
+        transaction_items
+            .inner_join(transaction_bundles.on(bundle_id.eq(item_bundle_id)))
+            .inner_join(transactions.on(id.eq(transaction_id)))
+            .filter(deleted_at.is_null())
+            .filter(organization_id.eq(session.organization))
+            .filter(debited_account.eq(masters.purchases_account_id))
+            .filter(change.gt(0))
+            .order_by((time, bundle_id))
+            .select((item_id, bundle_id, change, price))
+            .load(&connection)?
+    };
+
+    // there is one item row for every item in a bundle, so the rows of a bundle are counted
+    let mut bundles: Vec<(i32, i32, Option<i64>, BTreeMap<InventoryItemId, i32>)> = vec![];
+    for (item, bundle, count, bundle_price) in purchases {
+        match bundles.last_mut() {
+            Some((last_bundle, _, _, items)) if *last_bundle == bundle => {
+                *items.entry(item).or_default() += 1;
+            }
+            _ => bundles.push((
+                bundle,
+                count,
+                bundle_price,
+                std::iter::once((item, 1)).collect(),
+            )),
+        }
+    }
+
+    let mut batches: HashMap<InventoryItemId, Vec<(Option<Currency>, i32)>> = HashMap::new();
+    for (_, count, bundle_price, items) in bundles {
+        // the price of a bundle with several different items can't be split between them
+        let single_item = items.len() == 1;
+        for (item, per_bundle) in items {
+            let unit_cost = bundle_price
+                .filter(|_| single_item)
+                .map(|bundle_price| Currency::from(bundle_price / i64::from(per_bundle)));
+            batches
+                .entry(item)
+                .or_default()
+                .push((unit_cost, count * per_bundle));
+        }
+    }
+
+    Ok(accept.ser(StockValueReport {
+        valuation,
+        items: items
+            .into_iter()
+            .map(|(item_id, name, stock, cost_price)| {
+                ItemStockValue::compute(
+                    valuation,
+                    item_id,
+                    name,
+                    stock,
+                    cost_price.map(Currency::from),
+                    batches.get(&item_id).map(Vec::as_slice).unwrap_or_default(),
+                )
+            })
+            .collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_stock_value() {
+        use strecklistan_api::report::{StockValuation, StockValueReport};
+        use strecklistan_api::transaction::TransactionBundle;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let coffee = db.item(org, "Kaffe", Some(1000.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let purchases = db.account(org, "Inköp", BookAccountType::Expenses);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let transaction = |debited_account, credited_account, change, price: i32| {
+            let mut item_ids = HashMap::new();
+            item_ids.insert(coffee, 1);
+            NewTransaction {
+                description: None,
+                bundles: vec![TransactionBundle {
+                    description: None,
+                    price: Some(price.into()),
+                    change,
+                    item_ids,
+                    expires_on: None,
+                    modifier_ids: vec![],
+                }],
+                debited_account,
+                credited_account,
+                amount: (change.abs() * price).into(),
+                note: None,
+                tags: vec![],
+                event_code: None,
+                age_verified: false,
+                member_id: None,
+                idempotency_key: None,
+            }
+        };
+        let _: i32 = post_json(
+            &client,
+            "/api/transaction",
+            &transaction(purchases, cash, 10, 500),
+        );
+        let _: i32 = post_json(
+            &client,
+            "/api/transaction",
+            &transaction(purchases, cash, 20, 400),
+        );
+        let _: i32 = post_json(
+            &client,
+            "/api/transaction",
+            &transaction(cash, sales, -15, 1000),
+        );
+
+        let report: StockValueReport = get_json(&client, "/api/report/stock_value");
+        assert_eq!(report.valuation, StockValuation::CostPrice);
+        assert_eq!(report.items.len(), 1);
+        assert_eq!(report.items[0].stock, 15);
+        assert_eq!(report.items[0].value, None);
+        assert!(report.is_incomplete());
+
+        let response = client
+            .put(format!("/api/inventory/item/{}/cost_price", coffee))
+            .header(ContentType::JSON)
+            .body("450")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let report: StockValueReport = get_json(&client, "/api/report/stock_value");
+        assert_eq!(report.total(), (15 * 450).into());
+
+        // the remaining 15 are from the latest purchase
+        let report: StockValueReport = get_json(&client, "/api/report/stock_value?fifo=true");
+        assert_eq!(report.valuation, StockValuation::Fifo);
+        assert_eq!(report.total(), (15 * 400).into());
+
+        let response = client
+            .put(format!("/api/inventory/item/{}/cost_price", coffee))
+            .header(ContentType::JSON)
+            .body("-100")
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::auth::{AdminSession, Session};
+use crate::database::DatabasePool;
+use crate::models::store_layout::{StoreLayoutButton, StoreLayoutTab};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use diesel::prelude::*;
+use itertools::Itertools;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, put, State};
+use std::collections::HashSet;
+use strecklistan_api::inventory::{InventoryBundleId, InventoryItemId};
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::store_layout::{QuickButton, QuickButtonTarget, StoreLayout, StoreTab};
+
+/// GET `/store/layout`
+///
+/// The hand-arranged quick buttons of the store page
+#[get("/store/layout")]
+pub fn get_store_layout(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<StoreLayout>, SJ> {
+    let connection = db_pool.inner().get()?;
+    Ok(accept.ser(load_store_layout(&connection, session.organization)?))
+}
+
+/// PUT `/store/layout`
+///
+/// Replace the entire store layout. Only admins may arrange the store.
+#[put("/store/layout", data = "<layout>")]
+pub fn put_store_layout(
+    db_pool: &State<DatabasePool>,
+    admin: AdminSession,
+    layout: Json<StoreLayout>,
+) -> Result<Status, SJ> {
+    let connection = db_pool.inner().get()?;
+    let layout = layout.into_inner();
+
+    layout
+        .validate()
+        .map_err(|e| SJ::new(Status::BadRequest, e))?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        replace_store_layout(&connection, admin.0.organization, &layout)?;
+        Ok(Status::NoContent)
+    })
+}
+
+/// The store layout of `organization`, with the tabs in order
+pub fn load_store_layout(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> QueryResult<StoreLayout> {
+    let tabs: Vec<StoreLayoutTab> = {
+        use crate::schema::tables::store_layout_tabs::dsl::*;
+        store_layout_tabs
+            .filter(organization_id.eq(organization))
+            .order_by(position)
+            .load(connection)?
+    };
+    let tab_ids: Vec<i32> = tabs.iter().map(|tab| tab.id).collect();
+
+    let buttons: Vec<StoreLayoutButton> = {
+        use crate::schema::tables::store_layout_buttons::dsl::*;
+        store_layout_buttons
+            .filter(tab_id.eq_any(&tab_ids))
+            .order_by((tab_id, y, x))
+            .load(connection)?
+    };
+
+    let mut buttons = buttons
+        .into_iter()
+        .into_group_map_by(|button| button.tab_id);
+
+    let tabs = tabs
+        .into_iter()
+        .map(|tab| StoreTab {
+            buttons: buttons
+                .remove(&tab.id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|button| {
+                    let target = match (button.item_id, button.bundle_id) {
+                        (Some(item_id), _) => QuickButtonTarget::Item(item_id),
+                        (_, Some(bundle_id)) => QuickButtonTarget::Bundle(bundle_id),
+                        (None, None) => return None,
+                    };
+                    Some(QuickButton {
+                        target,
+                        x: button.x,
+                        y: button.y,
+                        width: button.width,
+                        height: button.height,
+                        color: button.color,
+                    })
+                })
+                .collect(),
+            name: tab.name,
+        })
+        .collect();
+
+    Ok(StoreLayout { tabs })
+}
+
+/// Replace the store layout of `organization` with `layout`, which must be valid
+///
+/// Fails with NotFound if a button points at an item or bundle of another organization.
+pub fn replace_store_layout(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    layout: &StoreLayout,
+) -> Result<(), SJ> {
+    let buttons = || layout.tabs.iter().flat_map(|tab| &tab.buttons);
+    let wanted_items: HashSet<InventoryItemId> = buttons()
+        .filter_map(|button| match button.target {
+            QuickButtonTarget::Item(item) => Some(item),
+            QuickButtonTarget::Bundle(_) => None,
+        })
+        .collect();
+    let wanted_bundles: HashSet<InventoryBundleId> = buttons()
+        .filter_map(|button| match button.target {
+            QuickButtonTarget::Item(_) => None,
+            QuickButtonTarget::Bundle(bundle) => Some(bundle),
+        })
+        .collect();
+
+    let found_items: i64 = {
+        use crate::schema::tables::inventory::dsl::*;
+        inventory
+            .filter(id.eq_any(wanted_items.iter().copied().collect::<Vec<_>>()))
+            .filter(organization_id.eq(organization))
+            .count()
+            .get_result(connection)?
+    };
+    let found_bundles: i64 = {
+        use crate::schema::tables::inventory_bundles::dsl::*;
+        inventory_bundles
+            .filter(id.eq_any(wanted_bundles.iter().copied().collect::<Vec<_>>()))
+            .filter(organization_id.eq(organization))
+            .count()
+            .get_result(connection)?
+    };
+    if found_items as usize != wanted_items.len() || found_bundles as usize != wanted_bundles.len()
+    {
+        return Err(SJ::new(Status::NotFound, "No such item or bundle"));
+    }
+
+    {
+        use crate::schema::tables::store_layout_tabs::dsl::*;
+        // buttons are removed by the cascade
+        diesel::delete(store_layout_tabs.filter(organization_id.eq(organization)))
+            .execute(connection)?;
+    }
+
+    for (tab_position, tab) in layout.tabs.iter().enumerate() {
+        let new_tab_id: i32 = {
+            use crate::schema::tables::store_layout_tabs::dsl::*;
+            diesel::insert_into(store_layout_tabs)
+                .values((
+                    name.eq(&tab.name),
+                    position.eq(tab_position as i32),
+                    organization_id.eq(organization),
+                ))
+                .returning(id)
+                .get_result(connection)?
+        };
+
+        use crate::schema::tables::store_layout_buttons::dsl::*;
+        let rows: Vec<_> = tab
+            .buttons
+            .iter()
+            .map(|button| {
+                let (item, bundle) = match button.target {
+                    QuickButtonTarget::Item(item) => (Some(item), None),
+                    QuickButtonTarget::Bundle(bundle) => (None, Some(bundle)),
+                };
+                (
+                    tab_id.eq(new_tab_id),
+                    item_id.eq(item),
+                    bundle_id.eq(bundle),
+                    x.eq(button.x),
+                    y.eq(button.y),
+                    width.eq(button.width),
+                    height.eq(button.height),
+                    color.eq(&button.color),
+                )
+            })
+            .collect();
+
+        diesel::insert_into(store_layout_buttons)
+            .values(&rows)
+            .execute(connection)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use crate::auth::Session;
+use crate::database::DatabasePool;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::Weekday;
+use diesel::prelude::*;
+use rocket::serde::json::Json;
+use rocket::{delete, get, post, put, State};
+use std::collections::HashMap;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::supplier::{NewSupplier, Supplier, SupplierId};
+use strecklistan_api::validation::Validate;
+
+/// GET `/suppliers`
+///
+/// Returns all suppliers, sorted by name
+#[get("/suppliers")]
+pub fn get_suppliers(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<Supplier>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    let mut item_ids: HashMap<SupplierId, Vec<InventoryItemId>> = HashMap::new();
+    {
+        use crate::schema::tables::inventory::dsl::*;
+        let rows: Vec<(InventoryItemId, Option<SupplierId>)> = inventory
+            .filter(organization_id.eq(session.organization))
+            .filter(supplier_id.is_not_null())
+            .select((id, supplier_id))
+            .order_by(id)
+            .load(&connection)?;
+        for (item_id, supplier) in rows {
+            if let Some(supplier) = supplier {
+                item_ids.entry(supplier).or_default().push(item_id);
+            }
+        }
+    }
+
+    use crate::schema::tables::suppliers::dsl::*;
+    let rows: Vec<(i32, String, Option<String>, Option<String>, Vec<String>)> = suppliers
+        .filter(organization_id.eq(session.organization))
+        .select((id, name, contact, ordering_url, delivery_days))
+        .order_by(name.asc())
+        .load(&connection)?;
+
+    let result = rows
+        .into_iter()
+        .map(
+            |(supplier_id, supplier_name, supplier_contact, url, days)| Supplier {
+                id: supplier_id,
+                name: supplier_name,
+                contact: supplier_contact,
+                ordering_url: url,
+                // the days are only written by this module, so unknown ones are just skipped
+                delivery_days: days.iter().filter_map(|day| day.parse().ok()).collect(),
+                item_ids: item_ids.remove(&supplier_id).unwrap_or_default(),
+            },
+        )
+        .collect();
+
+    Ok(accept.ser(result))
+}
+
+/// POST `/supplier`
+///
+/// Add a supplier, returns the id of the new supplier
+#[post("/supplier", data = "<supplier>")]
+pub fn post_supplier(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    supplier: Json<NewSupplier>,
+) -> Result<Ser<SupplierId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let supplier = validate(supplier.into_inner())?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        let supplier_id = {
+            use crate::schema::tables::suppliers::dsl::*;
+            diesel::insert_into(suppliers)
+                .values((
+                    name.eq(&supplier.name),
+                    contact.eq(&supplier.contact),
+                    ordering_url.eq(&supplier.ordering_url),
+                    delivery_days.eq(day_names(&supplier.delivery_days)),
+                    organization_id.eq(session.organization),
+                ))
+                .returning(id)
+                .get_result(&connection)?
+        };
+
+        link_items(
+            &connection,
+            session.organization,
+            supplier_id,
+            &supplier.item_ids,
+        )?;
+
+        Ok(accept.ser(supplier_id))
+    })
+}
+
+/// PUT `/supplier/<supplier_id>`
+///
+/// Replace everything about a supplier, including which items it supplies
+#[put("/supplier/<supplier_id>", data = "<supplier>")]
+pub fn put_supplier(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    supplier_id: SupplierId,
+    supplier: Json<NewSupplier>,
+) -> Result<Ser<SupplierId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let supplier = validate(supplier.into_inner())?;
+
+    connection.transaction::<_, SJ, _>(|| {
+        {
+            use crate::schema::tables::suppliers::dsl::*;
+            diesel::update(
+                suppliers
+                    .filter(id.eq(supplier_id))
+                    .filter(organization_id.eq(session.organization)),
+            )
+            .set((
+                name.eq(&supplier.name),
+                contact.eq(&supplier.contact),
+                ordering_url.eq(&supplier.ordering_url),
+                delivery_days.eq(day_names(&supplier.delivery_days)),
+            ))
+            .returning(id)
+            .get_result::<SupplierId>(&connection)?;
+        }
+
+        link_items(
+            &connection,
+            session.organization,
+            supplier_id,
+            &supplier.item_ids,
+        )?;
+
+        Ok(accept.ser(supplier_id))
+    })
+}
+
+/// DELETE `/supplier/<supplier_id>`
+///
+/// Remove a supplier, its items are kept but no longer linked to any supplier
+#[delete("/supplier/<supplier_id>")]
+pub fn delete_supplier(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    supplier_id: SupplierId,
+) -> Result<Ser<SupplierId>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::suppliers::dsl::*;
+
+    let deleted_id = diesel::delete(
+        suppliers
+            .filter(id.eq(supplier_id))
+            .filter(organization_id.eq(session.organization)),
+    )
+    .returning(id)
+    .get_result(&connection)?;
+
+    Ok(accept.ser(deleted_id))
+}
+
+fn validate(mut supplier: NewSupplier) -> Result<NewSupplier, SJ> {
+    supplier.name = supplier.name.trim().to_string();
+
+    let non_empty = |field: Option<String>| {
+        field
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+    supplier.contact = non_empty(supplier.contact);
+    supplier.ordering_url = non_empty(supplier.ordering_url);
+
+    supplier
+        .delivery_days
+        .sort_by_key(|day| day.num_days_from_monday());
+    supplier.delivery_days.dedup();
+
+    supplier.validate()?;
+    Ok(supplier)
+}
+
+fn day_names(days: &[Weekday]) -> Vec<String> {
+    days.iter().map(|day| day.to_string()).collect()
+}
+
+/// Make `item_ids` the only items supplied by the supplier.
+///
+/// Items of other organizations are left alone.
+fn link_items(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    supplier: SupplierId,
+    item_ids: &[InventoryItemId],
+) -> Result<(), SJ> {
+    use crate::schema::tables::inventory::dsl::*;
+
+    diesel::update(
+        inventory
+            .filter(supplier_id.eq(supplier))
+            .filter(id.ne_all(item_ids)),
+    )
+    .set(supplier_id.eq(None::<SupplierId>))
+    .execute(connection)?;
+
+    diesel::update(
+        inventory
+            .filter(organization_id.eq(organization))
+            .filter(id.eq_any(item_ids)),
+    )
+    .set(supplier_id.eq(supplier))
+    .execute(connection)?;
+
+    Ok(())
+}
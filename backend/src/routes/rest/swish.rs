@@ -0,0 +1,478 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::models::swish as relational;
+use crate::models::transaction::relational::NewTransaction;
+use crate::routes::rest::book_account::master_accounts;
+use crate::routes::rest::live::LiveEvents;
+use crate::routes::rest::member::mail_receipts;
+use crate::util::outbox;
+use crate::util::rate_limit::{Public, RateLimit};
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use crate::util::swish;
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use log::warn;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::{get, post, State};
+use serde_json::json;
+use strecklistan_api::book_account::BookAccountId;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::member::MemberId;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::swish::{SwishDeposit, SwishPayment, SwishStatus};
+use strecklistan_api::transaction::TransactionId;
+
+/// Swish doesn't take payments below 1 kr
+const MIN_DEPOSIT: i64 = 100;
+
+/// The same limit as for deposits at the counter
+const MAX_DEPOSIT: i64 = 5000 * 100;
+
+/// Swish gives up on payments which aren't approved within 3 minutes, so older ones are no
+/// longer shown as pending. The extra minutes are a buffer for a payment which was approved at the
+/// last moment, but whose callback is late or lost and has to be checked with Swish first.
+const PENDING_MINUTES: i64 = 10;
+
+/// Payments which haven't been called back about after this long are checked with Swish when
+/// they're polled, in case the callback was lost
+const CALLBACK_GRACE_SECS: i64 = 10;
+
+const DEPOSIT_DESCRIPTION: &str = "Insättning";
+
+/// Shown to the member in the Swish app
+const SWISH_MESSAGE: &str = "Insättning tillgodo";
+
+/// POST `/public/balance/<card>/swish`
+///
+/// Start a deposit to the tillgodo account of the member whose balance card has the code `card`,
+/// which they approve in the Swish app. The account is credited once Swish calls back about it.
+/// Doesn't require a login, so it's rate limited per client instead.
+#[post("/public/balance/<card>/swish", data = "<deposit>")]
+pub fn post_swish_deposit(
+    _limit: RateLimit<Public>,
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    accept: SerAccept,
+    card: String,
+    deposit: Json<SwishDeposit>,
+) -> Result<Ser<SwishPayment>, SJ> {
+    let swish_config = config
+        .swish
+        .as_ref()
+        .ok_or_else(|| SJ::new(Status::NotFound, "Swish deposits are disabled"))?;
+
+    let deposit_amount: i64 = Currency::from(deposit.amount).into();
+    if !(MIN_DEPOSIT..=MAX_DEPOSIT).contains(&deposit_amount) {
+        return Err(SJ::new(
+            Status::BadRequest,
+            format!(
+                "The amount must be between {} and {}",
+                Currency::from(MIN_DEPOSIT),
+                Currency::from(MAX_DEPOSIT)
+            ),
+        ));
+    }
+
+    let connection = db_pool.inner().get()?;
+
+    let (member, organization): (MemberId, OrganizationId) = {
+        use crate::schema::tables::members::dsl::*;
+        members
+            .filter(balance_token.eq(&card))
+            .filter(archived_at.is_null())
+            .select((id, organization_id))
+            .first(&connection)
+            .optional()?
+            .ok_or_else(|| SJ::new(Status::NotFound, "No such balance card"))?
+    };
+
+    let account: BookAccountId = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(creditor.eq(member))
+            .order_by(id.asc())
+            .select(id)
+            .first(&connection)
+            .optional()?
+            .ok_or_else(|| SJ::new(Status::NotFound, "The member has no tillgodo account"))?
+    };
+
+    let payment_id = swish::new_payment_id();
+    {
+        use crate::schema::tables::swish_payments::dsl::*;
+        diesel::insert_into(swish_payments)
+            .values((
+                id.eq(&payment_id),
+                organization_id.eq(organization),
+                member_id.eq(member),
+                credited_account.eq(account),
+                amount.eq(deposit_amount),
+            ))
+            .execute(&connection)?;
+    }
+
+    let result = swish::create(swish_config, &payment_id, deposit_amount, SWISH_MESSAGE);
+
+    {
+        use crate::schema::tables::swish_payments::dsl::*;
+        let row = swish_payments.filter(id.eq(&payment_id));
+        match &result {
+            Ok(payment_token) => {
+                diesel::update(row)
+                    .set(token.eq(payment_token))
+                    .execute(&connection)?;
+            }
+            Err(e) => {
+                warn!("Could not make Swish payment request {}: {}", payment_id, e);
+                diesel::update(row)
+                    .set((
+                        status.eq(SwishStatus::Error),
+                        error.eq(e),
+                        completed_at.eq(Utc::now()),
+                    ))
+                    .execute(&connection)?;
+            }
+        }
+    }
+
+    live.notify(organization, LiveEvent::SwishPaymentsChanged);
+    if result.is_err() {
+        return Err(SJ::new(Status::BadGateway, "Swish could not be reached"));
+    }
+    Ok(accept.ser(load_payment(&connection, &payment_id)?))
+}
+
+/// GET `/public/swish/<payment_id>`
+///
+/// A Swish deposit started from a balance card, polled by the member while it's pending. The id
+/// can't be guessed, so it's enough to see the payment.
+#[get("/public/swish/<payment_id>")]
+pub fn get_swish_payment(
+    _limit: RateLimit<Public>,
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    accept: SerAccept,
+    payment_id: String,
+) -> Result<Ser<SwishPayment>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let payment = load_payment(&connection, &payment_id)?;
+
+    let overdue = Utc::now() - payment.created_at > Duration::seconds(CALLBACK_GRACE_SECS);
+    if payment.status.is_pending() && overdue {
+        match complete_payment(&connection, config, live, &payment_id) {
+            Ok(_) => return Ok(accept.ser(load_payment(&connection, &payment_id)?)),
+            Err(e) => warn!("Could not check Swish payment {}: {:?}", payment_id, e),
+        }
+    }
+
+    Ok(accept.ser(payment))
+}
+
+/// POST `/swish/callback/<payment_id>`
+///
+/// Called by Swish when a payment request has been paid or has failed. The body isn't trusted,
+/// the payment is fetched from Swish instead. Calling this again for a payment which is done
+/// changes nothing.
+#[post("/swish/callback/<payment_id>")]
+pub fn swish_callback(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    payment_id: String,
+) -> Result<SJ, SJ> {
+    let connection = db_pool.inner().get()?;
+    complete_payment(&connection, config, live, &payment_id)?;
+    Ok(SJ::new(Status::Ok, "Payment updated"))
+}
+
+/// GET `/swish/pending`
+///
+/// The Swish deposits which members have started but not yet paid, oldest first
+#[get("/swish/pending")]
+pub fn get_pending_swish_payments(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<SwishPayment>>, SJ> {
+    let connection = db_pool.inner().get()?;
+
+    use crate::schema::tables::swish_payments::dsl::*;
+    let since = Utc::now() - Duration::minutes(PENDING_MINUTES);
+    let pending: Vec<relational::SwishPayment> = swish_payments
+        .filter(organization_id.eq(session.organization))
+        .filter(status.eq(SwishStatus::Created))
+        .filter(created_at.ge(since))
+        .order_by(created_at.asc())
+        .load(&connection)?;
+
+    Ok(accept.ser(pending.into_iter().map(Into::into).collect()))
+}
+
+/// Fetch the result of a pending payment from Swish, and make the deposit if it was paid.
+///
+/// Payments which are already done are left as they are, without asking Swish.
+fn complete_payment(
+    connection: &PgConnection,
+    config: &Config,
+    live: &LiveEvents,
+    payment_id: &str,
+) -> Result<SwishStatus, SJ> {
+    let payment = load_payment(connection, payment_id)?;
+    if !payment.status.is_pending() {
+        return Ok(payment.status);
+    }
+
+    let swish_config = config
+        .swish
+        .as_ref()
+        .ok_or_else(|| SJ::new(Status::NotFound, "Swish deposits are disabled"))?;
+    let outcome = swish::fetch(swish_config, payment_id).map_err(|e| {
+        warn!("Could not fetch Swish payment {}: {}", payment_id, e);
+        SJ::new(Status::BadGateway, "Swish could not be reached")
+    })?;
+    if outcome.status.is_pending() {
+        return Ok(outcome.status);
+    }
+
+    let completed = connection.transaction::<_, SJ, _>(|| {
+        let (organization, member_account, paid_amount, current): (
+            OrganizationId,
+            BookAccountId,
+            i64,
+            SwishStatus,
+        ) = {
+            use crate::schema::tables::swish_payments::dsl::*;
+            swish_payments
+                .filter(id.eq(payment_id))
+                .select((organization_id, credited_account, amount, status))
+                .for_update()
+                .first(connection)?
+        };
+
+        // another callback got here first
+        if !current.is_pending() {
+            return Ok(None);
+        }
+
+        let now = Utc::now();
+        let deposit = if outcome.status == SwishStatus::Paid {
+            let bank_account = master_accounts(connection, organization)?.bank_account_id;
+            let deposit_id: TransactionId = {
+                use crate::schema::tables::transactions::dsl::*;
+                diesel::insert_into(transactions)
+                    .values(NewTransaction {
+                        description: Some(DEPOSIT_DESCRIPTION.to_string()),
+                        time: Some(now),
+                        debited_account: bank_account,
+                        credited_account: member_account,
+                        amount: paid_amount,
+                        note: None,
+                        tags: vec![],
+                        created_by: None,
+                        idempotency_key: None,
+                        organization_id: organization,
+                        event_code: None,
+                        age_verified: false,
+                        device: None,
+                        member_id: None,
+                    })
+                    .returning(id)
+                    .get_result(connection)?
+            };
+
+            outbox::notify_webhooks(
+                connection,
+                config,
+                organization,
+                "transaction.created",
+                json!({
+                    "transaction_id": deposit_id,
+                    "amount": paid_amount,
+                    "debited_account": bank_account,
+                    "credited_account": member_account,
+                    "created_by": None::<String>,
+                }),
+            )?;
+
+            mail_receipts(
+                connection,
+                config,
+                deposit_id,
+                (bank_account, member_account),
+                paid_amount.into(),
+                Some(DEPOSIT_DESCRIPTION),
+            )?;
+
+            Some(deposit_id)
+        } else {
+            None
+        };
+
+        use crate::schema::tables::swish_payments::dsl::*;
+        diesel::update(swish_payments.filter(id.eq(payment_id)))
+            .set((
+                status.eq(outcome.status),
+                error.eq(&outcome.error),
+                transaction_id.eq(deposit),
+                token.eq(None::<String>),
+                completed_at.eq(now),
+            ))
+            .execute(connection)?;
+
+        Ok(Some((organization, deposit)))
+    })?;
+
+    if let Some((organization, deposit)) = completed {
+        if let Some(deposit_id) = deposit {
+            live.notify(
+                organization,
+                LiveEvent::TransactionCreated { id: deposit_id },
+            );
+        }
+        live.notify(organization, LiveEvent::SwishPaymentsChanged);
+    }
+
+    Ok(outcome.status)
+}
+
+fn load_payment(connection: &PgConnection, payment_id: &str) -> Result<SwishPayment, SJ> {
+    use crate::schema::tables::swish_payments::dsl::*;
+    let payment: relational::SwishPayment = swish_payments
+        .filter(id.eq(payment_id))
+        .first(connection)
+        .optional()?
+        .ok_or_else(|| SJ::new(Status::NotFound, "No such Swish payment"))?;
+    Ok(payment.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::Status;
+
+    /// Answers the requests to a fake Swish API with `responses`, one per connection, and returns
+    /// its url
+    fn swish_stub(responses: Vec<(&'static str, &'static str, String)>) -> String {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for (status, header, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                reader.read_exact(&mut vec![0; length]).unwrap();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    header,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn test_swish_deposit() {
+        use crate::config::SwishConfig;
+        use std::convert::TryFrom;
+        use strecklistan_api::currency::{Currency, NonNegativeCurrency};
+        use strecklistan_api::public::PublicBalance;
+        use strecklistan_api::swish::{SwishDeposit, SwishPayment, SwishStatus};
+
+        let mut db = TestDb::new();
+        let api_url = swish_stub(vec![
+            (
+                "201 Created",
+                "PaymentRequestToken: c28a4061470f4af48973bd2a4642b4fa\r\n",
+                String::new(),
+            ),
+            ("200 OK", "", r#"{"status":"PAID"}"#.to_string()),
+        ]);
+        db.config.swish = Some(SwishConfig {
+            payee: "1231181189".to_string(),
+            api_url,
+            callback_url: "https://example.com/api".to_string(),
+            certificate: None,
+            certificate_password: String::new(),
+        });
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let (member, _) = db.member(org, "Testsson");
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let response = client
+            .put(format!("/api/member/{}/balance_card", member))
+            .dispatch();
+        let card: String = response.into_json().unwrap();
+
+        let public = db.client();
+        let deposit_uri = format!("/api/public/balance/{}/swish", card);
+        let deposit = |kr: i64| SwishDeposit {
+            amount: NonNegativeCurrency::try_from(Currency::from(kr * 100)).unwrap(),
+        };
+        assert_eq!(post(&public, &deposit_uri, &deposit(0)), Status::BadRequest);
+        assert_eq!(
+            post(&public, &deposit_uri, &deposit(10000)),
+            Status::BadRequest
+        );
+
+        let payment: SwishPayment = post_json(&public, &deposit_uri, &deposit(200));
+        assert_eq!(payment.status, SwishStatus::Created);
+        assert_eq!(payment.amount, 20000.into());
+        assert_eq!(
+            payment.token.as_deref(),
+            Some("c28a4061470f4af48973bd2a4642b4fa")
+        );
+
+        // nothing is deposited before Swish says it's paid
+        let pending: Vec<SwishPayment> = get_json(&client, "/api/swish/pending");
+        assert_eq!(pending, vec![payment.clone()]);
+        let balance: PublicBalance = get_json(&public, &format!("/api/public/balance/{}", card));
+        assert_eq!(balance.balance, 0.into());
+        assert!(balance.swish);
+
+        let callback_uri = format!("/api/swish/callback/{}", payment.id);
+        assert_eq!(public.post(&callback_uri).dispatch().status(), Status::Ok);
+
+        let paid: SwishPayment = get_json(&public, &format!("/api/public/swish/{}", payment.id));
+        assert_eq!(paid.status, SwishStatus::Paid);
+        assert!(paid.transaction_id.is_some());
+        let balance: PublicBalance = get_json(&public, &format!("/api/public/balance/{}", card));
+        assert_eq!(balance.balance, 20000.into());
+        let pending: Vec<SwishPayment> = get_json(&client, "/api/swish/pending");
+        assert!(pending.is_empty());
+
+        // Swish may call back more than once, without asking the stub again
+        assert_eq!(public.post(&callback_uri).dispatch().status(), Status::Ok);
+        let balance: PublicBalance = get_json(&public, &format!("/api/public/balance/{}", card));
+        assert_eq!(balance.balance, 20000.into());
+
+        let unknown = public.post("/api/swish/callback/0000").dispatch();
+        assert_eq!(unknown.status(), Status::NotFound);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::auth::Session;
+use crate::database::DatabasePool;
+use crate::models::transaction::relational;
+use crate::util::client::Client;
+use crate::util::ser::{Ser, SerAccept};
+use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use rocket::http::{CookieJar, Status};
+use rocket::serde::json::Json;
+use rocket::{delete, get, put, State};
+use strecklistan_api::training::{TrainingTransaction, TrainingTransactionId};
+use strecklistan_api::transaction::TransactionBundle;
+
+/// How many training sales are listed at most
+const TRAINING_TRANSACTION_LIMIT: i64 = 200;
+
+/// GET `/session/training`
+///
+/// Whether the session is in training mode
+#[get("/session/training")]
+pub fn get_training_mode(session: Session, accept: SerAccept) -> Ser<bool> {
+    accept.ser(session.training)
+}
+
+/// PUT `/session/training`
+///
+/// Enter or leave training mode. While in training mode, sales are put in a sandbox instead of
+/// the books, and everything else which would change the books is refused.
+#[put("/session/training", data = "<training>")]
+pub fn put_training_mode(
+    session: Session,
+    cookies: &CookieJar<'_>,
+    client: Client,
+    accept: SerAccept,
+    training: Json<bool>,
+) -> Ser<bool> {
+    let session = Session {
+        training: training.into_inner(),
+        ..session
+    };
+    session.set_cookie(cookies, client.secure);
+    accept.ser(session.training)
+}
+
+/// GET `/training/transactions`
+///
+/// The sales made in training mode in the organization, newest first
+#[get("/training/transactions")]
+pub fn get_training_transactions(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<Vec<TrainingTransaction>>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::training_transactions::dsl::*;
+
+    #[allow(clippy::type_complexity)]
+    let rows: Vec<(
+        i32,
+        DateTime<Utc>,
+        String,
+        Option<String>,
+        i32,
+        i32,
+        i64,
+        serde_json::Value,
+    )> = training_transactions
+        .filter(organization_id.eq(session.organization))
+        .select((
+            id,
+            time,
+            created_by,
+            description,
+            debited_account,
+            credited_account,
+            amount,
+            bundles,
+        ))
+        .order_by(time.desc())
+        .limit(TRAINING_TRANSACTION_LIMIT)
+        .load(&connection)?;
+
+    let sales = rows
+        .into_iter()
+        .map(
+            |(sale_id, sale_time, user, desc, debited, credited, sum, sale_bundles)| {
+                Ok(TrainingTransaction {
+                    id: sale_id,
+                    time: sale_time,
+                    created_by: user,
+                    description: desc,
+                    debited_account: debited,
+                    credited_account: credited,
+                    amount: sum.into(),
+                    bundles: serde_json::from_value(sale_bundles).map_err(|e| {
+                        SJ::new(
+                            Status::InternalServerError,
+                            format!("Stored training sale is invalid: {}", e),
+                        )
+                    })?,
+                })
+            },
+        )
+        .collect::<Result<_, SJ>>()?;
+
+    Ok(accept.ser(sales))
+}
+
+/// DELETE `/training/transactions`
+///
+/// Throw away the training sales of the logged in user, returns how many there were
+#[delete("/training/transactions")]
+pub fn delete_training_transactions(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+) -> Result<Ser<usize>, SJ> {
+    let connection = db_pool.inner().get()?;
+    use crate::schema::tables::training_transactions::dsl::*;
+
+    let deleted = diesel::delete(
+        training_transactions
+            .filter(organization_id.eq(session.organization))
+            .filter(created_by.eq(&session.user)),
+    )
+    .execute(&connection)?;
+
+    Ok(accept.ser(deleted))
+}
+
+/// Put a sale made in training mode in the sandbox, instead of the books
+pub fn insert_training_transaction(
+    connection: &PgConnection,
+    transaction: &relational::NewTransaction,
+    user: &str,
+    sale_bundles: &[TransactionBundle],
+) -> Result<TrainingTransactionId, SJ> {
+    use crate::schema::tables::training_transactions::dsl::*;
+
+    let sale_bundles = serde_json::to_value(sale_bundles)
+        .map_err(|e| SJ::new(Status::InternalServerError, e.to_string()))?;
+
+    let sale_id = diesel::insert_into(training_transactions)
+        .values((
+            organization_id.eq(transaction.organization_id),
+            created_by.eq(user),
+            description.eq(&transaction.description),
+            debited_account.eq(transaction.debited_account),
+            credited_account.eq(transaction.credited_account),
+            amount.eq(transaction.amount),
+            bundles.eq(sale_bundles),
+        ))
+        .returning(id)
+        .get_result(connection)?;
+
+    Ok(sale_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType};
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_training_mode() {
+        use strecklistan_api::error::{ApiError, ErrorCode};
+        use strecklistan_api::training::TrainingTransaction;
+        use strecklistan_api::transaction::{Transaction, TransactionBundle};
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let coffee = db.item(org, "Kaffe", Some(500.into()));
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let (_, member_account) = db.member(org, "Testsson");
+        let real_sale = db.transaction(org, member_account, sales, 1000.into());
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let set_training = |training: bool| {
+            client
+                .put("/api/session/training")
+                .header(ContentType::JSON)
+                .body(serde_json::to_string(&training).unwrap())
+                .dispatch()
+                .status()
+        };
+
+        assert!(!get_json::<bool>(&client, "/api/session/training"));
+        assert_eq!(set_training(true), Status::Ok);
+        assert!(get_json::<bool>(&client, "/api/session/training"));
+
+        let mut item_ids = HashMap::new();
+        item_ids.insert(coffee, 2);
+        let sale = NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: None,
+                change: -2,
+                item_ids,
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: member_account,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let training_id: i32 = post_json(&client, "/api/transaction", &sale);
+
+        // the sale is kept out of the books, the stock, and the balance of the member
+        let training: Vec<TrainingTransaction> = get_json(&client, "/api/training/transactions");
+        assert_eq!(training.len(), 1);
+        assert_eq!(training[0].id, training_id);
+        assert_eq!(training[0].created_by, "tester");
+        assert_eq!(training[0].bundles, sale.bundles);
+
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 1);
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert_eq!(items[&coffee].stock, 0);
+        let accounts: HashMap<BookAccountId, BookAccount> = get_json(&client, "/api/book_accounts");
+        assert_eq!(accounts[&member_account].balance, (-1000).into());
+
+        // real transactions can't be changed while practicing
+        let response = client
+            .delete(format!("/api/transaction/{}", real_sale))
+            .dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+        let error: ApiError<()> = response.into_json().unwrap();
+        assert_eq!(error.code, ErrorCode::TrainingMode);
+        assert_eq!(post(&client, "/api/register/open", &()), Status::Forbidden);
+
+        assert_eq!(set_training(false), Status::Ok);
+        let deleted: usize = client
+            .delete("/api/training/transactions")
+            .dispatch()
+            .into_json()
+            .unwrap();
+        assert_eq!(deleted, 1);
+        let training: Vec<TrainingTransaction> = get_json(&client, "/api/training/transactions");
+        assert!(training.is_empty());
+    }
+}
@@ -1,22 +1,77 @@
+use crate::auth::Session;
+use crate::config::Config;
+use crate::database::item_cache::ItemCache;
 use crate::database::DatabasePool;
 use crate::models::transaction::{object, relational};
+use crate::routes::rest::book_account::check_accounts;
+use crate::routes::rest::inventory::{
+    check_age_verified, check_items, check_stock, load_purchase_limits,
+};
+use crate::routes::rest::live::LiveEvents;
+use crate::routes::rest::member::mail_receipts;
+use crate::routes::rest::queue::issue_ticket;
+use crate::routes::rest::training::insert_training_transaction;
+use crate::util::approval::Gate;
+use crate::util::audit::{self, AuditAction};
+use crate::util::device::ThisDevice;
+use crate::util::outbox;
 use crate::util::ser::{Ser, SerAccept};
 use crate::util::status_json::StatusJson as SJ;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use diesel::prelude::*;
 use itertools::Itertools;
+use rocket::http::Status;
 use rocket::serde::json::Json;
-use rocket::{delete, get, post, State};
-use std::collections::HashMap;
+use rocket::{delete, get, patch, post, State};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use strecklistan_api::approval::GatedAction;
+use strecklistan_api::book_account::{tillgodo_balances, BookAccountId};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::inventory::InventoryItemId;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::member::MemberId;
+use strecklistan_api::organization::{is_locked, OrganizationId};
+use strecklistan_api::time::ReportingPeriod;
+use strecklistan_api::transaction::{returnable_items, TransactionId};
+use strecklistan_api::validation::Validate;
+use uuid::Uuid;
+
+const RETURN_DESCRIPTION: &str = "Retur";
 
 /// POST `/transaction`
 ///
 /// Create a new transaction
+///
+/// Responds with 409 Conflict, and the items which there aren't enough of, if the transaction
+/// would take the stock of an item below zero.
+///
+/// In training mode the sale is put in the sandbox instead, and the id is that of the
+/// [TrainingTransaction](strecklistan_api::training::TrainingTransaction).
+///
+/// Depending on the configuration, large discounts and sales which take the tillgodo of a member
+/// below zero need the approval of an admin, see [Gate]. So do sales to members of more of an
+/// item than its [PurchaseLimit](strecklistan_api::inventory::PurchaseLimit).
+///
+/// A sale which isn't paid from a tillgodo may be tagged with the member who buys it, which only
+/// counts it towards their purchase limits and statistics. Responds with 404 Not Found if there is
+/// no such member, and 400 Bad Request if the sale is paid from the tillgodo of another member.
 #[post("/transaction", data = "<transaction>")]
+#[allow(clippy::too_many_arguments)]
 pub fn post_transaction(
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    item_cache: &State<ItemCache>,
+    live: &State<LiveEvents>,
+    session: Session,
+    device: ThisDevice,
+    gate: Gate<'_>,
     accept: SerAccept,
     transaction: Json<object::NewTransaction>,
 ) -> Result<Ser<i32>, SJ> {
+    transaction.validate()?;
     let connection = db_pool.inner().get()?;
 
     let object::NewTransaction {
@@ -25,94 +80,1097 @@ pub fn post_transaction(
         debited_account,
         credited_account,
         amount,
+        note,
+        tags,
+        event_code,
+        age_verified,
+        member_id,
+        idempotency_key,
     } = transaction.into_inner();
 
+    let member_id = resolve_member(
+        &connection,
+        session.organization,
+        debited_account,
+        member_id,
+    )?;
+
+    let annotation = object::TransactionAnnotation {
+        note,
+        tags,
+        event_code,
+    }
+    .normalized();
     let transaction = relational::NewTransaction {
         description,
         time: None,
         debited_account,
         credited_account,
         amount: amount.into(),
+        note: annotation.note,
+        tags: annotation.tags,
+        created_by: Some(session.user),
+        idempotency_key,
+        organization_id: session.organization,
+        event_code: annotation.event_code,
+        age_verified,
+        device: device.name(),
+        member_id,
     };
 
-    connection.transaction::<_, SJ, _>(|| {
-        let transaction_id = {
+    // training sales are checked like real ones, but kept out of the books, the stock, and the
+    // balances of members
+    if session.training {
+        let training_id = connection.transaction::<_, SJ, _>(|| {
+            check_accounts(
+                &connection,
+                session.organization,
+                &[transaction.debited_account, transaction.credited_account],
+            )?;
+            check_items(&connection, item_cache, session.organization, &bundles)?;
+            check_age_verified(
+                &connection,
+                item_cache,
+                session.organization,
+                &bundles,
+                transaction.age_verified,
+            )?;
+            insert_training_transaction(&connection, &transaction, &session.user, &bundles)
+        })?;
+        return Ok(accept.ser(training_id));
+    }
+
+    let (transaction_id, created, queue_number) = connection.transaction::<_, SJ, _>(|| {
+        // the transaction has already been posted, don't create it again
+        if let Some(existing) = find_posted(&connection, session.organization, &transaction)? {
+            return Ok((existing, false, None));
+        }
+
+        check_accounts(
+            &connection,
+            session.organization,
+            &[transaction.debited_account, transaction.credited_account],
+        )?;
+        check_items(&connection, item_cache, session.organization, &bundles)?;
+        check_age_verified(
+            &connection,
+            item_cache,
+            session.organization,
+            &bundles,
+            transaction.age_verified,
+        )?;
+        check_stock(&connection, &bundles, config.stock.negative_stock)?;
+        check_approvals(&connection, config, &gate, &session, &transaction, &bundles)?;
+
+        let transaction_id: Option<i32> = {
             use crate::schema::tables::transactions::dsl::*;
             diesel::insert_into(transactions)
-                .values(transaction)
+                .values(&transaction)
+                .on_conflict_do_nothing()
                 .returning(id)
-                .get_result(&connection)?
+                .get_result(&connection)
+                .optional()?
+        };
+
+        // the same key was posted concurrently, and that request got to insert it first
+        let transaction_id = match transaction_id {
+            Some(transaction_id) => transaction_id,
+            None => {
+                let existing = find_posted(&connection, session.organization, &transaction)?
+                    .ok_or_else(|| SJ::new(Status::Conflict, "The transaction already exists"))?;
+                return Ok((existing, false, None));
+            }
         };
 
-        for bundle in bundles.into_iter() {
-            let new_bundle = relational::NewTransactionBundle {
+        outbox::notify_webhooks(
+            &connection,
+            config,
+            session.organization,
+            "transaction.created",
+            json!({
+                "transaction_id": transaction_id,
+                "amount": transaction.amount,
+                "debited_account": transaction.debited_account,
+                "credited_account": transaction.credited_account,
+                "created_by": transaction.created_by,
+            }),
+        )?;
+
+        insert_bundles(&connection, transaction_id, bundles)?;
+
+        mail_receipts(
+            &connection,
+            config,
+            transaction_id,
+            (transaction.debited_account, transaction.credited_account),
+            transaction.amount.into(),
+            transaction.description.as_deref(),
+        )?;
+
+        let queue_number = issue_ticket(
+            &connection,
+            config,
+            session.organization,
+            transaction_id,
+            transaction.credited_account,
+        )?;
+
+        Ok((transaction_id, true, queue_number))
+    })?;
+
+    if created {
+        live.notify(
+            session.organization,
+            LiveEvent::TransactionCreated { id: transaction_id },
+        );
+    }
+    if let Some(number) = queue_number {
+        live.notify(
+            session.organization,
+            LiveEvent::QueueTicketIssued {
                 transaction_id,
-                description: bundle.description,
-                price: bundle.price.map(|p| p.into()),
-                change: bundle.change,
-            };
+                number,
+            },
+        );
+    }
+    Ok(accept.ser(transaction_id))
+}
+
+/// The id of the transaction which was already posted with the idempotency key of `transaction`
+fn find_posted(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    transaction: &relational::NewTransaction,
+) -> QueryResult<Option<i32>> {
+    use crate::schema::tables::transactions::dsl::*;
+    match &transaction.idempotency_key {
+        Some(key) => transactions
+            .filter(organization_id.eq(organization))
+            .filter(idempotency_key.eq(key))
+            .select(id)
+            .first(connection)
+            .optional(),
+        None => Ok(None),
+    }
+}
+
+/// Check that an admin has approved the [GatedActions](GatedAction) of a new sale, if the
+/// configuration requires it
+fn check_approvals(
+    connection: &PgConnection,
+    config: &Config,
+    gate: &Gate<'_>,
+    session: &Session,
+    transaction: &relational::NewTransaction,
+    bundles: &[object::TransactionBundle],
+) -> Result<(), SJ> {
+    let sale = Currency::from(transaction.amount);
 
-            let bundle_id = {
-                use crate::schema::tables::transaction_bundles::dsl::*;
-                diesel::insert_into(transaction_bundles)
-                    .values(&new_bundle)
-                    .returning(id)
-                    .get_result(&connection)?
+    if let Some(percent) = config.approvals.discount_over_percent {
+        // what the cart costs at the prices of its bundles, before the total was overridden
+        let cart: i64 = bundles
+            .iter()
+            .map(|bundle| i64::from(bundle.price.unwrap_or_default()) * -i64::from(bundle.change))
+            .sum();
+        let lowest = cart * (100 - i64::from(percent.min(100))) / 100;
+        if i64::from(sale) < lowest {
+            gate.check(
+                connection,
+                session,
+                GatedAction::Discount,
+                &format!("Sale of {}:- for {}:-", Currency::from(cart), sale),
+            )?;
+        }
+    }
+
+    if config.approvals.negative_balance {
+        let account = transaction.debited_account;
+        let member_account: bool = {
+            use crate::schema::tables::book_accounts::dsl::*;
+            diesel::select(diesel::dsl::exists(
+                book_accounts
+                    .filter(id.eq(account))
+                    .filter(creditor.is_not_null()),
+            ))
+            .get_result(connection)?
+        };
+
+        if member_account {
+            let rows: Vec<(BookAccountId, BookAccountId, i64, DateTime<Utc>)> = {
+                use crate::schema::tables::transactions::dsl::*;
+                transactions
+                    .filter(deleted_at.is_null())
+                    .filter(debited_account.eq(account).or(credited_account.eq(account)))
+                    .select((debited_account, credited_account, amount, time))
+                    .load(connection)?
             };
 
-            let item_ids: Vec<_> = bundle
-                .item_ids
-                .into_iter()
-                .flat_map(|(item_id, count)| std::iter::repeat(item_id).take(count as usize))
-                .map(|item_id| relational::NewTransactionItem { bundle_id, item_id })
-                .collect();
+            let balance = tillgodo_balances(
+                &[account],
+                rows.into_iter().map(|(debited, credited, change, at)| {
+                    (debited, credited, Currency::from(change), at)
+                }),
+            )[&account]
+                .balance;
+            if balance < sale {
+                gate.check(
+                    connection,
+                    session,
+                    GatedAction::NegativeBalance,
+                    &format!(
+                        "Sale of {}:- from account {} with a balance of {}:-",
+                        sale, account, balance,
+                    ),
+                )?;
+            }
+        }
+    }
 
-            {
-                use crate::schema::tables::transaction_items::dsl::*;
-                diesel::insert_into(transaction_items)
-                    .values(&item_ids)
-                    .execute(&connection)?;
+    check_purchase_limits(
+        connection,
+        config,
+        gate,
+        session,
+        transaction.member_id,
+        bundles,
+    )?;
+
+    Ok(())
+}
+
+/// Check that a sale to a member doesn't take what the member has bought of an item in the
+/// current period over its purchase limit, unless an admin approves it
+pub fn check_purchase_limits(
+    connection: &PgConnection,
+    config: &Config,
+    gate: &Gate<'_>,
+    session: &Session,
+    member: Option<MemberId>,
+    bundles: &[object::TransactionBundle],
+) -> Result<(), SJ> {
+    let member = match member {
+        Some(member) => member,
+        None => return Ok(()),
+    };
+
+    let mut requested: BTreeMap<InventoryItemId, i32> = BTreeMap::new();
+    for bundle in bundles.iter().filter(|bundle| bundle.change < 0) {
+        for (&item, &per_bundle) in &bundle.item_ids {
+            *requested.entry(item).or_default() -= bundle.change * per_bundle as i32;
+        }
+    }
+    let item_ids: Vec<InventoryItemId> = requested.keys().copied().collect();
+    let limits = load_purchase_limits(connection, session.organization, Some(&item_ids))?;
+    if limits.is_empty() {
+        return Ok(());
+    }
+
+    let tz = config.reporting_timezone;
+    let now = Utc::now();
+    for (item, limit) in limits {
+        let period = ReportingPeriod::at(limit.per, now, tz).range(tz);
+        let bought = member_purchases(connection, session.organization, member, period)?
+            .get(&item)
+            .copied()
+            .unwrap_or(0);
+
+        let buying = i64::from(requested[&item]);
+        if bought + buying > i64::from(limit.quantity) {
+            gate.check(
+                connection,
+                session,
+                GatedAction::PurchaseLimit,
+                &format!(
+                    "Sale of {} of item {} to member {} who has bought {} of at most {} per {}",
+                    buying, item, member, bought, limit.quantity, limit.per,
+                ),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The member who buys the goods of a sale from `debited_account`: `tagged` if the cashier chose
+/// one, or else the member whose tillgodo pays for it
+pub fn resolve_member(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    debited_account: BookAccountId,
+    tagged: Option<MemberId>,
+) -> Result<Option<MemberId>, SJ> {
+    let paying: Option<MemberId> = {
+        use crate::schema::tables::book_accounts::dsl::*;
+        book_accounts
+            .filter(id.eq(debited_account))
+            .filter(organization_id.eq(organization))
+            .select(creditor)
+            .first::<Option<MemberId>>(connection)
+            .optional()?
+            .flatten()
+    };
+
+    let tagged = match tagged {
+        Some(tagged) => tagged,
+        None => return Ok(paying),
+    };
+
+    let exists: bool = {
+        use crate::schema::tables::members::dsl::*;
+        diesel::select(diesel::dsl::exists(
+            members
+                .filter(id.eq(tagged))
+                .filter(organization_id.eq(organization)),
+        ))
+        .get_result(connection)?
+    };
+    if !exists {
+        return Err(SJ::new(Status::NotFound, "No such member"));
+    }
+    if paying.map_or(false, |paying| paying != tagged) {
+        return Err(SJ::new(
+            Status::BadRequest,
+            "The sale is paid from the tillgodo of another member",
+        ));
+    }
+
+    Ok(Some(tagged))
+}
+
+/// How many of every item `member` has bought during `range`, less what they have returned
+pub fn member_purchases(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    member: MemberId,
+    range: Range<DateTime<Utc>>,
+) -> QueryResult<HashMap<InventoryItemId, i64>> {
+    use crate::schema::tables::transaction_bundles::dsl::{
+        change, id as bundle_id, transaction_bundles, transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, item_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::{
+        deleted_at, id, member_id, organization_id, time, transactions,
+    };
+    let rows: Vec<(InventoryItemId, i32)> = transaction_items
+        .inner_join(transaction_bundles.on(bundle_id.eq(item_bundle_id)))
+        .inner_join(transactions.on(id.eq(transaction_id)))
+        .filter(organization_id.eq(organization))
+        .filter(member_id.eq(member))
+        .filter(deleted_at.is_null())
+        .filter(time.ge(range.start))
+        .filter(time.lt(range.end))
+        .select((item_id, change))
+        .load(connection)?;
+
+    let mut bought = HashMap::new();
+    for (item, sold) in rows {
+        *bought.entry(item).or_default() -= i64::from(sold);
+    }
+    Ok(bought)
+}
+
+/// Insert the bundles of a new transaction, and their items
+pub fn insert_bundles(
+    connection: &PgConnection,
+    transaction_id: TransactionId,
+    bundles: Vec<object::TransactionBundle>,
+) -> QueryResult<()> {
+    for bundle in bundles.into_iter() {
+        let new_bundle = relational::NewTransactionBundle {
+            transaction_id,
+            description: bundle.description,
+            price: bundle.price.map(|p| p.into()),
+            change: bundle.change,
+            expires_on: bundle.expires_on,
+            modifier_ids: bundle.modifier_ids,
+        };
+
+        let bundle_id = {
+            use crate::schema::tables::transaction_bundles::dsl::*;
+            diesel::insert_into(transaction_bundles)
+                .values(&new_bundle)
+                .returning(id)
+                .get_result(connection)?
+        };
+
+        let item_ids: Vec<_> = bundle
+            .item_ids
+            .into_iter()
+            .flat_map(|(item_id, count)| std::iter::repeat(item_id).take(count as usize))
+            .map(|item_id| relational::NewTransactionItem { bundle_id, item_id })
+            .collect();
+
+        {
+            use crate::schema::tables::transaction_items::dsl::*;
+            diesel::insert_into(transaction_items)
+                .values(&item_ids)
+                .execute(connection)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// POST `/transaction/<id>/return`
+///
+/// Return goods sold in a transaction. The return is a transaction of its own, which pays back
+/// `amount` to the account which the sale was paid from, and puts the items back in stock if
+/// they are restocked.
+///
+/// No more may be returned than was sold, and no more paid back than was paid, counting what has
+/// already been returned.
+#[post("/transaction/<id>/return", data = "<goods_return>")]
+pub fn post_goods_return(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    session: Session,
+    device: ThisDevice,
+    accept: SerAccept,
+    id: TransactionId,
+    goods_return: Json<object::NewGoodsReturn>,
+) -> Result<Ser<TransactionId>, SJ> {
+    session.check_not_training()?;
+    goods_return.validate()?;
+    let connection = db_pool.inner().get()?;
+
+    let object::NewGoodsReturn {
+        items,
+        amount,
+        reason,
+        restock,
+        note,
+    } = goods_return.into_inner();
+    let items: BTreeMap<InventoryItemId, u32> =
+        items.into_iter().filter(|&(_, count)| count > 0).collect();
+    let note = object::TransactionAnnotation {
+        note,
+        ..Default::default()
+    }
+    .normalized()
+    .note;
+
+    let transaction_id = connection.transaction::<_, SJ, _>(|| {
+        let (original, deleted_at) = load_transaction(&connection, session.organization, id)?;
+        if deleted_at.is_some() {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "Goods can't be returned from a deleted transaction",
+            ));
+        }
+        if load_goods_return(&connection, id)?.is_some() {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "Returned goods can't be returned again",
+            ));
+        }
+
+        let returns = load_goods_returns(&connection, id)?;
+        let returnable = returnable_items(&original, &returns);
+        if items
+            .iter()
+            .any(|(item_id, &count)| count > returnable.get(item_id).copied().unwrap_or(0))
+        {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "More items are returned than were sold",
+            ));
+        }
+
+        let paid_back: Currency = returns.iter().map(|r| r.amount).sum();
+        if paid_back + amount > original.amount {
+            return Err(SJ::new(
+                Status::BadRequest,
+                "More is paid back than was paid for the sale",
+            ));
+        }
+
+        // the sale the other way around
+        let transaction = relational::NewTransaction {
+            description: Some(RETURN_DESCRIPTION.to_string()),
+            time: None,
+            debited_account: original.credited_account,
+            credited_account: original.debited_account,
+            amount: amount.into(),
+            note,
+            tags: vec![],
+            created_by: Some(session.user.clone()),
+            idempotency_key: None,
+            organization_id: session.organization,
+            event_code: original.event_code.clone(),
+            age_verified: false,
+            device: device.name(),
+            member_id: original.member_id,
+        };
+
+        let transaction_id: Option<i32> = {
+            use crate::schema::tables::transactions::dsl::*;
+            diesel::insert_into(transactions)
+                .values(&transaction)
+                .on_conflict_do_nothing()
+                .returning(id)
+                .get_result(&connection)
+                .optional()?
+        };
+
+        // the same key was posted concurrently, and that request got to insert it first
+        let transaction_id = match transaction_id {
+            Some(transaction_id) => transaction_id,
+            None => {
+                let existing = find_posted(&connection, session.organization, &transaction)?
+                    .ok_or_else(|| SJ::new(Status::Conflict, "The transaction already exists"))?;
+                return Ok((existing, false, None));
             }
+        };
+
+        {
+            use crate::schema::tables::goods_returns::dsl::goods_returns;
+            diesel::insert_into(goods_returns)
+                .values(&relational::GoodsReturn {
+                    transaction_id,
+                    original_transaction_id: id,
+                    reason,
+                    restocked: restock,
+                })
+                .execute(&connection)?;
         }
 
-        Ok(accept.ser(transaction_id))
-    })
+        {
+            use crate::schema::tables::goods_return_items::dsl::goods_return_items;
+            let rows: Vec<_> = items
+                .iter()
+                .map(|(&item_id, &count)| relational::GoodsReturnItem {
+                    transaction_id,
+                    item_id,
+                    amount: count as i32,
+                })
+                .collect();
+            diesel::insert_into(goods_return_items)
+                .values(&rows)
+                .execute(&connection)?;
+        }
+
+        // the items are sold back, at what they were sold for
+        if restock {
+            let unit_prices = original.unit_prices();
+            let bundles = items
+                .iter()
+                .map(|(&item_id, &count)| object::TransactionBundle {
+                    description: None,
+                    price: unit_prices.get(&item_id).copied(),
+                    change: count as i32,
+                    item_ids: std::iter::once((item_id, 1)).collect(),
+                    expires_on: None,
+                    modifier_ids: vec![],
+                })
+                .collect();
+            insert_bundles(&connection, transaction_id, bundles)?;
+        }
+
+        outbox::notify_webhooks(
+            &connection,
+            config,
+            session.organization,
+            "transaction.created",
+            json!({
+                "transaction_id": transaction_id,
+                "amount": transaction.amount,
+                "debited_account": transaction.debited_account,
+                "credited_account": transaction.credited_account,
+                "created_by": transaction.created_by,
+                "returned_from": id,
+            }),
+        )?;
+
+        Ok(transaction_id)
+    })?;
+
+    live.notify(
+        session.organization,
+        LiveEvent::TransactionCreated { id: transaction_id },
+    );
+    Ok(accept.ser(transaction_id))
 }
 
 /// DELETE `/transaction/<transaction_id>`
+///
+/// Responds with 409 Conflict if the transaction is before the lock date of the organization.
+///
+/// Depending on the configuration, voiding a transaction which isn't recent needs the approval of
+/// an admin, see [Gate].
 #[delete("/transaction/<transaction_id>")]
 pub fn delete_transaction(
     db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    session: Session,
+    gate: Gate<'_>,
     accept: SerAccept,
     transaction_id: i32,
 ) -> Result<Ser<i32>, SJ> {
+    session.check_not_training()?;
+    let connection = db_pool.inner().get()?;
+
+    let deleted_id = connection.transaction::<_, SJ, _>(|| {
+        let transaction_time =
+            check_unlocked(&connection, config, session.organization, transaction_id)?;
+
+        if let Some(minutes) = config.approvals.void_after_minutes {
+            if transaction_time < Utc::now() - Duration::minutes(minutes.into()) {
+                gate.check(
+                    &connection,
+                    &session,
+                    GatedAction::Void,
+                    &format!("Void of transaction {}", transaction_id),
+                )?;
+            }
+        }
+
+        use crate::schema::tables::transactions::dsl::{
+            deleted_at, id, organization_id, transactions,
+        };
+        let deleted_id: i32 = diesel::update(transactions)
+            .set(deleted_at.eq(Some(chrono::Utc::now())))
+            .filter(id.eq(transaction_id))
+            .filter(organization_id.eq(session.organization))
+            .returning(id)
+            .get_result(&connection)?;
+
+        outbox::notify_webhooks(
+            &connection,
+            config,
+            session.organization,
+            "transaction.deleted",
+            json!({ "transaction_id": deleted_id }),
+        )?;
+
+        Ok(deleted_id)
+    })?;
+
+    live.notify(
+        session.organization,
+        LiveEvent::TransactionDeleted { id: deleted_id },
+    );
+    Ok(accept.ser(deleted_id))
+}
+
+/// PATCH `/transaction/<transaction_id>`
+///
+/// Change the note, tags and event code of a transaction
+///
+/// Responds with 409 Conflict if the transaction is before the lock date of the organization.
+#[patch("/transaction/<transaction_id>", data = "<annotation>")]
+pub fn patch_transaction(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    session: Session,
+    accept: SerAccept,
+    transaction_id: TransactionId,
+    annotation: Json<object::TransactionAnnotation>,
+) -> Result<Ser<object::TransactionAnnotation>, SJ> {
+    session.check_not_training()?;
+    annotation.validate()?;
     let connection = db_pool.inner().get()?;
+    let annotation = annotation.into_inner().normalized();
+    check_unlocked(&connection, config, session.organization, transaction_id)?;
 
-    use crate::schema::tables::transactions::dsl::{deleted_at, id, transactions};
-    let deleted_id = diesel::update(transactions)
-        .set(deleted_at.eq(Some(chrono::Utc::now().naive_utc())))
+    use crate::schema::tables::transactions::dsl::{
+        event_code, id, note, organization_id, tags, transactions,
+    };
+    diesel::update(transactions)
         .filter(id.eq(transaction_id))
+        .filter(organization_id.eq(session.organization))
+        .set((
+            note.eq(&annotation.note),
+            tags.eq(&annotation.tags),
+            event_code.eq(&annotation.event_code),
+        ))
         .returning(id)
-        .get_result(&connection)?;
+        .get_result::<TransactionId>(&connection)?;
 
-    Ok(accept.ser(deleted_id))
+    Ok(accept.ser(annotation))
+}
+
+/// POST `/transactions/batch`
+///
+/// Void or tag several transactions at once. Either all of them are changed or none of them, and
+/// every change is recorded in the audit log with the same batch id, which is returned.
+///
+/// Responds with 404 Not Found if any of the transactions doesn't exist or has been voided, and
+/// with 409 Conflict if any of them is before the lock date of the organization.
+///
+/// Depending on the configuration, voiding transactions which aren't recent needs the approval of
+/// an admin, see [Gate].
+#[post("/transactions/batch", data = "<batch>")]
+pub fn post_transaction_batch(
+    db_pool: &State<DatabasePool>,
+    config: &State<Config>,
+    live: &State<LiveEvents>,
+    session: Session,
+    gate: Gate<'_>,
+    accept: SerAccept,
+    batch: Json<object::TransactionBatch>,
+) -> Result<Ser<object::BatchOutcome>, SJ> {
+    session.check_not_training()?;
+    batch.validate()?;
+    let object::TransactionBatch {
+        transaction_ids: mut batch_ids,
+        operation,
+    } = batch.into_inner();
+    batch_ids.sort_unstable();
+    batch_ids.dedup();
+
+    let connection = db_pool.inner().get()?;
+    let batch_id = Uuid::new_v4().to_string();
+
+    connection.transaction::<_, SJ, _>(|| {
+        use crate::schema::tables::transactions::dsl::{
+            deleted_at, id, organization_id, tags, time, transactions,
+        };
+        let selected = transactions
+            .filter(id.eq_any(&batch_ids))
+            .filter(organization_id.eq(session.organization))
+            .filter(deleted_at.is_null());
+
+        let found: Vec<(TransactionId, DateTime<Utc>, Vec<String>)> = selected
+            .clone()
+            .select((id, time, tags))
+            .for_update()
+            .load(&connection)?;
+        if found.len() != batch_ids.len() {
+            return Err(SJ::new(
+                Status::NotFound,
+                "Some of the transactions don't exist or have been voided",
+            ));
+        }
+
+        let lock_date = load_lock_date(&connection, session.organization)?;
+        if found
+            .iter()
+            .any(|&(_, tr_time, _)| is_locked(tr_time, lock_date, config.reporting_timezone))
+        {
+            return Err(SJ::new(
+                Status::Conflict,
+                "Some of the transactions are before the lock date and can't be changed",
+            )
+            .with_code(ErrorCode::PeriodLocked)
+            .with_details(&lock_date));
+        }
+
+        match &operation {
+            object::BatchOperation::Void { reason } => {
+                let reason = reason.trim();
+                if let Some(minutes) = config.approvals.void_after_minutes {
+                    let recent = Utc::now() - Duration::minutes(minutes.into());
+                    if found.iter().any(|&(_, tr_time, _)| tr_time < recent) {
+                        gate.check(
+                            &connection,
+                            &session,
+                            GatedAction::Void,
+                            &format!("Void of {} transactions: {}", batch_ids.len(), reason),
+                        )?;
+                    }
+                }
+
+                diesel::update(selected)
+                    .set(deleted_at.eq(Some(Utc::now())))
+                    .execute(&connection)?;
+
+                for &transaction_id in &batch_ids {
+                    audit::record_batch(
+                        &connection,
+                        session.organization,
+                        &session.user,
+                        AuditAction::TransactionVoided,
+                        &batch_id,
+                        &format!("Voided transaction {}: {}", transaction_id, reason),
+                    )?;
+                    outbox::notify_webhooks(
+                        &connection,
+                        config,
+                        session.organization,
+                        "transaction.deleted",
+                        json!({ "transaction_id": transaction_id, "batch_id": batch_id }),
+                    )?;
+                }
+            }
+            object::BatchOperation::AddTags { tags: added } => {
+                for (transaction_id, _, current) in found {
+                    let annotation = object::TransactionAnnotation {
+                        note: None,
+                        tags: current.into_iter().chain(added.iter().cloned()).collect(),
+                        event_code: None,
+                    }
+                    .normalized();
+
+                    diesel::update(transactions)
+                        .filter(id.eq(transaction_id))
+                        .set(tags.eq(&annotation.tags))
+                        .execute(&connection)?;
+
+                    audit::record_batch(
+                        &connection,
+                        session.organization,
+                        &session.user,
+                        AuditAction::TransactionTagged,
+                        &batch_id,
+                        &format!(
+                            "Tagged transaction {} with {}",
+                            transaction_id,
+                            annotation.tags.join(", ")
+                        ),
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    })?;
+
+    if let object::BatchOperation::Void { .. } = operation {
+        for &transaction_id in &batch_ids {
+            live.notify(
+                session.organization,
+                LiveEvent::TransactionDeleted { id: transaction_id },
+            );
+        }
+    }
+
+    Ok(accept.ser(object::BatchOutcome {
+        batch_id,
+        transaction_ids: batch_ids,
+    }))
+}
+
+/// The date before which the transactions of an organization can't be voided or edited
+pub fn load_lock_date(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> Result<Option<NaiveDate>, SJ> {
+    use crate::schema::tables::organizations::dsl::{id, lock_date, organizations};
+    Ok(organizations
+        .filter(id.eq(organization))
+        .select(lock_date)
+        .first(connection)?)
 }
 
-/// GET `/transactions`
+/// Fail if the transaction is before the lock date of the organization, i.e. in a closed period.
+/// Returns the time of the transaction.
+fn check_unlocked(
+    connection: &PgConnection,
+    config: &Config,
+    organization: OrganizationId,
+    transaction_id: TransactionId,
+) -> Result<DateTime<Utc>, SJ> {
+    let lock_date = load_lock_date(connection, organization)?;
+
+    let transaction_time: DateTime<Utc> = {
+        use crate::schema::tables::transactions::dsl::{id, organization_id, time, transactions};
+        transactions
+            .filter(id.eq(transaction_id))
+            .filter(organization_id.eq(organization))
+            .select(time)
+            .first(connection)?
+    };
+
+    if is_locked(transaction_time, lock_date, config.reporting_timezone) {
+        return Err(SJ::new(
+            Status::Conflict,
+            "The transaction is before the lock date and can't be changed",
+        )
+        .with_code(ErrorCode::PeriodLocked)
+        .with_details(&lock_date));
+    }
+
+    Ok(transaction_time)
+}
+
+/// GET `/transactions?<limit>&<before>`
+///
+/// Returns a list of all transactions, newest first.
 ///
-/// Returns a list of all transactions
-#[get("/transactions")]
+/// With `limit`, only that many are returned. The next page is fetched by passing the id of the
+/// last transaction as `before`.
+#[get("/transactions?<limit>&<before>")]
 pub fn get_transactions(
     db_pool: &State<DatabasePool>,
+    session: Session,
     accept: SerAccept,
+    limit: Option<i64>,
+    before: Option<TransactionId>,
 ) -> Result<Ser<Vec<object::Transaction>>, SJ> {
     let connection = db_pool.inner().get()?;
+    if limit.is_none() && before.is_none() {
+        return Ok(accept.ser(load_transactions(&connection, session.organization, None)?));
+    }
+
+    if let Some(limit) = limit.filter(|&limit| limit <= 0) {
+        return Err(SJ::new(
+            Status::BadRequest,
+            format!("The limit must be positive, not {}", limit),
+        ));
+    }
+    Ok(accept.ser(load_transaction_page_before(
+        &connection,
+        session.organization,
+        limit,
+        before,
+    )?))
+}
+
+/// GET `/transaction/<id>`
+///
+/// A single transaction with everything known about it, including if it has been deleted
+#[get("/transaction/<id>")]
+pub fn get_transaction(
+    db_pool: &State<DatabasePool>,
+    session: Session,
+    accept: SerAccept,
+    id: TransactionId,
+) -> Result<Ser<object::TransactionDetail>, SJ> {
+    let connection = db_pool.inner().get()?;
+    let (transaction, deleted_at) = load_transaction(&connection, session.organization, id)?;
+
+    let izettle_reference = {
+        use crate::schema::tables::izettle_post_transaction::dsl::*;
+        izettle_post_transaction
+            .filter(transaction_id.eq(id))
+            .select(izettle_transaction_id)
+            .first(&connection)
+            .optional()?
+    };
+
+    let device = {
+        use crate::schema::tables::transactions::dsl::{device, id as tr_id, transactions};
+        transactions
+            .filter(tr_id.eq(id))
+            .select(device)
+            .first(&connection)?
+    };
+
+    Ok(accept.ser(object::TransactionDetail {
+        transaction,
+        izettle_reference,
+        deleted_at,
+        goods_return: load_goods_return(&connection, id)?,
+        returns: load_goods_returns(&connection, id)?,
+        device,
+    }))
+}
+
+/// Load a single transaction, and when it was deleted if it has been
+fn load_transaction(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    id: TransactionId,
+) -> Result<(object::Transaction, Option<DateTime<Utc>>), SJ> {
+    use crate::schema::tables::transaction_bundles::dsl::{
+        id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::{
+        id as transaction_id, organization_id, transactions,
+    };
+    let joined: Vec<JoinedRow> = transactions
+        .filter(transaction_id.eq(id))
+        .filter(organization_id.eq(organization))
+        .left_join(transaction_bundles.on(transaction_id.eq(bundle_transaction_id)))
+        .left_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
+        .order_by(bundle_id.asc())
+        .load(connection)?;
+
+    let deleted_at = match joined.first() {
+        Some((tr, _, _)) => tr.deleted_at,
+        None => return Err(SJ::new(Status::NotFound, "No such transaction")),
+    };
+
+    let transaction = group_joined(joined)
+        .pop()
+        .expect("rows of a single transaction form one transaction");
+
+    Ok((transaction, deleted_at))
+}
+
+/// The return of goods which the transaction `id` is, if it is one
+fn load_goods_return(
+    connection: &PgConnection,
+    id: TransactionId,
+) -> Result<Option<object::GoodsReturn>, SJ> {
+    use crate::schema::tables::goods_returns::dsl::{goods_returns, transaction_id};
+    use crate::schema::tables::transactions::dsl::{amount, id as tr_id, transactions};
+
+    let row: Option<(relational::GoodsReturn, i64)> = goods_returns
+        .inner_join(transactions.on(tr_id.eq(transaction_id)))
+        .filter(transaction_id.eq(id))
+        .select((crate::schema::tables::goods_returns::all_columns, amount))
+        .first(connection)
+        .optional()?;
+
+    row.map(|(goods_return, paid_back)| with_returned_items(connection, goods_return, paid_back))
+        .transpose()
+}
+
+/// The returns of goods from the transaction `original`, which haven't been deleted
+fn load_goods_returns(
+    connection: &PgConnection,
+    original: TransactionId,
+) -> Result<Vec<object::GoodsReturn>, SJ> {
+    use crate::schema::tables::goods_returns::dsl::{
+        goods_returns, original_transaction_id, transaction_id,
+    };
+    use crate::schema::tables::transactions::dsl::{amount, deleted_at, id as tr_id, transactions};
+
+    let rows: Vec<(relational::GoodsReturn, i64)> = goods_returns
+        .inner_join(transactions.on(tr_id.eq(transaction_id)))
+        .filter(original_transaction_id.eq(original))
+        .filter(deleted_at.is_null())
+        .select((crate::schema::tables::goods_returns::all_columns, amount))
+        .order_by(transaction_id.asc())
+        .load(connection)?;
+
+    rows.into_iter()
+        .map(|(goods_return, paid_back)| with_returned_items(connection, goods_return, paid_back))
+        .collect()
+}
 
-    let joined: Vec<(
-        relational::Transaction,
-        Option<relational::TransactionBundle>,
-        Option<relational::TransactionItem>,
-    )> = {
+fn with_returned_items(
+    connection: &PgConnection,
+    goods_return: relational::GoodsReturn,
+    paid_back: i64,
+) -> Result<object::GoodsReturn, SJ> {
+    use crate::schema::tables::goods_return_items::dsl::{
+        amount, goods_return_items, item_id, transaction_id,
+    };
+    let items: Vec<(InventoryItemId, i32)> = goods_return_items
+        .filter(transaction_id.eq(goods_return.transaction_id))
+        .select((item_id, amount))
+        .load(connection)?;
+
+    Ok(object::GoodsReturn {
+        transaction_id: goods_return.transaction_id,
+        original_transaction_id: goods_return.original_transaction_id,
+        amount: paid_back.into(),
+        reason: goods_return.reason,
+        restocked: goods_return.restocked,
+        items: items
+            .into_iter()
+            .map(|(item, count)| (item, count as u32))
+            .collect(),
+    })
+}
+
+/// Load all transactions of an organization which aren't deleted, newest first, optionally only
+/// those in `range`
+pub fn load_transactions(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    range: Option<Range<DateTime<Utc>>>,
+) -> Result<Vec<object::Transaction>, SJ> {
+    let joined: Vec<JoinedRow> = {
         use crate::schema::tables::transaction_bundles::dsl::{
             id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
         };
@@ -120,18 +1178,201 @@ pub fn get_transactions(
             bundle_id as item_bundle_id, transaction_items,
         };
         use crate::schema::tables::transactions::dsl::{
-            deleted_at, id as transaction_id, time, transactions,
+            deleted_at, id as transaction_id, organization_id, time, transactions,
         };
-        transactions
+        let mut query = transactions
             .filter(deleted_at.is_null())
+            .filter(organization_id.eq(organization))
             .left_join(transaction_bundles.on(transaction_id.eq(bundle_transaction_id)))
             .left_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
             .order_by(time.desc())
             .order_by(transaction_id.desc())
-            .load(&connection)?
+            .into_boxed();
+
+        if let Some(range) = range {
+            query = query
+                .filter(time.ge(range.start))
+                .filter(time.lt(range.end));
+        }
+
+        query.load(connection)?
+    };
+
+    Ok(group_joined(joined))
+}
+
+/// Load at most `limit` transactions of an organization which aren't deleted, newest first,
+/// starting after the transaction `before` if given
+pub fn load_transaction_page_before(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    limit: Option<i64>,
+    before: Option<TransactionId>,
+) -> Result<Vec<object::Transaction>, SJ> {
+    use crate::schema::tables::transaction_bundles::dsl::{
+        id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::{
+        deleted_at, id as transaction_id, organization_id, time, transactions,
     };
 
-    let transactions: Vec<object::Transaction> = joined
+    // pick the transactions first, since the joined rows can't be limited
+    let mut query = transactions
+        .filter(deleted_at.is_null())
+        .filter(organization_id.eq(organization))
+        .select(transaction_id)
+        .order_by((time.desc(), transaction_id.desc()))
+        .into_boxed();
+
+    if let Some(before) = before {
+        let before_time: DateTime<Utc> = transactions
+            .filter(transaction_id.eq(before))
+            .filter(organization_id.eq(organization))
+            .select(time)
+            .first(connection)
+            .optional()?
+            .ok_or_else(|| SJ::new(Status::NotFound, "No such transaction"))?;
+        query = query.filter(
+            time.lt(before_time)
+                .or(time.eq(before_time).and(transaction_id.lt(before))),
+        );
+    }
+    if let Some(limit) = limit {
+        query = query.limit(limit);
+    }
+    let ids: Vec<TransactionId> = query.load(connection)?;
+
+    let joined: Vec<JoinedRow> = transactions
+        .filter(transaction_id.eq_any(&ids))
+        .left_join(transaction_bundles.on(transaction_id.eq(bundle_transaction_id)))
+        .left_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
+        .order_by((time.desc(), transaction_id.desc(), bundle_id.asc()))
+        .load(connection)?;
+
+    Ok(group_joined(joined))
+}
+
+/// Load the transactions of an organization which aren't deleted and are tagged with an event
+/// code, newest first
+pub fn load_event_transactions(
+    connection: &PgConnection,
+    organization: OrganizationId,
+) -> Result<Vec<object::Transaction>, SJ> {
+    use crate::schema::tables::transaction_bundles::dsl::{
+        id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::{
+        deleted_at, event_code, id as transaction_id, organization_id, time, transactions,
+    };
+
+    let joined: Vec<JoinedRow> = transactions
+        .filter(deleted_at.is_null())
+        .filter(organization_id.eq(organization))
+        .filter(event_code.is_not_null())
+        .left_join(transaction_bundles.on(transaction_id.eq(bundle_transaction_id)))
+        .left_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
+        .order_by((time.desc(), transaction_id.desc(), bundle_id.asc()))
+        .load(connection)?;
+
+    Ok(group_joined(joined))
+}
+
+/// Load the transactions of an organization made before `cutoff`, as the ledger looked at that
+/// time, newest first.
+///
+/// Transactions which were deleted after the cutoff are included, since they were still in the
+/// books then.
+pub fn load_transactions_at(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    cutoff: DateTime<Utc>,
+) -> Result<Vec<object::Transaction>, SJ> {
+    use crate::schema::tables::transaction_bundles::dsl::{
+        id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::{
+        deleted_at, id as transaction_id, organization_id, time, transactions,
+    };
+
+    let joined: Vec<JoinedRow> = transactions
+        .filter(organization_id.eq(organization))
+        .filter(time.lt(cutoff))
+        .filter(deleted_at.is_null().or(deleted_at.ge(cutoff)))
+        .left_join(transaction_bundles.on(transaction_id.eq(bundle_transaction_id)))
+        .left_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
+        .order_by((time.desc(), transaction_id.desc(), bundle_id.asc()))
+        .load(connection)?;
+
+    Ok(group_joined(joined))
+}
+
+/// Load at most `limit` transactions of an organization which aren't deleted, oldest first,
+/// starting after the transaction with the given time and id.
+///
+/// For going through every transaction without loading them all at once.
+pub fn load_transaction_page(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    after: Option<(DateTime<Utc>, TransactionId)>,
+    limit: i64,
+) -> Result<Vec<object::Transaction>, SJ> {
+    use crate::schema::tables::transaction_bundles::dsl::{
+        id as bundle_id, transaction_bundles, transaction_id as bundle_transaction_id,
+    };
+    use crate::schema::tables::transaction_items::dsl::{
+        bundle_id as item_bundle_id, transaction_items,
+    };
+    use crate::schema::tables::transactions::dsl::{
+        deleted_at, id as transaction_id, organization_id, time, transactions,
+    };
+
+    let mut page = transactions
+        .filter(deleted_at.is_null())
+        .filter(organization_id.eq(organization))
+        .order_by((time.asc(), transaction_id.asc()))
+        .select(transaction_id)
+        .limit(limit)
+        .into_boxed();
+
+    if let Some((after_time, after_id)) = after {
+        page = page.filter(
+            time.gt(after_time)
+                .or(time.eq(after_time).and(transaction_id.gt(after_id))),
+        );
+    }
+
+    let page_ids: Vec<TransactionId> = page.load(connection)?;
+
+    let joined: Vec<JoinedRow> = transactions
+        .filter(transaction_id.eq_any(&page_ids))
+        .left_join(transaction_bundles.on(transaction_id.eq(bundle_transaction_id)))
+        .left_join(transaction_items.on(bundle_id.eq(item_bundle_id)))
+        .order_by((time.asc(), transaction_id.asc(), bundle_id.asc()))
+        .load(connection)?;
+
+    Ok(group_joined(joined))
+}
+
+type JoinedRow = (
+    relational::Transaction,
+    Option<relational::TransactionBundle>,
+    Option<relational::TransactionItem>,
+);
+
+/// Assemble transactions from rows of transactions left joined with their bundles and items.
+///
+/// The rows of each transaction, and of each bundle, must be adjacent.
+fn group_joined(joined: Vec<JoinedRow>) -> Vec<object::Transaction> {
+    joined
         .into_iter()
         .group_by(|(tr, _, _)| tr.id)
         .into_iter()
@@ -145,6 +1386,12 @@ pub fn get_transactions(
                 debited_account: t0.debited_account,
                 credited_account: t0.credited_account,
                 amount: t0.amount.into(),
+                note: t0.note,
+                tags: t0.tags,
+                event_code: t0.event_code,
+                age_verified: t0.age_verified,
+                member_id: t0.member_id,
+                created_by: t0.created_by,
                 bundles: std::iter::once(b0.map(|b0| (b0, i0)))
                     .chain(xs.map(|(_, bx, ix)| bx.map(|bx| (bx, ix))))
                     .flatten()
@@ -163,12 +1410,456 @@ pub fn get_transactions(
                             price: bundle.price.map(|p| p.into()),
                             change: bundle.change,
                             item_ids,
+                            expires_on: bundle.expires_on,
+                            modifier_ids: bundle.modifier_ids,
                         }
                     })
                     .collect(),
             }
         })
-        .collect();
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{get_json, login, post, post_json, TestDb};
+    use rocket::http::{ContentType, Status};
+    use std::collections::HashMap;
+    use strecklistan_api::book_account::{BookAccount, BookAccountId, BookAccountType};
+    use strecklistan_api::inventory::{InventoryItemId, InventoryItemStock};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_outbox() {
+        use crate::config::WebhookConfig;
+        use crate::schema::tables::outbox::dsl::*;
+        use crate::util::outbox::{backoff, deliver_due};
+        use chrono::{Duration, Utc};
+        use diesel::prelude::*;
+
+        let mut db = TestDb::new();
+        db.config.webhooks = vec![WebhookConfig {
+            // nothing listens on the discard port, so deliveries fail right away
+            url: "http://127.0.0.1:9/hook".to_string(),
+            secret: Some("secret".to_string()),
+        }];
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (_, member_account) = db.member(org, "Testsson");
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let pending = || -> i64 {
+            outbox
+                .filter(delivered_at.is_null())
+                .filter(failed_at.is_null())
+                .count()
+                .get_result(&db.conn())
+                .unwrap()
+        };
+
+        let mut transaction = NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account: cash,
+            credited_account: member_account,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let _: i32 = post_json(&client, "/api/transaction", &transaction);
+        assert_eq!(pending(), 1);
+
+        // the webhook isn't called about changes which are rolled back
+        transaction.debited_account = -1;
+        assert_ne!(post(&client, "/api/transaction", &transaction), Status::Ok);
+        assert_eq!(pending(), 1);
+
+        assert_eq!(deliver_due(&db.conn(), &db.config).unwrap(), 1);
+        let (tries, next): (i32, chrono::DateTime<Utc>) = outbox
+            .select((attempts, next_attempt_at))
+            .first(&db.conn())
+            .unwrap();
+        assert_eq!(tries, 1);
+        assert!(next > Utc::now() + Duration::seconds(20));
+
+        // the failed message waits before it's retried
+        assert_eq!(deliver_due(&db.conn(), &db.config).unwrap(), 0);
+        assert_eq!(pending(), 1);
+
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(2), Duration::seconds(60));
+        assert_eq!(backoff(100), Duration::hours(1));
+    }
+
+    #[test]
+    fn test_idempotency_key() {
+        let db = TestDb::new();
+        let ours = db.organization("Test");
+        let theirs = db.organization("Other");
+        db.user("tester", "hunter2", ours, false);
+        db.user("other", "hunter2", theirs, false);
+
+        let post_with_key = |user: &str, org| -> i32 {
+            let cash = db.account(org, "Kassa", BookAccountType::Assets);
+            let (_, member_account) = db.member(org, "Testsson");
+            let client = db.client();
+            login(&client, user, "hunter2");
+            let transaction = NewTransaction {
+                description: None,
+                bundles: vec![],
+                debited_account: cash,
+                credited_account: member_account,
+                amount: 1000.into(),
+                note: None,
+                tags: vec![],
+                event_code: None,
+                age_verified: false,
+                member_id: None,
+                idempotency_key: Some("same-key".to_string()),
+            };
+            let first: i32 = post_json(&client, "/api/transaction", &transaction);
+            let again: i32 = post_json(&client, "/api/transaction", &transaction);
+            assert_eq!(first, again, "The retry created another transaction");
+            first
+        };
+
+        // the keys are chosen by the clients, so other organizations may use the same ones
+        assert_ne!(
+            post_with_key("tester", ours),
+            post_with_key("other", theirs)
+        );
+    }
+
+    #[test]
+    fn test_goods_return() {
+        use strecklistan_api::currency::Currency;
+        use strecklistan_api::transaction::{
+            NewGoodsReturn, ReturnReason, TransactionBundle, TransactionDetail,
+        };
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let mug = db.item(org, "Mugg", Some(5000.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let mut item_ids = HashMap::new();
+        item_ids.insert(mug, 1);
+        let sale = NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(5000.into()),
+                change: -3,
+                item_ids,
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 15000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let sale_id: i32 = post_json(&client, "/api/transaction", &sale);
+
+        let goods_return = |count: u32, restock: bool| NewGoodsReturn {
+            items: vec![(mug, count)].into_iter().collect(),
+            amount: Currency::from(5000 * count as i32),
+            reason: ReturnReason::Defective,
+            restock,
+            note: None,
+        };
+        let uri = format!("/api/transaction/{}/return", sale_id);
+
+        let return_id: i32 = post_json(&client, &uri, &goods_return(1, true));
+        let _: i32 = post_json(&client, &uri, &goods_return(1, false));
+
+        // only one mug is left to return
+        assert_eq!(
+            post(&client, &uri, &goods_return(2, true)),
+            Status::BadRequest
+        );
+
+        // only the restocked mug is back in stock
+        let items: HashMap<InventoryItemId, InventoryItemStock> =
+            get_json(&client, "/api/inventory/items");
+        assert_eq!(items[&mug].stock, -2);
+
+        let detail: TransactionDetail = get_json(&client, &format!("/api/transaction/{}", sale_id));
+        assert_eq!(detail.returns.len(), 2);
 
-    Ok(accept.ser(transactions))
+        let detail: TransactionDetail =
+            get_json(&client, &format!("/api/transaction/{}", return_id));
+        let returned = detail.goods_return.expect("the transaction is a return");
+        assert_eq!(returned.original_transaction_id, sale_id);
+        assert_eq!(detail.transaction.debited_account, sales);
+        assert_eq!(detail.transaction.credited_account, cash);
+        assert_eq!(detail.transaction.amount, 5000.into());
+
+        // returns can't be returned
+        let uri = format!("/api/transaction/{}/return", return_id);
+        assert_eq!(
+            post(&client, &uri, &goods_return(1, true)),
+            Status::BadRequest
+        );
+    }
+
+    #[test]
+    fn test_recent_transactions() {
+        use crate::routes::rest::live::LiveEvents;
+        use strecklistan_api::live::LiveEvent;
+        use strecklistan_api::transaction::Transaction;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+        let mut live = client.rocket().state::<LiveEvents>().unwrap().subscribe();
+
+        let sale = NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account: cash,
+            credited_account: sales,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+        let ids: Vec<i32> = (0..3)
+            .map(|_| post_json(&client, "/api/transaction", &sale))
+            .collect();
+
+        for &id in &ids {
+            let (event_org, event) = live.try_recv().expect("every sale is announced");
+            assert_eq!(event_org, org);
+            assert_eq!(event, LiveEvent::TransactionCreated { id });
+        }
+
+        let page: Vec<Transaction> = get_json(&client, "/api/transactions?limit=2");
+        let page_ids: Vec<i32> = page.iter().map(|tr| tr.id).collect();
+        assert_eq!(page_ids, vec![ids[2], ids[1]]);
+
+        let uri = format!("/api/transactions?limit=2&before={}", ids[1]);
+        let page: Vec<Transaction> = get_json(&client, &uri);
+        let page_ids: Vec<i32> = page.iter().map(|tr| tr.id).collect();
+        assert_eq!(page_ids, vec![ids[0]]);
+
+        let response = client.get("/api/transactions?limit=0").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let uri = format!("/api/transaction/{}", ids[2]);
+        assert_eq!(client.delete(&uri).dispatch().status(), Status::Ok);
+        let (_, event) = live.try_recv().unwrap();
+        assert_eq!(event, LiveEvent::TransactionDeleted { id: ids[2] });
+    }
+
+    #[test]
+    fn test_member_tags() {
+        use strecklistan_api::inventory::PurchaseLimit;
+        use strecklistan_api::time::PeriodKind;
+        use strecklistan_api::transaction::{TransactionBundle, TransactionDetail};
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        let other_org = db.organization("Other");
+        db.user("tester", "hunter2", org, false);
+        db.user("boss", "hunter3", org, true);
+        let kaffe = db.item(org, "Kaffe", Some(500.into()));
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let sales = db.account(org, "Försäljning", BookAccountType::Revenue);
+        let (member, member_account) = db.member(org, "Testsson");
+        let (_, other_account) = db.member(org, "Andersson");
+        let (stranger, _) = db.member(other_org, "Främling");
+
+        let sale = |debited_account, change: i32, member_id| NewTransaction {
+            description: None,
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: Some(500.into()),
+                change,
+                item_ids: vec![(kaffe, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account,
+            credited_account: sales,
+            amount: (500 * i64::from(change.abs())).into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id,
+            idempotency_key: None,
+        };
+
+        let boss = db.client();
+        login(&boss, "boss", "hunter3");
+        let _: i32 = post_json(&boss, "/api/transaction", &sale(cash, 10, None));
+        let response = boss
+            .put(&format!("/api/inventory/item/{}/purchase_limit", kaffe))
+            .header(ContentType::JSON)
+            .body(
+                serde_json::to_string(&Some(PurchaseLimit {
+                    quantity: 3,
+                    per: PeriodKind::Day,
+                }))
+                .unwrap(),
+            )
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        // paid in cash, without touching the tillgodo of the member
+        let id: i32 = post_json(&client, "/api/transaction", &sale(cash, -2, Some(member)));
+        let detail: TransactionDetail = get_json(&client, &format!("/api/transaction/{}", id));
+        assert_eq!(detail.transaction.member_id, Some(member));
+        let balance = |account| {
+            let accounts: HashMap<BookAccountId, BookAccount> =
+                get_json(&client, "/api/book_accounts");
+            accounts[&account].balance
+        };
+        assert_eq!(balance(member_account), 0.into());
+
+        // sales from a tillgodo are the owner's without being tagged
+        let id: i32 = post_json(&client, "/api/transaction", &sale(member_account, -1, None));
+        let detail: TransactionDetail = get_json(&client, &format!("/api/transaction/{}", id));
+        assert_eq!(detail.transaction.member_id, Some(member));
+
+        let bought: HashMap<InventoryItemId, i64> =
+            get_json(&client, &format!("/api/analytics/member/{}/items", member));
+        assert_eq!(bought.get(&kaffe), Some(&3));
+
+        let status = post(&client, "/api/transaction", &sale(cash, -1, Some(member)));
+        assert_eq!(
+            status,
+            Status::Forbidden,
+            "the tagged sales count towards the limit"
+        );
+
+        let status = post(&client, "/api/transaction", &sale(cash, -1, Some(stranger)));
+        assert_eq!(
+            status,
+            Status::NotFound,
+            "members of other organizations can't be tagged"
+        );
+
+        let status = post(
+            &client,
+            "/api/transaction",
+            &sale(other_account, -1, Some(member)),
+        );
+        assert_eq!(status, Status::BadRequest, "the payer is the buyer");
+    }
+
+    #[test]
+    fn test_transaction_batch() {
+        use diesel::prelude::*;
+        use strecklistan_api::transaction::{
+            BatchOperation, BatchOutcome, Transaction, TransactionBatch,
+        };
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let cash = db.account(org, "Kassa", BookAccountType::Assets);
+        let (_, member_account) = db.member(org, "Testsson");
+        let ids: Vec<i32> = (1..=3)
+            .map(|i: i32| db.transaction(org, cash, member_account, (i * 100).into()))
+            .collect();
+
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let tag = TransactionBatch {
+            transaction_ids: vec![ids[0], ids[1]],
+            operation: BatchOperation::AddTags {
+                tags: vec![" felpris ".to_string()],
+            },
+        };
+        let outcome: BatchOutcome = post_json(&client, "/api/transactions/batch", &tag);
+        assert_eq!(outcome.transaction_ids, vec![ids[0], ids[1]]);
+        let _: BatchOutcome = post_json(&client, "/api/transactions/batch", &tag);
+
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        let tags_of = |tr_id| {
+            transactions
+                .iter()
+                .find(|tr| tr.id == tr_id)
+                .map(|tr| tr.tags.clone())
+                .unwrap()
+        };
+        assert_eq!(tags_of(ids[0]), vec!["felpris".to_string()]);
+        assert!(tags_of(ids[2]).is_empty());
+
+        let void = |transaction_ids: Vec<i32>, reason: &str| TransactionBatch {
+            transaction_ids,
+            operation: BatchOperation::Void {
+                reason: reason.to_string(),
+            },
+        };
+        let uri = "/api/transactions/batch";
+        assert_eq!(
+            post(&client, uri, &void(ids.clone(), " ")),
+            Status::UnprocessableEntity
+        );
+        assert_eq!(
+            post(&client, uri, &void(vec![ids[0], 9999], "Övningspass")),
+            Status::NotFound
+        );
+
+        let outcome: BatchOutcome =
+            post_json(&client, uri, &void(vec![ids[0], ids[2]], "Övningspass"));
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].id, ids[1]);
+
+        // nothing is voided twice, and nothing else of a failed batch is voided
+        assert_eq!(
+            post(&client, uri, &void(vec![ids[1], ids[2]], "Övningspass")),
+            Status::NotFound
+        );
+        let transactions: Vec<Transaction> = get_json(&client, "/api/transactions");
+        assert_eq!(transactions.len(), 1);
+
+        let audited: Vec<(String, Option<String>)> = {
+            use crate::schema::tables::audit_log::dsl::*;
+            audit_log
+                .filter(action.eq("transaction_voided"))
+                .order_by(id)
+                .select((description, batch_id))
+                .load(&db.conn())
+                .unwrap()
+        };
+        assert_eq!(audited.len(), 2);
+        assert!(audited
+            .iter()
+            .all(|(_, batch)| batch.as_deref() == Some(outcome.batch_id.as_str())));
+        assert!(audited[0].0.ends_with("Övningspass"));
+    }
 }
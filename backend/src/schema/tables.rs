@@ -1,3 +1,30 @@
+table! {
+    audit_log (id) {
+        id -> Int4,
+        time -> Timestamptz,
+        organization_id -> Nullable<Int4>,
+        user_name -> Nullable<Varchar>,
+        action -> Text,
+        description -> Text,
+        batch_id -> Nullable<Text>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use strecklistan_api::announcement::AnnouncementSeverityMapping;
+    announcements (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        message -> Text,
+        severity -> AnnouncementSeverityMapping,
+        starts_at -> Timestamptz,
+        ends_at -> Nullable<Timestamptz>,
+        created_by -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use strecklistan_api::book_account::BookAccountTypeMapping;
@@ -6,6 +33,47 @@ table! {
         name -> Text,
         account_type -> BookAccountTypeMapping,
         creditor -> Nullable<Int4>,
+        organization_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use strecklistan_api::correction::CorrectionStatusMapping;
+    corrections (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        debited_account -> Nullable<Int4>,
+        credited_account -> Nullable<Int4>,
+        amount -> Nullable<Int8>,
+        item_id -> Nullable<Int4>,
+        stock_change -> Nullable<Int4>,
+        reason -> Text,
+        status -> CorrectionStatusMapping,
+        requested_by -> Nullable<Varchar>,
+        requested_at -> Timestamptz,
+        decided_by -> Nullable<Varchar>,
+        decided_at -> Nullable<Timestamptz>,
+        transaction_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    devices (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        token -> Text,
+        name -> Text,
+        registered_by -> Nullable<Varchar>,
+        registered_at -> Timestamptz,
+    }
+}
+
+table! {
+    event_reservations (signup_id, item_id) {
+        signup_id -> Int4,
+        item_id -> Int4,
+        amount -> Int4,
     }
 }
 
@@ -28,6 +96,77 @@ table! {
         end_time -> Timestamptz,
         price -> Int4,
         published -> Bool,
+        organization_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use strecklistan_api::izettle::FailedPaymentStatusMapping;
+    failed_payments (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        reference -> Int4,
+        reason -> Text,
+        timed_out -> Bool,
+        failed_at -> Timestamptz,
+        time -> Timestamptz,
+        description -> Nullable<Text>,
+        debited_account -> Int4,
+        credited_account -> Int4,
+        amount -> Int8,
+        note -> Nullable<Text>,
+        tags -> Array<Text>,
+        created_by -> Nullable<Varchar>,
+        event_code -> Nullable<Text>,
+        age_verified -> Bool,
+        member_id -> Nullable<Int4>,
+        bundles -> Jsonb,
+        status -> FailedPaymentStatusMapping,
+        resolved_by -> Nullable<Varchar>,
+        resolved_at -> Nullable<Timestamptz>,
+        retry_reference -> Nullable<Int4>,
+    }
+}
+
+table! {
+    filter_presets (id) {
+        id -> Int4,
+        user_name -> Varchar,
+        organization_id -> Int4,
+        page -> Text,
+        name -> Text,
+        filter -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    goods_return_items (transaction_id, item_id) {
+        transaction_id -> Int4,
+        item_id -> Int4,
+        amount -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use strecklistan_api::transaction::ReturnReasonMapping;
+    goods_returns (transaction_id) {
+        transaction_id -> Int4,
+        original_transaction_id -> Int4,
+        reason -> ReturnReasonMapping,
+        restocked -> Bool,
+    }
+}
+
+table! {
+    import_batches (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        created_by -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+        rolled_back_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -37,6 +176,20 @@ table! {
         name -> Nullable<Text>,
         price -> Nullable<Int4>,
         image_url -> Nullable<Text>,
+        supplier_id -> Nullable<Int4>,
+        organization_id -> Int4,
+        archived_at -> Nullable<Timestamptz>,
+        age_restricted -> Bool,
+        cost_price -> Nullable<Int4>,
+        prepared -> Bool,
+        low_stock_at -> Nullable<Int4>,
+    }
+}
+
+table! {
+    inventory_barcodes (barcode, item_id) {
+        barcode -> Text,
+        item_id -> Int4,
     }
 }
 
@@ -54,6 +207,18 @@ table! {
         name -> Text,
         price -> Int4,
         image_url -> Nullable<Text>,
+        organization_id -> Int4,
+    }
+}
+
+table! {
+    inventory_modifiers (id) {
+        id -> Int4,
+        item_id -> Int4,
+        group_name -> Nullable<Text>,
+        name -> Text,
+        price_delta -> Int4,
+        archived_at -> Nullable<Timestamptz>,
     }
 }
 
@@ -80,7 +245,15 @@ table! {
         time -> Timestamptz,
         debited_account -> Int4,
         credited_account -> Int4,
-        amount -> Int4,
+        amount -> Int8,
+        note -> Nullable<Text>,
+        tags -> Array<Text>,
+        created_by -> Nullable<Varchar>,
+        idempotency_key -> Nullable<Text>,
+        organization_id -> Int4,
+        event_code -> Nullable<Text>,
+        age_verified -> Bool,
+        member_id -> Nullable<Int4>,
     }
 }
 
@@ -89,8 +262,9 @@ table! {
         id -> Int4,
         transaction_id -> Int4,
         description -> Nullable<Text>,
-        price -> Nullable<Int4>,
+        price -> Nullable<Int8>,
         change -> Int4,
+        modifier_ids -> Array<Int4>,
     }
 }
 
@@ -108,6 +282,200 @@ table! {
         first_name -> Text,
         last_name -> Text,
         nickname -> Nullable<Text>,
+        organization_id -> Int4,
+        archived_at -> Nullable<Timestamptz>,
+        email -> Nullable<Text>,
+        email_verified_at -> Nullable<Timestamptz>,
+        email_code -> Nullable<Text>,
+        email_code_sent_at -> Nullable<Timestamptz>,
+        email_code_attempts -> Int4,
+        mail_receipts -> Bool,
+        mail_balance_reminders -> Bool,
+        mail_event_info -> Bool,
+        balance_reminded_at -> Nullable<Timestamptz>,
+        balance_token -> Nullable<Text>,
+    }
+}
+
+table! {
+    organizations (id) {
+        id -> Int4,
+        name -> Text,
+        lock_date -> Nullable<Date>,
+        org_number -> Nullable<Varchar>,
+        footer_text -> Nullable<Text>,
+        logo -> Nullable<Bytea>,
+        logo_content_type -> Nullable<Varchar>,
+        logo_updated_at -> Nullable<Timestamptz>,
+        transactions_exported_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    outbox (id) {
+        id -> Int4,
+        created_at -> Timestamptz,
+        kind -> Text,
+        payload -> Jsonb,
+        attempts -> Int4,
+        next_attempt_at -> Timestamptz,
+        last_error -> Nullable<Text>,
+        delivered_at -> Nullable<Timestamptz>,
+        failed_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    parked_carts (id) {
+        id -> Int4,
+        name -> Text,
+        created_at -> Timestamptz,
+        debited_account -> Nullable<Int4>,
+        bundles -> Jsonb,
+        organization_id -> Int4,
+    }
+}
+
+table! {
+    prepared_orders (transaction_id) {
+        transaction_id -> Int4,
+        organization_id -> Int4,
+        prepared_by -> Nullable<Varchar>,
+        prepared_at -> Timestamptz,
+    }
+}
+
+table! {
+    purchase_limits (item_id) {
+        item_id -> Int4,
+        quantity -> Int4,
+        per -> Text,
+    }
+}
+
+table! {
+    queue_tickets (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        transaction_id -> Int4,
+        day -> Date,
+        number -> Int4,
+        created_at -> Timestamptz,
+        ready_at -> Nullable<Timestamptz>,
+        collected_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    register_shifts (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        opened_by -> Nullable<Varchar>,
+        opened_at -> Timestamptz,
+        closed_by -> Nullable<Varchar>,
+        closed_at -> Nullable<Timestamptz>,
+        auto_closed -> Bool,
+        reminded_at -> Nullable<Timestamptz>,
+        reconciled_by -> Nullable<Varchar>,
+        reconciled_at -> Nullable<Timestamptz>,
+        opened_on -> Nullable<Text>,
+        closed_on -> Nullable<Text>,
+    }
+}
+
+table! {
+    sales_goals (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        name -> Text,
+        revenue_target -> Nullable<Int8>,
+        quantity_target -> Nullable<Int4>,
+        period -> Nullable<Text>,
+        event_code -> Nullable<Text>,
+        created_by -> Nullable<Varchar>,
+        created_at -> Timestamptz,
+    }
+}
+
+table! {
+    storage_locations (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        name -> Text,
+    }
+}
+
+table! {
+    store_layout_buttons (id) {
+        id -> Int4,
+        tab_id -> Int4,
+        item_id -> Nullable<Int4>,
+        bundle_id -> Nullable<Int4>,
+        x -> Int4,
+        y -> Int4,
+        width -> Int4,
+        height -> Int4,
+        color -> Nullable<Text>,
+    }
+}
+
+table! {
+    store_layout_tabs (id) {
+        id -> Int4,
+        name -> Text,
+        position -> Int4,
+        organization_id -> Int4,
+    }
+}
+
+table! {
+    stored_items (location_id, item_id) {
+        location_id -> Int4,
+        item_id -> Int4,
+        amount -> Int4,
+    }
+}
+
+table! {
+    suppliers (id) {
+        id -> Int4,
+        name -> Text,
+        contact -> Nullable<Text>,
+        ordering_url -> Nullable<Text>,
+        delivery_days -> Array<Text>,
+        organization_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use strecklistan_api::swish::SwishStatusMapping;
+    swish_payments (id) {
+        id -> Text,
+        organization_id -> Int4,
+        member_id -> Int4,
+        credited_account -> Int4,
+        amount -> Int8,
+        status -> SwishStatusMapping,
+        token -> Nullable<Text>,
+        error -> Nullable<Text>,
+        transaction_id -> Nullable<Int4>,
+        created_at -> Timestamptz,
+        completed_at -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    training_transactions (id) {
+        id -> Int4,
+        organization_id -> Int4,
+        created_by -> Varchar,
+        time -> Timestamptz,
+        description -> Nullable<Text>,
+        debited_account -> Int4,
+        credited_account -> Int4,
+        amount -> Int8,
+        bundles -> Jsonb,
     }
 }
 
@@ -116,8 +484,10 @@ table! {
         id -> Int4,
         transaction_id -> Int4,
         description -> Nullable<Text>,
-        price -> Nullable<Int4>,
+        price -> Nullable<Int8>,
         change -> Int4,
+        expires_on -> Nullable<Date>,
+        modifier_ids -> Array<Int4>,
     }
 }
 
@@ -129,6 +499,17 @@ table! {
     }
 }
 
+table! {
+    transaction_line_summaries (organization_id, day, item_id) {
+        organization_id -> Int4,
+        day -> Date,
+        item_id -> Int4,
+        sold -> Int8,
+        restocked -> Int8,
+        stock_change -> Int8,
+    }
+}
+
 table! {
     transactions (id) {
         id -> Int4,
@@ -136,8 +517,41 @@ table! {
         time -> Timestamptz,
         debited_account -> Int4,
         credited_account -> Int4,
-        amount -> Int4,
+        amount -> Int8,
         deleted_at -> Nullable<Timestamptz>,
+        note -> Nullable<Text>,
+        tags -> Array<Text>,
+        created_by -> Nullable<Varchar>,
+        idempotency_key -> Nullable<Text>,
+        organization_id -> Int4,
+        event_code -> Nullable<Text>,
+        age_verified -> Bool,
+        import_batch_id -> Nullable<Int4>,
+        device -> Nullable<Text>,
+        member_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    user_devices (user_name, device_id) {
+        user_name -> Varchar,
+        device_id -> Text,
+        last_seen -> Timestamptz,
+    }
+}
+
+table! {
+    user_organizations (user_name, organization_id) {
+        user_name -> Varchar,
+        organization_id -> Int4,
+        is_admin -> Bool,
+    }
+}
+
+table! {
+    user_preferences (user_name) {
+        user_name -> Varchar,
+        preferences -> Jsonb,
     }
 }
 
@@ -147,37 +561,123 @@ table! {
         display_name -> Nullable<Varchar>,
         salted_pass -> Varchar,
         hash_iterations -> Int4,
+        sessions_invalidated_at -> Nullable<Timestamptz>,
+        email -> Nullable<Varchar>,
+        failed_logins -> Int4,
+        locked_until -> Nullable<Timestamptz>,
     }
 }
 
+joinable!(announcements -> organizations (organization_id));
+joinable!(announcements -> users (created_by));
+joinable!(audit_log -> organizations (organization_id));
+joinable!(audit_log -> users (user_name));
 joinable!(book_accounts -> members (creditor));
+joinable!(corrections -> inventory (item_id));
+joinable!(corrections -> organizations (organization_id));
+joinable!(corrections -> transactions (transaction_id));
+joinable!(devices -> organizations (organization_id));
+joinable!(devices -> users (registered_by));
+joinable!(event_reservations -> event_signups (signup_id));
+joinable!(event_reservations -> inventory (item_id));
 joinable!(event_signups -> events (event));
+joinable!(events -> organizations (organization_id));
+joinable!(failed_payments -> members (member_id));
+joinable!(failed_payments -> organizations (organization_id));
+joinable!(filter_presets -> organizations (organization_id));
+joinable!(filter_presets -> users (user_name));
+joinable!(goods_return_items -> goods_returns (transaction_id));
+joinable!(goods_return_items -> inventory (item_id));
+joinable!(inventory -> suppliers (supplier_id));
+joinable!(inventory_barcodes -> inventory (item_id));
 joinable!(inventory_bundle_items -> inventory (item_id));
 joinable!(inventory_bundle_items -> inventory_bundles (bundle_id));
+joinable!(inventory_modifiers -> inventory (item_id));
 joinable!(inventory_tags -> inventory (item_id));
 joinable!(izettle_post_transaction -> transactions (transaction_id));
+joinable!(izettle_transaction -> members (member_id));
 joinable!(izettle_transaction_bundle -> izettle_transaction (transaction_id));
 joinable!(izettle_transaction_item -> inventory (item_id));
 joinable!(izettle_transaction_item -> izettle_transaction_bundle (bundle_id));
+joinable!(parked_carts -> book_accounts (debited_account));
+joinable!(prepared_orders -> organizations (organization_id));
+joinable!(prepared_orders -> transactions (transaction_id));
+joinable!(prepared_orders -> users (prepared_by));
+joinable!(purchase_limits -> inventory (item_id));
+joinable!(queue_tickets -> organizations (organization_id));
+joinable!(queue_tickets -> transactions (transaction_id));
+joinable!(register_shifts -> organizations (organization_id));
+joinable!(sales_goals -> organizations (organization_id));
+joinable!(sales_goals -> users (created_by));
+joinable!(storage_locations -> organizations (organization_id));
+joinable!(store_layout_buttons -> inventory (item_id));
+joinable!(store_layout_buttons -> inventory_bundles (bundle_id));
+joinable!(store_layout_buttons -> store_layout_tabs (tab_id));
+joinable!(stored_items -> inventory (item_id));
+joinable!(stored_items -> storage_locations (location_id));
+joinable!(swish_payments -> book_accounts (credited_account));
+joinable!(swish_payments -> members (member_id));
+joinable!(swish_payments -> organizations (organization_id));
+joinable!(swish_payments -> transactions (transaction_id));
+joinable!(training_transactions -> organizations (organization_id));
+joinable!(training_transactions -> users (created_by));
 joinable!(transaction_bundles -> transactions (transaction_id));
 joinable!(transaction_items -> inventory (item_id));
 joinable!(transaction_items -> transaction_bundles (bundle_id));
+joinable!(transaction_line_summaries -> inventory (item_id));
+joinable!(transaction_line_summaries -> organizations (organization_id));
+joinable!(transactions -> members (member_id));
+joinable!(user_devices -> users (user_name));
+joinable!(user_organizations -> organizations (organization_id));
+joinable!(user_organizations -> users (user_name));
+joinable!(user_preferences -> users (user_name));
 
 allow_tables_to_appear_in_same_query!(
+    announcements,
+    audit_log,
     book_accounts,
+    corrections,
+    devices,
+    event_reservations,
     event_signups,
     events,
+    failed_payments,
+    filter_presets,
+    goods_return_items,
+    goods_returns,
+    import_batches,
     inventory,
+    inventory_barcodes,
     inventory_bundle_items,
     inventory_bundles,
+    inventory_modifiers,
     inventory_tags,
     izettle_post_transaction,
     izettle_transaction,
     izettle_transaction_bundle,
     izettle_transaction_item,
     members,
+    organizations,
+    outbox,
+    parked_carts,
+    prepared_orders,
+    purchase_limits,
+    queue_tickets,
+    register_shifts,
+    sales_goals,
+    storage_locations,
+    store_layout_buttons,
+    store_layout_tabs,
+    stored_items,
+    suppliers,
+    swish_payments,
+    training_transactions,
     transaction_bundles,
     transaction_items,
+    transaction_line_summaries,
     transactions,
+    user_devices,
+    user_organizations,
+    user_preferences,
     users,
 );
@@ -1,6 +1,8 @@
 // Bindings to database views aren't automatically generated by diesel.
 // This file has to be updated manually.
 
+use super::tables::inventory;
+
 table! {
     events_with_signups (id) {
         id -> Int4,
@@ -11,6 +13,7 @@ table! {
         end_time -> Timestamptz,
         price -> Int4,
         published -> Bool,
+        organization_id -> Int4,
         signups -> Int8,
     }
 }
@@ -24,3 +27,34 @@ table! {
         stock -> Int4,
     }
 }
+
+allow_tables_to_appear_in_same_query!(inventory, inventory_stock);
+
+table! {
+    daily_sales (organization_id, day, item_id) {
+        organization_id -> Int4,
+        day -> Date,
+        created_by -> Nullable<Varchar>,
+        item_id -> Int4,
+        sold -> Int8,
+    }
+}
+
+table! {
+    item_totals (item_id) {
+        organization_id -> Int4,
+        item_id -> Int4,
+        sold -> Int8,
+        restocked -> Int8,
+        last_sold -> Nullable<Timestamptz>,
+    }
+}
+
+table! {
+    modifier_totals (modifier_id) {
+        organization_id -> Int4,
+        item_id -> Int4,
+        modifier_id -> Int4,
+        sold -> Int8,
+    }
+}
@@ -0,0 +1,199 @@
+//! Builders for the rows which most tests need
+
+use super::TestDb;
+use crate::auth::hash_password;
+use crate::models::transaction::relational::NewTransaction;
+use chrono::{DateTime, Utc};
+use diesel::prelude::*;
+use strecklistan_api::book_account::{BookAccountId, BookAccountType};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::event::EventId;
+use strecklistan_api::inventory::{InventoryItemId, ItemModifierId};
+use strecklistan_api::member::MemberId;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::transaction::TransactionId;
+
+impl TestDb {
+    pub fn organization(&self, org_name: &str) -> OrganizationId {
+        use crate::schema::tables::organizations::dsl::*;
+        diesel::insert_into(organizations)
+            .values(name.eq(org_name))
+            .returning(id)
+            .get_result(&self.conn())
+            .expect("Could not create organization")
+    }
+
+    /// A user in `organization` who logs in with `password`
+    pub fn user(&self, user: &str, password: &str, organization: OrganizationId, admin: bool) {
+        let connection = self.conn();
+        let hash = hash_password(password, &self.config.passwords).unwrap();
+        {
+            use crate::schema::tables::users::dsl::*;
+            diesel::insert_into(users)
+                .values((
+                    name.eq(user),
+                    salted_pass.eq(hash),
+                    hash_iterations.eq(self.config.passwords.iterations as i32),
+                ))
+                .execute(&connection)
+                .expect("Could not create user");
+        }
+        self.membership(user, organization, admin);
+    }
+
+    /// Add an existing user to another organization, with or without the admin role there
+    pub fn membership(&self, user: &str, organization: OrganizationId, admin: bool) {
+        use crate::schema::tables::user_organizations::dsl::*;
+        diesel::insert_into(user_organizations)
+            .values((
+                user_name.eq(user),
+                organization_id.eq(organization),
+                is_admin.eq(admin),
+            ))
+            .execute(&self.conn())
+            .expect("Could not add user to organization");
+    }
+
+    pub fn item(
+        &self,
+        organization: OrganizationId,
+        item_name: &str,
+        item_price: Option<Currency>,
+    ) -> InventoryItemId {
+        use crate::schema::tables::inventory::dsl::*;
+        diesel::insert_into(inventory)
+            .values((
+                name.eq(item_name),
+                price.eq(item_price.map(|p| i64::from(p) as i32)),
+                organization_id.eq(organization),
+            ))
+            .returning(id)
+            .get_result(&self.conn())
+            .expect("Could not create item")
+    }
+
+    /// A variant or add-on of `item`, which changes its price by `delta`
+    pub fn modifier(
+        &self,
+        item: InventoryItemId,
+        group: Option<&str>,
+        modifier_name: &str,
+        delta: Currency,
+    ) -> ItemModifierId {
+        use crate::schema::tables::inventory_modifiers::dsl::*;
+        diesel::insert_into(inventory_modifiers)
+            .values((
+                item_id.eq(item),
+                group_name.eq(group),
+                name.eq(modifier_name),
+                price_delta.eq(i64::from(delta) as i32),
+            ))
+            .returning(id)
+            .get_result(&self.conn())
+            .expect("Could not create modifier")
+    }
+
+    pub fn account(
+        &self,
+        organization: OrganizationId,
+        account_name: &str,
+        kind: BookAccountType,
+    ) -> BookAccountId {
+        use crate::schema::tables::book_accounts::dsl::*;
+        diesel::insert_into(book_accounts)
+            .values((
+                name.eq(account_name),
+                account_type.eq(kind),
+                organization_id.eq(organization),
+            ))
+            .returning(id)
+            .get_result(&self.conn())
+            .expect("Could not create book account")
+    }
+
+    /// A member and their tillgodo account
+    pub fn member(&self, organization: OrganizationId, first: &str) -> (MemberId, BookAccountId) {
+        let connection = self.conn();
+        let member = {
+            use crate::schema::tables::members::dsl::*;
+            diesel::insert_into(members)
+                .values((
+                    first_name.eq(first),
+                    last_name.eq(""),
+                    organization_id.eq(organization),
+                ))
+                .returning(id)
+                .get_result(&connection)
+                .expect("Could not create member")
+        };
+        let account = {
+            use crate::schema::tables::book_accounts::dsl::*;
+            diesel::insert_into(book_accounts)
+                .values((
+                    name.eq(first),
+                    account_type.eq(BookAccountType::Liabilities),
+                    creditor.eq(Some(member)),
+                    organization_id.eq(organization),
+                ))
+                .returning(id)
+                .get_result(&connection)
+                .expect("Could not create member account")
+        };
+        (member, account)
+    }
+
+    /// A transaction without any items, e.g. a deposit
+    pub fn transaction(
+        &self,
+        organization: OrganizationId,
+        debited: BookAccountId,
+        credited: BookAccountId,
+        amount: Currency,
+    ) -> TransactionId {
+        use crate::schema::tables::transactions::dsl::*;
+        diesel::insert_into(transactions)
+            .values(NewTransaction {
+                description: None,
+                time: Some(Utc::now()),
+                debited_account: debited,
+                credited_account: credited,
+                amount: amount.into(),
+                note: None,
+                tags: vec![],
+                created_by: None,
+                idempotency_key: None,
+                organization_id: organization,
+                event_code: None,
+                age_verified: false,
+                device: None,
+                member_id: None,
+            })
+            .returning(id)
+            .get_result(&self.conn())
+            .expect("Could not create transaction")
+    }
+
+    /// A published event of `organization` which starts at `start`
+    pub fn event(
+        &self,
+        organization: OrganizationId,
+        event_title: &str,
+        start: DateTime<Utc>,
+    ) -> EventId {
+        use crate::schema::tables::events::dsl::*;
+        diesel::insert_into(events)
+            .values((
+                title.eq(event_title),
+                background.eq(""),
+                location.eq(""),
+                start_time.eq(start),
+                end_time.eq(start),
+                price.eq(0),
+                published.eq(true),
+                organization_id.eq(organization),
+            ))
+            .returning(id)
+            .get_result(&self.conn())
+            .expect("Could not create event")
+    }
+}
@@ -0,0 +1,117 @@
+//! Helpers for testing routes against a real database.
+//!
+//! Every [TestDb] keeps its data in a transaction which is never committed, so tests don't see
+//! each other's data and leave the database as it was. `DATABASE_URL` must still point to a
+//! migrated database, see `travis.env`.
+//!
+//! The tests themselves live in a `tests` module next to the routes they cover.
+//!
+//! ```ignore
+//! let db = TestDb::new();
+//! let org = db.organization("Test");
+//! db.user("tester", "hunter2", org, false);
+//!
+//! let client = db.client();
+//! login(&client, "tester", "hunter2");
+//! let members: HashMap<MemberId, Member> = get_json(&client, "/api/members");
+//! ```
+
+mod fixtures;
+
+use crate::build_rocket;
+use crate::config::Config;
+use crate::database::{DatabaseConn, DatabasePool};
+use crate::routes::index::IndexHtml;
+use diesel::connection::Connection;
+use diesel::pg::PgConnection;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection};
+use dotenv::dotenv;
+use r2d2::Pool;
+use rocket::http::{ContentType, Status};
+use rocket::local::blocking::Client;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use strecklistan_api::user::Credentials;
+
+pub struct TestDb {
+    pub pool: DatabasePool,
+    pub config: Config,
+}
+
+/// Starts a transaction on the connection which is rolled back when it is closed
+#[derive(Debug)]
+struct TestTransaction;
+
+impl CustomizeConnection<PgConnection, diesel::r2d2::Error> for TestTransaction {
+    fn on_acquire(&self, connection: &mut PgConnection) -> Result<(), diesel::r2d2::Error> {
+        connection
+            .begin_test_transaction()
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+impl TestDb {
+    pub fn new() -> Self {
+        dotenv().ok();
+        let config = Config::load().expect("Could not load config");
+
+        // a single connection, so that everything happens in the same transaction
+        let pool = Pool::builder()
+            .max_size(1)
+            .connection_customizer(Box::new(TestTransaction))
+            .build(ConnectionManager::new(&config.database.url))
+            .expect("Could not create database pool");
+
+        TestDb { pool, config }
+    }
+
+    pub fn conn(&self) -> DatabaseConn {
+        self.pool.get().expect("Could not get database connection")
+    }
+
+    /// A client for the server, which uses this database
+    pub fn client(&self) -> Client {
+        let index = IndexHtml::default().with_base_path(&self.config.proxy.base_path);
+        let rocket = build_rocket(self.config.clone(), self.pool.clone()).manage(index);
+        Client::tracked(rocket).expect("Could not start rocket")
+    }
+}
+
+/// Log in, the client then keeps the session cookie
+pub fn login(client: &Client, user: &str, password: &str) {
+    let credentials = Credentials {
+        name: user.to_string(),
+        password: password.to_string(),
+        organization: None,
+    };
+    let status = post(client, "/api/login", &credentials);
+    assert_eq!(status, Status::Ok, "Failed to log in as {}", user);
+}
+
+/// GET `uri` and read the response, which must be successful
+pub fn get_json<T: DeserializeOwned>(client: &Client, uri: &str) -> T {
+    let response = client.get(uri).dispatch();
+    assert_eq!(response.status(), Status::Ok, "GET {}", uri);
+    response.into_json().expect("Invalid response body")
+}
+
+/// POST `body` to `uri` and read the response, which must be successful
+pub fn post_json<B: Serialize, T: DeserializeOwned>(client: &Client, uri: &str, body: &B) -> T {
+    let response = client
+        .post(uri)
+        .header(ContentType::JSON)
+        .body(serde_json::to_string(body).unwrap())
+        .dispatch();
+    assert_eq!(response.status(), Status::Ok, "POST {}", uri);
+    response.into_json().expect("Invalid response body")
+}
+
+/// POST `body` to `uri`, for checking the status of failing requests
+pub fn post<B: Serialize>(client: &Client, uri: &str, body: &B) -> Status {
+    client
+        .post(uri)
+        .header(ContentType::JSON)
+        .body(serde_json::to_string(body).unwrap())
+        .dispatch()
+        .status()
+}
@@ -0,0 +1,86 @@
+//! The materialized views behind the analytics page, `daily_sales`, `item_totals` and
+//! `modifier_totals`.
+//!
+//! Aggregating every transaction on every page load got too slow, so the aggregates are stored
+//! and recomputed periodically by the [AnalyticsRefresher].
+
+use crate::config::Config;
+use crate::database::DatabasePool;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use log::{error, info};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use std::time::{Duration, Instant};
+use strecklistan_api::time::Tz;
+
+/// Recompute the analytics views, grouping by calendar day in `tz`
+pub fn refresh(connection: &PgConnection, tz: Tz) -> QueryResult<()> {
+    let started = Instant::now();
+
+    connection.transaction(|| {
+        // the views cast times to dates in the timezone of the session
+        diesel::sql_query("SELECT set_config('TimeZone', $1, true)")
+            .bind::<Text, _>(tz.name())
+            .execute(connection)?;
+
+        // concurrently, so that the analytics page can be read while refreshing
+        diesel::sql_query("REFRESH MATERIALIZED VIEW CONCURRENTLY daily_sales")
+            .execute(connection)?;
+        diesel::sql_query("REFRESH MATERIALIZED VIEW CONCURRENTLY item_totals")
+            .execute(connection)?;
+        diesel::sql_query("REFRESH MATERIALIZED VIEW CONCURRENTLY modifier_totals")
+            .execute(connection)?;
+        Ok(())
+    })?;
+
+    info!("Refreshed the analytics in {:?}", started.elapsed());
+    Ok(())
+}
+
+/// Refreshes the analytics views periodically, once the server has started
+pub struct AnalyticsRefresher;
+
+#[rocket::async_trait]
+impl Fairing for AnalyticsRefresher {
+    fn info(&self) -> Info {
+        Info {
+            name: "Analytics refresher",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (db_pool, config) = match (rocket.state::<DatabasePool>(), rocket.state::<Config>()) {
+            (Some(db_pool), Some(config)) => (db_pool.clone(), config),
+            _ => {
+                error!("The analytics refresher needs the database pool and the config");
+                return;
+            }
+        };
+
+        let tz = config.reporting_timezone;
+        let period = Duration::from_secs(config.analytics.refresh_interval_secs);
+        rocket::tokio::spawn(async move {
+            // the first tick is right away, so the views are refreshed in the reporting timezone
+            // at startup. The migration creating them doesn't know about it.
+            let mut interval = rocket::tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+
+                let db_pool = db_pool.clone();
+                let result = rocket::tokio::task::spawn_blocking(move || {
+                    let connection = db_pool.get().map_err(|e| e.to_string())?;
+                    refresh(&connection, tz).map_err(|e| e.to_string())
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("Failed to refresh the analytics: {}", e),
+                    Err(e) => error!("The analytics refresher panicked: {}", e),
+                }
+            }
+        });
+    }
+}
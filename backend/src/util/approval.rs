@@ -0,0 +1,168 @@
+//! Approvals of actions which users who aren't admins may only take with the consent of an admin.
+//!
+//! An admin approves an action by entering their password on the register, which gives a token
+//! that lets the action be taken for a short while. The request for the action carries the token
+//! in the [APPROVAL_HEADER], and every action taken with it is recorded in the audit log together
+//! with who approved it.
+//! Which actions need approval is configured in [ApprovalsConfig](crate::config::ApprovalsConfig).
+
+use crate::auth::{AdminSession, Session};
+use crate::util::audit::{self, AuditAction};
+use crate::util::StatusJson;
+use chrono::{DateTime, Duration, Utc};
+use diesel::PgConnection;
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::State;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use strecklistan_api::approval::{Approval, GatedAction, APPROVAL_HEADER};
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::user::UserName;
+use uuid::Uuid;
+
+struct Grant {
+    organization: OrganizationId,
+    action: GatedAction,
+    approved_by: UserName,
+    expires: DateTime<Utc>,
+}
+
+/// The approvals which haven't expired yet.
+///
+/// They are kept in memory, so they are lost when the server restarts.
+#[derive(Default)]
+pub struct Approvals {
+    grants: Mutex<HashMap<String, Grant>>,
+}
+
+impl Approvals {
+    /// Let `action` be taken in `organization` for `ttl`
+    pub fn grant(
+        &self,
+        organization: OrganizationId,
+        action: GatedAction,
+        approved_by: UserName,
+        ttl: Duration,
+    ) -> Approval {
+        let token = Uuid::new_v4().to_string();
+        let expires = Utc::now() + ttl;
+        self.grants.lock().unwrap().insert(
+            token.clone(),
+            Grant {
+                organization,
+                action,
+                approved_by: approved_by.clone(),
+                expires,
+            },
+        );
+        Approval {
+            token,
+            action,
+            approved_by,
+            expires,
+        }
+    }
+
+    /// Who approved `action` with `token`, if it hasn't expired
+    fn approved_by(
+        &self,
+        token: &str,
+        organization: OrganizationId,
+        action: GatedAction,
+    ) -> Option<UserName> {
+        let now = Utc::now();
+        let mut grants = self.grants.lock().unwrap();
+        grants.retain(|_, grant| grant.expires > now);
+
+        grants
+            .get(token)
+            .filter(|grant| grant.organization == organization && grant.action == action)
+            .map(|grant| grant.approved_by.clone())
+    }
+}
+
+/// Request guard for routes with [GatedActions](GatedAction), see [Gate::check]
+pub struct Gate<'r> {
+    approvals: &'r Approvals,
+    admin: bool,
+    tokens: Vec<String>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Gate<'r> {
+    type Error = StatusJson;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let approvals = match req.guard::<&State<Approvals>>().await {
+            Outcome::Success(approvals) => approvals.inner(),
+            _ => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    Status::InternalServerError.into(),
+                ))
+            }
+        };
+        let admin = matches!(
+            req.guard::<Option<AdminSession>>().await,
+            Outcome::Success(Some(_))
+        );
+        // a request may need several approvals, e.g. a sale with a discount on credit
+        let tokens = req
+            .headers()
+            .get(APPROVAL_HEADER)
+            .flat_map(|value| value.split(','))
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        Outcome::Success(Gate {
+            approvals,
+            admin,
+            tokens,
+        })
+    }
+}
+
+impl Gate<'_> {
+    /// Let `session` take `action` if it belongs to an admin, or if the request carries a valid
+    /// approval of it. Approved actions are recorded in the audit log, described by
+    /// `description`.
+    ///
+    /// Responds with 403 Forbidden and [ErrorCode::ApprovalRequired] otherwise.
+    pub fn check(
+        &self,
+        connection: &PgConnection,
+        session: &Session,
+        action: GatedAction,
+        description: &str,
+    ) -> Result<(), StatusJson> {
+        if self.admin {
+            return Ok(());
+        }
+
+        let approved_by = self.tokens.iter().find_map(|token| {
+            self.approvals
+                .approved_by(token, session.organization, action)
+        });
+        match approved_by {
+            Some(admin) => {
+                audit::record(
+                    connection,
+                    Some(session.organization),
+                    Some(&session.user),
+                    AuditAction::ActionApproved,
+                    &format!("{}, approved by {}", description, admin),
+                )?;
+                Ok(())
+            }
+            None => Err(
+                StatusJson::new(Status::Forbidden, "An admin has to approve this")
+                    .with_code(ErrorCode::ApprovalRequired)
+                    .with_details(&action),
+            ),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! The audit log, a record of security relevant events for admins to review.
+
+use crate::schema::tables::audit_log;
+use diesel::prelude::*;
+use strecklistan_api::organization::OrganizationId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Login,
+    LoginFailed,
+    AccountLocked,
+    NewDeviceLogin,
+    LockDateChanged,
+    ImportRolledBack,
+    ItemEdited,
+    BalanceReminderSent,
+    CatalogImported,
+    ActionApproved,
+    DeviceRegistered,
+    DeviceRemoved,
+    RetentionApplied,
+    CorrectionRequested,
+    CorrectionApplied,
+    CorrectionRejected,
+    TransactionVoided,
+    TransactionTagged,
+    FailedPaymentRetried,
+    FailedPaymentWrittenOff,
+    FailedPaymentPaidLate,
+}
+
+impl AuditAction {
+    /// The name stored in the `action` column
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Login => "login",
+            AuditAction::LoginFailed => "login_failed",
+            AuditAction::AccountLocked => "account_locked",
+            AuditAction::NewDeviceLogin => "new_device_login",
+            AuditAction::LockDateChanged => "lock_date_changed",
+            AuditAction::ImportRolledBack => "import_rolled_back",
+            AuditAction::ItemEdited => "item_edited",
+            AuditAction::BalanceReminderSent => "balance_reminder_sent",
+            AuditAction::CatalogImported => "catalog_imported",
+            AuditAction::ActionApproved => "action_approved",
+            AuditAction::DeviceRegistered => "device_registered",
+            AuditAction::DeviceRemoved => "device_removed",
+            AuditAction::RetentionApplied => "retention_applied",
+            AuditAction::CorrectionRequested => "correction_requested",
+            AuditAction::CorrectionApplied => "correction_applied",
+            AuditAction::CorrectionRejected => "correction_rejected",
+            AuditAction::TransactionVoided => "transaction_voided",
+            AuditAction::TransactionTagged => "transaction_tagged",
+            AuditAction::FailedPaymentRetried => "failed_payment_retried",
+            AuditAction::FailedPaymentWrittenOff => "failed_payment_written_off",
+            AuditAction::FailedPaymentPaidLate => "failed_payment_paid_late",
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "audit_log"]
+struct NewAuditEntry<'a> {
+    organization_id: Option<OrganizationId>,
+    user_name: Option<&'a str>,
+    action: &'a str,
+    description: &'a str,
+    batch_id: Option<&'a str>,
+}
+
+/// Add an entry to the audit log.
+///
+/// `organization` and `user` are `None` for events which don't belong to one, e.g. a failed
+/// login as a user which doesn't exist.
+pub fn record(
+    connection: &PgConnection,
+    organization: Option<OrganizationId>,
+    user: Option<&str>,
+    action: AuditAction,
+    description: &str,
+) -> QueryResult<()> {
+    diesel::insert_into(audit_log::table)
+        .values(NewAuditEntry {
+            organization_id: organization,
+            user_name: user,
+            action: action.as_str(),
+            description,
+            batch_id: None,
+        })
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Add an entry to the audit log for one of the changes of a batch, e.g. of transactions which
+/// were voided together. Every entry of the batch is recorded with the same `batch_id`.
+pub fn record_batch(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    user: &str,
+    action: AuditAction,
+    batch_id: &str,
+    description: &str,
+) -> QueryResult<()> {
+    diesel::insert_into(audit_log::table)
+        .values(NewAuditEntry {
+            organization_id: Some(organization),
+            user_name: Some(user),
+            action: action.as_str(),
+            description,
+            batch_id: Some(batch_id),
+        })
+        .execute(connection)?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+//! Limits of the size of request bodies, to keep clients from filling up the memory of the server.
+//!
+//! Rocket stops reading a body when it exceeds the limit of its data type and responds with
+//! 413 Payload Too Large. The [BodyLimits] fairing sets those limits from the config.
+
+use crate::config::BodyLimitsConfig;
+use rocket::data::{ByteUnit, Limits, ToByteUnit};
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::{Build, Request, Rocket};
+
+/// The data types read by routes which take structured data
+const JSON_TYPES: &[&str] = &["json", "msgpack", "form", "string"];
+
+/// The data types read by routes which take files
+const UPLOAD_TYPES: &[&str] = &["file", "data-form", "bytes"];
+
+pub struct BodyLimits {
+    json: ByteUnit,
+    upload: ByteUnit,
+}
+
+impl BodyLimits {
+    pub fn new(config: &BodyLimitsConfig) -> Self {
+        BodyLimits {
+            json: config.json_kib.kibibytes(),
+            upload: config.upload_kib.kibibytes(),
+        }
+    }
+
+    fn limits(&self) -> Limits {
+        let json = JSON_TYPES.iter().map(|&name| (name, self.json));
+        let upload = UPLOAD_TYPES.iter().map(|&name| (name, self.upload));
+        json.chain(upload)
+            .fold(Limits::default(), |limits, (name, limit)| {
+                limits.limit(name, limit)
+            })
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for BodyLimits {
+    fn info(&self) -> Info {
+        Info {
+            name: "Body limits",
+            kind: Kind::Ignite,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        let figment = rocket.figment().clone().merge(("limits", self.limits()));
+        Ok(rocket.configure(figment))
+    }
+}
+
+/// The limit which applies to the body of `req`, judging by its content type
+pub fn limit_of(req: &Request) -> ByteUnit {
+    let is_upload = req
+        .content_type()
+        .map(|content_type| !content_type.is_json() && !content_type.is_form())
+        .unwrap_or(false);
+    let name = if is_upload { "file" } else { "json" };
+    req.limits().get(name).unwrap_or(Limits::JSON)
+}
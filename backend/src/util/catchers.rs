@@ -1,17 +1,29 @@
+use crate::util::body_limits::limit_of;
+use crate::util::rate_limit::retry_after;
 use crate::util::StatusJson;
-use rocket::http::Status;
-use rocket::{catch, catchers, Catcher, Request};
+use rocket::http::{Header, Status};
+use rocket::{catch, catchers, Catcher, Request, Responder};
 
 pub fn catchers() -> Vec<Catcher> {
-    catchers![not_found, unauthorized, bad_request,]
+    catchers![
+        not_found,
+        unauthorized,
+        forbidden,
+        bad_request,
+        payload_too_large,
+        too_many_requests,
+    ]
+}
+
+#[derive(Responder)]
+pub struct RateLimited {
+    error: StatusJson,
+    retry_after: Header<'static>,
 }
 
 #[catch(404)]
 pub fn not_found(_: &Request) -> StatusJson {
-    StatusJson {
-        status: Status::NotFound,
-        description: "Route Not Found".into(),
-    }
+    StatusJson::new(Status::NotFound, "Route Not Found")
 }
 
 #[catch(401)]
@@ -19,7 +31,28 @@ pub fn unauthorized(_: &Request) -> StatusJson {
     Status::Unauthorized.into()
 }
 
+#[catch(403)]
+pub fn forbidden(_: &Request) -> StatusJson {
+    Status::Forbidden.into()
+}
+
 #[catch(400)]
 pub fn bad_request(_: &Request) -> StatusJson {
     Status::BadRequest.into()
 }
+
+#[catch(413)]
+pub fn payload_too_large(req: &Request) -> StatusJson {
+    StatusJson::new(
+        Status::PayloadTooLarge,
+        format!("Request body too large, the limit is {}", limit_of(req)),
+    )
+}
+
+#[catch(429)]
+pub fn too_many_requests(req: &Request) -> RateLimited {
+    RateLimited {
+        error: StatusJson::new(Status::TooManyRequests, "Too many requests"),
+        retry_after: Header::new("Retry-After", retry_after(req).unwrap_or(60).to_string()),
+    }
+}
@@ -0,0 +1,72 @@
+//! Who made a request, also when it was forwarded by a reverse proxy.
+
+use crate::config::Config;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::State;
+use std::convert::Infallible;
+use std::net::IpAddr;
+
+/// Request guard for the address of the client and whether it connected over https.
+///
+/// The `X-Forwarded-For` and `X-Forwarded-Proto` headers are only honored on requests from the
+/// trusted proxies in the config.
+#[derive(Debug, Clone, Copy)]
+pub struct Client {
+    /// `None` if the address of the connection is unknown, e.g. in local tests
+    pub ip: Option<IpAddr>,
+
+    /// Whether the client connected to the proxy over https
+    pub secure: bool,
+}
+
+impl Client {
+    fn from_request(req: &Request, trusted: &[IpAddr]) -> Self {
+        let remote = req.remote().map(|addr| addr.ip());
+
+        match remote {
+            Some(proxy) if trusted.contains(&proxy) => {
+                // every proxy appends the address it got the request from, so the client is the
+                // last address which wasn't added by one of our proxies
+                let forwarded: Vec<IpAddr> = req
+                    .headers()
+                    .get("X-Forwarded-For")
+                    .flat_map(|header| header.split(','))
+                    .filter_map(|addr| addr.trim().parse().ok())
+                    .collect();
+                let ip = forwarded
+                    .iter()
+                    .rev()
+                    .find(|addr| !trusted.contains(addr))
+                    .or_else(|| forwarded.first())
+                    .copied()
+                    .unwrap_or(proxy);
+
+                let forwarded_secure = req
+                    .headers()
+                    .get_one("X-Forwarded-Proto")
+                    .map(|proto| proto.eq_ignore_ascii_case("https"));
+
+                Client {
+                    ip: Some(ip),
+                    secure: forwarded_secure.unwrap_or(false),
+                }
+            }
+            // the server itself doesn't do TLS, so direct connections are never secure
+            ip => Client { ip, secure: false },
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Client {
+    type Error = Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let trusted = match req.guard::<&State<Config>>().await {
+            Outcome::Success(config) => config.proxy.trusted.as_slice(),
+            _ => &[],
+        };
+        Outcome::Success(*req.local_cache(|| Client::from_request(req, trusted)))
+    }
+}
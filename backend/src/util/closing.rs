@@ -0,0 +1,160 @@
+//! The end of the day check, which notices a register that is still open after closing time.
+//!
+//! The webhooks are told once per shift, and the clients are told to show a banner. If
+//! `closing.auto_close_after_minutes` is set, the register is also closed by the server a while
+//! later and flagged, so that the cash is counted and the shift reconciled the next day.
+
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::routes::rest::live::LiveEvents;
+use crate::routes::rest::register::notify_store;
+use crate::util::outbox;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use log::{error, info};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::serde::json::json;
+use rocket::{Orbit, Rocket};
+use std::sync::Arc;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::register::{closing_deadline, RegisterShiftId};
+use strecklistan_api::user::UserName;
+
+/// Remind about and auto-close the registers which are still open at `now`, returns the
+/// organizations whose register changed
+pub fn check_closing(
+    connection: &PgConnection,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> QueryResult<Vec<OrganizationId>> {
+    let closing_time = match config.closing.closing_time() {
+        Some(closing_time) => closing_time,
+        None => return Ok(vec![]),
+    };
+    let auto_close_after = config
+        .closing
+        .auto_close_after_minutes
+        .map(|minutes| Duration::minutes(minutes.into()));
+
+    use crate::schema::tables::register_shifts::dsl::*;
+    connection.transaction(|| {
+        // locked like the outbox, so that several servers sharing a database don't both remind
+        let open: Vec<(
+            RegisterShiftId,
+            OrganizationId,
+            Option<UserName>,
+            DateTime<Utc>,
+            Option<DateTime<Utc>>,
+        )> = register_shifts
+            .filter(closed_at.is_null())
+            .select((id, organization_id, opened_by, opened_at, reminded_at))
+            .for_update()
+            .skip_locked()
+            .load(connection)?;
+
+        let mut changed = vec![];
+        for (shift_id, organization, opener, opened, reminded) in open {
+            let deadline = closing_deadline(opened, closing_time, config.reporting_timezone);
+            if now < deadline {
+                continue;
+            }
+            let data = json!({
+                "shift_id": shift_id,
+                "opened_by": opener,
+                "opened_at": opened,
+                "closes_at": deadline,
+            });
+
+            let auto_close = auto_close_after.map_or(false, |after| now >= deadline + after);
+            if auto_close {
+                info!("Closing the register of organization {}", organization);
+                diesel::update(register_shifts.filter(id.eq(shift_id)))
+                    .set((closed_at.eq(now), auto_closed.eq(true)))
+                    .execute(connection)?;
+                outbox::notify_webhooks(
+                    connection,
+                    config,
+                    organization,
+                    "register.auto_closed",
+                    data,
+                )?;
+                notify_store(connection, config, organization, false)?;
+                changed.push(organization);
+            } else if reminded.is_none() {
+                diesel::update(register_shifts.filter(id.eq(shift_id)))
+                    .set(reminded_at.eq(now))
+                    .execute(connection)?;
+                outbox::notify_webhooks(
+                    connection,
+                    config,
+                    organization,
+                    "register.closing_overdue",
+                    data,
+                )?;
+                changed.push(organization);
+            }
+        }
+
+        Ok(changed)
+    })
+}
+
+/// Checks the open registers against the closing time periodically, once the server has started
+pub struct ClosingWatcher;
+
+#[rocket::async_trait]
+impl Fairing for ClosingWatcher {
+    fn info(&self) -> Info {
+        Info {
+            name: "Closing watcher",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (db_pool, config, live) = match (
+            rocket.state::<DatabasePool>(),
+            rocket.state::<Config>(),
+            rocket.state::<LiveEvents>(),
+        ) {
+            (Some(db_pool), Some(config), Some(live)) => {
+                (db_pool.clone(), Arc::new(config.clone()), live.clone())
+            }
+            _ => {
+                error!("The closing watcher needs the database pool, the config and live events");
+                return;
+            }
+        };
+
+        if config.closing.closing_time().is_none() {
+            return;
+        }
+
+        let period = std::time::Duration::from_secs(config.closing.check_interval_secs);
+        rocket::tokio::spawn(async move {
+            let mut interval = rocket::tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+
+                let (db_pool, config) = (db_pool.clone(), Arc::clone(&config));
+                let result = rocket::tokio::task::spawn_blocking(move || {
+                    let connection = db_pool.get().map_err(|e| e.to_string())?;
+                    check_closing(&connection, &config, Utc::now()).map_err(|e| e.to_string())
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(changed)) => {
+                        // the clients show a banner for the overdue register
+                        for organization in changed {
+                            live.notify(organization, LiveEvent::RegisterChanged);
+                        }
+                    }
+                    Ok(Err(e)) => error!("Failed to check the closing time: {}", e),
+                    Err(e) => error!("The closing watcher panicked: {}", e),
+                }
+            }
+        });
+    }
+}
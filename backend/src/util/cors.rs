@@ -0,0 +1,64 @@
+//! Cross-origin resource sharing, for sites other than our own which call the API from a browser.
+
+use crate::config::CorsConfig;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Method, Status};
+use rocket::{Request, Response};
+use strecklistan_api::approval::APPROVAL_HEADER;
+
+/// Sets the CORS headers of responses to requests from the allowed origins.
+///
+/// Requests from other origins get no CORS headers, so browsers keep them from reading the
+/// response.
+pub struct Cors {
+    allowed_origins: Vec<String>,
+}
+
+impl Cors {
+    pub fn new(config: &CorsConfig) -> Self {
+        Cors {
+            allowed_origins: config.allowed_origins.clone(),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Cors {
+    fn info(&self) -> Info {
+        Info {
+            name: "CORS",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        // the response depends on the origin even if it isn't allowed, so caches must know
+        res.adjoin_header(Header::new("Vary", "Origin"));
+
+        let origin = match req.headers().get_one("Origin") {
+            Some(origin) if self.allowed_origins.iter().any(|allowed| allowed == origin) => origin,
+            _ => return,
+        };
+
+        res.set_header(Header::new(
+            "Access-Control-Allow-Origin",
+            origin.to_string(),
+        ));
+        res.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
+
+        // answer preflight requests, which don't have routes of their own
+        if req.method() == Method::Options {
+            res.set_header(Header::new(
+                "Access-Control-Allow-Methods",
+                "GET, POST, PUT, PATCH, DELETE",
+            ));
+            res.set_header(Header::new(
+                "Access-Control-Allow-Headers",
+                format!("Content-Type, Accept, {}", APPROVAL_HEADER),
+            ));
+            res.set_header(Header::new("Access-Control-Max-Age", "3600"));
+            res.set_status(Status::NoContent);
+            res.set_sized_body(0, std::io::Cursor::new(""));
+        }
+    }
+}
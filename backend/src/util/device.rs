@@ -0,0 +1,173 @@
+//! Devices which an admin has given a name, e.g. the register "Kassa 1".
+//!
+//! Browsers are told apart by the device cookie, which is set when someone logs in on them. An
+//! admin registers the device they are on under a name, after which the transactions and shifts
+//! made on it carry that name, and the admin page shows whether it's online.
+
+use crate::auth::Session;
+use crate::database::DatabasePool;
+use crate::util::StatusJson;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::State;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use strecklistan_api::device::DeviceId;
+
+/// Identifies the browser across logins, to notice logins from new devices and to attribute what's
+/// done on registered devices
+pub const DEVICE_COOKIE: &str = "device";
+
+/// How long a device counts as online after it was last heard from
+const ONLINE_FOR_SECS: i64 = 60;
+
+/// Request guard for the device a request comes from
+pub struct ThisDevice {
+    /// The device cookie, if the browser has one
+    pub token: Option<String>,
+
+    /// The id and name of the device, if it's registered in the organization of the session
+    pub registered: Option<(DeviceId, String)>,
+}
+
+impl ThisDevice {
+    /// The name of the device, if it's registered
+    pub fn name(&self) -> Option<String> {
+        self.registered.as_ref().map(|(_, name)| name.clone())
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ThisDevice {
+    type Error = StatusJson;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let device_token = req
+            .cookies()
+            .get_private(DEVICE_COOKIE)
+            .map(|cookie| cookie.value().to_string());
+        let session = match req.guard::<Session>().await {
+            Outcome::Success(session) => session,
+            _ => {
+                return Outcome::Success(ThisDevice {
+                    token: device_token,
+                    registered: None,
+                })
+            }
+        };
+        let token_value = match device_token.clone() {
+            Some(token_value) => token_value,
+            None => {
+                return Outcome::Success(ThisDevice {
+                    token: device_token,
+                    registered: None,
+                })
+            }
+        };
+
+        let (db_pool, activity) = match (
+            req.guard::<&State<DatabasePool>>().await,
+            req.guard::<&State<DeviceActivity>>().await,
+        ) {
+            (Outcome::Success(db_pool), Outcome::Success(activity)) => (db_pool, activity),
+            _ => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    Status::InternalServerError.into(),
+                ))
+            }
+        };
+
+        let registered = db_pool
+            .inner()
+            .get()
+            .map_err(StatusJson::from)
+            .and_then(|connection| {
+                use crate::schema::tables::devices::dsl::*;
+                devices
+                    .filter(organization_id.eq(session.organization))
+                    .filter(token.eq(&token_value))
+                    .select((id, name))
+                    .first::<(DeviceId, String)>(&connection)
+                    .optional()
+                    .map_err(StatusJson::from)
+            });
+
+        match registered {
+            Ok(registered) => {
+                if let Some((device, _)) = &registered {
+                    activity.record(*device);
+                }
+                Outcome::Success(ThisDevice {
+                    token: device_token,
+                    registered,
+                })
+            }
+            Err(e) => Outcome::Failure((e.status, e)),
+        }
+    }
+}
+
+/// Keeps track of when the registered devices were last heard from, and which of them are
+/// listening for changes, for the admin page.
+///
+/// Only the requests to this server process are seen, and nothing is known after a restart until
+/// the devices are heard from again.
+#[derive(Default, Clone)]
+pub struct DeviceActivity {
+    last_seen: Arc<Mutex<HashMap<DeviceId, DateTime<Utc>>>>,
+    listening: Arc<Mutex<HashMap<DeviceId, usize>>>,
+}
+
+/// Counts a device as online for as long as it's kept, see [DeviceActivity::listen]
+pub struct Listening {
+    activity: DeviceActivity,
+    device: DeviceId,
+}
+
+impl DeviceActivity {
+    pub fn record(&self, device: DeviceId) {
+        self.last_seen.lock().unwrap().insert(device, Utc::now());
+    }
+
+    pub fn last_seen(&self, device: DeviceId) -> Option<DateTime<Utc>> {
+        self.last_seen.lock().unwrap().get(&device).copied()
+    }
+
+    /// Count `device` as online while the returned value is kept, e.g. for as long as its event
+    /// stream is open
+    pub fn listen(&self, device: DeviceId) -> Listening {
+        *self.listening.lock().unwrap().entry(device).or_default() += 1;
+        Listening {
+            activity: self.clone(),
+            device,
+        }
+    }
+
+    /// Whether `device` is listening for changes, or was heard from recently
+    pub fn is_online(&self, device: DeviceId) -> bool {
+        let listening = self.listening.lock().unwrap().contains_key(&device);
+        let recent = self
+            .last_seen(device)
+            .map(|time| Utc::now() - time <= Duration::seconds(ONLINE_FOR_SECS))
+            .unwrap_or(false);
+        listening || recent
+    }
+}
+
+impl Drop for Listening {
+    fn drop(&mut self) {
+        let mut listening = self.activity.listening.lock().unwrap();
+        if let Some(count) = listening.get_mut(&self.device) {
+            *count -= 1;
+            if *count == 0 {
+                listening.remove(&self.device);
+            }
+        }
+        drop(listening);
+        self.activity.record(self.device);
+    }
+}
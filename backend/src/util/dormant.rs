@@ -0,0 +1,185 @@
+//! Reminders about tillgodo balances which members haven't touched for a while.
+//!
+//! A member is mailed once the balance has been left untouched for
+//! `dormant.remind_after_days`, if they have opted in to balance reminders, and again only once
+//! the balance has been touched and left again. Every reminder is recorded in the audit log, since
+//! the reminders are what the organization points to before forfeiting a balance.
+
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::util::audit::{self, AuditAction};
+use crate::util::outbox;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use log::{error, info};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use std::sync::Arc;
+use strecklistan_api::book_account::{tillgodo_balances, BookAccountId, TillgodoBalance};
+use strecklistan_api::currency::Currency;
+use strecklistan_api::member::{MailTopic, MemberId};
+use strecklistan_api::organization::OrganizationId;
+
+/// Mail the members whose balances have been untouched for long enough at `now`, returns how
+/// many were reminded
+pub fn remind_dormant(
+    connection: &PgConnection,
+    config: &Config,
+    now: DateTime<Utc>,
+) -> QueryResult<usize> {
+    let remind_after = match config.dormant.remind_after_days {
+        Some(days) if config.smtp.is_some() => Duration::days(days.into()),
+        _ => return Ok(0),
+    };
+
+    connection.transaction(|| {
+        // locked like the outbox, so that several servers sharing a database don't both remind
+        let candidates: Vec<(
+            BookAccountId,
+            MemberId,
+            OrganizationId,
+            Option<DateTime<Utc>>,
+        )> = {
+            use crate::schema::tables::book_accounts::dsl::{book_accounts, id as account_id};
+            use crate::schema::tables::members::dsl::*;
+            book_accounts
+                .inner_join(members)
+                .filter(archived_at.is_null())
+                .filter(email_verified_at.is_not_null())
+                .filter(mail_balance_reminders.eq(true))
+                .select((account_id, id, organization_id, balance_reminded_at))
+                .for_update()
+                .skip_locked()
+                .load(connection)?
+        };
+        if candidates.is_empty() {
+            return Ok(0);
+        }
+
+        let account_ids: Vec<BookAccountId> = candidates.iter().map(|c| c.0).collect();
+        let rows: Vec<(BookAccountId, BookAccountId, i64, DateTime<Utc>)> = {
+            use crate::schema::tables::transactions::dsl::*;
+            transactions
+                .filter(deleted_at.is_null())
+                .filter(
+                    debited_account
+                        .eq_any(&account_ids)
+                        .or(credited_account.eq_any(&account_ids)),
+                )
+                .select((debited_account, credited_account, amount, time))
+                .load(connection)?
+        };
+        let balances = tillgodo_balances(
+            &account_ids,
+            rows.into_iter().map(|(debited, credited, change, at)| {
+                (debited, credited, Currency::from(change), at)
+            }),
+        );
+
+        let mut reminded = 0;
+        for (account, member, organization, reminded_at) in candidates {
+            let TillgodoBalance {
+                balance,
+                last_activity,
+            } = balances[&account];
+            let last_activity = match last_activity {
+                Some(last_activity) => last_activity,
+                None => continue,
+            };
+            let due = balance > Currency::default()
+                && last_activity <= now - remind_after
+                && reminded_at.map_or(true, |reminded_at| reminded_at < last_activity);
+            if !due {
+                continue;
+            }
+
+            let since = last_activity.format("%Y-%m-%d");
+            let body = format!(
+                "You have {}:- left on your tillgodo account, which hasn't been used since {}.\n\n\
+                 Come by and spend it, or ask us how to get it paid back. Balances which are \
+                 left unused for too long may be forfeited.",
+                balance, since,
+            );
+            let sent = outbox::enqueue_member_mail(
+                connection,
+                config,
+                member,
+                MailTopic::BalanceReminders,
+                "Your tillgodo balance".to_string(),
+                body,
+            )?;
+            if !sent {
+                continue;
+            }
+
+            {
+                use crate::schema::tables::members::dsl::*;
+                diesel::update(members.filter(id.eq(member)))
+                    .set(balance_reminded_at.eq(now))
+                    .execute(connection)?;
+            }
+            audit::record(
+                connection,
+                Some(organization),
+                None,
+                AuditAction::BalanceReminderSent,
+                &format!(
+                    "Reminded member #{} about {}:- on account #{}, untouched since {}",
+                    member, balance, account, since,
+                ),
+            )?;
+            reminded += 1;
+        }
+
+        Ok(reminded)
+    })
+}
+
+/// Checks the balances for reminders to send periodically, once the server has started
+pub struct DormantReminder;
+
+#[rocket::async_trait]
+impl Fairing for DormantReminder {
+    fn info(&self) -> Info {
+        Info {
+            name: "Dormant balance reminder",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (db_pool, config) = match (rocket.state::<DatabasePool>(), rocket.state::<Config>()) {
+            (Some(db_pool), Some(config)) => (db_pool.clone(), Arc::new(config.clone())),
+            _ => {
+                error!("The dormant balance reminder needs the database pool and the config");
+                return;
+            }
+        };
+
+        if config.dormant.remind_after_days.is_none() || config.smtp.is_none() {
+            return;
+        }
+
+        let period = std::time::Duration::from_secs(config.dormant.check_interval_secs);
+        rocket::tokio::spawn(async move {
+            let mut interval = rocket::tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+
+                let (db_pool, config) = (db_pool.clone(), Arc::clone(&config));
+                let result = rocket::tokio::task::spawn_blocking(move || {
+                    let connection = db_pool.get().map_err(|e| e.to_string())?;
+                    remind_dormant(&connection, &config, Utc::now()).map_err(|e| e.to_string())
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(reminded)) => info!("Reminded {} members about their balances", reminded),
+                    Ok(Err(e)) => error!("Failed to send balance reminders: {}", e),
+                    Err(e) => error!("The dormant balance reminder panicked: {}", e),
+                }
+            }
+        });
+    }
+}
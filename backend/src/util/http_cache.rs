@@ -0,0 +1,163 @@
+//! Validators for the resources which the frontend keeps in its resource store.
+//!
+//! The [HttpCache] fairing gives the responses of those resources an `ETag` and a
+//! `Cache-Control` header, and answers requests for a version which the client already has with
+//! 304 Not Modified. That keeps the frequent refetching during an event cheap, and lets the
+//! frontend keep the responses it has validated across page loads.
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{
+    hyper::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, VARY},
+    Header, Method, Status,
+};
+use rocket::{Request, Response};
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+
+/// The resources which are cached, and for how many seconds clients may use them without asking
+/// again. Those with no max-age are revalidated every time they're used.
+const RESOURCES: &[(&str, u32)] = &[
+    ("/api/admin/announcements", 0),
+    ("/api/admin/dormant_balances", 0),
+    ("/api/admin/duplicates", 0),
+    ("/api/admin/import/batches", 0),
+    ("/api/admin/retention", 0),
+    ("/api/admin/status", 0),
+    ("/api/admin/users", 0),
+    ("/api/analytics/daily_sales", 0),
+    ("/api/analytics/item_totals", 0),
+    ("/api/analytics/modifier_totals", 0),
+    ("/api/book_accounts", 0),
+    ("/api/book_accounts/masters", 300),
+    ("/api/checkout/rules", 0),
+    ("/api/corrections", 0),
+    ("/api/goals", 0),
+    ("/api/inventory/barcodes", 0),
+    ("/api/inventory/bundles", 0),
+    ("/api/inventory/expiring", 0),
+    ("/api/inventory/items", 0),
+    ("/api/inventory/locations", 0),
+    ("/api/inventory/low_stock", 0),
+    ("/api/inventory/modifiers", 0),
+    ("/api/inventory/popular", 0),
+    ("/api/inventory/purchase_limits", 0),
+    ("/api/members", 0),
+    ("/api/organization/branding", 0),
+    ("/api/parked_carts", 0),
+    ("/api/reporting_timezone", 300),
+    ("/api/store/layout", 0),
+    ("/api/suppliers", 0),
+    ("/api/transactions", 0),
+];
+
+/// Sets the validators of the [RESOURCES], see the [module docs](self)
+pub struct HttpCache;
+
+/// The `Cache-Control` header of the resource at `path`, if it's cached
+fn cache_control(path: &str) -> Option<String> {
+    let &(_, max_age) = RESOURCES.iter().find(|&&(resource, _)| resource == path)?;
+
+    // the responses depend on who is logged in, so they must not end up in shared caches
+    Some(match max_age {
+        0 => "private, no-cache".to_string(),
+        max_age => format!("private, max-age={}", max_age),
+    })
+}
+
+/// Whether the `If-None-Match` header `header` lists `etag`
+fn matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(|tag| tag.trim())
+        .map(|tag| tag.strip_prefix("W/").unwrap_or(tag))
+        .any(|tag| tag == "*" || tag == etag)
+}
+
+#[rocket::async_trait]
+impl Fairing for HttpCache {
+    fn info(&self) -> Info {
+        Info {
+            name: "HTTP cache validators",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        if req.method() != Method::Get || res.status() != Status::Ok {
+            return;
+        }
+        let cache_control = match cache_control(req.uri().path().as_str()) {
+            Some(cache_control) => cache_control,
+            None => return,
+        };
+
+        let body = match res.body_mut().to_bytes().await {
+            Ok(body) => body,
+            Err(_) => return,
+        };
+        let etag = format!("\"{}\"", hex::encode(&Sha256::digest(&body)[..16]));
+
+        res.set_header(Header::new(ETAG.as_str(), etag.clone()));
+        res.set_header(Header::new(CACHE_CONTROL.as_str(), cache_control));
+
+        // json or msgpack depending on what was accepted, for whoever holds the session
+        res.adjoin_header(Header::new(VARY.as_str(), "Accept"));
+        res.adjoin_header(Header::new(VARY.as_str(), "Cookie"));
+
+        let not_modified = req
+            .headers()
+            .get(IF_NONE_MATCH.as_str())
+            .any(|header| matches(header, &etag));
+        if not_modified {
+            res.set_status(Status::NotModified);
+            res.set_sized_body(0, Cursor::new(Vec::new()));
+        } else {
+            res.set_sized_body(body.len(), Cursor::new(body));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testutil::{login, TestDb};
+    use rocket::http::Status;
+    use strecklistan_api::book_account::BookAccountType;
+
+    #[test]
+    fn test_http_cache() {
+        use rocket::http::Header;
+
+        let db = TestDb::new();
+        let org = db.organization("Test");
+        db.user("tester", "hunter2", org, false);
+        let client = db.client();
+        login(&client, "tester", "hunter2");
+
+        let response = client.get("/api/book_accounts").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(
+            response.headers().get_one("Cache-Control"),
+            Some("private, no-cache")
+        );
+        let etag = response.headers().get_one("ETag").unwrap().to_string();
+
+        let response = client
+            .get("/api/book_accounts")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotModified);
+        assert!(response.into_bytes().unwrap_or_default().is_empty());
+
+        db.account(org, "Kassa", BookAccountType::Assets);
+        let response = client
+            .get("/api/book_accounts")
+            .header(Header::new("If-None-Match", etag.clone()))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_ne!(response.headers().get_one("ETag"), Some(etag.as_str()));
+
+        // only the resources of the resource store are cached
+        let response = client.get("/api/kitchen").dispatch();
+        assert_eq!(response.headers().get_one("ETag"), None);
+    }
+}
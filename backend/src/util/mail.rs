@@ -0,0 +1,33 @@
+//! Outgoing mail, sent through the SMTP server in the config.
+//!
+//! Mail is queued with [crate::util::outbox::enqueue_mail] and sent by the outbox worker, so that
+//! it's only sent if the change which caused it is committed.
+
+use crate::config::SmtpConfig;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Send a mail right away, blocking until the SMTP server has accepted it
+pub fn send(smtp: &SmtpConfig, mail: Mail) -> Result<(), Box<dyn std::error::Error>> {
+    let message = Message::builder()
+        .from(smtp.from.parse()?)
+        .to(mail.to.parse()?)
+        .subject(mail.subject)
+        .body(mail.body)?;
+
+    let mut transport = SmtpTransport::relay(&smtp.host)?.port(smtp.port);
+    if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    transport.build().send(&message)?;
+    Ok(())
+}
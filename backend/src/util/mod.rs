@@ -1,9 +1,27 @@
+pub mod analytics;
+pub mod approval;
+pub mod audit;
+pub mod body_limits;
 mod catchers;
+pub mod client;
+pub mod closing;
+pub mod cors;
+pub mod device;
+pub mod dormant;
+pub mod http_cache;
+pub mod mail;
 pub mod ord;
+pub mod outbox;
+pub mod payment_timeout;
+pub mod pdf;
+pub mod qr;
+pub mod rate_limit;
+pub mod retention;
 pub mod ser;
+pub mod signing;
 pub mod static_cached_files;
 pub mod status_json;
-pub mod testing;
+pub mod swish;
 
 // Re-exporting module members for convenience
 
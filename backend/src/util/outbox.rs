@@ -0,0 +1,341 @@
+//! The outbox, where side effects of changes wait until they have been delivered.
+//!
+//! Mail and webhook calls are written to the `outbox` table in the same database transaction as
+//! the change which caused them, so they are sent if and only if the change is committed. The
+//! [OutboxWorker] delivers them in the background and retries failed deliveries, waiting twice as
+//! long every time.
+//!
+//! Messages are delivered at least once: a crash right after a delivery means it's made again.
+//! Webhook calls carry the id of the message in `X-Strecklistan-Delivery`, so receivers can tell.
+
+use crate::config::{Config, OutboxConfig};
+use crate::database::DatabasePool;
+use crate::schema::tables::outbox;
+use crate::util::mail::{self, Mail};
+use crate::util::signing;
+use chrono::{DateTime, Duration, Utc};
+use diesel::prelude::*;
+use log::{error, info, warn};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+use strecklistan_api::member::{ContactPreferences, MailTopic, MemberContact, MemberId};
+use strecklistan_api::organization::OrganizationId;
+
+/// How many messages are delivered per check of the outbox
+const BATCH_SIZE: i64 = 20;
+
+/// The wait before the second attempt to deliver a message
+const FIRST_BACKOFF_SECS: i64 = 30;
+
+/// The longest wait between two attempts to deliver a message
+const MAX_BACKOFF_SECS: i64 = 60 * 60;
+
+/// How long a webhook may take to respond
+const WEBHOOK_TIMEOUT_SECS: u64 = 10;
+
+const KIND_MAIL: &str = "mail";
+const KIND_WEBHOOK: &str = "webhook";
+
+/// The body of a webhook call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    /// What happened, e.g. `transaction.created`
+    pub event: String,
+    pub organization_id: OrganizationId,
+    pub time: DateTime<Utc>,
+    pub data: Value,
+}
+
+/// The payload of a webhook message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WebhookCall {
+    url: String,
+    event: WebhookEvent,
+}
+
+#[derive(Insertable)]
+#[table_name = "outbox"]
+struct NewMessage<'a> {
+    kind: &'a str,
+    payload: Value,
+}
+
+#[derive(Queryable)]
+struct PendingMessage {
+    id: i32,
+    kind: String,
+    payload: Value,
+    attempts: i32,
+}
+
+fn enqueue(connection: &PgConnection, kind: &str, payload: Value) -> QueryResult<()> {
+    diesel::insert_into(outbox::table)
+        .values(NewMessage { kind, payload })
+        .execute(connection)?;
+    Ok(())
+}
+
+/// Queue a mail. Does nothing if mail is disabled in the config.
+pub fn enqueue_mail(connection: &PgConnection, config: &Config, mail: Mail) -> QueryResult<()> {
+    if config.smtp.is_none() {
+        return Ok(());
+    }
+
+    let payload = serde_json::to_value(mail).expect("mail serializes to json");
+    enqueue(connection, KIND_MAIL, payload)
+}
+
+/// Queue a mail to a member about `topic`, if they have confirmed their address and opted in to
+/// the topic. Archived members aren't mailed. Returns whether the mail was queued.
+///
+/// Every mail to members must be sent through here, except for the code which confirms the
+/// address.
+pub fn enqueue_member_mail(
+    connection: &PgConnection,
+    config: &Config,
+    member: MemberId,
+    topic: MailTopic,
+    subject: String,
+    body: String,
+) -> QueryResult<bool> {
+    if config.smtp.is_none() {
+        return Ok(false);
+    }
+
+    let contact = {
+        use crate::schema::tables::members::dsl::*;
+        members
+            .filter(id.eq(member))
+            .filter(archived_at.is_null())
+            .select((
+                email,
+                email_verified_at,
+                mail_receipts,
+                mail_balance_reminders,
+                mail_event_info,
+            ))
+            .first(connection)
+            .optional()?
+            .map(
+                |(to, verified_at, receipts, balance_reminders, event_info)| MemberContact {
+                    member_id: member,
+                    email: to,
+                    email_verified_at: verified_at,
+                    preferences: ContactPreferences {
+                        receipts,
+                        balance_reminders,
+                        event_info,
+                    },
+                },
+            )
+    };
+
+    let to = match contact
+        .as_ref()
+        .and_then(|contact| contact.address_for(topic))
+    {
+        Some(to) => to.to_string(),
+        None => return Ok(false),
+    };
+    enqueue_mail(connection, config, Mail { to, subject, body })?;
+    Ok(true)
+}
+
+/// Queue a call to every webhook in the config about `event`
+pub fn notify_webhooks(
+    connection: &PgConnection,
+    config: &Config,
+    organization: OrganizationId,
+    event: &str,
+    data: Value,
+) -> QueryResult<()> {
+    let event = WebhookEvent {
+        event: event.to_string(),
+        organization_id: organization,
+        time: Utc::now(),
+        data,
+    };
+
+    for webhook in &config.webhooks {
+        let call = WebhookCall {
+            url: webhook.url.clone(),
+            event: event.clone(),
+        };
+        let payload = serde_json::to_value(call).expect("webhook call serializes to json");
+        enqueue(connection, KIND_WEBHOOK, payload)?;
+    }
+
+    Ok(())
+}
+
+/// How long to wait before the next attempt, after `attempts` failed ones
+pub fn backoff(attempts: u32) -> Duration {
+    let doublings = attempts.saturating_sub(1).min(20);
+    let secs = FIRST_BACKOFF_SECS.saturating_mul(1 << doublings);
+    Duration::seconds(secs.min(MAX_BACKOFF_SECS))
+}
+
+/// Deliver the messages which are due, and returns how many there were
+pub fn deliver_due(connection: &PgConnection, config: &Config) -> QueryResult<usize> {
+    use crate::schema::tables::outbox::dsl::*;
+
+    connection.transaction(|| {
+        // the messages stay locked until their deliveries are recorded, so that several servers
+        // sharing a database don't deliver the same message
+        let due: Vec<PendingMessage> = outbox
+            .filter(delivered_at.is_null())
+            .filter(failed_at.is_null())
+            .filter(next_attempt_at.le(Utc::now()))
+            .order_by(next_attempt_at.asc())
+            .limit(BATCH_SIZE)
+            .select((id, kind, payload, attempts))
+            .for_update()
+            .skip_locked()
+            .load(connection)?;
+
+        for message in &due {
+            let result = deliver(config, message);
+            record_attempt(connection, &config.outbox, message, result)?;
+        }
+
+        Ok(due.len())
+    })
+}
+
+fn deliver(config: &Config, message: &PendingMessage) -> Result<(), String> {
+    match message.kind.as_str() {
+        KIND_MAIL => {
+            let smtp = config.smtp.as_ref().ok_or("mail is disabled")?;
+            let mail: Mail =
+                serde_json::from_value(message.payload.clone()).map_err(|e| e.to_string())?;
+            mail::send(smtp, mail).map_err(|e| e.to_string())
+        }
+        KIND_WEBHOOK => {
+            let call: WebhookCall =
+                serde_json::from_value(message.payload.clone()).map_err(|e| e.to_string())?;
+            call_webhook(config, message.id, call)
+        }
+        other => Err(format!("unknown kind of message \"{}\"", other)),
+    }
+}
+
+fn call_webhook(config: &Config, message_id: i32, call: WebhookCall) -> Result<(), String> {
+    // the secret is looked up now rather than stored in the outbox, so it never ends up in the
+    // database
+    let webhook = config
+        .webhooks
+        .iter()
+        .find(|webhook| webhook.url == call.url)
+        .ok_or("the webhook is no longer configured")?;
+
+    let body = serde_json::to_vec(&call.event).map_err(|e| e.to_string())?;
+    let mut request = ureq::post(&webhook.url)
+        .timeout(std::time::Duration::from_secs(WEBHOOK_TIMEOUT_SECS))
+        .set("Content-Type", "application/json")
+        .set("X-Strecklistan-Event", &call.event.event)
+        .set("X-Strecklistan-Delivery", &message_id.to_string());
+
+    if let Some(secret) = &webhook.secret {
+        request = request.set("X-Strecklistan-Signature", &signing::sign(secret, &body));
+    }
+
+    request.send_bytes(&body).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn record_attempt(
+    connection: &PgConnection,
+    config: &OutboxConfig,
+    message: &PendingMessage,
+    result: Result<(), String>,
+) -> QueryResult<()> {
+    use crate::schema::tables::outbox::dsl::*;
+
+    let tries = message.attempts + 1;
+    let row = outbox.filter(id.eq(message.id));
+    let now = Utc::now();
+
+    match result {
+        Ok(()) => {
+            info!("Delivered {} message {}", message.kind, message.id);
+            diesel::update(row)
+                .set((
+                    attempts.eq(tries),
+                    delivered_at.eq(now),
+                    last_error.eq(None::<String>),
+                ))
+                .execute(connection)?;
+        }
+        Err(e) if tries >= config.max_attempts as i32 => {
+            error!(
+                "Gave up on {} message {} after {} attempts: {}",
+                message.kind, message.id, tries, e
+            );
+            diesel::update(row)
+                .set((attempts.eq(tries), failed_at.eq(now), last_error.eq(e)))
+                .execute(connection)?;
+        }
+        Err(e) => {
+            warn!(
+                "Failed to deliver {} message {}: {}",
+                message.kind, message.id, e
+            );
+            diesel::update(row)
+                .set((
+                    attempts.eq(tries),
+                    next_attempt_at.eq(now + backoff(tries as u32)),
+                    last_error.eq(e),
+                ))
+                .execute(connection)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Delivers the messages in the outbox in the background, once the server has started
+pub struct OutboxWorker;
+
+#[rocket::async_trait]
+impl Fairing for OutboxWorker {
+    fn info(&self) -> Info {
+        Info {
+            name: "Outbox worker",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (db_pool, config) = match (rocket.state::<DatabasePool>(), rocket.state::<Config>()) {
+            (Some(db_pool), Some(config)) => (db_pool.clone(), Arc::new(config.clone())),
+            _ => {
+                error!("The outbox worker needs the database pool and the config");
+                return;
+            }
+        };
+
+        let period = std::time::Duration::from_secs(config.outbox.interval_secs);
+        rocket::tokio::spawn(async move {
+            let mut interval = rocket::tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+
+                let (db_pool, config) = (db_pool.clone(), Arc::clone(&config));
+                let result = rocket::tokio::task::spawn_blocking(move || {
+                    let connection = db_pool.get().map_err(|e| e.to_string())?;
+                    deliver_due(&connection, &config).map_err(|e| e.to_string())
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => error!("Failed to check the outbox: {}", e),
+                    Err(e) => error!("The outbox worker panicked: {}", e),
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,95 @@
+//! Card payments which the bridge never reports a result for.
+//!
+//! A payment which has been pending for longer than `payments.izettle_timeout_secs` is taken off
+//! the queue of the bridge and kept as a [FailedPayment](strecklistan_api::izettle::FailedPayment),
+//! rather than blocking the payments behind it forever. If the bridge reports it as paid after
+//! all, it's booked then.
+
+use crate::config::Config;
+use crate::database::DatabasePool;
+use crate::routes::rest::izettle::failed_payment::fail_timed_out;
+use crate::routes::rest::izettle::izettle_bridge_poll::PaymentQueue;
+use crate::routes::rest::live::LiveEvents;
+use chrono::Utc;
+use log::{error, info};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use std::collections::HashSet;
+use std::sync::Arc;
+use strecklistan_api::live::LiveEvent;
+
+/// How often the pending payments are checked
+const CHECK_INTERVAL_SECS: u64 = 10;
+
+/// Fails the payments which have timed out periodically, once the server has started
+pub struct PaymentTimeout;
+
+#[rocket::async_trait]
+impl Fairing for PaymentTimeout {
+    fn info(&self) -> Info {
+        Info {
+            name: "Card payment timeout",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let state = (
+            rocket.state::<DatabasePool>(),
+            rocket.state::<Config>(),
+            rocket.state::<PaymentQueue>(),
+            rocket.state::<LiveEvents>(),
+        );
+        let (db_pool, config, queue, live) = match state {
+            (Some(db_pool), Some(config), Some(queue), Some(live)) => (
+                db_pool.clone(),
+                Arc::new(config.clone()),
+                queue.clone(),
+                live.clone(),
+            ),
+            _ => {
+                error!(
+                    "The card payment timeout needs the database pool, the config and the queues"
+                );
+                return;
+            }
+        };
+
+        if !config.payments.izettle_enabled {
+            return;
+        }
+
+        let period = std::time::Duration::from_secs(CHECK_INTERVAL_SECS);
+        rocket::tokio::spawn(async move {
+            let mut interval = rocket::tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+
+                let (db_pool, config) = (db_pool.clone(), Arc::clone(&config));
+                let result = rocket::tokio::task::spawn_blocking(move || {
+                    let connection = db_pool.get().map_err(|e| e.to_string())?;
+                    fail_timed_out(&connection, &config, Utc::now()).map_err(|e| e.description)
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(failed)) => {
+                        if !failed.is_empty() {
+                            info!("{} card payments timed out", failed.len());
+                        }
+                        let mut organizations = HashSet::new();
+                        for (organization, reference) in failed {
+                            queue.notify(reference);
+                            organizations.insert(organization);
+                        }
+                        for organization in organizations {
+                            live.notify(organization, LiveEvent::FailedPaymentsChanged);
+                        }
+                    }
+                    Ok(Err(e)) => error!("Failed to check for timed out card payments: {}", e),
+                    Err(e) => error!("The card payment timeout panicked: {}", e),
+                }
+            }
+        });
+    }
+}
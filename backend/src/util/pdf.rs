@@ -0,0 +1,130 @@
+//! A minimal PDF writer for exports which are only lines of text.
+//!
+//! The text is set in Courier, which every PDF reader has built in, so no fonts are embedded and
+//! columns can be lined up with spaces. Only characters in Latin-1 can be shown, which covers
+//! Swedish. Anything else is replaced with `?`.
+
+use chrono::{DateTime, Utc};
+use std::io::Write;
+
+/// A4, in points
+const PAGE_WIDTH: u32 = 595;
+const PAGE_HEIGHT: u32 = 842;
+
+const MARGIN: u32 = 50;
+const FONT_SIZE: u32 = 9;
+const LINE_HEIGHT: u32 = 12;
+const LINES_PER_PAGE: usize = ((PAGE_HEIGHT - 2 * MARGIN) / LINE_HEIGHT) as usize;
+
+/// How many characters fit on a line
+pub const LINE_WIDTH: usize = ((PAGE_WIDTH - 2 * MARGIN) * 10 / (FONT_SIZE * 6)) as usize;
+
+/// Lay out `lines` on as many A4 pages as needed.
+///
+/// `title` and `created` end up in the document properties.
+pub fn text_document(title: &str, created: DateTime<Utc>, lines: &[String]) -> Vec<u8> {
+    let pages: Vec<&[String]> = if lines.is_empty() {
+        vec![lines]
+    } else {
+        lines.chunks(LINES_PER_PAGE).collect()
+    };
+
+    let mut info = b"<< /Title ".to_vec();
+    info.extend(string_literal(title));
+    info.extend(
+        format!(
+            " /Producer (strecklistan) /CreationDate (D:{}Z) >>",
+            created.format("%Y%m%d%H%M%S")
+        )
+        .as_bytes(),
+    );
+
+    // objects 1-4 are the catalog, the page tree, the font and the document info, then every
+    // page is followed by its content
+    let page_ids: Vec<usize> = (0..pages.len()).map(|i| 5 + 2 * i).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec(),
+        info,
+    ];
+
+    for (page, &page_id) in pages.iter().zip(&page_ids) {
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH,
+                PAGE_HEIGHT,
+                page_id + 1
+            )
+            .into_bytes(),
+        );
+
+        let mut content = format!(
+            "BT /F1 {} Tf {} TL {} {} Td\n",
+            FONT_SIZE,
+            LINE_HEIGHT,
+            MARGIN,
+            PAGE_HEIGHT - MARGIN - FONT_SIZE
+        )
+        .into_bytes();
+        for line in page.iter() {
+            content.extend(string_literal(line));
+            content.extend(b" Tj T*\n");
+        }
+        content.extend(b"ET");
+
+        let mut stream = format!("<< /Length {} >>\nstream\n", content.len()).into_bytes();
+        stream.extend(content);
+        stream.extend(b"\nendstream");
+        objects.push(stream);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        writeln!(pdf, "{} 0 obj", i + 1).unwrap();
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    writeln!(pdf, "xref\n0 {}\n0000000000 65535 f ", objects.len() + 1).unwrap();
+    for offset in offsets {
+        writeln!(pdf, "{:010} 00000 n ", offset).unwrap();
+    }
+    writeln!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R /Info 4 0 R >>\nstartxref\n{}\n%%EOF",
+        objects.len() + 1,
+        xref
+    )
+    .unwrap();
+
+    pdf
+}
+
+/// `text` as a PDF string, encoded as Latin-1 which matches WinAnsiEncoding for letters
+fn string_literal(text: &str) -> Vec<u8> {
+    let mut literal = vec![b'('];
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => literal.extend([b'\\', c as u8].iter()),
+            ' '..='~' | '\u{a0}'..='\u{ff}' => literal.push(c as u32 as u8),
+            _ => literal.push(b'?'),
+        }
+    }
+    literal.push(b')');
+    literal
+}
@@ -0,0 +1,81 @@
+//! Printable cards with a QR code, drawn as SVG.
+//!
+//! The card is the size of a credit card, so it fits in a wallet. Units are tenths of a
+//! millimeter.
+
+use qrcode::types::QrError;
+use qrcode::{Color, EcLevel, QrCode};
+use std::fmt::Write;
+
+const CARD_WIDTH: u32 = 856;
+const CARD_HEIGHT: u32 = 540;
+
+/// The QR code, including the quiet zone around it which scanners need
+const CODE_SIZE: u32 = 440;
+
+/// The number of blank modules around the code, 4 according to the standard
+const QUIET_ZONE: usize = 4;
+
+/// A card with a QR code of `url` on the left, and `lines` of text on the right. The first line
+/// is set in bold.
+pub fn card(url: &str, lines: &[&str]) -> Result<String, QrError> {
+    let code = QrCode::with_error_correction_level(url, EcLevel::M)?;
+    let width = code.width();
+    let modules = width + 2 * QUIET_ZONE;
+
+    // one subpath per dark module, in module units
+    let mut path = String::new();
+    for (i, color) in code.to_colors().into_iter().enumerate() {
+        if color == Color::Dark {
+            let (x, y) = (i % width + QUIET_ZONE, i / width + QUIET_ZONE);
+            write!(path, "M{} {}h1v1h-1z", x, y).unwrap();
+        }
+    }
+
+    let offset = (CARD_HEIGHT - CODE_SIZE) / 2;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="85.6mm" height="54mm" viewBox="0 0 {w} {h}">"#,
+        w = CARD_WIDTH,
+        h = CARD_HEIGHT,
+    );
+    write!(
+        svg,
+        r#"<rect width="{w}" height="{h}" rx="30" fill="white" stroke="black" stroke-width="2"/>"#,
+        w = CARD_WIDTH,
+        h = CARD_HEIGHT,
+    )
+    .unwrap();
+    write!(
+        svg,
+        r#"<g transform="translate({o} {o}) scale({s})"><path d="{d}" fill="black"/></g>"#,
+        o = offset,
+        s = f64::from(CODE_SIZE) / modules as f64,
+        d = path,
+    )
+    .unwrap();
+
+    let text_x = offset + CODE_SIZE + 10;
+    for (i, line) in lines.iter().enumerate() {
+        let (size, weight) = if i == 0 { (44, "bold") } else { (26, "normal") };
+        write!(
+            svg,
+            r#"<text x="{x}" y="{y}" font-family="sans-serif" font-size="{size}" font-weight="{weight}">{text}</text>"#,
+            x = text_x,
+            y = 140 + 60 * i,
+            size = size,
+            weight = weight,
+            text = escape(line),
+        )
+        .unwrap();
+    }
+
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+/// Escape text to be put in an XML element
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,178 @@
+//! Per-client rate limiting of the routes which are easy to abuse, mostly those which don't require
+//! a login.
+//!
+//! Every limited route family has a token bucket per client IP. Routes are limited by taking a
+//! [RateLimit] request guard, which fails with 429 Too Many Requests when the bucket is empty.
+//! The 429 catcher then adds a `Retry-After` header, see [retry_after].
+
+use crate::config::{RateLimitConfig, RateLimitsConfig};
+use crate::util::client::Client;
+use crate::util::StatusJson;
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest, Request};
+use rocket::State;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often the buckets of clients which have stopped making requests are forgotten
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Limits how often every client may make requests.
+///
+/// The buckets are kept in memory, so they are reset when the server restarts.
+pub struct RateLimiter {
+    /// Tokens added to every bucket per second
+    rate: f64,
+
+    /// The max number of tokens in a bucket, i.e. how many requests can be made at once
+    burst: f64,
+
+    buckets: Mutex<Buckets>,
+}
+
+struct Buckets {
+    /// The number of tokens left and when they were counted, per client
+    clients: HashMap<IpAddr, (Instant, f64)>,
+
+    pruned_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        RateLimiter {
+            rate: f64::from(config.requests_per_minute) / 60.0,
+            burst: f64::from(config.burst),
+            buckets: Mutex::new(Buckets {
+                clients: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Count a request from `client`.
+    ///
+    /// Returns how long the client has to wait if it has made too many.
+    pub fn check(&self, client: IpAddr) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+
+        let refill = |counted: Instant, tokens: f64| {
+            let elapsed = now.duration_since(counted).as_secs_f64();
+            (tokens + elapsed * self.rate).min(self.burst)
+        };
+
+        // forget clients whose buckets are full again, so the map doesn't grow forever. Scanning
+        // every client takes a while, so it's only done now and then.
+        if now.duration_since(buckets.pruned_at) >= PRUNE_INTERVAL {
+            buckets
+                .clients
+                .retain(|_, (counted, tokens)| refill(*counted, *tokens) < self.burst);
+            buckets.pruned_at = now;
+        }
+
+        let (counted, tokens) = buckets.clients.entry(client).or_insert((now, self.burst));
+        *tokens = refill(*counted, *tokens);
+        *counted = now;
+
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - *tokens) / self.rate))
+        }
+    }
+}
+
+/// The rate limiters of every limited route family
+pub struct RateLimiters {
+    pub login: RateLimiter,
+    pub public: RateLimiter,
+    pub signup: RateLimiter,
+}
+
+impl RateLimiters {
+    pub fn new(config: &RateLimitsConfig) -> Self {
+        RateLimiters {
+            login: RateLimiter::new(&config.login),
+            public: RateLimiter::new(&config.public),
+            signup: RateLimiter::new(&config.signup),
+        }
+    }
+}
+
+/// A family of routes which share a rate limit, see [RateLimit]
+pub trait LimitedRoutes: Send + Sync + 'static {
+    fn limiter(limiters: &RateLimiters) -> &RateLimiter;
+}
+
+pub struct Login;
+pub struct Public;
+pub struct Signup;
+
+impl LimitedRoutes for Login {
+    fn limiter(limiters: &RateLimiters) -> &RateLimiter {
+        &limiters.login
+    }
+}
+
+impl LimitedRoutes for Public {
+    fn limiter(limiters: &RateLimiters) -> &RateLimiter {
+        &limiters.public
+    }
+}
+
+impl LimitedRoutes for Signup {
+    fn limiter(limiters: &RateLimiters) -> &RateLimiter {
+        &limiters.signup
+    }
+}
+
+/// Request guard which counts the request against the rate limit of `R`
+pub struct RateLimit<R>(PhantomData<R>);
+
+/// How long the client of a rate limited request has to wait, in whole seconds
+struct RetryAfter(Option<u64>);
+
+#[rocket::async_trait]
+impl<'r, R: LimitedRoutes> FromRequest<'r> for RateLimit<R> {
+    type Error = StatusJson;
+
+    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
+        let limiters = match req.guard::<&State<RateLimiters>>().await {
+            Outcome::Success(limiters) => limiters,
+            _ => {
+                return Outcome::Failure((
+                    Status::InternalServerError,
+                    Status::InternalServerError.into(),
+                ))
+            }
+        };
+
+        // requests without a known client can't be told apart, so they aren't limited
+        let client = match req.guard::<Client>().await {
+            Outcome::Success(Client { ip: Some(ip), .. }) => ip,
+            _ => return Outcome::Success(RateLimit(PhantomData)),
+        };
+
+        match R::limiter(limiters).check(client) {
+            Ok(()) => Outcome::Success(RateLimit(PhantomData)),
+            Err(wait) => {
+                let secs = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                req.local_cache(|| RetryAfter(Some(secs)));
+                Outcome::Failure((
+                    Status::TooManyRequests,
+                    StatusJson::new(Status::TooManyRequests, "Too many requests"),
+                ))
+            }
+        }
+    }
+}
+
+/// How many seconds the client has to wait, if the request was rate limited
+pub fn retry_after(req: &Request) -> Option<u64> {
+    req.local_cache(|| RetryAfter(None)).0
+}
@@ -0,0 +1,300 @@
+//! Compaction and deletion of old data, as set in `retention` in the config.
+//!
+//! The items of old transactions are compacted into daily totals per item in
+//! `transaction_line_summaries`, which the stock and the sales statistics count along with the
+//! items which are left. Only transactions which have been exported in full are compacted, so
+//! that their items can still be looked up in the export. Old audit log entries are deleted.
+//!
+//! In a dry run, which is the default, nothing is removed. What would be is logged instead, and
+//! shown on the admin page, see [plan].
+
+use crate::config::{Config, RetentionConfig};
+use crate::database::DatabasePool;
+use crate::util::audit::{self, AuditAction};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use diesel::dsl::{count_star, sql};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Text, Timestamptz};
+use log::{error, info};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Orbit, Rocket};
+use std::sync::Arc;
+use strecklistan_api::admin::RetentionReport;
+use strecklistan_api::organization::OrganizationId;
+use strecklistan_api::time::Tz;
+
+/// What would be compacted or deleted of `organization` at `now`
+pub fn plan(
+    connection: &PgConnection,
+    config: &RetentionConfig,
+    organization: OrganizationId,
+    now: DateTime<Utc>,
+) -> QueryResult<RetentionReport> {
+    let exported_at: Option<DateTime<Utc>> = {
+        use crate::schema::tables::organizations::dsl::*;
+        organizations
+            .find(organization)
+            .select(transactions_exported_at)
+            .first(connection)?
+    };
+    let compact_before = config
+        .compact_after_years
+        .zip(exported_at)
+        .map(|(years, exported_at)| years_before(now, years).min(exported_at));
+
+    let (compactable_transactions, compactable_lines) = match compact_before {
+        Some(before) => {
+            use crate::schema::tables::transaction_bundles::dsl::{
+                id as bundle_id, transaction_bundles, transaction_id,
+            };
+            use crate::schema::tables::transaction_items::dsl::{
+                bundle_id as item_bundle_id, transaction_items,
+            };
+            use crate::schema::tables::transactions::dsl::{
+                deleted_at, id, organization_id, time, transactions,
+            };
+            transactions
+                .inner_join(transaction_bundles.on(transaction_id.eq(id)))
+                .inner_join(transaction_items.on(item_bundle_id.eq(bundle_id)))
+                .filter(organization_id.eq(organization))
+                .filter(time.lt(before))
+                .filter(deleted_at.is_null())
+                .select((
+                    sql::<BigInt>("COUNT(DISTINCT transactions.id)"),
+                    count_star(),
+                ))
+                .first(connection)?
+        }
+        None => (0, 0),
+    };
+
+    let prune_audit_before = config
+        .audit_log_days
+        .map(|days| now - Duration::days(days.into()));
+    let prunable_audit_entries = match prune_audit_before {
+        Some(before) => {
+            use crate::schema::tables::audit_log::dsl::*;
+            audit_log
+                .filter(organization_id.eq(organization))
+                .filter(time.lt(before))
+                .count()
+                .get_result(connection)?
+        }
+        None => 0,
+    };
+
+    Ok(RetentionReport {
+        dry_run: config.dry_run,
+        transactions_exported_at: exported_at,
+        compact_before,
+        compactable_transactions,
+        compactable_lines,
+        prune_audit_before,
+        prunable_audit_entries,
+    })
+}
+
+/// Compact and delete what's old enough at `now`, or only log it in a dry run
+pub fn run(connection: &PgConnection, config: &Config, now: DateTime<Utc>) -> QueryResult<()> {
+    let retention = &config.retention;
+    let organizations: Vec<OrganizationId> = {
+        use crate::schema::tables::organizations::dsl::*;
+        organizations.select(id).order_by(id).load(connection)?
+    };
+
+    for organization in organizations {
+        let report = plan(connection, retention, organization, now)?;
+        if report.is_empty() {
+            continue;
+        }
+        let what = describe(&report);
+        if retention.dry_run {
+            info!(
+                "The retention jobs would {} of organization #{} (dry run)",
+                what, organization
+            );
+            continue;
+        }
+
+        connection.transaction(|| {
+            if let Some(before) = report.compact_before {
+                compact(connection, organization, before, config.reporting_timezone)?;
+            }
+            if let Some(before) = report.prune_audit_before {
+                use crate::schema::tables::audit_log::dsl::*;
+                diesel::delete(
+                    audit_log
+                        .filter(organization_id.eq(organization))
+                        .filter(time.lt(before)),
+                )
+                .execute(connection)?;
+            }
+            audit::record(
+                connection,
+                Some(organization),
+                None,
+                AuditAction::RetentionApplied,
+                &format!("Ran the retention jobs to {}", what),
+            )
+        })?;
+        info!(
+            "The retention jobs did {} of organization #{}",
+            what, organization
+        );
+    }
+
+    // e.g. failed logins of unknown users
+    if let Some(days) = retention.audit_log_days {
+        use crate::schema::tables::audit_log::dsl::*;
+        let before = now - Duration::days(days.into());
+        let entries = audit_log
+            .filter(organization_id.is_null())
+            .filter(time.lt(before));
+        if retention.dry_run {
+            let count: i64 = entries.count().get_result(connection)?;
+            if count > 0 {
+                info!(
+                    "The retention jobs would delete {} audit log entries without an organization (dry run)",
+                    count
+                );
+            }
+        } else {
+            diesel::delete(entries).execute(connection)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the items of the transactions of `organization` before `before` with daily totals per
+/// item. Deleted transactions keep their items, since they don't count anyway.
+fn compact(
+    connection: &PgConnection,
+    organization: OrganizationId,
+    before: DateTime<Utc>,
+    tz: Tz,
+) -> QueryResult<()> {
+    // the days are calendar days in the reporting timezone, like those of the analytics views
+    diesel::sql_query("SELECT set_config('TimeZone', $1, true)")
+        .bind::<Text, _>(tz.name())
+        .execute(connection)?;
+
+    diesel::sql_query(
+        "INSERT INTO transaction_line_summaries
+             (organization_id, day, item_id, sold, restocked, stock_change)
+         SELECT transactions.organization_id,
+                transactions.time::DATE,
+                item.item_id,
+                COALESCE(-SUM(bundle.change) FILTER (WHERE bundle.change < 0), 0),
+                COALESCE(SUM(bundle.change) FILTER (WHERE bundle.change > 0), 0),
+                COALESCE(SUM(bundle.change) FILTER (WHERE transactions.import_batch_id IS NULL), 0)
+         FROM transactions
+             INNER JOIN transaction_bundles AS bundle ON bundle.transaction_id = transactions.id
+             INNER JOIN transaction_items AS item ON item.bundle_id = bundle.id
+         WHERE transactions.organization_id = $1
+           AND transactions.time < $2
+           AND transactions.deleted_at IS NULL
+         GROUP BY transactions.organization_id, transactions.time::DATE, item.item_id
+         ON CONFLICT (organization_id, day, item_id) DO UPDATE
+         SET sold = transaction_line_summaries.sold + EXCLUDED.sold,
+             restocked = transaction_line_summaries.restocked + EXCLUDED.restocked,
+             stock_change = transaction_line_summaries.stock_change + EXCLUDED.stock_change",
+    )
+    .bind::<Integer, _>(organization)
+    .bind::<Timestamptz, _>(before)
+    .execute(connection)?;
+
+    // the items go with the bundles
+    diesel::sql_query(
+        "DELETE FROM transaction_bundles AS bundle
+         USING transactions
+         WHERE bundle.transaction_id = transactions.id
+           AND transactions.organization_id = $1
+           AND transactions.time < $2
+           AND transactions.deleted_at IS NULL",
+    )
+    .bind::<Integer, _>(organization)
+    .bind::<Timestamptz, _>(before)
+    .execute(connection)?;
+
+    // it's only refreshed when the transactions change
+    diesel::sql_query("REFRESH MATERIALIZED VIEW inventory_stock").execute(connection)?;
+    Ok(())
+}
+
+/// The same time `years` years before `time`, or a day later if that date doesn't exist
+fn years_before(time: DateTime<Utc>, years: u32) -> DateTime<Utc> {
+    let year = time.year() - years as i32;
+    time.with_year(year)
+        .unwrap_or_else(|| (time + Duration::days(1)).with_year(year).unwrap_or(time))
+}
+
+/// E.g. "compact the items of 12 transactions (30 rows) from before 2014-07-01"
+fn describe(report: &RetentionReport) -> String {
+    let mut parts = vec![];
+    if let (Some(before), true) = (report.compact_before, report.compactable_lines > 0) {
+        parts.push(format!(
+            "compact the items of {} transactions ({} rows) from before {}",
+            report.compactable_transactions,
+            report.compactable_lines,
+            before.format("%Y-%m-%d %H:%M"),
+        ));
+    }
+    if let (Some(before), true) = (report.prune_audit_before, report.prunable_audit_entries > 0) {
+        parts.push(format!(
+            "delete {} audit log entries from before {}",
+            report.prunable_audit_entries,
+            before.format("%Y-%m-%d %H:%M"),
+        ));
+    }
+    parts.join(" and ")
+}
+
+/// Runs the retention jobs periodically, once the server has started
+pub struct RetentionJob;
+
+#[rocket::async_trait]
+impl Fairing for RetentionJob {
+    fn info(&self) -> Info {
+        Info {
+            name: "Retention jobs",
+            kind: Kind::Liftoff,
+        }
+    }
+
+    async fn on_liftoff(&self, rocket: &Rocket<Orbit>) {
+        let (db_pool, config) = match (rocket.state::<DatabasePool>(), rocket.state::<Config>()) {
+            (Some(db_pool), Some(config)) => (db_pool.clone(), Arc::new(config.clone())),
+            _ => {
+                error!("The retention jobs need the database pool and the config");
+                return;
+            }
+        };
+
+        let retention = &config.retention;
+        if retention.compact_after_years.is_none() && retention.audit_log_days.is_none() {
+            return;
+        }
+
+        let period = std::time::Duration::from_secs(retention.check_interval_secs);
+        rocket::tokio::spawn(async move {
+            let mut interval = rocket::tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+
+                let (db_pool, config) = (db_pool.clone(), Arc::clone(&config));
+                let result = rocket::tokio::task::spawn_blocking(move || {
+                    let connection = db_pool.get().map_err(|e| e.to_string())?;
+                    run(&connection, &config, Utc::now()).map_err(|e| e.to_string())
+                })
+                .await;
+
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!("The retention jobs failed: {}", e),
+                    Err(e) => error!("The retention jobs panicked: {}", e),
+                }
+            }
+        });
+    }
+}
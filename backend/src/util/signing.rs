@@ -0,0 +1,12 @@
+//! HMAC signatures, which let the receiver of something tell that it came from us unaltered.
+
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+/// The HMAC-SHA256 of `body` with `secret`, formatted as `sha256=<hex>`
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
@@ -5,26 +5,48 @@
 use log::error;
 use rocket::fs::NamedFile;
 use rocket::http::{
-    hyper::header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH},
-    Header, Method, Status,
+    hyper::header::{ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, ETAG, IF_NONE_MATCH, VARY},
+    ContentType, Header, Method, Status,
 };
 use rocket::response::{Responder, Response};
 use rocket::route::{Handler, Outcome};
 use rocket::{Data, Request, Route};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
+/// The number of hex digits of the content hash put in versioned file names
+const HASH_LEN: usize = 16;
+
+/// The cache-control directive of versioned files, which never change
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Precompressed variants of the files, by `Content-Encoding` and file suffix, in order of
+/// preference
+const ENCODINGS: &[(&str, &str)] = &[("br", ".br"), ("gzip", ".gz")];
+
 /// Custom handler for serving static files with Cache Control
 ///
 /// It is based on the StaticFiles handler from rocket.
 /// The difference is that it generates an etag for each file based on its path, and
 /// the start time of the program. Meaning that files **must not be changed** after the
 /// program has started.
+///
+/// Every file can also be requested by a versioned name containing a hash of its contents, e.g.
+/// `styles.0123456789abcdef.css`, which is cached forever. See [StaticCachedFiles::versioned_urls].
+///
+/// If a file has a precompressed variant next to it (`styles.css.br` or `styles.css.gz`), that
+/// one is served to clients which accept it.
 #[derive(Clone)]
 pub struct StaticCachedFiles {
     root: PathBuf,
     rank: isize,
     max_age: u32,
+
+    /// The path of every file, relative to `root`, by its versioned path
+    versioned: HashMap<PathBuf, PathBuf>,
 }
 
 impl StaticCachedFiles {
@@ -50,10 +72,19 @@ impl StaticCachedFiles {
             panic!("refusing to continue due to invalid static files path");
         }
 
+        let versioned = match hash_files(path) {
+            Ok(versioned) => versioned,
+            Err(e) => {
+                error!("Failed to hash the files in '{}': {}", path.display(), e);
+                panic!("refusing to continue due to unreadable static files");
+            }
+        };
+
         StaticCachedFiles {
             root: path.into(),
             max_age: Self::DEFAULT_MAX_AGE,
             rank: Self::DEFAULT_RANK,
+            versioned,
         }
     }
 
@@ -81,6 +112,69 @@ impl StaticCachedFiles {
         self.max_age = max_age;
         self
     }
+
+    /// The url and versioned url of every file, given that the files are mounted at `route`.
+    pub fn versioned_urls(&self, route: &str) -> Vec<(String, String)> {
+        let url = |path: &Path| format!("{}/{}", route.trim_end_matches('/'), path.display());
+        self.versioned
+            .iter()
+            .map(|(versioned, path)| (url(path), url(versioned)))
+            .collect()
+    }
+}
+
+/// Hash the contents of every file in `root`, returns their paths relative to `root` by their
+/// versioned paths.
+fn hash_files(root: &Path) -> io::Result<HashMap<PathBuf, PathBuf>> {
+    use sha2::{Digest, Sha256};
+
+    let mut versioned = HashMap::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(root.join(&dir))? {
+            let entry = entry?;
+            let path = dir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+
+            let extension = path.extension().and_then(|ext| ext.to_str());
+            let is_compressed = ENCODINGS
+                .iter()
+                .any(|(_, suffix)| extension == Some(&suffix[1..]));
+            if is_compressed {
+                continue;
+            }
+
+            let hash = hex::encode(Sha256::digest(&fs::read(root.join(&path))?));
+            versioned.insert(versioned_path(&path, &hash[..HASH_LEN]), path);
+        }
+    }
+
+    Ok(versioned)
+}
+
+/// Put `hash` before the extension of `path`, e.g. `styles.css` -> `styles.<hash>.css`
+fn versioned_path(path: &Path, hash: &str) -> PathBuf {
+    let mut name = OsString::new();
+    if let Some(stem) = path.file_stem() {
+        name.push(stem);
+    }
+    name.push(".");
+    name.push(hash);
+    if let Some(extension) = path.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    path.with_file_name(name)
+}
+
+/// The path of the precompressed variant of `path` with the given suffix
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
 }
 
 impl From<StaticCachedFiles> for Vec<Route> {
@@ -112,24 +206,51 @@ struct CachedFile {
     etag: String,
     cache_control: String,
     file: Option<NamedFile>,
+
+    /// The type of the uncompressed file, if a compressed variant is served
+    content_type: Option<ContentType>,
+    content_encoding: Option<&'static str>,
 }
 
 impl CachedFile {
-    pub async fn open<P: AsRef<Path>>(
-        path: P,
+    /// Open the file at `path`, or the first precompressed variant of it in `encodings`.
+    pub async fn open(
+        path: PathBuf,
+        encodings: &[(&'static str, &str)],
         req_etag: Option<String>,
         cache_control: String,
     ) -> io::Result<CachedFile> {
-        let etag = generate_etag(&path);
+        let compressed = encodings
+            .iter()
+            .map(|&(encoding, suffix)| (encoding, with_suffix(&path, suffix)))
+            .find(|(_, compressed)| compressed.is_file());
+
+        let (file_path, content_encoding, etag) = match compressed {
+            Some((encoding, compressed)) => {
+                let etag = format!("{}-{}", generate_etag(&path), encoding);
+                (compressed, Some(encoding), etag)
+            }
+            None => {
+                let etag = generate_etag(&path);
+                (path.clone(), None, etag)
+            }
+        };
+
         let file = match req_etag {
             Some(req_etag) if req_etag == etag => None,
-            _ => Some(NamedFile::open(path).await?),
+            _ => Some(NamedFile::open(file_path).await?),
         };
 
         Ok(CachedFile {
             etag,
             cache_control,
             file,
+            content_type: content_encoding.and_then(|_| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .and_then(ContentType::from_extension)
+            }),
+            content_encoding,
         })
     }
 }
@@ -147,6 +268,13 @@ impl<'r> Responder<'r, 'static> for CachedFile {
 
         response.adjoin_header(Header::new(ETAG.as_str(), self.etag));
         response.adjoin_header(Header::new(CACHE_CONTROL.as_str(), self.cache_control));
+        response.adjoin_header(Header::new(VARY.as_str(), ACCEPT_ENCODING.as_str()));
+        if let Some(content_type) = self.content_type {
+            response.set_header(content_type);
+        }
+        if let Some(encoding) = self.content_encoding {
+            response.set_header(Header::new(CONTENT_ENCODING.as_str(), encoding));
+        }
 
         Ok(response)
     }
@@ -163,29 +291,49 @@ impl Handler for StaticCachedFiles {
             return Outcome::forward(data);
         }
 
-        let path = req
-            .segments(0..)
-            .map(|path: PathBuf| self.root.join(path))
-            .ok();
-
-        match path {
-            None => Outcome::forward(data),
-            Some(p) if p.is_dir() => Outcome::forward(data),
-            Some(p) => {
-                let req_etag = req
-                    .headers()
-                    .get_one(IF_NONE_MATCH.as_str())
-                    .map(|etag| etag.to_string());
-                let cache_control = format!("must-revalidate, max-age={}", self.max_age);
-                if let Ok(Ok(response)) = CachedFile::open(p, req_etag, cache_control)
-                    .await
-                    .map(|file| file.respond_to(req))
-                {
-                    Outcome::Success(response)
-                } else {
-                    Outcome::Forward(data)
-                }
-            }
+        let path: Option<PathBuf> = req.segments(0..).ok();
+
+        // versioned files never change, so they may be cached forever
+        let (path, cache_control) = match path {
+            None => return Outcome::forward(data),
+            Some(path) => match self.versioned.get(&path) {
+                Some(original) => (self.root.join(original), IMMUTABLE.to_string()),
+                None => (
+                    self.root.join(path),
+                    format!("must-revalidate, max-age={}", self.max_age),
+                ),
+            },
+        };
+
+        if path.is_dir() {
+            return Outcome::forward(data);
+        }
+
+        let req_etag = req
+            .headers()
+            .get_one(IF_NONE_MATCH.as_str())
+            .map(|etag| etag.to_string());
+        let encodings: Vec<_> = ENCODINGS
+            .iter()
+            .copied()
+            .filter(|(encoding, _)| accepts_encoding(req, encoding))
+            .collect();
+        if let Ok(Ok(response)) = CachedFile::open(path, &encodings, req_etag, cache_control)
+            .await
+            .map(|file| file.respond_to(req))
+        {
+            Outcome::Success(response)
+        } else {
+            Outcome::Forward(data)
         }
     }
 }
+
+/// Whether the `Accept-Encoding` header of `req` lists `encoding`
+fn accepts_encoding(req: &Request<'_>, encoding: &str) -> bool {
+    req.headers()
+        .get(ACCEPT_ENCODING.as_str())
+        .flat_map(|header| header.split(','))
+        .filter_map(|value| value.split(';').next())
+        .any(|value| value.trim().eq_ignore_ascii_case(encoding))
+}
@@ -3,8 +3,11 @@ use duplicate::duplicate;
 use log::{info, warn};
 use rocket::http::Status;
 use rocket::response::{Responder, Response};
-use rocket::serde::json::{json, Json};
-use rocket::Request; // macro
+use rocket::serde::json::{json, Json, Value};
+use rocket::Request;
+use serde::Serialize; // macro
+use strecklistan_api::error::ErrorCode;
+use strecklistan_api::validation::FieldError;
 
 /// An error message which can be serialized as JSON.
 ///
@@ -12,27 +15,47 @@ use rocket::Request; // macro
 /// ```json
 /// {
 ///   "status": 404,
+///   "code": "NotFound",
 ///   "description": "Not Found"
 /// }
 /// ```
+///
+/// Errors have a `code` which clients can branch on, see [ErrorCode]. Errors which the client is
+/// expected to act on may also have a `details` field, see [StatusJson::with_details].
 #[derive(Debug, Clone)]
 pub struct StatusJson {
     pub status: Status,
+    pub code: Option<ErrorCode>,
     pub description: String,
+    pub details: Option<Value>,
 }
 
 impl StatusJson {
+    /// The code is derived from the status, use [StatusJson::with_code] for a more specific one
     pub fn new<S: ToString>(status: Status, description: S) -> Self {
         StatusJson {
             status,
+            code: default_code(status),
             description: description.to_string(),
+            details: None,
         }
     }
 
+    pub fn with_code(mut self, code: ErrorCode) -> Self {
+        self.code = Some(code);
+        self
+    }
+
     pub fn describe<S: ToString>(mut self, description: S) -> Self {
         self.description = description.to_string();
         self
     }
+
+    /// Attach structured information about the error, serialized as the `details` field
+    pub fn with_details<T: Serialize>(mut self, details: &T) -> Self {
+        self.details = serde_json::to_value(details).ok();
+        self
+    }
 }
 
 impl<'r> Responder<'r, 'static> for StatusJson {
@@ -47,11 +70,18 @@ impl<'r> Responder<'r, 'static> for StatusJson {
             info!("Responding with status {}", self.status);
         }
 
-        let mut response = Json(json!({
+        let mut body = json!({
             "status": self.status.code,
             "description": self.description,
-        }))
-        .respond_to(req)?;
+        });
+        if let Some(code) = self.code {
+            body["code"] = json!(code);
+        }
+        if let Some(details) = self.details {
+            body["details"] = details;
+        }
+
+        let mut response = Json(body).respond_to(req)?;
 
         response.set_status(self.status);
 
@@ -63,36 +93,48 @@ impl<'r> Responder<'r, 'static> for StatusJson {
   status_code                     T;
   [ Status::BadRequest ]          [ r2d2::Error ];
   [ Status::InternalServerError ] [ diesel::ConnectionError ];
+  [ Status::InternalServerError ] [ csv::Error ];
 )]
 impl From<T> for StatusJson {
     fn from(e: T) -> StatusJson {
-        StatusJson {
-            status: status_code,
-            description: e.to_string(),
-        }
+        StatusJson::new(status_code, e)
     }
 }
 
 impl From<Status> for StatusJson {
     fn from(status: Status) -> StatusJson {
-        StatusJson {
-            description: status.reason().unwrap_or("").to_string(),
-            status,
-        }
+        StatusJson::new(status, status.reason().unwrap_or(""))
     }
 }
 
 impl From<DieselError> for StatusJson {
     fn from(e: DieselError) -> StatusJson {
         match e {
-            DieselError::NotFound => StatusJson {
-                status: Status::NotFound,
-                description: "Not Found in Database".into(),
-            },
-            err => StatusJson {
-                status: Status::InternalServerError,
-                description: err.to_string(),
-            },
+            DieselError::NotFound => StatusJson::new(Status::NotFound, "Not Found in Database"),
+            err => StatusJson::new(Status::InternalServerError, err),
         }
     }
 }
+
+/// Rejected fields of a request, see [Validate](strecklistan_api::validation::Validate)
+impl From<Vec<FieldError>> for StatusJson {
+    fn from(errors: Vec<FieldError>) -> StatusJson {
+        StatusJson::new(Status::UnprocessableEntity, "Invalid fields").with_details(&errors)
+    }
+}
+
+/// The most fitting code of an error with `status`, successful responses have none
+fn default_code(status: Status) -> Option<ErrorCode> {
+    let code = match status.code {
+        0..=399 => return None,
+        401 => ErrorCode::Unauthorized,
+        403 => ErrorCode::Forbidden,
+        404 => ErrorCode::NotFound,
+        413 => ErrorCode::PayloadTooLarge,
+        422 => ErrorCode::InvalidFields,
+        429 => ErrorCode::RateLimited,
+        400..=499 => ErrorCode::BadRequest,
+        _ => ErrorCode::Internal,
+    };
+    Some(code)
+}
@@ -0,0 +1,137 @@
+//! Payment requests through the Swish Commerce API, for the deposits members make themselves.
+//!
+//! Swish knows us by the client certificate it issued. It reports the result of a payment to the
+//! callback url, but anyone could call that, so the payment is always fetched from Swish before
+//! the result is trusted.
+
+use crate::config::SwishConfig;
+use native_tls::{Identity, TlsConnector};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+use strecklistan_api::swish::{SwishPaymentId, SwishStatus};
+
+const TIMEOUT_SECS: u64 = 10;
+
+/// Payment requests are made in this currency
+const CURRENCY: &str = "SEK";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentRequest<'a> {
+    payee_payment_reference: &'a str,
+    callback_url: String,
+    payee_alias: &'a str,
+    amount: String,
+    currency: &'static str,
+    message: &'a str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentRequestStatus {
+    status: String,
+    #[serde(default)]
+    error_message: Option<String>,
+}
+
+/// How a payment request went, according to Swish
+pub struct Outcome {
+    pub status: SwishStatus,
+
+    /// Why it wasn't paid, if Swish said
+    pub error: Option<String>,
+}
+
+/// A new instruction UUID, which Swish wants as 32 uppercase hex digits
+pub fn new_payment_id() -> SwishPaymentId {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    hex::encode_upper(bytes)
+}
+
+/// Ask Swish for a payment of `amount` öre, which the payer approves in the Swish app. `message`
+/// is shown to the payer, and may be at most 50 characters.
+///
+/// Returns the token which opens the payment in the Swish app on the phone it was made from.
+pub fn create(
+    config: &SwishConfig,
+    id: &str,
+    amount: i64,
+    message: &str,
+) -> Result<Option<String>, String> {
+    let request = PaymentRequest {
+        payee_payment_reference: id,
+        callback_url: format!("{}/swish/callback/{}", config.callback_url, id),
+        payee_alias: &config.payee,
+        amount: format!("{}.{:02}", amount / 100, amount % 100),
+        currency: CURRENCY,
+        message,
+    };
+    let body = serde_json::to_value(&request).map_err(|e| e.to_string())?;
+
+    let url = format!("{}/v2/paymentrequests/{}", config.api_url, id);
+    let response = agent(config)?
+        .put(&url)
+        .send_json(body)
+        .map_err(describe_error)?;
+
+    Ok(response.header("PaymentRequestToken").map(str::to_string))
+}
+
+/// Fetch the status of the payment request `id`
+pub fn fetch(config: &SwishConfig, id: &str) -> Result<Outcome, String> {
+    let url = format!("{}/v1/paymentrequests/{}", config.api_url, id);
+    let response: PaymentRequestStatus = agent(config)?
+        .get(&url)
+        .call()
+        .map_err(describe_error)?
+        .into_json()
+        .map_err(|e| e.to_string())?;
+
+    let status = match response.status.as_str() {
+        "CREATED" => SwishStatus::Created,
+        "PAID" => SwishStatus::Paid,
+        "DECLINED" => SwishStatus::Declined,
+        "ERROR" => SwishStatus::Error,
+        "CANCELLED" => SwishStatus::Cancelled,
+        other => return Err(format!("unknown payment status \"{}\"", other)),
+    };
+
+    Ok(Outcome {
+        status,
+        error: response.error_message,
+    })
+}
+
+fn agent(config: &SwishConfig) -> Result<ureq::Agent, String> {
+    let mut builder = ureq::AgentBuilder::new().timeout(Duration::from_secs(TIMEOUT_SECS));
+
+    if let Some(path) = &config.certificate {
+        let pkcs12 =
+            fs::read(path).map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let identity = Identity::from_pkcs12(&pkcs12, &config.certificate_password)
+            .map_err(|e| format!("invalid certificate {}: {}", path.display(), e))?;
+        let connector = TlsConnector::builder()
+            .identity(identity)
+            .build()
+            .map_err(|e| e.to_string())?;
+        builder = builder.tls_connector(Arc::new(connector));
+    }
+
+    Ok(builder.build())
+}
+
+/// Swish explains rejected requests with a list of error codes in the body
+fn describe_error(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(status, response) => format!(
+            "Swish answered {}: {}",
+            status,
+            response.into_string().unwrap_or_default()
+        ),
+        e => e.to_string(),
+    }
+}
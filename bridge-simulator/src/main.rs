@@ -0,0 +1,421 @@
+//! Stands in for the iZettle bridge, so that card payments can be made without a card reader.
+//!
+//! The simulator talks to the bridge endpoints of the backend like the real bridge does, and
+//! answers every pending payment with the configured outcome. It either polls for payments, or
+//! waits for them on the event stream of the backend.
+//!
+//! Like the real bridge, it survives the server going away: requests which fail are retried with
+//! a growing, jittered delay. The result of a payment is saved to `--state-file` before it is
+//! reported, so a result which couldn't be reported, e.g. since the bridge crashed after the card
+//! was approved, is reported when the bridge starts again.
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+use structopt::StructOpt;
+
+/// The delay before the first retry of a failed request, in milliseconds
+const RETRY_BASE_DELAY_MS: u64 = 500;
+
+/// The longest delay between retries, in milliseconds
+const RETRY_MAX_DELAY_MS: u64 = 30_000;
+
+/// How long a request other than the event stream may take, in milliseconds
+const REQUEST_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(StructOpt)]
+#[structopt(name = "bridge-simulator")]
+struct Opt {
+    /// The url of the strecklistan server
+    #[structopt(
+        long,
+        env = "STRECKLISTAN_SERVER",
+        default_value = "http://localhost:8000"
+    )]
+    server: String,
+
+    /// Sent as a bearer token, for servers with `payments.bridge_token` set
+    #[structopt(long, env = "IZETTLE_BRIDGE_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+
+    /// How to find out about payments: poll or sse
+    #[structopt(long, env = "BRIDGE_MODE", default_value = "poll")]
+    mode: Mode,
+
+    /// How to answer payments: approve, decline, cancel or timeout.
+    ///
+    /// If given more than once, the outcomes are used in turn.
+    #[structopt(long, default_value = "approve")]
+    outcome: Vec<Outcome>,
+
+    /// The reason given when declining a payment
+    #[structopt(long, default_value = "Declined by the bridge simulator")]
+    decline_reason: String,
+
+    /// How long the customer takes to pay, in milliseconds
+    #[structopt(long, default_value = "1000")]
+    pay_delay: u64,
+
+    /// How long to wait for the card reader, in milliseconds.
+    ///
+    /// Payments which take longer, and those with the timeout outcome, are reported as failed
+    /// after this long. Without it, they are never answered.
+    #[structopt(long, env = "BRIDGE_READER_TIMEOUT")]
+    reader_timeout: Option<u64>,
+
+    /// Where the result of a payment is kept until it has been reported
+    #[structopt(long, env = "BRIDGE_STATE_FILE", default_value = "bridge-state.json")]
+    state_file: PathBuf,
+
+    /// The tip to give on payments which allow one, in öre
+    #[structopt(long)]
+    tip: Option<i64>,
+
+    /// How long to wait between polls, in milliseconds
+    #[structopt(long, default_value = "500")]
+    poll_interval: u64,
+
+    /// Exit after answering this many payments
+    #[structopt(long)]
+    count: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Mode {
+    /// Ask the server for a pending payment every `--poll-interval`
+    Poll,
+
+    /// Keep a stream of server-sent events open, and only ask when it says there's a payment
+    Sse,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "poll" => Ok(Mode::Poll),
+            "sse" => Ok(Mode::Sse),
+            _ => Err(format!("unknown mode \"{}\", expected poll or sse", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Outcome {
+    Approve,
+    Decline,
+    Cancel,
+
+    /// Never answer the payment, like a reader which lost its connection.
+    ///
+    /// The bridge answers payments in order, so later payments wait until the simulator is
+    /// restarted, unless `--reader-timeout` is given.
+    Timeout,
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "approve" => Ok(Outcome::Approve),
+            "decline" => Ok(Outcome::Decline),
+            "cancel" => Ok(Outcome::Cancel),
+            "timeout" => Ok(Outcome::Timeout),
+            _ => Err(format!(
+                "unknown outcome \"{}\", expected approve, decline, cancel or timeout",
+                s
+            )),
+        }
+    }
+}
+
+/// The response of `GET /api/izettle/bridge/poll`
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum BridgePollResult {
+    PendingPayment {
+        id: i32,
+        amount: i64,
+        #[serde(default)]
+        tip_allowed: bool,
+    },
+    NoPendingTransaction,
+}
+
+/// The body of `POST /api/izettle/bridge/payment_response/<reference>`
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum PaymentResponse {
+    TransactionPaid {
+        tip: Option<i64>,
+        amount: Option<i64>,
+    },
+    TransactionFailed {
+        reason: String,
+    },
+    TransactionCancelled,
+}
+
+/// A result which is saved in the state file until it has been reported
+#[derive(Serialize, Deserialize)]
+struct SavedResult {
+    id: i32,
+    response: PaymentResponse,
+}
+
+struct Bridge<'a> {
+    opt: &'a Opt,
+    client: Client,
+    outcomes: Box<dyn Iterator<Item = Outcome> + 'a>,
+
+    /// Payments which timed out are still pending, so they must not be answered again
+    ignored: HashSet<i32>,
+    answered: usize,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+
+    if let Err(e) = run(&opt) {
+        eprintln!("error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(opt: &Opt) -> Result<(), Box<dyn Error>> {
+    // no timeout by default, since the event stream is open for as long as the bridge runs
+    let client = Client::builder().timeout(None).build()?;
+    let mut bridge = Bridge {
+        opt,
+        client,
+        outcomes: Box::new(opt.outcome.iter().copied().cycle()),
+        ignored: HashSet::new(),
+        answered: 0,
+    };
+
+    println!("Waiting for payments from {} ({:?})", opt.server, opt.mode);
+
+    let mut failures = 0;
+    while !bridge.is_done() {
+        match bridge.step() {
+            Ok(()) => failures = 0,
+            Err(e) => {
+                failures += 1;
+                let delay = retry_delay_ms(failures);
+                eprintln!("{}, retrying in {} ms", e, delay);
+                sleep(Duration::from_millis(delay));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl Bridge<'_> {
+    fn is_done(&self) -> bool {
+        self.opt
+            .count
+            .map(|count| self.answered >= count)
+            .unwrap_or(false)
+    }
+
+    /// Report a saved result, then wait for a payment and answer it
+    fn step(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(saved) = load_result(&self.opt.state_file)? {
+            println!("Reporting the saved result of payment {}", saved.id);
+            self.report(&saved)?;
+        }
+
+        match self.opt.mode {
+            Mode::Poll => {
+                if !self.poll()? {
+                    sleep(Duration::from_millis(self.opt.poll_interval));
+                }
+                Ok(())
+            }
+            Mode::Sse => self.listen(),
+        }
+    }
+
+    /// Read the event stream until it ends, answering payments as they are announced
+    fn listen(&mut self) -> Result<(), Box<dyn Error>> {
+        let response = self
+            .request(
+                self.client
+                    .get(format!("{}/api/izettle/bridge/events", self.opt.server)),
+            )
+            .header("Accept", "text/event-stream")
+            .send()?
+            .error_for_status()?;
+
+        let mut event = None;
+        for line in BufReader::new(response).lines() {
+            let line = line?;
+            if let Some(name) = line.strip_prefix("event:") {
+                event = Some(name.trim().to_string());
+            } else if line.is_empty() {
+                // an event is ended by a blank line, heartbeats have no name
+                if event.take().as_deref() == Some("pending") {
+                    self.poll()?;
+                    if self.is_done() {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        Err("the event stream was closed".into())
+    }
+
+    /// Answer the oldest pending payment, if any. Returns whether there was one.
+    fn poll(&mut self) -> Result<bool, Box<dyn Error>> {
+        let poll: BridgePollResult = self
+            .request(
+                self.client
+                    .get(format!("{}/api/izettle/bridge/poll", self.opt.server)),
+            )
+            .header("Accept", "application/json")
+            .timeout(Duration::from_millis(REQUEST_TIMEOUT_MS))
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        let (id, amount, tip_allowed) = match poll {
+            BridgePollResult::PendingPayment {
+                id,
+                amount,
+                tip_allowed,
+            } if !self.ignored.contains(&id) => (id, amount, tip_allowed),
+            _ => return Ok(false),
+        };
+
+        let outcome = self.outcomes.next().expect("there is always an outcome");
+        println!(
+            "Payment {} of {}.{:02} kr: {:?}",
+            id,
+            amount / 100,
+            amount % 100,
+            outcome
+        );
+
+        let timed_out = matches!(outcome, Outcome::Timeout)
+            || self
+                .opt
+                .reader_timeout
+                .map(|timeout| self.opt.pay_delay > timeout)
+                .unwrap_or(false);
+        let response = if timed_out {
+            match self.opt.reader_timeout {
+                Some(timeout) => {
+                    sleep(Duration::from_millis(timeout));
+                    PaymentResponse::TransactionFailed {
+                        reason: "The card reader didn't answer in time".to_string(),
+                    }
+                }
+                None => {
+                    self.ignored.insert(id);
+                    self.answered += 1;
+                    return Ok(true);
+                }
+            }
+        } else {
+            sleep(Duration::from_millis(self.opt.pay_delay));
+            match outcome {
+                Outcome::Approve | Outcome::Timeout => PaymentResponse::TransactionPaid {
+                    tip: self.opt.tip.filter(|_| tip_allowed),
+                    amount: Some(amount),
+                },
+                Outcome::Decline => PaymentResponse::TransactionFailed {
+                    reason: self.opt.decline_reason.clone(),
+                },
+                Outcome::Cancel => PaymentResponse::TransactionCancelled,
+            }
+        };
+
+        // the card may have been charged, so the result must not be lost if reporting it fails
+        let saved = SavedResult { id, response };
+        save_result(&self.opt.state_file, &saved)?;
+        self.report(&saved)?;
+        self.answered += 1;
+
+        Ok(true)
+    }
+
+    /// Send a result to the server, and forget it once the server has it
+    fn report(&self, saved: &SavedResult) -> Result<(), Box<dyn Error>> {
+        let response = self
+            .request(self.client.post(format!(
+                "{}/api/izettle/bridge/payment_response/{}",
+                self.opt.server, saved.id
+            )))
+            .timeout(Duration::from_millis(REQUEST_TIMEOUT_MS))
+            .json(&saved.response)
+            .send()?;
+
+        let status = response.status();
+        if status.is_client_error() && status != StatusCode::UNAUTHORIZED {
+            // retrying won't help, e.g. since the payment was cancelled by the cashier meanwhile
+            eprintln!(
+                "The server refused the result of payment {}: {}",
+                saved.id,
+                response.text().unwrap_or_default()
+            );
+        } else {
+            response.error_for_status()?;
+        }
+
+        fs::remove_file(&self.opt.state_file)?;
+        Ok(())
+    }
+
+    fn request(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.opt.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+}
+
+/// The result in `path` which hasn't been reported yet, if any
+fn load_result(path: &Path) -> Result<Option<SavedResult>, Box<dyn Error>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Write `result` to `path`, through a temporary file so that a crash can't leave half of it
+fn save_result(path: &Path, result: &SavedResult) -> Result<(), Box<dyn Error>> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, serde_json::to_vec(result)?)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+/// How long to wait before retrying after `failures` failures in a row.
+///
+/// The delay doubles with every failure, and a random part of up to half of it is left out so
+/// that bridges which lost the server at the same time don't all come back at once.
+fn retry_delay_ms(failures: u32) -> u64 {
+    let doublings = failures.saturating_sub(1).min(16);
+    let delay = RETRY_BASE_DELAY_MS
+        .saturating_mul(1 << doublings)
+        .min(RETRY_MAX_DELAY_MS);
+
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(failures);
+    delay - hasher.finish() % (delay / 2 + 1)
+}
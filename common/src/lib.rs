@@ -2,6 +2,7 @@
 extern crate lazy_static;
 
 pub mod models;
+pub mod time;
 
 pub use models::*;
 
@@ -0,0 +1,107 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// Everything shown on the admin page, except for the list of users
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct AdminStatus {
+    pub features: FeatureFlags,
+    pub bridge: BridgeStatus,
+    pub webhooks: Vec<WebhookStatus>,
+    pub backup: BackupStatus,
+
+    /// Transactions before this date can't be voided or edited, see
+    /// [is_locked](crate::organization::is_locked)
+    pub lock_date: Option<NaiveDate>,
+}
+
+/// Optional parts of the system, as set in the server config
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct FeatureFlags {
+    pub izettle_enabled: bool,
+    pub smtp_enabled: bool,
+    pub webhooks_enabled: bool,
+}
+
+/// The state of the iZettle bridge, which handles card payments
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct BridgeStatus {
+    /// When the bridge last asked for a pending payment, since the server started
+    pub last_poll: Option<DateTime<Utc>>,
+
+    /// Payments waiting to be picked up by the bridge
+    pub pending_payments: i64,
+    pub oldest_pending: Option<DateTime<Utc>>,
+}
+
+/// A configured webhook. The secret is never sent to the client.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct WebhookStatus {
+    pub url: String,
+    pub signed: bool,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct BackupStatus {
+    /// Whether a backup directory is configured
+    pub configured: bool,
+
+    /// The most recent file in the backup directory
+    pub latest: Option<BackupFile>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct BackupFile {
+    pub name: String,
+    pub modified: DateTime<Utc>,
+    pub size_bytes: u64,
+}
+
+/// What the retention jobs would compact or delete of the organization on their next run, see
+/// `retention` in the server config
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct RetentionReport {
+    /// Whether the jobs only log what they would do
+    pub dry_run: bool,
+
+    /// When the transactions were last exported in full
+    pub transactions_exported_at: Option<DateTime<Utc>>,
+
+    /// The items of the transactions before this are compacted into daily totals. The configured
+    /// age, or when the transactions were last exported if that's earlier. `None` if nothing is
+    /// compacted, or the transactions have never been exported.
+    pub compact_before: Option<DateTime<Utc>>,
+
+    /// The transactions before `compact_before` which still have items
+    pub compactable_transactions: i64,
+
+    /// The item rows of those transactions
+    pub compactable_lines: i64,
+
+    /// Audit log entries before this are deleted, `None` if they are kept
+    pub prune_audit_before: Option<DateTime<Utc>>,
+
+    pub prunable_audit_entries: i64,
+}
+
+impl RetentionReport {
+    /// Whether the next run has anything to do
+    pub fn is_empty(&self) -> bool {
+        self.compactable_lines == 0 && self.prunable_audit_entries == 0
+    }
+}
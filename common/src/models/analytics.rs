@@ -0,0 +1,493 @@
+use crate::currency::Currency;
+use crate::models::book_account::{BookAccount, BookAccountId, BookAccountType, MasterAccounts};
+use crate::models::inventory::{InventoryItemId, ItemModifierId};
+use crate::models::transaction::Transaction;
+use crate::models::user::UserName;
+use crate::time::{local_date, Tz};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derives::Queryable;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// How many days [ItemSparklines] cover, including today
+pub const SPARKLINE_DAYS: usize = 30;
+
+/// Sales by the same cashier which are further apart than this belong to different shifts
+pub const SHIFT_GAP_MINUTES: i64 = 90;
+
+/// A shift is counted as at least this long, so that a single sale isn't a record pace
+pub const SHIFT_MIN_MINUTES: i64 = 15;
+
+/// How many of an item a cashier sold during one day
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DailySales {
+    /// The calendar day in the reporting timezone
+    pub day: NaiveDate,
+    pub created_by: Option<UserName>,
+    pub item_id: InventoryItemId,
+    pub sold: i64,
+}
+
+/// How many of an item have been sold and restocked, ever
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ItemTotals {
+    pub item_id: InventoryItemId,
+    pub sold: i64,
+    pub restocked: i64,
+    pub last_sold: Option<DateTime<Utc>>,
+}
+
+/// How many of an item have been sold with a modifier, ever
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ModifierTotals {
+    pub item_id: InventoryItemId,
+    pub modifier_id: ItemModifierId,
+    pub sold: i64,
+}
+
+/// How many of every item have been sold per day lately, to be drawn as tiny charts
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ItemSparklines {
+    /// The day of the first count, in the reporting timezone
+    pub first_day: NaiveDate,
+
+    /// [SPARKLINE_DAYS] counts for every item which has been sold since `first_day`, oldest first
+    pub sold: HashMap<InventoryItemId, Vec<i64>>,
+}
+
+/// The sales a cashier made during one shift, from their first sale to their last.
+///
+/// There is no clocking in, so the shifts are guessed from the sales, see [SHIFT_GAP_MINUTES].
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CashierShift {
+    pub cashier: UserName,
+    pub first_sale: DateTime<Utc>,
+    pub last_sale: DateTime<Utc>,
+    pub transaction_count: u32,
+    pub revenue: Currency,
+}
+
+impl CashierShift {
+    /// Split the sales of every cashier into shifts, the latest shift first.
+    ///
+    /// Sales are the transactions which credit `sales_account`, those of unknown cashiers are
+    /// skipped.
+    pub fn group(transactions: &[Transaction], sales_account: BookAccountId) -> Vec<Self> {
+        let mut sales: BTreeMap<&UserName, Vec<&Transaction>> = BTreeMap::new();
+        for tr in transactions
+            .iter()
+            .filter(|tr| tr.credited_account == sales_account)
+        {
+            if let Some(cashier) = &tr.created_by {
+                sales.entry(cashier).or_default().push(tr);
+            }
+        }
+
+        let gap = Duration::minutes(SHIFT_GAP_MINUTES);
+        let mut shifts = vec![];
+        for (cashier, mut sales) in sales {
+            sales.sort_by_key(|tr| tr.time);
+
+            let mut shift: Option<CashierShift> = None;
+            for tr in sales {
+                if let Some(current) = shift
+                    .as_mut()
+                    .filter(|current| tr.time - current.last_sale <= gap)
+                {
+                    current.last_sale = tr.time;
+                    current.transaction_count += 1;
+                    current.revenue += tr.amount;
+                } else {
+                    shifts.extend(shift.replace(CashierShift {
+                        cashier: cashier.clone(),
+                        first_sale: tr.time,
+                        last_sale: tr.time,
+                        transaction_count: 1,
+                        revenue: tr.amount,
+                    }));
+                }
+            }
+            shifts.extend(shift);
+        }
+
+        shifts.sort_by_key(|shift| Reverse(shift.first_sale));
+        shifts
+    }
+
+    /// The length of the shift, at least [SHIFT_MIN_MINUTES]
+    pub fn duration(&self) -> Duration {
+        (self.last_sale - self.first_sale).max(Duration::minutes(SHIFT_MIN_MINUTES))
+    }
+
+    pub fn transactions_per_hour(&self) -> f64 {
+        self.transaction_count as f64 / (self.duration().num_seconds() as f64 / 3600.0)
+    }
+
+    /// The average amount of a sale
+    pub fn average_basket(&self) -> Currency {
+        Currency::from(i64::from(self.revenue) / i64::from(self.transaction_count.max(1)))
+    }
+}
+
+/// How a sale was paid, judging by the account which was debited
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PaymentMethod {
+    Cash,
+
+    /// Paid to the bank account, i.e. by card through iZettle
+    Card,
+
+    /// Paid from the tillgodo account of a member
+    Tillgodo,
+
+    /// Any other account
+    Other,
+}
+
+impl PaymentMethod {
+    pub const ALL: [PaymentMethod; 4] = [
+        PaymentMethod::Cash,
+        PaymentMethod::Card,
+        PaymentMethod::Tillgodo,
+        PaymentMethod::Other,
+    ];
+
+    /// How a sale which debited `account` was paid
+    pub fn of(
+        account: BookAccountId,
+        masters: &MasterAccounts,
+        accounts: &HashMap<BookAccountId, BookAccount>,
+    ) -> Self {
+        let account_type = accounts.get(&account).map(|acc| acc.account_type);
+        if account == masters.cash_account_id {
+            PaymentMethod::Cash
+        } else if account == masters.bank_account_id {
+            PaymentMethod::Card
+        } else if account_type == Some(BookAccountType::Liabilities) {
+            PaymentMethod::Tillgodo
+        } else {
+            PaymentMethod::Other
+        }
+    }
+}
+
+/// The sales of one day which were paid the same way
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PaymentMethodSales {
+    /// The calendar day in the reporting timezone
+    pub day: NaiveDate,
+    pub method: PaymentMethod,
+    pub count: u32,
+    pub revenue: Currency,
+}
+
+impl PaymentMethodSales {
+    /// Sum up the sales, the transactions which credit the sales account, per day in `tz` and
+    /// payment method
+    pub fn group(
+        transactions: &[Transaction],
+        masters: &MasterAccounts,
+        accounts: &HashMap<BookAccountId, BookAccount>,
+        tz: Tz,
+    ) -> Vec<Self> {
+        let mut sales: BTreeMap<(NaiveDate, PaymentMethod), (u32, Currency)> = BTreeMap::new();
+        for tr in transactions
+            .iter()
+            .filter(|tr| tr.credited_account == masters.sales_account_id)
+        {
+            let method = PaymentMethod::of(tr.debited_account, masters, accounts);
+            let (count, revenue) = sales.entry((local_date(tr.time, tz), method)).or_default();
+            *count += 1;
+            *revenue += tr.amount;
+        }
+
+        sales
+            .into_iter()
+            .map(|((day, method), (count, revenue))| PaymentMethodSales {
+                day,
+                method,
+                count,
+                revenue,
+            })
+            .collect()
+    }
+}
+
+/// What the items of one category were sold for during one day
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CategorySales {
+    /// The calendar day in the reporting timezone
+    pub day: NaiveDate,
+
+    /// `None` for items without a category
+    pub category: Option<String>,
+    pub revenue: Currency,
+}
+
+impl CategorySales {
+    /// Split the sales, the transactions which credit `sales_account`, by the categories of the
+    /// items sold, per day in `tz`, by day.
+    ///
+    /// The amount of a sale is split between its bundles by their prices, and the price of a
+    /// bundle between its items by their number, so that discounts on the whole purchase are
+    /// shared by all of its items.
+    pub fn group(
+        transactions: &[Transaction],
+        sales_account: BookAccountId,
+        categories: &HashMap<InventoryItemId, String>,
+        tz: Tz,
+    ) -> Vec<Self> {
+        let mut sales: BTreeMap<(NaiveDate, Option<&String>), i64> = BTreeMap::new();
+        for tr in transactions
+            .iter()
+            .filter(|tr| tr.credited_account == sales_account)
+        {
+            let day = local_date(tr.time, tz);
+
+            // the share of every category of the listed price of the sale
+            let mut shares: BTreeMap<Option<&String>, f64> = BTreeMap::new();
+            for bundle in &tr.bundles {
+                let sold = -i64::from(bundle.change);
+                let price = bundle.price.map(i64::from).unwrap_or(0);
+                let items: u32 = bundle.item_ids.values().sum();
+                for (item_id, &count) in &bundle.item_ids {
+                    let share = (price * sold) as f64 * f64::from(count) / f64::from(items);
+                    *shares.entry(categories.get(item_id)).or_default() += share;
+                }
+            }
+
+            let listed: f64 = shares.values().sum();
+            let amount = i64::from(tr.amount);
+            if shares.is_empty() || listed <= 0.0 {
+                *sales.entry((day, None)).or_default() += amount;
+                continue;
+            }
+
+            // rounding is settled with the largest share, so that the whole amount is counted
+            let mut rest = amount;
+            let mut largest: Option<(Option<&String>, f64)> = None;
+            for (&category, &share) in &shares {
+                let revenue = (amount as f64 * share / listed).round() as i64;
+                *sales.entry((day, category)).or_default() += revenue;
+                rest -= revenue;
+                let is_largest = match largest {
+                    Some((_, largest)) => share > largest,
+                    None => true,
+                };
+                if is_largest {
+                    largest = Some((category, share));
+                }
+            }
+            if let Some((category, _)) = largest {
+                *sales.entry((day, category)).or_default() += rest;
+            }
+        }
+
+        sales
+            .into_iter()
+            .map(|((day, category), revenue)| CategorySales {
+                day,
+                category: category.cloned(),
+                revenue: revenue.into(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{Datelike, TimeZone};
+
+    #[test]
+    fn test_group_shifts() {
+        let (cash, sales) = (1, 2);
+        let transaction =
+            |hour, minute, cashier: &str, credited_account, amount: i32| Transaction {
+                id: 0,
+                description: None,
+                time: Utc.ymd(2021, 7, 20).and_hms(hour, minute, 0),
+                bundles: vec![],
+                debited_account: cash,
+                credited_account,
+                amount: amount.into(),
+                note: None,
+                tags: vec![],
+                event_code: None,
+                age_verified: false,
+                member_id: None,
+                created_by: Some(cashier.to_string()),
+            };
+        let transactions = vec![
+            transaction(17, 0, "kalle", sales, 1000),
+            transaction(17, 45, "kalle", sales, 500),
+            transaction(18, 30, "kalle", sales, 1500),
+            // a break
+            transaction(21, 0, "kalle", sales, 2000),
+            transaction(17, 10, "lisa", sales, 700),
+            // not a sale
+            transaction(17, 20, "lisa", cash, 10000),
+        ];
+
+        let shifts = CashierShift::group(&transactions, sales);
+        let summary: Vec<(&str, u32, Currency)> = shifts
+            .iter()
+            .map(|shift| {
+                (
+                    shift.cashier.as_str(),
+                    shift.transaction_count,
+                    shift.revenue,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("kalle", 1, 2000.into()),
+                ("lisa", 1, 700.into()),
+                ("kalle", 3, 3000.into()),
+            ]
+        );
+
+        let evening = &shifts[2];
+        assert_eq!(evening.duration(), Duration::minutes(90));
+        assert_eq!(evening.transactions_per_hour(), 2.0);
+        assert_eq!(evening.average_basket(), 1000.into());
+
+        // a single sale counts as a short shift
+        assert_eq!(shifts[1].duration(), Duration::minutes(SHIFT_MIN_MINUTES));
+        assert_eq!(shifts[1].transactions_per_hour(), 4.0);
+    }
+
+    #[test]
+    fn test_group_revenue() {
+        use crate::models::transaction::TransactionBundle;
+
+        let masters = MasterAccounts {
+            bank_account_id: 1,
+            cash_account_id: 2,
+            sales_account_id: 3,
+            purchases_account_id: 4,
+            tips_account_id: 5,
+        };
+        let member_account = BookAccount {
+            id: 10,
+            name: "Kalle".to_string(),
+            account_type: BookAccountType::Liabilities,
+            creditor: Some(1),
+            balance: Currency::default(),
+        };
+        let accounts = vec![(10, member_account)].into_iter().collect();
+
+        let bundle = |item_ids: &[(InventoryItemId, u32)], price: i32| TransactionBundle {
+            description: None,
+            price: Some(price.into()),
+            change: -1,
+            item_ids: item_ids.iter().copied().collect(),
+            expires_on: None,
+            modifier_ids: vec![],
+        };
+        let transaction = |day, debited_account, amount: i32, bundles| Transaction {
+            id: 0,
+            description: None,
+            time: Utc.ymd(2021, 7, day).and_hms(12, 0, 0),
+            bundles,
+            debited_account,
+            credited_account: masters.sales_account_id,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        };
+        let transactions = vec![
+            transaction(
+                20,
+                2,
+                1500,
+                vec![bundle(&[(1, 1)], 1000), bundle(&[(2, 1)], 500)],
+            ),
+            // a bundle of two items from different categories, with a discount
+            transaction(20, 1, 900, vec![bundle(&[(1, 1), (3, 2)], 1200)]),
+            transaction(21, 10, 1000, vec![bundle(&[(2, 2)], 500)]),
+            // a purchase, not a sale
+            Transaction {
+                credited_account: 2,
+                ..transaction(21, 4, 5000, vec![])
+            },
+        ];
+
+        let tz = chrono_tz::Europe::Stockholm;
+        let by_method: Vec<(u32, PaymentMethod, u32, i64)> =
+            PaymentMethodSales::group(&transactions, &masters, &accounts, tz)
+                .into_iter()
+                .map(|sales| {
+                    (
+                        sales.day.day(),
+                        sales.method,
+                        sales.count,
+                        sales.revenue.into(),
+                    )
+                })
+                .collect();
+        assert_eq!(
+            by_method,
+            vec![
+                (20, PaymentMethod::Cash, 1, 1500),
+                (20, PaymentMethod::Card, 1, 900),
+                (21, PaymentMethod::Tillgodo, 1, 1000),
+            ]
+        );
+
+        let categories = vec![(1, "Dryck".to_string()), (2, "Mat".to_string())]
+            .into_iter()
+            .collect();
+        let by_category =
+            CategorySales::group(&transactions, masters.sales_account_id, &categories, tz);
+        let by_category: Vec<(u32, Option<&str>, i64)> = by_category
+            .iter()
+            .map(|sales| {
+                (
+                    sales.day.day(),
+                    sales.category.as_deref(),
+                    sales.revenue.into(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            by_category,
+            vec![
+                (20, None, 600),
+                (20, Some("Dryck"), 1300),
+                (20, Some("Mat"), 500),
+                (21, Some("Mat"), 1000),
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::models::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use {diesel_derive_enum::DbEnum, diesel_derives::Queryable};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type AnnouncementId = i32;
+
+/// How much an announcement should stand out
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(DbEnum))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnnouncementSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl AnnouncementSeverity {
+    pub const ALL: [AnnouncementSeverity; 3] = [
+        AnnouncementSeverity::Info,
+        AnnouncementSeverity::Warning,
+        AnnouncementSeverity::Critical,
+    ];
+}
+
+/// An announcement to make, see [Announcement]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewAnnouncement {
+    pub message: String,
+    pub severity: AnnouncementSeverity,
+    pub starts_at: DateTime<Utc>,
+
+    /// `None` if it's shown until it's removed
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+/// A message from the admins which is shown as a banner on every page while it's active, e.g.
+/// that the card reader is broken tonight
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Announcement {
+    pub id: AnnouncementId,
+    pub message: String,
+    pub severity: AnnouncementSeverity,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: Option<DateTime<Utc>>,
+    pub created_by: Option<UserName>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Announcement {
+    /// Whether the announcement should be shown at `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now && self.ends_at.map(|end| now < end).unwrap_or(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_is_active() {
+        let start = Utc.ymd(2021, 7, 18).and_hms(17, 0, 0);
+        let mut announcement = Announcement {
+            id: 1,
+            message: "Kortläsaren är trasig ikväll".to_string(),
+            severity: AnnouncementSeverity::Warning,
+            starts_at: start,
+            ends_at: None,
+            created_by: None,
+            created_at: start,
+        };
+
+        assert!(!announcement.is_active(start - Duration::seconds(1)));
+        assert!(announcement.is_active(start));
+        assert!(announcement.is_active(start + Duration::days(365)));
+
+        announcement.ends_at = Some(start + Duration::hours(6));
+        assert!(announcement.is_active(start + Duration::hours(5)));
+        assert!(!announcement.is_active(start + Duration::hours(6)));
+    }
+}
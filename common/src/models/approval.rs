@@ -0,0 +1,58 @@
+use crate::models::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The header which a request for a [GatedAction] carries the token of its [Approval] in. A
+/// request which needs several approvals carries their tokens separated by commas.
+pub const APPROVAL_HEADER: &str = "X-Approval";
+
+/// Actions which users who aren't admins may need the approval of an admin for, depending on the
+/// configuration of the server
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GatedAction {
+    /// Voiding a transaction which isn't recent
+    Void,
+
+    /// Selling for less than the list price, by more than the limit
+    Discount,
+
+    /// Selling from the tillgodo of a member who doesn't have enough on it
+    NegativeBalance,
+
+    /// Selling more of an item to a member than its [PurchaseLimit](crate::inventory::PurchaseLimit)
+    PurchaseLimit,
+}
+
+impl GatedAction {
+    pub const ALL: [GatedAction; 4] = [
+        GatedAction::Void,
+        GatedAction::Discount,
+        GatedAction::NegativeBalance,
+        GatedAction::PurchaseLimit,
+    ];
+}
+
+/// An admin approving an action, with their own password
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApprovalRequest {
+    pub name: UserName,
+    pub password: String,
+    pub action: GatedAction,
+}
+
+/// Permission to take an action until it expires, see [APPROVAL_HEADER]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Approval {
+    pub token: String,
+    pub action: GatedAction,
+    pub approved_by: UserName,
+    pub expires: DateTime<Utc>,
+}
@@ -1,5 +1,8 @@
 use crate::currency::Currency;
 use crate::models::member::MemberId;
+use crate::models::transaction::TransactionId;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 
 #[cfg(feature = "diesel_impl")]
 use {diesel_derive_enum::DbEnum, diesel_derives::Queryable};
@@ -67,6 +70,25 @@ pub struct MasterAccounts {
     pub cash_account_id: BookAccountId,
     pub sales_account_id: BookAccountId,
     pub purchases_account_id: BookAccountId,
+
+    /// Tips on card payments, kept apart from the sales
+    pub tips_account_id: BookAccountId,
+}
+
+/// A transaction as seen from a single book account
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub transaction_id: TransactionId,
+    pub time: DateTime<Utc>,
+    pub description: Option<String>,
+
+    /// How much the balance of the account changed
+    pub change: Currency,
+
+    /// The balance of the account after the transaction
+    pub balance: Currency,
 }
 
 impl BookAccount {
@@ -89,3 +111,40 @@ impl BookAccount {
         self.balance += self.debit_diff(amount);
     }
 }
+
+/// The tillgodo balance of a member account, and when it was last touched
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct TillgodoBalance {
+    pub balance: Currency,
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+/// Sum up the tillgodo balances of the member `accounts` from transactions given as
+/// `(debited, credited, amount, time)`. Every one of `accounts` is in the result, including those
+/// without any transactions.
+///
+/// Member accounts are liabilities, so credits increase the balance.
+pub fn tillgodo_balances<I>(
+    accounts: &[BookAccountId],
+    transactions: I,
+) -> HashMap<BookAccountId, TillgodoBalance>
+where
+    I: IntoIterator<Item = (BookAccountId, BookAccountId, Currency, DateTime<Utc>)>,
+{
+    let mut balances: HashMap<BookAccountId, TillgodoBalance> = accounts
+        .iter()
+        .map(|&account| (account, TillgodoBalance::default()))
+        .collect();
+
+    for (debited, credited, amount, time) in transactions {
+        for &(account, change) in &[(credited, amount), (debited, -amount)] {
+            if let Some(entry) = balances.get_mut(&account) {
+                entry.balance += change;
+                entry.last_activity = entry.last_activity.max(Some(time));
+            }
+        }
+    }
+
+    balances
+}
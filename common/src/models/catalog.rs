@@ -0,0 +1,215 @@
+use crate::currency::Currency;
+use crate::models::inventory::{InventoryBundleId, InventoryItemId};
+use crate::models::store_layout::{QuickButtonTarget, StoreLayout};
+use std::collections::HashSet;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The version of the [Catalog] format which is exported, and the only one which can be imported
+pub const CATALOG_VERSION: u32 = 1;
+
+/// Everything which is sold by an organization, for seeding another deployment with it.
+///
+/// Ids are only used to refer to items and bundles within the catalog, since they are given new
+/// ones when they are imported. Stock, suppliers and archived items aren't included.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Catalog {
+    /// See [CATALOG_VERSION]
+    pub version: u32,
+    pub items: Vec<CatalogItem>,
+    pub bundles: Vec<CatalogBundle>,
+
+    /// The quick buttons of the store page, which point at the items and bundles of the catalog
+    pub layout: StoreLayout,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CatalogItem {
+    pub id: InventoryItemId,
+    pub name: String,
+
+    /// `None` for items which aren't for sale
+    pub price: Option<Currency>,
+    pub cost_price: Option<Currency>,
+    pub image_url: Option<String>,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub age_restricted: bool,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub prepared: bool,
+
+    /// The tags of the item, of which the first is its category
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub categories: Vec<String>,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub barcodes: Vec<String>,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub modifiers: Vec<CatalogModifier>,
+}
+
+/// A variant or add-on of a [CatalogItem], see [ItemModifier](crate::inventory::ItemModifier)
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CatalogModifier {
+    pub group: Option<String>,
+    pub name: String,
+    pub price_delta: Currency,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CatalogBundle {
+    pub id: InventoryBundleId,
+    pub name: String,
+    pub price: Currency,
+    pub image_url: Option<String>,
+    pub item_ids: Vec<InventoryItemId>,
+}
+
+/// What importing a catalog did, or would do in a dry run
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct CatalogImportSummary {
+    pub items_created: u32,
+
+    /// Items which already existed with the same name
+    pub items_updated: u32,
+
+    pub bundles_created: u32,
+
+    /// Bundles which already existed with the same name
+    pub bundles_updated: u32,
+
+    /// Whether the store layout was replaced by the one of the catalog
+    pub layout_replaced: bool,
+}
+
+impl Catalog {
+    /// Check that the catalog can be imported, i.e. that it's of the current version, that every
+    /// item and bundle which is referred to is in the catalog, and that the layout is valid.
+    ///
+    /// Returns a description of the first problem found.
+    pub fn check(&self) -> Result<(), String> {
+        if self.version != CATALOG_VERSION {
+            return Err(format!(
+                "unsupported catalog version {}, expected {}",
+                self.version, CATALOG_VERSION
+            ));
+        }
+
+        let mut item_ids = HashSet::new();
+        let mut item_names = HashSet::new();
+        for item in &self.items {
+            if !item_ids.insert(item.id) {
+                return Err(format!("more than one item has the id {}", item.id));
+            }
+            if !item_names.insert(item.name.trim()) {
+                return Err(format!("more than one item is named \"{}\"", item.name));
+            }
+        }
+
+        let mut bundle_ids = HashSet::new();
+        let mut bundle_names = HashSet::new();
+        for bundle in &self.bundles {
+            if !bundle_ids.insert(bundle.id) {
+                return Err(format!("more than one bundle has the id {}", bundle.id));
+            }
+            if !bundle_names.insert(bundle.name.trim()) {
+                return Err(format!("more than one bundle is named \"{}\"", bundle.name));
+            }
+            if let Some(id) = bundle.item_ids.iter().find(|id| !item_ids.contains(id)) {
+                return Err(format!(
+                    "the bundle \"{}\" contains the unknown item {}",
+                    bundle.name, id
+                ));
+            }
+        }
+
+        for tab in &self.layout.tabs {
+            for button in &tab.buttons {
+                let known = match button.target {
+                    QuickButtonTarget::Item(id) => item_ids.contains(&id),
+                    QuickButtonTarget::Bundle(id) => bundle_ids.contains(&id),
+                };
+                if !known {
+                    return Err(format!("unknown button target in tab \"{}\"", tab.name));
+                }
+            }
+        }
+
+        self.layout.validate()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::store_layout::{QuickButton, StoreTab};
+
+    fn catalog() -> Catalog {
+        let item = |id, name: &str| CatalogItem {
+            id,
+            name: name.to_string(),
+            price: Some(Currency::from(1000)),
+            cost_price: None,
+            image_url: None,
+            age_restricted: false,
+            prepared: false,
+            categories: vec![],
+            barcodes: vec![],
+            modifiers: vec![],
+        };
+        Catalog {
+            version: CATALOG_VERSION,
+            items: vec![item(1, "Kaffe"), item(2, "Bulle")],
+            bundles: vec![CatalogBundle {
+                id: 1,
+                name: "Fika".to_string(),
+                price: Currency::from(1500),
+                image_url: None,
+                item_ids: vec![1, 2],
+            }],
+            layout: StoreLayout {
+                tabs: vec![StoreTab {
+                    name: "Fika".to_string(),
+                    buttons: vec![
+                        QuickButton::new(QuickButtonTarget::Item(1), 0, 0),
+                        QuickButton::new(QuickButtonTarget::Bundle(1), 1, 0),
+                    ],
+                }],
+            },
+        }
+    }
+
+    #[test]
+    fn test_check_catalog() {
+        assert_eq!(catalog().check(), Ok(()));
+
+        let mut old = catalog();
+        old.version = 0;
+        assert!(old.check().is_err());
+
+        let mut duplicate = catalog();
+        duplicate.items[1].name = "Kaffe".to_string();
+        assert!(duplicate.check().is_err());
+
+        let mut unknown_item = catalog();
+        unknown_item.bundles[0].item_ids.push(3);
+        assert!(unknown_item.check().is_err());
+
+        let mut unknown_target = catalog();
+        unknown_target.layout.tabs[0].buttons[1].target = QuickButtonTarget::Bundle(2);
+        assert!(unknown_target.check().is_err());
+    }
+}
@@ -0,0 +1,302 @@
+use crate::currency::Currency;
+use crate::models::inventory::{InventoryItemId, ItemModifierId};
+use crate::models::transaction::TransactionBundle;
+use std::collections::HashMap;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// A cart which should be priced, see [CheckoutQuote::compute]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct QuoteRequest {
+    pub bundles: Vec<TransactionBundle>,
+
+    /// The amount the customer paid with, if paying in cash
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub tendered: Option<Currency>,
+
+    /// Whether the customer pays in cash, which is rounded. Implied by `tendered`.
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub cash: bool,
+}
+
+/// How carts are priced, as configured on the server
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CheckoutRules {
+    /// Cash totals are rounded to the nearest multiple of this, e.g. 100 for whole kronor.
+    /// Card and tillgodo payments are exact.
+    pub cash_rounding: Currency,
+}
+
+impl Default for CheckoutRules {
+    fn default() -> Self {
+        CheckoutRules {
+            cash_rounding: Currency::from(1),
+        }
+    }
+}
+
+/// The description of the line which records the rounding of a cash total
+pub const ROUNDING_DESCRIPTION: &str = "Öresavrundning";
+
+/// One bundle of a priced cart
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct QuoteLine {
+    pub bundle: TransactionBundle,
+
+    /// The number of bundles being sold
+    pub quantity: i32,
+
+    /// The price of one bundle
+    pub unit_price: Currency,
+
+    /// What one bundle would cost if all of its items, and their modifiers, were bought
+    /// separately
+    pub list_price: Currency,
+
+    pub total: Currency,
+
+    /// How much cheaper the bundle is than its items, in total
+    pub discount: Currency,
+}
+
+/// A priced cart
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CheckoutQuote {
+    pub lines: Vec<QuoteLine>,
+
+    /// The sum of the list prices of every line
+    pub subtotal: Currency,
+
+    pub discount: Currency,
+
+    /// What was added to the total to round it, may be negative. Included in the total.
+    pub rounding: Currency,
+
+    pub total: Currency,
+
+    /// What the customer should get back, if an amount was tendered
+    pub change: Option<Currency>,
+}
+
+/// Round `amount` to the nearest multiple of `step`, with halves rounded up
+pub fn round_to(amount: Currency, step: Currency) -> Currency {
+    let (amount, step) = (i64::from(amount), i64::from(step));
+    if step <= 1 {
+        return amount.into();
+    }
+    let remainder = amount.rem_euclid(step);
+    let rounded = if remainder * 2 >= step {
+        amount - remainder + step
+    } else {
+        amount - remainder
+    };
+    rounded.into()
+}
+
+impl CheckoutQuote {
+    /// Price the bundles in `request`.
+    ///
+    /// Bundles without a price are priced as the sum of their items and the price deltas of their
+    /// modifiers. Items without a price are free. Cash totals are rounded according to `rules`.
+    pub fn compute(
+        request: &QuoteRequest,
+        item_prices: &HashMap<InventoryItemId, Currency>,
+        modifier_prices: &HashMap<ItemModifierId, Currency>,
+        rules: &CheckoutRules,
+    ) -> Self {
+        let lines: Vec<QuoteLine> = request
+            .bundles
+            .iter()
+            .map(|bundle| {
+                let items: Currency = bundle
+                    .item_ids
+                    .iter()
+                    .map(|(id, &per_bundle)| {
+                        let price: i64 = item_prices.get(id).copied().unwrap_or_default().into();
+                        Currency::from(price * i64::from(per_bundle))
+                    })
+                    .sum();
+                let modifiers: Currency = bundle
+                    .modifier_ids
+                    .iter()
+                    .map(|id| modifier_prices.get(id).copied().unwrap_or_default())
+                    .sum();
+                let list_price = items + modifiers;
+                let unit_price = bundle.price.unwrap_or(list_price);
+                let quantity = -bundle.change;
+                let times =
+                    |price: Currency| Currency::from(i64::from(price) * i64::from(quantity));
+
+                QuoteLine {
+                    bundle: bundle.clone(),
+                    quantity,
+                    unit_price,
+                    list_price,
+                    total: times(unit_price),
+                    discount: times(list_price - unit_price),
+                }
+            })
+            .collect();
+
+        let discount = lines.iter().map(|line| line.discount).sum();
+        let exact: Currency = lines.iter().map(|line| line.total).sum();
+        let total = if request.cash || request.tendered.is_some() {
+            round_to(exact, rules.cash_rounding)
+        } else {
+            exact
+        };
+
+        CheckoutQuote {
+            lines,
+            subtotal: exact + discount,
+            discount,
+            rounding: total - exact,
+            total,
+            change: request.tendered.map(|tendered| tendered - total),
+        }
+    }
+
+    /// A line recording the rounding of the total, to add to the transaction, if it was rounded
+    pub fn rounding_bundle(&self) -> Option<TransactionBundle> {
+        if self.rounding == Currency::default() {
+            return None;
+        }
+        Some(TransactionBundle {
+            description: Some(ROUNDING_DESCRIPTION.to_string()),
+            price: Some(self.rounding),
+            change: -1,
+            item_ids: HashMap::new(),
+            expires_on: None,
+            modifier_ids: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quote() {
+        let item_prices: HashMap<_, _> = vec![(1, Currency::from(1000)), (2, Currency::from(500))]
+            .into_iter()
+            .collect();
+
+        let request = QuoteRequest {
+            bundles: vec![
+                // 2 colas, priced by the item
+                TransactionBundle {
+                    description: None,
+                    price: None,
+                    change: -2,
+                    item_ids: vec![(1, 1)].into_iter().collect(),
+                    expires_on: None,
+                    modifier_ids: vec![],
+                },
+                // a cola and two bars for 15:-
+                TransactionBundle {
+                    description: Some("Combo".to_string()),
+                    price: Some(1500.into()),
+                    change: -1,
+                    item_ids: vec![(1, 1), (2, 2)].into_iter().collect(),
+                    expires_on: None,
+                    modifier_ids: vec![],
+                },
+            ],
+            tendered: Some(5000.into()),
+            cash: false,
+        };
+
+        let quote = CheckoutQuote::compute(
+            &request,
+            &item_prices,
+            &HashMap::new(),
+            &CheckoutRules::default(),
+        );
+
+        assert_eq!(quote.lines[0].total, 2000.into());
+        assert_eq!(quote.lines[1].list_price, 2000.into());
+        assert_eq!(quote.subtotal, 4000.into());
+        assert_eq!(quote.discount, 500.into());
+        assert_eq!(quote.total, 3500.into());
+        assert_eq!(quote.change, Some(1500.into()));
+        assert_eq!(quote.rounding_bundle(), None);
+    }
+
+    #[test]
+    fn test_cash_rounding() {
+        let item_prices: HashMap<_, _> = vec![(1, Currency::from(1250))].into_iter().collect();
+        let rules = CheckoutRules {
+            cash_rounding: Currency::from(100),
+        };
+        let mut request = QuoteRequest {
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: None,
+                change: -1,
+                item_ids: vec![(1, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            tendered: None,
+            cash: false,
+        };
+
+        // only cash is rounded
+        let card = CheckoutQuote::compute(&request, &item_prices, &HashMap::new(), &rules);
+        assert_eq!(card.total, 1250.into());
+
+        request.cash = true;
+        let cash = CheckoutQuote::compute(&request, &item_prices, &HashMap::new(), &rules);
+        assert_eq!(cash.subtotal, 1250.into());
+        assert_eq!(cash.rounding, 50.into());
+        assert_eq!(cash.total, 1300.into());
+        assert_eq!(
+            cash.rounding_bundle().and_then(|b| b.price),
+            Some(50.into())
+        );
+
+        assert_eq!(round_to(1249.into(), 100.into()), 1200.into());
+        assert_eq!(round_to((-1260).into(), 100.into()), (-1300).into());
+        assert_eq!(round_to(1249.into(), 1.into()), 1249.into());
+    }
+
+    #[test]
+    fn test_modifier_prices() {
+        let item_prices: HashMap<_, _> = vec![(1, Currency::from(1000))].into_iter().collect();
+        let modifier_prices: HashMap<_, _> =
+            vec![(1, Currency::from(500)), (2, Currency::from(-200))]
+                .into_iter()
+                .collect();
+        let request = QuoteRequest {
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: None,
+                change: -2,
+                item_ids: vec![(1, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![1, 2],
+            }],
+            tendered: None,
+            cash: false,
+        };
+
+        let quote = CheckoutQuote::compute(
+            &request,
+            &item_prices,
+            &modifier_prices,
+            &CheckoutRules::default(),
+        );
+        assert_eq!(quote.lines[0].list_price, 1300.into());
+        assert_eq!(quote.total, 2600.into());
+    }
+}
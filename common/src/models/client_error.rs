@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// How many breadcrumbs the frontend keeps, and the server accepts in a report
+pub const MAX_BREADCRUMBS: usize = 100;
+
+/// Something the frontend did shortly before an error, to make the error reproducible
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Breadcrumb {
+    pub time: DateTime<Utc>,
+    pub kind: BreadcrumbKind,
+
+    /// E.g. the message, or the method, path and status of the request
+    pub text: String,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BreadcrumbKind {
+    /// A message handled by the app
+    Msg,
+
+    /// A request to the server
+    Fetch,
+}
+
+impl BreadcrumbKind {
+    pub const ALL: [BreadcrumbKind; 2] = [BreadcrumbKind::Msg, BreadcrumbKind::Fetch];
+}
+
+/// An error in the frontend, e.g. a panic, reported to the server so that it ends up in its log
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ClientErrorReport {
+    pub message: String,
+
+    /// The url of the page which the error happened on
+    pub url: String,
+
+    /// The version of the frontend
+    pub version: String,
+
+    /// What the app looked like, e.g. which page was open and who was logged in
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub state: BTreeMap<String, String>,
+
+    /// What happened before the error, oldest first
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub breadcrumbs: Vec<Breadcrumb>,
+}
@@ -0,0 +1,83 @@
+use crate::currency::Currency;
+use crate::models::book_account::BookAccountId;
+use crate::models::inventory::InventoryItemId;
+use crate::models::transaction::TransactionId;
+use crate::models::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derive_enum::DbEnum;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type CorrectionId = i32;
+
+/// A change made by hand rather than by a sale, e.g. to fix a deposit to the wrong member or
+/// after counting the stock
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum CorrectionKind {
+    /// Move `amount` from one book account to another
+    Balance {
+        debited_account: BookAccountId,
+        credited_account: BookAccountId,
+        amount: Currency,
+    },
+
+    /// Change the stock of an item, negative if some are missing
+    Stock {
+        item_id: InventoryItemId,
+        change: i32,
+    },
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewCorrection {
+    pub kind: CorrectionKind,
+
+    /// Why the correction is made, shown to whoever approves it
+    pub reason: String,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(DbEnum))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CorrectionStatus {
+    /// Too large to be made without the approval of a second user, who hasn't decided yet
+    Pending,
+
+    /// Made, at once or once it was approved
+    Applied,
+
+    /// Not made, since it was rejected
+    Rejected,
+}
+
+/// A correction, and who requested and approved it.
+///
+/// Corrections which are larger than the limits set on the server wait for the approval of an
+/// admin other than the one who requested them.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Correction {
+    pub id: CorrectionId,
+    pub kind: CorrectionKind,
+    pub reason: String,
+    pub status: CorrectionStatus,
+
+    pub requested_by: Option<UserName>,
+    pub requested_at: DateTime<Utc>,
+
+    /// Who approved or rejected it, `None` if it's pending or didn't need approval
+    pub decided_by: Option<UserName>,
+    pub decided_at: Option<DateTime<Utc>>,
+
+    /// The transaction which made the correction, once it's applied
+    pub transaction_id: Option<TransactionId>,
+}
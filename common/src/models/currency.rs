@@ -2,7 +2,10 @@ mod non_negative;
 pub use non_negative::*;
 
 use regex::Regex;
+use std::convert::TryFrom;
 use std::fmt::{self, Display};
+use std::iter::Sum;
+use std::num::TryFromIntError;
 use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
 use std::str::FromStr;
 
@@ -11,20 +14,28 @@ use serde::{Deserialize, Serialize};
 
 /// A unit of money.
 ///
-/// A number with two decimals of precision, internally represented as an i32.
+/// A number with two decimals of precision, internally represented as an i64.
+///
+/// The value is serialized as a plain integer number of minor units (e.g. öre),
+/// which is the same wire format as when it was backed by an i32.
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "debug", derive(Debug))]
 #[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Default)]
-pub struct Currency(i32);
+pub struct Currency(i64);
 
 impl Currency {
+    /// Create from a number of minor units (e.g. öre), like `From<i64>` but usable in constants
+    pub const fn from_minor(minor: i64) -> Self {
+        Currency(minor)
+    }
+
     /// Extract the fractional part of this number
-    pub fn fractional(self) -> i32 {
+    pub fn fractional(self) -> i64 {
         self.0 % 100
     }
 
     /// Extract the non-fractional part of this number
-    pub fn whole(self) -> i32 {
+    pub fn whole(self) -> i64 {
         self.0 / 100
     }
 
@@ -117,7 +128,7 @@ impl FromStr for Currency {
 
         if let Some(captures) = CURRENCY_RE.captures(s) {
             let neg = captures.name("neg").is_some();
-            let whole: i32 = captures
+            let whole: i64 = captures
                 .name("whole")
                 .expect("regex group did not exist")
                 .as_str()
@@ -127,7 +138,7 @@ impl FromStr for Currency {
 
             let frac_s = captures.name("frac").map(|f| f.as_str()).unwrap_or("00");
 
-            let mut frac: i32 = frac_s
+            let mut frac: i64 = frac_s
                 .parse()
                 .map_err(|_| CurrencyParseError::IntegerOverflow)?;
 
@@ -155,16 +166,36 @@ impl FromStr for Currency {
 
 impl From<i32> for Currency {
     fn from(other: i32) -> Self {
+        Currency(other.into())
+    }
+}
+
+impl From<i64> for Currency {
+    fn from(other: i64) -> Self {
         Currency(other)
     }
 }
 
-impl From<Currency> for i32 {
+impl From<Currency> for i64 {
     fn from(val: Currency) -> Self {
         val.0
     }
 }
 
+impl TryFrom<Currency> for i32 {
+    type Error = TryFromIntError;
+
+    fn try_from(val: Currency) -> Result<Self, Self::Error> {
+        i32::try_from(val.0)
+    }
+}
+
+impl Sum for Currency {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Currency::default(), |a, b| a + b)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -240,4 +271,14 @@ mod test {
         assert_eq!(Currency::from(-232323).as_f64(), -2323.23);
         assert_eq!(Currency::from(-1).as_f64(), -0.01);
     }
+
+    #[test]
+    fn test_currency_above_i32() {
+        let big = Currency::from(i64::from(i32::MAX) * 10);
+        assert_eq!(big.whole(), i64::from(i32::MAX) / 10);
+        assert_eq!(format!("{}", big).parse(), Ok(big));
+        assert!(i32::try_from(big).is_err());
+        assert_eq!(i32::try_from(Currency::from(4200)), Ok(4200));
+        assert_eq!(vec![big, big, -big].into_iter().sum::<Currency>(), big);
+    }
 }
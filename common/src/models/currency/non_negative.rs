@@ -1,45 +1,167 @@
 use crate::currency::{Currency, CurrencyParseError};
 use std::convert::TryFrom;
 use std::fmt::{self, Display};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign};
 use std::str::FromStr;
 
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// A unit of money which is never less than 0.
+///
+/// Deserializing a negative value fails, so this can be used directly in API types.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde_impl",
+    serde(try_from = "Currency", into = "Currency")
+)]
 #[cfg_attr(feature = "debug", derive(Debug))]
 #[derive(Clone, Copy, PartialEq, Eq, Ord, PartialOrd, Default)]
-pub struct AbsCurrency(Currency);
+pub struct NonNegativeCurrency(Currency);
+
+/// The old name of [NonNegativeCurrency]
+#[deprecated(note = "renamed to NonNegativeCurrency")]
+pub type AbsCurrency = NonNegativeCurrency;
+
+/// Error returned when trying to convert a negative [Currency] to a [NonNegativeCurrency]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NegativeCurrencyError;
+
+impl Display for NegativeCurrencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("currency less than 0")
+    }
+}
+
+impl NonNegativeCurrency {
+    /// Subtract `other` from `self`, returning `None` if the result would be negative.
+    pub fn checked_sub(self, other: NonNegativeCurrency) -> Option<NonNegativeCurrency> {
+        NonNegativeCurrency::try_from(self.0 - other.0).ok()
+    }
+
+    /// Subtract `other` from `self`, clamping the result at 0.
+    pub fn saturating_sub(self, other: NonNegativeCurrency) -> NonNegativeCurrency {
+        self.checked_sub(other).unwrap_or_default()
+    }
+}
 
-impl TryFrom<Currency> for AbsCurrency {
-    type Error = &'static str;
+impl TryFrom<Currency> for NonNegativeCurrency {
+    type Error = NegativeCurrencyError;
 
     fn try_from(value: Currency) -> Result<Self, Self::Error> {
         if value < 0.into() {
-            Err("currency less than 0")
+            Err(NegativeCurrencyError)
         } else {
-            Ok(AbsCurrency(value))
+            Ok(NonNegativeCurrency(value))
         }
     }
 }
 
-impl From<AbsCurrency> for Currency {
-    fn from(val: AbsCurrency) -> Self {
+impl From<NonNegativeCurrency> for Currency {
+    fn from(val: NonNegativeCurrency) -> Self {
         val.0
     }
 }
 
-impl FromStr for AbsCurrency {
+/// The sum of two non-negative numbers is never negative.
+impl Add for NonNegativeCurrency {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        NonNegativeCurrency(self.0 + other.0)
+    }
+}
+
+impl AddAssign for NonNegativeCurrency {
+    fn add_assign(&mut self, other: Self) {
+        self.0 += other.0;
+    }
+}
+
+/// Adding a [Currency] can make the result negative, so the output is a plain [Currency].
+impl Add<Currency> for NonNegativeCurrency {
+    type Output = Currency;
+    fn add(self, other: Currency) -> Currency {
+        self.0 + other
+    }
+}
+
+impl Add<NonNegativeCurrency> for Currency {
+    type Output = Currency;
+    fn add(self, other: NonNegativeCurrency) -> Currency {
+        self + other.0
+    }
+}
+
+impl Sum for NonNegativeCurrency {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(NonNegativeCurrency::default(), |a, b| a + b)
+    }
+}
+
+impl FromStr for NonNegativeCurrency {
     type Err = CurrencyParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let currency = Currency::from_str(s)?;
-        if currency < 0.into() {
-            Err(CurrencyParseError::MatchFailed)
-        } else {
-            Ok(AbsCurrency(currency))
-        }
+        NonNegativeCurrency::try_from(currency).map_err(|_| CurrencyParseError::MatchFailed)
     }
 }
 
-impl Display for AbsCurrency {
+impl Display for NonNegativeCurrency {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.0.fmt(f)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn nn(v: i64) -> NonNegativeCurrency {
+        NonNegativeCurrency::try_from(Currency::from(v)).unwrap()
+    }
+
+    #[test]
+    fn test_non_negative_conversion() {
+        assert_eq!(
+            NonNegativeCurrency::try_from(Currency::from(-1)),
+            Err(NegativeCurrencyError)
+        );
+        assert_eq!(Currency::from(nn(0)), Currency::from(0));
+        assert_eq!("-3".parse::<NonNegativeCurrency>().ok(), None);
+        assert_eq!("3.5".parse::<NonNegativeCurrency>(), Ok(nn(350)));
+    }
+
+    #[test]
+    fn test_non_negative_arithmetic() {
+        assert_eq!(nn(500).checked_sub(nn(200)), Some(nn(300)));
+        assert_eq!(nn(500).checked_sub(nn(500)), Some(nn(0)));
+        assert_eq!(nn(200).checked_sub(nn(500)), None);
+        assert_eq!(nn(200).saturating_sub(nn(500)), nn(0));
+        assert_eq!(nn(200) + nn(300), nn(500));
+        assert_eq!(nn(200) + Currency::from(-300), Currency::from(-100));
+        assert_eq!(Currency::from(-300) + nn(200), Currency::from(-100));
+        assert_eq!(
+            vec![nn(1), nn(2), nn(3)]
+                .into_iter()
+                .sum::<NonNegativeCurrency>(),
+            nn(6)
+        );
+
+        let mut a = nn(10);
+        a += nn(5);
+        assert_eq!(a, nn(15));
+    }
+
+    #[cfg(feature = "serde_impl")]
+    #[test]
+    fn test_non_negative_serde() {
+        assert_eq!(serde_json::to_string(&nn(1234)).unwrap(), "1234");
+        assert_eq!(
+            serde_json::from_str::<NonNegativeCurrency>("1234").unwrap(),
+            nn(1234)
+        );
+        assert!(serde_json::from_str::<NonNegativeCurrency>("-1").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::currency::Currency;
+use crate::models::admin::BridgeStatus;
+use crate::models::book_account::MasterAccounts;
+use crate::models::transaction::Transaction;
+use crate::time::{local_date, Tz};
+use chrono::{NaiveDate, Timelike};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The sales of the day so far and the state of the card payments, for the dashboard page.
+///
+/// Whether the register is open and which items are low on stock are fetched on their own.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Dashboard {
+    /// Today, in the reporting timezone
+    pub date: NaiveDate,
+
+    /// The sum of the sales, not counting tips or deposits
+    pub revenue: Currency,
+    pub sales: u32,
+
+    /// From the first to the last hour with any sales, including the hours between them
+    pub hours: Vec<HourlySales>,
+
+    /// Only when card payments are enabled
+    pub bridge: Option<BridgeStatus>,
+
+    /// Whether the bridge has polled recently enough to take card payments
+    pub bridge_online: bool,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct HourlySales {
+    /// The hour of the day in the reporting timezone, 0-23
+    pub hour: u32,
+    pub count: u32,
+    pub total: Currency,
+}
+
+impl Dashboard {
+    /// The sales of `date`, without the state of the bridge which the server fills in
+    pub fn compute(
+        date: NaiveDate,
+        tz: Tz,
+        transactions: &[Transaction],
+        masters: &MasterAccounts,
+    ) -> Self {
+        let mut hours: BTreeMap<u32, (u32, Currency)> = BTreeMap::new();
+
+        for tr in transactions
+            .iter()
+            .filter(|tr| tr.credited_account == masters.sales_account_id)
+            .filter(|tr| local_date(tr.time, tz) == date)
+        {
+            let (count, total) = hours.entry(tr.time.with_timezone(&tz).hour()).or_default();
+            *count += 1;
+            *total += tr.amount;
+        }
+
+        let hours: Vec<HourlySales> = match (hours.keys().next(), hours.keys().next_back()) {
+            (Some(&first), Some(&last)) => (first..=last)
+                .map(|hour| {
+                    let (count, total) = hours.get(&hour).copied().unwrap_or_default();
+                    HourlySales { hour, count, total }
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        Dashboard {
+            date,
+            revenue: hours.iter().map(|hour| hour.total).sum(),
+            sales: hours.iter().map(|hour| hour.count).sum(),
+            hours,
+            bridge: None,
+            bridge_online: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::time::DEFAULT_REPORTING_TIMEZONE;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_dashboard() {
+        let masters = MasterAccounts {
+            bank_account_id: 1,
+            cash_account_id: 2,
+            sales_account_id: 3,
+            purchases_account_id: 4,
+            tips_account_id: 6,
+        };
+        let transaction = |hour, credited_account, amount: i32| Transaction {
+            id: 0,
+            description: None,
+            time: Utc.ymd(2021, 7, 1).and_hms(hour, 30, 0),
+            bundles: vec![],
+            debited_account: masters.cash_account_id,
+            credited_account,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        };
+
+        // UTC+2 in the summer, so 16:30 UTC is 18:30 local time
+        let transactions = vec![
+            transaction(16, 3, 1000),
+            transaction(16, 3, 500),
+            transaction(19, 3, 2000),
+            // tips and deposits aren't sales
+            transaction(17, 6, 100),
+            transaction(17, 5, 10000),
+            // after midnight local time, which is the next day
+            transaction(22, 3, 700),
+        ];
+        let date = NaiveDate::from_ymd(2021, 7, 1);
+        let dashboard =
+            Dashboard::compute(date, DEFAULT_REPORTING_TIMEZONE, &transactions, &masters);
+
+        assert_eq!(dashboard.revenue, Currency::from(3500));
+        assert_eq!(dashboard.sales, 3);
+        assert_eq!(
+            dashboard
+                .hours
+                .iter()
+                .map(|hour| (hour.hour, hour.count, hour.total))
+                .collect::<Vec<_>>(),
+            vec![
+                (18, 2, Currency::from(1500)),
+                (19, 0, Currency::default()),
+                (20, 0, Currency::default()),
+                (21, 1, Currency::from(2000)),
+            ]
+        );
+
+        let nothing = Dashboard::compute(date, DEFAULT_REPORTING_TIMEZONE, &[], &masters);
+        assert!(nothing.hours.is_empty());
+        assert_eq!(nothing.revenue, Currency::default());
+    }
+}
@@ -0,0 +1,35 @@
+use crate::models::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type DeviceId = i32;
+
+/// A browser which an admin has given a name, e.g. the register "Kassa 1", so that the
+/// transactions and shifts made on it can be told apart from those of other devices
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Device {
+    pub id: DeviceId,
+    pub name: String,
+    pub registered_by: Option<UserName>,
+    pub registered_at: DateTime<Utc>,
+
+    /// When the device was last heard from since the server started, if it has been
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub last_seen: Option<DateTime<Utc>>,
+
+    /// Whether the device is listening for changes, or was heard from recently
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub online: bool,
+}
+
+/// The name to register the device which sends it as, or to rename a device to
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DeviceName {
+    pub name: String,
+}
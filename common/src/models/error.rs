@@ -0,0 +1,116 @@
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// What went wrong with a request, so that clients can act on errors without reading the
+/// description
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// The request was malformed
+    BadRequest,
+
+    /// Some fields of the request were rejected, the details are
+    /// [FieldErrors](crate::validation::FieldError)
+    InvalidFields,
+
+    /// Not logged in, or the session has expired
+    Unauthorized,
+
+    /// Wrong username or password
+    InvalidCredentials,
+
+    /// Too many failed logins, the account is locked for a while. The details are when it is
+    /// unlocked, as a [DateTime](chrono::DateTime)
+    AccountLocked,
+
+    /// Logged in, but not allowed to do this
+    Forbidden,
+
+    /// The user is in several organizations and has to pick one, the details are
+    /// [Organizations](crate::organization::Organization)
+    ChooseOrganization,
+
+    NotFound,
+
+    /// A sale would take items below zero in stock, the details are
+    /// [StockShortages](crate::inventory::StockShortage)
+    StockEmpty,
+
+    /// Deposits can only be made to member accounts
+    NotAMemberAccount,
+
+    /// Age-restricted items were sold without confirming that the ID of the buyer was checked
+    AgeNotVerified,
+
+    /// The transaction is dated before the lock date of the organization, so it can't be voided or
+    /// edited. The details are the lock date, as a [NaiveDate](chrono::NaiveDate)
+    PeriodLocked,
+
+    /// The session is in training mode, in which only sales can be made, and they are kept out of
+    /// the books
+    TrainingMode,
+
+    /// Only an admin may do this, unless an admin approves it. The details are the
+    /// [GatedAction](crate::approval::GatedAction) to approve.
+    ApprovalRequired,
+
+    /// iZettle payments are disabled on the server
+    IZettleDisabled,
+
+    /// The iZettle bridge hasn't polled for payments recently
+    BridgeOffline,
+
+    /// The bridge reported the result of a payment which doesn't exist
+    UnknownPayment,
+
+    /// Another result has already been reported for the payment. The details are its status,
+    /// e.g. `"paid"`
+    PaymentFinalized,
+
+    /// The bridge charged another amount than the payment is for. The details are the amount of
+    /// the payment, in öre
+    AmountMismatch,
+
+    /// The bridge reported a payment as paid without saying what it charged
+    AmountMissing,
+
+    /// Too many requests from the same client, try again later
+    RateLimited,
+
+    /// The request body is larger than the server accepts
+    PayloadTooLarge,
+
+    /// Something went wrong on the server
+    Internal,
+}
+
+/// The body of every error response from the API
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiError<T> {
+    /// The HTTP status code
+    pub status: u16,
+    pub code: ErrorCode,
+    pub description: String,
+
+    /// Structured information about the error, depending on `code`
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub details: Option<T>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_error_without_details() {
+        let error: ApiError<Vec<String>> = serde_json::from_str(
+            r#"{"status": 503, "code": "BridgeOffline", "description": "Offline"}"#,
+        )
+        .unwrap();
+        assert_eq!(error.code, ErrorCode::BridgeOffline);
+        assert_eq!(error.details, None);
+    }
+}
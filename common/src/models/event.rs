@@ -0,0 +1,179 @@
+use crate::currency::Currency;
+use crate::models::book_account::{BookAccount, BookAccountId, BookAccountType};
+use crate::models::inventory::InventoryItemId;
+use crate::models::transaction::Transaction;
+use chrono::{DateTime, Utc};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The profit and loss of an event or project, from the transactions tagged with its event code
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct EventProfitAndLoss {
+    pub event_code: String,
+
+    /// What was credited to revenue accounts, less what was debited from them, e.g. refunds
+    pub revenue: Currency,
+
+    /// What was debited to expense accounts, less what was credited to them
+    pub costs: Currency,
+
+    pub transaction_count: u32,
+    pub first_transaction: DateTime<Utc>,
+    pub last_transaction: DateTime<Utc>,
+}
+
+impl EventProfitAndLoss {
+    /// The profit, or the loss if negative
+    pub fn result(&self) -> Currency {
+        self.revenue - self.costs
+    }
+
+    /// Sum up the transactions of every event code, the most recent event first.
+    ///
+    /// Transactions without an event code are skipped.
+    pub fn compute(
+        transactions: &[Transaction],
+        accounts: &HashMap<BookAccountId, BookAccount>,
+    ) -> Vec<Self> {
+        let account_type = |id| accounts.get(&id).map(|acc| acc.account_type);
+
+        let mut events: BTreeMap<&str, EventProfitAndLoss> = BTreeMap::new();
+        for tr in transactions {
+            let event_code = match &tr.event_code {
+                Some(event_code) => event_code,
+                None => continue,
+            };
+
+            let event = events
+                .entry(event_code.as_str())
+                .or_insert_with(|| EventProfitAndLoss {
+                    event_code: event_code.clone(),
+                    revenue: Currency::default(),
+                    costs: Currency::default(),
+                    transaction_count: 0,
+                    first_transaction: tr.time,
+                    last_transaction: tr.time,
+                });
+
+            event.transaction_count += 1;
+            event.first_transaction = event.first_transaction.min(tr.time);
+            event.last_transaction = event.last_transaction.max(tr.time);
+
+            match account_type(tr.credited_account) {
+                Some(BookAccountType::Revenue) => event.revenue += tr.amount,
+                Some(BookAccountType::Expenses) => event.costs -= tr.amount,
+                _ => {}
+            }
+            match account_type(tr.debited_account) {
+                Some(BookAccountType::Revenue) => event.revenue -= tr.amount,
+                Some(BookAccountType::Expenses) => event.costs += tr.amount,
+                _ => {}
+            }
+        }
+
+        let mut events: Vec<_> = events.into_values().collect();
+        events.sort_by_key(|event| Reverse(event.last_transaction));
+        events
+    }
+}
+
+pub type EventId = i32;
+pub type SignupId = i32;
+
+/// Someone who signed up to an event, and what they pre-ordered
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct EventSignup {
+    pub id: SignupId,
+    pub event: EventId,
+    pub name: String,
+    pub email: String,
+
+    /// How many of each item is set aside for them, e.g. pre-ordered food. Until the event starts
+    /// they are deducted from what can be sold, see
+    /// [InventoryItemStock::sellable](crate::inventory::InventoryItemStock::sellable).
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub reservations: BTreeMap<InventoryItemId, u32>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewEventSignup {
+    pub name: String,
+    pub email: String,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub reservations: BTreeMap<InventoryItemId, u32>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn account(id: BookAccountId, account_type: BookAccountType) -> BookAccount {
+        BookAccount {
+            id,
+            name: String::new(),
+            account_type,
+            creditor: None,
+            balance: Currency::default(),
+        }
+    }
+
+    #[test]
+    fn test_profit_and_loss() {
+        let accounts: HashMap<_, _> = vec![
+            account(1, BookAccountType::Assets),
+            account(2, BookAccountType::Revenue),
+            account(3, BookAccountType::Expenses),
+        ]
+        .into_iter()
+        .map(|acc| (acc.id, acc))
+        .collect();
+
+        let transaction =
+            |id, day, debited_account, credited_account, amount: i32, event: &str| Transaction {
+                id,
+                description: None,
+                time: Utc.ymd(2021, 7, day).and_hms(12, 0, 0),
+                bundles: vec![],
+                debited_account,
+                credited_account,
+                amount: amount.into(),
+                note: None,
+                tags: vec![],
+                event_code: Some(event.to_string()).filter(|event| !event.is_empty()),
+                age_verified: false,
+                member_id: None,
+                created_by: None,
+            };
+
+        let transactions = vec![
+            transaction(1, 1, 3, 1, 3000, "pubrunda"), // restock
+            transaction(2, 2, 1, 2, 2500, "pubrunda"), // sale
+            transaction(3, 2, 2, 1, 500, "pubrunda"),  // refund
+            transaction(4, 3, 1, 2, 1000, "lan"),
+            transaction(5, 3, 1, 2, 9999, ""), // not tagged
+        ];
+
+        let events = EventProfitAndLoss::compute(&transactions, &accounts);
+        let summary: Vec<_> = events
+            .iter()
+            .map(|e| (e.event_code.as_str(), e.transaction_count, e.result()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("lan", 1, 1000.into()), ("pubrunda", 3, (-1000).into())]
+        );
+        assert_eq!(events[1].revenue, 2000.into());
+        assert_eq!(events[1].costs, 3000.into());
+    }
+}
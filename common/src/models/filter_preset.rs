@@ -0,0 +1,200 @@
+use crate::models::book_account::BookAccountId;
+use crate::models::inventory::InventoryItemId;
+use crate::models::user::UserName;
+use crate::time::{PeriodKind, ReportingPeriod};
+use chrono::NaiveDate;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type FilterPresetId = i32;
+
+/// The pages which filter presets can be saved for
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PresetPage {
+    Transactions,
+    Analytics,
+}
+
+impl PresetPage {
+    pub const ALL: [PresetPage; 2] = [PresetPage::Transactions, PresetPage::Analytics];
+}
+
+/// How the page is stored in the database
+impl Display for PresetPage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PresetPage::Transactions => "transactions",
+            PresetPage::Analytics => "analytics",
+        })
+    }
+}
+
+impl FromStr for PresetPage {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PresetPage::ALL
+            .iter()
+            .copied()
+            .find(|page| page.to_string() == s)
+            .ok_or(())
+    }
+}
+
+/// Which days a preset shows.
+///
+/// Periods are relative to the day the preset is picked, so that e.g. "this month" follows along.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PresetDates {
+    /// The period which today is in, e.g. this month
+    Current(PeriodKind),
+
+    /// The period before the current one, e.g. last month
+    Previous(PeriodKind),
+
+    /// The same days every time, unbounded where missing
+    Fixed {
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    },
+}
+
+impl Default for PresetDates {
+    fn default() -> Self {
+        PresetDates::Fixed {
+            from: None,
+            to: None,
+        }
+    }
+}
+
+impl PresetDates {
+    /// The dates of a filter which is saved on `today`. A range which is exactly the current or
+    /// the previous period of some kind is saved as that period, so that it follows along.
+    pub fn from_range(from: Option<NaiveDate>, to: Option<NaiveDate>, today: NaiveDate) -> Self {
+        PeriodKind::ALL
+            .iter()
+            .flat_map(|&kind| vec![PresetDates::Current(kind), PresetDates::Previous(kind)])
+            .find(|dates| dates.range(today) == (from, to))
+            .unwrap_or(PresetDates::Fixed { from, to })
+    }
+
+    /// The period which is shown when the preset is picked on `today`, unless the days are fixed
+    pub fn period(&self, today: NaiveDate) -> Option<ReportingPeriod> {
+        match *self {
+            PresetDates::Current(kind) => Some(ReportingPeriod::containing(kind, today)),
+            PresetDates::Previous(kind) => {
+                Some(ReportingPeriod::containing(kind, today).previous())
+            }
+            PresetDates::Fixed { .. } => None,
+        }
+    }
+
+    /// The first and the last day which are shown when the preset is picked on `today`, inclusive
+    pub fn range(&self, today: NaiveDate) -> (Option<NaiveDate>, Option<NaiveDate>) {
+        match (*self, self.period(today)) {
+            (PresetDates::Fixed { from, to }, _) => (from, to),
+            (_, period) => (
+                period.map(|period| period.first_day()),
+                period.map(|period| period.last_day()),
+            ),
+        }
+    }
+}
+
+/// The filters of a page. Those which the page doesn't have are ignored when the preset is picked.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(default))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct PresetFilter {
+    pub dates: PresetDates,
+
+    /// Only transactions paid with this account, e.g. card payments
+    pub paid_with: Option<BookAccountId>,
+
+    /// Only transactions containing this item
+    pub item: Option<InventoryItemId>,
+
+    /// Only transactions with a note or tag containing this
+    pub search: String,
+
+    /// Only sales made by this user
+    pub cashier: Option<UserName>,
+}
+
+/// A named filter which a user has saved for a page
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct FilterPreset {
+    pub id: FilterPresetId,
+    pub name: String,
+    pub page: PresetPage,
+    pub filter: PresetFilter,
+}
+
+/// Saving a preset with the name of an existing one on the same page replaces it
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewFilterPreset {
+    pub name: String,
+    pub page: PresetPage,
+    pub filter: PresetFilter,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_preset_dates() {
+        let today = NaiveDate::from_ymd(2021, 8, 7);
+        let day = |m, d| Some(NaiveDate::from_ymd(2021, m, d));
+
+        assert_eq!(
+            PresetDates::Current(PeriodKind::Month).range(today),
+            (day(8, 1), day(8, 31))
+        );
+        assert_eq!(
+            PresetDates::Previous(PeriodKind::Month).range(today),
+            (day(7, 1), day(7, 31))
+        );
+        assert_eq!(
+            PresetDates::Previous(PeriodKind::Semester).range(today),
+            (day(1, 1), day(6, 30))
+        );
+
+        let fixed = PresetDates::Fixed {
+            from: day(6, 1),
+            to: None,
+        };
+        assert_eq!(fixed.period(today), None);
+        assert_eq!(fixed.range(today), (day(6, 1), None));
+        assert_eq!(PresetDates::default().range(today), (None, None));
+
+        assert_eq!(
+            PresetDates::from_range(day(7, 1), day(7, 31), today),
+            PresetDates::Previous(PeriodKind::Month)
+        );
+        assert_eq!(
+            PresetDates::from_range(day(8, 7), day(8, 7), today),
+            PresetDates::Current(PeriodKind::Day)
+        );
+        assert_eq!(
+            PresetDates::from_range(day(7, 1), day(7, 30), today),
+            PresetDates::Fixed {
+                from: day(7, 1),
+                to: day(7, 30)
+            }
+        );
+    }
+}
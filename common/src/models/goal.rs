@@ -0,0 +1,217 @@
+use crate::currency::Currency;
+use crate::models::book_account::BookAccountId;
+use crate::models::transaction::Transaction;
+use crate::models::user::UserName;
+use crate::time::{local_date, ReportingPeriod, Tz};
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type SalesGoalId = i32;
+
+/// What a goal counts, and how much of it to reach
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum GoalTarget {
+    /// What is credited to the sales account, less refunds
+    Revenue(Currency),
+
+    /// The number of items sold, less those returned
+    Quantity(u32),
+}
+
+impl GoalTarget {
+    /// The target in its unit, öre for revenue and items for quantity
+    pub fn value(&self) -> i64 {
+        match *self {
+            GoalTarget::Revenue(amount) => amount.into(),
+            GoalTarget::Quantity(count) => count.into(),
+        }
+    }
+}
+
+/// Which sales count towards a goal
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum GoalScope {
+    /// The sales during a period, e.g. a day or a semester
+    Period(ReportingPeriod),
+
+    /// The sales tagged with an event code, e.g. of a pub night
+    Event(String),
+}
+
+/// A goal to set, see [SalesGoal]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewSalesGoal {
+    pub name: String,
+    pub target: GoalTarget,
+    pub scope: GoalScope,
+}
+
+/// A revenue or quantity to reach during a period or an event, set by the admins
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct SalesGoal {
+    pub id: SalesGoalId,
+    pub name: String,
+    pub target: GoalTarget,
+    pub scope: GoalScope,
+    pub created_by: Option<UserName>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// How far a goal has come
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct SalesGoalProgress {
+    pub goal: SalesGoal,
+
+    /// In the unit of the target, see [GoalTarget::value]
+    pub achieved: i64,
+}
+
+impl SalesGoalProgress {
+    /// Sum up the sales of `transactions` which count towards `goal`, with the periods in the
+    /// timezone `tz`.
+    ///
+    /// Sales are the transactions which credit `sales_account`, and refunds those which debit it.
+    pub fn compute(
+        goal: SalesGoal,
+        transactions: &[Transaction],
+        sales_account: BookAccountId,
+        tz: Tz,
+    ) -> Self {
+        let achieved: i64 = transactions
+            .iter()
+            .filter(|tr| match &goal.scope {
+                GoalScope::Period(period) => period.contains(local_date(tr.time, tz)),
+                GoalScope::Event(code) => tr.event_code.as_ref() == Some(code),
+            })
+            .map(|tr| match goal.target {
+                GoalTarget::Revenue(_) if tr.credited_account == sales_account => tr.amount.into(),
+                GoalTarget::Revenue(_) if tr.debited_account == sales_account => {
+                    -i64::from(tr.amount)
+                }
+                GoalTarget::Quantity(_)
+                    if tr.credited_account == sales_account
+                        || tr.debited_account == sales_account =>
+                {
+                    tr.bundles
+                        .iter()
+                        .map(|bundle| {
+                            let per_bundle: u32 = bundle.item_ids.values().sum();
+                            -i64::from(bundle.change) * i64::from(per_bundle)
+                        })
+                        .sum()
+                }
+                _ => 0,
+            })
+            .sum();
+
+        SalesGoalProgress { goal, achieved }
+    }
+
+    /// How much of the goal has been reached, above 1 if it has been exceeded
+    pub fn fraction(&self) -> f64 {
+        self.achieved as f64 / self.goal.target.value().max(1) as f64
+    }
+
+    pub fn is_reached(&self) -> bool {
+        self.achieved >= self.goal.target.value()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::time::{PeriodKind, DEFAULT_REPORTING_TIMEZONE};
+    use crate::transaction::TransactionBundle;
+    use chrono::{NaiveDate, TimeZone};
+
+    #[test]
+    fn test_compute() {
+        let (cash, sales) = (1, 2);
+        let transaction =
+            |id, day: u32, debited_account, credited_account, amount: i32| Transaction {
+                id,
+                description: None,
+                time: Utc.ymd(2021, 7, day).and_hms(20, 0, 0),
+                bundles: vec![TransactionBundle {
+                    description: None,
+                    price: None,
+                    change: if credited_account == sales { -2 } else { 1 },
+                    item_ids: vec![(1, 1)].into_iter().collect(),
+                    expires_on: None,
+                    modifier_ids: vec![],
+                }],
+                debited_account,
+                credited_account,
+                amount: amount.into(),
+                note: None,
+                tags: vec![],
+                event_code: Some("pubrunda".to_string()),
+                age_verified: false,
+                member_id: None,
+                created_by: None,
+            };
+        let transactions = vec![
+            transaction(1, 19, cash, sales, 1000),
+            transaction(2, 19, cash, sales, 500),
+            // a refund
+            transaction(3, 19, sales, cash, 250),
+            transaction(4, 20, cash, sales, 1000),
+        ];
+
+        let goal = |target, scope| SalesGoal {
+            id: 1,
+            name: "Pubrunda".to_string(),
+            target,
+            scope,
+            created_by: None,
+            created_at: Utc.ymd(2021, 7, 1).and_hms(0, 0, 0),
+        };
+        let day = GoalScope::Period(ReportingPeriod::containing(
+            PeriodKind::Day,
+            NaiveDate::from_ymd(2021, 7, 19),
+        ));
+        let tz = DEFAULT_REPORTING_TIMEZONE;
+
+        let revenue = SalesGoalProgress::compute(
+            goal(GoalTarget::Revenue(1000.into()), day.clone()),
+            &transactions,
+            sales,
+            tz,
+        );
+        assert_eq!(revenue.achieved, 1250);
+        assert!(revenue.is_reached());
+        assert_eq!(revenue.fraction(), 1.25);
+
+        let quantity = SalesGoalProgress::compute(
+            goal(GoalTarget::Quantity(10), day),
+            &transactions,
+            sales,
+            tz,
+        );
+        assert_eq!(quantity.achieved, 3);
+        assert!(!quantity.is_reached());
+
+        let event = SalesGoalProgress::compute(
+            goal(
+                GoalTarget::Quantity(6),
+                GoalScope::Event("pubrunda".to_string()),
+            ),
+            &transactions,
+            sales,
+            tz,
+        );
+        assert_eq!(event.achieved, 5);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::currency::Currency;
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type ImportBatchId = i32;
+
+/// A problem with a line of an imported file
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ImportError {
+    /// The line of the file, the header being line 1
+    pub line: u64,
+    pub message: String,
+}
+
+/// What importing a file of historical transactions did, or would do in a dry run
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// The batch which the transactions were imported as, `None` in a dry run or if nothing was
+    /// imported since the file has errors
+    pub batch_id: Option<ImportBatchId>,
+
+    pub sales: u32,
+    pub sales_total: Currency,
+    pub deposits: u32,
+    pub deposits_total: Currency,
+
+    /// The dates of the first and the last transaction in the file
+    pub first_date: Option<NaiveDate>,
+    pub last_date: Option<NaiveDate>,
+
+    /// Nothing is imported if there are any errors
+    pub errors: Vec<ImportError>,
+}
+
+/// A file of historical transactions which has been imported
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ImportBatch {
+    pub id: ImportBatchId,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    pub transaction_count: u32,
+
+    /// When the transactions of the batch were removed again
+    pub rolled_back_at: Option<DateTime<Utc>>,
+}
@@ -1,4 +1,6 @@
 use crate::currency::Currency;
+use crate::time::PeriodKind;
+use chrono::{DateTime, NaiveDate, Utc};
 
 #[cfg(feature = "diesel_impl")]
 use diesel_derives::Queryable;
@@ -11,6 +13,7 @@ use std::hash::{Hash, Hasher};
 
 pub type InventoryItemId = i32;
 pub type InventoryBundleId = i32;
+pub type ItemModifierId = i32;
 
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "debug", derive(Debug))]
@@ -48,6 +51,41 @@ pub struct InventoryItemStock {
     pub price: Option<i32>,
     pub image_url: Option<String>,
     pub stock: i32,
+
+    /// When the item was archived. Archived items aren't sold anymore, but are kept for the
+    /// history.
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub archived_at: Option<DateTime<Utc>>,
+
+    /// Whether the ID of the buyer must be checked before the item is sold, e.g. strong beer
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub age_restricted: bool,
+
+    /// Whether the item is prepared after payment, e.g. food, and so shown on the kitchen board
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub prepared: bool,
+
+    /// Warn when this many or fewer are left for sale, see
+    /// [low_stock_alerts](crate::location::low_stock_alerts)
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub low_stock_at: Option<i32>,
+
+    /// How many of the stock are set aside for upcoming events, see
+    /// [EventSignup](crate::event::EventSignup)
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub reserved: i32,
+}
+
+impl InventoryItemStock {
+    /// Whether the item is shown in the store
+    pub fn is_for_sale(&self) -> bool {
+        self.price.is_some() && self.archived_at.is_none()
+    }
+
+    /// How many can be sold, the stock less what's reserved
+    pub fn sellable(&self) -> i32 {
+        self.stock - self.reserved
+    }
 }
 
 impl PartialEq for InventoryItemStock {
@@ -92,3 +130,246 @@ impl PartialEq for InventoryBundle {
 }
 
 impl Eq for InventoryBundle {}
+
+/// A variant or add-on of an item, e.g. "stor" or "med mjölk", which changes its price
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ItemModifier {
+    pub id: ItemModifierId,
+    pub item_id: InventoryItemId,
+
+    /// Modifiers in the same group are alternatives, of which at most one is chosen, e.g. the
+    /// sizes. Modifiers without a group are chosen independently.
+    pub group: Option<String>,
+
+    pub name: String,
+
+    /// Added to the price of the item, may be negative
+    pub price_delta: Currency,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+/// The description of a cart line selling `item_name` with `modifiers`, e.g. "Kaffe (stor, mjölk)"
+pub fn describe_modified(item_name: &str, modifiers: &[&ItemModifier]) -> String {
+    if modifiers.is_empty() {
+        return item_name.to_string();
+    }
+    let names: Vec<&str> = modifiers.iter().map(|m| m.name.as_str()).collect();
+    format!("{} ({})", item_name, names.join(", "))
+}
+
+/// Whether `modifiers` may be chosen together, i.e. whether no two of them are alternatives in
+/// the same group of the same item
+pub fn compatible_modifiers(modifiers: &[&ItemModifier]) -> bool {
+    modifiers.iter().enumerate().all(|(i, a)| {
+        modifiers[i + 1..].iter().all(|b| {
+            a.id != b.id && (a.item_id != b.item_id || a.group.is_none() || a.group != b.group)
+        })
+    })
+}
+
+/// An item which a sale would take below zero in stock
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct StockShortage {
+    pub item_id: InventoryItemId,
+    pub name: String,
+
+    /// What could be sold before the sale, the stock less what's reserved for upcoming events
+    pub in_stock: i32,
+
+    /// How many of the item the sale contains
+    pub requested: i32,
+}
+
+/// At most `quantity` of an item may be sold to a member in every `per`, e.g. 2 per day of a
+/// subsidized item. Selling more needs the approval of an admin, see
+/// [GatedAction::PurchaseLimit](crate::approval::GatedAction::PurchaseLimit).
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PurchaseLimit {
+    pub quantity: u32,
+
+    /// The limit starts over at the start of every period, in the reporting timezone
+    pub per: PeriodKind,
+}
+
+/// Items from one restock which are still in stock and will soon expire
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ExpiringBatch {
+    pub item_id: InventoryItemId,
+    pub name: String,
+    pub expires_on: NaiveDate,
+
+    /// How many of the items from the batch are left
+    pub count: i32,
+}
+
+/// Which of the restocked batches of an item are still in stock.
+///
+/// `batches` are every restock of the item with its size, oldest first, labeled with e.g. the
+/// expiry date or the cost of the batch. Items are assumed to be sold in the order they were
+/// stocked, so the `stock` remaining items are taken from the newest batches. The remaining
+/// batches are returned oldest first.
+pub fn remaining_batches<T: Copy>(stock: i32, batches: &[(T, i32)]) -> Vec<(T, i32)> {
+    let mut left = stock;
+    let mut remaining = vec![];
+    for &(label, size) in batches.iter().rev() {
+        if left <= 0 {
+            break;
+        }
+        let count = size.min(left);
+        left -= count;
+        remaining.push((label, count));
+    }
+    remaining.reverse();
+    remaining
+}
+
+/// How to change the price of the items in a [InventoryBatchEdit]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PriceAdjustment {
+    /// Add to the price, or subtract if negative
+    Amount(Currency),
+
+    /// Raise the price by a percentage, or lower it if negative
+    Percent(i32),
+}
+
+impl PriceAdjustment {
+    /// The adjusted price, rounded to whole öre, or `None` if it would be negative
+    pub fn apply(self, price: Currency) -> Option<Currency> {
+        let price: i64 = price.into();
+        let adjusted = match self {
+            PriceAdjustment::Amount(amount) => price + i64::from(amount),
+            PriceAdjustment::Percent(percent) => {
+                (price * (100 + i64::from(percent)) + 50).div_euclid(100)
+            }
+        };
+        (adjusted >= 0).then(|| adjusted.into())
+    }
+}
+
+/// Changes to apply to several items at once, all or none of them
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct InventoryBatchEdit {
+    pub item_ids: Vec<InventoryItemId>,
+
+    /// Replace the tags of the items with this one, or remove them with an empty string
+    pub category: Option<String>,
+
+    /// Items without a price are left without one
+    pub price: Option<PriceAdjustment>,
+
+    /// Archive the items, which hides them from the store
+    pub archive: bool,
+}
+
+impl InventoryBatchEdit {
+    /// Whether the edit would change anything
+    pub fn is_empty(&self) -> bool {
+        self.item_ids.is_empty()
+            || (self.category.is_none() && self.price.is_none() && !self.archive)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn modifier(id: ItemModifierId, group: Option<&str>, name: &str) -> ItemModifier {
+        ItemModifier {
+            id,
+            item_id: 1,
+            group: group.map(|group| group.to_string()),
+            name: name.to_string(),
+            price_delta: Currency::default(),
+            archived_at: None,
+        }
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let small = modifier(1, Some("Storlek"), "liten");
+        let large = modifier(2, Some("Storlek"), "stor");
+        let milk = modifier(3, None, "mjölk");
+        let oat_milk = modifier(4, None, "havremjölk");
+
+        assert!(compatible_modifiers(&[&large, &milk, &oat_milk]));
+        assert!(!compatible_modifiers(&[&small, &milk, &large]));
+        assert!(!compatible_modifiers(&[&milk, &milk]));
+
+        // the groups of different items are unrelated
+        let other_item = ItemModifier {
+            item_id: 2,
+            ..modifier(5, Some("Storlek"), "stor")
+        };
+        assert!(compatible_modifiers(&[&small, &other_item]));
+
+        assert_eq!(
+            describe_modified("Kaffe", &[&large, &milk]),
+            "Kaffe (stor, mjölk)"
+        );
+        assert_eq!(describe_modified("Kaffe", &[]), "Kaffe");
+    }
+
+    #[test]
+    fn test_remaining_batches() {
+        let june = NaiveDate::from_ymd(2021, 6, 1);
+        let july = NaiveDate::from_ymd(2021, 7, 1);
+        let batches = vec![(Some(june), 24), (None, 6), (Some(july), 24)];
+
+        assert_eq!(remaining_batches(0, &batches), vec![]);
+        assert_eq!(remaining_batches(10, &batches), vec![(Some(july), 10)]);
+        assert_eq!(
+            remaining_batches(32, &batches),
+            vec![(Some(june), 2), (None, 6), (Some(july), 24)]
+        );
+        // more in stock than was ever restocked, e.g. because of manual corrections
+        assert_eq!(remaining_batches(60, &batches), batches);
+    }
+
+    #[test]
+    fn test_price_adjustment() {
+        let price = Currency::from(1250);
+
+        assert_eq!(
+            PriceAdjustment::Amount(Currency::from(250)).apply(price),
+            Some(Currency::from(1500))
+        );
+        assert_eq!(
+            PriceAdjustment::Amount(Currency::from(-1250)).apply(price),
+            Some(Currency::from(0))
+        );
+        assert_eq!(
+            PriceAdjustment::Amount(Currency::from(-1300)).apply(price),
+            None
+        );
+
+        assert_eq!(
+            PriceAdjustment::Percent(10).apply(price),
+            Some(Currency::from(1375))
+        );
+        // rounded to whole öre
+        assert_eq!(
+            PriceAdjustment::Percent(-15).apply(price),
+            Some(Currency::from(1063))
+        );
+        assert_eq!(
+            PriceAdjustment::Percent(-100).apply(price),
+            Some(Currency::from(0))
+        );
+        assert_eq!(PriceAdjustment::Percent(-101).apply(price), None);
+    }
+}
@@ -1,10 +1,19 @@
-use crate::transaction::TransactionId;
+use crate::book_account::BookAccountId;
+use crate::currency::{Currency, NonNegativeCurrency};
+use crate::member::MemberId;
+use crate::transaction::{TransactionBundle, TransactionId};
+use crate::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derive_enum::DbEnum;
 
 #[cfg(feature = "serde_impl")]
 use serde::{Deserialize, Serialize};
 
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq)]
 pub enum IZettlePayment {
     /// The transaction has been paid
     Paid {
@@ -24,3 +33,89 @@ pub enum IZettlePayment {
     /// No pending payment exists for the given ID
     NoTransaction,
 }
+
+/// How a card payment is getting on, pushed to the store page while it waits for it
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq)]
+pub struct IZettlePaymentStatus {
+    pub payment: IZettlePayment,
+
+    /// Whether the bridge has been heard from recently
+    pub bridge_online: bool,
+
+    /// Whether the payment is on the card reader, rather than waiting for payments which were
+    /// started before it
+    pub at_reader: bool,
+}
+
+/// A tillgodo deposit which is to be paid by card
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone)]
+pub struct IZettleDeposit {
+    /// The tillgodo account of the member
+    pub credited_account: BookAccountId,
+    pub amount: NonNegativeCurrency,
+}
+
+pub type FailedPaymentId = i32;
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(DbEnum))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FailedPaymentStatus {
+    /// Nobody has looked at it yet
+    Unresolved,
+
+    /// Sent to the bridge again, as a new payment
+    Retried,
+
+    /// Given up on, nothing was booked
+    WrittenOff,
+
+    /// The bridge reported it as paid after it had timed out, and it was booked then
+    PaidLate,
+}
+
+/// A card payment which the bridge reported as failed, or which got no result in time.
+///
+/// They are kept until an admin retries them or writes them off, so that the card payments of the
+/// day can be reconciled with those in iZettle.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq)]
+pub struct FailedPayment {
+    pub id: FailedPaymentId,
+
+    /// The reference which the bridge and the store page knew the payment by
+    pub reference: i32,
+
+    /// Why it failed, as reported by the bridge
+    pub reason: String,
+
+    /// Whether it failed because the bridge never reported a result
+    pub timed_out: bool,
+
+    /// When the payment was started
+    pub time: DateTime<Utc>,
+    pub failed_at: DateTime<Utc>,
+
+    pub description: Option<String>,
+    pub debited_account: BookAccountId,
+    pub credited_account: BookAccountId,
+    pub amount: Currency,
+    pub bundles: Vec<TransactionBundle>,
+    pub created_by: Option<UserName>,
+    pub member_id: Option<MemberId>,
+
+    pub status: FailedPaymentStatus,
+
+    /// Who retried it or wrote it off
+    pub resolved_by: Option<UserName>,
+    pub resolved_at: Option<DateTime<Utc>>,
+
+    /// The reference of the new payment, if it was retried
+    pub retry_reference: Option<i32>,
+}
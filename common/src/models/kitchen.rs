@@ -0,0 +1,134 @@
+use crate::models::inventory::{describe_modified, InventoryItemId, ItemModifier, ItemModifierId};
+use crate::models::transaction::{Transaction, TransactionId};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// Some of an item to prepare, e.g. "2 × Burgare (ost)"
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct KitchenLine {
+    /// The name of the item, with the modifiers which were chosen for it
+    pub name: String,
+    pub quantity: u32,
+}
+
+/// A paid order with items which are prepared after payment, shown on the kitchen board until
+/// it's marked as done
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct KitchenOrder {
+    pub transaction_id: TransactionId,
+    pub time: DateTime<Utc>,
+
+    /// The number called on the queue page, if queue tickets are enabled
+    pub queue_number: Option<i32>,
+
+    /// Only the items to prepare, the rest of the sale is handed over at the counter
+    pub lines: Vec<KitchenLine>,
+}
+
+impl KitchenOrder {
+    /// The lines of `transaction` to prepare, where `prepared` are the names of the items which
+    /// are prepared after payment. Equal lines are added together, in the order they were sold.
+    pub fn lines(
+        transaction: &Transaction,
+        prepared: &HashMap<InventoryItemId, String>,
+        modifiers: &HashMap<ItemModifierId, ItemModifier>,
+    ) -> Vec<KitchenLine> {
+        let mut lines: Vec<KitchenLine> = vec![];
+        for bundle in transaction.bundles.iter().filter(|b| b.change < 0) {
+            let mut item_ids: Vec<_> = bundle.item_ids.iter().collect();
+            item_ids.sort_unstable();
+
+            for (item_id, &count) in item_ids {
+                let item_name = match prepared.get(item_id) {
+                    Some(item_name) => item_name,
+                    None => continue,
+                };
+                let chosen: Vec<&ItemModifier> = bundle
+                    .modifier_ids
+                    .iter()
+                    .filter_map(|id| modifiers.get(id))
+                    .filter(|modifier| modifier.item_id == *item_id)
+                    .collect();
+
+                let name = describe_modified(item_name, &chosen);
+                let quantity = bundle.change.unsigned_abs() * count;
+                match lines.iter_mut().find(|line| line.name == name) {
+                    Some(line) => line.quantity += quantity,
+                    None => lines.push(KitchenLine { name, quantity }),
+                }
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::transaction::TransactionBundle;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_lines() {
+        let (burgare, cola) = (1, 2);
+        let ost = ItemModifier {
+            id: 1,
+            item_id: burgare,
+            group: None,
+            name: "ost".to_string(),
+            price_delta: 500.into(),
+            archived_at: None,
+        };
+        let bundle =
+            |change, item_ids: Vec<(InventoryItemId, u32)>, modifier_ids| TransactionBundle {
+                description: None,
+                price: None,
+                change,
+                item_ids: item_ids.into_iter().collect(),
+                expires_on: None,
+                modifier_ids,
+            };
+        let transaction = Transaction {
+            id: 7,
+            description: None,
+            time: Utc.ymd(2021, 7, 1).and_hms(18, 0, 0),
+            bundles: vec![
+                bundle(-2, vec![(burgare, 1), (cola, 1)], vec![]),
+                bundle(-1, vec![(burgare, 1)], vec![ost.id]),
+                bundle(-1, vec![(burgare, 1)], vec![]),
+            ],
+            debited_account: 1,
+            credited_account: 2,
+            amount: 10000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        };
+
+        let prepared = vec![(burgare, "Burgare".to_string())].into_iter().collect();
+        let modifiers = vec![(ost.id, ost)].into_iter().collect();
+        assert_eq!(
+            KitchenOrder::lines(&transaction, &prepared, &modifiers),
+            vec![
+                KitchenLine {
+                    name: "Burgare".to_string(),
+                    quantity: 3,
+                },
+                KitchenLine {
+                    name: "Burgare (ost)".to_string(),
+                    quantity: 1,
+                },
+            ]
+        );
+    }
+}
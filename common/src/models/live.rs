@@ -0,0 +1,61 @@
+use crate::transaction::TransactionId;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// Something which changed on the server, pushed to every logged in client of the organization
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq)]
+pub enum LiveEvent {
+    /// A transaction was made, including returns and card payments
+    TransactionCreated { id: TransactionId },
+
+    /// A transaction was deleted
+    TransactionDeleted { id: TransactionId },
+
+    /// Many transactions changed at once, e.g. by an import, so anything cached is stale
+    TransactionsChanged,
+
+    /// An announcement was made or removed
+    AnnouncementsChanged,
+
+    /// The register was opened, closed or reconciled, or is past closing time
+    RegisterChanged,
+
+    /// A sale was given a number in the queue, see [QueueTicket](crate::queue::QueueTicket)
+    QueueTicketIssued {
+        transaction_id: TransactionId,
+        number: i32,
+    },
+
+    /// An order in the queue was prepared or collected
+    QueueChanged,
+
+    /// An order on the kitchen board was marked as done
+    KitchenChanged,
+
+    /// A Swish deposit was started by a member, or Swish reported how it went
+    SwishPaymentsChanged,
+
+    /// A correction was requested, approved or rejected, see
+    /// [Correction](crate::correction::Correction)
+    CorrectionsChanged,
+
+    /// A card payment failed, or a failed one was retried or written off, see
+    /// [FailedPayment](crate::izettle::FailedPayment)
+    FailedPaymentsChanged,
+}
+
+/// An answer to a long poll for [LiveEvent]s, for clients whose network doesn't let the event
+/// stream through
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq)]
+pub struct LivePoll {
+    /// What to ask for the events after, in the next poll
+    pub cursor: u64,
+
+    /// Oldest first, empty if nothing happened before the poll timed out
+    pub events: Vec<LiveEvent>,
+}
@@ -0,0 +1,161 @@
+use crate::models::inventory::{InventoryItemId, InventoryItemStock};
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type StorageLocationId = i32;
+
+/// Somewhere items are kept apart from those for sale, e.g. the "förråd".
+///
+/// What of the stock of an item isn't kept in any storage location is for sale, see [for_sale].
+/// Sales and restocks change the stock for sale, and items are moved to and from the storage
+/// locations with a [StockTransfer].
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct StorageLocation {
+    pub id: StorageLocationId,
+    pub name: String,
+
+    /// How many of each item are kept here, only those with any
+    pub items: BTreeMap<InventoryItemId, i32>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct NewStorageLocation {
+    pub name: String,
+}
+
+/// Moving some of an item from one place to another, where `None` is the items for sale
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct StockTransfer {
+    pub item_id: InventoryItemId,
+    pub from: Option<StorageLocationId>,
+    pub to: Option<StorageLocationId>,
+    pub amount: i32,
+}
+
+/// An item of which only a few are left for sale, and where more of it is kept
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct LowStockAlert {
+    pub item_id: InventoryItemId,
+    pub name: String,
+    pub for_sale: i32,
+    pub low_stock_at: i32,
+
+    /// How many are kept in each of the storage locations which have any
+    pub stored: BTreeMap<StorageLocationId, i32>,
+}
+
+impl LowStockAlert {
+    /// How many are kept in the storage locations in total
+    pub fn total_stored(&self) -> i32 {
+        self.stored.values().sum()
+    }
+}
+
+/// How many of the stock of `item` are for sale, i.e. not kept in any of `locations`. May be
+/// negative if more has been sold than was thought to be for sale.
+pub fn for_sale(item: &InventoryItemStock, locations: &[StorageLocation]) -> i32 {
+    let stored: i32 = locations
+        .iter()
+        .filter_map(|location| location.items.get(&item.id))
+        .sum();
+    item.stock - stored
+}
+
+/// The items which aren't archived and of which no more than their `low_stock_at` are left for
+/// sale, sorted by name
+pub fn low_stock_alerts<'a>(
+    items: impl IntoIterator<Item = &'a InventoryItemStock>,
+    locations: &[StorageLocation],
+) -> Vec<LowStockAlert> {
+    let mut alerts: Vec<LowStockAlert> = items
+        .into_iter()
+        .filter(|item| item.archived_at.is_none())
+        .filter_map(|item| {
+            let low_stock_at = item.low_stock_at?;
+            let for_sale = for_sale(item, locations);
+            if for_sale > low_stock_at {
+                return None;
+            }
+            Some(LowStockAlert {
+                item_id: item.id,
+                name: item.name.clone(),
+                for_sale,
+                low_stock_at,
+                stored: locations
+                    .iter()
+                    .filter_map(|location| Some((location.id, *location.items.get(&item.id)?)))
+                    .collect(),
+            })
+        })
+        .collect();
+    alerts.sort_by(|a, b| a.name.cmp(&b.name).then(a.item_id.cmp(&b.item_id)));
+    alerts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn item(
+        id: InventoryItemId,
+        name: &str,
+        stock: i32,
+        low_stock_at: Option<i32>,
+    ) -> InventoryItemStock {
+        InventoryItemStock {
+            id,
+            name: name.to_string(),
+            price: Some(500),
+            image_url: None,
+            stock,
+            archived_at: None,
+            age_restricted: false,
+            prepared: false,
+            low_stock_at,
+            reserved: 0,
+        }
+    }
+
+    #[test]
+    fn test_low_stock_alerts() {
+        let locations = vec![
+            StorageLocation {
+                id: 1,
+                name: "Förråd".to_string(),
+                items: vec![(1, 24), (2, 10)].into_iter().collect(),
+            },
+            StorageLocation {
+                id: 2,
+                name: "Källare".to_string(),
+                items: vec![(1, 6)].into_iter().collect(),
+            },
+        ];
+        let items = vec![
+            item(1, "Kaffe", 32, Some(5)),
+            item(2, "Te", 30, Some(5)),
+            item(3, "Kaka", 3, None),
+            item(4, "Bulle", 0, Some(0)),
+        ];
+
+        assert_eq!(for_sale(&items[0], &locations), 2);
+        assert_eq!(for_sale(&items[1], &locations), 20);
+
+        let alerts = low_stock_alerts(&items, &locations);
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].name, "Bulle");
+        assert!(alerts[0].stored.is_empty());
+        assert_eq!(alerts[1].item_id, 1);
+        assert_eq!(alerts[1].for_sale, 2);
+        assert_eq!(alerts[1].total_stored(), 30);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::models::book_account::BookAccount;
+use chrono::{DateTime, Utc};
+
 #[cfg(feature = "diesel_impl")]
 use diesel_derives::Queryable;
 
@@ -15,6 +18,11 @@ pub struct Member {
     pub first_name: String,
     pub last_name: String,
     pub nickname: Option<String>,
+
+    /// When the member was archived, e.g. after leaving. Archived members can't be searched for,
+    /// but are kept for the history.
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub archived_at: Option<DateTime<Utc>>,
 }
 
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
@@ -25,3 +33,105 @@ pub struct NewMember {
     pub last_name: String,
     pub nickname: Option<String>,
 }
+
+/// A member together with their tillgodo account
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct MemberSearchResult {
+    pub member: Member,
+    pub account: BookAccount,
+}
+
+/// What a member can be mailed about, each of which they must opt in to
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MailTopic {
+    /// A receipt of every purchase and deposit on their tillgodo account
+    Receipts,
+
+    /// A reminder about a balance which hasn't been used in a long time
+    BalanceReminders,
+
+    /// News about upcoming events
+    EventInfo,
+}
+
+/// The topics a member has opted in to, nothing by default
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContactPreferences {
+    pub receipts: bool,
+    pub balance_reminders: bool,
+    pub event_info: bool,
+}
+
+impl ContactPreferences {
+    pub fn allows(&self, topic: MailTopic) -> bool {
+        match topic {
+            MailTopic::Receipts => self.receipts,
+            MailTopic::BalanceReminders => self.balance_reminders,
+            MailTopic::EventInfo => self.event_info,
+        }
+    }
+}
+
+/// Where and about what a member may be mailed
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct MemberContact {
+    pub member_id: MemberId,
+    pub email: Option<String>,
+
+    /// When the member confirmed the address with the code mailed to it. Nothing is sent to an
+    /// address before it's confirmed, except for the code.
+    pub email_verified_at: Option<DateTime<Utc>>,
+
+    pub preferences: ContactPreferences,
+}
+
+impl MemberContact {
+    /// The confirmed address to mail about `topic` to, if the member has opted in to it
+    pub fn address_for(&self, topic: MailTopic) -> Option<&str> {
+        match (&self.email, self.email_verified_at) {
+            (Some(email), Some(_)) if self.preferences.allows(topic) => Some(email),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_address_for() {
+        let mut contact = MemberContact {
+            member_id: 1,
+            email: Some("kalle@example.com".to_string()),
+            email_verified_at: None,
+            preferences: ContactPreferences {
+                receipts: true,
+                ..ContactPreferences::default()
+            },
+        };
+        // not confirmed
+        assert_eq!(contact.address_for(MailTopic::Receipts), None);
+
+        contact.email_verified_at = Some(Utc.ymd(2021, 7, 1).and_hms(18, 0, 0));
+        assert_eq!(
+            contact.address_for(MailTopic::Receipts),
+            Some("kalle@example.com")
+        );
+        // not opted in to
+        assert_eq!(contact.address_for(MailTopic::BalanceReminders), None);
+        assert_eq!(contact.address_for(MailTopic::EventInfo), None);
+
+        contact.email = None;
+        assert_eq!(contact.address_for(MailTopic::Receipts), None);
+    }
+}
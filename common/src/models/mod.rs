@@ -1,6 +1,37 @@
+pub mod admin;
+pub mod analytics;
+pub mod announcement;
+pub mod approval;
 pub mod book_account;
+pub mod catalog;
+pub mod checkout;
+pub mod client_error;
+pub mod correction;
 pub mod currency;
+pub mod dashboard;
+pub mod device;
+pub mod error;
+pub mod event;
+pub mod filter_preset;
+pub mod goal;
+pub mod import;
 pub mod inventory;
 pub mod izettle;
+pub mod kitchen;
+pub mod live;
+pub mod location;
 pub mod member;
+pub mod organization;
+pub mod parked_cart;
+pub mod preferences;
+pub mod public;
+pub mod queue;
+pub mod register;
+pub mod report;
+pub mod store_layout;
+pub mod supplier;
+pub mod swish;
+pub mod training;
 pub mod transaction;
+pub mod user;
+pub mod validation;
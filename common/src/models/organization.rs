@@ -0,0 +1,107 @@
+use crate::time::{start_of_day, Tz};
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derives::Queryable;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type OrganizationId = i32;
+
+/// Everything in the system belongs to exactly one organization, e.g. a chapter running its own
+/// register. Users pick which of their organizations to use when logging in.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Organization {
+    pub id: OrganizationId,
+    pub name: String,
+}
+
+/// How an organization presents itself in the header, on reports, exports and mail, so that
+/// every chapter running the system gets its own branding
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Branding {
+    pub name: String,
+
+    /// The organisationsnummer, e.g. "802000-0000"
+    pub org_number: Option<String>,
+
+    /// Printed at the bottom of reports and mail, e.g. an address or a bankgiro number
+    pub footer_text: Option<String>,
+
+    /// When the logo was uploaded, `None` if there is no logo
+    pub logo_updated_at: Option<DateTime<Utc>>,
+}
+
+impl Branding {
+    /// Where the logo is served, changing whenever a new one is uploaded
+    pub fn logo_url(&self) -> Option<String> {
+        self.logo_updated_at
+            .map(|time| format!("/api/organization/logo?v={}", time.timestamp()))
+    }
+
+    /// The parts which are edited as text
+    pub fn settings(&self) -> BrandingSettings {
+        BrandingSettings {
+            name: self.name.clone(),
+            org_number: self.org_number.clone(),
+            footer_text: self.footer_text.clone(),
+        }
+    }
+}
+
+/// The parts of the [Branding] which are edited as text, the logo is uploaded on its own
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct BrandingSettings {
+    pub name: String,
+    pub org_number: Option<String>,
+    pub footer_text: Option<String>,
+}
+
+/// Whether something made at `time` is before the `lock_date` of an organization, in the
+/// timezone `tz`.
+///
+/// The books are closed before the lock date, e.g. for a month which has been reported, so the
+/// transactions made then can't be voided or edited.
+pub fn is_locked(time: DateTime<Utc>, lock_date: Option<NaiveDate>, tz: Tz) -> bool {
+    lock_date
+        .map(|lock_date| time < start_of_day(lock_date, tz))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::time::DEFAULT_REPORTING_TIMEZONE;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_is_locked() {
+        let tz = DEFAULT_REPORTING_TIMEZONE;
+        let lock_date = Some(NaiveDate::from_ymd(2021, 7, 1));
+
+        // 21:59 UTC is still June 30th in Stockholm, 23:30 UTC is already July 1st
+        assert!(is_locked(
+            Utc.ymd(2021, 6, 30).and_hms(21, 59, 0),
+            lock_date,
+            tz
+        ));
+        assert!(!is_locked(
+            Utc.ymd(2021, 6, 30).and_hms(23, 30, 0),
+            lock_date,
+            tz
+        ));
+        assert!(!is_locked(
+            Utc.ymd(2021, 6, 30).and_hms(21, 59, 0),
+            None,
+            tz
+        ));
+    }
+}
@@ -0,0 +1,29 @@
+use crate::models::book_account::BookAccountId;
+use crate::models::transaction::TransactionBundle;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type ParkedCartId = i32;
+
+/// A cart which has been put on hold, to be resumed later
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ParkedCart {
+    pub id: ParkedCartId,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub debited_account: Option<BookAccountId>,
+    pub bundles: Vec<TransactionBundle>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewParkedCart {
+    pub name: String,
+    pub debited_account: Option<BookAccountId>,
+    pub bundles: Vec<TransactionBundle>,
+}
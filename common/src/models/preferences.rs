@@ -0,0 +1,174 @@
+use crate::models::currency::{Currency, NonNegativeCurrency};
+use crate::models::store_layout::QuickButtonTarget;
+use std::convert::TryFrom;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// Settings which follow a user between devices.
+///
+/// Every field has a default, so preferences stored by an older version can still be loaded.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(default))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct UserPreferences {
+    pub default_page: DefaultPage,
+    pub theme: Theme,
+    pub language: Language,
+    pub quick_buttons: QuickButtonsLayout,
+    pub notifications: NotificationSettings,
+
+    /// Items pinned to the favorites row of the store page
+    pub pinned: Vec<QuickButtonTarget>,
+
+    /// Amounts which can be picked with a single click on the deposit page
+    pub deposit_presets: Vec<NonNegativeCurrency>,
+
+    pub achievements: AchievementProgress,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        UserPreferences {
+            default_page: Default::default(),
+            theme: Default::default(),
+            language: Default::default(),
+            quick_buttons: Default::default(),
+            notifications: Default::default(),
+            pinned: vec![],
+            deposit_presets: [50, 100, 200]
+                .iter()
+                .filter_map(|&kr| NonNegativeCurrency::try_from(Currency::from(kr * 100)).ok())
+                .collect(),
+            achievements: Default::default(),
+        }
+    }
+}
+
+/// The page which is opened when visiting the site root
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefaultPage {
+    #[default]
+    Store,
+    Deposit,
+    Transactions,
+    Analytics,
+
+    /// Only for admins, who land on it unless they have saved something else
+    Dashboard,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    Light,
+    Dark,
+    /// Follow the theme of the operating system
+    #[default]
+    System,
+}
+
+/// The language of the user interface
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    Swedish,
+    English,
+}
+
+/// How items are presented on the store page
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuickButtonsLayout {
+    /// Large buttons with images
+    #[default]
+    Grid,
+    /// Compact rows without images
+    List,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(default))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NotificationSettings {
+    /// Whether to show popup notifications at all
+    pub enabled: bool,
+
+    /// Multiplier (in percent) for how long notifications stay on screen
+    pub duration_percent: u32,
+
+    /// Whether error notifications stay on screen until they are dismissed
+    pub sticky_errors: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            enabled: true,
+            duration_percent: 100,
+            sticky_errors: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    /// Scale a notification duration according to the user's settings
+    pub fn scale_duration(&self, duration_ms: u32) -> u32 {
+        (duration_ms as u64 * self.duration_percent as u64 / 100) as u32
+    }
+}
+
+/// Easter eggs, celebrated by the penguin when they are unlocked
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    /// Made the first sale of a day
+    FirstSaleOfTheDay,
+
+    /// Sold [COFFEE_ACHIEVEMENT] coffees
+    HundredthCoffee,
+
+    /// Counted the cash register and it matched the expected amount to the öre
+    BalancedCashCount,
+}
+
+/// How many coffees have to be sold for [Achievement::HundredthCoffee]
+pub const COFFEE_ACHIEVEMENT: u32 = 100;
+
+/// What a user has done towards the [Achievement]s
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(default))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct AchievementProgress {
+    /// In the order they were unlocked
+    pub unlocked: Vec<Achievement>,
+
+    /// How many coffees the user has sold
+    pub coffees_sold: u32,
+}
+
+impl AchievementProgress {
+    pub fn has(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Unlock an achievement, returns whether it's new
+    pub fn unlock(&mut self, achievement: Achievement) -> bool {
+        if self.has(achievement) {
+            false
+        } else {
+            self.unlocked.push(achievement);
+            true
+        }
+    }
+}
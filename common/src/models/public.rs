@@ -0,0 +1,66 @@
+use crate::currency::Currency;
+use crate::models::goal::SalesGoalProgress;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// Aggregates which are safe to show to anyone, e.g. on an info screen in the hallway
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicStats {
+    /// The number of items sold so far today
+    pub items_sold_today: i32,
+
+    /// The items in stock with the public tag (e.g. "fika"), sorted by name
+    pub stock: Vec<PublicStock>,
+
+    /// The quantity goals which haven't ended, revenue is not public
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub goals: Vec<SalesGoalProgress>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicStock {
+    pub name: String,
+    pub image_url: Option<String>,
+    pub stock: i32,
+}
+
+/// The tillgodo balance of a member, shown to anyone with the code on their balance card
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicBalance {
+    /// Only the first name, in case the card is lost
+    pub first_name: String,
+    pub balance: Currency,
+
+    /// The latest deposits, newest first
+    pub deposits: Vec<PublicDeposit>,
+
+    /// Whether the member may deposit with Swish, see [SwishDeposit](crate::swish::SwishDeposit)
+    pub swish: bool,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct PublicDeposit {
+    pub time: DateTime<Utc>,
+    pub amount: Currency,
+}
+
+/// Whether the store is open, i.e. whether the register is, e.g. for lighting a sign by the door
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PublicOpenStatus {
+    pub open: bool,
+
+    /// When the store was opened, or closed if it isn't open. `None` if it has never been opened.
+    pub since: Option<DateTime<Utc>>,
+}
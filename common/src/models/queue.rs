@@ -0,0 +1,33 @@
+use crate::models::transaction::TransactionId;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derives::Queryable;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type QueueTicketId = i32;
+
+/// The number handed out with a sale when the order is prepared after payment, e.g. food.
+///
+/// The numbers start over at 1 every day, in the reporting timezone.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct QueueTicket {
+    pub id: QueueTicketId,
+    pub number: i32,
+    pub transaction_id: TransactionId,
+    pub created_at: DateTime<Utc>,
+
+    /// When the order was prepared, and the number called on the customer display
+    pub ready_at: Option<DateTime<Utc>>,
+}
+
+impl QueueTicket {
+    pub fn is_ready(&self) -> bool {
+        self.ready_at.is_some()
+    }
+}
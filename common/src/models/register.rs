@@ -0,0 +1,172 @@
+use crate::models::user::UserName;
+use crate::time::Tz;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derives::Queryable;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type RegisterShiftId = i32;
+
+/// The time between opening the register and closing it at the end of the day
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct RegisterShift {
+    pub id: RegisterShiftId,
+    pub opened_by: Option<UserName>,
+    pub opened_at: DateTime<Utc>,
+    pub closed_by: Option<UserName>,
+    pub closed_at: Option<DateTime<Utc>>,
+
+    /// Whether the shift was closed by the server since nobody did so by closing time. The cash
+    /// hasn't been counted then, which someone has to do the next day.
+    pub auto_closed: bool,
+
+    pub reconciled_by: Option<UserName>,
+    pub reconciled_at: Option<DateTime<Utc>>,
+
+    /// The names of the registered devices the shift was opened and closed on, see
+    /// [Device](crate::device::Device)
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub opened_on: Option<String>,
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub closed_on: Option<String>,
+}
+
+impl RegisterShift {
+    pub fn is_open(&self) -> bool {
+        self.closed_at.is_none()
+    }
+
+    /// Whether the shift was closed automatically and nobody has counted the cash since
+    pub fn needs_reconciliation(&self) -> bool {
+        self.auto_closed && self.reconciled_at.is_none()
+    }
+}
+
+/// Whether the register is open, and what is left to do about it
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct RegisterStatus {
+    pub open_shift: Option<RegisterShift>,
+
+    /// When the open shift should be closed, if a closing time is configured
+    pub closes_at: Option<DateTime<Utc>>,
+
+    /// The shifts which were closed automatically and haven't been reconciled, oldest first
+    pub unreconciled: Vec<RegisterShift>,
+}
+
+impl RegisterStatus {
+    /// Whether the register is still open past closing time at `now`
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.open_shift.is_some() && self.closes_at.is_some_and(|closes_at| now >= closes_at)
+    }
+}
+
+/// The first time the register should be closed after it was opened at `opened_at`, with the
+/// closing time `closing_time` in the timezone `tz`.
+///
+/// A closing time after midnight, e.g. 02:00, thus belongs to the night the register was opened.
+pub fn closing_deadline(
+    opened_at: DateTime<Utc>,
+    closing_time: NaiveTime,
+    tz: Tz,
+) -> DateTime<Utc> {
+    let opened_on = opened_at.with_timezone(&tz).date().naive_local();
+    let deadline = local_time(opened_on, closing_time, tz);
+    if deadline > opened_at {
+        deadline
+    } else {
+        local_time(opened_on + Duration::days(1), closing_time, tz)
+    }
+}
+
+/// The instant of `time` on `date` in the timezone `tz`, or the first existing hour after it if
+/// it falls in a DST gap
+fn local_time(date: NaiveDate, time: NaiveTime, tz: Tz) -> DateTime<Utc> {
+    let mut local = date.and_time(time);
+    loop {
+        if let Some(local) = tz.from_local_datetime(&local).earliest() {
+            return local.with_timezone(&Utc);
+        }
+        local += Duration::hours(1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::time::DEFAULT_REPORTING_TIMEZONE;
+
+    #[test]
+    fn test_closing_deadline() {
+        let tz = DEFAULT_REPORTING_TIMEZONE;
+        let two_am = NaiveTime::from_hms(2, 0, 0);
+        let ten_pm = NaiveTime::from_hms(22, 0, 0);
+
+        // 20:00 and 01:00 in Stockholm, which is UTC+2 in the summer
+        let evening = Utc.ymd(2021, 7, 1).and_hms(18, 0, 0);
+        let night = Utc.ymd(2021, 7, 1).and_hms(23, 0, 0);
+
+        assert_eq!(
+            closing_deadline(evening, two_am, tz),
+            Utc.ymd(2021, 7, 2).and_hms(0, 0, 0)
+        );
+        assert_eq!(
+            closing_deadline(night, two_am, tz),
+            Utc.ymd(2021, 7, 2).and_hms(0, 0, 0)
+        );
+        assert_eq!(
+            closing_deadline(evening, ten_pm, tz),
+            Utc.ymd(2021, 7, 1).and_hms(20, 0, 0)
+        );
+        // opened after closing time, so it's the next evening
+        assert_eq!(
+            closing_deadline(night, ten_pm, tz),
+            Utc.ymd(2021, 7, 2).and_hms(20, 0, 0)
+        );
+        // 02:30 doesn't exist on the night to summer time
+        assert_eq!(
+            closing_deadline(
+                Utc.ymd(2021, 3, 27).and_hms(18, 0, 0),
+                NaiveTime::from_hms(2, 30, 0),
+                tz
+            ),
+            Utc.ymd(2021, 3, 28).and_hms(1, 30, 0)
+        );
+    }
+
+    #[test]
+    fn test_is_overdue() {
+        let opened_at = Utc.ymd(2021, 7, 1).and_hms(18, 0, 0);
+        let closes_at = Utc.ymd(2021, 7, 2).and_hms(0, 0, 0);
+        let mut status = RegisterStatus {
+            open_shift: Some(RegisterShift {
+                id: 1,
+                opened_by: Some("tester".to_string()),
+                opened_at,
+                closed_by: None,
+                closed_at: None,
+                auto_closed: false,
+                reconciled_by: None,
+                reconciled_at: None,
+                opened_on: None,
+                closed_on: None,
+            }),
+            closes_at: Some(closes_at),
+            unreconciled: vec![],
+        };
+
+        assert!(!status.is_overdue(opened_at));
+        assert!(status.is_overdue(closes_at));
+
+        status.closes_at = None;
+        assert!(!status.is_overdue(closes_at));
+    }
+}
@@ -0,0 +1,837 @@
+use crate::currency::Currency;
+use crate::models::book_account::{
+    tillgodo_balances, BookAccount, BookAccountId, BookAccountType, MasterAccounts,
+};
+use crate::models::inventory::{remaining_batches, InventoryItemId};
+use crate::models::member::{Member, MemberId};
+use crate::models::transaction::{Transaction, TransactionId};
+use crate::time::{local_date, PeriodKind, ReportingPeriod};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The end-of-day summary (Z-report) of everything which happened during one day
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DailyReport {
+    pub date: NaiveDate,
+
+    /// Sales, grouped by the account which was debited, i.e. how they were paid
+    pub sales: Vec<AccountTotal>,
+
+    /// Deposits to member accounts, grouped by how they were paid
+    pub deposits: Vec<AccountTotal>,
+
+    /// Tips on card payments, grouped by how they were paid. Not included in the sales.
+    pub tips: Vec<AccountTotal>,
+
+    /// The number of every item which was sold
+    pub items: Vec<ItemSales>,
+
+    /// How much the cash register should have changed, according to the books
+    pub expected_cash: Currency,
+
+    /// The fees which the payment providers are expected to keep of the payments of the day
+    pub fees: Vec<ProviderFees>,
+
+    pub transaction_count: u32,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct AccountTotal {
+    pub account_id: BookAccountId,
+    pub name: String,
+    pub count: u32,
+    pub total: Currency,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ItemSales {
+    pub item_id: InventoryItemId,
+    pub name: String,
+    pub count: i32,
+}
+
+impl DailyReport {
+    /// Summarize the transactions made on `date` in the timezone `tz`.
+    ///
+    /// `transactions` may contain transactions from other days, they are skipped.
+    pub fn compute(
+        date: NaiveDate,
+        tz: Tz,
+        transactions: &[Transaction],
+        accounts: &HashMap<BookAccountId, BookAccount>,
+        masters: &MasterAccounts,
+        item_names: &HashMap<InventoryItemId, String>,
+        fees: &PaymentFees,
+    ) -> Self {
+        let mut sales: BTreeMap<BookAccountId, (u32, Currency)> = BTreeMap::new();
+        let mut deposits: BTreeMap<BookAccountId, (u32, Currency)> = BTreeMap::new();
+        let mut tips: BTreeMap<BookAccountId, (u32, Currency)> = BTreeMap::new();
+        let mut items: BTreeMap<InventoryItemId, i32> = BTreeMap::new();
+        let mut expected_cash = Currency::default();
+        let mut transaction_count = 0;
+
+        let is_member_account = |id| {
+            accounts
+                .get(&id)
+                .map(|acc| acc.account_type == BookAccountType::Liabilities)
+                .unwrap_or(false)
+        };
+
+        for tr in transactions
+            .iter()
+            .filter(|tr| local_date(tr.time, tz) == date)
+        {
+            transaction_count += 1;
+
+            if tr.debited_account == masters.cash_account_id {
+                expected_cash += tr.amount;
+            }
+            if tr.credited_account == masters.cash_account_id {
+                expected_cash -= tr.amount;
+            }
+
+            if tr.credited_account == masters.sales_account_id {
+                let (count, total) = sales.entry(tr.debited_account).or_default();
+                *count += 1;
+                *total += tr.amount;
+
+                for bundle in &tr.bundles {
+                    for (&item_id, &per_bundle) in &bundle.item_ids {
+                        *items.entry(item_id).or_default() -= bundle.change * per_bundle as i32;
+                    }
+                }
+            } else if tr.credited_account == masters.tips_account_id {
+                let (count, total) = tips.entry(tr.debited_account).or_default();
+                *count += 1;
+                *total += tr.amount;
+            } else if is_member_account(tr.credited_account) {
+                let (count, total) = deposits.entry(tr.debited_account).or_default();
+                *count += 1;
+                *total += tr.amount;
+            }
+        }
+
+        let account_totals = |totals: BTreeMap<BookAccountId, (u32, Currency)>| {
+            totals
+                .into_iter()
+                .map(|(account_id, (count, total))| AccountTotal {
+                    account_id,
+                    name: accounts
+                        .get(&account_id)
+                        .map(|acc| acc.name.clone())
+                        .unwrap_or_default(),
+                    count,
+                    total,
+                })
+                .collect()
+        };
+
+        DailyReport {
+            date,
+            sales: account_totals(sales),
+            deposits: account_totals(deposits),
+            tips: account_totals(tips),
+            items: items
+                .into_iter()
+                .filter(|&(_, count)| count != 0)
+                .map(|(item_id, count)| ItemSales {
+                    item_id,
+                    name: item_names.get(&item_id).cloned().unwrap_or_default(),
+                    count,
+                })
+                .collect(),
+            expected_cash,
+            fees: fees.of(transactions
+                .iter()
+                .filter(|tr| local_date(tr.time, tz) == date)),
+            transaction_count,
+        }
+    }
+
+    pub fn total_sales(&self) -> Currency {
+        self.sales.iter().map(|acc| acc.total).sum()
+    }
+
+    pub fn total_deposits(&self) -> Currency {
+        self.deposits.iter().map(|acc| acc.total).sum()
+    }
+
+    pub fn total_tips(&self) -> Currency {
+        self.tips.iter().map(|acc| acc.total).sum()
+    }
+
+    pub fn total_fees(&self) -> Currency {
+        self.fees.iter().map(|fees| fees.fees).sum()
+    }
+}
+
+/// A service which payments go through, and which keeps a fee of each of them
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PaymentProvider {
+    /// Card payments through the iZettle bridge
+    IZettle,
+    Swish,
+}
+
+/// The fee which every payment provider keeps of each payment, in hundredths of a percent.
+/// Providers which aren't listed keep nothing.
+pub type FeeRates = BTreeMap<PaymentProvider, u32>;
+
+/// The payments which went through a provider, and what the providers keep of them
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default)]
+pub struct PaymentFees {
+    /// The provider of every transaction which was paid through one
+    pub providers: HashMap<TransactionId, PaymentProvider>,
+    pub rates: FeeRates,
+}
+
+impl PaymentFees {
+    /// The fee which `provider` is expected to keep of a payment of `amount`.
+    ///
+    /// The providers charge every payment by itself, so the fee is rounded to whole öre here,
+    /// half away from zero, rather than after it's summed.
+    pub fn fee(&self, provider: PaymentProvider, amount: Currency) -> Currency {
+        let rate = i64::from(self.rates.get(&provider).copied().unwrap_or(0));
+        let amount = i64::from(amount);
+        let fee = (amount.abs() * rate + 5000) / 10000;
+        Currency::from(fee * amount.signum())
+    }
+
+    /// The expected fees of those of `transactions` which were paid through a provider.
+    ///
+    /// The transactions of one payment, e.g. a sale and the tip on it, are booked at the same
+    /// time, so those made at the same time through the same provider are charged as one.
+    pub fn of<'a>(
+        &self,
+        transactions: impl IntoIterator<Item = &'a Transaction>,
+    ) -> Vec<ProviderFees> {
+        let mut payments: BTreeMap<(PaymentProvider, DateTime<Utc>), Currency> = BTreeMap::new();
+        for tr in transactions {
+            if let Some(&provider) = self.providers.get(&tr.id) {
+                *payments.entry((provider, tr.time)).or_default() += tr.amount;
+            }
+        }
+
+        let mut fees: BTreeMap<PaymentProvider, ProviderFees> = BTreeMap::new();
+        for ((provider, _), amount) in payments {
+            let totals = fees.entry(provider).or_insert_with(|| ProviderFees {
+                provider,
+                rate: self.rates.get(&provider).copied().unwrap_or(0),
+                count: 0,
+                gross: Currency::default(),
+                fees: Currency::default(),
+            });
+            totals.count += 1;
+            totals.gross += amount;
+            totals.fees += self.fee(provider, amount);
+        }
+        fees.into_values().collect()
+    }
+}
+
+/// The fees which a payment provider is expected to have kept of the payments made through it
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProviderFees {
+    pub provider: PaymentProvider,
+
+    /// The fee of each payment, in hundredths of a percent
+    pub rate: u32,
+
+    /// The number of payments
+    pub count: u32,
+
+    /// The sum of the payments
+    pub gross: Currency,
+
+    /// The sum of the expected fee of every payment
+    pub fees: Currency,
+}
+
+impl ProviderFees {
+    /// What the provider is expected to pay out
+    pub fn net(&self) -> Currency {
+        self.gross - self.fees
+    }
+}
+
+/// The expected fees of the payment providers during one month, for matching them against the
+/// bank statement
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct MonthlyReport {
+    /// The first day of the month
+    pub month: NaiveDate,
+
+    /// The fees of every day which had payments through a provider, in order
+    pub days: Vec<DailyFees>,
+
+    /// The fees of the whole month
+    pub fees: Vec<ProviderFees>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DailyFees {
+    pub date: NaiveDate,
+    pub fees: Vec<ProviderFees>,
+}
+
+impl MonthlyReport {
+    /// Summarize the fees of the month which `month` is in, in the timezone `tz`.
+    ///
+    /// `transactions` may contain transactions from other months, they are skipped.
+    pub fn compute(
+        month: NaiveDate,
+        tz: Tz,
+        transactions: &[Transaction],
+        fees: &PaymentFees,
+    ) -> Self {
+        let period = ReportingPeriod::containing(PeriodKind::Month, month);
+
+        let mut days: BTreeMap<NaiveDate, Vec<&Transaction>> = BTreeMap::new();
+        for tr in transactions {
+            let date = local_date(tr.time, tz);
+            if period.contains(date) {
+                days.entry(date).or_default().push(tr);
+            }
+        }
+
+        MonthlyReport {
+            month: period.first_day(),
+            fees: fees.of(days.values().flatten().copied()),
+            days: days
+                .into_iter()
+                .map(|(date, transactions)| DailyFees {
+                    date,
+                    fees: fees.of(transactions),
+                })
+                .filter(|day| !day.fees.is_empty())
+                .collect(),
+        }
+    }
+
+    pub fn total_fees(&self) -> Currency {
+        self.fees.iter().map(|fees| fees.fees).sum()
+    }
+}
+
+/// How the items in stock are valued
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StockValuation {
+    /// Every item is valued at its cost price
+    CostPrice,
+
+    /// The items in stock are assumed to be the ones which were purchased last, and are valued
+    /// at what those purchases cost. Items which can't be traced to a purchase are valued at
+    /// their cost price.
+    Fifo,
+}
+
+/// The value of everything in stock, for the annual financial statement
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct StockValueReport {
+    pub valuation: StockValuation,
+
+    /// The items which are in stock, by name
+    pub items: Vec<ItemStockValue>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct ItemStockValue {
+    pub item_id: InventoryItemId,
+    pub name: String,
+    pub stock: i32,
+    pub cost_price: Option<Currency>,
+
+    /// The value of the stock, or `None` if what some of the items cost is unknown
+    pub value: Option<Currency>,
+}
+
+impl ItemStockValue {
+    /// Value the `stock` of an item.
+    ///
+    /// `purchases` are the sizes of every purchase of the item with what each item cost, if
+    /// known, oldest first. They are only used by [StockValuation::Fifo].
+    pub fn compute(
+        valuation: StockValuation,
+        item_id: InventoryItemId,
+        name: String,
+        stock: i32,
+        cost_price: Option<Currency>,
+        purchases: &[(Option<Currency>, i32)],
+    ) -> Self {
+        let value_of = |unit_cost: Option<Currency>, count: i32| {
+            unit_cost.map(|cost| Currency::from(i64::from(cost) * i64::from(count)))
+        };
+
+        let value = match valuation {
+            StockValuation::CostPrice => value_of(cost_price, stock),
+            StockValuation::Fifo => {
+                let batches = remaining_batches(stock, purchases);
+                let purchased: i32 = batches.iter().map(|&(_, count)| count).sum();
+                batches
+                    .into_iter()
+                    .chain(std::iter::once((None, stock - purchased)))
+                    .filter(|&(_, count)| count > 0)
+                    .map(|(unit_cost, count)| value_of(unit_cost.or(cost_price), count))
+                    .sum()
+            }
+        };
+
+        ItemStockValue {
+            item_id,
+            name,
+            stock,
+            cost_price,
+            value,
+        }
+    }
+}
+
+impl StockValueReport {
+    /// The value of every item whose value is known
+    pub fn total(&self) -> Currency {
+        self.items.iter().filter_map(|item| item.value).sum()
+    }
+
+    /// Whether the total is missing the value of some items
+    pub fn is_incomplete(&self) -> bool {
+        self.items.iter().any(|item| item.value.is_none())
+    }
+}
+
+/// What was owed to a member through their tillgodo account at the cutoff of an audit
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct MemberLiability {
+    pub member_id: MemberId,
+    pub account_id: BookAccountId,
+    pub name: String,
+    pub balance: Currency,
+
+    /// The last transaction on the account before the cutoff
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+impl MemberLiability {
+    /// The balances of the tillgodo `accounts` of members, as they were at `cutoff`, sorted by
+    /// name.
+    ///
+    /// The balances are computed from `transactions`, those made at or after the cutoff are
+    /// skipped. Every account is listed, including those which are empty.
+    pub fn compute(
+        cutoff: DateTime<Utc>,
+        accounts: &[(Member, BookAccount)],
+        transactions: &[Transaction],
+    ) -> Vec<Self> {
+        let account_ids: Vec<BookAccountId> = accounts.iter().map(|(_, acc)| acc.id).collect();
+        let balances = tillgodo_balances(
+            &account_ids,
+            transactions
+                .iter()
+                .filter(|tr| tr.time < cutoff)
+                .map(|tr| (tr.debited_account, tr.credited_account, tr.amount, tr.time)),
+        );
+
+        let mut liabilities: Vec<_> = accounts
+            .iter()
+            .map(|(member, account)| MemberLiability {
+                member_id: member.id,
+                account_id: account.id,
+                name: format!("{} {}", member.first_name, member.last_name),
+                balance: balances[&account.id].balance,
+                last_activity: balances[&account.id].last_activity,
+            })
+            .collect();
+        liabilities.sort_by(|a, b| a.name.cmp(&b.name).then(a.member_id.cmp(&b.member_id)));
+        liabilities
+    }
+}
+
+/// A tillgodo balance which hasn't been touched for a while
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DormantBalance {
+    pub member_id: MemberId,
+    pub account_id: BookAccountId,
+    pub name: String,
+    pub balance: Currency,
+
+    /// The last transaction on the account
+    pub last_activity: DateTime<Utc>,
+
+    /// When the member was reminded about the balance, if they have been since it was last
+    /// touched
+    pub reminded_at: Option<DateTime<Utc>>,
+
+    /// Whether the balance has been untouched for long enough to be forfeited
+    pub forfeitable: bool,
+}
+
+/// The tillgodo balances which haven't been touched for a while, and those of them which may be
+/// forfeited according to the policy
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DormantBalanceReport {
+    /// How many days a balance has been untouched before it's listed
+    pub dormant_days: u32,
+
+    /// How many days a balance has been untouched before it may be forfeited
+    pub forfeit_days: u32,
+
+    /// The balances which have been untouched the longest first
+    pub balances: Vec<DormantBalance>,
+}
+
+impl DormantBalanceReport {
+    /// Find the dormant balances among the `liabilities` at `now`.
+    ///
+    /// Only positive balances are listed, since there is nothing to forfeit or remind about
+    /// otherwise. `reminded` is when every member was last reminded about their balance.
+    pub fn compute(
+        now: DateTime<Utc>,
+        dormant_days: u32,
+        forfeit_days: u32,
+        liabilities: Vec<MemberLiability>,
+        reminded: &HashMap<MemberId, DateTime<Utc>>,
+    ) -> Self {
+        let dormant_since = now - Duration::days(dormant_days.into());
+        let forfeit_since = now - Duration::days(forfeit_days.into());
+
+        let mut balances: Vec<DormantBalance> = liabilities
+            .into_iter()
+            .filter(|liability| liability.balance > Currency::default())
+            .filter_map(|liability| {
+                let last_activity = liability.last_activity?;
+                if last_activity > dormant_since {
+                    return None;
+                }
+                Some(DormantBalance {
+                    member_id: liability.member_id,
+                    account_id: liability.account_id,
+                    name: liability.name,
+                    balance: liability.balance,
+                    last_activity,
+                    reminded_at: reminded
+                        .get(&liability.member_id)
+                        .copied()
+                        .filter(|&reminded_at| reminded_at > last_activity),
+                    forfeitable: last_activity <= forfeit_since,
+                })
+            })
+            .collect();
+        balances.sort_by_key(|balance| (balance.last_activity, balance.account_id));
+
+        DormantBalanceReport {
+            dormant_days,
+            forfeit_days,
+            balances,
+        }
+    }
+
+    /// The sum of all dormant balances
+    pub fn total(&self) -> Currency {
+        self.balances.iter().map(|b| b.balance).sum()
+    }
+
+    /// The sum of the balances which may be forfeited
+    pub fn total_forfeitable(&self) -> Currency {
+        self.balances
+            .iter()
+            .filter(|b| b.forfeitable)
+            .map(|b| b.balance)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::models::transaction::TransactionBundle;
+    use chrono::{TimeZone, Utc};
+
+    fn account(id: BookAccountId, name: &str, account_type: BookAccountType) -> BookAccount {
+        BookAccount {
+            id,
+            name: name.to_string(),
+            account_type,
+            creditor: None,
+            balance: Currency::default(),
+        }
+    }
+
+    #[test]
+    fn test_daily_report() {
+        let masters = MasterAccounts {
+            bank_account_id: 1,
+            cash_account_id: 2,
+            sales_account_id: 3,
+            purchases_account_id: 4,
+            tips_account_id: 6,
+        };
+        let accounts: HashMap<_, _> = vec![
+            account(1, "Bank", BookAccountType::Assets),
+            account(2, "Cash", BookAccountType::Assets),
+            account(3, "Sales", BookAccountType::Revenue),
+            account(4, "Purchases", BookAccountType::Expenses),
+            account(5, "Member", BookAccountType::Liabilities),
+            account(6, "Tips", BookAccountType::Revenue),
+        ]
+        .into_iter()
+        .map(|acc| (acc.id, acc))
+        .collect();
+        let item_names: HashMap<_, _> = vec![(10, "Cola".to_string())].into_iter().collect();
+
+        let transaction = |id, hour, debited_account, credited_account, amount: i32| Transaction {
+            id,
+            description: None,
+            time: Utc.ymd(2021, 7, 1).and_hms(hour, 0, 0),
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: None,
+                change: -2,
+                item_ids: vec![(10, 1)].into_iter().collect(),
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account,
+            credited_account,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        };
+
+        let transactions = vec![
+            transaction(1, 10, 2, 3, 1000), // cash sale
+            transaction(2, 11, 1, 3, 500),  // card sale
+            transaction(3, 12, 2, 5, 2000), // cash deposit
+            transaction(5, 11, 1, 6, 100),  // tip on the card sale
+            transaction(4, 23, 2, 3, 700),  // the next day in Stockholm
+        ];
+
+        let fees = PaymentFees {
+            providers: vec![(2, PaymentProvider::IZettle), (5, PaymentProvider::IZettle)]
+                .into_iter()
+                .collect(),
+            rates: vec![(PaymentProvider::IZettle, 185)].into_iter().collect(),
+        };
+
+        let date = NaiveDate::from_ymd(2021, 7, 1);
+        let tz = crate::time::DEFAULT_REPORTING_TIMEZONE;
+        let report = DailyReport::compute(
+            date,
+            tz,
+            &transactions,
+            &accounts,
+            &masters,
+            &item_names,
+            &fees,
+        );
+
+        assert_eq!(report.transaction_count, 4);
+        assert_eq!(report.total_sales(), 1500.into());
+        assert_eq!(report.sales.len(), 2);
+        assert_eq!(report.total_deposits(), 2000.into());
+        assert_eq!(report.total_tips(), 100.into());
+        assert_eq!(report.expected_cash, 3000.into());
+        assert_eq!(
+            report.items,
+            vec![ItemSales {
+                item_id: 10,
+                name: "Cola".to_string(),
+                count: 4,
+            }]
+        );
+
+        // 1.85 % of the card payment of 6 kr, including the tip
+        assert_eq!(
+            report.fees,
+            vec![ProviderFees {
+                provider: PaymentProvider::IZettle,
+                rate: 185,
+                count: 1,
+                gross: 600.into(),
+                fees: 11.into(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_payment_fee() {
+        let fees = PaymentFees {
+            providers: HashMap::new(),
+            rates: vec![(PaymentProvider::IZettle, 175)].into_iter().collect(),
+        };
+        let fee = |amount: i64| i64::from(fees.fee(PaymentProvider::IZettle, amount.into()));
+
+        assert_eq!(fee(10000), 175);
+        assert_eq!(fee(2000), 35);
+        assert_eq!(fee(200), 4); // 3.5 öre
+        assert_eq!(fee(-200), -4);
+        assert_eq!(fee(0), 0);
+        assert_eq!(i64::from(fees.fee(PaymentProvider::Swish, 10000.into())), 0);
+    }
+
+    #[test]
+    fn test_member_liabilities() {
+        let member = |id, first_name: &str| Member {
+            id,
+            first_name: first_name.to_string(),
+            last_name: "Svensson".to_string(),
+            nickname: None,
+            archived_at: None,
+        };
+        let accounts = vec![
+            (
+                member(1, "Stina"),
+                account(11, "Stina", BookAccountType::Liabilities),
+            ),
+            (
+                member(2, "Anna"),
+                account(12, "Anna", BookAccountType::Liabilities),
+            ),
+        ];
+
+        let transaction = |id, day, debited_account, credited_account, amount: i32| Transaction {
+            id,
+            description: None,
+            time: Utc.ymd(2021, 12, day).and_hms(12, 0, 0),
+            bundles: vec![],
+            debited_account,
+            credited_account,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        };
+        let transactions = vec![
+            transaction(1, 1, 2, 11, 10000), // deposit
+            transaction(2, 5, 11, 3, 2500),  // purchase
+            transaction(3, 31, 2, 11, 5000), // deposit after the cutoff
+        ];
+
+        let cutoff = Utc.ymd(2021, 12, 31).and_hms(0, 0, 0);
+        let liabilities = MemberLiability::compute(cutoff, &accounts, &transactions);
+        assert_eq!(
+            liabilities,
+            vec![
+                MemberLiability {
+                    member_id: 2,
+                    account_id: 12,
+                    name: "Anna Svensson".to_string(),
+                    balance: 0.into(),
+                    last_activity: None,
+                },
+                MemberLiability {
+                    member_id: 1,
+                    account_id: 11,
+                    name: "Stina Svensson".to_string(),
+                    balance: 7500.into(),
+                    last_activity: Some(Utc.ymd(2021, 12, 5).and_hms(12, 0, 0)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dormant_balances() {
+        let now = Utc.ymd(2021, 7, 1).and_hms(12, 0, 0);
+        let liability = |member_id, balance: i32, days_ago: Option<i64>| MemberLiability {
+            member_id,
+            account_id: member_id + 10,
+            name: format!("Medlem {}", member_id),
+            balance: balance.into(),
+            last_activity: days_ago.map(|days| now - Duration::days(days)),
+        };
+        let liabilities = vec![
+            liability(1, 5000, Some(400)),
+            liability(2, 2500, Some(1200)),
+            liability(3, 5000, Some(30)),   // still in use
+            liability(4, 0, Some(1200)),    // empty
+            liability(5, -1000, Some(800)), // owes money
+            liability(6, 0, None),
+        ];
+        let reminded = vec![
+            (1, now - Duration::days(10)),
+            (2, now - Duration::days(1300)), // before the balance was last touched
+        ]
+        .into_iter()
+        .collect();
+
+        let report = DormantBalanceReport::compute(now, 365, 1095, liabilities, &reminded);
+        assert_eq!(
+            report
+                .balances
+                .iter()
+                .map(|b| (b.member_id, b.reminded_at.is_some(), b.forfeitable))
+                .collect::<Vec<_>>(),
+            vec![(2, false, true), (1, true, false)]
+        );
+        assert_eq!(report.total(), 7500.into());
+        assert_eq!(report.total_forfeitable(), 2500.into());
+    }
+
+    #[test]
+    fn test_stock_value() {
+        // 10 bought at 5:-, then 24 at 4:-, then 6 of unknown cost
+        let purchases = vec![(Some(500.into()), 10), (Some(400.into()), 24), (None, 6)];
+        let value = |valuation, stock, cost_price: Option<i32>| {
+            let cost_price = cost_price.map(Currency::from);
+            ItemStockValue::compute(valuation, 1, "Cola".into(), stock, cost_price, &purchases)
+                .value
+        };
+
+        assert_eq!(
+            value(StockValuation::CostPrice, 30, Some(450)),
+            Some(13500.into())
+        );
+        assert_eq!(value(StockValuation::CostPrice, 30, None), None);
+
+        // the 6 of unknown cost are the last ones bought, so they are valued at the cost price
+        assert_eq!(
+            value(StockValuation::Fifo, 30, Some(450)),
+            Some((24 * 400 + 6 * 450).into())
+        );
+        assert_eq!(value(StockValuation::Fifo, 30, None), None);
+
+        // more in stock than was ever purchased, e.g. because of donations
+        assert_eq!(
+            value(StockValuation::Fifo, 45, Some(450)),
+            Some((10 * 500 + 24 * 400 + 11 * 450).into())
+        );
+        assert_eq!(value(StockValuation::Fifo, 0, None), Some(0.into()));
+    }
+}
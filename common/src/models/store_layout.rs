@@ -0,0 +1,144 @@
+use crate::models::inventory::{InventoryBundleId, InventoryItemId};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The number of columns in the quick button grid of the store page
+pub const STORE_GRID_COLUMNS: i32 = 6;
+
+/// A hand-arranged layout of the store page
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct StoreLayout {
+    pub tabs: Vec<StoreTab>,
+}
+
+/// A category tab in the store page with its own grid of buttons
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct StoreTab {
+    pub name: String,
+    pub buttons: Vec<QuickButton>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuickButtonTarget {
+    Item(InventoryItemId),
+    Bundle(InventoryBundleId),
+}
+
+/// A button in the grid. Positions and sizes are counted in grid cells.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct QuickButton {
+    pub target: QuickButtonTarget,
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+
+    /// A CSS color, e.g. "#ff8800"
+    pub color: Option<String>,
+}
+
+impl QuickButton {
+    pub fn new(target: QuickButtonTarget, x: i32, y: i32) -> Self {
+        QuickButton {
+            target,
+            x,
+            y,
+            width: 1,
+            height: 1,
+            color: None,
+        }
+    }
+
+    /// Check if the button covers the cell at (x, y)
+    pub fn covers(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+
+    pub fn overlaps(&self, other: &QuickButton) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+}
+
+impl StoreTab {
+    /// The number of rows occupied by buttons
+    pub fn rows(&self) -> i32 {
+        self.buttons
+            .iter()
+            .map(|b| b.y + b.height)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl StoreLayout {
+    /// Check that all buttons fit in the grid and don't overlap.
+    ///
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        for tab in &self.tabs {
+            if tab.name.trim().is_empty() {
+                return Err("tab names must not be empty".to_string());
+            }
+
+            for (i, button) in tab.buttons.iter().enumerate() {
+                if button.x < 0 || button.y < 0 || button.width < 1 || button.height < 1 {
+                    return Err(format!("invalid button position in tab \"{}\"", tab.name));
+                }
+
+                if button.x + button.width > STORE_GRID_COLUMNS {
+                    return Err(format!("button outside of grid in tab \"{}\"", tab.name));
+                }
+
+                if tab.buttons[..i].iter().any(|other| other.overlaps(button)) {
+                    return Err(format!("overlapping buttons in tab \"{}\"", tab.name));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tab(buttons: Vec<QuickButton>) -> StoreLayout {
+        StoreLayout {
+            tabs: vec![StoreTab {
+                name: "Dryck".to_string(),
+                buttons,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_layout_validation() {
+        let mut big = QuickButton::new(QuickButtonTarget::Item(1), 0, 0);
+        big.width = 2;
+        big.height = 2;
+
+        let beside = QuickButton::new(QuickButtonTarget::Item(2), 2, 1);
+        let inside = QuickButton::new(QuickButtonTarget::Bundle(1), 1, 1);
+        let outside = QuickButton::new(QuickButtonTarget::Item(3), STORE_GRID_COLUMNS, 0);
+
+        assert!(tab(vec![big.clone(), beside]).validate().is_ok());
+        assert!(tab(vec![big.clone(), inside]).validate().is_err());
+        assert!(tab(vec![outside]).validate().is_err());
+        assert!(big.covers(1, 1));
+        assert!(!big.covers(2, 0));
+        assert_eq!(tab(vec![big]).tabs[0].rows(), 2);
+    }
+}
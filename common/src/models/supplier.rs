@@ -0,0 +1,51 @@
+use crate::models::inventory::InventoryItemId;
+use chrono::Weekday;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type SupplierId = i32;
+
+/// Somewhere items are bought from
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Supplier {
+    pub id: SupplierId,
+    pub name: String,
+
+    /// Who to talk to, e.g. a phone number or an email address
+    pub contact: Option<String>,
+
+    /// Where orders are placed
+    pub ordering_url: Option<String>,
+
+    /// The days of the week when the supplier delivers
+    pub delivery_days: Vec<Weekday>,
+
+    /// The items which are bought from this supplier
+    pub item_ids: Vec<InventoryItemId>,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct NewSupplier {
+    pub name: String,
+    pub contact: Option<String>,
+    pub ordering_url: Option<String>,
+    pub delivery_days: Vec<Weekday>,
+    pub item_ids: Vec<InventoryItemId>,
+}
+
+impl From<Supplier> for NewSupplier {
+    fn from(supplier: Supplier) -> Self {
+        NewSupplier {
+            name: supplier.name,
+            contact: supplier.contact,
+            ordering_url: supplier.ordering_url,
+            delivery_days: supplier.delivery_days,
+            item_ids: supplier.item_ids,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::book_account::BookAccountId;
+use crate::currency::{Currency, NonNegativeCurrency};
+use crate::member::MemberId;
+use crate::transaction::TransactionId;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derive_enum::DbEnum;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The instruction UUID of a Swish payment request, 32 uppercase hex digits
+pub type SwishPaymentId = String;
+
+/// How far a Swish payment has come, as reported by Swish
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(DbEnum))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SwishStatus {
+    /// Waiting for the member to pay in the Swish app
+    Created,
+    Paid,
+    Declined,
+    Error,
+    Cancelled,
+}
+
+impl SwishStatus {
+    /// Whether the payment may still be paid
+    pub fn is_pending(self) -> bool {
+        self == SwishStatus::Created
+    }
+}
+
+/// A deposit which a member makes themselves by scanning their balance card, see
+/// [PublicBalance](crate::public::PublicBalance)
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct SwishDeposit {
+    pub amount: NonNegativeCurrency,
+}
+
+/// A self-service deposit paid with Swish. The tillgodo account of the member is credited once
+/// Swish reports it as paid.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct SwishPayment {
+    pub id: SwishPaymentId,
+    pub member_id: MemberId,
+    pub credited_account: BookAccountId,
+    pub amount: Currency,
+    pub status: SwishStatus,
+
+    /// Opens the payment in the Swish app on the phone of the member, while it's pending
+    pub token: Option<String>,
+
+    /// Why Swish didn't complete the payment, if it didn't
+    pub error: Option<String>,
+
+    /// The deposit, once it's paid
+    pub transaction_id: Option<TransactionId>,
+
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
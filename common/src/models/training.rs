@@ -0,0 +1,28 @@
+use crate::currency::Currency;
+use crate::models::book_account::BookAccountId;
+use crate::models::transaction::TransactionBundle;
+use crate::models::user::UserName;
+use chrono::{DateTime, Utc};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type TrainingTransactionId = i32;
+
+/// A sale made in training mode.
+///
+/// Training sales are kept apart from the real transactions, so they never show up in reports,
+/// take items from stock, or draw from the balances of members.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct TrainingTransaction {
+    pub id: TrainingTransactionId,
+    pub time: DateTime<Utc>,
+    pub created_by: UserName,
+    pub description: Option<String>,
+    pub debited_account: BookAccountId,
+    pub credited_account: BookAccountId,
+    pub amount: Currency,
+    pub bundles: Vec<TransactionBundle>,
+}
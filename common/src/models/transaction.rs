@@ -1,9 +1,14 @@
 use crate::currency::Currency;
 use crate::models::book_account::BookAccountId;
-use crate::models::inventory::InventoryItemId;
-use chrono::{DateTime, Utc};
+use crate::models::inventory::{InventoryItemId, ItemModifierId};
+use crate::models::member::MemberId;
+use crate::models::user::UserName;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use std::collections::HashMap;
 
+#[cfg(feature = "diesel_impl")]
+use diesel_derive_enum::DbEnum;
+
 #[cfg(feature = "serde_impl")]
 use serde::{Deserialize, Serialize};
 
@@ -18,6 +23,34 @@ pub struct NewTransaction {
     pub debited_account: BookAccountId,
     pub credited_account: BookAccountId,
     pub amount: Currency,
+
+    /// A free-text note, e.g. "betalas av sektionen"
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub note: Option<String>,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub tags: Vec<String>,
+
+    /// The event or project the transaction belongs to, e.g. "pubrunda-2021", see
+    /// [EventProfitAndLoss](crate::event::EventProfitAndLoss)
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub event_code: Option<String>,
+
+    /// The cashier confirmed that the ID of the buyer was checked, required for selling
+    /// age-restricted items
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub age_verified: bool,
+
+    /// The member who bought the goods, for purchase limits and statistics. A sale paid with the
+    /// tillgodo of a member is theirs anyway, other sales may be tagged with any member without
+    /// touching their balance.
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub member_id: Option<MemberId>,
+
+    /// Chosen by the client, a transaction is only created once per key even if it is posted
+    /// several times
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub idempotency_key: Option<String>,
 }
 
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
@@ -31,6 +64,191 @@ pub struct Transaction {
     pub debited_account: BookAccountId,
     pub credited_account: BookAccountId,
     pub amount: Currency,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub note: Option<String>,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub tags: Vec<String>,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub event_code: Option<String>,
+
+    /// Whether the ID of the buyer was checked, see [NewTransaction::age_verified]
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub age_verified: bool,
+
+    /// The member who bought the goods, see [NewTransaction::member_id]
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub member_id: Option<MemberId>,
+
+    /// The user who made the transaction, if it is known
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub created_by: Option<UserName>,
+}
+
+impl Transaction {
+    /// Whether the note, any of the tags or the event code contains `query`, ignoring case
+    pub fn annotation_matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.note
+            .iter()
+            .chain(self.tags.iter())
+            .chain(self.event_code.iter())
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+
+    /// How many of each item the transaction sold
+    pub fn items_sold(&self) -> HashMap<InventoryItemId, u32> {
+        let mut sold = HashMap::new();
+        for bundle in self.bundles.iter().filter(|bundle| bundle.change < 0) {
+            for (&item_id, &per_bundle) in &bundle.item_ids {
+                *sold.entry(item_id).or_default() += per_bundle * (-bundle.change) as u32;
+            }
+        }
+        sold
+    }
+
+    /// What the items which were sold on their own were sold for, including their variants and
+    /// add-ons. Items only sold in bundles with other items have no price of their own.
+    pub fn unit_prices(&self) -> HashMap<InventoryItemId, Currency> {
+        let mut prices = HashMap::new();
+        for bundle in self.bundles.iter().filter(|bundle| bundle.change < 0) {
+            let mut items = bundle.item_ids.iter();
+            if let (Some((&item_id, &1)), None, Some(price)) =
+                (items.next(), items.next(), bundle.price)
+            {
+                prices.entry(item_id).or_insert(price);
+            }
+        }
+        prices
+    }
+
+    /// Whether `other` looks like the same purchase as `self`, i.e. the same amount, accounts
+    /// and items
+    pub fn same_contents(&self, other: &Transaction) -> bool {
+        self.amount == other.amount
+            && self.debited_account == other.debited_account
+            && self.credited_account == other.credited_account
+            && self.bundles == other.bundles
+    }
+}
+
+/// Two transactions which are likely the same purchase, submitted twice
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct DuplicatePair {
+    pub original: Transaction,
+    pub duplicate: Transaction,
+}
+
+/// Find transactions with the same contents made within `window` of each other.
+///
+/// Every transaction is part of at most one pair, so three identical transactions in a row give
+/// one pair and one transaction left over.
+pub fn find_duplicates(transactions: &[Transaction], window: Duration) -> Vec<DuplicatePair> {
+    let mut sorted: Vec<&Transaction> = transactions.iter().collect();
+    sorted.sort_by_key(|tr| (tr.time, tr.id));
+
+    let mut paired = vec![false; sorted.len()];
+    let mut pairs = vec![];
+
+    for i in 0..sorted.len() {
+        if paired[i] {
+            continue;
+        }
+
+        let original = sorted[i];
+        let duplicate = (i + 1..sorted.len())
+            .take_while(|&j| sorted[j].time - original.time <= window)
+            .find(|&j| !paired[j] && original.same_contents(sorted[j]));
+
+        if let Some(j) = duplicate {
+            paired[i] = true;
+            paired[j] = true;
+            pairs.push(DuplicatePair {
+                original: original.clone(),
+                duplicate: sorted[j].clone(),
+            });
+        }
+    }
+
+    pairs
+}
+
+/// The note, tags and event code of a transaction, which can be changed after it was made
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct TransactionAnnotation {
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub event_code: Option<String>,
+}
+
+impl TransactionAnnotation {
+    /// Trim the note, tags and event code, and drop empty and duplicate tags
+    pub fn normalized(self) -> Self {
+        let trim = |text: String| Some(text.trim().to_string()).filter(|text| !text.is_empty());
+        let note = self.note.and_then(trim);
+        let event_code = self.event_code.and_then(trim);
+
+        let mut tags: Vec<String> = vec![];
+        for tag in self.tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+
+        TransactionAnnotation {
+            note,
+            tags,
+            event_code,
+        }
+    }
+
+    /// Parse a comma-separated list of tags, as typed by a user
+    pub fn parse_tags(input: &str) -> Vec<String> {
+        input.split(',').map(|tag| tag.to_string()).collect()
+    }
+}
+
+/// The most transactions which can be changed by one [TransactionBatch]
+pub const MAX_BATCH_SIZE: usize = 500;
+
+/// A change to several transactions at once, e.g. to clean up after a training session or a
+/// misconfigured price. Either every transaction is changed or none of them.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct TransactionBatch {
+    pub transaction_ids: Vec<TransactionId>,
+    pub operation: BatchOperation,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum BatchOperation {
+    /// Void every transaction, for the same reason
+    Void { reason: String },
+
+    /// Add the tags to every transaction, next to those it already has
+    AddTags { tags: Vec<String> },
+}
+
+/// What a [TransactionBatch] changed
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct BatchOutcome {
+    /// Recorded with every change of the batch in the audit log
+    pub batch_id: String,
+    pub transaction_ids: Vec<TransactionId>,
 }
 
 impl PartialEq for Transaction {
@@ -41,6 +259,110 @@ impl PartialEq for Transaction {
 
 impl Eq for Transaction {}
 
+/// Everything known about a single transaction, for the detail view
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct TransactionDetail {
+    pub transaction: Transaction,
+
+    /// The id of the iZettle payment, if the transaction was paid by card through the bridge
+    pub izettle_reference: Option<i32>,
+
+    /// When the transaction was deleted (voided), if it has been
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// What was returned, and from which sale, if the transaction is a return of goods
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub goods_return: Option<GoodsReturn>,
+
+    /// The returns of goods sold in the transaction, which haven't been deleted
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub returns: Vec<GoodsReturn>,
+    /// The name of the registered device the transaction was made on, see
+    /// [Device](crate::device::Device)
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub device: Option<String>,
+}
+
+/// Why goods were returned
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(DbEnum))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ReturnReason {
+    Defective,
+    WrongItem,
+    Expired,
+    ChangedMind,
+    Other,
+}
+
+impl ReturnReason {
+    pub const ALL: [ReturnReason; 5] = [
+        ReturnReason::Defective,
+        ReturnReason::WrongItem,
+        ReturnReason::Expired,
+        ReturnReason::ChangedMind,
+        ReturnReason::Other,
+    ];
+}
+
+/// Goods to return from a sale, see [GoodsReturn]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct NewGoodsReturn {
+    /// How many of each item are returned
+    pub items: HashMap<InventoryItemId, u32>,
+
+    /// What is paid back, to the account which the sale was paid from
+    pub amount: Currency,
+
+    pub reason: ReturnReason,
+
+    /// Whether the items are put back in stock, rather than thrown away, e.g. if they're broken
+    pub restock: bool,
+
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub note: Option<String>,
+}
+
+/// Goods returned from a sale.
+///
+/// Unlike a deleted transaction, a return is a transaction of its own, which pays back for the
+/// returned items. Items which are put back in stock are sold back in its bundles.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct GoodsReturn {
+    /// The transaction which paid back for the items
+    pub transaction_id: TransactionId,
+
+    /// The sale which the items were returned from
+    pub original_transaction_id: TransactionId,
+
+    pub amount: Currency,
+    pub reason: ReturnReason,
+    pub restocked: bool,
+    pub items: HashMap<InventoryItemId, u32>,
+}
+
+/// How many of each item sold in `original` may still be returned, after `returns`
+pub fn returnable_items(
+    original: &Transaction,
+    returns: &[GoodsReturn],
+) -> HashMap<InventoryItemId, u32> {
+    let mut returnable = original.items_sold();
+    for (item_id, amount) in returns.iter().flat_map(|r| r.items.iter()) {
+        if let Some(left) = returnable.get_mut(item_id) {
+            *left = left.saturating_sub(*amount);
+        }
+    }
+    returnable.retain(|_, left| *left > 0);
+    returnable
+}
+
 #[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "debug", derive(Debug))]
 #[derive(Clone, PartialEq, Eq)]
@@ -49,4 +371,123 @@ pub struct TransactionBundle {
     pub price: Option<Currency>,
     pub change: i32,
     pub item_ids: HashMap<InventoryItemId, u32>,
+
+    /// The best-before date of the items, if this bundle restocks them
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub expires_on: Option<NaiveDate>,
+
+    /// The variants and add-ons chosen for the items, e.g. the size of a coffee. Their price
+    /// deltas are included in the price of the bundle.
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub modifier_ids: Vec<ItemModifierId>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normalize_annotation() {
+        let annotation = TransactionAnnotation {
+            note: Some("  ".to_string()),
+            tags: TransactionAnnotation::parse_tags("event: LAN 2024, ,sektionen,event: LAN 2024"),
+            event_code: Some(" pubrunda-2021 ".to_string()),
+        }
+        .normalized();
+
+        assert_eq!(annotation.note, None);
+        assert_eq!(annotation.tags, vec!["event: LAN 2024", "sektionen"]);
+        assert_eq!(annotation.event_code.as_deref(), Some("pubrunda-2021"));
+    }
+
+    #[test]
+    fn test_returnable_items() {
+        use chrono::TimeZone;
+
+        let bundle = |items: &[(InventoryItemId, u32)], price: i32, change| TransactionBundle {
+            description: None,
+            price: Some(price.into()),
+            change,
+            item_ids: items.iter().copied().collect(),
+            expires_on: None,
+            modifier_ids: vec![],
+        };
+        let sale = Transaction {
+            id: 1,
+            description: None,
+            time: Utc.ymd(2021, 7, 1).and_hms(12, 0, 0),
+            bundles: vec![
+                bundle(&[(1, 1)], 500, -3),
+                bundle(&[(1, 1), (2, 1)], 800, -1), // a deal
+                bundle(&[(2, 2)], 600, -1),
+            ],
+            debited_account: 1,
+            credited_account: 2,
+            amount: 2900.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        };
+
+        let sold = sale.items_sold();
+        assert_eq!(sold[&1], 4);
+        assert_eq!(sold[&2], 3);
+
+        // item 2 was never sold on its own
+        let prices = sale.unit_prices();
+        assert_eq!(prices.get(&1), Some(&500.into()));
+        assert_eq!(prices.get(&2), None);
+
+        let returned = GoodsReturn {
+            transaction_id: 2,
+            original_transaction_id: 1,
+            amount: 1000.into(),
+            reason: ReturnReason::Defective,
+            restocked: false,
+            items: vec![(1, 4), (2, 1)].into_iter().collect(),
+        };
+        let returnable = returnable_items(&sale, &[returned]);
+        assert_eq!(returnable.get(&1), None);
+        assert_eq!(returnable.get(&2), Some(&2));
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        use chrono::TimeZone;
+
+        let transaction = |id, seconds, amount: i32| Transaction {
+            id,
+            description: None,
+            time: Utc.ymd(2021, 7, 1).and_hms(12, 0, seconds),
+            bundles: vec![],
+            debited_account: 1,
+            credited_account: 2,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        };
+
+        let transactions = vec![
+            transaction(1, 0, 100),
+            transaction(2, 2, 100), // duplicate of 1
+            transaction(3, 3, 100), // 1 is already paired
+            transaction(4, 4, 500), // different amount
+            transaction(5, 30, 100),
+            transaction(6, 50, 100), // too long after 5
+        ];
+
+        let pairs = find_duplicates(&transactions, Duration::seconds(5));
+        let ids: Vec<_> = pairs
+            .iter()
+            .map(|pair| (pair.original.id, pair.duplicate.id))
+            .collect();
+        assert_eq!(ids, vec![(1, 2)]);
+    }
 }
@@ -0,0 +1,34 @@
+use crate::models::organization::OrganizationId;
+
+#[cfg(feature = "diesel_impl")]
+use diesel_derives::Queryable;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+pub type UserName = String;
+
+/// A user which can log in to the system
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[cfg_attr(feature = "diesel_impl", derive(Queryable))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct User {
+    pub name: UserName,
+    pub display_name: Option<String>,
+
+    /// Admins can manage users and see the status of the system
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub is_admin: bool,
+}
+
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub name: UserName,
+    pub password: String,
+
+    /// Which organization to log in to, may be left out if the user only belongs to one
+    #[cfg_attr(feature = "serde_impl", serde(default))]
+    pub organization: Option<OrganizationId>,
+}
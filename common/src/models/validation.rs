@@ -0,0 +1,483 @@
+use crate::announcement::NewAnnouncement;
+use crate::book_account::NewBookAccount;
+use crate::catalog::Catalog;
+use crate::correction::{CorrectionKind, NewCorrection};
+use crate::currency::Currency;
+use crate::device::DeviceName;
+use crate::event::NewEventSignup;
+use crate::filter_preset::NewFilterPreset;
+use crate::goal::{GoalScope, GoalTarget, NewSalesGoal};
+use crate::izettle::IZettleDeposit;
+use crate::location::NewStorageLocation;
+use crate::member::NewMember;
+use crate::organization::BrandingSettings;
+use crate::parked_cart::NewParkedCart;
+use crate::supplier::NewSupplier;
+use crate::transaction::{
+    BatchOperation, NewGoodsReturn, NewTransaction, TransactionAnnotation, TransactionBatch,
+    TransactionBundle, MAX_BATCH_SIZE,
+};
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The max number of characters of names, e.g. of members and accounts
+pub const MAX_NAME_LENGTH: usize = 64;
+
+/// The max number of characters of free text, e.g. descriptions and notes
+pub const MAX_TEXT_LENGTH: usize = 500;
+
+/// The max number of characters of an email address
+pub const MAX_EMAIL_LENGTH: usize = 254;
+
+/// The largest amount of a single transaction, in either direction
+pub const MAX_AMOUNT: Currency = Currency::from_minor(10_000_000);
+
+/// Why the value of a field was rejected
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub enum Invalid {
+    Empty,
+    TooLong { max: usize },
+    OutOfRange { min: Currency, max: Currency },
+    NotAUrl,
+    NotAnEmail,
+}
+
+/// A field of a request which was rejected, see [Validate]
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// The name of the field, as it is serialized
+    pub field: String,
+    pub error: Invalid,
+}
+
+/// Requests which are checked by the server before they are acted on.
+///
+/// Rejected requests are answered with 422 Unprocessable Entity and the errors as details.
+pub trait Validate {
+    fn validate(&self) -> Result<(), Vec<FieldError>>;
+}
+
+/// Collects the errors of the fields of a request
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn error(&mut self, field: &str, error: Invalid) -> &mut Self {
+        self.errors.push(FieldError {
+            field: field.to_string(),
+            error,
+        });
+        self
+    }
+
+    /// The field must contain something other than whitespace, and at most `max` characters
+    pub fn required(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.trim().is_empty() {
+            self.error(field, Invalid::Empty)
+        } else {
+            self.max_length(field, value, max)
+        }
+    }
+
+    pub fn max_length(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.chars().count() > max {
+            self.error(field, Invalid::TooLong { max })
+        } else {
+            self
+        }
+    }
+
+    pub fn optional(&mut self, field: &str, value: &Option<String>, max: usize) -> &mut Self {
+        match value {
+            Some(value) => self.max_length(field, value, max),
+            None => self,
+        }
+    }
+
+    pub fn range(
+        &mut self,
+        field: &str,
+        value: Currency,
+        min: Currency,
+        max: Currency,
+    ) -> &mut Self {
+        if value < min || value > max {
+            self.error(field, Invalid::OutOfRange { min, max })
+        } else {
+            self
+        }
+    }
+
+    pub fn url(&mut self, field: &str, value: &Option<String>) -> &mut Self {
+        match value {
+            Some(url) if !url.starts_with("http://") && !url.starts_with("https://") => {
+                self.error(field, Invalid::NotAUrl)
+            }
+            _ => self.optional(field, value, MAX_TEXT_LENGTH),
+        }
+    }
+
+    /// Only a sanity check, whether the address works is checked by mailing it
+    pub fn email(&mut self, field: &str, value: &Option<String>) -> &mut Self {
+        let valid = |email: &str| match email.split_once('@') {
+            Some((user, domain)) => {
+                !user.is_empty()
+                    && domain.contains('.')
+                    && !domain.contains('@')
+                    && !email.contains(char::is_whitespace)
+            }
+            None => false,
+        };
+        match value {
+            Some(email) if !valid(email) => self.error(field, Invalid::NotAnEmail),
+            _ => self.optional(field, value, MAX_EMAIL_LENGTH),
+        }
+    }
+
+    /// Add the errors of another validation, e.g. of a part of the request
+    pub fn include(&mut self, result: Result<(), Vec<FieldError>>) -> &mut Self {
+        if let Err(errors) = result {
+            self.errors.extend(errors);
+        }
+        self
+    }
+
+    pub fn finish(&mut self) -> Result<(), Vec<FieldError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(std::mem::take(&mut self.errors))
+        }
+    }
+}
+
+/// The error of `field`, if it was rejected
+pub fn field_error<'a>(errors: &'a [FieldError], field: &str) -> Option<&'a Invalid> {
+    errors
+        .iter()
+        .find(|error| error.field == field)
+        .map(|error| &error.error)
+}
+
+impl Validate for NewTransaction {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.optional("description", &self.description, MAX_TEXT_LENGTH)
+            .range("amount", self.amount, -MAX_AMOUNT, MAX_AMOUNT)
+            .optional("note", &self.note, MAX_TEXT_LENGTH)
+            .optional("event_code", &self.event_code, MAX_NAME_LENGTH);
+        validate_tags(&mut v, &self.tags);
+        validate_bundles(&mut v, &self.bundles);
+        v.finish()
+    }
+}
+
+impl Validate for NewGoodsReturn {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.range("amount", self.amount, Currency::default(), MAX_AMOUNT)
+            .optional("note", &self.note, MAX_TEXT_LENGTH);
+        if self.items.values().all(|&amount| amount == 0) {
+            v.error("items", Invalid::Empty);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for TransactionAnnotation {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.optional("note", &self.note, MAX_TEXT_LENGTH).optional(
+            "event_code",
+            &self.event_code,
+            MAX_NAME_LENGTH,
+        );
+        validate_tags(&mut v, &self.tags);
+        v.finish()
+    }
+}
+
+impl Validate for TransactionBatch {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        if self.transaction_ids.is_empty() {
+            v.error("transaction_ids", Invalid::Empty);
+        } else if self.transaction_ids.len() > MAX_BATCH_SIZE {
+            v.error(
+                "transaction_ids",
+                Invalid::TooLong {
+                    max: MAX_BATCH_SIZE,
+                },
+            );
+        }
+        match &self.operation {
+            BatchOperation::Void { reason } => {
+                v.required("reason", reason, MAX_TEXT_LENGTH);
+            }
+            BatchOperation::AddTags { tags } => {
+                if tags.iter().all(|tag| tag.trim().is_empty()) {
+                    v.error("tags", Invalid::Empty);
+                }
+                validate_tags(&mut v, tags);
+            }
+        }
+        v.finish()
+    }
+}
+
+impl Validate for NewMember {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .required("first_name", &self.first_name, MAX_NAME_LENGTH)
+            .required("last_name", &self.last_name, MAX_NAME_LENGTH)
+            .optional("nickname", &self.nickname, MAX_NAME_LENGTH)
+            .finish()
+    }
+}
+
+impl Validate for NewBookAccount {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .required("name", &self.name, MAX_NAME_LENGTH)
+            .finish()
+    }
+}
+
+impl Validate for NewSupplier {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .required("name", &self.name, MAX_NAME_LENGTH)
+            .optional("contact", &self.contact, MAX_TEXT_LENGTH)
+            .url("ordering_url", &self.ordering_url)
+            .finish()
+    }
+}
+
+impl Validate for NewParkedCart {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.required("name", &self.name, MAX_NAME_LENGTH);
+        validate_bundles(&mut v, &self.bundles);
+        v.finish()
+    }
+}
+
+impl Validate for NewFilterPreset {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.required("name", &self.name, MAX_NAME_LENGTH);
+        v.max_length("search", &self.filter.search, MAX_TEXT_LENGTH);
+        v.optional("cashier", &self.filter.cashier, MAX_NAME_LENGTH);
+        v.finish()
+    }
+}
+
+impl Validate for NewAnnouncement {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .required("message", &self.message, MAX_TEXT_LENGTH)
+            .finish()
+    }
+}
+
+impl Validate for DeviceName {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .required("name", &self.name, MAX_NAME_LENGTH)
+            .finish()
+    }
+}
+
+impl Validate for NewStorageLocation {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .required("name", &self.name, MAX_NAME_LENGTH)
+            .finish()
+    }
+}
+
+impl Validate for NewCorrection {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.required("reason", &self.reason, MAX_TEXT_LENGTH);
+        match self.kind {
+            CorrectionKind::Balance {
+                debited_account,
+                credited_account,
+                amount,
+            } => {
+                v.range("amount", amount, Currency::from(1), MAX_AMOUNT);
+                // moving money to where it is changes nothing
+                if debited_account == credited_account {
+                    v.error("credited_account", Invalid::Empty);
+                }
+            }
+            CorrectionKind::Stock { change: 0, .. } => {
+                v.error("change", Invalid::Empty);
+            }
+            CorrectionKind::Stock { .. } => {}
+        }
+        v.finish()
+    }
+}
+
+impl Validate for NewEventSignup {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.required("name", &self.name, MAX_NAME_LENGTH);
+        if self.email.trim().is_empty() {
+            v.error("email", Invalid::Empty);
+        } else {
+            v.email("email", &Some(self.email.clone()));
+        }
+        v.finish()
+    }
+}
+
+impl Validate for BrandingSettings {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .required("name", &self.name, MAX_NAME_LENGTH)
+            .optional("org_number", &self.org_number, MAX_NAME_LENGTH)
+            .optional("footer_text", &self.footer_text, MAX_TEXT_LENGTH)
+            .finish()
+    }
+}
+
+impl Validate for NewSalesGoal {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        v.required("name", &self.name, MAX_NAME_LENGTH);
+        // a goal of nothing is no goal
+        match self.target {
+            GoalTarget::Revenue(amount) if amount <= Currency::default() => {
+                v.error("target", Invalid::Empty);
+            }
+            GoalTarget::Quantity(0) => {
+                v.error("target", Invalid::Empty);
+            }
+            _ => {}
+        }
+        if let GoalScope::Event(code) = &self.scope {
+            v.required("scope", code, MAX_NAME_LENGTH);
+        }
+        v.finish()
+    }
+}
+
+impl Validate for IZettleDeposit {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        Validator::default()
+            .range("amount", self.amount.into(), Currency::from(1), MAX_AMOUNT)
+            .finish()
+    }
+}
+
+impl Validate for Catalog {
+    fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut v = Validator::default();
+        for item in &self.items {
+            v.required("items", &item.name, MAX_NAME_LENGTH)
+                .url("items", &item.image_url);
+            for price in item.price.iter().chain(&item.cost_price) {
+                v.range("items", *price, Currency::default(), MAX_AMOUNT);
+            }
+            for text in item.categories.iter().chain(&item.barcodes) {
+                v.required("items", text, MAX_NAME_LENGTH);
+            }
+            for modifier in &item.modifiers {
+                v.required("items", &modifier.name, MAX_NAME_LENGTH)
+                    .optional("items", &modifier.group, MAX_NAME_LENGTH)
+                    .range("items", modifier.price_delta, -MAX_AMOUNT, MAX_AMOUNT);
+            }
+        }
+        for bundle in &self.bundles {
+            v.required("bundles", &bundle.name, MAX_NAME_LENGTH)
+                .url("bundles", &bundle.image_url)
+                .range("bundles", bundle.price, Currency::default(), MAX_AMOUNT);
+        }
+        v.finish()
+    }
+}
+
+fn validate_tags(v: &mut Validator, tags: &[String]) {
+    if let Some(tag) = tags
+        .iter()
+        .find(|tag| tag.chars().count() > MAX_NAME_LENGTH)
+    {
+        v.max_length("tags", tag, MAX_NAME_LENGTH);
+    }
+}
+
+fn validate_bundles(v: &mut Validator, bundles: &[TransactionBundle]) {
+    for bundle in bundles {
+        v.optional("bundles", &bundle.description, MAX_TEXT_LENGTH);
+        if let Some(price) = bundle.price {
+            v.range("bundles", price, -MAX_AMOUNT, MAX_AMOUNT);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validator() {
+        let errors = Validator::default()
+            .required("name", "  ", MAX_NAME_LENGTH)
+            .required("nickname", "Kalle", MAX_NAME_LENGTH)
+            .max_length("note", &"x".repeat(MAX_TEXT_LENGTH + 1), MAX_TEXT_LENGTH)
+            .range(
+                "amount",
+                Currency::from(-1),
+                Currency::default(),
+                MAX_AMOUNT,
+            )
+            .url("ordering_url", &Some("example.com".to_string()))
+            .email("email", &Some("kalle@localhost".to_string()))
+            .finish()
+            .unwrap_err();
+
+        assert_eq!(errors.len(), 5);
+        assert_eq!(field_error(&errors, "name"), Some(&Invalid::Empty));
+        assert_eq!(field_error(&errors, "nickname"), None);
+        assert_eq!(
+            field_error(&errors, "note"),
+            Some(&Invalid::TooLong {
+                max: MAX_TEXT_LENGTH
+            })
+        );
+        assert_eq!(
+            field_error(&errors, "ordering_url"),
+            Some(&Invalid::NotAUrl)
+        );
+        assert_eq!(field_error(&errors, "email"), Some(&Invalid::NotAnEmail));
+
+        for email in &[
+            "kalle",
+            "@example.com",
+            "kalle@@example.com",
+            "kalle @example.com",
+        ] {
+            assert!(Validator::default()
+                .email("email", &Some(email.to_string()))
+                .finish()
+                .is_err());
+        }
+
+        assert!(Validator::default()
+            .required("name", "Kalle", MAX_NAME_LENGTH)
+            .email("email", &Some("kalle@example.com".to_string()))
+            .email("backup_email", &None)
+            .finish()
+            .is_ok());
+    }
+}
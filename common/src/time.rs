@@ -0,0 +1,391 @@
+//! Helpers for working with timestamps in the reporting timezone.
+//!
+//! All timestamps are stored and transmitted as UTC. Whenever something is grouped by calendar
+//! day or week it has to be done in the local time of the organization, otherwise sales made
+//! just after midnight end up on the wrong day, and DST transitions shift everything by an hour.
+
+use chrono::{DateTime, Datelike, Duration, IsoWeek, NaiveDate, TimeZone, Utc, Weekday};
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::ops::Range;
+use std::str::FromStr;
+
+pub use chrono_tz::Tz;
+
+#[cfg(feature = "serde_impl")]
+use serde::{Deserialize, Serialize};
+
+/// The timezone used for reports if nothing else is configured.
+pub const DEFAULT_REPORTING_TIMEZONE: Tz = chrono_tz::Europe::Stockholm;
+
+/// The calendar date of `time` in the timezone `tz`.
+pub fn local_date(time: DateTime<Utc>, tz: Tz) -> NaiveDate {
+    time.with_timezone(&tz).date().naive_local()
+}
+
+/// The ISO week of `time` in the timezone `tz`.
+pub fn local_week(time: DateTime<Utc>, tz: Tz) -> IsoWeek {
+    local_date(time, tz).iso_week()
+}
+
+/// The instant when `date` starts in the timezone `tz`.
+///
+/// If midnight doesn't exist on that date (i.e. a DST gap) the first existing hour is used.
+pub fn start_of_day(date: NaiveDate, tz: Tz) -> DateTime<Utc> {
+    let mut time = date.and_hms(0, 0, 0);
+    loop {
+        if let Some(local) = tz.from_local_datetime(&time).earliest() {
+            return local.with_timezone(&Utc);
+        }
+        time += Duration::hours(1);
+    }
+}
+
+/// The instant when the ISO week `week` starts in the timezone `tz`.
+pub fn start_of_week(week: IsoWeek, tz: Tz) -> DateTime<Utc> {
+    start_of_day(
+        NaiveDate::from_isoywd(week.year(), week.week(), Weekday::Mon),
+        tz,
+    )
+}
+
+/// How long a [ReportingPeriod] is
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(rename_all = "lowercase"))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PeriodKind {
+    Day,
+    Week,
+    Month,
+    Semester,
+}
+
+impl PeriodKind {
+    pub const ALL: [PeriodKind; 4] = [
+        PeriodKind::Day,
+        PeriodKind::Week,
+        PeriodKind::Month,
+        PeriodKind::Semester,
+    ];
+}
+
+impl Display for PeriodKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PeriodKind::Day => "day",
+            PeriodKind::Week => "week",
+            PeriodKind::Month => "month",
+            PeriodKind::Semester => "semester",
+        })
+    }
+}
+
+impl FromStr for PeriodKind {
+    type Err = PeriodParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PeriodKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.to_string() == s)
+            .ok_or(PeriodParseError)
+    }
+}
+
+/// A calendar day, ISO week, month or semester in the reporting timezone.
+///
+/// Semesters follow the Swedish academic year: spring (VT) is January to June, and autumn (HT) is
+/// July to December.
+///
+/// Periods are written as `2021-06-30`, `2021-W26`, `2021-06` and `VT2021`, which is also how they
+/// are serialized.
+#[cfg_attr(feature = "serde_impl", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde_impl", serde(try_from = "String", into = "String"))]
+#[cfg_attr(feature = "debug", derive(Debug))]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReportingPeriod {
+    kind: PeriodKind,
+    first_day: NaiveDate,
+}
+
+/// Error returned when a [ReportingPeriod] or a [PeriodKind] can't be parsed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PeriodParseError;
+
+impl Display for PeriodParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid period, expected e.g. 2021-06-30, 2021-W26, 2021-06 or VT2021")
+    }
+}
+
+impl ReportingPeriod {
+    /// The period of `kind` which `date` is in
+    pub fn containing(kind: PeriodKind, date: NaiveDate) -> Self {
+        let first_day = match kind {
+            PeriodKind::Day => date,
+            PeriodKind::Week => date - Duration::days(date.weekday().num_days_from_monday().into()),
+            PeriodKind::Month => NaiveDate::from_ymd(date.year(), date.month(), 1),
+            PeriodKind::Semester => {
+                NaiveDate::from_ymd(date.year(), if date.month() <= 6 { 1 } else { 7 }, 1)
+            }
+        };
+        ReportingPeriod { kind, first_day }
+    }
+
+    /// The period of `kind` which `time` is in, in the timezone `tz`
+    pub fn at(kind: PeriodKind, time: DateTime<Utc>, tz: Tz) -> Self {
+        ReportingPeriod::containing(kind, local_date(time, tz))
+    }
+
+    pub fn kind(&self) -> PeriodKind {
+        self.kind
+    }
+
+    pub fn first_day(&self) -> NaiveDate {
+        self.first_day
+    }
+
+    /// The last day of the period, inclusive
+    pub fn last_day(&self) -> NaiveDate {
+        self.next().first_day.pred()
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.first_day <= date && date <= self.last_day()
+    }
+
+    /// The period of the same kind which follows this one
+    pub fn next(&self) -> Self {
+        let first_day = match self.kind {
+            PeriodKind::Day => self.first_day.succ(),
+            PeriodKind::Week => self.first_day + Duration::weeks(1),
+            PeriodKind::Month => add_months(self.first_day, 1),
+            PeriodKind::Semester => add_months(self.first_day, 6),
+        };
+        ReportingPeriod {
+            kind: self.kind,
+            first_day,
+        }
+    }
+
+    /// The period of the same kind which comes before this one
+    pub fn previous(&self) -> Self {
+        ReportingPeriod::containing(self.kind, self.first_day.pred())
+    }
+
+    /// The instants when the period starts and ends in the timezone `tz`, the end is exclusive
+    pub fn range(&self, tz: Tz) -> Range<DateTime<Utc>> {
+        start_of_day(self.first_day, tz)..start_of_day(self.next().first_day, tz)
+    }
+
+    /// Every day of the period, in order
+    pub fn days(&self) -> impl Iterator<Item = NaiveDate> {
+        let end = self.next().first_day;
+        std::iter::successors(Some(self.first_day), |day| Some(day.succ()))
+            .take_while(move |&day| day < end)
+    }
+}
+
+/// The first day of the month `months` after the month of `date`
+fn add_months(date: NaiveDate, months: u32) -> NaiveDate {
+    let month0 = date.month0() + months;
+    NaiveDate::from_ymd(date.year() + (month0 / 12) as i32, month0 % 12 + 1, 1)
+}
+
+impl Display for ReportingPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let day = self.first_day;
+        match self.kind {
+            PeriodKind::Day => write!(f, "{}", day.format("%Y-%m-%d")),
+            PeriodKind::Week => {
+                write!(f, "{}-W{:02}", day.iso_week().year(), day.iso_week().week())
+            }
+            PeriodKind::Month => write!(f, "{}", day.format("%Y-%m")),
+            PeriodKind::Semester if day.month() <= 6 => write!(f, "VT{}", day.year()),
+            PeriodKind::Semester => write!(f, "HT{}", day.year()),
+        }
+    }
+}
+
+impl FromStr for ReportingPeriod {
+    type Err = PeriodParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let year = |year: &str| year.parse::<i32>().map_err(|_| PeriodParseError);
+
+        let (kind, first_day) = if let Some(y) = s.strip_prefix("VT") {
+            (
+                PeriodKind::Semester,
+                NaiveDate::from_ymd_opt(year(y)?, 1, 1),
+            )
+        } else if let Some(y) = s.strip_prefix("HT") {
+            (
+                PeriodKind::Semester,
+                NaiveDate::from_ymd_opt(year(y)?, 7, 1),
+            )
+        } else if let Some((y, week)) = s.split_once("-W") {
+            let week = week.parse().map_err(|_| PeriodParseError)?;
+            let day = NaiveDate::from_isoywd_opt(year(y)?, week, Weekday::Mon);
+            (PeriodKind::Week, day)
+        } else if s.len() == "2021-06".len() {
+            let day = NaiveDate::parse_from_str(&format!("{}-01", s), "%Y-%m-%d").ok();
+            (PeriodKind::Month, day)
+        } else {
+            (
+                PeriodKind::Day,
+                NaiveDate::parse_from_str(s, "%Y-%m-%d").ok(),
+            )
+        };
+
+        let first_day = first_day.ok_or(PeriodParseError)?;
+        Ok(ReportingPeriod { kind, first_day })
+    }
+}
+
+impl TryFrom<String> for ReportingPeriod {
+    type Error = PeriodParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ReportingPeriod> for String {
+    fn from(period: ReportingPeriod) -> Self {
+        period.to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_local_date_around_midnight() {
+        // 23:30 UTC is 01:30 the next day in Stockholm during summer time
+        let time = Utc.ymd(2021, 6, 30).and_hms(23, 30, 0);
+        assert_eq!(
+            local_date(time, DEFAULT_REPORTING_TIMEZONE),
+            NaiveDate::from_ymd(2021, 7, 1)
+        );
+        assert_eq!(local_date(time, Tz::UTC), NaiveDate::from_ymd(2021, 6, 30));
+    }
+
+    #[test]
+    fn test_start_of_day_dst() {
+        let tz = DEFAULT_REPORTING_TIMEZONE;
+
+        // winter time, UTC+1
+        let winter = start_of_day(NaiveDate::from_ymd(2021, 3, 27), tz);
+        assert_eq!(winter, Utc.ymd(2021, 3, 26).and_hms(23, 0, 0));
+
+        // summer time, UTC+2
+        let summer = start_of_day(NaiveDate::from_ymd(2021, 3, 29), tz);
+        assert_eq!(summer, Utc.ymd(2021, 3, 28).and_hms(22, 0, 0));
+
+        // the day of the DST transition is only 23 hours long
+        let transition = start_of_day(NaiveDate::from_ymd(2021, 3, 28), tz);
+        assert_eq!(summer - transition, Duration::hours(23));
+    }
+
+    #[test]
+    fn test_start_of_week() {
+        let tz = DEFAULT_REPORTING_TIMEZONE;
+        let time = Utc.ymd(2021, 1, 3).and_hms(23, 30, 0); // monday 00:30 local time
+        let week = local_week(time, tz);
+        assert_eq!(week.week(), 1);
+        assert_eq!(
+            start_of_week(week, tz),
+            Utc.ymd(2021, 1, 3).and_hms(23, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_reporting_period_boundaries() {
+        let date = NaiveDate::from_ymd(2021, 6, 30); // a wednesday
+        let period = |kind| ReportingPeriod::containing(kind, date);
+
+        assert_eq!(period(PeriodKind::Day).last_day(), date);
+        assert_eq!(
+            period(PeriodKind::Week).first_day(),
+            NaiveDate::from_ymd(2021, 6, 28)
+        );
+        assert_eq!(
+            period(PeriodKind::Week).last_day(),
+            NaiveDate::from_ymd(2021, 7, 4)
+        );
+        assert_eq!(
+            period(PeriodKind::Month).first_day(),
+            NaiveDate::from_ymd(2021, 6, 1)
+        );
+        assert_eq!(
+            period(PeriodKind::Semester).last_day(),
+            NaiveDate::from_ymd(2021, 6, 30)
+        );
+        assert_eq!(
+            period(PeriodKind::Semester).next().last_day(),
+            NaiveDate::from_ymd(2021, 12, 31)
+        );
+        assert_eq!(
+            period(PeriodKind::Month).next().next().previous(),
+            period(PeriodKind::Month).next()
+        );
+        assert_eq!(period(PeriodKind::Week).days().count(), 7);
+
+        // december rolls over into the next year
+        let december =
+            ReportingPeriod::containing(PeriodKind::Month, NaiveDate::from_ymd(2021, 12, 24));
+        assert_eq!(december.next().first_day(), NaiveDate::from_ymd(2022, 1, 1));
+    }
+
+    #[test]
+    fn test_reporting_period_range() {
+        let tz = DEFAULT_REPORTING_TIMEZONE;
+
+        // the month with the switch to summer time is an hour short
+        let march = ReportingPeriod::containing(PeriodKind::Month, NaiveDate::from_ymd(2021, 3, 1));
+        let range = march.range(tz);
+        assert_eq!(range.start, Utc.ymd(2021, 2, 28).and_hms(23, 0, 0));
+        assert_eq!(range.end, Utc.ymd(2021, 3, 31).and_hms(22, 0, 0));
+
+        // 23:30 UTC on a sunday is already monday in Stockholm
+        let time = Utc.ymd(2021, 1, 3).and_hms(23, 30, 0);
+        assert_eq!(
+            ReportingPeriod::at(PeriodKind::Week, time, tz).to_string(),
+            "2021-W01"
+        );
+    }
+
+    #[test]
+    fn test_reporting_period_parse() {
+        for period in &[
+            "2021-06-30",
+            "2021-W26",
+            "2020-W53",
+            "2021-06",
+            "VT2021",
+            "HT2021",
+        ] {
+            let parsed: ReportingPeriod = period.parse().unwrap();
+            assert_eq!(&parsed.to_string(), period);
+        }
+
+        assert_eq!(
+            "HT2021".parse::<ReportingPeriod>().unwrap().first_day(),
+            NaiveDate::from_ymd(2021, 7, 1)
+        );
+        assert_eq!(
+            "2021-W01".parse::<ReportingPeriod>().unwrap().first_day(),
+            NaiveDate::from_ymd(2021, 1, 4)
+        );
+
+        for invalid in &["", "2021-13", "2021-W54", "XT2021", "2021-02-30", "week"] {
+            assert_eq!(invalid.parse::<ReportingPeriod>(), Err(PeriodParseError));
+        }
+
+        assert_eq!("month".parse(), Ok(PeriodKind::Month));
+        assert!("fortnight".parse::<PeriodKind>().is_err());
+    }
+}
@@ -0,0 +1,1471 @@
+//! Contract tests of the types which are sent between the frontend and the backend.
+//!
+//! Every type is serialized and compared to a JSON snapshot in `tests/snapshots`, so that changes
+//! to the wire format are noticed before they break a frontend which was built against the old
+//! one. The snapshots must also still deserialize.
+//!
+//! If a change is intended, update the snapshots by running the tests with `UPDATE_SNAPSHOTS=1`
+//! and commit them.
+
+use chrono::{NaiveDate, TimeZone, Utc, Weekday};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use strecklistan_api::admin::*;
+use strecklistan_api::analytics::*;
+use strecklistan_api::announcement::*;
+use strecklistan_api::approval::*;
+use strecklistan_api::book_account::*;
+use strecklistan_api::catalog::*;
+use strecklistan_api::checkout::*;
+use strecklistan_api::client_error::*;
+use strecklistan_api::correction::*;
+use strecklistan_api::currency::{Currency, NonNegativeCurrency};
+use strecklistan_api::dashboard::*;
+use strecklistan_api::device::*;
+use strecklistan_api::error::*;
+use strecklistan_api::event::*;
+use strecklistan_api::filter_preset::*;
+use strecklistan_api::goal::*;
+use strecklistan_api::import::*;
+use strecklistan_api::inventory::*;
+use strecklistan_api::izettle::*;
+use strecklistan_api::kitchen::*;
+use strecklistan_api::live::*;
+use strecklistan_api::location::*;
+use strecklistan_api::member::*;
+use strecklistan_api::organization::*;
+use strecklistan_api::parked_cart::*;
+use strecklistan_api::preferences::*;
+use strecklistan_api::public::*;
+use strecklistan_api::queue::*;
+use strecklistan_api::register::*;
+use strecklistan_api::report::*;
+use strecklistan_api::store_layout::*;
+use strecklistan_api::supplier::*;
+use strecklistan_api::swish::*;
+use strecklistan_api::time::{PeriodKind, ReportingPeriod};
+use strecklistan_api::training::*;
+use strecklistan_api::transaction::*;
+use strecklistan_api::user::*;
+use strecklistan_api::validation::*;
+
+fn snapshot_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.json", name))
+}
+
+/// Check that `value` serializes like the snapshot `name`, and that the snapshot deserializes
+fn check<T: Serialize + DeserializeOwned>(name: &str, value: &T) {
+    let json = serde_json::to_value(value).unwrap();
+    let path = snapshot_path(name);
+
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        let pretty = serde_json::to_string_pretty(&json).unwrap();
+        fs::write(&path, pretty + "\n").unwrap();
+    }
+
+    let snapshot = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "Missing snapshot {:?}, run the tests with UPDATE_SNAPSHOTS=1 to create it",
+            path
+        )
+    });
+    let snapshot: Value = serde_json::from_str(&snapshot).unwrap();
+    assert_eq!(json, snapshot, "{} doesn't match its snapshot", name);
+
+    let parsed: T = serde_json::from_value(snapshot)
+        .unwrap_or_else(|e| panic!("The snapshot of {} doesn't deserialize: {}", name, e));
+    assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+}
+
+fn time() -> chrono::DateTime<Utc> {
+    Utc.ymd(2021, 7, 1).and_hms(18, 30, 0)
+}
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd(2021, 7, 1)
+}
+
+fn bundle() -> TransactionBundle {
+    let mut item_ids = HashMap::new();
+    item_ids.insert(1, 2);
+    TransactionBundle {
+        description: Some("Kaffe".to_string()),
+        price: Some(Currency::from(500)),
+        change: -2,
+        item_ids,
+        expires_on: Some(date()),
+        modifier_ids: vec![3],
+    }
+}
+
+fn transaction() -> Transaction {
+    Transaction {
+        id: 7,
+        description: Some("Försäljning".to_string()),
+        time: time(),
+        bundles: vec![bundle()],
+        debited_account: 1,
+        credited_account: 2,
+        amount: Currency::from(1000),
+        note: Some("Pubkväll".to_string()),
+        tags: vec!["pub".to_string()],
+        event_code: Some("pubrunda-2021".to_string()),
+        age_verified: false,
+        member_id: None,
+        created_by: Some("tester".to_string()),
+    }
+}
+
+fn goods_return() -> GoodsReturn {
+    GoodsReturn {
+        transaction_id: 9,
+        original_transaction_id: 7,
+        amount: Currency::from(500),
+        reason: ReturnReason::WrongItem,
+        restocked: true,
+        items: vec![(1, 1)].into_iter().collect(),
+    }
+}
+
+fn member() -> Member {
+    Member {
+        id: 3,
+        first_name: "Test".to_string(),
+        last_name: "Testsson".to_string(),
+        nickname: Some("Testis".to_string()),
+        archived_at: None,
+    }
+}
+
+fn account() -> BookAccount {
+    BookAccount {
+        id: 2,
+        name: "Testis".to_string(),
+        account_type: BookAccountType::Liabilities,
+        creditor: Some(3),
+        balance: Currency::from(2500),
+    }
+}
+
+#[test]
+fn test_admin() {
+    check(
+        "admin_status",
+        &AdminStatus {
+            features: FeatureFlags {
+                izettle_enabled: true,
+                smtp_enabled: false,
+                webhooks_enabled: true,
+            },
+            bridge: BridgeStatus {
+                last_poll: Some(time()),
+                pending_payments: 1,
+                oldest_pending: None,
+            },
+            webhooks: vec![WebhookStatus {
+                url: "https://example.com/hook".to_string(),
+                signed: true,
+            }],
+            backup: BackupStatus {
+                configured: true,
+                latest: Some(BackupFile {
+                    name: "backup.sql".to_string(),
+                    modified: time(),
+                    size_bytes: 1024,
+                }),
+            },
+            lock_date: Some(NaiveDate::from_ymd(2021, 6, 30)),
+        },
+    );
+    check(
+        "retention_report",
+        &RetentionReport {
+            dry_run: true,
+            transactions_exported_at: Some(time()),
+            compact_before: Some(time()),
+            compactable_transactions: 120,
+            compactable_lines: 300,
+            prune_audit_before: None,
+            prunable_audit_entries: 0,
+        },
+    );
+}
+
+#[test]
+fn test_analytics() {
+    check(
+        "daily_sales",
+        &DailySales {
+            day: NaiveDate::from_ymd(2021, 6, 1),
+            created_by: Some("tester".to_string()),
+            item_id: 1,
+            sold: 3,
+        },
+    );
+    check(
+        "item_totals",
+        &ItemTotals {
+            item_id: 1,
+            sold: 40,
+            restocked: 48,
+            last_sold: Some(time()),
+        },
+    );
+    check(
+        "modifier_totals",
+        &ModifierTotals {
+            item_id: 1,
+            modifier_id: 3,
+            sold: 12,
+        },
+    );
+    check(
+        "item_sparklines",
+        &ItemSparklines {
+            first_day: NaiveDate::from_ymd(2021, 6, 1),
+            sold: vec![(1, vec![0, 2, 5])].into_iter().collect(),
+        },
+    );
+    check(
+        "cashier_shift",
+        &CashierShift {
+            cashier: "tester".to_string(),
+            first_sale: time(),
+            last_sale: Utc.ymd(2021, 7, 1).and_hms(21, 0, 0),
+            transaction_count: 42,
+            revenue: Currency::from(63000),
+        },
+    );
+    check(
+        "payment_method_sales",
+        &PaymentMethodSales {
+            day: NaiveDate::from_ymd(2021, 6, 1),
+            method: PaymentMethod::Tillgodo,
+            count: 17,
+            revenue: Currency::from(25500),
+        },
+    );
+    check("payment_methods", &PaymentMethod::ALL.to_vec());
+    check(
+        "category_sales",
+        &CategorySales {
+            day: NaiveDate::from_ymd(2021, 6, 1),
+            category: Some("Dryck".to_string()),
+            revenue: Currency::from(41000),
+        },
+    );
+}
+
+#[test]
+fn test_announcements() {
+    let starts_at = Utc.ymd(2021, 7, 18).and_hms(15, 0, 0);
+    check(
+        "new_announcement",
+        &NewAnnouncement {
+            message: "Kortläsaren är trasig ikväll, endast kontant/tillgodo".to_string(),
+            severity: AnnouncementSeverity::Warning,
+            starts_at,
+            ends_at: Some(Utc.ymd(2021, 7, 18).and_hms(23, 0, 0)),
+        },
+    );
+    check(
+        "announcement",
+        &Announcement {
+            id: 3,
+            message: "Stängt under tentaveckan".to_string(),
+            severity: AnnouncementSeverity::Info,
+            starts_at,
+            ends_at: None,
+            created_by: Some("admin".to_string()),
+            created_at: Utc.ymd(2021, 7, 18).and_hms(14, 30, 0),
+        },
+    );
+    check(
+        "announcement_severities",
+        &AnnouncementSeverity::ALL.to_vec(),
+    );
+}
+
+#[test]
+fn test_approvals() {
+    check(
+        "approval_request",
+        &ApprovalRequest {
+            name: "admin".to_string(),
+            password: "hunter2".to_string(),
+            action: GatedAction::Void,
+        },
+    );
+    check(
+        "approval",
+        &Approval {
+            token: "3f0a".to_string(),
+            action: GatedAction::Discount,
+            approved_by: "admin".to_string(),
+            expires: time(),
+        },
+    );
+    check("gated_actions", &GatedAction::ALL.to_vec());
+}
+
+#[test]
+fn test_book_accounts() {
+    check("book_account", &account());
+    check(
+        "new_book_account",
+        &NewBookAccount {
+            name: "Kassa".to_string(),
+            account_type: BookAccountType::Assets,
+            creditor: None,
+        },
+    );
+    check(
+        "master_accounts",
+        &MasterAccounts {
+            bank_account_id: 1,
+            cash_account_id: 2,
+            sales_account_id: 3,
+            purchases_account_id: 4,
+            tips_account_id: 5,
+        },
+    );
+    check(
+        "ledger_entry",
+        &LedgerEntry {
+            transaction_id: 7,
+            time: time(),
+            description: None,
+            change: Currency::from(-500),
+            balance: Currency::from(2000),
+        },
+    );
+}
+
+#[test]
+fn test_catalog() {
+    check(
+        "catalog",
+        &Catalog {
+            version: CATALOG_VERSION,
+            items: vec![CatalogItem {
+                id: 1,
+                name: "Kaffe".to_string(),
+                price: Some(Currency::from(500)),
+                cost_price: Some(Currency::from(150)),
+                image_url: Some("/static/kaffe.png".to_string()),
+                age_restricted: false,
+                prepared: false,
+                categories: vec!["Dryck".to_string()],
+                barcodes: vec!["7310050001234".to_string()],
+                modifiers: vec![CatalogModifier {
+                    group: Some("Storlek".to_string()),
+                    name: "stor".to_string(),
+                    price_delta: Currency::from(200),
+                }],
+            }],
+            bundles: vec![CatalogBundle {
+                id: 2,
+                name: "Två kaffe".to_string(),
+                price: Currency::from(800),
+                image_url: None,
+                item_ids: vec![1, 1],
+            }],
+            layout: StoreLayout {
+                tabs: vec![StoreTab {
+                    name: "Dryck".to_string(),
+                    buttons: vec![QuickButton::new(QuickButtonTarget::Bundle(2), 0, 0)],
+                }],
+            },
+        },
+    );
+    check(
+        "catalog_import_summary",
+        &CatalogImportSummary {
+            items_created: 12,
+            items_updated: 3,
+            bundles_created: 2,
+            bundles_updated: 0,
+            layout_replaced: true,
+        },
+    );
+}
+
+#[test]
+fn test_checkout() {
+    let request = QuoteRequest {
+        bundles: vec![bundle()],
+        tendered: Some(Currency::from(2000)),
+        cash: true,
+    };
+    let rules = CheckoutRules {
+        cash_rounding: Currency::from(100),
+    };
+    let mut prices = HashMap::new();
+    prices.insert(1, Currency::from(300));
+    let mut modifier_prices = HashMap::new();
+    modifier_prices.insert(3, Currency::from(50));
+    check("quote_request", &request);
+    check("checkout_rules", &rules);
+    check(
+        "checkout_quote",
+        &CheckoutQuote::compute(&request, &prices, &modifier_prices, &rules),
+    );
+}
+
+#[test]
+fn test_corrections() {
+    check(
+        "new_correction",
+        &NewCorrection {
+            kind: CorrectionKind::Stock {
+                item_id: 1,
+                change: -24,
+            },
+            reason: "Inventering".to_string(),
+        },
+    );
+    check(
+        "correction",
+        &Correction {
+            id: 3,
+            kind: CorrectionKind::Balance {
+                debited_account: 1,
+                credited_account: 2,
+                amount: 50000.into(),
+            },
+            reason: "Insättningen gjordes två gånger".to_string(),
+            status: CorrectionStatus::Applied,
+            requested_by: Some("cashier".to_string()),
+            requested_at: time(),
+            decided_by: Some("admin".to_string()),
+            decided_at: Some(time()),
+            transaction_id: Some(7),
+        },
+    );
+}
+
+#[test]
+fn test_client_errors() {
+    let mut state = BTreeMap::new();
+    state.insert("page".to_string(), "Store".to_string());
+    check(
+        "client_error_report",
+        &ClientErrorReport {
+            message: "panicked at 'index out of bounds'".to_string(),
+            url: "https://strecklistan.example/store".to_string(),
+            version: "0.10.0".to_string(),
+            state,
+            breadcrumbs: vec![
+                Breadcrumb {
+                    time: time(),
+                    kind: BreadcrumbKind::Fetch,
+                    text: "GET /api/inventory/items -> 200".to_string(),
+                },
+                Breadcrumb {
+                    time: time(),
+                    kind: BreadcrumbKind::Msg,
+                    text: "StoreMsg(ClearCart)".to_string(),
+                },
+            ],
+        },
+    );
+    check("breadcrumb_kinds", &BreadcrumbKind::ALL);
+}
+
+#[test]
+fn test_dashboard() {
+    check(
+        "dashboard",
+        &Dashboard {
+            date: date(),
+            revenue: Currency::from(3500),
+            sales: 3,
+            hours: vec![
+                HourlySales {
+                    hour: 18,
+                    count: 2,
+                    total: Currency::from(1500),
+                },
+                HourlySales {
+                    hour: 19,
+                    count: 1,
+                    total: Currency::from(2000),
+                },
+            ],
+            bridge: Some(BridgeStatus {
+                last_poll: Some(time()),
+                pending_payments: 0,
+                oldest_pending: None,
+            }),
+            bridge_online: true,
+        },
+    );
+}
+
+#[test]
+fn test_errors() {
+    check(
+        "api_error",
+        &ApiError {
+            status: 409,
+            code: ErrorCode::StockEmpty,
+            description: "Not enough in stock".to_string(),
+            details: Some(vec![StockShortage {
+                item_id: 1,
+                name: "Kaffe".to_string(),
+                in_stock: 1,
+                requested: 2,
+            }]),
+        },
+    );
+    check(
+        "error_codes",
+        &vec![
+            ErrorCode::BadRequest,
+            ErrorCode::InvalidFields,
+            ErrorCode::Unauthorized,
+            ErrorCode::InvalidCredentials,
+            ErrorCode::AccountLocked,
+            ErrorCode::Forbidden,
+            ErrorCode::ChooseOrganization,
+            ErrorCode::NotFound,
+            ErrorCode::StockEmpty,
+            ErrorCode::NotAMemberAccount,
+            ErrorCode::AgeNotVerified,
+            ErrorCode::PeriodLocked,
+            ErrorCode::TrainingMode,
+            ErrorCode::ApprovalRequired,
+            ErrorCode::IZettleDisabled,
+            ErrorCode::BridgeOffline,
+            ErrorCode::UnknownPayment,
+            ErrorCode::PaymentFinalized,
+            ErrorCode::AmountMismatch,
+            ErrorCode::AmountMissing,
+            ErrorCode::RateLimited,
+            ErrorCode::PayloadTooLarge,
+            ErrorCode::Internal,
+        ],
+    );
+    check(
+        "field_errors",
+        &vec![
+            FieldError {
+                field: "name".to_string(),
+                error: Invalid::Empty,
+            },
+            FieldError {
+                field: "note".to_string(),
+                error: Invalid::TooLong { max: 500 },
+            },
+            FieldError {
+                field: "amount".to_string(),
+                error: Invalid::OutOfRange {
+                    min: Currency::from(0),
+                    max: MAX_AMOUNT,
+                },
+            },
+            FieldError {
+                field: "ordering_url".to_string(),
+                error: Invalid::NotAUrl,
+            },
+            FieldError {
+                field: "email".to_string(),
+                error: Invalid::NotAnEmail,
+            },
+        ],
+    );
+}
+
+#[test]
+fn test_filter_presets() {
+    check(
+        "filter_preset",
+        &FilterPreset {
+            id: 3,
+            name: "Kort denna månad".to_string(),
+            page: PresetPage::Transactions,
+            filter: PresetFilter {
+                dates: PresetDates::Current(PeriodKind::Month),
+                paid_with: Some(2),
+                item: None,
+                search: "sittning".to_string(),
+                cashier: None,
+            },
+        },
+    );
+    check(
+        "new_filter_preset",
+        &NewFilterPreset {
+            name: "Sommaren".to_string(),
+            page: PresetPage::Analytics,
+            filter: PresetFilter {
+                dates: PresetDates::Fixed {
+                    from: Some(date()),
+                    to: None,
+                },
+                cashier: Some("tester".to_string()),
+                ..Default::default()
+            },
+        },
+    );
+}
+
+#[test]
+fn test_goals() {
+    let goal = SalesGoal {
+        id: 2,
+        name: "Tusen koppar".to_string(),
+        target: GoalTarget::Quantity(1000),
+        scope: GoalScope::Period("VT2021".parse().unwrap()),
+        created_by: Some("tester".to_string()),
+        created_at: time(),
+    };
+    check(
+        "new_sales_goal",
+        &NewSalesGoal {
+            name: "Pubrunda".to_string(),
+            target: GoalTarget::Revenue(Currency::from(500000)),
+            scope: GoalScope::Event("pubrunda".to_string()),
+        },
+    );
+    check(
+        "sales_goal_progress",
+        &SalesGoalProgress {
+            goal,
+            achieved: 420,
+        },
+    );
+}
+
+#[test]
+fn test_import() {
+    check(
+        "import_summary",
+        &ImportSummary {
+            batch_id: Some(3),
+            sales: 120,
+            sales_total: Currency::from(360000),
+            deposits: 14,
+            deposits_total: Currency::from(280000),
+            first_date: Some(NaiveDate::from_ymd(2018, 8, 20)),
+            last_date: Some(date()),
+            errors: vec![ImportError {
+                line: 4,
+                message: "Unknown account \"Kasa\"".to_string(),
+            }],
+        },
+    );
+    check(
+        "import_batch",
+        &ImportBatch {
+            id: 3,
+            created_at: time(),
+            created_by: Some("kassör".to_string()),
+            transaction_count: 134,
+            rolled_back_at: None,
+        },
+    );
+}
+
+#[test]
+fn test_inventory() {
+    check(
+        "inventory_item",
+        &InventoryItem {
+            id: 1,
+            name: "Kaffe".to_string(),
+            price: Some(500),
+            image_url: Some("/static/kaffe.png".to_string()),
+        },
+    );
+    check(
+        "inventory_item_stock",
+        &InventoryItemStock {
+            id: 1,
+            name: "Kaffe".to_string(),
+            price: Some(500),
+            image_url: None,
+            stock: 12,
+            archived_at: None,
+            age_restricted: false,
+            prepared: false,
+            low_stock_at: Some(5),
+            reserved: 2,
+        },
+    );
+    check(
+        "inventory_item_tag",
+        &InventoryItemTag {
+            tag: "dryck".to_string(),
+            item_id: 1,
+        },
+    );
+    check(
+        "inventory_bundle",
+        &InventoryBundle {
+            id: 4,
+            name: "Fika".to_string(),
+            price: Currency::from(1500),
+            image_url: None,
+            item_ids: vec![1, 2],
+        },
+    );
+    check(
+        "item_modifier",
+        &ItemModifier {
+            id: 3,
+            item_id: 1,
+            group: Some("Storlek".to_string()),
+            name: "stor".to_string(),
+            price_delta: Currency::from(50),
+            archived_at: None,
+        },
+    );
+    check(
+        "purchase_limit",
+        &PurchaseLimit {
+            quantity: 2,
+            per: PeriodKind::Day,
+        },
+    );
+    check(
+        "expiring_batch",
+        &ExpiringBatch {
+            item_id: 1,
+            name: "Mjölk".to_string(),
+            expires_on: date(),
+            count: 3,
+        },
+    );
+    check(
+        "inventory_batch_edit",
+        &InventoryBatchEdit {
+            item_ids: vec![1, 2],
+            category: Some("dryck".to_string()),
+            price: Some(PriceAdjustment::Percent(10)),
+            archive: false,
+        },
+    );
+    check(
+        "price_adjustment",
+        &vec![
+            PriceAdjustment::Amount(Currency::from(-100)),
+            PriceAdjustment::Percent(-20),
+        ],
+    );
+}
+
+#[test]
+fn test_izettle() {
+    check(
+        "izettle_payments",
+        &vec![
+            IZettlePayment::Paid { transaction_id: 7 },
+            IZettlePayment::Pending,
+            IZettlePayment::Cancelled,
+            IZettlePayment::Failed {
+                reason: "Declined".to_string(),
+            },
+            IZettlePayment::NoTransaction,
+        ],
+    );
+    check(
+        "izettle_payment_status",
+        &IZettlePaymentStatus {
+            payment: IZettlePayment::Pending,
+            bridge_online: true,
+            at_reader: false,
+        },
+    );
+    check(
+        "izettle_deposit",
+        &IZettleDeposit {
+            credited_account: 2,
+            amount: NonNegativeCurrency::try_from(Currency::from(10000)).unwrap(),
+        },
+    );
+    check(
+        "failed_payment",
+        &FailedPayment {
+            id: 3,
+            reference: 42,
+            reason: "Declined".to_string(),
+            timed_out: false,
+            time: time(),
+            failed_at: time(),
+            description: None,
+            debited_account: 1,
+            credited_account: 2,
+            amount: 1000.into(),
+            bundles: vec![bundle()],
+            created_by: Some("cashier".to_string()),
+            member_id: None,
+            status: FailedPaymentStatus::Retried,
+            resolved_by: Some("admin".to_string()),
+            resolved_at: Some(time()),
+            retry_reference: Some(43),
+        },
+    );
+}
+
+#[test]
+fn test_kitchen() {
+    check(
+        "kitchen_order",
+        &KitchenOrder {
+            transaction_id: 7,
+            time: time(),
+            queue_number: Some(12),
+            lines: vec![KitchenLine {
+                name: "Burgare (ost)".to_string(),
+                quantity: 2,
+            }],
+        },
+    );
+}
+
+#[test]
+fn test_devices() {
+    check(
+        "device",
+        &Device {
+            id: 2,
+            name: "Kassa 1".to_string(),
+            registered_by: Some("admin".to_string()),
+            registered_at: time(),
+            last_seen: Some(time()),
+            online: true,
+        },
+    );
+    check(
+        "device_name",
+        &DeviceName {
+            name: "Kassa 1".to_string(),
+        },
+    );
+}
+
+#[test]
+fn test_live_events() {
+    check(
+        "live_events",
+        &vec![
+            LiveEvent::TransactionCreated { id: 7 },
+            LiveEvent::TransactionDeleted { id: 7 },
+            LiveEvent::TransactionsChanged,
+            LiveEvent::AnnouncementsChanged,
+            LiveEvent::RegisterChanged,
+            LiveEvent::QueueTicketIssued {
+                transaction_id: 7,
+                number: 12,
+            },
+            LiveEvent::QueueChanged,
+            LiveEvent::KitchenChanged,
+            LiveEvent::SwishPaymentsChanged,
+            LiveEvent::CorrectionsChanged,
+            LiveEvent::FailedPaymentsChanged,
+        ],
+    );
+    check(
+        "live_poll",
+        &LivePoll {
+            cursor: 1625164200042,
+            events: vec![LiveEvent::TransactionCreated { id: 7 }],
+        },
+    );
+}
+
+#[test]
+fn test_locations() {
+    check(
+        "storage_location",
+        &StorageLocation {
+            id: 1,
+            name: "Förråd".to_string(),
+            items: vec![(1, 24)].into_iter().collect(),
+        },
+    );
+    check(
+        "new_storage_location",
+        &NewStorageLocation {
+            name: "Förråd".to_string(),
+        },
+    );
+    check(
+        "stock_transfer",
+        &StockTransfer {
+            item_id: 1,
+            from: Some(1),
+            to: None,
+            amount: 12,
+        },
+    );
+    check(
+        "low_stock_alert",
+        &LowStockAlert {
+            item_id: 1,
+            name: "Kaffe".to_string(),
+            for_sale: 2,
+            low_stock_at: 5,
+            stored: vec![(1, 24)].into_iter().collect(),
+        },
+    );
+}
+
+#[test]
+fn test_members() {
+    check("member", &member());
+    check(
+        "new_member",
+        &NewMember {
+            first_name: "Test".to_string(),
+            last_name: "Testsson".to_string(),
+            nickname: None,
+        },
+    );
+    check(
+        "member_search_result",
+        &MemberSearchResult {
+            member: member(),
+            account: account(),
+        },
+    );
+    check(
+        "member_contact",
+        &MemberContact {
+            member_id: 1,
+            email: Some("test@example.com".to_string()),
+            email_verified_at: Some(time()),
+            preferences: ContactPreferences {
+                receipts: true,
+                balance_reminders: false,
+                event_info: true,
+            },
+        },
+    );
+    check(
+        "mail_topics",
+        &vec![
+            MailTopic::Receipts,
+            MailTopic::BalanceReminders,
+            MailTopic::EventInfo,
+        ],
+    );
+}
+
+#[test]
+fn test_organizations_and_users() {
+    check(
+        "organization",
+        &Organization {
+            id: 1,
+            name: "Sektionen".to_string(),
+        },
+    );
+    check(
+        "branding",
+        &Branding {
+            name: "Sektionen".to_string(),
+            org_number: Some("802000-0000".to_string()),
+            footer_text: Some("Bankgiro 123-4567".to_string()),
+            logo_updated_at: Some(Utc.ymd(2021, 7, 19).and_hms(12, 0, 0)),
+        },
+    );
+    check(
+        "branding_settings",
+        &BrandingSettings {
+            name: "Sektionen".to_string(),
+            org_number: None,
+            footer_text: Some("Bankgiro 123-4567".to_string()),
+        },
+    );
+    check(
+        "user",
+        &User {
+            name: "tester".to_string(),
+            display_name: Some("Test Testsson".to_string()),
+            is_admin: true,
+        },
+    );
+    check(
+        "credentials",
+        &Credentials {
+            name: "tester".to_string(),
+            password: "hunter2".to_string(),
+            organization: Some(1),
+        },
+    );
+}
+
+#[test]
+fn test_parked_carts() {
+    check(
+        "parked_cart",
+        &ParkedCart {
+            id: 5,
+            name: "Bord 3".to_string(),
+            created_at: time(),
+            debited_account: Some(2),
+            bundles: vec![bundle()],
+        },
+    );
+    check(
+        "new_parked_cart",
+        &NewParkedCart {
+            name: "Bord 3".to_string(),
+            debited_account: None,
+            bundles: vec![bundle()],
+        },
+    );
+}
+
+#[test]
+fn test_preferences() {
+    check("default_preferences", &UserPreferences::default());
+    check(
+        "preferences",
+        &UserPreferences {
+            default_page: DefaultPage::Deposit,
+            theme: Theme::Dark,
+            language: Language::English,
+            quick_buttons: QuickButtonsLayout::List,
+            notifications: NotificationSettings {
+                enabled: true,
+                duration_percent: 150,
+                sticky_errors: true,
+            },
+            pinned: vec![QuickButtonTarget::Item(1), QuickButtonTarget::Bundle(4)],
+            deposit_presets: vec![NonNegativeCurrency::try_from(Currency::from(5000)).unwrap()],
+            achievements: AchievementProgress {
+                unlocked: vec![Achievement::FirstSaleOfTheDay],
+                coffees_sold: 42,
+            },
+        },
+    );
+}
+
+#[test]
+fn test_public() {
+    check(
+        "public_stats",
+        &PublicStats {
+            items_sold_today: 42,
+            stock: vec![PublicStock {
+                name: "Kaffe".to_string(),
+                image_url: None,
+                stock: 12,
+            }],
+            goals: vec![],
+        },
+    );
+    check(
+        "public_balance",
+        &PublicBalance {
+            first_name: "Stina".to_string(),
+            balance: Currency::from(7500),
+            deposits: vec![PublicDeposit {
+                time: time(),
+                amount: Currency::from(10000),
+            }],
+            swish: true,
+        },
+    );
+    check(
+        "public_open_status",
+        &PublicOpenStatus {
+            open: true,
+            since: Some(time()),
+        },
+    );
+}
+
+#[test]
+fn test_swish() {
+    check(
+        "swish_deposit",
+        &SwishDeposit {
+            amount: NonNegativeCurrency::try_from(Currency::from(20000)).unwrap(),
+        },
+    );
+    check(
+        "swish_payment",
+        &SwishPayment {
+            id: "0D6CC1C4E37B4E1E9C4C6A5F0E0B6A2D".to_string(),
+            member_id: 3,
+            credited_account: 9,
+            amount: Currency::from(20000),
+            status: SwishStatus::Paid,
+            token: None,
+            error: None,
+            transaction_id: Some(42),
+            created_at: time(),
+            completed_at: Some(time()),
+        },
+    );
+}
+
+#[test]
+fn test_queue() {
+    check(
+        "queue_ticket",
+        &QueueTicket {
+            id: 4,
+            number: 12,
+            transaction_id: 7,
+            created_at: time(),
+            ready_at: None,
+        },
+    );
+}
+
+#[test]
+fn test_register() {
+    let shift = RegisterShift {
+        id: 3,
+        opened_by: Some("tester".to_string()),
+        opened_at: time(),
+        closed_by: None,
+        closed_at: None,
+        auto_closed: false,
+        reconciled_by: None,
+        reconciled_at: None,
+        opened_on: Some("Kassa 1".to_string()),
+        closed_on: None,
+    };
+    check(
+        "register_status",
+        &RegisterStatus {
+            open_shift: Some(shift.clone()),
+            closes_at: Some(Utc.ymd(2021, 7, 2).and_hms(0, 0, 0)),
+            unreconciled: vec![RegisterShift {
+                id: 2,
+                closed_at: Some(time()),
+                auto_closed: true,
+                ..shift
+            }],
+        },
+    );
+}
+
+#[test]
+fn test_report() {
+    check(
+        "daily_report",
+        &DailyReport {
+            date: date(),
+            sales: vec![AccountTotal {
+                account_id: 1,
+                name: "Kassa".to_string(),
+                count: 3,
+                total: Currency::from(4500),
+            }],
+            deposits: vec![],
+            tips: vec![AccountTotal {
+                account_id: 2,
+                name: "Bankkonto".to_string(),
+                count: 1,
+                total: Currency::from(500),
+            }],
+            items: vec![ItemSales {
+                item_id: 1,
+                name: "Kaffe".to_string(),
+                count: 9,
+            }],
+            expected_cash: Currency::from(4500),
+            fees: vec![provider_fees()],
+            transaction_count: 3,
+        },
+    );
+    check(
+        "monthly_report",
+        &MonthlyReport {
+            month: NaiveDate::from_ymd(2021, 7, 1),
+            days: vec![DailyFees {
+                date: date(),
+                fees: vec![provider_fees()],
+            }],
+            fees: vec![provider_fees()],
+        },
+    );
+}
+
+fn provider_fees() -> ProviderFees {
+    ProviderFees {
+        provider: PaymentProvider::IZettle,
+        rate: 185,
+        count: 1,
+        gross: Currency::from(500),
+        fees: Currency::from(9),
+    }
+}
+
+#[test]
+fn test_member_liability() {
+    check(
+        "member_liability",
+        &MemberLiability {
+            member_id: 1,
+            account_id: 5,
+            name: "Stina Svensson".to_string(),
+            balance: Currency::from(7500),
+            last_activity: Some(time()),
+        },
+    );
+}
+
+#[test]
+fn test_stock_value_report() {
+    check(
+        "stock_value_report",
+        &StockValueReport {
+            valuation: StockValuation::Fifo,
+            items: vec![
+                ItemStockValue {
+                    item_id: 1,
+                    name: "Kaffe".to_string(),
+                    stock: 12,
+                    cost_price: Some(Currency::from(300)),
+                    value: Some(Currency::from(3300)),
+                },
+                ItemStockValue {
+                    item_id: 2,
+                    name: "Te".to_string(),
+                    stock: 4,
+                    cost_price: None,
+                    value: None,
+                },
+            ],
+        },
+    );
+}
+
+#[test]
+fn test_dormant_balance_report() {
+    check(
+        "dormant_balance_report",
+        &DormantBalanceReport {
+            dormant_days: 365,
+            forfeit_days: 1095,
+            balances: vec![DormantBalance {
+                member_id: 1,
+                account_id: 11,
+                name: "Stina Svensson".to_string(),
+                balance: Currency::from(2500),
+                last_activity: time(),
+                reminded_at: Some(time()),
+                forfeitable: true,
+            }],
+        },
+    );
+}
+
+#[test]
+fn test_event_profit_and_loss() {
+    check(
+        "event_profit_and_loss",
+        &EventProfitAndLoss {
+            event_code: "pubrunda-2021".to_string(),
+            revenue: Currency::from(25000),
+            costs: Currency::from(18000),
+            transaction_count: 12,
+            first_transaction: time(),
+            last_transaction: time(),
+        },
+    );
+}
+
+#[test]
+fn test_event_signups() {
+    let reservations: BTreeMap<InventoryItemId, u32> = vec![(3, 2)].into_iter().collect();
+    check(
+        "event_signup",
+        &EventSignup {
+            id: 5,
+            event: 2,
+            name: "Kalle Anka".to_string(),
+            email: "kalle@example.com".to_string(),
+            reservations: reservations.clone(),
+        },
+    );
+    check(
+        "new_event_signup",
+        &NewEventSignup {
+            name: "Kalle Anka".to_string(),
+            email: "kalle@example.com".to_string(),
+            reservations,
+        },
+    );
+}
+
+#[test]
+fn test_store_layout() {
+    check(
+        "store_layout",
+        &StoreLayout {
+            tabs: vec![StoreTab {
+                name: "Dryck".to_string(),
+                buttons: vec![QuickButton {
+                    target: QuickButtonTarget::Item(1),
+                    x: 0,
+                    y: 1,
+                    width: 2,
+                    height: 1,
+                    color: Some("#ff8800".to_string()),
+                }],
+            }],
+        },
+    );
+}
+
+#[test]
+fn test_suppliers() {
+    check(
+        "supplier",
+        &Supplier {
+            id: 2,
+            name: "Grossisten".to_string(),
+            contact: Some("order@example.com".to_string()),
+            ordering_url: Some("https://example.com".to_string()),
+            delivery_days: vec![Weekday::Tue, Weekday::Fri],
+            item_ids: vec![1],
+        },
+    );
+    check(
+        "new_supplier",
+        &NewSupplier {
+            name: "Grossisten".to_string(),
+            contact: None,
+            ordering_url: None,
+            delivery_days: vec![Weekday::Mon],
+            item_ids: vec![],
+        },
+    );
+}
+
+#[test]
+fn test_time() {
+    let day = NaiveDate::from_ymd(2021, 6, 30);
+    check(
+        "reporting_periods",
+        &[
+            ReportingPeriod::containing(PeriodKind::Day, day),
+            ReportingPeriod::containing(PeriodKind::Week, day),
+            ReportingPeriod::containing(PeriodKind::Month, day),
+            ReportingPeriod::containing(PeriodKind::Semester, day),
+        ],
+    );
+    check("period_kind", &PeriodKind::Semester);
+}
+
+#[test]
+fn test_training() {
+    check(
+        "training_transaction",
+        &TrainingTransaction {
+            id: 4,
+            time: time(),
+            created_by: "tester".to_string(),
+            description: Some("Försäljning".to_string()),
+            debited_account: 1,
+            credited_account: 2,
+            amount: Currency::from(1000),
+            bundles: vec![bundle()],
+        },
+    );
+}
+
+#[test]
+fn test_transactions() {
+    check("transaction", &transaction());
+    check(
+        "new_transaction",
+        &NewTransaction {
+            description: Some("Försäljning".to_string()),
+            bundles: vec![bundle()],
+            debited_account: 1,
+            credited_account: 2,
+            amount: Currency::from(1000),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: Some("3f0a".to_string()),
+        },
+    );
+    check(
+        "transaction_annotation",
+        &TransactionAnnotation {
+            note: Some("Fel pris".to_string()),
+            tags: vec!["rättelse".to_string()],
+            event_code: None,
+        },
+    );
+    check(
+        "transaction_batch",
+        &TransactionBatch {
+            transaction_ids: vec![1, 2],
+            operation: BatchOperation::Void {
+                reason: "Övningspass".to_string(),
+            },
+        },
+    );
+    check(
+        "transaction_batch_tags",
+        &TransactionBatch {
+            transaction_ids: vec![3],
+            operation: BatchOperation::AddTags {
+                tags: vec!["felpris".to_string()],
+            },
+        },
+    );
+    check(
+        "batch_outcome",
+        &BatchOutcome {
+            batch_id: "6b1f2c3e-54a1-4d6e-9a0b-2f1c5d7e8a90".to_string(),
+            transaction_ids: vec![1, 2],
+        },
+    );
+    check(
+        "transaction_detail",
+        &TransactionDetail {
+            transaction: transaction(),
+            izettle_reference: Some(12),
+            deleted_at: None,
+            goods_return: None,
+            returns: vec![goods_return()],
+            device: Some("Kassa 1".to_string()),
+        },
+    );
+    check("goods_return", &goods_return());
+    check(
+        "new_goods_return",
+        &NewGoodsReturn {
+            items: vec![(1, 1)].into_iter().collect(),
+            amount: Currency::from(500),
+            reason: ReturnReason::Defective,
+            restock: false,
+            note: Some("Trasig kopp".to_string()),
+        },
+    );
+    check("return_reasons", &ReturnReason::ALL.to_vec());
+    check(
+        "duplicate_pair",
+        &DuplicatePair {
+            original: transaction(),
+            duplicate: Transaction {
+                id: 8,
+                ..transaction()
+            },
+        },
+    );
+}
@@ -0,0 +1,23 @@
+//! The charts of the strecklistan frontend, drawn as SVG.
+//!
+//! Only a few pages show charts, and the register never does, so they are built as a wasm module
+//! of their own which the frontend loads when it first shows a chart. The frontend describes the
+//! chart with a [ChartSpec], which is all it depends on this crate for, and calls [render_chart]
+//! of the loaded module with it.
+
+mod spec;
+
+#[cfg(feature = "render")]
+mod render;
+
+pub use spec::*;
+
+/// Draw the chart of `spec`, a [ChartSpec] as JSON, as HTML. Invalid specs draw nothing.
+#[cfg(feature = "render")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+pub fn render_chart(spec: &str) -> String {
+    match serde_json::from_str::<ChartSpec>(spec) {
+        Ok(spec) => render::render(&spec),
+        Err(_) => String::new(),
+    }
+}
@@ -0,0 +1,447 @@
+//! Drawing of the charts as SVG, written out as a string of HTML for the frontend to insert.
+//!
+//! Hovering a column, or a slice of a pie, shows its exact values. Charts fill the width of their
+//! container, see `charts.css` of the frontend.
+
+use crate::{ChartKind, ChartSpec, Unit};
+use std::f64::consts::PI;
+use std::fmt::Write;
+use strecklistan_api::currency::Currency;
+
+const WIDTH: f64 = 600.0;
+const HEIGHT: f64 = 250.0;
+
+/// Room for the labels of the axes
+const MARGIN_LEFT: f64 = 56.0;
+const MARGIN_BOTTOM: f64 = 32.0;
+const MARGIN_TOP: f64 = 8.0;
+const MARGIN_RIGHT: f64 = 8.0;
+
+const PIE_SIZE: f64 = 200.0;
+const PIE_RADIUS: f64 = 95.0;
+
+/// The number of steps of the y axis to aim for
+const Y_STEPS: i64 = 4;
+
+/// At most this many labels are written under the x axis, the others are only in the tooltips
+const MAX_X_LABELS: usize = 12;
+
+/// The share of a column which its bars take up
+const BAR_FILL: f64 = 0.8;
+
+/// The colors of the series, repeated if there are more of them
+const COLORS: &[&str] = &[
+    "#785ddc", "#38a169", "#dd6b20", "#3182ce", "#d53f8c", "#d69e2e", "#319795", "#718096",
+];
+
+pub fn render(chart: &ChartSpec) -> String {
+    let (width, height, shapes) = match chart.kind {
+        ChartKind::Pie => (PIE_SIZE, PIE_SIZE, pie(chart)),
+        _ => (WIDTH, HEIGHT, columns(chart)),
+    };
+    let svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" class="chart_svg" viewBox="0 0 {} {}"{}>{}</svg>"#,
+        width,
+        height,
+        if chart.compact {
+            r#" preserveAspectRatio="none""#
+        } else {
+            ""
+        },
+        shapes,
+    );
+    if chart.compact {
+        return svg;
+    }
+
+    let class = if chart.kind == ChartKind::Pie {
+        "chart chart_pie"
+    } else {
+        "chart"
+    };
+    format!(r#"<div class="{}">{}{}</div>"#, class, svg, legend(chart))
+}
+
+fn fmt(chart: &ChartSpec, value: i64) -> String {
+    match chart.unit {
+        Unit::Count => value.to_string(),
+        Unit::Currency => format!("{}:-", Currency::from(value)),
+    }
+}
+
+/// The number of columns of a chart with axes
+fn len(chart: &ChartSpec) -> usize {
+    let values = chart.series.iter().map(|series| series.values.len());
+    values.max().unwrap_or(0).max(chart.labels.len())
+}
+
+fn value_at(chart: &ChartSpec, series: usize, column: usize) -> i64 {
+    chart.series[series]
+        .values
+        .get(column)
+        .copied()
+        .unwrap_or(0)
+}
+
+/// The lowest and highest values to make room for, including zero
+fn range(chart: &ChartSpec) -> (i64, i64) {
+    let columns = 0..len(chart);
+    let series = 0..chart.series.len();
+    if chart.kind == ChartKind::StackedBar {
+        // positive values are stacked upwards, and negative ones downwards
+        columns.fold((0, 0), |(low, high), column| {
+            let values = series.clone().map(|series| value_at(chart, series, column));
+            let up: i64 = values.clone().filter(|&value| value > 0).sum();
+            let down: i64 = values.filter(|&value| value < 0).sum();
+            (low.min(down), high.max(up))
+        })
+    } else {
+        let values = chart.series.iter().flat_map(|series| series.values.iter());
+        values.fold((0, 0), |(low, high), &value| {
+            (low.min(value), high.max(value))
+        })
+    }
+}
+
+fn columns(chart: &ChartSpec) -> String {
+    let len = len(chart);
+    if len == 0 {
+        return String::new();
+    }
+
+    let (left, top, width, height) = if chart.compact {
+        (0.0, 0.0, WIDTH, HEIGHT)
+    } else {
+        let width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+        let height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+        (MARGIN_LEFT, MARGIN_TOP, width, height)
+    };
+
+    let (low, high) = range(chart);
+    let steps = if chart.compact {
+        vec![low, high.max(low + 1)]
+    } else {
+        steps(low, high, chart.unit)
+    };
+    let (low, high) = (steps[0], steps[steps.len() - 1]);
+
+    let y = |value: i64| top + height - (value - low) as f64 / (high - low) as f64 * height;
+    let column = width / len as f64;
+    let x = |index: usize| left + column * (index as f64 + 0.5);
+
+    let mut svg = String::new();
+    if !chart.compact {
+        for &step in &steps {
+            let _ = write!(
+                svg,
+                r#"<line class="chart_grid" x1="{}" x2="{}" y1="{}" y2="{}"/>"#,
+                left,
+                left + width,
+                y(step),
+                y(step),
+            );
+            let _ = write!(
+                svg,
+                r#"<text class="chart_axis_label" x="{}" y="{}" text-anchor="end" dominant-baseline="middle">{}</text>"#,
+                left - 6.0,
+                y(step),
+                escape(&fmt(chart, step)),
+            );
+        }
+
+        let every = (len + MAX_X_LABELS - 1) / MAX_X_LABELS;
+        for (index, label) in chart.labels.iter().enumerate().step_by(every) {
+            let _ = write!(
+                svg,
+                r#"<text class="chart_axis_label" x="{}" y="{}" text-anchor="middle">{}</text>"#,
+                x(index),
+                top + height + 16.0,
+                escape(label),
+            );
+        }
+    }
+
+    match chart.kind {
+        ChartKind::Line => {
+            for (series, color) in chart.series.iter().zip(colors()) {
+                let points: Vec<String> = series
+                    .values
+                    .iter()
+                    .enumerate()
+                    .map(|(index, &value)| format!("{:.1},{:.1}", x(index), y(value)))
+                    .collect();
+                let _ = write!(
+                    svg,
+                    r#"<polyline class="chart_line" points="{}" stroke="{}"/>"#,
+                    points.join(" "),
+                    color,
+                );
+            }
+        }
+        ChartKind::Bar => {
+            let bar = column * BAR_FILL / chart.series.len() as f64;
+            for index in 0..len {
+                let start = x(index) - column * BAR_FILL / 2.0;
+                for (series, color) in (0..chart.series.len()).zip(colors()) {
+                    let value = value_at(chart, series, index);
+                    let x = start + bar * series as f64;
+                    svg += &rect(x, bar, y(0), y(value), color);
+                }
+            }
+        }
+        ChartKind::StackedBar => {
+            let bar = column * BAR_FILL;
+            for index in 0..len {
+                let (mut up, mut down) = (0, 0);
+                for (series, color) in (0..chart.series.len()).zip(colors()) {
+                    let value = value_at(chart, series, index);
+                    let base = if value < 0 { &mut down } else { &mut up };
+                    let from = *base;
+                    *base += value;
+                    svg += &rect(x(index) - bar / 2.0, bar, y(from), y(*base), color);
+                }
+            }
+        }
+        ChartKind::Pie => {}
+    }
+
+    if !chart.compact {
+        // drawn last, so that they are on top and catch the hovering
+        for index in 0..len {
+            let _ = write!(
+                svg,
+                r#"<rect class="chart_hover" x="{}" y="{}" width="{}" height="{}"><title>{}</title></rect>"#,
+                left + column * index as f64,
+                top,
+                column,
+                height,
+                escape(&tooltip(chart, index)),
+            );
+        }
+
+        let _ = write!(
+            svg,
+            r#"<line class="chart_axis" x1="{}" x2="{}" y1="{}" y2="{}"/>"#,
+            left,
+            left + width,
+            y(0),
+            y(0),
+        );
+    }
+
+    svg
+}
+
+/// The label and every value of the column at `index`
+fn tooltip(chart: &ChartSpec, index: usize) -> String {
+    let label = chart
+        .labels
+        .get(index)
+        .map(String::as_str)
+        .unwrap_or_default();
+    if let [series] = chart.series.as_slice() {
+        let value = fmt(chart, series.values.get(index).copied().unwrap_or(0));
+        return format!("{}: {}", label, value);
+    }
+
+    let mut tooltip = label.to_string();
+    for (i, series) in chart.series.iter().enumerate() {
+        let value = fmt(chart, value_at(chart, i, index));
+        let _ = write!(tooltip, "\n{}: {}", series.name, value);
+    }
+    tooltip
+}
+
+/// The sum of each series, for the slices of a pie. Negative sums get no slice.
+fn sums(chart: &ChartSpec) -> Vec<i64> {
+    chart
+        .series
+        .iter()
+        .map(|series| series.values.iter().sum::<i64>().max(0))
+        .collect()
+}
+
+fn pie(chart: &ChartSpec) -> String {
+    let sums = sums(chart);
+    let total: i64 = sums.iter().sum();
+    if total == 0 {
+        return String::new();
+    }
+
+    let center = PIE_SIZE / 2.0;
+    let point = |angle: f64| {
+        // starting from the top, clockwise
+        let x = center + PIE_RADIUS * angle.sin();
+        let y = center - PIE_RADIUS * angle.cos();
+        format!("{:.2} {:.2}", x, y)
+    };
+
+    let mut angle = 0.0;
+    let mut svg = String::new();
+    for ((series, &sum), color) in chart.series.iter().zip(&sums).zip(colors()) {
+        if sum == 0 {
+            continue;
+        }
+        let share = sum as f64 / total as f64;
+        let end = angle + share * 2.0 * PI;
+        let title = format!(
+            "{}: {} ({:.0}%)",
+            series.name,
+            fmt(chart, sum),
+            share * 100.0
+        );
+
+        // an arc can't go all the way round
+        if sum == total {
+            let _ = write!(
+                svg,
+                r#"<circle class="chart_slice" cx="{}" cy="{}" r="{}" fill="{}"><title>{}</title></circle>"#,
+                center,
+                center,
+                PIE_RADIUS,
+                color,
+                escape(&title),
+            );
+        } else {
+            let large_arc = if share > 0.5 { 1 } else { 0 };
+            let _ = write!(
+                svg,
+                r#"<path class="chart_slice" d="M {} {} L {} A {} {} 0 {} 1 {} Z" fill="{}"><title>{}</title></path>"#,
+                center,
+                center,
+                point(angle),
+                PIE_RADIUS,
+                PIE_RADIUS,
+                large_arc,
+                point(end),
+                color,
+                escape(&title),
+            );
+        }
+        angle = end;
+    }
+    svg
+}
+
+/// The colors of the series, which a chart of a single series doesn't need
+fn legend(chart: &ChartSpec) -> String {
+    let pie = chart.kind == ChartKind::Pie;
+    if !pie && chart.series.len() < 2 {
+        return String::new();
+    }
+
+    let sums = sums(chart);
+    let total: i64 = sums.iter().sum();
+    let mut html = String::from(r#"<ul class="chart_legend">"#);
+    for ((series, sum), color) in chart.series.iter().zip(sums).zip(colors()) {
+        let _ = write!(
+            html,
+            r#"<li><span class="chart_legend_color" style="background-color: {}"></span>{}"#,
+            color,
+            escape(&series.name),
+        );
+        if pie && total > 0 {
+            let _ = write!(
+                html,
+                r#"<span class="chart_legend_value">{} ({:.0}%)</span>"#,
+                escape(&fmt(chart, sum)),
+                sum as f64 / total as f64 * 100.0,
+            );
+        }
+        html += "</li>";
+    }
+    html += "</ul>";
+    html
+}
+
+fn colors() -> impl Iterator<Item = &'static str> {
+    COLORS.iter().copied().cycle()
+}
+
+/// A bar between the heights `y0` and `y1`, whichever is higher
+fn rect(x: f64, width: f64, y0: f64, y1: f64, color: &str) -> String {
+    format!(
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+        x,
+        y0.min(y1),
+        width,
+        (y1 - y0).abs(),
+        color,
+    )
+}
+
+/// Escape text for use in the markup, where the labels and names would otherwise be read as tags
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Evenly spaced values from at most `low` to at least `high`, in round steps. Amounts of money
+/// are stepped in whole kronor.
+fn steps(low: i64, high: i64, unit: Unit) -> Vec<i64> {
+    let scale = match unit {
+        Unit::Count => 1,
+        Unit::Currency => 100,
+    };
+
+    let range = ((high - low) / scale).max(1);
+    let rough = (range + Y_STEPS - 1) / Y_STEPS;
+    let magnitude = 10i64.pow((rough as f64).log10().floor() as u32);
+    let step = [1, 2, 5, 10]
+        .iter()
+        .map(|factor| factor * magnitude)
+        .find(|&step| step >= rough)
+        .unwrap_or(rough)
+        * scale;
+
+    let first = (low as f64 / step as f64).floor() as i64;
+    let last = (high as f64 / step as f64).ceil() as i64;
+    (first..=last.max(first + 1)).map(|i| i * step).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Series;
+
+    #[test]
+    fn test_steps() {
+        assert_eq!(steps(0, 10, Unit::Count), [0, 5, 10]);
+        assert_eq!(steps(0, 37, Unit::Count), [0, 10, 20, 30, 40]);
+        assert_eq!(steps(0, 0, Unit::Count), [0, 1]);
+        assert_eq!(steps(-15, 15, Unit::Count), [-20, -10, 0, 10, 20]);
+
+        // whole kronor, even for small amounts
+        assert_eq!(steps(0, 150, Unit::Currency), [0, 100, 200]);
+        assert_eq!(
+            steps(0, 123_456, Unit::Currency),
+            [0, 50_000, 100_000, 150_000]
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        let chart = ChartSpec {
+            kind: ChartKind::Pie,
+            unit: Unit::Count,
+            labels: vec![],
+            series: vec![Series {
+                name: "<b>Fika & bullar</b>".to_string(),
+                values: vec![1, 2],
+            }],
+            compact: false,
+        };
+        let html = render(&chart);
+        assert!(html.contains("&lt;b&gt;Fika &amp; bullar&lt;/b&gt;"));
+        assert!(!html.contains("<b>"));
+    }
+}
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChartKind {
+    Line,
+
+    /// The series side by side
+    Bar,
+
+    /// The series on top of each other, as parts of a whole
+    StackedBar,
+
+    /// The sum of every series, as a share of the sum of all of them
+    Pie,
+}
+
+/// What the values are, which decides how they're written
+#[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Unit {
+    Count,
+
+    /// Amounts in öre
+    Currency,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Series {
+    pub name: String,
+    pub values: Vec<i64>,
+}
+
+/// What to draw, which the frontend hands over to the charts module
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ChartSpec {
+    pub kind: ChartKind,
+    pub unit: Unit,
+
+    /// The labels of the x axis, one for each value of the series
+    pub labels: Vec<String>,
+
+    pub series: Vec<Series>,
+
+    /// Only draw the lines or bars, without axes, legend or tooltips, stretched to fill the size
+    /// given by the stylesheet
+    pub compact: bool,
+}
@@ -0,0 +1,216 @@
+//! Easter eggs for the people behind the counter. Achievements are unlocked by what the user
+//! does in this browser, stored in their preferences, and celebrated by the penguin.
+
+use crate::i18n::Text;
+use crate::live::RecentTransactions;
+use crate::notification_manager::{Notification, NotificationLevel};
+use crate::strings;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use strecklistan_api::{
+    inventory::{InventoryItemId, InventoryItemStock},
+    preferences::{Achievement, AchievementProgress, COFFEE_ACHIEVEMENT},
+    transaction::{NewTransaction, TransactionId},
+};
+
+/// For how long an unlocked achievement is shown
+pub const ACHIEVEMENT_MS: u32 = 8000;
+
+/// Something the user did which may unlock an achievement
+#[derive(Clone, Debug)]
+pub enum AchievementEvent {
+    /// The user made a sale
+    Sale {
+        transaction_id: TransactionId,
+        /// When the day of the sale started, in the reporting timezone
+        day_start: DateTime<Utc>,
+        coffees: u32,
+    },
+
+    /// The counted cash matched what was expected
+    CashBalanced,
+}
+
+/// Record `event`, returns the achievements which it unlocked.
+///
+/// Whether a sale was the first of the day is checked against the recent transactions, so it's
+/// only noticed if they have been loaded.
+pub fn record(
+    progress: &mut AchievementProgress,
+    event: AchievementEvent,
+    recent: &RecentTransactions,
+) -> Vec<Achievement> {
+    let reached = match event {
+        AchievementEvent::Sale {
+            transaction_id,
+            day_start,
+            coffees,
+        } => {
+            let first_of_day = recent.is_loaded()
+                && !recent
+                    .iter()
+                    .any(|tr| tr.id != transaction_id && tr.time >= day_start);
+            progress.coffees_sold = progress.coffees_sold.saturating_add(coffees);
+
+            let mut reached = vec![];
+            if first_of_day {
+                reached.push(Achievement::FirstSaleOfTheDay);
+            }
+            if progress.coffees_sold >= COFFEE_ACHIEVEMENT {
+                reached.push(Achievement::HundredthCoffee);
+            }
+            reached
+        }
+        AchievementEvent::CashBalanced => vec![Achievement::BalancedCashCount],
+    };
+
+    reached
+        .into_iter()
+        .filter(|&achievement| progress.unlock(achievement))
+        .collect()
+}
+
+/// How many coffees are sold by a transaction, going by the names of the items
+pub fn count_coffees(
+    transaction: &NewTransaction,
+    inventory: &HashMap<InventoryItemId, InventoryItemStock>,
+) -> u32 {
+    let is_coffee = |item_id: &InventoryItemId| {
+        inventory
+            .get(item_id)
+            .map(|item| {
+                let name = item.name.to_lowercase();
+                name.contains("kaffe") || name.contains("coffee")
+            })
+            .unwrap_or(false)
+    };
+
+    transaction
+        .bundles
+        .iter()
+        .filter(|bundle| bundle.change < 0)
+        .flat_map(|bundle| {
+            bundle
+                .item_ids
+                .iter()
+                .filter(|(item_id, _)| is_coffee(item_id))
+                .map(move |(_, &count)| count * bundle.change.unsigned_abs())
+        })
+        .sum()
+}
+
+/// A notification with the penguin, celebrating `achievement`
+pub fn notification(achievement: Achievement) -> Notification {
+    let (title, body) = describe(achievement);
+    Notification::new(NotificationLevel::Success, title)
+        .with_body(body.to_string())
+        .with_penguin()
+}
+
+fn describe(achievement: Achievement) -> (Text, Text) {
+    match achievement {
+        Achievement::FirstSaleOfTheDay => (
+            strings::ACHIEVEMENT_FIRST_SALE,
+            strings::ACHIEVEMENT_FIRST_SALE_BODY,
+        ),
+        Achievement::HundredthCoffee => (
+            strings::ACHIEVEMENT_COFFEE,
+            strings::ACHIEVEMENT_COFFEE_BODY,
+        ),
+        Achievement::BalancedCashCount => (
+            strings::ACHIEVEMENT_BALANCED,
+            strings::ACHIEVEMENT_BALANCED_BODY,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+    use strecklistan_api::transaction::TransactionBundle;
+
+    fn sale(transaction_id: TransactionId, coffees: u32) -> AchievementEvent {
+        AchievementEvent::Sale {
+            transaction_id,
+            day_start: Utc.ymd(2021, 7, 18).and_hms(22, 0, 0),
+            coffees,
+        }
+    }
+
+    #[test]
+    fn test_record() {
+        let mut progress = AchievementProgress::default();
+        let not_loaded = RecentTransactions::default();
+
+        // nothing is known about the other sales of the day
+        assert_eq!(record(&mut progress, sale(1, 60), &not_loaded), vec![]);
+        assert_eq!(
+            record(&mut progress, sale(2, 40), &not_loaded),
+            vec![Achievement::HundredthCoffee]
+        );
+        assert_eq!(record(&mut progress, sale(3, 1), &not_loaded), vec![]);
+        assert_eq!(progress.coffees_sold, 101);
+
+        assert_eq!(
+            record(&mut progress, AchievementEvent::CashBalanced, &not_loaded),
+            vec![Achievement::BalancedCashCount]
+        );
+        assert_eq!(
+            progress.unlocked,
+            vec![Achievement::HundredthCoffee, Achievement::BalancedCashCount]
+        );
+    }
+
+    #[test]
+    fn test_count_coffees() {
+        let item = |id, name: &str| {
+            (
+                id,
+                InventoryItemStock {
+                    id,
+                    name: name.to_string(),
+                    price: Some(500),
+                    image_url: None,
+                    stock: 10,
+                    archived_at: None,
+                    age_restricted: false,
+                    prepared: false,
+                    low_stock_at: None,
+                    reserved: 0,
+                },
+            )
+        };
+        let inventory = vec![item(1, "Kaffe"), item(2, "Bryggkaffe stor"), item(3, "Te")]
+            .into_iter()
+            .collect();
+        let bundle = |change, items: Vec<(InventoryItemId, u32)>| TransactionBundle {
+            description: None,
+            price: None,
+            change,
+            item_ids: items.into_iter().collect(),
+            expires_on: None,
+            modifier_ids: vec![],
+        };
+        let transaction = NewTransaction {
+            description: None,
+            bundles: vec![
+                bundle(-2, vec![(1, 1), (3, 1)]),
+                bundle(-1, vec![(2, 3)]),
+                // restocking isn't selling
+                bundle(10, vec![(1, 1)]),
+            ],
+            debited_account: 1,
+            credited_account: 2,
+            amount: 1500.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        assert_eq!(count_coffees(&transaction, &inventory), 5);
+    }
+}
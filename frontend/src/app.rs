@@ -1,146 +1,554 @@
+use crate::achievements::{self, AchievementEvent, ACHIEVEMENT_MS};
+use crate::components::approval::{ApprovalDialog, ApprovalMsg};
 use crate::generated::css_classes::C;
-use crate::notification_manager::{NotificationManager, NotificationMessage};
+use crate::i18n;
+use crate::live::{Live, LiveMsg};
+use crate::notification_manager::{
+    Notification, NotificationLevel, NotificationManager, NotificationMessage,
+};
 use crate::page::{
+    admin::{AdminMsg, AdminPage},
     analytics::{AnalyticsMsg, AnalyticsPage},
+    balance::{BalanceMsg, BalancePage},
+    dashboard::{DashboardMsg, DashboardPage},
     deposit::{DepositionMsg, DepositionPage},
+    events::{EventsMsg, EventsPage},
+    kiosk::{KioskMsg, KioskPage},
+    kitchen::{KitchenMsg, KitchenPage},
+    login::{LoginMsg, LoginPage},
+    queue::{QueueMsg, QueuePage},
+    report::{ReportMsg, ReportPage},
+    stock_value::{StockValueMsg, StockValuePage},
     store::{StoreMsg, StorePage},
     transactions::{TransactionsMsg, TransactionsPage},
-    Page,
+    Page, UrlParams,
 };
-use crate::util::compare_semver;
+use crate::strings;
+use crate::util::fetch::{add_approval, get_with_retry, is_online, send_with_retry};
+use crate::util::{backup, base, breadcrumbs, charts, compare_semver, resource_cache, simple_ev};
+use chrono::Utc;
 use seed::prelude::*;
 use seed::*;
 use seed_fetcher::{ResourceMsg, ResourceStore};
 use semver::Version;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use strecklistan_api::announcement::AnnouncementSeverity;
+use strecklistan_api::approval::GatedAction;
+use strecklistan_api::organization::Branding;
+use strecklistan_api::preferences::{DefaultPage, Language, Theme, UserPreferences};
+use strecklistan_api::store_layout::QuickButtonTarget;
+use strecklistan_api::user::User;
 
 const PKG_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+const APP_NAME: &str = "DrawIT";
+const REGISTER_MODE_KEY: &str = "register_mode";
+
+/// The api version which the tab was last reloaded for, so that it's only reloaded once
+const RELOADED_FOR_KEY: &str = "reloaded_for_api";
 
 pub struct Model {
     pub page: Page,
+    /// The query parameters and hash of the current url
+    pub url_params: UrlParams,
 
+    /// The scroll position of every page which has been navigated away from
+    pub scroll_positions: HashMap<Page, f64>,
+
+    /// An unrecoverable error, shown instead of the page
     pub error: Option<(String, String)>,
 
+    /// The server could not be reached, shown as a banner with a retry button
+    pub connection_error: Option<String>,
+
+    /// The browser has no network, shown as a banner until it's back
+    pub offline: bool,
+
     pub store_page: Option<StorePage>,
     pub transactions_page: Option<TransactionsPage>,
     pub analytics_page: Option<AnalyticsPage>,
     pub deposition_page: Option<DepositionPage>,
+    pub report_page: Option<ReportPage>,
+    pub events_page: Option<EventsPage>,
+    pub stock_value_page: Option<StockValuePage>,
+    pub admin_page: Option<AdminPage>,
+    pub dashboard_page: Option<DashboardPage>,
+    pub kiosk_page: Option<KioskPage>,
+    pub queue_page: Option<QueuePage>,
+    pub kitchen_page: Option<KitchenPage>,
+    pub balance_page: Option<BalancePage>,
+    pub login_page: LoginPage,
+
+    pub auth: AuthState,
+
+    /// The page to go to after logging in
+    pub after_login: Option<(Page, UrlParams)>,
 
     pub rs: ResourceStore,
     pub notifications: NotificationManager,
+
+    /// The changes pushed by the server, and the recent transactions and announcements kept up to
+    /// date by them
+    pub live: Live,
+
+    pub preferences: UserPreferences,
+
+    /// The name and logo of the organization of the user, `None` until they're fetched
+    pub branding: Option<Branding>,
+
+    /// Large touch targets and less navigation, for the touchscreen at the counter.
+    /// Stored in local storage since it belongs to the device rather than the user.
+    pub register_mode: bool,
+
+    /// Whether the links of the header are shown on a small screen, where they are folded away
+    pub menu_open: bool,
+
+    /// Sales are kept out of the books while practicing. It belongs to the session, so the server
+    /// has the final say.
+    pub training: bool,
+
+    /// The dialog where an admin approves an action, and the message which retries the action
+    /// once they have
+    pub approval: Option<(ApprovalDialog, Msg)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum AuthState {
+    /// Waiting for the server to tell us who is logged in
+    Unknown,
+    LoggedIn(User),
+    LoggedOut,
 }
 
 #[derive(Clone, Debug)]
 pub enum Msg {
-    ChangePage(Page),
+    ChangePage(Page, UrlParams),
+
+    FetchedUser(Option<User>),
+    LogOut,
+    LoggedOut,
 
     ResourceMsg(ResourceMsg),
+    /// A resource which was shown from local storage has changed on the server
+    ResourceChanged(&'static str),
+    LiveMsg(LiveMsg),
+    /// The charts module is loaded, so the charts can be drawn
+    ChartsLoaded,
 
     FetchedApiVersion(String),
+    FetchedPreferences(UserPreferences),
+    /// Also sent by the admin page when the branding is changed
+    FetchedBranding(Branding),
+    SavePreferences(UserPreferences),
+    TogglePinned(QuickButtonTarget),
+    /// Record progress towards the achievements, and celebrate the ones which are unlocked
+    Achievement(AchievementEvent),
+    /// Switch to the next theme: system -> dark -> light
+    ToggleTheme,
+    ToggleLanguage,
+    ToggleRegisterMode,
+    ToggleMenu,
+    SetTrainingMode(bool),
+    FetchedTrainingMode(bool),
+
+    /// An admin has to approve an action, which is retried by sending `retry` once they have
+    RequestApproval {
+        action: GatedAction,
+        retry: Box<Msg>,
+    },
+    ApprovalMsg(ApprovalMsg),
 
-    ShowError { header: String, dump: String },
+    ConnectionFailed(String),
+    Reconnect,
+    WentOffline,
+    /// The network is back, reload everything which may have failed while it was gone
+    WentOnline,
+    ReloadPage,
 
     AnalyticsMsg(AnalyticsMsg),
     DepositionMsg(DepositionMsg),
     TransactionsMsg(TransactionsMsg),
     StoreMsg(StoreMsg),
+    LoginMsg(LoginMsg),
+    ReportMsg(ReportMsg),
+    EventsMsg(EventsMsg),
+    StockValueMsg(StockValueMsg),
+    AdminMsg(AdminMsg),
+    DashboardMsg(DashboardMsg),
+    KioskMsg(KioskMsg),
+    QueueMsg(QueueMsg),
+    KitchenMsg(KitchenMsg),
+    BalanceMsg(BalanceMsg),
 
     NotificationMessage(NotificationMessage),
 }
 
 pub fn init(url: Url, orders: &mut impl Orders<Msg>) -> Model {
     orders
-        .subscribe(|subs::UrlChanged(mut url)| {
+        .subscribe(|subs::UrlChanged(url)| {
+            let params = UrlParams::from_url(&url);
+            let mut url = base::skip_base_path(url);
             let page = match url.remaining_path_parts().as_slice() {
                 [] | [""] | ["store"] => Page::Store,
                 ["transactions"] => Page::TransactionHistory,
                 ["analytics"] => Page::Analytics,
                 ["deposit"] => Page::Deposit,
+                ["login"] => Page::Login,
+                ["report"] => Page::Report,
+                ["events"] => Page::Events,
+                ["stock_value"] => Page::StockValue,
+                ["admin"] => Page::Admin,
+                ["dashboard"] => Page::Dashboard,
+                ["kiosk"] => Page::Kiosk,
+                ["queue"] => Page::Queue,
+                ["kitchen"] => Page::Kitchen,
+                ["balance"] => Page::Balance,
                 _ => Page::NotFound,
             };
 
-            Msg::ChangePage(page)
+            Msg::ChangePage(page, params)
         })
         .notify(subs::UrlChanged(url.clone()));
 
-    orders.perform_cmd(async move {
-        let response: Result<String, FetchError> =
-            async { Ok(fetch("/api/version").await?.text().await?) }.await;
-        match response {
-            Ok(response) => Msg::FetchedApiVersion(response),
-            Err(e) => Msg::ShowError {
-                header: "Failed to contact server".to_owned(),
-                dump: format!("{:#?}", e),
-            },
-        }
-    });
+    orders
+        .stream(streams::window_event(Ev::Offline, |_| Msg::WentOffline))
+        .stream(streams::window_event(Ev::Online, |_| Msg::WentOnline));
+
+    connect(orders);
+
+    let register_mode = LocalStorage::get(REGISTER_MODE_KEY).unwrap_or(false);
+    apply_register_mode(register_mode);
+
+    let sender = orders.msg_sender();
+    resource_cache::install(move |url| sender(Some(Msg::ResourceChanged(url))));
+
+    let charts_sender = orders.msg_sender();
+    charts::install(move || charts_sender(Some(Msg::ChartsLoaded)));
 
     let rs = ResourceStore::new(&mut orders.proxy(Msg::ResourceMsg));
     Model {
         page: Page::Store,
+        url_params: Default::default(),
+        scroll_positions: HashMap::new(),
         error: None,
+        connection_error: None,
+        offline: !is_online(),
         store_page: None,
         transactions_page: None,
         analytics_page: None,
         deposition_page: None,
+        report_page: None,
+        events_page: None,
+        stock_value_page: None,
+        admin_page: None,
+        dashboard_page: None,
+        kiosk_page: None,
+        queue_page: None,
+        kitchen_page: None,
+        balance_page: None,
+        login_page: Default::default(),
+        auth: AuthState::Unknown,
+        after_login: None,
         rs,
         notifications: Default::default(),
+        live: Default::default(),
+        preferences: Default::default(),
+        branding: None,
+        register_mode,
+        menu_open: false,
+        training: false,
+        approval: None,
     }
 }
 
 pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
-    #[cfg(debug_assertions)]
-    log!("message", msg);
+    debug_log!("message", msg);
+    breadcrumbs::record_msg(&msg);
 
     let rs = &model.rs;
     match msg {
-        Msg::ChangePage(page) => {
-            model.page = page;
+        Msg::ChangePage(page, params) => {
+            // everything except the login, kiosk and balance pages requires a logged in user
+            if matches!(model.auth, AuthState::LoggedOut)
+                && !matches!(page, Page::Login | Page::Kiosk | Page::Balance)
+            {
+                model.after_login = Some((page, params));
+                orders.request_url(base::page_url("login"));
+                return;
+            }
 
-            model.transactions_page = None;
+            // the admin pages are hidden from everyone else, the server checks the role as well
+            let page = match (page, &model.auth) {
+                (Page::Admin | Page::Dashboard, AuthState::LoggedIn(user)) if !user.is_admin => {
+                    Page::NotFound
+                }
+                (page, _) => page,
+            };
+
+            if page != model.page && !confirm_leave_page(model) {
+                // the url has already changed, so put back the url of the current page
+                model.url_params.replace_url(model.page);
+                return;
+            }
 
+            // pages are kept alive when navigating away, so that they can be restored as they were
+            if page != model.page {
+                model
+                    .scroll_positions
+                    .insert(model.page, window().scroll_y().unwrap_or(0.0));
+                let scroll_y = model.scroll_positions.get(&page).copied().unwrap_or(0.0);
+                orders.after_next_render(move |_| window().scroll_to_with_x_and_y(0.0, scroll_y));
+            }
+
+            model.page = page;
+            model.url_params = params.clone();
+            model.menu_open = false;
+            breadcrumbs::set_state("page", format!("{:?}", page));
+            set_title(page, model.branding.as_ref());
+
+            // pages keep their state if the url doesn't say otherwise, and write it back to the url
             match page {
                 Page::Store => {
-                    model.store_page.get_or_insert_with(|| {
-                        StorePage::new(rs, &mut orders.proxy(Msg::StoreMsg))
+                    let store_page = model.store_page.get_or_insert_with(|| {
+                        let mut p = StorePage::new(rs, &mut orders.proxy(Msg::StoreMsg));
+                        p.restore_backup(rs);
+                        p
                     });
+                    store_page.set_params(&params);
+                    store_page.set_training(model.training);
+                    store_page.update_url();
                 }
                 Page::TransactionHistory => {
-                    model.transactions_page = Some(TransactionsPage::new(
-                        &model.rs,
-                        &mut orders.proxy(Msg::TransactionsMsg),
-                    ))
+                    let transactions_page = model.transactions_page.get_or_insert_with(|| {
+                        TransactionsPage::new(rs, &mut orders.proxy(Msg::TransactionsMsg))
+                    });
+                    transactions_page.set_params(&params, rs);
+                    transactions_page.update_url();
                 }
                 Page::Analytics => {
-                    model.analytics_page.get_or_insert_with(|| {
+                    charts::load();
+                    let analytics_page = model.analytics_page.get_or_insert_with(|| {
                         AnalyticsPage::new(rs, &mut orders.proxy(Msg::AnalyticsMsg))
                     });
+                    analytics_page.set_params(&params);
+                    analytics_page.update_url();
                 }
                 Page::Deposit => {
-                    model.deposition_page.get_or_insert_with(|| {
-                        DepositionPage::new(rs, &mut orders.proxy(Msg::DepositionMsg))
+                    let deposition_page = model.deposition_page.get_or_insert_with(|| {
+                        let mut p = DepositionPage::new(rs, &mut orders.proxy(Msg::DepositionMsg));
+                        p.restore_backup();
+                        p
                     });
+                    deposition_page.set_params(&params);
+                    deposition_page.update_url();
+                }
+                Page::Report => {
+                    let report_page = model
+                        .report_page
+                        .get_or_insert_with(|| ReportPage::new(orders));
+                    report_page.set_params(&params, orders);
+                    report_page.update_url();
+                }
+                Page::Events => {
+                    charts::load();
+                    model
+                        .events_page
+                        .get_or_insert_with(|| EventsPage::new(orders));
+                }
+                Page::StockValue => {
+                    // for the sparklines
+                    charts::load();
+                    let stock_value_page = model
+                        .stock_value_page
+                        .get_or_insert_with(|| StockValuePage::new(orders));
+                    stock_value_page.set_params(&params, orders);
+                    stock_value_page.update_url();
+                }
+                Page::Admin => {
+                    model.admin_page.get_or_insert_with(|| {
+                        AdminPage::new(rs, &mut orders.proxy(Msg::AdminMsg))
+                    });
+                }
+                Page::Dashboard => {
+                    charts::load();
+                    model
+                        .dashboard_page
+                        .get_or_insert_with(|| DashboardPage::new(rs, orders));
+                }
+                Page::Kiosk => {
+                    model
+                        .kiosk_page
+                        .get_or_insert_with(|| KioskPage::new(orders));
+                }
+                Page::Queue => {
+                    model
+                        .queue_page
+                        .get_or_insert_with(|| QueuePage::new(orders))
+                        .set_params(&params);
+                }
+                Page::Kitchen => {
+                    model
+                        .kitchen_page
+                        .get_or_insert_with(|| KitchenPage::new(orders));
+                }
+                Page::Balance => {
+                    model
+                        .balance_page
+                        .get_or_insert_with(BalancePage::default)
+                        .set_params(&params, orders);
+                }
+                Page::Login | Page::NotFound => {}
+            }
+        }
+
+        Msg::FetchedUser(Some(user)) => {
+            breadcrumbs::set_state("user", &user.name);
+            breadcrumbs::set_state("admin", user.is_admin);
+            model.auth = AuthState::LoggedIn(user);
+            fetch_preferences(orders);
+            fetch_branding(orders);
+            fetch_training_mode(orders);
+            model.live.connect(orders);
+
+            // now that the role of the user is known, check that they may see the page
+            if matches!(model.page, Page::Admin | Page::Dashboard) {
+                orders.send_msg(Msg::ChangePage(model.page, model.url_params.clone()));
+            }
+        }
+        Msg::FetchedUser(None) => {
+            model.auth = AuthState::LoggedOut;
+            if !matches!(model.page, Page::Login) {
+                orders.send_msg(Msg::ChangePage(model.page, model.url_params.clone()));
+            }
+        }
+        Msg::LogOut => {
+            orders.perform_cmd(async move {
+                let result = async {
+                    Request::new("/api/logout")
+                        .method(Method::Post)
+                        .fetch()
+                        .await?
+                        .check_status()
                 }
-                Page::NotFound => {}
+                .await;
+                match result {
+                    Ok(_) => Some(Msg::LoggedOut),
+                    Err(e) => {
+                        error!("Failed to log out", e);
+                        None
+                    }
+                }
+            });
+        }
+        Msg::LoggedOut => {
+            model.auth = AuthState::LoggedOut;
+
+            // don't keep the state of the pages around for the next user
+            model.store_page = None;
+            model.transactions_page = None;
+            model.analytics_page = None;
+            model.deposition_page = None;
+            model.report_page = None;
+            model.events_page = None;
+            model.stock_value_page = None;
+            model.admin_page = None;
+            model.scroll_positions.clear();
+            backup::clear();
+
+            // the next user may belong to another organization, so drop everything fetched
+            resource_cache::clear();
+            model.rs = ResourceStore::new(&mut orders.proxy(Msg::ResourceMsg));
+            model.live.disconnect();
+
+            model.preferences = Default::default();
+            model.branding = None;
+            model.training = false;
+            apply_training_mode(false);
+            apply_theme(model.preferences.theme);
+            i18n::set_language(model.preferences.language);
+            model.notifications.settings = model.preferences.notifications.clone();
+
+            // the pages are gone, so show the login page until the redirect happens
+            let page = std::mem::replace(&mut model.page, Page::Login);
+            orders.send_msg(Msg::ChangePage(page, model.url_params.clone()));
+        }
+        Msg::LoginMsg(msg) => {
+            if let LoginMsg::LoggedIn(user) = &msg {
+                model.auth = AuthState::LoggedIn(user.clone());
+                fetch_preferences(orders);
+                fetch_branding(orders);
+                model.live.connect(orders);
+
+                let url = match model.after_login.take() {
+                    Some((page, params)) => params.url(page),
+                    None => base::page_url(""),
+                };
+                orders.request_url(url);
             }
+            model.login_page.update(msg, orders);
         }
 
         Msg::ResourceMsg(msg) => {
             model.rs.update(msg, &mut orders.proxy(Msg::ResourceMsg));
         }
+        Msg::ResourceChanged(url) => model.rs.mark_as_dirty(url, orders),
+        Msg::LiveMsg(msg) => model.live.update(msg, orders),
+        Msg::ChartsLoaded => {}
 
-        Msg::ShowError { header, dump } => {
-            model.error = Some((header, dump));
+        Msg::ConnectionFailed(reason) => {
+            model.connection_error = Some(reason);
+        }
+        Msg::Reconnect => {
+            model.connection_error = None;
+            connect(orders);
+            orders.send_msg(Msg::ReloadPage);
+        }
+        Msg::WentOffline => {
+            model.offline = true;
+        }
+        Msg::WentOnline => {
+            if model.offline {
+                model.offline = false;
+                orders.send_msg(Msg::Reconnect);
+            }
+        }
+        Msg::ReloadPage => {
+            let msg = match model.page {
+                Page::Store => Msg::StoreMsg(StoreMsg::Reload),
+                Page::Deposit => Msg::DepositionMsg(DepositionMsg::Reload),
+                Page::TransactionHistory => Msg::TransactionsMsg(TransactionsMsg::Reload),
+                Page::Analytics => Msg::AnalyticsMsg(AnalyticsMsg::Reload),
+                Page::Report => Msg::ReportMsg(ReportMsg::Reload),
+                Page::Events => Msg::EventsMsg(EventsMsg::Reload),
+                Page::StockValue => Msg::StockValueMsg(StockValueMsg::Reload),
+                Page::Admin => Msg::AdminMsg(AdminMsg::Reload),
+                Page::Dashboard => Msg::DashboardMsg(DashboardMsg::Reload),
+                Page::Kiosk => Msg::KioskMsg(KioskMsg::Reload),
+                Page::Queue => Msg::QueueMsg(QueueMsg::Reload),
+                Page::Kitchen => Msg::KitchenMsg(KitchenMsg::Reload),
+                Page::Balance => Msg::BalanceMsg(BalanceMsg::Reload),
+                Page::Login | Page::NotFound => return,
+            };
+            orders.send_msg(msg);
         }
 
         Msg::FetchedApiVersion(response) => {
             if let Ok(api_version) = Version::parse(&response) {
                 let frontend_version = Version::parse(PKG_VERSION).unwrap();
 
-                log!("API version:", response);
-                log!("Application version:", PKG_VERSION);
+                debug_log!("API version:", response);
+                debug_log!("Application version:", PKG_VERSION);
 
                 if !compare_semver(frontend_version, api_version) {
+                    // the app is most likely served along with the api, so reloading picks up the
+                    // matching version. the cart and the deposit form are kept in the backup.
+                    let reloaded_for: Option<String> = SessionStorage::get(RELOADED_FOR_KEY).ok();
+                    if reloaded_for.as_deref() != Some(response.as_str()) {
+                        SessionStorage::insert(RELOADED_FOR_KEY, &response).ok();
+                        window().location().reload().ok();
+                        return;
+                    }
+
                     model.error = Some((
                         "Mismatching api version.".to_string(),
                         format!(
@@ -154,11 +562,184 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
             }
         }
 
+        Msg::FetchedPreferences(preferences) => {
+            apply_theme(preferences.theme);
+            i18n::set_language(preferences.language);
+            set_title(model.page, model.branding.as_ref());
+            model.notifications.settings = preferences.notifications.clone();
+
+            let at_root = base::skip_base_path(Url::current())
+                .remaining_path_parts()
+                .iter()
+                .all(|part| part.is_empty());
+            if at_root {
+                let page = match preferences.default_page {
+                    DefaultPage::Store => Page::Store,
+                    DefaultPage::Deposit => Page::Deposit,
+                    DefaultPage::Transactions => Page::TransactionHistory,
+                    DefaultPage::Analytics => Page::Analytics,
+                    DefaultPage::Dashboard => Page::Dashboard,
+                };
+                orders.send_msg(Msg::ChangePage(page, UrlParams::default()));
+            }
+
+            model.preferences = preferences;
+        }
+
+        Msg::FetchedBranding(branding) => {
+            model.branding = Some(branding);
+            set_title(model.page, model.branding.as_ref());
+        }
+        Msg::SavePreferences(preferences) => {
+            apply_theme(preferences.theme);
+            i18n::set_language(preferences.language);
+            set_title(model.page, model.branding.as_ref());
+            model.notifications.settings = preferences.notifications.clone();
+            model.preferences = preferences.clone();
+
+            orders.perform_cmd(async move {
+                let result = async {
+                    send_with_retry(
+                        Request::new("/api/preferences")
+                            .method(Method::Put)
+                            .json(&preferences)?,
+                    )
+                    .await?
+                    .check_status()
+                }
+                .await;
+                if let Err(e) = result {
+                    error!("Failed to save preferences", e);
+                }
+            });
+        }
+
+        Msg::Achievement(event) => {
+            let mut preferences = model.preferences.clone();
+            let unlocked =
+                achievements::record(&mut preferences.achievements, event, &model.live.recent);
+            for achievement in unlocked {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: ACHIEVEMENT_MS,
+                        notification: achievements::notification(achievement),
+                    },
+                ));
+            }
+            if preferences != model.preferences {
+                orders.send_msg(Msg::SavePreferences(preferences));
+            }
+        }
+
+        Msg::TogglePinned(target) => {
+            let mut preferences = model.preferences.clone();
+            if preferences.pinned.contains(&target) {
+                preferences.pinned.retain(|&pinned| pinned != target);
+            } else {
+                preferences.pinned.push(target);
+            }
+            orders.send_msg(Msg::SavePreferences(preferences));
+        }
+
+        Msg::ToggleTheme => {
+            let mut preferences = model.preferences.clone();
+            preferences.theme = match preferences.theme {
+                Theme::System => Theme::Dark,
+                Theme::Dark => Theme::Light,
+                Theme::Light => Theme::System,
+            };
+            orders.send_msg(Msg::SavePreferences(preferences));
+        }
+
+        Msg::ToggleLanguage => {
+            let mut preferences = model.preferences.clone();
+            preferences.language = match preferences.language {
+                Language::Swedish => Language::English,
+                Language::English => Language::Swedish,
+            };
+            orders.send_msg(Msg::SavePreferences(preferences));
+        }
+
+        Msg::ToggleRegisterMode => {
+            model.register_mode = !model.register_mode;
+            apply_register_mode(model.register_mode);
+            if let Err(e) = LocalStorage::insert(REGISTER_MODE_KEY, &model.register_mode) {
+                error!("Failed to save register mode", e);
+            }
+        }
+
+        Msg::ToggleMenu => {
+            model.menu_open = !model.menu_open;
+        }
+
+        Msg::SetTrainingMode(training) => {
+            orders.perform_cmd(async move {
+                let result: Result<bool, FetchError> = async {
+                    Request::new("/api/session/training")
+                        .method(Method::Put)
+                        .json(&training)?
+                        .fetch()
+                        .await?
+                        .check_status()?
+                        .json()
+                        .await
+                }
+                .await;
+                match result {
+                    Ok(training) => Msg::FetchedTrainingMode(training),
+                    Err(e) => {
+                        error!("Failed to switch training mode", e);
+                        Msg::NotificationMessage(NotificationMessage::ShowNotification {
+                            duration_ms: 5000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::TRAINING_MODE_FAILED,
+                            ),
+                        })
+                    }
+                }
+            });
+        }
+        Msg::FetchedTrainingMode(training) => {
+            breadcrumbs::set_state("training", training);
+            model.training = training;
+            apply_training_mode(training);
+            if let Some(store_page) = model.store_page.as_mut() {
+                store_page.set_training(training);
+            }
+        }
+
+        Msg::RequestApproval { action, retry } => {
+            model.approval = Some((ApprovalDialog::new(action), *retry));
+        }
+        Msg::ApprovalMsg(ApprovalMsg::Approved(approval)) => {
+            if let Some((_, retry)) = model.approval.take() {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 5000,
+                        notification: Notification::new(
+                            NotificationLevel::Success,
+                            strings::APPROVED_BY,
+                        )
+                        .with_body(approval.approved_by.clone()),
+                    },
+                ));
+                add_approval(approval);
+                orders.send_msg(retry);
+            }
+        }
+        Msg::ApprovalMsg(ApprovalMsg::Cancel) => model.approval = None,
+        Msg::ApprovalMsg(msg) => {
+            if let Some((dialog, _)) = model.approval.as_mut() {
+                dialog.update(msg, &mut orders.proxy(Msg::ApprovalMsg));
+            }
+        }
+
         Msg::DepositionMsg(msg) => {
-            model
-                .deposition_page
-                .as_mut()
-                .and_then(|p| p.update(msg, &rs, orders).ok());
+            if let Some(p) = model.deposition_page.as_mut() {
+                p.update(msg, &rs, orders).ok();
+                p.save_backup();
+            }
         }
         Msg::AnalyticsMsg(msg) => {
             model
@@ -172,20 +753,271 @@ pub fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
                 .as_mut()
                 .and_then(|p| p.update(msg, &rs, orders).ok());
         }
-        Msg::StoreMsg(msg) => {
+        Msg::ReportMsg(msg) => {
+            if let Some(page) = model.report_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+        Msg::EventsMsg(msg) => {
+            if let Some(page) = model.events_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+        Msg::StockValueMsg(msg) => {
+            if let Some(page) = model.stock_value_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+        Msg::KioskMsg(msg) => {
+            if let Some(page) = model.kiosk_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+        Msg::QueueMsg(msg) => {
+            if let Some(page) = model.queue_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+        Msg::KitchenMsg(msg) => {
+            if let Some(page) = model.kitchen_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+        Msg::DashboardMsg(msg) => {
+            if let Some(page) = model.dashboard_page.as_mut() {
+                page.update(msg, rs, orders);
+            }
+        }
+        Msg::BalanceMsg(msg) => {
+            if let Some(page) = model.balance_page.as_mut() {
+                page.update(msg, orders);
+            }
+        }
+        Msg::AdminMsg(msg) => {
             model
-                .store_page
+                .admin_page
                 .as_mut()
                 .and_then(|p| p.update(msg, &rs, orders).ok());
         }
+        // keyboard shortcuts only apply while the store page is visible
+        Msg::StoreMsg(StoreMsg::GlobalKeyDown(_)) if !matches!(model.page, Page::Store) => {}
+        Msg::StoreMsg(msg) => {
+            if let Some(p) = model.store_page.as_mut() {
+                p.update(msg, &rs, orders).ok();
+                p.save_backup();
+            }
+        }
 
         Msg::NotificationMessage(msg) => model.notifications.update(msg, orders),
     }
 }
 
+/// Check the api version and who is logged in
+fn connect(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let response: Result<String, FetchError> =
+            async { get_with_retry("/api/version".into()).await?.text().await }.await;
+        match response {
+            Ok(response) => Msg::FetchedApiVersion(response),
+            Err(e) => Msg::ConnectionFailed(format!("{:?}", e)),
+        }
+    });
+
+    orders.perform_cmd(async move {
+        let response: Result<User, FetchError> =
+            async { get_with_retry("/api/me".into()).await?.json().await }.await;
+        match response {
+            Ok(user) => Msg::FetchedUser(Some(user)),
+            Err(FetchError::StatusError(status)) if status.code == 401 => Msg::FetchedUser(None),
+            Err(e) => Msg::ConnectionFailed(format!("{:?}", e)),
+        }
+    });
+}
+
+fn fetch_preferences(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let response: Result<UserPreferences, FetchError> = async {
+            get_with_retry("/api/preferences".into())
+                .await?
+                .json()
+                .await
+        }
+        .await;
+        match response {
+            Ok(preferences) => Some(Msg::FetchedPreferences(preferences)),
+            Err(e) => {
+                error!("Failed to fetch preferences", e);
+                None
+            }
+        }
+    });
+}
+
+fn fetch_training_mode(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let response: Result<bool, FetchError> = async {
+            get_with_retry("/api/session/training".into())
+                .await?
+                .json()
+                .await
+        }
+        .await;
+        match response {
+            Ok(training) => Some(Msg::FetchedTrainingMode(training)),
+            Err(e) => {
+                error!("Failed to fetch training mode", e);
+                None
+            }
+        }
+    });
+}
+
+fn fetch_branding(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let response: Result<Branding, FetchError> = async {
+            get_with_retry("/api/organization/branding".into())
+                .await?
+                .json()
+                .await
+        }
+        .await;
+        match response {
+            Ok(branding) => Some(Msg::FetchedBranding(branding)),
+            Err(e) => {
+                error!("Failed to fetch branding", e);
+                None
+            }
+        }
+    });
+}
+
 pub fn view(model: &Model) -> Vec<Node<Msg>> {
+    // the info screen has nobody to use the header
+    if let (Page::Kiosk, Some(kiosk_page), None) = (model.page, &model.kiosk_page, &model.error) {
+        return vec![kiosk_page.view()];
+    }
+    // nor does the customer display of the queue
+    if let (Page::Queue, Some(queue_page), None) = (model.page, &model.queue_page, &model.error) {
+        if queue_page.is_display() {
+            return vec![queue_page.view()];
+        }
+    }
+    // members looking up the balance of their card aren't staff
+    if let (Page::Balance, Some(balance_page), None) =
+        (model.page, &model.balance_page, &model.error)
+    {
+        return vec![balance_page.view()];
+    }
+
     vec![
         model.notifications.view(),
+        match &model.approval {
+            Some((dialog, _)) => dialog.view().map_msg(Msg::ApprovalMsg),
+            None => empty![],
+        },
+        if model.offline {
+            div![C![C.connection_error], span![strings::OFFLINE]]
+        } else if let Some(reason) = &model.connection_error {
+            div![
+                C![C.connection_error],
+                span![strings::CONNECTION_FAILED],
+                span![C![C.connection_error_reason], reason],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, Msg::Reconnect),
+                    strings::RETRY,
+                ],
+            ]
+        } else {
+            empty![]
+        },
+        div![
+            C![C.announcements],
+            if model.training {
+                div![
+                    C![C.announcement, C.announcement_training],
+                    span![strings::TRAINING_MODE_ACTIVE],
+                    button![
+                        C![C.announcement_action, C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::SetTrainingMode(false)),
+                        strings::LEAVE_TRAINING_MODE,
+                    ],
+                ]
+            } else {
+                empty![]
+            },
+            model
+                .live
+                .announcements
+                .visible(Utc::now())
+                .map(|announcement| {
+                    div![
+                        match announcement.severity {
+                            AnnouncementSeverity::Info => C![C.announcement],
+                            AnnouncementSeverity::Warning => {
+                                C![C.announcement, C.announcement_warning]
+                            }
+                            AnnouncementSeverity::Critical => {
+                                C![C.announcement, C.announcement_critical]
+                            }
+                        },
+                        span![&announcement.message],
+                        button![
+                            C![C.announcement_dismiss, C.border_on_focus],
+                            attrs! {At::Title => strings::DISMISS},
+                            simple_ev(
+                                Ev::Click,
+                                Msg::LiveMsg(LiveMsg::DismissAnnouncement(announcement.id))
+                            ),
+                            "✕",
+                        ],
+                    ]
+                }),
+            if model.live.register.is_overdue(Utc::now()) {
+                div![
+                    C![C.announcement, C.announcement_critical],
+                    span![strings::REGISTER_OVERDUE],
+                    button![
+                        C![C.announcement_action, C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::LiveMsg(LiveMsg::CloseRegister)),
+                        strings::CLOSE_REGISTER,
+                    ],
+                ]
+            } else {
+                empty![]
+            },
+            match &model.auth {
+                AuthState::LoggedIn(user)
+                    if user.is_admin && !model.live.pending_corrections.is_empty() =>
+                {
+                    div![
+                        C![C.announcement, C.announcement_warning],
+                        span![format!(
+                            "{} ({})",
+                            strings::CORRECTIONS_PENDING,
+                            model.live.pending_corrections.len()
+                        )],
+                        a![
+                            C![C.announcement_action, C.rounded, C.border_on_focus],
+                            attrs! {At::Href => base::page_href("/admin")},
+                            strings::REVIEW_CORRECTIONS,
+                        ],
+                    ]
+                }
+                _ => empty![],
+            },
+            model.live.register.unreconciled.iter().map(|shift| {
+                div![
+                    C![C.announcement, C.announcement_warning],
+                    span![strings::REGISTER_AUTO_CLOSED],
+                    button![
+                        C![C.announcement_action, C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::LiveMsg(LiveMsg::ReconcileShift(shift.id))),
+                        strings::RECONCILE,
+                    ],
+                ]
+            }),
+        ],
         div![
             div![
                 C![C.header],
@@ -194,39 +1026,200 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
                 } else {
                     empty![]
                 },
+                // only shown on small screens, where the links are folded away
+                div![
+                    C![C.header_bar],
+                    button![
+                        C![C.header_menu_button, C.border_on_focus],
+                        attrs! {At::Title => strings::MENU},
+                        simple_ev(Ev::Click, Msg::ToggleMenu),
+                        if model.menu_open { "✕" } else { "☰" },
+                    ],
+                    span![C![C.header_title], model.page.title()],
+                ],
                 div![
                     // links
                     //a!["hem", C![C.header_link], attrs! {At::Href => "/"}],
                     C![C.header_link_box],
+                    if model.menu_open {
+                        C![C.header_link_box_open]
+                    } else {
+                        C![]
+                    },
+                    model.branding.as_ref().map(|branding| {
+                        div![
+                            C![C.header_brand],
+                            branding.logo_url().map(|url| {
+                                img![
+                                    C![C.header_logo],
+                                    attrs! {At::Src => base::api_url(&url), At::Alt => ""}
+                                ]
+                            }),
+                            span![&branding.name],
+                        ]
+                    }),
                     a![
-                        "försäljning",
+                        strings::PAGE_STORE,
                         C![C.header_link],
-                        attrs! {At::Href => "/store"}
+                        attrs! {At::Href => base::page_href("/store")}
                     ],
                     a![
-                        "tillgodo",
+                        strings::PAGE_DEPOSIT,
                         C![C.header_link],
-                        attrs! {At::Href => "/deposit"}
+                        attrs! {At::Href => base::page_href("/deposit")}
                     ],
+                    if model.register_mode {
+                        vec![]
+                    } else {
+                        vec![
+                            a![
+                                strings::PAGE_TRANSACTIONS,
+                                C![C.header_link],
+                                attrs! {At::Href => base::page_href("/transactions")}
+                            ],
+                            a![
+                                strings::PAGE_ANALYTICS,
+                                C![C.header_link],
+                                attrs! {At::Href => base::page_href("/analytics")}
+                            ],
+                            a![
+                                strings::PAGE_EVENTS,
+                                C![C.header_link],
+                                attrs! {At::Href => base::page_href("/events")}
+                            ],
+                            a![
+                                strings::PAGE_STOCK_VALUE,
+                                C![C.header_link],
+                                attrs! {At::Href => base::page_href("/stock_value")}
+                            ],
+                        ]
+                    },
                     a![
-                        "transaktioner",
+                        strings::PAGE_REPORT,
                         C![C.header_link],
-                        attrs! {At::Href => "/transactions"}
+                        attrs! {At::Href => base::page_href("/report")}
                     ],
-                    a![
-                        "analys",
-                        C![C.header_link],
-                        attrs! {At::Href => "/analytics"}
+                    match &model.auth {
+                        AuthState::LoggedIn(user) if user.is_admin => vec![
+                            a![
+                                strings::PAGE_DASHBOARD,
+                                C![C.header_link],
+                                attrs! {At::Href => base::page_href("/dashboard")}
+                            ],
+                            a![
+                                strings::PAGE_ADMIN,
+                                C![C.header_link],
+                                attrs! {At::Href => base::page_href("/admin")}
+                            ],
+                        ],
+                        _ => vec![],
+                    },
+                    button![
+                        C![C.header_toggle_button, C.rounded, C.border_on_focus],
+                        attrs! {At::Title => strings::REFRESH},
+                        simple_ev(Ev::Click, Msg::ReloadPage),
+                        "⟳",
+                    ],
+                    button![
+                        if model.register_mode {
+                            C![
+                                C.header_toggle_button,
+                                C.header_toggle_button_active,
+                                C.rounded,
+                                C.border_on_focus
+                            ]
+                        } else {
+                            C![C.header_toggle_button, C.rounded, C.border_on_focus]
+                        },
+                        attrs! {At::Title => strings::REGISTER_MODE},
+                        simple_ev(Ev::Click, Msg::ToggleRegisterMode),
+                        "▦",
+                    ],
+                    button![
+                        if model.training {
+                            C![
+                                C.header_toggle_button,
+                                C.header_toggle_button_active,
+                                C.rounded,
+                                C.border_on_focus
+                            ]
+                        } else {
+                            C![C.header_toggle_button, C.rounded, C.border_on_focus]
+                        },
+                        attrs! {At::Title => strings::TRAINING_MODE},
+                        simple_ev(Ev::Click, Msg::SetTrainingMode(!model.training)),
+                        "✎",
                     ],
+                    button![
+                        C![C.header_toggle_button, C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::ToggleLanguage),
+                        strings::SWITCH_LANGUAGE,
+                    ],
+                    button![
+                        C![C.header_toggle_button, C.rounded, C.border_on_focus],
+                        match model.preferences.theme {
+                            Theme::Light => attrs! {At::Title => strings::THEME_LIGHT},
+                            Theme::Dark => attrs! {At::Title => strings::THEME_DARK},
+                            Theme::System => attrs! {At::Title => strings::THEME_SYSTEM},
+                        },
+                        simple_ev(Ev::Click, Msg::ToggleTheme),
+                        match model.preferences.theme {
+                            Theme::Light => "☀",
+                            Theme::Dark => "☾",
+                            Theme::System => "◐",
+                        },
+                    ],
+                    if let AuthState::LoggedIn(user) = &model.auth {
+                        div![
+                            C![C.header_user],
+                            span![user.display_name.as_ref().unwrap_or(&user.name)],
+                            button![
+                                C![C.header_logout_button, C.rounded, C.border_on_focus],
+                                simple_ev(Ev::Click, Msg::LogOut),
+                                strings::LOG_OUT,
+                            ],
+                        ]
+                    } else {
+                        empty![]
+                    },
                 ],
             ],
             match &model.error {
                 None => match model.page {
                     Page::Analytics => model.analytics_page.as_ref().unwrap().view(&model.rs),
-                    Page::Store => model.store_page.as_ref().unwrap().view(&model.rs),
-                    Page::Deposit => model.deposition_page.as_ref().unwrap().view(&model.rs),
-                    Page::TransactionHistory =>
-                        model.transactions_page.as_ref().unwrap().view(&model.rs),
+                    Page::Store => model.store_page.as_ref().unwrap().view(
+                        &model.rs,
+                        &model.preferences,
+                        is_admin(&model.auth)
+                    ),
+                    Page::Deposit => model
+                        .deposition_page
+                        .as_ref()
+                        .unwrap()
+                        .view(&model.rs, &model.preferences),
+                    Page::TransactionHistory => model
+                        .transactions_page
+                        .as_ref()
+                        .unwrap()
+                        .view(&model.rs, &model.live.recent),
+                    Page::Report => model
+                        .report_page
+                        .as_ref()
+                        .unwrap()
+                        .view(model.branding.as_ref(), &model.live.register),
+                    Page::Events => model.events_page.as_ref().unwrap().view(),
+                    Page::StockValue => model.stock_value_page.as_ref().unwrap().view(),
+                    Page::Admin => model.admin_page.as_ref().unwrap().view(&model.rs),
+                    Page::Dashboard => model
+                        .dashboard_page
+                        .as_ref()
+                        .unwrap()
+                        .view(&model.rs, &model.live.register),
+                    Page::Kiosk => model.kiosk_page.as_ref().unwrap().view(),
+                    Page::Queue => model.queue_page.as_ref().unwrap().view(),
+                    Page::Kitchen => model.kitchen_page.as_ref().unwrap().view(),
+                    Page::Balance => model.balance_page.as_ref().unwrap().view(),
+                    Page::Login => model.login_page.view(),
                     Page::NotFound => {
                         div![C![C.not_found_message, C.unselectable], "404"]
                     }
@@ -234,7 +1227,7 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
 
                 Some((header, message)) => div![
                     C![C.error_page],
-                    p!["An has error occured."],
+                    p![strings::ERROR_OCCURRED],
                     p![header],
                     textarea![
                         C![C.code_box],
@@ -247,3 +1240,66 @@ pub fn view(model: &Model) -> Vec<Node<Msg>> {
         ],
     ]
 }
+
+/// Whether the logged in user may change the settings of the organization
+fn is_admin(auth: &AuthState) -> bool {
+    matches!(auth, AuthState::LoggedIn(user) if user.is_admin)
+}
+
+/// Ask the user whether to leave the current page, if doing so would abandon unsaved input
+fn confirm_leave_page(model: &Model) -> bool {
+    let message = match model.page {
+        Page::Store => model
+            .store_page
+            .as_ref()
+            .filter(|page| page.has_unsaved_changes())
+            .map(|_| strings::LEAVE_UNSAVED_CART),
+        Page::Deposit => model
+            .deposition_page
+            .as_ref()
+            .filter(|page| page.has_unsaved_changes())
+            .map(|_| strings::LEAVE_UNSAVED_DEPOSIT),
+        _ => None,
+    };
+
+    match message {
+        Some(message) => window().confirm_with_message(message.get()).unwrap_or(true),
+        None => true,
+    }
+}
+
+/// Title the tab after the page and the organization, or the app before the organization is known
+fn set_title(page: Page, branding: Option<&Branding>) {
+    let name = branding.map(|branding| branding.name.as_str());
+    document().set_title(&format!("{} | {}", page.title(), name.unwrap_or(APP_NAME)));
+}
+
+/// Flag register mode on the root element, where the stylesheets can pick it up
+fn apply_register_mode(register_mode: bool) {
+    if let Some(root) = document().document_element() {
+        let _ = root.set_attribute(
+            "data-register-mode",
+            if register_mode { "on" } else { "off" },
+        );
+    }
+}
+
+/// Flag training mode on the root element, where the stylesheets can pick it up
+fn apply_training_mode(training: bool) {
+    if let Some(root) = document().document_element() {
+        let _ = root.set_attribute("data-training", if training { "on" } else { "off" });
+    }
+}
+
+/// Set the theme on the root element, where the stylesheets can pick it up
+fn apply_theme(theme: Theme) {
+    let theme = match theme {
+        Theme::Light => "light",
+        Theme::Dark => "dark",
+        Theme::System => "system",
+    };
+
+    if let Some(root) = document().document_element() {
+        let _ = root.set_attribute("data-theme", theme);
+    }
+}
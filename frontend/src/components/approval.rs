@@ -0,0 +1,165 @@
+use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::strings;
+use crate::util::simple_ev;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::approval::{Approval, ApprovalRequest, GatedAction};
+
+#[derive(Clone, Debug)]
+pub enum ApprovalMsg {
+    NameInput(String),
+    PasswordInput(String),
+    KeyDown(web_sys::KeyboardEvent),
+    Submit,
+
+    /// The admin approved the action, handled by the parent
+    Approved(Approval),
+    Failed(String),
+    /// Handled by the parent
+    Cancel,
+}
+
+/// A dialog where an admin approves an action of a cashier with their own password, e.g. a large
+/// discount. See [with_approvals](crate::util::fetch::with_approvals).
+#[derive(Clone)]
+pub struct ApprovalDialog {
+    pub action: GatedAction,
+    name: String,
+    password: String,
+    sending: bool,
+    error: Option<String>,
+}
+
+pub fn action_text(action: GatedAction) -> Text {
+    match action {
+        GatedAction::Void => strings::APPROVAL_VOID,
+        GatedAction::Discount => strings::APPROVAL_DISCOUNT,
+        GatedAction::NegativeBalance => strings::APPROVAL_NEGATIVE_BALANCE,
+        GatedAction::PurchaseLimit => strings::APPROVAL_PURCHASE_LIMIT,
+    }
+}
+
+impl ApprovalDialog {
+    pub fn new(action: GatedAction) -> Self {
+        ApprovalDialog {
+            action,
+            name: String::new(),
+            password: String::new(),
+            sending: false,
+            error: None,
+        }
+    }
+
+    pub fn update(&mut self, msg: ApprovalMsg, orders: &mut impl Orders<ApprovalMsg>) {
+        match msg {
+            ApprovalMsg::NameInput(input) => self.name = input,
+            ApprovalMsg::PasswordInput(input) => self.password = input,
+            ApprovalMsg::KeyDown(ev) => {
+                if ev.key() == "Enter" {
+                    self.update(ApprovalMsg::Submit, orders);
+                }
+            }
+            ApprovalMsg::Submit => {
+                if self.sending || self.name.is_empty() {
+                    return;
+                }
+
+                self.sending = true;
+                self.error = None;
+                let request = ApprovalRequest {
+                    name: self.name.clone(),
+                    password: self.password.clone(),
+                    action: self.action,
+                };
+
+                orders.perform_cmd(async move {
+                    let result = async {
+                        Request::new("/api/approval")
+                            .method(Method::Post)
+                            .json(&request)?
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(approval) => ApprovalMsg::Approved(approval),
+                        Err(FetchError::StatusError(status)) if status.code == 401 => {
+                            ApprovalMsg::Failed(strings::INVALID_CREDENTIALS.to_string())
+                        }
+                        Err(FetchError::StatusError(status)) if status.code == 403 => {
+                            ApprovalMsg::Failed(strings::NOT_AN_ADMIN.to_string())
+                        }
+                        Err(FetchError::StatusError(status)) if status.code == 423 => {
+                            ApprovalMsg::Failed(strings::ACCOUNT_LOCKED.to_string())
+                        }
+                        Err(FetchError::StatusError(status)) if status.code == 429 => {
+                            ApprovalMsg::Failed(strings::TOO_MANY_LOGIN_ATTEMPTS.to_string())
+                        }
+                        Err(e) => ApprovalMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            ApprovalMsg::Failed(reason) => {
+                self.sending = false;
+                self.password.clear();
+                self.error = Some(reason);
+            }
+            ApprovalMsg::Approved(_) | ApprovalMsg::Cancel => {}
+        }
+    }
+
+    pub fn view(&self) -> Node<ApprovalMsg> {
+        div![
+            C![C.approval_overlay],
+            div![
+                C![C.approval_dialog, C.rounded],
+                h2![strings::APPROVAL_REQUIRED],
+                p![action_text(self.action)],
+                p![C![C.approval_hint], strings::APPROVAL_HINT],
+                input![
+                    C![C.login_input, C.rounded, C.border_on_focus],
+                    attrs! {At::Value => self.name},
+                    attrs! {At::Placeholder => strings::USERNAME},
+                    attrs! {At::AutoFocus => true},
+                    attrs! {At::AutoComplete => "off"},
+                    input_ev(Ev::Input, ApprovalMsg::NameInput),
+                    keyboard_ev(Ev::KeyDown, ApprovalMsg::KeyDown),
+                ],
+                input![
+                    C![C.login_input, C.rounded, C.border_on_focus],
+                    attrs! {At::Type => "password"},
+                    attrs! {At::Value => self.password},
+                    attrs! {At::Placeholder => strings::PASSWORD},
+                    attrs! {At::AutoComplete => "off"},
+                    input_ev(Ev::Input, ApprovalMsg::PasswordInput),
+                    keyboard_ev(Ev::KeyDown, ApprovalMsg::KeyDown),
+                ],
+                if let Some(error) = &self.error {
+                    div![C![C.wide_button_message], error]
+                } else {
+                    empty![]
+                },
+                div![
+                    C![C.approval_buttons],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, ApprovalMsg::Cancel),
+                        strings::ABORT,
+                    ],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        attrs! {
+                            At::Disabled => (self.sending || self.name.is_empty()).as_at_value(),
+                        },
+                        simple_ev(Ev::Click, ApprovalMsg::Submit),
+                        strings::APPROVE,
+                    ],
+                ],
+            ],
+        ]
+    }
+}
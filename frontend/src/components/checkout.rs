@@ -1,29 +1,59 @@
 use crate::components::parsed_input::{ParsedInput, ParsedInputMsg};
 use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::page::loading::Loading;
 use crate::strings;
-use crate::util::simple_ev;
+use crate::util::fetch::{get_with_retry, sale_response, send_with_retry, with_approvals, Gated};
+use crate::util::{new_idempotency_key, simple_ev, with_pending};
 use seed::prelude::*;
 use seed::*;
 use seed_fetcher::ResourceStore;
 use seed_fetcher::Resources;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::convert::TryInto;
+use std::mem;
 use strecklistan_api::{
-    book_account::{BookAccountId, MasterAccounts},
-    currency::{AbsCurrency, Currency},
+    approval::GatedAction,
+    book_account::{BookAccount, BookAccountId, MasterAccounts},
+    checkout::{CheckoutQuote, CheckoutRules, QuoteRequest},
+    currency::{Currency, NonNegativeCurrency},
     inventory::{
-        InventoryBundle, InventoryBundleId, InventoryItemId, InventoryItemStock as InventoryItem,
+        describe_modified, InventoryBundle, InventoryBundleId, InventoryItemId,
+        InventoryItemStock as InventoryItem, ItemModifier, ItemModifierId, StockShortage,
     },
-    transaction::{NewTransaction, TransactionBundle, TransactionId},
+    member::{Member, MemberSearchResult},
+    transaction::{NewTransaction, TransactionAnnotation, TransactionBundle, TransactionId},
 };
 
+/// How many members are suggested when tagging a purchase
+const MEMBER_RESULTS: usize = 5;
+
 #[derive(Clone, Debug)]
 pub enum CheckoutMsg {
     ConfirmPurchase,
+
+    /// A purchase which was paid outside of the checkout, e.g. by card, is complete
     PurchaseSent {
         transaction_id: TransactionId,
     },
 
+    /// The server saved a purchase, which was cleared from the cart when it was sent
+    PurchaseSaved {
+        idempotency_key: String,
+        transaction_id: TransactionId,
+        amount: Currency,
+    },
+
+    /// The server didn't save a purchase, so it is put back in the cart
+    PurchaseFailed {
+        idempotency_key: String,
+        error: PurchaseError,
+    },
+
+    /// The purchase was rejected since some items aren't in stock
+    InsufficientStock(Vec<StockShortage>),
+
     TotalInputMsg(ParsedInputMsg),
     AddItem {
         item_id: InventoryItemId,
@@ -33,21 +63,113 @@ pub enum CheckoutMsg {
         bundle_id: InventoryBundleId,
         amount: i32,
     },
+
+    /// Add an item with some of its variants and add-ons, e.g. a large coffee with milk
+    AddModifiedItem {
+        item_id: InventoryItemId,
+        modifier_ids: Vec<ItemModifierId>,
+        amount: i32,
+    },
     SetBundleChange {
         bundle_index: usize,
         change: i32,
     },
     ClearCart,
+    SetNote(String),
+    SetTags(String),
+    SetEventCode(String),
+
+    /// The ID of the buyer has been checked, so age-restricted items may be sold
+    AgeVerified,
+    CancelAgeCheck,
+
+    SearchMember(String),
+    MemberResults {
+        query: String,
+        results: Vec<MemberSearchResult>,
+    },
+
+    /// Tag the purchase with the member who buys it, for their purchase limits and statistics
+    TagMember(Member),
+    UntagMember,
+}
+
+#[derive(Clone, Debug)]
+pub enum PurchaseError {
+    /// Some items aren't in stock
+    InsufficientStock(Vec<StockShortage>),
+
+    /// An admin has to approve the purchase before it's sent again
+    ApprovalRequired(GatedAction),
+
+    /// The purchase didn't reach the server, or the server failed
+    Failed,
+}
+
+/// A purchase which has been cleared from the cart, but not yet saved by the server
+#[derive(Clone)]
+struct SentPurchase {
+    transaction: NewTransaction,
+
+    /// The checkout as it was when the purchase was sent, for putting it back
+    cart: Checkout,
+}
+
+impl SentPurchase {
+    fn has_key(&self, idempotency_key: &str) -> bool {
+        self.transaction.idempotency_key.as_deref() == Some(idempotency_key)
+    }
+}
+
+/// The cart as it is kept in the [backup](crate::util::backup) across reloads of the page.
+/// Purchases which have been sent aren't part of it.
+#[derive(Serialize, Deserialize)]
+pub struct CartBackup {
+    bundles: Vec<TransactionBundle>,
+    debited_account: Option<BookAccountId>,
+    member: Option<Member>,
+    note: String,
+    tags: String,
+    event_code: String,
+
+    /// The total as typed, if it was changed by hand
+    total: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct Checkout {
-    transaction_total_input: ParsedInput<AbsCurrency>,
+    transaction_total_input: ParsedInput<NonNegativeCurrency>,
     transaction_bundles: Vec<TransactionBundle>,
     pub debited_account: Option<BookAccountId>,
     override_transaction_total: bool,
-    pub confirm_button_message: Option<&'static str>,
+    note: String,
+
+    /// Comma-separated tags, as typed
+    tags: String,
+    event_code: String,
+
+    /// Whether the ID of the buyer has been checked, recorded on the transaction
+    age_verified: bool,
+
+    /// Whether the purchase waits for the ID of the buyer to be checked
+    age_check_open: bool,
+
+    /// The member the purchase is tagged with, which is only sent if it isn't paid from a
+    /// tillgodo, since then it's the purchase of the owner of that
+    member: Option<Member>,
+    member_search: String,
+    member_results: Vec<MemberSearchResult>,
+
+    /// Sent with the purchase so that it's only made once, even if it's posted twice
+    idempotency_key: String,
+
+    /// The items which there weren't enough of, the last time the purchase was attempted
+    shortages: Vec<StockShortage>,
+    pub confirm_button_message: Option<Text>,
     pub disabled: bool,
+
+    /// Purchases which are shown as made, but which the server hasn't saved yet
+    sent: Vec<SentPurchase>,
 }
 
 #[derive(Resources)]
@@ -61,11 +183,117 @@ struct Res<'a> {
 
     #[url = "/api/inventory/bundles"]
     bundles: &'a HashMap<InventoryBundleId, InventoryBundle>,
+
+    #[url = "/api/inventory/modifiers"]
+    modifiers: &'a HashMap<ItemModifierId, ItemModifier>,
+
+    #[url = "/api/checkout/rules"]
+    rules: &'a CheckoutRules,
+}
+
+/// A cart line selling one `item`
+fn item_line(item: &InventoryItem) -> TransactionBundle {
+    let mut item_ids = HashMap::new();
+    item_ids.insert(item.id, 1);
+
+    TransactionBundle {
+        description: None,
+        price: Some(item.price.unwrap_or(0).into()),
+        change: 0,
+        item_ids,
+        expires_on: None,
+        modifier_ids: vec![],
+    }
+}
+
+/// A cart line selling one `item` with `modifiers`, priced with their price deltas
+fn modified_item_line(item: &InventoryItem, modifiers: &[&ItemModifier]) -> TransactionBundle {
+    if modifiers.is_empty() {
+        return item_line(item);
+    }
+
+    // in the same order however they were chosen, so that equal lines are merged
+    let mut modifiers = modifiers.to_vec();
+    modifiers.sort_by_key(|modifier| modifier.id);
+
+    let price = Currency::from(item.price.unwrap_or(0))
+        + modifiers
+            .iter()
+            .map(|modifier| modifier.price_delta)
+            .sum::<Currency>();
+
+    TransactionBundle {
+        description: Some(describe_modified(&item.name, &modifiers)),
+        price: Some(price),
+        modifier_ids: modifiers.iter().map(|modifier| modifier.id).collect(),
+        ..item_line(item)
+    }
+}
+
+/// A cart line selling one `bundle`
+fn bundle_line(bundle: &InventoryBundle) -> TransactionBundle {
+    let mut item_ids = HashMap::new();
+    for &id in bundle.item_ids.iter() {
+        *item_ids.entry(id).or_default() += 1;
+    }
+
+    TransactionBundle {
+        description: Some(bundle.name.clone()),
+        price: Some(bundle.price),
+        change: 0,
+        item_ids,
+        expires_on: None,
+        modifier_ids: vec![],
+    }
+}
+
+/// Add `amount` of `line` to the cart, merging it with an equal line if there is one
+fn add_to_cart(cart: &mut Vec<TransactionBundle>, line: TransactionBundle, amount: i32) {
+    match cart.iter_mut().find(|b| {
+        b.item_ids == line.item_ids
+            && b.description == line.description
+            && b.modifier_ids == line.modifier_ids
+    }) {
+        Some(b) => b.change -= amount,
+        None => cart.push(TransactionBundle {
+            change: -amount,
+            ..line
+        }),
+    }
+}
+
+/// Add the lines of `other` to `cart`
+fn merge_carts(cart: &mut Vec<TransactionBundle>, other: Vec<TransactionBundle>) {
+    for line in other {
+        let amount = -line.change;
+        add_to_cart(cart, line, amount);
+    }
+}
+
+/// Whether `cart` sells any items which require the ID of the buyer to be checked
+fn contains_age_restricted(
+    cart: &[TransactionBundle],
+    inventory: &HashMap<InventoryItemId, InventoryItem>,
+) -> bool {
+    cart.iter()
+        .filter(|bundle| bundle.change < 0)
+        .flat_map(|bundle| bundle.item_ids.keys())
+        .filter_map(|item_id| inventory.get(item_id))
+        .any(|item| item.age_restricted)
+}
+
+/// Remove the lines of which nothing is sold
+fn remove_cleared(cart: &mut Vec<TransactionBundle>) {
+    cart.retain(|bundle| bundle.change != 0);
 }
 
 impl Checkout {
     pub fn new(rs: &ResourceStore, orders: &mut impl Orders<CheckoutMsg>) -> Self {
         Res::acquire(rs, orders).ok();
+        Checkout::empty()
+    }
+
+    fn empty() -> Self {
         Checkout {
             transaction_bundles: vec![],
             debited_account: None,
@@ -73,8 +301,19 @@ impl Checkout {
                 .with_error_message(strings::INVALID_MONEY_MESSAGE_SHORT)
                 .with_input_kind("text"),
             override_transaction_total: false,
+            note: String::new(),
+            tags: String::new(),
+            event_code: String::new(),
+            age_verified: false,
+            age_check_open: false,
+            member: None,
+            member_search: String::new(),
+            member_results: vec![],
+            idempotency_key: new_idempotency_key(),
+            shortages: vec![],
             disabled: false,
             confirm_button_message: None,
+            sent: vec![],
         }
     }
 
@@ -90,48 +329,84 @@ impl Checkout {
         };
 
         match msg {
+            // the cart is cleared right away, and put back if the server doesn't save it
             CheckoutMsg::ConfirmPurchase => {
                 self.remove_cleared_items();
                 if let Some(transaction) = self.build_transaction(rs) {
-                    self.disabled = true;
+                    self.send(transaction.clone());
 
                     orders.perform_cmd(async move {
+                        let idempotency_key =
+                            transaction.idempotency_key.clone().unwrap_or_default();
+                        let amount = transaction.amount;
                         let result = async {
-                            Request::new("/api/transaction")
-                                .method(Method::Post)
-                                .json(&transaction)?
-                                .fetch()
-                                .await?
-                                .json()
-                                .await
+                            let response = send_with_retry(with_approvals(
+                                Request::new("/api/transaction")
+                                    .method(Method::Post)
+                                    .json(&transaction)?,
+                            ))
+                            .await?;
+                            sale_response(response).await
                         }
                         .await;
                         match result {
-                            Ok(transaction_id) => {
-                                Some(CheckoutMsg::PurchaseSent { transaction_id })
+                            Ok(Ok(transaction_id)) => CheckoutMsg::PurchaseSaved {
+                                idempotency_key,
+                                transaction_id,
+                                amount,
+                            },
+                            Ok(Err(Gated::Rejected(shortages))) => CheckoutMsg::PurchaseFailed {
+                                idempotency_key,
+                                error: PurchaseError::InsufficientStock(shortages),
+                            },
+                            Ok(Err(Gated::ApprovalRequired(action))) => {
+                                CheckoutMsg::PurchaseFailed {
+                                    idempotency_key,
+                                    error: PurchaseError::ApprovalRequired(action),
+                                }
                             }
                             Err(e) => {
                                 error!("Failed to post purchase", e);
-                                None
+                                CheckoutMsg::PurchaseFailed {
+                                    idempotency_key,
+                                    error: PurchaseError::Failed,
+                                }
                             }
                         }
                     });
                 }
             }
             CheckoutMsg::PurchaseSent { transaction_id } => {
+                debug_log!("Posted transaction ID: ", transaction_id);
+                self.clear();
+            }
+            CheckoutMsg::PurchaseSaved {
+                idempotency_key,
+                transaction_id,
+                ..
+            } => {
+                debug_log!("Posted transaction ID: ", transaction_id);
+                self.sent.retain(|sent| !sent.has_key(&idempotency_key));
+            }
+            CheckoutMsg::PurchaseFailed {
+                idempotency_key,
+                error,
+            } => {
+                self.roll_back(&idempotency_key);
+                if let PurchaseError::InsufficientStock(shortages) = error {
+                    self.shortages = shortages;
+                }
+            }
+            CheckoutMsg::InsufficientStock(shortages) => {
                 self.disabled = false;
-                log!("Posted transaction ID: ", transaction_id);
-                self.transaction_total_input.set_value(Default::default());
-                self.transaction_bundles = vec![];
-                self.debited_account = None;
-                self.override_transaction_total = false;
+                self.shortages = shortages;
             }
             CheckoutMsg::TotalInputMsg(msg) => {
                 match &msg {
                     ParsedInputMsg::FocusOut => {
                         if self.transaction_total_input.get_value().is_none() {
                             self.override_transaction_total = false;
-                            self.recompute_new_transaction_total();
+                            self.recompute_new_transaction_total(rs);
                         }
                     }
                     ParsedInputMsg::Input(_) => {
@@ -143,103 +418,265 @@ impl Checkout {
             }
             CheckoutMsg::AddItem { item_id, amount } => {
                 if !self.disabled {
-                    let item = res
-                        .inventory
-                        .get(&item_id)
-                        .unwrap_or_else(|| panic!("No inventory item with that id exists"))
-                        .clone();
-
-                    let mut item_ids = HashMap::new();
-                    item_ids.insert(item.id, 1);
-
-                    let bundle = TransactionBundle {
-                        description: None,
-                        price: Some(item.price.unwrap_or(0).into()),
-                        change: -amount,
-                        item_ids,
-                    };
-
-                    if let Some(b) = self.transaction_bundles.iter_mut().find(|b| {
-                        b.item_ids == bundle.item_ids && b.description == bundle.description
-                    }) {
-                        b.change -= amount;
-                    } else {
-                        self.transaction_bundles.push(bundle);
+                    match res.inventory.get(&item_id) {
+                        Some(item) => {
+                            add_to_cart(&mut self.transaction_bundles, item_line(item), amount)
+                        }
+                        None => error!("No inventory item with id", item_id),
                     }
                 }
             }
-            CheckoutMsg::AddBundle { bundle_id, amount } => {
-                let bundle = res
-                    .bundles
-                    .get(&bundle_id)
-                    .unwrap_or_else(|| panic!("No inventory bundle with that id exists"))
-                    .clone();
-
-                let mut item_ids = HashMap::new();
-                for &id in bundle.item_ids.iter() {
-                    *item_ids.entry(id).or_default() += 1;
+            CheckoutMsg::AddBundle { bundle_id, amount } => match res.bundles.get(&bundle_id) {
+                Some(bundle) => {
+                    add_to_cart(&mut self.transaction_bundles, bundle_line(bundle), amount)
                 }
-
-                let bundle = TransactionBundle {
-                    description: Some(bundle.name.clone()),
-                    price: Some(bundle.price),
-                    change: -amount,
-                    item_ids,
-                };
-
-                if let Some(b) = self
-                    .transaction_bundles
-                    .iter_mut()
-                    .find(|b| b.item_ids == bundle.item_ids && b.description == bundle.description)
-                {
-                    b.change -= amount;
-                } else {
-                    log!("Pushing bundle", bundle);
-                    self.transaction_bundles.push(bundle);
+                None => error!("No inventory bundle with id", bundle_id),
+            },
+            CheckoutMsg::AddModifiedItem {
+                item_id,
+                modifier_ids,
+                amount,
+            } => {
+                if !self.disabled {
+                    match res.inventory.get(&item_id) {
+                        Some(item) => {
+                            let modifiers: Vec<&ItemModifier> = modifier_ids
+                                .iter()
+                                .filter_map(|id| res.modifiers.get(id))
+                                .collect();
+                            let line = modified_item_line(item, &modifiers);
+                            add_to_cart(&mut self.transaction_bundles, line, amount)
+                        }
+                        None => error!("No inventory item with id", item_id),
+                    }
                 }
             }
             CheckoutMsg::SetBundleChange {
                 bundle_index,
                 change,
             } => {
-                self.transaction_bundles[bundle_index].change = change;
+                if let Some(bundle) = self.transaction_bundles.get_mut(bundle_index) {
+                    bundle.change = change;
+                }
             }
             CheckoutMsg::ClearCart => {
                 self.transaction_bundles.clear();
             }
+            CheckoutMsg::SetNote(note) => self.note = note,
+            CheckoutMsg::SetTags(tags) => self.tags = tags,
+            CheckoutMsg::SetEventCode(event_code) => self.event_code = event_code,
+            CheckoutMsg::AgeVerified => {
+                self.age_verified = true;
+                self.age_check_open = false;
+                orders.send_msg(CheckoutMsg::ConfirmPurchase);
+            }
+            CheckoutMsg::CancelAgeCheck => self.age_check_open = false,
+            CheckoutMsg::SearchMember(input) => {
+                self.member_search = input;
+                if self.member_search.trim().is_empty() {
+                    self.member_results.clear();
+                } else {
+                    let query = self.member_search.clone();
+                    orders.perform_cmd(async move {
+                        let url = format!(
+                            "/api/members/search?q={}&limit={}",
+                            String::from(js_sys::encode_uri_component(&query)),
+                            MEMBER_RESULTS,
+                        );
+                        let result = async { get_with_retry(url).await?.json().await }.await;
+                        match result {
+                            Ok(results) => Some(CheckoutMsg::MemberResults { query, results }),
+                            Err(e) => {
+                                error!("Failed to search members", e);
+                                None
+                            }
+                        }
+                    });
+                }
+            }
+            CheckoutMsg::MemberResults { query, results } => {
+                // ignore responses to outdated searches
+                if query == self.member_search {
+                    self.member_results = results;
+                }
+            }
+            CheckoutMsg::TagMember(member) => {
+                self.member = Some(member);
+                self.member_search.clear();
+                self.member_results.clear();
+            }
+            CheckoutMsg::UntagMember => self.member = None,
         }
 
-        self.recompute_new_transaction_total();
+        self.recompute_new_transaction_total(rs);
     }
 
-    fn recompute_new_transaction_total(&mut self) {
+    /// Empty the cart, returning what was in it. The purchases which are being sent are kept.
+    fn clear(&mut self) -> Checkout {
+        let sent = mem::take(&mut self.sent);
+        let cart = mem::replace(self, Checkout::empty());
+        self.sent = sent;
+        cart
+    }
+
+    /// Show `transaction` as made, by clearing the cart until the server has saved it
+    fn send(&mut self, transaction: NewTransaction) {
+        let cart = self.clear();
+        self.sent.push(SentPurchase { transaction, cart });
+    }
+
+    /// Put a purchase which the server didn't save back in the cart, together with anything
+    /// added to the cart since it was sent
+    fn roll_back(&mut self, idempotency_key: &str) {
+        let index = match self
+            .sent
+            .iter()
+            .position(|sent| sent.has_key(idempotency_key))
+        {
+            Some(index) => index,
+            None => return,
+        };
+
+        let SentPurchase { cart, .. } = self.sent.remove(index);
+        let added_since = self.clear();
+        let sent = mem::take(&mut self.sent);
+        *self = cart;
+        self.sent = sent;
+
+        if !added_since.transaction_bundles.is_empty() {
+            self.override_transaction_total = false;
+        }
+        merge_carts(
+            &mut self.transaction_bundles,
+            added_since.transaction_bundles,
+        );
+    }
+
+    /// Ask for the ID of the buyer to be checked before the purchase is made, if the cart sells
+    /// age-restricted items and it hasn't been checked already. Returns whether it was asked for.
+    pub fn ask_for_age_check(&mut self, rs: &ResourceStore) -> bool {
+        let restricted = Res::acquire_now(rs)
+            .map(|res| contains_age_restricted(&self.transaction_bundles, res.inventory))
+            .unwrap_or(false);
+        self.age_check_open = restricted && !self.age_verified;
+        self.age_check_open
+    }
+
+    /// The purchase with `idempotency_key`, while the server hasn't saved it
+    pub fn sent_transaction(&self, idempotency_key: &str) -> Option<&NewTransaction> {
+        self.sent
+            .iter()
+            .find(|sent| sent.has_key(idempotency_key))
+            .map(|sent| &sent.transaction)
+    }
+
+    /// Whether there are purchases which the server hasn't saved yet
+    pub fn has_pending(&self) -> bool {
+        !self.sent.is_empty()
+    }
+
+    /// `account` with the purchases which the server hasn't saved yet
+    pub fn pending_balance(&self, account: &BookAccount) -> BookAccount {
+        with_pending(account, self.sent.iter().map(|sent| &sent.transaction))
+    }
+
+    /// Price the cart, the same way as `POST /api/checkout/quote` does
+    pub fn quote(&self, rs: &ResourceStore) -> CheckoutQuote {
+        let res = Res::acquire_now(rs).ok();
+        let item_prices: HashMap<InventoryItemId, Currency> = res
+            .as_ref()
+            .map(|res| {
+                res.inventory
+                    .values()
+                    .filter_map(|item| Some((item.id, item.price?.into())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let modifier_prices: HashMap<ItemModifierId, Currency> = res
+            .as_ref()
+            .map(|res| {
+                res.modifiers
+                    .values()
+                    .map(|modifier| (modifier.id, modifier.price_delta))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let rules = res
+            .as_ref()
+            .map(|res| res.rules.clone())
+            .unwrap_or_default();
+        let cash = match (&res, self.debited_account) {
+            (Some(res), Some(account)) => account == res.master_accounts.cash_account_id,
+            _ => false,
+        };
+
+        let request = QuoteRequest {
+            bundles: self.transaction_bundles.clone(),
+            tendered: None,
+            cash,
+        };
+        CheckoutQuote::compute(&request, &item_prices, &modifier_prices, &rules)
+    }
+
+    /// Recompute the total of the cart, unless it was changed by hand
+    pub fn recompute_new_transaction_total(&mut self, rs: &ResourceStore) {
         if !self.override_transaction_total {
-            let amount: Currency = self
-                .transaction_bundles
-                .iter()
-                .map(|bundle| -bundle.change * bundle.price.map(|p| p.into()).unwrap_or(0i32))
-                .sum::<i32>()
-                .into();
+            let amount = self.quote(rs).total;
             self.transaction_total_input
                 .set_value(amount.try_into().unwrap_or(Default::default()));
         }
     }
 
+    /// Whether the purchase is paid from the tillgodo of a member, rather than with cash or card
+    fn paid_from_tillgodo(&self, masters: &MasterAccounts) -> bool {
+        self.debited_account.map_or(false, |account| {
+            account != masters.bank_account_id && account != masters.cash_account_id
+        })
+    }
+
     pub fn build_transaction(&self, rs: &ResourceStore) -> Option<NewTransaction> {
+        let annotation = TransactionAnnotation {
+            note: Some(self.note.clone()),
+            tags: TransactionAnnotation::parse_tags(&self.tags),
+            event_code: Some(self.event_code.clone()),
+        }
+        .normalized();
+
         Res::acquire_now(rs)
             .ok()
             .zip(self.transaction_total_input.get_value().copied())
             .map(|(res, amount)| NewTransaction {
-                bundles: self.transaction_bundles.clone(),
+                bundles: self.bundles_to_send(rs),
                 amount: amount.into(),
                 description: Some(strings::TRANSACTION_SALE.into()),
                 credited_account: res.master_accounts.sales_account_id,
                 debited_account: self
                     .debited_account
                     .unwrap_or(res.master_accounts.bank_account_id),
+                note: annotation.note,
+                tags: annotation.tags,
+                event_code: annotation.event_code,
+                age_verified: self.age_verified,
+                member_id: self
+                    .member
+                    .as_ref()
+                    .filter(|_| !self.paid_from_tillgodo(res.master_accounts))
+                    .map(|member| member.id),
+                idempotency_key: Some(self.idempotency_key.clone()),
             })
     }
 
+    /// The cart, with a line recording the rounding of the total if it was rounded.
+    ///
+    /// A total which was typed in by hand isn't rounded.
+    fn bundles_to_send(&self, rs: &ResourceStore) -> Vec<TransactionBundle> {
+        let mut bundles = self.transaction_bundles.clone();
+        if !self.override_transaction_total {
+            bundles.extend(self.quote(rs).rounding_bundle());
+        }
+        bundles
+    }
+
     pub fn transaction_amount(&self) -> Currency {
         self.transaction_total_input
             .get_value()
@@ -248,19 +685,130 @@ impl Checkout {
             .into()
     }
 
-    pub fn set_debited(&mut self, acc_id: BookAccountId) {
+    pub fn is_empty(&self) -> bool {
+        self.transaction_bundles.is_empty()
+    }
+
+    pub fn bundles(&self) -> &[TransactionBundle] {
+        &self.transaction_bundles
+    }
+
+    /// Replace the contents of the cart, e.g. when resuming a parked cart
+    pub fn restore(
+        &mut self,
+        bundles: Vec<TransactionBundle>,
+        debited_account: Option<BookAccountId>,
+        rs: &ResourceStore,
+    ) {
+        self.transaction_bundles = bundles;
+        self.debited_account = debited_account;
+        self.override_transaction_total = false;
+        self.recompute_new_transaction_total(rs);
+    }
+
+    /// The cart to back up, or `None` if there is nothing in it
+    pub fn backup(&self) -> Option<CartBackup> {
+        if self.is_empty() {
+            return None;
+        }
+
+        Some(CartBackup {
+            bundles: self.transaction_bundles.clone(),
+            debited_account: self.debited_account,
+            member: self.member.clone(),
+            note: self.note.clone(),
+            tags: self.tags.clone(),
+            event_code: self.event_code.clone(),
+            total: Some(self.transaction_total_input.get_text().to_string())
+                .filter(|_| self.override_transaction_total),
+        })
+    }
+
+    /// Put back a cart which was backed up before the page was reloaded
+    pub fn restore_backup(&mut self, backup: CartBackup, rs: &ResourceStore) {
+        self.restore(backup.bundles, backup.debited_account, rs);
+        self.member = backup.member;
+        self.note = backup.note;
+        self.tags = backup.tags;
+        self.event_code = backup.event_code;
+        if let Some(total) = backup.total {
+            self.override_transaction_total = true;
+            self.transaction_total_input
+                .update(ParsedInputMsg::Input(total));
+        }
+    }
+
+    /// Pay from `acc_id`, which changes the total if cash is rounded
+    pub fn set_debited(&mut self, acc_id: BookAccountId, rs: &ResourceStore) {
         self.debited_account = Some(acc_id);
+        self.recompute_new_transaction_total(rs);
     }
 
     pub fn remove_cleared_items(&mut self) {
-        self.transaction_bundles.retain(|bundle| bundle.change != 0);
+        remove_cleared(&mut self.transaction_bundles);
+    }
+
+    /// Who the purchase is tagged with, or a search for a member to tag it with
+    fn view_member(&self, masters: &MasterAccounts) -> Node<CheckoutMsg> {
+        // a purchase paid from a tillgodo is always the purchase of its owner
+        if self.paid_from_tillgodo(masters) {
+            return empty![];
+        }
+
+        match &self.member {
+            Some(member) => div![
+                C![C.new_transaction_total_row],
+                span![
+                    C![C.new_transaction_total_text],
+                    format!(
+                        "{}: {} {}",
+                        strings::BOUGHT_BY,
+                        member.first_name,
+                        member.last_name
+                    ),
+                ],
+                button![
+                    C![C.new_transaction_clear_button, C.border_on_focus],
+                    simple_ev(Ev::Click, CheckoutMsg::UntagMember),
+                ],
+            ],
+            None => div![
+                C![C.new_transaction_member_search],
+                input![
+                    C![
+                        C.new_transaction_annotation_field,
+                        C.rounded,
+                        C.border_on_focus
+                    ],
+                    attrs! {
+                        At::Placeholder => strings::TAG_MEMBER,
+                        At::Value => self.member_search,
+                    },
+                    input_ev(Ev::Input, CheckoutMsg::SearchMember),
+                ],
+                self.member_results
+                    .iter()
+                    .map(|result| div![
+                        C![C.tillgodo_entry],
+                        div![
+                            C![C.tillgodo_nick],
+                            result.member.nickname.clone().unwrap_or_default(),
+                        ],
+                        div![
+                            C![C.tillgodo_name],
+                            format!("{} {}", result.member.first_name, result.member.last_name),
+                        ],
+                        simple_ev(Ev::Click, CheckoutMsg::TagMember(result.member.clone())),
+                    ])
+                    .collect::<Vec<_>>(),
+            ],
+        }
     }
 
     pub fn view(&self, rs: &ResourceStore) -> Node<CheckoutMsg> {
         let res = match Res::acquire_now(rs) {
             Ok(res) => res,
-            // TODO: proper loading component?
-            Err(_) => return div!["loading"],
+            Err(_) => return Loading::spinner(),
         };
 
         div![
@@ -270,7 +818,10 @@ impl Checkout {
                 .enumerate()
                 .rev() // display newest bundle first
                 .map(|(bundle_index, bundle)| {
-                    let mut items = bundle.item_ids.keys().map(|id| &res.inventory[id]);
+                    let mut items = bundle
+                        .item_ids
+                        .keys()
+                        .filter_map(|id| res.inventory.get(id));
 
                     // TODO: Properly display more complicated bundles
 
@@ -285,7 +836,7 @@ impl Checkout {
                         .description
                         .as_ref()
                         .map(|s| s.as_str())
-                        .unwrap_or(item_name.unwrap_or("[NAMN SAKNAS]"));
+                        .unwrap_or(item_name.unwrap_or(strings::MISSING_NAME.get()));
                     let price = bundle.price.unwrap_or(item_price.into());
 
                     p![
@@ -307,9 +858,54 @@ impl Checkout {
                         ],
                         span![C![C.transaction_entry_item_name], format!("x {}", name),],
                         span![C![C.transaction_entry_item_price], format!("{}:-", price),],
+                        self.shortages
+                            .iter()
+                            .filter(|shortage| bundle.item_ids.contains_key(&shortage.item_id))
+                            .map(|shortage| {
+                                div![
+                                    C![C.transaction_entry_shortage],
+                                    format!(
+                                        "{}: {} {}",
+                                        shortage.name,
+                                        shortage.in_stock,
+                                        strings::STOCK_LEFT
+                                    ),
+                                ]
+                            })
+                            .collect::<Vec<_>>(),
                     ]
                 })
                 .collect::<Vec<_>>(),
+            {
+                let quote = self.quote(rs);
+                let discount = quote.discount;
+                vec![
+                    if discount > Currency::default() {
+                        p![
+                            C![C.transaction_entry, C.new_transaction_discount],
+                            span![C![C.transaction_entry_item_name], strings::DISCOUNT],
+                            span![
+                                C![C.transaction_entry_item_price],
+                                format!("-{}:-", discount)
+                            ],
+                        ]
+                    } else {
+                        empty![]
+                    },
+                    if quote.rounding != Currency::default() && !self.override_transaction_total {
+                        p![
+                            C![C.transaction_entry, C.new_transaction_discount],
+                            span![C![C.transaction_entry_item_name], strings::ROUNDING],
+                            span![
+                                C![C.transaction_entry_item_price],
+                                format!("{}:-", quote.rounding)
+                            ],
+                        ]
+                    } else {
+                        empty![]
+                    },
+                ]
+            },
             div![
                 C![C.new_transaction_total_row],
                 span![C![C.new_transaction_total_text], strings::TRANSACTION_TOTAL],
@@ -336,7 +932,65 @@ impl Checkout {
                     simple_ev(Ev::Click, CheckoutMsg::ClearCart),
                 ],
             ],
-            if !self.disabled {
+            div![
+                C![C.new_transaction_annotation_row],
+                input![
+                    C![
+                        C.new_transaction_annotation_field,
+                        C.rounded,
+                        C.border_on_focus
+                    ],
+                    attrs! {
+                        At::Placeholder => strings::NOTE,
+                        At::Value => self.note,
+                    },
+                    input_ev(Ev::Input, CheckoutMsg::SetNote),
+                ],
+                input![
+                    C![
+                        C.new_transaction_annotation_field,
+                        C.rounded,
+                        C.border_on_focus
+                    ],
+                    attrs! {
+                        At::Placeholder => strings::TAGS_PLACEHOLDER,
+                        At::Value => self.tags,
+                    },
+                    input_ev(Ev::Input, CheckoutMsg::SetTags),
+                ],
+                input![
+                    C![
+                        C.new_transaction_annotation_field,
+                        C.rounded,
+                        C.border_on_focus
+                    ],
+                    attrs! {
+                        At::Placeholder => strings::EVENT_CODE,
+                        At::Value => self.event_code,
+                    },
+                    input_ev(Ev::Input, CheckoutMsg::SetEventCode),
+                ],
+            ],
+            self.view_member(res.master_accounts),
+            if self.age_check_open {
+                div![
+                    C![C.age_check],
+                    p![strings::AGE_RESTRICTED_IN_CART],
+                    div![
+                        C![C.age_check_buttons],
+                        button![
+                            C![C.wide_button, C.border_on_focus],
+                            simple_ev(Ev::Click, CheckoutMsg::AgeVerified),
+                            strings::ID_CHECKED,
+                        ],
+                        button![
+                            C![C.wide_button, C.border_on_focus],
+                            simple_ev(Ev::Click, CheckoutMsg::CancelAgeCheck),
+                            strings::ABORT,
+                        ],
+                    ],
+                ]
+            } else if !self.disabled {
                 if self.transaction_bundles.is_empty() {
                     button![
                         C![C.greyed_out, C.wide_button, C.border_on_focus],
@@ -346,13 +1000,13 @@ impl Checkout {
                             St::Filter => "invert(100%)",
                         },],
                         attrs! { At::Disabled => true },
-                        "Slutför Köp",
+                        strings::COMPLETE_PURCHASE,
                     ]
                 } else {
                     button![
                         C![C.wide_button, C.border_on_focus],
                         simple_ev(Ev::Click, CheckoutMsg::ConfirmPurchase),
-                        "Slutför Köp",
+                        strings::COMPLETE_PURCHASE,
                     ]
                 }
             } else {
@@ -367,7 +1021,7 @@ impl Checkout {
                         },
                     ],
                     attrs! { At::Disabled => true },
-                    "Slutför Köp",
+                    strings::COMPLETE_PURCHASE,
                 ]
             },
             if let Some(message) = &self.confirm_button_message {
@@ -378,3 +1032,168 @@ impl Checkout {
         ]
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn item(id: InventoryItemId, price: Option<i32>) -> InventoryItem {
+        InventoryItem {
+            id,
+            name: format!("item {}", id),
+            price,
+            image_url: None,
+            stock: 10,
+            archived_at: None,
+            age_restricted: false,
+            prepared: false,
+            low_stock_at: None,
+            reserved: 0,
+        }
+    }
+
+    #[test]
+    fn test_add_items() {
+        let mut cart = vec![];
+        add_to_cart(&mut cart, item_line(&item(1, Some(500))), 1);
+        add_to_cart(&mut cart, item_line(&item(2, None)), 1);
+        add_to_cart(&mut cart, item_line(&item(1, Some(500))), 2);
+
+        assert_eq!(cart.len(), 2);
+        assert_eq!(cart[0].change, -3);
+        assert_eq!(cart[0].price, Some(500.into()));
+        assert_eq!(cart[1].change, -1);
+        assert_eq!(cart[1].price, Some(0.into()));
+    }
+
+    #[test]
+    fn test_add_bundles() {
+        let bundle = InventoryBundle {
+            id: 1,
+            name: "Two for one".to_string(),
+            price: 500.into(),
+            image_url: None,
+            item_ids: vec![1, 1],
+        };
+
+        let mut cart = vec![];
+        add_to_cart(&mut cart, item_line(&item(1, Some(500))), 1);
+        add_to_cart(&mut cart, bundle_line(&bundle), 1);
+        add_to_cart(&mut cart, bundle_line(&bundle), 1);
+
+        // a bundle is never merged with its items, even if they are the same
+        assert_eq!(cart.len(), 2);
+        assert_eq!(cart[1].item_ids.get(&1), Some(&2));
+        assert_eq!(cart[1].change, -2);
+
+        let item_prices = vec![(1, Currency::from(500))].into_iter().collect();
+        let request = QuoteRequest {
+            bundles: cart,
+            tendered: Some(2000.into()),
+            cash: true,
+        };
+        let quote = CheckoutQuote::compute(
+            &request,
+            &item_prices,
+            &HashMap::new(),
+            &CheckoutRules::default(),
+        );
+        assert_eq!(quote.subtotal, 2500.into());
+        assert_eq!(quote.discount, 1000.into());
+        assert_eq!(quote.total, 1500.into());
+        assert_eq!(quote.change, Some(500.into()));
+    }
+
+    #[test]
+    fn test_add_modified_items() {
+        let modifier = |id, group: &str, name: &str, price_delta: i32| ItemModifier {
+            id,
+            item_id: 1,
+            group: Some(group.to_string()),
+            name: name.to_string(),
+            price_delta: price_delta.into(),
+            archived_at: None,
+        };
+        let large = modifier(1, "Storlek", "stor", 200);
+        let oat_milk = modifier(2, "Mjölk", "havremjölk", 100);
+        let coffee = item(1, Some(1000));
+
+        let mut cart = vec![];
+        add_to_cart(
+            &mut cart,
+            modified_item_line(&coffee, &[&large, &oat_milk]),
+            1,
+        );
+        add_to_cart(
+            &mut cart,
+            modified_item_line(&coffee, &[&oat_milk, &large]),
+            1,
+        );
+        add_to_cart(&mut cart, modified_item_line(&coffee, &[&large]), 1);
+        add_to_cart(&mut cart, modified_item_line(&coffee, &[]), 1);
+
+        // the same choices are merged, whatever order they were made in
+        assert_eq!(cart.len(), 3);
+        assert_eq!(cart[0].change, -2);
+        assert_eq!(cart[0].price, Some(1300.into()));
+        assert_eq!(cart[0].modifier_ids, vec![1, 2]);
+        assert_eq!(
+            cart[0].description.as_deref(),
+            Some("item 1 (stor, havremjölk)")
+        );
+        assert_eq!(cart[1].price, Some(1200.into()));
+        assert_eq!(cart[2].description, None);
+        assert!(cart[2].modifier_ids.is_empty());
+    }
+
+    #[test]
+    fn test_remove_cleared() {
+        let mut cart = vec![];
+        add_to_cart(&mut cart, item_line(&item(1, Some(500))), 1);
+        add_to_cart(&mut cart, item_line(&item(2, Some(500))), 1);
+        add_to_cart(&mut cart, item_line(&item(1, Some(500))), -1);
+        remove_cleared(&mut cart);
+
+        assert_eq!(cart.len(), 1);
+        assert_eq!(cart[0].item_ids.get(&2), Some(&1));
+    }
+
+    #[test]
+    fn test_contains_age_restricted() {
+        let beer = InventoryItem {
+            age_restricted: true,
+            ..item(2, Some(2500))
+        };
+        let inventory: HashMap<_, _> = vec![item(1, Some(500)), beer.clone()]
+            .into_iter()
+            .map(|item| (item.id, item))
+            .collect();
+
+        let mut cart = vec![];
+        add_to_cart(&mut cart, item_line(&item(1, Some(500))), 1);
+        assert!(!contains_age_restricted(&cart, &inventory));
+
+        add_to_cart(&mut cart, item_line(&beer), 1);
+        assert!(contains_age_restricted(&cart, &inventory));
+
+        // nothing is sold of a cleared line
+        cart[1].change = 0;
+        assert!(!contains_age_restricted(&cart, &inventory));
+    }
+
+    #[test]
+    fn test_merge_carts() {
+        let mut failed = vec![];
+        add_to_cart(&mut failed, item_line(&item(1, Some(500))), 2);
+
+        // the next customer had started shopping when the purchase failed
+        let mut added_since = vec![];
+        add_to_cart(&mut added_since, item_line(&item(1, Some(500))), 1);
+        add_to_cart(&mut added_since, item_line(&item(2, Some(500))), 1);
+
+        merge_carts(&mut failed, added_since);
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].change, -3);
+        assert_eq!(failed[1].change, -1);
+    }
+}
@@ -0,0 +1,385 @@
+use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::strings;
+use crate::util::fetch::{api_response, form_response};
+use crate::util::simple_ev;
+use crate::views::view_field_error;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::{
+    book_account::{BookAccount, BookAccountId},
+    correction::{Correction, CorrectionId, CorrectionKind, CorrectionStatus, NewCorrection},
+    currency::Currency,
+    inventory::{InventoryItemId, InventoryItemStock as InventoryItem},
+    live::LiveEvent,
+    validation::FieldError,
+};
+
+#[derive(Clone, Debug)]
+pub enum CorrectionsMsg {
+    /// Whether the stock of an item is corrected, rather than a balance
+    SetStock(bool),
+    SetDebitedAccount(String),
+    SetCreditedAccount(String),
+    SetAmount(String),
+    SetItem(String),
+    SetChange(String),
+    SetReason(String),
+    Request,
+
+    Approve(CorrectionId),
+    Reject(CorrectionId),
+
+    /// The server took the correction, and applied it unless it waits for approval
+    Requested(Correction),
+    Changed,
+    /// The server rejected some fields of the correction
+    Invalid(Vec<FieldError>),
+    /// Handled by the parent
+    Failed(String),
+
+    Live(LiveEvent),
+}
+
+/// Manual corrections of balances and of the stock, and those which wait for the approval of a
+/// second admin
+pub struct CorrectionsPanel {
+    stock: bool,
+    debited_account: Option<BookAccountId>,
+    credited_account: Option<BookAccountId>,
+    amount: String,
+    item: Option<InventoryItemId>,
+    change: String,
+    reason: String,
+
+    /// The fields of the correction which were rejected when it was requested
+    errors: Vec<FieldError>,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/corrections"]
+    #[policy = "SilentRefetch"]
+    corrections: &'a Vec<Correction>,
+
+    #[url = "/api/book_accounts"]
+    #[policy = "SilentRefetch"]
+    book_accounts: &'a HashMap<BookAccountId, BookAccount>,
+
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    inventory: &'a HashMap<InventoryItemId, InventoryItem>,
+}
+
+impl CorrectionsPanel {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<CorrectionsMsg>) -> Self {
+        orders.subscribe(CorrectionsMsg::Live);
+        Res::acquire(rs, orders).ok();
+        CorrectionsPanel {
+            stock: false,
+            debited_account: None,
+            credited_account: None,
+            amount: String::new(),
+            item: None,
+            change: String::new(),
+            reason: String::new(),
+            errors: vec![],
+        }
+    }
+
+    /// The correction which has been entered, if it's complete
+    fn new_correction(&self) -> Option<NewCorrection> {
+        let kind = if self.stock {
+            CorrectionKind::Stock {
+                item_id: self.item?,
+                change: self.change.trim().parse().ok()?,
+            }
+        } else {
+            CorrectionKind::Balance {
+                debited_account: self.debited_account?,
+                credited_account: self.credited_account?,
+                amount: self.amount.trim().parse().ok()?,
+            }
+        };
+        Some(NewCorrection {
+            kind,
+            reason: self.reason.clone(),
+        })
+    }
+
+    pub fn update(
+        &mut self,
+        msg: CorrectionsMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<CorrectionsMsg>,
+    ) -> Result<(), NotAvailable> {
+        match msg {
+            CorrectionsMsg::SetStock(stock) => self.stock = stock,
+            CorrectionsMsg::SetDebitedAccount(id) => self.debited_account = id.parse().ok(),
+            CorrectionsMsg::SetCreditedAccount(id) => self.credited_account = id.parse().ok(),
+            CorrectionsMsg::SetAmount(amount) => self.amount = amount,
+            CorrectionsMsg::SetItem(id) => self.item = id.parse().ok(),
+            CorrectionsMsg::SetChange(change) => self.change = change,
+            CorrectionsMsg::SetReason(reason) => self.reason = reason,
+            CorrectionsMsg::Request => {
+                let correction = match self.new_correction() {
+                    Some(correction) => correction,
+                    None => return Ok(()),
+                };
+                orders.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new("/api/corrections")
+                            .method(Method::Post)
+                            .json(&correction)?;
+                        form_response::<Correction>(request.fetch().await?).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(correction)) => CorrectionsMsg::Requested(correction),
+                        Ok(Err(errors)) => CorrectionsMsg::Invalid(errors),
+                        Err(e) => CorrectionsMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            CorrectionsMsg::Approve(id) => decide(id, "approve", orders),
+            CorrectionsMsg::Reject(id) => decide(id, "reject", orders),
+            CorrectionsMsg::Requested(_) => {
+                self.amount.clear();
+                self.change.clear();
+                self.reason.clear();
+                self.errors.clear();
+                self.update(CorrectionsMsg::Changed, rs, orders)?;
+            }
+            CorrectionsMsg::Changed | CorrectionsMsg::Live(LiveEvent::CorrectionsChanged) => {
+                rs.mark_as_dirty(Res::corrections_url(), orders);
+                rs.mark_as_dirty(Res::book_accounts_url(), orders);
+                rs.mark_as_dirty(Res::inventory_url(), orders);
+            }
+            CorrectionsMsg::Invalid(errors) => self.errors = errors,
+            CorrectionsMsg::Failed(_) | CorrectionsMsg::Live(_) => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<CorrectionsMsg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return section![C![C.admin_section], h2![strings::ADMIN_CORRECTIONS]],
+        };
+
+        let mut accounts: Vec<&BookAccount> = res.book_accounts.values().collect();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut items: Vec<&InventoryItem> = res
+            .inventory
+            .values()
+            .filter(|item| item.archived_at.is_none())
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let account_name = |id: BookAccountId| {
+            res.book_accounts
+                .get(&id)
+                .map(|account| account.name.as_str())
+                .unwrap_or(strings::MISSING_NAME.get())
+        };
+        let describe = |kind: &CorrectionKind| match *kind {
+            CorrectionKind::Balance {
+                debited_account,
+                credited_account,
+                amount,
+            } => format!(
+                "{}:- {} → {}",
+                amount,
+                account_name(debited_account),
+                account_name(credited_account)
+            ),
+            CorrectionKind::Stock { item_id, change } => format!(
+                "{} {:+}",
+                res.inventory
+                    .get(&item_id)
+                    .map(|item| item.name.as_str())
+                    .unwrap_or(strings::MISSING_NAME.get()),
+                change
+            ),
+        };
+        let account_select = |selected: Option<BookAccountId>,
+                              msg: fn(String) -> CorrectionsMsg| {
+            select![
+                option![
+                    attrs! {At::Value => ""},
+                    attrs! {At::Selected => selected.is_none().as_at_value()},
+                    strings::CHOOSE_ACCOUNT,
+                ],
+                accounts.iter().map(|account| {
+                    option![
+                        attrs! {At::Value => account.id},
+                        attrs! {At::Selected => (selected == Some(account.id)).as_at_value()},
+                        &account.name,
+                    ]
+                }),
+                input_ev(Ev::Change, msg),
+            ]
+        };
+
+        section![
+            C![C.admin_section],
+            h2![strings::ADMIN_CORRECTIONS],
+            p![C![C.admin_status_off], strings::CORRECTIONS_HINT],
+            table![
+                C![C.admin_table],
+                res.corrections.iter().map(|correction| {
+                    let id = correction.id;
+                    tr![
+                        td![format!("#{}", id)],
+                        td![
+                            div![describe(&correction.kind)],
+                            div![C![C.admin_status_off], &correction.reason],
+                        ],
+                        td![correction.requested_by.as_deref().unwrap_or("")],
+                        td![
+                            div![status_text(correction.status)],
+                            div![
+                                C![C.admin_status_off],
+                                correction.decided_by.as_deref().unwrap_or(""),
+                            ],
+                        ],
+                        if correction.status == CorrectionStatus::Pending {
+                            td![
+                                button![
+                                    C![C.rounded, C.border_on_focus],
+                                    simple_ev(Ev::Click, CorrectionsMsg::Approve(id)),
+                                    strings::APPROVE,
+                                ],
+                                button![
+                                    C![C.rounded, C.border_on_focus],
+                                    simple_ev(Ev::Click, CorrectionsMsg::Reject(id)),
+                                    strings::REJECT,
+                                ],
+                            ]
+                        } else {
+                            td![]
+                        },
+                    ]
+                }),
+            ],
+            h3![strings::NEW_CORRECTION],
+            div![
+                C![C.admin_correction_form],
+                select![
+                    option![
+                        attrs! {At::Value => "balance"},
+                        attrs! {At::Selected => (!self.stock).as_at_value()},
+                        strings::CORRECT_BALANCE,
+                    ],
+                    option![
+                        attrs! {At::Value => "stock"},
+                        attrs! {At::Selected => self.stock.as_at_value()},
+                        strings::CORRECT_STOCK,
+                    ],
+                    input_ev(Ev::Change, |kind| CorrectionsMsg::SetStock(kind == "stock")),
+                ],
+                if self.stock {
+                    div![
+                        select![
+                            option![
+                                attrs! {At::Value => ""},
+                                attrs! {At::Selected => self.item.is_none().as_at_value()},
+                                strings::CHOOSE_ITEM,
+                            ],
+                            items.iter().map(|item| {
+                                option![
+                                    attrs! {At::Value => item.id},
+                                    attrs! {At::Selected => (self.item == Some(item.id)).as_at_value()},
+                                    &item.name,
+                                ]
+                            }),
+                            input_ev(Ev::Change, CorrectionsMsg::SetItem),
+                        ],
+                        input![
+                            C![C.rounded, C.border_on_focus],
+                            attrs! {
+                                At::Type => "number",
+                                At::Placeholder => strings::STOCK_CHANGE,
+                                At::Value => self.change,
+                            },
+                            input_ev(Ev::Input, CorrectionsMsg::SetChange),
+                        ],
+                        view_field_error(&self.errors, "change"),
+                    ]
+                } else {
+                    div![
+                        label![
+                            strings::MOVE_FROM,
+                            account_select(self.debited_account, CorrectionsMsg::SetDebitedAccount),
+                        ],
+                        label![
+                            strings::MOVE_TO,
+                            account_select(
+                                self.credited_account,
+                                CorrectionsMsg::SetCreditedAccount
+                            ),
+                        ],
+                        view_field_error(&self.errors, "credited_account"),
+                        input![
+                            C![C.rounded, C.border_on_focus],
+                            attrs! {
+                                At::Placeholder => strings::CORRECTION_AMOUNT,
+                                At::Value => self.amount,
+                            },
+                            input_ev(Ev::Input, CorrectionsMsg::SetAmount),
+                        ],
+                        view_field_error(&self.errors, "amount"),
+                    ]
+                },
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {
+                        At::Placeholder => strings::CORRECTION_REASON,
+                        At::Value => self.reason,
+                    },
+                    input_ev(Ev::Input, CorrectionsMsg::SetReason),
+                ],
+                view_field_error(&self.errors, "reason"),
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {
+                        At::Disabled => (self.new_correction().is_none()
+                            || self.reason.trim().is_empty())
+                        .as_at_value()
+                    },
+                    simple_ev(Ev::Click, CorrectionsMsg::Request),
+                    strings::REQUEST_CORRECTION,
+                ],
+            ],
+        ]
+    }
+}
+
+fn status_text(status: CorrectionStatus) -> Text {
+    match status {
+        CorrectionStatus::Pending => strings::CORRECTION_PENDING,
+        CorrectionStatus::Applied => strings::CORRECTION_APPLIED,
+        CorrectionStatus::Rejected => strings::CORRECTION_REJECTED,
+    }
+}
+
+/// Approve or reject a pending correction. Not retried, since the server refuses to do it twice.
+fn decide(id: CorrectionId, decision: &str, orders: &mut impl Orders<CorrectionsMsg>) {
+    let url = format!("/api/corrections/{}/{}", id, decision);
+    orders.perform_cmd(async move {
+        let result = async {
+            let request = Request::new(url).method(Method::Post);
+            api_response::<Correction>(request.fetch().await?).await
+        }
+        .await;
+        match result {
+            Ok(Ok(_)) => CorrectionsMsg::Changed,
+            // e.g. that the admin requested it themselves
+            Ok(Err(error)) => CorrectionsMsg::Failed(error.description),
+            Err(e) => CorrectionsMsg::Failed(format!("{:?}", e)),
+        }
+    });
+}
@@ -0,0 +1,172 @@
+use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::strings;
+use crate::util::fetch::api_response;
+use crate::util::simple_ev;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::{
+    book_account::{BookAccount, BookAccountId},
+    izettle::{FailedPayment, FailedPaymentId, FailedPaymentStatus},
+    live::LiveEvent,
+    time::Tz,
+};
+
+#[derive(Clone, Debug)]
+pub enum FailedPaymentsMsg {
+    Retry(FailedPaymentId),
+    WriteOff(FailedPaymentId),
+
+    Changed,
+    /// Handled by the parent
+    Failed(String),
+
+    Live(LiveEvent),
+}
+
+/// Card payments which failed or got no result from the bridge, for reconciling the day
+pub struct FailedPaymentsPanel;
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/izettle/failed_payments"]
+    #[policy = "SilentRefetch"]
+    failed_payments: &'a Vec<FailedPayment>,
+
+    #[url = "/api/book_accounts"]
+    #[policy = "SilentRefetch"]
+    book_accounts: &'a HashMap<BookAccountId, BookAccount>,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
+}
+
+impl FailedPaymentsPanel {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<FailedPaymentsMsg>) -> Self {
+        orders.subscribe(FailedPaymentsMsg::Live);
+        Res::acquire(rs, orders).ok();
+        FailedPaymentsPanel
+    }
+
+    pub fn update(
+        &mut self,
+        msg: FailedPaymentsMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<FailedPaymentsMsg>,
+    ) -> Result<(), NotAvailable> {
+        match msg {
+            FailedPaymentsMsg::Retry(id) => resolve(id, "retry", orders),
+            FailedPaymentsMsg::WriteOff(id) => resolve(id, "write_off", orders),
+            FailedPaymentsMsg::Changed
+            | FailedPaymentsMsg::Live(LiveEvent::FailedPaymentsChanged) => {
+                rs.mark_as_dirty(Res::failed_payments_url(), orders);
+            }
+            FailedPaymentsMsg::Failed(_) | FailedPaymentsMsg::Live(_) => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<FailedPaymentsMsg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return section![C![C.admin_section], h2![strings::ADMIN_FAILED_PAYMENTS]],
+        };
+
+        let account_name = |id: BookAccountId| {
+            res.book_accounts
+                .get(&id)
+                .map(|account| account.name.as_str())
+                .unwrap_or(strings::MISSING_NAME.get())
+        };
+
+        section![
+            C![C.admin_section],
+            h2![strings::ADMIN_FAILED_PAYMENTS],
+            p![C![C.admin_status_off], strings::FAILED_PAYMENTS_HINT],
+            if res.failed_payments.is_empty() {
+                p![strings::NO_FAILED_PAYMENTS]
+            } else {
+                table![
+                    C![C.admin_table],
+                    res.failed_payments.iter().map(|payment| {
+                        let id = payment.id;
+                        tr![
+                            td![payment
+                                .time
+                                .with_timezone(res.reporting_timezone)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string()],
+                            td![
+                                div![format!(
+                                    "{}:- {} → {}",
+                                    payment.amount,
+                                    account_name(payment.debited_account),
+                                    account_name(payment.credited_account)
+                                )],
+                                div![
+                                    C![C.admin_status_off],
+                                    payment.description.as_deref().unwrap_or(""),
+                                ],
+                            ],
+                            td![payment.created_by.as_deref().unwrap_or("")],
+                            td![&payment.reason],
+                            td![
+                                div![status_text(payment.status)],
+                                div![
+                                    C![C.admin_status_off],
+                                    payment.resolved_by.as_deref().unwrap_or(""),
+                                ],
+                            ],
+                            if payment.status == FailedPaymentStatus::Unresolved {
+                                td![
+                                    button![
+                                        C![C.rounded, C.border_on_focus],
+                                        simple_ev(Ev::Click, FailedPaymentsMsg::Retry(id)),
+                                        strings::RETRY_PAYMENT,
+                                    ],
+                                    button![
+                                        C![C.rounded, C.border_on_focus],
+                                        simple_ev(Ev::Click, FailedPaymentsMsg::WriteOff(id)),
+                                        strings::WRITE_OFF,
+                                    ],
+                                ]
+                            } else {
+                                td![]
+                            },
+                        ]
+                    }),
+                ]
+            },
+        ]
+    }
+}
+
+fn status_text(status: FailedPaymentStatus) -> Text {
+    match status {
+        FailedPaymentStatus::Unresolved => strings::FAILED_PAYMENT_UNRESOLVED,
+        FailedPaymentStatus::Retried => strings::FAILED_PAYMENT_RETRIED,
+        FailedPaymentStatus::WrittenOff => strings::FAILED_PAYMENT_WRITTEN_OFF,
+        FailedPaymentStatus::PaidLate => strings::FAILED_PAYMENT_PAID_LATE,
+    }
+}
+
+/// Retry or write off a failed payment. Not retried, since the server refuses to do it twice.
+fn resolve(id: FailedPaymentId, action: &str, orders: &mut impl Orders<FailedPaymentsMsg>) {
+    let url = format!("/api/izettle/failed_payments/{}/{}", id, action);
+    orders.perform_cmd(async move {
+        let result = async {
+            let request = Request::new(url).method(Method::Post);
+            api_response::<FailedPayment>(request.fetch().await?).await
+        }
+        .await;
+        match result {
+            Ok(Ok(_)) => FailedPaymentsMsg::Changed,
+            // e.g. that the bridge is offline
+            Ok(Err(error)) => FailedPaymentsMsg::Failed(error.description),
+            Err(e) => FailedPaymentsMsg::Failed(format!("{:?}", e)),
+        }
+    });
+}
@@ -84,19 +84,7 @@ impl FilterMenu {
 
             // compare against the filter value
             let ord = value.cmp_to_str(&filter.value);
-
-            match (*filter.op.selected(), ord) {
-                (FilterOp::GrTh, Ordering::Greater)            // >  true if greater
-                | (FilterOp::GrEq, Ordering::Greater)          // >=     ... greater
-                | (FilterOp::GrEq, Ordering::Equal)            // >=     ... equals
-                | (FilterOp::LeTh, Ordering::Less)             // <      ... less
-                | (FilterOp::LeEq, Ordering::Less)             // <=     ... less
-                | (FilterOp::LeEq, Ordering::Equal)            // <=     ... equals
-                | (FilterOp::NotEquals, Ordering::Greater)     // !=     ... greater
-                | (FilterOp::NotEquals, Ordering::Less)        // !=     ... less
-                | (FilterOp::Equals, Ordering::Equal) => true, // ==     ... equals
-                _ => false,                                    // otherwise false
-            }
+            filter.op.selected().matches(ord)
         })
     }
 
@@ -179,6 +167,22 @@ impl FilterMenu {
 }
 
 impl FilterOp {
+    /// Whether a value which compares as `ord` to the filter value passes the filter
+    pub fn matches(self, ord: Ordering) -> bool {
+        match (self, ord) {
+            (FilterOp::GrTh, Ordering::Greater)            // >  true if greater
+            | (FilterOp::GrEq, Ordering::Greater)          // >=     ... greater
+            | (FilterOp::GrEq, Ordering::Equal)            // >=     ... equals
+            | (FilterOp::LeTh, Ordering::Less)             // <      ... less
+            | (FilterOp::LeEq, Ordering::Less)             // <=     ... less
+            | (FilterOp::LeEq, Ordering::Equal)            // <=     ... equals
+            | (FilterOp::NotEquals, Ordering::Greater)     // !=     ... greater
+            | (FilterOp::NotEquals, Ordering::Less)        // !=     ... less
+            | (FilterOp::Equals, Ordering::Equal) => true, // ==     ... equals
+            _ => false,                                    // otherwise false
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             FilterOp::NotEquals => "!=",
@@ -190,3 +194,30 @@ impl FilterOp {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::FilterOp;
+    use crate::util::CompareToStr;
+    use std::cmp::Ordering;
+
+    #[test]
+    fn test_filter_ops() {
+        let passes = |op: FilterOp| {
+            [Ordering::Less, Ordering::Equal, Ordering::Greater]
+                .iter()
+                .map(|&ord| op.matches(ord))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(passes(FilterOp::Equals), [false, true, false]);
+        assert_eq!(passes(FilterOp::NotEquals), [true, false, true]);
+        assert_eq!(passes(FilterOp::GrTh), [false, false, true]);
+        assert_eq!(passes(FilterOp::GrEq), [false, true, true]);
+        assert_eq!(passes(FilterOp::LeTh), [true, false, false]);
+        assert_eq!(passes(FilterOp::LeEq), [true, true, false]);
+
+        assert!(FilterOp::GrTh.matches(100.cmp_to_str("99")));
+        assert!(!FilterOp::Equals.matches("Kassan".cmp_to_str("Banken")));
+    }
+}
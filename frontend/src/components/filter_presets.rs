@@ -0,0 +1,187 @@
+use crate::generated::css_classes::C;
+use crate::strings;
+use crate::util::fetch::send_with_retry;
+use crate::util::simple_ev;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{ResourceStore, Resources};
+use strecklistan_api::filter_preset::{
+    FilterPreset, FilterPresetId, NewFilterPreset, PresetFilter, PresetPage,
+};
+
+#[derive(Clone, Debug)]
+pub enum FilterPresetsMsg {
+    Pick(String),
+    SetName(String),
+    Save(PresetFilter),
+    Saved(FilterPreset),
+    Delete,
+    Deleted(FilterPresetId),
+
+    /// A preset was picked, its filter is applied by the parent
+    Apply(PresetFilter),
+    /// Saving or deleting failed, handled by the parent
+    Failed(String),
+}
+
+/// A dropdown of the filters which the user has saved for a page, and a form for saving the
+/// current filter of the page
+#[derive(Clone)]
+pub struct FilterPresets {
+    page: PresetPage,
+    name_input: String,
+
+    /// The preset which was picked last, which can be deleted
+    picked: Option<FilterPresetId>,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/filter_presets"]
+    #[policy = "SilentRefetch"]
+    presets: &'a Vec<FilterPreset>,
+}
+
+impl FilterPresets {
+    pub fn new(
+        page: PresetPage,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<FilterPresetsMsg>,
+    ) -> Self {
+        Res::acquire(rs, orders).ok();
+        FilterPresets {
+            page,
+            name_input: String::new(),
+            picked: None,
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        msg: FilterPresetsMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<FilterPresetsMsg>,
+    ) {
+        match msg {
+            FilterPresetsMsg::Pick(input) => {
+                let preset = Res::acquire_now(rs).ok().and_then(|res| {
+                    let id: FilterPresetId = input.parse().ok()?;
+                    res.presets.iter().find(|preset| preset.id == id).cloned()
+                });
+                self.picked = preset.as_ref().map(|preset| preset.id);
+                if let Some(preset) = preset {
+                    self.name_input = preset.name;
+                    orders.send_msg(FilterPresetsMsg::Apply(preset.filter));
+                }
+            }
+            FilterPresetsMsg::SetName(input) => self.name_input = input,
+            FilterPresetsMsg::Save(filter) => {
+                let preset = NewFilterPreset {
+                    name: self.name_input.clone(),
+                    page: self.page,
+                    filter,
+                };
+                orders.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new("/api/filter_preset")
+                            .method(Method::Put)
+                            .json(&preset)?;
+                        send_with_retry(request).await?.check_status()?.json().await
+                    }
+                    .await;
+                    match result {
+                        Ok(preset) => FilterPresetsMsg::Saved(preset),
+                        Err(e) => FilterPresetsMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            FilterPresetsMsg::Saved(preset) => {
+                self.picked = Some(preset.id);
+                self.name_input = preset.name;
+                rs.mark_as_dirty(Res::presets_url(), orders);
+            }
+            FilterPresetsMsg::Delete => {
+                let id = match self.picked {
+                    Some(id) => id,
+                    None => return,
+                };
+                orders.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new(format!("/api/filter_preset/{}", id))
+                            .method(Method::Delete);
+                        send_with_retry(request).await?.check_status()?.json().await
+                    }
+                    .await;
+                    match result {
+                        Ok(id) => FilterPresetsMsg::Deleted(id),
+                        Err(e) => FilterPresetsMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            FilterPresetsMsg::Deleted(id) => {
+                if self.picked == Some(id) {
+                    self.picked = None;
+                    self.name_input.clear();
+                }
+                rs.mark_as_dirty(Res::presets_url(), orders);
+            }
+            FilterPresetsMsg::Apply(_) | FilterPresetsMsg::Failed(_) => {}
+        }
+    }
+
+    /// `current` is the filter of the page, which is saved under the typed name
+    pub fn view(&self, rs: &ResourceStore, current: PresetFilter) -> Node<FilterPresetsMsg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return empty![],
+        };
+        let can_save = !self.name_input.trim().is_empty();
+
+        div![
+            C![C.filter_presets],
+            select![
+                option![
+                    attrs! {At::Value => ""},
+                    attrs! {At::Selected => self.picked.is_none().as_at_value()},
+                    strings::FILTER_PRESETS,
+                ],
+                res.presets
+                    .iter()
+                    .filter(|preset| preset.page == self.page)
+                    .map(|preset| {
+                        let selected = self.picked == Some(preset.id);
+                        option![
+                            attrs! {At::Value => preset.id},
+                            attrs! {At::Selected => selected.as_at_value()},
+                            &preset.name,
+                        ]
+                    }),
+                input_ev(Ev::Change, FilterPresetsMsg::Pick),
+            ],
+            if self.picked.is_some() {
+                button![
+                    C![C.border_on_focus],
+                    attrs! {At::Title => strings::DELETE_PRESET},
+                    simple_ev(Ev::Click, FilterPresetsMsg::Delete),
+                    "✖",
+                ]
+            } else {
+                empty![]
+            },
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::PRESET_NAME,
+                    At::Value => self.name_input,
+                },
+                input_ev(Ev::Input, FilterPresetsMsg::SetName),
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                attrs! {At::Disabled => (!can_save).as_at_value()},
+                simple_ev(Ev::Click, FilterPresetsMsg::Save(current)),
+                strings::SAVE_PRESET,
+            ],
+        ]
+    }
+}
@@ -0,0 +1,351 @@
+use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::strings;
+use crate::util::fetch::send_with_retry;
+use crate::util::simple_ev;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{ResourceStore, Resources};
+use std::collections::{BTreeMap, HashMap};
+use strecklistan_api::{
+    currency::Currency,
+    inventory::{InventoryItemId, InventoryItemStock as InventoryItem},
+    transaction::{
+        returnable_items, NewGoodsReturn, ReturnReason, TransactionDetail, TransactionId,
+    },
+};
+
+#[derive(Clone, Debug)]
+pub enum GoodsReturnMsg {
+    SetCount(InventoryItemId, String),
+    SetReason(String),
+    ToggleRestock,
+    SetNote(String),
+
+    Submit,
+    /// The return was saved as the transaction, handled by the parent
+    Saved(TransactionId),
+    /// Saving the return failed, handled by the parent
+    Failed(String),
+    /// Handled by the parent
+    Cancel,
+}
+
+/// A form for returning goods from a sale, in the detail drawer of the transaction
+#[derive(Clone)]
+pub struct GoodsReturnForm {
+    transaction_id: TransactionId,
+
+    /// How many of each item may still be returned, in the order they are listed
+    returnable: BTreeMap<InventoryItemId, u32>,
+
+    /// What each item is paid back with
+    prices: HashMap<InventoryItemId, Currency>,
+
+    /// What hasn't already been paid back by earlier returns
+    max_amount: Currency,
+
+    counts: HashMap<InventoryItemId, u32>,
+    reason: Option<ReturnReason>,
+    restock: bool,
+    note: String,
+    sending: bool,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    inventory: &'a HashMap<InventoryItemId, InventoryItem>,
+}
+
+pub fn reason_text(reason: ReturnReason) -> Text {
+    match reason {
+        ReturnReason::Defective => strings::RETURN_DEFECTIVE,
+        ReturnReason::WrongItem => strings::RETURN_WRONG_ITEM,
+        ReturnReason::Expired => strings::RETURN_EXPIRED,
+        ReturnReason::ChangedMind => strings::RETURN_CHANGED_MIND,
+        ReturnReason::Other => strings::RETURN_OTHER,
+    }
+}
+
+impl GoodsReturnForm {
+    pub fn new(detail: &TransactionDetail, rs: &ResourceStore) -> Self {
+        let transaction = &detail.transaction;
+        let mut prices = transaction.unit_prices();
+
+        // items which were only sold in bundles are paid back with their list price
+        if let Ok(res) = Res::acquire_now(rs) {
+            for item in res.inventory.values() {
+                if let Some(price) = item.price {
+                    prices.entry(item.id).or_insert_with(|| price.into());
+                }
+            }
+        }
+
+        let paid_back: Currency = detail.returns.iter().map(|r| r.amount).sum();
+
+        GoodsReturnForm {
+            transaction_id: transaction.id,
+            returnable: returnable_items(transaction, &detail.returns)
+                .into_iter()
+                .collect(),
+            prices,
+            max_amount: transaction.amount - paid_back,
+            counts: HashMap::new(),
+            reason: None,
+            restock: true,
+            note: String::new(),
+            sending: false,
+        }
+    }
+
+    /// Whether anything is left to return from the transaction
+    pub fn has_returnable(detail: &TransactionDetail) -> bool {
+        detail.goods_return.is_none()
+            && detail.deleted_at.is_none()
+            && !returnable_items(&detail.transaction, &detail.returns).is_empty()
+    }
+
+    /// What is paid back for the chosen items, at what they were sold for
+    fn amount(&self) -> Currency {
+        let total: Currency = self
+            .counts
+            .iter()
+            .map(|(item_id, &count)| {
+                let price = self.prices.get(item_id).copied().unwrap_or_default();
+                Currency::from(i64::from(price) * i64::from(count))
+            })
+            .sum();
+        total.min(self.max_amount)
+    }
+
+    fn can_submit(&self) -> bool {
+        !self.sending && self.reason.is_some() && self.counts.values().any(|&count| count > 0)
+    }
+
+    pub fn update(&mut self, msg: GoodsReturnMsg, orders: &mut impl Orders<GoodsReturnMsg>) {
+        match msg {
+            GoodsReturnMsg::SetCount(item_id, input) => {
+                let max = self.returnable.get(&item_id).copied().unwrap_or(0);
+                let count = input.parse().unwrap_or(0).min(max);
+                self.counts.insert(item_id, count);
+            }
+            GoodsReturnMsg::SetReason(input) => {
+                self.reason = input
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| ReturnReason::ALL.get(i))
+                    .copied();
+            }
+            GoodsReturnMsg::ToggleRestock => self.restock = !self.restock,
+            GoodsReturnMsg::SetNote(note) => self.note = note,
+            GoodsReturnMsg::Submit => {
+                let reason = match self.reason {
+                    Some(reason) if self.can_submit() => reason,
+                    _ => return,
+                };
+                self.sending = true;
+
+                let goods_return = NewGoodsReturn {
+                    items: self
+                        .counts
+                        .iter()
+                        .filter(|(_, &count)| count > 0)
+                        .map(|(&item_id, &count)| (item_id, count))
+                        .collect(),
+                    amount: self.amount(),
+                    reason,
+                    restock: self.restock,
+                    note: Some(self.note.clone()),
+                };
+                let url = format!("/api/transaction/{}/return", self.transaction_id);
+                orders.perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(Request::new(url).method(Method::Post).json(&goods_return)?)
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(id) => GoodsReturnMsg::Saved(id),
+                        Err(e) => GoodsReturnMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            GoodsReturnMsg::Failed(_) => self.sending = false,
+            GoodsReturnMsg::Saved(_) | GoodsReturnMsg::Cancel => {}
+        }
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<GoodsReturnMsg> {
+        let item_name = |id: &InventoryItemId| {
+            Res::acquire_now(rs)
+                .ok()
+                .and_then(|res| res.inventory.get(id).map(|item| item.name.clone()))
+                .unwrap_or_else(|| strings::MISSING_NAME.get().to_string())
+        };
+
+        div![
+            C![C.goods_return_form],
+            h3![strings::RETURN_GOODS],
+            table![
+                C![C.transaction_detail_table],
+                tr![th![strings::ITEM], th![strings::QUANTITY]],
+                self.returnable.iter().map(|(&item_id, &max)| {
+                    let count = self.counts.get(&item_id).copied().unwrap_or(0);
+                    tr![
+                        td![item_name(&item_id)],
+                        td![input![
+                            C![C.rounded, C.border_on_focus],
+                            attrs! {
+                                At::Type => "number",
+                                At::Min => 0,
+                                At::Max => max,
+                                At::Value => count,
+                            },
+                            input_ev(Ev::Input, move |input| {
+                                GoodsReturnMsg::SetCount(item_id, input)
+                            }),
+                        ]],
+                    ]
+                }),
+            ],
+            select![
+                C![C.rounded, C.border_on_focus],
+                option![
+                    attrs! {
+                        At::Value => "",
+                        At::Disabled => true.as_at_value(),
+                        At::Selected => self.reason.is_none().as_at_value(),
+                    },
+                    strings::CHOOSE_RETURN_REASON,
+                ],
+                ReturnReason::ALL.iter().enumerate().map(|(i, &reason)| {
+                    option![
+                        attrs! {
+                            At::Value => i,
+                            At::Selected => (self.reason == Some(reason)).as_at_value(),
+                        },
+                        reason_text(reason),
+                    ]
+                }),
+                input_ev(Ev::Change, GoodsReturnMsg::SetReason),
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox"},
+                    attrs! {At::Checked => self.restock.as_at_value()},
+                    ev(Ev::Change, |_| GoodsReturnMsg::ToggleRestock),
+                ],
+                strings::RESTOCK,
+            ],
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::NOTE,
+                    At::Value => self.note,
+                },
+                input_ev(Ev::Input, GoodsReturnMsg::SetNote),
+            ],
+            p![
+                C![C.transaction_detail_total],
+                span![strings::PAID_BACK, " "],
+                span![format!("{}:-", self.amount())],
+            ],
+            div![
+                C![C.goods_return_buttons],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {At::Disabled => (!self.can_submit()).as_at_value()},
+                    simple_ev(Ev::Click, GoodsReturnMsg::Submit),
+                    strings::CONFIRM,
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, GoodsReturnMsg::Cancel),
+                    strings::ABORT,
+                ],
+            ],
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use strecklistan_api::transaction::{GoodsReturn, Transaction, TransactionBundle};
+
+    fn detail() -> TransactionDetail {
+        let bundle = |item_id, price: i32, change| TransactionBundle {
+            description: None,
+            price: Some(price.into()),
+            change,
+            item_ids: vec![(item_id, 1)].into_iter().collect(),
+            expires_on: None,
+            modifier_ids: vec![],
+        };
+
+        TransactionDetail {
+            transaction: Transaction {
+                id: 1,
+                description: None,
+                time: Utc.ymd(2021, 7, 14).and_hms(12, 0, 0),
+                bundles: vec![bundle(1, 500, -2), bundle(2, 2000, -1)],
+                debited_account: 1,
+                credited_account: 2,
+                amount: 2500.into(),
+                note: None,
+                tags: vec![],
+                event_code: None,
+                age_verified: false,
+                member_id: None,
+                created_by: None,
+            },
+            izettle_reference: None,
+            deleted_at: None,
+            goods_return: None,
+            returns: vec![GoodsReturn {
+                transaction_id: 2,
+                original_transaction_id: 1,
+                amount: 1000.into(),
+                reason: ReturnReason::Defective,
+                restocked: false,
+                items: vec![(1, 1)].into_iter().collect(),
+            }],
+            device: None,
+        }
+    }
+
+    #[test]
+    fn test_return_amount() {
+        let detail = detail();
+        let mut form = GoodsReturnForm {
+            transaction_id: 1,
+            returnable: returnable_items(&detail.transaction, &detail.returns)
+                .into_iter()
+                .collect(),
+            prices: detail.transaction.unit_prices(),
+            max_amount: 1500.into(),
+            counts: HashMap::new(),
+            reason: None,
+            restock: true,
+            note: String::new(),
+            sending: false,
+        };
+        assert_eq!(form.returnable.get(&1), Some(&1));
+        assert!(!form.can_submit());
+
+        form.counts.insert(1, 1);
+        assert_eq!(form.amount(), 500.into());
+        form.reason = Some(ReturnReason::WrongItem);
+        assert!(form.can_submit());
+
+        // more than what's left of the sale isn't paid back, even if a discount was given
+        form.counts.insert(2, 1);
+        assert_eq!(form.amount(), 1500.into());
+    }
+}
@@ -1,25 +1,97 @@
+use crate::generated::css_classes::C;
 use crate::strings;
+use crate::util::base;
+use crate::util::fetch::{api_response, error_details, with_approvals};
+use crate::util::simple_ev;
+use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
 use seed::app::cmds::timeout;
 use seed::prelude::*;
 use seed::*;
+use serde::Serialize;
 use strecklistan_api::{
-    izettle::IZettlePayment,
+    approval::GatedAction,
+    error::ErrorCode,
+    inventory::StockShortage,
+    izettle::{IZettleDeposit, IZettlePayment, IZettlePaymentStatus},
     transaction::{NewTransaction, TransactionId},
 };
+use wasm_bindgen::JsCast;
+use web_sys::{EventSource, MessageEvent};
 
 const POLL_TIMEOUT_MS: u32 = 1000;
+const TICK_INTERVAL_MS: u32 = 1000;
 
 /// Helper component for handling iZettle payments
-#[derive(Clone)]
 pub struct IZettlePay {
-    pending: Option<i32>,
+    pending: Option<PendingPayment>,
+}
+
+/// A payment which the bridge hasn't finished yet
+struct PendingPayment {
+    reference: i32,
+    started: DateTime<Utc>,
+
+    /// The last status pushed by the server, None until the first one arrives
+    status: Option<IZettlePaymentStatus>,
+
+    cancelling: bool,
+    cancel_failed: bool,
+    switch_to_cash: bool,
+
+    /// None if the event stream failed, and the payment is polled instead
+    _status_stream: Option<StatusStream>,
+
+    /// Keeps the elapsed time up to date
+    _ticker: StreamHandle,
+}
+
+/// The server-sent status events of a payment, closed when dropped
+struct StatusStream {
+    source: EventSource,
+    _on_status: Closure<dyn FnMut(MessageEvent)>,
+    _on_error: Closure<dyn FnMut(web_sys::Event)>,
+    _handle: StreamHandle,
+}
+
+impl Drop for StatusStream {
+    fn drop(&mut self) {
+        self.source.close();
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum IZettlePayMsg {
+    /// The server accepted the payment, and is waiting for the bridge
+    PaymentStarted(i32),
+
+    /// The server pushed a new status for the payment
+    StatusChanged {
+        reference: i32,
+        status: IZettlePaymentStatus,
+    },
+
+    /// The event stream broke, fall back to polling
+    StreamFailed(i32),
+
     /// Poll for payment completion
     PollPendingPayment(i32),
 
+    /// The state of the payment was fetched
+    PaymentUpdated {
+        reference: i32,
+        payment: IZettlePayment,
+    },
+
+    /// Update the elapsed time
+    Tick,
+
+    /// Give up on the payment, optionally to take cash instead
+    Cancel { switch_to_cash: bool },
+
+    /// The payment couldn't be cancelled
+    CancelFailed,
+
     /// There was an error processing the payment
     Error(IZettlePayErr),
 
@@ -28,6 +100,9 @@ pub enum IZettlePayMsg {
 
     /// The payment was intentionally cancelled
     PaymentCancelled,
+
+    /// The payment was cancelled so that the purchase can be paid with cash
+    SwitchedToCash,
 }
 
 #[derive(Clone, Debug)]
@@ -40,6 +115,15 @@ pub enum IZettlePayErr {
 
     /// A network request has failed
     NetworkError { reason: String },
+
+    /// The payment wasn't started since some items aren't in stock
+    InsufficientStock(Vec<StockShortage>),
+
+    /// The payment wasn't started since an admin has to approve the purchase first
+    ApprovalRequired(GatedAction),
+
+    /// The payment wasn't started since card payments can't be taken right now
+    Unavailable { reason: String },
 }
 
 impl IZettlePay {
@@ -47,24 +131,57 @@ impl IZettlePay {
         IZettlePay { pending: None }
     }
 
-    pub fn pay(&mut self, transaction: NewTransaction, mut orders: impl Orders<IZettlePayMsg>) {
+    pub fn pay(&mut self, transaction: NewTransaction, orders: impl Orders<IZettlePayMsg>) {
+        self.begin("/api/izettle/client/transaction", transaction, orders);
+    }
+
+    /// Pay for a tillgodo deposit, which is credited when the payment completes
+    pub fn pay_deposit(&mut self, deposit: IZettleDeposit, orders: impl Orders<IZettlePayMsg>) {
+        self.begin("/api/izettle/client/deposit", deposit, orders);
+    }
+
+    fn begin<T: Serialize + 'static>(
+        &mut self,
+        url: &'static str,
+        payment: T,
+        mut orders: impl Orders<IZettlePayMsg>,
+    ) {
         if self.pending.is_some() {
             return;
         }
 
         orders.perform_cmd(async move {
             let result = async {
-                Request::new("/api/izettle/client/transaction")
-                    .method(Method::Post)
-                    .json(&transaction)?
+                let response = with_approvals(Request::new(url).method(Method::Post))
+                    .json(&payment)?
                     .fetch()
-                    .await?
-                    .json()
-                    .await
+                    .await?;
+                api_response(response).await
             }
             .await;
+            let posting_failed = || IZettlePayErr::NetworkError {
+                reason: strings::POSTING_TRANSACTION_FAILED.to_string(),
+            };
             match result {
-                Ok(reference) => Some(IZettlePayMsg::PollPendingPayment(reference)),
+                Ok(Ok(reference)) => Some(IZettlePayMsg::PaymentStarted(reference)),
+                Ok(Err(error)) => Some(IZettlePayMsg::Error(match error.code {
+                    ErrorCode::StockEmpty => error_details(error)
+                        .map(IZettlePayErr::InsufficientStock)
+                        .unwrap_or_else(posting_failed),
+                    ErrorCode::ApprovalRequired => error_details(error)
+                        .map(IZettlePayErr::ApprovalRequired)
+                        .unwrap_or_else(posting_failed),
+                    ErrorCode::IZettleDisabled => IZettlePayErr::Unavailable {
+                        reason: strings::IZETTLE_DISABLED.to_string(),
+                    },
+                    ErrorCode::BridgeOffline => IZettlePayErr::Unavailable {
+                        reason: strings::BRIDGE_OFFLINE.to_string(),
+                    },
+                    _ => {
+                        error!("Failed to post transaction", error.description);
+                        posting_failed()
+                    }
+                })),
                 Err(e) => {
                     error!("Failed to post transaction", e);
                     Some(IZettlePayMsg::Error(IZettlePayErr::NetworkError {
@@ -76,14 +193,14 @@ impl IZettlePay {
     }
 
     pub fn pending(&self) -> Option<i32> {
-        self.pending
+        self.pending.as_ref().map(|pending| pending.reference)
     }
 
     pub fn update(&mut self, msg: IZettlePayMsg, mut orders: impl Orders<IZettlePayMsg>) {
         match msg {
-            IZettlePayMsg::PaymentCancelled | IZettlePayMsg::PaymentCompleted { .. } => {
-                self.pending = None
-            }
+            IZettlePayMsg::PaymentCancelled
+            | IZettlePayMsg::SwitchedToCash
+            | IZettlePayMsg::PaymentCompleted { .. } => self.pending = None,
             IZettlePayMsg::Error(error) => {
                 self.pending = None;
                 match error {
@@ -93,11 +210,49 @@ impl IZettlePay {
                     IZettlePayErr::NoTransaction { reference } => {
                         error!("iZettle payment {} does not exist", reference);
                     }
-                    IZettlePayErr::NetworkError { .. } => {}
+                    IZettlePayErr::NetworkError { .. }
+                    | IZettlePayErr::InsufficientStock(_)
+                    | IZettlePayErr::ApprovalRequired(_)
+                    | IZettlePayErr::Unavailable { .. } => {}
+                }
+            }
+            IZettlePayMsg::PaymentStarted(reference) => {
+                let status_stream = open_status_stream(reference, &mut orders);
+                if status_stream.is_none() {
+                    orders.send_msg(IZettlePayMsg::PollPendingPayment(reference));
+                }
+                self.pending = Some(PendingPayment {
+                    reference,
+                    started: Utc::now(),
+                    status: None,
+                    cancelling: false,
+                    cancel_failed: false,
+                    switch_to_cash: false,
+                    _status_stream: status_stream,
+                    _ticker: orders.stream_with_handle(streams::interval(TICK_INTERVAL_MS, || {
+                        IZettlePayMsg::Tick
+                    })),
+                });
+            }
+            IZettlePayMsg::StatusChanged { reference, status } => {
+                let payment = status.payment.clone();
+                if let Some(pending) = self.pending_mut(reference) {
+                    pending.status = Some(status);
+                }
+                self.resolve(reference, payment, orders);
+            }
+            IZettlePayMsg::StreamFailed(reference) => {
+                if let Some(pending) = self.pending_mut(reference) {
+                    if pending._status_stream.take().is_some() {
+                        orders.send_msg(IZettlePayMsg::PollPendingPayment(reference));
+                    }
                 }
             }
             IZettlePayMsg::PollPendingPayment(reference) => {
-                self.pending = Some(reference);
+                // the payment has been resolved some other way
+                if self.pending_mut(reference).is_none() {
+                    return;
+                }
 
                 orders.perform_cmd(async move {
                     let result = async {
@@ -114,21 +269,7 @@ impl IZettlePay {
                             timeout(POLL_TIMEOUT_MS, || ()).await;
                             Some(IZettlePayMsg::PollPendingPayment(reference))
                         }
-                        Ok(IZettlePayment::Paid { transaction_id }) => {
-                            Some(IZettlePayMsg::PaymentCompleted { transaction_id })
-                        }
-                        Ok(IZettlePayment::Cancelled) => Some(IZettlePayMsg::PaymentCancelled),
-                        Ok(IZettlePayment::NoTransaction) => {
-                            Some(IZettlePayMsg::Error(IZettlePayErr::NoTransaction {
-                                reference,
-                            }))
-                        }
-                        Ok(IZettlePayment::Failed { reason }) => {
-                            Some(IZettlePayMsg::Error(IZettlePayErr::PaymentFailed {
-                                reference,
-                                reason,
-                            }))
-                        }
+                        Ok(payment) => Some(IZettlePayMsg::PaymentUpdated { reference, payment }),
                         Err(e) => {
                             error!("Failed to poll for payment", e);
                             Some(IZettlePayMsg::Error(IZettlePayErr::NetworkError {
@@ -138,6 +279,212 @@ impl IZettlePay {
                     }
                 });
             }
+            IZettlePayMsg::PaymentUpdated { reference, payment } => {
+                self.resolve(reference, payment, orders);
+            }
+            IZettlePayMsg::Tick => {}
+            IZettlePayMsg::Cancel { switch_to_cash } => {
+                let pending = match &mut self.pending {
+                    Some(pending) if !pending.cancelling => pending,
+                    _ => return,
+                };
+                pending.cancelling = true;
+                pending.cancel_failed = false;
+                pending.switch_to_cash = switch_to_cash;
+
+                let reference = pending.reference;
+                orders.perform_cmd(async move {
+                    let result = async {
+                        Request::new(&format!("/api/izettle/client/cancel/{}", reference))
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(IZettlePayment::Pending) => Some(IZettlePayMsg::CancelFailed),
+                        Ok(payment) => Some(IZettlePayMsg::PaymentUpdated { reference, payment }),
+                        Err(e) => {
+                            error!("Failed to cancel payment", e);
+                            Some(IZettlePayMsg::CancelFailed)
+                        }
+                    }
+                });
+            }
+            IZettlePayMsg::CancelFailed => {
+                if let Some(pending) = &mut self.pending {
+                    pending.cancelling = false;
+                    pending.cancel_failed = true;
+                    pending.switch_to_cash = false;
+                }
+            }
         }
     }
+
+    fn pending_mut(&mut self, reference: i32) -> Option<&mut PendingPayment> {
+        self.pending
+            .as_mut()
+            .filter(|pending| pending.reference == reference)
+    }
+
+    /// Finish the payment if it's no longer pending.
+    ///
+    /// The result may arrive both from the event stream and from cancelling, but only the first
+    /// one is passed on.
+    fn resolve(
+        &mut self,
+        reference: i32,
+        payment: IZettlePayment,
+        mut orders: impl Orders<IZettlePayMsg>,
+    ) {
+        let switch_to_cash = match self.pending_mut(reference) {
+            Some(pending) => pending.switch_to_cash,
+            None => return,
+        };
+
+        let msg = match payment {
+            IZettlePayment::Pending => return,
+            IZettlePayment::Paid { transaction_id } => {
+                IZettlePayMsg::PaymentCompleted { transaction_id }
+            }
+            IZettlePayment::Cancelled if switch_to_cash => IZettlePayMsg::SwitchedToCash,
+            IZettlePayment::Cancelled => IZettlePayMsg::PaymentCancelled,
+            IZettlePayment::NoTransaction => {
+                IZettlePayMsg::Error(IZettlePayErr::NoTransaction { reference })
+            }
+            IZettlePayment::Failed { reason } => {
+                IZettlePayMsg::Error(IZettlePayErr::PaymentFailed { reference, reason })
+            }
+        };
+
+        // closes the event stream
+        self.pending = None;
+        orders.send_msg(msg);
+    }
+
+    /// The progress of the pending payment, with buttons to give up on it
+    pub fn view_pending(&self, allow_cash: bool) -> Node<IZettlePayMsg> {
+        let pending = match &self.pending {
+            Some(pending) => pending,
+            None => return empty![],
+        };
+
+        let elapsed = (Utc::now() - pending.started).num_seconds().max(0);
+        let (bridge, reader) = match &pending.status {
+            None => (strings::PAYMENT_CONNECTING, None),
+            Some(status) if !status.bridge_online => (strings::BRIDGE_OFFLINE, None),
+            Some(status) if status.at_reader => (
+                strings::BRIDGE_STATUS_ONLINE,
+                Some(strings::PAYMENT_AT_READER),
+            ),
+            Some(_) => (strings::BRIDGE_STATUS_ONLINE, Some(strings::PAYMENT_QUEUED)),
+        };
+        let bridge_class = match &pending.status {
+            Some(status) if !status.bridge_online => C![C.izettle_pending_warning],
+            _ => C![],
+        };
+
+        div![
+            C![C.izettle_pending_overlay],
+            div![
+                C![C.izettle_pending, C.rounded],
+                h2![strings::PAYMENT_PENDING],
+                p![
+                    C![C.izettle_pending_elapsed],
+                    format!("{}:{:02}", elapsed / 60, elapsed % 60),
+                ],
+                p![bridge_class, bridge],
+                match reader {
+                    Some(reader) => p![reader],
+                    None => empty![],
+                },
+                if pending.cancel_failed {
+                    p![
+                        C![C.izettle_pending_warning],
+                        strings::CANCEL_PAYMENT_FAILED
+                    ]
+                } else {
+                    empty![]
+                },
+                p![C![C.izettle_pending_hint], strings::CANCEL_ON_READER_HINT],
+                div![
+                    C![C.izettle_pending_buttons],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! { At::Disabled => pending.cancelling.as_at_value() },
+                        simple_ev(
+                            Ev::Click,
+                            IZettlePayMsg::Cancel {
+                                switch_to_cash: false
+                            }
+                        ),
+                        strings::CANCEL_PAYMENT,
+                    ],
+                    if allow_cash {
+                        button![
+                            C![C.rounded, C.border_on_focus],
+                            attrs! { At::Disabled => pending.cancelling.as_at_value() },
+                            simple_ev(
+                                Ev::Click,
+                                IZettlePayMsg::Cancel {
+                                    switch_to_cash: true
+                                }
+                            ),
+                            strings::SWITCH_TO_CASH,
+                        ]
+                    } else {
+                        empty![]
+                    },
+                ],
+            ],
+        ]
+    }
+}
+
+/// Listen for status events of a payment, or None if the browser can't
+fn open_status_stream(
+    reference: i32,
+    orders: &mut impl Orders<IZettlePayMsg>,
+) -> Option<StatusStream> {
+    let source = base::event_source(&format!("/api/izettle/client/events/{}", reference))
+        .map_err(|e| error!("Failed to open the payment event stream", e))
+        .ok()?;
+    let (sender, receiver) = mpsc::unbounded();
+
+    let status_sender = sender.clone();
+    let on_status = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let status = event
+            .data()
+            .as_string()
+            .and_then(|data| serde_json::from_str(&data).ok());
+        let msg = match status {
+            Some(status) => IZettlePayMsg::StatusChanged { reference, status },
+            None => {
+                error!("Invalid payment status event", event.data());
+                IZettlePayMsg::StreamFailed(reference)
+            }
+        };
+        let _ = status_sender.unbounded_send(msg);
+    }) as Box<dyn FnMut(MessageEvent)>);
+
+    // the stream is closed by the server once the payment is done, which the browser reports as an
+    // error, but then the final status has already been received
+    let on_error = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        let _ = sender.unbounded_send(IZettlePayMsg::StreamFailed(reference));
+    }) as Box<dyn FnMut(web_sys::Event)>);
+
+    source
+        .add_event_listener_with_callback("status", on_status.as_ref().unchecked_ref())
+        .ok()?;
+    source.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+    Some(StatusStream {
+        source,
+        _on_status: on_status,
+        _on_error: on_error,
+        _handle: orders.stream_with_handle(receiver),
+    })
 }
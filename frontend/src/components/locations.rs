@@ -0,0 +1,400 @@
+use crate::generated::css_classes::C;
+use crate::strings;
+use crate::util::fetch::{api_response, form_response, send_with_retry};
+use crate::util::simple_ev;
+use crate::views::view_field_error;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::{
+    inventory::{InventoryItemId, InventoryItemStock as InventoryItem},
+    location::{
+        for_sale, LowStockAlert, NewStorageLocation, StockTransfer, StorageLocation,
+        StorageLocationId,
+    },
+    validation::FieldError,
+};
+
+#[derive(Clone, Debug)]
+pub enum LocationsMsg {
+    Toggle,
+
+    SetTransferItem(String),
+    SetTransferFrom(String),
+    SetTransferTo(String),
+    SetTransferAmount(String),
+    Transfer,
+
+    SetLowStockAt(InventoryItemId, String),
+
+    SetNewName(String),
+    AddLocation,
+    RemoveLocation(StorageLocationId),
+
+    Changed,
+    /// The server rejected some fields of the new location
+    Invalid(Vec<FieldError>),
+    /// Saving failed, handled by the parent
+    Failed(String),
+}
+
+/// A drawer showing where the items are kept, e.g. in the "förråd", where they are moved between
+/// the storage locations and the items for sale, and which items are running low for sale
+pub struct LocationsPanel {
+    pub open: bool,
+
+    transfer_item: Option<InventoryItemId>,
+    transfer_from: Option<StorageLocationId>,
+    transfer_to: Option<StorageLocationId>,
+    transfer_amount: String,
+
+    new_name: String,
+
+    /// The fields of the new location which were rejected when it was added
+    errors: Vec<FieldError>,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/inventory/locations"]
+    #[policy = "SilentRefetch"]
+    locations: &'a Vec<StorageLocation>,
+
+    #[url = "/api/inventory/low_stock"]
+    #[policy = "SilentRefetch"]
+    low_stock: &'a Vec<LowStockAlert>,
+
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    inventory: &'a HashMap<InventoryItemId, InventoryItem>,
+}
+
+impl LocationsPanel {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<LocationsMsg>) -> Self {
+        Res::acquire(rs, orders).ok();
+        LocationsPanel {
+            open: false,
+            transfer_item: None,
+            transfer_from: None,
+            transfer_to: None,
+            transfer_amount: String::new(),
+            new_name: String::new(),
+            errors: vec![],
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        msg: LocationsMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<LocationsMsg>,
+    ) -> Result<(), NotAvailable> {
+        match msg {
+            LocationsMsg::Toggle => self.open = !self.open,
+            LocationsMsg::SetTransferItem(item) => self.transfer_item = item.parse().ok(),
+            LocationsMsg::SetTransferFrom(from) => self.transfer_from = from.parse().ok(),
+            LocationsMsg::SetTransferTo(to) => self.transfer_to = to.parse().ok(),
+            LocationsMsg::SetTransferAmount(amount) => self.transfer_amount = amount,
+            LocationsMsg::Transfer => {
+                let (item_id, amount) = match (self.transfer_item, self.transfer_amount.parse()) {
+                    (Some(item_id), Ok(amount)) => (item_id, amount),
+                    _ => return Ok(()),
+                };
+                let transfer = StockTransfer {
+                    item_id,
+                    from: self.transfer_from,
+                    to: self.transfer_to,
+                    amount,
+                };
+                orders.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new("/api/inventory/transfers")
+                            .method(Method::Post)
+                            .json(&transfer)?;
+                        api_response::<InventoryItemId>(send_with_retry(request).await?).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(_)) => LocationsMsg::Changed,
+                        // e.g. that there aren't that many to move
+                        Ok(Err(error)) => LocationsMsg::Failed(error.description),
+                        Err(e) => LocationsMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            LocationsMsg::SetLowStockAt(item_id, input) => {
+                let threshold: Option<i32> = match input.trim() {
+                    "" => None,
+                    input => match input.parse() {
+                        Ok(threshold) => Some(threshold),
+                        Err(_) => return Ok(()),
+                    },
+                };
+                orders.perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/inventory/item/{}/low_stock_at", item_id))
+                                .method(Method::Put)
+                                .json(&threshold)?,
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => LocationsMsg::Changed,
+                        Err(e) => LocationsMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            LocationsMsg::SetNewName(name) => self.new_name = name,
+            LocationsMsg::AddLocation => {
+                let location = NewStorageLocation {
+                    name: self.new_name.clone(),
+                };
+                orders.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new("/api/inventory/locations")
+                            .method(Method::Post)
+                            .json(&location)?;
+                        form_response::<StorageLocationId>(request.fetch().await?).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(_)) => LocationsMsg::Changed,
+                        Ok(Err(errors)) => LocationsMsg::Invalid(errors),
+                        Err(e) => LocationsMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            LocationsMsg::RemoveLocation(id) => {
+                orders.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new(format!("/api/inventory/locations/{}", id))
+                            .method(Method::Delete);
+                        api_response::<StorageLocationId>(send_with_retry(request).await?).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(_)) => LocationsMsg::Changed,
+                        // the items in it have to be moved out first
+                        Ok(Err(error)) => LocationsMsg::Failed(error.description),
+                        Err(e) => LocationsMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            LocationsMsg::Changed => {
+                self.transfer_amount.clear();
+                self.new_name.clear();
+                self.errors.clear();
+                rs.mark_as_dirty(Res::locations_url(), orders);
+                rs.mark_as_dirty(Res::low_stock_url(), orders);
+                rs.mark_as_dirty(Res::inventory_url(), orders);
+            }
+            LocationsMsg::Invalid(errors) => self.errors = errors,
+            LocationsMsg::Failed(_) => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<LocationsMsg> {
+        if !self.open {
+            return empty![];
+        }
+
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return div![C![C.locations_drawer]],
+        };
+
+        let mut items: Vec<&InventoryItem> = res
+            .inventory
+            .values()
+            .filter(|item| item.archived_at.is_none())
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let location_name = |id: &StorageLocationId| {
+            res.locations
+                .iter()
+                .find(|location| location.id == *id)
+                .map(|location| location.name.as_str())
+                .unwrap_or(strings::MISSING_NAME.get())
+        };
+        let place_options = |selected: Option<StorageLocationId>| {
+            std::iter::once(option![
+                attrs! {At::Value => ""},
+                attrs! {At::Selected => selected.is_none().as_at_value()},
+                strings::FOR_SALE,
+            ])
+            .chain(res.locations.iter().map(move |location| {
+                option![
+                    attrs! {At::Value => location.id},
+                    attrs! {At::Selected => (selected == Some(location.id)).as_at_value()},
+                    &location.name,
+                ]
+            }))
+            .collect::<Vec<_>>()
+        };
+
+        div![
+            C![C.locations_drawer],
+            h2![strings::STORAGE],
+            if res.low_stock.is_empty() {
+                empty![]
+            } else {
+                div![
+                    h3![strings::LOW_STOCK],
+                    res.low_stock.iter().map(|alert| {
+                        let stored = if alert.stored.is_empty() {
+                            strings::NONE_STORED.to_string()
+                        } else {
+                            alert
+                                .stored
+                                .iter()
+                                .map(|(id, amount)| {
+                                    format!("{} {} {}", amount, strings::STORED_IN, location_name(id))
+                                })
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        };
+                        div![
+                            C![C.low_stock_alert, C.rounded],
+                            div![
+                                C![C.supplier_name],
+                                format!(
+                                    "{}: {} {}",
+                                    alert.name,
+                                    alert.for_sale,
+                                    strings::LEFT_FOR_SALE
+                                ),
+                            ],
+                            div![C![C.supplier_items], stored],
+                        ]
+                    }),
+                ]
+            },
+            h3![strings::MOVE_ITEMS],
+            div![
+                C![C.supplier_editor, C.rounded],
+                select![
+                    option![
+                        attrs! {At::Value => ""},
+                        attrs! {At::Selected => self.transfer_item.is_none().as_at_value()},
+                        strings::CHOOSE_ITEM,
+                    ],
+                    items.iter().map(|item| {
+                        option![
+                            attrs! {At::Value => item.id},
+                            attrs! {At::Selected => (self.transfer_item == Some(item.id)).as_at_value()},
+                            &item.name,
+                        ]
+                    }),
+                    input_ev(Ev::Change, LocationsMsg::SetTransferItem),
+                ],
+                label![
+                    strings::MOVE_FROM,
+                    select![
+                        place_options(self.transfer_from),
+                        input_ev(Ev::Change, LocationsMsg::SetTransferFrom),
+                    ],
+                ],
+                label![
+                    strings::MOVE_TO,
+                    select![
+                        place_options(self.transfer_to),
+                        input_ev(Ev::Change, LocationsMsg::SetTransferTo),
+                    ],
+                ],
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {
+                        At::Type => "number",
+                        At::Min => 1,
+                        At::Placeholder => strings::QUANTITY,
+                        At::Value => self.transfer_amount,
+                    },
+                    input_ev(Ev::Input, LocationsMsg::SetTransferAmount),
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {
+                        At::Disabled => (self.transfer_item.is_none()
+                            || self.transfer_from == self.transfer_to
+                            || self.transfer_amount.parse::<u32>().is_err())
+                        .as_at_value()
+                    },
+                    simple_ev(Ev::Click, LocationsMsg::Transfer),
+                    strings::MOVE,
+                ],
+            ],
+            table![
+                C![C.stock_locations],
+                tr![
+                    th![],
+                    th![strings::FOR_SALE],
+                    res.locations.iter().map(|location| th![&location.name]),
+                    th![attrs! {At::Title => strings::LOW_STOCK_AT_HINT}, strings::LOW_STOCK_AT],
+                ],
+                items.iter().map(|item| {
+                    let item_id = item.id;
+                    tr![
+                        td![&item.name],
+                        td![for_sale(item, res.locations).to_string()],
+                        res.locations.iter().map(|location| {
+                            td![location
+                                .items
+                                .get(&item_id)
+                                .map(|amount| amount.to_string())
+                                .unwrap_or_default()]
+                        }),
+                        td![input![
+                            C![C.rounded, C.border_on_focus],
+                            attrs! {
+                                At::Type => "number",
+                                At::Min => 0,
+                                At::Value => item
+                                    .low_stock_at
+                                    .map(|threshold| threshold.to_string())
+                                    .unwrap_or_default(),
+                            },
+                            input_ev(Ev::Change, move |input| {
+                                LocationsMsg::SetLowStockAt(item_id, input)
+                            }),
+                        ]],
+                    ]
+                }),
+            ],
+            h3![strings::STORAGE_LOCATIONS],
+            res.locations.iter().map(|location| {
+                div![
+                    C![C.supplier, C.rounded],
+                    span![C![C.supplier_name], &location.name],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, LocationsMsg::RemoveLocation(location.id)),
+                        strings::REMOVE,
+                    ],
+                ]
+            }),
+            div![
+                C![C.supplier_editor, C.rounded],
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {At::Value => self.new_name},
+                    attrs! {At::Placeholder => strings::STORAGE_LOCATION_NAME},
+                    input_ev(Ev::Input, LocationsMsg::SetNewName),
+                ],
+                view_field_error(&self.errors, "name"),
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {At::Disabled => self.new_name.trim().is_empty().as_at_value()},
+                    simple_ev(Ev::Click, LocationsMsg::AddLocation),
+                    strings::ADD_STORAGE_LOCATION,
+                ],
+            ],
+        ]
+    }
+}
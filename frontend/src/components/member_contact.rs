@@ -0,0 +1,295 @@
+use crate::generated::css_classes::C;
+use crate::strings;
+use crate::util::base;
+use crate::util::fetch::{form_response, get_with_retry};
+use crate::util::simple_ev;
+use crate::views::view_field_error;
+use js_sys::encode_uri_component;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::{
+    member::{MailTopic, MemberContact, MemberId},
+    validation::FieldError,
+};
+
+const TOPICS: [MailTopic; 3] = [
+    MailTopic::Receipts,
+    MailTopic::BalanceReminders,
+    MailTopic::EventInfo,
+];
+
+#[derive(Clone, Debug)]
+pub enum MemberContactMsg {
+    Fetched(MemberContact),
+
+    SetEmail(String),
+    SaveEmail,
+
+    /// The code which was mailed to the address, as told by the member
+    SetCode(String),
+    Verify,
+
+    TogglePreference(MailTopic),
+
+    /// The code of the balance card of the member, if one has been made
+    CardFetched(Option<String>),
+
+    /// Make a new balance card, which stops the old one from working
+    NewCard,
+
+    /// The server rejected the address
+    Invalid(Vec<FieldError>),
+
+    /// Saving failed, handled by the parent
+    Failed(String),
+}
+
+/// The email address of a member, and what they have opted in to be mailed about
+pub struct MemberContactPanel {
+    member_id: MemberId,
+    contact: Option<MemberContact>,
+    card: Option<String>,
+    email_input: String,
+    code_input: String,
+
+    /// The fields which were rejected when the address was saved
+    errors: Vec<FieldError>,
+}
+
+impl MemberContactPanel {
+    pub fn new(member_id: MemberId, orders: &mut impl Orders<MemberContactMsg>) -> Self {
+        orders.perform_cmd(async move {
+            let url = format!("/api/member/{}/contact", member_id);
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(contact) => MemberContactMsg::Fetched(contact),
+                Err(e) => MemberContactMsg::Failed(format!("{:?}", e)),
+            }
+        });
+        orders.perform_cmd(async move {
+            let url = format!("/api/member/{}/balance_card", member_id);
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(card) => MemberContactMsg::CardFetched(card),
+                Err(e) => MemberContactMsg::Failed(format!("{:?}", e)),
+            }
+        });
+        MemberContactPanel {
+            member_id,
+            contact: None,
+            card: None,
+            email_input: String::new(),
+            code_input: String::new(),
+            errors: vec![],
+        }
+    }
+
+    pub fn update(&mut self, msg: MemberContactMsg, orders: &mut impl Orders<MemberContactMsg>) {
+        match msg {
+            MemberContactMsg::Fetched(contact) => {
+                self.email_input = contact.email.clone().unwrap_or_default();
+                self.code_input.clear();
+                self.errors.clear();
+                self.contact = Some(contact);
+            }
+            MemberContactMsg::SetEmail(input) => self.email_input = input,
+            MemberContactMsg::SaveEmail => {
+                let email = Some(self.email_input.trim().to_string()).filter(|e| !e.is_empty());
+                self.send(
+                    Request::new(format!("/api/member/{}/email", self.member_id))
+                        .method(Method::Put)
+                        .json(&email),
+                    orders,
+                );
+            }
+            MemberContactMsg::SetCode(input) => self.code_input = input,
+            MemberContactMsg::Verify => {
+                let code = self.code_input.trim().to_string();
+                self.send(
+                    Request::new(format!("/api/member/{}/verify_email", self.member_id))
+                        .method(Method::Post)
+                        .json(&code),
+                    orders,
+                );
+            }
+            MemberContactMsg::TogglePreference(topic) => {
+                if let Some(contact) = &self.contact {
+                    let mut preferences = contact.preferences;
+                    match topic {
+                        MailTopic::Receipts => preferences.receipts ^= true,
+                        MailTopic::BalanceReminders => preferences.balance_reminders ^= true,
+                        MailTopic::EventInfo => preferences.event_info ^= true,
+                    }
+                    self.send(
+                        Request::new(format!(
+                            "/api/member/{}/contact_preferences",
+                            self.member_id
+                        ))
+                        .method(Method::Put)
+                        .json(&preferences),
+                        orders,
+                    );
+                }
+            }
+            MemberContactMsg::CardFetched(card) => self.card = card,
+            MemberContactMsg::NewCard => {
+                let url = format!("/api/member/{}/balance_card", self.member_id);
+
+                // not retried, since every request replaces the card
+                orders.perform_cmd(async move {
+                    let result = async {
+                        Request::new(url)
+                            .method(Method::Put)
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(card) => MemberContactMsg::CardFetched(Some(card)),
+                        Err(e) => MemberContactMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            MemberContactMsg::Invalid(errors) => self.errors = errors,
+            MemberContactMsg::Failed(_) => {}
+        }
+    }
+
+    /// Not retried, since a new code is mailed every time the address is saved
+    fn send(
+        &self,
+        request: Result<Request<'static>, FetchError>,
+        orders: &mut impl Orders<MemberContactMsg>,
+    ) {
+        orders.perform_cmd(async move {
+            let result =
+                async { form_response::<MemberContact>(request?.fetch().await?).await }.await;
+            match result {
+                Ok(Ok(contact)) => MemberContactMsg::Fetched(contact),
+                Ok(Err(errors)) => MemberContactMsg::Invalid(errors),
+                Err(e) => MemberContactMsg::Failed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn view(&self) -> Node<MemberContactMsg> {
+        let contact = match &self.contact {
+            Some(contact) => contact,
+            None => return div![C![C.penguin, C.penguin_small]],
+        };
+
+        let verified = contact.email.is_some() && contact.email_verified_at.is_some();
+        div![
+            C![C.member_contact],
+            div![
+                C![C.member_contact_row],
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {
+                        At::Type => "email",
+                        At::Placeholder => strings::EMAIL,
+                        At::Value => self.email_input,
+                    },
+                    input_ev(Ev::Input, MemberContactMsg::SetEmail),
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, MemberContactMsg::SaveEmail),
+                    strings::SAVE,
+                ],
+            ],
+            view_field_error(&self.errors, "email"),
+            match (&contact.email, verified) {
+                (None, _) => empty![],
+                (Some(_), true) => div![C![C.member_contact_verified], strings::EMAIL_VERIFIED],
+                (Some(_), false) => div![
+                    C![C.member_contact_row],
+                    span![strings::EMAIL_CODE_SENT],
+                    input![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {
+                            At::Placeholder => strings::EMAIL_CODE,
+                            At::Value => self.code_input,
+                        },
+                        input_ev(Ev::Input, MemberContactMsg::SetCode),
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, MemberContactMsg::Verify),
+                        strings::CONFIRM,
+                    ],
+                ],
+            },
+            TOPICS.iter().map(|&topic| {
+                label![
+                    input![
+                        attrs! {At::Type => "checkbox"},
+                        attrs! {At::Checked => contact.preferences.allows(topic).as_at_value()},
+                        ev(Ev::Change, move |_| MemberContactMsg::TogglePreference(
+                            topic
+                        )),
+                    ],
+                    match topic {
+                        MailTopic::Receipts => strings::MAIL_RECEIPTS,
+                        MailTopic::BalanceReminders => strings::MAIL_BALANCE_REMINDERS,
+                        MailTopic::EventInfo => strings::MAIL_EVENT_INFO,
+                    },
+                ]
+            }),
+            if verified {
+                empty![]
+            } else {
+                p![C![C.member_contact_hint], strings::MAIL_NOT_VERIFIED_HINT]
+            },
+            self.view_card(),
+        ]
+    }
+
+    fn view_card(&self) -> Node<MemberContactMsg> {
+        if self.card.is_none() {
+            return div![
+                C![C.member_contact_row],
+                span![strings::BALANCE_CARD],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, MemberContactMsg::NewCard),
+                    strings::NEW_BALANCE_CARD,
+                ],
+            ];
+        }
+
+        // the card links to the balance page of this frontend, wherever it's hosted
+        let origin = window().location().origin().unwrap_or_default();
+        let page = format!("{}{}", origin, base::page_href("/balance"));
+        let page: String = encode_uri_component(&page).into();
+        div![
+            div![
+                C![C.member_contact_row],
+                span![strings::BALANCE_CARD],
+                a![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {
+                        At::Href => base::api_url(&format!(
+                            "/api/member/{}/balance_card.svg?page={}",
+                            self.member_id, page
+                        )),
+                        At::Target => "_blank",
+                    },
+                    strings::PRINT_BALANCE_CARD,
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, MemberContactMsg::NewCard),
+                    strings::REPLACE_BALANCE_CARD,
+                ],
+            ],
+            p![
+                C![C.member_contact_hint],
+                strings::REPLACE_BALANCE_CARD_HINT
+            ],
+        ]
+    }
+}
@@ -1,5 +1,14 @@
+pub mod approval;
 pub mod checkout;
+pub mod corrections;
+pub mod failed_payments;
 pub mod filter_menu;
+pub mod filter_presets;
+pub mod goods_return;
 pub mod izettle_pay;
+pub mod locations;
+pub mod member_contact;
 pub mod parsed_input;
 pub mod select;
+pub mod store_grid;
+pub mod suppliers;
@@ -1,4 +1,5 @@
 use crate::generated::css_classes::C;
+use crate::i18n::Text;
 use crate::util::simple_ev;
 use seed::prelude::*;
 use seed::{attrs, div, empty, input, Attrs, C};
@@ -9,7 +10,7 @@ pub struct ParsedInput<T> {
     text: String,
     parsed: Option<T>,
     input_kind: &'static str,
-    error_message: Option<&'static str>,
+    error_message: Option<Text>,
 }
 
 #[derive(Clone, Debug)]
@@ -33,7 +34,7 @@ where
         }
     }
 
-    pub fn with_error_message(self, error_message: &'static str) -> Self {
+    pub fn with_error_message(self, error_message: Text) -> Self {
         ParsedInput {
             error_message: Some(error_message),
             ..self
@@ -81,4 +82,8 @@ where
     pub fn get_value(&self) -> Option<&T> {
         self.parsed.as_ref()
     }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
 }
@@ -0,0 +1,583 @@
+use crate::generated::css_classes::C;
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::fetch::send_with_retry;
+use crate::util::{compare_fuzzy, simple_ev};
+use seed::prelude::*;
+use seed::virtual_dom::event_handler_manager::event_handler::EventHandler;
+use seed::*;
+use seed_fetcher::{NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::{
+    currency::Currency,
+    inventory::{
+        InventoryBundle, InventoryBundleId, InventoryItemId, InventoryItemStock as InventoryItem,
+    },
+    store_layout::{QuickButton, QuickButtonTarget, StoreLayout, StoreTab, STORE_GRID_COLUMNS},
+};
+use wasm_bindgen::JsCast;
+
+/// Number of empty rows shown below the buttons when editing, so there is room to grow
+const EDITOR_EXTRA_ROWS: i32 = 2;
+
+#[derive(Clone, Debug)]
+pub enum StoreGridMsg {
+    SelectTab(usize),
+
+    /// A button was pressed, handled by the parent
+    Activate(QuickButtonTarget),
+
+    StartEditing,
+    CancelEditing,
+    Save,
+    Saved,
+    /// Saving failed, handled by the parent
+    SaveFailed(String),
+
+    DragButton(usize),
+    DragNew(QuickButtonTarget),
+    DropAt {
+        x: i32,
+        y: i32,
+    },
+    DropOnPalette,
+
+    SelectButton(usize),
+    Resize {
+        width: i32,
+        height: i32,
+    },
+    SetColor(String),
+    RemoveButton,
+
+    AddTab,
+    RenameTab(String),
+    RemoveTab,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Dragging {
+    Button(usize),
+    New(QuickButtonTarget),
+}
+
+/// The hand-arranged quick button grid of the store page, including the layout editor
+pub struct StoreGrid {
+    /// The copy of the layout which is being edited
+    layout: StoreLayout,
+    pub editing: bool,
+    selected_tab: usize,
+    selected_button: Option<usize>,
+    dragging: Option<Dragging>,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/store/layout"]
+    layout: &'a StoreLayout,
+
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    inventory: &'a HashMap<InventoryItemId, InventoryItem>,
+
+    #[url = "/api/inventory/bundles"]
+    bundles: &'a HashMap<InventoryBundleId, InventoryBundle>,
+}
+
+impl StoreGrid {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<StoreGridMsg>) -> Self {
+        Res::acquire(rs, orders).ok();
+        StoreGrid {
+            layout: StoreLayout::default(),
+            editing: false,
+            selected_tab: 0,
+            selected_button: None,
+            dragging: None,
+        }
+    }
+
+    pub fn selected_tab(&self) -> usize {
+        self.selected_tab
+    }
+
+    pub fn select_tab(&mut self, index: usize) {
+        self.selected_tab = index;
+        self.selected_button = None;
+    }
+
+    pub fn update(
+        &mut self,
+        msg: StoreGridMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<StoreGridMsg>,
+    ) -> Result<(), NotAvailable> {
+        let res = Res::acquire(rs, orders)?;
+
+        match msg {
+            StoreGridMsg::SelectTab(index) => self.select_tab(index),
+            StoreGridMsg::Activate(_) => {}
+
+            StoreGridMsg::StartEditing => {
+                self.layout = res.layout.clone();
+                if self.layout.tabs.is_empty() {
+                    self.layout.tabs.push(new_tab());
+                }
+                self.selected_tab = 0;
+                self.selected_button = None;
+                self.editing = true;
+            }
+            StoreGridMsg::CancelEditing => {
+                self.editing = false;
+                self.selected_tab = 0;
+                self.selected_button = None;
+            }
+            StoreGridMsg::Save => {
+                let layout = self.layout.clone();
+                orders.perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new("/api/store/layout")
+                                .method(Method::Put)
+                                .json(&layout)?,
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => StoreGridMsg::Saved,
+                        Err(e) => StoreGridMsg::SaveFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            StoreGridMsg::Saved => {
+                self.editing = false;
+                self.selected_button = None;
+                rs.mark_as_dirty(Res::layout_url(), orders);
+            }
+            StoreGridMsg::SaveFailed(_) => {}
+
+            StoreGridMsg::DragButton(index) => {
+                self.selected_button = Some(index);
+                self.dragging = Some(Dragging::Button(index));
+            }
+            StoreGridMsg::DragNew(target) => {
+                self.dragging = Some(Dragging::New(target));
+            }
+            StoreGridMsg::DropAt { x, y } => {
+                if let Some(tab) = self.layout.tabs.get_mut(self.selected_tab) {
+                    match self.dragging.take() {
+                        Some(Dragging::Button(index)) => {
+                            let mut moved = tab.buttons[index].clone();
+                            moved.x = x.min(STORE_GRID_COLUMNS - moved.width);
+                            moved.y = y;
+                            if fits(tab, &moved, Some(index)) {
+                                tab.buttons[index] = moved;
+                            }
+                        }
+                        Some(Dragging::New(target)) => {
+                            let button = QuickButton::new(target, x, y);
+                            if fits(tab, &button, None) {
+                                tab.buttons.push(button);
+                                self.selected_button = Some(tab.buttons.len() - 1);
+                            }
+                        }
+                        None => {}
+                    }
+                }
+            }
+            StoreGridMsg::DropOnPalette => {
+                if let Some(Dragging::Button(index)) = self.dragging.take() {
+                    self.selected_button = Some(index);
+                    return self.update(StoreGridMsg::RemoveButton, rs, orders);
+                }
+            }
+
+            StoreGridMsg::SelectButton(index) => {
+                self.selected_button = Some(index);
+            }
+            StoreGridMsg::Resize { width, height } => {
+                if let Some((tab, index)) = self.selected_mut() {
+                    let mut resized = tab.buttons[index].clone();
+                    resized.width = width;
+                    resized.height = height;
+                    if fits(tab, &resized, Some(index)) {
+                        tab.buttons[index] = resized;
+                    }
+                }
+            }
+            StoreGridMsg::SetColor(color) => {
+                if let Some((tab, index)) = self.selected_mut() {
+                    tab.buttons[index].color = Some(color);
+                }
+            }
+            StoreGridMsg::RemoveButton => {
+                if let Some((tab, index)) = self.selected_mut() {
+                    tab.buttons.remove(index);
+                }
+                self.selected_button = None;
+            }
+
+            StoreGridMsg::AddTab => {
+                self.layout.tabs.push(new_tab());
+                self.selected_tab = self.layout.tabs.len() - 1;
+                self.selected_button = None;
+            }
+            StoreGridMsg::RenameTab(name) => {
+                if let Some(tab) = self.layout.tabs.get_mut(self.selected_tab) {
+                    tab.name = name;
+                }
+            }
+            StoreGridMsg::RemoveTab => {
+                if self.selected_tab < self.layout.tabs.len() {
+                    self.layout.tabs.remove(self.selected_tab);
+                }
+                self.selected_tab = 0;
+                self.selected_button = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn selected_mut(&mut self) -> Option<(&mut StoreTab, usize)> {
+        let index = self.selected_button?;
+        let tab = self.layout.tabs.get_mut(self.selected_tab)?;
+        if index < tab.buttons.len() {
+            Some((tab, index))
+        } else {
+            None
+        }
+    }
+
+    /// Show the layout. Returns `None` if there is nothing to show.
+    ///
+    /// Buttons which don't match `search` are dimmed.
+    pub fn view(&self, rs: &ResourceStore, search: &str) -> Option<Node<StoreGridMsg>> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Some(Loading::skeleton_grid()),
+        };
+
+        if self.editing {
+            return Some(self.view_editor(&res));
+        }
+
+        let tab = res.layout.tabs.get(self.selected_tab)?;
+
+        let matches = |button: &QuickButton| {
+            search.is_empty()
+                || target_name(&res, button.target)
+                    .map(|name| compare_fuzzy(name.chars(), search.chars()).is_full_match(search))
+                    .unwrap_or(false)
+        };
+
+        Some(div![
+            self.view_tabs(&res.layout),
+            div![
+                C![C.store_grid],
+                grid_style(tab.rows()),
+                tab.buttons
+                    .iter()
+                    // the buttons of archived items are kept in the layout, in case they return
+                    .filter(|button| !is_archived(&res, button.target))
+                    .map(|button| {
+                        let target = button.target;
+                        div![
+                            C![C.store_grid_button, C.unselectable],
+                            if matches(button) {
+                                C![]
+                            } else {
+                                C![C.store_grid_button_dimmed]
+                            },
+                            button_style(button),
+                            simple_ev(Ev::Click, StoreGridMsg::Activate(target)),
+                            view_button_label(&res, button.target),
+                        ]
+                    }),
+            ],
+        ])
+    }
+
+    fn view_tabs(&self, layout: &StoreLayout) -> Node<StoreGridMsg> {
+        div![
+            C![C.store_grid_tabs],
+            layout.tabs.iter().enumerate().map(|(i, tab)| {
+                button![
+                    C![C.store_grid_tab, C.border_on_focus],
+                    if i == self.selected_tab {
+                        C![C.store_grid_tab_selected]
+                    } else {
+                        C![]
+                    },
+                    simple_ev(Ev::Click, StoreGridMsg::SelectTab(i)),
+                    &tab.name,
+                ]
+            }),
+        ]
+    }
+
+    fn view_editor(&self, res: &Res) -> Node<StoreGridMsg> {
+        let tab = match self.layout.tabs.get(self.selected_tab) {
+            Some(tab) => tab,
+            None => {
+                return div![
+                    C![C.store_grid_toolbar],
+                    button![
+                        C![C.store_grid_tab, C.border_on_focus],
+                        simple_ev(Ev::Click, StoreGridMsg::AddTab),
+                        strings::LAYOUT_ADD_TAB,
+                    ],
+                ]
+            }
+        };
+        let rows = tab.rows() + EDITOR_EXTRA_ROWS;
+        let selected = self.selected_button.and_then(|i| tab.buttons.get(i));
+
+        let placed: Vec<QuickButtonTarget> = tab.buttons.iter().map(|b| b.target).collect();
+        let mut unplaced: Vec<(QuickButtonTarget, &str)> = res
+            .inventory
+            .values()
+            .filter(|item| item.is_for_sale())
+            .map(|item| (QuickButtonTarget::Item(item.id), item.name.as_str()))
+            .chain(
+                res.bundles
+                    .values()
+                    .map(|bundle| (QuickButtonTarget::Bundle(bundle.id), bundle.name.as_str())),
+            )
+            .filter(|(target, _)| !placed.contains(target))
+            .collect();
+        unplaced.sort_by_key(|&(_, name)| name);
+
+        div![
+            self.view_tabs(&self.layout),
+            div![
+                C![C.store_grid_toolbar],
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {At::Value => tab.name},
+                    attrs! {At::Placeholder => strings::LAYOUT_TAB_NAME},
+                    input_ev(Ev::Input, StoreGridMsg::RenameTab),
+                ],
+                button![
+                    C![C.border_on_focus],
+                    simple_ev(Ev::Click, StoreGridMsg::AddTab),
+                    strings::LAYOUT_ADD_TAB,
+                ],
+                button![
+                    C![C.border_on_focus],
+                    simple_ev(Ev::Click, StoreGridMsg::RemoveTab),
+                    strings::LAYOUT_REMOVE_TAB,
+                ],
+                if let Some(button) = selected {
+                    let (width, height) = (button.width, button.height);
+                    vec![
+                        button![
+                            C![C.border_on_focus],
+                            simple_ev(
+                                Ev::Click,
+                                StoreGridMsg::Resize {
+                                    width: width + 1,
+                                    height
+                                }
+                            ),
+                            "↔+",
+                        ],
+                        button![
+                            C![C.border_on_focus],
+                            simple_ev(
+                                Ev::Click,
+                                StoreGridMsg::Resize {
+                                    width: (width - 1).max(1),
+                                    height
+                                }
+                            ),
+                            "↔-",
+                        ],
+                        button![
+                            C![C.border_on_focus],
+                            simple_ev(
+                                Ev::Click,
+                                StoreGridMsg::Resize {
+                                    width,
+                                    height: height + 1
+                                }
+                            ),
+                            "↕+",
+                        ],
+                        button![
+                            C![C.border_on_focus],
+                            simple_ev(
+                                Ev::Click,
+                                StoreGridMsg::Resize {
+                                    width,
+                                    height: (height - 1).max(1)
+                                }
+                            ),
+                            "↕-",
+                        ],
+                        input![
+                            attrs! {At::Type => "color"},
+                            attrs! {At::Value => button.color.as_deref().unwrap_or("#ececec")},
+                            input_ev(Ev::Input, StoreGridMsg::SetColor),
+                        ],
+                        button![
+                            C![C.border_on_focus],
+                            simple_ev(Ev::Click, StoreGridMsg::RemoveButton),
+                            strings::LAYOUT_REMOVE_BUTTON,
+                        ],
+                    ]
+                } else {
+                    vec![]
+                },
+                button![
+                    C![C.border_on_focus],
+                    simple_ev(Ev::Click, StoreGridMsg::CancelEditing),
+                    strings::ABORT,
+                ],
+                button![
+                    C![C.border_on_focus],
+                    simple_ev(Ev::Click, StoreGridMsg::Save),
+                    strings::LAYOUT_SAVE,
+                ],
+            ],
+            div![
+                C![C.store_grid, C.store_grid_editing],
+                grid_style(rows),
+                (0..rows).flat_map(|y| {
+                    (0..STORE_GRID_COLUMNS)
+                        .filter(move |&x| !tab.buttons.iter().any(|b| b.covers(x, y)))
+                        .map(move |x| {
+                            div![
+                                C![C.store_grid_cell],
+                                style! {
+                                    St::GridColumn => x + 1,
+                                    St::GridRow => y + 1,
+                                },
+                                ev(Ev::DragOver, |event| event.prevent_default()),
+                                ev(Ev::Drop, move |event| {
+                                    event.prevent_default();
+                                    StoreGridMsg::DropAt { x, y }
+                                }),
+                            ]
+                        })
+                }),
+                tab.buttons.iter().enumerate().map(|(i, button)| {
+                    div![
+                        C![C.store_grid_button, C.unselectable],
+                        if self.selected_button == Some(i) {
+                            C![C.store_grid_button_selected]
+                        } else {
+                            C![]
+                        },
+                        button_style(button),
+                        attrs! {At::Draggable => true},
+                        drag_start(StoreGridMsg::DragButton(i)),
+                        simple_ev(Ev::Click, StoreGridMsg::SelectButton(i)),
+                        view_button_label(res, button.target),
+                    ]
+                }),
+            ],
+            div![
+                C![C.store_grid_palette],
+                ev(Ev::DragOver, |event| event.prevent_default()),
+                ev(Ev::Drop, |event| {
+                    event.prevent_default();
+                    StoreGridMsg::DropOnPalette
+                }),
+                unplaced.into_iter().map(|(target, name)| {
+                    div![
+                        C![C.store_grid_palette_entry, C.unselectable],
+                        attrs! {At::Draggable => true},
+                        drag_start(StoreGridMsg::DragNew(target)),
+                        name,
+                    ]
+                }),
+            ],
+        ]
+    }
+}
+
+fn new_tab() -> StoreTab {
+    StoreTab {
+        name: strings::LAYOUT_NEW_TAB.to_string(),
+        buttons: vec![],
+    }
+}
+
+/// Check if `button` can be placed in `tab` without overlapping anything except `ignore`
+fn fits(tab: &StoreTab, button: &QuickButton, ignore: Option<usize>) -> bool {
+    button.x >= 0
+        && button.y >= 0
+        && button.x + button.width <= STORE_GRID_COLUMNS
+        && tab
+            .buttons
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| Some(i) != ignore)
+            .all(|(_, other)| !other.overlaps(button))
+}
+
+fn grid_style(rows: i32) -> Style {
+    style! {
+        St::GridTemplateColumns => format!("repeat({}, 1fr)", STORE_GRID_COLUMNS),
+        St::GridTemplateRows => format!("repeat({}, 6rem)", rows.max(1)),
+    }
+}
+
+fn button_style(button: &QuickButton) -> Style {
+    style! {
+        St::GridColumn => format!("{} / span {}", button.x + 1, button.width),
+        St::GridRow => format!("{} / span {}", button.y + 1, button.height),
+        St::BackgroundColor => button.color.clone(),
+    }
+}
+
+/// Firefox won't start dragging unless some data is attached to the drag event
+fn drag_start(msg: StoreGridMsg) -> EventHandler<StoreGridMsg> {
+    ev(Ev::DragStart, move |event| {
+        if let Some(data) = event
+            .dyn_ref::<web_sys::DragEvent>()
+            .and_then(|event| event.data_transfer())
+        {
+            let _ = data.set_data("text/plain", "");
+        }
+        msg
+    })
+}
+
+fn target_name<'a>(res: &Res<'a>, target: QuickButtonTarget) -> Option<&'a str> {
+    match target {
+        QuickButtonTarget::Item(id) => res.inventory.get(&id).map(|item| item.name.as_str()),
+        QuickButtonTarget::Bundle(id) => res.bundles.get(&id).map(|bundle| bundle.name.as_str()),
+    }
+}
+
+fn is_archived(res: &Res, target: QuickButtonTarget) -> bool {
+    match target {
+        QuickButtonTarget::Item(id) => res
+            .inventory
+            .get(&id)
+            .map(|item| item.archived_at.is_some())
+            .unwrap_or(false),
+        QuickButtonTarget::Bundle(_) => false,
+    }
+}
+
+fn view_button_label(res: &Res, target: QuickButtonTarget) -> Vec<Node<StoreGridMsg>> {
+    let (name, price) = match target {
+        QuickButtonTarget::Item(id) => match res.inventory.get(&id) {
+            Some(item) => (item.name.as_str(), Currency::from(item.price.unwrap_or(0))),
+            None => return vec![span![strings::MISSING_NAME]],
+        },
+        QuickButtonTarget::Bundle(id) => match res.bundles.get(&id) {
+            Some(bundle) => (bundle.name.as_str(), bundle.price),
+            None => return vec![span![strings::MISSING_NAME]],
+        },
+    };
+
+    vec![
+        span![C![C.store_grid_button_name], name],
+        span![C![C.store_grid_button_price], format!("{}:-", price)],
+    ]
+}
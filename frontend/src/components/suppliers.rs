@@ -0,0 +1,344 @@
+use crate::generated::css_classes::C;
+use crate::strings;
+use crate::util::fetch::{form_response, send_with_retry};
+use crate::util::simple_ev;
+use crate::views::view_field_error;
+use chrono::Weekday;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{NotAvailable, ResourceStore, Resources};
+use std::collections::HashMap;
+use strecklistan_api::{
+    inventory::{InventoryItemId, InventoryItemStock as InventoryItem},
+    supplier::{NewSupplier, Supplier, SupplierId},
+    validation::FieldError,
+};
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+#[derive(Clone, Debug)]
+pub enum SuppliersMsg {
+    Toggle,
+
+    /// Start editing a supplier, or a new one if `None`
+    Edit(Option<SupplierId>),
+    CancelEdit,
+    SetName(String),
+    SetContact(String),
+    SetOrderingUrl(String),
+    ToggleDeliveryDay(Weekday),
+    ToggleItem(InventoryItemId),
+
+    Save,
+    Delete(SupplierId),
+    Changed,
+    /// The server rejected some fields of the edited supplier
+    Invalid(Vec<FieldError>),
+    /// Saving or deleting failed, handled by the parent
+    Failed(String),
+}
+
+/// A drawer listing where items are bought from, where suppliers can be added and edited
+pub struct SuppliersPanel {
+    pub open: bool,
+
+    /// The supplier being edited, and its id unless it is a new one
+    editing: Option<(Option<SupplierId>, NewSupplier)>,
+
+    /// The fields of the edited supplier which were rejected when it was saved
+    errors: Vec<FieldError>,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/suppliers"]
+    suppliers: &'a Vec<Supplier>,
+
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    inventory: &'a HashMap<InventoryItemId, InventoryItem>,
+}
+
+impl SuppliersPanel {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<SuppliersMsg>) -> Self {
+        Res::acquire(rs, orders).ok();
+        SuppliersPanel {
+            open: false,
+            editing: None,
+            errors: vec![],
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        msg: SuppliersMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<SuppliersMsg>,
+    ) -> Result<(), NotAvailable> {
+        let res = Res::acquire(rs, orders)?;
+
+        match msg {
+            SuppliersMsg::Toggle => {
+                self.open = !self.open;
+                self.editing = None;
+            }
+            SuppliersMsg::Edit(id) => {
+                let supplier = id
+                    .and_then(|id| res.suppliers.iter().find(|s| s.id == id))
+                    .cloned()
+                    .map(NewSupplier::from)
+                    .unwrap_or_default();
+                self.editing = Some((id, supplier));
+                self.errors.clear();
+            }
+            SuppliersMsg::CancelEdit => self.editing = None,
+            SuppliersMsg::SetName(name) => {
+                if let Some((_, supplier)) = &mut self.editing {
+                    supplier.name = name;
+                }
+            }
+            SuppliersMsg::SetContact(contact) => {
+                if let Some((_, supplier)) = &mut self.editing {
+                    supplier.contact = Some(contact);
+                }
+            }
+            SuppliersMsg::SetOrderingUrl(url) => {
+                if let Some((_, supplier)) = &mut self.editing {
+                    supplier.ordering_url = Some(url);
+                }
+            }
+            SuppliersMsg::ToggleDeliveryDay(day) => {
+                if let Some((_, supplier)) = &mut self.editing {
+                    toggle(&mut supplier.delivery_days, day);
+                }
+            }
+            SuppliersMsg::ToggleItem(item_id) => {
+                if let Some((_, supplier)) = &mut self.editing {
+                    toggle(&mut supplier.item_ids, item_id);
+                }
+            }
+            SuppliersMsg::Save => {
+                if let Some((id, supplier)) = self.editing.clone() {
+                    orders.perform_cmd(async move {
+                        let request = match id {
+                            Some(id) => {
+                                Request::new(format!("/api/supplier/{}", id)).method(Method::Put)
+                            }
+                            None => Request::new("/api/supplier").method(Method::Post),
+                        };
+                        let result = async {
+                            form_response::<SupplierId>(request.json(&supplier)?.fetch().await?)
+                                .await
+                        }
+                        .await;
+                        match result {
+                            Ok(Ok(_)) => SuppliersMsg::Changed,
+                            Ok(Err(errors)) => SuppliersMsg::Invalid(errors),
+                            Err(e) => SuppliersMsg::Failed(format!("{:?}", e)),
+                        }
+                    });
+                }
+            }
+            SuppliersMsg::Delete(id) => {
+                orders.perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/supplier/{}", id)).method(Method::Delete),
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => SuppliersMsg::Changed,
+                        Err(e) => SuppliersMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            SuppliersMsg::Changed => {
+                self.editing = None;
+                rs.mark_as_dirty(Res::suppliers_url(), orders);
+            }
+            SuppliersMsg::Invalid(errors) => self.errors = errors,
+            SuppliersMsg::Failed(_) => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<SuppliersMsg> {
+        if !self.open {
+            return empty![];
+        }
+
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return div![C![C.suppliers_drawer]],
+        };
+
+        let item_name = |id: &InventoryItemId| {
+            res.inventory
+                .get(id)
+                .map(|item| item.name.as_str())
+                .unwrap_or(strings::MISSING_NAME.get())
+        };
+
+        div![
+            C![C.suppliers_drawer],
+            h2![strings::SUPPLIERS],
+            match &self.editing {
+                Some((_, supplier)) => self.view_editor(&res, supplier),
+                None => button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, SuppliersMsg::Edit(None)),
+                    strings::NEW_SUPPLIER,
+                ],
+            },
+            res.suppliers.iter().map(|supplier| {
+                div![
+                    C![C.supplier, C.rounded],
+                    div![C![C.supplier_name], &supplier.name],
+                    match &supplier.contact {
+                        Some(contact) => div![contact],
+                        None => empty![],
+                    },
+                    match &supplier.ordering_url {
+                        Some(url) => div![a![
+                            attrs! {At::Href => url, At::Target => "_blank"},
+                            strings::ORDERING_URL,
+                        ]],
+                        None => empty![],
+                    },
+                    if supplier.delivery_days.is_empty() {
+                        empty![]
+                    } else {
+                        div![format!(
+                            "{}: {}",
+                            strings::DELIVERY_DAYS,
+                            supplier
+                                .delivery_days
+                                .iter()
+                                .map(|&day| weekday_name(day))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )]
+                    },
+                    div![
+                        C![C.supplier_items],
+                        supplier
+                            .item_ids
+                            .iter()
+                            .map(item_name)
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, SuppliersMsg::Edit(Some(supplier.id))),
+                        strings::EDIT,
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, SuppliersMsg::Delete(supplier.id)),
+                        strings::REMOVE,
+                    ],
+                ]
+            }),
+        ]
+    }
+
+    fn view_editor(&self, res: &Res, supplier: &NewSupplier) -> Node<SuppliersMsg> {
+        let mut items: Vec<&InventoryItem> = res.inventory.values().collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+
+        div![
+            C![C.supplier_editor, C.rounded],
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {At::Value => supplier.name},
+                attrs! {At::Placeholder => strings::SUPPLIER_NAME},
+                input_ev(Ev::Input, SuppliersMsg::SetName),
+            ],
+            view_field_error(&self.errors, "name"),
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {At::Value => supplier.contact.as_deref().unwrap_or("")},
+                attrs! {At::Placeholder => strings::SUPPLIER_CONTACT},
+                input_ev(Ev::Input, SuppliersMsg::SetContact),
+            ],
+            view_field_error(&self.errors, "contact"),
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {At::Type => "url"},
+                attrs! {At::Value => supplier.ordering_url.as_deref().unwrap_or("")},
+                attrs! {At::Placeholder => strings::ORDERING_URL},
+                input_ev(Ev::Input, SuppliersMsg::SetOrderingUrl),
+            ],
+            view_field_error(&self.errors, "ordering_url"),
+            div![
+                C![C.supplier_days],
+                WEEKDAYS.iter().map(|&day| {
+                    label![
+                        input![
+                            attrs! {At::Type => "checkbox"},
+                            attrs! {At::Checked => supplier.delivery_days.contains(&day).as_at_value()},
+                            ev(Ev::Change, move |_| SuppliersMsg::ToggleDeliveryDay(day)),
+                        ],
+                        weekday_name(day),
+                    ]
+                }),
+            ],
+            div![
+                C![C.supplier_item_list],
+                items.into_iter().map(|item| {
+                    let item_id = item.id;
+                    label![
+                        input![
+                            attrs! {At::Type => "checkbox"},
+                            attrs! {At::Checked => supplier.item_ids.contains(&item_id).as_at_value()},
+                            ev(Ev::Change, move |_| SuppliersMsg::ToggleItem(item_id)),
+                        ],
+                        &item.name,
+                    ]
+                }),
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                if supplier.name.trim().is_empty() {
+                    attrs! {At::Disabled => true}
+                } else {
+                    attrs! {}
+                },
+                simple_ev(Ev::Click, SuppliersMsg::Save),
+                strings::SAVE,
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                simple_ev(Ev::Click, SuppliersMsg::CancelEdit),
+                strings::ABORT,
+            ],
+        ]
+    }
+}
+
+/// Add `value` if it isn't in `values`, otherwise remove it
+fn toggle<T: PartialEq>(values: &mut Vec<T>, value: T) {
+    match values.iter().position(|v| *v == value) {
+        Some(index) => {
+            values.remove(index);
+        }
+        None => values.push(value),
+    }
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    strings::WEEKDAYS_SHORT[day.num_days_from_monday() as usize].get()
+}
@@ -16,6 +16,13 @@ pub struct FuzzyCharMatch {
     pub search_str_index: usize,
 }
 
+impl FuzzyScore {
+    /// Whether every character of `search` was found, i.e. the search should not filter this out
+    pub fn is_full_match(&self, search: &str) -> bool {
+        self.matches.len() == search.chars().count()
+    }
+}
+
 impl PartialOrd for FuzzyScore {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -12,4 +12,6 @@ css_typegen!(
     "frontend/static/charts.css",
     "frontend/static/notifications.css",
     "frontend/static/penguin.css",
+    "frontend/static/theme.css",
+    "frontend/static/report.css",
 );
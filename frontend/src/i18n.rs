@@ -0,0 +1,86 @@
+//! Translation of the user interface.
+//!
+//! All user facing strings are defined in [crate::strings] as [Text]s, which are resolved to the
+//! current language when they are displayed.
+
+use seed::prelude::*;
+use std::cell::Cell;
+use std::fmt;
+use strecklistan_api::preferences::Language;
+
+thread_local! {
+    static LANGUAGE: Cell<Language> = Cell::new(Language::default());
+}
+
+/// Set the language which all [Text]s are displayed in
+pub fn set_language(language: Language) {
+    LANGUAGE.with(|l| l.set(language));
+}
+
+pub fn language() -> Language {
+    LANGUAGE.with(|l| l.get())
+}
+
+/// A string with one translation per supported language
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Text {
+    sv: &'static str,
+    en: &'static str,
+}
+
+impl Text {
+    /// Create a [Text] from its swedish and english translations
+    pub const fn new(sv: &'static str, en: &'static str) -> Self {
+        Text { sv, en }
+    }
+
+    /// Get the translation for the current language
+    pub fn get(self) -> &'static str {
+        match language() {
+            Language::Swedish => self.sv,
+            Language::English => self.en,
+        }
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.get())
+    }
+}
+
+impl From<Text> for String {
+    fn from(text: Text) -> String {
+        text.get().to_string()
+    }
+}
+
+impl<Ms> UpdateEl<Ms> for Text {
+    fn update_el(self, el: &mut El<Ms>) {
+        self.get().update_el(el)
+    }
+}
+
+impl<Ms> UpdateEl<Ms> for &Text {
+    fn update_el(self, el: &mut El<Ms>) {
+        self.get().update_el(el)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_translation() {
+        let text = Text::new("Hej", "Hello");
+
+        set_language(Language::Swedish);
+        assert_eq!(text.get(), "Hej");
+
+        set_language(Language::English);
+        assert_eq!(text.to_string(), "Hello");
+
+        set_language(Language::default());
+    }
+}
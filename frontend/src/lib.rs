@@ -1,9 +1,25 @@
 #![deny(unreachable_patterns)]
 
+/// Log to the console like [seed::log], but only with the `debug_logging` feature. Release builds
+/// leave it out, and with it the code for formatting what is logged.
+macro_rules! debug_log {
+    ($($arg:expr),* $(,)?) => {{
+        #[cfg(feature = "debug_logging")]
+        seed::log!($($arg),*);
+        #[cfg(not(feature = "debug_logging"))]
+        {
+            $(let _ = &$arg;)*
+        }
+    }};
+}
+
+mod achievements;
 mod app;
 mod components;
 mod fuzzy_search;
 mod generated;
+mod i18n;
+mod live;
 mod models;
 mod notification_manager;
 mod page;
@@ -20,16 +36,16 @@ pub fn start() {
     App::start("app", app::init, app::update, app::view);
 }
 
-// see cargo.toml for more info
-cfg_if::cfg_if! {
-    // When the `console_error_panic_hook` feature is enabled, we can call the
-    // `set_panic_hook` function to get better error messages if we ever panic.
-    if #[cfg(feature = "console_error_panic_hook")] {
-        use console_error_panic_hook::set_once as set_panic_hook;
-    } else {
-        #[inline]
-        fn set_panic_hook() {}
-    }
+/// Report panics to the server, see [util::breadcrumbs]
+fn set_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        // When the `console_error_panic_hook` feature is enabled, panics are also logged to the
+        // console with better error messages. See cargo.toml for more info.
+        #[cfg(feature = "console_error_panic_hook")]
+        console_error_panic_hook::hook(info);
+
+        util::breadcrumbs::report(info.to_string());
+    }));
 }
 
 // see cargo.toml for more info
@@ -0,0 +1,526 @@
+use crate::app::Msg;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
+use crate::strings;
+use crate::util::base;
+use crate::util::fetch::get_with_retry;
+use chrono::{DateTime, Utc};
+use futures::channel::mpsc;
+use seed::app::cmds::timeout;
+use seed::prelude::*;
+use seed::*;
+use std::collections::HashSet;
+use strecklistan_api::{
+    announcement::{Announcement, AnnouncementId},
+    correction::Correction,
+    live::{LiveEvent, LivePoll},
+    register::{RegisterShiftId, RegisterStatus},
+    transaction::{Transaction, TransactionDetail, TransactionId},
+};
+use wasm_bindgen::JsCast;
+use web_sys::{EventSource, MessageEvent};
+
+/// How many of the latest transactions are kept in the browser
+pub const RECENT_TRANSACTIONS: usize = 200;
+
+/// How long the event stream may take to open before the changes are polled for instead, e.g.
+/// since a proxy holds the stream back
+const STREAM_OPEN_TIMEOUT_MS: u32 = 10_000;
+
+/// How long to wait before polling again after a poll failed
+const POLL_RETRY_MS: u32 = 5_000;
+
+#[derive(Clone, Debug)]
+pub enum LiveMsg {
+    /// The event stream was opened, or reopened after losing the connection, or the first poll
+    /// was answered
+    Connected,
+
+    /// The event stream failed, or didn't open in time. The changes are polled for instead if it
+    /// never opened, or if the browser gave up on it.
+    StreamFailed,
+
+    Polled(LivePoll),
+    PollFailed,
+
+    /// The server pushed a change, which is passed on to the pages with `orders.notify`
+    Event(LiveEvent),
+
+    RecentFetched(Vec<Transaction>),
+    TransactionFetched(TransactionDetail),
+    AnnouncementsFetched(Vec<Announcement>),
+    RegisterFetched(RegisterStatus),
+    PendingCorrectionsFetched(Vec<Correction>),
+
+    /// Hide the banner of an announcement on this device, until the page is reloaded
+    DismissAnnouncement(AnnouncementId),
+
+    OpenRegister,
+    CloseRegister,
+
+    /// The cash of a shift which was closed automatically has been counted
+    ReconcileShift(RegisterShiftId),
+}
+
+/// The changes pushed by the server while someone is logged in, and what's kept up to date by them
+#[derive(Default)]
+pub struct Live {
+    channel: Option<Channel>,
+    pub recent: RecentTransactions,
+    pub announcements: Announcements,
+
+    /// Whether the register is open, shown as a banner when it should have been closed
+    pub register: RegisterStatus,
+
+    /// The corrections which wait for approval, shown as a banner to admins
+    pub pending_corrections: Vec<Correction>,
+}
+
+/// The latest transactions of the organization, newest first, so that they can be shown before the
+/// whole history has been fetched
+#[derive(Default)]
+pub struct RecentTransactions {
+    transactions: Vec<Transaction>,
+
+    /// Whether the cache has been filled since the event stream was opened
+    loaded: bool,
+}
+
+/// The announcements of the organization which haven't ended, shown as banners on every page
+#[derive(Default)]
+pub struct Announcements {
+    announcements: Vec<Announcement>,
+    dismissed: HashSet<AnnouncementId>,
+}
+
+/// How the changes reach the browser
+enum Channel {
+    Stream(LiveStream),
+    Poll(LivePolling),
+}
+
+/// The server-sent events of `/api/live`, closed when dropped
+struct LiveStream {
+    source: EventSource,
+
+    /// Whether the stream has been open, after which the browser reconnects it by itself
+    opened: bool,
+
+    _on_open: Closure<dyn FnMut(web_sys::Event)>,
+    _on_error: Closure<dyn FnMut(web_sys::Event)>,
+    _on_change: Closure<dyn FnMut(MessageEvent)>,
+    _handle: StreamHandle,
+    _open_timeout: CmdHandle,
+}
+
+/// Long polls of `/api/live/poll`, for networks which don't let the event stream through
+struct LivePolling {
+    /// What the next poll asks for the events after, `None` until the first poll is answered
+    cursor: Option<u64>,
+
+    /// The poll which is waiting for an answer, or the wait before the next one. Aborted when
+    /// dropped.
+    _request: CmdHandle,
+}
+
+impl Drop for LiveStream {
+    fn drop(&mut self) {
+        self.source.close();
+    }
+}
+
+impl Live {
+    /// Start listening for changes, once the user has logged in
+    ///
+    /// The changes are streamed if the network lets the stream through, and polled for otherwise.
+    pub fn connect(&mut self, orders: &mut impl Orders<Msg>) {
+        if self.channel.is_none() {
+            self.channel = Some(match open_stream(orders) {
+                Some(stream) => Channel::Stream(stream),
+                None => Channel::Poll(poll(None, 0, orders)),
+            });
+        }
+    }
+
+    /// Stop listening and forget everything, since the next user may see another organization
+    pub fn disconnect(&mut self) {
+        self.channel = None;
+        self.recent = RecentTransactions::default();
+        self.announcements = Announcements::default();
+        self.register = RegisterStatus::default();
+        self.pending_corrections.clear();
+    }
+
+    pub fn update(&mut self, msg: LiveMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            // changes may have been missed while the connection was gone
+            LiveMsg::Connected => {
+                if let Some(Channel::Stream(stream)) = &mut self.channel {
+                    stream.opened = true;
+                }
+                fetch_recent(orders);
+                fetch_announcements(orders);
+                fetch_register(orders);
+                fetch_pending_corrections(orders);
+            }
+            LiveMsg::StreamFailed => {
+                if let Some(Channel::Stream(stream)) = &self.channel {
+                    if !stream.opened || stream.source.ready_state() == EventSource::CLOSED {
+                        self.channel = Some(Channel::Poll(poll(None, 0, orders)));
+                    }
+                }
+            }
+            LiveMsg::Polled(answer) => {
+                if let Some(Channel::Poll(polling)) = &mut self.channel {
+                    let first = polling.cursor.is_none();
+                    *polling = poll(Some(answer.cursor), 0, orders);
+                    if first {
+                        self.update(LiveMsg::Connected, orders);
+                    }
+                    for event in answer.events {
+                        self.update(LiveMsg::Event(event), orders);
+                    }
+                }
+            }
+            LiveMsg::PollFailed => {
+                if let Some(Channel::Poll(polling)) = &mut self.channel {
+                    *polling = poll(polling.cursor, POLL_RETRY_MS, orders);
+                }
+            }
+            LiveMsg::Event(event) => {
+                match &event {
+                    LiveEvent::TransactionCreated { id } => fetch_transaction(*id, orders),
+                    LiveEvent::TransactionDeleted { id } => self.recent.remove(*id),
+                    // also sent when events were missed, which may have been about anything
+                    LiveEvent::TransactionsChanged => {
+                        fetch_recent(orders);
+                        fetch_announcements(orders);
+                        fetch_register(orders);
+                        fetch_pending_corrections(orders);
+                    }
+                    LiveEvent::AnnouncementsChanged => fetch_announcements(orders),
+                    LiveEvent::RegisterChanged => fetch_register(orders),
+                    LiveEvent::CorrectionsChanged => fetch_pending_corrections(orders),
+                    // the queue, kitchen, deposit and admin pages fetch what they show themselves
+                    LiveEvent::QueueTicketIssued { .. }
+                    | LiveEvent::QueueChanged
+                    | LiveEvent::KitchenChanged
+                    | LiveEvent::SwishPaymentsChanged
+                    | LiveEvent::FailedPaymentsChanged => {}
+                }
+                orders.notify(event);
+            }
+            LiveMsg::RecentFetched(transactions) => self.recent.replace(transactions),
+            LiveMsg::TransactionFetched(detail) => {
+                // it may have been deleted before it could be fetched
+                if detail.deleted_at.is_none() {
+                    self.recent.insert(detail.transaction);
+                }
+            }
+            LiveMsg::AnnouncementsFetched(announcements) => {
+                self.announcements.announcements = announcements;
+            }
+            LiveMsg::DismissAnnouncement(id) => {
+                self.announcements.dismissed.insert(id);
+            }
+            LiveMsg::RegisterFetched(status) => self.register = status,
+            LiveMsg::PendingCorrectionsFetched(corrections) => {
+                self.pending_corrections = corrections;
+            }
+            LiveMsg::OpenRegister => post_register("/api/register/open".into(), orders),
+            LiveMsg::CloseRegister => post_register("/api/register/close".into(), orders),
+            LiveMsg::ReconcileShift(id) => {
+                let url = format!("/api/register/shifts/{}/reconcile", id);
+                post_register(url, orders);
+            }
+        }
+    }
+}
+
+impl RecentTransactions {
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Transaction> {
+        self.transactions.iter()
+    }
+
+    fn replace(&mut self, mut transactions: Vec<Transaction>) {
+        transactions.truncate(RECENT_TRANSACTIONS);
+        self.transactions = transactions;
+        self.loaded = true;
+    }
+
+    /// Add or update a transaction, keeping the newest first
+    fn insert(&mut self, transaction: Transaction) {
+        self.remove(transaction.id);
+        let key = |tr: &Transaction| (tr.time, tr.id);
+        let index = self
+            .transactions
+            .iter()
+            .position(|tr| key(tr) < key(&transaction))
+            .unwrap_or(self.transactions.len());
+        self.transactions.insert(index, transaction);
+        self.transactions.truncate(RECENT_TRANSACTIONS);
+    }
+
+    fn remove(&mut self, id: TransactionId) {
+        self.transactions.retain(|tr| tr.id != id);
+    }
+}
+
+impl Announcements {
+    /// The announcements to show at `now`, which haven't been dismissed
+    pub fn visible(&self, now: DateTime<Utc>) -> impl Iterator<Item = &Announcement> {
+        let dismissed = &self.dismissed;
+        self.announcements
+            .iter()
+            .filter(move |a| a.is_active(now) && !dismissed.contains(&a.id))
+    }
+}
+
+fn open_stream(orders: &mut impl Orders<Msg>) -> Option<LiveStream> {
+    let source = base::event_source("/api/live")
+        .map_err(|e| error!("Failed to open the live event stream", e))
+        .ok()?;
+    let (sender, receiver) = mpsc::unbounded();
+
+    let open_sender = sender.clone();
+    let on_open = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        let _ = open_sender.unbounded_send(Msg::LiveMsg(LiveMsg::Connected));
+    }) as Box<dyn FnMut(web_sys::Event)>);
+
+    let error_sender = sender.clone();
+    let on_error = Closure::wrap(Box::new(move |_: web_sys::Event| {
+        let _ = error_sender.unbounded_send(Msg::LiveMsg(LiveMsg::StreamFailed));
+    }) as Box<dyn FnMut(web_sys::Event)>);
+
+    let on_change = Closure::wrap(Box::new(move |event: MessageEvent| {
+        let change = event
+            .data()
+            .as_string()
+            .and_then(|data| serde_json::from_str(&data).ok());
+        match change {
+            Some(change) => {
+                let _ = sender.unbounded_send(Msg::LiveMsg(LiveMsg::Event(change)));
+            }
+            None => error!("Invalid live event", event.data()),
+        }
+    }) as Box<dyn FnMut(MessageEvent)>);
+
+    // the browser reconnects by itself if the connection is lost, and then opens it again
+    source.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    source.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    source
+        .add_event_listener_with_callback("change", on_change.as_ref().unchecked_ref())
+        .ok()?;
+
+    Some(LiveStream {
+        source,
+        opened: false,
+        _on_open: on_open,
+        _on_error: on_error,
+        _on_change: on_change,
+        _handle: orders.stream_with_handle(receiver),
+        _open_timeout: orders.perform_cmd_with_handle(timeout(STREAM_OPEN_TIMEOUT_MS, || {
+            Msg::LiveMsg(LiveMsg::StreamFailed)
+        })),
+    })
+}
+
+/// Ask for the events after `cursor` once `delay_ms` has passed, or for the current cursor if
+/// there is none yet
+fn poll(cursor: Option<u64>, delay_ms: u32, orders: &mut impl Orders<Msg>) -> LivePolling {
+    let url = match cursor {
+        Some(cursor) => format!("/api/live/poll?after={}", cursor),
+        None => "/api/live/poll".to_string(),
+    };
+    let request = orders.perform_cmd_with_handle(async move {
+        if delay_ms > 0 {
+            timeout(delay_ms, || ()).await;
+        }
+        let result = async {
+            Request::new(url)
+                .fetch()
+                .await?
+                .check_status()?
+                .json()
+                .await
+        }
+        .await;
+        match result {
+            Ok(answer) => Msg::LiveMsg(LiveMsg::Polled(answer)),
+            Err(e) => {
+                error!("Failed to poll for live events", e);
+                Msg::LiveMsg(LiveMsg::PollFailed)
+            }
+        }
+    });
+    LivePolling {
+        cursor,
+        _request: request,
+    }
+}
+
+fn fetch_recent(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let url = format!("/api/transactions?limit={}", RECENT_TRANSACTIONS);
+        let result = async { get_with_retry(url).await?.json().await }.await;
+        match result {
+            Ok(transactions) => Some(Msg::LiveMsg(LiveMsg::RecentFetched(transactions))),
+            Err(e) => {
+                error!("Failed to fetch the recent transactions", e);
+                None
+            }
+        }
+    });
+}
+
+fn fetch_transaction(id: TransactionId, orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let url = format!("/api/transaction/{}", id);
+        let result = async { get_with_retry(url).await?.json().await }.await;
+        match result {
+            Ok(detail) => Some(Msg::LiveMsg(LiveMsg::TransactionFetched(detail))),
+            Err(e) => {
+                error!("Failed to fetch a new transaction", e);
+                None
+            }
+        }
+    });
+}
+
+fn fetch_announcements(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let result = async {
+            get_with_retry("/api/announcements".into())
+                .await?
+                .json()
+                .await
+        }
+        .await;
+        match result {
+            Ok(announcements) => Some(Msg::LiveMsg(LiveMsg::AnnouncementsFetched(announcements))),
+            Err(e) => {
+                error!("Failed to fetch the announcements", e);
+                None
+            }
+        }
+    });
+}
+
+fn fetch_register(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let result = async { get_with_retry("/api/register".into()).await?.json().await }.await;
+        match result {
+            Ok(status) => Some(Msg::LiveMsg(LiveMsg::RegisterFetched(status))),
+            Err(e) => {
+                error!("Failed to fetch the register status", e);
+                None
+            }
+        }
+    });
+}
+
+fn fetch_pending_corrections(orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let result = async {
+            get_with_retry("/api/corrections/pending".into())
+                .await?
+                .json()
+                .await
+        }
+        .await;
+        match result {
+            Ok(corrections) => Some(Msg::LiveMsg(LiveMsg::PendingCorrectionsFetched(
+                corrections,
+            ))),
+            Err(e) => {
+                error!("Failed to fetch the pending corrections", e);
+                None
+            }
+        }
+    });
+}
+
+/// Open, close or reconcile the register. Not retried, since the server refuses to do it twice.
+fn post_register(url: String, orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let result = async {
+            Request::new(url)
+                .method(Method::Post)
+                .fetch()
+                .await?
+                .check_status()?
+                .json()
+                .await
+        }
+        .await;
+        match result {
+            Ok(status) => Msg::LiveMsg(LiveMsg::RegisterFetched(status)),
+            Err(e) => Msg::NotificationMessage(NotificationMessage::ShowNotification {
+                duration_ms: 10000,
+                notification: Notification::new(
+                    NotificationLevel::Error,
+                    strings::REGISTER_UPDATE_FAILED,
+                )
+                .with_body(format!("{:?}", e)),
+            }),
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn transaction(id: TransactionId, hour: u32) -> Transaction {
+        Transaction {
+            id,
+            description: None,
+            time: Utc.ymd(2021, 6, 30).and_hms(hour, 0, 0),
+            bundles: vec![],
+            debited_account: 1,
+            credited_account: 2,
+            amount: 1000.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        }
+    }
+
+    fn ids(recent: &RecentTransactions) -> Vec<TransactionId> {
+        recent.iter().map(|tr| tr.id).collect()
+    }
+
+    #[test]
+    fn test_recent_transactions() {
+        let mut recent = RecentTransactions::default();
+        assert!(!recent.is_loaded());
+
+        recent.replace(vec![transaction(3, 14), transaction(2, 12)]);
+        assert!(recent.is_loaded());
+
+        recent.insert(transaction(4, 15));
+        // made offline and synced later, so it's older than its id says
+        recent.insert(transaction(5, 13));
+        assert_eq!(ids(&recent), vec![4, 3, 5, 2]);
+
+        recent.insert(transaction(3, 14));
+        recent.remove(2);
+        assert_eq!(ids(&recent), vec![4, 3, 5]);
+
+        recent.replace(
+            (0..RECENT_TRANSACTIONS as i32 + 10)
+                .map(|id| transaction(id, 12))
+                .collect(),
+        );
+        recent.insert(transaction(1000, 1));
+        assert_eq!(recent.iter().count(), RECENT_TRANSACTIONS);
+        assert!(recent.iter().all(|tr| tr.id != 1000));
+    }
+}
@@ -5,11 +5,19 @@ use seed::app::cmds::timeout;
 use seed::prelude::*;
 use seed::*;
 use std::collections::BTreeMap;
+use strecklistan_api::preferences::NotificationSettings;
 
 pub type NotificationId = u32;
 
+/// Max number of notifications on screen, the oldest ones are removed to make room for new ones
+pub const MAX_VISIBLE_NOTIFICATIONS: usize = 5;
+
 #[derive(Debug, Clone)]
 pub enum NotificationMessage {
+    /// Show a notification, which is removed after `duration_ms` unless it is sticky.
+    ///
+    /// Notifications with an action are always shown for the full duration, regardless of the
+    /// user's settings, since the action is only valid for as long as the notification is shown.
     ShowNotification {
         duration_ms: u32,
         notification: Notification,
@@ -21,12 +29,63 @@ pub enum NotificationMessage {
 pub struct NotificationManager {
     next_id: NotificationId,
     notifications: BTreeMap<NotificationId, Notification>,
+    pub settings: NotificationSettings,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
 }
 
 #[derive(Debug, Clone)]
 pub struct Notification {
+    pub level: NotificationLevel,
     pub title: String,
     pub body: Option<String>,
+    pub action: Option<NotificationAction>,
+
+    /// Whether the penguin shows up to celebrate
+    pub penguin: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct NotificationAction {
+    pub label: String,
+    pub msg: Box<Msg>,
+}
+
+impl Notification {
+    pub fn new(level: NotificationLevel, title: impl Into<String>) -> Self {
+        Notification {
+            level,
+            title: title.into(),
+            body: None,
+            action: None,
+            penguin: false,
+        }
+    }
+
+    pub fn with_body(mut self, body: impl Into<Option<String>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Add a button to the notification which sends `msg` when clicked
+    pub fn with_action(mut self, label: impl Into<String>, msg: Msg) -> Self {
+        self.action = Some(NotificationAction {
+            label: label.into(),
+            msg: Box::new(msg),
+        });
+        self
+    }
+
+    pub fn with_penguin(mut self) -> Self {
+        self.penguin = true;
+        self
+    }
 }
 
 impl NotificationManager {
@@ -35,13 +94,35 @@ impl NotificationManager {
             C![C.notification_list],
             self.notifications.iter().map(|(id, notification)| {
                 div![
-                    C![C.notification],
+                    C![
+                        C.notification,
+                        match notification.level {
+                            NotificationLevel::Info => C.notification_info,
+                            NotificationLevel::Success => C.notification_success,
+                            NotificationLevel::Warning => C.notification_warning,
+                            NotificationLevel::Error => C.notification_error,
+                        },
+                    ],
+                    if notification.penguin {
+                        div![C![C.penguin, C.notification_penguin]]
+                    } else {
+                        empty![]
+                    },
                     p![C![C.notification_title], &notification.title],
                     if let Some(body) = &notification.body {
                         p![C![C.notification_body], &body]
                     } else {
                         empty![]
                     },
+                    if let Some(action) = &notification.action {
+                        button![
+                            C![C.notification_action, C.rounded, C.border_on_focus],
+                            simple_ev(Ev::Click, (*action.msg).clone()),
+                            &action.label,
+                        ]
+                    } else {
+                        empty![]
+                    },
                     simple_ev(
                         Ev::Click,
                         Msg::NotificationMessage(NotificationMessage::RemoveNotification(*id))
@@ -57,17 +138,65 @@ impl NotificationManager {
                 duration_ms,
                 notification,
             } => {
-                let id = self.next_id;
-                self.next_id += 1;
+                let has_action = notification.action.is_some();
 
-                self.notifications.insert(id, notification);
-                orders.perform_cmd(timeout(duration_ms, move || {
-                    Msg::NotificationMessage(NotificationMessage::RemoveNotification(id))
-                }));
+                // actions are always shown, even if notifications are disabled
+                if !self.settings.enabled && !has_action {
+                    return;
+                }
+
+                let duration_ms = if has_action {
+                    Some(duration_ms)
+                } else if self.is_sticky(&notification) {
+                    None
+                } else {
+                    Some(self.settings.scale_duration(duration_ms))
+                };
+
+                self.show(duration_ms, notification, orders);
             }
             NotificationMessage::RemoveNotification(id) => {
                 self.notifications.remove(&id);
             }
         }
     }
+
+    /// Whether the notification stays on screen until it is clicked
+    fn is_sticky(&self, notification: &Notification) -> bool {
+        self.settings.sticky_errors
+            && notification.level == NotificationLevel::Error
+            && notification.action.is_none()
+    }
+
+    fn show(
+        &mut self,
+        duration_ms: Option<u32>,
+        notification: Notification,
+        orders: &mut impl Orders<Msg>,
+    ) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        while self.notifications.len() >= MAX_VISIBLE_NOTIFICATIONS {
+            // prefer making room by removing the oldest notification which isn't sticky
+            let oldest = self
+                .notifications
+                .iter()
+                .find(|(_, n)| !self.is_sticky(n))
+                .or_else(|| self.notifications.iter().next())
+                .map(|(&id, _)| id);
+
+            match oldest {
+                Some(oldest) => self.notifications.remove(&oldest),
+                None => break,
+            };
+        }
+
+        self.notifications.insert(id, notification);
+        if let Some(duration_ms) = duration_ms {
+            orders.perform_cmd(timeout(duration_ms, move || {
+                Msg::NotificationMessage(NotificationMessage::RemoveNotification(id))
+            }));
+        }
+    }
 }
@@ -0,0 +1,1756 @@
+use crate::app::Msg;
+use crate::components::corrections::{CorrectionsMsg, CorrectionsPanel};
+use crate::components::failed_payments::{FailedPaymentsMsg, FailedPaymentsPanel};
+use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
+use crate::page::analytics::period_kind_name;
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::base;
+use crate::util::fetch::{send_with_retry, unlocked_response};
+use crate::util::{simple_ev, DATETIME_INPUT_FMT, DATE_INPUT_FMT};
+use crate::views::view_goal_progress;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::Resources;
+use seed_fetcher::{event, NotAvailable, ResourceStore};
+use std::collections::HashMap;
+use strecklistan_api::{
+    admin::{AdminStatus, RetentionReport},
+    announcement::{Announcement, AnnouncementId, AnnouncementSeverity, NewAnnouncement},
+    currency::Currency,
+    device::{Device, DeviceId, DeviceName},
+    goal::{GoalScope, GoalTarget, NewSalesGoal, SalesGoalId, SalesGoalProgress},
+    import::{ImportBatch, ImportBatchId, ImportSummary},
+    inventory::{InventoryItemId, InventoryItemStock, PurchaseLimit},
+    organization::{Branding, BrandingSettings},
+    report::DormantBalanceReport,
+    time::{PeriodKind, ReportingPeriod, Tz},
+    transaction::{DuplicatePair, Transaction, TransactionId},
+    user::{User, UserName},
+};
+use web_sys::{File, HtmlInputElement};
+
+#[derive(Clone, Debug)]
+pub enum AdminMsg {
+    /// Fetch all resources of the page again
+    Reload,
+
+    SetAdmin {
+        user: UserName,
+        admin: bool,
+    },
+    AdminSet,
+    /// Log a user out everywhere
+    InvalidateSessions(UserName),
+    SessionsInvalidated(UserName),
+    SetDeviceName(String),
+    /// Register the device the page is open on, under the name which has been entered
+    RegisterDevice,
+    /// Start renaming a device, from the name it has
+    EditDeviceName(DeviceId, String),
+    SetDeviceRename(String),
+    RenameDevice,
+    RemoveDevice(DeviceId),
+    DevicesChanged,
+    VoidTransaction(TransactionId),
+    TransactionVoided,
+    SetLockDate(String),
+    LockDateSet,
+    SetImportFile(Option<File>),
+    /// Send the chosen file to be imported, or only checked if `dry_run`
+    Import {
+        dry_run: bool,
+    },
+    Imported(ImportSummary),
+    RollbackImport(ImportBatchId),
+    ImportRolledBack,
+    SetAnnouncementMessage(String),
+    /// The index of the severity in [AnnouncementSeverity::ALL]
+    SetAnnouncementSeverity(String),
+    SetAnnouncementStarts(String),
+    SetAnnouncementEnds(String),
+    PostAnnouncement,
+    AnnouncementPosted,
+    DeleteAnnouncement(AnnouncementId),
+    AnnouncementDeleted,
+    SetBrandingName(String),
+    SetBrandingOrgNumber(String),
+    SetBrandingFooter(String),
+    SaveBranding,
+    /// Upload the chosen image as the logo
+    UploadLogo(Option<File>),
+    RemoveLogo,
+    BrandingSaved(Branding),
+    SetGoalName(String),
+    /// Whether the goal is of revenue, "revenue", or of the number of items sold
+    SetGoalMetric(String),
+    SetGoalTarget(String),
+    /// A [PeriodKind], or "" for an event
+    SetGoalScope(String),
+    SetGoalScopeInput(String),
+    PostGoal,
+    GoalPosted,
+    DeleteGoal(SalesGoalId),
+    GoalDeleted,
+    SetLimitItem(String),
+    SetLimitQuantity(String),
+    /// A [PeriodKind]
+    SetLimitPer(String),
+    SaveLimit,
+    RemoveLimit(InventoryItemId),
+    LimitsChanged,
+    /// Recompute the statistics of the analytics page right away
+    RefreshAnalytics,
+    AnalyticsRefreshed,
+    RefreshAnalyticsFailed(String),
+    CorrectionsMsg(CorrectionsMsg),
+    FailedPaymentsMsg(FailedPaymentsMsg),
+    Failed(String),
+
+    // -- Resource Events -- //
+    ResFetched(event::Fetched),
+    ResMarkDirty(event::MarkDirty),
+}
+
+/// Settings and status of the system, only available to admins
+pub struct AdminPage {
+    /// The name to register this device as
+    device_name: String,
+
+    /// The device being renamed, and its new name
+    renamed_device: Option<(DeviceId, String)>,
+
+    /// The file of historical transactions to import
+    import_file: Option<File>,
+
+    /// The result of the latest check or import of `import_file`
+    import_summary: Option<ImportSummary>,
+
+    /// The announcement being written, with the times as they were entered
+    announcement_message: String,
+    announcement_severity: AnnouncementSeverity,
+    announcement_starts: String,
+    announcement_ends: String,
+
+    /// The branding being edited, `None` while it's left as it was fetched
+    branding: Option<BrandingSettings>,
+
+    /// The goal being set, with the target and scope as they were entered
+    goal_name: String,
+    goal_revenue: bool,
+    goal_target: String,
+    /// `None` if the goal is for an event
+    goal_period_kind: Option<PeriodKind>,
+    /// The period, the current one if empty, or the event code
+    goal_scope: String,
+
+    /// The purchase limit being set, with the quantity as it was entered
+    limit_item: Option<InventoryItemId>,
+    limit_quantity: String,
+    limit_per: PeriodKind,
+
+    corrections: CorrectionsPanel,
+    failed_payments: FailedPaymentsPanel,
+}
+
+/// Everything the admin page shows is fetched from these routes
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/admin/status"]
+    status: &'a AdminStatus,
+
+    #[url = "/api/admin/users"]
+    users: &'a Vec<User>,
+
+    #[url = "/api/admin/devices"]
+    devices: &'a Vec<Device>,
+
+    #[url = "/api/device"]
+    this_device: &'a Option<Device>,
+
+    #[url = "/api/admin/duplicates"]
+    duplicates: &'a Vec<DuplicatePair>,
+
+    #[url = "/api/admin/dormant_balances"]
+    dormant_balances: &'a DormantBalanceReport,
+
+    #[url = "/api/admin/retention"]
+    retention: &'a RetentionReport,
+
+    #[url = "/api/admin/import/batches"]
+    import_batches: &'a Vec<ImportBatch>,
+
+    #[url = "/api/admin/announcements"]
+    announcements: &'a Vec<Announcement>,
+
+    #[url = "/api/organization/branding"]
+    branding: &'a Branding,
+
+    #[url = "/api/goals"]
+    goals: &'a Vec<SalesGoalProgress>,
+
+    #[url = "/api/inventory/purchase_limits"]
+    purchase_limits: &'a HashMap<InventoryItemId, PurchaseLimit>,
+
+    #[url = "/api/inventory/items"]
+    inventory: &'a HashMap<InventoryItemId, InventoryItemStock>,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
+}
+
+impl AdminPage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<AdminMsg>) -> Self {
+        orders.subscribe(AdminMsg::ResFetched);
+        orders.subscribe(AdminMsg::ResMarkDirty);
+        Res::acquire(rs, orders).ok();
+        AdminPage {
+            device_name: String::new(),
+            renamed_device: None,
+            import_file: None,
+            import_summary: None,
+            announcement_message: String::new(),
+            announcement_severity: AnnouncementSeverity::Info,
+            announcement_starts: String::new(),
+            announcement_ends: String::new(),
+            branding: None,
+            goal_name: String::new(),
+            goal_revenue: false,
+            goal_target: String::new(),
+            goal_period_kind: Some(PeriodKind::Day),
+            goal_scope: String::new(),
+            limit_item: None,
+            limit_quantity: String::new(),
+            limit_per: PeriodKind::Day,
+            corrections: CorrectionsPanel::new(rs, &mut orders.proxy(AdminMsg::CorrectionsMsg)),
+            failed_payments: FailedPaymentsPanel::new(
+                rs,
+                &mut orders.proxy(AdminMsg::FailedPaymentsMsg),
+            ),
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        msg: AdminMsg,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<(), NotAvailable> {
+        match msg {
+            AdminMsg::Reload => {
+                rs.mark_as_dirty(Res::status_url(), orders);
+                rs.mark_as_dirty(Res::users_url(), orders);
+                rs.mark_as_dirty(Res::devices_url(), orders);
+                rs.mark_as_dirty(Res::duplicates_url(), orders);
+                rs.mark_as_dirty(Res::import_batches_url(), orders);
+                rs.mark_as_dirty(Res::announcements_url(), orders);
+                rs.mark_as_dirty(Res::branding_url(), orders);
+                rs.mark_as_dirty(Res::goals_url(), orders);
+            }
+            AdminMsg::SetAdmin { user, admin } => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/admin/users/{}/admin", user))
+                                .method(Method::Put)
+                                .json(&admin)?,
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::AdminSet,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::AdminSet => {
+                rs.mark_as_dirty(Res::users_url(), orders);
+            }
+            AdminMsg::InvalidateSessions(user) => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new(format!("/api/admin/users/{}/invalidate_sessions", user))
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::SessionsInvalidated(user),
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::SessionsInvalidated(user) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 5000,
+                        notification: Notification::new(
+                            NotificationLevel::Info,
+                            strings::SESSIONS_INVALIDATED,
+                        )
+                        .with_body(user),
+                    },
+                ));
+            }
+            AdminMsg::SetDeviceName(name) => self.device_name = name,
+            AdminMsg::RegisterDevice => {
+                let device_name = DeviceName {
+                    name: self.device_name.trim().to_string(),
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(send_device_name(
+                    Request::new("/api/admin/devices").method(Method::Post),
+                    device_name,
+                ));
+            }
+            AdminMsg::EditDeviceName(id, name) => self.renamed_device = Some((id, name)),
+            AdminMsg::SetDeviceRename(name) => {
+                if let Some((_, renamed)) = &mut self.renamed_device {
+                    *renamed = name;
+                }
+            }
+            AdminMsg::RenameDevice => {
+                let (id, name) = match &self.renamed_device {
+                    Some((id, name)) => (*id, name.trim().to_string()),
+                    None => return Ok(()),
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(send_device_name(
+                    Request::new(format!("/api/admin/devices/{}", id)).method(Method::Put),
+                    DeviceName { name },
+                ));
+            }
+            AdminMsg::RemoveDevice(id) => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/admin/devices/{}", id))
+                                .method(Method::Delete),
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::DevicesChanged,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::DevicesChanged => {
+                self.device_name.clear();
+                self.renamed_device = None;
+                rs.mark_as_dirty(Res::devices_url(), orders);
+                rs.mark_as_dirty(Res::this_device_url(), orders);
+            }
+            AdminMsg::VoidTransaction(id) => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        let request =
+                            Request::new(format!("/api/transaction/{}", id)).method(Method::Delete);
+                        unlocked_response::<TransactionId>(send_with_retry(request).await?).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(_)) => AdminMsg::TransactionVoided,
+                        Ok(Err(lock_date)) => AdminMsg::Failed(format!(
+                            "{} ({}: {})",
+                            strings::PERIOD_LOCKED,
+                            strings::LOCK_DATE,
+                            lock_date
+                        )),
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::TransactionVoided => {
+                rs.mark_as_dirty(Res::duplicates_url(), orders);
+                rs.mark_as_dirty("/api/transactions", orders);
+            }
+            AdminMsg::SetLockDate(input) => {
+                // an empty input opens the books again
+                let lock_date: Option<NaiveDate> = match input.as_str() {
+                    "" => None,
+                    input => match NaiveDate::parse_from_str(input, DATE_INPUT_FMT) {
+                        Ok(date) => Some(date),
+                        Err(_) => return Ok(()),
+                    },
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new("/api/admin/lock_date")
+                                .method(Method::Put)
+                                .json(&lock_date)?,
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::LockDateSet,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::LockDateSet => {
+                rs.mark_as_dirty(Res::status_url(), orders);
+            }
+            AdminMsg::SetImportFile(file) => {
+                self.import_file = file;
+                self.import_summary = None;
+            }
+            AdminMsg::Import { dry_run } => {
+                let file = match &self.import_file {
+                    Some(file) => file.clone(),
+                    None => return Ok(()),
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    // not retried, an import which timed out might still have been made
+                    let result = async {
+                        Request::new(format!(
+                            "/api/admin/import/transactions?dry_run={}",
+                            dry_run
+                        ))
+                        .method(Method::Post)
+                        .header(Header::content_type("text/csv"))
+                        .body(file.into())
+                        .fetch()
+                        .await?
+                        .check_status()?
+                        .json()
+                        .await
+                    }
+                    .await;
+                    match result {
+                        Ok(summary) => AdminMsg::Imported(summary),
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::Imported(summary) => {
+                if summary.batch_id.is_some() {
+                    rs.mark_as_dirty(Res::import_batches_url(), orders);
+                    rs.mark_as_dirty("/api/transactions", orders);
+                    rs.mark_as_dirty("/api/book_accounts", orders);
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 5000,
+                            notification: Notification::new(
+                                NotificationLevel::Info,
+                                strings::IMPORT_DONE,
+                            ),
+                        },
+                    ));
+                }
+                self.import_summary = Some(summary);
+            }
+            AdminMsg::RollbackImport(id) => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new(format!("/api/admin/import/batches/{}/rollback", id))
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::ImportRolledBack,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::ImportRolledBack => {
+                rs.mark_as_dirty(Res::import_batches_url(), orders);
+                rs.mark_as_dirty("/api/transactions", orders);
+                rs.mark_as_dirty("/api/book_accounts", orders);
+            }
+            AdminMsg::SetAnnouncementMessage(message) => self.announcement_message = message,
+            AdminMsg::SetAnnouncementSeverity(input) => {
+                if let Some(&severity) = input
+                    .parse()
+                    .ok()
+                    .and_then(|i: usize| AnnouncementSeverity::ALL.get(i))
+                {
+                    self.announcement_severity = severity;
+                }
+            }
+            AdminMsg::SetAnnouncementStarts(input) => self.announcement_starts = input,
+            AdminMsg::SetAnnouncementEnds(input) => self.announcement_ends = input,
+            AdminMsg::PostAnnouncement => {
+                let tz = *Res::acquire(rs, orders)?.reporting_timezone;
+                // the times are entered in the reporting timezone, and an empty one is left open
+                let parse = |input: &str| {
+                    NaiveDateTime::parse_from_str(input, DATETIME_INPUT_FMT)
+                        .ok()
+                        .and_then(|time| tz.from_local_datetime(&time).earliest())
+                        .map(|time| time.with_timezone(&Utc))
+                };
+                let announcement = NewAnnouncement {
+                    message: self.announcement_message.clone(),
+                    severity: self.announcement_severity,
+                    starts_at: parse(&self.announcement_starts).unwrap_or_else(Utc::now),
+                    ends_at: parse(&self.announcement_ends),
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new("/api/admin/announcements")
+                            .method(Method::Post)
+                            .json(&announcement)?
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::AnnouncementPosted,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::AnnouncementPosted => {
+                self.announcement_message.clear();
+                self.announcement_starts.clear();
+                self.announcement_ends.clear();
+                rs.mark_as_dirty(Res::announcements_url(), orders);
+            }
+            AdminMsg::DeleteAnnouncement(id) => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/admin/announcements/{}", id))
+                                .method(Method::Delete),
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::AnnouncementDeleted,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::AnnouncementDeleted => {
+                rs.mark_as_dirty(Res::announcements_url(), orders);
+            }
+            AdminMsg::SetBrandingName(name) => self.edit_branding(rs, orders)?.name = name,
+            AdminMsg::SetBrandingOrgNumber(number) => {
+                self.edit_branding(rs, orders)?.org_number = Some(number)
+            }
+            AdminMsg::SetBrandingFooter(footer) => {
+                self.edit_branding(rs, orders)?.footer_text = Some(footer)
+            }
+            AdminMsg::SaveBranding => {
+                let settings = match &self.branding {
+                    Some(settings) => settings.clone(),
+                    None => return Ok(()),
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new("/api/admin/organization")
+                                .method(Method::Put)
+                                .json(&settings)?,
+                        )
+                        .await?
+                        .check_status()?
+                        .json()
+                        .await
+                    }
+                    .await;
+                    match result {
+                        Ok(branding) => AdminMsg::BrandingSaved(branding),
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::UploadLogo(file) => {
+                let file = match file {
+                    Some(file) => file,
+                    None => return Ok(()),
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new("/api/admin/organization/logo")
+                            .method(Method::Put)
+                            .header(Header::content_type(file.type_()))
+                            .body(file.into())
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(branding) => AdminMsg::BrandingSaved(branding),
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::RemoveLogo => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new("/api/admin/organization/logo").method(Method::Delete),
+                        )
+                        .await?
+                        .check_status()?
+                        .json()
+                        .await
+                    }
+                    .await;
+                    match result {
+                        Ok(branding) => AdminMsg::BrandingSaved(branding),
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::BrandingSaved(branding) => {
+                self.branding = None;
+                rs.mark_as_dirty(Res::branding_url(), orders);
+                orders.send_msg(Msg::FetchedBranding(branding));
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 5000,
+                        notification: Notification::new(
+                            NotificationLevel::Info,
+                            strings::BRANDING_SAVED,
+                        ),
+                    },
+                ));
+            }
+            AdminMsg::SetGoalName(name) => self.goal_name = name,
+            AdminMsg::SetGoalMetric(input) => self.goal_revenue = input == "revenue",
+            AdminMsg::SetGoalTarget(input) => self.goal_target = input,
+            AdminMsg::SetGoalScope(input) => {
+                self.goal_period_kind = input.parse().ok();
+                self.goal_scope.clear();
+            }
+            AdminMsg::SetGoalScopeInput(input) => self.goal_scope = input,
+            AdminMsg::PostGoal => {
+                let tz = *Res::acquire(rs, orders)?.reporting_timezone;
+                let goal = match self.new_goal(tz) {
+                    Some(goal) => goal,
+                    None => return Ok(()),
+                };
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new("/api/admin/goals")
+                            .method(Method::Post)
+                            .json(&goal)?
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::GoalPosted,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::GoalPosted => {
+                self.goal_name.clear();
+                self.goal_target.clear();
+                self.goal_scope.clear();
+                rs.mark_as_dirty(Res::goals_url(), orders);
+            }
+            AdminMsg::DeleteGoal(id) => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/admin/goals/{}", id)).method(Method::Delete),
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::GoalDeleted,
+                        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::GoalDeleted => {
+                rs.mark_as_dirty(Res::goals_url(), orders);
+            }
+            AdminMsg::SetLimitItem(input) => self.limit_item = input.parse().ok(),
+            AdminMsg::SetLimitQuantity(input) => self.limit_quantity = input,
+            AdminMsg::SetLimitPer(input) => {
+                if let Ok(per) = input.parse() {
+                    self.limit_per = per;
+                }
+            }
+            AdminMsg::SaveLimit => {
+                let (item_id, limit) = match self.new_limit() {
+                    Some(limit) => limit,
+                    None => return Ok(()),
+                };
+                orders
+                    .proxy(Msg::AdminMsg)
+                    .perform_cmd(send_limit(item_id, Some(limit)));
+            }
+            AdminMsg::RemoveLimit(item_id) => {
+                orders
+                    .proxy(Msg::AdminMsg)
+                    .perform_cmd(send_limit(item_id, None));
+            }
+            AdminMsg::LimitsChanged => {
+                self.limit_quantity.clear();
+                rs.mark_as_dirty(Res::purchase_limits_url(), orders);
+            }
+            AdminMsg::RefreshAnalytics => {
+                orders.proxy(Msg::AdminMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new("/api/analytics/refresh")
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => AdminMsg::AnalyticsRefreshed,
+                        Err(e) => AdminMsg::RefreshAnalyticsFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            AdminMsg::AnalyticsRefreshed => {
+                rs.mark_as_dirty("/api/analytics/daily_sales", orders);
+                rs.mark_as_dirty("/api/analytics/item_totals", orders);
+                rs.mark_as_dirty("/api/analytics/modifier_totals", orders);
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 5000,
+                        notification: Notification::new(
+                            NotificationLevel::Info,
+                            strings::ANALYTICS_REFRESHED,
+                        ),
+                    },
+                ));
+            }
+            AdminMsg::RefreshAnalyticsFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::ANALYTICS_REFRESH_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+            AdminMsg::CorrectionsMsg(msg) => {
+                if let CorrectionsMsg::Failed(reason) = &msg {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::ADMIN_UPDATE_FAILED,
+                            )
+                            .with_body(reason.clone()),
+                        },
+                    ));
+                }
+
+                self.corrections.update(
+                    msg,
+                    rs,
+                    &mut orders.proxy(Msg::AdminMsg).proxy(AdminMsg::CorrectionsMsg),
+                )?;
+            }
+            AdminMsg::FailedPaymentsMsg(msg) => {
+                if let FailedPaymentsMsg::Failed(reason) = &msg {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::ADMIN_UPDATE_FAILED,
+                            )
+                            .with_body(reason.clone()),
+                        },
+                    ));
+                }
+
+                self.failed_payments.update(
+                    msg,
+                    rs,
+                    &mut orders
+                        .proxy(Msg::AdminMsg)
+                        .proxy(AdminMsg::FailedPaymentsMsg),
+                )?;
+            }
+            AdminMsg::Failed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::ADMIN_UPDATE_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+            AdminMsg::ResFetched(_) | AdminMsg::ResMarkDirty(_) => {}
+        }
+        Ok(())
+    }
+
+    /// The branding being edited, starting from the fetched one
+    fn edit_branding(
+        &mut self,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) -> Result<&mut BrandingSettings, NotAvailable> {
+        let branding = Res::acquire(rs, orders)?.branding;
+        Ok(self.branding.get_or_insert_with(|| branding.settings()))
+    }
+
+    /// The goal which has been entered, if it's complete
+    fn new_goal(&self, tz: Tz) -> Option<NewSalesGoal> {
+        let name = self.goal_name.trim();
+        let target = if self.goal_revenue {
+            GoalTarget::Revenue(self.goal_target.parse::<Currency>().ok()?)
+        } else {
+            GoalTarget::Quantity(self.goal_target.trim().parse().ok()?)
+        };
+        let scope_input = self.goal_scope.trim();
+        let scope = match self.goal_period_kind {
+            Some(kind) if scope_input.is_empty() => {
+                GoalScope::Period(ReportingPeriod::at(kind, Utc::now(), tz))
+            }
+            Some(_) => GoalScope::Period(scope_input.parse().ok()?),
+            None if scope_input.is_empty() => return None,
+            None => GoalScope::Event(scope_input.to_string()),
+        };
+
+        if name.is_empty() || target.value() <= 0 {
+            return None;
+        }
+        Some(NewSalesGoal {
+            name: name.to_string(),
+            target,
+            scope,
+        })
+    }
+
+    /// The purchase limit which has been entered, if it's complete
+    fn new_limit(&self) -> Option<(InventoryItemId, PurchaseLimit)> {
+        let quantity = self
+            .limit_quantity
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|&quantity| quantity > 0)?;
+        Some((
+            self.limit_item?,
+            PurchaseLimit {
+                quantity,
+                per: self.limit_per,
+            },
+        ))
+    }
+
+    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+        let res = match Res::acquire_now(rs) {
+            Ok(res) => res,
+            Err(_) => return Loading::view_with_retry(),
+        };
+        let tz = res.reporting_timezone;
+        let fmt_time = |time: &DateTime<Utc>| {
+            time.with_timezone(tz)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        };
+        let fmt_flag = |enabled: bool| {
+            if enabled {
+                span![C![C.admin_status_ok], strings::ENABLED]
+            } else {
+                span![C![C.admin_status_off], strings::DISABLED]
+            }
+        };
+
+        let item_name = |id: InventoryItemId| {
+            res.inventory
+                .get(&id)
+                .map(|item| item.name.as_str())
+                .unwrap_or(strings::MISSING_NAME.get())
+        };
+        let mut items: Vec<&InventoryItemStock> = res
+            .inventory
+            .values()
+            .filter(|item| item.archived_at.is_none())
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut limits: Vec<(InventoryItemId, &str, PurchaseLimit)> = res
+            .purchase_limits
+            .iter()
+            .map(|(&id, &limit)| (id, item_name(id), limit))
+            .collect();
+        limits.sort_by(|a, b| a.1.cmp(b.1));
+
+        let status = res.status;
+        let fetched_branding = res.branding.settings();
+        let branding = self.branding.as_ref().unwrap_or(&fetched_branding);
+        let view_transaction = |tr: &Transaction| {
+            td![
+                div![format!("#{} {}", tr.id, fmt_time(&tr.time))],
+                div![
+                    C![C.admin_status_off],
+                    tr.created_by.as_deref().unwrap_or(""),
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, AdminMsg::VoidTransaction(tr.id)),
+                    strings::VOID,
+                ],
+            ]
+        };
+
+        div![
+            C![C.admin_page],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_USERS],
+                table![
+                    C![C.admin_table],
+                    res.users.iter().map(|user| {
+                        let name = user.name.clone();
+                        let admin = !user.is_admin;
+                        tr![
+                            td![&user.name],
+                            td![user.display_name.as_deref().unwrap_or("")],
+                            td![label![
+                                input![
+                                    attrs! {At::Type => "checkbox"},
+                                    attrs! {At::Checked => user.is_admin.as_at_value()},
+                                    ev(Ev::Change, move |_| AdminMsg::SetAdmin {
+                                        user: name,
+                                        admin,
+                                    }),
+                                ],
+                                strings::ADMIN_ROLE,
+                            ]],
+                            td![button![
+                                C![C.rounded, C.border_on_focus],
+                                simple_ev(
+                                    Ev::Click,
+                                    AdminMsg::InvalidateSessions(user.name.clone())
+                                ),
+                                strings::INVALIDATE_SESSIONS,
+                            ]],
+                        ]
+                    }),
+                ],
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::DEVICES],
+                p![C![C.admin_status_off], strings::DEVICES_HINT],
+                match res.this_device {
+                    Some(device) => p![format!("{} {}", strings::THIS_DEVICE_IS, device.name)],
+                    None => div![
+                        C![C.admin_device_form],
+                        input![
+                            attrs! {
+                                At::Type => "text",
+                                At::Placeholder => strings::DEVICE_NAME,
+                                At::Value => self.device_name,
+                            },
+                            input_ev(Ev::Input, AdminMsg::SetDeviceName),
+                        ],
+                        button![
+                            C![C.rounded, C.border_on_focus],
+                            attrs! {
+                                At::Disabled => self.device_name.trim().is_empty().as_at_value()
+                            },
+                            simple_ev(Ev::Click, AdminMsg::RegisterDevice),
+                            strings::REGISTER_THIS_DEVICE,
+                        ],
+                    ],
+                },
+                if res.devices.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_DEVICES]
+                } else {
+                    table![
+                        C![C.admin_table],
+                        res.devices.iter().map(|device| {
+                            let renamed = self
+                                .renamed_device
+                                .as_ref()
+                                .filter(|(id, _)| *id == device.id)
+                                .map(|(_, name)| name);
+                            tr![
+                                match renamed {
+                                    Some(name) => td![
+                                        input![
+                                            attrs! {
+                                                At::Type => "text",
+                                                At::Value => name,
+                                            },
+                                            input_ev(Ev::Input, AdminMsg::SetDeviceRename),
+                                        ],
+                                        button![
+                                            C![C.rounded, C.border_on_focus],
+                                            attrs! {
+                                                At::Disabled => name.trim().is_empty().as_at_value()
+                                            },
+                                            simple_ev(Ev::Click, AdminMsg::RenameDevice),
+                                            strings::SAVE,
+                                        ],
+                                    ],
+                                    None => td![&device.name],
+                                },
+                                td![if device.online {
+                                    span![C![C.admin_status_ok], strings::ONLINE]
+                                } else {
+                                    span![
+                                        C![C.admin_status_off],
+                                        format!(
+                                            "{} {}",
+                                            strings::LAST_SEEN,
+                                            device
+                                                .last_seen
+                                                .as_ref()
+                                                .map(fmt_time)
+                                                .unwrap_or_else(|| strings::UNKNOWN.to_string())
+                                        ),
+                                    ]
+                                }],
+                                td![device.registered_by.as_deref().unwrap_or("")],
+                                td![
+                                    button![
+                                        C![C.rounded, C.border_on_focus],
+                                        simple_ev(
+                                            Ev::Click,
+                                            AdminMsg::EditDeviceName(device.id, device.name.clone())
+                                        ),
+                                        strings::RENAME,
+                                    ],
+                                    button![
+                                        C![C.rounded, C.border_on_focus],
+                                        simple_ev(Ev::Click, AdminMsg::RemoveDevice(device.id)),
+                                        strings::REMOVE,
+                                    ],
+                                ],
+                            ]
+                        }),
+                    ]
+                },
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_BRANDING],
+                p![C![C.admin_status_off], strings::BRANDING_HINT],
+                div![
+                    C![C.admin_branding_form],
+                    label![
+                        strings::ORGANIZATION_NAME,
+                        input![
+                            attrs! {
+                                At::Type => "text",
+                                At::Value => branding.name,
+                            },
+                            input_ev(Ev::Input, AdminMsg::SetBrandingName),
+                        ],
+                    ],
+                    label![
+                        strings::ORG_NUMBER,
+                        input![
+                            attrs! {
+                                At::Type => "text",
+                                At::Value => branding.org_number.as_deref().unwrap_or(""),
+                            },
+                            input_ev(Ev::Input, AdminMsg::SetBrandingOrgNumber),
+                        ],
+                    ],
+                    label![
+                        strings::FOOTER_TEXT,
+                        textarea![
+                            attrs! {
+                                At::Rows => 3,
+                                At::Value => branding.footer_text.as_deref().unwrap_or(""),
+                            },
+                            input_ev(Ev::Input, AdminMsg::SetBrandingFooter),
+                        ],
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {
+                            At::Disabled => (self.branding.is_none()
+                                || branding.name.trim().is_empty())
+                            .as_at_value()
+                        },
+                        simple_ev(Ev::Click, AdminMsg::SaveBranding),
+                        strings::SAVE,
+                    ],
+                ],
+                div![
+                    C![C.admin_branding_logo],
+                    res.branding.logo_url().map(|url| {
+                        img![C![C.admin_logo_preview], attrs! {At::Src => base::api_url(&url), At::Alt => ""}]
+                    }),
+                    label![
+                        strings::LOGO,
+                        input![
+                            attrs! {
+                                At::Type => "file",
+                                At::Accept => "image/png,image/jpeg,image/gif,image/webp",
+                            },
+                            ev(Ev::Change, |event| {
+                                let file = event
+                                    .target()
+                                    .and_then(|target| target.dyn_into::<HtmlInputElement>().ok())
+                                    .and_then(|input| input.files())
+                                    .and_then(|files| files.get(0));
+                                AdminMsg::UploadLogo(file)
+                            }),
+                        ],
+                    ],
+                    if res.branding.logo_url().is_some() {
+                        button![
+                            C![C.rounded, C.border_on_focus],
+                            simple_ev(Ev::Click, AdminMsg::RemoveLogo),
+                            strings::REMOVE_LOGO,
+                        ]
+                    } else {
+                        empty![]
+                    },
+                ],
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::GOALS],
+                p![C![C.admin_status_off], strings::GOALS_HINT],
+                div![
+                    C![C.admin_goal_form],
+                    input![
+                        attrs! {
+                            At::Type => "text",
+                            At::Placeholder => strings::GOAL_NAME,
+                            At::Value => self.goal_name,
+                        },
+                        input_ev(Ev::Input, AdminMsg::SetGoalName),
+                    ],
+                    select![
+                        option![
+                            attrs! {At::Value => "quantity"},
+                            attrs! {At::Selected => (!self.goal_revenue).as_at_value()},
+                            strings::GOAL_QUANTITY,
+                        ],
+                        option![
+                            attrs! {At::Value => "revenue"},
+                            attrs! {At::Selected => self.goal_revenue.as_at_value()},
+                            strings::GOAL_REVENUE,
+                        ],
+                        input_ev(Ev::Change, AdminMsg::SetGoalMetric),
+                    ],
+                    input![
+                        attrs! {
+                            At::Type => "number",
+                            At::Min => 1,
+                            At::Placeholder => strings::GOAL_TARGET,
+                            At::Value => self.goal_target,
+                        },
+                        input_ev(Ev::Input, AdminMsg::SetGoalTarget),
+                    ],
+                    select![
+                        PeriodKind::ALL.iter().map(|&kind| {
+                            option![
+                                attrs! {At::Value => kind},
+                                attrs! {
+                                    At::Selected =>
+                                        (self.goal_period_kind == Some(kind)).as_at_value()
+                                },
+                                period_kind_name(kind),
+                            ]
+                        }),
+                        option![
+                            attrs! {At::Value => ""},
+                            attrs! {At::Selected => self.goal_period_kind.is_none().as_at_value()},
+                            strings::GOAL_EVENT,
+                        ],
+                        input_ev(Ev::Change, AdminMsg::SetGoalScope),
+                    ],
+                    input![
+                        attrs! {
+                            At::Type => "text",
+                            At::Placeholder => match self.goal_period_kind {
+                                Some(kind) => ReportingPeriod::at(kind, Utc::now(), *tz).to_string(),
+                                None => strings::EVENT_CODE.to_string(),
+                            },
+                            At::Value => self.goal_scope,
+                        },
+                        input_ev(Ev::Input, AdminMsg::SetGoalScopeInput),
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {At::Disabled => self.new_goal(*tz).is_none().as_at_value()},
+                        simple_ev(Ev::Click, AdminMsg::PostGoal),
+                        strings::SET_GOAL,
+                    ],
+                ],
+                if res.goals.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_GOALS]
+                } else {
+                    table![
+                        C![C.admin_table],
+                        res.goals.iter().map(|progress| {
+                            tr![
+                                td![view_goal_progress(progress)],
+                                td![progress.goal.created_by.as_deref().unwrap_or("")],
+                                td![button![
+                                    C![C.rounded, C.border_on_focus],
+                                    simple_ev(Ev::Click, AdminMsg::DeleteGoal(progress.goal.id)),
+                                    strings::REMOVE,
+                                ]],
+                            ]
+                        }),
+                    ]
+                },
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::PURCHASE_LIMITS],
+                p![C![C.admin_status_off], strings::PURCHASE_LIMITS_HINT],
+                div![
+                    C![C.admin_goal_form],
+                    select![
+                        option![
+                            attrs! {At::Value => ""},
+                            attrs! {At::Selected => self.limit_item.is_none().as_at_value()},
+                            strings::CHOOSE_ITEM,
+                        ],
+                        items.iter().map(|item| {
+                            option![
+                                attrs! {At::Value => item.id},
+                                attrs! {
+                                    At::Selected => (self.limit_item == Some(item.id)).as_at_value()
+                                },
+                                &item.name,
+                            ]
+                        }),
+                        input_ev(Ev::Change, AdminMsg::SetLimitItem),
+                    ],
+                    input![
+                        attrs! {
+                            At::Type => "number",
+                            At::Min => 1,
+                            At::Placeholder => strings::PURCHASE_LIMIT_QUANTITY,
+                            At::Value => self.limit_quantity,
+                        },
+                        input_ev(Ev::Input, AdminMsg::SetLimitQuantity),
+                    ],
+                    select![
+                        PeriodKind::ALL.iter().map(|&kind| {
+                            option![
+                                attrs! {At::Value => kind},
+                                attrs! {At::Selected => (self.limit_per == kind).as_at_value()},
+                                period_kind_name(kind),
+                            ]
+                        }),
+                        input_ev(Ev::Change, AdminMsg::SetLimitPer),
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {At::Disabled => self.new_limit().is_none().as_at_value()},
+                        simple_ev(Ev::Click, AdminMsg::SaveLimit),
+                        strings::SET_PURCHASE_LIMIT,
+                    ],
+                ],
+                if limits.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_PURCHASE_LIMITS]
+                } else {
+                    table![
+                        C![C.admin_table],
+                        limits.iter().map(|&(item_id, item_name, limit)| {
+                            tr![
+                                td![item_name],
+                                td![format!("{} / {}", limit.quantity, period_kind_name(limit.per))],
+                                td![button![
+                                    C![C.rounded, C.border_on_focus],
+                                    simple_ev(Ev::Click, AdminMsg::RemoveLimit(item_id)),
+                                    strings::REMOVE,
+                                ]],
+                            ]
+                        }),
+                    ]
+                },
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_ANNOUNCEMENTS],
+                p![C![C.admin_status_off], strings::ANNOUNCEMENTS_HINT],
+                div![
+                    C![C.admin_announcement_form],
+                    input![
+                        attrs! {
+                            At::Type => "text",
+                            At::Placeholder => strings::ANNOUNCEMENT_MESSAGE,
+                            At::Value => self.announcement_message,
+                        },
+                        input_ev(Ev::Input, AdminMsg::SetAnnouncementMessage),
+                    ],
+                    select![
+                        AnnouncementSeverity::ALL
+                            .iter()
+                            .enumerate()
+                            .map(|(i, &severity)| {
+                                option![
+                                    attrs! {At::Value => i},
+                                    attrs! {
+                                        At::Selected =>
+                                            (severity == self.announcement_severity).as_at_value()
+                                    },
+                                    severity_name(severity),
+                                ]
+                            }),
+                        input_ev(Ev::Change, AdminMsg::SetAnnouncementSeverity),
+                    ],
+                    label![
+                        strings::ANNOUNCEMENT_STARTS,
+                        input![
+                            attrs! {
+                                At::Type => "datetime-local",
+                                At::Value => self.announcement_starts,
+                            },
+                            input_ev(Ev::Change, AdminMsg::SetAnnouncementStarts),
+                        ],
+                    ],
+                    label![
+                        strings::ANNOUNCEMENT_ENDS,
+                        input![
+                            attrs! {
+                                At::Type => "datetime-local",
+                                At::Value => self.announcement_ends,
+                            },
+                            input_ev(Ev::Change, AdminMsg::SetAnnouncementEnds),
+                        ],
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {
+                            At::Disabled => self.announcement_message.trim().is_empty().as_at_value()
+                        },
+                        simple_ev(Ev::Click, AdminMsg::PostAnnouncement),
+                        strings::ANNOUNCE,
+                    ],
+                ],
+                if res.announcements.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_ANNOUNCEMENTS]
+                } else {
+                    let now = Utc::now();
+                    table![
+                        C![C.admin_table],
+                        res.announcements.iter().map(|announcement| {
+                            tr![
+                                td![&announcement.message],
+                                td![severity_name(announcement.severity)],
+                                td![
+                                    if announcement.is_active(now) {
+                                        C![]
+                                    } else {
+                                        C![C.admin_status_off]
+                                    },
+                                    format!(
+                                        "{} – {}",
+                                        fmt_time(&announcement.starts_at),
+                                        announcement
+                                            .ends_at
+                                            .as_ref()
+                                            .map(fmt_time)
+                                            .unwrap_or_default()
+                                    ),
+                                ],
+                                td![announcement.created_by.as_deref().unwrap_or("")],
+                                td![button![
+                                    C![C.rounded, C.border_on_focus],
+                                    simple_ev(
+                                        Ev::Click,
+                                        AdminMsg::DeleteAnnouncement(announcement.id)
+                                    ),
+                                    strings::REMOVE,
+                                ]],
+                            ]
+                        }),
+                    ]
+                },
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_DUPLICATES],
+                if res.duplicates.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_DUPLICATES]
+                } else {
+                    table![
+                        C![C.admin_table],
+                        res.duplicates.iter().map(|pair| {
+                            tr![
+                                td![
+                                    pair.original
+                                        .description
+                                        .as_deref()
+                                        .unwrap_or(strings::TRANSACTION.get()),
+                                    br![],
+                                    format!("{}:-", pair.original.amount),
+                                ],
+                                view_transaction(&pair.original),
+                                view_transaction(&pair.duplicate),
+                            ]
+                        }),
+                    ]
+                },
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_DORMANT],
+                p![C![C.admin_status_off], strings::DORMANT_HINT],
+                if res.dormant_balances.balances.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_DORMANT_BALANCES]
+                } else {
+                    let dormant = res.dormant_balances;
+                    let fmt_date = |time: &DateTime<Utc>| {
+                        time.with_timezone(tz).format(DATE_INPUT_FMT).to_string()
+                    };
+                    table![
+                        C![C.admin_table],
+                        tr![
+                            th![strings::MEMBER_NAME],
+                            th![strings::BALANCE],
+                            th![strings::LAST_ACTIVITY],
+                            th![strings::REMINDED],
+                        ],
+                        dormant.balances.iter().map(|balance| {
+                            let reminded = balance.reminded_at.as_ref().map(&fmt_date);
+                            tr![
+                                if balance.forfeitable {
+                                    C![C.admin_status_bad]
+                                } else {
+                                    C![]
+                                },
+                                td![&balance.name],
+                                td![format!("{}:-", balance.balance)],
+                                td![fmt_date(&balance.last_activity)],
+                                td![reminded.unwrap_or_default()],
+                            ]
+                        }),
+                        tr![
+                            td![strings::DORMANT_TOTAL],
+                            td![format!("{}:-", dormant.total())],
+                        ],
+                        tr![
+                            C![C.admin_status_bad],
+                            td![strings::FORFEITABLE_TOTAL],
+                            td![format!("{}:-", dormant.total_forfeitable())],
+                        ],
+                    ]
+                },
+            ],
+            self.corrections
+                .view(rs)
+                .map_msg(AdminMsg::CorrectionsMsg),
+            self.failed_payments
+                .view(rs)
+                .map_msg(AdminMsg::FailedPaymentsMsg),
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_RETENTION],
+                p![C![C.admin_status_off], strings::RETENTION_HINT],
+                if res.retention.dry_run {
+                    p![C![C.admin_status_off], strings::RETENTION_DRY_RUN]
+                } else {
+                    empty![]
+                },
+                {
+                    let retention = res.retention;
+                    let fmt_before = |before: &Option<DateTime<Utc>>| match before {
+                        Some(before) => format!(" ({} {})", strings::BEFORE, fmt_time(before)),
+                        None => String::new(),
+                    };
+                    table![
+                        C![C.admin_table],
+                        tr![
+                            td![strings::TRANSACTIONS_EXPORTED],
+                            td![retention
+                                .transactions_exported_at
+                                .as_ref()
+                                .map(&fmt_time)
+                                .unwrap_or_else(|| strings::NEVER.to_string())],
+                        ],
+                        tr![
+                            td![strings::COMPACTABLE_TRANSACTIONS],
+                            td![format!(
+                                "{}, {} {}{}",
+                                retention.compactable_transactions,
+                                retention.compactable_lines,
+                                strings::ROWS,
+                                fmt_before(&retention.compact_before),
+                            )],
+                        ],
+                        tr![
+                            td![strings::PRUNABLE_AUDIT_ENTRIES],
+                            td![format!(
+                                "{}{}",
+                                retention.prunable_audit_entries,
+                                fmt_before(&retention.prune_audit_before),
+                            )],
+                        ],
+                    ]
+                },
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::LOCK_DATE],
+                p![C![C.admin_status_off], strings::LOCK_DATE_HINT],
+                input![
+                    attrs! {
+                        At::Type => "date",
+                        At::Value => status
+                            .lock_date
+                            .map(|date| date.format(DATE_INPUT_FMT).to_string())
+                            .unwrap_or_default(),
+                    },
+                    input_ev(Ev::Change, AdminMsg::SetLockDate),
+                ],
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_IMPORT],
+                p![C![C.admin_status_off], strings::IMPORT_HINT],
+                div![
+                    C![C.admin_import_controls],
+                    input![
+                        attrs! {At::Type => "file", At::Accept => ".csv,text/csv"},
+                        ev(Ev::Change, |event| {
+                            let file = event
+                                .target()
+                                .and_then(|target| target.dyn_into::<HtmlInputElement>().ok())
+                                .and_then(|input| input.files())
+                                .and_then(|files| files.get(0));
+                            AdminMsg::SetImportFile(file)
+                        }),
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {At::Disabled => self.import_file.is_none().as_at_value()},
+                        simple_ev(Ev::Click, AdminMsg::Import { dry_run: true }),
+                        strings::CHECK_IMPORT,
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {At::Disabled => self.import_file.is_none().as_at_value()},
+                        simple_ev(Ev::Click, AdminMsg::Import { dry_run: false }),
+                        strings::IMPORT,
+                    ],
+                ],
+                self.import_summary.as_ref().map(|summary| {
+                    div![
+                        table![
+                            C![C.admin_table],
+                            tr![
+                                td![strings::REPORT_TOTAL_SALES],
+                                td![format!("{} × {}:-", summary.sales, summary.sales_total)],
+                            ],
+                            tr![
+                                td![strings::REPORT_TOTAL_DEPOSITS],
+                                td![format!(
+                                    "{} × {}:-",
+                                    summary.deposits, summary.deposits_total
+                                )],
+                            ],
+                            tr![
+                                td![strings::IMPORT_PERIOD],
+                                td![match (summary.first_date, summary.last_date) {
+                                    (Some(first), Some(last)) => format!("{} – {}", first, last),
+                                    _ => String::new(),
+                                }],
+                            ],
+                        ],
+                        ul![summary.errors.iter().map(|error| {
+                            li![
+                                C![C.admin_status_bad],
+                                format!(
+                                    "{} {}: {}",
+                                    strings::IMPORT_LINE,
+                                    error.line,
+                                    error.message
+                                ),
+                            ]
+                        })],
+                    ]
+                }),
+                table![
+                    C![C.admin_table],
+                    res.import_batches.iter().map(|batch| {
+                        tr![
+                            td![format!("#{}", batch.id)],
+                            td![fmt_time(&batch.created_at)],
+                            td![batch.created_by.as_deref().unwrap_or("")],
+                            td![format!(
+                                "{} {}",
+                                batch.transaction_count,
+                                strings::IMPORT_TRANSACTIONS
+                            )],
+                            td![match &batch.rolled_back_at {
+                                Some(time) => span![
+                                    C![C.admin_status_off],
+                                    format!("{} {}", strings::IMPORT_ROLLED_BACK, fmt_time(time)),
+                                ],
+                                None => button![
+                                    C![C.rounded, C.border_on_focus],
+                                    simple_ev(Ev::Click, AdminMsg::RollbackImport(batch.id)),
+                                    strings::ROLL_BACK_IMPORT,
+                                ],
+                            }],
+                        ]
+                    }),
+                ],
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_FEATURES],
+                table![
+                    C![C.admin_table],
+                    tr![
+                        td![strings::FEATURE_IZETTLE],
+                        td![fmt_flag(status.features.izettle_enabled)],
+                    ],
+                    tr![
+                        td![strings::FEATURE_SMTP],
+                        td![fmt_flag(status.features.smtp_enabled)],
+                    ],
+                    tr![
+                        td![strings::FEATURE_WEBHOOKS],
+                        td![fmt_flag(status.features.webhooks_enabled)],
+                    ],
+                ],
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_BRIDGE],
+                table![
+                    C![C.admin_table],
+                    tr![
+                        td![strings::BRIDGE_LAST_POLL],
+                        td![match &status.bridge.last_poll {
+                            Some(time) => span![fmt_time(time)],
+                            None => span![C![C.admin_status_off], strings::NEVER],
+                        }],
+                    ],
+                    tr![
+                        td![strings::BRIDGE_PENDING],
+                        td![status.bridge.pending_payments.to_string()],
+                    ],
+                    tr![
+                        td![strings::BRIDGE_OLDEST_PENDING],
+                        td![status
+                            .bridge
+                            .oldest_pending
+                            .as_ref()
+                            .map(fmt_time)
+                            .unwrap_or_default()],
+                    ],
+                ],
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_WEBHOOKS],
+                if status.webhooks.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_WEBHOOKS]
+                } else {
+                    table![
+                        C![C.admin_table],
+                        status.webhooks.iter().map(|webhook| {
+                            tr![
+                                td![&webhook.url],
+                                td![if webhook.signed {
+                                    strings::WEBHOOK_SIGNED
+                                } else {
+                                    strings::WEBHOOK_UNSIGNED
+                                }],
+                            ]
+                        }),
+                    ]
+                },
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_ANALYTICS],
+                p![C![C.admin_status_off], strings::ANALYTICS_REFRESH_HINT],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, AdminMsg::RefreshAnalytics),
+                    strings::REFRESH_ANALYTICS,
+                ],
+            ],
+            section![
+                C![C.admin_section],
+                h2![strings::ADMIN_BACKUP],
+                match (status.backup.configured, &status.backup.latest) {
+                    (false, _) => p![C![C.admin_status_off], strings::BACKUP_NOT_CONFIGURED],
+                    (true, None) => p![C![C.admin_status_bad], strings::NO_BACKUPS],
+                    (true, Some(backup)) => table![
+                        C![C.admin_table],
+                        tr![td![strings::BACKUP_LATEST], td![&backup.name]],
+                        tr![td![strings::BACKUP_TIME], td![fmt_time(&backup.modified)]],
+                        tr![
+                            td![strings::BACKUP_SIZE],
+                            td![format!("{:.1} MB", backup.size_bytes as f64 / 1e6)],
+                        ],
+                    ],
+                },
+            ],
+        ]
+        .map_msg(Msg::AdminMsg)
+    }
+}
+
+fn severity_name(severity: AnnouncementSeverity) -> Text {
+    match severity {
+        AnnouncementSeverity::Info => strings::SEVERITY_INFO,
+        AnnouncementSeverity::Warning => strings::SEVERITY_WARNING,
+        AnnouncementSeverity::Critical => strings::SEVERITY_CRITICAL,
+    }
+}
+
+/// Register or rename a device, telling the admin if the name is taken
+async fn send_device_name(request: Request<'static>, device_name: DeviceName) -> AdminMsg {
+    let result = async { request.json(&device_name)?.fetch().await?.check_status() }.await;
+    match result {
+        Ok(_) => AdminMsg::DevicesChanged,
+        Err(FetchError::StatusError(status)) if status.code == 409 => {
+            AdminMsg::Failed(strings::DEVICE_NAME_TAKEN.to_string())
+        }
+        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+    }
+}
+
+/// Set or remove the purchase limit of an item
+async fn send_limit(item_id: InventoryItemId, limit: Option<PurchaseLimit>) -> AdminMsg {
+    let url = format!("/api/inventory/item/{}/purchase_limit", item_id);
+    let result = async {
+        Request::new(url)
+            .method(Method::Put)
+            .json(&limit)?
+            .fetch()
+            .await?
+            .check_status()
+    }
+    .await;
+    match result {
+        Ok(_) => AdminMsg::LimitsChanged,
+        Err(e) => AdminMsg::Failed(format!("{:?}", e)),
+    }
+}
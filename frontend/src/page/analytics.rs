@@ -1,25 +1,57 @@
 use crate::app::Msg;
+use crate::components::filter_presets::{FilterPresets, FilterPresetsMsg};
 use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
 use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
+use crate::strings;
+use crate::util::fetch::get_with_retry;
 use crate::util::{simple_ev, DATE_INPUT_FMT};
-use chrono::{DateTime, Datelike, Duration, IsoWeek, NaiveDate, Utc, Weekday};
+use crate::views::chart::{Chart, ChartKind, Unit};
+use crate::views::view_goal_progress;
+use chrono::{Datelike, Duration, IsoWeek, NaiveDate, Utc};
 use seed::app::cmds::timeout;
 use seed::{prelude::*, *};
 use seed_fetcher::Resources;
 use seed_fetcher::{event, NotAvailable, ResourceStore};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::rc::Rc;
 use strecklistan_api::{
-    inventory::{InventoryItemId, InventoryItemStock},
-    transaction::Transaction,
+    analytics::{
+        CashierShift, CategorySales, DailySales, ItemTotals, ModifierTotals, PaymentMethod,
+        PaymentMethodSales,
+    },
+    book_account::{BookAccount, BookAccountId},
+    filter_preset::{PresetDates, PresetFilter, PresetPage},
+    goal::SalesGoalProgress,
+    inventory::{InventoryItemId, InventoryItemStock, ItemModifier, ItemModifierId},
+    time::{local_date, PeriodKind, ReportingPeriod, Tz},
+    user::UserName,
 };
 
 #[derive(Clone, Debug)]
 pub enum AnalyticsMsg {
+    /// Fetch all resources of the page again
+    Reload,
+
     ComputeCharts,
-    ChartsComputed(Rc<HashMap<InventoryItemId, Node<AnalyticsMsg>>>),
+    ChartsComputed(Rc<Vec<Node<AnalyticsMsg>>>),
     SetStartDate(String),
     SetEndDate(String),
+    SetPeriodKind(String),
+    PreviousPeriod,
+    NextPeriod,
+    SetCashier(String),
+    ShiftsFetched(Vec<CashierShift>),
+    ShiftsFetchFailed(String),
+    RevenueFetched(Vec<PaymentMethodSales>, Vec<CategorySales>),
+    RevenueFetchFailed(String),
+    SetTimeTravel(bool),
+    SetAsOfDate(String),
+    SnapshotFetched(Rc<Snapshot>),
+    SnapshotFetchFailed(String),
+    FilterPresetsMsg(FilterPresetsMsg),
 
     // -- Resource Events -- //
     ResFetched(event::Fetched),
@@ -28,29 +60,76 @@ pub enum AnalyticsMsg {
 
 //#[derive(Clone)]
 pub struct AnalyticsPage {
-    /// An index of the inventory stocks at the start of every week
-    //inventory_by_week: Rc<BTreeMap<IsoWeek, HashMap<InventoryItemId, i32>>>,
-
-    /// Pre-computed and cached charts
-    charts: Rc<HashMap<InventoryItemId, Node<AnalyticsMsg>>>,
+    /// Pre-computed and cached charts, of the most sold item first
+    charts: Rc<Vec<Node<AnalyticsMsg>>>,
 
     /// Handle for the process computing the charts
     charts_job: Option<CmdHandle>,
 
-    /// Start-date filter for computing charts
-    start_date: DateTime<Utc>,
+    /// Start-date filter for computing charts, in the reporting timezone
+    start_date: NaiveDate,
+
+    /// End-date filter for computing charts, inclusive, in the reporting timezone
+    end_date: NaiveDate,
+
+    /// The period which the dates were set from, if they weren't chosen by hand
+    period: Option<ReportingPeriod>,
+
+    /// Only count sales made by this user, set by the `cashier` url parameter
+    cashier: Option<UserName>,
+
+    /// The shifts between the dates, fetched along with the charts. Only the user's own unless
+    /// they are an admin.
+    shifts: Option<Vec<CashierShift>>,
 
-    /// End-date filter for computing charts
-    end_date: DateTime<Utc>,
+    /// The revenue between the dates by payment method and by item category, fetched along with
+    /// the charts
+    revenue: Option<(Vec<PaymentMethodSales>, Vec<CategorySales>)>,
+
+    /// The day at the end of which the stock and the balances are shown, when looking back in
+    /// time. Set by the `as_of` url parameter.
+    as_of: Option<NaiveDate>,
+
+    /// The stock and the balances at the end of `as_of`
+    snapshot: Option<Rc<Snapshot>>,
+
+    presets: FilterPresets,
 }
 
+/// The stock and the balances as they were at the end of a day, computed by the server from the
+/// transactions as they were then
+#[derive(Debug)]
+pub struct Snapshot {
+    items: HashMap<InventoryItemId, InventoryItemStock>,
+    accounts: HashMap<BookAccountId, BookAccount>,
+}
+
+/// The sales are aggregated by the server, which recomputes them periodically
 #[derive(Resources)]
 struct Res<'a> {
-    #[url = "/api/transactions"]
-    transactions: &'a Vec<Transaction>,
+    #[url = "/api/analytics/daily_sales"]
+    daily_sales: &'a Vec<DailySales>,
+
+    #[url = "/api/analytics/item_totals"]
+    item_totals: &'a Vec<ItemTotals>,
+
+    #[url = "/api/analytics/modifier_totals"]
+    modifier_totals: &'a Vec<ModifierTotals>,
+
+    #[url = "/api/inventory/modifiers"]
+    modifiers: &'a HashMap<ItemModifierId, ItemModifier>,
 
     #[url = "/api/inventory/items"]
     inventory: &'a HashMap<InventoryItemId, InventoryItemStock>,
+
+    #[url = "/api/book_accounts"]
+    book_accounts: &'a HashMap<BookAccountId, BookAccount>,
+
+    #[url = "/api/goals"]
+    goals: &'a Vec<SalesGoalProgress>,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
 }
 
 impl AnalyticsPage {
@@ -59,21 +138,83 @@ impl AnalyticsPage {
         orders.subscribe(AnalyticsMsg::ResMarkDirty);
         Res::acquire(rs, orders).ok();
 
-        let now = Utc::now();
+        let today = Utc::now().naive_utc().date();
         AnalyticsPage {
-            charts: Rc::new(HashMap::new()),
+            charts: Rc::new(vec![]),
             charts_job: None,
-            start_date: now - Duration::days(365),
-            end_date: now,
+            start_date: today - Duration::days(365),
+            end_date: today,
+            period: None,
+            cashier: None,
+            shifts: None,
+            revenue: None,
+            as_of: None,
+            snapshot: None,
+            presets: FilterPresets::new(
+                PresetPage::Analytics,
+                rs,
+                &mut orders.proxy(AnalyticsMsg::FilterPresetsMsg),
+            ),
         }
     }
 
+    /// Read the date range from the `period`, or the `from` and `to`, url parameters
+    pub fn set_params(&mut self, params: &UrlParams) {
+        if let Some(period) = params.get("period").and_then(|period| period.parse().ok()) {
+            self.set_period(period);
+        }
+
+        let parse_date = |key| {
+            params
+                .get(key)
+                .and_then(|date| NaiveDate::parse_from_str(date, DATE_INPUT_FMT).ok())
+        };
+
+        if let Some(date) = parse_date("from") {
+            self.start_date = date;
+        }
+        if let Some(date) = parse_date("to") {
+            self.end_date = date;
+        }
+        if let Some(cashier) = params.get("cashier") {
+            self.cashier = Some(cashier.to_string());
+        }
+        if let Some(date) = parse_date("as_of") {
+            self.as_of = Some(date);
+        }
+    }
+
+    /// Reflect the state of the page in the url
+    pub fn update_url(&self) {
+        let dates =
+            |date: NaiveDate| Some(date.format(DATE_INPUT_FMT)).filter(|_| self.period.is_none());
+        UrlParams::default()
+            .with("period", self.period)
+            .with("from", dates(self.start_date))
+            .with("to", dates(self.end_date))
+            .with("cashier", self.cashier.as_ref())
+            .with("as_of", self.as_of.map(|date| date.format(DATE_INPUT_FMT)))
+            .replace_url(Page::Analytics);
+    }
+
     pub fn update(
         &mut self,
         msg: AnalyticsMsg,
         rs: &ResourceStore,
         orders: &mut impl Orders<Msg>,
     ) -> Result<(), NotAvailable> {
+        if let AnalyticsMsg::Reload = msg {
+            rs.mark_as_dirty(Res::daily_sales_url(), orders);
+            rs.mark_as_dirty(Res::item_totals_url(), orders);
+            rs.mark_as_dirty(Res::modifier_totals_url(), orders);
+            rs.mark_as_dirty(Res::modifiers_url(), orders);
+            rs.mark_as_dirty(Res::inventory_url(), orders);
+            rs.mark_as_dirty(Res::book_accounts_url(), orders);
+            rs.mark_as_dirty(Res::goals_url(), orders);
+            rs.mark_as_dirty(Res::reporting_timezone_url(), orders);
+            return Ok(());
+        }
+
         let res = Res::acquire(rs, orders)?;
 
         let mut orders_local = orders.proxy(|msg| Msg::AnalyticsMsg(msg));
@@ -81,6 +222,9 @@ impl AnalyticsPage {
         match msg {
             AnalyticsMsg::ComputeCharts => {
                 self.compute_charts(&res, &mut orders_local);
+                self.fetch_shifts(&mut orders_local);
+                self.fetch_revenue(&mut orders_local);
+                self.fetch_snapshot(&mut orders_local);
             }
             AnalyticsMsg::ChartsComputed(charts) => {
                 self.charts = charts;
@@ -88,49 +232,240 @@ impl AnalyticsPage {
             }
             AnalyticsMsg::SetStartDate(input) => {
                 if let Ok(date) = NaiveDate::parse_from_str(&input, DATE_INPUT_FMT) {
-                    self.start_date = DateTime::from_utc(date.and_hms(0, 0, 0), Utc);
+                    self.start_date = date;
+                    self.period = None;
+                    self.update_url();
                 }
             }
             AnalyticsMsg::SetEndDate(input) => {
                 if let Ok(date) = NaiveDate::parse_from_str(&input, DATE_INPUT_FMT) {
-                    self.end_date = DateTime::from_utc(date.and_hms(0, 0, 0), Utc);
+                    self.end_date = date;
+                    self.period = None;
+                    self.update_url();
+                }
+            }
+            AnalyticsMsg::SetPeriodKind(input) => {
+                match input.parse() {
+                    Ok(kind) => self.set_period(ReportingPeriod::containing(kind, self.end_date)),
+                    Err(_) => self.period = None,
+                }
+                self.update_url();
+            }
+            AnalyticsMsg::PreviousPeriod => {
+                if let Some(period) = self.period {
+                    self.set_period(period.previous());
+                    self.update_url();
+                }
+            }
+            AnalyticsMsg::NextPeriod => {
+                if let Some(period) = self.period {
+                    self.set_period(period.next());
+                    self.update_url();
+                }
+            }
+            AnalyticsMsg::SetCashier(input) => {
+                self.cashier = Some(input).filter(|cashier| !cashier.is_empty());
+                self.update_url();
+            }
+            AnalyticsMsg::FilterPresetsMsg(msg) => {
+                match &msg {
+                    FilterPresetsMsg::Apply(filter) => {
+                        let today = local_date(Utc::now(), *res.reporting_timezone);
+                        self.apply_preset(filter.clone(), today);
+                        orders_local.send_msg(AnalyticsMsg::ComputeCharts);
+                    }
+                    FilterPresetsMsg::Failed(reason) => {
+                        orders.send_msg(Msg::NotificationMessage(
+                            NotificationMessage::ShowNotification {
+                                duration_ms: 10000,
+                                notification: Notification::new(
+                                    NotificationLevel::Error,
+                                    strings::SAVE_FAILED,
+                                )
+                                .with_body(reason.clone()),
+                            },
+                        ));
+                    }
+                    _ => {}
+                }
+                self.presets.update(
+                    msg,
+                    rs,
+                    &mut orders_local.proxy(AnalyticsMsg::FilterPresetsMsg),
+                );
+            }
+            AnalyticsMsg::ShiftsFetched(shifts) => {
+                self.shifts = Some(shifts);
+            }
+            AnalyticsMsg::ShiftsFetchFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::SHIFTS_FETCH_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+            AnalyticsMsg::RevenueFetched(by_method, by_category) => {
+                self.revenue = Some((by_method, by_category));
+            }
+            AnalyticsMsg::SetTimeTravel(enabled) => {
+                self.as_of = Some(self.end_date).filter(|_| enabled);
+                self.update_url();
+                self.fetch_snapshot(&mut orders_local);
+            }
+            AnalyticsMsg::SetAsOfDate(input) => {
+                if let Ok(date) = NaiveDate::parse_from_str(&input, DATE_INPUT_FMT) {
+                    self.as_of = Some(date);
+                    self.update_url();
+                    self.fetch_snapshot(&mut orders_local);
                 }
             }
+            AnalyticsMsg::SnapshotFetched(snapshot) => {
+                self.snapshot = Some(snapshot);
+            }
+            AnalyticsMsg::SnapshotFetchFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::SNAPSHOT_FETCH_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+            AnalyticsMsg::RevenueFetchFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::REVENUE_FETCH_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
 
             AnalyticsMsg::ResFetched(_) => {}
-            AnalyticsMsg::ResMarkDirty(_) => {}
+            AnalyticsMsg::ResMarkDirty(_) | AnalyticsMsg::Reload => {}
         }
 
         Ok(())
     }
 
     pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
-        let _res = match Res::acquire_now(rs) {
+        let res = match Res::acquire_now(rs) {
             Ok(res) => res,
-            Err(_) => return Loading::view(),
+            Err(_) => return Loading::with_retry(Loading::skeleton_charts()),
         };
 
         if self.charts_job.is_some() {
             return div![
                 C![C.accounting_page],
-                h2!["Laddar statistik..."],
-                Loading::view(),
+                h2![strings::LOADING_ANALYTICS],
+                Loading::spinner(),
+                Loading::skeleton_charts(),
             ];
         }
 
+        let fmt_date = |date: NaiveDate| date.format(DATE_INPUT_FMT).to_string();
+        let time_travel = self.as_of.is_some();
+
         div![
             C![C.accounting_page],
             div![
+                C![C.analytics_controls],
                 input![
                     attrs! {At::Type => "date"},
-                    attrs! {At::Value => self.start_date.format(DATE_INPUT_FMT).to_string()},
+                    attrs! {At::Value => fmt_date(self.start_date)},
                     input_ev(Ev::Input, |input| AnalyticsMsg::SetStartDate(input)),
                 ],
                 input![
                     attrs! {At::Type => "date"},
-                    attrs! {At::Value => self.end_date.format(DATE_INPUT_FMT).to_string()},
+                    attrs! {At::Value => fmt_date(self.end_date)},
                     input_ev(Ev::Input, |input| AnalyticsMsg::SetEndDate(input)),
                 ],
+                select![
+                    option![
+                        attrs! {At::Value => ""},
+                        attrs! {At::Selected => self.period.is_none().as_at_value()},
+                        strings::PERIOD_CUSTOM,
+                    ],
+                    PeriodKind::ALL.iter().map(|&kind| {
+                        let selected = self.period.map(|period| period.kind()) == Some(kind);
+                        option![
+                            attrs! {At::Value => kind},
+                            attrs! {At::Selected => selected.as_at_value()},
+                            period_kind_name(kind),
+                        ]
+                    }),
+                    input_ev(Ev::Change, AnalyticsMsg::SetPeriodKind),
+                ],
+                if let Some(period) = self.period {
+                    span![
+                        button![
+                            simple_ev(Ev::Click, AnalyticsMsg::PreviousPeriod),
+                            attrs! {At::Title => strings::PREVIOUS_PERIOD},
+                            "<",
+                        ],
+                        span![C![C.analytics_period], period.to_string()],
+                        button![
+                            simple_ev(Ev::Click, AnalyticsMsg::NextPeriod),
+                            attrs! {At::Title => strings::NEXT_PERIOD},
+                            ">",
+                        ],
+                    ]
+                } else {
+                    empty![]
+                },
+                select![
+                    option![
+                        attrs! {At::Value => ""},
+                        attrs! {At::Selected => self.cashier.is_none().as_at_value()},
+                        strings::ALL_CASHIERS,
+                    ],
+                    cashiers(&res.daily_sales).into_iter().map(|cashier| {
+                        let selected = self.cashier.as_ref() == Some(cashier);
+                        option![
+                            attrs! {At::Value => cashier},
+                            attrs! {At::Selected => selected.as_at_value()},
+                            cashier,
+                        ]
+                    }),
+                    input_ev(Ev::Change, AnalyticsMsg::SetCashier),
+                ],
+                self.presets
+                    .view(
+                        rs,
+                        self.current_filter(local_date(Utc::now(), *res.reporting_timezone)),
+                    )
+                    .map_msg(AnalyticsMsg::FilterPresetsMsg),
+                label![
+                    input![
+                        attrs! {At::Type => "checkbox"},
+                        attrs! {At::Checked => self.as_of.is_some().as_at_value()},
+                        ev(Ev::Change, move |_| AnalyticsMsg::SetTimeTravel(
+                            !time_travel
+                        )),
+                    ],
+                    strings::TIME_TRAVEL,
+                ],
+                if let Some(as_of) = self.as_of {
+                    input![
+                        attrs! {At::Type => "date"},
+                        attrs! {At::Value => fmt_date(as_of)},
+                        attrs! {At::Title => strings::AS_OF_HINT},
+                        input_ev(Ev::Input, |input| AnalyticsMsg::SetAsOfDate(input)),
+                    ]
+                } else {
+                    empty![]
+                },
                 if self.charts_job.is_some() {
                     button![
                         C![C.wide_button],
@@ -144,40 +479,318 @@ impl AnalyticsPage {
                             div![],
                         ],
                         attrs! { At::Disabled => true },
-                        "Beräkna Statistik",
+                        strings::COMPUTE_ANALYTICS,
                     ]
                 } else {
                     button![
                         C![C.wide_button],
                         simple_ev(Ev::Click, AnalyticsMsg::ComputeCharts),
-                        "Beräkna Statistik",
+                        strings::COMPUTE_ANALYTICS,
                     ]
                 },
             ],
-            div![self.charts.values().map(|chart| chart.clone())],
+            if res.goals.is_empty() {
+                empty![]
+            } else {
+                div![
+                    C![C.analytics_goals],
+                    h2![strings::GOALS],
+                    res.goals.iter().map(view_goal_progress),
+                ]
+            },
+            self.view_snapshot(&res),
+            self.view_shifts(*res.reporting_timezone),
+            self.view_revenue(),
+            div![self.charts.iter().cloned()],
         ]
         .map_msg(|msg| Msg::AnalyticsMsg(msg))
     }
 
+    /// The filters of the page, for saving them as a preset
+    fn current_filter(&self, today: NaiveDate) -> PresetFilter {
+        PresetFilter {
+            dates: PresetDates::from_range(Some(self.start_date), Some(self.end_date), today),
+            cashier: self.cashier.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn apply_preset(&mut self, filter: PresetFilter, today: NaiveDate) {
+        match filter.dates.period(today) {
+            Some(period) => self.set_period(period),
+            None => {
+                let (from, to) = filter.dates.range(today);
+                self.start_date = from.unwrap_or(self.start_date);
+                self.end_date = to.unwrap_or(self.end_date);
+                self.period = None;
+            }
+        }
+        self.cashier = filter.cashier;
+        self.update_url();
+    }
+
+    fn set_period(&mut self, period: ReportingPeriod) {
+        self.period = Some(period);
+        self.start_date = period.first_day();
+        self.end_date = period.last_day();
+    }
+
+    fn fetch_shifts(&mut self, orders: &mut impl Orders<AnalyticsMsg>) {
+        self.shifts = None;
+        let url = format!(
+            "/api/analytics/shifts?from={}&to={}",
+            self.start_date.format(DATE_INPUT_FMT),
+            self.end_date.format(DATE_INPUT_FMT),
+        );
+        orders.perform_cmd(async move {
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(shifts) => AnalyticsMsg::ShiftsFetched(shifts),
+                Err(e) => AnalyticsMsg::ShiftsFetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    fn fetch_revenue(&mut self, orders: &mut impl Orders<AnalyticsMsg>) {
+        self.revenue = None;
+        let query = format!(
+            "from={}&to={}",
+            self.start_date.format(DATE_INPUT_FMT),
+            self.end_date.format(DATE_INPUT_FMT),
+        );
+        orders.perform_cmd(async move {
+            let result: Result<_, FetchError> = async {
+                let by_method = get_with_retry(format!("/api/analytics/payment_methods?{}", query))
+                    .await?
+                    .json()
+                    .await?;
+                let by_category = get_with_retry(format!("/api/analytics/categories?{}", query))
+                    .await?
+                    .json()
+                    .await?;
+                Ok((by_method, by_category))
+            }
+            .await;
+            match result {
+                Ok((by_method, by_category)) => {
+                    AnalyticsMsg::RevenueFetched(by_method, by_category)
+                }
+                Err(e) => AnalyticsMsg::RevenueFetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    fn fetch_snapshot(&mut self, orders: &mut impl Orders<AnalyticsMsg>) {
+        self.snapshot = None;
+        let as_of = match self.as_of {
+            Some(as_of) => as_of.format(DATE_INPUT_FMT).to_string(),
+            None => return,
+        };
+        orders.perform_cmd(async move {
+            let result: Result<_, FetchError> = async {
+                let items = get_with_retry(format!("/api/inventory/items?as_of={}", as_of))
+                    .await?
+                    .json()
+                    .await?;
+                let accounts = get_with_retry(format!("/api/book_accounts?as_of={}", as_of))
+                    .await?
+                    .json()
+                    .await?;
+                Ok(Snapshot { items, accounts })
+            }
+            .await;
+            match result {
+                Ok(snapshot) => AnalyticsMsg::SnapshotFetched(Rc::new(snapshot)),
+                Err(e) => AnalyticsMsg::SnapshotFetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    /// The stock and the balances at the end of `as_of` next to the current ones, of the items
+    /// and accounts which had or have any
+    fn view_snapshot(&self, res: &Res) -> Node<AnalyticsMsg> {
+        let (as_of, snapshot) = match (self.as_of, &self.snapshot) {
+            (Some(as_of), Some(snapshot)) => (as_of, snapshot),
+            _ => return empty![],
+        };
+
+        let mut items: Vec<&InventoryItemStock> = snapshot
+            .items
+            .values()
+            .filter(|item| {
+                let now = res.inventory.get(&item.id).map_or(0, |item| item.stock);
+                item.stock != 0 || now != 0
+            })
+            .collect();
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut accounts: Vec<&BookAccount> = snapshot.accounts.values().collect();
+        accounts.sort_by(|a, b| a.name.cmp(&b.name));
+
+        div![
+            C![C.analytics_snapshot],
+            h2![format!(
+                "{} {}",
+                strings::SNAPSHOT_TITLE,
+                as_of.format(DATE_INPUT_FMT)
+            )],
+            p![C![C.analytics_shifts_hint], strings::SNAPSHOT_HINT],
+            div![
+                C![C.analytics_snapshot_tables],
+                table![
+                    C![C.analytics_shifts_table],
+                    tr![th![strings::ITEM], th![strings::THEN], th![strings::NOW],],
+                    items.into_iter().map(|item| {
+                        let now = res.inventory.get(&item.id).map_or(0, |item| item.stock);
+                        tr![
+                            td![&item.name],
+                            td![item.stock.to_string()],
+                            td![now.to_string()],
+                        ]
+                    }),
+                ],
+                table![
+                    C![C.analytics_shifts_table],
+                    tr![
+                        th![strings::ACCOUNT_NAME],
+                        th![strings::THEN],
+                        th![strings::NOW],
+                    ],
+                    accounts.into_iter().map(|account| {
+                        let now = res
+                            .book_accounts
+                            .get(&account.id)
+                            .map(|account| account.balance)
+                            .unwrap_or_default();
+                        tr![
+                            td![&account.name],
+                            td![format!("{}:-", account.balance)],
+                            td![format!("{}:-", now)],
+                        ]
+                    }),
+                ],
+            ],
+        ]
+    }
+
+    /// The revenue between the dates by payment method and by category, of all cashiers
+    fn view_revenue(&self) -> Node<AnalyticsMsg> {
+        let (by_method, by_category) = match &self.revenue {
+            Some(revenue) => revenue,
+            None => return empty![],
+        };
+
+        let by_method = by_method.iter().map(|sales| {
+            let method = payment_method_name(sales.method).to_string();
+            (sales.day, method, i64::from(sales.revenue))
+        });
+        let by_category = by_category.iter().map(|sales| {
+            let category = match &sales.category {
+                Some(category) => category.clone(),
+                None => strings::NO_CATEGORY.to_string(),
+            };
+            (sales.day, category, i64::from(sales.revenue))
+        });
+
+        div![
+            C![C.analytics_revenue],
+            h2![strings::REVENUE_BY_PAYMENT_METHOD],
+            view_breakdown(by_method, self.start_date, self.end_date),
+            h2![strings::REVENUE_BY_CATEGORY],
+            view_breakdown(by_category, self.start_date, self.end_date),
+        ]
+    }
+
+    /// The shifts of the selected cashier, or of everyone, latest first
+    fn view_shifts(&self, tz: Tz) -> Node<AnalyticsMsg> {
+        let shifts: Vec<&CashierShift> = match &self.shifts {
+            Some(shifts) => shifts
+                .iter()
+                .filter(|shift| self.cashier.as_ref().map_or(true, |c| &shift.cashier == c))
+                .collect(),
+            None => return empty![],
+        };
+
+        let fmt_duration = |duration: Duration| {
+            format!(
+                "{}:{:02}",
+                duration.num_hours(),
+                duration.num_minutes() % 60
+            )
+        };
+
+        div![
+            C![C.analytics_shifts],
+            h2![strings::SHIFTS],
+            p![C![C.analytics_shifts_hint], strings::SHIFTS_HINT],
+            if shifts.is_empty() {
+                p![strings::NO_SHIFTS]
+            } else {
+                table![
+                    C![C.analytics_shifts_table],
+                    tr![
+                        th![strings::CASHIER],
+                        th![strings::SHIFT_TIME],
+                        th![strings::SHIFT_DURATION],
+                        th![strings::SHIFT_TRANSACTIONS],
+                        th![strings::TRANSACTIONS_PER_HOUR],
+                        th![strings::AVERAGE_BASKET],
+                        th![strings::SHIFT_REVENUE],
+                    ],
+                    shifts.iter().map(|shift| {
+                        let first_sale = shift.first_sale.with_timezone(&tz);
+                        let last_sale = shift.last_sale.with_timezone(&tz);
+                        tr![
+                            td![&shift.cashier],
+                            td![format!(
+                                "{} {}–{}",
+                                first_sale.format("%Y-%m-%d"),
+                                first_sale.format("%H:%M"),
+                                last_sale.format("%H:%M"),
+                            )],
+                            td![fmt_duration(shift.duration())],
+                            td![shift.transaction_count.to_string()],
+                            td![format!("{:.1}", shift.transactions_per_hour())],
+                            td![format!("{}:-", shift.average_basket())],
+                            td![format!("{}:-", shift.revenue)],
+                        ]
+                    }),
+                ]
+            },
+        ]
+    }
+
     fn compute_charts(&mut self, res: &Res, orders: &mut impl Orders<AnalyticsMsg>) {
         if self.charts_job.is_some() {
             return;
         }
 
-        self.charts = Rc::new(HashMap::new());
+        self.charts = Rc::new(vec![]);
 
-        let inventory_by_week = calculate_inventory_by_week(&res.transactions);
-        let inventory = res.inventory.clone();
+        let sales = made_by(res.daily_sales, self.cashier.as_ref());
         let start_date = self.start_date;
         let end_date = self.end_date;
 
-        self.charts_job = Some(orders.perform_cmd_with_handle(async move {
-            let mut charts = HashMap::new();
-            for (id, item) in inventory {
-                let chart =
-                    plot_sales_over_time(&inventory_by_week, start_date, end_date, id, item.name);
+        // items which have never been sold get no chart
+        let items: Vec<(InventoryItemId, String, Vec<(String, i64)>)> = res
+            .item_totals
+            .iter()
+            .filter(|totals| totals.sold > 0)
+            .filter_map(|totals| {
+                let item = res.inventory.get(&totals.item_id)?;
+                let modifiers = modifier_sales(res.modifier_totals, res.modifiers, item.id);
+                Some((item.id, item.name.clone(), modifiers))
+            })
+            .collect();
 
-                charts.insert(id, chart);
+        self.charts_job = Some(orders.perform_cmd_with_handle(async move {
+            let mut charts = vec![];
+            for (id, name, modifiers) in items {
+                let chart = plot_sales_over_time(&sales, start_date, end_date, id, name);
+                charts.push(chart);
+                if !modifiers.is_empty() {
+                    charts.push(view_modifier_sales(&modifiers));
+                }
 
                 timeout(10, || ()).await
             }
@@ -186,99 +799,270 @@ impl AnalyticsPage {
     }
 }
 
-fn week_date(week: IsoWeek) -> DateTime<Utc> {
-    let naive = NaiveDate::from_isoywd(week.year(), week.week(), Weekday::Mon).and_hms(0, 0, 0);
-    DateTime::from_utc(naive, Utc)
+pub fn period_kind_name(kind: PeriodKind) -> Text {
+    match kind {
+        PeriodKind::Day => strings::PERIOD_DAY,
+        PeriodKind::Week => strings::PERIOD_WEEK,
+        PeriodKind::Month => strings::PERIOD_MONTH,
+        PeriodKind::Semester => strings::PERIOD_SEMESTER,
+    }
 }
 
-fn calculate_inventory_by_week(
-    transactions_unsorted: &[Transaction],
-) -> BTreeMap<IsoWeek, HashMap<InventoryItemId, i32>> {
-    let mut transactions = BTreeMap::new();
+fn payment_method_name(method: PaymentMethod) -> Text {
+    match method {
+        PaymentMethod::Cash => strings::PAYMENT_CASH,
+        PaymentMethod::Card => strings::PAYMENT_CARD,
+        PaymentMethod::Tillgodo => strings::PAYMENT_TILLGODO,
+        PaymentMethod::Other => strings::PAYMENT_OTHER,
+    }
+}
 
-    for transaction in transactions_unsorted.iter() {
-        transactions
-            .entry(transaction.time.iso_week())
-            .or_insert(vec![])
-            .push(transaction.clone());
+/// The weeks from `start_date` up to and including `end_date`
+fn weeks(start_date: NaiveDate, end_date: NaiveDate) -> BTreeSet<IsoWeek> {
+    let mut weeks = BTreeSet::new();
+    let mut day = start_date;
+    while day <= end_date {
+        weeks.insert(day.iso_week());
+        day += Duration::days(7);
+    }
+    if start_date <= end_date {
+        weeks.insert(end_date.iso_week());
+    }
+    weeks
+}
+
+/// The revenue of every part between `start_date` and `end_date` per ISO week, in the order of
+/// the parts
+fn weekly_revenue<K: Ord>(
+    revenue: impl Iterator<Item = (NaiveDate, K, i64)>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> BTreeMap<K, Vec<i64>> {
+    let weeks: Vec<IsoWeek> = weeks(start_date, end_date).into_iter().collect();
+    let mut parts: BTreeMap<K, Vec<i64>> = BTreeMap::new();
+    for (day, part, amount) in revenue {
+        if let Ok(week) = weeks.binary_search(&day.iso_week()) {
+            parts.entry(part).or_insert_with(|| vec![0; weeks.len()])[week] += amount;
+        }
     }
+    parts
+}
 
-    let mut inventory = HashMap::new();
-    let mut result = BTreeMap::new();
+/// The revenue split into parts per week, next to the share of each part of the total
+fn view_breakdown(
+    revenue: impl Iterator<Item = (NaiveDate, String, i64)>,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> Node<AnalyticsMsg> {
+    let labels = weeks(start_date, end_date)
+        .into_iter()
+        .map(|week| format!("{} w{:.02}", week.year(), week.week()));
+    let parts = weekly_revenue(revenue, start_date, end_date);
 
-    for (week, weeks_transactions) in transactions.iter() {
-        for transaction in weeks_transactions.iter() {
-            for bundle in transaction.bundles.iter() {
-                for (item_id, item_count) in bundle.item_ids.iter() {
-                    *inventory.entry(*item_id).or_default() += *item_count as i32 * bundle.change;
-                }
-            }
+    let over_time = parts.iter().fold(
+        Chart::new(ChartKind::StackedBar)
+            .unit(Unit::Currency)
+            .labels(labels),
+        |chart, (part, weekly)| chart.series(part, weekly.clone()),
+    );
+    let shares = parts.iter().fold(
+        Chart::new(ChartKind::Pie).unit(Unit::Currency),
+        |chart, (part, weekly)| chart.series(part, vec![weekly.iter().sum()]),
+    );
+
+    div![C![C.analytics_breakdown], over_time.view(), shares.view()]
+}
+
+/// How many of `item_id` have been sold with each of its modifiers, ever, most sold first
+fn modifier_sales(
+    totals: &[ModifierTotals],
+    modifiers: &HashMap<ItemModifierId, ItemModifier>,
+    item_id: InventoryItemId,
+) -> Vec<(String, i64)> {
+    // the totals are sorted by the server
+    totals
+        .iter()
+        .filter(|totals| totals.item_id == item_id)
+        .filter_map(|totals| {
+            let modifier = modifiers.get(&totals.modifier_id)?;
+            Some((modifier.name.clone(), totals.sold))
+        })
+        .collect()
+}
+
+fn view_modifier_sales(modifiers: &[(String, i64)]) -> Node<AnalyticsMsg> {
+    let chart = modifiers
+        .iter()
+        .fold(Chart::new(ChartKind::Pie), |chart, (name, sold)| {
+            chart.series(name, vec![*sold])
+        });
+    div![
+        C![C.chart_modifier_sales],
+        h3![strings::MODIFIER_SALES],
+        chart.view(),
+    ]
+}
+
+/// Every user who has sold something, in alphabetical order
+fn cashiers(sales: &[DailySales]) -> BTreeSet<&UserName> {
+    sales
+        .iter()
+        .filter_map(|sales| sales.created_by.as_ref())
+        .collect()
+}
+
+/// The sales made by `cashier`, or all of them if `None`
+fn made_by(sales: &[DailySales], cashier: Option<&UserName>) -> Vec<DailySales> {
+    sales
+        .iter()
+        .filter(|sales| cashier.is_none() || sales.created_by.as_ref() == cashier)
+        .cloned()
+        .collect()
+}
+
+/// The number of `item_id` sold per ISO week between `start_date` and `end_date`, inclusive.
+///
+/// Every week in the range is included, also those without sales.
+fn weekly_sales(
+    sales: &[DailySales],
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+    item_id: InventoryItemId,
+) -> Vec<(IsoWeek, u32)> {
+    let mut weeks: BTreeMap<IsoWeek, u32> = weeks(start_date, end_date)
+        .into_iter()
+        .map(|week| (week, 0))
+        .collect();
+
+    for sales in sales {
+        if sales.item_id == item_id && sales.day >= start_date && sales.day <= end_date {
+            *weeks.entry(sales.day.iso_week()).or_default() += sales.sold as u32;
         }
-        result.insert(*week, inventory.clone());
     }
 
-    result
+    weeks.into_iter().collect()
 }
 
+/// The weekly sales of `item_id`, stacked by who sold them
 fn plot_sales_over_time(
-    inventory_by_week: &BTreeMap<IsoWeek, HashMap<InventoryItemId, i32>>,
-    start_date: DateTime<Utc>,
-    end_date: DateTime<Utc>,
+    sales: &[DailySales],
+    start_date: NaiveDate,
+    end_date: NaiveDate,
     item_id: InventoryItemId,
     name: String,
 ) -> Node<AnalyticsMsg> {
-    let mut iter = inventory_by_week
-        .iter()
-        .map(|(week, inventory)| {
-            let stock = *inventory.get(&item_id).unwrap_or(&0);
-            (week_date(*week), stock)
-        })
-        .filter(|&(date, _)| date >= start_date)
-        .filter(|&(date, _)| date <= end_date);
-
-    let mut last_weeks_stock = iter.next().map(|(_, s)| s).unwrap_or(0);
-    let points: Vec<(String, u32)> = iter
-        .map(|(date, this_weeks_stock)| {
-            let sales = last_weeks_stock - this_weeks_stock;
-            last_weeks_stock = this_weeks_stock;
-            (date, sales)
-        })
-        .filter(|(_, sales)| *sales >= 0)
-        .map(|(date, sales)| {
-            let datefmt = format!("{} w{:.02}", date.year(), date.iso_week().week());
-            (datefmt, sales as u32)
-        })
+    let weeks: Vec<String> = weekly_sales(sales, start_date, end_date, item_id)
+        .into_iter()
+        .map(|(week, _)| format!("{} w{:.02}", week.year(), week.week()))
         .collect();
 
-    plot(name, &points)
+    let mut by_cashier: BTreeMap<Option<&UserName>, Vec<DailySales>> = BTreeMap::new();
+    for sales in sales.iter().filter(|sales| sales.item_id == item_id) {
+        let cashier = by_cashier.entry(sales.created_by.as_ref()).or_default();
+        cashier.push(sales.clone());
+    }
+
+    let kind = if by_cashier.len() > 1 {
+        ChartKind::StackedBar
+    } else {
+        ChartKind::Bar
+    };
+    let chart =
+        by_cashier
+            .iter()
+            .fold(Chart::new(kind).labels(weeks), |chart, (cashier, sales)| {
+                let sold = weekly_sales(sales, start_date, end_date, item_id)
+                    .into_iter()
+                    .map(|(_, sold)| i64::from(sold))
+                    .collect();
+                match cashier {
+                    Some(cashier) => chart.series(cashier, sold),
+                    None => chart.series(strings::UNKNOWN, sold),
+                }
+            });
+
+    div![h2![name], chart.view()]
 }
 
-fn plot<K>(name: String, points: &[(K, u32)]) -> Node<AnalyticsMsg>
-where
-    K: std::fmt::Display,
-{
-    let y_max = points.iter().map(|(_, v)| *v).max().unwrap();
-    div![
-        h2![name],
-        div![
-            C![C.chart_histogram],
-            points
-                .iter()
-                .map(|(k, v)| {
-                    let percentage = if y_max == 0 { 0 } else { v * 100 / y_max };
+#[cfg(test)]
+mod test {
+    use super::*;
 
-                    div![
-                        C![C.chart_histogram_col],
-                        div![style!(St::FlexBasis => format!("{}%", 100 - percentage)),],
-                        div![
-                            C![C.chart_histogram_col_line, C.chart_col_tooltip],
-                            style!(St::FlexBasis => format!("{}%", percentage)),
-                            span![C![C.chart_col_tooltiptext], format!("{}", v),],
-                        ],
-                        div![C![C.chart_histogram_col_label], format!("{}", k),],
-                    ]
-                })
-                .collect::<Vec<_>>()
-        ],
-    ]
+    /// `sold` of item 1 sold by `cashier` on the given day of june 2021
+    fn sales(day: u32, sold: i64, cashier: &str) -> DailySales {
+        DailySales {
+            day: NaiveDate::from_ymd(2021, 6, day),
+            created_by: Some(cashier.to_string()),
+            item_id: 1,
+            sold,
+        }
+    }
+
+    #[test]
+    fn test_weekly_sales() {
+        let sales = vec![
+            sales(2, 3, "tux"),  // week 22
+            sales(8, 5, "kim"),  // week 23
+            sales(9, 1, "tux"),  // week 23
+            sales(22, 4, "kim"), // week 25
+            DailySales {
+                item_id: 2,
+                ..sales(3, 7, "tux")
+            },
+        ];
+
+        let start = NaiveDate::from_ymd(2021, 6, 1);
+        let end = NaiveDate::from_ymd(2021, 6, 30);
+        let weekly: Vec<(u32, u32)> = weekly_sales(&sales, start, end, 1)
+            .into_iter()
+            .map(|(week, sold)| (week.week(), sold))
+            .collect();
+        assert_eq!(weekly, [(22, 3), (23, 6), (24, 0), (25, 4), (26, 0)]);
+
+        // sales outside of the range are left out
+        let end = NaiveDate::from_ymd(2021, 6, 8);
+        let weekly: Vec<(u32, u32)> = weekly_sales(&sales, start, end, 1)
+            .into_iter()
+            .map(|(week, sold)| (week.week(), sold))
+            .collect();
+        assert_eq!(weekly, [(22, 3), (23, 5)]);
+
+        assert!(weekly_sales(&sales, start, end, 3)
+            .iter()
+            .all(|&(_, sold)| sold == 0));
+        assert!(weekly_sales(&sales, end, start, 1).is_empty());
+    }
+
+    #[test]
+    fn test_weekly_revenue() {
+        let day = |day| NaiveDate::from_ymd(2021, 6, day);
+        let revenue = vec![
+            (day(2), "Kontant", 500),  // week 22
+            (day(8), "Kort", 1000),    // week 23
+            (day(9), "Kontant", 700),  // week 23
+            (day(28), "Kontant", 300), // week 26, after the end
+        ];
+
+        let weekly = weekly_revenue(revenue.into_iter(), day(1), day(20));
+        let weekly: Vec<(&str, Vec<i64>)> = weekly.into_iter().collect();
+        assert_eq!(
+            weekly,
+            [("Kontant", vec![500, 700, 0]), ("Kort", vec![0, 1000, 0])]
+        );
+    }
+
+    #[test]
+    fn test_cashiers() {
+        let sales = vec![sales(1, 1, "tux"), sales(2, 1, "kim"), sales(3, 1, "tux")];
+
+        let names: Vec<&str> = cashiers(&sales)
+            .into_iter()
+            .map(|name| name.as_str())
+            .collect();
+        assert_eq!(names, ["kim", "tux"]);
+
+        let tux = "tux".to_string();
+        assert_eq!(made_by(&sales, Some(&tux)).len(), 2);
+        assert_eq!(made_by(&sales, None).len(), 3);
+    }
 }
@@ -0,0 +1,306 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::page::loading::Loading;
+use crate::page::UrlParams;
+use crate::strings;
+use crate::util::base;
+use crate::util::fetch::get_with_retry;
+use crate::util::{simple_ev, DATE_INPUT_FMT};
+use chrono::Local;
+use js_sys::encode_uri_component;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::currency::NonNegativeCurrency;
+use strecklistan_api::public::PublicBalance;
+use strecklistan_api::swish::{SwishDeposit, SwishPayment, SwishPaymentId, SwishStatus};
+
+/// How often a pending Swish payment is checked
+const SWISH_POLL_MS: u32 = 3000;
+
+#[derive(Clone, Debug)]
+pub enum BalanceMsg {
+    Reload,
+    Fetched(PublicBalance),
+
+    /// The code of the card is unknown, or the request failed
+    FetchFailed(String),
+
+    SetAmount(String),
+    SwishDeposit,
+
+    /// The payment was made, and is waiting to be approved in the Swish app
+    SwishStarted(SwishPayment),
+    SwishUpdated(SwishPayment),
+
+    /// The payment couldn't be made
+    SwishFailed(String),
+    PollSwish,
+}
+
+/// The page opened by the QR code on a balance card, showing the balance of the member without
+/// logging in
+#[derive(Default)]
+pub struct BalancePage {
+    /// The code of the card
+    card: Option<String>,
+    balance: Option<PublicBalance>,
+    error: Option<String>,
+
+    amount_input: String,
+
+    /// The latest Swish deposit, which is polled while it's pending
+    swish: Option<SwishPayment>,
+    swish_error: Option<String>,
+    swish_poll: Option<StreamHandle>,
+}
+
+impl BalancePage {
+    /// Read the code of the card from the `card` url parameter, and the Swish deposit which the
+    /// Swish app returned from, from the `swish` parameter
+    pub fn set_params(&mut self, params: &UrlParams, orders: &mut impl Orders<Msg>) {
+        let card = params.get("card").map(str::to_string);
+        if card != self.card {
+            self.card = card;
+            self.balance = None;
+            self.error = None;
+            self.swish = None;
+            self.fetch(orders);
+        }
+
+        if let (Some(payment_id), None) = (params.get("swish"), &self.swish) {
+            self.poll_swish(payment_id.to_string(), orders);
+        }
+    }
+
+    fn poll_swish(&mut self, payment_id: SwishPaymentId, orders: &mut impl Orders<Msg>) {
+        let url = format!("/api/public/swish/{}", payment_id);
+        orders.proxy(Msg::BalanceMsg).perform_cmd(async move {
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(payment) => Some(BalanceMsg::SwishUpdated(payment)),
+                // it's polled again shortly
+                Err(e) => {
+                    error!("Failed to fetch the Swish payment", e);
+                    None
+                }
+            }
+        });
+        if self.swish_poll.is_none() {
+            self.swish_poll = Some(
+                orders
+                    .proxy(Msg::BalanceMsg)
+                    .stream_with_handle(streams::interval(SWISH_POLL_MS, || BalanceMsg::PollSwish)),
+            );
+        }
+    }
+
+    fn fetch(&self, orders: &mut impl Orders<Msg>) {
+        let card = match &self.card {
+            Some(card) => card.clone(),
+            None => return,
+        };
+        orders.proxy(Msg::BalanceMsg).perform_cmd(async move {
+            let url = format!("/api/public/balance/{}", card);
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(balance) => BalanceMsg::Fetched(balance),
+                Err(e) => BalanceMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn update(&mut self, msg: BalanceMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            BalanceMsg::Reload => {
+                self.error = None;
+                self.fetch(orders);
+            }
+            BalanceMsg::Fetched(balance) => self.balance = Some(balance),
+            BalanceMsg::FetchFailed(reason) => self.error = Some(reason),
+            BalanceMsg::SetAmount(input) => self.amount_input = input,
+            BalanceMsg::SwishDeposit => {
+                let (card, amount) = match (&self.card, self.amount()) {
+                    (Some(card), Some(amount)) => (card.clone(), amount),
+                    _ => return,
+                };
+                self.swish_error = None;
+
+                // not retried, since every request is a new payment
+                orders.proxy(Msg::BalanceMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new(format!("/api/public/balance/{}/swish", card))
+                            .method(Method::Post)
+                            .json(&SwishDeposit { amount })?
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(payment) => BalanceMsg::SwishStarted(payment),
+                        Err(e) => BalanceMsg::SwishFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            BalanceMsg::SwishStarted(payment) => {
+                self.amount_input.clear();
+                let payment_id = payment.id.clone();
+                if let (Some(token), Some(card)) = (&payment.token, &self.card) {
+                    open_swish(token, card, &payment_id);
+                }
+                self.swish = Some(payment);
+                self.poll_swish(payment_id, orders);
+            }
+            BalanceMsg::SwishUpdated(payment) => {
+                if !payment.status.is_pending() {
+                    self.swish_poll = None;
+                }
+                if payment.status == SwishStatus::Paid {
+                    self.fetch(orders);
+                }
+                self.swish = Some(payment);
+            }
+            BalanceMsg::SwishFailed(reason) => {
+                self.swish_poll = None;
+                self.swish_error = Some(reason);
+            }
+            BalanceMsg::PollSwish => {
+                if let Some(payment) = &self.swish {
+                    let payment_id = payment.id.clone();
+                    self.poll_swish(payment_id, orders);
+                }
+            }
+        }
+    }
+
+    /// The amount to deposit, if a valid one has been entered
+    fn amount(&self) -> Option<NonNegativeCurrency> {
+        self.amount_input
+            .trim()
+            .parse()
+            .ok()
+            .filter(|&amount| amount != NonNegativeCurrency::default())
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        let balance = match (&self.balance, &self.error, &self.card) {
+            (Some(balance), _, _) => balance,
+            (None, Some(_), _) | (None, None, None) => {
+                return div![C![C.balance_page], p![strings::UNKNOWN_BALANCE_CARD]];
+            }
+            (None, None, Some(_)) => return Loading::view(),
+        };
+
+        div![
+            C![C.balance_page],
+            h1![&balance.first_name],
+            div![C![C.balance_amount], format!("{}:-", balance.balance)],
+            span![strings::BALANCE_LEFT],
+            if balance.deposits.is_empty() {
+                empty![]
+            } else {
+                div![
+                    C![C.balance_deposits],
+                    h2![strings::LATEST_DEPOSITS],
+                    balance.deposits.iter().map(|deposit| {
+                        div![
+                            C![C.balance_deposit],
+                            span![deposit
+                                .time
+                                .with_timezone(&Local)
+                                .format(DATE_INPUT_FMT)
+                                .to_string()],
+                            span![format!("{}:-", deposit.amount)],
+                        ]
+                    }),
+                ]
+            },
+            if balance.swish {
+                self.view_swish()
+            } else {
+                empty![]
+            },
+        ]
+    }
+
+    fn view_swish(&self) -> Node<Msg> {
+        let form = || {
+            div![
+                C![C.balance_swish_form],
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {
+                        At::Type => "text",
+                        At::from("inputmode") => "decimal",
+                        At::Placeholder => strings::SWISH_AMOUNT,
+                        At::Value => self.amount_input,
+                    },
+                    input_ev(Ev::Input, |input| Msg::BalanceMsg(BalanceMsg::SetAmount(
+                        input
+                    ))),
+                ],
+                button![
+                    C![C.wide_button, C.border_on_focus],
+                    if self.amount().is_some() {
+                        attrs! {}
+                    } else {
+                        attrs! {At::Disabled => true}
+                    },
+                    simple_ev(Ev::Click, Msg::BalanceMsg(BalanceMsg::SwishDeposit)),
+                    strings::SWISH_DEPOSIT,
+                ],
+            ]
+        };
+
+        div![
+            C![C.balance_swish],
+            match &self.swish {
+                Some(payment) if payment.status.is_pending() => div![
+                    p![strings::SWISH_WAITING],
+                    match (&payment.token, &self.card) {
+                        (Some(token), Some(card)) => {
+                            let (token, card, payment_id) =
+                                (token.clone(), card.clone(), payment.id.clone());
+                            button![
+                                C![C.wide_button, C.border_on_focus],
+                                ev(Ev::Click, move |_| open_swish(&token, &card, &payment_id)),
+                                strings::OPEN_SWISH,
+                            ]
+                        }
+                        _ => empty![],
+                    },
+                ],
+                Some(payment) if payment.status == SwishStatus::Paid =>
+                    div![p![C![C.balance_swish_paid], strings::SWISH_PAID], form(),],
+                Some(_) => div![p![strings::SWISH_NOT_PAID], form()],
+                None => form(),
+            },
+            match &self.swish_error {
+                Some(_) => p![strings::SWISH_FAILED],
+                None => empty![],
+            },
+        ]
+    }
+}
+
+/// Open the payment in the Swish app, which returns to this page with the payment in the url
+fn open_swish(token: &str, card: &str, payment_id: &str) {
+    let origin = window().location().origin().unwrap_or_default();
+    let return_url = format!(
+        "{}{}?card={}&swish={}",
+        origin,
+        base::page_href("/balance"),
+        card,
+        payment_id
+    );
+    let return_url: String = encode_uri_component(&return_url).into();
+    let url = format!(
+        "swish://paymentrequest?token={}&callbackurl={}",
+        token, return_url
+    );
+    if let Err(e) = window().location().set_href(&url) {
+        error!("Failed to open Swish", e);
+    }
+}
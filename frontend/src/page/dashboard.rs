@@ -0,0 +1,264 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::fetch::get_with_retry;
+use crate::util::simple_ev;
+use crate::views::chart::{Chart, ChartKind, Unit};
+use chrono::Utc;
+use seed::prelude::*;
+use seed::*;
+use seed_fetcher::{ResourceStore, Resources};
+use strecklistan_api::dashboard::Dashboard;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::location::LowStockAlert;
+use strecklistan_api::register::RegisterStatus;
+use strecklistan_api::time::Tz;
+
+/// How often the dashboard is fetched even if no sales are made, since the bridge doesn't announce
+/// when it goes silent
+const REFRESH_INTERVAL_MS: u32 = 30_000;
+
+#[derive(Clone, Debug)]
+pub enum DashboardMsg {
+    /// Fetch the dashboard again
+    Reload,
+
+    Fetched(Dashboard),
+    FetchFailed(String),
+
+    /// A change pushed by the server, which may have been a sale
+    Live(LiveEvent),
+
+    Tick,
+}
+
+/// Today's sales, the register, the card reader and the items running low on one screen, for
+/// admins to keep an eye on during an event
+pub struct DashboardPage {
+    dashboard: Option<Dashboard>,
+    error: Option<String>,
+
+    /// Whether a fetch is under way, and whether something changed since it started. During a rush
+    /// the sales come faster than the dashboard can be fetched, so they're fetched one at a time.
+    fetching: bool,
+    changed: bool,
+
+    _tick: StreamHandle,
+}
+
+#[derive(Resources)]
+struct Res<'a> {
+    #[url = "/api/inventory/low_stock"]
+    #[policy = "SilentRefetch"]
+    low_stock: &'a Vec<LowStockAlert>,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
+}
+
+impl DashboardPage {
+    pub fn new(rs: &ResourceStore, orders: &mut impl Orders<Msg>) -> Self {
+        orders
+            .proxy(Msg::DashboardMsg)
+            .subscribe(DashboardMsg::Live);
+        let tick = orders
+            .proxy(Msg::DashboardMsg)
+            .stream_with_handle(streams::interval(REFRESH_INTERVAL_MS, || {
+                DashboardMsg::Tick
+            }));
+        Res::acquire(rs, orders).ok();
+        let mut page = DashboardPage {
+            dashboard: None,
+            error: None,
+            fetching: false,
+            changed: false,
+            _tick: tick,
+        };
+        page.fetch(orders);
+        page
+    }
+
+    fn fetch(&mut self, orders: &mut impl Orders<Msg>) {
+        if self.fetching {
+            self.changed = true;
+            return;
+        }
+        self.fetching = true;
+        self.changed = false;
+        orders.proxy(Msg::DashboardMsg).perform_cmd(async move {
+            let result =
+                async { get_with_retry("/api/dashboard".into()).await?.json().await }.await;
+            match result {
+                Ok(dashboard) => DashboardMsg::Fetched(dashboard),
+                Err(e) => DashboardMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    /// Fetch again if something changed while the last fetch was under way
+    fn fetched(&mut self, orders: &mut impl Orders<Msg>) {
+        self.fetching = false;
+        if self.changed {
+            self.fetch(orders);
+        }
+    }
+
+    pub fn update(&mut self, msg: DashboardMsg, rs: &ResourceStore, orders: &mut impl Orders<Msg>) {
+        match msg {
+            DashboardMsg::Reload => {
+                self.error = None;
+                self.fetch(orders);
+                rs.mark_as_dirty(Res::low_stock_url(), orders);
+            }
+            DashboardMsg::Fetched(dashboard) => {
+                self.dashboard = Some(dashboard);
+                self.error = None;
+                self.fetched(orders);
+            }
+            DashboardMsg::FetchFailed(reason) => {
+                self.error = Some(reason);
+                self.fetched(orders);
+            }
+            DashboardMsg::Live(event) => match event {
+                LiveEvent::TransactionCreated { .. }
+                | LiveEvent::TransactionDeleted { .. }
+                | LiveEvent::TransactionsChanged => {
+                    self.fetch(orders);
+                    rs.mark_as_dirty(Res::low_stock_url(), orders);
+                }
+                _ => {}
+            },
+            DashboardMsg::Tick => self.fetch(orders),
+        }
+    }
+
+    /// The register is kept up to date by the [Live](crate::live::Live) connection
+    pub fn view(&self, rs: &ResourceStore, register: &RegisterStatus) -> Node<Msg> {
+        let (dashboard, res) = match (&self.dashboard, &self.error, Res::acquire_now(rs)) {
+            (Some(dashboard), _, Ok(res)) => (dashboard, res),
+            (None, Some(error), _) => {
+                return div![
+                    C![C.report_page],
+                    p![strings::ERROR_OCCURRED],
+                    p![error],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::DashboardMsg(DashboardMsg::Reload)),
+                        strings::RETRY,
+                    ],
+                ]
+            }
+            _ => return Loading::view_with_retry(),
+        };
+        let tz = res.reporting_timezone;
+
+        div![
+            C![C.dashboard_page],
+            div![
+                C![C.dashboard_tile, C.rounded],
+                h2![strings::SALES_TODAY],
+                div![
+                    C![C.dashboard_revenue_total],
+                    format!("{}:-", dashboard.revenue)
+                ],
+                div![
+                    C![C.admin_status_off],
+                    format!("{} {}", dashboard.sales, strings::DASHBOARD_SALES),
+                ],
+            ],
+            div![
+                C![C.dashboard_tile, C.dashboard_chart, C.rounded],
+                h2![strings::SALES_PER_HOUR],
+                if dashboard.hours.is_empty() {
+                    p![C![C.admin_status_off], strings::NO_SALES_TODAY]
+                } else {
+                    Chart::new(ChartKind::Bar)
+                        .unit(Unit::Currency)
+                        .labels(
+                            dashboard
+                                .hours
+                                .iter()
+                                .map(|hour| format!("{:02}:00", hour.hour)),
+                        )
+                        .series(
+                            strings::REPORT_TOTAL_SALES,
+                            dashboard
+                                .hours
+                                .iter()
+                                .map(|hour| hour.total.into())
+                                .collect(),
+                        )
+                        .view()
+                },
+            ],
+            div![
+                C![C.dashboard_tile, C.rounded],
+                h2![strings::DASHBOARD_REGISTER],
+                match &register.open_shift {
+                    Some(shift) => p![
+                        C![C.admin_status_ok],
+                        format!(
+                            "{} {} {}",
+                            strings::REGISTER_OPENED_BY,
+                            shift
+                                .opened_by
+                                .clone()
+                                .unwrap_or_else(|| strings::UNKNOWN.to_string()),
+                            shift.opened_at.with_timezone(tz).format("%H:%M"),
+                        ),
+                    ],
+                    None => p![C![C.admin_status_off], strings::REGISTER_CLOSED],
+                },
+                if register.is_overdue(Utc::now()) {
+                    p![C![C.admin_status_bad], strings::REGISTER_OVERDUE]
+                } else {
+                    empty![]
+                },
+                if register.unreconciled.is_empty() {
+                    empty![]
+                } else {
+                    p![C![C.admin_status_bad], strings::REGISTER_AUTO_CLOSED]
+                },
+            ],
+            div![
+                C![C.dashboard_tile, C.rounded],
+                h2![strings::ADMIN_BRIDGE],
+                match &dashboard.bridge {
+                    None => p![C![C.admin_status_off], strings::IZETTLE_DISABLED],
+                    Some(bridge) => div![
+                        if dashboard.bridge_online {
+                            p![C![C.admin_status_ok], strings::BRIDGE_STATUS_ONLINE]
+                        } else {
+                            p![C![C.admin_status_bad], strings::BRIDGE_OFFLINE]
+                        },
+                        p![format!(
+                            "{}: {}",
+                            strings::BRIDGE_PENDING,
+                            bridge.pending_payments
+                        )],
+                    ],
+                },
+            ],
+            div![
+                C![C.dashboard_tile, C.rounded],
+                h2![strings::LOW_STOCK],
+                if res.low_stock.is_empty() {
+                    p![C![C.admin_status_off], strings::NOTHING_RUNNING_LOW]
+                } else {
+                    ul![res.low_stock.iter().map(|alert| {
+                        li![
+                            C![C.low_stock_alert, C.rounded],
+                            format!(
+                                "{}: {} {}",
+                                alert.name,
+                                alert.for_sale,
+                                strings::LEFT_FOR_SALE
+                            ),
+                        ]
+                    })]
+                },
+            ],
+        ]
+    }
+}
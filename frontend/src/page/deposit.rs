@@ -1,54 +1,113 @@
 use crate::app::Msg;
 use crate::components::izettle_pay::{IZettlePay, IZettlePayErr, IZettlePayMsg};
+use crate::components::member_contact::{MemberContactMsg, MemberContactPanel};
 use crate::components::parsed_input::{ParsedInput, ParsedInputMsg};
-use crate::fuzzy_search::{FuzzyScore, FuzzySearch};
 use crate::generated::css_classes::C;
-use crate::notification_manager::{Notification, NotificationMessage};
+use crate::i18n::Text;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
 use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
 use crate::strings;
-use crate::util::simple_ev;
-use crate::views::view_tillgodo;
+use crate::util::fetch::{form_response, get_with_retry, send_with_retry};
+use crate::util::{backup, new_idempotency_key, simple_ev, with_pending};
+use crate::views::{view_field_error, view_tillgodo};
 use seed::prelude::*;
 use seed::*;
 use seed_fetcher::Resources;
 use seed_fetcher::{event, NotAvailable, ResourceStore};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use strecklistan_api::{
-    book_account::{BookAccount, BookAccountId, MasterAccounts},
-    currency::AbsCurrency,
-    member::{Member, MemberId, NewMember},
+    book_account::{BookAccount, BookAccountId, LedgerEntry, MasterAccounts},
+    currency::{Currency, NonNegativeCurrency},
+    izettle::IZettleDeposit,
+    live::LiveEvent,
+    member::{Member, MemberId, MemberSearchResult, NewMember},
+    preferences::UserPreferences,
+    swish::SwishPayment,
+    time::Tz,
     transaction::{NewTransaction, TransactionId},
+    validation::FieldError,
 };
 
-#[derive(Clone)]
 pub struct DepositionPage {
-    accs_search: Vec<(FuzzyScore, BookAccountId)>,
     search_string: String,
+    search_results: Vec<MemberSearchResult>,
+
+    /// Index of the search result selected with the arrow keys
+    highlighted: usize,
 
     debit: DebitOption,
     credit_account: Option<BookAccountId>,
-    amount_input: ParsedInput<AbsCurrency>,
+    amount_input: ParsedInput<NonNegativeCurrency>,
     izettle_pay: IZettlePay,
 
     new_member: Option<(String, String, String, Option<String>)>,
 
+    /// The fields of the new member which were rejected by the server
+    new_member_errors: Vec<FieldError>,
+
+    /// The ledger of the selected account, `None` while it is loading
+    history: Option<Option<Vec<LedgerEntry>>>,
+
+    /// The contact details of the member of the selected account, if shown
+    contact: Option<MemberContactPanel>,
+
     request_in_progress: bool,
+
+    /// Sent with the deposit so that it's only made once, even if it's posted twice
+    idempotency_key: String,
+
+    /// Deposits which are shown as made, but which the server hasn't saved yet
+    sent: Vec<SentDeposit>,
+
+    /// Deposits which members have started with Swish themselves, but not yet paid
+    swish_pending: Vec<SwishPayment>,
+}
+
+/// A deposit which has been cleared from the form, but not yet saved by the server
+#[derive(Clone)]
+struct SentDeposit {
+    transaction: NewTransaction,
+
+    /// The amount as it was entered, for putting it back
+    amount_input: ParsedInput<NonNegativeCurrency>,
 }
 
 #[derive(Clone, Debug)]
 pub enum DepositionMsg {
+    /// Fetch all resources of the page again
+    Reload,
+
     SearchDebit(String),
+    SearchResults {
+        query: String,
+        results: Vec<MemberSearchResult>,
+    },
 
     CreditKeyDown(web_sys::KeyboardEvent),
     CreditSelect(BookAccountId),
     SelectDebit(DebitOption),
 
     AmountInputMsg(ParsedInputMsg),
+    SelectPreset(NonNegativeCurrency),
+    KeypadPress(KeypadKey),
 
     Deposit,
     DepositSent {
         transaction_id: TransactionId,
     },
+
+    /// The server saved a deposit, which was cleared from the form when it was sent
+    DepositSaved {
+        idempotency_key: String,
+        transaction_id: TransactionId,
+    },
+
+    /// The server didn't save a deposit, so it is put back in the form
+    DepositNotSaved {
+        idempotency_key: String,
+    },
     DepositFailed {
         message_title: String,
         message_body: Option<String>,
@@ -56,9 +115,22 @@ pub enum DepositionMsg {
 
     IZettlePay(IZettlePayMsg),
 
+    ToggleHistory,
+    HistoryFetched {
+        account: BookAccountId,
+        ledger: Vec<LedgerEntry>,
+    },
+
+    ToggleContact,
+    Contact(MemberContactMsg),
+
+    SwishPendingFetched(Vec<SwishPayment>),
+    Live(LiveEvent),
+
     ShowNewMemberMenu,
     NewMember(NewMemberMsg),
     NewMemberCreated((MemberId, BookAccountId)),
+    NewMemberInvalid(Vec<FieldError>),
 
     // -- Resource Messages -- //
     ResFetched(event::Fetched),
@@ -75,6 +147,22 @@ pub enum NewMemberMsg {
     HideMenu,
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum KeypadKey {
+    Digit(u32),
+    Point,
+    Backspace,
+    Clear,
+}
+
+/// The deposit form as it is kept in the [backup](crate::util::backup) across reloads of the
+/// page. The selected account survives them in the url.
+#[derive(Serialize, Deserialize)]
+struct DepositBackup {
+    amount: String,
+    new_member: Option<(String, String, String, Option<String>)>,
+}
+
 #[derive(Clone, Debug)]
 pub enum DebitOption {
     IZettleEPay,
@@ -83,6 +171,9 @@ pub enum DebitOption {
     Cash,
 }
 
+/// Deposits larger than this are most likely typos
+const MAX_DEPOSIT_KR: i64 = 5000;
+
 #[derive(Resources)]
 struct Res<'a> {
     #[url = "/api/book_accounts"]
@@ -93,30 +184,37 @@ struct Res<'a> {
 
     #[url = "/api/members"]
     members: &'a HashMap<MemberId, Member>,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
 }
 
 impl DepositionPage {
     pub fn new(rs: &ResourceStore, orders: &mut impl Orders<DepositionMsg>) -> Self {
         orders.subscribe(DepositionMsg::ResFetched);
         orders.subscribe(DepositionMsg::ResMarkDirty);
+        orders.subscribe(DepositionMsg::Live);
+        Res::acquire(rs, orders).ok();
+        fetch_swish_pending(orders);
 
-        let mut page = DepositionPage {
+        DepositionPage {
             debit: DebitOption::IZettleEPay,
             izettle_pay: IZettlePay::new(),
             credit_account: None,
             search_string: String::new(),
-            accs_search: vec![],
+            search_results: vec![],
+            highlighted: 0,
             amount_input: ParsedInput::new("0")
                 .with_error_message(strings::INVALID_MONEY_MESSAGE_LONG),
             new_member: None,
+            new_member_errors: vec![],
+            history: None,
+            contact: None,
             request_in_progress: false,
-        };
-
-        if let Ok(res) = Res::acquire(rs, orders) {
-            page.rebuild_data(&res);
+            idempotency_key: new_idempotency_key(),
+            sent: vec![],
+            swish_pending: vec![],
         }
-
-        page
     }
 
     pub fn update(
@@ -125,6 +223,14 @@ impl DepositionPage {
         rs: &ResourceStore,
         orders: &mut impl Orders<Msg>,
     ) -> Result<(), NotAvailable> {
+        if let DepositionMsg::Reload = msg {
+            rs.mark_as_dirty(Res::book_accounts_url(), orders);
+            rs.mark_as_dirty(Res::master_accounts_url(), orders);
+            rs.mark_as_dirty(Res::members_url(), orders);
+            rs.mark_as_dirty(Res::reporting_timezone_url(), orders);
+            return Ok(());
+        }
+
         let res = Res::acquire(rs, orders)?;
 
         let mut orders_local = orders.proxy(|msg| Msg::DepositionMsg(msg));
@@ -132,27 +238,114 @@ impl DepositionPage {
         match msg {
             DepositionMsg::SearchDebit(input) => {
                 self.search_string = input;
-                for (score, acc_id) in self.accs_search.iter_mut() {
-                    let acc = &res.book_accounts[acc_id];
-                    *score = res.members[&acc.creditor.unwrap()].compare_fuzzy(&self.search_string);
-                }
-
-                self.accs_search
-                    .sort_by(|(scr_a, acc_a_id), (scr_b, acc_b_id)| {
-                        scr_b.cmp(scr_a).then(acc_a_id.cmp(&acc_b_id))
+                if self.search_string.trim().is_empty() {
+                    self.search_results.clear();
+                } else {
+                    let query = self.search_string.clone();
+                    orders_local.perform_cmd(async move {
+                        let url = format!(
+                            "/api/members/search?q={}",
+                            String::from(js_sys::encode_uri_component(&query))
+                        );
+                        let result = async { get_with_retry(url).await?.json().await }.await;
+                        match result {
+                            Ok(results) => Some(DepositionMsg::SearchResults { query, results }),
+                            Err(e) => {
+                                error!("Failed to search members", e);
+                                None
+                            }
+                        }
                     });
+                }
+            }
+            DepositionMsg::SearchResults { query, results } => {
+                // ignore responses to outdated searches
+                if query == self.search_string {
+                    self.search_results = results;
+                    self.highlighted = 0;
+                }
             }
             DepositionMsg::CreditKeyDown(ev) => match ev.key().as_str() {
                 "Enter" => {
-                    if let Some((_, acc_id)) = self.accs_search.first() {
-                        orders_local.send_msg(DepositionMsg::CreditSelect(*acc_id));
+                    if let Some(result) = self.search_results.get(self.highlighted) {
+                        orders_local.send_msg(DepositionMsg::CreditSelect(result.account.id));
+                    }
+                }
+                "ArrowDown" => {
+                    ev.prevent_default();
+                    if self.highlighted + 1 < self.search_results.len() {
+                        self.highlighted += 1;
                     }
                 }
+                "ArrowUp" => {
+                    ev.prevent_default();
+                    self.highlighted = self.highlighted.saturating_sub(1);
+                }
+                "Escape" => {
+                    self.search_string.clear();
+                    self.search_results.clear();
+                }
                 _ => {}
             },
             DepositionMsg::CreditSelect(acc_id) => {
                 self.search_string = String::new();
+                self.search_results.clear();
                 self.credit_account = Some(acc_id);
+                self.history = None;
+                self.contact = None;
+                self.update_url();
+            }
+            DepositionMsg::ToggleHistory => match (&self.history, self.credit_account) {
+                (None, Some(account)) => {
+                    self.history = Some(None);
+                    orders_local.perform_cmd(async move {
+                        let url = format!("/api/book_account/{}/ledger", account);
+                        let result = async { get_with_retry(url).await?.json().await }.await;
+                        match result {
+                            Ok(ledger) => Some(DepositionMsg::HistoryFetched { account, ledger }),
+                            Err(e) => {
+                                error!("Failed to fetch ledger", e);
+                                None
+                            }
+                        }
+                    });
+                }
+                _ => self.history = None,
+            },
+            DepositionMsg::HistoryFetched { account, ledger } => {
+                if self.credit_account == Some(account) && self.history.is_some() {
+                    self.history = Some(Some(ledger));
+                }
+            }
+            DepositionMsg::ToggleContact => {
+                self.contact = match (&self.contact, self.selected_member(&res)) {
+                    (None, Some(member)) => Some(MemberContactPanel::new(
+                        member,
+                        &mut orders_local.proxy(DepositionMsg::Contact),
+                    )),
+                    _ => None,
+                };
+            }
+            DepositionMsg::Contact(msg) => {
+                let failed = match &msg {
+                    MemberContactMsg::Failed(reason) => Some(reason.clone()),
+                    _ => None,
+                };
+                if let Some(contact) = &mut self.contact {
+                    contact.update(msg, &mut orders_local.proxy(DepositionMsg::Contact));
+                }
+                if let Some(reason) = failed {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::CONTACT_UPDATE_FAILED,
+                            )
+                            .with_body(reason),
+                        },
+                    ));
+                }
             }
             DepositionMsg::SelectDebit(debit) => {
                 self.debit = debit;
@@ -160,7 +353,38 @@ impl DepositionPage {
             DepositionMsg::AmountInputMsg(msg) => {
                 self.amount_input.update(msg);
             }
+            DepositionMsg::SelectPreset(amount) => {
+                self.amount_input.set_value(amount);
+            }
+            DepositionMsg::KeypadPress(key) => {
+                let mut text = self.amount_input.get_text().to_string();
+                match key {
+                    KeypadKey::Digit(digit) => {
+                        if text == "0" {
+                            text.clear();
+                        }
+                        text.extend(std::char::from_digit(digit, 10));
+                    }
+                    KeypadKey::Point => {
+                        if !text.contains('.') {
+                            text.push('.');
+                        }
+                    }
+                    KeypadKey::Backspace => {
+                        text.pop();
+                    }
+                    KeypadKey::Clear => text.clear(),
+                }
+                if text.is_empty() {
+                    text.push('0');
+                }
+                self.amount_input.update(ParsedInputMsg::Input(text));
+            }
             DepositionMsg::Deposit => {
+                if self.validation_error().is_some() {
+                    return Ok(());
+                }
+
                 if let Some((credit_acc, &amount)) =
                     self.credit_account.zip(self.amount_input.get_value())
                 {
@@ -175,37 +399,54 @@ impl DepositionPage {
                             }
                         },
                         bundles: vec![],
+                        note: None,
+                        tags: vec![],
+                        event_code: None,
+                        age_verified: false,
+                        member_id: None,
+                        idempotency_key: Some(self.idempotency_key.clone()),
                     };
 
-                    self.request_in_progress = true;
-
                     if let DebitOption::IZettleEPay = self.debit {
+                        self.request_in_progress = true;
+                        let deposit = IZettleDeposit {
+                            credited_account: credit_acc,
+                            amount,
+                        };
                         self.izettle_pay
-                            .pay(transaction, orders_local.proxy(DepositionMsg::IZettlePay));
+                            .pay_deposit(deposit, orders_local.proxy(DepositionMsg::IZettlePay));
                     } else {
+                        // the form is cleared right away, and filled in again if the server
+                        // doesn't save the deposit
+                        self.sent.push(SentDeposit {
+                            transaction: transaction.clone(),
+                            amount_input: self.amount_input.clone(),
+                        });
+                        self.clear_form();
+
                         orders_local.perform_cmd(async move {
+                            let idempotency_key =
+                                transaction.idempotency_key.clone().unwrap_or_default();
                             let result = async {
-                                Request::new("/api/transaction")
-                                    .method(Method::Post)
-                                    .json(&transaction)?
-                                    .fetch()
-                                    .await?
-                                    .json()
-                                    .await
+                                send_with_retry(
+                                    Request::new("/api/transaction")
+                                        .method(Method::Post)
+                                        .json(&transaction)?,
+                                )
+                                .await?
+                                .check_status()?
+                                .json()
+                                .await
                             }
                             .await;
                             match result {
-                                Ok(transaction_id) => {
-                                    Some(DepositionMsg::DepositSent { transaction_id })
-                                }
+                                Ok(transaction_id) => DepositionMsg::DepositSaved {
+                                    idempotency_key,
+                                    transaction_id,
+                                },
                                 Err(e) => {
                                     error!("Failed to post transaction", e);
-                                    Some(DepositionMsg::DepositFailed {
-                                        message_title: strings::SERVER_ERROR.to_string(),
-                                        message_body: Some(
-                                            strings::POSTING_TRANSACTION_FAILED.to_string(),
-                                        ),
-                                    })
+                                    DepositionMsg::DepositNotSaved { idempotency_key }
                                 }
                             }
                         });
@@ -217,23 +458,66 @@ impl DepositionPage {
                 orders.send_msg(Msg::NotificationMessage(
                     NotificationMessage::ShowNotification {
                         duration_ms: 5000,
-                        notification: Notification {
-                            title: strings::DEPOSIT_COMPLETE.to_string(),
-                            body: self
-                                .amount_input
+                        notification: Notification::new(
+                            NotificationLevel::Success,
+                            strings::DEPOSIT_COMPLETE,
+                        )
+                        .with_body(
+                            self.amount_input
                                 .get_value()
                                 .map(|value| format!("{}:-", value)),
-                        },
+                        ),
                     },
                 ));
 
                 self.request_in_progress = false;
-                self.amount_input.set_value(Default::default());
-                self.credit_account = None;
+                self.clear_form();
                 rs.mark_as_dirty(Res::book_accounts_url(), orders);
                 rs.mark_as_dirty(Res::members_url(), orders);
             }
 
+            DepositionMsg::DepositSaved {
+                idempotency_key,
+                transaction_id,
+            } => {
+                debug_log!("Posted transaction ID: ", transaction_id);
+                if let Some(sent) = self.take_sent(&idempotency_key) {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 5000,
+                            notification: Notification::new(
+                                NotificationLevel::Success,
+                                strings::DEPOSIT_COMPLETE,
+                            )
+                            .with_body(format!("{}:-", sent.transaction.amount)),
+                        },
+                    ));
+                }
+                rs.mark_as_dirty(Res::book_accounts_url(), orders);
+                rs.mark_as_dirty(Res::members_url(), orders);
+            }
+
+            DepositionMsg::DepositNotSaved { idempotency_key } => {
+                if let Some(sent) = self.take_sent(&idempotency_key) {
+                    self.amount_input = sent.amount_input;
+                    self.credit_account = Some(sent.transaction.credited_account);
+                    self.idempotency_key = idempotency_key;
+                    self.history = None;
+                    self.contact = None;
+                    self.update_url();
+                }
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::DEPOSIT_NOT_SAVED,
+                        )
+                        .with_body(strings::POSTING_TRANSACTION_FAILED.to_string()),
+                    },
+                ));
+            }
+
             DepositionMsg::DepositFailed {
                 message_title,
                 message_body,
@@ -242,10 +526,8 @@ impl DepositionPage {
                 orders.send_msg(Msg::NotificationMessage(
                     NotificationMessage::ShowNotification {
                         duration_ms: 10000,
-                        notification: Notification {
-                            title: message_title,
-                            body: message_body,
-                        },
+                        notification: Notification::new(NotificationLevel::Error, message_title)
+                            .with_body(message_body),
                     },
                 ));
             }
@@ -255,10 +537,12 @@ impl DepositionPage {
                     &IZettlePayMsg::PaymentCompleted { transaction_id } => {
                         Some(DepositionMsg::DepositSent { transaction_id })
                     }
-                    IZettlePayMsg::PaymentCancelled => Some(DepositionMsg::DepositFailed {
-                        message_title: strings::PAYMENT_CANCELLED.to_string(),
-                        message_body: None,
-                    }),
+                    IZettlePayMsg::PaymentCancelled | IZettlePayMsg::SwitchedToCash => {
+                        Some(DepositionMsg::DepositFailed {
+                            message_title: strings::PAYMENT_CANCELLED.to_string(),
+                            message_body: None,
+                        })
+                    }
                     IZettlePayMsg::Error(IZettlePayErr::PaymentFailed { reason, .. }) => {
                         Some(DepositionMsg::DepositFailed {
                             message_title: strings::PAYMENT_FAILED.to_string(),
@@ -277,7 +561,28 @@ impl DepositionPage {
                             message_body: Some(reason.clone()),
                         })
                     }
-                    IZettlePayMsg::PollPendingPayment(_) => None,
+                    IZettlePayMsg::Error(IZettlePayErr::Unavailable { reason }) => {
+                        Some(DepositionMsg::DepositFailed {
+                            message_title: strings::PAYMENT_FAILED.to_string(),
+                            message_body: Some(reason.clone()),
+                        })
+                    }
+                    // deposits don't contain any items, or anything else to approve
+                    IZettlePayMsg::Error(IZettlePayErr::InsufficientStock(_))
+                    | IZettlePayMsg::Error(IZettlePayErr::ApprovalRequired(_)) => {
+                        Some(DepositionMsg::DepositFailed {
+                            message_title: strings::SERVER_ERROR.to_string(),
+                            message_body: None,
+                        })
+                    }
+                    IZettlePayMsg::PaymentStarted(_)
+                    | IZettlePayMsg::StatusChanged { .. }
+                    | IZettlePayMsg::StreamFailed(_)
+                    | IZettlePayMsg::PollPendingPayment(_)
+                    | IZettlePayMsg::PaymentUpdated { .. }
+                    | IZettlePayMsg::Tick
+                    | IZettlePayMsg::Cancel { .. }
+                    | IZettlePayMsg::CancelFailed => None,
                 };
 
                 if let Some(msg) = reaction {
@@ -288,8 +593,18 @@ impl DepositionPage {
                     .update(msg, orders_local.proxy(DepositionMsg::IZettlePay));
             }
 
+            DepositionMsg::SwishPendingFetched(pending) => self.swish_pending = pending,
+            DepositionMsg::Live(LiveEvent::SwishPaymentsChanged) => {
+                fetch_swish_pending(&mut orders_local);
+
+                // a paid deposit changes the balance of the member
+                rs.mark_as_dirty(Res::book_accounts_url(), orders);
+            }
+            DepositionMsg::Live(_) => {}
+
             DepositionMsg::ShowNewMemberMenu => {
                 self.new_member = Some((String::new(), String::new(), String::new(), None));
+                self.new_member_errors.clear();
             }
 
             DepositionMsg::NewMember(msg) => {
@@ -306,7 +621,7 @@ impl DepositionPage {
                         }
                         NewMemberMsg::Create => {
                             if first_name == "" || last_name == "" {
-                                log!("Missing fields: `first_name` and `last_name` required");
+                                debug_log!("Missing fields: `first_name` and `last_name` required");
                             } else {
                                 let msg = (
                                     NewMember {
@@ -323,18 +638,21 @@ impl DepositionPage {
                                 );
                                 orders_local.perform_cmd(async move {
                                     let response = async {
-                                        Request::new("/api/add_member_with_book_account")
-                                            .method(Method::Post)
-                                            .json(&msg)?
-                                            .fetch()
-                                            .await?
-                                            .json()
-                                            .await
+                                        let response =
+                                            Request::new("/api/add_member_with_book_account")
+                                                .method(Method::Post)
+                                                .json(&msg)?
+                                                .fetch()
+                                                .await?;
+                                        form_response(response).await
                                     }
                                     .await;
 
                                     match response {
-                                        Ok(data) => Some(DepositionMsg::NewMemberCreated(data)),
+                                        Ok(Ok(data)) => Some(DepositionMsg::NewMemberCreated(data)),
+                                        Ok(Err(errors)) => {
+                                            Some(DepositionMsg::NewMemberInvalid(errors))
+                                        }
                                         Err(e) => {
                                             error!("Failed to create new member", e);
                                             None
@@ -349,28 +667,123 @@ impl DepositionPage {
                 }
             }
             DepositionMsg::NewMemberCreated((member_id, book_account_id)) => {
-                log!("New member ID: ", member_id);
-                log!("New book account ID: ", book_account_id);
+                debug_log!("New member ID: ", member_id);
+                debug_log!("New book account ID: ", book_account_id);
                 self.new_member = None;
+                self.credit_account = Some(book_account_id);
+                self.update_url();
                 rs.mark_as_dirty(Res::book_accounts_url(), orders);
                 rs.mark_as_dirty(Res::members_url(), orders);
             }
 
-            DepositionMsg::ResFetched(event::Fetched(resource)) => {
-                if Res::has_resource(resource) {
-                    self.rebuild_data(&res);
-                }
+            DepositionMsg::NewMemberInvalid(errors) => {
+                self.new_member_errors = errors;
             }
-            DepositionMsg::ResMarkDirty(_) => {}
+
+            DepositionMsg::ResFetched(_)
+            | DepositionMsg::ResMarkDirty(_)
+            | DepositionMsg::Reload => {}
         }
 
         Ok(())
     }
 
-    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+    /// Empty the form, for the next deposit
+    fn clear_form(&mut self) {
+        self.idempotency_key = new_idempotency_key();
+        self.amount_input.set_value(Default::default());
+        self.credit_account = None;
+        self.history = None;
+        self.contact = None;
+        self.update_url();
+    }
+
+    /// Stop showing a sent deposit as made, returns it if it was still being sent
+    fn take_sent(&mut self, idempotency_key: &str) -> Option<SentDeposit> {
+        let index = self.sent.iter().position(|sent| {
+            sent.transaction.idempotency_key.as_deref() == Some(idempotency_key)
+        })?;
+        Some(self.sent.remove(index))
+    }
+
+    /// Select the account given by the `account` url parameter
+    pub fn set_params(&mut self, params: &UrlParams) {
+        if let Some(account) = params.parse("account") {
+            if self.credit_account != Some(account) {
+                self.credit_account = Some(account);
+                self.history = None;
+                self.contact = None;
+            }
+        }
+    }
+
+    /// Reflect the state of the page in the url
+    pub fn update_url(&self) {
+        UrlParams::default()
+            .with("account", self.credit_account)
+            .replace_url(Page::Deposit);
+    }
+
+    /// Whether a deposit or a new member has been partially entered, or a deposit is still being
+    /// sent
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.form_entered() || !self.sent.is_empty()
+    }
+
+    /// Whether a deposit or a new member has been partially entered
+    fn form_entered(&self) -> bool {
+        let amount_entered = match self.amount_input.get_value().copied() {
+            Some(amount) => amount != Default::default(),
+            None => !self.amount_input.get_text().is_empty(),
+        };
+
+        let member_entered = match &self.new_member {
+            Some((first_name, last_name, nickname, acc_name)) => {
+                !first_name.is_empty()
+                    || !last_name.is_empty()
+                    || !nickname.is_empty()
+                    || acc_name.is_some()
+            }
+            None => false,
+        };
+
+        amount_entered || member_entered
+    }
+
+    /// Keep what has been entered in the backup of the tab, so that it survives a reload
+    pub fn save_backup(&self) {
+        let form = Some(DepositBackup {
+            amount: self.amount_input.get_text().to_string(),
+            new_member: self.new_member.clone(),
+        })
+        .filter(|_| self.form_entered());
+        backup::save(backup::DEPOSIT, form);
+    }
+
+    /// Put back what was entered before the page was reloaded, if it was backed up
+    pub fn restore_backup(&mut self) {
+        if let Some(form) = backup::load::<DepositBackup>(backup::DEPOSIT) {
+            self.amount_input.update(ParsedInputMsg::Input(form.amount));
+            self.new_member = form.new_member;
+        }
+    }
+
+    /// The member whose tillgodo account is selected, if any
+    fn selected_member(&self, res: &Res) -> Option<MemberId> {
+        self.credit_account
+            .and_then(|account| res.book_accounts.get(&account))
+            .and_then(|account| account.creditor)
+    }
+
+    /// Why the current input can't be deposited, if it can't
+    fn validation_error(&self) -> Option<Text> {
+        deposit_error(self.amount_input.get_value().copied(), self.credit_account)
+    }
+
+    pub fn view(&self, rs: &ResourceStore, preferences: &UserPreferences) -> Node<Msg> {
         let res = match Res::acquire_now(rs) {
             Ok(res) => res,
-            Err(_) => return Loading::view(),
+            Err(_) => return Loading::view_with_retry(),
         };
 
         if let Some((first_name, last_name, nickname, acc_name)) = &self.new_member {
@@ -387,18 +800,21 @@ impl DepositionPage {
                     attrs! {At::Value => first_name},
                     input_ev(Ev::Input, NewMemberMsg::FirstNameInput),
                 ],
+                view_field_error(&self.new_member_errors, "first_name"),
                 input![
                     C![C.border_on_focus, C.new_member_view_item],
                     attrs! {At::Placeholder => strings::LAST_NAME},
                     attrs! {At::Value => last_name},
                     input_ev(Ev::Input, NewMemberMsg::LastNameInput),
                 ],
+                view_field_error(&self.new_member_errors, "last_name"),
                 input![
                     C![C.border_on_focus, C.new_member_view_item],
                     attrs! {At::Placeholder => strings::NICKNAME},
                     attrs! {At::Value => nickname},
                     input_ev(Ev::Input, NewMemberMsg::NicknameInput),
                 ],
+                view_field_error(&self.new_member_errors, "nickname"),
                 input![
                     C![C.border_on_focus, C.new_member_view_item],
                     attrs! {At::Placeholder => strings::ACCOUNT_NAME},
@@ -408,6 +824,7 @@ impl DepositionPage {
                     }},
                     input_ev(Ev::Input, NewMemberMsg::AccNameInput),
                 ],
+                view_field_error(&self.new_member_errors, "account_name"),
                 button![
                     C![C.border_on_focus, C.wide_button, C.new_member_view_item],
                     if first_name == "" || last_name == "" {
@@ -440,7 +857,7 @@ impl DepositionPage {
                                     .map(|acc| acc.name.as_str())
                                     .unwrap_or("[MISSING]")
                             } else {
-                                strings::CHOOSE_TILLGODO_ACC
+                                strings::CHOOSE_TILLGODO_ACC.get()
                             };
                             attrs! {At::Placeholder => s}
                         },
@@ -452,23 +869,60 @@ impl DepositionPage {
                         simple_ev(Ev::Click, DepositionMsg::ShowNewMemberMenu),
                         "+",
                     ],
-                    self.accs_search
+                    self.search_results
                         .iter()
-                        .filter_map(|(_, acc_id)| res.book_accounts.get(acc_id))
-                        .filter_map(|acc| acc.creditor.map(|creditor| (acc, creditor)))
-                        .filter_map(|(acc, creditor)| res
-                            .members
-                            .get(&creditor)
-                            .map(|member| (acc, member)))
-                        .map(|(acc, member)| div![
-                            if self.credit_account == Some(acc.id) {
+                        .enumerate()
+                        .map(|(i, result)| div![
+                            if i == self.highlighted {
                                 C![C.border_highlight]
                             } else {
                                 C![]
                             },
-                            view_tillgodo(acc, member, DepositionMsg::CreditSelect(acc.id)),
+                            view_tillgodo(
+                                &with_pending(
+                                    &result.account,
+                                    self.sent.iter().map(|sent| &sent.transaction),
+                                ),
+                                &result.member,
+                                DepositionMsg::CreditSelect(result.account.id)
+                            ),
                         ])
                         .collect::<Vec<_>>(),
+                    if self.credit_account.is_some() {
+                        button![
+                            C![C.wide_button, C.border_on_focus],
+                            simple_ev(Ev::Click, DepositionMsg::ToggleHistory),
+                            if self.history.is_some() {
+                                strings::HIDE_HISTORY
+                            } else {
+                                strings::SHOW_HISTORY
+                            },
+                        ]
+                    } else {
+                        empty![]
+                    },
+                    match &self.history {
+                        Some(Some(ledger)) => view_ledger(ledger, *res.reporting_timezone),
+                        Some(None) => div![C![C.penguin, C.penguin_small]],
+                        None => empty![],
+                    },
+                    if self.selected_member(&res).is_some() {
+                        button![
+                            C![C.wide_button, C.border_on_focus],
+                            simple_ev(Ev::Click, DepositionMsg::ToggleContact),
+                            if self.contact.is_some() {
+                                strings::HIDE_CONTACT
+                            } else {
+                                strings::SHOW_CONTACT
+                            },
+                        ]
+                    } else {
+                        empty![]
+                    },
+                    match &self.contact {
+                        Some(contact) => contact.view().map_msg(DepositionMsg::Contact),
+                        None => empty![],
+                    },
                 ],
                 div![
                     C![C.pay_method_select_box],
@@ -501,9 +955,25 @@ impl DepositionPage {
                             strings::OTHER_EPAY,
                         ],
                     ],
+                    div![
+                        C![C.deposit_presets],
+                        preferences.deposit_presets.iter().map(|&amount| {
+                            button![
+                                C![C.deposit_preset, C.rounded, C.border_on_focus],
+                                if self.amount_input.get_value() == Some(&amount) {
+                                    C![C.debit_selected]
+                                } else {
+                                    C![]
+                                },
+                                simple_ev(Ev::Click, DepositionMsg::SelectPreset(amount)),
+                                format!("{}:-", amount),
+                            ]
+                        }),
+                    ],
                     self.amount_input
                         .view(C![C.deposit_amount_input, C.rounded, C.border_on_focus])
                         .map_msg(DepositionMsg::AmountInputMsg),
+                    view_keypad(),
                     if self.request_in_progress {
                         button![
                             C![C.wide_button, C.border_on_focus],
@@ -521,43 +991,143 @@ impl DepositionPage {
                     } else {
                         button![
                             C![C.wide_button, C.border_on_focus],
-                            {
-                                let disabled = match self.amount_input.get_value().copied() {
-                                    None => true,
-                                    Some(x) if x == Default::default() => true,
-                                    Some(_) if self.credit_account.is_none() => true,
-                                    Some(_) => false,
-                                };
-
-                                if disabled {
-                                    attrs! { At::Disabled => true }
-                                } else {
-                                    attrs! {}
-                                }
+                            if self.validation_error().is_some() {
+                                attrs! { At::Disabled => true }
+                            } else {
+                                attrs! {}
                             },
                             simple_ev(Ev::Click, DepositionMsg::Deposit),
                             strings::DEPOSIT,
                         ]
                     },
+                    match self.validation_error() {
+                        Some(error) if !self.request_in_progress => {
+                            div![C![C.wide_button_message], error]
+                        }
+                        _ => empty![],
+                    },
                     if let Some(_) = &self.izettle_pay.pending() {
                         div![C![C.wide_button_message], strings::WAITING_FOR_PAYMENT]
                     } else {
                         empty![]
                     },
+                    view_swish_pending(&self.swish_pending, &res),
                 ],
             ]
         }
         .map_msg(|msg| Msg::DepositionMsg(msg))
     }
+}
+
+fn view_ledger(ledger: &[LedgerEntry], timezone: Tz) -> Node<DepositionMsg> {
+    if ledger.is_empty() {
+        return div![C![C.ledger], strings::NO_HISTORY];
+    }
+
+    table![
+        C![C.ledger],
+        tr![
+            th![strings::DATE],
+            th![strings::DESCRIPTION],
+            th![strings::CHANGE],
+            th![strings::BALANCE],
+        ],
+        // newest first
+        ledger.iter().rev().map(|entry| {
+            tr![
+                td![entry
+                    .time
+                    .with_timezone(&timezone)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()],
+                td![entry.description.as_deref().unwrap_or("")],
+                td![
+                    if entry.change < 0.into() {
+                        C![C.ledger_negative]
+                    } else {
+                        C![C.ledger_positive]
+                    },
+                    format!("{}:-", entry.change),
+                ],
+                td![format!("{}:-", entry.balance)],
+            ]
+        }),
+    ]
+}
+
+fn view_swish_pending(pending: &[SwishPayment], res: &Res) -> Node<DepositionMsg> {
+    if pending.is_empty() {
+        return empty![];
+    }
+
+    div![
+        C![C.swish_pending],
+        h3![strings::SWISH_PENDING],
+        pending.iter().map(|payment| {
+            div![
+                C![C.swish_pending_row],
+                span![res
+                    .book_accounts
+                    .get(&payment.credited_account)
+                    .map(|account| account.name.as_str())
+                    .unwrap_or("[MISSING]")],
+                span![format!("{}:-", payment.amount)],
+            ]
+        }),
+    ]
+}
+
+fn fetch_swish_pending(orders: &mut impl Orders<DepositionMsg>) {
+    orders.perform_cmd(async move {
+        let result = async {
+            get_with_retry("/api/swish/pending".into())
+                .await?
+                .json()
+                .await
+        }
+        .await;
+        match result {
+            Ok(pending) => Some(DepositionMsg::SwishPendingFetched(pending)),
+            Err(e) => {
+                error!("Failed to fetch pending Swish deposits", e);
+                None
+            }
+        }
+    });
+}
 
-    fn rebuild_data(&mut self, res: &Res) {
-        self.search_string = String::new();
-        self.accs_search = res
-            .book_accounts
-            .values()
-            .filter(|acc| acc.creditor.is_some())
-            .map(|acc| (Default::default(), acc.id))
-            .collect();
+fn view_keypad() -> Node<DepositionMsg> {
+    let key = |label: &str, key| {
+        button![
+            C![C.keypad_key, C.rounded, C.border_on_focus],
+            simple_ev(Ev::Click, DepositionMsg::KeypadPress(key)),
+            label,
+        ]
+    };
+
+    div![
+        C![C.keypad],
+        (1..=9).map(|digit| key(&digit.to_string(), KeypadKey::Digit(digit))),
+        key(".", KeypadKey::Point),
+        key("0", KeypadKey::Digit(0)),
+        key("⌫", KeypadKey::Backspace),
+        key("C", KeypadKey::Clear),
+    ]
+}
+
+/// Why a deposit of `amount` to `credit_account` can't be made, if it can't
+fn deposit_error(
+    amount: Option<NonNegativeCurrency>,
+    credit_account: Option<BookAccountId>,
+) -> Option<Text> {
+    match amount {
+        None => Some(strings::INVALID_MONEY_MESSAGE_SHORT),
+        Some(x) if x == Default::default() => Some(strings::DEPOSIT_AMOUNT_ZERO),
+        Some(x) if Currency::from(x) > Currency::from(MAX_DEPOSIT_KR * 100) => {
+            Some(strings::DEPOSIT_AMOUNT_TOO_LARGE)
+        }
+        Some(_) if credit_account.is_none() => Some(strings::DEPOSIT_NO_ACCOUNT),
+        Some(_) => None,
     }
 }
 
@@ -571,3 +1141,38 @@ fn generate_tillgodo_acc_name(first_name: &str, nickname: &str) -> String {
         }
     )
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_deposit_error() {
+        let amount = |kr: i64| NonNegativeCurrency::try_from(Currency::from(kr * 100)).ok();
+
+        assert_eq!(
+            deposit_error(None, Some(1)),
+            Some(strings::INVALID_MONEY_MESSAGE_SHORT)
+        );
+        assert_eq!(
+            deposit_error(amount(0), Some(1)),
+            Some(strings::DEPOSIT_AMOUNT_ZERO)
+        );
+        assert_eq!(
+            deposit_error(amount(MAX_DEPOSIT_KR + 1), Some(1)),
+            Some(strings::DEPOSIT_AMOUNT_TOO_LARGE)
+        );
+        assert_eq!(
+            deposit_error(amount(100), None),
+            Some(strings::DEPOSIT_NO_ACCOUNT)
+        );
+        assert_eq!(deposit_error(amount(MAX_DEPOSIT_KR), Some(1)), None);
+    }
+
+    #[test]
+    fn test_tillgodo_acc_name() {
+        assert_eq!(generate_tillgodo_acc_name("Joakim", ""), "Tillgodo/Joakim");
+        assert_eq!(generate_tillgodo_acc_name("Joakim", "Tux"), "Tillgodo/Tux");
+    }
+}
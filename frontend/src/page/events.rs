@@ -0,0 +1,152 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
+use crate::strings;
+use crate::util::fetch::get_with_retry;
+use crate::util::simple_ev;
+use crate::views::chart::{Chart, ChartKind, Unit};
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::{currency::Currency, event::EventProfitAndLoss};
+
+#[derive(Clone, Debug)]
+pub enum EventsMsg {
+    /// Fetch the events again
+    Reload,
+
+    Fetched(Vec<EventProfitAndLoss>),
+    FetchFailed(String),
+}
+
+/// The profit and loss of every event code which transactions have been tagged with
+pub struct EventsPage {
+    events: Option<Vec<EventProfitAndLoss>>,
+    error: Option<String>,
+}
+
+impl EventsPage {
+    pub fn new(orders: &mut impl Orders<Msg>) -> Self {
+        let page = EventsPage {
+            events: None,
+            error: None,
+        };
+        page.fetch(orders);
+        page
+    }
+
+    fn fetch(&self, orders: &mut impl Orders<Msg>) {
+        orders.proxy(Msg::EventsMsg).perform_cmd(async move {
+            let url = "/api/events/profit_and_loss".to_string();
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(events) => EventsMsg::Fetched(events),
+                Err(e) => EventsMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn update(&mut self, msg: EventsMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            EventsMsg::Reload => {
+                self.error = None;
+                self.fetch(orders);
+            }
+            EventsMsg::Fetched(events) => {
+                self.events = Some(events);
+            }
+            EventsMsg::FetchFailed(reason) => {
+                self.error = Some(reason);
+            }
+        }
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        let events = match (&self.events, &self.error) {
+            (Some(events), _) => events,
+            (None, Some(error)) => {
+                return div![
+                    C![C.report_page],
+                    p![strings::ERROR_OCCURRED],
+                    p![error],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::EventsMsg(EventsMsg::Reload)),
+                        strings::RETRY,
+                    ],
+                ]
+            }
+            (None, None) => return Loading::view_with_retry(),
+        };
+
+        let fmt_money = |amount: Currency| format!("{}:-", amount);
+
+        div![
+            C![C.report_page],
+            h1![strings::TITLE_EVENTS],
+            if events.is_empty() {
+                empty![]
+            } else {
+                view_chart(events)
+            },
+            if events.is_empty() {
+                p![strings::NO_EVENTS]
+            } else {
+                table![
+                    C![C.report_table],
+                    tr![
+                        th![strings::EVENT_CODE],
+                        th![strings::REPORT_TRANSACTION_COUNT],
+                        th![strings::EVENT_REVENUE],
+                        th![strings::EVENT_COSTS],
+                        th![strings::EVENT_RESULT],
+                    ],
+                    events.iter().map(|event| {
+                        // the transactions page searches the event codes too
+                        let transactions_url = UrlParams::default()
+                            .with("q", Some(&event.event_code))
+                            .url(Page::TransactionHistory);
+                        let result = event.result();
+                        tr![
+                            td![a![
+                                attrs! {At::Href => transactions_url.to_string()},
+                                &event.event_code
+                            ]],
+                            td![C![C.report_amount], event.transaction_count.to_string()],
+                            td![C![C.report_amount], fmt_money(event.revenue)],
+                            td![C![C.report_amount], fmt_money(event.costs)],
+                            td![
+                                C![
+                                    C.report_amount,
+                                    if result < Currency::default() {
+                                        C.ledger_negative
+                                    } else {
+                                        C.ledger_positive
+                                    }
+                                ],
+                                fmt_money(result),
+                            ],
+                        ]
+                    }),
+                ]
+            },
+        ]
+    }
+}
+
+/// The revenue and costs of the events, oldest first
+fn view_chart<M>(events: &[EventProfitAndLoss]) -> Node<M> {
+    let amounts = |amount: fn(&EventProfitAndLoss) -> Currency| -> Vec<i64> {
+        events
+            .iter()
+            .rev()
+            .map(|event| amount(event).into())
+            .collect()
+    };
+    Chart::new(ChartKind::Bar)
+        .unit(Unit::Currency)
+        .labels(events.iter().rev().map(|event| &event.event_code))
+        .series(strings::EVENT_REVENUE, amounts(|event| event.revenue))
+        .series(strings::EVENT_COSTS, amounts(|event| event.costs))
+        .view()
+}
@@ -0,0 +1,111 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::fetch::get_with_retry;
+use crate::views::view_goal_progress;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::public::PublicStats;
+
+/// How often the stats are fetched again
+const REFRESH_INTERVAL_MS: u32 = 60_000;
+
+#[derive(Clone, Debug)]
+pub enum KioskMsg {
+    Reload,
+    Fetched(PublicStats),
+    FetchFailed(String),
+}
+
+/// A page for the info screen in the hallway, showing the public stats without logging in
+pub struct KioskPage {
+    stats: Option<PublicStats>,
+    _refresh: StreamHandle,
+}
+
+impl KioskPage {
+    pub fn new(orders: &mut impl Orders<Msg>) -> Self {
+        let refresh = orders
+            .proxy(Msg::KioskMsg)
+            .stream_with_handle(streams::interval(REFRESH_INTERVAL_MS, || KioskMsg::Reload));
+        let page = KioskPage {
+            stats: None,
+            _refresh: refresh,
+        };
+        page.fetch(orders);
+        page
+    }
+
+    fn fetch(&self, orders: &mut impl Orders<Msg>) {
+        orders.proxy(Msg::KioskMsg).perform_cmd(async move {
+            let result = async {
+                get_with_retry("/api/public/stats".to_string())
+                    .await?
+                    .json()
+                    .await
+            }
+            .await;
+            match result {
+                Ok(stats) => KioskMsg::Fetched(stats),
+                Err(e) => KioskMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn update(&mut self, msg: KioskMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            KioskMsg::Reload => self.fetch(orders),
+            KioskMsg::Fetched(stats) => self.stats = Some(stats),
+            KioskMsg::FetchFailed(reason) => {
+                // keep showing the old stats, the screen is not attended anyway
+                error!("Failed to fetch public stats", reason);
+            }
+        }
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        let stats = match &self.stats {
+            Some(stats) => stats,
+            None => return Loading::view(),
+        };
+
+        div![
+            C![C.kiosk_page],
+            div![
+                C![C.kiosk_sold],
+                span![C![C.kiosk_sold_count], stats.items_sold_today.to_string()],
+                span![strings::ITEMS_SOLD_TODAY],
+            ],
+            div![
+                C![C.kiosk_goals],
+                stats.goals.iter().map(view_goal_progress)
+            ],
+            div![
+                C![C.kiosk_stock],
+                stats.stock.iter().map(|item| {
+                    div![
+                        if item.stock > 0 {
+                            C![C.kiosk_item, C.rounded]
+                        } else {
+                            C![C.kiosk_item, C.kiosk_item_empty, C.rounded]
+                        },
+                        match &item.image_url {
+                            Some(url) => img![C![C.kiosk_item_image], attrs! {At::Src => url}],
+                            None => empty![],
+                        },
+                        div![C![C.kiosk_item_name], &item.name],
+                        div![
+                            C![C.kiosk_item_stock],
+                            if item.stock > 0 {
+                                format!("{} {}", item.stock, strings::STOCK_LEFT)
+                            } else {
+                                strings::INSUFFICIENT_STOCK.to_string()
+                            },
+                        ],
+                    ]
+                }),
+            ],
+        ]
+    }
+}
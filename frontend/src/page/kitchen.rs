@@ -0,0 +1,183 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::strings;
+use crate::util::fetch::get_with_retry;
+use crate::util::simple_ev;
+use chrono::Utc;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::kitchen::KitchenOrder;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::transaction::TransactionId;
+
+/// How often the time the orders have waited is updated
+const TICK_INTERVAL_MS: u32 = 30_000;
+
+#[derive(Clone, Debug)]
+pub enum KitchenMsg {
+    /// Fetch the orders again
+    Reload,
+
+    Fetched(Vec<KitchenOrder>),
+    FetchFailed(String),
+
+    /// A change pushed by the server, which may have been a new order
+    Live(LiveEvent),
+
+    /// The order of a sale has been prepared
+    Done(TransactionId),
+
+    /// Show for how long the orders have waited
+    Tick,
+}
+
+/// The paid orders with items to prepare, for a screen in the kitchen
+pub struct KitchenPage {
+    orders: Option<Vec<KitchenOrder>>,
+    error: Option<String>,
+    _tick: StreamHandle,
+}
+
+impl KitchenPage {
+    pub fn new(orders: &mut impl Orders<Msg>) -> Self {
+        orders.proxy(Msg::KitchenMsg).subscribe(KitchenMsg::Live);
+        let tick = orders
+            .proxy(Msg::KitchenMsg)
+            .stream_with_handle(streams::interval(TICK_INTERVAL_MS, || KitchenMsg::Tick));
+        let page = KitchenPage {
+            orders: None,
+            error: None,
+            _tick: tick,
+        };
+        page.fetch(orders);
+        page
+    }
+
+    fn fetch(&self, orders: &mut impl Orders<Msg>) {
+        orders.proxy(Msg::KitchenMsg).perform_cmd(async move {
+            let result = async { get_with_retry("/api/kitchen".into()).await?.json().await }.await;
+            match result {
+                Ok(kitchen_orders) => KitchenMsg::Fetched(kitchen_orders),
+                Err(e) => KitchenMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn update(&mut self, msg: KitchenMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            KitchenMsg::Reload => {
+                self.error = None;
+                self.fetch(orders);
+            }
+            KitchenMsg::Fetched(kitchen_orders) => {
+                self.orders = Some(kitchen_orders);
+                self.error = None;
+            }
+            KitchenMsg::FetchFailed(reason) => {
+                self.error = Some(reason);
+            }
+            KitchenMsg::Live(event) => match event {
+                LiveEvent::TransactionCreated { .. }
+                | LiveEvent::TransactionDeleted { .. }
+                | LiveEvent::TransactionsChanged
+                | LiveEvent::KitchenChanged
+                | LiveEvent::QueueChanged => self.fetch(orders),
+                _ => {}
+            },
+            KitchenMsg::Done(transaction_id) => {
+                let url = format!("/api/kitchen/{}/done", transaction_id);
+
+                // not retried, since the server refuses to mark an order as done twice
+                orders.perform_cmd(async move {
+                    let result = async {
+                        Request::new(url)
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(kitchen_orders) => Msg::KitchenMsg(KitchenMsg::Fetched(kitchen_orders)),
+                        Err(e) => Msg::NotificationMessage(NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::KITCHEN_UPDATE_FAILED,
+                            )
+                            .with_body(format!("{:?}", e)),
+                        }),
+                    }
+                });
+            }
+            KitchenMsg::Tick => {}
+        }
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        let kitchen_orders = match (&self.orders, &self.error) {
+            (Some(kitchen_orders), _) => kitchen_orders,
+            (None, Some(error)) => {
+                return div![
+                    C![C.report_page],
+                    p![strings::ERROR_OCCURRED],
+                    p![error],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::KitchenMsg(KitchenMsg::Reload)),
+                        strings::RETRY,
+                    ],
+                ]
+            }
+            (None, None) => return Loading::view_with_retry(),
+        };
+
+        let now = Utc::now();
+        div![
+            C![C.kitchen_page],
+            if kitchen_orders.is_empty() {
+                p![C![C.kitchen_empty], strings::KITCHEN_EMPTY]
+            } else {
+                div![
+                    C![C.kitchen_orders],
+                    kitchen_orders.iter().map(|order| {
+                        let waited = (now - order.time).num_minutes().max(0);
+                        div![
+                            C![C.kitchen_order, C.rounded],
+                            div![
+                                C![C.kitchen_order_header],
+                                span![
+                                    C![C.kitchen_order_number],
+                                    match order.queue_number {
+                                        Some(number) => number.to_string(),
+                                        None => format!("#{}", order.transaction_id),
+                                    }
+                                ],
+                                span![C![C.kitchen_order_waited], format!("{} min", waited)],
+                            ],
+                            ul![
+                                C![C.kitchen_order_lines],
+                                order
+                                    .lines
+                                    .iter()
+                                    .map(|line| li![format!("{} × {}", line.quantity, line.name)]),
+                            ],
+                            button![
+                                C![C.wide_button, C.border_on_focus],
+                                simple_ev(
+                                    Ev::Click,
+                                    Msg::KitchenMsg(KitchenMsg::Done(order.transaction_id))
+                                ),
+                                strings::KITCHEN_DONE,
+                            ],
+                        ]
+                    })
+                ]
+            },
+        ]
+    }
+}
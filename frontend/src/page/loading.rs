@@ -1,11 +1,67 @@
+use crate::app::Msg;
 use crate::generated::css_classes::C;
+use crate::strings;
+use crate::util::simple_ev;
 use seed::prelude::*;
 use seed::*;
 
+const SKELETON_GRID_BUTTONS: usize = 12;
+const SKELETON_LIST_ROWS: usize = 8;
+const SKELETON_CHARTS: usize = 2;
+
 pub struct Loading;
 
 impl Loading {
     pub fn view<M>() -> Node<M> {
         div![C![C.penguin, C.margin_hcenter]]
     }
+
+    /// A small spinner, for parts of a page which are still loading
+    pub fn spinner<M>() -> Node<M> {
+        div![
+            C![C.lds_ripple, C.lds_ripple_dark, C.margin_hcenter],
+            div![],
+            div![],
+        ]
+    }
+
+    /// Like [Loading::view], but offers to fetch the page again if loading takes a while
+    pub fn view_with_retry() -> Node<Msg> {
+        Loading::with_retry(Loading::view())
+    }
+
+    /// Show `placeholder`, and offer to fetch the page again if loading takes a while
+    pub fn with_retry(placeholder: Node<Msg>) -> Node<Msg> {
+        div![
+            placeholder,
+            button![
+                C![
+                    C.loading_retry,
+                    C.margin_hcenter,
+                    C.rounded,
+                    C.border_on_focus
+                ],
+                simple_ev(Ev::Click, Msg::ReloadPage),
+                strings::RETRY,
+            ],
+        ]
+    }
+
+    /// Placeholder for a grid of store buttons
+    pub fn skeleton_grid<M>() -> Node<M> {
+        div![
+            C![C.skeleton_grid],
+            (0..SKELETON_GRID_BUTTONS).map(|_| div![C![C.skeleton, C.skeleton_button]]),
+        ]
+    }
+
+    /// Placeholder for a list, e.g. of transactions
+    pub fn skeleton_list<M>() -> Node<M> {
+        div![(0..SKELETON_LIST_ROWS).map(|_| div![C![C.skeleton, C.skeleton_row]])]
+    }
+
+    /// Placeholder for a page of charts
+    pub fn skeleton_charts<M>() -> Node<M> {
+        div![(0..SKELETON_CHARTS).map(|_| div![C![C.skeleton, C.skeleton_chart]])]
+    }
 }
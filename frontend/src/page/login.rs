@@ -0,0 +1,165 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::strings;
+use crate::util::fetch::login_response;
+use crate::util::simple_ev;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::{
+    organization::{Organization, OrganizationId},
+    user::{Credentials, User},
+};
+
+#[derive(Clone, Debug)]
+pub enum LoginMsg {
+    NameInput(String),
+    PasswordInput(String),
+    SelectOrganization(String),
+    KeyDown(web_sys::KeyboardEvent),
+    Submit,
+
+    /// The login succeeded, handled by the app
+    LoggedIn(User),
+    /// The user is in several organizations and has to pick one
+    ChooseOrganization(Vec<Organization>),
+    Failed(String),
+}
+
+#[derive(Default)]
+pub struct LoginPage {
+    name: String,
+    password: String,
+
+    /// The organizations of the user, once the server has asked which one to use
+    organizations: Vec<Organization>,
+    organization: Option<OrganizationId>,
+
+    request_in_progress: bool,
+    error: Option<String>,
+}
+
+impl LoginPage {
+    pub fn update(&mut self, msg: LoginMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            LoginMsg::NameInput(input) => self.name = input,
+            LoginMsg::PasswordInput(input) => self.password = input,
+            LoginMsg::SelectOrganization(input) => self.organization = input.parse().ok(),
+            LoginMsg::KeyDown(ev) => {
+                if ev.key() == "Enter" {
+                    self.update(LoginMsg::Submit, orders);
+                }
+            }
+            LoginMsg::Submit => {
+                if self.request_in_progress || self.name.is_empty() {
+                    return;
+                }
+
+                self.request_in_progress = true;
+                self.error = None;
+                let credentials = Credentials {
+                    name: self.name.clone(),
+                    password: self.password.clone(),
+                    organization: self.organization,
+                };
+
+                orders.proxy(Msg::LoginMsg).perform_cmd(async move {
+                    let result = async {
+                        let response = Request::new("/api/login")
+                            .method(Method::Post)
+                            .json(&credentials)?
+                            .fetch()
+                            .await?;
+                        login_response(response).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(user)) => LoginMsg::LoggedIn(user),
+                        Ok(Err(organizations)) => LoginMsg::ChooseOrganization(organizations),
+                        Err(FetchError::StatusError(status)) if status.code == 401 => {
+                            LoginMsg::Failed(strings::INVALID_CREDENTIALS.to_string())
+                        }
+                        Err(FetchError::StatusError(status)) if status.code == 429 => {
+                            LoginMsg::Failed(strings::TOO_MANY_LOGIN_ATTEMPTS.to_string())
+                        }
+                        Err(FetchError::StatusError(status)) if status.code == 423 => {
+                            LoginMsg::Failed(strings::ACCOUNT_LOCKED.to_string())
+                        }
+                        Err(e) => LoginMsg::Failed(format!("{:?}", e)),
+                    }
+                });
+            }
+            LoginMsg::LoggedIn(_) => {
+                self.request_in_progress = false;
+                self.password.clear();
+                self.organizations.clear();
+                self.organization = None;
+            }
+            LoginMsg::ChooseOrganization(organizations) => {
+                // keep the password, so that the user only has to pick an organization
+                self.request_in_progress = false;
+                self.organization = organizations.first().map(|org| org.id);
+                self.organizations = organizations;
+            }
+            LoginMsg::Failed(reason) => {
+                self.request_in_progress = false;
+                self.password.clear();
+                self.error = Some(reason);
+            }
+        }
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        div![
+            C![C.login_page],
+            h1![strings::LOG_IN],
+            input![
+                C![C.login_input, C.rounded, C.border_on_focus],
+                attrs! {At::Value => self.name},
+                attrs! {At::Placeholder => strings::USERNAME},
+                attrs! {At::AutoFocus => true},
+                input_ev(Ev::Input, LoginMsg::NameInput),
+                keyboard_ev(Ev::KeyDown, LoginMsg::KeyDown),
+            ],
+            input![
+                C![C.login_input, C.rounded, C.border_on_focus],
+                attrs! {At::Type => "password"},
+                attrs! {At::Value => self.password},
+                attrs! {At::Placeholder => strings::PASSWORD},
+                input_ev(Ev::Input, LoginMsg::PasswordInput),
+                keyboard_ev(Ev::KeyDown, LoginMsg::KeyDown),
+            ],
+            if self.organizations.is_empty() {
+                empty![]
+            } else {
+                select![
+                    C![C.login_input, C.rounded, C.border_on_focus],
+                    attrs! {At::Title => strings::CHOOSE_ORGANIZATION},
+                    self.organizations.iter().map(|org| {
+                        option![
+                            attrs! {At::Value => org.id},
+                            attrs! {At::Selected => (self.organization == Some(org.id)).as_at_value()},
+                            &org.name,
+                        ]
+                    }),
+                    input_ev(Ev::Change, LoginMsg::SelectOrganization),
+                ]
+            },
+            button![
+                C![C.wide_button, C.border_on_focus],
+                if self.request_in_progress || self.name.is_empty() {
+                    attrs! {At::Disabled => true}
+                } else {
+                    attrs! {}
+                },
+                simple_ev(Ev::Click, LoginMsg::Submit),
+                strings::LOG_IN,
+            ],
+            if let Some(error) = &self.error {
+                div![C![C.wide_button_message], error]
+            } else {
+                empty![]
+            },
+        ]
+        .map_msg(Msg::LoginMsg)
+    }
+}
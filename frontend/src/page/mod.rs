@@ -1,14 +1,144 @@
+pub mod admin;
 pub mod analytics;
+pub mod balance;
+pub mod dashboard;
 pub mod deposit;
+pub mod events;
+pub mod kiosk;
+pub mod kitchen;
 pub mod loading;
+pub mod login;
+pub mod queue;
+pub mod report;
+pub mod stock_value;
 pub mod store;
 pub mod transactions;
 
-#[derive(Debug, Clone, Copy)]
+use crate::i18n::Text;
+use crate::strings;
+use crate::util::base;
+use seed::prelude::*;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Page {
     NotFound,
+    Login,
     Store,
     Deposit,
     TransactionHistory,
     Analytics,
+    Report,
+    Events,
+    StockValue,
+    Admin,
+    Dashboard,
+    Kiosk,
+    Queue,
+    Kitchen,
+    Balance,
+}
+
+impl Page {
+    pub fn title(self) -> Text {
+        match self {
+            Page::NotFound => strings::TITLE_NOT_FOUND,
+            Page::Login => strings::LOG_IN,
+            Page::Store => strings::TITLE_STORE,
+            Page::Deposit => strings::TITLE_DEPOSIT,
+            Page::TransactionHistory => strings::TITLE_TRANSACTIONS,
+            Page::Analytics => strings::TITLE_ANALYTICS,
+            Page::Report => strings::DAILY_REPORT,
+            Page::Events => strings::TITLE_EVENTS,
+            Page::StockValue => strings::TITLE_STOCK_VALUE,
+            Page::Admin => strings::TITLE_ADMIN,
+            Page::Dashboard => strings::TITLE_DASHBOARD,
+            Page::Kiosk => strings::TITLE_KIOSK,
+            Page::Queue => strings::TITLE_QUEUE,
+            Page::Kitchen => strings::TITLE_KITCHEN,
+            Page::Balance => strings::TITLE_BALANCE,
+        }
+    }
+
+    /// The first part of the url path of the page
+    pub fn path(self) -> &'static str {
+        match self {
+            Page::NotFound => "",
+            Page::Login => "login",
+            Page::Store => "store",
+            Page::Deposit => "deposit",
+            Page::TransactionHistory => "transactions",
+            Page::Analytics => "analytics",
+            Page::Report => "report",
+            Page::Events => "events",
+            Page::StockValue => "stock_value",
+            Page::Admin => "admin",
+            Page::Dashboard => "dashboard",
+            Page::Kiosk => "kiosk",
+            Page::Queue => "queue",
+            Page::Kitchen => "kitchen",
+            Page::Balance => "balance",
+        }
+    }
+}
+
+/// The query parameters and hash fragment of the current url, e.g. `?from=2024-01-01#42`
+#[derive(Debug, Clone, Default)]
+pub struct UrlParams {
+    search: UrlSearch,
+    hash: Option<String>,
+}
+
+impl UrlParams {
+    pub fn from_url(url: &Url) -> Self {
+        UrlParams {
+            search: url.search().clone(),
+            hash: url.hash().cloned(),
+        }
+    }
+
+    /// Get the first value of the query parameter `key`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.search
+            .get(key)
+            .and_then(|values| values.first())
+            .map(|value| value.as_str())
+    }
+
+    /// Get and parse the first value of the query parameter `key`
+    pub fn parse<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key).and_then(|value| value.parse().ok())
+    }
+
+    pub fn hash(&self) -> Option<&str> {
+        self.hash.as_deref()
+    }
+
+    /// Add the query parameter `key`, unless `value` is `None`
+    pub fn with(mut self, key: &str, value: Option<impl ToString>) -> Self {
+        if let Some(value) = value {
+            self.search.push_value(key, value.to_string());
+        }
+        self
+    }
+
+    pub fn with_hash(mut self, hash: Option<impl ToString>) -> Self {
+        self.hash = hash.map(|hash| hash.to_string());
+        self
+    }
+
+    /// The url of `page` with these parameters
+    pub fn url(&self, page: Page) -> Url {
+        let mut url = base::page_url(page.path()).set_search(self.search.clone());
+        if let Some(hash) = &self.hash {
+            url = url.set_hash(hash);
+        }
+        url
+    }
+
+    /// Replace the url in the address bar without navigating, used by pages to reflect their
+    /// state in the url
+    pub fn replace_url(&self, page: Page) {
+        self.url(page).go_and_replace();
+    }
 }
@@ -0,0 +1,211 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::i18n::Text;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
+use crate::strings;
+use crate::util::fetch::get_with_retry;
+use crate::util::simple_ev;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::live::LiveEvent;
+use strecklistan_api::queue::{QueueTicket, QueueTicketId};
+
+#[derive(Clone, Debug)]
+pub enum QueueMsg {
+    /// Fetch the queue again
+    Reload,
+
+    Fetched(Vec<QueueTicket>),
+    FetchFailed(String),
+
+    /// A change pushed by the server, which may have been to the queue
+    Live(LiveEvent),
+
+    /// Mark an order as ready, or as collected if it already is
+    Advance(QueueTicketId),
+}
+
+/// The numbers of the orders which are being prepared and which are ready, for the kitchen and
+/// for a screen facing the customers
+pub struct QueuePage {
+    tickets: Option<Vec<QueueTicket>>,
+    error: Option<String>,
+
+    /// Shown to the customers, without the header and without changing the queue on click
+    display: bool,
+}
+
+impl QueuePage {
+    pub fn new(orders: &mut impl Orders<Msg>) -> Self {
+        orders.proxy(Msg::QueueMsg).subscribe(QueueMsg::Live);
+        let page = QueuePage {
+            tickets: None,
+            error: None,
+            display: false,
+        };
+        page.fetch(orders);
+        page
+    }
+
+    pub fn set_params(&mut self, params: &UrlParams) {
+        self.display = params.get("display").is_some();
+    }
+
+    pub fn is_display(&self) -> bool {
+        self.display
+    }
+
+    fn fetch(&self, orders: &mut impl Orders<Msg>) {
+        orders.proxy(Msg::QueueMsg).perform_cmd(async move {
+            let result = async { get_with_retry("/api/queue".into()).await?.json().await }.await;
+            match result {
+                Ok(tickets) => QueueMsg::Fetched(tickets),
+                Err(e) => QueueMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn update(&mut self, msg: QueueMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            QueueMsg::Reload => {
+                self.error = None;
+                self.fetch(orders);
+            }
+            QueueMsg::Fetched(tickets) => {
+                self.tickets = Some(tickets);
+                self.error = None;
+            }
+            QueueMsg::FetchFailed(reason) => {
+                self.error = Some(reason);
+            }
+            QueueMsg::Live(event) => match event {
+                LiveEvent::QueueTicketIssued { .. }
+                | LiveEvent::QueueChanged
+                | LiveEvent::TransactionDeleted { .. }
+                | LiveEvent::TransactionsChanged => self.fetch(orders),
+                _ => {}
+            },
+            QueueMsg::Advance(id) => {
+                let ticket = self.tickets.iter().flatten().find(|t| t.id == id);
+                let action = match ticket {
+                    Some(ticket) if ticket.is_ready() => "collected",
+                    Some(_) => "ready",
+                    None => return,
+                };
+                let url = format!("/api/queue/{}/{}", id, action);
+
+                // not retried, since the server refuses to advance an order twice
+                orders.perform_cmd(async move {
+                    let result = async {
+                        Request::new(url)
+                            .method(Method::Post)
+                            .fetch()
+                            .await?
+                            .check_status()?
+                            .json()
+                            .await
+                    }
+                    .await;
+                    match result {
+                        Ok(tickets) => Msg::QueueMsg(QueueMsg::Fetched(tickets)),
+                        Err(e) => Msg::NotificationMessage(NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::QUEUE_UPDATE_FAILED,
+                            )
+                            .with_body(format!("{:?}", e)),
+                        }),
+                    }
+                });
+            }
+        }
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        let tickets = match (&self.tickets, &self.error) {
+            (Some(tickets), _) => tickets,
+            (None, Some(error)) => {
+                return div![
+                    C![C.report_page],
+                    p![strings::ERROR_OCCURRED],
+                    p![error],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::QueueMsg(QueueMsg::Reload)),
+                        strings::RETRY,
+                    ],
+                ]
+            }
+            (None, None) => return Loading::view_with_retry(),
+        };
+
+        let (ready, preparing): (Vec<_>, Vec<_>) = tickets.iter().partition(|t| t.is_ready());
+
+        div![
+            if self.display {
+                C![C.queue_page, C.queue_display]
+            } else {
+                C![C.queue_page]
+            },
+            if self.display {
+                empty![]
+            } else {
+                div![
+                    C![C.queue_controls],
+                    span![strings::QUEUE_HINT],
+                    a![
+                        C![C.queue_display_link],
+                        attrs! {
+                            At::Href => UrlParams::default()
+                                .with("display", Some(1))
+                                .url(Page::Queue)
+                                .to_string(),
+                            At::Target => "_blank",
+                        },
+                        strings::QUEUE_DISPLAY,
+                    ],
+                ]
+            },
+            div![
+                C![C.queue_columns],
+                self.view_column(strings::QUEUE_PREPARING, &preparing),
+                self.view_column(strings::QUEUE_READY, &ready),
+            ],
+        ]
+    }
+
+    fn view_column(&self, title: Text, tickets: &[&QueueTicket]) -> Node<Msg> {
+        div![
+            C![C.queue_column],
+            h2![title],
+            if tickets.is_empty() {
+                p![C![C.queue_empty], strings::QUEUE_EMPTY]
+            } else {
+                div![
+                    C![C.queue_numbers],
+                    tickets.iter().map(|ticket| {
+                        let class = if ticket.is_ready() {
+                            C![C.queue_number, C.queue_number_ready, C.rounded]
+                        } else {
+                            C![C.queue_number, C.rounded]
+                        };
+                        if self.display {
+                            div![class, ticket.number.to_string()]
+                        } else {
+                            let id = ticket.id;
+                            button![
+                                class,
+                                C![C.border_on_focus],
+                                simple_ev(Ev::Click, Msg::QueueMsg(QueueMsg::Advance(id))),
+                                ticket.number.to_string(),
+                            ]
+                        }
+                    })
+                ]
+            },
+        ]
+    }
+}
@@ -0,0 +1,490 @@
+use crate::achievements::AchievementEvent;
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::live::LiveMsg;
+use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
+use crate::strings;
+use crate::util::base;
+use crate::util::fetch::get_with_retry;
+use crate::util::{simple_ev, DATE_INPUT_FMT};
+use chrono::{NaiveDate, Utc};
+use seed::prelude::*;
+use seed::*;
+use std::collections::BTreeMap;
+use strecklistan_api::{
+    currency::Currency,
+    organization::Branding,
+    register::RegisterStatus,
+    report::{DailyReport, MonthlyReport, PaymentProvider, ProviderFees},
+};
+
+/// The notes and coins in the cash register, in öre
+const DENOMINATIONS: &[i64] = &[50000, 20000, 10000, 5000, 2000, 1000, 500, 200, 100];
+
+#[derive(Clone, Debug)]
+pub enum ReportMsg {
+    /// Fetch the report again
+    Reload,
+
+    Fetched(NaiveDate, DailyReport),
+    FetchedMonth(NaiveDate, MonthlyReport),
+    FetchFailed(String),
+    SetDate(String),
+    SetCount {
+        denomination: i64,
+        count: String,
+    },
+    SetFloat(String),
+    Print,
+    OpenRegister,
+    CloseRegister,
+}
+
+/// The end-of-day report (Z-report), made to be printed
+pub struct ReportPage {
+    date: NaiveDate,
+    report: Option<DailyReport>,
+
+    /// The fees of the month of the report
+    month: Option<MonthlyReport>,
+    error: Option<String>,
+
+    /// The counted number of every denomination in the cash register
+    cash_count: BTreeMap<i64, u32>,
+
+    /// The cash which was in the register when the day started
+    opening_float: Option<Currency>,
+}
+
+impl ReportPage {
+    pub fn new(orders: &mut impl Orders<Msg>) -> Self {
+        let page = ReportPage {
+            date: Utc::now().naive_utc().date(),
+            report: None,
+            month: None,
+            error: None,
+            cash_count: BTreeMap::new(),
+            opening_float: None,
+        };
+        page.fetch(orders);
+        page
+    }
+
+    /// Read the date from the `date` url parameter
+    pub fn set_params(&mut self, params: &UrlParams, orders: &mut impl Orders<Msg>) {
+        let date = params
+            .get("date")
+            .and_then(|date| NaiveDate::parse_from_str(date, DATE_INPUT_FMT).ok());
+
+        if let Some(date) = date.filter(|&date| date != self.date) {
+            self.set_date(date, orders);
+        }
+    }
+
+    /// Reflect the state of the page in the url
+    pub fn update_url(&self) {
+        UrlParams::default()
+            .with("date", Some(self.date.format(DATE_INPUT_FMT)))
+            .replace_url(Page::Report);
+    }
+
+    fn set_date(&mut self, date: NaiveDate, orders: &mut impl Orders<Msg>) {
+        self.date = date;
+        self.report = None;
+        self.month = None;
+        self.cash_count.clear();
+        self.opening_float = None;
+        self.fetch(orders);
+    }
+
+    fn fetch(&self, orders: &mut impl Orders<Msg>) {
+        let date = self.date;
+        orders.proxy(Msg::ReportMsg).perform_cmd(async move {
+            let url = format!("/api/report/daily/{}", date.format(DATE_INPUT_FMT));
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(report) => ReportMsg::Fetched(date, report),
+                Err(e) => ReportMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+        orders.proxy(Msg::ReportMsg).perform_cmd(async move {
+            let url = format!("/api/report/monthly/{}", date.format("%Y-%m"));
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(month) => ReportMsg::FetchedMonth(date, month),
+                Err(e) => ReportMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn update(&mut self, msg: ReportMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            ReportMsg::Reload => {
+                self.error = None;
+                self.fetch(orders);
+            }
+            ReportMsg::Fetched(date, report) => {
+                // ignore responses for dates which are no longer selected
+                if date == self.date {
+                    self.report = Some(report);
+                }
+            }
+            ReportMsg::FetchedMonth(date, month) => {
+                if date == self.date {
+                    self.month = Some(month);
+                }
+            }
+            ReportMsg::FetchFailed(reason) => {
+                self.error = Some(reason);
+            }
+            ReportMsg::SetDate(input) => {
+                if let Ok(date) = NaiveDate::parse_from_str(&input, DATE_INPUT_FMT) {
+                    self.set_date(date, orders);
+                    self.update_url();
+                }
+            }
+            ReportMsg::SetCount {
+                denomination,
+                count,
+            } => {
+                match count.parse() {
+                    Ok(count) => {
+                        self.cash_count.insert(denomination, count);
+                    }
+                    Err(_) => {
+                        self.cash_count.remove(&denomination);
+                    }
+                }
+                self.check_balanced(orders);
+            }
+            ReportMsg::SetFloat(input) => {
+                self.opening_float = input.parse().ok();
+                self.check_balanced(orders);
+            }
+            ReportMsg::Print => {
+                if let Err(e) = window().print() {
+                    error!("Failed to print", e);
+                }
+            }
+            ReportMsg::OpenRegister => {
+                orders.send_msg(Msg::LiveMsg(LiveMsg::OpenRegister));
+            }
+            ReportMsg::CloseRegister => {
+                orders.send_msg(Msg::LiveMsg(LiveMsg::CloseRegister));
+            }
+        }
+    }
+
+    fn counted_cash(&self) -> Currency {
+        self.cash_count
+            .iter()
+            .map(|(&denomination, &count)| Currency::from(denomination * i64::from(count)))
+            .sum()
+    }
+
+    /// Celebrate a cash count which matches the expected cash
+    fn check_balanced(&self, orders: &mut impl Orders<Msg>) {
+        let report = match &self.report {
+            Some(report) => report,
+            None => return,
+        };
+        let counted = self.counted_cash();
+        let opening_float = self.opening_float.unwrap_or_default();
+        if counted != Currency::default() && counted - opening_float == report.expected_cash {
+            orders.send_msg(Msg::Achievement(AchievementEvent::CashBalanced));
+        }
+    }
+
+    /// The report, printed under the name and logo of the organization if they are known
+    pub fn view(&self, branding: Option<&Branding>, register: &RegisterStatus) -> Node<Msg> {
+        let report = match (&self.report, &self.error) {
+            (Some(report), _) => report,
+            (None, Some(error)) => {
+                return div![
+                    C![C.report_page],
+                    p![strings::ERROR_OCCURRED],
+                    p![error],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::ReportMsg(ReportMsg::Reload)),
+                        strings::RETRY,
+                    ],
+                ]
+            }
+            (None, None) => return Loading::view_with_retry(),
+        };
+
+        let fmt_money = |amount: Currency| format!("{}:-", amount);
+        let account_rows = |totals: &[strecklistan_api::report::AccountTotal]| {
+            totals
+                .iter()
+                .map(|acc| {
+                    tr![
+                        td![&acc.name],
+                        td![acc.count.to_string()],
+                        td![C![C.report_amount], fmt_money(acc.total)],
+                    ]
+                })
+                .collect::<Vec<_>>()
+        };
+        let fee_row = |label: String, fees: &ProviderFees, sum: bool| {
+            tr![
+                IF!(sum => C![C.report_sum_row]),
+                td![label],
+                td![fees.count.to_string()],
+                td![C![C.report_amount], fmt_money(fees.gross)],
+                td![C![C.report_amount], fmt_money(-fees.fees)],
+                td![C![C.report_amount], fmt_money(fees.net())],
+            ]
+        };
+        let fee_header = tr![
+            th![],
+            th![],
+            th![],
+            th![strings::REPORT_TOTAL_FEES],
+            th![strings::REPORT_PAID_OUT],
+        ];
+
+        let counted = self.counted_cash();
+        let opening_float = self.opening_float.unwrap_or_default();
+
+        div![
+            C![C.report_page],
+            div![
+                C![C.report_controls],
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {At::Type => "date"},
+                    attrs! {At::Value => self.date.format(DATE_INPUT_FMT).to_string()},
+                    input_ev(Ev::Input, ReportMsg::SetDate),
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, ReportMsg::Print),
+                    strings::PRINT,
+                ],
+                match &register.open_shift {
+                    Some(shift) => div![
+                        C![C.report_register],
+                        span![
+                            format!(
+                                "{} {}",
+                                strings::REGISTER_OPENED_BY,
+                                shift
+                                    .opened_by
+                                    .clone()
+                                    .unwrap_or_else(|| strings::UNKNOWN.to_string()),
+                            ),
+                            shift
+                                .opened_on
+                                .as_ref()
+                                .map(|device| format!(" ({}: {})", strings::DEVICE, device)),
+                        ],
+                        button![
+                            C![C.rounded, C.border_on_focus],
+                            simple_ev(Ev::Click, ReportMsg::CloseRegister),
+                            strings::CLOSE_REGISTER,
+                        ],
+                    ],
+                    None => div![
+                        C![C.report_register],
+                        span![strings::REGISTER_CLOSED],
+                        button![
+                            C![C.rounded, C.border_on_focus],
+                            simple_ev(Ev::Click, ReportMsg::OpenRegister),
+                            strings::OPEN_REGISTER,
+                        ],
+                    ],
+                },
+            ],
+            branding.map(|branding| {
+                div![
+                    C![C.report_branding],
+                    branding.logo_url().map(|url| {
+                        img![C![C.report_logo], attrs! {At::Src => base::api_url(&url), At::Alt => ""}]
+                    }),
+                    div![
+                        div![C![C.report_organization], &branding.name],
+                        branding.org_number.as_ref().map(|number| {
+                            div![format!("{} {}", strings::ORG_NUMBER, number)]
+                        }),
+                    ],
+                ]
+            }),
+            h1![format!(
+                "{} {}",
+                strings::DAILY_REPORT,
+                report.date.format(DATE_INPUT_FMT)
+            )],
+            table![
+                C![C.report_table],
+                tr![
+                    td![strings::REPORT_TRANSACTION_COUNT],
+                    td![],
+                    td![C![C.report_amount], report.transaction_count.to_string()],
+                ],
+                tr![
+                    td![strings::REPORT_TOTAL_SALES],
+                    td![],
+                    td![C![C.report_amount], fmt_money(report.total_sales())],
+                ],
+                tr![
+                    td![strings::REPORT_TOTAL_DEPOSITS],
+                    td![],
+                    td![C![C.report_amount], fmt_money(report.total_deposits())],
+                ],
+                if report.tips.is_empty() {
+                    empty![]
+                } else {
+                    tr![
+                        td![strings::REPORT_TOTAL_TIPS],
+                        td![],
+                        td![C![C.report_amount], fmt_money(report.total_tips())],
+                    ]
+                },
+                if report.fees.is_empty() {
+                    empty![]
+                } else {
+                    tr![
+                        td![strings::REPORT_TOTAL_FEES],
+                        td![],
+                        td![C![C.report_amount], fmt_money(report.total_fees())],
+                    ]
+                },
+            ],
+            h2![strings::REPORT_SALES],
+            table![C![C.report_table], account_rows(&report.sales)],
+            h2![strings::REPORT_DEPOSITS],
+            table![C![C.report_table], account_rows(&report.deposits)],
+            if report.tips.is_empty() {
+                vec![]
+            } else {
+                vec![
+                    h2![strings::REPORT_TIPS],
+                    table![C![C.report_table], account_rows(&report.tips)],
+                ]
+            },
+            if report.fees.is_empty() {
+                vec![]
+            } else {
+                vec![
+                    h2![strings::REPORT_FEES],
+                    table![
+                        C![C.report_table],
+                        fee_header.clone(),
+                        report.fees.iter().map(|fees| {
+                            fee_row(provider_name(fees.provider).to_string(), fees, false)
+                        }),
+                    ],
+                ]
+            },
+            h2![strings::REPORT_ITEMS],
+            table![
+                C![C.report_table],
+                report.items.iter().map(|item| {
+                    tr![
+                        td![&item.name],
+                        td![C![C.report_amount], item.count.to_string()],
+                    ]
+                }),
+            ],
+            h2![strings::REPORT_CASH_COUNT],
+            table![
+                C![C.report_table],
+                DENOMINATIONS.iter().map(|&denomination| {
+                    let count = self.cash_count.get(&denomination);
+                    tr![
+                        td![fmt_money(Currency::from(denomination))],
+                        td![input![
+                            C![C.report_count_input, C.rounded, C.border_on_focus],
+                            attrs! {At::Type => "number", At::Min => 0},
+                            attrs! {At::Value => count.map(|c| c.to_string()).unwrap_or_default()},
+                            input_ev(Ev::Input, move |count| ReportMsg::SetCount {
+                                denomination,
+                                count,
+                            }),
+                        ]],
+                        td![
+                            C![C.report_amount],
+                            count
+                                .map(|&c| fmt_money(Currency::from(denomination * i64::from(c))))
+                                .unwrap_or_default(),
+                        ],
+                    ]
+                }),
+                tr![
+                    C![C.report_sum_row],
+                    td![strings::REPORT_COUNTED_CASH],
+                    td![],
+                    td![C![C.report_amount], fmt_money(counted)],
+                ],
+                tr![
+                    td![strings::REPORT_OPENING_FLOAT],
+                    td![input![
+                        C![C.report_count_input, C.rounded, C.border_on_focus],
+                        attrs! {At::Type => "number", At::Min => 0, At::Step => "any"},
+                        attrs! {
+                            At::Value => self.opening_float.map(|f| f.to_string()).unwrap_or_default()
+                        },
+                        input_ev(Ev::Input, ReportMsg::SetFloat),
+                    ]],
+                    td![C![C.report_amount], fmt_money(-opening_float)],
+                ],
+                tr![
+                    td![strings::REPORT_EXPECTED_CASH],
+                    td![],
+                    td![C![C.report_amount], fmt_money(report.expected_cash)],
+                ],
+                tr![
+                    C![C.report_sum_row],
+                    td![strings::REPORT_CASH_DIFFERENCE],
+                    td![],
+                    td![
+                        C![C.report_amount],
+                        fmt_money(counted - opening_float - report.expected_cash)
+                    ],
+                ],
+            ],
+            match &self.month {
+                Some(month) if !month.fees.is_empty() => vec![
+                    h2![strings::REPORT_MONTHLY_FEES],
+                    table![
+                        C![C.report_table],
+                        fee_header,
+                        month.days.iter().flat_map(|day| {
+                            day.fees.iter().map(move |fees| {
+                                let label = format!(
+                                    "{} {}",
+                                    day.date.format(DATE_INPUT_FMT),
+                                    provider_name(fees.provider)
+                                );
+                                fee_row(label, fees, false)
+                            })
+                        }),
+                        month.fees.iter().map(|fees| {
+                            fee_row(provider_name(fees.provider).to_string(), fees, true)
+                        }),
+                    ],
+                ],
+                _ => vec![],
+            },
+            div![
+                C![C.report_signature],
+                span![strings::REPORT_SIGNATURE],
+                span![C![C.report_signature_line]],
+            ],
+            branding
+                .and_then(|branding| branding.footer_text.as_ref())
+                .map(|footer| p![C![C.report_footer], footer]),
+        ]
+        .map_msg(Msg::ReportMsg)
+    }
+}
+
+fn provider_name(provider: PaymentProvider) -> &'static str {
+    match provider {
+        PaymentProvider::IZettle => "iZettle",
+        PaymentProvider::Swish => "Swish",
+    }
+}
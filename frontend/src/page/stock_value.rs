@@ -0,0 +1,488 @@
+use crate::app::Msg;
+use crate::generated::css_classes::C;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
+use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
+use crate::strings;
+use crate::util::fetch::{get_with_retry, send_with_retry};
+use crate::util::simple_ev;
+use crate::views::view_sparkline;
+use seed::prelude::*;
+use seed::*;
+use std::collections::HashSet;
+use strecklistan_api::{
+    analytics::{ItemSparklines, SPARKLINE_DAYS},
+    currency::Currency,
+    inventory::{InventoryBatchEdit, InventoryItemId, PriceAdjustment},
+    report::{StockValuation, StockValueReport},
+};
+
+#[derive(Clone, Debug)]
+pub enum StockValueMsg {
+    /// Fetch the report again
+    Reload,
+
+    Fetched(StockValuation, StockValueReport),
+    FetchFailed(String),
+    SparklinesFetched(ItemSparklines),
+    SetFifo(bool),
+    SetCostPrice(InventoryItemId, String),
+    CostPriceSaved,
+    CostPriceFailed(String),
+    Print,
+
+    /// Select an item for batch editing, or deselect it
+    ToggleSelected(InventoryItemId),
+    SelectAll(bool),
+    SetBatchCategory(String),
+    SetBatchPrice(String),
+    SetBatchPercent(bool),
+    SetBatchArchive(bool),
+    ApplyBatch,
+    BatchApplied,
+    BatchFailed(String),
+}
+
+/// The value of everything in stock, for the annual financial statement
+pub struct StockValuePage {
+    valuation: StockValuation,
+    report: Option<StockValueReport>,
+    error: Option<String>,
+
+    /// What every item has sold lately, to help decide what to restock
+    sparklines: Option<ItemSparklines>,
+
+    /// The items to change at once, and how
+    selected: HashSet<InventoryItemId>,
+    batch_category: String,
+    batch_price: String,
+    batch_percent: bool,
+    batch_archive: bool,
+}
+
+impl StockValuePage {
+    pub fn new(orders: &mut impl Orders<Msg>) -> Self {
+        let page = StockValuePage {
+            valuation: StockValuation::CostPrice,
+            report: None,
+            error: None,
+            sparklines: None,
+            selected: HashSet::new(),
+            batch_category: String::new(),
+            batch_price: String::new(),
+            batch_percent: false,
+            batch_archive: false,
+        };
+        page.fetch(orders);
+        fetch_sparklines(orders);
+        page
+    }
+
+    /// Read the valuation from the `fifo` url parameter
+    pub fn set_params(&mut self, params: &UrlParams, orders: &mut impl Orders<Msg>) {
+        let valuation = match params.parse("fifo") {
+            Some(true) => StockValuation::Fifo,
+            _ => StockValuation::CostPrice,
+        };
+
+        if valuation != self.valuation {
+            self.valuation = valuation;
+            self.report = None;
+            self.fetch(orders);
+        }
+    }
+
+    /// Reflect the state of the page in the url
+    pub fn update_url(&self) {
+        UrlParams::default()
+            .with(
+                "fifo",
+                (self.valuation == StockValuation::Fifo).then(|| true),
+            )
+            .replace_url(Page::StockValue);
+    }
+
+    fn fetch(&self, orders: &mut impl Orders<Msg>) {
+        let valuation = self.valuation;
+        orders.proxy(Msg::StockValueMsg).perform_cmd(async move {
+            let url = match valuation {
+                StockValuation::CostPrice => "/api/report/stock_value".to_string(),
+                StockValuation::Fifo => "/api/report/stock_value?fifo=true".to_string(),
+            };
+            let result = async { get_with_retry(url).await?.json().await }.await;
+            match result {
+                Ok(report) => StockValueMsg::Fetched(valuation, report),
+                Err(e) => StockValueMsg::FetchFailed(format!("{:?}", e)),
+            }
+        });
+    }
+
+    pub fn update(&mut self, msg: StockValueMsg, orders: &mut impl Orders<Msg>) {
+        match msg {
+            StockValueMsg::Reload => {
+                self.error = None;
+                self.fetch(orders);
+                fetch_sparklines(orders);
+            }
+            StockValueMsg::CostPriceSaved => {
+                self.error = None;
+                self.fetch(orders);
+            }
+            StockValueMsg::Fetched(valuation, report) => {
+                // ignore responses for valuations which are no longer selected
+                if valuation == self.valuation {
+                    self.report = Some(report);
+                }
+            }
+            StockValueMsg::FetchFailed(reason) => {
+                self.error = Some(reason);
+            }
+            StockValueMsg::SparklinesFetched(sparklines) => {
+                self.sparklines = Some(sparklines);
+            }
+            StockValueMsg::SetFifo(fifo) => {
+                self.valuation = if fifo {
+                    StockValuation::Fifo
+                } else {
+                    StockValuation::CostPrice
+                };
+                self.report = None;
+                self.fetch(orders);
+                self.update_url();
+            }
+            StockValueMsg::SetCostPrice(item_id, input) => {
+                let cost_price: Option<Currency> = match input.trim() {
+                    "" => None,
+                    input => match input.parse() {
+                        Ok(cost_price) => Some(cost_price),
+                        Err(_) => return,
+                    },
+                };
+                orders.proxy(Msg::StockValueMsg).perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/inventory/item/{}/cost_price", item_id))
+                                .method(Method::Put)
+                                .json(&cost_price)?,
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => StockValueMsg::CostPriceSaved,
+                        Err(e) => StockValueMsg::CostPriceFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            StockValueMsg::CostPriceFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::SAVE_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+            StockValueMsg::Print => {
+                if let Err(e) = window().print() {
+                    error!("Failed to print", e);
+                }
+            }
+            StockValueMsg::ToggleSelected(item_id) => {
+                if !self.selected.remove(&item_id) {
+                    self.selected.insert(item_id);
+                }
+            }
+            StockValueMsg::SelectAll(all) => {
+                self.selected = match (all, &self.report) {
+                    (true, Some(report)) => report.items.iter().map(|item| item.item_id).collect(),
+                    _ => HashSet::new(),
+                };
+            }
+            StockValueMsg::SetBatchCategory(category) => self.batch_category = category,
+            StockValueMsg::SetBatchPrice(price) => self.batch_price = price,
+            StockValueMsg::SetBatchPercent(percent) => self.batch_percent = percent,
+            StockValueMsg::SetBatchArchive(archive) => self.batch_archive = archive,
+            StockValueMsg::ApplyBatch => {
+                let price = match (self.batch_price.trim(), self.batch_percent) {
+                    ("", _) => None,
+                    (input, true) => match input.parse() {
+                        Ok(percent) => Some(PriceAdjustment::Percent(percent)),
+                        Err(_) => return,
+                    },
+                    (input, false) => match input.parse() {
+                        Ok(amount) => Some(PriceAdjustment::Amount(amount)),
+                        Err(_) => return,
+                    },
+                };
+                let category = self.batch_category.trim();
+                let edit = InventoryBatchEdit {
+                    item_ids: self.selected.iter().copied().collect(),
+                    category: (!category.is_empty()).then(|| category.to_string()),
+                    price,
+                    archive: self.batch_archive,
+                };
+                if edit.is_empty() {
+                    return;
+                }
+
+                // not retried, since a price adjustment must only be applied once
+                orders.proxy(Msg::StockValueMsg).perform_cmd(async move {
+                    let result = async {
+                        Request::new("/api/inventory/batch")
+                            .method(Method::Patch)
+                            .json(&edit)?
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => StockValueMsg::BatchApplied,
+                        Err(e) => StockValueMsg::BatchFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            StockValueMsg::BatchApplied => {
+                self.selected.clear();
+                self.batch_category.clear();
+                self.batch_price.clear();
+                self.batch_archive = false;
+                self.fetch(orders);
+            }
+            StockValueMsg::BatchFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::BATCH_EDIT_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+        }
+    }
+
+    pub fn view(&self) -> Node<Msg> {
+        let report = match (&self.report, &self.error) {
+            (Some(report), _) => report,
+            (None, Some(error)) => {
+                return div![
+                    C![C.report_page],
+                    p![strings::ERROR_OCCURRED],
+                    p![error],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::StockValueMsg(StockValueMsg::Reload)),
+                        strings::RETRY,
+                    ],
+                ]
+            }
+            (None, None) => return Loading::view_with_retry(),
+        };
+
+        let fmt_money = |amount: Currency| format!("{}:-", amount);
+        let fifo = report.valuation == StockValuation::Fifo;
+        let all_selected = !report.items.is_empty() && self.selected.len() == report.items.len();
+
+        div![
+            C![C.report_page],
+            div![
+                C![C.report_controls],
+                label![
+                    input![
+                        attrs! {At::Type => "checkbox"},
+                        attrs! {At::Checked => fifo.as_at_value()},
+                        ev(Ev::Change, move |_| StockValueMsg::SetFifo(!fifo)),
+                    ],
+                    strings::VALUATION_FIFO,
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, StockValueMsg::Print),
+                    strings::PRINT,
+                ],
+            ],
+            h1![strings::TITLE_STOCK_VALUE],
+            p![if fifo {
+                strings::VALUATION_FIFO_DESCRIPTION
+            } else {
+                strings::VALUATION_COST_PRICE_DESCRIPTION
+            }],
+            if self.selected.is_empty() {
+                empty![]
+            } else {
+                self.view_batch_edit()
+            },
+            table![
+                C![C.report_table],
+                tr![
+                    th![
+                        C![C.report_select],
+                        input![
+                            attrs! {At::Type => "checkbox"},
+                            attrs! {At::Checked => all_selected.as_at_value()},
+                            ev(Ev::Change, move |_| StockValueMsg::SelectAll(!all_selected)),
+                        ],
+                    ],
+                    th![strings::ITEM],
+                    th![strings::IN_STOCK_COUNT],
+                    th![strings::COST_PRICE],
+                    th![strings::STOCK_VALUE],
+                    th![C![C.report_sparkline], strings::SOLD_LAST_30_DAYS],
+                ],
+                report.items.iter().map(|item| {
+                    let item_id = item.item_id;
+                    tr![
+                        td![
+                            C![C.report_select],
+                            input![
+                                attrs! {At::Type => "checkbox"},
+                                attrs! {
+                                    At::Checked => self.selected.contains(&item_id).as_at_value()
+                                },
+                                ev(Ev::Change, move |_| StockValueMsg::ToggleSelected(item_id)),
+                            ],
+                        ],
+                        td![&item.name],
+                        td![C![C.report_amount], item.stock.to_string()],
+                        td![input![
+                            C![C.report_count_input, C.rounded, C.border_on_focus],
+                            attrs! {At::Type => "number", At::Min => 0, At::Step => "any"},
+                            attrs! {
+                                At::Value => item.cost_price.map(|c| c.to_string()).unwrap_or_default()
+                            },
+                            input_ev(Ev::Change, move |input| {
+                                StockValueMsg::SetCostPrice(item_id, input)
+                            }),
+                        ]],
+                        td![
+                            C![C.report_amount],
+                            item.value.map(fmt_money).unwrap_or_else(|| "?".to_string()),
+                        ],
+                        td![
+                            C![C.report_sparkline],
+                            match &self.sparklines {
+                                Some(sparklines) => {
+                                    let sold = sparklines
+                                        .sold
+                                        .get(&item_id)
+                                        .cloned()
+                                        .unwrap_or_else(|| vec![0; SPARKLINE_DAYS]);
+                                    div![
+                                        C![C.report_sparkline_chart],
+                                        view_sparkline(&sold),
+                                        span![sold.iter().sum::<i64>().to_string()],
+                                    ]
+                                }
+                                None => empty![],
+                            }
+                        ],
+                    ]
+                }),
+                tr![
+                    C![C.report_sum_row],
+                    td![C![C.report_select]],
+                    td![strings::STOCK_VALUE_TOTAL],
+                    td![],
+                    td![],
+                    td![C![C.report_amount], fmt_money(report.total())],
+                    td![C![C.report_sparkline]],
+                ],
+            ],
+            if report.is_incomplete() {
+                p![strings::STOCK_VALUE_INCOMPLETE]
+            } else {
+                empty![]
+            },
+            div![
+                C![C.report_signature],
+                span![strings::REPORT_SIGNATURE],
+                span![C![C.report_signature_line]],
+            ],
+        ]
+        .map_msg(Msg::StockValueMsg)
+    }
+
+    /// The changes to apply to all of the selected items
+    fn view_batch_edit(&self) -> Node<StockValueMsg> {
+        let percent = self.batch_percent;
+        let archive = self.batch_archive;
+        div![
+            C![C.report_controls, C.report_batch_edit],
+            span![format!(
+                "{} {}",
+                self.selected.len(),
+                strings::BATCH_SELECTED
+            )],
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::BATCH_CATEGORY_PLACEHOLDER,
+                    At::Value => self.batch_category,
+                },
+                input_ev(Ev::Input, StockValueMsg::SetBatchCategory),
+            ],
+            input![
+                C![C.report_count_input, C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Type => "number",
+                    At::Step => "any",
+                    At::Placeholder => strings::BATCH_PRICE_PLACEHOLDER,
+                    At::Value => self.batch_price,
+                },
+                input_ev(Ev::Input, StockValueMsg::SetBatchPrice),
+            ],
+            select![
+                C![C.rounded, C.border_on_focus],
+                option![
+                    attrs! {At::Value => "amount", At::Selected => (!percent).as_at_value()},
+                    "kr",
+                ],
+                option![
+                    attrs! {At::Value => "percent", At::Selected => percent.as_at_value()},
+                    "%",
+                ],
+                input_ev(Ev::Change, |value| {
+                    StockValueMsg::SetBatchPercent(value == "percent")
+                }),
+            ],
+            label![
+                input![
+                    attrs! {At::Type => "checkbox"},
+                    attrs! {At::Checked => archive.as_at_value()},
+                    ev(Ev::Change, move |_| StockValueMsg::SetBatchArchive(
+                        !archive
+                    )),
+                ],
+                strings::BATCH_ARCHIVE,
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                simple_ev(Ev::Click, StockValueMsg::ApplyBatch),
+                strings::BATCH_APPLY,
+            ],
+        ]
+    }
+}
+
+/// The sparklines are only a help, so the page works without them
+fn fetch_sparklines(orders: &mut impl Orders<Msg>) {
+    orders.proxy(Msg::StockValueMsg).perform_cmd(async move {
+        let url = "/api/analytics/item_sparklines".to_string();
+        let result = async { get_with_retry(url).await?.json().await }.await;
+        match result {
+            Ok(sparklines) => Some(StockValueMsg::SparklinesFetched(sparklines)),
+            Err(e) => {
+                error!("Failed to fetch the item sparklines", e);
+                None
+            }
+        }
+    });
+}
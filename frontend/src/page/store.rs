@@ -1,24 +1,43 @@
+use crate::achievements::{count_coffees, AchievementEvent};
 use crate::app::Msg;
-use crate::components::checkout::{Checkout, CheckoutMsg};
+use crate::components::checkout::{Checkout, CheckoutMsg, PurchaseError};
 use crate::components::izettle_pay::{IZettlePay, IZettlePayErr, IZettlePayMsg};
+use crate::components::locations::{LocationsMsg, LocationsPanel};
+use crate::components::store_grid::{StoreGrid, StoreGridMsg};
+use crate::components::suppliers::{SuppliersMsg, SuppliersPanel};
 use crate::fuzzy_search::{FuzzyScore, FuzzySearch};
 use crate::generated::css_classes::C;
-use crate::notification_manager::{Notification, NotificationMessage};
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
 use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
 use crate::strings;
-use crate::util::{compare_fuzzy, simple_ev};
+use crate::util::fetch::{get_with_retry, send_with_retry};
+use crate::util::{backup, compare_fuzzy, is_barcode, simple_ev};
 use crate::views::{view_inventory_bundle, view_inventory_item, view_tillgodo};
+use chrono::{NaiveDate, Utc};
+use itertools::Itertools;
 use seed::prelude::*;
 use seed::*;
 use seed_fetcher::{event, DontFetch, NotAvailable, ResourceStore, Resources};
 use std::collections::HashMap;
 use strecklistan_api::{
+    approval::GatedAction,
     book_account::{BookAccount, BookAccountId, MasterAccounts},
+    currency::Currency,
     inventory::{
-        InventoryBundle, InventoryBundleId, InventoryItemId, InventoryItemStock as InventoryItem,
+        describe_modified, ExpiringBatch, InventoryBundle, InventoryBundleId, InventoryItemId,
+        InventoryItemStock as InventoryItem, ItemModifier, ItemModifierId, StockShortage,
     },
+    location::LowStockAlert,
     member::{Member, MemberId},
+    parked_cart::{NewParkedCart, ParkedCart, ParkedCartId},
+    preferences::{QuickButtonsLayout, UserPreferences},
+    queue::QueueTicket,
+    store_layout::QuickButtonTarget,
+    time::{local_date, start_of_day, Tz},
+    transaction::{NewTransaction, TransactionId},
 };
+use wasm_bindgen::JsCast;
 
 #[derive(Clone, Debug)]
 enum StoreItemId {
@@ -50,7 +69,7 @@ impl StoreItem<'_> {
 
     pub fn in_stock(&self) -> bool {
         match self {
-            StoreItem::Item(item) => item.stock > 0,
+            StoreItem::Item(item) => item.sellable() > 0,
             StoreItem::Bundle(_) => true,
         }
     }
@@ -79,6 +98,9 @@ impl FuzzySearch for Member {
 
 #[derive(Clone, Debug)]
 pub enum StoreMsg {
+    /// Fetch all resources of the page again
+    Reload,
+
     ResFetched(event::Fetched),
     ResMarkDirty(event::MarkDirty),
 
@@ -92,15 +114,54 @@ pub enum StoreMsg {
         message_title: String,
         message_body: Option<String>,
     },
+    /// The card payment was cancelled, take the purchase in cash instead
+    PayWithCash,
+
+    /// The card payment wasn't started, since an admin has to approve the purchase first
+    ApprovalRequired(GatedAction),
 
     SearchInput(String),
     SearchKeyDown(web_sys::KeyboardEvent),
 
     CheckoutMsg(CheckoutMsg),
+    GridMsg(StoreGridMsg),
+    SuppliersMsg(SuppliersMsg),
+    LocationsMsg(LocationsMsg),
+
+    /// Key presses anywhere on the page, used for keyboard shortcuts
+    GlobalKeyDown(web_sys::KeyboardEvent),
+    ToggleShortcuts,
+
+    ToggleParkedCarts,
+    ParkNameInput(String),
+    ParkCart,
+    CartParked,
+    ResumeCart(ParkedCartId),
+    DeleteParkedCart(ParkedCartId),
+    ParkedCartsChanged,
+    ParkedCartsFailed(String),
+
+    UndoPurchase(TransactionId),
+    PurchaseUndone,
+    UndoFailed(String),
+
+    ToggleModifier(ItemModifierId),
+    ConfirmModifiers,
+    CancelModifiers,
+}
+
+/// An item which is about to be added to the cart, once its variants and add-ons are chosen
+struct ModifierPicker {
+    item_id: InventoryItemId,
+    amount: i32,
+    selected: Vec<ItemModifierId>,
 }
 
 pub struct StorePage {
     checkout: Checkout,
+    grid: StoreGrid,
+    suppliers: SuppliersPanel,
+    locations: LocationsPanel,
 
     inventory_search_string: String,
     inventory_search: Vec<(FuzzyScore, StoreItemId)>,
@@ -110,6 +171,20 @@ pub struct StorePage {
 
     izettle_pay: IZettlePay,
     izettle: bool,
+
+    /// Quantity typed with the number keys, used for the next added item
+    quantity: Option<i32>,
+    show_shortcuts: bool,
+    _key_stream: StreamHandle,
+
+    show_parked_carts: bool,
+    park_name: String,
+
+    modifier_picker: Option<ModifierPicker>,
+
+    /// Sales are kept out of the books, so card payments don't go through iZettle, and there is
+    /// nothing to undo or to show in the queue
+    training: bool,
 }
 
 #[derive(Resources)]
@@ -117,6 +192,9 @@ struct Res<'a> {
     #[url = "/api/inventory/bundles"]
     bundles: &'a HashMap<InventoryBundleId, InventoryBundle>,
 
+    #[url = "/api/inventory/modifiers"]
+    modifiers: &'a HashMap<ItemModifierId, ItemModifier>,
+
     #[url = "/api/inventory/items"]
     #[policy = "SilentRefetch"]
     inventory: &'a HashMap<InventoryItemId, InventoryItem>,
@@ -131,17 +209,59 @@ struct Res<'a> {
     #[url = "/api/members"]
     members: &'a HashMap<MemberId, Member>,
 
+    #[url = "/api/inventory/barcodes"]
+    barcodes: &'a HashMap<String, InventoryItemId>,
+
+    #[url = "/api/inventory/popular"]
+    #[policy = "SilentRefetch"]
+    popular: &'a Vec<InventoryItemId>,
+
+    #[url = "/api/inventory/expiring"]
+    #[policy = "SilentRefetch"]
+    expiring: &'a Vec<ExpiringBatch>,
+
+    #[url = "/api/parked_carts"]
+    #[policy = "SilentRefetch"]
+    parked_carts: &'a Vec<ParkedCart>,
+
+    #[url = "/api/inventory/low_stock"]
+    #[policy = "SilentRefetch"]
+    low_stock: &'a Vec<LowStockAlert>,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
+
     #[url = "/api/transactions"]
     #[allow(dead_code)]
     transactions: DontFetch,
 }
 
+/// Max number of entries in the favorites row
+const MAX_FAVORITES: usize = 8;
+
+/// For how long a completed purchase can be undone
+const UNDO_PURCHASE_MS: u32 = 30000;
+
+/// For how long the queue number of a sale is shown, so that it can be told to the customer
+const QUEUE_NUMBER_MS: u32 = 60000;
+
+/// Upper limit for quantities typed with the number keys
+const MAX_QUANTITY: i32 = 999;
+
+const INVENTORY_SEARCH_ID: &str = "inventory_search";
+
 impl StorePage {
     pub fn new(rs: &ResourceStore, orders: &mut impl Orders<StoreMsg>) -> Self {
         orders.subscribe(StoreMsg::ResFetched);
         orders.subscribe(StoreMsg::ResMarkDirty);
+        let key_stream = orders.stream_with_handle(streams::window_event(Ev::KeyDown, |event| {
+            StoreMsg::GlobalKeyDown(event.unchecked_into())
+        }));
         let mut p = StorePage {
             checkout: Checkout::new(rs, &mut orders.proxy(StoreMsg::CheckoutMsg)),
+            grid: StoreGrid::new(rs, &mut orders.proxy(StoreMsg::GridMsg)),
+            suppliers: SuppliersPanel::new(rs, &mut orders.proxy(StoreMsg::SuppliersMsg)),
+            locations: LocationsPanel::new(rs, &mut orders.proxy(StoreMsg::LocationsMsg)),
 
             inventory_search_string: String::new(),
             inventory_search: vec![],
@@ -151,6 +271,17 @@ impl StorePage {
 
             izettle_pay: IZettlePay::new(),
             izettle: true,
+
+            quantity: None,
+            show_shortcuts: false,
+            _key_stream: key_stream,
+
+            show_parked_carts: false,
+            park_name: String::new(),
+
+            modifier_picker: None,
+
+            training: false,
         };
         if let Ok(state) = Res::acquire(rs, orders) {
             p.rebuild_data(&state);
@@ -158,12 +289,67 @@ impl StorePage {
         p
     }
 
+    /// Select the grid tab given by the `tab` url parameter
+    pub fn set_params(&mut self, params: &UrlParams) {
+        if let Some(tab) = params.parse("tab") {
+            if !self.grid.editing {
+                self.grid.select_tab(tab);
+            }
+        }
+    }
+
+    pub fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+
+    /// Whether there is a cart which would be lost when leaving the page, or a purchase which
+    /// couldn't be put back in it if it fails
+    pub fn has_unsaved_changes(&self) -> bool {
+        !self.checkout.is_empty() || self.checkout.has_pending()
+    }
+
+    /// Keep the cart in the backup of the tab, so that it survives a reload
+    pub fn save_backup(&self) {
+        backup::save(backup::CART, self.checkout.backup());
+    }
+
+    /// Put back the cart from before the page was reloaded, if it was backed up
+    pub fn restore_backup(&mut self, rs: &ResourceStore) {
+        if let Some(cart) = backup::load(backup::CART) {
+            self.checkout.restore_backup(cart, rs);
+        }
+    }
+
+    /// Reflect the state of the page in the url
+    pub fn update_url(&self) {
+        let tab = self.grid.selected_tab();
+        UrlParams::default()
+            .with("tab", Some(tab).filter(|&tab| tab > 0))
+            .replace_url(Page::Store);
+    }
+
     pub fn update(
         &mut self,
         msg: StoreMsg,
         rs: &ResourceStore,
         orders: &mut impl Orders<Msg>,
     ) -> Result<(), NotAvailable> {
+        if let StoreMsg::Reload = msg {
+            rs.mark_as_dirty(Res::bundles_url(), orders);
+            rs.mark_as_dirty(Res::modifiers_url(), orders);
+            rs.mark_as_dirty(Res::inventory_url(), orders);
+            rs.mark_as_dirty(Res::book_accounts_url(), orders);
+            rs.mark_as_dirty(Res::master_accounts_url(), orders);
+            rs.mark_as_dirty(Res::members_url(), orders);
+            rs.mark_as_dirty(Res::barcodes_url(), orders);
+            rs.mark_as_dirty(Res::popular_url(), orders);
+            rs.mark_as_dirty(Res::expiring_url(), orders);
+            rs.mark_as_dirty(Res::parked_carts_url(), orders);
+            rs.mark_as_dirty(Res::low_stock_url(), orders);
+            rs.mark_as_dirty(Res::reporting_timezone_url(), orders);
+            return Ok(());
+        }
+
         let res = Res::acquire(rs, orders)?;
 
         let mut orders_local = orders.proxy(Msg::StoreMsg);
@@ -173,8 +359,12 @@ impl StorePage {
                 if Res::has_resource(resource) {
                     self.rebuild_data(&res);
                 }
+
+                // a cart restored from the backup may have been priced before the checkout had
+                // what it needs
+                self.checkout.recompute_new_transaction_total(rs);
             }
-            StoreMsg::ResMarkDirty(_) => {}
+            StoreMsg::ResMarkDirty(_) | StoreMsg::Reload => {}
             StoreMsg::SearchDebit(input) => {
                 self.tillgodolista_search_string = input;
                 self.sort_tillgodolista_search(&res);
@@ -191,7 +381,7 @@ impl StorePage {
             StoreMsg::DebitSelect(acc_id) => {
                 self.izettle = false;
                 self.tillgodolista_search_string = String::new();
-                self.checkout.set_debited(acc_id);
+                self.checkout.set_debited(acc_id, rs);
             }
 
             StoreMsg::SearchInput(input) => {
@@ -199,7 +389,35 @@ impl StorePage {
                 self.sort_store_list(&res);
             }
             StoreMsg::SearchKeyDown(ev) => match ev.key().as_str() {
-                "Enter" => match self.inventory_search.first() {
+                // barcode scanners type the code followed by enter
+                "Enter" if is_barcode(&self.inventory_search_string) => {
+                    match res.barcodes.get(&self.inventory_search_string) {
+                        Some(&item_id) => {
+                            self.inventory_search_string.clear();
+                            self.sort_store_list(&res);
+                            let msg =
+                                StoreMsg::CheckoutMsg(CheckoutMsg::AddItem { item_id, amount: 1 });
+                            self.update(msg, rs, orders)?;
+                        }
+                        None => {
+                            orders.send_msg(Msg::NotificationMessage(
+                                NotificationMessage::ShowNotification {
+                                    duration_ms: 5000,
+                                    notification: Notification::new(
+                                        NotificationLevel::Warning,
+                                        strings::UNKNOWN_BARCODE,
+                                    )
+                                    .with_body(self.inventory_search_string.clone()),
+                                },
+                            ));
+                        }
+                    }
+                }
+                "Enter" => match self
+                    .inventory_search
+                    .first()
+                    .filter(|(score, _)| score.is_full_match(&self.inventory_search_string))
+                {
                     Some((_, StoreItemId::Item(item_id))) => {
                         let msg = StoreMsg::CheckoutMsg(CheckoutMsg::AddItem {
                             item_id: *item_id,
@@ -229,6 +447,7 @@ impl StorePage {
                         message_title: strings::PAYMENT_CANCELLED.to_string(),
                         message_body: None,
                     }),
+                    IZettlePayMsg::SwitchedToCash => Some(StoreMsg::PayWithCash),
                     IZettlePayMsg::Error(IZettlePayErr::PaymentFailed { reason, .. }) => {
                         Some(StoreMsg::CancelIZettle {
                             message_title: strings::PAYMENT_FAILED.to_string(),
@@ -247,7 +466,26 @@ impl StorePage {
                             message_body: Some(reason.clone()),
                         })
                     }
-                    IZettlePayMsg::PollPendingPayment(_) => None,
+                    IZettlePayMsg::Error(IZettlePayErr::Unavailable { reason }) => {
+                        Some(StoreMsg::CancelIZettle {
+                            message_title: strings::PAYMENT_FAILED.to_string(),
+                            message_body: Some(reason.clone()),
+                        })
+                    }
+                    IZettlePayMsg::Error(IZettlePayErr::InsufficientStock(shortages)) => Some(
+                        StoreMsg::CheckoutMsg(CheckoutMsg::InsufficientStock(shortages.clone())),
+                    ),
+                    &IZettlePayMsg::Error(IZettlePayErr::ApprovalRequired(action)) => {
+                        Some(StoreMsg::ApprovalRequired(action))
+                    }
+                    IZettlePayMsg::PaymentStarted(_)
+                    | IZettlePayMsg::StatusChanged { .. }
+                    | IZettlePayMsg::StreamFailed(_)
+                    | IZettlePayMsg::PollPendingPayment(_)
+                    | IZettlePayMsg::PaymentUpdated { .. }
+                    | IZettlePayMsg::Tick
+                    | IZettlePayMsg::Cancel { .. }
+                    | IZettlePayMsg::CancelFailed => None,
                 };
 
                 if let Some(msg) = reaction {
@@ -267,6 +505,34 @@ impl StorePage {
                 self.izettle = true;
             }
 
+            StoreMsg::PayWithCash => {
+                self.checkout.disabled = false;
+                self.checkout.confirm_button_message = None;
+                self.update(
+                    StoreMsg::DebitSelect(res.master_accounts.cash_account_id),
+                    rs,
+                    orders,
+                )?;
+                self.update(
+                    StoreMsg::CheckoutMsg(CheckoutMsg::ConfirmPurchase),
+                    rs,
+                    orders,
+                )?;
+            }
+
+            StoreMsg::ApprovalRequired(action) => {
+                self.checkout.disabled = false;
+                self.checkout.confirm_button_message = None;
+
+                // the payment is started again once an admin has approved it
+                orders.send_msg(Msg::RequestApproval {
+                    action,
+                    retry: Box::new(Msg::StoreMsg(StoreMsg::CheckoutMsg(
+                        CheckoutMsg::ConfirmPurchase,
+                    ))),
+                });
+            }
+
             StoreMsg::CancelIZettle {
                 message_title,
                 message_body,
@@ -276,18 +542,337 @@ impl StorePage {
                 orders.send_msg(Msg::NotificationMessage(
                     NotificationMessage::ShowNotification {
                         duration_ms: 10000,
-                        notification: Notification {
-                            title: message_title,
-                            body: message_body,
+                        notification: Notification::new(NotificationLevel::Error, message_title)
+                            .with_body(message_body),
+                    },
+                ));
+            }
+
+            StoreMsg::GlobalKeyDown(ev) => {
+                if self.grid.editing {
+                    return Ok(());
+                }
+
+                let in_input = ev
+                    .target()
+                    .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                    .map(|element| {
+                        matches!(
+                            element.tag_name().as_str(),
+                            "INPUT" | "TEXTAREA" | "SELECT" | "BUTTON"
+                        )
+                    })
+                    .unwrap_or(false);
+
+                let key = ev.key();
+                if in_input {
+                    if key == "Escape" {
+                        blur_active_element();
+                    }
+                    return Ok(());
+                }
+
+                match key.as_str() {
+                    "/" => {
+                        ev.prevent_default();
+                        focus_element(INVENTORY_SEARCH_ID);
+                    }
+                    "?" => self.show_shortcuts = !self.show_shortcuts,
+                    "Enter" if self.modifier_picker.is_some() => {
+                        ev.prevent_default();
+                        self.update(StoreMsg::ConfirmModifiers, rs, orders)?;
+                    }
+                    "Escape" if self.modifier_picker.is_some() => self.modifier_picker = None,
+                    "Enter" => {
+                        ev.prevent_default();
+                        if !self.checkout.is_empty() && !self.checkout.disabled {
+                            let msg = StoreMsg::CheckoutMsg(CheckoutMsg::ConfirmPurchase);
+                            self.update(msg, rs, orders)?;
+                        }
+                    }
+                    "Escape" => {
+                        self.quantity = None;
+                        self.show_shortcuts = false;
+                        let msg = StoreMsg::CheckoutMsg(CheckoutMsg::ClearCart);
+                        self.update(msg, rs, orders)?;
+                    }
+                    "Backspace" => {
+                        self.quantity = self.quantity.map(|q| q / 10).filter(|&q| q > 0);
+                    }
+                    _ => {
+                        if let Some(digit) = key.chars().next().and_then(|c| c.to_digit(10)) {
+                            if key.len() == 1 {
+                                let quantity = self.quantity.unwrap_or(0) * 10 + digit as i32;
+                                self.quantity = Some(quantity.min(MAX_QUANTITY)).filter(|&q| q > 0);
+                            }
+                        }
+                    }
+                }
+            }
+            StoreMsg::ToggleShortcuts => self.show_shortcuts = !self.show_shortcuts,
+
+            StoreMsg::ToggleParkedCarts => self.show_parked_carts = !self.show_parked_carts,
+            StoreMsg::ParkNameInput(input) => self.park_name = input,
+            StoreMsg::ParkCart => {
+                if self.checkout.is_empty() || self.checkout.disabled {
+                    return Ok(());
+                }
+
+                let name = match self.park_name.trim() {
+                    "" => format!("{} {}", strings::PARKED_CART, res.parked_carts.len() + 1),
+                    name => name.to_string(),
+                };
+                let cart = NewParkedCart {
+                    name,
+                    // iZettle payments are started over when the cart is resumed
+                    debited_account: if self.izettle {
+                        None
+                    } else {
+                        self.checkout.debited_account
+                    },
+                    bundles: self.checkout.bundles().to_vec(),
+                };
+
+                self.checkout.disabled = true;
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        Request::new("/api/parked_cart")
+                            .method(Method::Post)
+                            .json(&cart)?
+                            .fetch()
+                            .await?
+                            .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => StoreMsg::CartParked,
+                        Err(e) => StoreMsg::ParkedCartsFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            StoreMsg::CartParked => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 5000,
+                        notification: Notification::new(
+                            NotificationLevel::Info,
+                            strings::CART_PARKED,
+                        )
+                        .with_body(self.park_name.clone()),
+                    },
+                ));
+                self.park_name.clear();
+                self.quantity = None;
+                self.checkout = Checkout::new(
+                    rs,
+                    &mut orders.proxy(Msg::StoreMsg).proxy(StoreMsg::CheckoutMsg),
+                );
+                self.izettle = true;
+                rs.mark_as_dirty(Res::parked_carts_url(), orders);
+            }
+            StoreMsg::ResumeCart(cart_id) => {
+                if !self.checkout.is_empty() {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 5000,
+                            notification: Notification::new(
+                                NotificationLevel::Warning,
+                                strings::CART_NOT_EMPTY,
+                            )
+                            .with_body(strings::PARK_CART_FIRST.to_string()),
                         },
+                    ));
+                    return Ok(());
+                }
+
+                if let Some(cart) = res.parked_carts.iter().find(|cart| cart.id == cart_id) {
+                    self.checkout
+                        .restore(cart.bundles.clone(), cart.debited_account, rs);
+                    self.izettle = cart.debited_account.is_none();
+                    self.show_parked_carts = false;
+                    self.update(StoreMsg::DeleteParkedCart(cart_id), rs, orders)?;
+                }
+            }
+            StoreMsg::DeleteParkedCart(cart_id) => {
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/parked_cart/{}", cart_id))
+                                .method(Method::Delete),
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => StoreMsg::ParkedCartsChanged,
+                        Err(e) => StoreMsg::ParkedCartsFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            StoreMsg::UndoPurchase(transaction_id) => {
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        send_with_retry(
+                            Request::new(format!("/api/transaction/{}", transaction_id))
+                                .method(Method::Delete),
+                        )
+                        .await?
+                        .check_status()
+                    }
+                    .await;
+                    match result {
+                        Ok(_) => StoreMsg::PurchaseUndone,
+                        Err(e) => StoreMsg::UndoFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            StoreMsg::PurchaseUndone => {
+                self.mark_sales_dirty(rs, orders);
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 5000,
+                        notification: Notification::new(
+                            NotificationLevel::Success,
+                            strings::PURCHASE_UNDONE,
+                        ),
+                    },
+                ));
+            }
+            StoreMsg::UndoFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::UNDO_PURCHASE_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+
+            StoreMsg::ToggleModifier(modifier_id) => {
+                if let (Some(picker), Some(modifier)) = (
+                    self.modifier_picker.as_mut(),
+                    res.modifiers.get(&modifier_id),
+                ) {
+                    if picker.selected.contains(&modifier_id) {
+                        picker.selected.retain(|&id| id != modifier_id);
+                    } else {
+                        // choosing an alternative deselects the others in its group
+                        if modifier.group.is_some() {
+                            picker.selected.retain(|id| {
+                                res.modifiers.get(id).map(|m| &m.group) != Some(&modifier.group)
+                            });
+                        }
+                        picker.selected.push(modifier_id);
+                    }
+                }
+            }
+            StoreMsg::ConfirmModifiers => {
+                if let Some(picker) = self.modifier_picker.take() {
+                    let msg = CheckoutMsg::AddModifiedItem {
+                        item_id: picker.item_id,
+                        modifier_ids: picker.selected,
+                        amount: picker.amount,
+                    };
+                    self.update(StoreMsg::CheckoutMsg(msg), rs, orders)?;
+                }
+            }
+            StoreMsg::CancelModifiers => self.modifier_picker = None,
+
+            StoreMsg::ParkedCartsChanged => {
+                rs.mark_as_dirty(Res::parked_carts_url(), orders);
+            }
+            StoreMsg::ParkedCartsFailed(reason) => {
+                self.checkout.disabled = false;
+                rs.mark_as_dirty(Res::parked_carts_url(), orders);
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::PARKED_CARTS_FAILED,
+                        )
+                        .with_body(reason),
                     },
                 ));
             }
 
+            StoreMsg::SuppliersMsg(msg) => {
+                if let SuppliersMsg::Failed(reason) = &msg {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::SAVE_FAILED,
+                            )
+                            .with_body(reason.clone()),
+                        },
+                    ));
+                }
+
+                self.suppliers.update(
+                    msg,
+                    rs,
+                    &mut orders.proxy(Msg::StoreMsg).proxy(StoreMsg::SuppliersMsg),
+                )?;
+            }
+
+            StoreMsg::LocationsMsg(msg) => {
+                if let LocationsMsg::Failed(reason) = &msg {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::SAVE_FAILED,
+                            )
+                            .with_body(reason.clone()),
+                        },
+                    ));
+                }
+
+                self.locations.update(
+                    msg,
+                    rs,
+                    &mut orders.proxy(Msg::StoreMsg).proxy(StoreMsg::LocationsMsg),
+                )?;
+            }
+
             StoreMsg::CheckoutMsg(msg) => {
+                // apply a quantity typed with the number keys
+                let msg = match (msg, self.quantity.take()) {
+                    (CheckoutMsg::AddItem { item_id, .. }, Some(amount)) => {
+                        CheckoutMsg::AddItem { item_id, amount }
+                    }
+                    (CheckoutMsg::AddBundle { bundle_id, .. }, Some(amount)) => {
+                        CheckoutMsg::AddBundle { bundle_id, amount }
+                    }
+                    (msg, quantity) => {
+                        self.quantity = quantity;
+                        msg
+                    }
+                };
+
                 let forward_msg = match msg {
+                    // the ID of the buyer is checked before age-restricted items are sold
+                    CheckoutMsg::ConfirmPurchase if self.checkout.ask_for_age_check(rs) => None,
+                    // items with variants are added once the variants have been chosen
+                    CheckoutMsg::AddItem { item_id, amount }
+                        if !item_modifiers(&res, item_id).is_empty() =>
+                    {
+                        self.modifier_picker = Some(ModifierPicker {
+                            item_id,
+                            amount,
+                            selected: vec![],
+                        });
+                        None
+                    }
                     // if iZettle integration is enabled we intercept and handle the purchase here
-                    CheckoutMsg::ConfirmPurchase if self.izettle => {
+                    CheckoutMsg::ConfirmPurchase if self.izettle && !self.training => {
                         if let Some(transaction) = self.checkout.build_transaction(rs) {
                             self.checkout.disabled = true;
                             self.checkout.remove_cleared_items();
@@ -298,29 +883,102 @@ impl StorePage {
                         }
                         None // don't forward the message
                     }
+                    // the cart is cleared right away, so the payment method is reset with it
+                    CheckoutMsg::ConfirmPurchase => {
+                        if self.checkout.build_transaction(rs).is_some() {
+                            self.izettle = true;
+                        }
+                        Some(CheckoutMsg::ConfirmPurchase)
+                    }
                     // show a notification & reload inventory when a purchase completes
-                    CheckoutMsg::PurchaseSent { .. } => {
-                        rs.mark_as_dirty(Res::inventory_url(), orders);
-                        rs.mark_as_dirty(Res::book_accounts_url(), orders);
-                        rs.mark_as_dirty(Res::transactions_url(), orders);
+                    CheckoutMsg::PurchaseSent { transaction_id } => {
+                        if let Some(transaction) = self.checkout.build_transaction(rs) {
+                            record_sale(transaction_id, &transaction, &res, orders);
+                        }
+                        let amount = self.checkout.transaction_amount();
+                        self.show_purchase_complete(transaction_id, amount, rs, orders);
+                        self.izettle = true;
+                        Some(CheckoutMsg::PurchaseSent { transaction_id })
+                    }
+                    CheckoutMsg::PurchaseSaved {
+                        idempotency_key,
+                        transaction_id,
+                        amount,
+                    } => {
+                        if self.training {
+                            self.show_training_sale(amount, orders);
+                        } else {
+                            if let Some(transaction) =
+                                self.checkout.sent_transaction(&idempotency_key)
+                            {
+                                record_sale(transaction_id, transaction, &res, orders);
+                            }
+                            self.show_purchase_complete(transaction_id, amount, rs, orders);
+                        }
+                        Some(CheckoutMsg::PurchaseSaved {
+                            idempotency_key,
+                            transaction_id,
+                            amount,
+                        })
+                    }
+                    // the purchase is put back in the cart, together with its payment method
+                    CheckoutMsg::PurchaseFailed {
+                        idempotency_key,
+                        error,
+                    } => {
+                        let notification = match &error {
+                            PurchaseError::InsufficientStock(shortages) => Some(
+                                Notification::new(
+                                    NotificationLevel::Error,
+                                    strings::INSUFFICIENT_STOCK,
+                                )
+                                .with_body(describe_shortages(shortages)),
+                            ),
+                            // the purchase is sent again once an admin has approved it
+                            &PurchaseError::ApprovalRequired(action) => {
+                                orders.send_msg(Msg::RequestApproval {
+                                    action,
+                                    retry: Box::new(Msg::StoreMsg(StoreMsg::CheckoutMsg(
+                                        CheckoutMsg::ConfirmPurchase,
+                                    ))),
+                                });
+                                None
+                            }
+                            PurchaseError::Failed => Some(
+                                Notification::new(
+                                    NotificationLevel::Error,
+                                    strings::PURCHASE_NOT_SAVED,
+                                )
+                                .with_body(strings::CART_RESTORED.to_string()),
+                            ),
+                        };
+                        if let Some(notification) = notification {
+                            orders.send_msg(Msg::NotificationMessage(
+                                NotificationMessage::ShowNotification {
+                                    duration_ms: 10000,
+                                    notification,
+                                },
+                            ));
+                        }
+                        self.izettle = false;
+                        Some(CheckoutMsg::PurchaseFailed {
+                            idempotency_key,
+                            error,
+                        })
+                    }
+                    CheckoutMsg::InsufficientStock(shortages) => {
+                        self.checkout.confirm_button_message = None;
                         orders.send_msg(Msg::NotificationMessage(
                             NotificationMessage::ShowNotification {
-                                duration_ms: 5000,
-                                notification: Notification {
-                                    title: strings::PURCHASE_COMPLETE.to_string(),
-                                    body: Some(format!(
-                                        "Total: {}:-",
-                                        self.checkout.transaction_amount(),
-                                    )),
-                                },
+                                duration_ms: 10000,
+                                notification: Notification::new(
+                                    NotificationLevel::Error,
+                                    strings::INSUFFICIENT_STOCK,
+                                )
+                                .with_body(describe_shortages(&shortages)),
                             },
                         ));
-                        self.checkout = Checkout::new(
-                            rs,
-                            &mut orders.proxy(Msg::StoreMsg).proxy(StoreMsg::CheckoutMsg),
-                        );
-                        self.izettle = true;
-                        None
+                        Some(CheckoutMsg::InsufficientStock(shortages))
                     }
                     msg => Some(msg),
                 };
@@ -333,17 +991,117 @@ impl StorePage {
                     );
                 }
             }
+
+            StoreMsg::GridMsg(msg) => {
+                let tab_selected = matches!(msg, StoreGridMsg::SelectTab(_));
+                match &msg {
+                    StoreGridMsg::Activate(target) => {
+                        let msg = match *target {
+                            QuickButtonTarget::Item(item_id) => {
+                                CheckoutMsg::AddItem { item_id, amount: 1 }
+                            }
+                            QuickButtonTarget::Bundle(bundle_id) => CheckoutMsg::AddBundle {
+                                bundle_id,
+                                amount: 1,
+                            },
+                        };
+                        self.update(StoreMsg::CheckoutMsg(msg), rs, orders)?;
+                    }
+                    StoreGridMsg::SaveFailed(reason) => {
+                        orders.send_msg(Msg::NotificationMessage(
+                            NotificationMessage::ShowNotification {
+                                duration_ms: 10000,
+                                notification: Notification::new(
+                                    NotificationLevel::Error,
+                                    strings::LAYOUT_SAVE_FAILED,
+                                )
+                                .with_body(reason.clone())
+                                .with_action(
+                                    strings::RETRY,
+                                    Msg::StoreMsg(StoreMsg::GridMsg(StoreGridMsg::Save)),
+                                ),
+                            },
+                        ));
+                    }
+                    _ => {}
+                }
+
+                self.grid.update(
+                    msg,
+                    rs,
+                    &mut orders.proxy(Msg::StoreMsg).proxy(StoreMsg::GridMsg),
+                )?;
+
+                if tab_selected {
+                    self.update_url();
+                }
+            }
         }
 
         Ok(())
     }
 
+    /// Show a notification, with a button for undoing the purchase
+    fn show_purchase_complete(
+        &self,
+        transaction_id: TransactionId,
+        amount: Currency,
+        rs: &ResourceStore,
+        orders: &mut impl Orders<Msg>,
+    ) {
+        self.mark_sales_dirty(rs, orders);
+        orders.send_msg(Msg::NotificationMessage(
+            NotificationMessage::ShowNotification {
+                duration_ms: UNDO_PURCHASE_MS,
+                notification: Notification::new(
+                    NotificationLevel::Success,
+                    strings::PURCHASE_COMPLETE,
+                )
+                .with_body(format!("Total: {}:-", amount))
+                .with_action(
+                    strings::UNDO,
+                    Msg::StoreMsg(StoreMsg::UndoPurchase(transaction_id)),
+                ),
+            },
+        ));
+        show_queue_number(transaction_id, orders);
+    }
+
+    /// Like [show_purchase_complete](Self::show_purchase_complete), for a sale which was only
+    /// made in training mode and so didn't change anything
+    fn show_training_sale(&self, amount: Currency, orders: &mut impl Orders<Msg>) {
+        orders.send_msg(Msg::NotificationMessage(
+            NotificationMessage::ShowNotification {
+                duration_ms: UNDO_PURCHASE_MS,
+                notification: Notification::new(
+                    NotificationLevel::Success,
+                    strings::PURCHASE_COMPLETE,
+                )
+                .with_body(format!(
+                    "{} – Total: {}:-",
+                    strings::TRAINING_MODE,
+                    amount
+                )),
+            },
+        ));
+    }
+
+    /// Reload everything which is affected by a purchase
+    fn mark_sales_dirty(&self, rs: &ResourceStore, orders: &mut impl Orders<Msg>) {
+        rs.mark_as_dirty(Res::inventory_url(), orders);
+        rs.mark_as_dirty(Res::popular_url(), orders);
+        rs.mark_as_dirty(Res::expiring_url(), orders);
+        rs.mark_as_dirty(Res::low_stock_url(), orders);
+        rs.mark_as_dirty(Res::book_accounts_url(), orders);
+        rs.mark_as_dirty(Res::transactions_url(), orders);
+    }
+
     fn rebuild_data(&mut self, res: &Res) {
         let items = res
             .inventory
             .values()
-            // Don't show items without a default price in the store view
-            .filter(|item| item.price.is_some())
+            // Don't show archived items or items without a default price in the store view
+            .filter(|item| item.is_for_sale())
             .map(|item| (Default::default(), StoreItemId::Item(item.id)));
 
         let bundles = res
@@ -360,6 +1118,12 @@ impl StorePage {
                 acc.creditor
                     .map(|member_id| (Default::default(), acc.id, member_id))
             })
+            .filter(|(_, _, member_id)| {
+                res.members
+                    .get(member_id)
+                    .map(|member| member.archived_at.is_none())
+                    .unwrap_or(false)
+            })
             .collect();
 
         self.sort_tillgodolista_search(res);
@@ -405,10 +1169,21 @@ impl StorePage {
             });
     }
 
-    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+    /// Only admins may edit the layout of the quick buttons
+    pub fn view(
+        &self,
+        rs: &ResourceStore,
+        preferences: &UserPreferences,
+        is_admin: bool,
+    ) -> Node<Msg> {
         let res = match Res::acquire_now(rs) {
             Ok(res) => res,
-            Err(_) => return Loading::view(),
+            Err(_) => {
+                return Loading::with_retry(div![
+                    C![C.store_page],
+                    div![C![C.store_inventory_area], Loading::skeleton_grid()],
+                ])
+            }
         };
 
         #[derive(PartialEq)]
@@ -454,9 +1229,10 @@ impl StorePage {
                                         .book_accounts
                                         .get(&self.checkout.debited_account.unwrap_or(
                                             res.master_accounts.bank_account_id))
+                                        .map(|acc| self.checkout.pending_balance(acc))
                                         .map(|acc| format!("{}: {}:-", acc.name, acc.balance))
                                         .unwrap_or("[MISSING]".into()),
-                                    _ => "Tillgodolista".into(),
+                                    _ => strings::TILLGODO_LIST.to_string(),
                                 },
                             }
                         },
@@ -482,7 +1258,7 @@ impl StorePage {
                                                 .get(member_id)
                                                 .map(|mem| (acc, mem))))
                                         .map(|(acc, member)| view_tillgodo(
-                                            acc,
+                                            &self.checkout.pending_balance(acc),
                                             member,
                                             Msg::StoreMsg(StoreMsg::DebitSelect(acc.id)),
                                         ))
@@ -513,40 +1289,479 @@ impl StorePage {
                 ],
                 input![
                     C![C.inventory_search_field, C.rounded, C.border_on_focus],
+                    attrs! {At::Id => INVENTORY_SEARCH_ID},
                     attrs! {At::Value => self.inventory_search_string},
-                    attrs! {At::Placeholder => "sök varor"},
+                    attrs! {At::Placeholder => strings::SEARCH_INVENTORY},
                     input_ev(Ev::Input, |input| Msg::StoreMsg(StoreMsg::SearchInput(
                         input
                     ))),
                     keyboard_ev(Ev::KeyDown, |ev| Msg::StoreMsg(StoreMsg::SearchKeyDown(ev))),
                 ],
+                if let Some(quantity) = self.quantity {
+                    div![C![C.quantity_badge], format!("{} x", quantity)]
+                } else {
+                    empty![]
+                },
+                button![
+                    C![C.shortcuts_button, C.rounded, C.border_on_focus],
+                    attrs! {At::Title => strings::SHORTCUTS},
+                    simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::ToggleShortcuts)),
+                    "?",
+                ],
+                button![
+                    C![C.parked_carts_button, C.rounded, C.border_on_focus],
+                    simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::ToggleParkedCarts)),
+                    format!("{} ({})", strings::PARKED_CARTS, res.parked_carts.len()),
+                ],
+                button![
+                    C![C.parked_carts_button, C.rounded, C.border_on_focus],
+                    simple_ev(
+                        Ev::Click,
+                        Msg::StoreMsg(StoreMsg::SuppliersMsg(SuppliersMsg::Toggle))
+                    ),
+                    strings::SUPPLIERS,
+                ],
+                button![
+                    C![C.parked_carts_button, C.rounded, C.border_on_focus],
+                    simple_ev(
+                        Ev::Click,
+                        Msg::StoreMsg(StoreMsg::LocationsMsg(LocationsMsg::Toggle))
+                    ),
+                    if res.low_stock.is_empty() {
+                        strings::STORAGE.to_string()
+                    } else {
+                        format!("{} ({})", strings::STORAGE, res.low_stock.len())
+                    },
+                ],
+                if is_admin && !self.grid.editing {
+                    button![
+                        C![C.edit_layout_button, C.rounded, C.border_on_focus],
+                        simple_ev(
+                            Ev::Click,
+                            Msg::StoreMsg(StoreMsg::GridMsg(StoreGridMsg::StartEditing))
+                        ),
+                        strings::EDIT_LAYOUT,
+                    ]
+                } else {
+                    empty![]
+                },
             ],
             div![
-                C![C.inventory_view],
-                self.inventory_search
-                    .iter()
-                    .map(|(fuzzy, element)| match element {
-                        StoreItemId::Item(item_id) => view_inventory_item(
-                            &res.inventory[item_id],
-                            fuzzy.matches.iter().map(|m| m.base_str_index),
-                            |item_id, amount| Msg::StoreMsg(StoreMsg::CheckoutMsg(
-                                CheckoutMsg::AddItem { item_id, amount }
-                            ))
-                        ),
-                        StoreItemId::Bundle(bundle_id) => view_inventory_bundle(
-                            &res.bundles[bundle_id],
-                            fuzzy.matches.iter().map(|m| m.base_str_index),
-                            |bundle_id, amount| Msg::StoreMsg(StoreMsg::CheckoutMsg(
-                                CheckoutMsg::AddBundle { bundle_id, amount }
-                            ))
-                        ),
-                    })
-                    .collect::<Vec<_>>(),
+                C![C.store_inventory_area],
+                if self.grid.editing {
+                    empty![]
+                } else {
+                    self.view_favorites(&res, preferences)
+                },
+                match self.grid.view(rs, &self.inventory_search_string) {
+                    Some(grid) => grid.map_msg(StoreMsg::GridMsg).map_msg(Msg::StoreMsg),
+                    None => self.view_inventory_list(&res, preferences),
+                },
             ],
             self.checkout
                 .view(rs)
                 .map_msg(StoreMsg::CheckoutMsg)
                 .map_msg(Msg::StoreMsg),
+            if self.show_shortcuts {
+                view_shortcuts()
+            } else {
+                empty![]
+            },
+            if self.show_parked_carts {
+                self.view_parked_carts(&res)
+            } else {
+                empty![]
+            },
+            self.view_modifier_picker(&res),
+            self.izettle_pay
+                .view_pending(true)
+                .map_msg(StoreMsg::IZettleMsg)
+                .map_msg(Msg::StoreMsg),
+            self.suppliers
+                .view(rs)
+                .map_msg(StoreMsg::SuppliersMsg)
+                .map_msg(Msg::StoreMsg),
+            self.locations
+                .view(rs)
+                .map_msg(StoreMsg::LocationsMsg)
+                .map_msg(Msg::StoreMsg),
         ]
     }
+
+    fn view_parked_carts(&self, res: &Res) -> Node<Msg> {
+        div![
+            C![C.parked_carts_drawer],
+            h2![strings::PARKED_CARTS],
+            div![
+                C![C.parked_cart_new],
+                input![
+                    C![C.rounded, C.border_on_focus],
+                    attrs! {At::Value => self.park_name},
+                    attrs! {At::Placeholder => strings::PARKED_CART_NAME},
+                    input_ev(Ev::Input, |input| Msg::StoreMsg(StoreMsg::ParkNameInput(
+                        input
+                    ))),
+                ],
+                button![
+                    C![C.rounded, C.border_on_focus],
+                    if self.checkout.is_empty() || self.checkout.disabled {
+                        attrs! {At::Disabled => true}
+                    } else {
+                        attrs! {}
+                    },
+                    simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::ParkCart)),
+                    strings::PARK_CART,
+                ],
+            ],
+            res.parked_carts.iter().map(|cart| {
+                let total: i64 = cart
+                    .bundles
+                    .iter()
+                    .map(|bundle| {
+                        -i64::from(bundle.change) * bundle.price.map(|p| p.into()).unwrap_or(0i64)
+                    })
+                    .sum();
+
+                div![
+                    C![C.parked_cart, C.rounded],
+                    div![
+                        span![C![C.parked_cart_name], &cart.name],
+                        span![
+                            C![C.parked_cart_time],
+                            cart.created_at
+                                .with_timezone(res.reporting_timezone)
+                                .format("%H:%M")
+                                .to_string(),
+                        ],
+                    ],
+                    div![format!("{}:-", Currency::from(total))],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::ResumeCart(cart.id))),
+                        strings::RESUME_CART,
+                    ],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(
+                            Ev::Click,
+                            Msg::StoreMsg(StoreMsg::DeleteParkedCart(cart.id))
+                        ),
+                        strings::REMOVE,
+                    ],
+                ]
+            }),
+        ]
+    }
+
+    /// Choose the variants and add-ons of the item which is about to be added
+    fn view_modifier_picker(&self, res: &Res) -> Node<Msg> {
+        let (picker, item) = match &self.modifier_picker {
+            Some(picker) => match res.inventory.get(&picker.item_id) {
+                Some(item) => (picker, item),
+                None => return empty![],
+            },
+            None => return empty![],
+        };
+
+        let modifiers = item_modifiers(res, picker.item_id);
+        let selected: Vec<&ItemModifier> = modifiers
+            .iter()
+            .copied()
+            .filter(|modifier| picker.selected.contains(&modifier.id))
+            .collect();
+        let price = Currency::from(item.price.unwrap_or(0))
+            + selected
+                .iter()
+                .map(|modifier| modifier.price_delta)
+                .sum::<Currency>();
+
+        div![
+            C![C.modifier_picker_overlay],
+            simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::CancelModifiers)),
+            div![
+                C![C.modifier_picker, C.rounded],
+                ev(Ev::Click, |event| event.stop_propagation()),
+                h2![describe_modified(&item.name, &selected)],
+                modifiers
+                    .iter()
+                    .group_by(|modifier| modifier.group.clone())
+                    .into_iter()
+                    .map(|(group, modifiers)| {
+                        div![
+                            C![C.modifier_group],
+                            h3![group.unwrap_or_else(|| strings::MODIFIER_EXTRAS.to_string())],
+                            modifiers
+                                .map(|modifier| {
+                                    let modifier_id = modifier.id;
+                                    button![
+                                        C![C.modifier_button, C.rounded, C.border_on_focus],
+                                        if picker.selected.contains(&modifier_id) {
+                                            C![C.modifier_button_selected]
+                                        } else {
+                                            C![]
+                                        },
+                                        simple_ev(
+                                            Ev::Click,
+                                            Msg::StoreMsg(StoreMsg::ToggleModifier(modifier_id)),
+                                        ),
+                                        &modifier.name,
+                                        if modifier.price_delta != Currency::default() {
+                                            span![
+                                                C![C.modifier_price_delta],
+                                                format_price_delta(modifier.price_delta),
+                                            ]
+                                        } else {
+                                            empty![]
+                                        },
+                                    ]
+                                })
+                                .collect::<Vec<_>>(),
+                        ]
+                    })
+                    .collect::<Vec<_>>(),
+                div![
+                    C![C.modifier_picker_buttons],
+                    button![
+                        C![C.rounded, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::CancelModifiers)),
+                        strings::ABORT,
+                    ],
+                    button![
+                        C![C.wide_button, C.border_on_focus],
+                        simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::ConfirmModifiers)),
+                        format!("{} {} x {}:-", strings::ADD_TO_CART, picker.amount, price),
+                    ],
+                ],
+            ],
+        ]
+    }
+
+    /// Whether an item should be shown given the current search string
+    fn matches_search(&self, res: &Res, score: &FuzzyScore, item: &StoreItemId) -> bool {
+        let search = &self.inventory_search_string;
+        if search.is_empty() || score.is_full_match(search) {
+            return true;
+        }
+
+        match item {
+            StoreItemId::Item(id) => res.barcodes.get(search) == Some(id),
+            StoreItemId::Bundle(_) => false,
+        }
+    }
+
+    /// Pinned items followed by the most sold ones
+    fn view_favorites(&self, res: &Res, preferences: &UserPreferences) -> Node<Msg> {
+        let popular = res
+            .popular
+            .iter()
+            .map(|&id| QuickButtonTarget::Item(id))
+            .filter(|target| !preferences.pinned.contains(target));
+
+        let favorites: Vec<(QuickButtonTarget, &str)> = preferences
+            .pinned
+            .iter()
+            .copied()
+            .chain(popular)
+            .filter_map(|target| match target {
+                QuickButtonTarget::Item(id) => res
+                    .inventory
+                    .get(&id)
+                    .filter(|item| item.is_for_sale())
+                    .map(|item| (target, item.name.as_str())),
+                QuickButtonTarget::Bundle(id) => res
+                    .bundles
+                    .get(&id)
+                    .map(|bundle| (target, bundle.name.as_str())),
+            })
+            .take(MAX_FAVORITES)
+            .collect();
+
+        if favorites.is_empty() {
+            return empty![];
+        }
+
+        div![
+            C![C.favorites_row],
+            span![C![C.favorites_title], strings::FAVORITES],
+            favorites.into_iter().map(|(target, name)| {
+                button![
+                    C![C.favorite_button, C.border_on_focus],
+                    if preferences.pinned.contains(&target) {
+                        C![C.favorite_button_pinned]
+                    } else {
+                        C![]
+                    },
+                    simple_ev(
+                        Ev::Click,
+                        Msg::StoreMsg(StoreMsg::GridMsg(StoreGridMsg::Activate(target)))
+                    ),
+                    name,
+                ]
+            }),
+        ]
+    }
+
+    fn view_inventory_list(&self, res: &Res, preferences: &UserPreferences) -> Node<Msg> {
+        let today = local_date(Utc::now(), *res.reporting_timezone);
+
+        // the batches are sorted by expiry date, so the first one of each item expires first
+        let mut expires_on: HashMap<InventoryItemId, NaiveDate> = HashMap::new();
+        for batch in res.expiring.iter() {
+            expires_on.entry(batch.item_id).or_insert(batch.expires_on);
+        }
+
+        div![
+            C![C.inventory_view],
+            if preferences.quick_buttons == QuickButtonsLayout::List {
+                C![C.inventory_view_list]
+            } else {
+                C![]
+            },
+            self.inventory_search
+                .iter()
+                .filter(|(fuzzy, element)| self.matches_search(res, fuzzy, element))
+                .map(|(fuzzy, element)| match element {
+                    StoreItemId::Item(item_id) => {
+                        let target = QuickButtonTarget::Item(*item_id);
+                        view_inventory_item(
+                            &res.inventory[item_id],
+                            fuzzy.matches.iter().map(|m| m.base_str_index),
+                            |item_id, amount| {
+                                Msg::StoreMsg(StoreMsg::CheckoutMsg(CheckoutMsg::AddItem {
+                                    item_id,
+                                    amount,
+                                }))
+                            },
+                            preferences.pinned.contains(&target),
+                            Msg::TogglePinned(target),
+                            expires_on.get(item_id).copied(),
+                            today,
+                        )
+                    }
+                    StoreItemId::Bundle(bundle_id) => {
+                        let target = QuickButtonTarget::Bundle(*bundle_id);
+                        view_inventory_bundle(
+                            &res.bundles[bundle_id],
+                            fuzzy.matches.iter().map(|m| m.base_str_index),
+                            |bundle_id, amount| {
+                                Msg::StoreMsg(StoreMsg::CheckoutMsg(CheckoutMsg::AddBundle {
+                                    bundle_id,
+                                    amount,
+                                }))
+                            },
+                            preferences.pinned.contains(&target),
+                            Msg::TogglePinned(target),
+                        )
+                    }
+                })
+                .collect::<Vec<_>>(),
+        ]
+    }
+}
+
+/// The variants and add-ons which can be chosen for an item, grouped
+fn item_modifiers<'a>(res: &Res<'a>, item_id: InventoryItemId) -> Vec<&'a ItemModifier> {
+    res.modifiers
+        .values()
+        .filter(|modifier| modifier.item_id == item_id && modifier.archived_at.is_none())
+        // the modifiers without a group come last
+        .sorted_by(|a, b| {
+            (a.group.is_none(), &a.group, a.id).cmp(&(b.group.is_none(), &b.group, b.id))
+        })
+        .collect()
+}
+
+/// A price delta with its sign, e.g. "+5:-"
+fn format_price_delta(delta: Currency) -> String {
+    if delta < Currency::default() {
+        format!("{}:-", delta)
+    } else {
+        format!("+{}:-", delta)
+    }
+}
+
+/// Tell the app about a sale, which may unlock an achievement
+fn record_sale(
+    transaction_id: TransactionId,
+    transaction: &NewTransaction,
+    res: &Res,
+    orders: &mut impl Orders<Msg>,
+) {
+    let tz = *res.reporting_timezone;
+    orders.send_msg(Msg::Achievement(AchievementEvent::Sale {
+        transaction_id,
+        day_start: start_of_day(local_date(Utc::now(), tz), tz),
+        coffees: count_coffees(transaction, res.inventory),
+    }));
+}
+
+/// Show the number which a sale was given in the queue, if queue tickets are enabled
+fn show_queue_number(transaction_id: TransactionId, orders: &mut impl Orders<Msg>) {
+    orders.perform_cmd(async move {
+        let url = format!("/api/queue/transaction/{}", transaction_id);
+        let result: Result<Option<QueueTicket>, FetchError> =
+            async { get_with_retry(url).await?.json().await }.await;
+        match result {
+            Ok(Some(ticket)) => Some(Msg::NotificationMessage(
+                NotificationMessage::ShowNotification {
+                    duration_ms: QUEUE_NUMBER_MS,
+                    notification: Notification::new(NotificationLevel::Info, strings::QUEUE_NUMBER)
+                        .with_body(ticket.number.to_string()),
+                },
+            )),
+            Ok(None) => None,
+            Err(e) => {
+                error!("Failed to fetch the queue number", e);
+                None
+            }
+        }
+    });
+}
+
+/// List the items which aren't in stock, for a notification
+fn describe_shortages(shortages: &[StockShortage]) -> String {
+    shortages
+        .iter()
+        .map(|shortage| {
+            format!(
+                "{}: {} {}",
+                shortage.name,
+                shortage.in_stock,
+                strings::STOCK_LEFT
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn view_shortcuts() -> Node<Msg> {
+    div![
+        C![C.shortcuts_overlay],
+        simple_ev(Ev::Click, Msg::StoreMsg(StoreMsg::ToggleShortcuts)),
+        div![
+            C![C.shortcuts_box, C.rounded],
+            h2![strings::SHORTCUTS],
+            table![strings::SHORTCUT_LIST
+                .iter()
+                .map(|&(key, description)| tr![td![kbd![key]], td![description]])],
+        ],
+    ]
+}
+
+fn focus_element(id: &str) {
+    if let Some(element) = document()
+        .get_element_by_id(id)
+        .and_then(|element| element.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = element.focus();
+    }
+}
+
+fn blur_active_element() {
+    if let Some(element) = document()
+        .active_element()
+        .and_then(|element| element.dyn_into::<web_sys::HtmlElement>().ok())
+    {
+        let _ = element.blur();
+    }
 }
@@ -1,20 +1,35 @@
 use crate::app::Msg;
 use crate::components::filter_menu::{FilterMenu, FilterMenuMsg};
+use crate::components::filter_presets::{FilterPresets, FilterPresetsMsg};
+use crate::components::goods_return::{self, GoodsReturnForm, GoodsReturnMsg};
 use crate::generated::css_classes::C;
+use crate::live::RecentTransactions;
+use crate::notification_manager::{Notification, NotificationLevel, NotificationMessage};
 use crate::page::loading::Loading;
+use crate::page::{Page, UrlParams};
+use crate::strings;
+use crate::util::base;
 use crate::util::export::{download_file, make_csv_transaction_list, CSVStyleTransaction};
-use crate::util::simple_ev;
-use chrono::{FixedOffset, Local};
+use crate::util::fetch::{
+    get_with_retry, send_with_retry, unlocked_response, void_response, with_approvals, Gated,
+};
+use crate::util::{simple_ev, DATE_INPUT_FMT};
+use chrono::{Datelike, NaiveDate, Utc};
 use seed::prelude::*;
 use seed::*;
 use seed_fetcher::Resources;
 use seed_fetcher::{event, NotAvailable, ResourceStore};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use strecklistan_api::{
-    book_account::{BookAccount, BookAccountId, MasterAccounts},
+    book_account::{BookAccount, BookAccountId, BookAccountType, MasterAccounts},
     currency::Currency,
+    filter_preset::{PresetDates, PresetFilter, PresetPage},
     inventory::{InventoryItemId, InventoryItemStock},
-    transaction::{Transaction, TransactionId},
+    time::{local_date, Tz},
+    transaction::{
+        BatchOperation, BatchOutcome, Transaction, TransactionAnnotation, TransactionBatch,
+        TransactionDetail, TransactionId,
+    },
 };
 
 const VIEW_COUNT_CHUNK: usize = 50;
@@ -27,13 +42,48 @@ pub enum ExportFormat {
 
 #[derive(Clone, Debug)]
 pub enum TransactionsMsg {
+    /// Fetch all resources of the page again
+    Reload,
+
     DeleteTransaction(TransactionId),
     TransactionDeleted(TransactionId),
     SetShowDelete(bool),
     SetShowLeftPanel(bool),
     FilterMenuMsg(FilterMenuMsg),
+    FilterPresetsMsg(FilterPresetsMsg),
     IncreaseViewLimit,
     ExportData(ExportFormat),
+    SetFromDate(String),
+    SetToDate(String),
+    SetBalancesDate(String),
+    SetPaidWith(String),
+    ClearItemFilter,
+    SetSearch(String),
+    ShowDetail(TransactionId),
+    DetailFetched(TransactionDetail),
+    CloseDetail,
+    SetNoteInput(String),
+    SetTagsInput(String),
+    SetEventCodeInput(String),
+    SaveAnnotation,
+    AnnotationSaved(TransactionId, TransactionAnnotation),
+    AnnotationFailed(String),
+    /// The transaction is before the lock date and can't be changed
+    PeriodLocked(NaiveDate),
+    StartReturn,
+    GoodsReturnMsg(GoodsReturnMsg),
+
+    SetSelecting(bool),
+    ToggleSelected(TransactionId),
+    SelectAllShown,
+    ClearSelection,
+    SetBatchReason(String),
+    SetBatchTags(String),
+    VoidSelected,
+    SendBatchVoid(TransactionBatch),
+    TagSelected,
+    BatchDone(BatchOutcome),
+    BatchFailed(String),
 
     ResFetched(event::Fetched),
     ResMarkDirty(event::MarkDirty),
@@ -45,13 +95,62 @@ pub struct TransactionsPage {
     show_left_panel: bool,
     view_limit: usize,
     filter_menu: FilterMenu,
-    timezone: FixedOffset,
+    presets: FilterPresets,
+
+    /// Only show transactions from this date and onwards, set by the `from` url parameter
+    from_date: Option<NaiveDate>,
+
+    /// Only show transactions up to and including this date, set by the `to` url parameter
+    to_date: Option<NaiveDate>,
+
+    /// The day at the end of which the tillgodo balances are exported
+    balances_date: Option<NaiveDate>,
+
+    /// Only show transactions containing this item, set by the `item` url parameter
+    item: Option<InventoryItemId>,
+
+    /// Only show transactions paid with this account, e.g. card payments, set by the `paid_with`
+    /// url parameter
+    paid_with: Option<BookAccountId>,
+
+    /// Only show transactions with a note or tag containing this, set by the `q` url parameter
+    search: String,
+
+    /// The transaction given by the url hash, e.g. `/transactions#42`
+    highlighted: Option<TransactionId>,
+
+    /// The transaction shown in the detail drawer, fetched separately since it can be deleted
+    detail: Option<TransactionDetail>,
+
+    /// The note being edited in the detail drawer
+    note_input: String,
+
+    /// The comma-separated tags being edited in the detail drawer
+    tags_input: String,
+
+    /// The event code being edited in the detail drawer
+    event_code_input: String,
+
+    /// The goods being returned from the transaction in the detail drawer
+    return_form: Option<GoodsReturnForm>,
 
     /// Only show transactions in this list
     filtered_transactions: Vec<usize>,
 
     /// The balance of all accounts based on the filtered transactions
     accounts_balance: HashMap<BookAccountId, Currency>,
+
+    /// Whether the transactions can be selected for a batch operation
+    selecting: bool,
+
+    /// The transactions which the batch operations apply to
+    selected: BTreeSet<TransactionId>,
+
+    /// Why the selected transactions are voided
+    batch_reason: String,
+
+    /// The comma-separated tags added to the selected transactions
+    batch_tags: String,
 }
 
 #[derive(Resources)]
@@ -68,6 +167,23 @@ struct Res<'a> {
 
     #[url = "/api/book_accounts/masters"]
     master_accounts: &'a MasterAccounts,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
+}
+
+/// What's needed to show the cached recent transactions, while all of them are being fetched
+#[derive(Resources)]
+struct RecentRes<'a> {
+    #[url = "/api/inventory/items"]
+    #[policy = "SilentRefetch"]
+    inventory: &'a HashMap<InventoryItemId, InventoryItemStock>,
+
+    #[url = "/api/book_accounts"]
+    book_accounts: &'a HashMap<BookAccountId, BookAccount>,
+
+    #[url = "/api/reporting_timezone"]
+    reporting_timezone: &'a Tz,
 }
 
 impl TransactionsPage {
@@ -75,11 +191,39 @@ impl TransactionsPage {
         let mut page = TransactionsPage {
             show_delete: false,
             show_left_panel: false,
-            timezone: *Local::now().offset(),
             view_limit: VIEW_COUNT_CHUNK,
-            filter_menu: FilterMenu::new(vec!["datum", "klockslag", "summa", "debet", "kredit"]),
+            filter_menu: FilterMenu::new(vec![
+                "datum",
+                "klockslag",
+                "summa",
+                "debet",
+                "kredit",
+                "kassör",
+            ]),
+            presets: FilterPresets::new(
+                PresetPage::Transactions,
+                rs,
+                &mut orders.proxy(TransactionsMsg::FilterPresetsMsg),
+            ),
+            from_date: None,
+            to_date: None,
+            // the end of the last financial year
+            balances_date: Some(NaiveDate::from_ymd(Utc::now().year() - 1, 12, 31)),
+            item: None,
+            paid_with: None,
+            search: String::new(),
+            highlighted: None,
+            detail: None,
+            note_input: String::new(),
+            tags_input: String::new(),
+            event_code_input: String::new(),
+            return_form: None,
             filtered_transactions: vec![],
             accounts_balance: HashMap::new(),
+            selecting: false,
+            selected: BTreeSet::new(),
+            batch_reason: String::new(),
+            batch_tags: String::new(),
         };
 
         orders.subscribe(TransactionsMsg::ResFetched);
@@ -91,49 +235,140 @@ impl TransactionsPage {
         page
     }
 
+    /// Apply the filters given by the url parameters.
+    ///
+    /// Parameters which are missing from the url don't affect the page, so that the filters are
+    /// kept when navigating back to the page.
+    pub fn set_params(&mut self, params: &UrlParams, rs: &ResourceStore) {
+        let parse_date = |key| {
+            params
+                .get(key)
+                .and_then(|date| NaiveDate::parse_from_str(date, DATE_INPUT_FMT).ok())
+        };
+
+        if let Some(date) = parse_date("from") {
+            self.from_date = Some(date);
+        }
+        if let Some(date) = parse_date("to") {
+            self.to_date = Some(date);
+        }
+        if let Some(item) = params.parse("item") {
+            self.item = Some(item);
+        }
+        if let Some(account) = params.parse("paid_with") {
+            self.paid_with = Some(account);
+        }
+        if let Some(search) = params.get("q") {
+            self.search = search.to_string();
+        }
+        if let Some(id) = params.hash().and_then(|hash| hash.parse().ok()) {
+            self.highlighted = Some(id);
+        }
+
+        Res::acquire_now(rs)
+            .map(|res| self.filter_transactions(&res))
+            .ok();
+    }
+
+    /// Reflect the state of the page in the url
+    pub fn update_url(&self) {
+        UrlParams::default()
+            .with(
+                "from",
+                self.from_date.map(|date| date.format(DATE_INPUT_FMT)),
+            )
+            .with("to", self.to_date.map(|date| date.format(DATE_INPUT_FMT)))
+            .with("item", self.item)
+            .with("paid_with", self.paid_with)
+            .with("q", Some(&self.search).filter(|q| !q.is_empty()))
+            .with_hash(self.highlighted)
+            .replace_url(Page::TransactionHistory);
+    }
+
     /// Rebuild self.filtered_transactions
     fn filter_transactions(&mut self, res: &Res) {
+        let tz = res.reporting_timezone;
+        let account_name = |id: &BookAccountId| {
+            res.book_accounts
+                .get(id)
+                .map(|acc| acc.name.as_str())
+                .unwrap_or(strings::MISSING_NAME.get())
+        };
+
         self.filtered_transactions = res
             .transactions
             .iter()
             .enumerate()
+            .filter(|(_, tr)| {
+                matches_filters(
+                    tr,
+                    tz,
+                    self.from_date,
+                    self.to_date,
+                    self.item,
+                    self.paid_with,
+                    &self.search,
+                )
+            })
             .filter(|(_, tr)| {
                 self.filter_menu.filter(&[
-                    &tr.time.with_timezone(&self.timezone).format("%Y-%m-%d"), // datum
-                    &tr.time.with_timezone(&self.timezone).format("%H:%M:%S"), // klockslag
-                    &tr.amount,                                                // summa
-                    &res.book_accounts.get(&tr.debited_account).unwrap().name, // debet
-                    &res.book_accounts.get(&tr.credited_account).unwrap().name, // kredit
+                    &tr.time.with_timezone(tz).format("%Y-%m-%d"), // datum
+                    &tr.time.with_timezone(tz).format("%H:%M:%S"), // klockslag
+                    &tr.amount,                                    // summa
+                    &account_name(&tr.debited_account),            // debet
+                    &account_name(&tr.credited_account),           // kredit
+                    &tr.created_by.as_deref().unwrap_or(""),       // kassör
                 ])
             })
             .map(|(i, _)| i)
             .collect();
 
-        self.accounts_balance.clear();
-        for tr in self
-            .filtered_transactions
-            .iter()
-            .map(|&i| &res.transactions[i])
-        {
-            if let Some(acc) = res.book_accounts.get(&tr.debited_account) {
-                *self.accounts_balance.entry(tr.debited_account).or_default() +=
-                    acc.debit_diff(tr.amount);
-            }
-            if let Some(acc) = res.book_accounts.get(&tr.credited_account) {
-                *self
-                    .accounts_balance
-                    .entry(tr.credited_account)
-                    .or_default() += acc.credit_diff(tr.amount);
-            }
+        self.accounts_balance = account_balances(
+            self.filtered_transactions
+                .iter()
+                .map(|&i| &res.transactions[i]),
+            res.book_accounts,
+        );
+    }
+
+    /// The filters of the page, for saving them as a preset
+    fn current_filter(&self, today: NaiveDate) -> PresetFilter {
+        PresetFilter {
+            dates: PresetDates::from_range(self.from_date, self.to_date, today),
+            paid_with: self.paid_with,
+            item: self.item,
+            search: self.search.clone(),
+            cashier: None,
         }
     }
 
+    fn apply_preset(&mut self, filter: PresetFilter, today: NaiveDate, res: &Res) {
+        let (from, to) = filter.dates.range(today);
+        self.from_date = from;
+        self.to_date = to;
+        self.paid_with = filter.paid_with;
+        self.item = filter.item;
+        self.search = filter.search;
+        self.view_limit = VIEW_COUNT_CHUNK;
+        self.filter_transactions(res);
+        self.update_url();
+    }
+
     pub fn update(
         &mut self,
         msg: TransactionsMsg,
         rs: &ResourceStore,
         orders: &mut impl Orders<Msg>,
     ) -> Result<(), NotAvailable> {
+        if let TransactionsMsg::Reload = msg {
+            rs.mark_as_dirty(Res::transactions_url(), orders);
+            rs.mark_as_dirty(Res::inventory_url(), orders);
+            rs.mark_as_dirty(Res::book_accounts_url(), orders);
+            rs.mark_as_dirty(Res::master_accounts_url(), orders);
+            rs.mark_as_dirty(Res::reporting_timezone_url(), orders);
+            return Ok(());
+        }
+
         let res = Res::acquire(rs, orders)?;
 
         let mut orders_local = orders.proxy(|msg| Msg::TransactionsMsg(msg));
@@ -143,30 +378,41 @@ impl TransactionsPage {
                     self.filter_transactions(&res);
                 }
             }
-            TransactionsMsg::ResMarkDirty(_) => {}
+            TransactionsMsg::ResMarkDirty(_) | TransactionsMsg::Reload => {}
             TransactionsMsg::DeleteTransaction(id) => {
                 self.show_delete = false;
-                orders_local.perform_cmd(async move {
+                orders.perform_cmd(async move {
                     let result = async {
-                        Request::new(format!("/api/transaction/{}", id))
-                            .method(Method::Delete)
-                            .fetch()
-                            .await?
-                            .json()
-                            .await
+                        let request = with_approvals(
+                            Request::new(format!("/api/transaction/{}", id)).method(Method::Delete),
+                        );
+                        void_response(send_with_retry(request).await?).await
                     }
                     .await;
-                    result
-                        .map_err(|e| {
+                    match result {
+                        Ok(Ok(id)) => Some(Msg::TransactionsMsg(
+                            TransactionsMsg::TransactionDeleted(id),
+                        )),
+                        Ok(Err(Gated::Rejected(lock_date))) => Some(Msg::TransactionsMsg(
+                            TransactionsMsg::PeriodLocked(lock_date),
+                        )),
+                        // the transaction is voided once an admin has approved it
+                        Ok(Err(Gated::ApprovalRequired(action))) => Some(Msg::RequestApproval {
+                            action,
+                            retry: Box::new(Msg::TransactionsMsg(
+                                TransactionsMsg::DeleteTransaction(id),
+                            )),
+                        }),
+                        Err(e) => {
                             error!("Failed to delete transaction", e);
-                        })
-                        .map(|id| TransactionsMsg::TransactionDeleted(id))
-                        .ok()
+                            None
+                        }
+                    }
                 });
             }
 
             TransactionsMsg::TransactionDeleted(id) => {
-                log!(format!("Transaction {} deleted", id));
+                debug_log!("Deleted transaction ID:", id);
                 rs.mark_as_dirty(Res::transactions_url(), orders);
                 rs.mark_as_dirty(Res::book_accounts_url(), orders);
                 rs.mark_as_dirty(Res::inventory_url(), orders);
@@ -186,6 +432,319 @@ impl TransactionsPage {
                 self.view_limit = VIEW_COUNT_CHUNK; // reset view limit
                 self.filter_transactions(&res);
             }
+            TransactionsMsg::FilterPresetsMsg(msg) => {
+                match &msg {
+                    FilterPresetsMsg::Apply(filter) => {
+                        let today = local_date(Utc::now(), *res.reporting_timezone);
+                        self.apply_preset(filter.clone(), today, &res);
+                    }
+                    FilterPresetsMsg::Failed(reason) => {
+                        orders.send_msg(Msg::NotificationMessage(
+                            NotificationMessage::ShowNotification {
+                                duration_ms: 10000,
+                                notification: Notification::new(
+                                    NotificationLevel::Error,
+                                    strings::SAVE_FAILED,
+                                )
+                                .with_body(reason.clone()),
+                            },
+                        ));
+                    }
+                    _ => {}
+                }
+                self.presets.update(
+                    msg,
+                    rs,
+                    &mut orders_local.proxy(TransactionsMsg::FilterPresetsMsg),
+                );
+            }
+            TransactionsMsg::SetFromDate(input) => {
+                self.from_date = NaiveDate::parse_from_str(&input, DATE_INPUT_FMT).ok();
+                self.view_limit = VIEW_COUNT_CHUNK;
+                self.filter_transactions(&res);
+                self.update_url();
+            }
+            TransactionsMsg::SetToDate(input) => {
+                self.to_date = NaiveDate::parse_from_str(&input, DATE_INPUT_FMT).ok();
+                self.view_limit = VIEW_COUNT_CHUNK;
+                self.filter_transactions(&res);
+                self.update_url();
+            }
+            TransactionsMsg::SetBalancesDate(input) => {
+                self.balances_date = NaiveDate::parse_from_str(&input, DATE_INPUT_FMT).ok();
+            }
+            TransactionsMsg::SetPaidWith(input) => {
+                self.paid_with = input.parse().ok();
+                self.view_limit = VIEW_COUNT_CHUNK;
+                self.filter_transactions(&res);
+                self.update_url();
+            }
+            TransactionsMsg::ClearItemFilter => {
+                self.item = None;
+                self.view_limit = VIEW_COUNT_CHUNK;
+                self.filter_transactions(&res);
+                self.update_url();
+            }
+            TransactionsMsg::SetSearch(input) => {
+                self.search = input;
+                self.view_limit = VIEW_COUNT_CHUNK;
+                self.filter_transactions(&res);
+                self.update_url();
+            }
+            TransactionsMsg::ShowDetail(id) => {
+                self.highlighted = Some(id);
+                self.return_form = None;
+                self.update_url();
+                orders_local.perform_cmd(async move {
+                    let url = format!("/api/transaction/{}", id);
+                    let result = async { get_with_retry(url).await?.json().await }.await;
+                    result
+                        .map_err(|e| {
+                            error!("Failed to fetch transaction", e);
+                        })
+                        .map(TransactionsMsg::DetailFetched)
+                        .ok()
+                });
+            }
+            TransactionsMsg::DetailFetched(detail) => {
+                // the user might have moved on to another transaction while waiting
+                if self.highlighted == Some(detail.transaction.id) {
+                    self.note_input = detail.transaction.note.clone().unwrap_or_default();
+                    self.tags_input = detail.transaction.tags.join(", ");
+                    self.event_code_input =
+                        detail.transaction.event_code.clone().unwrap_or_default();
+                    self.detail = Some(detail);
+                }
+            }
+            TransactionsMsg::CloseDetail => {
+                self.detail = None;
+                self.return_form = None;
+            }
+            TransactionsMsg::SetNoteInput(input) => self.note_input = input,
+            TransactionsMsg::SetTagsInput(input) => self.tags_input = input,
+            TransactionsMsg::SetEventCodeInput(input) => self.event_code_input = input,
+            TransactionsMsg::SaveAnnotation => {
+                let id = match &self.detail {
+                    Some(detail) => detail.transaction.id,
+                    None => return Ok(()),
+                };
+                let annotation = TransactionAnnotation {
+                    note: Some(self.note_input.clone()),
+                    tags: TransactionAnnotation::parse_tags(&self.tags_input),
+                    event_code: Some(self.event_code_input.clone()),
+                };
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new(format!("/api/transaction/{}", id))
+                            .method(Method::Patch)
+                            .json(&annotation)?;
+                        unlocked_response(request.fetch().await?).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(annotation)) => TransactionsMsg::AnnotationSaved(id, annotation),
+                        Ok(Err(lock_date)) => TransactionsMsg::PeriodLocked(lock_date),
+                        Err(e) => TransactionsMsg::AnnotationFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            TransactionsMsg::AnnotationSaved(id, annotation) => {
+                if let Some(detail) = self.detail.as_mut().filter(|d| d.transaction.id == id) {
+                    self.note_input = annotation.note.clone().unwrap_or_default();
+                    self.tags_input = annotation.tags.join(", ");
+                    self.event_code_input = annotation.event_code.clone().unwrap_or_default();
+                    detail.transaction.note = annotation.note;
+                    detail.transaction.tags = annotation.tags;
+                    detail.transaction.event_code = annotation.event_code;
+                }
+                rs.mark_as_dirty(Res::transactions_url(), orders);
+            }
+            TransactionsMsg::AnnotationFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::SAVE_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
+            TransactionsMsg::PeriodLocked(lock_date) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::PERIOD_LOCKED,
+                        )
+                        .with_body(format!(
+                            "{}: {}",
+                            strings::LOCK_DATE,
+                            lock_date
+                        )),
+                    },
+                ));
+            }
+            TransactionsMsg::StartReturn => {
+                if let Some(detail) = &self.detail {
+                    self.return_form = Some(GoodsReturnForm::new(detail, rs));
+                }
+            }
+            TransactionsMsg::GoodsReturnMsg(GoodsReturnMsg::Cancel) => self.return_form = None,
+            TransactionsMsg::GoodsReturnMsg(GoodsReturnMsg::Saved(id)) => {
+                debug_log!("Goods returned as transaction ID:", id);
+                self.return_form = None;
+                rs.mark_as_dirty(Res::transactions_url(), orders);
+                rs.mark_as_dirty(Res::book_accounts_url(), orders);
+                rs.mark_as_dirty(Res::inventory_url(), orders);
+                if let Some(detail) = &self.detail {
+                    orders_local.send_msg(TransactionsMsg::ShowDetail(detail.transaction.id));
+                }
+            }
+            TransactionsMsg::GoodsReturnMsg(msg) => {
+                if let GoodsReturnMsg::Failed(reason) = &msg {
+                    orders.send_msg(Msg::NotificationMessage(
+                        NotificationMessage::ShowNotification {
+                            duration_ms: 10000,
+                            notification: Notification::new(
+                                NotificationLevel::Error,
+                                strings::RETURN_FAILED,
+                            )
+                            .with_body(reason.clone()),
+                        },
+                    ));
+                }
+                if let Some(form) = &mut self.return_form {
+                    form.update(
+                        msg,
+                        &mut orders_local.proxy(|msg| TransactionsMsg::GoodsReturnMsg(msg)),
+                    );
+                }
+            }
+            TransactionsMsg::SetSelecting(selecting) => {
+                self.selecting = selecting;
+                if !selecting {
+                    self.selected.clear();
+                }
+            }
+            TransactionsMsg::ToggleSelected(id) => {
+                if !self.selected.remove(&id) {
+                    self.selected.insert(id);
+                }
+            }
+            TransactionsMsg::SelectAllShown => {
+                self.selected.extend(
+                    self.filtered_transactions
+                        .iter()
+                        .take(self.view_limit)
+                        .map(|&i| res.transactions[i].id),
+                );
+            }
+            TransactionsMsg::ClearSelection => self.selected.clear(),
+            TransactionsMsg::SetBatchReason(input) => self.batch_reason = input,
+            TransactionsMsg::SetBatchTags(input) => self.batch_tags = input,
+            TransactionsMsg::VoidSelected => {
+                if self.selected.is_empty() || self.batch_reason.trim().is_empty() {
+                    return Ok(());
+                }
+                let confirmed = window()
+                    .confirm_with_message(strings::CONFIRM_VOID_SELECTED.get())
+                    .unwrap_or(false);
+                if !confirmed {
+                    return Ok(());
+                }
+                let batch = TransactionBatch {
+                    transaction_ids: self.selected.iter().copied().collect(),
+                    operation: BatchOperation::Void {
+                        reason: self.batch_reason.trim().to_string(),
+                    },
+                };
+                orders_local.send_msg(TransactionsMsg::SendBatchVoid(batch));
+            }
+            TransactionsMsg::SendBatchVoid(batch) => {
+                orders.perform_cmd(async move {
+                    let result = async {
+                        let request = with_approvals(
+                            Request::new("/api/transactions/batch")
+                                .method(Method::Post)
+                                .json(&batch)?,
+                        );
+                        void_response(send_with_retry(request).await?).await
+                    }
+                    .await;
+                    let msg = match result {
+                        Ok(Ok(outcome)) => TransactionsMsg::BatchDone(outcome),
+                        Ok(Err(Gated::Rejected(lock_date))) => {
+                            TransactionsMsg::PeriodLocked(lock_date)
+                        }
+                        // the transactions are voided once an admin has approved it
+                        Ok(Err(Gated::ApprovalRequired(action))) => {
+                            return Msg::RequestApproval {
+                                action,
+                                retry: Box::new(Msg::TransactionsMsg(
+                                    TransactionsMsg::SendBatchVoid(batch),
+                                )),
+                            }
+                        }
+                        Err(e) => TransactionsMsg::BatchFailed(format!("{:?}", e)),
+                    };
+                    Msg::TransactionsMsg(msg)
+                });
+            }
+            TransactionsMsg::TagSelected => {
+                let tags = TransactionAnnotation::parse_tags(&self.batch_tags);
+                if self.selected.is_empty() || tags.is_empty() {
+                    return Ok(());
+                }
+                let batch = TransactionBatch {
+                    transaction_ids: self.selected.iter().copied().collect(),
+                    operation: BatchOperation::AddTags { tags },
+                };
+                orders_local.perform_cmd(async move {
+                    let result = async {
+                        let request = Request::new("/api/transactions/batch")
+                            .method(Method::Post)
+                            .json(&batch)?;
+                        unlocked_response(send_with_retry(request).await?).await
+                    }
+                    .await;
+                    match result {
+                        Ok(Ok(outcome)) => TransactionsMsg::BatchDone(outcome),
+                        Ok(Err(lock_date)) => TransactionsMsg::PeriodLocked(lock_date),
+                        Err(e) => TransactionsMsg::BatchFailed(format!("{:?}", e)),
+                    }
+                });
+            }
+            TransactionsMsg::BatchDone(outcome) => {
+                debug_log!(
+                    "Changed transactions in batch",
+                    outcome.batch_id,
+                    outcome.transaction_ids
+                );
+                self.selected.clear();
+                self.batch_reason.clear();
+                self.batch_tags.clear();
+                rs.mark_as_dirty(Res::transactions_url(), orders);
+                rs.mark_as_dirty(Res::book_accounts_url(), orders);
+                rs.mark_as_dirty(Res::inventory_url(), orders);
+                if let Some(detail) = &self.detail {
+                    orders_local.send_msg(TransactionsMsg::ShowDetail(detail.transaction.id));
+                }
+            }
+            TransactionsMsg::BatchFailed(reason) => {
+                orders.send_msg(Msg::NotificationMessage(
+                    NotificationMessage::ShowNotification {
+                        duration_ms: 10000,
+                        notification: Notification::new(
+                            NotificationLevel::Error,
+                            strings::BATCH_FAILED,
+                        )
+                        .with_body(reason),
+                    },
+                ));
+            }
             TransactionsMsg::IncreaseViewLimit => {
                 self.view_limit += VIEW_COUNT_CHUNK;
                 self.filter_transactions(&res);
@@ -203,7 +762,11 @@ impl TransactionsPage {
                             .ok();
                     }
                     ExportFormat::CSV(style) => {
-                        let serialized = make_csv_transaction_list(&transactions, style);
+                        let serialized = make_csv_transaction_list(
+                            &transactions,
+                            style,
+                            *res.reporting_timezone,
+                        );
                         download_file("transactions.csv", mime::TEXT_CSV, &serialized).ok();
                     }
                 }
@@ -213,10 +776,15 @@ impl TransactionsPage {
         Ok(())
     }
 
-    pub fn view(&self, rs: &ResourceStore) -> Node<Msg> {
+    pub fn view(&self, rs: &ResourceStore, recent: &RecentTransactions) -> Node<Msg> {
         let res = match Res::acquire_now(rs) {
             Ok(res) => res,
-            Err(_) => return Loading::view(),
+            Err(_) => return self.view_recent(rs, recent),
+        };
+
+        let fmt_date = |date: Option<NaiveDate>| {
+            date.map(|date| date.format(DATE_INPUT_FMT).to_string())
+                .unwrap_or_default()
         };
 
         let show_acc_entry = |name: &str, balance: Currency| {
@@ -241,12 +809,33 @@ impl TransactionsPage {
             )
         };
 
+        let today = local_date(Utc::now(), *res.reporting_timezone);
+
+        // the accounts which sales are paid with, e.g. cash and card
+        let mut payment_accounts: Vec<&BookAccount> = res
+            .book_accounts
+            .values()
+            .filter(|acc| acc.account_type == BookAccountType::Assets && acc.creditor.is_none())
+            .collect();
+        payment_accounts.sort_by(|a, b| a.name.cmp(&b.name));
+
         let transaction_list: Vec<_> = self
             .filtered_transactions
             .iter()
             .take(self.view_limit)
             .map(|&i| &res.transactions[i])
-            .map(|tr| view_transaction(self.timezone, &res, tr, self.show_delete))
+            .map(|tr| {
+                let highlighted = self.highlighted == Some(tr.id);
+                view_transaction(
+                    *res.reporting_timezone,
+                    res.book_accounts,
+                    res.inventory,
+                    tr,
+                    self.show_delete,
+                    highlighted,
+                    IF!(self.selecting => self.selected.contains(&tr.id)),
+                )
+            })
             .collect();
 
         div![
@@ -260,7 +849,7 @@ impl TransactionsPage {
                 },
                 div![
                     C![C.left_panel_entry],
-                    h2![C![C.left_panel_entry_header], "Balansräkning"],
+                    h2![C![C.left_panel_entry_header], strings::BALANCE_SHEET],
                 ],
                 div![
                     C![C.balance_sheet, C.margin_hcenter],
@@ -269,7 +858,7 @@ impl TransactionsPage {
                     show_acc(&res.master_accounts.sales_account_id),
                     show_acc(&res.master_accounts.purchases_account_id),
                     show_acc_entry(
-                        "Tillgodo Totalt",
+                        strings::TILLGODO_TOTAL.get(),
                         self.accounts_balance
                             .iter()
                             .filter_map(|(id, balance)| res
@@ -284,14 +873,86 @@ impl TransactionsPage {
                 hr![C![C.left_panel_entry]],
                 div![
                     C![C.left_panel_entry],
-                    h2![C![C.left_panel_entry_header], "Filtrera (WIP)"],
+                    h2![C![C.left_panel_entry_header], strings::FILTER],
+                ],
+                div![
+                    C![C.left_panel_entry],
+                    self.presets
+                        .view(rs, self.current_filter(today))
+                        .map_msg(TransactionsMsg::FilterPresetsMsg),
+                ],
+                div![
+                    C![C.left_panel_entry, C.transactions_date_filter],
+                    span![strings::FROM_DATE],
+                    input![
+                        attrs! {At::Type => "date"},
+                        attrs! {At::Value => fmt_date(self.from_date)},
+                        input_ev(Ev::Input, TransactionsMsg::SetFromDate),
+                    ],
+                    span![strings::TO_DATE],
+                    input![
+                        attrs! {At::Type => "date"},
+                        attrs! {At::Value => fmt_date(self.to_date)},
+                        input_ev(Ev::Input, TransactionsMsg::SetToDate),
+                    ],
+                ],
+                if let Some(item) = self.item {
+                    div![
+                        C![C.left_panel_entry, C.transactions_item_filter],
+                        span![format!(
+                            "{}: {}",
+                            strings::ITEM,
+                            res.inventory
+                                .get(&item)
+                                .map(|item| item.name.as_str())
+                                .unwrap_or(strings::MISSING_NAME.get()),
+                        )],
+                        button![
+                            C![C.border_on_focus],
+                            simple_ev(Ev::Click, TransactionsMsg::ClearItemFilter),
+                            "✖",
+                        ],
+                    ]
+                } else {
+                    empty![]
+                },
+                div![
+                    C![C.left_panel_entry, C.transactions_paid_with_filter],
+                    select![
+                        option![
+                            attrs! {At::Value => ""},
+                            attrs! {At::Selected => self.paid_with.is_none().as_at_value()},
+                            strings::ALL_PAYMENT_METHODS,
+                        ],
+                        payment_accounts.iter().map(|acc| {
+                            let selected = self.paid_with == Some(acc.id);
+                            option![
+                                attrs! {At::Value => acc.id},
+                                attrs! {At::Selected => selected.as_at_value()},
+                                &acc.name,
+                            ]
+                        }),
+                        input_ev(Ev::Change, TransactionsMsg::SetPaidWith),
+                    ],
+                ],
+                div![
+                    C![C.left_panel_entry, C.transactions_search_filter],
+                    input![
+                        C![C.rounded, C.border_on_focus],
+                        attrs! {
+                            At::Type => "search",
+                            At::Placeholder => strings::SEARCH_NOTES,
+                            At::Value => self.search,
+                        },
+                        input_ev(Ev::Input, TransactionsMsg::SetSearch),
+                    ],
                 ],
                 self.filter_menu
                     .view()
                     .map_msg(|msg| TransactionsMsg::FilterMenuMsg(msg)),
                 div![
                     C![C.left_panel_entry],
-                    h2![C![C.left_panel_entry_header], "Exportera Data"],
+                    h2![C![C.left_panel_entry_header], strings::EXPORT_DATA],
                     button![
                         C![C.wide_button],
                         "JSON",
@@ -299,7 +960,7 @@ impl TransactionsPage {
                     ],
                     button![
                         C![C.wide_button],
-                        "CSV (En rad per vara)",
+                        strings::EXPORT_CSV_PER_ITEM,
                         simple_ev(
                             Ev::Click,
                             TransactionsMsg::ExportData(ExportFormat::CSV(
@@ -307,6 +968,49 @@ impl TransactionsPage {
                             ))
                         ),
                     ],
+                    // made by the server, since the whole history may not fit in the browser
+                    a![
+                        C![C.wide_button, C.wide_button_link],
+                        attrs! {
+                            At::Href => base::api_url("/api/export/transactions.csv"),
+                            At::Download => "transactions.csv",
+                        },
+                        strings::EXPORT_CSV_ALL,
+                    ],
+                ],
+                div![
+                    C![C.left_panel_entry, C.member_balances_export],
+                    h2![
+                        C![C.left_panel_entry_header],
+                        strings::EXPORT_MEMBER_BALANCES
+                    ],
+                    span![strings::MEMBER_BALANCES_AT],
+                    input![
+                        attrs! {At::Type => "date"},
+                        attrs! {At::Value => fmt_date(self.balances_date)},
+                        input_ev(Ev::Input, TransactionsMsg::SetBalancesDate),
+                    ],
+                    self.balances_date
+                        .map(|date| {
+                            let date = date.format(DATE_INPUT_FMT);
+                            ["csv", "pdf"]
+                                .iter()
+                                .map(|format| {
+                                    a![
+                                        C![C.wide_button, C.wide_button_link],
+                                        attrs! {
+                                            At::Href => base::api_url(&format!(
+                                                "/api/export/member_balances.{}?date={}",
+                                                format, date
+                                            )),
+                                            At::Download => format!("tillgodo_{}.{}", date, format),
+                                        },
+                                        format.to_uppercase(),
+                                    ]
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default(),
                 ],
                 // TODO: implement this
                 /*
@@ -337,42 +1041,228 @@ impl TransactionsPage {
                     C![C.transactions_page_button_box],
                     button![
                         C![C.transactions_page_show_delete],
-                        "Radera transaktioner?",
+                        strings::SHOW_DELETE,
                         simple_ev(Ev::Click, TransactionsMsg::SetShowDelete(!self.show_delete)),
                     ],
+                    button![
+                        C![C.transactions_page_select],
+                        strings::SELECT_TRANSACTIONS,
+                        simple_ev(Ev::Click, TransactionsMsg::SetSelecting(!self.selecting)),
+                    ],
                 ],
+                IF!(self.selecting => self.view_batch_bar()),
                 transaction_list,
                 if self.view_limit < self.filtered_transactions.len() {
                     button![
                         C![C.wide_button],
-                        "Visa fler",
+                        strings::SHOW_MORE,
                         simple_ev(Ev::Click, TransactionsMsg::IncreaseViewLimit),
                     ]
                 } else {
                     empty![]
                 },
             ],
+            match &self.detail {
+                Some(detail) => view_detail(
+                    *res.reporting_timezone,
+                    &res,
+                    detail,
+                    &self.note_input,
+                    &self.tags_input,
+                    &self.event_code_input,
+                    self.return_form.as_ref(),
+                    rs,
+                ),
+                None => empty![],
+            },
         ]
         .map_msg(|msg| Msg::TransactionsMsg(msg))
     }
+
+    /// The operations on the selected transactions
+    fn view_batch_bar(&self) -> Node<TransactionsMsg> {
+        let any_selected = !self.selected.is_empty();
+        div![
+            C![C.transactions_batch_bar],
+            span![format!(
+                "{} {}",
+                self.selected.len(),
+                strings::SELECTED_COUNT
+            )],
+            button![
+                C![C.rounded, C.border_on_focus],
+                simple_ev(Ev::Click, TransactionsMsg::SelectAllShown),
+                strings::SELECT_ALL_SHOWN,
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                attrs! {At::Disabled => (!any_selected).as_at_value()},
+                simple_ev(Ev::Click, TransactionsMsg::ClearSelection),
+                strings::CLEAR_SELECTION,
+            ],
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::VOID_REASON,
+                    At::Value => self.batch_reason,
+                },
+                input_ev(Ev::Input, TransactionsMsg::SetBatchReason),
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Disabled =>
+                        (!any_selected || self.batch_reason.trim().is_empty()).as_at_value(),
+                },
+                simple_ev(Ev::Click, TransactionsMsg::VoidSelected),
+                strings::VOID_SELECTED,
+            ],
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::TAGS_PLACEHOLDER,
+                    At::Value => self.batch_tags,
+                },
+                input_ev(Ev::Input, TransactionsMsg::SetBatchTags),
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                attrs! {At::Disabled => (!any_selected).as_at_value()},
+                simple_ev(Ev::Click, TransactionsMsg::TagSelected),
+                strings::ADD_TAGS,
+            ],
+        ]
+    }
+
+    /// Today's transactions from the cache, shown until the whole history has been fetched
+    fn view_recent(&self, rs: &ResourceStore, recent: &RecentTransactions) -> Node<Msg> {
+        let res = match RecentRes::acquire_now(rs) {
+            Ok(res) if recent.is_loaded() => res,
+            _ => {
+                return Loading::with_retry(div![
+                    C![C.transactions_page],
+                    div![C![C.transactions_list], Loading::skeleton_list()],
+                ])
+            }
+        };
+
+        let tz = res.reporting_timezone;
+        let today = Utc::now().with_timezone(tz).date().naive_local();
+
+        div![
+            C![C.transactions_page],
+            div![
+                C![C.transactions_list],
+                recent
+                    .iter()
+                    .filter(|tr| tr.time.with_timezone(tz).date().naive_local() == today)
+                    .map(|tr| {
+                        view_transaction(
+                            *tz,
+                            res.book_accounts,
+                            res.inventory,
+                            tr,
+                            false,
+                            self.highlighted == Some(tr.id),
+                            None,
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+                div![
+                    C![C.transactions_loading_older],
+                    strings::LOADING_OLDER_TRANSACTIONS
+                ],
+            ],
+        ]
+        .map_msg(|msg| Msg::TransactionsMsg(msg))
+    }
+}
+
+/// Whether `tr` passes the date, item, payment and search filters of the page
+fn matches_filters(
+    tr: &Transaction,
+    tz: &Tz,
+    from_date: Option<NaiveDate>,
+    to_date: Option<NaiveDate>,
+    item: Option<InventoryItemId>,
+    paid_with: Option<BookAccountId>,
+    search: &str,
+) -> bool {
+    let date = tr.time.with_timezone(tz).naive_local().date();
+    let has_item = |item| {
+        tr.bundles
+            .iter()
+            .any(|bundle| bundle.item_ids.contains_key(&item))
+    };
+
+    from_date.map(|from| date >= from).unwrap_or(true)
+        && to_date.map(|to| date <= to).unwrap_or(true)
+        && item.map(has_item).unwrap_or(true)
+        && paid_with
+            .map(|account| tr.debited_account == account)
+            .unwrap_or(true)
+        && (search.is_empty() || tr.annotation_matches(search))
+}
+
+/// The balance of every account which `transactions` move money to or from
+fn account_balances<'a>(
+    transactions: impl Iterator<Item = &'a Transaction>,
+    accounts: &HashMap<BookAccountId, BookAccount>,
+) -> HashMap<BookAccountId, Currency> {
+    let mut balances: HashMap<BookAccountId, Currency> = HashMap::new();
+    for tr in transactions {
+        if let Some(acc) = accounts.get(&tr.debited_account) {
+            *balances.entry(tr.debited_account).or_default() += acc.debit_diff(tr.amount);
+        }
+        if let Some(acc) = accounts.get(&tr.credited_account) {
+            *balances.entry(tr.credited_account).or_default() += acc.credit_diff(tr.amount);
+        }
+    }
+    balances
 }
 
 fn view_transaction(
-    timezone: FixedOffset,
-    res: &Res,
+    timezone: Tz,
+    book_accounts: &HashMap<BookAccountId, BookAccount>,
+    inventory: &HashMap<InventoryItemId, InventoryItemStock>,
     transaction: &Transaction,
     show_delete: bool,
+    highlighted: bool,
+    selected: Option<bool>,
 ) -> Node<TransactionsMsg> {
     div![
         C![C.transaction_view],
+        if highlighted {
+            C![C.transaction_view_highlighted]
+        } else {
+            C![]
+        },
+        attrs! {At::Id => transaction.id},
         p![
             C![C.transaction_line],
+            match selected {
+                Some(selected) => input![
+                    C![C.transaction_view_select],
+                    attrs! {
+                        At::Type => "checkbox",
+                        At::Checked => selected.as_at_value(),
+                    },
+                    simple_ev(Ev::Click, TransactionsMsg::ToggleSelected(transaction.id)),
+                ],
+                None => empty![],
+            },
             span![format!("#{} ", transaction.id)],
             span![transaction
                 .description
                 .as_ref()
                 .map(|s| s.as_str())
-                .unwrap_or("Transaktion")],
+                .unwrap_or(strings::TRANSACTION.get())],
+            button![
+                C![C.transaction_view_detail_button, C.border_on_focus],
+                attrs! {At::Title => strings::TRANSACTION_DETAILS},
+                simple_ev(Ev::Click, TransactionsMsg::ShowDetail(transaction.id)),
+                "ⓘ",
+            ],
             if show_delete {
                 button![
                     C![C.transaction_view_delete_button],
@@ -398,10 +1288,10 @@ fn view_transaction(
         ],
         p![
             C![C.transaction_line],
-            span!["Debet: "],
+            span![format!("{}: ", strings::DEBIT)],
             span![
                 C![C.font_bold],
-                res.book_accounts
+                book_accounts
                     .get(&transaction.debited_account)
                     .map(|acc| acc.name.as_str())
                     .unwrap_or("[MISSING]")
@@ -409,20 +1299,28 @@ fn view_transaction(
         ],
         p![
             C![C.transaction_line],
-            span!["Kredit: "],
+            span![format!("{}: ", strings::CREDIT)],
             span![
                 C![C.font_bold],
-                res.book_accounts
+                book_accounts
                     .get(&transaction.credited_account)
                     .map(|acc| acc.name.as_str())
                     .unwrap_or("[MISSING]")
             ],
         ],
+        match &transaction.created_by {
+            Some(user) => p![
+                C![C.transaction_line],
+                span![format!("{}: ", strings::CASHIER)],
+                span![C![C.font_bold], user],
+            ],
+            None => empty![],
+        },
         transaction
             .bundles
             .iter()
             .map(|bundle| {
-                let mut items = bundle.item_ids.keys().map(|id| &res.inventory[id]);
+                let mut items = bundle.item_ids.keys().map(|id| &inventory[id]);
 
                 // TODO: Properly display more complicated bundles
 
@@ -437,7 +1335,7 @@ fn view_transaction(
                     .description
                     .as_ref()
                     .map(|s| s.as_str())
-                    .unwrap_or(item_name.unwrap_or("[NAMN SAKNAS]"));
+                    .unwrap_or(item_name.unwrap_or(strings::MISSING_NAME.get()));
                 let price = bundle.price.unwrap_or(item_price.into());
                 p![
                     C![C.transaction_entry],
@@ -450,11 +1348,348 @@ fn view_transaction(
             })
             .collect::<Vec<_>>(),
         p![
-            span!["Totalt: "],
+            span![strings::TRANSACTION_TOTAL, " "],
             span![
                 C![C.transaction_entry_item_price],
                 format!("{}:-", transaction.amount),
             ],
         ],
+        match &transaction.note {
+            Some(note) => p![C![C.transaction_line, C.transaction_note], note],
+            None => empty![],
+        },
+        if transaction.tags.is_empty() {
+            empty![]
+        } else {
+            p![
+                C![C.transaction_line],
+                transaction
+                    .tags
+                    .iter()
+                    .map(|tag| span![C![C.transaction_tag], tag]),
+            ]
+        },
     ]
 }
+
+/// The drawer with every line of a transaction, and what has happened to it
+fn view_detail(
+    timezone: Tz,
+    res: &Res,
+    detail: &TransactionDetail,
+    note_input: &str,
+    tags_input: &str,
+    event_code_input: &str,
+    return_form: Option<&GoodsReturnForm>,
+    rs: &ResourceStore,
+) -> Node<TransactionsMsg> {
+    let transaction = &detail.transaction;
+    let account_name = |id: &BookAccountId| {
+        res.book_accounts
+            .get(id)
+            .map(|acc| acc.name.as_str())
+            .unwrap_or("[MISSING]")
+    };
+    let fmt_time = |time: &chrono::DateTime<chrono::Utc>| {
+        time.with_timezone(&timezone)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+    let item_name = |id: &InventoryItemId| {
+        res.inventory
+            .get(id)
+            .map(|item| item.name.as_str())
+            .unwrap_or(strings::MISSING_NAME.get())
+    };
+
+    div![
+        C![C.transaction_detail_drawer],
+        div![
+            C![C.transaction_detail_header],
+            h2![format!("#{}", transaction.id)],
+            button![
+                C![C.border_on_focus],
+                simple_ev(Ev::Click, TransactionsMsg::CloseDetail),
+                "✖",
+            ],
+        ],
+        p![transaction
+            .description
+            .as_deref()
+            .unwrap_or(strings::TRANSACTION.get())],
+        table![
+            C![C.transaction_detail_table],
+            tr![td![strings::TIME], td![fmt_time(&transaction.time)]],
+            tr![
+                td![strings::PAYMENT_METHOD],
+                td![account_name(&transaction.debited_account)],
+            ],
+            tr![
+                td![strings::CREDIT],
+                td![account_name(&transaction.credited_account)],
+            ],
+            tr![
+                td![strings::CASHIER],
+                td![transaction
+                    .created_by
+                    .as_deref()
+                    .unwrap_or(strings::UNKNOWN.get())],
+            ],
+            match &detail.device {
+                Some(device) => tr![td![strings::DEVICE], td![device]],
+                None => empty![],
+            },
+            match detail.izettle_reference {
+                Some(reference) => tr![
+                    td![strings::IZETTLE_REFERENCE],
+                    td![format!("#{}", reference)]
+                ],
+                None => empty![],
+            },
+            if transaction.age_verified {
+                tr![td![strings::AGE_CHECK], td![strings::ID_CHECKED]]
+            } else {
+                empty![]
+            },
+        ],
+        match &detail.goods_return {
+            Some(goods_return) => table![
+                C![C.transaction_detail_table],
+                tr![
+                    td![strings::RETURN_OF],
+                    td![a![
+                        attrs! {At::Href => format!("#{}", goods_return.original_transaction_id)},
+                        format!("#{}", goods_return.original_transaction_id),
+                        simple_ev(
+                            Ev::Click,
+                            TransactionsMsg::ShowDetail(goods_return.original_transaction_id)
+                        ),
+                    ]],
+                ],
+                tr![
+                    td![strings::RETURN_REASON],
+                    td![goods_return::reason_text(goods_return.reason)],
+                ],
+                goods_return.items.iter().map(|(id, &count)| {
+                    tr![
+                        td![format!("{} x {}", count, item_name(id))],
+                        td![if goods_return.restocked {
+                            strings::RESTOCKED
+                        } else {
+                            strings::NOT_RESTOCKED
+                        }],
+                    ]
+                }),
+            ],
+            None => empty![],
+        },
+        table![
+            C![C.transaction_detail_table],
+            tr![
+                th![strings::ITEM],
+                th![strings::QUANTITY],
+                th![strings::UNIT_PRICE],
+                th![strings::BUNDLE],
+            ],
+            transaction.bundles.iter().flat_map(|bundle| {
+                bundle.item_ids.iter().map(move |(id, &per_bundle)| {
+                    let item = res.inventory.get(id);
+                    let unit_price = match (bundle.item_ids.len(), bundle.price) {
+                        // the price of a single-item bundle is the price of the item
+                        (1, Some(price)) => Some(price),
+                        _ => item.and_then(|item| item.price).map(Currency::from),
+                    };
+                    tr![
+                        td![item_name(id)],
+                        td![(-bundle.change * per_bundle as i32).to_string()],
+                        td![unit_price
+                            .map(|price| format!("{}:-", price))
+                            .unwrap_or_default()],
+                        td![bundle.description.as_deref().unwrap_or("")],
+                    ]
+                })
+            }),
+        ],
+        p![
+            C![C.transaction_detail_total],
+            span![strings::TRANSACTION_TOTAL, " "],
+            span![format!("{}:-", transaction.amount)],
+        ],
+        div![
+            C![C.transaction_detail_annotation],
+            textarea![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::NOTE,
+                    At::Value => note_input,
+                },
+                input_ev(Ev::Input, TransactionsMsg::SetNoteInput),
+            ],
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::TAGS_PLACEHOLDER,
+                    At::Value => tags_input,
+                },
+                input_ev(Ev::Input, TransactionsMsg::SetTagsInput),
+            ],
+            input![
+                C![C.rounded, C.border_on_focus],
+                attrs! {
+                    At::Placeholder => strings::EVENT_CODE,
+                    At::Value => event_code_input,
+                },
+                input_ev(Ev::Input, TransactionsMsg::SetEventCodeInput),
+            ],
+            button![
+                C![C.rounded, C.border_on_focus],
+                simple_ev(Ev::Click, TransactionsMsg::SaveAnnotation),
+                strings::SAVE,
+            ],
+        ],
+        match return_form {
+            Some(form) => form
+                .view(rs)
+                .map_msg(|msg| TransactionsMsg::GoodsReturnMsg(msg)),
+            None if GoodsReturnForm::has_returnable(detail) => button![
+                C![C.rounded, C.border_on_focus],
+                simple_ev(Ev::Click, TransactionsMsg::StartReturn),
+                strings::RETURN_GOODS,
+            ],
+            None => empty![],
+        },
+        h3![strings::HISTORY],
+        ul![
+            li![format!(
+                "{} {}",
+                fmt_time(&transaction.time),
+                strings::CREATED
+            )],
+            match &detail.deleted_at {
+                Some(deleted_at) => li![
+                    C![C.transaction_detail_deleted],
+                    format!("{} {}", fmt_time(deleted_at), strings::DELETED),
+                ],
+                None => empty![],
+            },
+            detail.returns.iter().map(|goods_return| {
+                let id = goods_return.transaction_id;
+                li![a![
+                    attrs! {At::Href => format!("#{}", id)},
+                    format!(
+                        "#{} {}: {}",
+                        id,
+                        strings::RETURNED,
+                        goods_return::reason_text(goods_return.reason)
+                    ),
+                    simple_ev(Ev::Click, TransactionsMsg::ShowDetail(id)),
+                ]]
+            }),
+        ],
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use strecklistan_api::book_account::BookAccountType;
+    use strecklistan_api::transaction::TransactionBundle;
+
+    fn transaction(id: TransactionId, hour: u32, item: InventoryItemId) -> Transaction {
+        let mut item_ids = HashMap::new();
+        item_ids.insert(item, 1);
+
+        Transaction {
+            id,
+            description: None,
+            time: Utc.ymd(2021, 6, 30).and_hms(hour, 0, 0),
+            bundles: vec![TransactionBundle {
+                description: None,
+                price: None,
+                change: -1,
+                item_ids,
+                expires_on: None,
+                modifier_ids: vec![],
+            }],
+            debited_account: 1,
+            credited_account: 2,
+            amount: 1000.into(),
+            note: Some("Sittning".to_string()),
+            tags: vec!["event: LAN".to_string()],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            created_by: None,
+        }
+    }
+
+    #[test]
+    fn test_filters() {
+        let tz = Tz::Europe__Stockholm;
+        let date = |d| Some(NaiveDate::from_ymd(2021, 6, d));
+        let tr = transaction(1, 12, 3);
+
+        assert!(matches_filters(&tr, &tz, None, None, None, None, ""));
+        assert!(matches_filters(
+            &tr,
+            &tz,
+            date(30),
+            date(30),
+            Some(3),
+            Some(1),
+            "lan"
+        ));
+        assert!(!matches_filters(
+            &tr,
+            &tz,
+            date(29),
+            date(29),
+            None,
+            None,
+            ""
+        ));
+        assert!(!matches_filters(&tr, &tz, None, None, Some(4), None, ""));
+        assert!(!matches_filters(&tr, &tz, None, None, None, None, "pub"));
+        assert!(!matches_filters(&tr, &tz, None, None, None, Some(2), ""));
+
+        // 23:00 UTC is the next day in Stockholm
+        let late = transaction(2, 23, 3);
+        assert!(!matches_filters(&late, &tz, None, date(30), None, None, ""));
+        assert!(matches_filters(
+            &late,
+            &Tz::UTC,
+            None,
+            date(30),
+            None,
+            None,
+            ""
+        ));
+    }
+
+    #[test]
+    fn test_account_balances() {
+        let account = |id, account_type| BookAccount {
+            id,
+            name: format!("account {}", id),
+            account_type,
+            creditor: None,
+            balance: 0.into(),
+        };
+        let accounts: HashMap<_, _> = vec![
+            (1, account(1, BookAccountType::Assets)),
+            (2, account(2, BookAccountType::Revenue)),
+        ]
+        .into_iter()
+        .collect();
+
+        let mut unknown = transaction(3, 12, 3);
+        unknown.debited_account = 42;
+        let transactions = vec![transaction(1, 12, 3), transaction(2, 12, 3), unknown];
+
+        let balances = account_balances(transactions.iter(), &accounts);
+        assert_eq!(balances.get(&1), Some(&Currency::from(2000)));
+        assert_eq!(balances.get(&2), Some(&Currency::from(3000)));
+        assert_eq!(balances.get(&42), None);
+    }
+}
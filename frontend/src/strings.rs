@@ -1,34 +1,872 @@
+use crate::i18n::Text;
+
+// Transaction descriptions are stored in the database, so they are not translated
 pub const TRANSACTION_SALE: &str = "Försäljning";
 pub const TRANSACTION_DEPOSIT: &str = "Insättning";
 pub const TRANSACTION_TILLGODO: &str = "Tillgodo";
 
-pub const ABORT: &str = "Avbryt";
-pub const CONFIRM: &str = "Bekräfta";
+pub const PAGE_STORE: Text = Text::new("försäljning", "store");
+pub const PAGE_DEPOSIT: Text = Text::new("tillgodo", "deposit");
+pub const PAGE_TRANSACTIONS: Text = Text::new("transaktioner", "transactions");
+pub const PAGE_ANALYTICS: Text = Text::new("analys", "analytics");
+pub const REGISTER_MODE: Text = Text::new("Kassaläge", "Register mode");
+pub const TRAINING_MODE: Text = Text::new("Träningsläge", "Training mode");
+pub const PAGE_REPORT: Text = Text::new("dagsrapport", "daily report");
+pub const PAGE_EVENTS: Text = Text::new("evenemang", "events");
+pub const PAGE_STOCK_VALUE: Text = Text::new("lagervärde", "stock value");
+pub const PAGE_ADMIN: Text = Text::new("admin", "admin");
+pub const PAGE_DASHBOARD: Text = Text::new("översikt", "dashboard");
+pub const TITLE_STORE: Text = Text::new("Försäljning", "Store");
+pub const TITLE_DEPOSIT: Text = Text::new("Tillgodo", "Deposit");
+pub const TITLE_TRANSACTIONS: Text = Text::new("Transaktioner", "Transactions");
+pub const TITLE_ANALYTICS: Text = Text::new("Analys", "Analytics");
+pub const TITLE_EVENTS: Text = Text::new("Evenemang", "Events");
+pub const TITLE_STOCK_VALUE: Text = Text::new("Lagervärde", "Stock value");
+pub const TITLE_ADMIN: Text = Text::new("Administration", "Administration");
+pub const TITLE_DASHBOARD: Text = Text::new("Översikt", "Dashboard");
+pub const TITLE_KIOSK: Text = Text::new("Info", "Info");
+pub const TITLE_NOT_FOUND: Text = Text::new("Sidan finns inte", "Page not found");
+pub const LEAVE_UNSAVED_CART: Text = Text::new(
+    "Varukorgen är inte tom. Vill du lämna sidan ändå?",
+    "The cart is not empty. Leave the page anyway?",
+);
+pub const LEAVE_UNSAVED_DEPOSIT: Text = Text::new(
+    "Insättningen är inte slutförd. Vill du lämna sidan ändå?",
+    "The deposit is not complete. Leave the page anyway?",
+);
+pub const REFRESH: Text = Text::new("Uppdatera", "Refresh");
+pub const MENU: Text = Text::new("Meny", "Menu");
+pub const SWITCH_LANGUAGE: Text = Text::new("EN", "SV");
+pub const ERROR_OCCURRED: Text = Text::new("Ett fel har inträffat.", "An error has occurred.");
+
+pub const LOG_IN: Text = Text::new("Logga in", "Log in");
+pub const LOG_OUT: Text = Text::new("Logga ut", "Log out");
+pub const THEME_LIGHT: Text = Text::new("Ljust tema", "Light theme");
+pub const THEME_DARK: Text = Text::new("Mörkt tema", "Dark theme");
+pub const THEME_SYSTEM: Text = Text::new("Följ systemets tema", "Follow the system theme");
+pub const USERNAME: Text = Text::new("Användarnamn", "Username");
+pub const PASSWORD: Text = Text::new("Lösenord", "Password");
+pub const CHOOSE_ORGANIZATION: Text = Text::new("Välj organisation", "Choose an organization");
+pub const INVALID_CREDENTIALS: Text = Text::new(
+    "Fel användarnamn eller lösenord",
+    "Wrong username or password",
+);
+pub const TOO_MANY_LOGIN_ATTEMPTS: Text = Text::new(
+    "För många inloggningsförsök, vänta en stund",
+    "Too many login attempts, wait a moment",
+);
+pub const ACCOUNT_LOCKED: Text = Text::new(
+    "Kontot är låst efter för många misslyckade inloggningar, försök igen senare",
+    "The account is locked after too many failed logins, try again later",
+);
+
+pub const ABORT: Text = Text::new("Avbryt", "Cancel");
+pub const CONFIRM: Text = Text::new("Bekräfta", "Confirm");
+
+pub const DEPOSIT: Text = Text::new("Sätt in", "Deposit");
+pub const CHOOSE_TILLGODO_ACC: Text = Text::new("Välj Tillgodokonto", "Choose a tillgodo account");
+pub const TILLGODO_LIST: Text = Text::new("Tillgodolista", "Tillgodo list");
+
+pub const IZETTLE: Text = Text::new("iZettle", "iZettle");
+pub const OTHER_EPAY: Text = Text::new("Swish", "Swish");
+
+pub const FIRST_NAME: Text = Text::new("Förnamn", "First name");
+pub const LAST_NAME: Text = Text::new("Efternamn", "Last name");
+pub const NICKNAME: Text = Text::new("Smeknamn", "Nickname");
+pub const ACCOUNT_NAME: Text = Text::new("Kontonamn", "Account name");
+
+pub const WAITING_FOR_PAYMENT: Text = Text::new("Väntar på betalning...", "Waiting for payment...");
+pub const SWISH_PENDING: Text = Text::new("Väntar på Swish", "Waiting for Swish");
+
+pub const INVALID_MONEY_MESSAGE_SHORT: Text = Text::new("Ogiltig summa", "Invalid amount");
+pub const INVALID_MONEY_MESSAGE_LONG: Text = Text::new(
+    "Måste vara giltig summa (e.g. 42 eller 123.45)",
+    "Must be a valid amount (e.g. 42 or 123.45)",
+);
+
+pub const DEPOSIT_COMPLETE: Text = Text::new("Insättning slutförd", "Deposit complete");
+pub const DEPOSIT_AMOUNT_ZERO: Text = Text::new("Ange ett belopp", "Enter an amount");
+pub const DEPOSIT_AMOUNT_TOO_LARGE: Text = Text::new(
+    "Beloppet är orimligt stort",
+    "The amount is unreasonably large",
+);
+pub const DEPOSIT_NO_ACCOUNT: Text =
+    Text::new("Välj ett tillgodokonto", "Choose a tillgodo account");
+
+pub const SHOW_HISTORY: Text = Text::new("Visa historik", "Show history");
+pub const HIDE_HISTORY: Text = Text::new("Dölj historik", "Hide history");
+pub const SHOW_CONTACT: Text = Text::new("Visa kontaktuppgifter", "Show contact details");
+pub const HIDE_CONTACT: Text = Text::new("Dölj kontaktuppgifter", "Hide contact details");
+pub const EMAIL: Text = Text::new("e-postadress", "email address");
+pub const EMAIL_CODE: Text = Text::new("kod", "code");
+pub const EMAIL_CODE_SENT: Text = Text::new(
+    "En kod har skickats till adressen:",
+    "A code has been sent to the address:",
+);
+pub const EMAIL_VERIFIED: Text = Text::new("Adressen är bekräftad", "The address is confirmed");
+pub const MAIL_RECEIPTS: Text = Text::new("Kvitton", "Receipts");
+pub const MAIL_BALANCE_REMINDERS: Text = Text::new("Påminnelser om saldot", "Balance reminders");
+pub const MAIL_EVENT_INFO: Text = Text::new("Information om evenemang", "Event info");
+pub const MAIL_NOT_VERIFIED_HINT: Text = Text::new(
+    "Inget skickas till adressen innan den har bekräftats med koden.",
+    "Nothing is sent to the address before it has been confirmed with the code.",
+);
+pub const BALANCE_CARD: Text = Text::new("Saldokort", "Balance card");
+pub const NEW_BALANCE_CARD: Text = Text::new("Skapa saldokort", "Create balance card");
+pub const PRINT_BALANCE_CARD: Text = Text::new("Skriv ut", "Print");
+pub const REPLACE_BALANCE_CARD: Text = Text::new("Ersätt kortet", "Replace the card");
+pub const REPLACE_BALANCE_CARD_HINT: Text = Text::new(
+    "Ett ersatt kort slutar fungera, t.ex. om det har tappats bort.",
+    "A replaced card stops working, e.g. if it has been lost.",
+);
+pub const CONTACT_UPDATE_FAILED: Text = Text::new(
+    "Kunde inte spara kontaktuppgifterna",
+    "Could not save the contact details",
+);
+pub const NO_HISTORY: Text = Text::new("Inga transaktioner", "No transactions");
+pub const DATE: Text = Text::new("Datum", "Date");
+pub const DESCRIPTION: Text = Text::new("Beskrivning", "Description");
+pub const CHANGE: Text = Text::new("Ändring", "Change");
+pub const BALANCE: Text = Text::new("Saldo", "Balance");
+pub const PURCHASE_COMPLETE: Text = Text::new("Köp slutfört", "Purchase complete");
+pub const COMPLETE_PURCHASE: Text = Text::new("Slutför Köp", "Complete purchase");
+pub const TAG_MEMBER: Text = Text::new(
+    "Köpande medlem, påverkar inte tillgodo",
+    "Buying member, doesn't touch their tillgodo",
+);
+pub const BOUGHT_BY: Text = Text::new("Köps av", "Bought by");
+pub const AGE_RESTRICTED_IN_CART: Text = Text::new(
+    "Varukorgen innehåller åldersbegränsade varor, kontrollera köparens legitimation",
+    "The cart contains age-restricted items, check the ID of the buyer",
+);
+pub const ID_CHECKED: Text = Text::new("Legitimation kontrollerad", "ID checked");
+pub const AGE_CHECK: Text = Text::new("Ålderskontroll", "Age check");
+
+pub const SERVER_ERROR: Text = Text::new("Serverfel", "Server error");
+pub const CONNECTION_FAILED: Text =
+    Text::new("Kunde inte nå servern", "Could not reach the server");
+pub const RETRY: Text = Text::new("Försök igen", "Try again");
+pub const OFFLINE: Text = Text::new(
+    "Ingen anslutning, sidan laddas om när den är tillbaka",
+    "No connection, the page will reload once it's back",
+);
+pub const PAYMENT_FAILED: Text = Text::new("Betalning misslyckades", "Payment failed");
+pub const PAYMENT_CANCELLED: Text = Text::new("Betalning avbruten", "Payment cancelled");
+pub const NO_PENDING_TRANSACTION: Text =
+    Text::new("Ingen pågående transaktion", "No pending transaction");
+pub const POSTING_TRANSACTION_FAILED: Text = Text::new(
+    "Misslyckades med att skicka transaktion",
+    "Failed to send transaction",
+);
+pub const POLLING_TRANSACTION_FAILED: Text = Text::new(
+    "Misslyckades med att polla transaktion",
+    "Failed to poll transaction",
+);
+pub const IZETTLE_DISABLED: Text =
+    Text::new("Kortbetalning är avstängd", "Card payments are disabled");
+pub const BRIDGE_OFFLINE: Text = Text::new(
+    "Kortläsaren svarar inte, kontrollera iZettle-bryggan",
+    "The card reader is not responding, check the iZettle bridge",
+);
+
+pub const PAYMENT_PENDING: Text = Text::new("Kortbetalning pågår", "Card payment in progress");
+pub const PAYMENT_CONNECTING: Text = Text::new("Hämtar status...", "Fetching the status...");
+pub const BRIDGE_STATUS_ONLINE: Text =
+    Text::new("Kortläsaren är ansluten", "The card reader is connected");
+pub const PAYMENT_AT_READER: Text = Text::new(
+    "Betalningen visas på kortläsaren",
+    "The payment is on the card reader",
+);
+pub const PAYMENT_QUEUED: Text = Text::new(
+    "Betalningen väntar på tidigare betalningar",
+    "The payment is waiting for earlier payments",
+);
+pub const CANCEL_ON_READER_HINT: Text = Text::new(
+    "Avbryt även på kortläsaren om betalningen visas där, annars kan kortet ändå debiteras",
+    "Cancel on the card reader too if the payment is shown there, or the card may still be charged",
+);
+pub const CANCEL_PAYMENT: Text = Text::new("Avbryt betalning", "Cancel payment");
+pub const CANCEL_PAYMENT_FAILED: Text = Text::new(
+    "Kunde inte avbryta betalningen, försök igen",
+    "Could not cancel the payment, try again",
+);
+pub const SWITCH_TO_CASH: Text = Text::new("Betala kontant istället", "Pay with cash instead");
+
+pub const TRANSACTION_TOTAL: Text = Text::new("Totalt:", "Total:");
+
+pub const MISSING_NAME: Text = Text::new("[NAMN SAKNAS]", "[NAME MISSING]");
+pub const IN_STOCK: Text = Text::new("i lager.", "in stock.");
+pub const RESERVED: Text = Text::new("förbeställda.", "pre-ordered.");
+pub const SEARCH_INVENTORY: Text = Text::new("sök varor", "search items");
+
+pub const TRANSACTION: Text = Text::new("Transaktion", "Transaction");
+pub const DEBIT: Text = Text::new("Debet", "Debit");
+pub const CREDIT: Text = Text::new("Kredit", "Credit");
+pub const BALANCE_SHEET: Text = Text::new("Balansräkning", "Balance sheet");
+pub const TILLGODO_TOTAL: Text = Text::new("Tillgodo Totalt", "Tillgodo total");
+pub const FILTER: Text = Text::new("Filtrera (WIP)", "Filter (WIP)");
+pub const FROM_DATE: Text = Text::new("Från", "From");
+pub const TO_DATE: Text = Text::new("Till", "To");
+pub const ITEM: Text = Text::new("Vara", "Item");
+pub const EXPORT_DATA: Text = Text::new("Exportera Data", "Export data");
+pub const EXPORT_CSV_PER_ITEM: Text = Text::new("CSV (En rad per vara)", "CSV (One row per item)");
+pub const EXPORT_CSV_ALL: Text = Text::new(
+    "CSV (Hela historiken, en rad per vara)",
+    "CSV (The whole history, one row per item)",
+);
+pub const EXPORT_MEMBER_BALANCES: Text = Text::new(
+    "Tillgodolista för revisionen",
+    "Tillgodo list for the audit",
+);
+pub const MEMBER_BALANCES_AT: Text = Text::new("Saldon vid slutet av", "Balances at the end of");
+pub const SHOW_DELETE: Text = Text::new("Radera transaktioner?", "Delete transactions?");
+pub const SHOW_MORE: Text = Text::new("Visa fler", "Show more");
+pub const SELECT_TRANSACTIONS: Text = Text::new("Markera transaktioner", "Select transactions");
+pub const SELECTED_COUNT: Text = Text::new("markerade", "selected");
+pub const SELECT_ALL_SHOWN: Text = Text::new("Markera alla visade", "Select all shown");
+pub const CLEAR_SELECTION: Text = Text::new("Avmarkera", "Clear selection");
+pub const VOID_REASON: Text = Text::new("anledning", "reason");
+pub const VOID_SELECTED: Text = Text::new("Makulera markerade", "Void selected");
+pub const CONFIRM_VOID_SELECTED: Text = Text::new(
+    "Makulera alla markerade transaktioner?",
+    "Void all of the selected transactions?",
+);
+pub const ADD_TAGS: Text = Text::new("Lägg till taggar", "Add tags");
+pub const BATCH_FAILED: Text = Text::new(
+    "Kunde inte ändra transaktionerna",
+    "Could not change the transactions",
+);
+pub const LOADING_OLDER_TRANSACTIONS: Text = Text::new(
+    "Laddar äldre transaktioner...",
+    "Loading older transactions...",
+);
+
+pub const LOADING_ANALYTICS: Text = Text::new("Laddar statistik...", "Loading statistics...");
+pub const COMPUTE_ANALYTICS: Text = Text::new("Beräkna Statistik", "Compute statistics");
+pub const MODIFIER_SALES: Text = Text::new("Varianter, totalt", "Variants, all time");
+
+pub const EDIT_LAYOUT: Text = Text::new("Redigera layout", "Edit layout");
+pub const LAYOUT_SAVE: Text = Text::new("Spara layout", "Save layout");
+pub const LAYOUT_SAVE_FAILED: Text = Text::new(
+    "Misslyckades med att spara layout",
+    "Failed to save the layout",
+);
+pub const LAYOUT_NEW_TAB: Text = Text::new("Ny flik", "New tab");
+pub const LAYOUT_TAB_NAME: Text = Text::new("Fliknamn", "Tab name");
+pub const LAYOUT_ADD_TAB: Text = Text::new("Lägg till flik", "Add tab");
+pub const LAYOUT_REMOVE_TAB: Text = Text::new("Ta bort flik", "Remove tab");
+pub const LAYOUT_REMOVE_BUTTON: Text = Text::new("Ta bort knapp", "Remove button");
+
+pub const FAVORITES: Text = Text::new("Favoriter", "Favorites");
+
+pub const SHORTCUTS: Text = Text::new("Kortkommandon", "Keyboard shortcuts");
+pub const SHORTCUT_LIST: &[(&str, Text)] = &[
+    (
+        "0-9",
+        Text::new("Antal för nästa vara", "Quantity of the next item"),
+    ),
+    ("/", Text::new("Sök varor", "Search items")),
+    ("Enter", Text::new("Slutför köp", "Complete purchase")),
+    ("Esc", Text::new("Töm varukorgen", "Empty the cart")),
+    (
+        "?",
+        Text::new("Visa/dölj kortkommandon", "Show/hide keyboard shortcuts"),
+    ),
+];
+
+pub const UNKNOWN_BARCODE: Text = Text::new("Okänd streckkod", "Unknown barcode");
+
+pub const PARKED_CARTS: Text = Text::new("Parkerade köp", "Parked carts");
+pub const PARKED_CART: Text = Text::new("Kund", "Customer");
+pub const PARKED_CART_NAME: Text = Text::new("Namn på köpet", "Name of the purchase");
+pub const PARK_CART: Text = Text::new("Parkera varukorg", "Park cart");
+pub const RESUME_CART: Text = Text::new("Återuppta", "Resume");
+pub const ADD_TO_CART: Text = Text::new("Lägg till", "Add");
+pub const MODIFIER_EXTRAS: Text = Text::new("Tillval", "Extras");
+pub const REMOVE: Text = Text::new("Ta bort", "Remove");
+pub const CART_NOT_EMPTY: Text = Text::new("Varukorgen är inte tom", "The cart is not empty");
+pub const CART_PARKED: Text = Text::new("Varukorgen parkerades", "The cart was parked");
+pub const PARK_CART_FIRST: Text = Text::new(
+    "Slutför eller parkera det pågående köpet först",
+    "Complete or park the current purchase first",
+);
+pub const PARKED_CARTS_FAILED: Text = Text::new(
+    "Misslyckades med att spara parkerade köp",
+    "Failed to save parked carts",
+);
+
+pub const UNDO: Text = Text::new("Ångra", "Undo");
+pub const PURCHASE_UNDONE: Text = Text::new("Köpet ångrades", "The purchase was undone");
+pub const UNDO_PURCHASE_FAILED: Text = Text::new(
+    "Misslyckades med att ångra köpet",
+    "Failed to undo the purchase",
+);
+pub const PURCHASE_NOT_SAVED: Text = Text::new("Köpet sparades inte", "The purchase wasn't saved");
+pub const CART_RESTORED: Text = Text::new(
+    "Det har lagts tillbaka i varukorgen",
+    "It has been put back in the cart",
+);
+pub const DEPOSIT_NOT_SAVED: Text =
+    Text::new("Insättningen sparades inte", "The deposit wasn't saved");
+
+pub const ADMIN_USERS: Text = Text::new("Användare", "Users");
+pub const ADMIN_ROLE: Text = Text::new("Admin", "Admin");
+pub const ADMIN_UPDATE_FAILED: Text = Text::new(
+    "Misslyckades med att ändra användaren",
+    "Failed to update the user",
+);
+pub const INVALIDATE_SESSIONS: Text = Text::new("Logga ut överallt", "Log out everywhere");
+pub const SESSIONS_INVALIDATED: Text = Text::new(
+    "Användaren loggades ut överallt",
+    "The user was logged out everywhere",
+);
+pub const DEVICES: Text = Text::new("Enheter", "Devices");
+pub const DEVICES_HINT: Text = Text::new(
+    "Ge kassorna namn, så syns det vilken kassa köp och pass gjordes på, och om den är uppkopplad",
+    "Name the registers to see which one sales and shifts were made on, and whether it's online",
+);
+pub const NO_DEVICES: Text = Text::new("Inga registrerade enheter", "No registered devices");
+pub const DEVICE: Text = Text::new("Enhet", "Device");
+pub const DEVICE_NAME: Text = Text::new("Namn, t.ex. Kassa 1", "Name, e.g. Register 1");
+pub const REGISTER_THIS_DEVICE: Text =
+    Text::new("Registrera den här enheten", "Register this device");
+pub const THIS_DEVICE_IS: Text = Text::new("Den här enheten är", "This device is");
+pub const DEVICE_NAME_TAKEN: Text = Text::new(
+    "Det finns redan en enhet med det namnet",
+    "There already is a device with that name",
+);
+pub const ONLINE: Text = Text::new("Uppkopplad", "Online");
+pub const LAST_SEEN: Text = Text::new("Senast sedd", "Last seen");
+pub const RENAME: Text = Text::new("Byt namn", "Rename");
+pub const ADMIN_FEATURES: Text = Text::new("Funktioner", "Features");
+pub const FEATURE_IZETTLE: Text = Text::new("Kortbetalning (iZettle)", "Card payments (iZettle)");
+pub const FEATURE_SMTP: Text = Text::new("E-post", "Email");
+pub const FEATURE_WEBHOOKS: Text = Text::new("Webhooks", "Webhooks");
+pub const ENABLED: Text = Text::new("På", "Enabled");
+pub const DISABLED: Text = Text::new("Av", "Disabled");
+pub const ADMIN_BRIDGE: Text = Text::new("iZettle-brygga", "iZettle bridge");
+pub const BRIDGE_LAST_POLL: Text = Text::new("Senast hörd av", "Last seen");
+pub const BRIDGE_PENDING: Text = Text::new("Väntande betalningar", "Pending payments");
+pub const BRIDGE_OLDEST_PENDING: Text =
+    Text::new("Äldsta väntande betalning", "Oldest pending payment");
+pub const NEVER: Text = Text::new("Aldrig", "Never");
+pub const ADMIN_WEBHOOKS: Text = Text::new("Webhooks", "Webhooks");
+pub const NO_WEBHOOKS: Text = Text::new("Inga webhooks konfigurerade", "No webhooks configured");
+pub const WEBHOOK_SIGNED: Text = Text::new("Signerad", "Signed");
+pub const WEBHOOK_UNSIGNED: Text = Text::new("Osignerad", "Unsigned");
+pub const ADMIN_BACKUP: Text = Text::new("Säkerhetskopior", "Backups");
+pub const BACKUP_NOT_CONFIGURED: Text = Text::new(
+    "Ingen katalog för säkerhetskopior konfigurerad",
+    "No backup directory configured",
+);
+pub const NO_BACKUPS: Text = Text::new("Inga säkerhetskopior hittades", "No backups found");
+pub const BACKUP_LATEST: Text = Text::new("Senaste", "Latest");
+pub const BACKUP_TIME: Text = Text::new("Tidpunkt", "Time");
+pub const BACKUP_SIZE: Text = Text::new("Storlek", "Size");
+pub const ADMIN_ANALYTICS: Text = Text::new("Statistik", "Statistics");
+pub const ANALYTICS_REFRESH_HINT: Text = Text::new(
+    "Statistiken räknas om automatiskt med jämna mellanrum",
+    "The statistics are recomputed automatically at regular intervals",
+);
+pub const REFRESH_ANALYTICS: Text = Text::new("Räkna om nu", "Recompute now");
+pub const ANALYTICS_REFRESHED: Text =
+    Text::new("Statistiken räknades om", "The statistics were recomputed");
+pub const ANALYTICS_REFRESH_FAILED: Text = Text::new(
+    "Misslyckades med att räkna om statistiken",
+    "Failed to recompute the statistics",
+);
+pub const LOCK_DATE: Text = Text::new("Låsdatum", "Lock date");
+pub const LOCK_DATE_HINT: Text = Text::new(
+    "Transaktioner före detta datum kan inte raderas eller ändras",
+    "Transactions before this date can't be voided or edited",
+);
+pub const PERIOD_LOCKED: Text = Text::new(
+    "Transaktionen ligger i en stängd period och kan inte ändras",
+    "The transaction is in a closed period and can't be changed",
+);
+pub const ADMIN_IMPORT: Text = Text::new("Importera historik", "Import history");
+pub const IMPORT_HINT: Text = Text::new(
+    "En CSV-fil med kolumnerna date, time, kind (sale eller deposit), amount, account, item, \
+     quantity och description. Importerade köp räknas inte in i lagret.",
+    "A CSV file with the columns date, time, kind (sale or deposit), amount, account, item, \
+     quantity and description. Imported sales don't count towards the stock.",
+);
+pub const CHECK_IMPORT: Text = Text::new("Kontrollera", "Check");
+pub const IMPORT: Text = Text::new("Importera", "Import");
+pub const IMPORT_DONE: Text = Text::new("Importen är klar", "The import is done");
+pub const IMPORT_PERIOD: Text = Text::new("Period", "Period");
+pub const IMPORT_LINE: Text = Text::new("Rad", "Line");
+pub const IMPORT_TRANSACTIONS: Text = Text::new("transaktioner", "transactions");
+pub const ROLL_BACK_IMPORT: Text = Text::new("Ångra", "Roll back");
+pub const IMPORT_ROLLED_BACK: Text = Text::new("Ångrad", "Rolled back");
+pub const ADMIN_ANNOUNCEMENTS: Text = Text::new("Meddelanden", "Announcements");
+pub const ANNOUNCEMENTS_HINT: Text = Text::new(
+    "Visas överst på alla sidor under den valda perioden, t.ex. om kortläsaren är trasig",
+    "Shown at the top of every page during the chosen period, e.g. if the card reader is broken",
+);
+pub const ANNOUNCEMENT_MESSAGE: Text = Text::new("Meddelande", "Message");
+pub const ANNOUNCEMENT_STARTS: Text = Text::new("Från", "From");
+pub const ANNOUNCEMENT_ENDS: Text = Text::new("Till (valfritt)", "Until (optional)");
+pub const ANNOUNCE: Text = Text::new("Publicera", "Publish");
+pub const NO_ANNOUNCEMENTS: Text = Text::new("Inga meddelanden", "No announcements");
+pub const SEVERITY_INFO: Text = Text::new("Info", "Info");
+pub const SEVERITY_WARNING: Text = Text::new("Varning", "Warning");
+pub const SEVERITY_CRITICAL: Text = Text::new("Kritisk", "Critical");
+pub const DISMISS: Text = Text::new("Dölj", "Dismiss");
+pub const ADMIN_BRANDING: Text = Text::new("Organisation", "Organization");
+pub const BRANDING_HINT: Text = Text::new(
+    "Visas i sidhuvudet och på rapporter, exporter och mejl",
+    "Shown in the header and on reports, exports and emails",
+);
+pub const ORGANIZATION_NAME: Text = Text::new("Namn", "Name");
+pub const ORG_NUMBER: Text = Text::new("Org.nr", "Org. no.");
+pub const FOOTER_TEXT: Text = Text::new("Sidfot", "Footer");
+pub const LOGO: Text = Text::new("Logotyp", "Logo");
+pub const REMOVE_LOGO: Text = Text::new("Ta bort logotyp", "Remove logo");
+pub const BRANDING_SAVED: Text = Text::new("Organisationen sparad", "Organization saved");
+
+pub const DAILY_REPORT: Text = Text::new("Dagsrapport", "Daily report");
+pub const PRINT: Text = Text::new("Skriv ut", "Print");
+pub const REPORT_TRANSACTION_COUNT: Text = Text::new("Antal transaktioner", "Transactions");
+pub const REPORT_TOTAL_SALES: Text = Text::new("Total försäljning", "Total sales");
+pub const REPORT_TOTAL_DEPOSITS: Text = Text::new("Totala insättningar", "Total deposits");
+pub const REPORT_TOTAL_TIPS: Text = Text::new("Total dricks", "Total tips");
+pub const REPORT_SALES: Text = Text::new("Försäljning per betalsätt", "Sales by payment method");
+pub const REPORT_DEPOSITS: Text =
+    Text::new("Insättningar per betalsätt", "Deposits by payment method");
+pub const REPORT_TIPS: Text = Text::new("Dricks per betalsätt", "Tips by payment method");
+pub const REPORT_TOTAL_FEES: Text = Text::new("Förväntade avgifter", "Expected fees");
+pub const REPORT_FEES: Text = Text::new("Avgifter per betaltjänst", "Fees by payment provider");
+pub const REPORT_MONTHLY_FEES: Text = Text::new("Avgifter under månaden", "Fees during the month");
+pub const REPORT_PAID_OUT: Text = Text::new("Utbetalas", "Paid out");
+pub const REPORT_ITEMS: Text = Text::new("Sålda varor", "Items sold");
+pub const REPORT_CASH_COUNT: Text = Text::new("Kassaräkning", "Cash count");
+pub const REPORT_COUNTED_CASH: Text = Text::new("Räknat", "Counted");
+pub const REPORT_OPENING_FLOAT: Text = Text::new("Växelkassa", "Opening float");
+pub const REPORT_EXPECTED_CASH: Text = Text::new(
+    "Kontantförsäljning och insättningar",
+    "Cash sales and deposits",
+);
+pub const REPORT_CASH_DIFFERENCE: Text = Text::new("Differens", "Difference");
+pub const REPORT_SIGNATURE: Text = Text::new("Signatur", "Signature");
+
+pub const VALUATION_FIFO: Text = Text::new("Värdera enligt FIFO", "Value by FIFO");
+pub const VALUATION_FIFO_DESCRIPTION: Text = Text::new(
+    "Varorna i lager antas vara de som köptes in senast, och värderas till vad de inköpen kostade.",
+    "The items in stock are assumed to be the ones purchased last, and are valued at what those purchases cost.",
+);
+pub const VALUATION_COST_PRICE_DESCRIPTION: Text = Text::new(
+    "Varorna i lager värderas till sitt inköpspris.",
+    "The items in stock are valued at their cost price.",
+);
+pub const IN_STOCK_COUNT: Text = Text::new("I lager", "In stock");
+pub const COST_PRICE: Text = Text::new("Inköpspris", "Cost price");
+pub const STOCK_VALUE: Text = Text::new("Värde", "Value");
+pub const SOLD_LAST_30_DAYS: Text =
+    Text::new("Sålt senaste 30 dagarna", "Sold in the last 30 days");
+pub const STOCK_VALUE_TOTAL: Text = Text::new("Totalt lagervärde", "Total stock value");
+pub const STOCK_VALUE_INCOMPLETE: Text = Text::new(
+    "Inköpspriset saknas för vissa varor, de ingår inte i totalen.",
+    "Some items are missing a cost price, they are not included in the total.",
+);
+pub const BATCH_SELECTED: Text = Text::new("markerade", "selected");
+pub const BATCH_CATEGORY_PLACEHOLDER: Text = Text::new("ny kategori", "new category");
+pub const BATCH_PRICE_PLACEHOLDER: Text = Text::new("ändra pris", "change price");
+pub const BATCH_ARCHIVE: Text = Text::new("Arkivera", "Archive");
+pub const BATCH_APPLY: Text = Text::new("Ändra", "Apply");
+pub const BATCH_EDIT_FAILED: Text =
+    Text::new("Kunde inte ändra varorna", "Could not edit the items");
+
+pub const NO_EVENTS: Text = Text::new(
+    "Inga transaktioner har märkts med en evenemangskod",
+    "No transactions have been tagged with an event code",
+);
+pub const EVENT_REVENUE: Text = Text::new("Intäkter", "Revenue");
+pub const EVENT_COSTS: Text = Text::new("Kostnader", "Costs");
+pub const EVENT_RESULT: Text = Text::new("Resultat", "Result");
+
+pub const TRANSACTION_DETAILS: Text = Text::new("Visa detaljer", "Show details");
+pub const TIME: Text = Text::new("Tid", "Time");
+pub const PAYMENT_METHOD: Text = Text::new("Betalsätt", "Payment method");
+pub const IZETTLE_REFERENCE: Text = Text::new("iZettle-referens", "iZettle reference");
+pub const QUANTITY: Text = Text::new("Antal", "Qty");
+pub const UNIT_PRICE: Text = Text::new("Á-pris", "Unit price");
+pub const BUNDLE: Text = Text::new("Paket", "Bundle");
+pub const HISTORY: Text = Text::new("Historik", "History");
+pub const CREATED: Text = Text::new("skapad", "created");
+pub const DELETED: Text = Text::new("raderad", "deleted");
+pub const RETURNED: Text = Text::new("returnerad", "returned");
+
+pub const RETURN_GOODS: Text = Text::new("Returnera varor", "Return goods");
+pub const RETURN_OF: Text = Text::new("Retur av", "Return of");
+pub const RETURN_REASON: Text = Text::new("Orsak", "Reason");
+pub const CHOOSE_RETURN_REASON: Text = Text::new("Välj orsak", "Choose a reason");
+pub const RETURN_DEFECTIVE: Text = Text::new("Trasig", "Defective");
+pub const RETURN_WRONG_ITEM: Text = Text::new("Fel vara", "Wrong item");
+pub const RETURN_EXPIRED: Text = Text::new("För gammal", "Expired");
+pub const RETURN_CHANGED_MIND: Text = Text::new("Ångrat köp", "Changed mind");
+pub const RETURN_OTHER: Text = Text::new("Annat", "Other");
+pub const RESTOCK: Text = Text::new("Lägg tillbaka i lager", "Put back in stock");
+pub const RESTOCKED: Text = Text::new("Tillbaka i lager", "Back in stock");
+pub const NOT_RESTOCKED: Text = Text::new("Kasserad", "Thrown away");
+pub const PAID_BACK: Text = Text::new("Återbetalas", "Paid back");
+pub const RETURN_FAILED: Text = Text::new("Kunde inte returnera", "Could not return the goods");
+
+pub const NOTE: Text = Text::new("Anteckning", "Note");
+pub const TAGS_PLACEHOLDER: Text = Text::new("taggar, kommaseparerade", "tags, comma-separated");
+pub const SEARCH_NOTES: Text = Text::new("sök anteckningar och taggar", "search notes and tags");
+pub const EVENT_CODE: Text = Text::new("Evenemangskod", "Event code");
+pub const SAVE: Text = Text::new("Spara", "Save");
+pub const SAVE_FAILED: Text = Text::new("Kunde inte spara", "Could not save");
+
+pub const CASHIER: Text = Text::new("Kassör", "Cashier");
+pub const UNKNOWN: Text = Text::new("okänd", "unknown");
+pub const ALL_CASHIERS: Text = Text::new("Alla kassörer", "All cashiers");
+pub const ALL_PAYMENT_METHODS: Text = Text::new("Alla betalsätt", "All payment methods");
+pub const FILTER_PRESETS: Text = Text::new("Sparade filter", "Saved filters");
+pub const PRESET_NAME: Text = Text::new("namn på filtret", "name of the filter");
+pub const SAVE_PRESET: Text = Text::new("Spara filter", "Save filter");
+pub const DELETE_PRESET: Text = Text::new("Ta bort filtret", "Delete the filter");
+
+pub const PERIOD_CUSTOM: Text = Text::new("Valfria datum", "Custom dates");
+pub const PERIOD_DAY: Text = Text::new("Dag", "Day");
+pub const PERIOD_WEEK: Text = Text::new("Vecka", "Week");
+pub const PERIOD_MONTH: Text = Text::new("Månad", "Month");
+pub const PERIOD_SEMESTER: Text = Text::new("Termin", "Semester");
+pub const PREVIOUS_PERIOD: Text = Text::new("Föregående period", "Previous period");
+pub const NEXT_PERIOD: Text = Text::new("Nästa period", "Next period");
+
+pub const GOALS: Text = Text::new("Mål", "Goals");
+pub const GOALS_HINT: Text = Text::new(
+    "Visas på statistiksidan, och mål i antal även på infoskärmen",
+    "Shown on the analytics page, and quantity goals also on the info screen",
+);
+pub const NO_GOALS: Text = Text::new("Inga mål", "No goals");
+pub const GOAL_NAME: Text = Text::new("Namn på målet", "Name of the goal");
+pub const GOAL_REVENUE: Text = Text::new("Omsättning (kr)", "Revenue (SEK)");
+pub const GOAL_QUANTITY: Text = Text::new("Antal sålda", "Items sold");
+pub const GOAL_TARGET: Text = Text::new("Mål", "Target");
+pub const GOAL_EVENT: Text = Text::new("Evenemang", "Event");
+pub const GOAL_ITEMS: Text = Text::new("st", "pcs");
+pub const SET_GOAL: Text = Text::new("Sätt mål", "Set goal");
+
+pub const PURCHASE_LIMITS: Text = Text::new("Köpgränser", "Purchase limits");
+pub const PURCHASE_LIMITS_HINT: Text = Text::new(
+    "Hur många av en vara varje medlem får köpa från sitt tillgodo per period. Mer än så kräver en admins godkännande.",
+    "How many of an item every member may buy from their balance per period. More than that needs the approval of an admin.",
+);
+pub const NO_PURCHASE_LIMITS: Text = Text::new("Inga köpgränser", "No purchase limits");
+pub const PURCHASE_LIMIT_QUANTITY: Text = Text::new("Antal", "Quantity");
+pub const SET_PURCHASE_LIMIT: Text = Text::new("Sätt gräns", "Set limit");
+
+pub const SHIFTS: Text = Text::new("Pass", "Shifts");
+pub const SHIFTS_HINT: Text = Text::new(
+    "Ett pass är försäljningar av samma kassör med högst 90 minuters uppehåll",
+    "A shift is the sales of one cashier with at most 90 minutes in between",
+);
+pub const NO_SHIFTS: Text = Text::new("Inga pass", "No shifts");
+pub const SHIFT_TIME: Text = Text::new("Tid", "Time");
+pub const SHIFT_DURATION: Text = Text::new("Längd", "Duration");
+pub const SHIFT_TRANSACTIONS: Text = Text::new("Köp", "Sales");
+pub const TRANSACTIONS_PER_HOUR: Text = Text::new("Köp/timme", "Sales/hour");
+pub const AVERAGE_BASKET: Text = Text::new("Snittköp", "Average basket");
+pub const SHIFT_REVENUE: Text = Text::new("Omsättning", "Revenue");
+pub const SHIFTS_FETCH_FAILED: Text =
+    Text::new("Kunde inte hämta passen", "Could not fetch the shifts");
+pub const REVENUE_BY_PAYMENT_METHOD: Text =
+    Text::new("Omsättning per betalsätt", "Revenue by payment method");
+pub const REVENUE_BY_CATEGORY: Text = Text::new("Omsättning per kategori", "Revenue by category");
+pub const PAYMENT_CASH: Text = Text::new("Kontant", "Cash");
+pub const PAYMENT_CARD: Text = Text::new("Kort", "Card");
+pub const PAYMENT_TILLGODO: Text = Text::new("Tillgodo", "Tillgodo");
+pub const PAYMENT_OTHER: Text = Text::new("Annat", "Other");
+pub const NO_CATEGORY: Text = Text::new("Utan kategori", "No category");
+pub const REVENUE_FETCH_FAILED: Text = Text::new(
+    "Kunde inte hämta omsättningen",
+    "Could not fetch the revenue",
+);
+pub const TIME_TRAVEL: Text = Text::new("Historiskt läge", "Time travel");
+pub const AS_OF_HINT: Text = Text::new(
+    "Visa lagret och saldona som de var vid dagens slut",
+    "Show the stock and the balances as they were at the end of the day",
+);
+pub const SNAPSHOT_TITLE: Text = Text::new("Lager och saldon", "Stock and balances on");
+pub const SNAPSHOT_HINT: Text = Text::new(
+    "Räknat från transaktionerna som de var då, även de som tagits bort sedan",
+    "Computed from the transactions as they were then, also those deleted since",
+);
+pub const THEN: Text = Text::new("Då", "Then");
+pub const NOW: Text = Text::new("Nu", "Now");
+pub const SNAPSHOT_FETCH_FAILED: Text = Text::new(
+    "Kunde inte hämta det historiska läget",
+    "Could not fetch the stock and balances of the day",
+);
+
+pub const OPEN_REGISTER: Text = Text::new("Öppna kassan", "Open the register");
+pub const CLOSE_REGISTER: Text = Text::new("Stäng kassan", "Close the register");
+pub const REGISTER_OPENED_BY: Text = Text::new("Kassan öppnades av", "The register was opened by");
+pub const REGISTER_CLOSED: Text = Text::new("Kassan är stängd", "The register is closed");
+pub const REGISTER_OVERDUE: Text = Text::new(
+    "Kassan borde ha stängts. Räkna kassan och stäng den.",
+    "The register should have been closed. Count the cash and close it.",
+);
+pub const REGISTER_AUTO_CLOSED: Text = Text::new(
+    "Kassan stängdes automatiskt utan att räknas. Räkna kassan och stäm av.",
+    "The register was closed automatically without being counted. Count the cash and reconcile.",
+);
+pub const RECONCILE: Text = Text::new("Avstämd", "Reconciled");
+pub const REGISTER_UPDATE_FAILED: Text = Text::new(
+    "Kunde inte uppdatera kassan",
+    "Could not update the register",
+);
+
+pub const TRAINING_MODE_ACTIVE: Text = Text::new(
+    "TRÄNINGSLÄGE – försäljningar bokförs inte, tar inget från lagret och drar inget tillgodo.",
+    "TRAINING MODE – sales aren't booked, don't take anything from stock, and don't draw from \
+     balances.",
+);
+pub const LEAVE_TRAINING_MODE: Text = Text::new("Avsluta träningen", "Stop training");
+pub const TRAINING_MODE_FAILED: Text = Text::new(
+    "Kunde inte byta träningsläge",
+    "Could not switch training mode",
+);
+
+pub const APPROVAL_REQUIRED: Text = Text::new(
+    "En admin behöver godkänna detta",
+    "An admin has to approve this",
+);
+pub const APPROVAL_VOID: Text = Text::new(
+    "Makulering av en transaktion som inte är ny",
+    "Voiding a transaction which isn't recent",
+);
+pub const APPROVAL_DISCOUNT: Text = Text::new(
+    "Försäljning med stor rabatt",
+    "A sale with a large discount",
+);
+pub const APPROVAL_NEGATIVE_BALANCE: Text = Text::new(
+    "Försäljning som tar tillgodot under noll",
+    "A sale which takes the balance below zero",
+);
+pub const APPROVAL_PURCHASE_LIMIT: Text = Text::new(
+    "Försäljning av fler av en vara än medlemmen får köpa",
+    "A sale of more of an item than the member may buy",
+);
+pub const APPROVAL_HINT: Text = Text::new(
+    "Admin loggar in med sitt eget lösenord för att godkänna.",
+    "An admin enters their own password to approve.",
+);
+pub const APPROVE: Text = Text::new("Godkänn", "Approve");
+pub const APPROVED_BY: Text = Text::new("Godkänt av", "Approved by");
+pub const NOT_AN_ADMIN: Text = Text::new(
+    "Bara admins kan godkänna detta",
+    "Only admins can approve this",
+);
+
+pub const ADMIN_CORRECTIONS: Text = Text::new("Rättelser", "Corrections");
+pub const CORRECTIONS_HINT: Text = Text::new(
+    "Rättelser av saldon och lager görs direkt, utom stora rättelser som väntar tills en annan admin har godkänt dem.",
+    "Corrections of balances and of the stock are made at once, except large ones which wait until another admin has approved them.",
+);
+pub const CORRECTIONS_PENDING: Text = Text::new(
+    "Rättelser väntar på godkännande",
+    "Corrections wait for approval",
+);
+pub const REVIEW_CORRECTIONS: Text = Text::new("Granska", "Review");
+pub const NEW_CORRECTION: Text = Text::new("Ny rättelse", "New correction");
+pub const CORRECT_BALANCE: Text =
+    Text::new("Flytta pengar mellan konton", "Move money between accounts");
+pub const CORRECT_STOCK: Text = Text::new("Ändra lagersaldo", "Change the stock");
+pub const CHOOSE_ACCOUNT: Text = Text::new("Välj konto", "Choose an account");
+pub const CORRECTION_AMOUNT: Text = Text::new("Belopp (kr)", "Amount (kr)");
+pub const STOCK_CHANGE: Text = Text::new("Ändring, t.ex. -3", "Change, e.g. -3");
+pub const CORRECTION_REASON: Text = Text::new("Orsak", "Reason");
+pub const REQUEST_CORRECTION: Text = Text::new("Rätta", "Correct");
+pub const REJECT: Text = Text::new("Avslå", "Reject");
+pub const CORRECTION_PENDING: Text = Text::new("Väntar", "Pending");
+pub const CORRECTION_APPLIED: Text = Text::new("Genomförd", "Applied");
+pub const CORRECTION_REJECTED: Text = Text::new("Avslagen", "Rejected");
+
+pub const ADMIN_FAILED_PAYMENTS: Text =
+    Text::new("Misslyckade kortbetalningar", "Failed card payments");
+pub const FAILED_PAYMENTS_HINT: Text = Text::new(
+    "Kortbetalningar som misslyckades, eller som inte fick något svar från kortläsaren. Skicka dem \
+     till kortläsaren igen, eller skriv av dem om inget ska bokföras.",
+    "Card payments which failed, or which got no answer from the card reader. Send them to the \
+     card reader again, or write them off if nothing is to be booked.",
+);
+pub const NO_FAILED_PAYMENTS: Text = Text::new(
+    "Inga misslyckade kortbetalningar",
+    "No failed card payments",
+);
+pub const RETRY_PAYMENT: Text = Text::new("Försök igen", "Retry");
+pub const WRITE_OFF: Text = Text::new("Skriv av", "Write off");
+pub const FAILED_PAYMENT_UNRESOLVED: Text = Text::new("Ej åtgärdad", "Unresolved");
+pub const FAILED_PAYMENT_RETRIED: Text = Text::new("Skickad igen", "Retried");
+pub const FAILED_PAYMENT_WRITTEN_OFF: Text = Text::new("Avskriven", "Written off");
+pub const FAILED_PAYMENT_PAID_LATE: Text = Text::new("Betald sent", "Paid late");
+
+pub const ADMIN_DUPLICATES: Text = Text::new("Möjliga dubbletter", "Possible duplicates");
+pub const NO_DUPLICATES: Text = Text::new("Inga dubbletter hittades", "No duplicates found");
+pub const VOID: Text = Text::new("Makulera", "Void");
+
+pub const ADMIN_DORMANT: Text = Text::new("Vilande tillgodo", "Dormant balances");
+pub const DORMANT_HINT: Text = Text::new(
+    "Tillgodo som inte har rörts på länge. Markerade saldon får förverkas enligt policyn.",
+    "Balances which haven't been touched for a long time. Marked balances may be forfeited \
+     according to the policy.",
+);
+pub const NO_DORMANT_BALANCES: Text = Text::new("Inga vilande tillgodo", "No dormant balances");
+pub const MEMBER_NAME: Text = Text::new("Medlem", "Member");
+pub const LAST_ACTIVITY: Text = Text::new("Senast använt", "Last used");
+pub const REMINDED: Text = Text::new("Påmind", "Reminded");
+pub const DORMANT_TOTAL: Text = Text::new("Totalt vilande", "Total dormant");
+pub const FORFEITABLE_TOTAL: Text = Text::new("Totalt som får förverkas", "Total forfeitable");
+
+pub const ADMIN_RETENTION: Text = Text::new("Gallring", "Data retention");
+pub const RETENTION_HINT: Text = Text::new(
+    "Varorna i gamla transaktioner slås ihop till summor per dag när transaktionerna har \
+     exporterats, och gamla händelser i granskningsloggen tas bort, enligt inställningarna på \
+     servern.",
+    "The items of old transactions are merged into totals per day once the transactions have \
+     been exported, and old entries of the audit log are deleted, according to the settings of \
+     the server.",
+);
+pub const RETENTION_DRY_RUN: Text = Text::new(
+    "Provkörning: inget tas bort förrän det slås på på servern.",
+    "Dry run: nothing is removed until it's turned on on the server.",
+);
+pub const TRANSACTIONS_EXPORTED: Text =
+    Text::new("Transaktioner exporterade", "Transactions exported");
+pub const COMPACTABLE_TRANSACTIONS: Text =
+    Text::new("Transaktioner att slå ihop", "Transactions to merge");
+pub const PRUNABLE_AUDIT_ENTRIES: Text =
+    Text::new("Händelser att ta bort", "Audit log entries to delete");
+pub const ROWS: Text = Text::new("rader", "rows");
+pub const BEFORE: Text = Text::new("före", "before");
+
+pub const DISCOUNT: Text = Text::new("Paketrabatt", "Bundle discount");
+pub const ROUNDING: Text = Text::new("Öresavrundning", "Rounding");
+
+pub const INSUFFICIENT_STOCK: Text = Text::new("Slut i lager", "Out of stock");
+pub const STOCK_LEFT: Text = Text::new("kvar i lager", "left in stock");
+pub const BEST_BEFORE: Text = Text::new("Bäst före", "Best before");
+pub const EXPIRED: Text = Text::new("Utgånget", "Expired");
+
+pub const SUPPLIERS: Text = Text::new("Leverantörer", "Suppliers");
+pub const NEW_SUPPLIER: Text = Text::new("Ny leverantör", "New supplier");
+pub const SUPPLIER_NAME: Text = Text::new("Namn", "Name");
+pub const SUPPLIER_CONTACT: Text = Text::new("Kontaktuppgifter", "Contact");
+pub const ORDERING_URL: Text = Text::new("Beställ", "Order");
+pub const DELIVERY_DAYS: Text = Text::new("Leveransdagar", "Delivery days");
+pub const EDIT: Text = Text::new("Redigera", "Edit");
+pub const WEEKDAYS_SHORT: [Text; 7] = [
+    Text::new("mån", "Mon"),
+    Text::new("tis", "Tue"),
+    Text::new("ons", "Wed"),
+    Text::new("tor", "Thu"),
+    Text::new("fre", "Fri"),
+    Text::new("lör", "Sat"),
+    Text::new("sön", "Sun"),
+];
 
-pub const DEPOSIT: &str = "Sätt in";
-pub const CHOOSE_TILLGODO_ACC: &str = "Välj Tillgodokonto";
+pub const STORAGE: Text = Text::new("Lager", "Storage");
+pub const STORAGE_LOCATIONS: Text = Text::new("Lagerplatser", "Storage locations");
+pub const STORAGE_LOCATION_NAME: Text = Text::new("T.ex. Förråd", "E.g. Storeroom");
+pub const ADD_STORAGE_LOCATION: Text = Text::new("Lägg till lagerplats", "Add storage location");
+pub const FOR_SALE: Text = Text::new("Till salu", "For sale");
+pub const LEFT_FOR_SALE: Text = Text::new("kvar till salu", "left for sale");
+pub const LOW_STOCK: Text = Text::new("Snart slut", "Running low");
+pub const LOW_STOCK_AT: Text = Text::new("Varna vid", "Warn at");
+pub const LOW_STOCK_AT_HINT: Text = Text::new(
+    "Varna när så här få eller färre finns till salu",
+    "Warn when this few or fewer are for sale",
+);
+pub const NONE_STORED: Text = Text::new("Inga fler i lager", "No more in storage");
+pub const STORED_IN: Text = Text::new("i", "in");
+pub const MOVE_ITEMS: Text = Text::new("Flytta varor", "Move items");
+pub const CHOOSE_ITEM: Text = Text::new("Välj vara", "Choose an item");
+pub const MOVE_FROM: Text = Text::new("Från", "From");
+pub const MOVE_TO: Text = Text::new("Till", "To");
+pub const MOVE: Text = Text::new("Flytta", "Move");
 
-pub const IZETTLE: &str = "iZettle";
-pub const OTHER_EPAY: &str = "Swish";
+pub const ITEMS_SOLD_TODAY: Text = Text::new("sålda idag", "sold today");
 
-pub const FIRST_NAME: &str = "Förnamn";
-pub const LAST_NAME: &str = "Efternamn";
-pub const NICKNAME: &str = "Smeknamn";
-pub const ACCOUNT_NAME: &str = "Kontonamn";
+pub const TITLE_QUEUE: Text = Text::new("Kö", "Queue");
+pub const QUEUE_NUMBER: Text = Text::new("Könummer", "Queue number");
+pub const QUEUE_PREPARING: Text = Text::new("Tillagas", "Preparing");
+pub const QUEUE_READY: Text = Text::new("Klart att hämta", "Ready for pickup");
+pub const QUEUE_EMPTY: Text = Text::new("Inga beställningar", "No orders");
+pub const QUEUE_DISPLAY: Text = Text::new("Öppna kundskärm", "Open customer display");
+pub const QUEUE_HINT: Text = Text::new(
+    "Tryck på ett nummer när beställningen är klar, och igen när den har hämtats",
+    "Tap a number when the order is ready, and again once it has been picked up",
+);
+pub const QUEUE_UPDATE_FAILED: Text =
+    Text::new("Kunde inte uppdatera kön", "Could not update the queue");
 
-pub const WAITING_FOR_PAYMENT: &str = "Väntar på betalning...";
+pub const TITLE_KITCHEN: Text = Text::new("Kök", "Kitchen");
+pub const KITCHEN_DONE: Text = Text::new("Klar", "Done");
+pub const KITCHEN_EMPTY: Text = Text::new("Inget att tillaga", "Nothing to prepare");
+pub const KITCHEN_UPDATE_FAILED: Text = Text::new(
+    "Kunde inte markera beställningen som klar",
+    "Could not mark the order as done",
+);
 
-pub const INVALID_MONEY_MESSAGE_SHORT: &str = "Ogiltig summa";
-pub const INVALID_MONEY_MESSAGE_LONG: &str = "Måste vara giltig summa (e.g. 42 eller 123.45)";
+pub const SALES_TODAY: Text = Text::new("Försäljning idag", "Sales today");
+pub const DASHBOARD_SALES: Text = Text::new("köp", "sales");
+pub const SALES_PER_HOUR: Text = Text::new("Försäljning per timme", "Sales per hour");
+pub const NO_SALES_TODAY: Text = Text::new("Inget sålt än idag", "Nothing sold yet today");
+pub const DASHBOARD_REGISTER: Text = Text::new("Kassan", "The register");
+pub const NOTHING_RUNNING_LOW: Text =
+    Text::new("Inget håller på att ta slut", "Nothing is running low");
 
-pub const DEPOSIT_COMPLETE: &str = "Insättning slutförd";
-pub const PURCHASE_COMPLETE: &str = "Köp slutfört";
+pub const TITLE_BALANCE: Text = Text::new("Saldo", "Balance");
+pub const BALANCE_LEFT: Text = Text::new("kvar att handla för", "left to spend");
+pub const LATEST_DEPOSITS: Text = Text::new("Senaste insättningar", "Latest deposits");
+pub const UNKNOWN_BALANCE_CARD: Text = Text::new(
+    "Kortet känns inte igen, det kan ha ersatts av ett nytt",
+    "The card isn't recognized, it may have been replaced by a new one",
+);
+pub const SWISH_AMOUNT: Text = Text::new("Belopp (kr)", "Amount (kr)");
+pub const SWISH_DEPOSIT: Text = Text::new("Sätt in med Swish", "Deposit with Swish");
+pub const SWISH_WAITING: Text = Text::new(
+    "Väntar på att betalningen godkänns i Swish…",
+    "Waiting for the payment to be approved in Swish…",
+);
+pub const OPEN_SWISH: Text = Text::new("Öppna Swish", "Open Swish");
+pub const SWISH_PAID: Text = Text::new("Insättningen är klar!", "The deposit is done!");
+pub const SWISH_NOT_PAID: Text = Text::new(
+    "Betalningen genomfördes inte",
+    "The payment didn't go through",
+);
+pub const SWISH_FAILED: Text = Text::new(
+    "Kunde inte starta betalningen, försök igen",
+    "Could not start the payment, try again",
+);
 
-pub const SERVER_ERROR: &str = "Serverfel";
-pub const PAYMENT_FAILED: &str = "Betalning misslyckades";
-pub const PAYMENT_CANCELLED: &str = "Betalning avbruten";
-pub const NO_PENDING_TRANSACTION: &str = "Ingen pågående transaktion";
-pub const POSTING_TRANSACTION_FAILED: &str = "Misslyckades med att skicka transaktion";
-pub const POLLING_TRANSACTION_FAILED: &str = "Misslyckades med att polla transaktion";
+pub const ACHIEVEMENT_FIRST_SALE: Text = Text::new("Först i kön!", "First in line!");
+pub const ACHIEVEMENT_FIRST_SALE_BODY: Text = Text::new(
+    "Du gjorde dagens första köp",
+    "You made the first sale of the day",
+);
+pub const ACHIEVEMENT_COFFEE: Text = Text::new("Koffeinkung", "Caffeine royalty");
+pub const ACHIEVEMENT_COFFEE_BODY: Text = Text::new(
+    "Du har sålt 100 koppar kaffe",
+    "You have sold 100 cups of coffee",
+);
+pub const ACHIEVEMENT_BALANCED: Text = Text::new("Pingvinprecision", "Penguin precision");
+pub const ACHIEVEMENT_BALANCED_BODY: Text = Text::new(
+    "Kassan stämde på öret",
+    "The cash register matched to the öre",
+);
 
-pub const TRANSACTION_TOTAL: &str = "Totalt:";
+pub const FIELD_REQUIRED: Text = Text::new("Måste fyllas i", "Required");
+pub const FIELD_TOO_LONG: Text =
+    Text::new("För långt, max antal tecken:", "Too long, max characters:");
+pub const FIELD_OUT_OF_RANGE: Text = Text::new("Måste vara mellan", "Must be between");
+pub const FIELD_NOT_A_URL: Text = Text::new(
+    "Måste vara en länk som börjar med http:// eller https://",
+    "Must be a link starting with http:// or https://",
+);
+pub const FIELD_NOT_AN_EMAIL: Text =
+    Text::new("Måste vara en e-postadress", "Must be an email address");
@@ -1,12 +1,21 @@
+pub mod backup;
+pub mod base;
+pub mod breadcrumbs;
+pub mod charts;
 pub mod export;
+pub mod fetch;
+pub mod resource_cache;
 
 use crate::fuzzy_search::{FuzzyCharMatch, FuzzyScore};
 use seed::browser::dom::event_handler::ev;
 use seed::dom_entity_names::Ev;
 use seed::virtual_dom::event_handler_manager::event_handler::EventHandler;
 use semver::Version;
+use strecklistan_api::book_account::BookAccount;
+use strecklistan_api::transaction::NewTransaction;
 
 pub const DATE_INPUT_FMT: &'static str = "%Y-%m-%d";
+pub const DATETIME_INPUT_FMT: &'static str = "%Y-%m-%dT%H:%M";
 //pub const TIME_INPUT_FMT: &'static str = "%H:%M";
 
 /// Check if client version supports api version
@@ -55,6 +64,38 @@ pub fn compare_semver(client_version: Version, api_version: Version) -> bool {
     }
 }
 
+/// Check if a search string looks like it came from a barcode scanner (EAN-8 to EAN-14)
+pub fn is_barcode(s: &str) -> bool {
+    (8..=14).contains(&s.len()) && s.chars().all(|c| c.is_ascii_digit())
+}
+
+/// A random key for making a POST request safe to repeat, see `NewTransaction::idempotency_key`
+pub fn new_idempotency_key() -> String {
+    format!(
+        "{:x}-{:08x}",
+        js_sys::Date::now() as u64,
+        (js_sys::Math::random() * f64::from(u32::MAX)) as u32,
+    )
+}
+
+/// `account` as it will be once `transactions` have been saved, for showing transactions which
+/// are still being sent as already made
+pub fn with_pending<'a>(
+    account: &BookAccount,
+    transactions: impl IntoIterator<Item = &'a NewTransaction>,
+) -> BookAccount {
+    let mut account = account.clone();
+    for transaction in transactions {
+        if transaction.debited_account == account.id {
+            account.debit(transaction.amount);
+        }
+        if transaction.credited_account == account.id {
+            account.credit(transaction.amount);
+        }
+    }
+    account
+}
+
 pub fn simple_ev<Ms: Clone + 'static>(trigger: impl Into<Ev>, message: Ms) -> EventHandler<Ms> {
     ev(trigger, move |_| message)
 }
@@ -171,8 +212,56 @@ where
 
 #[cfg(test)]
 mod test {
-    use super::CompareToStr;
+    use super::{compare_fuzzy, is_barcode, with_pending, CompareToStr};
     use std::cmp::Ordering;
+    use strecklistan_api::book_account::{BookAccount, BookAccountType};
+    use strecklistan_api::transaction::NewTransaction;
+
+    #[test]
+    fn test_search_helpers() {
+        assert!(is_barcode("7310070005509"));
+        assert!(is_barcode("12345678"));
+        assert!(!is_barcode("1234"));
+        assert!(!is_barcode("73100700055a9"));
+
+        let search = "cc";
+        assert!(compare_fuzzy("Coca-Cola".chars(), search.chars()).is_full_match(search));
+        assert!(!compare_fuzzy("Fanta".chars(), search.chars()).is_full_match(search));
+    }
+
+    #[test]
+    fn test_with_pending() {
+        let tillgodo = BookAccount {
+            id: 1,
+            name: "Tillgodo/Tux".to_string(),
+            account_type: BookAccountType::Liabilities,
+            creditor: Some(1),
+            balance: 1000.into(),
+        };
+        let transaction = |debited_account, credited_account, amount: i32| NewTransaction {
+            description: None,
+            bundles: vec![],
+            debited_account,
+            credited_account,
+            amount: amount.into(),
+            note: None,
+            tags: vec![],
+            event_code: None,
+            age_verified: false,
+            member_id: None,
+            idempotency_key: None,
+        };
+
+        // a deposit, a purchase, and a purchase by someone else
+        let pending = vec![
+            transaction(2, 1, 500),
+            transaction(1, 3, 300),
+            transaction(4, 3, 200),
+        ];
+        assert_eq!(with_pending(&tillgodo, &pending).balance, 1200.into());
+        assert_eq!(with_pending(&tillgodo, &[]).balance, 1000.into());
+    }
+
     #[test]
     fn test_str_cmp() {
         assert_eq!(1.cmp_to_str("1"), Ordering::Equal);
@@ -0,0 +1,39 @@
+//! Backups of what is being entered on the pages, kept in the session storage of the tab.
+//!
+//! The cart and the deposit form are written here as they change, and read back when their page
+//! is created, so that they survive a reload of the tab, whether it's by accident or because the
+//! app was upgraded. Unlike local storage, the session storage isn't shared with other tabs, and
+//! it's dropped when the tab is closed.
+
+use seed::prelude::*;
+use seed::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The cart of the store page
+pub const CART: &str = "backup_cart";
+
+/// The deposit form
+pub const DEPOSIT: &str = "backup_deposit";
+
+/// Back up `value` under `key`, or drop the backup if there is nothing to keep
+pub fn save<T: Serialize>(key: &str, value: Option<T>) {
+    let result = match value {
+        Some(value) => SessionStorage::insert(key, &value),
+        None => SessionStorage::remove(key),
+    };
+    if let Err(e) = result {
+        error!("Failed to back up", key, e);
+    }
+}
+
+/// The backup under `key`, if there is one which this version of the app can read
+pub fn load<T: DeserializeOwned>(key: &str) -> Option<T> {
+    SessionStorage::get(key).ok()
+}
+
+/// Drop every backup, so that it isn't restored for the next user
+pub fn clear() {
+    for key in &[CART, DEPOSIT] {
+        SessionStorage::remove(key).ok();
+    }
+}
@@ -0,0 +1,157 @@
+//! Where the app is served, and where it finds the API.
+//!
+//! A reverse proxy may serve the site under a path, e.g. `https://example.com/strecklistan/`,
+//! which the server then writes into the `base-path` meta tag of the page (see `proxy.base_path`
+//! in the config of the backend). The links to the pages and the calls to the API go through it.
+//!
+//! During development the frontend may instead be served on its own, and talk to a backend on
+//! another origin which is given when it's built:
+//!
+//! ```sh
+//! STRECKLISTAN_API_URL=http://localhost:8000 cargo make build
+//! ```
+//!
+//! The backend then has to allow the origin of the frontend in `cors.allowed_origins`.
+//!
+//! The rest of the app uses the paths of a site at the root, e.g. `/api/items` or `/store`.
+//! Requests for those are moved to the API by the [resource cache](crate::util::resource_cache),
+//! which every request passes through, see [relocate].
+
+use seed::prelude::*;
+use seed::*;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{EventSource, EventSourceInit, Request, RequestCredentials, RequestInit};
+
+struct Base {
+    /// E.g. `/strecklistan`, empty if the site is served at the root
+    path: String,
+
+    /// What the paths of the API are relative to, e.g. `https://example.com/strecklistan`
+    api: String,
+}
+
+thread_local! {
+    static BASE: Base = Base::load();
+}
+
+impl Base {
+    fn load() -> Self {
+        let path = document()
+            .query_selector(r#"meta[name="base-path"]"#)
+            .ok()
+            .flatten()
+            .and_then(|meta| meta.get_attribute("content"))
+            .unwrap_or_default();
+        let api = match option_env!("STRECKLISTAN_API_URL") {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!(
+                "{}{}",
+                window().location().origin().unwrap_or_default(),
+                path
+            ),
+        };
+        Base { path, api }
+    }
+}
+
+/// The link to the page at `path`, e.g. `/store`
+pub fn page_href(path: &str) -> String {
+    BASE.with(|base| format!("{}{}", base.path, path))
+}
+
+/// The url of the page whose path starts with `part`, e.g. `store`. An empty `part` is the root.
+pub fn page_url(part: &str) -> Url {
+    let parts: Vec<String> = BASE.with(|base| {
+        base.path
+            .split('/')
+            .chain(Some(part))
+            .filter(|part| !part.is_empty())
+            .map(String::from)
+            .collect()
+    });
+    Url::new().set_path(parts)
+}
+
+/// Load the stylesheet `static/{name}`, unless it's already loaded. The stylesheets of pages
+/// which the register doesn't need, such as those of the charts, are left out of `index.html` to
+/// make the first load faster.
+pub fn require_stylesheet(name: &str) {
+    let href = page_href(&format!("/static/{}", name));
+    let document = document();
+    let selector = format!(r#"link[rel="stylesheet"][href="{}"]"#, href);
+    if let Ok(Some(_)) = document.query_selector(&selector) {
+        return;
+    }
+
+    let head = document.query_selector("head").ok().flatten();
+    let link = document.create_element("link").ok();
+    if let (Some(head), Some(link)) = (head, link) {
+        let _ = link.set_attribute("rel", "stylesheet");
+        let _ = link.set_attribute("href", &href);
+        let _ = head.append_child(&link);
+    }
+}
+
+/// Skip the parts of the path of `url` which the site is served under, leaving those of the page
+pub fn skip_base_path(mut url: Url) -> Url {
+    let parts = BASE.with(|base| base.path.split('/').filter(|part| !part.is_empty()).count());
+    for _ in 0..parts {
+        url.next_path_part();
+    }
+    url
+}
+
+/// The url of the API resource at `path`, e.g. `/api/items`
+pub fn api_url(path: &str) -> String {
+    BASE.with(|base| format!("{}{}", base.api, path))
+}
+
+/// The path of the API resource at `url`, e.g. `/api/items`, if it is one
+pub fn api_path(url: &str) -> Option<&str> {
+    let path = BASE.with(|base| url.strip_prefix(base.api.as_str()))?;
+    if path.starts_with("/api/") {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Where a request for `url` should go instead, if it's for the API at the root of this origin
+/// but the API is elsewhere
+pub fn relocated(url: &str) -> Option<String> {
+    let origin = window().location().origin().ok()?;
+    let path = url.strip_prefix(&origin)?;
+    if !path.starts_with("/api/") {
+        return None;
+    }
+    let relocated = api_url(path);
+    if relocated == url {
+        None
+    } else {
+        Some(relocated)
+    }
+}
+
+/// A copy of `request` which goes to `url`, see [relocated]
+pub async fn relocate(request: Request, url: String) -> Result<Request, JsValue> {
+    let mut init = RequestInit::new();
+    init.method(&request.method())
+        .headers(&request.headers())
+        .signal(Some(&request.signal()))
+        // the session cookie has to go along if the API is on another origin
+        .credentials(RequestCredentials::Include);
+
+    let method = request.method();
+    if method != "GET" && method != "HEAD" {
+        let body = JsFuture::from(request.array_buffer()?).await?;
+        init.body(Some(&body));
+    }
+    Request::new_with_str_and_init(&url, &init)
+}
+
+/// Listen to the events of the API at `path`, e.g. `/api/live`
+pub fn event_source(path: &str) -> Result<EventSource, JsValue> {
+    let mut init = EventSourceInit::new();
+    init.with_credentials(true);
+    EventSource::new_with_event_source_init_dict(&api_url(path), &init)
+}
@@ -0,0 +1,139 @@
+//! What the app did shortly before it broke.
+//!
+//! The latest messages and requests are kept as [Breadcrumb]s, and sent to the server together
+//! with the state of the app when the frontend panics, see [report]. The server writes them to
+//! its log, so that "the page just broke" can be reproduced.
+
+use crate::util::base;
+use chrono::Utc;
+use seed::prelude::*;
+use seed::*;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{self, Debug, Write};
+use strecklistan_api::client_error::{
+    Breadcrumb, BreadcrumbKind, ClientErrorReport, MAX_BREADCRUMBS,
+};
+
+/// Longer breadcrumbs are cut off, e.g. messages carrying whole resources
+const MAX_TEXT_LENGTH: usize = 200;
+
+/// How many errors are reported at most until the page is reloaded, in case they repeat
+const MAX_REPORTS: u32 = 5;
+
+const REPORT_PATH: &str = "/api/client_error";
+
+thread_local! {
+    static BREADCRUMBS: RefCell<VecDeque<Breadcrumb>> =
+        RefCell::new(VecDeque::with_capacity(MAX_BREADCRUMBS));
+
+    /// What the app looks like, see [set_state]
+    static STATE: RefCell<BTreeMap<String, String>> = RefCell::new(BTreeMap::new());
+
+    static REPORTS: Cell<u32> = Cell::new(0);
+}
+
+/// A string which stops growing at [MAX_TEXT_LENGTH], and fails the formatting then so that the
+/// rest isn't formatted for nothing
+struct Truncated(String);
+
+impl Write for Truncated {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            if self.0.len() >= MAX_TEXT_LENGTH {
+                self.0.push('…');
+                return Err(fmt::Error);
+            }
+            self.0.push(c);
+        }
+        Ok(())
+    }
+}
+
+/// Remember that something happened, forgetting the oldest breadcrumb if there are too many
+pub fn record(kind: BreadcrumbKind, text: String) {
+    let mut truncated = Truncated(String::new());
+    let _ = truncated.write_str(&text);
+    push(kind, truncated.0);
+}
+
+/// Remember a message which the app handled
+pub fn record_msg(msg: &impl Debug) {
+    let mut text = Truncated(String::new());
+    let _ = write!(text, "{:?}", msg);
+    push(BreadcrumbKind::Msg, text.0);
+}
+
+fn push(kind: BreadcrumbKind, text: String) {
+    BREADCRUMBS.with(|breadcrumbs| {
+        let mut breadcrumbs = breadcrumbs.borrow_mut();
+        if breadcrumbs.len() >= MAX_BREADCRUMBS {
+            breadcrumbs.pop_front();
+        }
+        breadcrumbs.push_back(Breadcrumb {
+            time: Utc::now(),
+            kind,
+            text,
+        });
+    });
+}
+
+/// Describe a part of the state of the app in the error reports, e.g. which page is open
+pub fn set_state(key: &str, value: impl ToString) {
+    STATE.with(|state| {
+        state
+            .borrow_mut()
+            .insert(key.to_string(), value.to_string())
+    });
+}
+
+/// Send an error to the server, together with the breadcrumbs and the state of the app.
+///
+/// The report is sent as a beacon, which the browser sends even if the app is about to die, e.g.
+/// since it panicked.
+pub fn report(message: String) {
+    let reports = REPORTS.with(|reports| reports.replace(reports.get() + 1));
+    if reports >= MAX_REPORTS {
+        return;
+    }
+
+    let report = ClientErrorReport {
+        message,
+        url: window().location().href().unwrap_or_default(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        state: STATE.with(|state| state.borrow().clone()),
+        breadcrumbs: BREADCRUMBS.with(|breadcrumbs| breadcrumbs.borrow().iter().cloned().collect()),
+    };
+    let body = match serde_json::to_string(&report) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+    if let Err(e) = window()
+        .navigator()
+        .send_beacon_with_opt_str(&base::api_url(REPORT_PATH), Some(&body))
+    {
+        error!("Failed to report the error", e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        for i in 0..MAX_BREADCRUMBS + 10 {
+            record(BreadcrumbKind::Fetch, format!("GET /api/{}", i));
+        }
+        record_msg(&"x".repeat(1000));
+
+        BREADCRUMBS.with(|breadcrumbs| {
+            let breadcrumbs = breadcrumbs.borrow();
+            assert_eq!(breadcrumbs.len(), MAX_BREADCRUMBS);
+            assert_eq!(breadcrumbs[0].text, "GET /api/11");
+            let last = breadcrumbs.back().unwrap();
+            assert_eq!(last.kind, BreadcrumbKind::Msg);
+            assert_eq!(last.text.chars().count(), MAX_TEXT_LENGTH + 1);
+        });
+    }
+}
@@ -0,0 +1,114 @@
+//! Loads the charts module, which draws the [charts](crate::views::chart).
+//!
+//! The charts are built from `frontend/charts` as a wasm module of their own, which is left out of
+//! the main bundle since the register never shows a chart. It's loaded together with `charts.css`
+//! when a page with charts is opened, or when the first chart is viewed. Until then the charts are
+//! left empty, and the app is told to draw them again once the module is loaded.
+
+use crate::util::base;
+use js_sys::{Function, Promise, Reflect};
+use seed::*;
+use std::cell::RefCell;
+use strecklistan_charts::ChartSpec;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+#[wasm_bindgen(inline_js = "export function import_module(url) { return import(url); }")]
+extern "C" {
+    fn import_module(url: &str) -> Promise;
+}
+
+enum Module {
+    NotLoaded,
+    Loading,
+
+    /// The `render_chart` function of the module
+    Loaded(Function),
+
+    /// Loading is tried again by [load], i.e. the next time a page with charts is opened
+    Failed,
+}
+
+thread_local! {
+    static MODULE: RefCell<Module> = RefCell::new(Module::NotLoaded);
+
+    static LOADED: RefCell<Option<Box<dyn Fn()>>> = RefCell::new(None);
+}
+
+/// `loaded` is called once the module is loaded, so that the charts can be drawn
+pub fn install(loaded: impl Fn() + 'static) {
+    LOADED.with(|callback| *callback.borrow_mut() = Some(Box::new(loaded)));
+}
+
+/// Start loading the module and its stylesheet, unless they're already loaded
+pub fn load() {
+    base::require_stylesheet("charts.css");
+
+    let start = MODULE.with(|module| {
+        let mut module = module.borrow_mut();
+        match *module {
+            Module::NotLoaded | Module::Failed => {
+                *module = Module::Loading;
+                true
+            }
+            Module::Loading | Module::Loaded(_) => false,
+        }
+    });
+    if !start {
+        return;
+    }
+
+    spawn_local(async {
+        let loaded = match import().await {
+            Ok(render) => Module::Loaded(render),
+            Err(e) => {
+                error!("Failed to load the charts", e);
+                Module::Failed
+            }
+        };
+        MODULE.with(|module| *module.borrow_mut() = loaded);
+        LOADED.with(|callback| {
+            if let Some(callback) = &*callback.borrow() {
+                callback();
+            }
+        });
+    });
+}
+
+/// The chart of `spec` as HTML, or `None` if the module isn't loaded yet
+pub fn render(spec: &ChartSpec) -> Option<String> {
+    let render = MODULE.with(|module| match &*module.borrow() {
+        Module::Loaded(render) => Some(render.clone()),
+        _ => None,
+    });
+    let render = match render {
+        Some(render) => render,
+        None => {
+            let not_loaded = MODULE.with(|module| matches!(*module.borrow(), Module::NotLoaded));
+            if not_loaded {
+                load();
+            }
+            return None;
+        }
+    };
+
+    let spec = serde_json::to_string(spec).ok()?;
+    match render.call1(&JsValue::NULL, &spec.into()) {
+        Ok(html) => html.as_string(),
+        Err(e) => {
+            error!("Failed to draw a chart", e);
+            None
+        }
+    }
+}
+
+/// Import the module, wait for its wasm to be instantiated, and get its `render_chart`
+async fn import() -> Result<Function, JsValue> {
+    let module = JsFuture::from(import_module(&base::page_href("/pkg/charts/charts.js"))).await?;
+    let init: Function = Reflect::get(&module, &"default".into())?.dyn_into()?;
+    let wasm = base::page_href("/pkg/charts/charts_bg.wasm");
+    let instantiated: Promise = init.call1(&JsValue::NULL, &wasm.into())?.dyn_into()?;
+    JsFuture::from(instantiated).await?;
+    Reflect::get(&module, &"render_chart".into())?.dyn_into()
+}
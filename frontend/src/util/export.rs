@@ -9,6 +9,7 @@ use strecklistan_api::{
     book_account::BookAccountId,
     currency::Currency,
     inventory::InventoryItemId,
+    time::Tz,
     transaction::{Transaction, TransactionId},
 };
 use wasm_bindgen::JsCast;
@@ -31,9 +32,11 @@ pub enum CSVStyleTransaction {
     //PerTransaction,
 }
 
+/// Serialize a list of transactions as CSV, with dates and times in the timezone `tz`
 pub fn make_csv_transaction_list(
     transactions: &[Transaction],
     style: CSVStyleTransaction,
+    tz: Tz,
 ) -> String {
     let mut data: Vec<u8> = vec![];
     let mut writer = csv_writer(&mut data);
@@ -60,11 +63,12 @@ pub fn make_csv_transaction_list(
             }
 
             for transaction in transactions {
+                let local_time = transaction.time.with_timezone(&tz);
                 let tr_record = Record {
                     transaction_id: transaction.id,
                     description: transaction.description.as_ref(),
-                    date: transaction.time.naive_utc().date(),
-                    time: transaction.time.time(),
+                    date: local_time.naive_local().date(),
+                    time: local_time.time(),
                     debited_account: transaction.debited_account,
                     credited_account: transaction.credited_account,
                     amount: transaction.amount,
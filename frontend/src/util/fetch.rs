@@ -0,0 +1,223 @@
+use chrono::{NaiveDate, Utc};
+use seed::app::cmds::timeout;
+use seed::prelude::*;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::cell::RefCell;
+use strecklistan_api::approval::{Approval, GatedAction, APPROVAL_HEADER};
+use strecklistan_api::error::{ApiError, ErrorCode};
+use strecklistan_api::inventory::StockShortage;
+use strecklistan_api::organization::Organization;
+use strecklistan_api::validation::FieldError;
+
+/// Max number of attempts for requests which fail because of network or server errors
+pub const MAX_FETCH_ATTEMPTS: u32 = 5;
+
+const RETRY_BASE_DELAY_MS: u32 = 500;
+const RETRY_MAX_DELAY_MS: u32 = 16000;
+
+thread_local! {
+    /// The approvals which admins have given on this register, until they expire
+    static APPROVALS: RefCell<Vec<Approval>> = RefCell::new(vec![]);
+}
+
+/// Remember an approval, so that it's sent with the requests for gated actions until it expires
+pub fn add_approval(approval: Approval) {
+    APPROVALS.with(|approvals| approvals.borrow_mut().push(approval));
+}
+
+/// Add the tokens of the approvals which haven't expired to `request`, see [add_approval]
+pub fn with_approvals(request: Request<'_>) -> Request<'_> {
+    let tokens = APPROVALS.with(|approvals| {
+        let mut approvals = approvals.borrow_mut();
+        let now = Utc::now();
+        approvals.retain(|approval| approval.expires > now);
+        approvals
+            .iter()
+            .map(|approval| approval.token.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    });
+    if tokens.is_empty() {
+        request
+    } else {
+        request.header(Header::custom(APPROVAL_HEADER, tokens))
+    }
+}
+
+/// The inner error of requests for actions which an admin may have to approve
+#[derive(Clone, Debug)]
+pub enum Gated<D> {
+    /// The request was rejected, with the details of the error
+    Rejected(D),
+
+    /// An admin has to approve the action before it's sent again, see [add_approval]
+    ApprovalRequired(GatedAction),
+}
+
+/// The delay before retry number `attempt` (counting from 1), doubling every time
+pub fn retry_delay_ms(attempt: u32) -> u32 {
+    let doublings = attempt.saturating_sub(1).min(16);
+    RETRY_BASE_DELAY_MS
+        .saturating_mul(1 << doublings)
+        .min(RETRY_MAX_DELAY_MS)
+}
+
+/// Whether a request which failed with `error` is worth retrying
+pub fn is_transient(error: &FetchError) -> bool {
+    match error {
+        FetchError::NetworkError(_) => true,
+        FetchError::StatusError(status) => status.code >= 500,
+        _ => false,
+    }
+}
+
+/// GET `url`, retrying with backoff on network and server errors.
+///
+/// Errors which won't go away by retrying, like 4xx responses, are returned immediately.
+pub async fn get_with_retry(url: String) -> Result<Response, FetchError> {
+    send_with_retry(Request::new(url)).await?.check_status()
+}
+
+/// Send `request`, retrying with backoff on network errors and 5xx responses.
+///
+/// Only for requests which are safe to send more than once: GET, PUT and DELETE, and POSTs
+/// carrying an idempotency key. Other responses, including errors, are returned as is so that
+/// their body can be read, and so is the last one if every attempt fails.
+pub async fn send_with_retry(request: Request<'_>) -> Result<Response, FetchError> {
+    let mut attempt = 1;
+    loop {
+        let result = request.clone().fetch().await;
+        let transient = match &result {
+            Ok(response) => response.status().code >= 500,
+            Err(e) => is_transient(e),
+        };
+        if !transient || attempt >= MAX_FETCH_ATTEMPTS {
+            return result;
+        }
+        timeout(retry_delay_ms(attempt), || ()).await;
+        attempt += 1;
+    }
+}
+
+/// Whether the browser thinks it's connected to a network
+pub fn is_online() -> bool {
+    window().navigator().on_line()
+}
+
+/// Read a response, with the body of error responses as the inner error.
+///
+/// Error responses without a body from the API, e.g. from a proxy, are returned as a
+/// [FetchError::StatusError].
+pub async fn api_response<T: DeserializeOwned + 'static>(
+    response: Response,
+) -> Result<Result<T, ApiError<Value>>, FetchError> {
+    let status = response.status();
+    if status.is_ok() {
+        return response.json().await.map(Ok);
+    }
+    match response.json().await {
+        Ok(error) => Ok(Err(error)),
+        Err(_) => Err(FetchError::StatusError(status)),
+    }
+}
+
+/// The details of an error response, if it has details of type `D`
+pub fn error_details<D: DeserializeOwned>(error: ApiError<Value>) -> Option<D> {
+    serde_json::from_value(error.details?).ok()
+}
+
+/// Read a response where errors with `code` have details which are returned as the inner error
+async fn detailed_response<T: DeserializeOwned + 'static, D: DeserializeOwned>(
+    response: Response,
+    code: ErrorCode,
+) -> Result<Result<T, D>, FetchError> {
+    let status = response.status();
+    match api_response(response).await? {
+        Ok(value) => Ok(Ok(value)),
+        Err(error) if error.code == code => error_details(error)
+            .map(Err)
+            .ok_or(FetchError::StatusError(status)),
+        Err(_) => Err(FetchError::StatusError(status)),
+    }
+}
+
+/// Like [detailed_response], for actions which an admin may have to approve
+async fn gated_response<T: DeserializeOwned + 'static, D: DeserializeOwned>(
+    response: Response,
+    code: ErrorCode,
+) -> Result<Result<T, Gated<D>>, FetchError> {
+    let status = response.status();
+    match api_response(response).await? {
+        Ok(value) => Ok(Ok(value)),
+        Err(error) if error.code == code => error_details(error)
+            .map(|details| Err(Gated::Rejected(details)))
+            .ok_or(FetchError::StatusError(status)),
+        Err(error) if error.code == ErrorCode::ApprovalRequired => error_details(error)
+            .map(|action| Err(Gated::ApprovalRequired(action)))
+            .ok_or(FetchError::StatusError(status)),
+        Err(_) => Err(FetchError::StatusError(status)),
+    }
+}
+
+/// Read the response of posting a sale.
+///
+/// A sale which would take items below zero in stock is rejected, in which case the items are
+/// returned as the inner error. So is what has to be approved by an admin, if anything.
+pub async fn sale_response<T: DeserializeOwned + 'static>(
+    response: Response,
+) -> Result<Result<T, Gated<Vec<StockShortage>>>, FetchError> {
+    gated_response(response, ErrorCode::StockEmpty).await
+}
+
+/// Read the response of logging in.
+///
+/// A user in several organizations who didn't pick one has to choose, in which case their
+/// organizations are returned as the inner error.
+pub async fn login_response<T: DeserializeOwned + 'static>(
+    response: Response,
+) -> Result<Result<T, Vec<Organization>>, FetchError> {
+    detailed_response(response, ErrorCode::ChooseOrganization).await
+}
+
+/// Read the response of submitting a form.
+///
+/// A request with invalid fields is rejected, in which case the rejected fields are returned as
+/// the inner error.
+pub async fn form_response<T: DeserializeOwned + 'static>(
+    response: Response,
+) -> Result<Result<T, Vec<FieldError>>, FetchError> {
+    detailed_response(response, ErrorCode::InvalidFields).await
+}
+
+/// Read the response of voiding or editing a transaction.
+///
+/// Transactions before the lock date of the organization can't be changed, in which case the
+/// lock date is returned as the inner error.
+pub async fn unlocked_response<T: DeserializeOwned + 'static>(
+    response: Response,
+) -> Result<Result<T, NaiveDate>, FetchError> {
+    detailed_response(response, ErrorCode::PeriodLocked).await
+}
+
+/// Read the response of voiding a transaction, like [unlocked_response]. Voiding a transaction
+/// which isn't recent may also have to be approved by an admin.
+pub async fn void_response<T: DeserializeOwned + 'static>(
+    response: Response,
+) -> Result<Result<T, Gated<NaiveDate>>, FetchError> {
+    gated_response(response, ErrorCode::PeriodLocked).await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay_ms(1), 500);
+        assert_eq!(retry_delay_ms(2), 1000);
+        assert_eq!(retry_delay_ms(3), 2000);
+        assert_eq!(retry_delay_ms(6), RETRY_MAX_DELAY_MS);
+        assert_eq!(retry_delay_ms(100), RETRY_MAX_DELAY_MS);
+    }
+}
@@ -0,0 +1,320 @@
+//! Keeps the resources of the [ResourceStore](seed_fetcher::ResourceStore) in local storage, so
+//! that a page which is reloaded during an event can be shown right away instead of after every
+//! resource has been fetched again.
+//!
+//! The resource store fetches its resources itself, so the cache sits in front of
+//! `window.fetch`. It follows the `Cache-Control` and `ETag` headers of the responses:
+//! - Resources which are still fresh by their `max-age` aren't fetched at all.
+//! - The first request for a stored resource after the page is loaded is answered from storage
+//!   while the resource is revalidated. If it has changed, the store is told to fetch it again.
+//! - Other requests go to the server, where the browser cache revalidates them with the `ETag`.
+//!
+//! Responses without an `ETag`, or marked `no-store`, aren't kept.
+//!
+//! Since every request passes through here, they are also recorded as
+//! [breadcrumbs](crate::util::breadcrumbs), and moved to the API if it isn't at the root of this
+//! origin, see [base](crate::util::base).
+
+use crate::util::{base, breadcrumbs};
+use js_sys::{Date, Function, Promise, Reflect, Uint8Array};
+use seed::prelude::*;
+use seed::*;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use strecklistan_api::client_error::BreadcrumbKind;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, spawn_local, JsFuture};
+use web_sys::{Headers, Request, Response, ResponseInit};
+
+/// The paths of the stored resources, so that they can be cleared
+const INDEX_KEY: &str = "resource_cache";
+
+/// Followed by the path of the resource
+const KEY_PREFIX: &str = "resource_cache:";
+
+#[derive(Serialize, Deserialize)]
+struct CachedResponse {
+    etag: String,
+    content_type: Option<String>,
+
+    /// Base64, since the body may be msgpack
+    body: String,
+
+    /// When the server last said that this is the current version, in ms since the epoch
+    validated_at: f64,
+
+    /// For how many seconds the response may be used without asking the server
+    max_age: Option<u32>,
+}
+
+thread_local! {
+    /// The resources which have been requested since the page was loaded
+    static REQUESTED: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+
+    /// The paths which have been handed to the resource store, which wants static strings
+    static INTERNED: RefCell<HashSet<&'static str>> = RefCell::new(HashSet::new());
+}
+
+impl CachedResponse {
+    fn is_fresh(&self) -> bool {
+        match self.max_age {
+            Some(max_age) => Date::now() - self.validated_at < f64::from(max_age) * 1000.0,
+            None => false,
+        }
+    }
+
+    fn to_response(&self) -> Result<Response, JsValue> {
+        let headers = Headers::new()?;
+        headers.set("ETag", &self.etag)?;
+        if let Some(content_type) = &self.content_type {
+            headers.set("Content-Type", content_type)?;
+        }
+        let mut init = ResponseInit::new();
+        init.status(200).headers(&headers);
+
+        let mut body: Vec<u8> = window()
+            .atob(&self.body)?
+            .chars()
+            .map(|c| c as u8)
+            .collect();
+        Response::new_with_opt_u8_array_and_init(Some(&mut body), &init)
+    }
+}
+
+/// Put the cache in front of `window.fetch`. `changed` is called with the path of a resource
+/// which turned out to have changed after it was answered from storage.
+///
+/// Must be called before the resource store is created.
+pub fn install(changed: impl Fn(&'static str) + 'static) {
+    let window = window();
+    let fetch = Reflect::get(&window, &"fetch".into()).map(|fetch| fetch.dyn_into::<Function>());
+    let fetch = match fetch {
+        Ok(Ok(fetch)) => fetch.bind(&window),
+        _ => return,
+    };
+    let changed: Rc<dyn Fn(&'static str)> = Rc::new(changed);
+
+    let cached_fetch = Closure::wrap(Box::new(move |input: JsValue, init: JsValue| {
+        cached_fetch(&fetch, &changed, input, init)
+    }) as Box<dyn Fn(JsValue, JsValue) -> Promise>);
+    if let Err(e) = Reflect::set(&window, &"fetch".into(), cached_fetch.as_ref()) {
+        error!("Failed to install the resource cache", e);
+    }
+    cached_fetch.forget();
+}
+
+/// Drop every stored resource, e.g. since the next user may belong to another organization
+pub fn clear() {
+    let paths: Vec<String> = LocalStorage::get(INDEX_KEY).unwrap_or_default();
+    for path in paths {
+        let _ = LocalStorage::remove(format!("{}{}", KEY_PREFIX, path));
+    }
+    let _ = LocalStorage::remove(INDEX_KEY);
+}
+
+fn cached_fetch(
+    fetch: &Function,
+    changed: &Rc<dyn Fn(&'static str)>,
+    input: JsValue,
+    init: JsValue,
+) -> Promise {
+    let relocated = input
+        .dyn_ref::<Request>()
+        .and_then(|request| base::relocated(&request.url()));
+    if let Some(url) = relocated {
+        let (fetch, changed) = (fetch.clone(), Rc::clone(changed));
+        let request: Request = input.unchecked_into();
+        return future_to_promise(async move {
+            let input = base::relocate(request, url).await?;
+            JsFuture::from(cached_fetch(&fetch, &changed, input.into(), init)).await
+        });
+    }
+
+    let path = match resource_path(&input) {
+        Some(path) => path,
+        None => return send(fetch, &input, &init),
+    };
+
+    let first = REQUESTED.with(|requested| requested.borrow_mut().insert(path.clone()));
+    match load(&path).map(|cached| (cached.is_fresh(), cached)) {
+        Some((true, cached)) => {
+            if let Ok(response) = cached.to_response() {
+                breadcrumbs::record(BreadcrumbKind::Fetch, format!("GET {} -> cached", path));
+                return Promise::resolve(&response);
+            }
+        }
+        Some((false, cached)) if first => {
+            if let Ok(response) = cached.to_response() {
+                spawn_local(revalidate(
+                    send(fetch, &input, &init),
+                    Rc::clone(changed),
+                    path,
+                    cached.etag,
+                ));
+                return Promise::resolve(&response);
+            }
+        }
+        _ => {}
+    }
+
+    let request = send(fetch, &input, &init);
+    future_to_promise(async move {
+        let response: Response = JsFuture::from(request).await?.unchecked_into();
+
+        // the body can only be read once, so the cache gets a copy of it
+        if response.ok() {
+            if let Ok(copy) = response.clone() {
+                spawn_local(save(path, copy));
+            }
+        }
+        Ok(response.into())
+    })
+}
+
+/// Fetch `input` with the real `window.fetch`, and record it as a breadcrumb once it's answered
+fn send(fetch: &Function, input: &JsValue, init: &JsValue) -> Promise {
+    let promise: Promise = match fetch.call2(&JsValue::NULL, input, init) {
+        Ok(promise) => promise.unchecked_into(),
+        Err(e) => Promise::reject(&e),
+    };
+
+    let request = match input.dyn_ref::<Request>() {
+        Some(request) => {
+            let url = request.url();
+            let path = base::api_path(&url).unwrap_or(&url);
+            format!("{} {}", request.method(), path)
+        }
+        None => return promise,
+    };
+    future_to_promise(async move {
+        let result = JsFuture::from(promise).await;
+        let outcome = match &result {
+            Ok(response) => response.unchecked_ref::<Response>().status().to_string(),
+            Err(_) => "failed".to_string(),
+        };
+        breadcrumbs::record(BreadcrumbKind::Fetch, format!("{} -> {}", request, outcome));
+        result
+    })
+}
+
+/// The path of the API resource which `input` GETs, if it does. Seed always fetches with a
+/// [Request], so other inputs are left alone.
+fn resource_path(input: &JsValue) -> Option<String> {
+    let request = input.dyn_ref::<Request>()?;
+    if request.method() != "GET" {
+        return None;
+    }
+    base::api_path(&request.url()).map(String::from)
+}
+
+/// Check whether the resource at `path` is still the one in storage, and tell the resource store
+/// to fetch it again if it isn't
+async fn revalidate(
+    request: Promise,
+    changed: Rc<dyn Fn(&'static str)>,
+    path: String,
+    etag: String,
+) {
+    let response: Response = match JsFuture::from(request).await {
+        Ok(response) => response.unchecked_into(),
+        Err(_) => return,
+    };
+
+    if response.ok() {
+        if response.headers().get("ETag").ok().flatten().as_deref() == Some(etag.as_str()) {
+            if let Some(mut cached) = load(&path) {
+                cached.validated_at = Date::now();
+                store(&path, &cached);
+            }
+            return;
+        }
+        save(path.clone(), response).await;
+    } else {
+        // e.g. the session has expired, which the store finds out when it fetches again
+        remove(&path);
+    }
+    changed(intern(path));
+}
+
+/// Keep `response` to the request for `path`, if its headers allow it
+async fn save(path: String, response: Response) {
+    let headers = response.headers();
+    let header = |name: &str| headers.get(name).ok().flatten();
+    let cache_control = header("Cache-Control").unwrap_or_default();
+    let etag = match header("ETag") {
+        Some(etag) if !cache_control.contains("no-store") => etag,
+        _ => return remove(&path),
+    };
+
+    let buffer = match response.array_buffer() {
+        Ok(buffer) => JsFuture::from(buffer).await,
+        Err(e) => Err(e),
+    };
+    let body: String = match buffer {
+        Ok(buffer) => Uint8Array::new(&buffer)
+            .to_vec()
+            .into_iter()
+            .map(char::from)
+            .collect(),
+        Err(_) => return,
+    };
+    let body = match window().btoa(&body) {
+        Ok(body) => body,
+        Err(_) => return,
+    };
+
+    let cached = CachedResponse {
+        etag,
+        content_type: header("Content-Type"),
+        body,
+        validated_at: Date::now(),
+        max_age: max_age(&cache_control),
+    };
+    store(&path, &cached);
+}
+
+/// For how many seconds a response with the `Cache-Control` header `cache_control` may be used
+/// without asking the server
+fn max_age(cache_control: &str) -> Option<u32> {
+    let directives = cache_control.split(',').map(str::trim);
+    if directives.clone().any(|directive| directive == "no-cache") {
+        return None;
+    }
+    directives
+        .filter_map(|directive| directive.strip_prefix("max-age="))
+        .find_map(|seconds| seconds.parse().ok())
+}
+
+fn load(path: &str) -> Option<CachedResponse> {
+    LocalStorage::get(format!("{}{}", KEY_PREFIX, path)).ok()
+}
+
+fn store(path: &str, cached: &CachedResponse) {
+    // the cache only saves time, so a full storage is no reason to fail
+    if LocalStorage::insert(format!("{}{}", KEY_PREFIX, path), cached).is_err() {
+        return;
+    }
+    let mut paths: Vec<String> = LocalStorage::get(INDEX_KEY).unwrap_or_default();
+    if !paths.iter().any(|stored| stored == path) {
+        paths.push(path.to_string());
+        let _ = LocalStorage::insert(INDEX_KEY, &paths);
+    }
+}
+
+fn remove(path: &str) {
+    let _ = LocalStorage::remove(format!("{}{}", KEY_PREFIX, path));
+}
+
+fn intern(path: String) -> &'static str {
+    INTERNED.with(|interned| {
+        let mut interned = interned.borrow_mut();
+        if let Some(&path) = interned.get(path.as_str()) {
+            return path;
+        }
+        let path: &'static str = Box::leak(path.into_boxed_str());
+        interned.insert(path);
+        path
+    })
+}
@@ -0,0 +1,72 @@
+//! SVG charts of series of counts or amounts of money.
+//!
+//! ```ignore
+//! Chart::new(ChartKind::Bar)
+//!     .unit(Unit::Currency)
+//!     .labels(events.iter().map(|event| &event.event_code))
+//!     .series(strings::EVENT_REVENUE, revenue)
+//!     .series(strings::EVENT_COSTS, costs)
+//!     .view()
+//! ```
+//!
+//! The charts are drawn by the charts module, see [charts](crate::util::charts), and are left empty
+//! until it's loaded. Hovering a column, or a slice of a pie, shows its exact values. Charts fill
+//! the width of their container, see `charts.css`.
+
+use crate::generated::css_classes::C;
+use crate::util::charts;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_charts::{ChartSpec, Series};
+
+pub use strecklistan_charts::{ChartKind, Unit};
+
+/// A chart, built up with the series to draw before it's viewed
+pub struct Chart(ChartSpec);
+
+impl Chart {
+    pub fn new(kind: ChartKind) -> Self {
+        Chart(ChartSpec {
+            kind,
+            unit: Unit::Count,
+            labels: vec![],
+            series: vec![],
+            compact: false,
+        })
+    }
+
+    pub fn unit(mut self, unit: Unit) -> Self {
+        self.0.unit = unit;
+        self
+    }
+
+    /// The labels of the x axis, one for each value of the series
+    pub fn labels<L: ToString>(mut self, labels: impl IntoIterator<Item = L>) -> Self {
+        self.0.labels = labels.into_iter().map(|label| label.to_string()).collect();
+        self
+    }
+
+    pub fn series(mut self, name: impl ToString, values: Vec<i64>) -> Self {
+        self.0.series.push(Series {
+            name: name.to_string(),
+            values,
+        });
+        self
+    }
+
+    /// Only draw the lines or bars, without axes, legend or tooltips, stretched to fill the size
+    /// given by the stylesheet
+    pub fn compact(mut self) -> Self {
+        self.0.compact = true;
+        self
+    }
+
+    pub fn view<M>(&self) -> Node<M> {
+        let html = match charts::render(&self.0) {
+            Some(html) => html,
+            None if self.0.compact => return empty![],
+            None => return div![C![C.chart, C.chart_loading]],
+        };
+        raw!(&html).into_iter().next().unwrap_or(empty![])
+    }
+}
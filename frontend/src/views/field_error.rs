@@ -0,0 +1,25 @@
+use crate::generated::css_classes::C;
+use crate::strings;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::validation::{field_error, FieldError, Invalid};
+
+/// The reason `field` was rejected by the server, shown below its input
+pub fn view_field_error<M>(errors: &[FieldError], field: &str) -> Node<M> {
+    match field_error(errors, field) {
+        Some(error) => div![C![C.field_error], describe_invalid(error)],
+        None => empty![],
+    }
+}
+
+pub fn describe_invalid(error: &Invalid) -> String {
+    match error {
+        Invalid::Empty => strings::FIELD_REQUIRED.to_string(),
+        Invalid::TooLong { max } => format!("{} {}", strings::FIELD_TOO_LONG, max),
+        Invalid::OutOfRange { min, max } => {
+            format!("{} {}:- – {}:-", strings::FIELD_OUT_OF_RANGE, min, max)
+        }
+        Invalid::NotAUrl => strings::FIELD_NOT_A_URL.to_string(),
+        Invalid::NotAnEmail => strings::FIELD_NOT_AN_EMAIL.to_string(),
+    }
+}
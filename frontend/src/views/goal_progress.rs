@@ -0,0 +1,48 @@
+use crate::generated::css_classes::C;
+use crate::strings;
+use seed::prelude::*;
+use seed::*;
+use strecklistan_api::currency::Currency;
+use strecklistan_api::goal::{GoalScope, GoalTarget, SalesGoalProgress};
+
+/// A sales goal as a bar which fills up as the goal is reached
+pub fn view_goal_progress<M>(progress: &SalesGoalProgress) -> Node<M> {
+    let goal = &progress.goal;
+    let fmt_value = |value: i64| match goal.target {
+        GoalTarget::Revenue(_) => format!("{}:-", Currency::from(value)),
+        GoalTarget::Quantity(_) => format!("{} {}", value, strings::GOAL_ITEMS),
+    };
+    let scope = match &goal.scope {
+        GoalScope::Period(period) => period.to_string(),
+        GoalScope::Event(code) => code.clone(),
+    };
+    let percent = (progress.fraction() * 100.0).max(0.0).min(100.0);
+
+    div![
+        C![C.goal],
+        div![
+            C![C.goal_header],
+            span![C![C.goal_name], &goal.name],
+            span![C![C.goal_scope], scope],
+            span![
+                C![C.goal_amount],
+                format!(
+                    "{} / {}",
+                    fmt_value(progress.achieved),
+                    fmt_value(goal.target.value())
+                ),
+            ],
+        ],
+        div![
+            C![C.goal_bar],
+            div![
+                if progress.is_reached() {
+                    C![C.goal_bar_fill, C.goal_bar_reached]
+                } else {
+                    C![C.goal_bar_fill]
+                },
+                style! {St::Width => format!("{:.1}%", percent)},
+            ],
+        ],
+    ]
+}
@@ -1,6 +1,8 @@
 use crate::app::Msg;
 use crate::generated::css_classes::C;
+use crate::strings;
 use crate::util::simple_ev;
+use chrono::NaiveDate;
 use itertools::Itertools;
 use seed::prelude::*;
 use seed::*;
@@ -53,10 +55,32 @@ fn build_search_highlight_spans(
         .collect()
 }
 
+/// A star which toggles whether an item is pinned to the favorites
+fn view_pin_star(pinned: bool, toggle_pin_ev: Msg) -> Node<Msg> {
+    span![
+        C![C.inventory_item_pin],
+        if pinned {
+            C![C.inventory_item_pinned]
+        } else {
+            C![]
+        },
+        ev(Ev::Click, move |event| {
+            // don't add the item to the cart
+            event.stop_propagation();
+            toggle_pin_ev
+        }),
+        if pinned { "★" } else { "☆" },
+    ]
+}
+
 pub fn view_inventory_item(
     item: &InventoryItemStock,
     highlight_chars: impl IntoIterator<Item = usize>,
     add_item_ev: impl FnOnce(InventoryItemId, i32) -> Msg,
+    pinned: bool,
+    toggle_pin_ev: Msg,
+    expires_on: Option<NaiveDate>,
+    today: NaiveDate,
 ) -> Node<Msg> {
     div![
         C![C.inventory_item, C.unselectable],
@@ -64,6 +88,7 @@ pub fn view_inventory_item(
         p![
             C![C.inventory_item_header],
             build_search_highlight_spans(&item.name, highlight_chars),
+            view_pin_star(pinned, toggle_pin_ev),
         ],
         div![
             C![C.inventory_item_image],
@@ -77,12 +102,33 @@ pub fn view_inventory_item(
         ],
         p![
             C![C.inventory_item_footer],
-            C![match item.stock {
+            C![match item.sellable() {
                 n if n <= 0 => C.inventory_item_footer_red,
                 n if n <= 10 => C.inventory_item_footer_yellow,
                 _ => C.inventory_item_footer_green,
             }],
-            format!("{} i lager.", item.stock),
+            match expires_on {
+                Some(date) if date < today => {
+                    span![
+                        C![C.inventory_item_expiry, C.inventory_item_expired],
+                        strings::EXPIRED
+                    ]
+                }
+                Some(date) => span![
+                    C![C.inventory_item_expiry],
+                    format!("{} {}", strings::BEST_BEFORE, date.format("%m-%d")),
+                ],
+                None => empty![],
+            },
+            format!("{} {}", item.sellable(), strings::IN_STOCK),
+            if item.reserved > 0 {
+                span![
+                    C![C.inventory_item_reserved],
+                    format!(" {} {}", item.reserved, strings::RESERVED),
+                ]
+            } else {
+                empty![]
+            },
         ],
     ]
 }
@@ -91,6 +137,8 @@ pub fn view_inventory_bundle(
     bundle: &InventoryBundle,
     highlight_chars: impl IntoIterator<Item = usize>,
     add_bundle_ev: impl FnOnce(InventoryBundleId, i32) -> Msg,
+    pinned: bool,
+    toggle_pin_ev: Msg,
 ) -> Node<Msg> {
     div![
         C![C.inventory_item, C.unselectable],
@@ -98,6 +146,7 @@ pub fn view_inventory_bundle(
         p![
             C![C.inventory_item_header],
             build_search_highlight_spans(&bundle.name, highlight_chars),
+            view_pin_star(pinned, toggle_pin_ev),
         ],
         if let Some(image_url) = bundle.image_url.as_ref() {
             img![C![C.inventory_item_image], attrs! { At::Src => image_url },]
@@ -1,5 +1,12 @@
+pub mod chart;
+pub mod field_error;
+pub mod goal_progress;
 pub mod inventory;
+pub mod sparkline;
 pub mod tillgodolista;
 
+pub use field_error::*;
+pub use goal_progress::*;
 pub use inventory::*;
+pub use sparkline::*;
 pub use tillgodolista::*;
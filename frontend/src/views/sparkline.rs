@@ -0,0 +1,16 @@
+use crate::generated::css_classes::C;
+use crate::views::chart::{Chart, ChartKind};
+use seed::prelude::*;
+use seed::*;
+
+/// A tiny line chart of `values`, scaled so that the largest one reaches the top. The size is set
+/// by the stylesheet.
+pub fn view_sparkline<M>(values: &[i64]) -> Node<M> {
+    div![
+        C![C.sparkline],
+        Chart::new(ChartKind::Line)
+            .series("", values.to_vec())
+            .compact()
+            .view(),
+    ]
+}